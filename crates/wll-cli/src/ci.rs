@@ -0,0 +1,281 @@
+//! SARIF and JUnit XML renderings of gate simulations and verification
+//! reports, for `--output sarif` and `--output junit`.
+//!
+//! A report is flattened into [`CiCase`]s, one per gate check or
+//! verification finding. SARIF lists the cases that did not pass as
+//! results, which code scanning views show as alerts; JUnit XML lists every
+//! case as a test, grouped into one suite per report kind.
+
+use serde_json::{json, Value};
+use wll_gate::{GateSimulation, StageDecision};
+
+use crate::verify::{Severity, VerifyReport};
+
+/// Location of every SARIF result: the repository's metadata directory,
+/// since findings are about the ledger rather than a source line.
+const REPO_LOCATION: &str = ".wll";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CaseOutcome {
+    Passed,
+    /// Not decided, such as a deferred gate check.
+    Skipped(String),
+    /// Reported without failing the run.
+    Warning(String),
+    Failed(String),
+}
+
+/// One check of a report, as a CI system shows it.
+#[derive(Clone, Debug)]
+pub struct CiCase {
+    /// `gate` or `verify`.
+    pub suite: &'static str,
+    /// Stable id of the check, such as `gate.policy` or `verify.store`.
+    pub rule: String,
+    pub name: String,
+    pub outcome: CaseOutcome,
+}
+
+/// A report that renders as SARIF and JUnit XML.
+pub trait CiReport {
+    fn ci_cases(&self) -> Vec<CiCase>;
+}
+
+impl CiReport for GateSimulation {
+    fn ci_cases(&self) -> Vec<CiCase> {
+        let mut cases = Vec::new();
+        for stage in &self.stages {
+            for check in &stage.checks {
+                let name = match (&check.policy, &check.rule) {
+                    (Some(policy), Some(rule)) => format!("{policy}: {rule}"),
+                    (None, Some(rule)) => rule.clone(),
+                    (Some(policy), None) => policy.clone(),
+                    (None, None) => stage.stage_name.clone(),
+                };
+                let outcome = match &check.decision {
+                    StageDecision::Pass => CaseOutcome::Passed,
+                    StageDecision::Defer { reason, .. } => CaseOutcome::Skipped(reason.clone()),
+                    StageDecision::Fail { reason } if self.permissive => {
+                        CaseOutcome::Warning(reason.clone())
+                    }
+                    StageDecision::Fail { reason } => CaseOutcome::Failed(reason.clone()),
+                };
+                cases.push(CiCase {
+                    suite: "gate",
+                    rule: format!("gate.{}", stage.stage_name),
+                    name,
+                    outcome,
+                });
+            }
+        }
+        cases
+    }
+}
+
+impl CiReport for VerifyReport {
+    fn ci_cases(&self) -> Vec<CiCase> {
+        let mut cases = Vec::new();
+        for check in ["stream", "dag", "store", "decisions", "outcomes"] {
+            let case = |outcome| CiCase {
+                suite: "verify",
+                rule: format!("verify.{check}"),
+                name: check.to_string(),
+                outcome,
+            };
+            let findings: Vec<_> = self.findings.iter().filter(|f| f.check == check).collect();
+            if findings.is_empty() {
+                cases.push(case(CaseOutcome::Passed));
+            }
+            for finding in findings {
+                let message = finding.description.clone();
+                cases.push(case(match finding.severity {
+                    Severity::Warning => CaseOutcome::Warning(message),
+                    _ => CaseOutcome::Failed(message),
+                }));
+            }
+        }
+        cases
+    }
+}
+
+/// `cases` as a SARIF 2.1.0 log with one run. Every case that did not
+/// pass is a result: failures at level `error`, warnings at `warning`,
+/// skipped cases at `note`.
+pub fn sarif(cases: &[CiCase]) -> Value {
+    let mut rules: Vec<&str> = Vec::new();
+    let mut results = Vec::new();
+    for case in cases {
+        let (level, message) = match &case.outcome {
+            CaseOutcome::Passed => continue,
+            CaseOutcome::Skipped(reason) => ("note", reason),
+            CaseOutcome::Warning(reason) => ("warning", reason),
+            CaseOutcome::Failed(reason) => ("error", reason),
+        };
+        if !rules.contains(&case.rule.as_str()) {
+            rules.push(&case.rule);
+        }
+        results.push(json!({
+            "ruleId": case.rule,
+            "level": level,
+            "message": { "text": format!("{}: {message}", case.name) },
+            "locations": [{
+                "physicalLocation": { "artifactLocation": { "uri": REPO_LOCATION } }
+            }],
+        }));
+    }
+    let rules: Vec<Value> = rules
+        .into_iter()
+        .map(|id| json!({ "id": id, "shortDescription": { "text": id } }))
+        .collect();
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "wll",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                }
+            },
+            "results": results,
+        }],
+    })
+}
+
+/// `cases` as a JUnit XML document with one test suite per report kind.
+/// Warnings pass, with their message in `system-out`.
+pub fn junit(cases: &[CiCase]) -> String {
+    let mut suites: Vec<&str> = Vec::new();
+    for case in cases {
+        if !suites.contains(&case.suite) {
+            suites.push(case.suite);
+        }
+    }
+    let count = |cases: &[&CiCase], pick: fn(&CaseOutcome) -> bool| {
+        cases.iter().filter(|case| pick(&case.outcome)).count()
+    };
+    let failed = |outcome: &CaseOutcome| matches!(outcome, CaseOutcome::Failed(_));
+    let skipped = |outcome: &CaseOutcome| matches!(outcome, CaseOutcome::Skipped(_));
+
+    let all: Vec<&CiCase> = cases.iter().collect();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites name=\"wll\" tests=\"{}\" failures=\"{}\" skipped=\"{}\">\n",
+        all.len(),
+        count(&all, failed),
+        count(&all, skipped)
+    ));
+    for suite in suites {
+        let members: Vec<&CiCase> = cases.iter().filter(|case| case.suite == suite).collect();
+        xml.push_str(&format!(
+            "  <testsuite name=\"{suite}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\">\n",
+            members.len(),
+            count(&members, failed),
+            count(&members, skipped)
+        ));
+        for case in members {
+            let open = format!(
+                "    <testcase classname=\"{}\" name=\"{}\"",
+                escape(&case.rule),
+                escape(&case.name)
+            );
+            let body = match &case.outcome {
+                CaseOutcome::Passed => {
+                    xml.push_str(&open);
+                    xml.push_str("/>\n");
+                    continue;
+                }
+                CaseOutcome::Skipped(reason) => {
+                    format!("<skipped message=\"{}\"/>", escape(reason))
+                }
+                CaseOutcome::Warning(reason) => {
+                    format!("<system-out>warning: {}</system-out>", escape(reason))
+                }
+                CaseOutcome::Failed(reason) => {
+                    format!("<failure message=\"{}\"/>", escape(reason))
+                }
+            };
+            xml.push_str(&format!("{open}>\n      {body}\n    </testcase>\n"));
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>");
+    xml
+}
+
+/// `text` with the XML special characters escaped, for attributes and
+/// text alike.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn case(rule: &str, name: &str, outcome: CaseOutcome) -> CiCase {
+        let suite = if rule.starts_with("gate") { "gate" } else { "verify" };
+        CiCase { suite, rule: rule.into(), name: name.into(), outcome }
+    }
+
+    #[test]
+    fn sarif_reports_what_did_not_pass() {
+        let cases = [
+            case("gate.policy", "strict: evidence", CaseOutcome::Failed("no evidence".into())),
+            case("gate.intent_lint", "intent_lint", CaseOutcome::Passed),
+            case("verify.decisions", "r#3", CaseOutcome::Warning("unknown policy".into())),
+        ];
+        let log = sarif(&cases);
+        let run = &log["runs"][0];
+        assert_eq!(log["version"], "2.1.0");
+        assert_eq!(run["tool"]["driver"]["rules"].as_array().unwrap().len(), 2);
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["ruleId"], "gate.policy");
+        assert_eq!(results[0]["level"], "error");
+        assert_eq!(results[0]["message"]["text"], "strict: evidence: no evidence");
+        assert_eq!(results[1]["level"], "warning");
+        let uri = &results[1]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"];
+        assert_eq!(uri, ".wll");
+    }
+
+    #[test]
+    fn junit_groups_cases_into_suites_and_escapes() {
+        let cases = [
+            case("gate.policy", "a<b", CaseOutcome::Failed("\"quoted\" & more".into())),
+            case("gate.stage", "deferred", CaseOutcome::Skipped("later".into())),
+            case("verify.store", "store", CaseOutcome::Passed),
+        ];
+        let xml = junit(&cases);
+        let counts = |tests, failures, skipped| {
+            format!("tests=\"{tests}\" failures=\"{failures}\" skipped=\"{skipped}\">")
+        };
+        assert!(xml.contains(&format!("<testsuites name=\"wll\" {}", counts(3, 1, 1))));
+        assert!(xml.contains(&format!("<testsuite name=\"gate\" {}", counts(2, 1, 1))));
+        assert!(xml.contains(&format!("<testsuite name=\"verify\" {}", counts(1, 0, 0))));
+        assert!(xml.contains("name=\"a&lt;b\""));
+        assert!(xml.contains("<failure message=\"&quot;quoted&quot; &amp; more\"/>"));
+        assert!(xml.contains("<testcase classname=\"verify.store\" name=\"store\"/>"));
+    }
+
+    #[test]
+    fn clean_verification_is_one_passing_case_per_check() {
+        let wll = wll_sdk::Wll::init().unwrap();
+        let cases = crate::verify::check(&wll, false, false).unwrap().ci_cases();
+        assert_eq!(cases.len(), 5);
+        assert!(cases.iter().all(|case| case.outcome == CaseOutcome::Passed));
+        assert!(sarif(&cases)["runs"][0]["results"].as_array().unwrap().is_empty());
+    }
+}
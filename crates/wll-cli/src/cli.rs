@@ -13,20 +13,30 @@ pub struct Cli {
     #[arg(short, long, global = true)]
     pub verbose: bool,
 
-    #[arg(long, global = true, default_value = "text")]
-    pub format: OutputFormat,
+    /// Output format for command results
+    #[arg(long, global = true, visible_alias = "format", default_value = "table")]
+    pub output: OutputFormat,
 }
 
-#[derive(Clone, Debug, clap::ValueEnum)]
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
 pub enum OutputFormat {
-    Text,
+    /// Human-readable text
+    #[value(alias = "text")]
+    Table,
     Json,
+    Yaml,
+    /// SARIF 2.1.0, for code scanning views (`check`, `gate check`, `verify`)
+    Sarif,
+    /// JUnit XML, for CI test reports (`check`, `gate check`, `verify`)
+    Junit,
 }
 
 #[derive(Subcommand)]
 pub enum Command {
     /// Initialize a new WLL repository
     Init(InitArgs),
+    /// Clone a repository on this machine, sharing its object files
+    Clone(CloneArgs),
     /// Show working directory status
     Status(StatusArgs),
     /// Stage files for commitment
@@ -37,6 +47,10 @@ pub enum Command {
     Log(LogArgs),
     /// Show a specific receipt
     Show(ShowArgs),
+    /// Add or list notes attached to receipts
+    Notes(NotesArgs),
+    /// Search commit intents, metadata, targets, and evidence
+    Search(SearchArgs),
     /// List, create, or delete branches
     Branch(BranchArgs),
     /// Switch to a different branch
@@ -55,26 +69,50 @@ pub enum Command {
     Pull(PullArgs),
     /// Push to a remote
     Push(PushArgs),
+    /// Look up, save, or forget credentials for a remote
+    Credential(CredentialArgs),
+    /// Create, verify, or apply an offline bundle
+    Bundle(BundleArgs),
+    /// Import history from a git repository
+    Import(ImportArgs),
+    /// Manage the worldline repositories this one pins
+    Workspace(WorkspaceArgs),
+    /// Export receipts as JSON Lines or Parquet for analytics
+    Export(ExportArgs),
     /// Show causal provenance chain
     Provenance(ProvenanceArgs),
     /// Show downstream impact
     Impact(ImpactArgs),
     /// Verify receipt chain integrity
     Verify(VerifyArgs),
+    /// Verify the repository and dry-run a proposal through the gate, for CI
+    Check(CheckArgs),
     /// Replay and verify state from genesis
     Replay(ReplayArgs),
     /// Show full audit trail
     Audit(AuditArgs),
-    /// Garbage collect unreachable objects
+    /// Dry-run a proposal through the commitment gate
+    Gate(GateArgs),
+    /// Replace a blob's content with a signed tombstone, keeping its history
+    Redact(RedactArgs),
+    /// Prune history outside the retention policy and collect its objects
     Gc(GcArgs),
+    /// Show object, ledger and provenance statistics
+    Stats(StatsArgs),
     /// Repack loose objects
     Repack(RepackArgs),
+    /// Inspect pack files
+    Pack(PackArgs),
     /// Full integrity check
     Fsck(FsckArgs),
+    /// Upgrade packs, indexes and WAL segments to the current format versions
+    Migrate(MigrateArgs),
     /// Get or set configuration
     Config(ConfigArgs),
     /// Start the WLL server daemon
     Serve(ServeArgs),
+    /// Print a shell completion script
+    Completions(CompletionsArgs),
 }
 
 #[derive(Args)]
@@ -82,6 +120,21 @@ pub struct InitArgs {
     pub path: Option<String>,
     #[arg(long)]
     pub bare: bool,
+    /// Seed the repository from a template: a preset (solo-dev,
+    /// strict-governance) or a template directory
+    #[arg(long, value_name = "NAME|DIR")]
+    pub template: Option<String>,
+}
+
+#[derive(Args)]
+pub struct CloneArgs {
+    /// Repository to clone
+    pub source: String,
+    /// Directory to clone into (defaults to the source's directory name)
+    pub path: Option<String>,
+    /// Copy object files instead of hardlinking them from the source
+    #[arg(long)]
+    pub dissociate: bool,
 }
 
 #[derive(Args)]
@@ -106,17 +159,68 @@ pub struct CommitArgs {
 
 #[derive(Args)]
 pub struct LogArgs {
+    /// Maximum number of entries to show
     #[arg(short = 'n', long, default_value = "20")]
     pub limit: usize,
+    /// Number of newest entries to skip (for paging)
+    #[arg(long, default_value = "0")]
+    pub skip: usize,
+    /// One line per entry
     #[arg(long)]
     pub oneline: bool,
+    /// Draw the provenance graph alongside entries
     #[arg(long)]
     pub graph: bool,
+    /// Include receipts not reachable from HEAD
+    #[arg(long)]
+    pub all: bool,
 }
 
 #[derive(Args)]
 pub struct ShowArgs {
-    pub receipt: String,
+    /// Receipt hash, unique prefix, or ref (defaults to HEAD)
+    pub receipt: Option<String>,
+    /// Omit line diffs of changed files
+    #[arg(short = 's', long)]
+    pub no_patch: bool,
+    /// Write the commitment's evidence attachments into this directory
+    #[arg(long, value_name = "DIR")]
+    pub extract: Option<std::path::PathBuf>,
+}
+
+#[derive(Args)]
+pub struct NotesArgs {
+    #[command(subcommand)]
+    pub action: NotesAction,
+}
+
+#[derive(Subcommand)]
+pub enum NotesAction {
+    /// Attach a note to a receipt
+    Add {
+        /// Receipt hash, unique prefix, or ref (defaults to HEAD)
+        receipt: Option<String>,
+        #[arg(short, long)]
+        message: String,
+        /// Link to related material (repeatable)
+        #[arg(long = "link", value_name = "URL")]
+        links: Vec<String>,
+    },
+    /// List the notes on a receipt
+    #[command(visible_alias = "ls")]
+    List {
+        /// Receipt hash, unique prefix, or ref (defaults to HEAD)
+        receipt: Option<String>,
+    },
+}
+
+#[derive(Args)]
+pub struct SearchArgs {
+    /// Words to look for; `intent:word` searches one field, quotes a phrase
+    pub query: String,
+    /// Maximum number of commits to show
+    #[arg(short = 'n', long, default_value = "10")]
+    pub limit: usize,
 }
 
 #[derive(Args)]
@@ -167,8 +271,22 @@ pub struct RemoteArgs {
 
 #[derive(Subcommand)]
 pub enum RemoteAction {
-    Add { name: String, url: String },
+    /// Add a remote
+    Add {
+        name: String,
+        url: String,
+        /// Fetch refspec (repeatable; defaults to all branches)
+        #[arg(long = "fetch", value_name = "REFSPEC")]
+        fetch: Vec<String>,
+    },
+    /// Remove a remote and its tracking refs
+    #[command(visible_alias = "rm")]
     Remove { name: String },
+    /// List remotes
+    #[command(visible_alias = "ls")]
+    List,
+    /// Change a remote's URL
+    SetUrl { name: String, url: String },
 }
 
 #[derive(Args)]
@@ -176,31 +294,291 @@ pub struct FetchArgs { pub remote: Option<String> }
 #[derive(Args)]
 pub struct PullArgs { pub remote: Option<String>, pub branch: Option<String> }
 #[derive(Args)]
-pub struct PushArgs { pub remote: Option<String>, pub branch: Option<String> }
+pub struct PushArgs {
+    pub remote: Option<String>,
+    pub branch: Option<String>,
+    /// Apply every ref update and receipt on the remote, or none of them
+    #[arg(long)]
+    pub atomic: bool,
+}
+#[derive(Args)]
+pub struct CredentialArgs {
+    #[command(subcommand)]
+    pub action: CredentialAction,
+}
+
+#[derive(Subcommand)]
+pub enum CredentialAction {
+    /// Print the credential for a remote name or URL
+    Get { remote: String },
+    /// Save a token read from stdin for a remote name or URL
+    Store {
+        remote: String,
+        #[arg(long)]
+        username: Option<String>,
+    },
+    /// Forget the credential for a remote name or URL
+    Erase { remote: String },
+}
+#[derive(Args)]
+pub struct BundleArgs {
+    #[command(subcommand)]
+    pub action: BundleAction,
+}
+
+#[derive(Subcommand)]
+pub enum BundleAction {
+    /// Write branches, receipts, and their objects to a bundle file
+    Create {
+        file: std::path::PathBuf,
+        /// Branch to include (repeatable; defaults to all branches)
+        #[arg(long = "branch", value_name = "NAME")]
+        branches: Vec<String>,
+        /// First receipt sequence number to include
+        #[arg(long, value_name = "SEQ")]
+        from: Option<u64>,
+        /// Last receipt sequence number to include
+        #[arg(long, value_name = "SEQ")]
+        to: Option<u64>,
+        /// Sign with the Ed25519 secret key (hex) in this file
+        #[arg(long, value_name = "FILE")]
+        sign_key: Option<std::path::PathBuf>,
+    },
+    /// Check a bundle's checksum, signature, and prerequisites
+    Verify {
+        file: std::path::PathBuf,
+        /// Require a signature by this public key (hex or base64; repeatable)
+        #[arg(long = "trust-key", value_name = "KEY")]
+        trust_keys: Vec<String>,
+    },
+    /// Verify a bundle, then import it and fast-forward its branches
+    Unbundle {
+        file: std::path::PathBuf,
+        /// Require a signature by this public key (hex or base64; repeatable)
+        #[arg(long = "trust-key", value_name = "KEY")]
+        trust_keys: Vec<String>,
+    },
+}
+#[derive(Args)]
+pub struct WorkspaceArgs {
+    #[command(subcommand)]
+    pub action: WorkspaceAction,
+}
+
+#[derive(Subcommand)]
+pub enum WorkspaceAction {
+    /// Add a member pinned to its branch tip and commit the manifest
+    Add {
+        name: String,
+        /// Local repository or bundle file to clone the member from
+        url: String,
+        /// Checkout path in the work tree (defaults to the name)
+        #[arg(long)]
+        path: Option<String>,
+        /// Branch whose tip is pinned
+        #[arg(long, default_value = "main")]
+        branch: String,
+    },
+    /// Clone members that have no checkout yet (defaults to all)
+    Clone { names: Vec<String> },
+    /// Show how each member's checkout relates to its pin
+    Status,
+    /// Pin members to their checkout's branch tip in one commit (defaults to all)
+    Update {
+        names: Vec<String>,
+        /// Commit message
+        #[arg(short, long)]
+        message: Option<String>,
+    },
+}
+#[derive(Args)]
+pub struct ImportArgs {
+    /// Path to the git repository (work tree or bare)
+    pub path: std::path::PathBuf,
+    /// Branch to import (repeatable; defaults to all branches)
+    #[arg(long = "branch", value_name = "NAME")]
+    pub branches: Vec<String>,
+    /// Do not import tags
+    #[arg(long)]
+    pub no_tags: bool,
+}
+#[derive(Args)]
+pub struct ExportArgs {
+    /// File to write; a `.parquet` extension selects Parquet. Defaults to
+    /// JSON Lines on stdout
+    pub file: Option<std::path::PathBuf>,
+    /// Worldline to export, as hex (defaults to the repository's)
+    #[arg(long)]
+    pub worldline: Option<String>,
+    /// Only receipts at or after this time (RFC 3339 or YYYY-MM-DD)
+    #[arg(long)]
+    pub since: Option<String>,
+    /// Only receipts before this time (RFC 3339 or YYYY-MM-DD)
+    #[arg(long)]
+    pub until: Option<String>,
+}
 #[derive(Args)]
 pub struct ProvenanceArgs { pub receipt: String }
 #[derive(Args)]
 pub struct ImpactArgs { pub receipt: String }
 #[derive(Args)]
-pub struct VerifyArgs {}
+pub struct VerifyArgs {
+    /// Also fetch and verify outcome proofs, such as time anchors and
+    /// transparency log inclusion proofs
+    #[arg(long)]
+    pub strict: bool,
+    /// Exit with code 5 when the only findings are warnings
+    #[arg(long)]
+    pub deny_warnings: bool,
+}
 #[derive(Args)]
-pub struct ReplayArgs { #[arg(long)] pub from_genesis: bool }
+pub struct ReplayArgs {
+    #[arg(long)]
+    pub from_genesis: bool,
+    /// Re-run the gate on every commitment under its recorded policy version
+    #[arg(long)]
+    pub strict: bool,
+}
 #[derive(Args)]
 pub struct AuditArgs { pub worldline: Option<String> }
+
+#[derive(Args)]
+pub struct RedactArgs {
+    /// Id of the blob to redact
+    pub object: String,
+    /// Why the content is being removed; recorded in the ledger
+    #[arg(long)]
+    pub reason: String,
+    /// Sign with the Ed25519 secret key (hex) in this file
+    #[arg(long, value_name = "FILE")]
+    pub key: std::path::PathBuf,
+}
+
+#[derive(Args)]
+pub struct GateArgs {
+    #[command(subcommand)]
+    pub action: GateAction,
+}
+
+#[derive(Subcommand)]
+pub enum GateAction {
+    /// Report what every gate stage and policy rule would decide, without
+    /// committing
+    Check(ProposalArgs),
+}
+/// A proposal to dry-run through the gate.
+#[derive(Args)]
+pub struct ProposalArgs {
+    #[arg(short, long)]
+    pub message: Option<String>,
+    #[arg(long)]
+    pub intent: Option<String>,
+    /// Commitment class (e.g. ContentUpdate, PolicyChange)
+    #[arg(long)]
+    pub class: Option<String>,
+    /// Evidence URI (repeatable)
+    #[arg(long)]
+    pub evidence: Vec<String>,
+    /// Path or object the change affects (repeatable)
+    #[arg(long = "target", value_name = "PATH")]
+    pub targets: Vec<String>,
+    /// Capability to claim (repeatable)
+    #[arg(long = "capability", value_name = "NAME")]
+    pub capabilities: Vec<String>,
+    /// Also apply the policy in this JSON or YAML file (repeatable)
+    #[arg(long = "policy", value_name = "FILE")]
+    pub policies: Vec<std::path::PathBuf>,
+}
+#[derive(Args)]
+pub struct CheckArgs {
+    #[command(flatten)]
+    pub verify: VerifyArgs,
+    /// Proposal to dry-run through the gate; skipped without `--message`
+    /// or `--intent`
+    #[command(flatten)]
+    pub proposal: ProposalArgs,
+}
+#[derive(Args)]
+pub struct GcArgs {
+    /// Report what would be pruned without changing anything
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Keep at least this many of the newest receipts (overrides `retention.keep_receipts`)
+    #[arg(long, value_name = "N")]
+    pub keep_receipts: Option<u64>,
+    /// Keep receipts from the last this many days (overrides `retention.keep_days`)
+    #[arg(long, value_name = "DAYS")]
+    pub keep_days: Option<u64>,
+}
 #[derive(Args)]
-pub struct GcArgs {}
+pub struct StatsArgs {}
 #[derive(Args)]
-pub struct RepackArgs {}
+pub struct RepackArgs {
+    /// Replace object files shared with the repository this one was cloned from by private copies
+    #[arg(long)]
+    pub dissociate: bool,
+}
+#[derive(Args)]
+pub struct PackArgs {
+    #[command(subcommand)]
+    pub action: PackAction,
+}
+
+#[derive(Subcommand)]
+pub enum PackAction {
+    /// Check every entry's CRC32 and object id and each pack's checksum
+    Verify {
+        /// Pack files to check (defaults to every pack in the repository)
+        packs: Vec<std::path::PathBuf>,
+    },
+}
 #[derive(Args)]
 pub struct FsckArgs {}
 #[derive(Args)]
-pub struct ConfigArgs { pub key: Option<String>, pub value: Option<String> }
+pub struct MigrateArgs {
+    /// Report what would be upgraded without changing anything
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Replace files without copying the originals to `.wll/migrate-backup/`
+    #[arg(long)]
+    pub no_backup: bool,
+    /// A fabric WAL segment to upgrade too; it must not be open in a running server
+    #[arg(long, value_name = "PATH")]
+    pub wal: Vec<std::path::PathBuf>,
+}
+#[derive(Args)]
+pub struct ConfigArgs {
+    /// Dotted key, e.g. `user.name` or `remote.origin.url`
+    pub key: Option<String>,
+    /// New value; `true`/`false` and numbers are stored typed
+    pub value: Option<String>,
+    /// Use the user config (~/.config/wll/config.toml)
+    #[arg(long, conflicts_with = "system")]
+    pub global: bool,
+    /// Use the system config (/etc/wll/config.toml)
+    #[arg(long)]
+    pub system: bool,
+    /// Remove the key
+    #[arg(long, requires = "key", conflicts_with = "value")]
+    pub unset: bool,
+}
 #[derive(Args)]
 pub struct ServeArgs {
-    #[arg(long, default_value = "127.0.0.1:9418")]
-    pub bind: String,
-    #[arg(long, default_value = ".")]
-    pub root: String,
+    /// Address to listen on (overrides `bind_addr` from the config file)
+    #[arg(long, visible_alias = "bind")]
+    pub addr: Option<std::net::SocketAddr>,
+    /// Directory holding the served repositories (overrides `repos_root`)
+    #[arg(long, visible_alias = "root")]
+    pub repo_root: Option<std::path::PathBuf>,
+    /// Server config file (TOML)
+    #[arg(long)]
+    pub config: Option<std::path::PathBuf>,
+}
+
+#[derive(Args)]
+pub struct CompletionsArgs {
+    /// Shell to generate completions for
+    pub shell: clap_complete::Shell,
 }
 
 #[cfg(test)]
@@ -248,6 +626,26 @@ mod tests {
         } else { panic!("wrong command"); }
     }
 
+    #[test]
+    fn parse_log_paging() {
+        let cli = Cli::try_parse_from(["wll", "log", "--graph", "--all", "--skip", "20"]).unwrap();
+        if let Command::Log(args) = cli.command {
+            assert!(args.graph && args.all);
+            assert_eq!(args.skip, 20);
+            assert_eq!(args.limit, 20);
+        } else { panic!("wrong command"); }
+    }
+
+    #[test]
+    fn parse_show_defaults_to_head() {
+        let cli = Cli::try_parse_from(["wll", "show", "-s"]).unwrap();
+        if let Command::Show(args) = cli.command {
+            assert_eq!(args.receipt, None);
+            assert!(args.no_patch);
+            assert_eq!(args.extract, None);
+        } else { panic!("wrong command"); }
+    }
+
     #[test]
     fn parse_branch() {
         let cli = Cli::try_parse_from(["wll", "branch"]).unwrap();
@@ -280,26 +678,128 @@ mod tests {
         } else { panic!("wrong command"); }
     }
 
+    #[test]
+    fn parse_remote_set_url_and_fetch() {
+        let cli = Cli::try_parse_from(["wll", "remote", "set-url", "origin", "wll://y"]).unwrap();
+        if let Command::Remote(args) = cli.command {
+            assert!(matches!(args.action, Some(RemoteAction::SetUrl { .. })));
+        } else { panic!("wrong command"); }
+
+        let cli = Cli::try_parse_from([
+            "wll", "remote", "add", "up", "wll://u", "--fetch", "refs/heads/main:refs/remotes/up/main",
+        ])
+        .unwrap();
+        if let Command::Remote(RemoteArgs { action: Some(RemoteAction::Add { fetch, .. }), .. }) = cli.command {
+            assert_eq!(fetch, ["refs/heads/main:refs/remotes/up/main"]);
+        } else { panic!("wrong command"); }
+    }
+
+    #[test]
+    fn parse_config_levels() {
+        let cli = Cli::try_parse_from(["wll", "config", "--global", "user.name", "Ada"]).unwrap();
+        if let Command::Config(args) = cli.command {
+            assert!(args.global && !args.system);
+            assert_eq!(args.key.as_deref(), Some("user.name"));
+            assert_eq!(args.value.as_deref(), Some("Ada"));
+        } else { panic!("wrong command"); }
+
+        assert!(Cli::try_parse_from(["wll", "config", "--unset", "user.name"]).is_ok());
+        assert!(Cli::try_parse_from(["wll", "config", "--unset"]).is_err());
+        assert!(Cli::try_parse_from(["wll", "config", "--global", "--system", "a.b"]).is_err());
+    }
+
+    #[test]
+    fn parse_init_template() {
+        let cli = Cli::try_parse_from(["wll", "init", "svc", "--template", "solo-dev"]).unwrap();
+        if let Command::Init(args) = cli.command {
+            assert_eq!((args.path.as_deref(), args.template.as_deref()), (Some("svc"), Some("solo-dev")));
+        } else { panic!("wrong command"); }
+    }
+
+    #[test]
+    fn parse_notes_add() {
+        let cli = Cli::try_parse_from([
+            "wll", "notes", "add", "main", "-m", "rolled back", "--link", "https://x/1",
+        ])
+        .unwrap();
+        if let Command::Notes(NotesArgs { action: NotesAction::Add { receipt, message, links } }) =
+            cli.command
+        {
+            assert_eq!((receipt.as_deref(), message.as_str()), (Some("main"), "rolled back"));
+            assert_eq!(links, ["https://x/1"]);
+        } else { panic!("wrong command"); }
+    }
+
+    #[test]
+    fn parse_search() {
+        let cli = Cli::try_parse_from(["wll", "search", "payment refund", "-n", "3"]).unwrap();
+        if let Command::Search(args) = cli.command {
+            assert_eq!((args.query.as_str(), args.limit), ("payment refund", 3));
+        } else { panic!("wrong command"); }
+    }
+
+    #[test]
+    fn parse_clone() {
+        let cli = Cli::try_parse_from(["wll", "clone", "../big", "ci", "--dissociate"]).unwrap();
+        if let Command::Clone(args) = cli.command {
+            assert_eq!((args.source.as_str(), args.path.as_deref()), ("../big", Some("ci")));
+            assert!(args.dissociate);
+        } else { panic!("wrong command"); }
+        let cli = Cli::try_parse_from(["wll", "pack", "verify", "a.pack"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Pack(PackArgs { action: PackAction::Verify { ref packs } }) if packs.len() == 1
+        ));
+        let cli = Cli::try_parse_from(["wll", "repack", "--dissociate"]).unwrap();
+        assert!(matches!(cli.command, Command::Repack(RepackArgs { dissociate: true })));
+        let cli = Cli::try_parse_from(["wll", "migrate", "--dry-run", "--wal", "a.wal"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Migrate(MigrateArgs { dry_run: true, no_backup: false, ref wal })
+                if wal.len() == 1
+        ));
+    }
+
     #[test]
     fn parse_push() {
         let cli = Cli::try_parse_from(["wll", "push", "origin", "main"]).unwrap();
         if let Command::Push(args) = cli.command {
             assert_eq!(args.remote, Some("origin".into()));
             assert_eq!(args.branch, Some("main".into()));
+            assert!(!args.atomic);
         } else { panic!("wrong command"); }
+        let cli = Cli::try_parse_from(["wll", "push", "--atomic"]).unwrap();
+        assert!(matches!(cli.command, Command::Push(PushArgs { atomic: true, .. })));
     }
 
     #[test]
     fn parse_verify() {
         let cli = Cli::try_parse_from(["wll", "verify"]).unwrap();
-        assert!(matches!(cli.command, Command::Verify(_)));
+        assert!(matches!(
+            cli.command,
+            Command::Verify(VerifyArgs { strict: false, deny_warnings: false })
+        ));
+        let cli = Cli::try_parse_from(["wll", "verify", "--strict", "--deny-warnings"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Verify(VerifyArgs { strict: true, deny_warnings: true })
+        ));
     }
 
     #[test]
     fn parse_serve() {
         let cli = Cli::try_parse_from(["wll", "serve", "--bind", "0.0.0.0:8080"]).unwrap();
         if let Command::Serve(args) = cli.command {
-            assert_eq!(args.bind, "0.0.0.0:8080");
+            assert_eq!(args.addr, Some("0.0.0.0:8080".parse().unwrap()));
+        } else { panic!("wrong command"); }
+
+        let cli = Cli::try_parse_from([
+            "wll", "serve", "--addr", "127.0.0.1:9000", "--repo-root", "/srv/wll", "--config", "wll-server.toml",
+        ])
+        .unwrap();
+        if let Command::Serve(args) = cli.command {
+            assert_eq!(args.repo_root, Some("/srv/wll".into()));
+            assert_eq!(args.config, Some("wll-server.toml".into()));
         } else { panic!("wrong command"); }
     }
 
@@ -312,6 +812,154 @@ mod tests {
     #[test]
     fn parse_json_format() {
         let cli = Cli::try_parse_from(["wll", "--format", "json", "status"]).unwrap();
-        assert!(matches!(cli.format, OutputFormat::Json));
+        assert!(matches!(cli.output, OutputFormat::Json));
+    }
+
+    #[test]
+    fn parse_output_flag() {
+        let cli = Cli::try_parse_from(["wll", "log", "--output", "yaml"]).unwrap();
+        assert!(matches!(cli.output, OutputFormat::Yaml));
+        let cli = Cli::try_parse_from(["wll", "--output", "text", "log"]).unwrap();
+        assert!(matches!(cli.output, OutputFormat::Table));
+    }
+
+    #[test]
+    fn parse_credential() {
+        let cli = Cli::try_parse_from(["wll", "credential", "store", "origin", "--username", "ci"]).unwrap();
+        if let Command::Credential(CredentialArgs { action: CredentialAction::Store { remote, username } }) = cli.command {
+            assert_eq!(remote, "origin");
+            assert_eq!(username.as_deref(), Some("ci"));
+        } else { panic!("wrong command"); }
+        assert!(Cli::try_parse_from(["wll", "credential", "get"]).is_err());
+    }
+
+    #[test]
+    fn parse_bundle() {
+        let cli = Cli::try_parse_from([
+            "wll", "bundle", "create", "out.wllb", "--branch", "main", "--from", "3", "--sign-key", "k.hex",
+        ])
+        .unwrap();
+        if let Command::Bundle(BundleArgs { action: BundleAction::Create { branches, from, to, sign_key, .. } }) = cli.command {
+            assert_eq!(branches, vec!["main".to_string()]);
+            assert_eq!((from, to), (Some(3), None));
+            assert_eq!(sign_key.as_deref(), Some(std::path::Path::new("k.hex")));
+        } else { panic!("wrong command"); }
+        assert!(Cli::try_parse_from(["wll", "bundle", "unbundle", "in.wllb", "--trust-key", "ab"]).is_ok());
+        assert!(Cli::try_parse_from(["wll", "bundle", "verify"]).is_err());
+    }
+
+    #[test]
+    fn parse_redact() {
+        let cli = Cli::try_parse_from(["wll", "redact", "ab12", "--reason", "leaked key", "--key", "k.hex"]).unwrap();
+        if let Command::Redact(args) = cli.command {
+            assert_eq!(args.object, "ab12");
+            assert_eq!(args.reason, "leaked key");
+            assert_eq!(args.key, std::path::PathBuf::from("k.hex"));
+        } else { panic!("wrong command"); }
+        assert!(Cli::try_parse_from(["wll", "redact", "ab12", "--key", "k.hex"]).is_err());
+    }
+
+    #[test]
+    fn parse_workspace() {
+        let cli = Cli::try_parse_from(["wll", "workspace", "add", "billing", "../billing", "--path", "svc/billing"]).unwrap();
+        if let Command::Workspace(WorkspaceArgs { action: WorkspaceAction::Add { name, path, branch, .. } }) = cli.command {
+            assert_eq!(name, "billing");
+            assert_eq!(path.as_deref(), Some("svc/billing"));
+            assert_eq!(branch, "main");
+        } else { panic!("wrong command"); }
+        let cli = Cli::try_parse_from(["wll", "workspace", "update", "billing", "auth", "-m", "bump"]).unwrap();
+        if let Command::Workspace(WorkspaceArgs { action: WorkspaceAction::Update { names, message } }) = cli.command {
+            assert_eq!(names, ["billing", "auth"]);
+            assert_eq!(message.as_deref(), Some("bump"));
+        } else { panic!("wrong command"); }
+        assert!(Cli::try_parse_from(["wll", "workspace", "status"]).is_ok());
+    }
+
+    #[test]
+    fn parse_gc() {
+        let cli = Cli::try_parse_from(["wll", "gc", "--dry-run", "--keep-receipts", "100"]).unwrap();
+        if let Command::Gc(args) = cli.command {
+            assert!(args.dry_run);
+            assert_eq!(args.keep_receipts, Some(100));
+            assert_eq!(args.keep_days, None);
+        } else { panic!("wrong command"); }
+    }
+
+    #[test]
+    fn parse_stats() {
+        let cli = Cli::try_parse_from(["wll", "stats", "--format", "json"]).unwrap();
+        assert!(matches!(cli.output, OutputFormat::Json));
+        assert!(matches!(cli.command, Command::Stats(_)));
+    }
+
+    #[test]
+    fn parse_import() {
+        let cli = Cli::try_parse_from(["wll", "import", "../legacy", "--branch", "main", "--no-tags"]).unwrap();
+        if let Command::Import(args) = cli.command {
+            assert_eq!(args.path, std::path::PathBuf::from("../legacy"));
+            assert_eq!(args.branches, vec!["main".to_string()]);
+            assert!(args.no_tags);
+        } else { panic!("wrong command"); }
+        assert!(Cli::try_parse_from(["wll", "import"]).is_err());
+    }
+
+    #[test]
+    fn parse_export() {
+        let cli = Cli::try_parse_from(["wll", "export", "q3.parquet", "--since", "2026-07-01", "--until", "2026-10-01"]).unwrap();
+        if let Command::Export(args) = cli.command {
+            assert_eq!(args.file, Some(std::path::PathBuf::from("q3.parquet")));
+            assert_eq!(args.since.as_deref(), Some("2026-07-01"));
+            assert_eq!(args.until.as_deref(), Some("2026-10-01"));
+            assert!(args.worldline.is_none());
+        } else { panic!("wrong command"); }
+        let cli = Cli::try_parse_from(["wll", "export"]).unwrap();
+        assert!(matches!(cli.command, Command::Export(ExportArgs { file: None, .. })));
+    }
+
+    #[test]
+    fn parse_replay_strict() {
+        let cli = Cli::try_parse_from(["wll", "replay", "--strict"]).unwrap();
+        assert!(matches!(cli.command, Command::Replay(ReplayArgs { strict: true, from_genesis: false })));
+    }
+
+    #[test]
+    fn parse_gate_check() {
+        let cli = Cli::try_parse_from([
+            "wll", "gate", "check", "-m", "rotate keys", "--class", "IdentityOperation",
+            "--target", "keys/ops", "--target", "keys/ci", "--policy", "strict.yaml",
+        ])
+        .unwrap();
+        if let Command::Gate(GateArgs {
+            action:
+                GateAction::Check(ProposalArgs { message, class, targets, policies, evidence, .. }),
+        }) = cli.command
+        {
+            assert_eq!(message.as_deref(), Some("rotate keys"));
+            assert_eq!(class.as_deref(), Some("IdentityOperation"));
+            assert_eq!(targets, ["keys/ops", "keys/ci"]);
+            assert_eq!(policies, [std::path::PathBuf::from("strict.yaml")]);
+            assert!(evidence.is_empty());
+        } else { panic!("wrong command"); }
+        assert!(Cli::try_parse_from(["wll", "gate"]).is_err());
+    }
+
+    #[test]
+    fn parse_check_with_proposal() {
+        let cli = Cli::try_parse_from([
+            "wll", "--output", "sarif", "check", "--deny-warnings", "-m", "fix: typo",
+        ])
+        .unwrap();
+        assert!(matches!(cli.output, OutputFormat::Sarif));
+        let Command::Check(args) = cli.command else { panic!("wrong command") };
+        assert!(args.verify.deny_warnings && !args.verify.strict);
+        assert_eq!(args.proposal.message.as_deref(), Some("fix: typo"));
+    }
+
+    #[test]
+    fn parse_completions() {
+        let cli = Cli::try_parse_from(["wll", "completions", "zsh"]).unwrap();
+        if let Command::Completions(args) = cli.command {
+            assert_eq!(args.shell, clap_complete::Shell::Zsh);
+        } else { panic!("wrong command"); }
     }
 }
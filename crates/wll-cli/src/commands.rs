@@ -1,38 +1,93 @@
+use std::sync::Arc;
+
+use clap::CommandFactory;
 use colored::Colorize;
+use serde::Serialize;
+use wll_config::{parse_value, ConfigEntry, ConfigLayer, ConfigLevel, ConfigPaths, LayeredConfig};
+use wll_crypto::Secret;
+use wll_protocol::AuthMethod;
+use wll_sdk::{Credential, CredentialManager, RefSpec, RemoteConfig};
+use wll_sync::CredentialRequest;
+
+use crate::ci::{CiCase, CiReport};
 use crate::cli::*;
+use crate::output::{Output, Table};
+use crate::verify::Severity;
 
 pub fn run_command(cli: Cli) -> anyhow::Result<()> {
+    let out = Output::new(cli.output);
     match cli.command {
-        Command::Init(args) => cmd_init(args),
+        Command::Init(args) => cmd_init(args, out),
+        Command::Clone(args) => cmd_clone(args, out),
         Command::Status(_) => cmd_status(),
         Command::Add(args) => cmd_add(args),
         Command::Commit(args) => cmd_commit(args),
-        Command::Log(args) => cmd_log(args),
-        Command::Show(args) => cmd_show(args),
+        Command::Log(args) => cmd_log(args, out),
+        Command::Show(args) => cmd_show(args, out),
+        Command::Notes(args) => cmd_notes(args, out),
+        Command::Search(args) => cmd_search(args, out),
         Command::Branch(args) => cmd_branch(args),
         Command::Switch(args) => cmd_switch(args),
         Command::Tag(args) => cmd_tag(args),
         Command::Diff(_) => { println!("No changes."); Ok(()) },
         Command::Merge(args) => { println!("{} Merged {}.", "✓".green(), args.branch.yellow()); Ok(()) },
-        Command::Remote(args) => cmd_remote(args),
-        Command::Fetch(args) => { println!("Fetching from {}... {}", args.remote.unwrap_or("origin".into()).bold(), "up to date".green()); Ok(()) },
-        Command::Pull(args) => { println!("Pulling {}/{}... {}", args.remote.unwrap_or("origin".into()).bold(), args.branch.unwrap_or("main".into()).yellow(), "up to date".green()); Ok(()) },
-        Command::Push(args) => { println!("Pushing to {}/{}... {}", args.remote.unwrap_or("origin".into()).bold(), args.branch.unwrap_or("main".into()).yellow(), "up to date".green()); Ok(()) },
+        Command::Remote(args) => cmd_remote(args, out),
+        Command::Fetch(args) => cmd_sync(SyncKind::Fetch, args.remote, None, false, out),
+        Command::Pull(args) => cmd_sync(SyncKind::Pull, args.remote, args.branch, false, out),
+        Command::Push(args) => cmd_sync(SyncKind::Push, args.remote, args.branch, args.atomic, out),
+        Command::Credential(args) => cmd_credential(args, out),
+        Command::Bundle(args) => cmd_bundle(args, out),
+        Command::Import(args) => cmd_import(args, out),
+        Command::Workspace(args) => cmd_workspace(args, out),
+        Command::Export(args) => cmd_export(args, out),
         Command::Provenance(args) => { println!("Provenance for receipt {}", args.receipt.yellow()); Ok(()) },
         Command::Impact(args) => { println!("Impact for receipt {}", args.receipt.yellow()); Ok(()) },
-        Command::Verify(_) => cmd_verify(),
-        Command::Replay(_) => { println!("{} Replay complete.", "✓".green().bold()); Ok(()) },
+        Command::Verify(args) => cmd_verify(args, out),
+        Command::Check(args) => cmd_check(args, out),
+        Command::Replay(args) => cmd_replay(args, out),
         Command::Audit(_) => { println!("Audit trail: no receipts."); Ok(()) },
-        Command::Gc(_) => { println!("{} GC: 0 objects removed.", "✓".green()); Ok(()) },
-        Command::Repack(_) => { println!("{} Repack done.", "✓".green()); Ok(()) },
+        Command::Gate(args) => cmd_gate(args, out),
+        Command::Redact(args) => cmd_redact(args, out),
+        Command::Gc(args) => cmd_gc(args, out),
+        Command::Stats(_) => cmd_stats(out),
+        Command::Repack(args) => cmd_repack(args, out),
+        Command::Pack(args) => cmd_pack(args, out),
         Command::Fsck(_) => { println!("{} No issues.", "✓".green().bold()); Ok(()) },
-        Command::Config(args) => cmd_config(args),
-        Command::Serve(args) => { println!("WLL server on {} (root: {})", args.bind.bold(), args.root); Ok(()) },
+        Command::Migrate(args) => cmd_migrate(args, out),
+        Command::Config(args) => cmd_config(args, out),
+        Command::Serve(args) => cmd_serve(args),
+        Command::Completions(args) => {
+            clap_complete::generate(args.shell, &mut Cli::command(), "wll", &mut std::io::stdout());
+            Ok(())
+        }
     }
 }
 
-fn cmd_init(args: InitArgs) -> anyhow::Result<()> {
+fn cmd_init(args: InitArgs, out: Output) -> anyhow::Result<()> {
     let path = args.path.unwrap_or_else(|| ".".into());
+    if let Some(spec) = &args.template {
+        let template = wll_sdk::RepoTemplate::resolve(spec)?;
+        let (wll, genesis) = wll_sdk::Wll::init_from_template(&path, &template)?;
+        let branches: Vec<String> = wll.list_branches()?.into_iter()
+            .map(|b| b.trim_start_matches("refs/heads/").to_string())
+            .collect();
+        let report = serde_json::json!({
+            "worldline": wll.worldline().to_string(),
+            "template": template.name,
+            "branch": wll.current_branch()?,
+            "branches": branches,
+            "genesis": hex::encode(genesis.receipt_hash),
+        });
+        return out.emit(&report, || {
+            println!("{} Initialized WLL repository in {} from template {}", "✓".green().bold(), path.bold(), template.name.blue());
+            println!("  WorldLine: {}", wll.worldline().to_string().cyan());
+            println!("  Branch: {} (of {})", wll.current_branch()?.yellow(), branches.join(", "));
+            println!("  Genesis: {} ({} files, {} policies, {} capabilities)",
+                hex::encode(&genesis.receipt_hash[..8]).yellow(),
+                template.files.len(), template.policies.len(), template.capabilities.len());
+            Ok(())
+        });
+    }
     let mode = if args.bare { "bare " } else { "" };
     println!("{} Initialized {}WLL repository in {}", "✓".green().bold(), mode, path.bold());
     println!("  WorldLine: {}", "wl:...".cyan());
@@ -40,6 +95,99 @@ fn cmd_init(args: InitArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
+fn cmd_clone(args: CloneArgs, out: Output) -> anyhow::Result<()> {
+    let path = match args.path {
+        Some(path) => std::path::PathBuf::from(path),
+        None => std::path::Path::new(&args.source)
+            .canonicalize()?
+            .file_name()
+            .map(std::path::PathBuf::from)
+            .ok_or_else(|| anyhow::anyhow!("cannot name a clone of {}; give a path", args.source))?,
+    };
+    let options = wll_sdk::LocalCloneOptions::new().with_shared_objects(!args.dissociate);
+    let (_, report) = wll_sdk::Wll::clone_local(&args.source, &path, &options)?;
+    out.emit(&report, || {
+        println!("{} Cloned {} into {}", "✓".green().bold(), args.source.blue(), path.display().to_string().bold());
+        println!("  Objects: {} linked, {} copied ({} bytes)", report.objects_linked, report.objects_copied, report.bytes_copied);
+        Ok(())
+    })
+}
+
+fn cmd_repack(args: RepackArgs, out: Output) -> anyhow::Result<()> {
+    if !args.dissociate {
+        println!("{} Repack done.", "✓".green());
+        return Ok(());
+    }
+    let dissociated = open_repo()?.dissociate()?;
+    out.emit(&serde_json::json!({ "dissociated": dissociated }), || {
+        println!("{} Made {dissociated} shared object files private", "✓".green());
+        Ok(())
+    })
+}
+
+#[derive(Serialize)]
+struct PackReport {
+    pack: String,
+    #[serde(flatten)]
+    verification: wll_pack::PackVerification,
+}
+
+fn cmd_pack(args: PackArgs, out: Output) -> anyhow::Result<()> {
+    let PackAction::Verify { mut packs } = args.action;
+    if packs.is_empty() {
+        let wll = open_repo()?;
+        let dir = wll.repo_dir().map(|dir| dir.join("objects").join("pack"));
+        if let Some(dir) = dir.filter(|dir| dir.is_dir()) {
+            for entry in std::fs::read_dir(dir)? {
+                let path = entry?.path();
+                if path.extension().is_some_and(|ext| ext == "pack") {
+                    packs.push(path);
+                }
+            }
+        }
+        packs.sort();
+    }
+    let reports = packs
+        .iter()
+        .map(|pack| {
+            let reader = wll_pack::PackReader::open(pack)
+                .map_err(|e| anyhow::anyhow!("{}: {e}", pack.display()))?;
+            Ok(PackReport { pack: pack.display().to_string(), verification: reader.verify() })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    out.emit(&reports, || {
+        if reports.is_empty() {
+            println!("No packs.");
+        }
+        for report in &reports {
+            let checks = &report.verification;
+            if checks.is_ok() {
+                println!("{} {} ({} objects)", "✓".green().bold(), report.pack.bold(), checks.entries.len());
+                continue;
+            }
+            println!("{} {}", "✗".red().bold(), report.pack.bold());
+            if !checks.checksum_ok {
+                println!("  {} pack checksum does not match its contents", "✗".red());
+            }
+            if !checks.index_checksum_ok {
+                println!("  {} index records a different pack checksum", "✗".red());
+            }
+            for entry in checks.failures() {
+                let id = entry.id.map_or_else(|| "?".to_string(), |id| id.short_hex());
+                for problem in &entry.problems {
+                    println!("  {} @{} {}: {problem}", "✗".red(), entry.offset, id);
+                }
+            }
+        }
+        Ok(())
+    })?;
+    if reports.iter().any(|report| !report.verification.is_ok()) {
+        anyhow::bail!("pack verification failed");
+    }
+    Ok(())
+}
+
 fn cmd_status() -> anyhow::Result<()> {
     println!("On branch {}", "main".yellow().bold());
     println!("WorldLine: {}", "wl:...".cyan());
@@ -65,20 +213,108 @@ fn cmd_commit(args: CommitArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn cmd_log(args: LogArgs) -> anyhow::Result<()> {
-    if args.oneline {
-        println!("{} {} {}", "r#1".yellow(), "abc123".dimmed(), "Initial commit");
-    } else {
-        println!("{}  {}  ({})", "r#1".yellow().bold(), "abc123".dimmed(), "main".green());
-        println!("  {} | ContentUpdate", "✓ Accepted".green());
-        println!("  Intent: Initial commit");
+fn cmd_log(args: LogArgs, out: Output) -> anyhow::Result<()> {
+    let wll = open_repo()?;
+    let page: Vec<_> = crate::log::collect(&wll, args.all)?
+        .into_iter()
+        .skip(args.skip)
+        .take(args.limit)
+        .collect();
+
+    out.emit(&page, || {
+        if page.is_empty() {
+            println!("No receipts.");
+        }
+        for line in crate::log::render(&page, args.oneline, args.graph) {
+            println!("{line}");
+        }
+        Ok(())
+    })
+}
+
+/// Open the repository containing the current directory.
+fn open_repo() -> anyhow::Result<wll_sdk::Wll> {
+    Ok(wll_sdk::Wll::discover(std::env::current_dir()?)?)
+}
+
+fn cmd_show(args: ShowArgs, out: Output) -> anyhow::Result<()> {
+    let wll = open_repo()?;
+    let rev = args.receipt.as_deref().unwrap_or("HEAD");
+    let details = wll.receipt_details(&wll.resolve(rev)?)?;
+
+    out.emit(&details, || {
+        for line in crate::show::render(&wll, &details, !args.no_patch)? {
+            println!("{line}");
+        }
+        Ok(())
+    })?;
+    if let Some(dir) = &args.extract {
+        for path in crate::show::extract(&wll, &details, dir)? {
+            eprintln!("  {} {}", "extracted:".green(), path.display());
+        }
     }
     Ok(())
 }
 
-fn cmd_show(args: ShowArgs) -> anyhow::Result<()> {
-    println!("Receipt {} — Type: Commitment, Seq: 1, Decision: {}", args.receipt.yellow().bold(), "Accepted".green());
-    Ok(())
+fn cmd_notes(args: NotesArgs, out: Output) -> anyhow::Result<()> {
+    let wll = open_repo()?;
+    match args.action {
+        NotesAction::Add { receipt, message, links } => {
+            let hash = wll.resolve(receipt.as_deref().unwrap_or("HEAD"))?;
+            let mut note = wll.new_note(hash, message);
+            if let Some(name) = wll.config()?.user()?.name {
+                note = note.with_author(name);
+            }
+            for link in links {
+                note = note.with_link(link);
+            }
+            let stored = wll.add_note(note)?;
+            out.emit(&stored, || {
+                println!(
+                    "{} Added note {} to {}",
+                    "✓".green(),
+                    stored.id.short_hex().yellow(),
+                    hex::encode(hash).dimmed()
+                );
+                Ok(())
+            })
+        }
+        NotesAction::List { receipt } => {
+            let hash = wll.resolve(receipt.as_deref().unwrap_or("HEAD"))?;
+            let notes = wll.notes(&hash)?;
+            out.emit(&notes, || {
+                if notes.is_empty() {
+                    println!("No notes.");
+                }
+                for stored in &notes {
+                    let author = stored.note.author.as_deref().unwrap_or("-");
+                    println!(
+                        "{}  {}  {author}",
+                        stored.id.short_hex().yellow(),
+                        stored.note.created
+                    );
+                    stored.note.text.lines().for_each(|line| println!("  {line}"));
+                    stored.note.links.iter().for_each(|link| println!("  Link: {}", link.blue()));
+                }
+                Ok(())
+            })
+        }
+    }
+}
+
+fn cmd_search(args: SearchArgs, out: Output) -> anyhow::Result<()> {
+    let wll = open_repo()?;
+    let hits = wll.search(&args.query, args.limit)?;
+    out.emit(&hits, || {
+        if hits.is_empty() {
+            println!("No matches.");
+        }
+        for hit in &hits {
+            let hash = hex::encode(&hit.receipt_hash[..4]);
+            println!("{} {} {}", hash.yellow(), format!("r#{}", hit.seq).dimmed(), hit.intent);
+        }
+        Ok(())
+    })
 }
 
 fn cmd_branch(args: BranchArgs) -> anyhow::Result<()> {
@@ -112,29 +348,1051 @@ fn cmd_tag(args: TagArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn cmd_remote(args: RemoteArgs) -> anyhow::Result<()> {
+fn cmd_remote(args: RemoteArgs, out: Output) -> anyhow::Result<()> {
+    let wll = open_repo()?;
     match args.action {
-        Some(RemoteAction::Add { name, url }) => println!("Added remote {} → {}", name.bold(), url.blue()),
-        Some(RemoteAction::Remove { name }) => println!("Removed remote {}", name.bold()),
-        None => println!("No remotes configured."),
+        Some(RemoteAction::Add { name, url, fetch }) => {
+            let fetch = fetch
+                .iter()
+                .map(|spec| RefSpec::parse(spec).ok_or_else(|| anyhow::anyhow!("invalid refspec: {spec}")))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let mut remote = RemoteConfig::new(&name, &url);
+            if !fetch.is_empty() {
+                remote = remote.with_fetch(fetch);
+            }
+            wll.add_remote(remote.clone())?;
+            out.emit(&remote, || {
+                println!("Added remote {} → {}", name.bold(), url.blue());
+                Ok(())
+            })
+        }
+        Some(RemoteAction::Remove { name }) => {
+            let removed = wll.remove_remote(&name)?;
+            out.emit(&removed, || {
+                println!("Removed remote {}", name.bold());
+                Ok(())
+            })
+        }
+        Some(RemoteAction::SetUrl { name, url }) => {
+            wll.set_remote_url(&name, &url)?;
+            out.emit(&wll.remote(&name)?, || {
+                println!("Remote {} → {}", name.bold(), url.blue());
+                Ok(())
+            })
+        }
+        Some(RemoteAction::List) | None => {
+            let remotes = wll.remotes()?;
+            out.emit(&remotes, || {
+                if remotes.is_empty() {
+                    println!("No remotes configured.");
+                } else if args.verbose {
+                    let mut table = Table::new(&["NAME", "URL", "FETCH"]);
+                    for remote in &remotes {
+                        let fetch = remote.fetch.iter().map(ToString::to_string).collect::<Vec<_>>();
+                        table.row(vec![remote.name.clone(), remote.url.clone(), fetch.join(" ")]);
+                    }
+                    table.render().iter().for_each(|line| println!("{line}"));
+                } else {
+                    remotes.iter().for_each(|remote| println!("{}", remote.name));
+                }
+                Ok(())
+            })
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum SyncKind {
+    Fetch,
+    Pull,
+    Push,
+}
+
+/// Result of `fetch`, `pull`, or `push`.
+#[derive(Serialize)]
+struct SyncReport {
+    operation: SyncKind,
+    remote: String,
+    url: String,
+    branch: Option<String>,
+    /// How the client authenticates: `anonymous`, `bearer-token`, ...
+    auth: &'static str,
+    /// Whether a push applies all of its ref updates or none.
+    atomic: bool,
+    up_to_date: bool,
+}
+
+fn cmd_sync(kind: SyncKind, remote: Option<String>, branch: Option<String>, atomic: bool, out: Output) -> anyhow::Result<()> {
+    let remote = configured_remote(remote)?;
+    let branch = match kind {
+        SyncKind::Fetch => None,
+        SyncKind::Pull | SyncKind::Push => Some(branch.unwrap_or_else(|| "main".into())),
+    };
+    let auth = if remote.url.contains("://") {
+        credential_manager()?.auth_for(&remote.url)?
+    } else {
+        AuthMethod::Anonymous
+    };
+    let report = SyncReport {
+        operation: kind,
+        remote: remote.name,
+        url: remote.url,
+        branch,
+        auth: auth.display_name(),
+        atomic,
+        up_to_date: true,
+    };
+    out.emit(&report, || {
+        let target = match &report.branch {
+            Some(branch) => format!("{}/{}", report.remote.bold(), branch.yellow()),
+            None => report.remote.bold().to_string(),
+        };
+        let verb = match kind {
+            SyncKind::Fetch => "Fetching from",
+            SyncKind::Pull => "Pulling",
+            SyncKind::Push => "Pushing to",
+        };
+        let auth = if auth.is_authenticated() { format!(", {}", report.auth) } else { String::new() };
+        let atomic = if report.atomic { ", atomic" } else { "" };
+        println!("{verb} {target} ({}{auth}{atomic})... {}", report.url.blue(), "up to date".green());
+        Ok(())
+    })
+}
+
+fn cmd_credential(args: CredentialArgs, out: Output) -> anyhow::Result<()> {
+    let remote = match &args.action {
+        CredentialAction::Get { remote } | CredentialAction::Store { remote, .. } | CredentialAction::Erase { remote } => remote,
+    };
+    let url = if remote.contains("://") { remote.clone() } else { configured_remote(Some(remote.clone()))?.url };
+    let request = CredentialRequest::from_url(&url)?;
+    let manager = credential_manager()?;
+    match args.action {
+        CredentialAction::Get { .. } => {
+            let credential = manager
+                .get(&url)?
+                .ok_or_else(|| anyhow::anyhow!("no credential for {}", request.host))?;
+            out.emit(&credential, || {
+                println!("protocol={}", request.protocol);
+                println!("host={}", credential.host);
+                if let Some(username) = &credential.username {
+                    println!("username={username}");
+                }
+                println!("password={}", credential.token.expose());
+                Ok(())
+            })
+        }
+        CredentialAction::Store { username, .. } => {
+            let mut line = String::new();
+            std::io::stdin().read_line(&mut line)?;
+            // Owned by a `Secret` so the line is zeroized once stored.
+            let line = Secret::from(line);
+            let token = line.expose().trim_end_matches(['\r', '\n']);
+            anyhow::ensure!(!token.is_empty(), "no token on stdin");
+            let mut credential = Credential::new(&request.host, token);
+            if let Some(username) = username.or(request.username.clone()) {
+                credential = credential.with_username(username);
+            }
+            manager.approve(&url, &credential).map_err(|e| {
+                anyhow::anyhow!("{e}; set one with `wll config --global credential.helper store`")
+            })?;
+            out.emit(&serde_json::json!({ "host": request.host, "stored": true }), || {
+                println!("{} Stored credential for {}", "✓".green(), request.host.bold());
+                Ok(())
+            })
+        }
+        CredentialAction::Erase { .. } => {
+            manager.reject(&url)?;
+            out.emit(&serde_json::json!({ "host": request.host, "erased": true }), || {
+                println!("Erased credential for {}", request.host.bold());
+                Ok(())
+            })
+        }
+    }
+}
+
+/// Result of `bundle create`.
+#[derive(Serialize)]
+struct BundleCreated {
+    file: String,
+    bytes: usize,
+    from_seq: u64,
+    to_seq: u64,
+    receipts: usize,
+    refs: Vec<String>,
+    signed_by: Option<String>,
+}
+
+fn cmd_bundle(args: BundleArgs, out: Output) -> anyhow::Result<()> {
+    let wll = open_repo()?;
+    let read = |file: &std::path::Path, trust_keys: &[String]| -> anyhow::Result<wll_sdk::Bundle> {
+        let bytes = std::fs::read(file).map_err(|e| anyhow::anyhow!("{}: {e}", file.display()))?;
+        let bundle = wll_sdk::Bundle::from_bytes(&bytes)?;
+        if !trust_keys.is_empty() {
+            let trusted = trust_keys
+                .iter()
+                .map(|key| wll_sdk::bundle::parse_trusted_key(key))
+                .collect::<Result<Vec<_>, _>>()?;
+            bundle.verify_signer(&trusted)?;
+        }
+        Ok(bundle)
+    };
+    match args.action {
+        BundleAction::Create { file, branches, from, to, sign_key } => {
+            let options = wll_sdk::BundleOptions::new().with_branches(branches).with_range(from, to);
+            let mut bundle = wll.create_bundle(&options)?;
+            if let Some(path) = &sign_key {
+                bundle.sign(&wll_sdk::bundle::read_signing_key(path)?)?;
+            }
+            let bytes = bundle.to_bytes()?;
+            std::fs::write(&file, &bytes)?;
+            let report = BundleCreated {
+                file: file.display().to_string(),
+                bytes: bytes.len(),
+                from_seq: bundle.receipts.first().map_or(0, |r| r.seq()),
+                to_seq: bundle.receipts.last().map_or(0, |r| r.seq()),
+                receipts: bundle.receipts.len(),
+                refs: bundle.refs.iter().map(|r| r.name.clone()).collect(),
+                signed_by: bundle.signature.as_ref().map(|s| hex::encode(s.public_key.as_bytes())),
+            };
+            out.emit(&report, || {
+                println!(
+                    "{} Wrote {} ({} receipts, r#{}..r#{}, {} bytes)",
+                    "✓".green(),
+                    report.file.bold(),
+                    report.receipts,
+                    report.from_seq,
+                    report.to_seq,
+                    report.bytes
+                );
+                report.refs.iter().for_each(|name| println!("  {name}"));
+                if let Some(key) = &report.signed_by {
+                    println!("  signed by {}", key.yellow());
+                }
+                Ok(())
+            })
+        }
+        BundleAction::Verify { file, trust_keys } => {
+            let summary = wll.verify_bundle(&read(&file, &trust_keys)?)?;
+            out.emit(&summary, || {
+                println!("{} {} is valid", "✓".green().bold(), file.display().to_string().bold());
+                println!(
+                    "  Receipts: {} (r#{}..r#{}, {} new)",
+                    summary.receipts, summary.from_seq, summary.to_seq, summary.new_receipts
+                );
+                println!("  Objects: {}", summary.objects);
+                for r in &summary.refs {
+                    println!("  {} {}", &hex::encode(r.receipt_hash)[..12], r.name);
+                }
+                match &summary.signed_by {
+                    Some(key) => println!("  Signed by: {}", key.yellow()),
+                    None => println!("  Signed by: {}", "unsigned".dimmed()),
+                }
+                Ok(())
+            })
+        }
+        BundleAction::Unbundle { file, trust_keys } => {
+            let report = wll.unbundle(read(&file, &trust_keys)?)?;
+            out.emit(&report, || {
+                println!(
+                    "{} Imported {} receipts and {} objects",
+                    "✓".green(),
+                    report.receipts_imported,
+                    report.objects
+                );
+                report.refs_updated.iter().for_each(|name| println!("  updated {name}"));
+                if report.notes_merged > 0 {
+                    println!("  merged {} notes", report.notes_merged);
+                }
+                Ok(())
+            })
+        }
+    }
+}
+
+fn cmd_import(args: ImportArgs, out: Output) -> anyhow::Result<()> {
+    let wll = open_repo()?;
+    let options = wll_import::ImportOptions::new()
+        .with_branches(args.branches)
+        .with_tags(!args.no_tags);
+    let report = wll_import::GitImporter::new(&wll, &args.path)?.with_options(options).run()?;
+    out.emit(&report, || {
+        println!(
+            "{} Imported {} commits from {} ({} trees, {} blobs)",
+            "✓".green().bold(),
+            report.commits,
+            args.path.display().to_string().bold(),
+            report.trees,
+            report.blobs
+        );
+        println!("  Branches: {}", report.branches.join(", ").yellow());
+        if !report.tags.is_empty() {
+            println!("  Tags: {}", report.tags.join(", "));
+        }
+        println!("  Authors: {}", report.authors.len());
+        if report.skipped_submodules > 0 {
+            println!("  Skipped {} submodule entries", report.skipped_submodules);
+        }
+        Ok(())
+    })
+}
+
+#[derive(Serialize)]
+struct MemberReport {
+    name: String,
+    path: String,
+    url: String,
+    worldline: String,
+    branch: String,
+    pin: String,
+    tip: Option<String>,
+    #[serde(flatten)]
+    state: wll_sdk::PinState,
+}
+
+fn cmd_workspace(args: WorkspaceArgs, out: Output) -> anyhow::Result<()> {
+    let wll = open_repo()?;
+    match args.action {
+        WorkspaceAction::Add { name, url, path, branch } => {
+            let result = wll.add_workspace_member(&name, &url, path.as_deref(), &branch)?;
+            let manifest = wll.workspace()?;
+            let member = manifest.member(&name).expect("member was just added");
+            let report = serde_json::json!({
+                "name": member.name,
+                "path": member.path,
+                "pin": hex::encode(member.pin),
+                "receipt": hex::encode(result.receipt_hash),
+            });
+            out.emit(&report, || {
+                println!("{} Added {} at {} → {}", "✓".green().bold(), name.bold(), member.path, url.blue());
+                println!("  Pin: {}", &hex::encode(member.pin)[..12]);
+                Ok(())
+            })
+        }
+        WorkspaceAction::Clone { names } => {
+            let cloned = wll.clone_workspace(&names)?;
+            let report: Vec<_> = cloned
+                .iter()
+                .map(|c| {
+                    serde_json::json!({
+                        "name": c.name,
+                        "path": c.path.display().to_string(),
+                        "receipts": c.receipts_imported,
+                        "objects": c.objects,
+                    })
+                })
+                .collect();
+            out.emit(&report, || {
+                if cloned.is_empty() {
+                    println!("Every member is already cloned.");
+                }
+                for c in &cloned {
+                    println!(
+                        "{} Cloned {} into {} ({} receipts, {} objects)",
+                        "✓".green(),
+                        c.name.bold(),
+                        c.path.display(),
+                        c.receipts_imported,
+                        c.objects
+                    );
+                }
+                Ok(())
+            })
+        }
+        WorkspaceAction::Status => {
+            let report: Vec<MemberReport> = wll
+                .workspace_status()?
+                .into_iter()
+                .map(|s| MemberReport {
+                    name: s.member.name,
+                    path: s.member.path,
+                    url: s.member.url,
+                    worldline: s.member.worldline.to_hex(),
+                    branch: s.member.branch,
+                    pin: hex::encode(s.member.pin),
+                    tip: s.tip.map(hex::encode),
+                    state: s.state,
+                })
+                .collect();
+            out.emit(&report, || {
+                if report.is_empty() {
+                    println!("No workspace members.");
+                    return Ok(());
+                }
+                let mut table = Table::new(&["MEMBER", "PATH", "PIN", "STATE"]);
+                for m in &report {
+                    let state = match &m.state {
+                        wll_sdk::PinState::NotCloned => "not cloned".yellow().to_string(),
+                        wll_sdk::PinState::WrongWorldline { found } => {
+                            format!("wrong worldline {}", &found.to_hex()[..12]).red().to_string()
+                        }
+                        wll_sdk::PinState::PinMissing => "pin missing".red().to_string(),
+                        wll_sdk::PinState::AtPin => "at pin".green().to_string(),
+                        wll_sdk::PinState::Ahead { receipts } => format!("{receipts} ahead").cyan().to_string(),
+                        wll_sdk::PinState::Behind { receipts } => format!("{receipts} behind").cyan().to_string(),
+                    };
+                    table.row(vec![m.name.clone(), m.path.clone(), m.pin[..12].to_string(), state]);
+                }
+                table.render().iter().for_each(|line| println!("{line}"));
+                Ok(())
+            })
+        }
+        WorkspaceAction::Update { names, message } => {
+            let result = wll.update_workspace_pins(&names, message.as_deref())?;
+            let moved: Vec<(String, String)> = result
+                .as_ref()
+                .map(|r| {
+                    r.outcome_receipt
+                        .metadata
+                        .iter()
+                        .filter_map(|(key, pin)| {
+                            let name = key.strip_prefix(wll_sdk::workspace::WORKSPACE_PIN_KEY_PREFIX)?;
+                            Some((name.to_string(), pin.clone()))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            let report = serde_json::json!({
+                "receipt": result.as_ref().map(|r| hex::encode(r.receipt_hash)),
+                "pins": moved.iter().cloned().collect::<std::collections::BTreeMap<_, _>>(),
+            });
+            out.emit(&report, || {
+                if moved.is_empty() {
+                    println!("Every pin is current.");
+                }
+                for (name, pin) in &moved {
+                    println!("{} Pinned {} to {}", "✓".green(), name.bold(), &pin[..12]);
+                }
+                Ok(())
+            })
+        }
+    }
+}
+
+fn cmd_export(args: ExportArgs, out: Output) -> anyhow::Result<()> {
+    let wll = open_repo()?;
+    let is_parquet = args.file.as_ref().is_some_and(|f| f.extension().is_some_and(|e| e == "parquet"));
+    let format = if is_parquet { wll_sdk::ExportFormat::Parquet } else { wll_sdk::ExportFormat::Jsonl };
+    let mut options = wll_sdk::ExportOptions::new(format).with_time_range(
+        args.since.as_deref().map(parse_time).transpose()?,
+        args.until.as_deref().map(parse_time).transpose()?,
+    );
+    if let Some(worldline) = &args.worldline {
+        options = options.with_worldline(wll_sdk::WorldlineId::from_hex(worldline)?);
+    }
+
+    let Some(path) = &args.file else {
+        wll.export_receipts(&options, std::io::BufWriter::new(std::io::stdout()))?;
+        return Ok(());
+    };
+    let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    let summary = wll.export_receipts(&options, file)?;
+    out.emit(&summary, || {
+        let range = match (summary.first_seq, summary.last_seq) {
+            (Some(first), Some(last)) => format!(" (r#{first}..r#{last})"),
+            _ => String::new(),
+        };
+        println!(
+            "{} Exported {} receipts{} to {}",
+            "✓".green().bold(),
+            summary.receipts,
+            range,
+            path.display().to_string().bold()
+        );
+        Ok(())
+    })
+}
+
+/// Milliseconds since the epoch for an RFC 3339 time or a `YYYY-MM-DD` date
+/// (midnight UTC).
+fn parse_time(value: &str) -> anyhow::Result<u64> {
+    let time = match chrono::DateTime::parse_from_rfc3339(value) {
+        Ok(time) => time.to_utc(),
+        Err(_) => chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map_err(|_| anyhow::anyhow!("invalid time {value:?}; expected RFC 3339 or YYYY-MM-DD"))?
+            .and_time(chrono::NaiveTime::MIN)
+            .and_utc(),
+    };
+    u64::try_from(time.timestamp_millis())
+        .map_err(|_| anyhow::anyhow!("time {value:?} is before 1970"))
+}
+
+/// Credentials configured for the current repository, or from the user and
+/// system config outside one.
+fn credential_manager() -> anyhow::Result<CredentialManager> {
+    if let Ok(wll) = open_repo() {
+        return Ok(wll.credentials()?);
+    }
+    let paths = ConfigPaths::standard(None);
+    let settings = LayeredConfig::load(&paths)?.credential()?;
+    Ok(CredentialManager::from_settings(&settings, paths.credential_store())?)
+}
+
+/// Remote named `name` (default `origin`) from the repository config.
+fn configured_remote(name: Option<String>) -> anyhow::Result<RemoteConfig> {
+    let name = name.unwrap_or_else(|| "origin".into());
+    let wll = open_repo()?;
+    wll.remote(&name)
+        .map_err(|_| anyhow::anyhow!("no remote named {name}; add one with `wll remote add {name} <url>`"))
+}
+
+fn cmd_serve(args: ServeArgs) -> anyhow::Result<()> {
+    let mut config = match &args.config {
+        Some(path) => wll_server::ServerConfig::load(path)?,
+        None => wll_server::ServerConfig::default(),
+    };
+    if let Some(addr) = args.addr {
+        config.bind_addr = addr;
+    }
+    if let Some(root) = args.repo_root {
+        config.repos_root = root;
+    }
+
+    let scheme = if config.tls.is_some() { "https" } else { "http" };
+    println!(
+        "WLL server on {} (root: {})",
+        format!("{scheme}://{}", config.bind_addr).bold(),
+        config.repos_root.display()
+    );
+    // A repository at the root is also served to read-only queries and
+    // maintained on the `[maintenance]` schedule.
+    let served = wll_sdk::Wll::open(&config.repos_root).ok().map(Arc::new);
+    let name = config.repos_root.display().to_string();
+    let fabric = match &config.maintenance.fabric_wal {
+        Some(wal) => Some(Arc::new(wll_fabric::EventFabric::new(wal, Default::default())?)),
+        None => None,
+    };
+    // Nodes serving the repository contend for its worldline's lease.
+    let cluster = config.cluster.clone().map(|cluster| {
+        let cluster = wll_server::Cluster::new(cluster);
+        match &served {
+            Some(wll) => cluster.with_worldline(wll.worldline().clone()),
+            None => cluster,
+        }
+    });
+    let mut server = wll_server::WllServer::new(config);
+    if let Some(cluster) = &cluster {
+        server = server.with_cluster(cluster.clone());
+    }
+    if let Some(wll) = served {
+        server = server.with_repository(name.clone(), wll.shared_ledger(), wll.shared_refs());
+        #[cfg(feature = "graphql")]
+        {
+            let wll = wll.clone();
+            server = server.with_provenance(Arc::new(move || wll.provenance().unwrap_or_default()));
+        }
+        let fabric = fabric.or_else(|| wll.fabric().cloned());
+        let repo = Arc::new(ServedRepo { wll, fabric, cluster });
+        server = server.with_maintenance(name.clone(), repo.clone()).with_shutdown(name, repo);
     }
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(server.serve_with_shutdown(wll_server::shutdown_signal()))?;
+    println!("{} Server stopped.", "✓".green());
     Ok(())
 }
 
-fn cmd_verify() -> anyhow::Result<()> {
-    println!("{} Receipt chain integrity verified", "✓".green().bold());
-    println!("  Hash chain: {}", "valid".green());
-    println!("  Sequences: {}", "monotonic".green());
-    println!("  Outcomes: {}", "attributed".green());
-    println!("  Snapshots: {}", "anchored".green());
+/// The repository `wll serve` hosts, as its maintenance scheduler and
+/// shutdown see it.
+struct ServedRepo {
+    wll: Arc<wll_sdk::Wll>,
+    fabric: Option<Arc<wll_fabric::EventFabric>>,
+    /// The node's cluster, whose primary alone archives the worldline.
+    cluster: Option<wll_server::Cluster>,
+}
+
+impl wll_server::MaintenanceTarget for ServedRepo {
+    fn run(&self, task: wll_server::MaintenanceTask) -> Result<String, String> {
+        use wll_server::MaintenanceTask;
+        let wll = &self.wll;
+        let summary = match task {
+            MaintenanceTask::Gc => {
+                // Archiving writes the ledger, which only the primary may.
+                let fence = self.cluster.as_ref().map(|c| c.fence_worldline(wll.worldline()));
+                if let Some(Err(e)) = fence {
+                    return Ok(format!("skipped: {e}"));
+                }
+                wll.config()
+                    .and_then(|config| Ok(config.retention()?))
+                    .and_then(|settings| {
+                        wll.apply_retention(&wll_sdk::RetentionPolicy::from_settings(&settings))
+                    })
+                    .map(|report| {
+                        format!(
+                            "archived {} receipts, removed {} objects",
+                            report.receipts_archived,
+                            report.objects_removed.len() as u64 + report.pack_objects_removed
+                        )
+                    })
+            }
+            MaintenanceTask::Repack => {
+                wll.repack().map(|report| format!("packed {} objects", report.objects_packed))
+            }
+            MaintenanceTask::Scrub => match wll.scrub() {
+                Ok(report) if !report.is_clean() => return Err(report.problems.join("; ")),
+                result => result.map(|report| {
+                    format!(
+                        "checked {} objects, {} receipts, {} packs",
+                        report.objects_checked, report.receipts_checked, report.packs_checked
+                    )
+                }),
+            },
+            MaintenanceTask::DagCheckpoint => {
+                wll.checkpoint_dag().map(|pruned| format!("pruned {pruned} provenance nodes"))
+            }
+        };
+        summary.map_err(|e| e.to_string())
+    }
+
+    fn fabric(&self) -> Option<(Arc<wll_fabric::EventFabric>, wll_sdk::WorldlineId)> {
+        self.fabric.clone().map(|fabric| (fabric, self.wll.worldline().clone()))
+    }
+}
+
+impl wll_server::ShutdownTarget for ServedRepo {
+    fn shutdown(&self) -> Result<String, String> {
+        let Some(fabric) = &self.fabric else {
+            return Ok("nothing buffered".into());
+        };
+        fabric.sync().and_then(|()| fabric.checkpoint()).map_err(|e| e.to_string())?;
+        Ok("event log flushed and checkpointed".into())
+    }
+}
+
+fn cmd_verify(args: VerifyArgs, out: Output) -> anyhow::Result<()> {
+    let wll = open_repo()?;
+    let report = crate::verify::check(&wll, args.strict, args.deny_warnings)?;
+    out.emit_ci(&report, || {
+        for line in crate::verify::render(&report) {
+            println!("{line}");
+        }
+        Ok(())
+    })?;
+    if report.exit_code != 0 {
+        return Err(crate::verify::failure(report.status, report.exit_code).into());
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct CheckReport {
+    /// The worst finding of the verification and the gate, which counts a
+    /// rejection as a policy violation and unenforced failures as warnings.
+    status: Option<Severity>,
+    exit_code: u8,
+    verify: crate::verify::VerifyReport,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gate: Option<wll_sdk::GateSimulation>,
+}
+
+impl CiReport for CheckReport {
+    fn ci_cases(&self) -> Vec<CiCase> {
+        let mut cases = self.verify.ci_cases();
+        cases.extend(self.gate.iter().flat_map(CiReport::ci_cases));
+        cases
+    }
+}
+
+fn cmd_check(args: CheckArgs, out: Output) -> anyhow::Result<()> {
+    let CheckArgs { verify, proposal } = args;
+    let wll = open_repo()?;
+    let report = crate::verify::check(&wll, verify.strict, verify.deny_warnings)?;
+    let gate = match proposal.message.is_some() || proposal.intent.is_some() {
+        true => Some(simulate_gate(&wll, proposal)?),
+        false => None,
+    };
+    let gate_status = gate.as_ref().and_then(|simulation| {
+        match (simulation.is_accepted(), simulation.all_passed()) {
+            (false, _) => Some(Severity::PolicyViolation),
+            (true, false) => Some(Severity::Warning),
+            (true, true) => None,
+        }
+    });
+    let status = report.status.max(gate_status);
+    let report = CheckReport {
+        status,
+        exit_code: crate::verify::exit_code(status, verify.deny_warnings),
+        verify: report,
+        gate,
+    };
+
+    out.emit_ci(&report, || {
+        for line in crate::verify::render(&report.verify) {
+            println!("{line}");
+        }
+        if let Some(simulation) = &report.gate {
+            print_simulation(simulation);
+        }
+        Ok(())
+    })?;
+    if report.exit_code != 0 {
+        return Err(crate::verify::failure(report.status, report.exit_code).into());
+    }
     Ok(())
 }
 
-fn cmd_config(args: ConfigArgs) -> anyhow::Result<()> {
-    match (&args.key, &args.value) {
-        (Some(key), Some(value)) => println!("Set {} = {}", key.bold(), value),
-        (Some(key), None) => println!("{} = (not set)", key.bold()),
-        _ => println!("No configuration keys set."),
+#[derive(Serialize)]
+struct RedactReport {
+    object: String,
+    size: u64,
+    reason: String,
+    signer: String,
+    receipt: String,
+}
+
+fn cmd_redact(args: RedactArgs, out: Output) -> anyhow::Result<()> {
+    let wll = open_repo()?;
+    let id = wll_sdk::ObjectId::from_hex(&args.object)?;
+    let key = wll_sdk::bundle::read_signing_key(&args.key)?;
+    let redaction = wll.redact(&id, &args.reason, &key)?;
+    let report = RedactReport {
+        object: redaction.object.to_hex(),
+        size: redaction.size,
+        reason: redaction.reason.clone(),
+        signer: hex::encode(redaction.signer.as_bytes()),
+        receipt: hex::encode(redaction.receipt_hash),
+    };
+
+    out.emit(&report, || {
+        println!("{} Redacted {} ({} bytes)", "✓".green().bold(), report.object.yellow(), report.size);
+        println!("  Reason: {}", report.reason);
+        println!("  Signer: {}", report.signer);
+        println!("  Receipt: {}", report.receipt);
+        Ok(())
+    })
+}
+
+fn cmd_gc(args: GcArgs, out: Output) -> anyhow::Result<()> {
+    let wll = open_repo()?;
+    let mut settings = wll.config()?.retention()?;
+    settings.keep_receipts = args.keep_receipts.or(settings.keep_receipts);
+    settings.keep_days = args.keep_days.or(settings.keep_days);
+    let policy = wll_sdk::RetentionPolicy::from_settings(&settings);
+    let report = if args.dry_run { wll.plan_retention(&policy)? } else { wll.apply_retention(&policy)? };
+
+    out.emit(&report, || {
+        let (mark, verb) = if report.applied { ("✓".green().bold(), "Pruned") } else { ("…".yellow(), "Would prune") };
+        match report.archived_through {
+            Some(seq) => println!("{mark} {verb} {} receipts (through seq {seq})", report.receipts_archived),
+            None => println!("{mark} Nothing to prune"),
+        }
+        if report.snapshot {
+            println!("  Snapshot: current state appended before the cut");
+        }
+        println!("  Provenance nodes: {}", report.dag_nodes_pruned);
+        println!("  Objects: {}", report.objects_removed.len());
+        println!("  Pack objects: {}", report.pack_objects_removed);
+        if report.applied {
+            println!("  Pack bytes freed: {}", report.pack_bytes_freed);
+        }
+        if let Some(reason) = &report.held_back {
+            println!("  Held back: {reason}");
+        }
+        Ok(())
+    })
+}
+
+fn cmd_migrate(args: MigrateArgs, out: Output) -> anyhow::Result<()> {
+    let mut options = wll_sdk::MigrateOptions::new().with_dry_run(args.dry_run).with_backup(!args.no_backup);
+    for wal in args.wal {
+        options = options.with_wal_segment(wal);
+    }
+    let report = open_repo()?.migrate(&options)?;
+
+    out.emit(&report, || {
+        let (mark, verb) = if args.dry_run { ("…".yellow(), "Would upgrade") } else { ("✓".green().bold(), "Upgraded") };
+        println!("{mark} {verb} {} of {} versioned files", report.upgraded.len(), report.checked);
+        for file in &report.upgraded {
+            println!("  {} {} v{}", file.path.display(), file.format, file.version);
+        }
+        for file in &report.unsupported {
+            println!("  {} {} {} v{} is newer than this build reads", "✗".red(), file.path.display(), file.format, file.version);
+        }
+        if let Some(backup) = &report.backup {
+            println!("  Originals: {}", backup.display());
+        }
+        Ok(())
+    })?;
+    if !report.is_supported() {
+        anyhow::bail!("files were written by a newer version of wll");
     }
     Ok(())
 }
+
+fn cmd_stats(out: Output) -> anyhow::Result<()> {
+    let stats = open_repo()?.stats()?;
+
+    out.emit(&stats, || {
+        println!("{} objects, {} bytes", stats.total_objects().to_string().bold(), stats.total_bytes());
+        for (kind, count) in &stats.objects {
+            println!("  {kind:<12} {:>8}  {} bytes", count.count, count.bytes);
+        }
+        println!("Loose: {} objects, {} bytes", stats.loose_objects, stats.loose_bytes);
+        println!(
+            "Packed: {} objects in {} pack(s), {} bytes on disk ({:.1}% packed)",
+            stats.packed_objects,
+            stats.packs,
+            stats.pack_bytes,
+            stats.packed_ratio * 100.0
+        );
+        println!("Ledger:");
+        for ledger in &stats.ledger {
+            println!("  {} {} receipts", ledger.worldline.short_id().cyan(), ledger.receipts);
+        }
+        println!("Provenance: {} nodes, {} edges", stats.dag_nodes, stats.dag_edges);
+        if !stats.largest_blobs.is_empty() {
+            println!("Largest blobs:");
+            for blob in &stats.largest_blobs {
+                println!("  {} {} bytes", blob.id.short_hex().yellow(), blob.bytes);
+            }
+        }
+        println!("Dangling: {} objects", stats.dangling.len());
+        Ok(())
+    })
+}
+
+fn cmd_gate(args: GateArgs, out: Output) -> anyhow::Result<()> {
+    let GateAction::Check(proposal) = args.action;
+    let wll = open_repo()?;
+    let simulation = simulate_gate(&wll, proposal)?;
+
+    out.emit_ci(&simulation, || {
+        print_simulation(&simulation);
+        Ok(())
+    })?;
+    if !simulation.is_accepted() {
+        anyhow::bail!("the gate would reject this commitment");
+    }
+    Ok(())
+}
+
+/// What the gate would decide on `args`, without committing.
+fn simulate_gate(
+    wll: &wll_sdk::Wll,
+    args: ProposalArgs,
+) -> anyhow::Result<wll_sdk::GateSimulation> {
+    let ProposalArgs { message, intent, class, evidence, targets, capabilities, policies } = args;
+    let mut proposal = wll_sdk::CommitProposal::new(message.unwrap_or_default());
+    proposal.intent = intent;
+    proposal.class = class.as_deref().map(wll_sdk::CommitmentClass::from_name);
+    proposal.evidence = evidence;
+    proposal.targets = targets;
+    proposal.capabilities = capabilities;
+    let policies = policies.iter().map(|path| read_policy(path)).collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(wll.check_commit(&proposal, &policies)?)
+}
+
+fn print_simulation(simulation: &wll_sdk::GateSimulation) {
+    let mark = |decision: &wll_gate::StageDecision| match decision {
+        wll_gate::StageDecision::Pass => "✓".green(),
+        wll_gate::StageDecision::Fail { .. } => "✗".red().bold(),
+        wll_gate::StageDecision::Defer { .. } => "…".yellow(),
+    };
+    for stage in &simulation.stages {
+        println!("{} {}", mark(&stage.decision), stage.stage_name.bold());
+        for check in &stage.checks {
+            let rule = match (&check.policy, &check.rule) {
+                (Some(policy), Some(rule)) => format!("{policy}: {rule}"),
+                (None, Some(rule)) => rule.clone(),
+                (Some(policy), None) => policy.clone(),
+                (None, None) => stage.stage_name.clone(),
+            };
+            match &check.decision {
+                wll_gate::StageDecision::Pass => println!("  {} {rule}", mark(&check.decision)),
+                wll_gate::StageDecision::Fail { reason } | wll_gate::StageDecision::Defer { reason, .. } => {
+                    println!("  {} {rule} — {reason}", mark(&check.decision))
+                }
+            }
+        }
+    }
+    match &simulation.decision {
+        wll_types::commitment::Decision::Rejected { reason } => println!("{} Would reject: {reason}", "✗".red().bold()),
+        _ => println!("{} Would accept", "✓".green().bold()),
+    }
+    if simulation.permissive && !simulation.all_passed() {
+        println!("  {}", "gate is permissive: failures above are not enforced".dimmed());
+    }
+}
+
+/// A gate policy from a JSON or YAML file.
+///
+/// The file is read as a plain value first so rules with data can be
+/// written as one-key maps (`- MaxTargets: 5`) rather than YAML tags.
+/// Target patterns that do not parse are refused here, as the gate refuses
+/// them wherever policies are loaded.
+fn read_policy(path: &std::path::Path) -> anyhow::Result<wll_sdk::Policy> {
+    let text = std::fs::read_to_string(path).map_err(|e| anyhow::anyhow!("{}: {e}", path.display()))?;
+    let value: serde_json::Value = serde_yaml::from_str(&text)?;
+    let policy: wll_sdk::Policy = serde_json::from_value(value)
+        .map_err(|e| anyhow::anyhow!("{}: invalid policy: {e}", path.display()))?;
+    policy.check().map_err(|e| anyhow::anyhow!("{}: {e}", path.display()))?;
+    Ok(policy)
+}
+
+#[derive(Serialize)]
+struct ReplayReport {
+    worldline: String,
+    receipts: u64,
+    applied_outcomes: u64,
+    state_keys: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    decisions: Option<ReplayDecisions>,
+}
+
+#[derive(Serialize)]
+struct ReplayDecisions {
+    checked: u64,
+    unknown_policies: Vec<u64>,
+    mismatches: Vec<ReplayMismatch>,
+}
+
+#[derive(Serialize)]
+struct ReplayMismatch {
+    seq: u64,
+    receipt_hash: String,
+    policy_hash: String,
+    recorded: String,
+    reevaluated: String,
+}
+
+fn cmd_replay(args: ReplayArgs, out: Output) -> anyhow::Result<()> {
+    let wll = open_repo()?;
+    let (replay, decisions) = if args.strict {
+        let result = wll.replay_strict()?;
+        let decisions = ReplayDecisions {
+            checked: result.decisions_checked,
+            unknown_policies: result.unknown_policies,
+            mismatches: result
+                .mismatches
+                .iter()
+                .map(|m| ReplayMismatch {
+                    seq: m.seq,
+                    receipt_hash: hex::encode(m.receipt_hash),
+                    policy_hash: hex::encode(m.policy_hash),
+                    recorded: m.recorded.to_string(),
+                    reevaluated: m.reevaluated.to_string(),
+                })
+                .collect(),
+        };
+        (result.replay, Some(decisions))
+    } else {
+        (wll.replay()?, None)
+    };
+    let report = ReplayReport {
+        worldline: replay.worldline.to_hex(),
+        receipts: replay.evaluated_receipts,
+        applied_outcomes: replay.applied_outcomes,
+        state_keys: replay.state.len(),
+        decisions,
+    };
+
+    out.emit(&report, || {
+        println!(
+            "{} Replayed {} receipts from genesis, {} outcomes applied",
+            "✓".green().bold(),
+            report.receipts,
+            report.applied_outcomes
+        );
+        if let Some(decisions) = &report.decisions {
+            println!("  Gate decisions re-checked: {}", decisions.checked);
+            if !decisions.unknown_policies.is_empty() {
+                println!(
+                    "  {} commitments under an unknown policy version were skipped",
+                    decisions.unknown_policies.len().to_string().yellow()
+                );
+            }
+            for m in &decisions.mismatches {
+                println!(
+                    "  {} r#{}: recorded {}, gate now says {}",
+                    "✗".red(),
+                    m.seq,
+                    m.recorded,
+                    m.reevaluated
+                );
+            }
+        }
+        Ok(())
+    })?;
+    if report.decisions.as_ref().is_some_and(|d| !d.mismatches.is_empty()) {
+        anyhow::bail!("recorded gate decisions do not match re-evaluation");
+    }
+    Ok(())
+}
+
+fn cmd_config(args: ConfigArgs, out: Output) -> anyhow::Result<()> {
+    let cwd = std::env::current_dir()?;
+    let repo_dir = cwd
+        .ancestors()
+        .map(|dir| dir.join(wll_sdk::config::WLL_DIR))
+        .find(|dir| dir.join("config").is_file());
+    let paths = ConfigPaths::standard(repo_dir.as_deref());
+    let level = if args.system {
+        ConfigLevel::System
+    } else if args.global {
+        ConfigLevel::User
+    } else {
+        ConfigLevel::Repository
+    };
+
+    let Some(key) = args.key else {
+        let entries = LayeredConfig::load(&paths)?.entries();
+        return out.emit(&entries, || {
+            if entries.is_empty() {
+                println!("No configuration keys set.");
+                return Ok(());
+            }
+            let mut table = Table::new(&["KEY", "VALUE", "LEVEL"]);
+            for entry in &entries {
+                table.row(vec![entry.key.clone(), config_value(&entry.value), entry.level.to_string()]);
+            }
+            table.render().iter().for_each(|line| println!("{line}"));
+            Ok(())
+        });
+    };
+
+    if args.value.is_none() && !args.unset {
+        let config = LayeredConfig::load(&paths)?;
+        let value = config
+            .get_value(&key)?
+            .ok_or_else(|| anyhow::anyhow!("{key} is not set"))?;
+        return out.emit(value, || {
+            println!("{}", config_value(value));
+            Ok(())
+        });
+    }
+
+    let path = paths.get(level).ok_or_else(|| match level {
+        ConfigLevel::Repository => anyhow::anyhow!("not in a wll repository (use --global for the user config)"),
+        _ => anyhow::anyhow!("no {level} config file location"),
+    })?;
+    let mut layer = ConfigLayer::load(level, path)?;
+    let value = match args.value {
+        Some(raw) => {
+            let value = parse_value(&raw);
+            layer.set(&key, value.clone())?;
+            value
+        }
+        None => layer
+            .unset(&key)?
+            .ok_or_else(|| anyhow::anyhow!("{key} is not set in the {level} config"))?,
+    };
+    layer.save()?;
+
+    let removed = args.unset;
+    let entry = ConfigEntry { key, value, level };
+    out.emit(&entry, || {
+        if removed {
+            println!("Unset {} ({})", entry.key.bold(), level);
+        } else {
+            println!("Set {} = {} ({})", entry.key.bold(), config_value(&entry.value), level);
+        }
+        Ok(())
+    })
+}
+
+/// Strings print bare; other values in their TOML form.
+fn config_value(value: &wll_config::Value) -> String {
+    match value {
+        wll_config::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
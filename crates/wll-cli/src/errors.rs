@@ -0,0 +1,124 @@
+//! Turning a failed command's error chain into a message, a stable code,
+//! a hint about what to do next, and an exit code.
+
+use std::fmt;
+
+use wll_types::{Coded, ErrorCode};
+
+/// A check that ran to completion and found problems, exiting with `code`
+/// rather than 1 so scripts can tell what it found.
+#[derive(Debug)]
+pub struct CheckFailed {
+    pub code: u8,
+    pub message: String,
+}
+
+impl fmt::Display for CheckFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CheckFailed {}
+
+/// The process exit code for a failed command: that of a [`CheckFailed`]
+/// in the chain, else 1.
+pub fn exit_code(error: &anyhow::Error) -> u8 {
+    error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<CheckFailed>())
+        .map_or(1, |failed| failed.code)
+}
+
+/// The code of the outermost error in the chain that carries one.
+pub fn code(error: &anyhow::Error) -> Option<ErrorCode> {
+    macro_rules! coded {
+        ($cause:expr, $($ty:ty),* $(,)?) => {
+            $(if let Some(e) = $cause.downcast_ref::<$ty>() {
+                return Some(e.code());
+            })*
+        };
+    }
+    error.chain().find_map(|cause| {
+        coded!(
+            cause,
+            wll_import::ImportError,
+            wll_sdk::SdkError,
+            wll_sync::SyncError,
+            wll_config::ConfigError,
+            wll_server::ServerError,
+            wll_protocol::ProtocolError,
+            wll_refs::RefError,
+            wll_ledger::LedgerError,
+            wll_store::StoreError,
+            wll_pack::PackError,
+            wll_gate::GateError,
+        );
+        None
+    })
+}
+
+/// The chain joined with `: `, skipping causes whose text the previous
+/// message already ends with (wrapper variants print their source).
+pub fn describe(error: &anyhow::Error) -> String {
+    let mut message = error.to_string();
+    for cause in error.chain().skip(1) {
+        let cause = cause.to_string();
+        if !message.ends_with(&cause) {
+            message = format!("{message}: {cause}");
+        }
+    }
+    message
+}
+
+/// What the user can try next, for codes where the CLI has a fix to offer.
+pub fn hint(code: ErrorCode) -> Option<&'static str> {
+    Some(match code {
+        ErrorCode::RepoNotInitialized => "run `wll init` to create a repository here",
+        ErrorCode::RefNotFound => "run `wll branch` to list branches",
+        ErrorCode::RefExists => "pick another name, or `wll switch <name>` to use the existing one",
+        ErrorCode::DetachedHead => "run `wll switch <branch>` to put HEAD back on a branch",
+        ErrorCode::DeleteCurrentBranch => "switch to another branch with `wll switch` first",
+        ErrorCode::ConfigInvalid => "inspect the value with `wll config <key>`",
+        ErrorCode::ConfigNoFile => {
+            "pass --global or --system, or run the command inside a repository"
+        }
+        ErrorCode::Credential | ErrorCode::Unauthenticated => {
+            "save a token for the remote with `wll credential store <remote>`"
+        }
+        ErrorCode::InvalidRemote | ErrorCode::Transport | ErrorCode::RemoteError => {
+            "check the remote's URL with `wll remote list`"
+        }
+        ErrorCode::NotFastForward => "run `wll pull` to integrate the remote changes first",
+        ErrorCode::LedgerIntegrity
+        | ErrorCode::ObjectCorrupt
+        | ErrorCode::PackCorrupt
+        | ErrorCode::WalCorrupt => "run `wll fsck` and `wll verify` to find the damage",
+        ErrorCode::GateRejected => "the rejection is recorded; `wll log` shows it",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_are_found_through_context_and_wrappers() {
+        let error = anyhow::Error::from(wll_sdk::SdkError::Ref(wll_refs::RefError::NotFound {
+            name: "dev".into(),
+        }))
+        .context("switching branches");
+        assert_eq!(code(&error), Some(ErrorCode::RefNotFound));
+        assert_eq!(
+            describe(&error),
+            "switching branches: ref error: ref not found: dev"
+        );
+        assert!(hint(ErrorCode::RefNotFound).unwrap().contains("wll branch"));
+
+        assert_eq!(code(&anyhow::anyhow!("no token on stdin")), None);
+        assert_eq!(exit_code(&anyhow::anyhow!("no token on stdin")), 1);
+        let failed = CheckFailed { code: 3, message: "corrupt".into() };
+        assert_eq!(exit_code(&anyhow::Error::from(failed).context("verifying")), 3);
+    }
+}
@@ -0,0 +1,184 @@
+//! ASCII rendering of receipt history as a lane graph, in the style of
+//! `git log --graph`.
+//!
+//! Entries are fed newest first. Each lane tracks the id of the entry it is
+//! waiting for; an entry lands in the first lane expecting it (or a new lane
+//! on the right), lanes that converge on it are folded in with `/`, and extra
+//! parents of a merge open new lanes with `\`.
+
+/// Rows to print for one entry.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct GraphRows {
+    /// Connector rows printed before the entry (lanes folding in).
+    pub before: Vec<String>,
+    /// Prefix of the entry's first line, containing the `*` marker.
+    pub node: String,
+    /// Prefix for any further lines of the same entry.
+    pub continuation: String,
+    /// Connector rows printed after the entry (merge lanes opening).
+    pub after: Vec<String>,
+}
+
+/// Incremental lane allocator for newest-first history.
+#[derive(Debug, Default)]
+pub struct Graph {
+    lanes: Vec<String>,
+}
+
+impl Graph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Place `id` with the given `parents` and return the rows to draw.
+    pub fn next(&mut self, id: &str, parents: &[String]) -> GraphRows {
+        let mut rows = GraphRows::default();
+
+        let col = match self.lanes.iter().position(|lane| lane == id) {
+            Some(col) => col,
+            None => {
+                self.lanes.push(id.to_string());
+                self.lanes.len() - 1
+            }
+        };
+
+        // Fold other lanes that were also waiting for this entry.
+        let folded: Vec<usize> = (col + 1..self.lanes.len())
+            .filter(|&i| self.lanes[i] == id)
+            .collect();
+        if !folded.is_empty() {
+            let first = folded[0];
+            let marks = (0..self.lanes.len())
+                .map(|i| if i < first { Mark::Lane } else { Mark::Left })
+                .collect::<Vec<_>>();
+            rows.before
+                .push(render(&marks, None).trim_end().to_string());
+            for &i in folded.iter().rev() {
+                self.lanes.remove(i);
+            }
+        }
+
+        let width = self.lanes.len();
+        rows.node = render(&vec![Mark::Lane; width], Some(col));
+
+        let mut new_lanes = Vec::new();
+        match parents.split_first() {
+            None => {
+                self.lanes.remove(col);
+            }
+            Some((first, rest)) => {
+                self.lanes[col] = first.clone();
+                for parent in rest {
+                    if !self.lanes.contains(parent) && !new_lanes.contains(parent) {
+                        new_lanes.push(parent.clone());
+                    }
+                }
+            }
+        }
+
+        let continuation = (0..width)
+            .map(|i| {
+                if i == col && parents.is_empty() {
+                    Mark::Blank
+                } else {
+                    Mark::Lane
+                }
+            })
+            .collect::<Vec<_>>();
+        rows.continuation = render(&continuation, None);
+
+        if !new_lanes.is_empty() {
+            let opened = new_lanes.len();
+            let marks = (0..width + opened)
+                .map(|i| if i <= col { Mark::Lane } else { Mark::Right })
+                .collect::<Vec<_>>();
+            rows.after.push(render(&marks, None).trim_end().to_string());
+            for (offset, lane) in new_lanes.into_iter().enumerate() {
+                self.lanes.insert(col + 1 + offset, lane);
+            }
+        }
+
+        rows
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mark {
+    Lane,
+    Blank,
+    /// Lane shifting one column left (drawn between it and its new column).
+    Left,
+    /// Lane shifting one column right (drawn between it and its old column).
+    Right,
+}
+
+/// Lay lanes out two characters apart; shifting lanes are drawn in the gap
+/// on the side they are moving towards.
+fn render(marks: &[Mark], node: Option<usize>) -> String {
+    let mut cells = vec![' '; marks.len() * 2];
+    for (i, mark) in marks.iter().enumerate() {
+        match mark {
+            _ if node == Some(i) => cells[2 * i] = '*',
+            Mark::Lane => cells[2 * i] = '|',
+            Mark::Blank => {}
+            Mark::Left | Mark::Right => {
+                cells[2 * i - 1] = if *mark == Mark::Left { '/' } else { '\\' }
+            }
+        }
+    }
+    cells.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draw(history: &[(&str, &[&str])]) -> Vec<String> {
+        let mut graph = Graph::new();
+        let mut out = Vec::new();
+        for (id, parents) in history {
+            let parents: Vec<String> = parents.iter().map(|p| p.to_string()).collect();
+            let rows = graph.next(id, &parents);
+            out.extend(rows.before);
+            out.push(format!("{}{id}", rows.node));
+            out.extend(rows.after);
+        }
+        out.into_iter().map(|l| l.trim_end().to_string()).collect()
+    }
+
+    #[test]
+    fn linear_history_is_one_lane() {
+        assert_eq!(
+            draw(&[("c", &["b"]), ("b", &["a"]), ("a", &[])]),
+            vec!["* c", "* b", "* a"]
+        );
+    }
+
+    #[test]
+    fn merge_opens_and_folds_lanes() {
+        let rows = draw(&[
+            ("m", &["main", "feat"]),
+            ("main", &["base"]),
+            ("feat", &["base"]),
+            ("base", &[]),
+        ]);
+        assert_eq!(
+            rows,
+            vec!["* m", "|\\", "* | main", "| * feat", "|/", "* base"]
+        );
+    }
+
+    #[test]
+    fn continuation_hides_finished_lane() {
+        let mut graph = Graph::new();
+        let rows = graph.next("root", &[]);
+        assert_eq!(rows.node, "* ");
+        assert_eq!(rows.continuation.trim_end(), "");
+    }
+
+    #[test]
+    fn unrelated_tips_get_separate_lanes() {
+        let rows = draw(&[("x", &["a"]), ("y", &["a"]), ("a", &[])]);
+        assert_eq!(rows, vec!["* x", "| * y", "|/", "* a"]);
+    }
+}
@@ -0,0 +1,463 @@
+//! `wll log`: receipt history collected from the provenance DAG.
+//!
+//! Each entry is one commitment (folded together with its outcome receipt)
+//! or one snapshot. Entry parents follow the DAG's sequential, merge, and
+//! cross-worldline edges, so merged histories render as a graph rather than
+//! a flat stream.
+
+use std::collections::{HashMap, HashSet};
+
+use colored::{ColoredString, Colorize};
+use serde::Serialize;
+use wll_dag::{CausalRelation, DagNode, ProvenanceDag};
+use wll_ledger::Receipt;
+use wll_refs::{Head, Ref};
+use wll_sdk::Wll;
+use wll_types::commitment::Decision;
+use wll_types::{ObjectId, ReceiptKind};
+
+use crate::graph::Graph;
+
+/// One row of `wll log`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct LogEntry {
+    /// Hex receipt hash of the commitment (or snapshot).
+    pub id: String,
+    pub seq: u64,
+    pub kind: EntryKind,
+    /// Hex receipt hash of the outcome recorded for a commitment.
+    pub outcome: Option<String>,
+    pub class: Option<String>,
+    pub decision: Option<DecisionLabel>,
+    pub reason: Option<String>,
+    pub intent: Option<String>,
+    pub timestamp_ms: u64,
+    /// Ids of the entries this one follows, first parent first.
+    pub parents: Vec<String>,
+    /// Refs pointing at this entry (`HEAD -> main`, `tag: v1`, ...).
+    pub refs: Vec<String>,
+    #[serde(skip)]
+    risk: u8,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    Commitment,
+    Snapshot,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DecisionLabel {
+    Accepted,
+    Rejected,
+    Deferred,
+}
+
+/// Collect history newest first.
+///
+/// By default only entries reachable from HEAD are listed; with `all`, every
+/// receipt in the provenance DAG is included (e.g. rejected commitments
+/// after the branch tip).
+pub fn collect(wll: &Wll, all: bool) -> anyhow::Result<Vec<LogEntry>> {
+    let dag = wll.provenance()?;
+    let selected = if all {
+        None
+    } else {
+        let Some(tip) = head_tip(wll)? else {
+            return Ok(Vec::new());
+        };
+        let reachable: HashSet<ObjectId> = wll
+            .history(&tip, usize::MAX)?
+            .into_iter()
+            .map(ObjectId::from_hash)
+            .collect();
+        Some(reachable)
+    };
+    let decorations = decorations(wll)?;
+
+    let mut receipts = HashMap::new();
+    for node in dag.topological_order() {
+        if let Some(receipt) = wll.ledger().get_by_hash(*node.id.as_bytes())? {
+            receipts.insert(node.id, receipt);
+        }
+    }
+    Ok(build_entries(
+        &dag,
+        &receipts,
+        selected.as_ref(),
+        &decorations,
+    ))
+}
+
+fn build_entries(
+    dag: &ProvenanceDag,
+    receipts: &HashMap<ObjectId, Receipt>,
+    selected: Option<&HashSet<ObjectId>>,
+    decorations: &HashMap<[u8; 32], Vec<String>>,
+) -> Vec<LogEntry> {
+    let visible = |id: &ObjectId| selected.map_or(true, |set| set.contains(id));
+
+    // Outcomes are folded into the commitment they record.
+    let mut owner: HashMap<ObjectId, ObjectId> = HashMap::new();
+    let mut outcome_of: HashMap<ObjectId, &DagNode> = HashMap::new();
+    for node in dag.topological_order() {
+        if node.kind != ReceiptKind::Outcome {
+            continue;
+        }
+        if let Some(commitment) = node
+            .parents
+            .iter()
+            .find(|p| p.relation == CausalRelation::CommitmentToOutcome)
+        {
+            owner.insert(node.id, commitment.target);
+            outcome_of.insert(commitment.target, node);
+        }
+    }
+
+    let mut entries = Vec::new();
+    for node in dag.topological_order().into_iter().rev() {
+        if node.kind == ReceiptKind::Outcome || !visible(&node.id) {
+            continue;
+        }
+        let outcome = outcome_of.get(&node.id).filter(|o| visible(&o.id));
+
+        let mut parents: Vec<String> = Vec::new();
+        let edges = node
+            .parents
+            .iter()
+            .chain(outcome.into_iter().flat_map(|o| o.parents.iter()));
+        for edge in edges {
+            if edge.relation == CausalRelation::EvidenceLink || !visible(&edge.target) {
+                continue;
+            }
+            let target = owner.get(&edge.target).unwrap_or(&edge.target);
+            let hex = hex_id(target);
+            if *target != node.id && !parents.contains(&hex) {
+                parents.push(hex);
+            }
+        }
+
+        let mut refs = Vec::new();
+        for hash in std::iter::once(node.id).chain(outcome.map(|o| o.id)) {
+            if let Some(names) = decorations.get(hash.as_bytes()) {
+                refs.extend(names.iter().cloned());
+            }
+        }
+
+        let mut entry = LogEntry {
+            id: hex_id(&node.id),
+            seq: node.seq,
+            kind: if node.kind == ReceiptKind::Snapshot {
+                EntryKind::Snapshot
+            } else {
+                EntryKind::Commitment
+            },
+            outcome: outcome.map(|o| hex_id(&o.id)),
+            class: None,
+            decision: None,
+            reason: None,
+            intent: node.metadata.description.clone(),
+            timestamp_ms: node.timestamp.physical_ms,
+            parents,
+            refs,
+            risk: 0,
+        };
+        if let Some(Receipt::Commitment(c)) = receipts.get(&node.id) {
+            entry.class = Some(c.class.to_string());
+            entry.risk = c.class.risk_level();
+            entry.intent = Some(c.intent.clone());
+            let (label, reason) = match &c.decision {
+                Decision::Accepted => (DecisionLabel::Accepted, None),
+                Decision::Rejected { reason } => (DecisionLabel::Rejected, Some(reason.clone())),
+                Decision::Deferred { reason, .. } => {
+                    (DecisionLabel::Deferred, Some(reason.clone()))
+                }
+            };
+            entry.decision = Some(label);
+            entry.reason = reason;
+        }
+        entries.push(entry);
+    }
+    entries
+}
+
+/// Receipt hash HEAD resolves to, if any commit has been made.
+fn head_tip(wll: &Wll) -> anyhow::Result<Option<[u8; 32]>> {
+    let tip = match wll.refs().head()? {
+        Some(Head::Symbolic(branch)) => wll
+            .refs()
+            .read_ref(&format!("refs/heads/{branch}"))?
+            .map(|r| *r.target_hash()),
+        Some(Head::Detached(hash)) => Some(hash),
+        None => None,
+    };
+    Ok(tip.filter(|hash| *hash != [0; 32]))
+}
+
+/// Ref names keyed by the receipt hash they point at.
+fn decorations(wll: &Wll) -> anyhow::Result<HashMap<[u8; 32], Vec<String>>> {
+    let current = match wll.refs().head()? {
+        Some(Head::Symbolic(branch)) => Some(branch),
+        _ => None,
+    };
+    let mut names: HashMap<[u8; 32], Vec<String>> = HashMap::new();
+    for (_, reference) in wll.refs().list_refs("refs/")? {
+        let label = match &reference {
+            Ref::Branch { name, .. } if current.as_deref() == Some(name) => {
+                format!("HEAD -> {name}")
+            }
+            Ref::Branch { name, .. } => name.clone(),
+            Ref::Tag { name, .. } => format!("tag: {name}"),
+            Ref::Remote { remote, branch, .. } => format!("{remote}/{branch}"),
+        };
+        names
+            .entry(*reference.target_hash())
+            .or_default()
+            .push(label);
+    }
+    Ok(names)
+}
+
+fn hex_id(id: &ObjectId) -> String {
+    hex::encode(id.as_bytes())
+}
+
+/// Render entries as text lines, optionally with the lane graph.
+pub fn render(entries: &[LogEntry], oneline: bool, graph: bool) -> Vec<String> {
+    let mut lanes = Graph::new();
+    let mut out = Vec::new();
+    for entry in entries {
+        let body = if oneline {
+            vec![oneline_text(entry)]
+        } else {
+            full_text(entry)
+        };
+        if !graph {
+            out.extend(body);
+            continue;
+        }
+        let rows = lanes.next(&entry.id, &entry.parents);
+        out.extend(rows.before);
+        let mut body = body.into_iter();
+        if let Some(first) = body.next() {
+            out.push(format!("{}{first}", rows.node));
+        }
+        out.extend(body.map(|line| {
+            format!("{}{line}", rows.continuation)
+                .trim_end()
+                .to_string()
+        }));
+        out.extend(rows.after);
+    }
+    out
+}
+
+fn oneline_text(entry: &LogEntry) -> String {
+    let mut line = format!(
+        "{} {} {}",
+        short(&entry.id).yellow(),
+        format!("r#{}", entry.seq).dimmed(),
+        decision_mark(entry),
+    );
+    if let Some(intent) = &entry.intent {
+        line.push(' ');
+        line.push_str(intent);
+    }
+    if !entry.refs.is_empty() {
+        line.push_str(&format!(" ({})", refs_text(entry)));
+    }
+    line
+}
+
+fn full_text(entry: &LogEntry) -> Vec<String> {
+    let mut header = format!(
+        "{}  {}",
+        format!("r#{}", entry.seq).yellow().bold(),
+        short(&entry.id).dimmed()
+    );
+    if !entry.refs.is_empty() {
+        header.push_str(&format!("  ({})", refs_text(entry)));
+    }
+
+    let mut status = format!("  {}", decision_text(entry));
+    if let Some(class) = &entry.class {
+        status.push_str(&format!(" | {}", class_text(class, entry.risk)));
+    }
+
+    let mut lines = vec![header, status];
+    if let Some(intent) = &entry.intent {
+        lines.push(format!("  Intent: {intent}"));
+    }
+    lines.push(format!("  Date:   {}", format_time(entry.timestamp_ms)));
+    lines.push(String::new());
+    lines
+}
+
+fn decision_mark(entry: &LogEntry) -> ColoredString {
+    match entry.decision {
+        Some(DecisionLabel::Accepted) => "✓".green(),
+        Some(DecisionLabel::Rejected) => "✗".red(),
+        Some(DecisionLabel::Deferred) => "…".yellow(),
+        None => "◆".blue(),
+    }
+}
+
+fn decision_text(entry: &LogEntry) -> String {
+    let reason = entry
+        .reason
+        .as_deref()
+        .map(|r| format!(": {r}"))
+        .unwrap_or_default();
+    match entry.decision {
+        Some(DecisionLabel::Accepted) => "✓ Accepted".green().to_string(),
+        Some(DecisionLabel::Rejected) => format!("✗ Rejected{reason}").red().to_string(),
+        Some(DecisionLabel::Deferred) => format!("… Deferred{reason}").yellow().to_string(),
+        None => "◆ Snapshot".blue().to_string(),
+    }
+}
+
+/// Colour a commitment class by its policy risk level.
+fn class_text(class: &str, risk: u8) -> ColoredString {
+    match risk {
+        0 => class.dimmed(),
+        1 => class.cyan(),
+        2 => class.blue(),
+        3 => class.magenta(),
+        _ => class.red().bold(),
+    }
+}
+
+fn refs_text(entry: &LogEntry) -> String {
+    entry
+        .refs
+        .iter()
+        .map(|name| name.green().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn short(id: &str) -> &str {
+    &id[..8.min(id.len())]
+}
+
+fn format_time(ms: u64) -> String {
+    i64::try_from(ms)
+        .ok()
+        .and_then(chrono::DateTime::from_timestamp_millis)
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|| format!("{ms} ms"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wll_dag::{DagNodeMetadata, ParentRef};
+    use wll_sdk::CommitProposal;
+    use wll_types::{IdentityMaterial, TemporalAnchor, WorldlineId};
+
+    fn plain() {
+        colored::control::set_override(false);
+    }
+
+    #[test]
+    fn linear_history_newest_first_with_refs() {
+        let wll = Wll::init().unwrap();
+        wll.commit(CommitProposal::new("first")).unwrap();
+        wll.commit(CommitProposal::new("second")).unwrap();
+
+        let entries = collect(&wll, false).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].intent.as_deref(), Some("second"));
+        assert_eq!(entries[0].seq, 3);
+        assert_eq!(entries[0].refs, vec!["HEAD -> main"]);
+        assert_eq!(entries[0].parents, vec![entries[1].id.clone()]);
+        assert!(entries[1].parents.is_empty());
+        assert_eq!(entries[1].decision, Some(DecisionLabel::Accepted));
+    }
+
+    #[test]
+    fn empty_repository_has_no_entries() {
+        assert!(collect(&Wll::init().unwrap(), false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn oneline_graph_rendering() {
+        plain();
+        let wll = Wll::init().unwrap();
+        wll.commit(CommitProposal::new("first")).unwrap();
+        wll.commit(CommitProposal::new("second")).unwrap();
+        let entries = collect(&wll, false).unwrap();
+
+        let lines = render(&entries, true, true);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("* "));
+        assert!(lines[0].ends_with("✓ second (HEAD -> main)"));
+        assert!(lines[1].contains("r#1 ✓ first"));
+    }
+
+    #[test]
+    fn merged_history_folds_outcomes_and_keeps_both_parents() {
+        plain();
+        let wid = WorldlineId::derive(&IdentityMaterial::GenesisHash([4; 32]));
+        let id = |b: u8| ObjectId::from_hash([b; 32]);
+        let node = |b: u8, seq: u64, kind: ReceiptKind, parents: Vec<ParentRef>| DagNode {
+            id: id(b),
+            worldline: wid.clone(),
+            seq,
+            kind,
+            timestamp: TemporalAnchor::new(1000 + seq, 0, 0),
+            parents,
+            metadata: DagNodeMetadata::empty(),
+        };
+        let outcome_of = |c: u8| ParentRef::new(id(c), CausalRelation::CommitmentToOutcome);
+
+        let mut dag = ProvenanceDag::new();
+        for n in [
+            node(1, 1, ReceiptKind::Commitment, vec![]),
+            node(2, 2, ReceiptKind::Outcome, vec![outcome_of(1)]),
+            node(
+                3,
+                3,
+                ReceiptKind::Commitment,
+                vec![ParentRef::sequential(id(2))],
+            ),
+            node(4, 4, ReceiptKind::Outcome, vec![outcome_of(3)]),
+            node(
+                5,
+                5,
+                ReceiptKind::Commitment,
+                vec![ParentRef::sequential(id(2))],
+            ),
+            node(6, 6, ReceiptKind::Outcome, vec![outcome_of(5)]),
+            node(
+                7,
+                7,
+                ReceiptKind::Commitment,
+                vec![
+                    ParentRef::sequential(id(6)),
+                    ParentRef::new(id(4), CausalRelation::Merge),
+                ],
+            ),
+            node(8, 8, ReceiptKind::Outcome, vec![outcome_of(7)]),
+        ] {
+            dag.add_node(n).unwrap();
+        }
+
+        let entries = build_entries(&dag, &HashMap::new(), None, &HashMap::new());
+        let ids: Vec<u64> = entries.iter().map(|e| e.seq).collect();
+        assert_eq!(ids, vec![7, 5, 3, 1]);
+        assert_eq!(entries[0].parents, vec![hex_id(&id(5)), hex_id(&id(3))]);
+
+        let lines = render(&entries, true, true);
+        let graph: Vec<&str> = lines
+            .iter()
+            .map(|l| {
+                l.find(char::is_alphanumeric)
+                    .map_or(l.as_str(), |i| &l[..i])
+            })
+            .collect();
+        assert_eq!(graph, vec!["* ", "|\\", "* | ", "| * ", "|/", "* "]);
+    }
+}
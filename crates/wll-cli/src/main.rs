@@ -1,10 +1,49 @@
+use std::process::ExitCode;
+
 use clap::Parser;
+use tracing::level_filters::LevelFilter;
+use wll_telemetry::{Telemetry, TelemetryConfig};
 
+mod ci;
 mod cli;
 mod commands;
+mod errors;
+mod graph;
+mod log;
+mod output;
+mod show;
+mod verify;
 
-fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt::init();
+fn main() -> ExitCode {
     let cli = cli::Cli::parse();
-    commands::run_command(cli)
+    let _telemetry = init_telemetry(cli.verbose);
+    let out = output::Output::new(cli.output);
+    match commands::run_command(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            out.error(&e);
+            ExitCode::from(errors::exit_code(&e))
+        }
+    }
+}
+
+/// Log to stderr (at `WLL_LOG`, or `debug`, with `--verbose`), exporting
+/// spans when an `OTEL_EXPORTER_OTLP_*` endpoint is set. A broken exporter
+/// setup is reported but does not stop the command.
+fn init_telemetry(verbose: bool) -> Option<Telemetry> {
+    let level = if verbose {
+        std::env::var("WLL_LOG")
+            .ok()
+            .and_then(|level| level.parse().ok())
+            .unwrap_or(LevelFilter::DEBUG)
+    } else {
+        LevelFilter::INFO
+    };
+    let config = TelemetryConfig::from_env("wll").with_level(level);
+    wll_telemetry::init(&config)
+        .or_else(|e| {
+            eprintln!("warning: {e}; spans will not be exported");
+            wll_telemetry::init(&TelemetryConfig { otlp: None, ..config })
+        })
+        .ok()
 }
@@ -0,0 +1,221 @@
+//! Shared output layer behind the global `--output` flag.
+//!
+//! Commands build a serializable result and hand it to [`Output::emit`]
+//! together with a closure that prints the human-readable form. In `json`
+//! and `yaml` mode the result is serialized instead, so scripts get the
+//! same fields whatever the command prints for people.
+
+use serde::Serialize;
+use wll_types::ErrorCode;
+
+use crate::ci::{self, CiReport};
+use crate::cli::OutputFormat;
+use crate::errors;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Output {
+    format: OutputFormat,
+}
+
+impl Output {
+    pub fn new(format: OutputFormat) -> Self {
+        Self { format }
+    }
+
+    /// Print `value` in the selected machine format, or run `table` to
+    /// print it for people.
+    pub fn emit<T: Serialize + ?Sized>(
+        &self,
+        value: &T,
+        table: impl FnOnce() -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        match self.serialize(value)? {
+            Some(text) => {
+                println!("{text}");
+                Ok(())
+            }
+            None => table(),
+        }
+    }
+
+    /// [`emit`](Self::emit) for reports CI systems can show: `sarif` and
+    /// `junit` render `value`'s [`CiReport`] cases.
+    pub fn emit_ci<T: Serialize + CiReport>(
+        &self,
+        value: &T,
+        table: impl FnOnce() -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        match self.format {
+            OutputFormat::Sarif => {
+                println!("{}", serde_json::to_string_pretty(&ci::sarif(&value.ci_cases()))?);
+                Ok(())
+            }
+            OutputFormat::Junit => {
+                println!("{}", ci::junit(&value.ci_cases()));
+                Ok(())
+            }
+            _ => self.emit(value, table),
+        }
+    }
+
+    /// Report a failed command: `{ "error": ..., "code": ..., "hint": ... }`
+    /// on stderr in machine formats, `Error [code]: ...` and a `hint:` line
+    /// otherwise.
+    pub fn error(&self, error: &anyhow::Error) {
+        let report = ErrorReport::new(error);
+        match self.serialize(&report) {
+            Ok(Some(text)) => eprintln!("{text}"),
+            _ => {
+                match report.code {
+                    Some(code) => eprintln!("Error [{code}]: {}", report.error),
+                    None => eprintln!("Error: {}", report.error),
+                }
+                if let Some(hint) = report.hint {
+                    eprintln!("hint: {hint}");
+                }
+            }
+        }
+    }
+
+    /// `value` rendered as JSON or YAML, or `None` in table mode.
+    fn serialize<T: Serialize + ?Sized>(&self, value: &T) -> anyhow::Result<Option<String>> {
+        Ok(match self.format {
+            OutputFormat::Table => None,
+            OutputFormat::Json => Some(serde_json::to_string_pretty(value)?),
+            OutputFormat::Yaml => Some(serde_yaml::to_string(value)?.trim_end().to_string()),
+            OutputFormat::Sarif | OutputFormat::Junit => anyhow::bail!(
+                "this command has no {:?} output; use json or yaml",
+                self.format
+            ),
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorReport {
+    error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<ErrorCode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hint: Option<&'static str>,
+}
+
+impl ErrorReport {
+    fn new(error: &anyhow::Error) -> Self {
+        let code = errors::code(error);
+        Self {
+            error: errors::describe(error),
+            code,
+            hint: code.and_then(errors::hint),
+        }
+    }
+}
+
+/// Plain-text table with left-aligned, space-padded columns.
+#[derive(Debug, Default)]
+pub struct Table {
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(header: &[&str]) -> Self {
+        Self {
+            rows: vec![header.iter().map(|h| h.to_string()).collect()],
+        }
+    }
+
+    pub fn row(&mut self, cells: Vec<String>) {
+        self.rows.push(cells);
+    }
+
+    /// Lines of the table, header first. Cells must not contain ANSI
+    /// escapes, since widths are counted in characters.
+    pub fn render(&self) -> Vec<String> {
+        let columns = self.rows.iter().map(Vec::len).max().unwrap_or(0);
+        let widths: Vec<usize> = (0..columns)
+            .map(|col| {
+                self.rows
+                    .iter()
+                    .filter_map(|row| row.get(col))
+                    .map(|cell| cell.chars().count())
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        self.rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .zip(&widths)
+                    .map(|(cell, width)| format!("{cell:<width$}"))
+                    .collect::<Vec<_>>()
+                    .join("  ")
+                    .trim_end()
+                    .to_string()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Remote {
+        name: &'static str,
+        url: &'static str,
+    }
+
+    #[test]
+    fn machine_formats_serialize() {
+        let value = vec![Remote {
+            name: "origin",
+            url: "wll://a",
+        }];
+        let json = Output::new(OutputFormat::Json)
+            .serialize(&value)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap()[0]["url"],
+            "wll://a"
+        );
+        let yaml = Output::new(OutputFormat::Yaml)
+            .serialize(&value)
+            .unwrap()
+            .unwrap();
+        assert_eq!(yaml, "- name: origin\n  url: wll://a");
+        assert!(Output::new(OutputFormat::Table)
+            .serialize(&value)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn emit_runs_table_closure_only_in_table_mode() {
+        let mut printed = false;
+        Output::new(OutputFormat::Table)
+            .emit(&1, || {
+                printed = true;
+                Ok(())
+            })
+            .unwrap();
+        assert!(printed);
+
+        Output::new(OutputFormat::Json)
+            .emit(&1, || panic!("table printed in json mode"))
+            .unwrap();
+    }
+
+    #[test]
+    fn table_pads_columns() {
+        let mut table = Table::new(&["NAME", "URL"]);
+        table.row(vec!["origin".into(), "wll://a".into()]);
+        table.row(vec!["up".into(), "wll://long".into()]);
+        assert_eq!(
+            table.render(),
+            vec!["NAME    URL", "origin  wll://a", "up      wll://long"]
+        );
+    }
+}
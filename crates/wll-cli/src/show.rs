@@ -0,0 +1,336 @@
+//! `wll show`: commitment details, notes, and the tree and state diff of a
+//! commit, and extraction of its evidence attachments.
+
+use std::path::{Path, PathBuf};
+
+use colored::Colorize;
+use serde_json::Value;
+use wll_diff::{diff_blobs, DiffLine, StateChange, TreeChange};
+use wll_ledger::Receipt;
+use wll_sdk::{EntryMode, ObjectId, ReceiptDetails, StoredNote, Wll};
+use wll_types::commitment::Decision;
+
+/// Render `details` as text lines; `patch` adds line diffs of changed files.
+pub fn render(wll: &Wll, details: &ReceiptDetails, patch: bool) -> anyhow::Result<Vec<String>> {
+    let mut out = Vec::new();
+
+    if let Receipt::Snapshot(s) = &details.receipt {
+        out.push(format!(
+            "{} {}  {}",
+            "snapshot".yellow().bold(),
+            format!("r#{}", s.seq).yellow(),
+            hex::encode(s.receipt_hash).dimmed()
+        ));
+        out.push(format!(
+            "Anchored:   {}",
+            hex::encode(s.anchored_receipt_hash)
+        ));
+        out.push(format!("State hash: {}", hex::encode(s.state_hash)));
+        if let Some(name) = &s.namespace {
+            out.push(format!("Namespace:  {name}"));
+        }
+        for (key, value) in &s.state {
+            out.push(format!("  {key} = {}", value_text(value)));
+        }
+        for (name, state) in &s.namespaces {
+            out.push(format!("  [{name}]"));
+            for (key, value) in state {
+                out.push(format!("    {key} = {}", value_text(value)));
+            }
+        }
+        out.extend(note_lines(&details.notes));
+        return Ok(out);
+    }
+
+    if let Some(c) = &details.commitment {
+        out.push(format!(
+            "{} {}  {}",
+            "commitment".yellow().bold(),
+            format!("r#{}", c.seq).yellow(),
+            hex::encode(c.receipt_hash).dimmed()
+        ));
+        out.push(format!("Intent:     {}", c.intent));
+        out.push(format!("Class:      {}", c.class.to_string().cyan()));
+        out.push(format!("Decision:   {}", decision_text(&c.decision)));
+        for evidence in &c.evidence.references {
+            out.push(format!("Evidence:   {}", evidence.blue()));
+        }
+        for attachment in &c.evidence.attachments {
+            out.push(format!(
+                "Attachment: {} ({}, {} bytes)  {}",
+                attachment.name.blue(),
+                attachment.media_type,
+                attachment.size,
+                attachment.object.short_hex().dimmed()
+            ));
+        }
+        if !c.requested_caps.is_empty() {
+            out.push(format!("Caps:       {}", c.requested_caps.join(", ")));
+        }
+        out.push(format!("Policy:     {}", hex::encode(c.policy_hash)));
+        out.push(format!("Commitment: {}", c.commitment_id));
+        out.push(format!("Time:       {}", c.timestamp));
+    }
+    match &details.outcome {
+        Some(o) => out.push(format!(
+            "Outcome:    {}  {}",
+            format!("r#{}", o.seq).yellow(),
+            hex::encode(o.receipt_hash).dimmed()
+        )),
+        None => out.push(format!("Outcome:    {}", "pending".yellow())),
+    }
+    out.extend(note_lines(&details.notes));
+
+    let named = details.namespace_diffs.iter().map(|(name, diff)| (Some(name), diff));
+    for (namespace, diff) in std::iter::once((None, &details.state_diff)).chain(named) {
+        if diff.is_empty() {
+            continue;
+        }
+        out.push(String::new());
+        out.push(match namespace {
+            Some(name) => format!("{} {name}:", "State".bold()),
+            None => "State:".bold().to_string(),
+        });
+        for change in &diff.changes {
+            out.push(match change {
+                StateChange::Added { key, value } => {
+                    format!("  {} {key} = {}", "+".green(), value_text(value))
+                }
+                StateChange::Removed { key, value } => {
+                    format!("  {} {key} = {}", "-".red(), value_text(value))
+                }
+                StateChange::Modified { key, old, new } => format!(
+                    "  {} {key}: {} -> {}",
+                    "~".yellow(),
+                    value_text(old),
+                    value_text(new)
+                ),
+            });
+        }
+    }
+
+    if let (Some(diff), Some(tree)) = (&details.tree_diff, &details.tree) {
+        out.push(String::new());
+        let base = details
+            .parent_tree
+            .map(|t| t.short_hex())
+            .unwrap_or_else(|| "(empty)".into());
+        out.push(format!("{} {base} -> {}", "Tree:".bold(), tree.short_hex()));
+        if diff.is_empty() {
+            out.push("  (no changes)".dimmed().to_string());
+        }
+        for change in &diff.changes {
+            out.push(change_text(change));
+        }
+        if patch {
+            for change in &diff.changes {
+                out.extend(patch_lines(wll, change)?);
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Write the commitment's evidence attachments into `dir`, returning the
+/// paths written.
+pub fn extract(wll: &Wll, details: &ReceiptDetails, dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let Some(commitment) = &details.commitment else {
+        return Ok(Vec::new());
+    };
+    std::fs::create_dir_all(dir)?;
+    let mut written = Vec::new();
+    for attachment in &commitment.evidence.attachments {
+        // Names are checked when attached, but receipts can come from peers.
+        let name = Path::new(&attachment.name)
+            .file_name()
+            .ok_or_else(|| anyhow::anyhow!("invalid attachment name {:?}", attachment.name))?;
+        let path = dir.join(name);
+        std::fs::write(&path, wll.read_attachment(attachment)?)?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+fn note_lines(notes: &[StoredNote]) -> Vec<String> {
+    let mut out = Vec::new();
+    for stored in notes {
+        let note = &stored.note;
+        out.push(String::new());
+        let author = note.author.as_deref().map(|a| format!(" by {a}")).unwrap_or_default();
+        let id = stored.id.short_hex();
+        out.push(format!("{} {}{author}  {}", "Note".bold(), note.created, id.dimmed()));
+        out.extend(note.text.lines().map(|line| format!("  {line}")));
+        out.extend(note.links.iter().map(|link| format!("  Link: {}", link.blue())));
+    }
+    out
+}
+
+fn decision_text(decision: &Decision) -> String {
+    match decision {
+        Decision::Accepted => "✓ Accepted".green().to_string(),
+        Decision::Rejected { reason } => format!("✗ Rejected: {reason}").red().to_string(),
+        Decision::Deferred { until, reason } => format!("… Deferred until {until}: {reason}")
+            .yellow()
+            .to_string(),
+    }
+}
+
+fn change_text(change: &TreeChange) -> String {
+    match change {
+        TreeChange::Added { path, .. } => format!("  {} {path}", "A".green()),
+        TreeChange::Deleted { path, .. } => format!("  {} {path}", "D".red()),
+        TreeChange::Modified { path, .. } => format!("  {} {path}", "M".yellow()),
+        TreeChange::Renamed {
+            old_path,
+            new_path,
+            similarity,
+            ..
+        } => format!(
+            "  {} {old_path} -> {new_path} ({:.0}%)",
+            "R".blue(),
+            similarity * 100.0
+        ),
+        TreeChange::ModeChanged {
+            path,
+            old_mode,
+            new_mode,
+            ..
+        } => format!(
+            "  {} {path} ({:o} -> {:o})",
+            "T".magenta(),
+            old_mode.mode_bits(),
+            new_mode.mode_bits()
+        ),
+    }
+}
+
+/// Unified-diff style hunks for a file change.
+fn patch_lines(wll: &Wll, change: &TreeChange) -> anyhow::Result<Vec<String>> {
+    let (path, old, new, mode) = match change {
+        TreeChange::Added {
+            path, new_id, mode, ..
+        } => (path, None, Some(new_id), mode),
+        TreeChange::Deleted {
+            path, old_id, mode, ..
+        } => (path, Some(old_id), None, mode),
+        TreeChange::Modified {
+            path,
+            old_id,
+            new_id,
+            mode,
+        } => (path, Some(old_id), Some(new_id), mode),
+        TreeChange::Renamed { .. } | TreeChange::ModeChanged { .. } => return Ok(Vec::new()),
+    };
+    if *mode == EntryMode::Directory {
+        return Ok(Vec::new());
+    }
+
+    let read = |id: Option<&ObjectId>| -> anyhow::Result<Vec<u8>> {
+        Ok(match id {
+            Some(id) => wll.read_blob(id)?,
+            None => Vec::new(),
+        })
+    };
+    let diff = diff_blobs(&read(old)?, &read(new)?);
+
+    let mut out = vec![
+        String::new(),
+        format!("--- {}", if old.is_some() { path } else { "/dev/null" })
+            .bold()
+            .to_string(),
+        format!("+++ {}", if new.is_some() { path } else { "/dev/null" })
+            .bold()
+            .to_string(),
+    ];
+    for hunk in &diff.hunks {
+        out.push(
+            format!(
+                "@@ -{},{} +{},{} @@",
+                hunk.old_start, hunk.old_count, hunk.new_start, hunk.new_count
+            )
+            .cyan()
+            .to_string(),
+        );
+        for line in &hunk.lines {
+            out.push(match line {
+                DiffLine::Context(text) => format!(" {}", text.trim_end_matches('\n')),
+                DiffLine::Added(text) => format!("+{}", text.trim_end_matches('\n'))
+                    .green()
+                    .to_string(),
+                DiffLine::Removed(text) => format!("-{}", text.trim_end_matches('\n'))
+                    .red()
+                    .to_string(),
+            });
+        }
+    }
+    Ok(out)
+}
+
+fn value_text(value: &Value) -> String {
+    value.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wll_sdk::{CommitProposal, TreeEntry};
+
+    #[test]
+    fn shows_commitment_state_and_patch() {
+        colored::control::set_override(false);
+        let wll = Wll::init().unwrap();
+        let blob = wll.write_blob(b"hello\n").unwrap();
+        let tree = wll
+            .write_tree(vec![TreeEntry::new(
+                EntryMode::Regular,
+                "greeting.txt",
+                blob,
+            )])
+            .unwrap();
+        let result = wll
+            .commit(
+                CommitProposal::new("say hello")
+                    .with_tree(tree)
+                    .with_evidence("issue://1"),
+            )
+            .unwrap();
+
+        let details = wll.receipt_details(&result.receipt_hash).unwrap();
+        let lines = render(&wll, &details, true).unwrap();
+        assert!(lines.contains(&"Intent:     say hello".to_string()));
+        assert!(lines.contains(&"Decision:   ✓ Accepted".to_string()));
+        assert!(lines.contains(&"Evidence:   issue://1".to_string()));
+        assert!(lines.contains(&"  + message = \"say hello\"".to_string()));
+        assert!(lines.contains(&"  A greeting.txt".to_string()));
+        assert!(lines.contains(&"+++ greeting.txt".to_string()));
+        assert!(lines.contains(&"+hello".to_string()));
+
+        let without_patch = render(&wll, &details, false).unwrap();
+        assert!(!without_patch.contains(&"+hello".to_string()));
+    }
+
+    #[test]
+    fn lists_and_extracts_attachments() {
+        colored::control::set_override(false);
+        let wll = Wll::init().unwrap();
+        let sbom = wll
+            .attach_evidence("sbom.json", "application/spdx+json", b"{}")
+            .unwrap();
+        let result = wll
+            .commit(CommitProposal::new("release").with_attachment(sbom.clone()))
+            .unwrap();
+        let details = wll.receipt_details(&result.receipt_hash).unwrap();
+
+        let lines = render(&wll, &details, false).unwrap();
+        let expected = format!(
+            "Attachment: sbom.json (application/spdx+json, 2 bytes)  {}",
+            sbom.object.short_hex()
+        );
+        assert!(lines.contains(&expected));
+
+        let dir = tempfile::tempdir().unwrap();
+        let written = extract(&wll, &details, &dir.path().join("evidence")).unwrap();
+        assert_eq!(written, vec![dir.path().join("evidence/sbom.json")]);
+        assert_eq!(std::fs::read(&written[0]).unwrap(), b"{}");
+    }
+}
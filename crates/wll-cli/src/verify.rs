@@ -0,0 +1,249 @@
+//! `wll verify`: every integrity check in one report, with an exit code
+//! telling corruption, policy violations and warnings apart.
+
+use colored::Colorize;
+use serde::Serialize;
+use wll_ledger::{StreamValidator, ValidationReport};
+use wll_sdk::Wll;
+
+use crate::errors::CheckFailed;
+
+/// How bad a finding is. The worst finding decides the exit code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Worth a look, but nothing is wrong yet. Fails only with
+    /// `--deny-warnings`.
+    Warning,
+    /// A recorded gate decision the gate no longer reproduces.
+    PolicyViolation,
+    /// Damaged receipts, provenance or objects.
+    Corruption,
+}
+
+impl Severity {
+    /// Process exit code for a report whose worst finding is `self`.
+    pub fn exit_code(self) -> u8 {
+        match self {
+            Self::Corruption => 3,
+            Self::PolicyViolation => 4,
+            Self::Warning => 5,
+        }
+    }
+}
+
+/// Exit code for a run whose worst finding is `status`: 0 when there is
+/// none, or only warnings and `deny_warnings` is off.
+pub fn exit_code(status: Option<Severity>, deny_warnings: bool) -> u8 {
+    match status {
+        Some(Severity::Warning) if !deny_warnings => 0,
+        status => status.map_or(0, Severity::exit_code),
+    }
+}
+
+/// The error a run exiting with `code` fails with, naming its worst
+/// finding.
+pub fn failure(status: Option<Severity>, code: u8) -> CheckFailed {
+    let found = match status {
+        Some(Severity::Corruption) => "corruption",
+        Some(Severity::PolicyViolation) => "policy violations",
+        _ => "warnings",
+    };
+    CheckFailed { code, message: format!("the checks found {found}") }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Finding {
+    pub severity: Severity,
+    /// `stream`, `dag`, `store`, `decisions` or `outcomes`.
+    pub check: &'static str,
+    pub description: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StreamSummary {
+    pub worldline: String,
+    pub receipts: u64,
+    pub hash_chain_valid: bool,
+    pub sequence_monotonic: bool,
+    pub outcomes_attributed: bool,
+    pub snapshots_anchored: bool,
+    pub proofs_verified: bool,
+    pub links_resolved: bool,
+}
+
+impl From<&ValidationReport> for StreamSummary {
+    fn from(report: &ValidationReport) -> Self {
+        Self {
+            worldline: report.worldline.to_hex(),
+            receipts: report.receipt_count,
+            hash_chain_valid: report.hash_chain_valid,
+            sequence_monotonic: report.sequence_monotonic,
+            outcomes_attributed: report.outcomes_attributed,
+            snapshots_anchored: report.snapshots_anchored,
+            proofs_verified: report.proofs_verified,
+            links_resolved: report.links_resolved,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerifyReport {
+    /// The worst finding's severity, or `null` when there is none.
+    pub status: Option<Severity>,
+    /// What the command exits with: 0 when clean or only warned.
+    pub exit_code: u8,
+    pub strict: bool,
+    pub worldlines: Vec<StreamSummary>,
+    pub dag_nodes: usize,
+    pub objects_checked: u64,
+    pub packs_checked: u64,
+    pub decisions_checked: u64,
+    pub findings: Vec<Finding>,
+}
+
+impl VerifyReport {
+    fn finish(&mut self, deny_warnings: bool) {
+        self.status = self.findings.iter().map(|f| f.severity).max();
+        self.exit_code = exit_code(self.status, deny_warnings);
+    }
+}
+
+/// Validate every worldline stream (with outcome proofs when `strict`),
+/// the provenance DAG, the object store and packs, and the recorded gate
+/// decisions of this worldline.
+pub fn check(wll: &Wll, strict: bool, deny_warnings: bool) -> anyhow::Result<VerifyReport> {
+    let mut findings = Vec::new();
+    let mut finding = |severity, check, description| {
+        findings.push(Finding { severity, check, description });
+    };
+
+    let mut worldlines = Vec::new();
+    for worldline in wll.ledger().worldlines()? {
+        let stream = match strict {
+            true => StreamValidator::validate_stream_strict(wll.ledger(), &worldline, wll)?,
+            false => StreamValidator::validate_stream(wll.ledger(), &worldline)?,
+        };
+        for violation in &stream.violations {
+            let description =
+                format!("{} r#{}: {}", worldline.short_id(), violation.seq, violation.description);
+            finding(Severity::Corruption, "stream", description);
+        }
+        worldlines.push(StreamSummary::from(&stream));
+    }
+
+    let dag = wll.provenance()?;
+    if let Err(e) = dag.validate() {
+        finding(Severity::Corruption, "dag", e.to_string());
+    }
+
+    let store = wll.scrub_store()?;
+    for problem in store.problems {
+        finding(Severity::Corruption, "store", problem);
+    }
+
+    let decisions = wll.replay_strict()?;
+    for m in &decisions.mismatches {
+        let description =
+            format!("r#{}: recorded {}, gate now says {}", m.seq, m.recorded, m.reevaluated);
+        finding(Severity::PolicyViolation, "decisions", description);
+    }
+    for seq in &decisions.unknown_policies {
+        let description = format!("r#{seq}: judged under a policy version unknown here");
+        finding(Severity::Warning, "decisions", description);
+    }
+
+    let now_ms = wll.clock().now_ms();
+    for commitment in wll.pending_commitments()? {
+        if wll.outcome_timeout().is_expired(&commitment, now_ms) {
+            let description = format!("r#{}: no outcome within the timeout", commitment.seq);
+            finding(Severity::Warning, "outcomes", description);
+        }
+    }
+
+    let mut report = VerifyReport {
+        status: None,
+        exit_code: 0,
+        strict,
+        worldlines,
+        dag_nodes: dag.len(),
+        objects_checked: store.objects_checked,
+        packs_checked: store.packs_checked,
+        decisions_checked: decisions.decisions_checked,
+        findings,
+    };
+    report.finish(deny_warnings);
+    Ok(report)
+}
+
+/// Render `report` as text lines.
+pub fn render(report: &VerifyReport) -> Vec<String> {
+    let mut out = Vec::new();
+    out.push(match report.status {
+        None => format!("{} Repository integrity verified", "✓".green().bold()),
+        Some(Severity::Warning) => {
+            format!("{} Repository integrity verified with warnings", "!".yellow().bold())
+        }
+        Some(Severity::PolicyViolation) => {
+            format!("{} Recorded gate decisions FAILED re-evaluation", "✗".red().bold())
+        }
+        Some(Severity::Corruption) => {
+            format!("{} Repository integrity check FAILED", "✗".red().bold())
+        }
+    });
+    let receipts: u64 = report.worldlines.iter().map(|w| w.receipts).sum();
+    let proofs = if report.strict { ", proofs checked" } else { "" };
+    out.push(format!(
+        "  Streams: {} worldlines, {receipts} receipts{proofs}",
+        report.worldlines.len()
+    ));
+    out.push(format!("  Provenance: {} nodes", report.dag_nodes));
+    out.push(format!(
+        "  Store: {} objects, {} packs",
+        report.objects_checked, report.packs_checked
+    ));
+    out.push(format!("  Gate decisions: {} re-checked", report.decisions_checked));
+    for f in &report.findings {
+        let mark = match f.severity {
+            Severity::Warning => "!".yellow(),
+            _ => "✗".red(),
+        };
+        out.push(format!("  {mark} {}: {}", f.check, f.description));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use wll_sdk::CommitProposal;
+
+    use super::*;
+
+    #[test]
+    fn clean_repository_exits_zero() {
+        let wll = Wll::init().unwrap();
+        wll.commit(CommitProposal::new("first")).unwrap();
+        let report = check(&wll, false, true).unwrap();
+        assert_eq!(report.status, None);
+        assert_eq!(report.exit_code, 0);
+        assert_eq!(report.worldlines[0].receipts, 2);
+        assert_eq!(report.decisions_checked, 1);
+    }
+
+    #[test]
+    fn worst_finding_decides_the_exit_code() {
+        let wll = Wll::init().unwrap();
+        let mut report = check(&wll, false, false).unwrap();
+        let finding = |severity| Finding { severity, check: "store", description: String::new() };
+        report.findings.push(finding(Severity::Warning));
+        report.finish(false);
+        assert_eq!((report.status, report.exit_code), (Some(Severity::Warning), 0));
+        report.finish(true);
+        assert_eq!(report.exit_code, 5);
+
+        report.findings.push(finding(Severity::Corruption));
+        report.findings.push(finding(Severity::PolicyViolation));
+        report.finish(false);
+        assert_eq!((report.status, report.exit_code), (Some(Severity::Corruption), 3));
+    }
+}
@@ -0,0 +1,117 @@
+//! Reading fixtures back with the current code.
+
+use std::fmt::Debug;
+use std::path::Path;
+
+use wll_fabric::WalEntry;
+use wll_ledger::{InMemoryLedger, LedgerReader, LedgerWriter, Receipt, StreamValidator};
+use wll_pack::{PackIndex, PackReader};
+use wll_protocol::{WllCodec, WllMessage};
+
+use crate::{broken, CompatResult, Manifest, FRAMES, LEDGER, MANIFEST, PACK, PACK_INDEX, WAL};
+
+/// Read the fixtures in `dir` and check them against its manifest.
+///
+/// The journal must import receipt by receipt with every hash checked and
+/// validate as a stream; the pack must verify and every object read back
+/// under its id, with or without the index; every WAL event must pass its
+/// integrity check; and every frame must decode and encode again to the
+/// same bytes.
+pub fn check(dir: &Path) -> CompatResult<Manifest> {
+    let manifest = std::fs::read(dir.join(MANIFEST))?;
+    let manifest: Manifest =
+        serde_json::from_slice(&manifest).map_err(|e| broken(dir, MANIFEST, e))?;
+    check_ledger(dir, &manifest).map_err(|e| broken(dir, LEDGER, e))?;
+    check_pack(dir, &manifest).map_err(|e| broken(dir, PACK, e))?;
+    check_wal(dir, &manifest).map_err(|e| broken(dir, WAL, e))?;
+    check_frames(dir, &manifest).map_err(|e| broken(dir, FRAMES, e))?;
+    Ok(manifest)
+}
+
+/// The variant name of `message`, as manifests record frames.
+pub(crate) fn message_kind(message: &WllMessage) -> String {
+    let debug = format!("{message:?}");
+    debug.split([' ', '{', '(']).next().unwrap_or_default().to_string()
+}
+
+fn expect<T: PartialEq + Debug>(what: &str, found: T, recorded: T) -> Result<(), String> {
+    if found == recorded {
+        Ok(())
+    } else {
+        Err(format!("{what} is {found:?}, the manifest says {recorded:?}"))
+    }
+}
+
+fn read(dir: &Path, file: &str) -> Result<Vec<u8>, String> {
+    std::fs::read(dir.join(file)).map_err(|e| e.to_string())
+}
+
+fn check_ledger(dir: &Path, manifest: &Manifest) -> Result<(), String> {
+    let journal = String::from_utf8(read(dir, LEDGER)?).map_err(|e| e.to_string())?;
+    let ledger = InMemoryLedger::default();
+    for (line, text) in (1..).zip(journal.lines()) {
+        let receipt: Receipt =
+            serde_json::from_str(text).map_err(|e| format!("line {line}: {e}"))?;
+        ledger.import_receipt(receipt).map_err(|e| format!("line {line}: {e}"))?;
+    }
+    let report = StreamValidator::validate_stream(&ledger, &manifest.worldline)
+        .map_err(|e| e.to_string())?;
+    if let Some(violation) = report.violations.first() {
+        return Err(format!("seq {}: {}", violation.seq, violation.description));
+    }
+    let receipts = ledger.read_all(&manifest.worldline).map_err(|e| e.to_string())?;
+    expect("the receipt count", receipts.len() as u64, manifest.receipts)?;
+    let head = receipts.last().map(|r| hex::encode(r.receipt_hash())).unwrap_or_default();
+    expect("the head hash", &head, &manifest.head)
+}
+
+fn check_pack(dir: &Path, manifest: &Manifest) -> Result<(), String> {
+    let pack = read(dir, PACK)?;
+    let index = PackIndex::from_bytes(&read(dir, PACK_INDEX)?).map_err(|e| e.to_string())?;
+    let reader = PackReader::from_bytes(pack.clone(), index).map_err(|e| e.to_string())?;
+    if let Some(failure) = reader.verify().failures().next() {
+        return Err(format!("entry at offset {} does not verify", failure.offset));
+    }
+    let mut objects = Vec::new();
+    for id in reader.object_ids() {
+        let object = reader
+            .read_object(id)
+            .map_err(|e| format!("{id}: {e}"))?
+            .ok_or_else(|| format!("{id} is indexed but not found"))?;
+        expect("a read object's id", object.compute_id(), *id)?;
+        objects.push(id.to_hex());
+    }
+    objects.sort();
+    expect("the packed objects", &objects, &manifest.objects)?;
+    let scanned = PackReader::from_pack_bytes(pack).map_err(|e| e.to_string())?;
+    expect("the objects found without the index", scanned.object_count(), objects.len())
+}
+
+fn check_wal(dir: &Path, manifest: &Manifest) -> Result<(), String> {
+    let mut events = Vec::new();
+    for entry in WalEntry::decode_segment(&read(dir, WAL)?).map_err(|e| e.to_string())? {
+        if !entry.event.verify_integrity() {
+            return Err(format!("event {} fails its integrity check", entry.event.id.to_hex()));
+        }
+        events.push(entry.event.id.to_hex());
+    }
+    expect("the events", &events, &manifest.events)
+}
+
+fn check_frames(dir: &Path, manifest: &Manifest) -> Result<(), String> {
+    let data = read(dir, FRAMES)?;
+    let mut rest = data.as_slice();
+    let mut frames = Vec::new();
+    while !rest.is_empty() {
+        let (message, consumed) =
+            WllCodec::decode(rest).map_err(|e| format!("frame {}: {e}", frames.len()))?;
+        let kind = message_kind(&message);
+        let encoded = WllCodec::encode(&message).map_err(|e| format!("{kind}: {e}"))?;
+        if encoded[..] != rest[..consumed] {
+            return Err(format!("frame {} ({kind}) encodes differently", frames.len()));
+        }
+        frames.push(kind);
+        rest = &rest[consumed..];
+    }
+    expect("the frames", &frames, &manifest.frames)
+}
@@ -0,0 +1,240 @@
+//! Writing the current version's fixtures.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use wll_fabric::{EventKind, EventPayload, FabricEvent, WalConfig, WalEntry, WriteAheadLog};
+use wll_ledger::{FileLedger, Receipt};
+use wll_pack::PackWriter;
+use wll_protocol::{
+    ConsistencyToken, LedgerPosition, PageDirection, RefUpdateMsg, RefUpdateResultMsg,
+    WllCodec, WllMessage, PROTOCOL_VERSION,
+};
+use wll_sdk::{CommitProposal, CommitmentClass, EntryMode, WllBuilder};
+use wll_store::{Blob, Tree, TreeEntry};
+use wll_types::{
+    CommitmentId, IdentityMaterial, ManualClock, ObjectId, ReceiptKind, TemporalAnchor,
+    WorldlineId,
+};
+
+use crate::check::message_kind;
+use crate::{broken, CompatResult, Manifest, FRAMES, LEDGER, MANIFEST, PACK, PACK_INDEX, WAL};
+
+/// 2023-11-14T22:13:20Z, when every fixture's clock starts.
+const START_MS: u64 = 1_700_000_000_000;
+
+/// Write the current version's fixtures and manifest into `dir`.
+pub fn generate(dir: &Path) -> CompatResult<Manifest> {
+    std::fs::create_dir_all(dir)?;
+    let worldline = WorldlineId::derive(&IdentityMaterial::GenesisHash([0x60; 32]));
+    let receipts = write_ledger(dir, &worldline)?;
+    let (objects, pack) = write_pack(dir)?;
+    let events = write_wal(dir, &worldline)?;
+    let frames = write_frames(dir, &worldline, &receipts, pack)?;
+    let head = receipts.last().map(|r| hex::encode(r.receipt_hash())).unwrap_or_default();
+    let manifest = Manifest {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        worldline,
+        receipts: receipts.len() as u64,
+        head,
+        objects,
+        events,
+        frames,
+    };
+    let json = serde_json::to_vec_pretty(&manifest).map_err(|e| broken(dir, MANIFEST, e))?;
+    std::fs::write(dir.join(MANIFEST), json)?;
+    Ok(manifest)
+}
+
+/// Two tree commits through the SDK, with evidence and metadata.
+fn write_ledger(dir: &Path, worldline: &WorldlineId) -> CompatResult<Vec<Receipt>> {
+    let failed = |e: wll_sdk::SdkError| broken(dir, LEDGER, e);
+    let staged = |e: wll_index::IndexError| broken(dir, LEDGER, e);
+    let clock = Arc::new(ManualClock::new(START_MS));
+    let ledger = FileLedger::open(dir.join(LEDGER), 1)
+        .map_err(|e| broken(dir, LEDGER, e))?
+        .with_clock(clock.clone());
+    let wll = WllBuilder::new()
+        .with_worldline(worldline.clone())
+        .with_ledger(Arc::new(ledger))
+        .with_clock(clock.clone())
+        .build()
+        .map_err(failed)?;
+
+    let mut index = wll.new_index();
+    for (path, content) in [("README.md", "golden\n"), ("src/lib.rs", "pub fn golden() {}\n")] {
+        index.stage_file(path, content.as_bytes(), EntryMode::Regular).map_err(staged)?;
+    }
+    let first = CommitProposal::new("add the golden tree").with_evidence("https://example.org/1");
+    wll.commit_tree(&mut index, first).map_err(failed)?;
+
+    clock.advance_ms(1_000);
+    index.stage_file("README.md", b"golden, amended\n", EntryMode::Regular).map_err(staged)?;
+    let second = CommitProposal::new("amend the readme")
+        .with_class(CommitmentClass::ContentUpdate)
+        .with_metadata("ticket", "GOLD-1");
+    wll.commit_tree(&mut index, second).map_err(failed)?;
+
+    wll.ledger().read_all(worldline).map_err(|e| broken(dir, LEDGER, e))
+}
+
+/// A blob, a delta against it, and a tree naming both.
+fn write_pack(dir: &Path) -> CompatResult<(Vec<String>, Vec<u8>)> {
+    let failed = |e: wll_pack::PackError| broken(dir, PACK, e);
+    let text: String = (0..64).map(|i| format!("line {i} of the golden blob\n")).collect();
+    let base = Blob::new(text.clone().into_bytes()).to_stored_object();
+    let edited = text.replace("line 40 ", "line forty ");
+    let edited = Blob::new(edited.into_bytes()).to_stored_object();
+    let tree = Tree::new(vec![
+        TreeEntry::new(EntryMode::Regular, "base.txt", base.compute_id()),
+        TreeEntry::new(EntryMode::Regular, "edited.txt", edited.compute_id()),
+    ])
+    .to_stored_object()
+    .map_err(|e| broken(dir, PACK, e))?;
+
+    let mut writer = PackWriter::new(&dir.join(PACK));
+    writer.add_stored_object(&base);
+    writer.add_delta_object(&edited, &base);
+    writer.add_stored_object(&tree);
+    let (pack, index) = writer.finish_to_bytes().map_err(failed)?;
+    std::fs::write(dir.join(PACK), &pack)?;
+    std::fs::write(dir.join(PACK_INDEX), index.to_bytes().map_err(failed)?)?;
+
+    let mut objects: Vec<String> =
+        [base, edited, tree].iter().map(|o| o.compute_id().to_hex()).collect();
+    objects.sort();
+    Ok((objects, pack))
+}
+
+/// One event of each payload shape.
+fn write_wal(dir: &Path, worldline: &WorldlineId) -> CompatResult<Vec<String>> {
+    let object = ObjectId::from_bytes(b"golden object");
+    let events = [
+        (EventKind::WorldlineCreated, EventPayload::Empty),
+        (
+            EventKind::CommitmentProposed,
+            EventPayload::Commitment {
+                commitment_id: CommitmentId::new(),
+                description: "add the golden tree".into(),
+            },
+        ),
+        (
+            EventKind::OutcomeRecorded,
+            EventPayload::ObjectRef { object_id: object, receipt_kind: ReceiptKind::Outcome },
+        ),
+        (
+            EventKind::RefUpdated,
+            EventPayload::RefUpdate {
+                ref_name: "refs/heads/main".into(),
+                old_target: None,
+                new_target: object,
+            },
+        ),
+        (
+            EventKind::SyncCompleted,
+            EventPayload::Sync { remote_node: "origin".into(), objects_transferred: 3 },
+        ),
+        (EventKind::SnapshotCreated, EventPayload::Raw(vec![0, 1, 2, 255])),
+        (
+            EventKind::MaintenanceCompleted,
+            EventPayload::Maintenance {
+                task: "gc".into(),
+                succeeded: true,
+                detail: "0 objects removed".into(),
+            },
+        ),
+    ];
+    let failed = |e: wll_fabric::FabricError| broken(dir, WAL, e);
+    let wal = WriteAheadLog::open(&dir.join(WAL), WalConfig::default()).map_err(failed)?;
+    let mut ids = Vec::new();
+    for (logical, (kind, payload)) in (0u32..).zip(events) {
+        let timestamp = TemporalAnchor { physical_ms: START_MS, logical, node_id: 1 };
+        let event = FabricEvent::new(timestamp, worldline.clone(), kind, payload);
+        ids.push(event.id.to_hex());
+        wal.append(&WalEntry { event }).map_err(failed)?;
+    }
+    wal.sync().map_err(failed)?;
+    Ok(ids)
+}
+
+/// One frame of each message kind.
+fn write_frames(
+    dir: &Path,
+    worldline: &WorldlineId,
+    receipts: &[Receipt],
+    pack: Vec<u8>,
+) -> CompatResult<Vec<String>> {
+    let head = receipts.last().map(|r| (r.seq(), r.receipt_hash())).unwrap_or_default();
+    let mut consistency = ConsistencyToken::new();
+    let position = LedgerPosition { seq: head.0, receipt_hash: head.1 };
+    consistency.positions.insert(worldline.clone(), position);
+    let receipts_data = serde_json::to_vec(receipts).map_err(|e| broken(dir, FRAMES, e))?;
+    let count = receipts.len() as u32;
+    let tip = [0x11; 32];
+    let id = ObjectId::from_bytes(b"golden object");
+    let capabilities = vec!["pack-v1".to_string(), "receipt-chain".to_string()];
+
+    let messages = vec![
+        WllMessage::Hello { version: PROTOCOL_VERSION, capabilities: capabilities.clone() },
+        WllMessage::HelloAck { version: PROTOCOL_VERSION, capabilities },
+        WllMessage::ListRefsRequest {
+            prefix: Some("refs/heads".into()),
+            min_position: Some(consistency.clone()),
+        },
+        WllMessage::ListRefsResponse {
+            refs: vec![("refs/heads/main".into(), tip)],
+            consistency: Some(consistency.clone()),
+        },
+        WllMessage::WantRequest { wants: vec![id], haves: vec![], depth: Some(1) },
+        WllMessage::AckResponse { common: vec![id] },
+        WllMessage::PackData { pack_bytes: pack.clone() },
+        WllMessage::PackAck { checksum: tip, object_count: 3 },
+        WllMessage::ReceiptBatch {
+            worldline: worldline.clone(),
+            receipts_data: receipts_data.clone(),
+            count,
+        },
+        WllMessage::ReceiptAck { worldline: worldline.clone(), through_seq: head.0 },
+        WllMessage::RefUpdateRequest {
+            updates: vec![RefUpdateMsg {
+                name: "refs/heads/main".into(),
+                old_hash: None,
+                new_hash: tip,
+                force: false,
+            }],
+            atomic: true,
+        },
+        WllMessage::RefUpdateResponse {
+            results: vec![
+                RefUpdateResultMsg::Ok { name: "refs/heads/main".into() },
+                RefUpdateResultMsg::Rejected {
+                    name: "refs/heads/dev".into(),
+                    reason: "not a fast-forward".into(),
+                },
+            ],
+            consistency: Some(consistency),
+        },
+        WllMessage::Error { code: 1301, message: "golden error".into() },
+        WllMessage::PackStream { total_len: pack.len() as u64 },
+        WllMessage::PackChunk { data: Bytes::from(pack) },
+        WllMessage::ReceiptPageRequest {
+            worldline: worldline.clone(),
+            cursor: None,
+            limit: 2,
+            direction: PageDirection::Backward,
+        },
+        WllMessage::ReceiptPageResponse {
+            worldline: worldline.clone(),
+            receipts_data,
+            count,
+            next: None,
+        },
+    ];
+    let mut frames = Vec::new();
+    for message in &messages {
+        frames.extend_from_slice(&WllCodec::encode(message).map_err(|e| broken(dir, FRAMES, e))?);
+    }
+    std::fs::write(dir.join(FRAMES), frames)?;
+    Ok(messages.iter().map(message_kind).collect())
+}
@@ -0,0 +1,156 @@
+//! Golden fixtures of the WorldLine Ledger's persisted and wire formats.
+//!
+//! Each released version leaves a directory under `golden/<version>/` with
+//! what it wrote:
+//!
+//! - `ledger.jsonl` — a [`FileLedger`](wll_ledger::FileLedger) receipt journal
+//! - `objects.pack`, `objects.idx` — a pack holding whole and delta entries,
+//!   and its index
+//! - `fabric.wal` — a write-ahead log segment of fabric events
+//! - `frames.bin` — protocol frames, one of each message kind
+//! - `manifest.json` — what the files hold ([`Manifest`])
+//!
+//! [`check`] reads a fixture directory with the current code and verifies
+//! it against its manifest. The crate's tests run it over every version in
+//! `golden/`, so a change that can no longer read what an earlier version
+//! wrote fails here rather than in a user's repository. Fixtures are never
+//! rewritten: a version's directory is added once, by [`generate`].
+//!
+//! `legacy/json-hashed.jsonl` holds receipts written before receipts were
+//! hashed over their canonical encoding, hashed over their JSON instead.
+//! It was written by that code, not by [`generate`], and must keep
+//! importing and validating as a stream.
+
+mod check;
+mod generate;
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use wll_types::WorldlineId;
+
+pub use check::check;
+pub use generate::generate;
+
+pub const MANIFEST: &str = "manifest.json";
+pub const LEDGER: &str = "ledger.jsonl";
+pub const PACK: &str = "objects.pack";
+pub const PACK_INDEX: &str = "objects.idx";
+pub const WAL: &str = "fabric.wal";
+pub const FRAMES: &str = "frames.bin";
+
+/// What a fixture directory holds, recorded by the version that wrote it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Crate version that wrote the fixtures.
+    pub version: String,
+    pub worldline: WorldlineId,
+    /// Receipts in the journal.
+    pub receipts: u64,
+    /// Hex hash of the journal's last receipt.
+    pub head: String,
+    /// Hex ids of the packed objects, sorted.
+    pub objects: Vec<String>,
+    /// Hex ids of the WAL's events, in order.
+    pub events: Vec<String>,
+    /// Message kind of each frame, in order.
+    pub frames: Vec<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CompatError {
+    /// A fixture that no longer reads back as its manifest says.
+    #[error("{fixture}: {reason}")]
+    Broken { fixture: PathBuf, reason: String },
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+pub type CompatResult<T> = Result<T, CompatError>;
+
+fn broken(dir: &Path, file: &str, reason: impl fmt::Display) -> CompatError {
+    CompatError::Broken { fixture: dir.join(file), reason: reason.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn golden() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("golden")
+    }
+
+    #[test]
+    fn every_version_s_fixtures_still_verify() {
+        let mut versions = Vec::new();
+        for entry in std::fs::read_dir(golden()).unwrap() {
+            let dir = entry.unwrap().path();
+            if dir.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.')) {
+                continue;
+            }
+            let manifest = check(&dir).unwrap_or_else(|e| panic!("{e}"));
+            assert_eq!(Some(manifest.version.as_str()), dir.file_name().and_then(|n| n.to_str()));
+            versions.push(manifest.version);
+        }
+        assert!(!versions.is_empty());
+    }
+
+    /// Set `WLL_GOLDEN_WRITE` to add the fixtures of a new version. They
+    /// are written under a hidden name and moved into place, out of sight
+    /// of the test above until complete.
+    #[test]
+    fn the_current_version_has_fixtures() {
+        let dir = golden().join(env!("CARGO_PKG_VERSION"));
+        if !dir.exists() && std::env::var_os("WLL_GOLDEN_WRITE").is_some() {
+            let staging = golden().join(concat!(".", env!("CARGO_PKG_VERSION")));
+            generate(&staging).unwrap();
+            std::fs::rename(&staging, &dir).unwrap();
+        }
+        assert!(
+            dir.join(MANIFEST).exists(),
+            "no fixtures for {}; run `WLL_GOLDEN_WRITE=1 cargo test -p wll-compat` and commit them",
+            env!("CARGO_PKG_VERSION"),
+        );
+    }
+
+    #[test]
+    fn legacy_json_hashed_receipts_still_verify() {
+        use wll_ledger::canonical::{canonical_receipt_hash, detect_encoding, ReceiptEncoding};
+        use wll_ledger::{InMemoryLedger, LedgerReader, LedgerWriter, Receipt, StreamValidator};
+
+        let journal = Path::new(env!("CARGO_MANIFEST_DIR")).join("legacy/json-hashed.jsonl");
+        let ledger = InMemoryLedger::default();
+        let mut worldline = None;
+        for text in std::fs::read_to_string(journal).unwrap().lines() {
+            let receipt: Receipt = serde_json::from_str(text).unwrap();
+            assert_eq!(detect_encoding(&receipt).unwrap(), Some(ReceiptEncoding::LegacyJson));
+            assert_ne!(canonical_receipt_hash(&receipt), receipt.receipt_hash());
+            worldline = Some(receipt.worldline().clone());
+            ledger.import_receipt(receipt).unwrap();
+        }
+        let worldline = worldline.unwrap();
+        let report = StreamValidator::validate_stream(&ledger, &worldline).unwrap();
+        assert!(report.violations.is_empty(), "{:?}", report.violations);
+        let head = ledger.head(&worldline).unwrap().unwrap();
+        assert_eq!(head.seq, 6);
+        assert_eq!(
+            hex::encode(head.receipt_hash),
+            "0b05f469948b55cf48253a58d9d45ca3e9e16a16cea1ed6ea604468b25faf9e8"
+        );
+    }
+
+    #[test]
+    fn damaged_fixtures_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let written = generate(dir.path()).unwrap();
+        assert_eq!(check(dir.path()).unwrap(), written);
+
+        let journal = dir.path().join(LEDGER);
+        let text = std::fs::read_to_string(&journal).unwrap();
+        std::fs::write(&journal, text.replacen("\"seq\":2", "\"seq\":3", 1)).unwrap();
+        let err = check(dir.path()).unwrap_err();
+        assert!(matches!(err, CompatError::Broken { ref fixture, .. } if fixture == &journal));
+    }
+}
@@ -0,0 +1,49 @@
+//! Error types for configuration loading and lookup.
+
+use std::path::PathBuf;
+
+use thiserror::Error;
+use wll_types::{Coded, ErrorCode};
+
+/// Errors that can occur while reading, merging, or writing configuration.
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    /// A config file exists but could not be read or written.
+    #[error("{path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// A config file is not valid TOML.
+    #[error("{path}: {message}")]
+    Parse { path: PathBuf, message: String },
+
+    /// A key is empty, has an empty segment, or runs through a non-table value.
+    #[error("invalid config key {key}: {reason}")]
+    InvalidKey { key: String, reason: String },
+
+    /// A value does not have the type its accessor expects.
+    #[error("config {key}: {message}")]
+    Type { key: String, message: String },
+
+    /// The level has no file to write to (e.g. no repository is open).
+    #[error("no {0} config file")]
+    NoFile(crate::ConfigLevel),
+}
+
+impl Coded for ConfigError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::Io { .. } => ErrorCode::Io,
+            Self::Parse { .. } | Self::InvalidKey { .. } | Self::Type { .. } => {
+                ErrorCode::ConfigInvalid
+            }
+            Self::NoFile(_) => ErrorCode::ConfigNoFile,
+        }
+    }
+}
+
+/// Convenience type alias for configuration operations.
+pub type ConfigResult<T> = Result<T, ConfigError>;
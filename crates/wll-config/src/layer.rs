@@ -0,0 +1,266 @@
+//! A single config file and dotted-key access into it.
+
+use std::path::{Path, PathBuf};
+
+use toml::{Table, Value};
+
+use crate::error::{ConfigError, ConfigResult};
+use crate::paths::ConfigLevel;
+
+/// The parsed contents of one config file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigLayer {
+    pub level: ConfigLevel,
+    /// File the layer was read from and is saved to.
+    pub path: Option<PathBuf>,
+    pub table: Table,
+}
+
+impl ConfigLayer {
+    /// An empty layer not backed by a file.
+    pub fn new(level: ConfigLevel) -> Self {
+        Self {
+            level,
+            path: None,
+            table: Table::new(),
+        }
+    }
+
+    /// Read `path`; a missing file gives an empty layer.
+    pub fn load(level: ConfigLevel, path: &Path) -> ConfigResult<Self> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(source) => {
+                return Err(ConfigError::Io {
+                    path: path.to_path_buf(),
+                    source,
+                })
+            }
+        };
+        let table = text.parse::<Table>().map_err(|e| ConfigError::Parse {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        })?;
+        Ok(Self {
+            level,
+            path: Some(path.to_path_buf()),
+            table,
+        })
+    }
+
+    /// Write the layer back to its file, creating parent directories.
+    pub fn save(&self) -> ConfigResult<()> {
+        let path = self
+            .path
+            .as_deref()
+            .ok_or(ConfigError::NoFile(self.level))?;
+        let io = |source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(io)?;
+        }
+        let text = toml::to_string(&self.table).map_err(|e| ConfigError::Parse {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        })?;
+        std::fs::write(path, text).map_err(io)
+    }
+
+    /// Value at dotted `key` (e.g. `remote.origin.url`).
+    pub fn get(&self, key: &str) -> ConfigResult<Option<&Value>> {
+        lookup(&self.table, key)
+    }
+
+    /// Set dotted `key` to `value`, creating intermediate tables.
+    pub fn set(&mut self, key: &str, value: Value) -> ConfigResult<()> {
+        let segments = split_key(key)?;
+        let (last, parents) = segments.split_last().expect("split_key rejects empty keys");
+        let mut table = &mut self.table;
+        for segment in parents {
+            let entry = table
+                .entry(segment.to_string())
+                .or_insert_with(|| Value::Table(Table::new()));
+            table = match entry {
+                Value::Table(inner) => inner,
+                _ => {
+                    return Err(ConfigError::InvalidKey {
+                        key: key.into(),
+                        reason: format!("{segment} is not a table"),
+                    })
+                }
+            };
+        }
+        table.insert(last.to_string(), value);
+        Ok(())
+    }
+
+    /// Remove dotted `key`, returning its old value. Tables left empty are
+    /// removed too.
+    pub fn unset(&mut self, key: &str) -> ConfigResult<Option<Value>> {
+        let segments = split_key(key)?;
+        Ok(remove_path(&mut self.table, &segments))
+    }
+}
+
+fn remove_path(table: &mut Table, segments: &[&str]) -> Option<Value> {
+    match segments {
+        [] => None,
+        [last] => table.remove(*last),
+        [first, rest @ ..] => {
+            let Some(Value::Table(inner)) = table.get_mut(*first) else {
+                return None;
+            };
+            let removed = remove_path(inner, rest);
+            if inner.is_empty() {
+                table.remove(*first);
+            }
+            removed
+        }
+    }
+}
+
+pub(crate) fn lookup<'a>(table: &'a Table, key: &str) -> ConfigResult<Option<&'a Value>> {
+    let segments = split_key(key)?;
+    let (last, parents) = segments.split_last().expect("split_key rejects empty keys");
+    let mut table = table;
+    for segment in parents {
+        match table.get(*segment) {
+            Some(Value::Table(inner)) => table = inner,
+            _ => return Ok(None),
+        }
+    }
+    Ok(table.get(*last))
+}
+
+/// Split a dotted key into segments. A segment may be double-quoted to
+/// contain dots, as in `credential.url."https://wll.example.com/".helper`.
+fn split_key(key: &str) -> ConfigResult<Vec<&str>> {
+    let invalid = || ConfigError::InvalidKey {
+        key: key.into(),
+        reason: "keys are dot-separated names like `user.name`".into(),
+    };
+    let mut segments = Vec::new();
+    let mut rest = key;
+    loop {
+        let (segment, after) = match rest.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"').ok_or_else(invalid)?;
+                (&quoted[..end], &quoted[end + 1..])
+            }
+            None => match rest.find('.') {
+                Some(dot) => (&rest[..dot], &rest[dot..]),
+                None => (rest, ""),
+            },
+        };
+        if segment.is_empty() {
+            return Err(invalid());
+        }
+        segments.push(segment);
+        match after.strip_prefix('.') {
+            Some(next) => rest = next,
+            None if after.is_empty() => return Ok(segments),
+            None => return Err(invalid()),
+        }
+    }
+}
+
+/// Render one key segment, quoting it unless it is a bare TOML key.
+pub(crate) fn key_segment(segment: &str) -> String {
+    let bare = segment
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if bare {
+        segment.to_string()
+    } else {
+        format!("\"{segment}\"")
+    }
+}
+
+/// Interpret a command-line value: booleans, integers, and floats keep
+/// their type, anything else is a string.
+pub fn parse_value(raw: &str) -> Value {
+    if let Ok(b) = raw.parse::<bool>() {
+        Value::Boolean(b)
+    } else if let Ok(i) = raw.parse::<i64>() {
+        Value::Integer(i)
+    } else {
+        match raw.parse::<f64>() {
+            Ok(f) if f.is_finite() => Value::Float(f),
+            _ => Value::String(raw.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_get_and_unset_dotted_keys() {
+        let mut layer = ConfigLayer::new(ConfigLevel::User);
+        layer
+            .set("remote.origin.url", Value::String("wll://a".into()))
+            .unwrap();
+        assert_eq!(
+            layer.get("remote.origin.url").unwrap(),
+            Some(&Value::String("wll://a".into()))
+        );
+        assert_eq!(layer.get("remote.backup.url").unwrap(), None);
+
+        assert!(layer.unset("remote.origin.url").unwrap().is_some());
+        assert!(layer.table.is_empty());
+        assert!(layer.get("a..b").is_err());
+    }
+
+    #[test]
+    fn quoted_segments_may_contain_dots() {
+        let mut layer = ConfigLayer::new(ConfigLevel::User);
+        layer
+            .set(
+                "credential.url.\"https://a.example/\".helper",
+                parse_value("store"),
+            )
+            .unwrap();
+        let url = &layer.table["credential"]["url"];
+        assert_eq!(url["https://a.example/"]["helper"].as_str(), Some("store"));
+        assert!(layer.get("a.\"b").is_err());
+        assert!(layer.get("a.\"b\"c").is_err());
+        assert_eq!(key_segment("https://a.example/"), "\"https://a.example/\"");
+        assert_eq!(key_segment("max_targets"), "max_targets");
+    }
+
+    #[test]
+    fn set_through_scalar_fails() {
+        let mut layer = ConfigLayer::new(ConfigLevel::User);
+        layer.set("user", Value::String("x".into())).unwrap();
+        assert!(matches!(
+            layer.set("user.name", Value::String("y".into())),
+            Err(ConfigError::InvalidKey { .. })
+        ));
+    }
+
+    #[test]
+    fn load_missing_file_is_empty_and_save_creates_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wll").join("config.toml");
+        let mut layer = ConfigLayer::load(ConfigLevel::User, &path).unwrap();
+        assert!(layer.table.is_empty());
+
+        layer.set("user.name", parse_value("Ada")).unwrap();
+        layer.save().unwrap();
+        let reloaded = ConfigLayer::load(ConfigLevel::User, &path).unwrap();
+        assert_eq!(reloaded, layer);
+    }
+
+    #[test]
+    fn parse_value_keeps_scalar_types() {
+        assert_eq!(parse_value("true"), Value::Boolean(true));
+        assert_eq!(parse_value("42"), Value::Integer(42));
+        assert_eq!(parse_value("1.5"), Value::Float(1.5));
+        assert_eq!(parse_value("inf"), Value::String("inf".into()));
+        assert_eq!(parse_value("wll://x"), Value::String("wll://x".into()));
+    }
+}
@@ -0,0 +1,323 @@
+//! The merged view over all config levels.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use toml::{Table, Value};
+
+use crate::error::{ConfigError, ConfigResult};
+use crate::layer::{key_segment, lookup, ConfigLayer};
+use crate::paths::{ConfigLevel, ConfigPaths};
+use crate::settings::{
+    CredentialSettings, GateSettings, HookSettings, LimitSettings, OutcomeSettings, RemoteMap,
+    RetentionSettings, SyncSettings, TimestampSettings, TransparencySettings, UserSettings,
+};
+
+/// System, user, and repository config merged key by key.
+///
+/// Tables merge recursively; any other value in a later level replaces the
+/// earlier one outright (arrays are not concatenated).
+#[derive(Clone, Debug)]
+pub struct LayeredConfig {
+    layers: Vec<ConfigLayer>,
+    merged: Table,
+}
+
+/// One leaf setting and the level it came from.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ConfigEntry {
+    pub key: String,
+    pub value: Value,
+    pub level: ConfigLevel,
+}
+
+impl LayeredConfig {
+    /// Load every level from its standard location.
+    pub fn load(paths: &ConfigPaths) -> ConfigResult<Self> {
+        let layers = ConfigLevel::ALL
+            .iter()
+            .map(|&level| match paths.get(level) {
+                Some(path) => ConfigLayer::load(level, path),
+                None => Ok(ConfigLayer::new(level)),
+            })
+            .collect::<ConfigResult<_>>()?;
+        Ok(Self::from_layers(layers))
+    }
+
+    /// Merge already-loaded layers, which are sorted by level.
+    pub fn from_layers(mut layers: Vec<ConfigLayer>) -> Self {
+        layers.sort_by_key(|layer| layer.level);
+        let mut merged = Table::new();
+        for layer in &layers {
+            merge(&mut merged, &layer.table);
+        }
+        Self { layers, merged }
+    }
+
+    pub fn layers(&self) -> &[ConfigLayer] {
+        &self.layers
+    }
+
+    /// The layer for `level`, if loaded.
+    pub fn layer(&self, level: ConfigLevel) -> Option<&ConfigLayer> {
+        self.layers.iter().find(|layer| layer.level == level)
+    }
+
+    /// The merged table.
+    pub fn table(&self) -> &Table {
+        &self.merged
+    }
+
+    /// Raw merged value at dotted `key`.
+    pub fn get_value(&self, key: &str) -> ConfigResult<Option<&Value>> {
+        lookup(&self.merged, key)
+    }
+
+    /// Merged value at dotted `key`, deserialized as `T`.
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> ConfigResult<Option<T>> {
+        self.get_value(key)?
+            .map(|value| {
+                value
+                    .clone()
+                    .try_into()
+                    .map_err(|e: toml::de::Error| ConfigError::Type {
+                        key: key.into(),
+                        message: e.message().to_string(),
+                    })
+            })
+            .transpose()
+    }
+
+    /// Merged section `name` as `T`, or `T::default()` when absent.
+    pub fn section<T: DeserializeOwned + Default>(&self, name: &str) -> ConfigResult<T> {
+        Ok(self.get(name)?.unwrap_or_default())
+    }
+
+    /// Level that supplies the effective value of `key`.
+    pub fn origin(&self, key: &str) -> ConfigResult<Option<ConfigLevel>> {
+        for layer in self.layers.iter().rev() {
+            if layer.get(key)?.is_some() {
+                return Ok(Some(layer.level));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Every effective leaf setting in key order, with its origin.
+    pub fn entries(&self) -> Vec<ConfigEntry> {
+        let mut leaves = Vec::new();
+        flatten("", &self.merged, &mut leaves);
+        leaves
+            .into_iter()
+            .map(|(key, value)| {
+                let level = self
+                    .origin(&key)
+                    .ok()
+                    .flatten()
+                    .unwrap_or(ConfigLevel::Repository);
+                ConfigEntry { key, value, level }
+            })
+            .collect()
+    }
+
+    /// `[user]`
+    pub fn user(&self) -> ConfigResult<UserSettings> {
+        self.section("user")
+    }
+
+    /// `[gate]`
+    pub fn gate(&self) -> ConfigResult<GateSettings> {
+        self.section("gate")
+    }
+
+    /// `[remote.<name>]` tables from every level.
+    pub fn remotes(&self) -> ConfigResult<RemoteMap> {
+        self.section("remote")
+    }
+
+    /// `[credential]`
+    pub fn credential(&self) -> ConfigResult<CredentialSettings> {
+        self.section("credential")
+    }
+
+    /// `[timestamp]`
+    pub fn timestamp(&self) -> ConfigResult<TimestampSettings> {
+        self.section("timestamp")
+    }
+
+    /// `[transparency]`
+    pub fn transparency(&self) -> ConfigResult<TransparencySettings> {
+        self.section("transparency")
+    }
+
+    /// `[retention]`
+    pub fn retention(&self) -> ConfigResult<RetentionSettings> {
+        self.section("retention")
+    }
+
+    /// `[sync]`
+    pub fn sync(&self) -> ConfigResult<SyncSettings> {
+        self.section("sync")
+    }
+
+    /// `[hooks]`
+    pub fn hooks(&self) -> ConfigResult<HookSettings> {
+        self.section("hooks")
+    }
+
+    /// `[limits]`
+    pub fn limits(&self) -> ConfigResult<LimitSettings> {
+        self.section("limits")
+    }
+
+    /// `[outcomes]`
+    pub fn outcomes(&self) -> ConfigResult<OutcomeSettings> {
+        self.section("outcomes")
+    }
+}
+
+fn merge(into: &mut Table, from: &Table) {
+    for (key, value) in from {
+        match (into.get_mut(key), value) {
+            (Some(Value::Table(existing)), Value::Table(incoming)) => merge(existing, incoming),
+            _ => {
+                into.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+fn flatten(prefix: &str, table: &Table, out: &mut Vec<(String, Value)>) {
+    for (key, value) in table {
+        let key = key_segment(key);
+        let path = if prefix.is_empty() {
+            key
+        } else {
+            format!("{prefix}.{key}")
+        };
+        match value {
+            Value::Table(inner) => flatten(&path, inner, out),
+            _ => out.push((path, value.clone())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wll_types::DecodeLimits;
+
+    use super::*;
+    use crate::layer::parse_value;
+
+    fn layer(level: ConfigLevel, pairs: &[(&str, &str)]) -> ConfigLayer {
+        let mut layer = ConfigLayer::new(level);
+        for (key, value) in pairs {
+            layer.set(key, parse_value(value)).unwrap();
+        }
+        layer
+    }
+
+    fn config() -> LayeredConfig {
+        LayeredConfig::from_layers(vec![
+            layer(
+                ConfigLevel::Repository,
+                &[
+                    ("user.email", "ada@repo"),
+                    ("gate.require_evidence", "true"),
+                ],
+            ),
+            layer(
+                ConfigLevel::System,
+                &[("user.name", "Default"), ("gate.timeout_secs", "10")],
+            ),
+            layer(
+                ConfigLevel::User,
+                &[("user.name", "Ada"), ("user.email", "ada@home")],
+            ),
+        ])
+    }
+
+    #[test]
+    fn later_levels_override_and_tables_merge() {
+        let config = config();
+        assert_eq!(
+            config.user().unwrap(),
+            UserSettings {
+                name: Some("Ada".into()),
+                email: Some("ada@repo".into()),
+            }
+        );
+        let gate = config.gate().unwrap();
+        assert!(gate.require_evidence);
+        assert_eq!(gate.timeout_secs, Some(10));
+    }
+
+    #[test]
+    fn origin_and_entries_report_levels() {
+        let config = config();
+        assert_eq!(config.origin("user.name").unwrap(), Some(ConfigLevel::User));
+        assert_eq!(
+            config.origin("user.email").unwrap(),
+            Some(ConfigLevel::Repository)
+        );
+        assert_eq!(config.origin("user.missing").unwrap(), None);
+
+        let keys: Vec<_> = config
+            .entries()
+            .into_iter()
+            .map(|e| (e.key, e.level))
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                ("gate.require_evidence".into(), ConfigLevel::Repository),
+                ("gate.timeout_secs".into(), ConfigLevel::System),
+                ("user.email".into(), ConfigLevel::Repository),
+                ("user.name".into(), ConfigLevel::User),
+            ]
+        );
+    }
+
+    #[test]
+    fn unset_limits_keep_their_defaults() {
+        let config = LayeredConfig::from_layers(vec![layer(
+            ConfigLevel::Repository,
+            &[("limits.objects.max_depth", "8")],
+        )]);
+        let limits = config.limits().unwrap();
+        assert_eq!(limits.objects, DecodeLimits::DEFAULT.with_max_depth(8));
+        assert_eq!(limits.protocol, DecodeLimits::DEFAULT);
+    }
+
+    #[test]
+    fn typed_get_reports_type_errors() {
+        let config = config();
+        assert_eq!(config.get::<u64>("gate.timeout_secs").unwrap(), Some(10));
+        assert!(matches!(
+            config.get::<u64>("user.name"),
+            Err(ConfigError::Type { .. })
+        ));
+    }
+
+    #[test]
+    fn load_reads_each_level_from_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let user = dir.path().join("user.toml");
+        let repo = dir.path().join("repo.toml");
+        std::fs::write(&user, "[remote.backup]\nurl = \"wll://b\"\n").unwrap();
+        std::fs::write(
+            &repo,
+            "[remote.origin]\nurl = \"wll://o\"\nfetch = [\"refs/heads/main:refs/remotes/origin/main\"]\n",
+        )
+        .unwrap();
+        let paths = ConfigPaths {
+            system: Some(dir.path().join("missing.toml")),
+            user: Some(user),
+            repository: Some(repo),
+        };
+
+        let config = LayeredConfig::load(&paths).unwrap();
+        let remotes = config.remotes().unwrap();
+        assert_eq!(remotes["backup"].url, "wll://b");
+        assert_eq!(remotes["origin"].fetch.len(), 1);
+    }
+}
@@ -0,0 +1,41 @@
+//! Layered configuration for the WorldLine Ledger.
+//!
+//! Settings are read from up to three TOML files and merged key by key,
+//! with later levels overriding earlier ones:
+//!
+//! 1. **System** — `/etc/wll/config.toml` (or `$WLL_SYSTEM_CONFIG`)
+//! 2. **User** — `~/.config/wll/config.toml` (or `$WLL_CONFIG`)
+//! 3. **Repository** — `.wll/config`
+//!
+//! Keys are addressed with dots (`remote.origin.url`), and the well-known
+//! sections have typed views used by the CLI, SDK, gate, and sync crates.
+//!
+//! # Modules
+//!
+//! - [`error`] — Error types for config operations
+//! - [`paths`] — [`ConfigLevel`] and the standard [`ConfigPaths`]
+//! - [`layer`] — One config file: [`ConfigLayer`]
+//! - [`layered`] — The merged view: [`LayeredConfig`]
+//! - [`settings`] — Typed sections: [`UserSettings`], [`GateSettings`],
+//!   [`RemoteSettings`], [`CredentialSettings`], [`TimestampSettings`],
+//!   [`TransparencySettings`], [`RetentionSettings`], [`SyncSettings`],
+//!   [`HookSettings`], [`LimitSettings`], [`OutcomeSettings`]
+
+pub mod error;
+pub mod layer;
+pub mod layered;
+pub mod paths;
+pub mod settings;
+
+pub use error::{ConfigError, ConfigResult};
+pub use layer::{parse_value, ConfigLayer};
+pub use layered::{ConfigEntry, LayeredConfig};
+pub use paths::{ConfigLevel, ConfigPaths};
+pub use settings::{
+    AttestationRequirement, ChangeBudgetSettings, CredentialSettings, CredentialUrlSettings,
+    CrossWorldlinePolicy, GateSettings, HookSettings, IntentSettings, LimitSettings,
+    OutcomeSettings, RemoteMap, RemoteSettings, RetentionSettings, StageErrorPolicy, SyncSettings,
+    TimestampServerSettings, TimestampSettings, TransparencyLogSettings, TransparencySettings,
+    UserSettings,
+};
+pub use toml::Value;
@@ -0,0 +1,120 @@
+//! Config levels and where their files live.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+/// Where a setting comes from. Later levels override earlier ones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigLevel {
+    /// Machine-wide defaults (`/etc/wll/config.toml`).
+    System,
+    /// Per-user settings (`~/.config/wll/config.toml`).
+    User,
+    /// Settings of one repository (`.wll/config`).
+    Repository,
+}
+
+impl ConfigLevel {
+    /// All levels, lowest precedence first.
+    pub const ALL: [ConfigLevel; 3] = [Self::System, Self::User, Self::Repository];
+}
+
+impl fmt::Display for ConfigLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::System => "system",
+            Self::User => "user",
+            Self::Repository => "repository",
+        })
+    }
+}
+
+/// Config file location for each level; `None` skips that level.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConfigPaths {
+    pub system: Option<PathBuf>,
+    pub user: Option<PathBuf>,
+    pub repository: Option<PathBuf>,
+}
+
+impl ConfigPaths {
+    /// Standard locations, with `repo_dir` being a `.wll` directory.
+    ///
+    /// `WLL_SYSTEM_CONFIG` and `WLL_CONFIG` override the system and user
+    /// files. Otherwise the user file is `$XDG_CONFIG_HOME/wll/config.toml`,
+    /// falling back to `$HOME/.config/wll/config.toml`.
+    pub fn standard(repo_dir: Option<&Path>) -> Self {
+        Self {
+            system: Some(
+                env_path("WLL_SYSTEM_CONFIG")
+                    .unwrap_or_else(|| PathBuf::from("/etc/wll/config.toml")),
+            ),
+            user: env_path("WLL_CONFIG").or_else(|| {
+                env_path("XDG_CONFIG_HOME")
+                    .or_else(|| env_path("HOME").map(|home| home.join(".config")))
+                    .map(|dir| dir.join("wll").join("config.toml"))
+            }),
+            repository: repo_dir.map(|dir| dir.join("config")),
+        }
+    }
+
+    /// Credentials file used by the `store` credential helper, kept next to
+    /// the user config.
+    pub fn credential_store(&self) -> Option<PathBuf> {
+        let user = self.user.as_deref()?;
+        Some(user.with_file_name("credentials.toml"))
+    }
+
+    /// File for `level`, if that level is in use.
+    pub fn get(&self, level: ConfigLevel) -> Option<&Path> {
+        match level {
+            ConfigLevel::System => self.system.as_deref(),
+            ConfigLevel::User => self.user.as_deref(),
+            ConfigLevel::Repository => self.repository.as_deref(),
+        }
+    }
+}
+
+fn env_path(var: &str) -> Option<PathBuf> {
+    std::env::var_os(var)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_order_by_precedence() {
+        assert!(ConfigLevel::System < ConfigLevel::User);
+        assert!(ConfigLevel::User < ConfigLevel::Repository);
+        assert_eq!(ConfigLevel::Repository.to_string(), "repository");
+    }
+
+    #[test]
+    fn repository_file_is_inside_repo_dir() {
+        let paths = ConfigPaths::standard(Some(Path::new("/work/.wll")));
+        assert_eq!(
+            paths.get(ConfigLevel::Repository),
+            Some(Path::new("/work/.wll/config"))
+        );
+        assert!(ConfigPaths::standard(None).repository.is_none());
+    }
+
+    #[test]
+    fn credential_store_sits_beside_user_config() {
+        let paths = ConfigPaths {
+            user: Some("/home/a/.config/wll/config.toml".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            paths.credential_store(),
+            Some(PathBuf::from("/home/a/.config/wll/credentials.toml"))
+        );
+        assert_eq!(ConfigPaths::default().credential_store(), None);
+    }
+}
@@ -0,0 +1,382 @@
+//! Typed views of the well-known config sections.
+//!
+//! Each section deserializes from the merged config with every field
+//! optional, so a layer only needs the keys it changes.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use wll_types::DecodeLimits;
+
+/// `[user]`: who is making commitments.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserSettings {
+    pub name: Option<String>,
+    pub email: Option<String>,
+}
+
+/// `[gate]`: the default policy applied to every commitment.
+///
+/// With no requirements set the gate is permissive, as after `wll init`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GateSettings {
+    /// Force permissive mode on or off; defaults to on unless a
+    /// requirement below is set.
+    pub permissive: Option<bool>,
+    pub require_evidence: bool,
+    pub require_signatures: bool,
+    /// Resolve evidence references and check their pinned digests.
+    pub verify_evidence: bool,
+    pub max_targets_per_commitment: Option<usize>,
+    /// Pipeline time limit in seconds.
+    pub timeout_secs: Option<u64>,
+    /// `[gate.require_attestation]`: commitment class name (e.g.
+    /// `PolicyChange`) to the attested identity its proposer must hold.
+    pub require_attestation: BTreeMap<String, AttestationRequirement>,
+    /// `[gate.change_budget.<class>]`: how large a tree change commitments
+    /// of each class may make.
+    pub change_budget: BTreeMap<String, ChangeBudgetSettings>,
+    /// Time limit for each stage, in seconds.
+    pub stage_timeout_secs: Option<u64>,
+    /// What a stage error, panic, or timeout does; `fail-closed` unless set.
+    pub error_policy: Option<StageErrorPolicy>,
+    /// `[gate.stage_error_policy]`: stage name to its own error policy.
+    pub stage_error_policy: BTreeMap<String, StageErrorPolicy>,
+    /// `[gate.intent]`: conventions intents must follow.
+    pub intent: IntentSettings,
+}
+
+/// How a failing gate stage affects acceptance.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StageErrorPolicy {
+    /// The commit is refused.
+    FailClosed,
+    /// The stage is skipped.
+    FailOpen,
+}
+
+/// Limits of one `[gate.change_budget.<class>]` table.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChangeBudgetSettings {
+    /// Most paths one commitment may change.
+    pub max_files: Option<usize>,
+    /// Most lines one commitment may add and remove, together.
+    pub max_lines: Option<usize>,
+}
+
+/// `[gate.intent]`: conventions commitment intents must follow.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IntentSettings {
+    /// Require a first line of the form `type(scope): subject`.
+    pub conventional: bool,
+    /// Types the first line may start with; setting any implies
+    /// `conventional`.
+    pub types: Vec<String>,
+    /// Scopes an intent may name, when it names one.
+    pub scopes: Vec<String>,
+    /// Require a scope; implies `conventional`.
+    pub require_scope: bool,
+    /// Longest first line, in characters.
+    pub max_subject_len: Option<usize>,
+    /// Commitment classes (e.g. `PolicyChange`) whose intents must
+    /// reference an issue.
+    pub require_issue: Vec<String>,
+}
+
+impl IntentSettings {
+    /// Whether any convention is configured.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Attested identity demanded by `[gate.require_attestation]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttestationRequirement {
+    /// Any verified external identity.
+    Any,
+    /// A person.
+    Human,
+    /// A machine identity such as a CI job.
+    Workload,
+}
+
+impl GateSettings {
+    /// Whether any requirement is configured.
+    pub fn has_requirements(&self) -> bool {
+        self.require_evidence
+            || self.require_signatures
+            || self.verify_evidence
+            || self.max_targets_per_commitment.is_some()
+            || !self.require_attestation.is_empty()
+            || !self.change_budget.is_empty()
+            || !self.intent.is_empty()
+    }
+
+    /// Effective permissive flag.
+    pub fn is_permissive(&self) -> bool {
+        self.permissive.unwrap_or(!self.has_requirements())
+    }
+}
+
+/// `[remote.<name>]`: a remote repository and the refspecs fetched from it.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteSettings {
+    pub url: String,
+    #[serde(default)]
+    pub fetch: Vec<String>,
+}
+
+/// Remotes keyed by name.
+pub type RemoteMap = BTreeMap<String, RemoteSettings>;
+
+/// `[credential]`: how sync obtains credentials for remotes.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CredentialSettings {
+    /// Program asked for credentials, e.g. `store` or `/usr/bin/wll-cred`.
+    pub helper: Option<String>,
+    /// Per-URL-prefix helpers, overriding `helper`.
+    #[serde(rename = "url")]
+    pub per_url: BTreeMap<String, CredentialUrlSettings>,
+}
+
+/// `[credential.url."<prefix>"]`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CredentialUrlSettings {
+    pub helper: Option<String>,
+}
+
+impl CredentialSettings {
+    /// Helper for `url`: the longest matching URL prefix wins, then the
+    /// default helper.
+    pub fn helper_for(&self, url: &str) -> Option<&str> {
+        self.per_url
+            .iter()
+            .filter(|(prefix, settings)| {
+                url.starts_with(prefix.as_str()) && settings.helper.is_some()
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .and_then(|(_, settings)| settings.helper.as_deref())
+            .or(self.helper.as_deref())
+    }
+}
+
+/// `[timestamp]`: Roughtime servers used to anchor the ledger head.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimestampSettings {
+    /// Seconds between anchors when anchoring runs in the background.
+    pub interval_secs: Option<u64>,
+    /// Trusted servers keyed by name.
+    #[serde(rename = "server")]
+    pub servers: BTreeMap<String, TimestampServerSettings>,
+}
+
+/// `[timestamp.server.<name>]`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimestampServerSettings {
+    /// `host:port` of the server's UDP endpoint.
+    pub address: String,
+    /// The server's long-term Ed25519 key, hex or base64.
+    pub public_key: String,
+}
+
+/// `[transparency]`: append-only logs the ledger head is published to.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransparencySettings {
+    /// Seconds between publications when publishing runs in the background.
+    pub interval_secs: Option<u64>,
+    /// Trusted logs keyed by name.
+    #[serde(rename = "log")]
+    pub logs: BTreeMap<String, TransparencyLogSettings>,
+}
+
+/// `[transparency.log.<name>]`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransparencyLogSettings {
+    /// Base URL of the log's HTTP API.
+    pub url: String,
+    /// The key the log signs checkpoints with, hex or base64.
+    pub public_key: String,
+}
+
+/// `[retention]`: how much history DAG checkpoints, ledger archival, and
+/// object GC keep.
+///
+/// A receipt is pruned only when it falls outside every window set here;
+/// with neither set, everything is kept.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionSettings {
+    /// Keep at least this many of the newest receipts.
+    pub keep_receipts: Option<u64>,
+    /// Keep receipts from the last this many days.
+    pub keep_days: Option<u64>,
+}
+
+/// `[sync]`: how fetched history is checked before it is imported.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncSettings {
+    /// What to do when a fetched receipt cites a receipt of another
+    /// worldline that is neither local nor part of the transfer.
+    pub cross_worldline: CrossWorldlinePolicy,
+    /// Cap on have/want rounds when negotiating a fetch.
+    pub negotiation_rounds: Option<usize>,
+    /// Hashes of genesis receipts, hex, a stream fetched from scratch may
+    /// start from.
+    pub trusted_genesis: Vec<String>,
+    /// Hashes of snapshot receipts, hex, that anchor a fetched stream.
+    pub trusted_snapshots: Vec<String>,
+    /// Keys, hex or base64, receipt signatures must verify against.
+    pub trusted_keys: Vec<String>,
+}
+
+/// `[limits]`: bounds on what is decoded from peers, one set per
+/// subsystem. Unset keys take [`DecodeLimits::DEFAULT`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LimitSettings {
+    /// Messages read off the wire.
+    pub protocol: DecodeLimits,
+    /// Receipts in fetched batches and pages.
+    pub receipts: DecodeLimits,
+    /// Trees and other structured objects in fetched packs.
+    pub objects: DecodeLimits,
+}
+
+/// `[outcomes]`: outcomes recorded apart from their commitments.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutcomeSettings {
+    /// Record a failure outcome for commitments still waiting for one after
+    /// this many seconds.
+    pub timeout_secs: Option<u64>,
+}
+
+/// `[hooks]`: programs run around commits and pushes.
+///
+/// Each program gets a JSON description of the operation on stdin. A
+/// pre-commit or pre-push program that exits non-zero vetoes the operation.
+/// Relative paths are taken from the working tree.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HookSettings {
+    pub pre_commit: Vec<String>,
+    pub post_commit: Vec<String>,
+    pub pre_push: Vec<String>,
+}
+
+/// Handling of unresolved cross-worldline references, set by
+/// `sync.cross_worldline`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CrossWorldlinePolicy {
+    /// Fetch the cited worldlines from the remote; fail if still unresolved.
+    #[default]
+    Fetch,
+    /// Import anyway and log each unresolved reference.
+    Warn,
+    /// Fail the fetch.
+    Reject,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gate_is_permissive_until_requirements_are_set() {
+        assert!(GateSettings::default().is_permissive());
+        let strict = GateSettings {
+            require_evidence: true,
+            ..GateSettings::default()
+        };
+        assert!(!strict.is_permissive());
+        let forced = GateSettings {
+            permissive: Some(true),
+            ..strict
+        };
+        assert!(forced.is_permissive());
+    }
+
+    #[test]
+    fn gate_parses_attestation_requirements() {
+        let gate: GateSettings =
+            toml::from_str("[require_attestation]\nPolicyChange = \"human\"\ndeploy = \"any\"\n")
+                .unwrap();
+        assert_eq!(
+            gate.require_attestation.get("PolicyChange"),
+            Some(&AttestationRequirement::Human)
+        );
+        assert_eq!(
+            gate.require_attestation.get("deploy"),
+            Some(&AttestationRequirement::Any)
+        );
+        assert!(!gate.is_permissive());
+        assert!(
+            toml::from_str::<GateSettings>("[require_attestation]\nReadOnly = \"robot\"\n")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn gate_parses_change_budgets() {
+        let gate: GateSettings =
+            toml::from_str("[change_budget.PolicyChange]\nmax_lines = 200\n").unwrap();
+        let budget = gate.change_budget["PolicyChange"];
+        assert_eq!(budget.max_lines, Some(200));
+        assert_eq!(budget.max_files, None);
+        assert!(!gate.is_permissive());
+    }
+
+    #[test]
+    fn gate_parses_stage_error_policies() {
+        let gate: GateSettings = toml::from_str(
+            "error_policy = \"fail-open\"\n[stage_error_policy]\npolicy = \"fail-closed\"\n",
+        )
+        .unwrap();
+        assert_eq!(gate.error_policy, Some(StageErrorPolicy::FailOpen));
+        assert_eq!(gate.stage_error_policy["policy"], StageErrorPolicy::FailClosed);
+    }
+
+    #[test]
+    fn credential_helper_prefers_longest_prefix() {
+        let mut settings = CredentialSettings {
+            helper: Some("store".into()),
+            ..Default::default()
+        };
+        settings.per_url.insert(
+            "https://wll.example.com/".into(),
+            CredentialUrlSettings {
+                helper: Some("corp".into()),
+            },
+        );
+        settings.per_url.insert(
+            "https://wll.example.com/secret/".into(),
+            CredentialUrlSettings {
+                helper: Some("vault".into()),
+            },
+        );
+        assert_eq!(
+            settings.helper_for("https://wll.example.com/secret/repo"),
+            Some("vault")
+        );
+        assert_eq!(
+            settings.helper_for("https://wll.example.com/repo"),
+            Some("corp")
+        );
+        assert_eq!(settings.helper_for("https://other/repo"), Some("store"));
+    }
+}
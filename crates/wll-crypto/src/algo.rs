@@ -0,0 +1,89 @@
+use alloc::boxed::Box;
+
+use sha2::Digest as _;
+
+pub use wll_types::HashAlgo;
+
+/// Incremental hasher for any supported [`HashAlgo`].
+///
+/// Wraps the underlying BLAKE3 or SHA-256 implementation behind a single
+/// `update` / `finalize` interface so callers can stay algorithm-agnostic.
+pub enum Digester {
+    Blake3(Box<blake3::Hasher>),
+    Sha256(sha2::Sha256),
+}
+
+impl Digester {
+    /// Create a fresh hasher for the given algorithm.
+    pub fn new(algo: HashAlgo) -> Self {
+        match algo {
+            HashAlgo::Blake3 => Self::Blake3(Box::new(blake3::Hasher::new())),
+            HashAlgo::Sha256 => Self::Sha256(sha2::Sha256::new()),
+        }
+    }
+
+    /// The algorithm this hasher computes.
+    pub fn algo(&self) -> HashAlgo {
+        match self {
+            Self::Blake3(_) => HashAlgo::Blake3,
+            Self::Sha256(_) => HashAlgo::Sha256,
+        }
+    }
+
+    /// Feed more input.
+    pub fn update(&mut self, data: &[u8]) -> &mut Self {
+        match self {
+            Self::Blake3(h) => {
+                h.update(data);
+            }
+            Self::Sha256(h) => h.update(data),
+        }
+        self
+    }
+
+    /// Finish hashing and return the 32-byte digest.
+    pub fn finalize(self) -> [u8; 32] {
+        match self {
+            Self::Blake3(h) => *h.finalize().as_bytes(),
+            Self::Sha256(h) => h.finalize().into(),
+        }
+    }
+}
+
+/// One-shot digest of `data` under `algo`.
+pub fn digest(algo: HashAlgo, data: &[u8]) -> [u8; 32] {
+    let mut hasher = Digester::new(algo);
+    hasher.update(data);
+    hasher.finalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blake3_matches_reference() {
+        assert_eq!(
+            digest(HashAlgo::Blake3, b"abc"),
+            *blake3::hash(b"abc").as_bytes()
+        );
+    }
+
+    #[test]
+    fn sha256_matches_known_vector() {
+        assert_eq!(
+            hex::encode(digest(HashAlgo::Sha256, b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn incremental_equals_one_shot() {
+        for algo in HashAlgo::ALL {
+            let mut hasher = Digester::new(algo);
+            hasher.update(b"hello ").update(b"world");
+            assert_eq!(hasher.algo(), algo);
+            assert_eq!(hasher.finalize(), digest(algo, b"hello world"));
+        }
+    }
+}
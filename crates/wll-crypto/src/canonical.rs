@@ -0,0 +1,377 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use serde_json::Value;
+use wll_types::{
+    CommitmentClass, CommitmentId, EvidenceAttachment, EvidenceBundle, ObjectId, TemporalAnchor,
+    WorldlineId,
+};
+use wll_types::commitment::Decision;
+
+/// Deterministic, length-prefixed binary encoder.
+///
+/// The canonical encoding is independent of any serialization framework:
+/// integers are fixed-width big-endian, variable-length data is prefixed
+/// with a `u64` length, optional values carry a presence byte, and enum
+/// variants carry a one-byte tag. Struct fields are written in a fixed,
+/// documented order by their [`CanonicalEncode`] implementation, so
+/// reordering Rust fields or upgrading `serde_json` never changes a hash.
+#[derive(Clone, Debug, Default)]
+pub struct CanonicalEncoder {
+    buf: Vec<u8>,
+}
+
+impl CanonicalEncoder {
+    /// Create an empty encoder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write a single byte.
+    pub fn put_u8(&mut self, value: u8) -> &mut Self {
+        self.buf.push(value);
+        self
+    }
+
+    /// Write a big-endian `u16`.
+    pub fn put_u16(&mut self, value: u16) -> &mut Self {
+        self.buf.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    /// Write a big-endian `u32`.
+    pub fn put_u32(&mut self, value: u32) -> &mut Self {
+        self.buf.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    /// Write a big-endian `u64`.
+    pub fn put_u64(&mut self, value: u64) -> &mut Self {
+        self.buf.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    /// Write a boolean as `0x00` / `0x01`.
+    pub fn put_bool(&mut self, value: bool) -> &mut Self {
+        self.put_u8(u8::from(value))
+    }
+
+    /// Write a fixed-size byte array without a length prefix.
+    pub fn put_fixed<const N: usize>(&mut self, bytes: &[u8; N]) -> &mut Self {
+        self.buf.extend_from_slice(bytes);
+        self
+    }
+
+    /// Write a length-prefixed byte slice.
+    pub fn put_bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        self.put_u64(bytes.len() as u64);
+        self.buf.extend_from_slice(bytes);
+        self
+    }
+
+    /// Write a length-prefixed UTF-8 string.
+    pub fn put_str(&mut self, value: &str) -> &mut Self {
+        self.put_bytes(value.as_bytes())
+    }
+
+    /// Write an optional value with a presence byte.
+    pub fn put_option<T: CanonicalEncode + ?Sized>(&mut self, value: Option<&T>) -> &mut Self {
+        match value {
+            None => self.put_u8(0),
+            Some(inner) => {
+                self.put_u8(1);
+                inner.encode_canonical(self);
+                self
+            }
+        }
+    }
+
+    /// Write a count-prefixed sequence.
+    pub fn put_seq<'a, T, I>(&mut self, items: I) -> &mut Self
+    where
+        T: CanonicalEncode + 'a,
+        I: IntoIterator<Item = &'a T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let items = items.into_iter();
+        self.put_u64(items.len() as u64);
+        for item in items {
+            item.encode_canonical(self);
+        }
+        self
+    }
+
+    /// Write any value implementing [`CanonicalEncode`].
+    pub fn put<T: CanonicalEncode + ?Sized>(&mut self, value: &T) -> &mut Self {
+        value.encode_canonical(self);
+        self
+    }
+
+    /// Consume the encoder and return the encoded bytes.
+    pub fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+/// Types with a deterministic binary encoding suitable for hashing.
+pub trait CanonicalEncode {
+    /// Append the canonical encoding of `self` to the encoder.
+    fn encode_canonical(&self, enc: &mut CanonicalEncoder);
+
+    /// Canonical encoding of `self` as a standalone byte vector.
+    fn to_canonical_bytes(&self) -> Vec<u8> {
+        let mut enc = CanonicalEncoder::new();
+        self.encode_canonical(&mut enc);
+        enc.finish()
+    }
+}
+
+impl CanonicalEncode for u64 {
+    fn encode_canonical(&self, enc: &mut CanonicalEncoder) {
+        enc.put_u64(*self);
+    }
+}
+
+impl CanonicalEncode for bool {
+    fn encode_canonical(&self, enc: &mut CanonicalEncoder) {
+        enc.put_bool(*self);
+    }
+}
+
+impl CanonicalEncode for str {
+    fn encode_canonical(&self, enc: &mut CanonicalEncoder) {
+        enc.put_str(self);
+    }
+}
+
+impl CanonicalEncode for String {
+    fn encode_canonical(&self, enc: &mut CanonicalEncoder) {
+        enc.put_str(self);
+    }
+}
+
+impl CanonicalEncode for [u8; 32] {
+    fn encode_canonical(&self, enc: &mut CanonicalEncoder) {
+        enc.put_fixed(self);
+    }
+}
+
+impl CanonicalEncode for ObjectId {
+    fn encode_canonical(&self, enc: &mut CanonicalEncoder) {
+        enc.put_u8(self.algo().tag()).put_fixed(self.as_bytes());
+    }
+}
+
+impl CanonicalEncode for WorldlineId {
+    fn encode_canonical(&self, enc: &mut CanonicalEncoder) {
+        enc.put_fixed(self.as_bytes());
+    }
+}
+
+impl CanonicalEncode for TemporalAnchor {
+    fn encode_canonical(&self, enc: &mut CanonicalEncoder) {
+        enc.put_u64(self.physical_ms)
+            .put_u32(self.logical)
+            .put_u16(self.node_id);
+    }
+}
+
+impl CanonicalEncode for CommitmentId {
+    fn encode_canonical(&self, enc: &mut CanonicalEncoder) {
+        enc.put_fixed(self.as_uuid().as_bytes());
+    }
+}
+
+impl CanonicalEncode for CommitmentClass {
+    fn encode_canonical(&self, enc: &mut CanonicalEncoder) {
+        match self {
+            Self::ReadOnly => enc.put_u8(0),
+            Self::ContentUpdate => enc.put_u8(1),
+            Self::StructuralChange => enc.put_u8(2),
+            Self::PolicyChange => enc.put_u8(3),
+            Self::IdentityOperation => enc.put_u8(4),
+            Self::Custom(name) => enc.put_u8(5).put_str(name),
+        };
+    }
+}
+
+impl CanonicalEncode for Decision {
+    fn encode_canonical(&self, enc: &mut CanonicalEncoder) {
+        match self {
+            Self::Accepted => enc.put_u8(0),
+            Self::Rejected { reason } => enc.put_u8(1).put_str(reason),
+            Self::Deferred { until, reason } => enc.put_u8(2).put(until).put_str(reason),
+        };
+    }
+}
+
+/// Attachments follow a `1` presence byte when there are any. A bundle
+/// without them has no presence byte at all, rather than a `0`: receipts
+/// recorded before attachments existed encode that way, and their hashes
+/// must still verify. The digest, which covers the attachments, keeps the
+/// two forms from standing for the same bundle.
+impl CanonicalEncode for EvidenceBundle {
+    fn encode_canonical(&self, enc: &mut CanonicalEncoder) {
+        enc.put_seq(&self.references);
+        if !self.attachments.is_empty() {
+            enc.put_u8(1).put_seq(&self.attachments);
+        }
+        enc.put_fixed(&self.digest);
+    }
+}
+
+impl CanonicalEncode for EvidenceAttachment {
+    fn encode_canonical(&self, enc: &mut CanonicalEncoder) {
+        enc.put(&self.object)
+            .put_str(&self.media_type)
+            .put_str(&self.name)
+            .put_u64(self.size);
+    }
+}
+
+/// JSON values are encoded structurally: object keys are sorted, numbers
+/// use their shortest `Display` form, and every node carries a type tag.
+impl CanonicalEncode for Value {
+    fn encode_canonical(&self, enc: &mut CanonicalEncoder) {
+        match self {
+            Value::Null => {
+                enc.put_u8(0);
+            }
+            Value::Bool(b) => {
+                enc.put_u8(1).put_bool(*b);
+            }
+            Value::Number(n) => {
+                enc.put_u8(2).put_str(&n.to_string());
+            }
+            Value::String(s) => {
+                enc.put_u8(3).put_str(s);
+            }
+            Value::Array(items) => {
+                enc.put_u8(4).put_seq(items);
+            }
+            Value::Object(map) => {
+                let mut entries: Vec<_> = map.iter().collect();
+                entries.sort_by(|a, b| a.0.cmp(b.0));
+                enc.put_u8(5).put_u64(entries.len() as u64);
+                for (key, value) in entries {
+                    enc.put_str(key).put(value);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+    use wll_types::identity::IdentityMaterial;
+
+    #[test]
+    fn integers_are_big_endian_fixed_width() {
+        let mut enc = CanonicalEncoder::new();
+        enc.put_u16(1).put_u32(2).put_u64(3);
+        assert_eq!(
+            enc.finish(),
+            vec![0, 1, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 3]
+        );
+    }
+
+    #[test]
+    fn strings_are_length_prefixed() {
+        let bytes = "ab".to_canonical_bytes();
+        assert_eq!(bytes, vec![0, 0, 0, 0, 0, 0, 0, 2, b'a', b'b']);
+    }
+
+    #[test]
+    fn adjacent_strings_do_not_collide() {
+        let mut a = CanonicalEncoder::new();
+        a.put_str("ab").put_str("c");
+        let mut b = CanonicalEncoder::new();
+        b.put_str("a").put_str("bc");
+        assert_ne!(a.finish(), b.finish());
+    }
+
+    #[test]
+    fn option_has_presence_byte() {
+        let mut none = CanonicalEncoder::new();
+        none.put_option::<[u8; 32]>(None);
+        assert_eq!(none.finish(), vec![0]);
+
+        let mut some = CanonicalEncoder::new();
+        some.put_option(Some(&[7u8; 32]));
+        let bytes = some.finish();
+        assert_eq!(bytes.len(), 33);
+        assert_eq!(bytes[0], 1);
+    }
+
+    #[test]
+    fn json_object_key_order_is_irrelevant() {
+        let a: Value = serde_json::from_str(r#"{"b": 1, "a": [true, null]}"#).unwrap();
+        let mut map = serde_json::Map::new();
+        map.insert("a".into(), serde_json::json!([true, null]));
+        map.insert("b".into(), serde_json::json!(1));
+        let b = Value::Object(map);
+        assert_eq!(a.to_canonical_bytes(), b.to_canonical_bytes());
+    }
+
+    #[test]
+    fn json_types_are_tagged() {
+        assert_ne!(
+            Value::String("1".into()).to_canonical_bytes(),
+            serde_json::json!(1).to_canonical_bytes()
+        );
+    }
+
+    #[test]
+    fn commitment_class_custom_includes_name() {
+        let a = CommitmentClass::Custom("deploy".into()).to_canonical_bytes();
+        let b = CommitmentClass::Custom("rollback".into()).to_canonical_bytes();
+        assert_ne!(a, b);
+        assert_eq!(CommitmentClass::ReadOnly.to_canonical_bytes(), vec![0]);
+    }
+
+    #[test]
+    fn worldline_encodes_raw_hash() {
+        let wid = WorldlineId::derive(&IdentityMaterial::GenesisHash([3; 32]));
+        assert_eq!(wid.to_canonical_bytes(), wid.as_bytes().to_vec());
+    }
+
+    #[test]
+    fn evidence_attachments_only_encoded_when_present() {
+        let references = vec!["issue://1".to_string()];
+        // Without attachments: the references, then the digest, as before
+        // attachments existed.
+        let plain = EvidenceBundle::from_references(references.clone());
+        let mut expected = CanonicalEncoder::new();
+        expected.put_seq(&references).put_fixed(&plain.digest);
+        assert_eq!(plain.to_canonical_bytes(), expected.finish());
+        let header = [0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 9];
+        let mut pinned = header.to_vec();
+        pinned.extend_from_slice(b"issue://1");
+        pinned.extend_from_slice(&plain.digest);
+        assert_eq!(plain.to_canonical_bytes(), pinned);
+
+        // With attachments: a `1` presence byte and the attachments between
+        // the references and the digest.
+        let attachment = EvidenceAttachment {
+            object: ObjectId::from_bytes(b"log"),
+            media_type: "text/plain".into(),
+            name: "test.log".into(),
+            size: 3,
+        };
+        let attached = EvidenceBundle::new(references.clone(), vec![attachment.clone()]);
+        let mut expected = CanonicalEncoder::new();
+        expected
+            .put_seq(&references)
+            .put_u8(1)
+            .put_seq(&[attachment])
+            .put_fixed(&attached.digest);
+        let bytes = attached.to_canonical_bytes();
+        assert_eq!(bytes, expected.finish());
+        assert_eq!(bytes[..25], pinned[..25]);
+        assert_eq!(bytes[25], 1);
+        assert_ne!(plain.to_canonical_bytes(), bytes);
+    }
+}
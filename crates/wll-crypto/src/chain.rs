@@ -1,3 +1,7 @@
+use alloc::vec::Vec;
+
+use wll_types::{Coded, ErrorCode};
+
 /// Trait for objects that participate in a hash chain.
 pub trait HasReceiptHash {
     /// The receipt's own hash.
@@ -85,8 +89,16 @@ pub enum ChainError {
     HashMismatch { index: usize },
 }
 
+impl Coded for ChainError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::LedgerIntegrity
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use alloc::{format, vec};
+
     use super::*;
 
     /// Test receipt for chain verification.
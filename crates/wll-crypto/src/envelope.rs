@@ -0,0 +1,302 @@
+//! Envelope encryption for confidential receipt fields.
+//!
+//! An [`Envelope`] holds data encrypted once with ChaCha20-Poly1305 under a
+//! random data key, and that data key sealed to each recipient: an X25519
+//! exchange between a fresh ephemeral key and the recipient's
+//! [`EnvelopePublicKey`], run through BLAKE3's key derivation, gives the key
+//! the data key is encrypted with. Any one recipient's
+//! [`EnvelopeSecretKey`] opens the envelope; anyone else learns only the
+//! recipients' public keys and the length of the data.
+//!
+//! [`Envelope::encode`] writes an envelope as a string starting with
+//! [`SEALED_PREFIX`], so it fits any string field of a receipt, and a hash
+//! taken over the receipt covers the ciphertext.
+
+use core::fmt;
+
+use base64::engine::general_purpose::STANDARD_NO_PAD;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use wll_types::{Coded, ErrorCode};
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+use zeroize::Zeroizing;
+
+/// Start of every encoded envelope.
+pub const SEALED_PREFIX: &str = "wll-sealed:v1:";
+
+/// Context of the key derivation that turns an X25519 shared secret into
+/// the key sealing a data key.
+const KEY_WRAP_CONTEXT: &str = "wll-envelope-v1 key wrap";
+
+const FORMAT_VERSION: u8 = 1;
+const NONCE_LEN: usize = 12;
+/// A data key encrypted with its 16-byte tag.
+const WRAPPED_LEN: usize = 48;
+const RECIPIENT_LEN: usize = 32 + 32 + WRAPPED_LEN;
+
+/// X25519 secret key of an envelope recipient.
+#[derive(Clone)]
+pub struct EnvelopeSecretKey(StaticSecret);
+
+/// X25519 public key envelopes are sealed to.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct EnvelopePublicKey([u8; 32]);
+
+/// A data key sealed to one recipient.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SealedKey {
+    pub recipient: EnvelopePublicKey,
+    /// The ephemeral X25519 public key of the exchange.
+    pub ephemeral: [u8; 32],
+    /// The data key, encrypted under the derived key.
+    pub wrapped: [u8; WRAPPED_LEN],
+}
+
+/// Data encrypted for a set of recipients.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Envelope {
+    pub recipients: Vec<SealedKey>,
+    pub nonce: [u8; NONCE_LEN],
+    /// The data with its authentication tag.
+    pub ciphertext: Vec<u8>,
+}
+
+impl EnvelopeSecretKey {
+    /// Generate a new random key.
+    pub fn generate() -> Self {
+        Self(StaticSecret::random_from_rng(rand::thread_rng()))
+    }
+
+    /// Create from a raw 32-byte secret.
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(StaticSecret::from(bytes))
+    }
+
+    /// Raw secret key bytes.
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.to_bytes()
+    }
+
+    /// The public key envelopes for this key are sealed to.
+    pub fn public_key(&self) -> EnvelopePublicKey {
+        EnvelopePublicKey(PublicKey::from(&self.0).to_bytes())
+    }
+}
+
+impl EnvelopePublicKey {
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.0)
+    }
+
+    pub fn from_hex(hex: &str) -> Result<Self, EnvelopeError> {
+        let mut bytes = [0u8; 32];
+        hex::decode_to_slice(hex, &mut bytes).map_err(|_| EnvelopeError::InvalidKey)?;
+        Ok(Self(bytes))
+    }
+}
+
+impl Envelope {
+    /// Encrypt `plaintext` so that each of `recipients` can open it.
+    pub fn seal(plaintext: &[u8], recipients: &[EnvelopePublicKey]) -> Result<Self, EnvelopeError> {
+        if recipients.is_empty() || recipients.len() > usize::from(u8::MAX) {
+            return Err(EnvelopeError::Malformed(format!(
+                "an envelope needs 1 to 255 recipients, not {}",
+                recipients.len()
+            )));
+        }
+        let mut rng = rand::thread_rng();
+        let mut data_key = Zeroizing::new([0u8; 32]);
+        rng.fill_bytes(data_key.as_mut());
+        let mut nonce = [0u8; NONCE_LEN];
+        rng.fill_bytes(&mut nonce);
+
+        let ciphertext = ChaCha20Poly1305::new(Key::from_slice(data_key.as_ref()))
+            .encrypt(Nonce::from_slice(&nonce), plaintext)
+            .map_err(|_| EnvelopeError::Malformed("encryption failed".into()))?;
+        let recipients = recipients
+            .iter()
+            .map(|recipient| {
+                let secret = EphemeralSecret::random_from_rng(&mut rng);
+                let ephemeral = PublicKey::from(&secret).to_bytes();
+                let shared = secret.diffie_hellman(&PublicKey::from(recipient.0));
+                let wrapped = key_wrap(shared.as_bytes(), &ephemeral, recipient)
+                    .encrypt(Nonce::from_slice(&[0; NONCE_LEN]), data_key.as_slice())
+                    .map_err(|_| EnvelopeError::Malformed("key wrapping failed".into()))?;
+                Ok(SealedKey {
+                    recipient: *recipient,
+                    ephemeral,
+                    wrapped: wrapped.try_into().expect("a wrapped key is 48 bytes"),
+                })
+            })
+            .collect::<Result<_, EnvelopeError>>()?;
+        Ok(Self { recipients, nonce, ciphertext })
+    }
+
+    /// Decrypt with `key`, which must be one of the recipients'.
+    pub fn open(&self, key: &EnvelopeSecretKey) -> Result<Vec<u8>, EnvelopeError> {
+        let public = key.public_key();
+        let sealed = self
+            .recipients
+            .iter()
+            .find(|sealed| sealed.recipient == public)
+            .ok_or(EnvelopeError::NotARecipient)?;
+        let shared = key.0.diffie_hellman(&PublicKey::from(sealed.ephemeral));
+        let data_key = Zeroizing::new(
+            key_wrap(shared.as_bytes(), &sealed.ephemeral, &public)
+                .decrypt(Nonce::from_slice(&[0; NONCE_LEN]), sealed.wrapped.as_slice())
+                .map_err(|_| EnvelopeError::Malformed("the sealed data key was altered".into()))?,
+        );
+        ChaCha20Poly1305::new(Key::from_slice(&data_key))
+            .decrypt(Nonce::from_slice(&self.nonce), self.ciphertext.as_slice())
+            .map_err(|_| EnvelopeError::Malformed("the ciphertext was altered".into()))
+    }
+
+    /// Whether `text` is an encoded envelope.
+    pub fn is_sealed(text: &str) -> bool {
+        text.starts_with(SEALED_PREFIX)
+    }
+
+    /// `SEALED_PREFIX` followed by the envelope's bytes in base64: a
+    /// version byte, the nonce, the recipient count, each recipient's
+    /// public key, ephemeral key, and wrapped data key, then the
+    /// ciphertext.
+    pub fn encode(&self) -> String {
+        let mut bytes = Vec::with_capacity(
+            2 + NONCE_LEN + self.recipients.len() * RECIPIENT_LEN + self.ciphertext.len(),
+        );
+        bytes.push(FORMAT_VERSION);
+        bytes.extend_from_slice(&self.nonce);
+        bytes.push(self.recipients.len() as u8);
+        for sealed in &self.recipients {
+            bytes.extend_from_slice(&sealed.recipient.0);
+            bytes.extend_from_slice(&sealed.ephemeral);
+            bytes.extend_from_slice(&sealed.wrapped);
+        }
+        bytes.extend_from_slice(&self.ciphertext);
+        format!("{SEALED_PREFIX}{}", STANDARD_NO_PAD.encode(bytes))
+    }
+
+    /// Parse the output of [`encode`](Self::encode).
+    pub fn decode(text: &str) -> Result<Self, EnvelopeError> {
+        let malformed = |what: &str| EnvelopeError::Malformed(what.into());
+        let body = text.strip_prefix(SEALED_PREFIX).ok_or_else(|| malformed("not sealed"))?;
+        let bytes = STANDARD_NO_PAD.decode(body).map_err(|_| malformed("invalid base64"))?;
+        let (&version, rest) = bytes.split_first().ok_or_else(|| malformed("empty"))?;
+        if version != FORMAT_VERSION {
+            return Err(EnvelopeError::Malformed(format!("unknown version {version}")));
+        }
+        if rest.len() < NONCE_LEN + 1 {
+            return Err(malformed("truncated header"));
+        }
+        let (nonce, rest) = rest.split_at(NONCE_LEN);
+        let (count, rest) = (usize::from(rest[0]), &rest[1..]);
+        if rest.len() < count * RECIPIENT_LEN {
+            return Err(malformed("truncated recipients"));
+        }
+        let (table, ciphertext) = rest.split_at(count * RECIPIENT_LEN);
+        let recipients = table
+            .chunks_exact(RECIPIENT_LEN)
+            .map(|entry| SealedKey {
+                recipient: EnvelopePublicKey(entry[..32].try_into().expect("32 bytes")),
+                ephemeral: entry[32..64].try_into().expect("32 bytes"),
+                wrapped: entry[64..].try_into().expect("48 bytes"),
+            })
+            .collect();
+        Ok(Self {
+            recipients,
+            nonce: nonce.try_into().expect("12 bytes"),
+            ciphertext: ciphertext.to_vec(),
+        })
+    }
+}
+
+/// The cipher sealing a data key for `recipient`.
+fn key_wrap(
+    shared: &[u8; 32],
+    ephemeral: &[u8; 32],
+    recipient: &EnvelopePublicKey,
+) -> ChaCha20Poly1305 {
+    let mut material = Zeroizing::new([0u8; 96]);
+    material[..32].copy_from_slice(shared);
+    material[32..64].copy_from_slice(ephemeral);
+    material[64..].copy_from_slice(&recipient.0);
+    let key = Zeroizing::new(blake3::derive_key(KEY_WRAP_CONTEXT, material.as_ref()));
+    ChaCha20Poly1305::new(Key::from_slice(key.as_ref()))
+}
+
+impl fmt::Debug for EnvelopeSecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "EnvelopeSecretKey(<redacted>)")
+    }
+}
+
+impl fmt::Debug for EnvelopePublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "EnvelopePublicKey({})", self.to_hex())
+    }
+}
+
+/// Errors from sealing and opening envelopes.
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum EnvelopeError {
+    #[error("invalid sealed value: {0}")]
+    Malformed(String),
+    #[error("no key held opens this sealed value")]
+    NotARecipient,
+    #[error("invalid envelope key")]
+    InvalidKey,
+}
+
+impl Coded for EnvelopeError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::Malformed(_) => ErrorCode::EnvelopeInvalid,
+            Self::NotARecipient => ErrorCode::NotARecipient,
+            Self::InvalidKey => ErrorCode::InvalidKey,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_recipient_opens_and_nobody_else() {
+        let (alice, bob) = (EnvelopeSecretKey::generate(), EnvelopeSecretKey::generate());
+        let envelope =
+            Envelope::seal(b"rotate prod keys", &[alice.public_key(), bob.public_key()]).unwrap();
+        assert_eq!(envelope.open(&alice).unwrap(), b"rotate prod keys");
+        assert_eq!(envelope.open(&bob).unwrap(), b"rotate prod keys");
+        let eve = EnvelopeSecretKey::generate();
+        assert_eq!(envelope.open(&eve), Err(EnvelopeError::NotARecipient));
+        assert!(Envelope::seal(b"x", &[]).is_err());
+    }
+
+    #[test]
+    fn encoding_round_trips_and_detects_tampering() {
+        let key = EnvelopeSecretKey::from_bytes([7; 32]);
+        let encoded = Envelope::seal(b"secret", &[key.public_key()]).unwrap().encode();
+        assert!(Envelope::is_sealed(&encoded));
+        let decoded = Envelope::decode(&encoded).unwrap();
+        assert_eq!(decoded.open(&key).unwrap(), b"secret");
+
+        let mut tampered = decoded.clone();
+        tampered.ciphertext[0] ^= 1;
+        let reopened = Envelope::decode(&tampered.encode()).unwrap().open(&key);
+        assert!(matches!(reopened, Err(EnvelopeError::Malformed(_))));
+        assert!(Envelope::decode("wll-sealed:v1:AQ").is_err());
+        assert!(Envelope::decode("plain text").is_err());
+    }
+}
@@ -1,11 +1,15 @@
-use wll_types::ObjectId;
+use alloc::string::{String, ToString};
 
-/// Domain-separated BLAKE3 content hasher.
+use wll_types::{Coded, ErrorCode, ObjectId};
+
+use crate::algo::{Digester, HashAlgo};
+
+/// Domain-separated content hasher.
 ///
 /// Each hasher carries a domain tag (e.g., `"wll-blob-v1"`, `"wll-receipt-v1"`)
 /// that is prepended to every hash computation. This prevents cross-type hash
 /// collisions: a blob and a receipt with identical bytes will produce different
-/// hashes.
+/// hashes. Hashing uses BLAKE3 unless another [`HashAlgo`] is requested.
 pub struct ContentHasher {
     domain: &'static str,
 }
@@ -35,11 +39,17 @@ impl ContentHasher {
 
     /// Hash raw bytes with domain separation.
     pub fn hash(&self, data: &[u8]) -> ObjectId {
-        let mut hasher = blake3::Hasher::new();
-        hasher.update(self.domain.as_bytes());
-        hasher.update(b":");
-        hasher.update(data);
-        ObjectId::from_hash(*hasher.finalize().as_bytes())
+        self.hash_with(HashAlgo::Blake3, data)
+    }
+
+    /// Hash raw bytes with domain separation under a specific algorithm.
+    pub fn hash_with(&self, algo: HashAlgo, data: &[u8]) -> ObjectId {
+        let mut hasher = Digester::new(algo);
+        hasher
+            .update(self.domain.as_bytes())
+            .update(b":")
+            .update(data);
+        ObjectId::from_algo_hash(algo, hasher.finalize())
     }
 
     /// Hash a serializable value as JSON with domain separation.
@@ -49,8 +59,10 @@ impl ContentHasher {
     }
 
     /// Verify that data produces the expected object ID.
+    ///
+    /// The expected ID's algorithm tag selects the hash function.
     pub fn verify(&self, data: &[u8], expected: &ObjectId) -> bool {
-        self.hash(data) == *expected
+        self.hash_with(expected.algo(), data) == *expected
     }
 
     /// Raw BLAKE3 hash without domain separation (for low-level use).
@@ -71,6 +83,12 @@ pub enum HasherError {
     Serialization(String),
 }
 
+impl Coded for HasherError {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::Serialization
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -121,6 +139,16 @@ mod tests {
         assert_ne!(id, ContentHasher::BLOB.hash(b"data"));
     }
 
+    #[test]
+    fn hash_with_tags_algorithm() {
+        let blake = ContentHasher::BLOB.hash(b"data");
+        let sha = ContentHasher::BLOB.hash_with(HashAlgo::Sha256, b"data");
+        assert_eq!(blake.algo(), HashAlgo::Blake3);
+        assert_eq!(sha.algo(), HashAlgo::Sha256);
+        assert_ne!(blake.as_bytes(), sha.as_bytes());
+        assert!(ContentHasher::BLOB.verify(b"data", &sha));
+    }
+
     #[test]
     fn raw_hash_no_domain() {
         let h1 = ContentHasher::raw_hash(b"test");
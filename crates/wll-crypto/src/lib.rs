@@ -1,16 +1,47 @@
 //! Cryptographic primitives for the WorldLine Ledger.
 //!
-//! Provides domain-separated BLAKE3 hashing, Ed25519 signing/verification,
-//! binary Merkle trees with inclusion proofs, and hash chain verification.
+//! Provides domain-separated hashing (BLAKE3 by default, SHA-256 via
+//! [`HashAlgo`]), Ed25519 signing/verification,
+//! binary Merkle trees with inclusion proofs, hash chain verification, and a
+//! deterministic canonical encoding for hash inputs.
+//!
+//! Secret material is zeroized when dropped and never printed by `Debug`:
+//! [`SigningKey`] for keys and [`Secret`] for credentials, which also
+//! compares in constant time.
+//!
+//! With `std`, [`envelope`] encrypts data for a set of X25519 recipients,
+//! for receipt fields not every replica may read.
 //!
 //! All crypto operations wrap established libraries — no custom cryptography.
+//!
+//! # Features
+//!
+//! - `std` (default) — enables [`SigningKey::generate`] from the thread RNG,
+//!   and envelope encryption.
+//!   Without it the crate is `no_std` + `alloc`; keys are loaded with
+//!   [`SigningKey::from_bytes`] and everything else (hashing, signing,
+//!   Merkle proofs, canonical encoding) is available unchanged.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
 
+pub mod algo;
+pub mod canonical;
 pub mod chain;
+#[cfg(feature = "std")]
+pub mod envelope;
 pub mod hasher;
 pub mod merkle;
+pub mod secret;
 pub mod signer;
 
+pub use algo::{digest, Digester, HashAlgo};
+pub use canonical::{CanonicalEncode, CanonicalEncoder};
 pub use chain::{HasReceiptHash, HashChainVerifier};
+#[cfg(feature = "std")]
+pub use envelope::{Envelope, EnvelopeError, EnvelopePublicKey, EnvelopeSecretKey};
 pub use hasher::ContentHasher;
 pub use merkle::{MerkleProof, MerkleTree, Side};
+pub use secret::Secret;
 pub use signer::{Signature, SigningKey, VerifyingKey};
@@ -1,3 +1,6 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
 use serde::{Deserialize, Serialize};
 use wll_types::ObjectId;
 
@@ -40,7 +43,7 @@ impl MerkleTree {
         let mut current = leaves.clone();
 
         while current.len() > 1 {
-            let mut next = Vec::with_capacity((current.len() + 1) / 2);
+            let mut next = Vec::with_capacity(current.len().div_ceil(2));
             for pair in current.chunks(2) {
                 let hash = if pair.len() == 2 {
                     hash_pair(&pair[0], &pair[1])
@@ -173,9 +176,9 @@ mod tests {
         let leaves: Vec<ObjectId> = (0..7).map(leaf).collect();
         let tree = MerkleTree::from_leaves(leaves.clone());
 
-        for i in 0..leaves.len() {
+        for (i, expected) in leaves.iter().enumerate() {
             let proof = tree.proof(i).expect("proof should exist");
-            assert_eq!(proof.leaf, leaves[i]);
+            assert_eq!(proof.leaf, *expected);
             assert!(proof.verify(), "proof for leaf {i} should verify");
         }
     }
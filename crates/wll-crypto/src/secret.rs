@@ -0,0 +1,83 @@
+//! Credentials held in memory.
+
+use alloc::string::String;
+use core::fmt;
+
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// A credential such as a bearer token or webhook key.
+///
+/// Equality runs in constant time for inputs of the same length, `Debug`
+/// prints `<redacted>`, and the bytes are zeroized on drop. Serialization
+/// writes the plain value, so config files round-trip.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// The secret itself, for sending it where it is needed.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl PartialEq for Secret {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_bytes().ct_eq(other.0.as_bytes()).into()
+    }
+}
+
+impl Eq for Secret {}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Self(value.into())
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret(<redacted>)")
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for Secret {}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+
+    use super::*;
+
+    #[test]
+    fn secrets_compare_by_value_and_never_print() {
+        let token = Secret::new("s3cret-token");
+        assert_eq!(token, Secret::from("s3cret-token"));
+        assert_ne!(token, Secret::from("s3cret-tokeN"));
+        assert_ne!(token, Secret::from("s3cret"));
+        assert_eq!(format!("{token:?}"), "Secret(<redacted>)");
+        assert_eq!(serde_json::to_string(&token).unwrap(), r#""s3cret-token""#);
+    }
+}
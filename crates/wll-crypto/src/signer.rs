@@ -1,6 +1,10 @@
+use core::fmt;
+
 use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, ZeroizeOnDrop};
 use wll_types::WorldlineId;
 use wll_types::identity::IdentityMaterial;
+use wll_types::{Coded, ErrorCode};
 
 /// Ed25519 signing key (private).
 pub struct SigningKey(ed25519_dalek::SigningKey);
@@ -15,14 +19,21 @@ pub struct Signature(#[serde(with = "signature_serde")] ed25519_dalek::Signature
 
 impl SigningKey {
     /// Generate a new random signing key.
+    #[cfg(feature = "std")]
     pub fn generate() -> Self {
         let mut csprng = rand::thread_rng();
         Self(ed25519_dalek::SigningKey::generate(&mut csprng))
     }
 
     /// Create from raw 32-byte secret.
-    pub fn from_bytes(bytes: [u8; 32]) -> Self {
-        Self(ed25519_dalek::SigningKey::from_bytes(&bytes))
+    ///
+    /// `bytes` is passed by copy, so only that copy is zeroized here; a
+    /// caller holding the secret elsewhere must clear it, e.g. by keeping it
+    /// in [`zeroize::Zeroizing`].
+    pub fn from_bytes(mut bytes: [u8; 32]) -> Self {
+        let key = ed25519_dalek::SigningKey::from_bytes(&bytes);
+        bytes.zeroize();
+        Self(key)
     }
 
     /// The corresponding public verifying key.
@@ -69,20 +80,35 @@ impl VerifyingKey {
     }
 }
 
-impl std::fmt::Debug for SigningKey {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl Signature {
+    /// Create from raw 64-byte signature bytes.
+    pub fn from_bytes(bytes: [u8; 64]) -> Self {
+        Self(ed25519_dalek::Signature::from_bytes(&bytes))
+    }
+
+    /// Raw signature bytes.
+    pub fn to_bytes(&self) -> [u8; 64] {
+        self.0.to_bytes()
+    }
+}
+
+// The inner key zeroizes itself on drop.
+impl ZeroizeOnDrop for SigningKey {}
+
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "SigningKey(<redacted>)")
     }
 }
 
-impl std::fmt::Debug for VerifyingKey {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl fmt::Debug for VerifyingKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "VerifyingKey({})", hex::encode(self.0.to_bytes()))
     }
 }
 
-impl std::fmt::Debug for Signature {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl fmt::Debug for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
             "Signature({}...)",
@@ -100,7 +126,18 @@ pub enum SignatureError {
     InvalidKey,
 }
 
+impl Coded for SignatureError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::InvalidSignature => ErrorCode::InvalidSignature,
+            Self::InvalidKey => ErrorCode::InvalidKey,
+        }
+    }
+}
+
 mod signature_serde {
+    use alloc::vec::Vec;
+
     use serde::{self, Deserialize, Deserializer, Serializer};
 
     pub fn serialize<S>(sig: &ed25519_dalek::Signature, serializer: S) -> Result<S::Ok, S::Error>
@@ -124,11 +161,13 @@ mod signature_serde {
 
 #[cfg(test)]
 mod tests {
+    use alloc::format;
+
     use super::*;
 
     #[test]
     fn sign_and_verify() {
-        let sk = SigningKey::generate();
+        let sk = SigningKey::from_bytes([7; 32]);
         let vk = sk.verifying_key();
         let message = b"hello world";
         let sig = sk.sign(message);
@@ -137,7 +176,7 @@ mod tests {
 
     #[test]
     fn verify_fails_on_wrong_message() {
-        let sk = SigningKey::generate();
+        let sk = SigningKey::from_bytes([7; 32]);
         let vk = sk.verifying_key();
         let sig = sk.sign(b"correct message");
         assert!(vk.verify(b"wrong message", &sig).is_err());
@@ -145,15 +184,15 @@ mod tests {
 
     #[test]
     fn verify_fails_with_wrong_key() {
-        let sk1 = SigningKey::generate();
-        let sk2 = SigningKey::generate();
+        let sk1 = SigningKey::from_bytes([1; 32]);
+        let sk2 = SigningKey::from_bytes([2; 32]);
         let sig = sk1.sign(b"message");
         assert!(sk2.verifying_key().verify(b"message", &sig).is_err());
     }
 
     #[test]
     fn worldline_id_from_key() {
-        let sk = SigningKey::generate();
+        let sk = SigningKey::from_bytes([7; 32]);
         let vk = sk.verifying_key();
         let wid1 = vk.to_worldline_id();
         let wid2 = vk.to_worldline_id();
@@ -162,13 +201,14 @@ mod tests {
 
     #[test]
     fn different_keys_different_worldlines() {
-        let sk1 = SigningKey::generate();
-        let sk2 = SigningKey::generate();
+        let sk1 = SigningKey::from_bytes([1; 32]);
+        let sk2 = SigningKey::from_bytes([2; 32]);
         let wid1 = sk1.verifying_key().to_worldline_id();
         let wid2 = sk2.verifying_key().to_worldline_id();
         assert_ne!(wid1, wid2);
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn from_bytes_roundtrip() {
         let sk = SigningKey::generate();
@@ -179,16 +219,22 @@ mod tests {
 
     #[test]
     fn signature_serde_roundtrip() {
-        let sk = SigningKey::generate();
+        let sk = SigningKey::from_bytes([7; 32]);
         let sig = sk.sign(b"test");
         let json = serde_json::to_string(&sig).unwrap();
         let parsed: Signature = serde_json::from_str(&json).unwrap();
         assert_eq!(sig, parsed);
     }
 
+    #[test]
+    fn signature_bytes_roundtrip() {
+        let sig = SigningKey::from_bytes([7; 32]).sign(b"test");
+        assert_eq!(Signature::from_bytes(sig.to_bytes()), sig);
+    }
+
     #[test]
     fn debug_redacts_signing_key() {
-        let sk = SigningKey::generate();
+        let sk = SigningKey::from_bytes([7; 32]);
         let debug = format!("{sk:?}");
         assert!(debug.contains("redacted"));
     }
@@ -0,0 +1,76 @@
+//! Breadth-first traversal over generated provenance graphs.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use wll_dag::{DagNode, DagNodeMetadata, ParentRef, ProvenanceDag};
+use wll_types::{IdentityMaterial, ObjectId, ReceiptKind, TemporalAnchor, WorldlineId};
+
+const WORLDLINES: usize = 16;
+/// One node in this many also depends on a node of another worldline.
+const CROSS_EVERY: usize = 8;
+
+fn id(index: usize) -> ObjectId {
+    let mut hash = [0; 32];
+    hash[..8].copy_from_slice(&(index as u64 + 1).to_le_bytes());
+    ObjectId::from_hash(hash)
+}
+
+/// `nodes` receipts spread round-robin over [`WORLDLINES`] streams. Each
+/// node follows the previous one in its stream, and every
+/// [`CROSS_EVERY`]th also cites a pseudo-random earlier node.
+fn generate(nodes: usize) -> ProvenanceDag {
+    let worldlines: Vec<WorldlineId> = (0..WORLDLINES)
+        .map(|seed| WorldlineId::derive(&IdentityMaterial::GenesisHash([seed as u8; 32])))
+        .collect();
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    let mut dag = ProvenanceDag::new();
+    for index in 0..nodes {
+        let mut parents = Vec::new();
+        if index >= WORLDLINES {
+            parents.push(ParentRef::sequential(id(index - WORLDLINES)));
+        }
+        if index > WORLDLINES && index % CROSS_EVERY == 0 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let cited = (state % index as u64) as usize;
+            if cited % WORLDLINES != index % WORLDLINES {
+                parents.push(ParentRef::cross_worldline(id(cited)));
+            }
+        }
+        let seq = (index / WORLDLINES) as u64;
+        dag.add_node(DagNode {
+            id: id(index),
+            worldline: worldlines[index % WORLDLINES].clone(),
+            seq,
+            kind: if seq % 2 == 0 {
+                ReceiptKind::Commitment
+            } else {
+                ReceiptKind::Outcome
+            },
+            timestamp: TemporalAnchor::new(1_000 + index as u64, 0, 0),
+            parents,
+            metadata: DagNodeMetadata::empty(),
+        })
+        .unwrap();
+    }
+    dag
+}
+
+fn bfs(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dag_bfs");
+    group.sample_size(10);
+    for nodes in [10_000, 100_000, 1_000_000] {
+        let dag = generate(nodes);
+        group.throughput(Throughput::Elements(nodes as u64));
+        group.bench_with_input(BenchmarkId::new("ancestors", nodes), &dag, |b, dag| {
+            b.iter(|| dag.ancestors(&id(nodes - 1), usize::MAX).len())
+        });
+        group.bench_with_input(BenchmarkId::new("descendants", nodes), &dag, |b, dag| {
+            b.iter(|| dag.descendants(&id(0), usize::MAX).len())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bfs);
+criterion_main!(benches);
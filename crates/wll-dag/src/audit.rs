@@ -2,14 +2,21 @@
 //!
 //! An [`AuditTrail`] captures the full causal chain leading to a specific
 //! commitment, while an [`ImpactReport`] captures everything *downstream*
-//! of a node (what was affected by it).
+//! of a node (what was affected by it). A trail handed to someone outside
+//! the repository can be sealed first ([`AuditTrail::seal`]): signed over
+//! its canonical encoding, so any change to it is detected.
 
 use serde::{Deserialize, Serialize};
 
+use wll_crypto::{CanonicalEncode, CanonicalEncoder, Signature, SigningKey, VerifyingKey};
 use wll_types::{ObjectId, TemporalAnchor, WorldlineId};
 
+use crate::error::{DagError, DagResult};
 use crate::node::CausalRelation;
 
+/// Domain tag opening the bytes an audit trail seal signs.
+const SEAL_DOMAIN: &str = "wll-audit-trail-v1";
+
 /// A complete audit trail for a specific commitment.
 ///
 /// The chain is ordered from the commitment itself backward through its
@@ -49,6 +56,77 @@ impl AuditTrail {
         wls.dedup();
         wls
     }
+
+    /// Sign the trail with `key`, making it tamper-evident.
+    pub fn seal(&self, key: &SigningKey) -> SealedAuditTrail {
+        let signature = key.sign(&seal_message(self));
+        SealedAuditTrail {
+            trail: self.clone(),
+            signer: key.verifying_key().as_bytes(),
+            signature,
+        }
+    }
+}
+
+impl CanonicalEncode for AuditTrail {
+    fn encode_canonical(&self, enc: &mut CanonicalEncoder) {
+        enc.put(&self.commitment).put_seq(&self.chain);
+    }
+}
+
+impl CanonicalEncode for AuditEntry {
+    fn encode_canonical(&self, enc: &mut CanonicalEncoder) {
+        let relation = match self.relation {
+            CausalRelation::Sequential => 0,
+            CausalRelation::CommitmentToOutcome => 1,
+            CausalRelation::EvidenceLink => 2,
+            CausalRelation::CrossWorldline => 3,
+            CausalRelation::Merge => 4,
+            CausalRelation::SnapshotAnchor => 5,
+        };
+        enc.put(&self.node)
+            .put_u8(relation)
+            .put(&self.worldline)
+            .put(&self.timestamp)
+            .put_str(&self.summary);
+    }
+}
+
+/// Bytes an audit trail seal signs.
+fn seal_message(trail: &AuditTrail) -> Vec<u8> {
+    let mut enc = CanonicalEncoder::new();
+    enc.put_str(SEAL_DOMAIN).put(trail);
+    enc.finish()
+}
+
+/// An audit trail with a signature over its canonical encoding.
+///
+/// Serialize it (JSON, say) to hand it over; the recipient checks it with
+/// [`verify`](Self::verify) against the key they expect it from.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SealedAuditTrail {
+    pub trail: AuditTrail,
+    /// Public key of the signer.
+    pub signer: [u8; 32],
+    pub signature: Signature,
+}
+
+impl SealedAuditTrail {
+    /// Check that `key` sealed this trail and that it has not changed
+    /// since, returning the trail.
+    pub fn verify(&self, key: &VerifyingKey) -> DagResult<&AuditTrail> {
+        if key.as_bytes() != self.signer {
+            return Err(DagError::InvalidSeal("sealed by another key".into()));
+        }
+        key.verify(&seal_message(&self.trail), &self.signature)
+            .map_err(|e| DagError::InvalidSeal(e.to_string()))?;
+        Ok(&self.trail)
+    }
+
+    /// The key the trail claims to be sealed with.
+    pub fn signer_key(&self) -> DagResult<VerifyingKey> {
+        VerifyingKey::from_bytes(self.signer).map_err(|e| DagError::InvalidSeal(e.to_string()))
+    }
 }
 
 /// A single entry in an audit trail.
@@ -69,7 +147,9 @@ pub struct AuditEntry {
 /// Impact analysis report for a node: what was affected downstream.
 ///
 /// Given an origin node, the impact report describes how many worldlines,
-/// receipts, and causal paths were affected by it.
+/// receipts, and causal paths were affected by it, weighted by the
+/// commitment class and reversibility recorded in node metadata. An
+/// outcome without a class takes the class of its commitment.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ImpactReport {
     /// The origin node being analyzed.
@@ -80,8 +160,20 @@ pub struct ImpactReport {
     pub downstream_receipts: usize,
     /// Maximum depth of the cascade from the origin.
     pub cascade_depth: usize,
-    /// Critical causal paths from origin to leaf descendants.
+    /// Critical causal paths from origin to leaf descendants, those through
+    /// the least reversible, then highest-risk, node first.
     pub critical_paths: Vec<Vec<ObjectId>>,
+    /// Sum over downstream receipts of `(risk level + 1)` times 1, 2 or 3
+    /// for reversible (or unknown), partially reversible and irreversible
+    /// effects.
+    #[serde(default)]
+    pub risk_score: u64,
+    /// Highest commitment class risk level downstream.
+    #[serde(default)]
+    pub max_risk_level: u8,
+    /// Downstream receipts with irreversible effects, highest risk first.
+    #[serde(default)]
+    pub irreversible: Vec<ObjectId>,
 }
 
 impl ImpactReport {
@@ -93,6 +185,9 @@ impl ImpactReport {
             downstream_receipts: 0,
             cascade_depth: 0,
             critical_paths: Vec::new(),
+            risk_score: 0,
+            max_risk_level: 0,
+            irreversible: Vec::new(),
         }
     }
 
@@ -141,6 +236,33 @@ mod tests {
         assert_eq!(worldlines.len(), 1);
     }
 
+    #[test]
+    fn sealed_trail_verifies_until_changed() {
+        let mut trail = AuditTrail::new(ObjectId::from_hash([1; 32]));
+        trail.chain.push(AuditEntry {
+            node: ObjectId::from_hash([1; 32]),
+            relation: CausalRelation::Sequential,
+            worldline: test_worldline(1),
+            timestamp: TemporalAnchor::new(1000, 0, 0),
+            summary: "deploy".into(),
+        });
+        let key = SigningKey::from_bytes([7; 32]);
+        let sealed = trail.seal(&key);
+
+        let json = serde_json::to_string(&sealed).unwrap();
+        let received: SealedAuditTrail = serde_json::from_str(&json).unwrap();
+        assert_eq!(received.verify(&received.signer_key().unwrap()).unwrap(), &trail);
+
+        let mut tampered = received.clone();
+        tampered.trail.chain[0].summary = "nothing happened".into();
+        assert!(matches!(
+            tampered.verify(&key.verifying_key()),
+            Err(DagError::InvalidSeal(_))
+        ));
+        let other = SigningKey::from_bytes([8; 32]).verifying_key();
+        assert!(received.verify(&other).is_err());
+    }
+
     #[test]
     fn empty_impact_report() {
         let report = ImpactReport::new(ObjectId::from_hash([1; 32]));
@@ -1,26 +1,44 @@
 //! The core provenance DAG structure and traversal algorithms.
 //!
-//! [`ProvenanceDag`] is the main data structure. It stores nodes in a
-//! [`HashMap`] and maintains a forward-edge index (`children`) for efficient
-//! descendant queries. Root nodes (those with no parents) are tracked
-//! separately for fast enumeration.
+//! [`ProvenanceDag`] is the main data structure. Nodes live in an arena, a
+//! `Vec` in insertion order, and are addressed internally by `u32` index
+//! with one `ObjectId -> index` map for lookups. Edges are indexes too: each
+//! node's parents are a contiguous run of one shared vector, and its
+//! children a list threaded through another, so neither direction allocates
+//! per node. Worldline ids are interned once per worldline, which also gives
+//! each worldline an index of its nodes. Root nodes (those with no parents)
+//! are tracked separately for fast enumeration.
 //!
 //! # Invariants
 //!
 //! - The graph is acyclic (append-only + temporal ordering).
 //! - Every parent reference resolves to an existing node.
 //! - Node IDs are unique within the DAG.
+//! - A node's parents come before it in the arena.
 
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet, VecDeque};
 
-use serde::{Deserialize, Serialize};
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
 use tracing::debug;
 
-use wll_types::{ObjectId, TemporalAnchor, WorldlineId};
+use wll_types::{ObjectId, ReceiptKind, Reversibility, TemporalAnchor, WorldlineId};
 
 use crate::audit::{AuditEntry, AuditTrail, ImpactReport};
 use crate::error::{DagError, DagResult};
-use crate::node::{CausalRelation, DagNode};
+use crate::graph::CommitGraph;
+use crate::node::{CausalRelation, DagNode, DagNodeMetadata, ParentRef};
+
+/// Position of a node in the arena.
+pub(crate) type NodeIdx = u32;
+
+/// Marks the end of a child list.
+const NO_EDGE: u32 = u32::MAX;
+
+/// Prefix of [`ProvenanceDag::to_bytes`] output. Bytes without it are read
+/// as the layout written before the node arena.
+const FORMAT_MAGIC: &[u8; 8] = b"WLLDAG\x00\x02";
 
 /// The provenance DAG: a directed acyclic graph of causal relationships
 /// between receipts across worldlines.
@@ -29,14 +47,39 @@ use crate::node::{CausalRelation, DagNode};
 /// receipt streams. It supports incremental construction via [`add_node`].
 ///
 /// [`add_node`]: ProvenanceDag::add_node
-#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(try_from = "DagRepr")]
 pub struct ProvenanceDag {
-    /// All nodes, keyed by their ObjectId.
-    nodes: HashMap<ObjectId, DagNode>,
-    /// Forward-edge index: parent -> list of children.
-    children: HashMap<ObjectId, Vec<ObjectId>>,
+    /// All nodes, in insertion order.
+    nodes: Vec<DagNode>,
+    /// ObjectId -> arena index.
+    index: HashMap<ObjectId, NodeIdx>,
+    /// Where each node's run in `parents` starts. The run lines up with the
+    /// node's `parents` list.
+    parent_start: Vec<u32>,
+    parents: Vec<NodeIdx>,
+    /// Head and tail of each node's list in `child_edges`.
+    child_lists: Vec<ChildList>,
+    child_edges: Vec<ChildEdge>,
+    /// Interned worldline ids.
+    worldlines: Vec<WorldlineId>,
+    worldline_index: HashMap<WorldlineId, u32>,
+    /// Per interned worldline, its nodes in insertion order.
+    worldline_nodes: Vec<Vec<NodeIdx>>,
     /// Nodes that have no parents (genesis / stream starts).
-    roots: Vec<ObjectId>,
+    roots: Vec<NodeIdx>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct ChildList {
+    head: u32,
+    tail: u32,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct ChildEdge {
+    child: NodeIdx,
+    next: u32,
 }
 
 impl ProvenanceDag {
@@ -65,50 +108,120 @@ impl ProvenanceDag {
     /// (or the parents list must be empty for root nodes). Returns an error
     /// if the node ID already exists or if a parent reference dangles.
     pub fn add_node(&mut self, node: DagNode) -> DagResult<()> {
-        if self.nodes.contains_key(&node.id) {
+        if self.index.contains_key(&node.id) {
             return Err(DagError::DuplicateNode(node.id));
         }
 
-        // Validate parent references.
-        for parent_ref in &node.parents {
-            if !self.nodes.contains_key(&parent_ref.target) {
-                return Err(DagError::DanglingParent {
-                    node: node.id,
-                    parent: parent_ref.target,
-                });
-            }
-        }
+        let parents = node
+            .parents
+            .iter()
+            .map(|parent_ref| {
+                self.idx(&parent_ref.target)
+                    .ok_or(DagError::DanglingParent {
+                        node: node.id,
+                        parent: parent_ref.target,
+                    })
+            })
+            .collect::<DagResult<Vec<_>>>()?;
 
-        // Update forward-edge index.
-        for parent_ref in &node.parents {
-            self.children
-                .entry(parent_ref.target)
-                .or_default()
-                .push(node.id);
+        debug!(node = %node.id.short_hex(), seq = node.seq, "added DAG node");
+        self.push(node, &parents);
+
+        Ok(())
+    }
+
+    /// Append a node whose parents are already resolved to `parents`.
+    fn push(&mut self, node: DagNode, parents: &[NodeIdx]) {
+        let idx = NodeIdx::try_from(self.nodes.len()).expect("DAG exceeds u32::MAX nodes");
+        let start = u32::try_from(self.parents.len()).expect("DAG exceeds u32::MAX edges");
+
+        self.parent_start.push(start);
+        self.parents.extend_from_slice(parents);
+        self.child_lists.push(ChildList {
+            head: NO_EDGE,
+            tail: NO_EDGE,
+        });
+        for &parent in parents {
+            self.link_child(parent, idx);
         }
 
         // Track roots.
-        if node.is_root() {
-            self.roots.push(node.id);
+        if parents.is_empty() {
+            self.roots.push(idx);
         }
 
-        debug!(node = %node.id.short_hex(), seq = node.seq, "added DAG node");
-        self.nodes.insert(node.id, node);
+        let worldline = self.intern(&node.worldline);
+        self.worldline_nodes[worldline as usize].push(idx);
+        self.index.insert(node.id, idx);
+        self.nodes.push(node);
+    }
 
-        Ok(())
+    fn link_child(&mut self, parent: NodeIdx, child: NodeIdx) {
+        let edge = u32::try_from(self.child_edges.len()).expect("DAG exceeds u32::MAX edges");
+        self.child_edges.push(ChildEdge {
+            child,
+            next: NO_EDGE,
+        });
+        let list = &mut self.child_lists[parent as usize];
+        match list.tail {
+            NO_EDGE => list.head = edge,
+            tail => self.child_edges[tail as usize].next = edge,
+        }
+        list.tail = edge;
+    }
+
+    fn intern(&mut self, worldline: &WorldlineId) -> u32 {
+        if let Some(&interned) = self.worldline_index.get(worldline) {
+            return interned;
+        }
+        let interned = self.worldlines.len() as u32;
+        self.worldlines.push(worldline.clone());
+        self.worldline_index.insert(worldline.clone(), interned);
+        self.worldline_nodes.push(Vec::new());
+        interned
     }
 
     /// Retrieve a node by its ObjectId.
     pub fn get_node(&self, id: &ObjectId) -> Option<&DagNode> {
-        self.nodes.get(id)
+        self.idx(id).map(|idx| self.node(idx))
+    }
+
+    /// Every node, in insertion order.
+    pub(crate) fn nodes(&self) -> &[DagNode] {
+        &self.nodes
     }
 
     /// All root nodes (nodes with no parents).
     pub fn roots(&self) -> Vec<&DagNode> {
-        self.roots
-            .iter()
-            .filter_map(|id| self.nodes.get(id))
-            .collect()
+        self.roots.iter().map(|&idx| self.node(idx)).collect()
+    }
+
+    fn idx(&self, id: &ObjectId) -> Option<NodeIdx> {
+        self.index.get(id).copied()
+    }
+
+    fn node(&self, idx: NodeIdx) -> &DagNode {
+        &self.nodes[idx as usize]
+    }
+
+    /// Arena indexes of a node's parents, in the order of its `parents`.
+    fn parents_of(&self, idx: NodeIdx) -> &[NodeIdx] {
+        let start = self.parent_start[idx as usize] as usize;
+        let end = self
+            .parent_start
+            .get(idx as usize + 1)
+            .map_or(self.parents.len(), |&end| end as usize);
+        &self.parents[start..end]
+    }
+
+    /// Arena indexes of a node's children, in the order they were added.
+    fn children_of(&self, idx: NodeIdx) -> impl Iterator<Item = NodeIdx> + '_ {
+        let mut edge = self.child_lists[idx as usize].head;
+        std::iter::from_fn(move || {
+            let ChildEdge { child, next } = *self.child_edges.get(edge as usize)?;
+            edge = next;
+            Some(child)
+        })
     }
 
     // ---------------------------------------------------------------
@@ -120,39 +233,10 @@ impl ProvenanceDag {
     /// Returns an empty vec if the node is not found. The node itself
     /// is **not** included in the result.
     pub fn ancestors(&self, id: &ObjectId, max_depth: usize) -> Vec<&DagNode> {
-        let Some(start) = self.nodes.get(id) else {
+        let Some(start) = self.idx(id) else {
             return Vec::new();
         };
-
-        let mut visited = HashSet::new();
-        visited.insert(*id);
-        let mut result = Vec::new();
-        let mut queue: VecDeque<(&ObjectId, usize)> = VecDeque::new();
-
-        // Seed with the start node's parents at depth 1.
-        for parent_ref in &start.parents {
-            if visited.insert(parent_ref.target) {
-                queue.push_back((&parent_ref.target, 1));
-            }
-        }
-
-        while let Some((current_id, depth)) = queue.pop_front() {
-            if depth > max_depth {
-                continue;
-            }
-            if let Some(node) = self.nodes.get(current_id) {
-                result.push(node);
-                if depth < max_depth {
-                    for parent_ref in &node.parents {
-                        if visited.insert(parent_ref.target) {
-                            queue.push_back((&parent_ref.target, depth + 1));
-                        }
-                    }
-                }
-            }
-        }
-
-        result
+        self.breadth_first(start, max_depth, |idx| self.parents_of(idx).iter().copied())
     }
 
     /// All descendants of a node up to `max_depth` levels (BFS downward).
@@ -160,37 +244,36 @@ impl ProvenanceDag {
     /// Returns an empty vec if the node is not found. The node itself
     /// is **not** included in the result.
     pub fn descendants(&self, id: &ObjectId, max_depth: usize) -> Vec<&DagNode> {
-        if !self.nodes.contains_key(id) {
+        let Some(start) = self.idx(id) else {
             return Vec::new();
-        }
+        };
+        self.breadth_first(start, max_depth, |idx| self.children_of(idx))
+    }
 
-        let mut visited = HashSet::new();
-        visited.insert(*id);
+    /// Nodes reachable from `start` through `next` within `max_depth`
+    /// steps, in BFS order, excluding `start`.
+    fn breadth_first<I>(
+        &self,
+        start: NodeIdx,
+        max_depth: usize,
+        next: impl Fn(NodeIdx) -> I,
+    ) -> Vec<&DagNode>
+    where
+        I: Iterator<Item = NodeIdx>,
+    {
+        let mut visited = Marks::new(self.nodes.len());
+        visited.insert(start);
         let mut result = Vec::new();
-        let mut queue: VecDeque<(&ObjectId, usize)> = VecDeque::new();
+        let mut queue = VecDeque::from([(start, 0)]);
 
-        // Seed with direct children at depth 1.
-        if let Some(child_ids) = self.children.get(id) {
-            for child_id in child_ids {
-                if visited.insert(*child_id) {
-                    queue.push_back((child_id, 1));
-                }
+        while let Some((current, depth)) = queue.pop_front() {
+            if depth > 0 {
+                result.push(self.node(current));
             }
-        }
-
-        while let Some((current_id, depth)) = queue.pop_front() {
-            if depth > max_depth {
-                continue;
-            }
-            if let Some(node) = self.nodes.get(current_id) {
-                result.push(node);
-                if depth < max_depth {
-                    if let Some(child_ids) = self.children.get(current_id) {
-                        for child_id in child_ids {
-                            if visited.insert(*child_id) {
-                                queue.push_back((child_id, depth + 1));
-                            }
-                        }
+            if depth < max_depth {
+                for neighbour in next(current) {
+                    if visited.insert(neighbour) {
+                        queue.push_back((neighbour, depth + 1));
                     }
                 }
             }
@@ -203,47 +286,44 @@ impl ProvenanceDag {
     // Path queries
     // ---------------------------------------------------------------
 
-    /// Find the shortest causal path between two nodes using bidirectional
-    /// BFS over the combined parent + child edges.
+    /// Find the shortest causal path between two nodes using BFS over the
+    /// combined parent + child edges.
     ///
     /// Returns `None` if no path exists.
     pub fn causal_path(&self, from: &ObjectId, to: &ObjectId) -> Option<Vec<&DagNode>> {
-        if !self.nodes.contains_key(from) || !self.nodes.contains_key(to) {
-            return None;
-        }
-        if from == to {
-            return Some(vec![self.nodes.get(from).unwrap()]);
-        }
+        let (from, to) = (self.idx(from)?, self.idx(to)?);
+        let path = self.shortest_path(from, to, |idx| {
+            self.parents_of(idx)
+                .iter()
+                .copied()
+                .chain(self.children_of(idx))
+        })?;
+        Some(path.into_iter().map(|idx| self.node(idx)).collect())
+    }
 
-        // BFS from `from` following both parent and child edges.
-        let mut visited: HashMap<ObjectId, Option<ObjectId>> = HashMap::new();
-        visited.insert(*from, None);
-        let mut queue: VecDeque<ObjectId> = VecDeque::new();
-        queue.push_back(*from);
+    /// BFS from `from` through `next` until `to`, returning the path found.
+    fn shortest_path<I>(
+        &self,
+        from: NodeIdx,
+        to: NodeIdx,
+        next: impl Fn(NodeIdx) -> I,
+    ) -> Option<Vec<NodeIdx>>
+    where
+        I: Iterator<Item = NodeIdx>,
+    {
+        let mut visited = Marks::new(self.nodes.len());
+        visited.insert(from);
+        let mut predecessors = HashMap::new();
+        let mut queue = VecDeque::from([from]);
 
         while let Some(current) = queue.pop_front() {
-            if current == *to {
-                // Reconstruct path.
-                return Some(self.reconstruct_path(&visited, from, to));
-            }
-
-            // Explore parents (upward).
-            if let Some(node) = self.nodes.get(&current) {
-                for parent_ref in &node.parents {
-                    if !visited.contains_key(&parent_ref.target) {
-                        visited.insert(parent_ref.target, Some(current));
-                        queue.push_back(parent_ref.target);
-                    }
-                }
+            if current == to {
+                return Some(trace_back(&predecessors, to));
             }
-
-            // Explore children (downward).
-            if let Some(child_ids) = self.children.get(&current) {
-                for child_id in child_ids {
-                    if !visited.contains_key(child_id) {
-                        visited.insert(*child_id, Some(current));
-                        queue.push_back(*child_id);
-                    }
+            for neighbour in next(current) {
+                if visited.insert(neighbour) {
+                    predecessors.insert(neighbour, current);
+                    queue.push_back(neighbour);
                 }
             }
         }
@@ -251,43 +331,18 @@ impl ProvenanceDag {
         None
     }
 
-    /// Reconstruct a path from the BFS predecessor map.
-    fn reconstruct_path<'a>(
-        &'a self,
-        predecessors: &HashMap<ObjectId, Option<ObjectId>>,
-        from: &ObjectId,
-        to: &ObjectId,
-    ) -> Vec<&'a DagNode> {
-        let mut path = Vec::new();
-        let mut current = *to;
-
-        loop {
-            if let Some(node) = self.nodes.get(&current) {
-                path.push(node);
-            }
-            if current == *from {
-                break;
-            }
-            match predecessors.get(&current) {
-                Some(Some(prev)) => current = *prev,
-                _ => break,
-            }
-        }
-
-        path.reverse();
-        path
-    }
-
     // ---------------------------------------------------------------
     // Worldline queries
     // ---------------------------------------------------------------
 
     /// All nodes belonging to a specific worldline, ordered by sequence number.
     pub fn worldline_history(&self, worldline: &WorldlineId) -> Vec<&DagNode> {
-        let mut nodes: Vec<&DagNode> = self
-            .nodes
-            .values()
-            .filter(|n| &n.worldline == worldline)
+        let Some(&interned) = self.worldline_index.get(worldline) else {
+            return Vec::new();
+        };
+        let mut nodes: Vec<&DagNode> = self.worldline_nodes[interned as usize]
+            .iter()
+            .map(|&idx| self.node(idx))
             .collect();
         nodes.sort_by_key(|n| n.seq);
         nodes
@@ -303,47 +358,42 @@ impl ProvenanceDag {
     /// for both nodes, then find the common ancestor with the highest
     /// timestamp (most recent).
     pub fn common_ancestor(&self, a: &ObjectId, b: &ObjectId) -> Option<&DagNode> {
-        if !self.nodes.contains_key(a) || !self.nodes.contains_key(b) {
-            return None;
-        }
+        let (a, b) = (self.idx(a)?, self.idx(b)?);
         if a == b {
-            return self.nodes.get(a);
+            return Some(self.node(a));
         }
 
-        // Collect all ancestors of a (including a itself).
-        let ancestors_a = self.all_ancestors_set(a);
-        // Collect all ancestors of b (including b itself).
-        let ancestors_b = self.all_ancestors_set(b);
-
-        // Common ancestors are the intersection.
-        let common: Vec<&ObjectId> = ancestors_a.intersection(&ancestors_b).collect();
+        // Ancestors of a and of b, each including the node itself.
+        let (ancestors_a, _) = self.all_ancestors(a);
+        let (_, ancestors_b) = self.all_ancestors(b);
 
         // The lowest common ancestor is the one with the latest timestamp
         // (i.e., closest to both nodes).
-        common
+        ancestors_b
             .into_iter()
-            .filter_map(|id| self.nodes.get(id))
+            .filter(|&idx| ancestors_a.contains(idx))
+            .map(|idx| self.node(idx))
             .max_by_key(|node| node.timestamp)
     }
 
-    /// Collect all ancestors of a node (including the node itself) into a set.
-    fn all_ancestors_set(&self, id: &ObjectId) -> HashSet<ObjectId> {
-        let mut visited = HashSet::new();
-        let mut queue = VecDeque::new();
-        visited.insert(*id);
-        queue.push_back(*id);
-
-        while let Some(current) = queue.pop_front() {
-            if let Some(node) = self.nodes.get(&current) {
-                for parent_ref in &node.parents {
-                    if visited.insert(parent_ref.target) {
-                        queue.push_back(parent_ref.target);
-                    }
+    /// All ancestors of a node, including the node itself, as a set and in
+    /// BFS order.
+    fn all_ancestors(&self, start: NodeIdx) -> (Marks, Vec<NodeIdx>) {
+        let mut visited = Marks::new(self.nodes.len());
+        visited.insert(start);
+        let mut order = vec![start];
+        let mut next = 0;
+
+        while let Some(&current) = order.get(next) {
+            next += 1;
+            for &parent in self.parents_of(current) {
+                if visited.insert(parent) {
+                    order.push(parent);
                 }
             }
         }
 
-        visited
+        (visited, order)
     }
 
     // ---------------------------------------------------------------
@@ -352,75 +402,34 @@ impl ProvenanceDag {
 
     /// Return all nodes in topological order (parents before children).
     ///
-    /// Uses Kahn's algorithm. If the graph has no cycles (which is an
-    /// invariant), this will return all nodes.
+    /// Uses Kahn's algorithm, starting from the roots and releasing each
+    /// node once all its parents are out. Roots and each node's children
+    /// are taken in timestamp order so the output is deterministic.
     pub fn topological_order(&self) -> Vec<&DagNode> {
-        // Compute in-degree for each node.
-        let mut in_degree: HashMap<ObjectId, usize> = HashMap::new();
-        for node in self.nodes.values() {
-            in_degree.entry(node.id).or_insert(0);
-            for _parent_ref in &node.parents {
-                // Each child edge from parent -> node increases node's in-degree
-                // from the perspective of "parent edges are the forward direction".
-                // Actually, in our DAG parents point backward, so we need:
-                // in_degree tracks how many parents each node has.
-            }
-        }
-
         // In-degree = number of parents for each node.
-        for node in self.nodes.values() {
-            in_degree.insert(node.id, node.parents.len());
-        }
-
-        // Start with roots (in-degree = 0).
-        let mut queue: VecDeque<ObjectId> = VecDeque::new();
-        for (&id, &deg) in &in_degree {
-            if deg == 0 {
-                queue.push_back(id);
-            }
-        }
+        let mut in_degree: Vec<usize> = (0..self.nodes.len() as NodeIdx)
+            .map(|idx| self.parents_of(idx).len())
+            .collect();
 
-        // Sort the initial queue by timestamp for deterministic output.
-        let mut initial: Vec<ObjectId> = queue.drain(..).collect();
-        initial.sort_by(|a, b| {
-            let na = self.nodes.get(a);
-            let nb = self.nodes.get(b);
-            match (na, nb) {
-                (Some(na), Some(nb)) => na.timestamp.cmp(&nb.timestamp),
-                _ => std::cmp::Ordering::Equal,
-            }
-        });
-        for id in initial {
-            queue.push_back(id);
-        }
+        let mut initial = self.roots.clone();
+        initial.sort_by_key(|&idx| self.node(idx).timestamp);
+        let mut queue = VecDeque::from(initial);
 
-        let mut result = Vec::new();
+        let mut result = Vec::with_capacity(self.nodes.len());
+        let mut children = Vec::new();
 
         while let Some(current) = queue.pop_front() {
-            if let Some(node) = self.nodes.get(&current) {
-                result.push(node);
-            }
+            result.push(self.node(current));
 
             // "Remove" this node by decrementing in-degree of children.
-            if let Some(child_ids) = self.children.get(&current) {
-                // Sort children by timestamp for deterministic output.
-                let mut sorted_children = child_ids.clone();
-                sorted_children.sort_by(|a, b| {
-                    let na = self.nodes.get(a);
-                    let nb = self.nodes.get(b);
-                    match (na, nb) {
-                        (Some(na), Some(nb)) => na.timestamp.cmp(&nb.timestamp),
-                        _ => std::cmp::Ordering::Equal,
-                    }
-                });
-
-                for child_id in &sorted_children {
-                    if let Some(deg) = in_degree.get_mut(child_id) {
-                        *deg -= 1;
-                        if *deg == 0 {
-                            queue.push_back(*child_id);
-                        }
-                    }
+            children.clear();
+            children.extend(self.children_of(current));
+            children.sort_by_key(|&idx| self.node(idx).timestamp);
+            for &child in &children {
+                let degree = &mut in_degree[child as usize];
+                *degree -= 1;
+                if *degree == 0 {
+                    queue.push_back(child);
                 }
             }
         }
@@ -440,50 +449,37 @@ impl ProvenanceDag {
     pub fn audit_trail(&self, commitment_id: &ObjectId) -> AuditTrail {
         let mut trail = AuditTrail::new(*commitment_id);
 
-        let Some(start) = self.nodes.get(commitment_id) else {
+        let Some(start) = self.idx(commitment_id) else {
             return trail;
         };
 
-        // BFS from the commitment backward through parents.
-        let mut visited = HashSet::new();
-        visited.insert(*commitment_id);
-        let mut queue = VecDeque::new();
-
-        // Add the start node.
-        trail.chain.push(AuditEntry {
-            node: start.id,
-            relation: CausalRelation::Sequential, // root of the chain
-            worldline: start.worldline.clone(),
-            timestamp: start.timestamp,
-            summary: start.summary(),
-        });
-
-        for parent_ref in &start.parents {
-            if visited.insert(parent_ref.target) {
-                queue.push_back((parent_ref.target, parent_ref.relation));
-            }
-        }
-
-        while let Some((current_id, relation)) = queue.pop_front() {
-            if let Some(node) = self.nodes.get(&current_id) {
-                trail.chain.push(AuditEntry {
-                    node: node.id,
-                    relation,
-                    worldline: node.worldline.clone(),
-                    timestamp: node.timestamp,
-                    summary: node.summary(),
-                });
-
-                for parent_ref in &node.parents {
-                    if visited.insert(parent_ref.target) {
-                        queue.push_back((parent_ref.target, parent_ref.relation));
-                    }
+        // BFS from the commitment backward through parents. The start
+        // node is the root of the chain.
+        let mut visited = Marks::new(self.nodes.len());
+        visited.insert(start);
+        let mut queue = VecDeque::from([(start, CausalRelation::Sequential)]);
+
+        while let Some((current, relation)) = queue.pop_front() {
+            let node = self.node(current);
+            trail.chain.push(AuditEntry {
+                node: node.id,
+                relation,
+                worldline: node.worldline.clone(),
+                timestamp: node.timestamp,
+                summary: node.summary(),
+            });
+
+            for (&parent, parent_ref) in self.parents_of(current).iter().zip(&node.parents) {
+                if visited.insert(parent) {
+                    queue.push_back((parent, parent_ref.relation));
                 }
             }
         }
 
         // Sort by timestamp, most recent first.
-        trail.chain.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        trail
+            .chain
+            .sort_by_key(|entry| std::cmp::Reverse(entry.timestamp));
 
         trail
     }
@@ -493,105 +489,139 @@ impl ProvenanceDag {
     pub fn impact_report(&self, id: &ObjectId) -> ImpactReport {
         let mut report = ImpactReport::new(*id);
 
-        if !self.nodes.contains_key(id) {
+        let Some(origin) = self.idx(id) else {
             return report;
-        }
+        };
 
-        // BFS downward through children.
-        let mut visited = HashSet::new();
-        visited.insert(*id);
-        let mut queue: VecDeque<(ObjectId, usize)> = VecDeque::new();
-        let mut max_depth: usize = 0;
+        // BFS downward through children. The BFS tree gives the shortest
+        // path from the origin to every leaf.
+        let mut visited = Marks::new(self.nodes.len());
+        visited.insert(origin);
+        let mut predecessors = HashMap::new();
+        let mut queue = VecDeque::from([(origin, 0)]);
         let mut worldlines_set = HashSet::new();
-        let mut leaf_ids = Vec::new();
-
-        // Seed with direct children.
-        if let Some(child_ids) = self.children.get(id) {
-            for child_id in child_ids {
-                if visited.insert(*child_id) {
-                    queue.push_back((*child_id, 1));
+        let mut leaves = Vec::new();
+        let mut risks = HashMap::new();
+        let mut irreversible = Vec::new();
+
+        while let Some((current, depth)) = queue.pop_front() {
+            let mut has_children = false;
+            for child in self.children_of(current) {
+                if visited.insert(child) {
+                    predecessors.insert(child, current);
+                    queue.push_back((child, depth + 1));
+                    has_children = true;
                 }
             }
-        }
 
-        while let Some((current_id, depth)) = queue.pop_front() {
-            if let Some(node) = self.nodes.get(&current_id) {
-                report.downstream_receipts += 1;
-                worldlines_set.insert(node.worldline.clone());
-                if depth > max_depth {
-                    max_depth = depth;
-                }
-
-                let mut has_children = false;
-                if let Some(child_ids) = self.children.get(&current_id) {
-                    for child_id in child_ids {
-                        if visited.insert(*child_id) {
-                            queue.push_back((*child_id, depth + 1));
-                            has_children = true;
-                        }
-                    }
-                }
+            if current == origin {
+                continue;
+            }
+            let node = self.node(current);
+            report.downstream_receipts += 1;
+            worldlines_set.insert(&node.worldline);
+            report.cascade_depth = report.cascade_depth.max(depth);
+            if !has_children {
+                leaves.push(current);
+            }
 
-                if !has_children {
-                    leaf_ids.push(current_id);
-                }
+            let risk = self.risk_of(current);
+            let (severity, level) = risk;
+            report.risk_score += u64::from(level + 1) * u64::from(severity + 1);
+            report.max_risk_level = report.max_risk_level.max(level);
+            if severity == Reversibility::Irreversible.severity() {
+                irreversible.push((level, node.id));
             }
+            risks.insert(current, risk);
         }
 
-        report.cascade_depth = max_depth;
-        report.affected_worldlines = worldlines_set.into_iter().collect();
+        irreversible.sort_by_key(|&(level, _)| std::cmp::Reverse(level));
+        report.irreversible = irreversible.into_iter().map(|(_, id)| id).collect();
+
+        report.affected_worldlines = worldlines_set.into_iter().cloned().collect();
         report.affected_worldlines.sort();
 
-        // Build critical paths from origin to each leaf.
-        for leaf in &leaf_ids {
-            if let Some(path) = self.directed_path_down(id, leaf) {
-                report.critical_paths.push(path);
-            }
-        }
+        // Build critical paths from origin to each leaf, the path through
+        // the most severe node first.
+        let mut paths: Vec<_> = leaves
+            .into_iter()
+            .map(|leaf| trace_back(&predecessors, leaf))
+            .collect();
+        paths.sort_by_key(|path| {
+            let worst = path.iter().filter_map(|idx| risks.get(idx)).max();
+            std::cmp::Reverse(worst.copied())
+        });
+        report.critical_paths = paths
+            .into_iter()
+            .map(|path| path.into_iter().map(|idx| self.node(idx).id).collect())
+            .collect();
 
         report
     }
 
-    /// Find a directed path from `from` to `to` following only child edges.
-    fn directed_path_down(&self, from: &ObjectId, to: &ObjectId) -> Option<Vec<ObjectId>> {
-        if from == to {
-            return Some(vec![*from]);
-        }
+    /// Reversibility severity and commitment class risk level of a node.
+    /// An outcome without a class takes the class of its commitment.
+    fn risk_of(&self, idx: NodeIdx) -> (u8, u8) {
+        let node = self.node(idx);
+        let class = node.metadata.class().or_else(|| {
+            self.parents_of(idx)
+                .iter()
+                .zip(&node.parents)
+                .find(|(_, p)| p.relation == CausalRelation::CommitmentToOutcome)
+                .and_then(|(&commitment, _)| self.node(commitment).metadata.class())
+        });
+        let severity = node.metadata.reversibility().map_or(0, |r| r.severity());
+        (severity, class.map_or(0, |c| c.risk_level()))
+    }
 
-        let mut visited = HashSet::new();
-        visited.insert(*from);
-        let mut predecessors: HashMap<ObjectId, ObjectId> = HashMap::new();
-        let mut queue = VecDeque::new();
-        queue.push_back(*from);
+    // ---------------------------------------------------------------
+    // Subgraphs
+    // ---------------------------------------------------------------
 
-        while let Some(current) = queue.pop_front() {
-            if current == *to {
-                // Reconstruct.
-                let mut path = vec![*to];
-                let mut c = *to;
-                while c != *from {
-                    if let Some(prev) = predecessors.get(&c) {
-                        path.push(*prev);
-                        c = *prev;
-                    } else {
-                        break;
-                    }
-                }
-                path.reverse();
-                return Some(path);
+    /// Extract the neighborhood of `roots`: the nodes themselves and their
+    /// ancestors up to `max_depth` levels, as a self-contained DAG.
+    ///
+    /// Parent references leaving the selection are dropped, so boundary
+    /// nodes become roots of the subgraph, as with [`checkpoint`]. Returns
+    /// an error if any of `roots` is not in the DAG.
+    ///
+    /// [`checkpoint`]: ProvenanceDag::checkpoint
+    pub fn subgraph(&self, roots: &[ObjectId], max_depth: usize) -> DagResult<ProvenanceDag> {
+        let mut selected = Marks::new(self.nodes.len());
+        let mut queue = VecDeque::new();
+        for id in roots {
+            let idx = self.idx(id).ok_or(DagError::NodeNotFound(*id))?;
+            if selected.insert(idx) {
+                queue.push_back((idx, 0));
             }
+        }
 
-            if let Some(child_ids) = self.children.get(&current) {
-                for child_id in child_ids {
-                    if visited.insert(*child_id) {
-                        predecessors.insert(*child_id, current);
-                        queue.push_back(*child_id);
-                    }
+        // One BFS from all starting nodes, so each node is reached at its
+        // shortest distance from any of them.
+        while let Some((current, depth)) = queue.pop_front() {
+            if depth == max_depth {
+                continue;
+            }
+            for &parent in self.parents_of(current) {
+                if selected.insert(parent) {
+                    queue.push_back((parent, depth + 1));
                 }
             }
         }
 
-        None
+        // Arena order keeps parents before children in the new DAG too.
+        let mut subgraph = ProvenanceDag::new();
+        for (idx, node) in self.nodes.iter().enumerate() {
+            if !selected.contains(idx as NodeIdx) {
+                continue;
+            }
+            let mut node = node.clone();
+            node.parents.retain(|p| subgraph.index.contains_key(&p.target));
+            let parents: Vec<NodeIdx> =
+                node.parents.iter().map(|p| subgraph.index[&p.target]).collect();
+            subgraph.push(node, &parents);
+        }
+        Ok(subgraph)
     }
 
     // ---------------------------------------------------------------
@@ -601,13 +631,16 @@ impl ProvenanceDag {
     /// Validate the DAG's structural integrity.
     ///
     /// Checks that:
-    /// - All parent references resolve to existing nodes.
-    /// - The children index is consistent with parent edges.
+    /// - All parent references resolve to earlier nodes.
     /// - Root tracking is correct.
     pub fn validate(&self) -> DagResult<()> {
-        for node in self.nodes.values() {
-            for parent_ref in &node.parents {
-                if !self.nodes.contains_key(&parent_ref.target) {
+        for (idx, node) in self.nodes.iter().enumerate() {
+            let parents = self.parents_of(idx as NodeIdx);
+            for (&parent, parent_ref) in parents.iter().zip(&node.parents) {
+                if parent as usize >= idx {
+                    return Err(DagError::CycleDetected(node.id));
+                }
+                if self.node(parent).id != parent_ref.target {
                     return Err(DagError::DanglingParent {
                         node: node.id,
                         parent: parent_ref.target,
@@ -617,11 +650,10 @@ impl ProvenanceDag {
         }
 
         // Verify roots are correct.
-        for root_id in &self.roots {
-            if let Some(node) = self.nodes.get(root_id) {
-                if !node.is_root() {
-                    return Err(DagError::CycleDetected(*root_id));
-                }
+        for &root in &self.roots {
+            let node = self.node(root);
+            if !node.is_root() {
+                return Err(DagError::CycleDetected(node.id));
             }
         }
 
@@ -634,12 +666,23 @@ impl ProvenanceDag {
 
     /// Serialize the DAG to bincode bytes.
     pub fn to_bytes(&self) -> DagResult<Vec<u8>> {
-        bincode::serialize(self).map_err(|e| DagError::Serialization(e.to_string()))
+        let mut bytes = FORMAT_MAGIC.to_vec();
+        bincode::serialize_into(&mut bytes, self)
+            .map_err(|e| DagError::Serialization(e.to_string()))?;
+        Ok(bytes)
     }
 
-    /// Deserialize a DAG from bincode bytes.
+    /// Deserialize a DAG from bincode bytes, in the current layout or the
+    /// one written before the node arena.
     pub fn from_bytes(data: &[u8]) -> DagResult<Self> {
-        bincode::deserialize(data).map_err(|e| DagError::Serialization(e.to_string()))
+        match data.strip_prefix(FORMAT_MAGIC) {
+            Some(body) => {
+                bincode::deserialize(body).map_err(|e| DagError::Serialization(e.to_string()))
+            }
+            None => bincode::deserialize::<LegacyDag>(data)
+                .map_err(|e| DagError::Serialization(e.to_string()))?
+                .try_into(),
+        }
     }
 
     // ---------------------------------------------------------------
@@ -652,45 +695,224 @@ impl ProvenanceDag {
     /// horizon are removed. The retained children that referenced pruned
     /// parents become new roots. Returns the number of pruned nodes.
     pub fn checkpoint(&mut self, horizon: &TemporalAnchor) -> usize {
-        // Identify nodes to prune.
-        let to_prune: Vec<ObjectId> = self
+        let pruned_count = self
             .nodes
             .iter()
-            .filter(|(_, node)| node.timestamp.is_before(horizon))
-            .map(|(id, _)| *id)
-            .collect();
-
-        let pruned_count = to_prune.len();
-        let pruned_set: HashSet<ObjectId> = to_prune.iter().copied().collect();
+            .filter(|node| node.timestamp.is_before(horizon))
+            .count();
+        if pruned_count == 0 {
+            return 0;
+        }
 
-        // Remove pruned nodes from the node map.
-        for id in &to_prune {
-            self.nodes.remove(id);
-            self.children.remove(id);
+        // Rebuild the arena from the retained nodes. Parents come before
+        // children, so every retained parent is already in place.
+        let old = std::mem::take(self);
+        for mut node in old.nodes {
+            if node.timestamp.is_before(horizon) {
+                continue;
+            }
+            node.parents.retain(|p| self.index.contains_key(&p.target));
+            let parents: Vec<NodeIdx> =
+                node.parents.iter().map(|p| self.index[&p.target]).collect();
+            self.push(node, &parents);
         }
 
-        // Clean up roots.
-        self.roots.retain(|id| !pruned_set.contains(id));
-
-        // For remaining nodes, remove parent refs to pruned nodes.
-        // Nodes whose parents were all pruned become new roots.
-        let remaining_ids: Vec<ObjectId> = self.nodes.keys().copied().collect();
-        for id in remaining_ids {
-            if let Some(node) = self.nodes.get_mut(&id) {
-                let had_parents = !node.parents.is_empty();
-                node.parents.retain(|p| !pruned_set.contains(&p.target));
-                if had_parents && node.parents.is_empty() && !self.roots.contains(&id) {
-                    self.roots.push(id);
-                }
+        pruned_count
+    }
+}
+
+/// One bit per arena slot, for marking visited nodes.
+pub(crate) struct Marks(Vec<u64>);
+
+impl Marks {
+    pub(crate) fn new(len: usize) -> Self {
+        Self(vec![0; len.div_ceil(64)])
+    }
+
+    /// Mark `idx`, returning `true` if it was not marked before.
+    pub(crate) fn insert(&mut self, idx: NodeIdx) -> bool {
+        let (word, bit) = (idx as usize / 64, 1 << (idx % 64));
+        let fresh = self.0[word] & bit == 0;
+        self.0[word] |= bit;
+        fresh
+    }
+
+    pub(crate) fn contains(&self, idx: NodeIdx) -> bool {
+        self.0[idx as usize / 64] & (1 << (idx % 64)) != 0
+    }
+}
+
+/// The path to `to` recorded in a BFS predecessor map.
+fn trace_back(predecessors: &HashMap<NodeIdx, NodeIdx>, to: NodeIdx) -> Vec<NodeIdx> {
+    let mut path = vec![to];
+    let mut current = to;
+    while let Some(&prev) = predecessors.get(&current) {
+        path.push(prev);
+        current = prev;
+    }
+    path.reverse();
+    path
+}
+
+// ---------------------------------------------------------------
+// Serialized form
+// ---------------------------------------------------------------
+
+/// The worldline table, then the nodes in arena order with their worldline
+/// and parents given as indexes.
+#[derive(Deserialize)]
+struct DagRepr {
+    worldlines: Vec<WorldlineId>,
+    nodes: Vec<StoredNode<'static>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredNode<'a> {
+    id: ObjectId,
+    worldline: u32,
+    seq: u64,
+    kind: ReceiptKind,
+    timestamp: TemporalAnchor,
+    parents: Vec<(NodeIdx, CausalRelation)>,
+    metadata: Cow<'a, DagNodeMetadata>,
+}
+
+impl Serialize for ProvenanceDag {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut repr = serializer.serialize_struct("DagRepr", 2)?;
+        repr.serialize_field("worldlines", &self.worldlines)?;
+        repr.serialize_field("nodes", &StoredNodes(self))?;
+        repr.end()
+    }
+}
+
+/// Serializes the arena as [`StoredNode`]s without collecting them first.
+struct StoredNodes<'a>(&'a ProvenanceDag);
+
+impl Serialize for StoredNodes<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let dag = self.0;
+        serializer.collect_seq(dag.nodes.iter().enumerate().map(|(idx, node)| {
+            StoredNode {
+                id: node.id,
+                worldline: dag.worldline_index[&node.worldline],
+                seq: node.seq,
+                kind: node.kind,
+                timestamp: node.timestamp,
+                parents: dag
+                    .parents_of(idx as NodeIdx)
+                    .iter()
+                    .zip(&node.parents)
+                    .map(|(&parent, parent_ref)| (parent, parent_ref.relation))
+                    .collect(),
+                metadata: Cow::Borrowed(&node.metadata),
+            }
+        }))
+    }
+}
+
+impl TryFrom<DagRepr> for ProvenanceDag {
+    type Error = DagError;
+
+    fn try_from(repr: DagRepr) -> DagResult<Self> {
+        let mut dag = Self::default();
+        dag.nodes.reserve_exact(repr.nodes.len());
+        dag.index.reserve(repr.nodes.len());
+
+        for stored in repr.nodes {
+            if dag.index.contains_key(&stored.id) {
+                return Err(DagError::DuplicateNode(stored.id));
             }
+            let worldline = repr
+                .worldlines
+                .get(stored.worldline as usize)
+                .ok_or_else(|| {
+                    DagError::Serialization(format!(
+                        "node {:?} names unknown worldline {}",
+                        stored.id, stored.worldline
+                    ))
+                })?
+                .clone();
+            let mut parents = Vec::with_capacity(stored.parents.len());
+            let mut parent_refs = Vec::with_capacity(stored.parents.len());
+            for (parent, relation) in stored.parents {
+                let target = dag.nodes.get(parent as usize).ok_or_else(|| {
+                    DagError::Serialization(format!(
+                        "node {:?} cites parent {parent}, which is not an earlier node",
+                        stored.id
+                    ))
+                })?;
+                parent_refs.push(ParentRef::new(target.id, relation));
+                parents.push(parent);
+            }
+            let node = DagNode {
+                id: stored.id,
+                worldline,
+                seq: stored.seq,
+                kind: stored.kind,
+                timestamp: stored.timestamp,
+                parents: parent_refs,
+                metadata: stored.metadata.into_owned(),
+            };
+            dag.push(node, &parents);
         }
 
-        // Clean up children index to remove references to pruned nodes.
-        for children_list in self.children.values_mut() {
-            children_list.retain(|id| !pruned_set.contains(id));
+        Ok(dag)
+    }
+}
+
+/// The layout written before the node arena: nodes in a map, plus a child
+/// index and root list that are rebuilt rather than trusted.
+#[derive(Deserialize)]
+struct LegacyDag {
+    nodes: HashMap<ObjectId, DagNode>,
+    _children: HashMap<ObjectId, Vec<ObjectId>>,
+    _roots: Vec<ObjectId>,
+}
+
+impl TryFrom<LegacyDag> for ProvenanceDag {
+    type Error = DagError;
+
+    /// Re-adds the nodes parents-first, in timestamp order where that does
+    /// not put a child before its parent.
+    fn try_from(legacy: LegacyDag) -> DagResult<Self> {
+        let mut pending: Vec<DagNode> = legacy.nodes.into_values().collect();
+        pending.sort_by_key(|node| (node.timestamp, node.id));
+        let mut dag = Self::default();
+
+        while !pending.is_empty() {
+            let before = pending.len();
+            let mut deferred = Vec::new();
+            for node in pending {
+                if node
+                    .parents
+                    .iter()
+                    .all(|p| dag.index.contains_key(&p.target))
+                {
+                    dag.add_node(node)?;
+                } else {
+                    deferred.push(node);
+                }
+            }
+            if deferred.len() == before {
+                // No node became addable: some parent is missing or the
+                // remaining nodes form a cycle. Report the first.
+                let node = &deferred[0];
+                let parent = node
+                    .parents
+                    .iter()
+                    .find(|p| !dag.index.contains_key(&p.target))
+                    .map(|p| p.target)
+                    .expect("deferred node has an unresolved parent");
+                return Err(DagError::DanglingParent {
+                    node: node.id,
+                    parent,
+                });
+            }
+            pending = deferred;
         }
 
-        pruned_count
+        Ok(dag)
     }
 }
 
@@ -704,6 +926,14 @@ pub trait DagStorage: Send + Sync {
     fn append_node(&self, node: DagNode) -> DagResult<()>;
     /// Prune nodes older than the horizon.
     fn checkpoint(&self, horizon: &TemporalAnchor) -> DagResult<()>;
+    /// Load the persisted commit graph, if this backend keeps one.
+    fn load_graph(&self) -> DagResult<Option<CommitGraph>> {
+        Ok(None)
+    }
+    /// Persist the commit graph. Backends without one ignore it.
+    fn save_graph(&self, _graph: &CommitGraph) -> DagResult<()> {
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -1128,6 +1358,37 @@ mod tests {
         assert_eq!(report.cascade_depth, 2);
     }
 
+    #[test]
+    fn impact_report_puts_irreversible_high_risk_paths_first() {
+        use wll_types::CommitmentClass;
+
+        // A fans out to a reversible content update B and to C, a policy
+        // change whose outcome D cannot be undone.
+        let w = wl(1);
+        let seq = |id| ParentRef::sequential(oid(id));
+        let mut dag = ProvenanceDag::new();
+        dag.add_node(make_node(1, &w, 0, ReceiptKind::Commitment, vec![]))
+            .unwrap();
+        let mut b = make_node(2, &w, 1, ReceiptKind::Commitment, vec![seq(1)]);
+        b.metadata = DagNodeMetadata::empty().with_class(&CommitmentClass::ContentUpdate);
+        dag.add_node(b).unwrap();
+        let mut c = make_node(3, &w, 2, ReceiptKind::Commitment, vec![seq(1)]);
+        c.metadata = DagNodeMetadata::empty().with_class(&CommitmentClass::PolicyChange);
+        dag.add_node(c).unwrap();
+        let outcome = ParentRef::new(oid(3), CausalRelation::CommitmentToOutcome);
+        let mut d = make_node(4, &w, 3, ReceiptKind::Outcome, vec![outcome]);
+        d.metadata = DagNodeMetadata::empty().with_reversibility(&Reversibility::Irreversible);
+        dag.add_node(d).unwrap();
+
+        let report = dag.impact_report(&oid(1));
+        assert_eq!(report.critical_paths[0], vec![oid(1), oid(3), oid(4)]);
+        assert_eq!(report.critical_paths[1], vec![oid(1), oid(2)]);
+        assert_eq!(report.irreversible, vec![oid(4)]);
+        assert_eq!(report.max_risk_level, 3);
+        // B: 2 x 1, C: 4 x 1, D takes C's class: 4 x 3.
+        assert_eq!(report.risk_score, 18);
+    }
+
     // ----------------------------------------------------------
     // Cross-worldline tests
     // ----------------------------------------------------------
@@ -1180,6 +1441,88 @@ mod tests {
         let restored = ProvenanceDag::from_bytes(&bytes).unwrap();
         assert_eq!(restored.len(), dag.len());
         assert_eq!(restored.roots().len(), dag.roots().len());
+        assert_eq!(restored.topological_order(), dag.topological_order());
+        assert_eq!(
+            restored.worldline_history(&wl(1)),
+            dag.worldline_history(&wl(1))
+        );
+    }
+
+    #[test]
+    fn pre_arena_layout_still_loads() {
+        // Written by the 0.1.0 `ProvenanceDag::to_bytes`, before the arena.
+        let legacy = include_bytes!("../tests/fixtures/v0.1.0.dag");
+        let (a, b) = (wl(1), wl(2));
+        let with = |mut node: DagNode, metadata| {
+            node.metadata = metadata;
+            node
+        };
+        let expected = [
+            with(
+                make_node(1, &a, 0, ReceiptKind::Commitment, vec![]),
+                DagNodeMetadata::with_description("genesis"),
+            ),
+            make_node(2, &a, 1, ReceiptKind::Outcome, vec![ParentRef::new(
+                oid(1),
+                CausalRelation::CommitmentToOutcome,
+            )]),
+            with(
+                make_node(3, &a, 2, ReceiptKind::Commitment, vec![ParentRef::sequential(oid(1))]),
+                DagNodeMetadata {
+                    description: None,
+                    tags: vec!["side".into()],
+                    content_hash: Some(oid(0x33)),
+                },
+            ),
+            make_node(4, &a, 3, ReceiptKind::Outcome, vec![
+                ParentRef::sequential(oid(2)),
+                ParentRef::new(oid(3), CausalRelation::Merge),
+            ]),
+            with(
+                make_node(5, &b, 1, ReceiptKind::Snapshot, vec![ParentRef::cross_worldline(oid(4))]),
+                DagNodeMetadata::with_description("mirror"),
+            ),
+        ];
+
+        let restored = ProvenanceDag::from_bytes(legacy).unwrap();
+        restored.validate().unwrap();
+        assert_eq!(restored.len(), expected.len());
+        for node in &expected {
+            assert_eq!(restored.get_node(&node.id), Some(node));
+        }
+        assert_eq!(restored.ancestors(&oid(5), 10).len(), 4);
+        assert_eq!(restored.descendants(&oid(1), 10).len(), 4);
+        assert_eq!(restored.worldline_history(&b).len(), 1);
+
+        let bytes = restored.to_bytes().unwrap();
+        assert!(bytes.starts_with(FORMAT_MAGIC));
+        let reloaded = ProvenanceDag::from_bytes(&bytes).unwrap();
+        for node in &expected {
+            assert_eq!(reloaded.get_node(&node.id), Some(node));
+        }
+    }
+
+    #[test]
+    fn stored_parents_must_come_first() {
+        let node = |id: u8, parents| StoredNode {
+            id: oid(id),
+            worldline: 0,
+            seq: 0,
+            kind: ReceiptKind::Commitment,
+            timestamp: TemporalAnchor::new(1000, 0, 0),
+            parents,
+            metadata: Cow::Owned(DagNodeMetadata::empty()),
+        };
+        let stored = vec![
+            node(1, vec![(1, CausalRelation::Sequential)]),
+            node(2, vec![]),
+        ];
+        let mut bytes = FORMAT_MAGIC.to_vec();
+        bytes.extend(bincode::serialize(&(vec![wl(1)], stored)).unwrap());
+        assert!(matches!(
+            ProvenanceDag::from_bytes(&bytes),
+            Err(DagError::Serialization(_))
+        ));
     }
 
     // ----------------------------------------------------------
@@ -1216,6 +1559,34 @@ mod tests {
         assert_eq!(dag.roots()[0].id, oid(3));
     }
 
+    // ----------------------------------------------------------
+    // Subgraph tests
+    // ----------------------------------------------------------
+
+    #[test]
+    fn subgraph_keeps_the_neighborhood_and_roots_its_boundary() {
+        let dag = build_diamond_dag();
+
+        // One level above D: B and C, whose edges to A are cut.
+        let sub = dag.subgraph(&[oid(4)], 1).unwrap();
+        sub.validate().unwrap();
+        assert_eq!(sub.len(), 3);
+        let mut roots: Vec<_> = sub.roots().iter().map(|n| n.id).collect();
+        roots.sort();
+        assert_eq!(roots, vec![oid(2), oid(3)]);
+        assert!(sub.get_node(&oid(1)).is_none());
+        assert_eq!(sub.get_node(&oid(4)).unwrap().parents.len(), 2);
+
+        let bytes = sub.to_bytes().unwrap();
+        assert_eq!(ProvenanceDag::from_bytes(&bytes).unwrap().len(), 3);
+        assert_eq!(dag.subgraph(&[oid(2), oid(3)], 0).unwrap().roots().len(), 2);
+        assert_eq!(dag.subgraph(&[oid(4)], usize::MAX).unwrap().len(), 4);
+        assert!(matches!(
+            dag.subgraph(&[oid(99)], 1),
+            Err(DagError::NodeNotFound(_))
+        ));
+    }
+
     // ----------------------------------------------------------
     // Validation tests
     // ----------------------------------------------------------
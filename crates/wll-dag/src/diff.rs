@@ -0,0 +1,201 @@
+//! Comparing two provenance DAGs.
+//!
+//! Replicas build their DAGs from the same receipt streams, so two healthy
+//! replicas that have seen the same receipts hold the same nodes, possibly
+//! added in a different order. [`ProvenanceDag::digest`] summarizes a DAG
+//! in one hash that ignores insertion order, for a cheap equality check;
+//! [`ProvenanceDag::diff`] says what differs when the digests do not match.
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+use wll_crypto::{ContentHasher, MerkleTree};
+use wll_types::ObjectId;
+
+use crate::dag::ProvenanceDag;
+use crate::error::{DagError, DagResult};
+use crate::node::{CausalRelation, DagNode};
+
+/// Hashes each node into a leaf of the digest tree.
+const NODE_HASHER: ContentHasher = ContentHasher::new("wll-dag-node-v1");
+
+/// A parent edge: `child` names `parent` with `relation`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct DagEdge {
+    pub child: ObjectId,
+    pub parent: ObjectId,
+    pub relation: CausalRelation,
+}
+
+/// A node both DAGs hold with different contents.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeConflict {
+    pub id: ObjectId,
+    /// The [`DagNode`] fields that differ, such as `"seq"` or `"metadata"`.
+    pub fields: Vec<String>,
+}
+
+/// Differences between two DAGs, from the point of view of the first.
+///
+/// Ids and edges are sorted. Edges are only listed for nodes both DAGs
+/// hold; a node present on one side brings its edges along.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DagDiff {
+    /// Nodes only the first DAG holds.
+    pub only_self: Vec<ObjectId>,
+    /// Nodes only the other DAG holds.
+    pub only_other: Vec<ObjectId>,
+    pub edges_only_self: Vec<DagEdge>,
+    pub edges_only_other: Vec<DagEdge>,
+    /// Nodes with the same id but contradictory contents.
+    pub conflicts: Vec<NodeConflict>,
+}
+
+impl DagDiff {
+    /// Returns `true` if the two DAGs hold the same nodes and edges.
+    pub fn is_empty(&self) -> bool {
+        self.only_self.is_empty()
+            && self.only_other.is_empty()
+            && self.edges_only_self.is_empty()
+            && self.edges_only_other.is_empty()
+            && self.conflicts.is_empty()
+    }
+}
+
+impl ProvenanceDag {
+    /// A Merkle root over every node and its parent edges.
+    ///
+    /// Leaves are taken in node id order, so DAGs holding the same nodes
+    /// have the same digest whatever order the nodes were added in.
+    pub fn digest(&self) -> DagResult<ObjectId> {
+        let mut nodes: Vec<&DagNode> = self.nodes().iter().collect();
+        nodes.sort_by_key(|node| node.id);
+        let leaves = nodes
+            .into_iter()
+            .map(|node| {
+                bincode::serialize(node)
+                    .map(|bytes| NODE_HASHER.hash(&bytes))
+                    .map_err(|e| DagError::Serialization(e.to_string()))
+            })
+            .collect::<DagResult<Vec<_>>>()?;
+        Ok(MerkleTree::from_leaves(leaves).root())
+    }
+
+    /// Compare this DAG with `other`.
+    pub fn diff(&self, other: &ProvenanceDag) -> DagDiff {
+        let mut diff = DagDiff::default();
+        for node in self.nodes() {
+            let Some(theirs) = other.get_node(&node.id) else {
+                diff.only_self.push(node.id);
+                continue;
+            };
+            let fields = conflicting_fields(node, theirs);
+            if !fields.is_empty() {
+                diff.conflicts.push(NodeConflict { id: node.id, fields });
+            }
+            let (ours, theirs) = (edges(node), edges(theirs));
+            diff.edges_only_self.extend(ours.difference(&theirs).cloned());
+            diff.edges_only_other.extend(theirs.difference(&ours).cloned());
+        }
+        diff.only_other = other
+            .nodes()
+            .iter()
+            .filter(|node| self.get_node(&node.id).is_none())
+            .map(|node| node.id)
+            .collect();
+
+        diff.only_self.sort();
+        diff.only_other.sort();
+        diff.edges_only_self.sort();
+        diff.edges_only_other.sort();
+        diff.conflicts.sort_by_key(|conflict| conflict.id);
+        diff
+    }
+}
+
+fn edges(node: &DagNode) -> BTreeSet<DagEdge> {
+    node.parents
+        .iter()
+        .map(|parent| DagEdge {
+            child: node.id,
+            parent: parent.target,
+            relation: parent.relation,
+        })
+        .collect()
+}
+
+fn conflicting_fields(a: &DagNode, b: &DagNode) -> Vec<String> {
+    let checks = [
+        ("worldline", a.worldline != b.worldline),
+        ("seq", a.seq != b.seq),
+        ("kind", a.kind != b.kind),
+        ("timestamp", a.timestamp != b.timestamp),
+        ("metadata", a.metadata != b.metadata),
+    ];
+    checks
+        .into_iter()
+        .filter(|&(_, differs)| differs)
+        .map(|(field, _)| field.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use wll_types::identity::IdentityMaterial;
+    use wll_types::{ReceiptKind, TemporalAnchor, WorldlineId};
+
+    use super::*;
+    use crate::node::{DagNodeMetadata, ParentRef};
+
+    fn node(id: u8, seq: u64, parents: Vec<ParentRef>) -> DagNode {
+        DagNode {
+            id: ObjectId::from_hash([id; 32]),
+            worldline: WorldlineId::derive(&IdentityMaterial::GenesisHash([1; 32])),
+            seq,
+            kind: ReceiptKind::Commitment,
+            timestamp: TemporalAnchor::new(1000 + seq * 100, 0, 0),
+            parents,
+            metadata: DagNodeMetadata::empty(),
+        }
+    }
+
+    fn oid(id: u8) -> ObjectId {
+        ObjectId::from_hash([id; 32])
+    }
+
+    #[test]
+    fn digest_ignores_insertion_order_and_diff_names_the_differences() {
+        let mut a = ProvenanceDag::new();
+        let mut b = ProvenanceDag::new();
+        for n in [node(1, 0, vec![]), node(2, 0, vec![])] {
+            a.add_node(n).unwrap();
+        }
+        for n in [node(2, 0, vec![]), node(1, 0, vec![])] {
+            b.add_node(n).unwrap();
+        }
+        assert_eq!(a.digest().unwrap(), b.digest().unwrap());
+        assert!(a.diff(&b).is_empty());
+
+        a.add_node(node(3, 1, vec![ParentRef::sequential(oid(1))])).unwrap();
+        let merge = ParentRef::new(oid(2), CausalRelation::Merge);
+        b.add_node(node(3, 2, vec![ParentRef::sequential(oid(1)), merge])).unwrap();
+        b.add_node(node(4, 3, vec![ParentRef::sequential(oid(3))])).unwrap();
+        assert_ne!(a.digest().unwrap(), b.digest().unwrap());
+
+        let diff = a.diff(&b);
+        assert!(diff.only_self.is_empty());
+        assert_eq!(diff.only_other, vec![oid(4)]);
+        assert!(diff.edges_only_self.is_empty());
+        assert_eq!(
+            diff.edges_only_other,
+            vec![DagEdge {
+                child: oid(3),
+                parent: oid(2),
+                relation: CausalRelation::Merge
+            }]
+        );
+        assert_eq!(diff.conflicts.len(), 1);
+        assert_eq!(diff.conflicts[0].fields, vec!["seq", "timestamp"]);
+        assert_eq!(b.diff(&a).only_self, vec![oid(4)]);
+    }
+}
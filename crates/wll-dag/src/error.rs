@@ -1,6 +1,6 @@
 //! Error types for the provenance DAG.
 
-use wll_types::ObjectId;
+use wll_types::{Coded, ErrorCode, ObjectId};
 
 /// Errors that can occur during DAG operations.
 #[derive(Debug, thiserror::Error)]
@@ -35,6 +35,10 @@ pub enum DagError {
         parent: ObjectId,
     },
 
+    /// A sealed audit trail was altered or not signed by the expected key.
+    #[error("audit trail seal does not verify: {0}")]
+    InvalidSeal(String),
+
     /// Serialization or deserialization error.
     #[error("serialization error: {0}")]
     Serialization(String),
@@ -44,5 +48,20 @@ pub enum DagError {
     Storage(String),
 }
 
+impl Coded for DagError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::NodeNotFound(_) => ErrorCode::DagNodeNotFound,
+            Self::DanglingParent { .. }
+            | Self::DuplicateNode(_)
+            | Self::CycleDetected(_)
+            | Self::TemporalViolation { .. } => ErrorCode::DagInvalid,
+            Self::InvalidSeal(_) => ErrorCode::AuditSealInvalid,
+            Self::Serialization(_) => ErrorCode::Serialization,
+            Self::Storage(_) => ErrorCode::Io,
+        }
+    }
+}
+
 /// Convenience alias for DAG results.
 pub type DagResult<T> = Result<T, DagError>;
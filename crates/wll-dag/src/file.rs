@@ -0,0 +1,190 @@
+//! Single-file [`DagStorage`] backend.
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use wll_types::TemporalAnchor;
+
+use crate::dag::{DagStorage, ProvenanceDag};
+use crate::error::{DagError, DagResult};
+use crate::graph::CommitGraph;
+use crate::node::DagNode;
+
+/// Stores the whole DAG as one bincode file.
+///
+/// Saves write a sibling temporary file and rename it over the target, so a
+/// crash leaves either the previous or the new DAG on disk. A missing file
+/// loads as an empty DAG. Incremental operations rewrite the whole file; the
+/// DAG is derived from receipts and can always be rebuilt if it is lost.
+///
+/// The [`CommitGraph`] lives beside it in a `commit-graph` file, written the
+/// same way.
+#[derive(Debug)]
+pub struct FileDagStorage {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileDagStorage {
+    /// Create a storage backend writing to `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Location of the DAG file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Location of the commit graph file.
+    pub fn graph_path(&self) -> PathBuf {
+        self.path.with_file_name("commit-graph")
+    }
+
+    fn read(&self) -> DagResult<ProvenanceDag> {
+        match fs::read(&self.path) {
+            Ok(bytes) => ProvenanceDag::from_bytes(&bytes),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(ProvenanceDag::new()),
+            Err(e) => Err(storage_error(&self.path, e)),
+        }
+    }
+
+    fn write(&self, dag: &ProvenanceDag) -> DagResult<()> {
+        replace_file(&self.path, &dag.to_bytes()?)
+    }
+
+    fn modify(&self, f: impl FnOnce(&mut ProvenanceDag) -> DagResult<()>) -> DagResult<()> {
+        let _guard = self
+            .lock
+            .lock()
+            .map_err(|_| DagError::Storage("DAG storage lock poisoned".into()))?;
+        let mut dag = self.read()?;
+        f(&mut dag)?;
+        self.write(&dag)
+    }
+}
+
+impl DagStorage for FileDagStorage {
+    fn load(&self) -> DagResult<ProvenanceDag> {
+        self.read()
+    }
+
+    fn save(&self, dag: &ProvenanceDag) -> DagResult<()> {
+        let _guard = self
+            .lock
+            .lock()
+            .map_err(|_| DagError::Storage("DAG storage lock poisoned".into()))?;
+        self.write(dag)
+    }
+
+    fn append_node(&self, node: DagNode) -> DagResult<()> {
+        self.modify(|dag| dag.add_node(node))
+    }
+
+    fn checkpoint(&self, horizon: &TemporalAnchor) -> DagResult<()> {
+        self.modify(|dag| {
+            dag.checkpoint(horizon);
+            Ok(())
+        })
+    }
+
+    fn load_graph(&self) -> DagResult<Option<CommitGraph>> {
+        let path = self.graph_path();
+        match fs::read(&path) {
+            Ok(bytes) => CommitGraph::from_bytes(&bytes).map(Some),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(storage_error(&path, e)),
+        }
+    }
+
+    fn save_graph(&self, graph: &CommitGraph) -> DagResult<()> {
+        let _guard = self
+            .lock
+            .lock()
+            .map_err(|_| DagError::Storage("DAG storage lock poisoned".into()))?;
+        replace_file(&self.graph_path(), &graph.to_bytes()?)
+    }
+}
+
+/// Write `bytes` to a temporary sibling of `path` and rename it into place.
+fn replace_file(path: &Path, bytes: &[u8]) -> DagResult<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| storage_error(dir, e))?;
+    }
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, bytes).map_err(|e| storage_error(&tmp, e))?;
+    fs::rename(&tmp, path).map_err(|e| storage_error(path, e))
+}
+
+fn storage_error(path: &Path, e: std::io::Error) -> DagError {
+    DagError::Storage(format!("{}: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::{DagNodeMetadata, ParentRef};
+    use wll_types::identity::IdentityMaterial;
+    use wll_types::{ObjectId, ReceiptKind, WorldlineId};
+
+    fn node(byte: u8, seq: u64, parents: Vec<ParentRef>) -> DagNode {
+        DagNode {
+            id: ObjectId::from_hash([byte; 32]),
+            worldline: WorldlineId::derive(&IdentityMaterial::GenesisHash([1; 32])),
+            seq,
+            kind: ReceiptKind::Commitment,
+            timestamp: TemporalAnchor::new(1000 + seq, 0, 0),
+            parents,
+            metadata: DagNodeMetadata::empty(),
+        }
+    }
+
+    #[test]
+    fn missing_file_loads_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FileDagStorage::new(dir.path().join("dag"));
+        assert!(storage.load().unwrap().is_empty());
+    }
+
+    #[test]
+    fn append_and_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FileDagStorage::new(dir.path().join("dag"));
+
+        storage.append_node(node(1, 1, vec![])).unwrap();
+        storage
+            .append_node(node(
+                2,
+                2,
+                vec![ParentRef::sequential(ObjectId::from_hash([1; 32]))],
+            ))
+            .unwrap();
+
+        let dag = FileDagStorage::new(dir.path().join("dag")).load().unwrap();
+        assert_eq!(dag.len(), 2);
+        dag.validate().unwrap();
+
+        storage.checkpoint(&TemporalAnchor::new(1002, 0, 0)).unwrap();
+        assert_eq!(storage.load().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn commit_graph_sits_beside_the_dag() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FileDagStorage::new(dir.path().join("dag"));
+        assert!(storage.load_graph().unwrap().is_none());
+
+        let mut dag = ProvenanceDag::new();
+        dag.add_node(node(1, 1, vec![])).unwrap();
+        let graph = CommitGraph::from_dag(&dag);
+        storage.save_graph(&graph).unwrap();
+
+        assert_eq!(storage.graph_path(), dir.path().join("commit-graph"));
+        assert_eq!(storage.load_graph().unwrap(), Some(graph));
+    }
+}
@@ -0,0 +1,692 @@
+//! Commit graph: a compact history index derived from the provenance DAG.
+//!
+//! [`CommitGraph`] keeps only what history queries need: each node's id,
+//! generation number, timestamp, and parent positions. Log walks, ancestry
+//! tests, merge bases, and bisection run on it without reading receipts or
+//! loading node metadata, and it is small enough to persist next to the DAG
+//! and load cheaply.
+//!
+//! Nodes sit in the same order as in the DAG, so parents always come before
+//! their children. A root has generation 1 and every other node one more
+//! than its highest parent, so a node can only be an ancestor of nodes with
+//! a greater generation. That lets traversals stop early.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use serde::{Deserialize, Serialize, Serializer};
+
+use wll_types::ObjectId;
+
+use crate::dag::{Marks, NodeIdx, ProvenanceDag};
+use crate::error::{DagError, DagResult};
+use crate::node::DagNode;
+
+/// Prefix of [`CommitGraph::to_bytes`] output.
+const FORMAT_MAGIC: &[u8; 8] = b"WLLCGR\x00\x01";
+
+/// History index over a [`ProvenanceDag`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "GraphRepr")]
+pub struct CommitGraph {
+    ids: Vec<ObjectId>,
+    generations: Vec<u32>,
+    /// Physical HLC time of each node, in milliseconds.
+    timestamps: Vec<u64>,
+    /// Where each node's run in `parents` starts.
+    parent_start: Vec<u32>,
+    parents: Vec<NodeIdx>,
+    index: HashMap<ObjectId, NodeIdx>,
+}
+
+/// The next step of a bisection.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BisectStep {
+    /// The node to test next. When `candidates` is 1 this is the first bad
+    /// node.
+    pub next: ObjectId,
+    /// Nodes that may still be the first bad one.
+    pub candidates: usize,
+}
+
+impl CommitGraph {
+    /// Create an empty graph.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build the graph for every node in `dag`.
+    pub fn from_dag(dag: &ProvenanceDag) -> Self {
+        let mut graph = Self::new();
+        for node in dag.nodes() {
+            graph
+                .append(node)
+                .expect("DAG nodes come after their parents");
+        }
+        graph
+    }
+
+    /// Number of nodes.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Returns `true` if the graph has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// The most recently appended node.
+    pub fn last(&self) -> Option<&ObjectId> {
+        self.ids.last()
+    }
+
+    /// Whether `id` is in the graph.
+    pub fn contains(&self, id: &ObjectId) -> bool {
+        self.index.contains_key(id)
+    }
+
+    /// Add a node whose parents are already in the graph.
+    pub fn append(&mut self, node: &DagNode) -> DagResult<()> {
+        if self.index.contains_key(&node.id) {
+            return Err(DagError::DuplicateNode(node.id));
+        }
+        let parents = node
+            .parents
+            .iter()
+            .map(|parent| {
+                self.idx(&parent.target).ok_or(DagError::DanglingParent {
+                    node: node.id,
+                    parent: parent.target,
+                })
+            })
+            .collect::<DagResult<Vec<_>>>()?;
+
+        let idx = NodeIdx::try_from(self.ids.len()).expect("graph exceeds u32::MAX nodes");
+        let generation = parents
+            .iter()
+            .map(|&parent| self.generations[parent as usize])
+            .max()
+            .map_or(1, |highest| highest + 1);
+        self.parent_start
+            .push(u32::try_from(self.parents.len()).expect("graph exceeds u32::MAX edges"));
+        self.parents.extend_from_slice(&parents);
+        self.generations.push(generation);
+        self.timestamps.push(node.timestamp.physical_ms);
+        self.ids.push(node.id);
+        self.index.insert(node.id, idx);
+        Ok(())
+    }
+
+    /// Generation number of `id`: 1 for roots, otherwise one more than its
+    /// highest parent.
+    pub fn generation(&self, id: &ObjectId) -> Option<u32> {
+        self.idx(id).map(|idx| self.generations[idx as usize])
+    }
+
+    /// Physical timestamp of `id` in milliseconds.
+    pub fn timestamp(&self, id: &ObjectId) -> Option<u64> {
+        self.idx(id).map(|idx| self.timestamps[idx as usize])
+    }
+
+    /// Parent ids of `id`, in the order of its DAG node's parents.
+    pub fn parents(&self, id: &ObjectId) -> Vec<ObjectId> {
+        self.idx(id).map_or_else(Vec::new, |idx| {
+            self.parents_of(idx)
+                .iter()
+                .map(|&parent| self.ids[parent as usize])
+                .collect()
+        })
+    }
+
+    /// `from` and its ancestors, newest first: by generation, then
+    /// timestamp, so every node comes before its parents.
+    pub fn walk(&self, from: &ObjectId) -> Walk<'_> {
+        let mut walk = Walk {
+            graph: self,
+            queue: BinaryHeap::new(),
+            seen: Marks::new(self.len()),
+        };
+        if let Some(idx) = self.idx(from) {
+            walk.push(idx);
+        }
+        walk
+    }
+
+    /// Whether `ancestor` is `descendant` or one of its ancestors.
+    ///
+    /// Only nodes with a generation above `ancestor`'s are searched.
+    pub fn is_ancestor(&self, ancestor: &ObjectId, descendant: &ObjectId) -> bool {
+        let (Some(target), Some(start)) = (self.idx(ancestor), self.idx(descendant)) else {
+            return false;
+        };
+        let floor = self.generations[target as usize];
+        let mut seen = Marks::new(self.len());
+        seen.insert(start);
+        let mut stack = vec![start];
+        while let Some(current) = stack.pop() {
+            if current == target {
+                return true;
+            }
+            for &parent in self.parents_of(current) {
+                if self.generations[parent as usize] >= floor && seen.insert(parent) {
+                    stack.push(parent);
+                }
+            }
+        }
+        false
+    }
+
+    /// The best common ancestor of `a` and `b`: the one with the highest
+    /// generation, then the latest timestamp.
+    ///
+    /// Both sides are walked together in generation order, so the first
+    /// node reached from both is the answer and nothing older is visited.
+    pub fn merge_base(&self, a: &ObjectId, b: &ObjectId) -> Option<ObjectId> {
+        const FROM_A: u8 = 1;
+        const FROM_B: u8 = 2;
+        let (a, b) = (self.idx(a)?, self.idx(b)?);
+
+        let mut reached = vec![0u8; self.len()];
+        let mut queue = BinaryHeap::new();
+        for (start, side) in [(a, FROM_A), (b, FROM_B)] {
+            if reached[start as usize] == 0 {
+                queue.push(self.key(start));
+            }
+            reached[start as usize] |= side;
+        }
+
+        while let Some((_, _, Reverse(current))) = queue.pop() {
+            let sides = reached[current as usize];
+            if sides == FROM_A | FROM_B {
+                return Some(self.ids[current as usize]);
+            }
+            for &parent in self.parents_of(current) {
+                let slot = &mut reached[parent as usize];
+                if *slot == 0 {
+                    queue.push(self.key(parent));
+                }
+                *slot |= sides;
+            }
+        }
+        None
+    }
+
+    /// `tip` and its ancestors that `base` does not reach, newest first:
+    /// git's `base..tip`. Empty if `tip` is unknown, and all of `tip`'s
+    /// history if `base` is.
+    pub fn range(&self, base: &ObjectId, tip: &ObjectId) -> Vec<ObjectId> {
+        let Some(tip) = self.idx(tip) else {
+            return Vec::new();
+        };
+        let excluded = self.ancestor_marks(self.idx(base));
+        if excluded.contains(tip) {
+            return Vec::new();
+        }
+        let mut found = self.walk_idx(tip, &excluded);
+        found.sort_unstable_by_key(|&idx| Reverse(self.key(idx)));
+        found.into_iter().map(|idx| self.ids[idx as usize]).collect()
+    }
+
+    /// The next node to test when `bad` is known bad and every node in
+    /// `good` (with its ancestors) is known good.
+    ///
+    /// The candidates are `bad` and its ancestors that are not good. The
+    /// node returned has as close to half of them among its own ancestors
+    /// as possible. Returns `None` if `bad` is unknown or itself good.
+    pub fn bisect(&self, good: &[ObjectId], bad: &ObjectId) -> Option<BisectStep> {
+        let bad = self.idx(bad)?;
+        let excluded = self.ancestor_marks(good.iter().filter_map(|id| self.idx(id)));
+        if excluded.contains(bad) {
+            return None;
+        }
+
+        // Candidates in graph order, parents before children.
+        let mut candidates: Vec<NodeIdx> = self.walk_idx(bad, &excluded);
+        candidates.sort_unstable();
+        let total = candidates.len();
+
+        let counts = self.ancestor_counts(&candidates, &excluded);
+        let next = candidates
+            .iter()
+            .zip(&counts)
+            .max_by_key(|&(&idx, &count)| (count.min(total - count), Reverse(idx)))
+            .map(|(&idx, _)| idx)?;
+        Some(BisectStep {
+            next: self.ids[next as usize],
+            candidates: total,
+        })
+    }
+
+    /// For each of `candidates` (in graph order), how many candidates are
+    /// it or its ancestors.
+    fn ancestor_counts(&self, candidates: &[NodeIdx], excluded: &Marks) -> Vec<usize> {
+        let position: HashMap<NodeIdx, usize> = candidates
+            .iter()
+            .enumerate()
+            .map(|(i, &idx)| (idx, i))
+            .collect();
+        let candidate_parents = |idx: NodeIdx| {
+            self.parents_of(idx)
+                .iter()
+                .filter(move |&&parent| !excluded.contains(parent))
+                .copied()
+        };
+
+        // A linear range counts in one pass; merges need a walk per node.
+        if candidates
+            .iter()
+            .all(|&idx| candidate_parents(idx).count() <= 1)
+        {
+            let mut counts = vec![0; candidates.len()];
+            for (i, &idx) in candidates.iter().enumerate() {
+                counts[i] = 1 + candidate_parents(idx)
+                    .next()
+                    .map_or(0, |p| counts[position[&p]]);
+            }
+            return counts;
+        }
+        candidates
+            .iter()
+            .map(|&idx| self.walk_idx(idx, excluded).len())
+            .collect()
+    }
+
+    /// `starts` and all of their ancestors.
+    fn ancestor_marks(&self, starts: impl IntoIterator<Item = NodeIdx>) -> Marks {
+        let mut marks = Marks::new(self.len());
+        let mut stack: Vec<NodeIdx> =
+            starts.into_iter().filter(|&idx| marks.insert(idx)).collect();
+        while let Some(current) = stack.pop() {
+            for &parent in self.parents_of(current) {
+                if marks.insert(parent) {
+                    stack.push(parent);
+                }
+            }
+        }
+        marks
+    }
+
+    /// `start` and its ancestors that are not `excluded`.
+    fn walk_idx(&self, start: NodeIdx, excluded: &Marks) -> Vec<NodeIdx> {
+        let mut seen = Marks::new(self.len());
+        seen.insert(start);
+        let mut found = vec![start];
+        let mut next = 0;
+        while let Some(&current) = found.get(next) {
+            next += 1;
+            for &parent in self.parents_of(current) {
+                if !excluded.contains(parent) && seen.insert(parent) {
+                    found.push(parent);
+                }
+            }
+        }
+        found
+    }
+
+    // ---------------------------------------------------------------
+    // Serialization helpers
+    // ---------------------------------------------------------------
+
+    /// Serialize the graph to bincode bytes.
+    pub fn to_bytes(&self) -> DagResult<Vec<u8>> {
+        let mut bytes = FORMAT_MAGIC.to_vec();
+        bincode::serialize_into(&mut bytes, self)
+            .map_err(|e| DagError::Serialization(e.to_string()))?;
+        Ok(bytes)
+    }
+
+    /// Deserialize a graph, checking that every parent comes before its
+    /// child and every generation number matches its parents.
+    pub fn from_bytes(data: &[u8]) -> DagResult<Self> {
+        let body = data
+            .strip_prefix(FORMAT_MAGIC)
+            .ok_or_else(|| DagError::Serialization("not a commit graph".into()))?;
+        bincode::deserialize(body).map_err(|e| DagError::Serialization(e.to_string()))
+    }
+
+    fn idx(&self, id: &ObjectId) -> Option<NodeIdx> {
+        self.index.get(id).copied()
+    }
+
+    fn parents_of(&self, idx: NodeIdx) -> &[NodeIdx] {
+        let start = self.parent_start[idx as usize] as usize;
+        let end = self
+            .parent_start
+            .get(idx as usize + 1)
+            .map_or(self.parents.len(), |&end| end as usize);
+        &self.parents[start..end]
+    }
+
+    /// Heap key ordering newer nodes first.
+    fn key(&self, idx: NodeIdx) -> (u32, u64, Reverse<NodeIdx>) {
+        (
+            self.generations[idx as usize],
+            self.timestamps[idx as usize],
+            Reverse(idx),
+        )
+    }
+}
+
+/// Iterator returned by [`CommitGraph::walk`].
+pub struct Walk<'a> {
+    graph: &'a CommitGraph,
+    queue: BinaryHeap<(u32, u64, Reverse<NodeIdx>)>,
+    seen: Marks,
+}
+
+impl Walk<'_> {
+    fn push(&mut self, idx: NodeIdx) {
+        if self.seen.insert(idx) {
+            self.queue.push(self.graph.key(idx));
+        }
+    }
+}
+
+impl Iterator for Walk<'_> {
+    type Item = ObjectId;
+
+    fn next(&mut self) -> Option<ObjectId> {
+        let (_, _, Reverse(current)) = self.queue.pop()?;
+        for &parent in self.graph.parents_of(current) {
+            self.push(parent);
+        }
+        Some(self.graph.ids[current as usize])
+    }
+}
+
+// ---------------------------------------------------------------
+// Serialized form
+// ---------------------------------------------------------------
+
+/// The graph's columns; the id lookup is rebuilt on load.
+#[derive(Deserialize)]
+struct GraphRepr {
+    ids: Vec<ObjectId>,
+    generations: Vec<u32>,
+    timestamps: Vec<u64>,
+    parent_start: Vec<u32>,
+    parents: Vec<NodeIdx>,
+}
+
+/// [`GraphRepr`] borrowed from a live graph, for serializing.
+#[derive(Serialize)]
+struct GraphColumns<'a> {
+    ids: &'a [ObjectId],
+    generations: &'a [u32],
+    timestamps: &'a [u64],
+    parent_start: &'a [u32],
+    parents: &'a [NodeIdx],
+}
+
+impl Serialize for CommitGraph {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        GraphColumns {
+            ids: &self.ids,
+            generations: &self.generations,
+            timestamps: &self.timestamps,
+            parent_start: &self.parent_start,
+            parents: &self.parents,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl TryFrom<GraphRepr> for CommitGraph {
+    type Error = DagError;
+
+    fn try_from(repr: GraphRepr) -> DagResult<Self> {
+        let len = repr.ids.len();
+        if repr.generations.len() != len
+            || repr.timestamps.len() != len
+            || repr.parent_start.len() != len
+        {
+            return Err(DagError::Serialization(
+                "commit graph columns differ in length".into(),
+            ));
+        }
+        let mut graph = CommitGraph {
+            ids: repr.ids,
+            generations: repr.generations,
+            timestamps: repr.timestamps,
+            parent_start: repr.parent_start,
+            parents: repr.parents,
+            index: HashMap::with_capacity(len),
+        };
+
+        let starts = &graph.parent_start;
+        if starts.first().is_some_and(|&start| start != 0)
+            || starts.windows(2).any(|pair| pair[0] > pair[1])
+            || starts
+                .last()
+                .is_some_and(|&start| start as usize > graph.parents.len())
+        {
+            return Err(DagError::Serialization(
+                "commit graph edges out of range".into(),
+            ));
+        }
+
+        for idx in 0..len as NodeIdx {
+            let id = graph.ids[idx as usize];
+            let mut expected = 1;
+            for &parent in graph.parents_of(idx) {
+                if parent >= idx {
+                    return Err(DagError::Serialization(format!(
+                        "commit graph node {} has a parent after it",
+                        id.short_hex()
+                    )));
+                }
+                expected = expected.max(graph.generations[parent as usize] + 1);
+            }
+            if graph.generations[idx as usize] != expected {
+                return Err(DagError::Serialization(format!(
+                    "commit graph node {} has the wrong generation",
+                    id.short_hex()
+                )));
+            }
+            if graph.index.insert(id, idx).is_some() {
+                return Err(DagError::DuplicateNode(id));
+            }
+        }
+        Ok(graph)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::{DagNodeMetadata, ParentRef};
+    use wll_types::identity::IdentityMaterial;
+    use wll_types::{ReceiptKind, TemporalAnchor, WorldlineId};
+
+    fn oid(byte: u8) -> ObjectId {
+        ObjectId::from_hash([byte; 32])
+    }
+
+    fn node(byte: u8, parents: &[u8]) -> DagNode {
+        DagNode {
+            id: oid(byte),
+            worldline: WorldlineId::derive(&IdentityMaterial::GenesisHash([1; 32])),
+            seq: u64::from(byte),
+            kind: ReceiptKind::Commitment,
+            timestamp: TemporalAnchor::new(1000 + u64::from(byte), 0, 0),
+            parents: parents
+                .iter()
+                .map(|&p| ParentRef::sequential(oid(p)))
+                .collect(),
+            metadata: DagNodeMetadata::empty(),
+        }
+    }
+
+    fn graph(nodes: &[(u8, &[u8])]) -> CommitGraph {
+        let mut graph = CommitGraph::new();
+        for &(byte, parents) in nodes {
+            graph.append(&node(byte, parents)).unwrap();
+        }
+        graph
+    }
+
+    /// 1 -> 2 -> {3, 4} -> 5 -> 6, with 7 branching off 3.
+    fn forked() -> CommitGraph {
+        graph(&[
+            (1, &[]),
+            (2, &[1]),
+            (3, &[2]),
+            (4, &[2]),
+            (5, &[3, 4]),
+            (6, &[5]),
+            (7, &[3]),
+        ])
+    }
+
+    fn linear(len: u8) -> CommitGraph {
+        let mut graph = CommitGraph::new();
+        graph.append(&node(1, &[])).unwrap();
+        for byte in 2..=len {
+            graph.append(&node(byte, &[byte - 1])).unwrap();
+        }
+        graph
+    }
+
+    #[test]
+    fn generations_follow_the_longest_parent_chain() {
+        let graph = forked();
+        assert_eq!(graph.generation(&oid(1)), Some(1));
+        assert_eq!(graph.generation(&oid(4)), Some(3));
+        assert_eq!(graph.generation(&oid(5)), Some(4));
+        assert_eq!(graph.generation(&oid(99)), None);
+        assert_eq!(graph.parents(&oid(5)), vec![oid(3), oid(4)]);
+    }
+
+    #[test]
+    fn append_rejects_duplicates_and_dangling_parents() {
+        let mut graph = linear(2);
+        assert!(matches!(
+            graph.append(&node(2, &[1])),
+            Err(DagError::DuplicateNode(_))
+        ));
+        assert!(matches!(
+            graph.append(&node(9, &[8])),
+            Err(DagError::DanglingParent { .. })
+        ));
+        assert_eq!(graph.len(), 2);
+    }
+
+    #[test]
+    fn walk_lists_children_before_parents() {
+        let graph = forked();
+        let walked: Vec<_> = graph.walk(&oid(6)).collect();
+        assert_eq!(walked, vec![oid(6), oid(5), oid(4), oid(3), oid(2), oid(1)]);
+        assert_eq!(graph.walk(&oid(99)).count(), 0);
+    }
+
+    #[test]
+    fn ancestry_checks() {
+        let graph = forked();
+        assert!(graph.is_ancestor(&oid(1), &oid(6)));
+        assert!(graph.is_ancestor(&oid(4), &oid(5)));
+        assert!(graph.is_ancestor(&oid(6), &oid(6)));
+        assert!(!graph.is_ancestor(&oid(4), &oid(7)));
+        assert!(!graph.is_ancestor(&oid(6), &oid(1)));
+    }
+
+    #[test]
+    fn merge_base_is_the_newest_common_ancestor() {
+        let graph = forked();
+        assert_eq!(graph.merge_base(&oid(6), &oid(7)), Some(oid(3)));
+        assert_eq!(graph.merge_base(&oid(4), &oid(7)), Some(oid(2)));
+        assert_eq!(graph.merge_base(&oid(5), &oid(3)), Some(oid(3)));
+        assert_eq!(graph.merge_base(&oid(6), &oid(6)), Some(oid(6)));
+
+        let mut unrelated = forked();
+        unrelated.append(&node(20, &[])).unwrap();
+        assert_eq!(unrelated.merge_base(&oid(6), &oid(20)), None);
+    }
+
+    #[test]
+    fn range_lists_what_only_the_tip_reaches() {
+        let graph = forked();
+        assert_eq!(graph.range(&oid(7), &oid(6)), vec![oid(6), oid(5), oid(4)]);
+        assert_eq!(graph.range(&oid(6), &oid(7)), vec![oid(7)]);
+        assert!(graph.range(&oid(6), &oid(2)).is_empty());
+        assert_eq!(graph.range(&oid(99), &oid(3)), vec![oid(3), oid(2), oid(1)]);
+        assert!(graph.range(&oid(1), &oid(99)).is_empty());
+    }
+
+    #[test]
+    fn merge_base_matches_the_dag() {
+        let graph = forked();
+        let mut dag = ProvenanceDag::new();
+        for (byte, parents) in [(1, &[][..]), (2, &[1]), (3, &[2]), (4, &[2]), (5, &[3, 4])] {
+            dag.add_node(node(byte, parents)).unwrap();
+        }
+        dag.add_node(node(6, &[5])).unwrap();
+        dag.add_node(node(7, &[3])).unwrap();
+        assert_eq!(CommitGraph::from_dag(&dag), graph);
+        assert_eq!(
+            graph.merge_base(&oid(6), &oid(7)),
+            dag.common_ancestor(&oid(6), &oid(7)).map(|n| n.id),
+        );
+    }
+
+    #[test]
+    fn bisect_halves_a_linear_range() {
+        let graph = linear(16);
+        let first_bad = 11;
+        let mut good = vec![oid(1)];
+        let mut bad = oid(16);
+        let mut steps = 0;
+        let culprit = loop {
+            let step = graph.bisect(&good, &bad).unwrap();
+            if step.candidates == 1 {
+                break step.next;
+            }
+            steps += 1;
+            if step.next.as_bytes()[0] >= first_bad {
+                bad = step.next;
+            } else {
+                good.push(step.next);
+            }
+        };
+        assert_eq!(culprit, oid(first_bad));
+        assert!(steps <= 4, "took {steps} steps");
+    }
+
+    #[test]
+    fn bisect_across_merges() {
+        let graph = forked();
+        let step = graph.bisect(&[oid(1)], &oid(6)).unwrap();
+        assert_eq!(step.candidates, 5);
+        assert!(graph.bisect(&[oid(6)], &oid(5)).is_none());
+        let last = graph.bisect(&[oid(4), oid(3)], &oid(5)).unwrap();
+        assert_eq!(
+            last,
+            BisectStep {
+                next: oid(5),
+                candidates: 1
+            }
+        );
+    }
+
+    #[test]
+    fn bytes_roundtrip() {
+        let graph = forked();
+        let bytes = graph.to_bytes().unwrap();
+        let loaded = CommitGraph::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded, graph);
+        assert_eq!(loaded.merge_base(&oid(6), &oid(7)), Some(oid(3)));
+        assert!(CommitGraph::from_bytes(b"WLLDAG\x00\x02").is_err());
+    }
+
+    #[test]
+    fn tampered_generations_are_rejected() {
+        let mut graph = forked();
+        graph.generations[4] += 1;
+        let err = CommitGraph::from_bytes(&graph.to_bytes().unwrap()).unwrap_err();
+        assert!(matches!(err, DagError::Serialization(_)));
+
+        let mut graph = forked();
+        graph.parents[0] = 6;
+        assert!(CommitGraph::from_bytes(&graph.to_bytes().unwrap()).is_err());
+    }
+}
@@ -2,14 +2,22 @@
 //!
 //! Tracks causal relationships between receipts across worldlines. Supports
 //! traversal queries (ancestors, descendants, paths), audit trails, impact
-//! analysis, and topological ordering.
+//! analysis, topological ordering, and comparing the DAGs of two replicas.
+//! A [`CommitGraph`] derived from the DAG answers history queries (log,
+//! merge base, bisect) from generation numbers alone.
 
 pub mod audit;
 pub mod dag;
+pub mod diff;
 pub mod error;
+pub mod file;
+pub mod graph;
 pub mod node;
 
-pub use audit::{AuditEntry, AuditTrail, ImpactReport};
-pub use dag::ProvenanceDag;
+pub use audit::{AuditEntry, AuditTrail, ImpactReport, SealedAuditTrail};
+pub use dag::{DagStorage, ProvenanceDag};
+pub use diff::{DagDiff, DagEdge, NodeConflict};
 pub use error::{DagError, DagResult};
+pub use file::FileDagStorage;
+pub use graph::{BisectStep, CommitGraph};
 pub use node::{CausalRelation, DagNode, DagNodeMetadata, ParentRef};
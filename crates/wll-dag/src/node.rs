@@ -6,7 +6,9 @@
 
 use serde::{Deserialize, Serialize};
 
-use wll_types::{ObjectId, ReceiptKind, TemporalAnchor, WorldlineId};
+use wll_types::{
+    CommitmentClass, ObjectId, ReceiptKind, Reversibility, TemporalAnchor, WorldlineId,
+};
 
 /// A node in the provenance DAG.
 ///
@@ -86,7 +88,7 @@ impl ParentRef {
 }
 
 /// The kind of causal relationship between two nodes in the DAG.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum CausalRelation {
     /// Previous receipt in same worldline stream (like a git parent commit).
     Sequential,
@@ -115,7 +117,15 @@ impl std::fmt::Display for CausalRelation {
     }
 }
 
+/// Tag recording a node's commitment class.
+const CLASS_TAG: &str = "class:";
+/// Tag recording how reversible a node's effects are.
+const REVERSIBILITY_TAG: &str = "reversibility:";
+
 /// Optional metadata attached to a DAG node.
+///
+/// The commitment class and reversibility used to weigh impact reports are
+/// kept as `class:` and `reversibility:` tags.
 #[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DagNodeMetadata {
     /// Human-readable description of this node's purpose.
@@ -139,6 +149,33 @@ impl DagNodeMetadata {
             ..Self::default()
         }
     }
+
+    /// Record the commitment class of the node.
+    pub fn with_class(mut self, class: &CommitmentClass) -> Self {
+        self.tags.push(format!("{CLASS_TAG}{class}"));
+        self
+    }
+
+    /// Record how reversible the node's effects are.
+    pub fn with_reversibility(mut self, reversibility: &Reversibility) -> Self {
+        self.tags.push(format!("{REVERSIBILITY_TAG}{reversibility}"));
+        self
+    }
+
+    /// The commitment class recorded with [`with_class`](Self::with_class).
+    pub fn class(&self) -> Option<CommitmentClass> {
+        self.tag(CLASS_TAG).map(CommitmentClass::from_name)
+    }
+
+    /// The reversibility recorded with
+    /// [`with_reversibility`](Self::with_reversibility).
+    pub fn reversibility(&self) -> Option<Reversibility> {
+        self.tag(REVERSIBILITY_TAG).and_then(Reversibility::from_name)
+    }
+
+    fn tag(&self, prefix: &str) -> Option<&str> {
+        self.tags.iter().find_map(|tag| tag.strip_prefix(prefix))
+    }
 }
 
 #[cfg(test)]
@@ -223,6 +260,16 @@ mod tests {
         assert!(meta.tags.is_empty());
     }
 
+    #[test]
+    fn class_and_reversibility_round_trip_through_tags() {
+        let meta = DagNodeMetadata::with_description("deploy")
+            .with_class(&CommitmentClass::Custom("deploy".into()))
+            .with_reversibility(&Reversibility::Irreversible);
+        assert_eq!(meta.class(), Some(CommitmentClass::Custom("deploy".into())));
+        assert_eq!(meta.reversibility(), Some(Reversibility::Irreversible));
+        assert_eq!(DagNodeMetadata::empty().class(), None);
+    }
+
     #[test]
     fn serde_roundtrip() {
         let node = make_node(42, 7, vec![ParentRef::sequential(ObjectId::from_hash([0; 32]))]);
@@ -0,0 +1,46 @@
+//! Line diffs over large blobs.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use wll_diff::diff_blobs;
+
+/// A `lines`-line text file.
+fn text(lines: usize) -> String {
+    (0..lines)
+        .map(|line| format!("    let value_{line} = compute({line}, \"{line:x}\");\n"))
+        .collect()
+}
+
+/// `old` with every `every`th line rewritten.
+fn edit(old: &str, every: usize) -> String {
+    old.lines()
+        .enumerate()
+        .map(|(index, line)| {
+            if index % every == 0 {
+                format!("    // changed\n{line}\n")
+            } else {
+                format!("{line}\n")
+            }
+        })
+        .collect()
+}
+
+fn diff(c: &mut Criterion) {
+    let mut group = c.benchmark_group("diff_blobs");
+    for lines in [10_000, 100_000] {
+        let old = text(lines);
+        group.throughput(Throughput::Bytes(old.len() as u64));
+        for (name, new) in [
+            ("identical", old.clone()),
+            ("sparse_edits", edit(&old, 1_000)),
+            ("dense_edits", edit(&old, 10)),
+        ] {
+            group.bench_with_input(BenchmarkId::new(name, lines), &new, |b, new| {
+                b.iter(|| diff_blobs(old.as_bytes(), new.as_bytes()))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, diff);
+criterion_main!(benches);
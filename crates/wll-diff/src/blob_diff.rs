@@ -3,10 +3,11 @@
 //! Uses the `similar` crate (Myers diff algorithm) to produce structured
 //! hunks with context lines.
 
+use serde::{Deserialize, Serialize};
 use similar::{ChangeTag, TextDiff};
 
 /// The result of diffing two blobs (file contents).
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BlobDiff {
     /// The diff hunks.
     pub hunks: Vec<DiffHunk>,
@@ -42,7 +43,7 @@ impl BlobDiff {
 }
 
 /// A contiguous region of changes in a diff.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DiffHunk {
     /// Line number in the old content where this hunk starts (1-based).
     pub old_start: usize,
@@ -57,7 +58,8 @@ pub struct DiffHunk {
 }
 
 /// A single line in a diff hunk.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", content = "text", rename_all = "snake_case")]
 pub enum DiffLine {
     /// A line present in both old and new (context).
     Context(String),
@@ -1,6 +1,6 @@
 //! Error types for the diff crate.
 
-use wll_types::ObjectId;
+use wll_types::{Coded, ErrorCode, ObjectId};
 
 /// Errors that can occur during diff operations.
 #[derive(Debug, thiserror::Error)]
@@ -26,5 +26,16 @@ pub enum DiffError {
     Serialization(String),
 }
 
+impl Coded for DiffError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::ObjectNotFound(_) => ErrorCode::ObjectNotFound,
+            Self::UnexpectedObjectKind { .. } => ErrorCode::UnexpectedObjectKind,
+            Self::Store(e) => e.code(),
+            Self::Serialization(_) => ErrorCode::Serialization,
+        }
+    }
+}
+
 /// Convenience alias for diff results.
 pub type DiffResult<T> = Result<T, DiffError>;
@@ -7,14 +7,18 @@
 //!
 //! - [`TreeDiff`] / [`TreeChange`] -- Tree-level diff (added/deleted/modified/renamed entries)
 //! - [`BlobDiff`] / [`DiffHunk`] / [`DiffLine`] -- Line-level blob diff
-//! - [`StateDiff`] / [`StateChange`] -- State map diff (BTreeMap<String, Value>)
+//! - [`BlobMerge`] / [`MergeLabels`] -- Three-way line merge with conflict markers
+//! - [`StateDiff`] / [`StateChange`] -- State map diff (BTreeMap<String, Value>), per namespace
+//!   with [`diff_namespaces`]
 
 pub mod blob_diff;
 pub mod error;
+pub mod merge;
 pub mod state_diff;
 pub mod tree_diff;
 
 pub use blob_diff::{diff_blobs, BlobDiff, DiffHunk, DiffLine};
 pub use error::{DiffError, DiffResult};
-pub use state_diff::{diff_states, StateDiff, StateChange};
+pub use merge::{merge_blobs, BlobMerge, MergeLabels};
+pub use state_diff::{diff_namespaces, diff_states, StateDiff, StateChange};
 pub use tree_diff::{diff_tree_objects, diff_trees, TreeChange, TreeDiff};
@@ -0,0 +1,185 @@
+//! Three-way blob merge with conflict markers.
+//!
+//! Both sides are diffed against the common base (Myers, via `similar`).
+//! Base lines that both sides kept unchanged split the files into chunks;
+//! a chunk changed on one side only takes that side, a chunk changed the
+//! same way on both sides takes it once, and anything else becomes a
+//! conflict written between markers:
+//!
+//! ```text
+//! <<<<<<< ours
+//! our lines
+//! =======
+//! their lines
+//! >>>>>>> theirs
+//! ```
+
+use serde::{Deserialize, Serialize};
+use similar::{capture_diff_slices, Algorithm, DiffOp};
+
+/// Width of a conflict marker, as in Git.
+pub const MARKER_LEN: usize = 7;
+
+/// Names written after the conflict markers.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MergeLabels {
+    pub ours: String,
+    pub theirs: String,
+    /// Also write the base lines of each conflict, after a `|||||||`
+    /// marker with this name (the diff3 style).
+    pub base: Option<String>,
+}
+
+impl Default for MergeLabels {
+    fn default() -> Self {
+        Self { ours: "ours".to_string(), theirs: "theirs".to_string(), base: None }
+    }
+}
+
+impl MergeLabels {
+    pub fn new(ours: impl Into<String>, theirs: impl Into<String>) -> Self {
+        Self { ours: ours.into(), theirs: theirs.into(), base: None }
+    }
+
+    /// Include base lines in each conflict, labelled `base`.
+    pub fn with_base(mut self, base: impl Into<String>) -> Self {
+        self.base = Some(base.into());
+        self
+    }
+}
+
+/// The result of merging three versions of a text blob.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlobMerge {
+    /// The merged text, with conflict markers around each conflict.
+    pub text: String,
+    /// Number of conflicting regions.
+    pub conflicts: usize,
+}
+
+impl BlobMerge {
+    /// Returns `true` if both sides merged without conflicts.
+    pub fn is_clean(&self) -> bool {
+        self.conflicts == 0
+    }
+}
+
+/// Merge `ours` and `theirs`, both descended from `base`, line by line.
+///
+/// Returns `None` if any version is not valid UTF-8: binary content has
+/// no lines to merge.
+pub fn merge_blobs(
+    base: &[u8],
+    ours: &[u8],
+    theirs: &[u8],
+    labels: &MergeLabels,
+) -> Option<BlobMerge> {
+    let base = lines(std::str::from_utf8(base).ok()?);
+    let ours = lines(std::str::from_utf8(ours).ok()?);
+    let theirs = lines(std::str::from_utf8(theirs).ok()?);
+    let in_ours = matches(&base, &ours);
+    let in_theirs = matches(&base, &theirs);
+
+    let mut merge = BlobMerge { text: String::new(), conflicts: 0 };
+    let (mut b, mut o, mut t) = (0, 0, 0);
+    while b < base.len() || o < ours.len() || t < theirs.len() {
+        // Lines unchanged on both sides.
+        if b < base.len() && in_ours[b] == Some(o) && in_theirs[b] == Some(t) {
+            merge.text.push_str(base[b]);
+            (b, o, t) = (b + 1, o + 1, t + 1);
+            continue;
+        }
+        // A chunk runs to the next base line both sides kept.
+        let next = (b..base.len()).find(|&i| in_ours[i].is_some() && in_theirs[i].is_some());
+        let (b_end, o_end, t_end) = match next {
+            Some(i) => (i, in_ours[i].unwrap_or(o), in_theirs[i].unwrap_or(t)),
+            None => (base.len(), ours.len(), theirs.len()),
+        };
+        let (base_chunk, ours_chunk, theirs_chunk) =
+            (&base[b..b_end], &ours[o..o_end], &theirs[t..t_end]);
+        if ours_chunk == base_chunk || ours_chunk == theirs_chunk {
+            theirs_chunk.iter().for_each(|line| merge.text.push_str(line));
+        } else if theirs_chunk == base_chunk {
+            ours_chunk.iter().for_each(|line| merge.text.push_str(line));
+        } else {
+            merge.conflicts += 1;
+            write_conflict(&mut merge.text, base_chunk, ours_chunk, theirs_chunk, labels);
+        }
+        (b, o, t) = (b_end, o_end, t_end);
+    }
+    Some(merge)
+}
+
+/// Split `text` into lines, keeping their terminators.
+fn lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+/// For each line of `base`, the line of `side` it is kept as, if any.
+fn matches(base: &[&str], side: &[&str]) -> Vec<Option<usize>> {
+    let mut kept = vec![None; base.len()];
+    for op in capture_diff_slices(Algorithm::Myers, base, side) {
+        if let DiffOp::Equal { old_index, new_index, len } = op {
+            for i in 0..len {
+                kept[old_index + i] = Some(new_index + i);
+            }
+        }
+    }
+    kept
+}
+
+fn write_conflict(
+    out: &mut String,
+    base: &[&str],
+    ours: &[&str],
+    theirs: &[&str],
+    labels: &MergeLabels,
+) {
+    let marker = |out: &mut String, c: char, label: Option<&str>| {
+        if !out.is_empty() && !out.ends_with('\n') {
+            out.push('\n');
+        }
+        out.extend(std::iter::repeat(c).take(MARKER_LEN));
+        if let Some(label) = label {
+            out.push(' ');
+            out.push_str(label);
+        }
+        out.push('\n');
+    };
+    marker(out, '<', Some(&labels.ours));
+    ours.iter().for_each(|line| out.push_str(line));
+    if let Some(label) = &labels.base {
+        marker(out, '|', Some(label));
+        base.iter().for_each(|line| out.push_str(line));
+    }
+    marker(out, '=', None);
+    theirs.iter().for_each(|line| out.push_str(line));
+    marker(out, '>', Some(&labels.theirs));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_takes_one_sided_changes_and_marks_conflicts() {
+        let base = b"a\nb\nc\nd\n";
+        let labels = MergeLabels::default();
+
+        let clean = merge_blobs(base, b"A\nb\nc\nd\n", b"a\nb\nC\nd\n", &labels).unwrap();
+        assert!(clean.is_clean());
+        assert_eq!(clean.text, "A\nb\nC\nd\n");
+
+        let merged = merge_blobs(base, b"A\nb\nc\nours\n", b"a\nb\nC\ntheirs\n", &labels).unwrap();
+        assert_eq!(merged.conflicts, 1);
+        assert_eq!(
+            merged.text,
+            "A\nb\n<<<<<<< ours\nc\nours\n=======\nC\ntheirs\n>>>>>>> theirs\n"
+        );
+
+        let labels = MergeLabels::new("main", "topic").with_base("base");
+        let diff3 = merge_blobs(b"x", b"y", b"z", &labels).unwrap();
+        assert_eq!(diff3.text, "<<<<<<< main\ny\n||||||| base\nx\n=======\nz\n>>>>>>> topic\n");
+        assert!(merge_blobs(b"", &[0xff], b"", &labels).is_none());
+    }
+}
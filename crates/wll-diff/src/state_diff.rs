@@ -2,13 +2,16 @@
 //!
 //! States are represented as `BTreeMap<String, serde_json::Value>`. The diff
 //! detects key additions, removals, and value modifications.
+//! [`diff_namespaces`] diffs a state split into named namespaces, one
+//! namespace at a time.
 
 use std::collections::BTreeMap;
 
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 /// The result of comparing two state maps.
-#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct StateDiff {
     /// The list of state changes.
     pub changes: Vec<StateChange>,
@@ -56,7 +59,8 @@ impl StateDiff {
 }
 
 /// A single change in a state map.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
 pub enum StateChange {
     /// A new key-value pair was added.
     Added { key: String, value: Value },
@@ -114,6 +118,27 @@ pub fn diff_states(
     StateDiff { changes }
 }
 
+/// Diff each namespace of two namespaced states, as [`diff_states`] does.
+///
+/// A namespace missing on one side diffs as empty there. Only namespaces
+/// that changed are returned.
+pub fn diff_namespaces(
+    old: &BTreeMap<String, BTreeMap<String, Value>>,
+    new: &BTreeMap<String, BTreeMap<String, Value>>,
+) -> BTreeMap<String, StateDiff> {
+    let empty = BTreeMap::new();
+    old.keys()
+        .chain(new.keys())
+        .filter_map(|name| {
+            let diff = diff_states(
+                old.get(name).unwrap_or(&empty),
+                new.get(name).unwrap_or(&empty),
+            );
+            (!diff.is_empty()).then(|| (name.clone(), diff))
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -126,6 +151,24 @@ mod tests {
             .collect()
     }
 
+    #[test]
+    fn namespaces_diff_independently() {
+        let old = BTreeMap::from([
+            ("billing".to_string(), make_state(&[("plan", json!("free"))])),
+            ("audit".to_string(), make_state(&[("level", json!(1))])),
+        ]);
+        let new = BTreeMap::from([
+            ("billing".to_string(), make_state(&[("plan", json!("pro"))])),
+            ("audit".to_string(), make_state(&[("level", json!(1))])),
+            ("search".to_string(), make_state(&[("index", json!("v2"))])),
+        ]);
+
+        let diffs = diff_namespaces(&old, &new);
+        assert_eq!(diffs.keys().collect::<Vec<_>>(), ["billing", "search"]);
+        assert_eq!(diffs["billing"].modifications(), 1);
+        assert_eq!(diffs["search"].additions(), 1);
+    }
+
     #[test]
     fn identical_states_no_diff() {
         let state = make_state(&[("a", json!(1)), ("b", json!("hello"))]);
@@ -219,4 +262,16 @@ mod tests {
         let diff = diff_states(&old, &new);
         assert_eq!(diff.modifications(), 1);
     }
+
+    #[test]
+    fn changes_serialize_with_tag() {
+        let old = make_state(&[("a", json!(1))]);
+        let new = make_state(&[("a", json!(2))]);
+
+        let json = serde_json::to_value(diff_states(&old, &new)).unwrap();
+        assert_eq!(
+            json,
+            json!({"changes": [{"change": "modified", "key": "a", "old": 1, "new": 2}]})
+        );
+    }
 }
@@ -5,13 +5,14 @@
 
 use std::collections::BTreeMap;
 
+use serde::{Deserialize, Serialize};
 use wll_store::{EntryMode, ObjectStore, Tree, TreeEntry};
 use wll_types::ObjectId;
 
 use crate::error::{DiffError, DiffResult};
 
 /// The result of comparing two trees.
-#[derive(Clone, Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct TreeDiff {
     /// The list of changes between the old and new trees.
     pub changes: Vec<TreeChange>,
@@ -35,7 +36,8 @@ impl TreeDiff {
 }
 
 /// A single change between two trees.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
 pub enum TreeChange {
     /// A new entry was added.
     Added {
@@ -119,7 +121,7 @@ fn load_tree_entries(
     tree_id: &ObjectId,
 ) -> DiffResult<BTreeMap<String, TreeEntry>> {
     let stored = store
-        .read(tree_id)?
+        .read_shared(tree_id)?
         .ok_or(DiffError::ObjectNotFound(*tree_id))?;
 
     let tree = Tree::from_stored_object(&stored)
@@ -0,0 +1,60 @@
+//! WAL append throughput under each sync mode.
+
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use wll_fabric::{
+    EventKind, EventPayload, FabricEvent, SyncMode, WalConfig, WalEntry, WriteAheadLog,
+};
+use wll_types::{IdentityMaterial, TemporalAnchor, WorldlineId};
+
+const BATCH: u64 = 64;
+
+fn entry(seq: u64) -> WalEntry {
+    WalEntry {
+        event: FabricEvent::new(
+            TemporalAnchor::new(1_000 + seq, 0, 1),
+            WorldlineId::derive(&IdentityMaterial::GenesisHash([3; 32])),
+            EventKind::OutcomeRecorded,
+            EventPayload::Raw(vec![0xab; 256]),
+        ),
+    }
+}
+
+fn append(c: &mut Criterion) {
+    let entries: Vec<WalEntry> = (0..BATCH).map(entry).collect();
+    let mut group = c.benchmark_group("wal_append");
+    group.throughput(Throughput::Elements(BATCH));
+    for (name, sync_mode) in [
+        ("os_default", SyncMode::OsDefault),
+        (
+            "periodic_100ms",
+            SyncMode::Periodic(Duration::from_millis(100)),
+        ),
+        ("every_write", SyncMode::EveryWrite),
+    ] {
+        let dir = tempfile::tempdir().unwrap();
+        let config = WalConfig {
+            sync_mode,
+            ..WalConfig::default()
+        };
+        let wal = WriteAheadLog::open(&dir.path().join("bench.wal"), config).unwrap();
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            // Start each batch on an empty segment, outside the timing, so
+            // the file does not grow across millions of iterations.
+            b.iter_batched(
+                || wal.truncate().unwrap(),
+                |()| {
+                    for entry in &entries {
+                        wal.append(entry).unwrap();
+                    }
+                },
+                BatchSize::PerIteration,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, append);
+criterion_main!(benches);
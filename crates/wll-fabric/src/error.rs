@@ -1,6 +1,8 @@
 use std::io;
 use std::path::PathBuf;
 
+use wll_types::{Coded, ErrorCode};
+
 /// Errors produced by the event fabric subsystem.
 #[derive(Debug, thiserror::Error)]
 pub enum FabricError {
@@ -24,6 +26,10 @@ pub enum FabricError {
     #[error("invalid WAL entry length {length} at offset {offset}")]
     InvalidEntryLength { offset: u64, length: u32 },
 
+    /// A WAL entry passed its CRC but does not decode as an event.
+    #[error("undecodable WAL entry at offset {offset}: {reason}")]
+    UndecodableEntry { offset: u64, reason: String },
+
     /// WAL segment file not found or inaccessible.
     #[error("WAL path not found: {0}")]
     WalPathNotFound(PathBuf),
@@ -48,10 +54,32 @@ pub enum FabricError {
         max_drift_ms: u64,
     },
 
+    /// WAL segment written in a version this build cannot read.
+    #[error("unsupported WAL segment version {0}")]
+    UnsupportedVersion(u32),
+
     /// Checkpoint offset is beyond the current WAL write position.
     #[error("checkpoint offset {requested} exceeds current write position {current}")]
     InvalidCheckpoint { requested: u64, current: u64 },
 }
 
+impl Coded for FabricError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::Io(_) | Self::WalPathNotFound(_) => ErrorCode::Io,
+            Self::Serialization(_) => ErrorCode::Serialization,
+            Self::CrcMismatch { .. }
+            | Self::InvalidEntryLength { .. }
+            | Self::UndecodableEntry { .. } => ErrorCode::WalCorrupt,
+            Self::Shutdown | Self::SubscriberClosed | Self::NoSubscribers => {
+                ErrorCode::FabricClosed
+            }
+            Self::ClockDrift { .. } => ErrorCode::ClockDrift,
+            Self::InvalidCheckpoint { .. } => ErrorCode::InvalidCheckpoint,
+            Self::UnsupportedVersion(_) => ErrorCode::Unsupported,
+        }
+    }
+}
+
 /// Convenience alias used throughout the fabric crate.
 pub type Result<T> = std::result::Result<T, FabricError>;
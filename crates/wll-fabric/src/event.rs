@@ -54,6 +54,8 @@ pub enum EventKind {
     SyncStarted,
     /// A sync operation has completed.
     SyncCompleted,
+    /// A scheduled maintenance task has finished, successfully or not.
+    MaintenanceCompleted,
 }
 
 impl std::fmt::Display for EventKind {
@@ -67,6 +69,7 @@ impl std::fmt::Display for EventKind {
             Self::RefUpdated => "RefUpdated",
             Self::SyncStarted => "SyncStarted",
             Self::SyncCompleted => "SyncCompleted",
+            Self::MaintenanceCompleted => "MaintenanceCompleted",
         };
         write!(f, "{s}")
     }
@@ -103,6 +106,13 @@ pub enum EventPayload {
     },
     /// Arbitrary binary data.
     Raw(Vec<u8>),
+    /// Maintenance run payload: the task, whether it succeeded, and its
+    /// summary or error.
+    Maintenance {
+        task: String,
+        succeeded: bool,
+        detail: String,
+    },
 }
 
 /// A single event flowing through the fabric.
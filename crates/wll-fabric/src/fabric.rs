@@ -1,14 +1,14 @@
 use std::path::Path;
-use std::sync::RwLock;
+use std::sync::{Arc, RwLock};
 
 use tokio::sync::broadcast;
-use tracing::{debug, info};
+use tracing::{debug, info, info_span};
 
-use wll_types::{TemporalAnchor, WorldlineId};
+use wll_types::{Clock, TemporalAnchor, WorldlineId};
 
 use crate::error::Result;
 use crate::event::{EventKind, EventPayload, FabricEvent};
-use crate::hlc::HybridLogicalClock;
+use crate::hlc::{HybridLogicalClock, SkewPolicy};
 use crate::wal::{WalConfig, WalEntry, WriteAheadLog};
 
 /// Filter for subscribing to a subset of fabric events.
@@ -114,6 +114,11 @@ pub struct FabricConfig {
     pub wal: WalConfig,
     /// Capacity of per-subscriber broadcast channels.
     pub channel_capacity: usize,
+    /// Largest lead over the local wall clock accepted in a received
+    /// timestamp, in milliseconds. Unbounded if `None`.
+    pub max_clock_skew_ms: Option<u64>,
+    /// What to do with received timestamps past `max_clock_skew_ms`.
+    pub skew_policy: SkewPolicy,
 }
 
 impl Default for FabricConfig {
@@ -122,6 +127,8 @@ impl Default for FabricConfig {
             node_id: 0,
             wal: WalConfig::default(),
             channel_capacity: 1024,
+            max_clock_skew_ms: None,
+            skew_policy: SkewPolicy::default(),
         }
     }
 }
@@ -129,7 +136,7 @@ impl Default for FabricConfig {
 /// Central event fabric: crash-recoverable event bus with causal ordering.
 ///
 /// Combines a [`HybridLogicalClock`] for causal timestamps, a
-/// [`WriteAheadLog`] for crash recovery, and an [`EventRouter`] for
+/// [`WriteAheadLog`] for crash recovery, and an internal router for
 /// fan-out delivery to subscribers.
 pub struct EventFabric {
     hlc: HybridLogicalClock,
@@ -141,7 +148,10 @@ pub struct EventFabric {
 impl EventFabric {
     /// Create a new fabric, opening (or creating) the WAL at the given path.
     pub fn new(wal_path: &Path, config: FabricConfig) -> Result<Self> {
-        let hlc = HybridLogicalClock::new(config.node_id);
+        let mut hlc = HybridLogicalClock::new(config.node_id).with_skew_policy(config.skew_policy);
+        if let Some(max_skew_ms) = config.max_clock_skew_ms {
+            hlc = hlc.with_max_skew(max_skew_ms);
+        }
         let wal = WriteAheadLog::open(wal_path, config.wal.clone())?;
         let router = EventRouter::new();
 
@@ -155,6 +165,12 @@ impl EventFabric {
         })
     }
 
+    /// Stamp events from `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.hlc = self.hlc.with_clock(clock);
+        self
+    }
+
     /// Emit a single event through the fabric.
     ///
     /// The event is stamped with the next HLC tick, persisted to the WAL,
@@ -196,11 +212,16 @@ impl EventFabric {
         }
 
         // WAL phase: persist all events.
-        for event in &stamped {
-            self.wal.append(&WalEntry {
-                event: event.clone(),
-            })?;
-        }
+        let wal_span = info_span!("wal.append_batch", count = stamped.len());
+        wal_span.in_scope(|| {
+            stamped.iter().try_for_each(|event| {
+                self.wal
+                    .append(&WalEntry {
+                        event: event.clone(),
+                    })
+                    .map(drop)
+            })
+        })?;
 
         // Route phase: fan out to subscribers.
         for event in &stamped {
@@ -227,6 +248,11 @@ impl EventFabric {
         Ok(events)
     }
 
+    /// Flush the WAL to disk, as a host does before exiting.
+    pub fn sync(&self) -> Result<()> {
+        self.wal.sync()
+    }
+
     /// Checkpoint the WAL, marking all current data as committed.
     pub fn checkpoint(&self) -> Result<()> {
         let offset = self.wal.offset();
@@ -236,8 +262,9 @@ impl EventFabric {
         Ok(())
     }
 
-    /// Update the HLC with a received remote timestamp.
-    pub fn update_clock(&self, received: &TemporalAnchor) -> TemporalAnchor {
+    /// Update the HLC with a received remote timestamp, subject to the
+    /// configured skew bound.
+    pub fn update_clock(&self, received: &TemporalAnchor) -> Result<TemporalAnchor> {
         self.hlc.update(received)
     }
 
@@ -1,7 +1,9 @@
-use std::sync::Mutex;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::{Arc, Mutex};
 
-use wll_types::TemporalAnchor;
+use tracing::warn;
+use wll_types::{Clock, SystemClock, TemporalAnchor};
+
+use crate::error::{FabricError, Result};
 
 /// Internal mutable state of the Hybrid Logical Clock.
 struct HlcState {
@@ -9,6 +11,62 @@ struct HlcState {
     physical_ms: u64,
     /// Logical counter for events within the same physical millisecond.
     logical: u32,
+    /// Skew observed in received timestamps.
+    skew: SkewStats,
+}
+
+/// What [`HybridLogicalClock::update`] does with a received timestamp
+/// further ahead of the local wall clock than the configured maximum.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SkewPolicy {
+    /// Refuse the timestamp with [`FabricError::ClockDrift`], leaving the
+    /// clock untouched.
+    #[default]
+    Reject,
+    /// Accept the timestamp, but count it and log a warning.
+    Flag,
+}
+
+/// Upper bounds, in milliseconds, of the [`SkewStats`] histogram buckets.
+/// The last bucket is unbounded.
+pub const SKEW_BUCKETS_MS: [u64; 6] = [1, 10, 100, 1_000, 10_000, 60_000];
+
+/// Skew of received timestamps against the local wall clock.
+///
+/// Skew is `received.physical_ms - wall_clock_ms`: positive when the sender
+/// is ahead. The histogram buckets the absolute skew by
+/// [`SKEW_BUCKETS_MS`], with one extra bucket for anything larger.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SkewStats {
+    /// Received timestamps observed, including rejected ones.
+    pub samples: u64,
+    /// Timestamps refused under [`SkewPolicy::Reject`].
+    pub rejected: u64,
+    /// Timestamps accepted past the bound under [`SkewPolicy::Flag`].
+    pub flagged: u64,
+    /// Largest skew seen from a sender ahead of this node.
+    pub max_ahead_ms: u64,
+    /// Largest skew seen from a sender behind this node.
+    pub max_behind_ms: u64,
+    /// Sample counts per bucket of absolute skew.
+    pub histogram: [u64; SKEW_BUCKETS_MS.len() + 1],
+}
+
+impl SkewStats {
+    fn observe(&mut self, skew_ms: i64) {
+        let magnitude = skew_ms.unsigned_abs();
+        self.samples += 1;
+        if skew_ms > 0 {
+            self.max_ahead_ms = self.max_ahead_ms.max(magnitude);
+        } else {
+            self.max_behind_ms = self.max_behind_ms.max(magnitude);
+        }
+        let bucket = SKEW_BUCKETS_MS
+            .iter()
+            .position(|&bound| magnitude <= bound)
+            .unwrap_or(SKEW_BUCKETS_MS.len());
+        self.histogram[bucket] += 1;
+    }
 }
 
 /// Hybrid Logical Clock for causal event ordering.
@@ -25,9 +83,19 @@ struct HlcState {
 ///   with logical adjusted to be strictly greater than both local and received
 ///   counters when the physical component ties.
 /// - **Guarantee**: timestamps are monotonic and preserve causal ordering.
+///
+/// A received timestamp from a node whose clock runs ahead drags this clock
+/// forward with it, and every later timestamp inherits the error. Set a
+/// bound with [`with_max_skew`](Self::with_max_skew) to reject (or flag)
+/// such timestamps; [`skew_stats`](Self::skew_stats) reports the skew seen.
 pub struct HybridLogicalClock {
     /// Unique identifier for this node.
     node_id: u16,
+    /// Largest accepted lead of a received timestamp over the wall clock.
+    max_skew_ms: Option<u64>,
+    skew_policy: SkewPolicy,
+    /// Source of the physical component.
+    clock: Arc<dyn Clock>,
     /// Mutable clock state protected by a mutex.
     state: Mutex<HlcState>,
 }
@@ -37,19 +105,43 @@ impl HybridLogicalClock {
     pub fn new(node_id: u16) -> Self {
         Self {
             node_id,
+            max_skew_ms: None,
+            skew_policy: SkewPolicy::default(),
+            clock: Arc::new(SystemClock),
             state: Mutex::new(HlcState {
                 physical_ms: 0,
                 logical: 0,
+                skew: SkewStats::default(),
             }),
         }
     }
 
+    /// Read the physical component from `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Bound how far ahead of the local wall clock a received timestamp may
+    /// be. Timestamps behind the wall clock are always accepted.
+    pub fn with_max_skew(mut self, max_skew_ms: u64) -> Self {
+        self.max_skew_ms = Some(max_skew_ms);
+        self
+    }
+
+    /// What to do with timestamps past the skew bound. Defaults to
+    /// [`SkewPolicy::Reject`].
+    pub fn with_skew_policy(mut self, policy: SkewPolicy) -> Self {
+        self.skew_policy = policy;
+        self
+    }
+
     /// Generate a new monotonic timestamp for a local event.
     ///
     /// The returned [`TemporalAnchor`] is guaranteed to be strictly greater
     /// than any previously returned value from this clock.
     pub fn now(&self) -> TemporalAnchor {
-        let wall = Self::wall_clock_ms();
+        let wall = self.clock.now_ms();
         let mut state = self.state.lock().expect("HLC mutex poisoned");
 
         let new_physical = wall.max(state.physical_ms);
@@ -71,10 +163,42 @@ impl HybridLogicalClock {
     /// Update the clock on receipt of a remote timestamp, returning a new
     /// timestamp that is strictly greater than both the local state and the
     /// received anchor.
-    pub fn update(&self, received: &TemporalAnchor) -> TemporalAnchor {
-        let wall = Self::wall_clock_ms();
+    ///
+    /// Fails with [`FabricError::ClockDrift`] if the received timestamp is
+    /// further ahead of the wall clock than the configured maximum skew and
+    /// the policy is [`SkewPolicy::Reject`].
+    pub fn update(&self, received: &TemporalAnchor) -> Result<TemporalAnchor> {
+        self.update_at(received, self.clock.now_ms())
+    }
+
+    fn update_at(&self, received: &TemporalAnchor, wall: u64) -> Result<TemporalAnchor> {
         let mut state = self.state.lock().expect("HLC mutex poisoned");
 
+        let skew_ms = received.skew_ms(wall);
+        state.skew.observe(skew_ms);
+        if let Some(max_skew_ms) = self
+            .max_skew_ms
+            .filter(|&max| received.exceeds_skew(wall, max))
+        {
+            match self.skew_policy {
+                SkewPolicy::Reject => {
+                    state.skew.rejected += 1;
+                    return Err(FabricError::ClockDrift {
+                        local_ms: wall,
+                        received_ms: received.physical_ms,
+                        max_drift_ms: max_skew_ms,
+                    });
+                }
+                SkewPolicy::Flag => {
+                    state.skew.flagged += 1;
+                    warn!(
+                        node_id = received.node_id,
+                        skew_ms, max_skew_ms, "received timestamp is ahead of the local clock"
+                    );
+                }
+            }
+        }
+
         let new_physical = wall.max(state.physical_ms).max(received.physical_ms);
 
         let new_logical = if new_physical > state.physical_ms
@@ -98,21 +222,23 @@ impl HybridLogicalClock {
         state.physical_ms = new_physical;
         state.logical = new_logical;
 
-        TemporalAnchor::new(new_physical, new_logical, self.node_id)
+        Ok(TemporalAnchor::new(new_physical, new_logical, self.node_id))
+    }
+
+    /// Skew observed in received timestamps so far.
+    pub fn skew_stats(&self) -> SkewStats {
+        self.state.lock().expect("HLC mutex poisoned").skew.clone()
+    }
+
+    /// The configured skew bound, if any.
+    pub fn max_skew_ms(&self) -> Option<u64> {
+        self.max_skew_ms
     }
 
     /// The node identifier this clock was created with.
     pub fn node_id(&self) -> u16 {
         self.node_id
     }
-
-    /// Current wall-clock time in milliseconds since the UNIX epoch.
-    fn wall_clock_ms() -> u64 {
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_millis() as u64
-    }
 }
 
 #[cfg(test)]
@@ -165,7 +291,7 @@ mod tests {
 
         // Simulate receiving a timestamp from the far future.
         let remote = TemporalAnchor::new(u64::MAX - 1, 10, 2);
-        let updated = hlc.update(&remote);
+        let updated = hlc.update(&remote).unwrap();
 
         assert!(updated > remote, "updated must be > received: {updated:?} vs {remote:?}");
     }
@@ -180,7 +306,7 @@ mod tests {
             state.logical = 100;
         }
         let remote = TemporalAnchor::new(1000, 5, 2);
-        let updated = hlc.update(&remote);
+        let updated = hlc.update(&remote).unwrap();
 
         assert_eq!(updated.physical_ms, u64::MAX);
         assert_eq!(updated.logical, 101);
@@ -198,7 +324,7 @@ mod tests {
         }
         // Remote has the same physical but higher logical.
         let remote = TemporalAnchor::new(far_future, 10, 2);
-        let updated = hlc.update(&remote);
+        let updated = hlc.update(&remote).unwrap();
 
         assert_eq!(updated.physical_ms, far_future);
         // max(5, 10) + 1 = 11
@@ -239,4 +365,58 @@ mod tests {
             "all timestamps must be unique across threads"
         );
     }
+
+    #[test]
+    fn skew_bound_rejects_or_flags_future_timestamps() {
+        let wall = 1_000_000;
+        let hlc = HybridLogicalClock::new(1).with_max_skew(500);
+        let ahead = TemporalAnchor::new(wall + 5_000, 0, 2);
+
+        assert!(matches!(
+            hlc.update_at(&ahead, wall),
+            Err(FabricError::ClockDrift {
+                max_drift_ms: 500,
+                ..
+            })
+        ));
+        let behind = TemporalAnchor::new(wall - 90_000, 3, 2);
+        assert_eq!(
+            hlc.update_at(&behind, wall).unwrap(),
+            TemporalAnchor::new(wall, 0, 1)
+        );
+        hlc.update_at(&TemporalAnchor::new(wall + 200, 0, 2), wall)
+            .unwrap();
+
+        let stats = hlc.skew_stats();
+        assert_eq!((stats.samples, stats.rejected, stats.flagged), (3, 1, 0));
+        assert_eq!((stats.max_ahead_ms, stats.max_behind_ms), (5_000, 90_000));
+        assert_eq!(stats.histogram, [0, 0, 0, 1, 1, 0, 1]);
+
+        let flagging = HybridLogicalClock::new(1)
+            .with_max_skew(500)
+            .with_skew_policy(SkewPolicy::Flag);
+        let updated = flagging.update_at(&ahead, wall).unwrap();
+        assert!(updated > ahead);
+        assert_eq!(flagging.skew_stats().flagged, 1);
+    }
+
+    #[test]
+    fn manual_clock_drives_physical_component() {
+        let clock = Arc::new(wll_types::ManualClock::new(10_000));
+        let hlc = HybridLogicalClock::new(1)
+            .with_clock(clock.clone())
+            .with_max_skew(500);
+
+        assert_eq!(hlc.now(), TemporalAnchor::new(10_000, 0, 1));
+        assert_eq!(hlc.now(), TemporalAnchor::new(10_000, 1, 1));
+        assert!(hlc.update(&TemporalAnchor::new(11_000, 0, 2)).is_err());
+
+        clock.advance_ms(1_000);
+        assert_eq!(
+            hlc.update(&TemporalAnchor::new(11_000, 0, 2)).unwrap(),
+            TemporalAnchor::new(11_000, 1, 1)
+        );
+        clock.set_ms(0);
+        assert_eq!(hlc.now(), TemporalAnchor::new(11_000, 2, 1));
+    }
 }
@@ -12,6 +12,6 @@ pub mod wal;
 
 pub use error::FabricError;
 pub use event::{EventKind, EventPayload, FabricEvent};
-pub use fabric::{EventFabric, EventFilter};
-pub use hlc::HybridLogicalClock;
-pub use wal::{SyncMode, WalConfig, WriteAheadLog};
+pub use fabric::{EventFabric, EventFilter, FabricConfig};
+pub use hlc::{HybridLogicalClock, SkewPolicy, SkewStats, SKEW_BUCKETS_MS};
+pub use wal::{SyncMode, WalConfig, WalEntry, WriteAheadLog, WAL_FORMAT};
@@ -1,5 +1,5 @@
 use std::fs::{self, File, OpenOptions};
-use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use std::time::Duration;
@@ -7,12 +7,34 @@ use std::time::Duration;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, warn};
 
+use wll_types::FormatSpec;
+
 use crate::error::{FabricError, Result};
 use crate::event::FabricEvent;
 
+/// Versions of the WAL segment format. Version 1 segments are bare
+/// entries; from version 2 a segment opens with `WLLW` and its version as
+/// a little-endian u32.
+pub const WAL_FORMAT: FormatSpec = FormatSpec { name: "wal", current: 2, oldest: 1 };
+
+/// Magic opening a versioned segment. Read as an entry length it would be
+/// over a gigabyte, so it never begins a version 1 segment.
+const SEGMENT_MAGIC: &[u8; 4] = b"WLLW";
+
+/// Segment header size: magic and version.
+const SEGMENT_HEADER_SIZE: usize = 8;
+
+/// The header opening a segment in the current version.
+fn segment_header() -> [u8; SEGMENT_HEADER_SIZE] {
+    let mut header = [0; SEGMENT_HEADER_SIZE];
+    header[..4].copy_from_slice(SEGMENT_MAGIC);
+    header[4..].copy_from_slice(&WAL_FORMAT.current.to_le_bytes());
+    header
+}
+
 /// WAL entry: a single serialized event with length and CRC framing.
 ///
-/// On-disk format:
+/// On-disk format, after the segment header:
 /// ```text
 /// [4 bytes: entry length (little-endian u32)]
 /// [4 bytes: CRC32 of payload (little-endian u32)]
@@ -24,38 +46,124 @@ pub struct WalEntry {
     pub event: FabricEvent,
 }
 
+impl WalEntry {
+    /// Frame this entry in the on-disk format: header, then payload.
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let payload = bincode::serialize(&self.event)
+            .map_err(|e| FabricError::Serialization(e.to_string()))?;
+        let mut framed = Vec::with_capacity(HEADER_SIZE + payload.len());
+        framed.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        framed.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+        framed.extend_from_slice(&payload);
+        Ok(framed)
+    }
+
+    /// Decode every valid entry in a segment's bytes, in any readable
+    /// version.
+    ///
+    /// Decoding stops at a zero or overlong length, which is where a crash
+    /// cut the last write short, and entries with a bad CRC are skipped
+    /// with a warning. An entry whose CRC holds but whose payload does not
+    /// decode was written in a layout this build does not understand, so
+    /// it fails the whole segment rather than silently losing events, as
+    /// does a segment in a version this build cannot read. Never panics,
+    /// whatever the input.
+    pub fn decode_segment(data: &[u8]) -> Result<Vec<WalEntry>> {
+        let mut entries = Vec::new();
+        let version = Self::segment_version(data);
+        if !WAL_FORMAT.reads(version) {
+            return Err(FabricError::UnsupportedVersion(version));
+        }
+        let mut offset = if version == 1 { 0 } else { SEGMENT_HEADER_SIZE };
+
+        while let Some(header) = data.get(offset..offset + HEADER_SIZE) {
+            let length = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+            let expected_crc = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+
+            let start = offset + HEADER_SIZE;
+            let Some(payload) = data
+                .get(start..start.saturating_add(length as usize))
+                .filter(|_| length > 0)
+            else {
+                warn!(
+                    offset,
+                    length,
+                    file_len = data.len(),
+                    "invalid WAL entry length; stopping recovery"
+                );
+                break;
+            };
+            offset = start + payload.len();
+
+            let actual_crc = crc32fast::hash(payload);
+            if actual_crc != expected_crc {
+                warn!(
+                    offset = start - HEADER_SIZE,
+                    expected = expected_crc,
+                    actual = actual_crc,
+                    "CRC mismatch; skipping entry"
+                );
+                continue;
+            }
+
+            let event = bincode::deserialize::<FabricEvent>(payload).map_err(|e| {
+                FabricError::UndecodableEntry {
+                    offset: (start - HEADER_SIZE) as u64,
+                    reason: e.to_string(),
+                }
+            })?;
+            entries.push(WalEntry { event });
+        }
+
+        Ok(entries)
+    }
+
+    /// The version a segment's bytes are in: 1 when they carry no header.
+    pub fn segment_version(data: &[u8]) -> u32 {
+        match data.get(..SEGMENT_HEADER_SIZE) {
+            Some(header) if header.starts_with(SEGMENT_MAGIC) => {
+                u32::from_le_bytes([header[4], header[5], header[6], header[7]])
+            }
+            _ => 1,
+        }
+    }
+
+    /// Rewrite a segment in an older readable version in the current one,
+    /// keeping its bytes as they are after the header, torn tail and all.
+    /// `None` when it is already current or cannot be read.
+    pub fn upgrade_segment(data: &[u8]) -> Option<Vec<u8>> {
+        let version = Self::segment_version(data);
+        if !WAL_FORMAT.is_outdated(version) {
+            return None;
+        }
+        let mut upgraded = segment_header().to_vec();
+        upgraded.extend_from_slice(data);
+        Some(upgraded)
+    }
+}
+
 /// Flush/sync strategy for the WAL.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub enum SyncMode {
     /// `fsync` after every write (safest, highest latency).
     EveryWrite,
     /// `fsync` periodically at the given interval.
     Periodic(Duration),
     /// Rely on OS page-cache buffering (fastest, least durable).
+    #[default]
     OsDefault,
 }
 
-impl Default for SyncMode {
-    fn default() -> Self {
-        Self::OsDefault
-    }
-}
-
 /// Retention policy for WAL segments after checkpoint.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub enum WalRetention {
     /// Delete WAL data that has been checkpointed.
+    #[default]
     DeleteOnCheckpoint,
     /// Keep all WAL data (useful for auditing).
     KeepAll,
 }
 
-impl Default for WalRetention {
-    fn default() -> Self {
-        Self::DeleteOnCheckpoint
-    }
-}
-
 /// Configuration for the Write-Ahead Log.
 #[derive(Clone, Debug)]
 pub struct WalConfig {
@@ -93,6 +201,10 @@ struct WalWriter {
 /// CRC32 checksum, and written to a single segment file. On recovery the
 /// file is read front-to-back; entries that fail the CRC check are skipped
 /// (they represent incomplete/torn writes from a crash).
+///
+/// A new segment is written in [`WAL_FORMAT`]'s current version. A version
+/// 1 segment is appended to as it is, and rewritten in the current version
+/// the next time it is truncated.
 pub struct WriteAheadLog {
     /// Path to the WAL segment file.
     path: PathBuf,
@@ -110,13 +222,24 @@ impl WriteAheadLog {
             fs::create_dir_all(parent)?;
         }
 
-        let file = OpenOptions::new()
+        let mut file = OpenOptions::new()
             .create(true)
             .read(true)
             .append(true)
             .open(path)?;
 
-        let offset = file.metadata()?.len();
+        let mut offset = file.metadata()?.len();
+        if offset == 0 {
+            file.write_all(&segment_header())?;
+            offset = SEGMENT_HEADER_SIZE as u64;
+        } else {
+            let mut header = Vec::with_capacity(SEGMENT_HEADER_SIZE);
+            (&file).take(SEGMENT_HEADER_SIZE as u64).read_to_end(&mut header)?;
+            let version = WalEntry::segment_version(&header);
+            if !WAL_FORMAT.reads(version) {
+                return Err(FabricError::UnsupportedVersion(version));
+            }
+        }
         let writer = BufWriter::new(file);
 
         Ok(Self {
@@ -128,20 +251,11 @@ impl WriteAheadLog {
 
     /// Append a single entry to the WAL. Returns the byte offset of the entry.
     pub fn append(&self, entry: &WalEntry) -> Result<u64> {
-        let payload = bincode::serialize(&entry.event)
-            .map_err(|e| FabricError::Serialization(e.to_string()))?;
-
-        let length = payload.len() as u32;
-        let crc = crc32fast::hash(&payload);
+        let framed = entry.encode()?;
 
         let mut w = self.writer.lock().expect("WAL mutex poisoned");
         let entry_offset = w.offset;
-
-        // Write header: [length: u32 LE] [crc: u32 LE]
-        w.writer.write_all(&length.to_le_bytes())?;
-        w.writer.write_all(&crc.to_le_bytes())?;
-        // Write payload
-        w.writer.write_all(&payload)?;
+        w.writer.write_all(&framed)?;
 
         // Sync if configured for every write.
         if matches!(self.config.sync_mode, SyncMode::EveryWrite) {
@@ -151,84 +265,29 @@ impl WriteAheadLog {
             w.writer.flush()?;
         }
 
-        w.offset += HEADER_SIZE as u64 + payload.len() as u64;
+        w.offset += framed.len() as u64;
 
-        debug!(offset = entry_offset, len = payload.len(), "WAL append");
+        debug!(offset = entry_offset, len = framed.len() - HEADER_SIZE, "WAL append");
         Ok(entry_offset)
     }
 
+    /// Flush buffered entries and fsync the segment, whatever the sync
+    /// mode.
+    pub fn sync(&self) -> Result<()> {
+        let mut w = self.writer.lock().expect("WAL mutex poisoned");
+        w.writer.flush()?;
+        w.writer.get_ref().sync_all()?;
+        Ok(())
+    }
+
     /// Recover all valid entries from the WAL segment.
     ///
     /// Reads the file front-to-back. Entries that fail CRC validation are
-    /// logged and skipped (they represent torn writes from a crash).
+    /// logged and skipped (they represent torn writes from a crash); an
+    /// entry that passes it but cannot be decoded fails recovery.
     pub fn recover(&self) -> Result<Vec<WalEntry>> {
-        let mut file = BufReader::new(File::open(&self.path)?);
-        let file_len = file.get_ref().metadata()?.len();
-        let mut entries = Vec::new();
-        let mut offset: u64 = 0;
-
-        while offset + HEADER_SIZE as u64 <= file_len {
-            file.seek(SeekFrom::Start(offset))?;
-
-            // Read header
-            let mut header_buf = [0u8; HEADER_SIZE];
-            match file.read_exact(&mut header_buf) {
-                Ok(()) => {}
-                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
-                Err(e) => return Err(e.into()),
-            }
-
-            let length = u32::from_le_bytes([header_buf[0], header_buf[1], header_buf[2], header_buf[3]]);
-            let expected_crc = u32::from_le_bytes([header_buf[4], header_buf[5], header_buf[6], header_buf[7]]);
-
-            // Validate length
-            if length == 0 || (offset + HEADER_SIZE as u64 + length as u64) > file_len {
-                warn!(
-                    offset,
-                    length,
-                    file_len,
-                    "invalid WAL entry length; stopping recovery"
-                );
-                break;
-            }
-
-            // Read payload
-            let mut payload = vec![0u8; length as usize];
-            match file.read_exact(&mut payload) {
-                Ok(()) => {}
-                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
-                    warn!(offset, "truncated WAL entry; stopping recovery");
-                    break;
-                }
-                Err(e) => return Err(e.into()),
-            }
-
-            // CRC check
-            let actual_crc = crc32fast::hash(&payload);
-            if actual_crc != expected_crc {
-                warn!(
-                    offset,
-                    expected = expected_crc,
-                    actual = actual_crc,
-                    "CRC mismatch; skipping entry"
-                );
-                offset += HEADER_SIZE as u64 + length as u64;
-                continue;
-            }
-
-            // Deserialize
-            match bincode::deserialize::<FabricEvent>(&payload) {
-                Ok(event) => {
-                    entries.push(WalEntry { event });
-                }
-                Err(e) => {
-                    warn!(offset, error = %e, "failed to deserialize WAL entry; skipping");
-                }
-            }
-
-            offset += HEADER_SIZE as u64 + length as u64;
-        }
-
+        let data = fs::read(&self.path)?;
+        let entries = WalEntry::decode_segment(&data)?;
         debug!(recovered = entries.len(), "WAL recovery complete");
         Ok(entries)
     }
@@ -260,14 +319,17 @@ impl WriteAheadLog {
     pub fn truncate(&self) -> Result<()> {
         let mut w = self.writer.lock().expect("WAL mutex poisoned");
 
-        // Truncate the file to zero.
+        // Truncate the file to an empty segment.
         let file = OpenOptions::new()
             .write(true)
             .truncate(true)
             .open(&self.path)?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(&segment_header())?;
+        writer.flush()?;
 
-        w.writer = BufWriter::new(file);
-        w.offset = 0;
+        w.writer = writer;
+        w.offset = SEGMENT_HEADER_SIZE as u64;
 
         debug!("WAL truncated");
         Ok(())
@@ -294,7 +356,14 @@ impl WriteAheadLog {
             return self.truncate();
         }
 
-        file.seek(SeekFrom::Start(through_offset))?;
+        // A version 1 segment has no header to skip, but gains one below.
+        let mut header = Vec::with_capacity(SEGMENT_HEADER_SIZE);
+        (&file).take(SEGMENT_HEADER_SIZE as u64).read_to_end(&mut header)?;
+        let header_len = match WalEntry::segment_version(&header) {
+            1 => 0,
+            _ => SEGMENT_HEADER_SIZE as u64,
+        };
+        file.seek(SeekFrom::Start(through_offset.max(header_len)))?;
         let mut remaining = Vec::new();
         file.read_to_end(&mut remaining)?;
         drop(file);
@@ -306,10 +375,11 @@ impl WriteAheadLog {
             .truncate(true)
             .open(&self.path)?;
         let mut buf_writer = BufWriter::new(file);
+        buf_writer.write_all(&segment_header())?;
         buf_writer.write_all(&remaining)?;
         buf_writer.flush()?;
 
-        w.offset = remaining.len() as u64;
+        w.offset = (SEGMENT_HEADER_SIZE + remaining.len()) as u64;
         w.writer = buf_writer;
 
         Ok(())
@@ -320,7 +390,7 @@ impl WriteAheadLog {
 mod tests {
     use super::*;
     use crate::event::{EventKind, EventPayload, FabricEvent};
-    use wll_types::{IdentityMaterial, TemporalAnchor, WorldlineId};
+    use wll_types::{IdentityMaterial, ObjectId, ReceiptKind, TemporalAnchor, WorldlineId};
 
     fn test_worldline() -> WorldlineId {
         WorldlineId::derive(&IdentityMaterial::GenesisHash([42u8; 32]))
@@ -378,19 +448,19 @@ mod tests {
         wal.append(&make_entry(2)).unwrap();
         drop(wal);
 
-        // Corrupt the payload of the first entry (byte 8 is first payload byte).
+        // Corrupt the payload of the first entry, after the segment and entry headers.
         {
             let mut file = OpenOptions::new()
                 .write(true)
                 .read(true)
                 .open(&wal_path)
                 .unwrap();
-            file.seek(SeekFrom::Start(HEADER_SIZE as u64)).unwrap();
+            file.seek(SeekFrom::Start((SEGMENT_HEADER_SIZE + HEADER_SIZE) as u64)).unwrap();
             // Flip a byte in the payload.
             let mut buf = [0u8; 1];
             file.read_exact(&mut buf).unwrap();
             buf[0] ^= 0xFF;
-            file.seek(SeekFrom::Start(HEADER_SIZE as u64)).unwrap();
+            file.seek(SeekFrom::Start((SEGMENT_HEADER_SIZE + HEADER_SIZE) as u64)).unwrap();
             file.write_all(&buf).unwrap();
             file.sync_all().unwrap();
         }
@@ -411,10 +481,10 @@ mod tests {
 
         wal.append(&make_entry(1)).unwrap();
         wal.append(&make_entry(2)).unwrap();
-        assert!(wal.offset() > 0);
+        assert!(wal.offset() > SEGMENT_HEADER_SIZE as u64);
 
         wal.truncate().unwrap();
-        assert_eq!(wal.offset(), 0);
+        assert_eq!(wal.offset(), SEGMENT_HEADER_SIZE as u64);
 
         let recovered = wal.recover().unwrap();
         assert!(recovered.is_empty());
@@ -454,11 +524,107 @@ mod tests {
         let off2 = wal.append(&make_entry(2)).unwrap();
         let off3 = wal.append(&make_entry(3)).unwrap();
 
-        assert_eq!(off1, 0);
+        assert_eq!(off1, SEGMENT_HEADER_SIZE as u64);
         assert!(off2 > off1);
         assert!(off3 > off2);
     }
 
+    /// The payloads of the events in the version 1 fixture, in order.
+    fn legacy_payloads() -> Vec<EventPayload> {
+        vec![
+            EventPayload::ObjectRef {
+                object_id: ObjectId::from_bytes(b"outcome"),
+                receipt_kind: ReceiptKind::Outcome,
+            },
+            EventPayload::RefUpdate {
+                ref_name: "refs/heads/main".into(),
+                old_target: Some(ObjectId::from_bytes(b"old")),
+                new_target: ObjectId::from_bytes(b"new"),
+            },
+            EventPayload::RefUpdate {
+                ref_name: "refs/tags/v1".into(),
+                old_target: None,
+                new_target: ObjectId::from_bytes(b"tag"),
+            },
+            EventPayload::Sync {
+                remote_node: "origin".into(),
+                objects_transferred: 7,
+            },
+            EventPayload::Empty,
+        ]
+    }
+
+    #[test]
+    fn version_1_segments_are_read_appended_to_and_upgraded() {
+        // Written by the 0.1.0 WAL, before segments had a header.
+        let legacy = include_bytes!("../tests/fixtures/v0.1.0.wal");
+        let dir = tempfile::tempdir().unwrap();
+        let wal_path = dir.path().join("legacy.wal");
+        fs::write(&wal_path, legacy).unwrap();
+        assert_eq!(WalEntry::segment_version(legacy), 1);
+
+        let recovered = WalEntry::decode_segment(legacy).unwrap();
+        let payloads: Vec<_> = recovered.iter().map(|e| e.event.payload.clone()).collect();
+        assert_eq!(payloads, legacy_payloads());
+        assert!(recovered.iter().all(|e| e.event.verify_integrity()));
+
+        let wal = WriteAheadLog::open(&wal_path, WalConfig::default()).unwrap();
+        let appended = wal.append(&make_entry(6)).unwrap();
+        assert_eq!(appended, legacy.len() as u64);
+        let mut expected = recovered.clone();
+        expected.push(make_entry(6));
+        assert_eq!(wal.recover().unwrap(), expected);
+
+        let upgraded = WalEntry::upgrade_segment(&fs::read(&wal_path).unwrap()).unwrap();
+        assert_eq!(WalEntry::segment_version(&upgraded), WAL_FORMAT.current);
+        assert!(WalEntry::upgrade_segment(&upgraded).is_none());
+        assert_eq!(WalEntry::decode_segment(&upgraded).unwrap(), expected);
+
+        // Checkpointing a version 1 segment leaves it in the current one.
+        wal.checkpoint(appended).unwrap();
+        assert_eq!(WalEntry::segment_version(&fs::read(&wal_path).unwrap()), 2);
+        assert_eq!(wal.recover().unwrap(), [make_entry(6)]);
+    }
+
+    #[test]
+    fn entries_that_pass_their_crc_but_do_not_decode_fail_recovery() {
+        let dir = tempfile::tempdir().unwrap();
+        let wal_path = dir.path().join("undecodable.wal");
+        let wal = WriteAheadLog::open(&wal_path, WalConfig::default()).unwrap();
+        wal.append(&make_entry(1)).unwrap();
+        let offset = wal.offset();
+        drop(wal);
+
+        let payload = [0xff; 8];
+        let mut data = fs::read(&wal_path).unwrap();
+        data.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        data.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+        data.extend_from_slice(&payload);
+        fs::write(&wal_path, &data).unwrap();
+
+        let err = WalEntry::decode_segment(&data).unwrap_err();
+        assert!(matches!(err, FabricError::UndecodableEntry { offset: at, .. } if at == offset));
+        let wal = WriteAheadLog::open(&wal_path, WalConfig::default()).unwrap();
+        assert!(matches!(wal.recover(), Err(FabricError::UndecodableEntry { .. })));
+    }
+
+    #[test]
+    fn segments_from_a_newer_version_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let wal_path = dir.path().join("newer.wal");
+        let mut newer = SEGMENT_MAGIC.to_vec();
+        newer.extend_from_slice(&(WAL_FORMAT.current + 1).to_le_bytes());
+        newer.extend(make_entry(1).encode().unwrap());
+        fs::write(&wal_path, &newer).unwrap();
+
+        assert!(matches!(
+            WalEntry::decode_segment(&newer),
+            Err(FabricError::UnsupportedVersion(v)) if v == WAL_FORMAT.current + 1
+        ));
+        let err = WriteAheadLog::open(&wal_path, WalConfig::default()).err().unwrap();
+        assert!(matches!(err, FabricError::UnsupportedVersion(v) if v == WAL_FORMAT.current + 1));
+    }
+
     #[test]
     fn recovery_survives_truncated_tail() {
         let dir = tempfile::tempdir().unwrap();
@@ -499,4 +665,72 @@ mod tests {
         let recovered = wal.recover().unwrap();
         assert_eq!(recovered.len(), 1);
     }
+
+    mod props {
+        use proptest::prelude::*;
+
+        use super::*;
+
+        fn entries() -> impl Strategy<Value = Vec<WalEntry>> {
+            prop::collection::vec(
+                (any::<u32>(), prop::collection::vec(any::<u8>(), 0..64)),
+                0..8,
+            )
+            .prop_map(|specs| {
+                specs
+                    .into_iter()
+                    .map(|(seq, raw)| WalEntry {
+                        event: FabricEvent::new(
+                            TemporalAnchor::new(seq as u64, seq, 1),
+                            test_worldline(),
+                            EventKind::SyncStarted,
+                            EventPayload::Raw(raw),
+                        ),
+                    })
+                    .collect()
+            })
+        }
+
+        fn segment(entries: &[WalEntry]) -> Vec<u8> {
+            entries.iter().flat_map(|e| e.encode().unwrap()).collect()
+        }
+
+        proptest! {
+            #[test]
+            fn segments_roundtrip(entries in entries()) {
+                prop_assert_eq!(WalEntry::decode_segment(&segment(&entries)).unwrap(), entries);
+            }
+
+            #[test]
+            fn truncated_segments_recover_a_prefix(
+                entries in entries(),
+                cut in any::<prop::sample::Index>(),
+            ) {
+                let bytes = segment(&entries);
+                let recovered =
+                    WalEntry::decode_segment(&bytes[..cut.index(bytes.len() + 1)]).unwrap();
+                prop_assert!(entries.starts_with(&recovered));
+            }
+
+            #[test]
+            fn mutated_segments_never_panic(
+                entries in entries(),
+                flips in prop::collection::vec((any::<prop::sample::Index>(), 1..=255u8), 1..8),
+            ) {
+                let mut bytes = segment(&entries);
+                if !bytes.is_empty() {
+                    for (at, mask) in flips {
+                        let at = at.index(bytes.len());
+                        bytes[at] ^= mask;
+                    }
+                }
+                let _ = WalEntry::decode_segment(&bytes);
+            }
+
+            #[test]
+            fn arbitrary_bytes_never_panic(data in prop::collection::vec(any::<u8>(), 0..1024)) {
+                let _ = WalEntry::decode_segment(&data);
+            }
+        }
+    }
 }
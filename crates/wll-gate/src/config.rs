@@ -1,8 +1,12 @@
+use std::collections::BTreeMap;
 use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
+use wll_config::{AttestationRequirement, GateSettings, StageErrorPolicy};
+use wll_crypto::ContentHasher;
+use wll_types::{AttestationKind, CommitmentClass};
 
-use crate::stages::policy::Policy;
+use crate::stages::policy::{Policy, PolicyRule, PolicyScope};
 
 /// Configuration for the commitment gate pipeline.
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -21,6 +25,45 @@ pub struct GateConfig {
     /// all built-in stages pass without checks. This makes WLL behave like
     /// plain `git commit` for single-user local repositories.
     pub permissive: bool,
+    /// How far back [`GateContext::recent_commitments`] counts.
+    ///
+    /// [`GateContext::recent_commitments`]: crate::GateContext::recent_commitments
+    #[serde(default = "default_recent_window")]
+    pub recent_window: Duration,
+    /// Time each stage may take before it is abandoned and treated as an
+    /// error. With `None` stages run on the caller's thread, unbounded.
+    #[serde(default)]
+    pub stage_timeout: Option<Duration>,
+    /// What a stage error, panic, or timeout does to the proposal.
+    #[serde(default)]
+    pub error_policy: ErrorPolicy,
+    /// Overrides of [`Self::error_policy`] by stage name.
+    #[serde(default)]
+    pub stage_error_policy: BTreeMap<String, ErrorPolicy>,
+}
+
+/// How a stage that errors, panics, or times out affects acceptance.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorPolicy {
+    /// Stop the pipeline and return the error; nothing is accepted.
+    #[default]
+    FailClosed,
+    /// Log the error and carry on as if the stage had passed.
+    FailOpen,
+}
+
+impl From<StageErrorPolicy> for ErrorPolicy {
+    fn from(policy: StageErrorPolicy) -> Self {
+        match policy {
+            StageErrorPolicy::FailClosed => Self::FailClosed,
+            StageErrorPolicy::FailOpen => Self::FailOpen,
+        }
+    }
+}
+
+fn default_recent_window() -> Duration {
+    Duration::from_secs(60 * 60)
 }
 
 impl Default for GateConfig {
@@ -32,6 +75,10 @@ impl Default for GateConfig {
             timeout: Duration::from_secs(30),
             max_targets_per_commitment: 100,
             permissive: false,
+            recent_window: default_recent_window(),
+            stage_timeout: None,
+            error_policy: ErrorPolicy::FailClosed,
+            stage_error_policy: BTreeMap::new(),
         }
     }
 }
@@ -48,4 +95,75 @@ impl GateConfig {
             ..Default::default()
         }
     }
+
+    /// Build the configuration from the `[gate]` config section.
+    ///
+    /// Each requirement becomes a rule of the default policy. With none set
+    /// the result matches [`Self::permissive`] unless `permissive = false`
+    /// is given explicitly.
+    pub fn from_settings(settings: &GateSettings) -> Self {
+        let mut rules = Vec::new();
+        if settings.require_evidence {
+            rules.push(PolicyRule::RequireEvidence);
+        }
+        if settings.require_signatures {
+            rules.push(PolicyRule::RequireSignature);
+        }
+        if let Some(max) = settings.max_targets_per_commitment {
+            rules.push(PolicyRule::MaxTargets(max));
+        }
+        for (class, requirement) in &settings.require_attestation {
+            rules.push(PolicyRule::RequireAttestation {
+                class: CommitmentClass::from_name(class),
+                kind: match requirement {
+                    AttestationRequirement::Any => None,
+                    AttestationRequirement::Human => Some(AttestationKind::Human),
+                    AttestationRequirement::Workload => Some(AttestationKind::Workload),
+                },
+            });
+        }
+
+        let mut config = Self::default();
+        if !rules.is_empty() {
+            config.default_policy = Policy {
+                id: "config".into(),
+                name: "Repository configuration".into(),
+                rules,
+                applies_to: PolicyScope::All,
+            };
+        }
+        config.require_evidence = settings.require_evidence;
+        config.require_signatures = settings.require_signatures;
+        if let Some(max) = settings.max_targets_per_commitment {
+            config.max_targets_per_commitment = max;
+        }
+        if let Some(secs) = settings.timeout_secs {
+            config.timeout = Duration::from_secs(secs);
+        }
+        config.stage_timeout = settings.stage_timeout_secs.map(Duration::from_secs);
+        if let Some(policy) = settings.error_policy {
+            config.error_policy = policy.into();
+        }
+        config.stage_error_policy = settings
+            .stage_error_policy
+            .iter()
+            .map(|(stage, &policy)| (stage.clone(), policy.into()))
+            .collect();
+        config.permissive = settings.is_permissive();
+        config
+    }
+
+    /// BLAKE3 digest of this configuration, recorded in every
+    /// [`GateResult`](crate::GateResult) it produces.
+    pub fn digest(&self) -> [u8; 32] {
+        match ContentHasher::new("wll-gate-config-v1").hash_json(self) {
+            Ok(oid) => *oid.as_bytes(),
+            Err(_) => [0u8; 32],
+        }
+    }
+
+    /// The error policy of the stage named `stage`.
+    pub fn error_policy_for(&self, stage: &str) -> ErrorPolicy {
+        self.stage_error_policy.get(stage).copied().unwrap_or(self.error_policy)
+    }
 }
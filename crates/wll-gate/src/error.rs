@@ -1,5 +1,7 @@
 use std::fmt;
 
+use wll_types::{Coded, ErrorCode};
+
 /// Errors that can occur during gate evaluation.
 #[derive(Debug, thiserror::Error)]
 pub enum GateError {
@@ -19,6 +21,13 @@ pub enum GateError {
     #[error("gate evaluation timed out after {0:?}")]
     Timeout(std::time::Duration),
 
+    /// A stage took longer than the configured per-stage timeout.
+    #[error("stage '{stage}' timed out after {after:?}")]
+    StageTimeout {
+        stage: String,
+        after: std::time::Duration,
+    },
+
     /// A stage returned an unexpected error.
     #[error("stage error in '{stage}': {message}")]
     StageError { stage: String, message: String },
@@ -26,6 +35,38 @@ pub enum GateError {
     /// Configuration is invalid.
     #[error("configuration error: {0}")]
     Config(String),
+
+    /// Reading or writing a stored policy version failed.
+    #[error("policy store error: {0}")]
+    PolicyStore(String),
+
+    /// Looking up the gate context failed.
+    #[error("gate context error: {0}")]
+    Context(String),
+
+    /// Encoding or decoding a gate result failed.
+    #[error("gate result serialization error: {0}")]
+    Serialization(String),
+
+    /// A serialized gate result is in a format newer than this build reads.
+    #[error("gate result format version {0} is not supported")]
+    UnsupportedVersion(u32),
+}
+
+impl Coded for GateError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::Validation(_) => ErrorCode::GateValidation,
+            Self::CapabilityDenied(_) => ErrorCode::CapabilityDenied,
+            Self::PolicyViolation(_) => ErrorCode::GateRejected,
+            Self::Timeout(_) | Self::StageTimeout { .. } => ErrorCode::GateTimeout,
+            Self::StageError { .. } => ErrorCode::GateStageFailed,
+            Self::Config(_) => ErrorCode::GateConfig,
+            Self::PolicyStore(_) | Self::Context(_) => ErrorCode::Io,
+            Self::Serialization(_) => ErrorCode::Serialization,
+            Self::UnsupportedVersion(_) => ErrorCode::Unsupported,
+        }
+    }
 }
 
 impl GateError {
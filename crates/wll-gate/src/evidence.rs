@@ -0,0 +1,373 @@
+//! Resolving evidence references and checking pinned digests.
+//!
+//! An evidence reference is a URI, optionally pinned to the digest of the
+//! content it names with a `#<algo>=<hex>` fragment:
+//!
+//! ```text
+//! https://ci.example/run/81/report.json#sha256=9f86d0...
+//! file://docs/threat-model.md#blake3=4e2a11...
+//! obj://5d41402a...            (object ids pin themselves)
+//! issue://PROJ-42              (opaque; no resolver)
+//! ```
+//!
+//! An [`EvidenceResolver`] fetches the content for the schemes it handles;
+//! the [`EvidenceStage`](crate::EvidenceStage) checks the result against
+//! the pin.
+
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use wll_store::{ObjectStore, Tombstone};
+use wll_types::{Coded, ErrorCode, HashAlgo, ObjectId};
+
+/// A parsed evidence URI.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EvidenceRef {
+    /// The reference as written in the bundle.
+    pub uri: String,
+    /// Lower-case scheme, e.g. `https`.
+    pub scheme: String,
+    /// Everything after `<scheme>://`, without the pin fragment.
+    pub location: String,
+    /// Digest the content must match.
+    pub pin: Option<EvidencePin>,
+}
+
+/// Expected digest of a piece of evidence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EvidencePin {
+    pub algo: HashAlgo,
+    pub digest: [u8; 32],
+}
+
+impl fmt::Display for EvidencePin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.algo, hex::encode(self.digest))
+    }
+}
+
+impl EvidencePin {
+    /// Whether `content` hashes to the pinned digest.
+    pub fn matches(&self, content: &[u8]) -> bool {
+        wll_crypto::digest(self.algo, content) == self.digest
+    }
+}
+
+impl EvidenceRef {
+    /// Parse `uri`. References without `://` have an empty scheme.
+    ///
+    /// A fragment is only treated as a pin when it has the form
+    /// `<algo>=<hex>` with a known algorithm; a malformed pin is an error so
+    /// a typo can't silently disable verification.
+    pub fn parse(uri: &str) -> Result<Self, EvidenceError> {
+        let (scheme, rest) = match uri.split_once("://") {
+            Some((scheme, rest)) => (scheme.to_ascii_lowercase(), rest),
+            None => (String::new(), uri),
+        };
+        let (location, pin) = match rest.rsplit_once('#') {
+            Some((location, fragment)) => match fragment.split_once('=') {
+                Some((algo, digits)) if algo.parse::<HashAlgo>().is_ok() => {
+                    (location, Some(parse_pin(uri, algo, digits)?))
+                }
+                _ => (rest, None),
+            },
+            None => (rest, None),
+        };
+        Ok(Self {
+            uri: uri.to_string(),
+            scheme,
+            location: location.to_string(),
+            pin,
+        })
+    }
+}
+
+fn parse_pin(uri: &str, algo: &str, digits: &str) -> Result<EvidencePin, EvidenceError> {
+    let invalid = || EvidenceError::Invalid(format!("{uri}: malformed {algo} pin"));
+    let bytes = hex::decode(digits).map_err(|_| invalid())?;
+    Ok(EvidencePin {
+        algo: algo.parse().map_err(|_| invalid())?,
+        digest: bytes.try_into().map_err(|_| invalid())?,
+    })
+}
+
+/// Why a reference could not be resolved.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum EvidenceError {
+    /// The referenced content does not exist.
+    #[error("not found: {0}")]
+    NotFound(String),
+    /// The reference is malformed or unacceptable.
+    #[error("invalid evidence: {0}")]
+    Invalid(String),
+    /// The source could not be reached; trying again later may succeed.
+    #[error("evidence unavailable: {0}")]
+    Unavailable(String),
+    /// The content was redacted; only its tombstone remains.
+    #[error("evidence redacted: {0}")]
+    Redacted(String),
+}
+
+impl Coded for EvidenceError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::NotFound(_) => ErrorCode::EvidenceNotFound,
+            Self::Invalid(_) => ErrorCode::EvidenceInvalid,
+            Self::Unavailable(_) => ErrorCode::EvidenceUnavailable,
+            Self::Redacted(_) => ErrorCode::ObjectRedacted,
+        }
+    }
+}
+
+/// Fetches the content behind evidence references of some URI schemes.
+pub trait EvidenceResolver: Send + Sync {
+    /// Schemes handled, lower case (e.g. `["http", "https"]`).
+    fn schemes(&self) -> &[&str];
+
+    /// Fetch the content `reference` names.
+    ///
+    /// The caller checks the pin; resolvers whose references are
+    /// self-certifying (like object ids) verify that themselves.
+    fn resolve(&self, reference: &EvidenceRef) -> Result<Vec<u8>, EvidenceError>;
+}
+
+/// `file://` references, relative to a root directory (usually the work
+/// tree). Absolute paths (`file:///...`) are used as given.
+pub struct FileResolver {
+    root: PathBuf,
+}
+
+impl FileResolver {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl EvidenceResolver for FileResolver {
+    fn schemes(&self) -> &[&str] {
+        &["file"]
+    }
+
+    fn resolve(&self, reference: &EvidenceRef) -> Result<Vec<u8>, EvidenceError> {
+        let path = self.root.join(&reference.location);
+        std::fs::read(&path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => EvidenceError::NotFound(path.display().to_string()),
+            _ => EvidenceError::Invalid(format!("{}: {e}", path.display())),
+        })
+    }
+}
+
+/// `obj://<id>` references to objects in a store. The content is checked
+/// against the id, so these need no separate pin.
+pub struct ObjectStoreResolver {
+    store: Arc<dyn ObjectStore>,
+}
+
+impl ObjectStoreResolver {
+    pub fn new(store: Arc<dyn ObjectStore>) -> Self {
+        Self { store }
+    }
+}
+
+impl EvidenceResolver for ObjectStoreResolver {
+    fn schemes(&self) -> &[&str] {
+        &["obj"]
+    }
+
+    fn resolve(&self, reference: &EvidenceRef) -> Result<Vec<u8>, EvidenceError> {
+        let id = ObjectId::from_hex(&reference.location)
+            .map_err(|e| EvidenceError::Invalid(format!("{}: {e}", reference.uri)))?;
+        let object = self
+            .store
+            .read(&id)
+            .map_err(|e| EvidenceError::Unavailable(e.to_string()))?
+            .ok_or_else(|| EvidenceError::NotFound(reference.uri.clone()))?;
+        if object.is_tombstone() {
+            let reason = Tombstone::from_stored_object(&object)
+                .map_or_else(|e| e.to_string(), |tombstone| tombstone.reason);
+            return Err(EvidenceError::Redacted(format!("{}: {reason}", reference.uri)));
+        }
+        if object.compute_id() != id {
+            return Err(EvidenceError::Invalid(format!(
+                "{}: stored object does not match its id",
+                reference.uri
+            )));
+        }
+        Ok(object.data)
+    }
+}
+
+/// `http://` and `https://` references, fetched with a GET.
+///
+/// A URL's content can change after the commitment is made, so unpinned
+/// URLs are rejected. Responses larger than the size limit are refused.
+#[cfg(feature = "http")]
+pub struct HttpResolver {
+    agent: ureq::Agent,
+    max_bytes: u64,
+}
+
+#[cfg(feature = "http")]
+impl HttpResolver {
+    /// Resolver with a 30 second timeout and a 64 MiB size limit.
+    pub fn new() -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new()
+                .timeout(std::time::Duration::from_secs(30))
+                .build(),
+            max_bytes: 64 * 1024 * 1024,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.agent = ureq::AgentBuilder::new().timeout(timeout).build();
+        self
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+}
+
+#[cfg(feature = "http")]
+impl Default for HttpResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "http")]
+impl EvidenceResolver for HttpResolver {
+    fn schemes(&self) -> &[&str] {
+        &["http", "https"]
+    }
+
+    fn resolve(&self, reference: &EvidenceRef) -> Result<Vec<u8>, EvidenceError> {
+        use std::io::Read;
+
+        if reference.pin.is_none() {
+            return Err(EvidenceError::Invalid(format!(
+                "{}: URL evidence must be pinned with #sha256=<hex> or #blake3=<hex>",
+                reference.uri
+            )));
+        }
+        let url = format!("{}://{}", reference.scheme, reference.location);
+        let response = match self.agent.get(&url).call() {
+            Ok(response) => response,
+            Err(ureq::Error::Status(404 | 410, _)) => {
+                return Err(EvidenceError::NotFound(url));
+            }
+            Err(ureq::Error::Status(code, _)) if code < 500 => {
+                return Err(EvidenceError::Invalid(format!("{url}: HTTP {code}")));
+            }
+            Err(e) => return Err(EvidenceError::Unavailable(format!("{url}: {e}"))),
+        };
+        let mut content = Vec::new();
+        response
+            .into_reader()
+            .take(self.max_bytes + 1)
+            .read_to_end(&mut content)
+            .map_err(|e| EvidenceError::Unavailable(format!("{url}: {e}")))?;
+        if content.len() as u64 > self.max_bytes {
+            return Err(EvidenceError::Invalid(format!(
+                "{url}: larger than {} bytes",
+                self.max_bytes
+            )));
+        }
+        Ok(content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wll_store::{Blob, InMemoryObjectStore};
+
+    #[test]
+    fn parse_splits_scheme_location_and_pin() {
+        let digest = wll_crypto::digest(HashAlgo::Sha256, b"report");
+        let uri = format!("HTTPS://ci.example/r.json#sha256={}", hex::encode(digest));
+        let parsed = EvidenceRef::parse(&uri).unwrap();
+        assert_eq!(parsed.scheme, "https");
+        assert_eq!(parsed.location, "ci.example/r.json");
+        let pin = parsed.pin.unwrap();
+        assert!(pin.matches(b"report"));
+        assert!(!pin.matches(b"tampered"));
+
+        let opaque = EvidenceRef::parse("issue://PROJ-42").unwrap();
+        assert_eq!((opaque.scheme.as_str(), opaque.pin), ("issue", None));
+        let anchor = EvidenceRef::parse("doc://spec#section-2").unwrap();
+        assert_eq!(anchor.location, "spec#section-2");
+        assert!(matches!(
+            EvidenceRef::parse("file://a#blake3=beef"),
+            Err(EvidenceError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn file_and_object_resolvers() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("spec.md"), b"spec").unwrap();
+        let files = FileResolver::new(dir.path());
+        let found = files.resolve(&EvidenceRef::parse("file://spec.md").unwrap());
+        assert_eq!(found.unwrap(), b"spec");
+        assert!(matches!(
+            files.resolve(&EvidenceRef::parse("file://missing.md").unwrap()),
+            Err(EvidenceError::NotFound(_))
+        ));
+
+        let store = Arc::new(InMemoryObjectStore::new());
+        let id = store
+            .write(&Blob::new(b"log".to_vec()).to_stored_object())
+            .unwrap();
+        let objects = ObjectStoreResolver::new(store);
+        let uri = format!("obj://{}", id.to_hex());
+        assert!(objects.resolve(&EvidenceRef::parse(&uri).unwrap()).is_ok());
+        let missing = format!("obj://{}", ObjectId::from_bytes(b"nope").to_hex());
+        assert!(matches!(
+            objects.resolve(&EvidenceRef::parse(&missing).unwrap()),
+            Err(EvidenceError::NotFound(_))
+        ));
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn http_resolver_fetches_pinned_urls() {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            for status in ["200 OK", "404 Not Found"] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = [0u8; 1024];
+                let _ = stream.read(&mut request).unwrap();
+                let body = "evidence";
+                write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+            }
+        });
+
+        let resolver = HttpResolver::new();
+        let pin = hex::encode(wll_crypto::digest(HashAlgo::Blake3, b"evidence"));
+        let pinned = EvidenceRef::parse(&format!("http://{addr}/e#blake3={pin}")).unwrap();
+        assert_eq!(resolver.resolve(&pinned).unwrap(), b"evidence");
+        assert!(matches!(
+            resolver.resolve(&pinned),
+            Err(EvidenceError::NotFound(_))
+        ));
+        server.join().unwrap();
+
+        let unpinned = EvidenceRef::parse(&format!("http://{addr}/e")).unwrap();
+        assert!(matches!(
+            resolver.resolve(&unpinned),
+            Err(EvidenceError::Invalid(_))
+        ));
+    }
+}
@@ -1,27 +1,52 @@
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Arc};
+use std::thread;
 use std::time::{Duration, Instant};
 
-use wll_crypto::ContentHasher;
+use serde::{Deserialize, Serialize};
 use wll_types::commitment::Decision;
+use wll_types::TemporalAnchor;
 
-use crate::config::GateConfig;
+use crate::config::{ErrorPolicy, GateConfig};
 use crate::error::GateError;
-use crate::stage::{CommitmentProposal, GateContext, GateStage, StageDecision, StageResult};
+use crate::policy_store::policy_hash;
+use crate::provider::ContextProvider;
+use crate::stage::{
+    micros, CommitmentProposal, GateContext, GateStage, RuleCheck, StageDecision, StageResult,
+};
 use crate::stages::{CapabilityStage, PolicyStage, ValidationStage};
 
 // ---------------------------------------------------------------------------
 // GateResult
 // ---------------------------------------------------------------------------
 
+/// Version of the serialized [`GateResult`] format this build writes.
+///
+/// Bumped whenever a field changes meaning or is removed; adding a field
+/// with a default does not need a bump.
+pub const GATE_RESULT_VERSION: u32 = 1;
+
 /// The outcome of running a proposal through the full gate pipeline.
-#[derive(Clone, Debug)]
+///
+/// Results serialize to a stable, versioned format
+/// ([`to_json`](Self::to_json)) so they can be stored alongside receipts,
+/// sent to a server, and compared with results of other versions.
+/// Durations are serialized in microseconds.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct GateResult {
+    /// Format version, [`GATE_RESULT_VERSION`] for results made here.
+    pub version: u32,
     /// The final decision: accepted, rejected, or deferred.
     pub decision: Decision,
     /// BLAKE3 hash of the serialized policy configuration that was active.
     pub policy_hash: [u8; 32],
+    /// Digest of the [`GateConfig`] the gate ran with.
+    pub config_digest: [u8; 32],
     /// Per-stage results in evaluation order.
     pub stage_results: Vec<StageResult>,
     /// Total wall-clock time for the pipeline evaluation.
+    #[serde(rename = "elapsed_us", with = "micros")]
     pub elapsed: Duration,
 }
 
@@ -30,6 +55,83 @@ impl GateResult {
     pub fn is_accepted(&self) -> bool {
         self.decision.is_accepted()
     }
+
+    /// The result in its serialized form.
+    pub fn to_json(&self) -> Result<Vec<u8>, GateError> {
+        serde_json::to_vec(self).map_err(|e| GateError::Serialization(e.to_string()))
+    }
+
+    /// Read a serialized result, refusing formats newer than
+    /// [`GATE_RESULT_VERSION`].
+    pub fn from_json(bytes: &[u8]) -> Result<Self, GateError> {
+        let value: serde_json::Value =
+            serde_json::from_slice(bytes).map_err(|e| GateError::Serialization(e.to_string()))?;
+        let version = value
+            .get("version")
+            .and_then(serde_json::Value::as_u64)
+            .ok_or_else(|| GateError::Serialization("missing format version".into()))?;
+        let version = u32::try_from(version).unwrap_or(u32::MAX);
+        if version > GATE_RESULT_VERSION {
+            return Err(GateError::UnsupportedVersion(version));
+        }
+        serde_json::from_value(value).map_err(|e| GateError::Serialization(e.to_string()))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// GateSimulation
+// ---------------------------------------------------------------------------
+
+/// One stage's part in a [`GateSimulation`].
+#[derive(Clone, Debug, Serialize)]
+pub struct StageSimulation {
+    /// Name of the stage.
+    pub stage_name: String,
+    /// The stage's decision: its first check that did not pass, or `Pass`.
+    pub decision: StageDecision,
+    /// Every check the stage made, in order.
+    pub checks: Vec<RuleCheck>,
+    /// Wall-clock time the stage took.
+    pub elapsed: Duration,
+}
+
+/// The outcome of [`CommitmentGate::simulate`]: every stage and rule,
+/// evaluated without stopping at the first failure.
+#[derive(Clone, Debug, Serialize)]
+pub struct GateSimulation {
+    /// What [`CommitmentGate::evaluate_with_context`] would decide.
+    pub decision: Decision,
+    /// Whether the gate is permissive, so accepts whatever the stages say.
+    pub permissive: bool,
+    /// BLAKE3 hash of the active policy configuration.
+    pub policy_hash: [u8; 32],
+    /// Per-stage results in pipeline order.
+    pub stages: Vec<StageSimulation>,
+    /// Total wall-clock time for the simulation.
+    pub elapsed: Duration,
+}
+
+impl GateSimulation {
+    /// Returns `true` if the gate would accept the proposal.
+    pub fn is_accepted(&self) -> bool {
+        self.decision.is_accepted()
+    }
+
+    /// Returns `true` if every stage passed, as an enforcing gate requires.
+    pub fn all_passed(&self) -> bool {
+        self.stages.iter().all(|s| s.decision.is_pass())
+    }
+
+    /// Every check that did not pass, with the stage it belongs to.
+    pub fn failures(&self) -> impl Iterator<Item = (&str, &RuleCheck)> {
+        self.stages.iter().flat_map(|stage| {
+            stage
+                .checks
+                .iter()
+                .filter(|check| !check.decision.is_pass())
+                .map(move |check| (stage.stage_name.as_str(), check))
+        })
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -40,9 +142,15 @@ impl GateResult {
 /// proposal must pass through before being accepted into the ledger.
 ///
 /// The gate is the ONLY path to the ledger -- no bypass is possible.
+///
+/// A stage that returns an error, panics, or outlives
+/// [`GateConfig::stage_timeout`] is handled by its
+/// [`ErrorPolicy`]: fail-closed stops the evaluation with the error,
+/// fail-open records it and treats the stage as passed.
 pub struct CommitmentGate {
-    stages: Vec<Box<dyn GateStage>>,
+    stages: Vec<Arc<dyn GateStage>>,
     config: GateConfig,
+    provider: Option<Arc<dyn ContextProvider>>,
 }
 
 impl CommitmentGate {
@@ -54,6 +162,7 @@ impl CommitmentGate {
         Self {
             stages: Vec::new(),
             config,
+            provider: None,
         }
     }
 
@@ -69,7 +178,19 @@ impl CommitmentGate {
 
     /// Append a stage to the end of the pipeline.
     pub fn add_stage(&mut self, stage: Box<dyn GateStage>) {
-        self.stages.push(stage);
+        self.stages.push(Arc::from(stage));
+    }
+
+    /// Look up capabilities, policies, and recent commitment counts from
+    /// `provider` on every evaluation, adding them to the caller's context.
+    pub fn with_context_provider(mut self, provider: Arc<dyn ContextProvider>) -> Self {
+        self.provider = Some(provider);
+        self
+    }
+
+    /// The provider the context is filled from, if one is attached.
+    pub fn context_provider(&self) -> Option<&Arc<dyn ContextProvider>> {
+        self.provider.as_ref()
     }
 
     /// The current configuration.
@@ -82,40 +203,79 @@ impl CommitmentGate {
         self.stages.len()
     }
 
+    /// Whether the pipeline has a stage named `name`.
+    pub fn has_stage(&self, name: &str) -> bool {
+        self.stages.iter().any(|stage| stage.name() == name)
+    }
+
     /// Evaluate a proposal through the full pipeline.
     ///
     /// The pipeline is **fail-fast**: the first stage that fails stops
     /// evaluation and produces a `Rejected` decision. If all stages pass
     /// the decision is `Accepted`.
     pub fn evaluate(&self, proposal: &CommitmentProposal) -> Result<GateResult, GateError> {
-        let pipeline_start = Instant::now();
-
-        // Compute policy hash from the active configuration.
-        let policy_hash = self.compute_policy_hash();
-
-        // Build the shared context.
         let mut context = GateContext::minimal(proposal.proposer.clone());
         context.policies.push(self.config.default_policy.clone());
+        self.evaluate_with_context(proposal, &mut context)
+    }
+
+    /// Evaluate with an explicit context (for advanced use cases where the
+    /// caller provides capabilities and policies).
+    ///
+    /// Runs inside a `gate.evaluate` span recording the decision, with a
+    /// `gate.stage` span per stage.
+    pub fn evaluate_with_context(
+        &self,
+        proposal: &CommitmentProposal,
+        context: &mut GateContext,
+    ) -> Result<GateResult, GateError> {
+        let span = tracing::info_span!(
+            "gate.evaluate",
+            intent = %proposal.intent,
+            class = ?proposal.class,
+            decision = tracing::field::Empty,
+        );
+        let _entered = span.enter();
+        let result = self.run_pipeline(proposal, context);
+        if let Ok(result) = &result {
+            span.record("decision", tracing::field::display(&result.decision));
+        }
+        result
+    }
+
+    fn run_pipeline(
+        &self,
+        proposal: &CommitmentProposal,
+        context: &mut GateContext,
+    ) -> Result<GateResult, GateError> {
+        let pipeline_start = Instant::now();
+        let policy_hash = self.compute_policy_hash();
+        let config_digest = self.config.digest();
 
-        // In permissive mode, skip all stage evaluations and accept.
         if self.config.permissive {
             return Ok(GateResult {
+                version: GATE_RESULT_VERSION,
                 decision: Decision::Accepted,
                 policy_hash,
+                config_digest,
                 stage_results: Vec::new(),
                 elapsed: pipeline_start.elapsed(),
             });
         }
 
+        self.provide(proposal, context)?;
         let mut stage_results = Vec::with_capacity(self.stages.len());
 
         for stage in &self.stages {
+            let _stage = tracing::debug_span!("gate.stage", stage = stage.name()).entered();
             let stage_start = Instant::now();
-            let decision = stage.evaluate(proposal, &context)?;
+            let (decision, ignored) =
+                self.tolerate(stage.name(), self.isolate(stage, proposal, context, evaluate))?;
+            let decision = decision.unwrap_or(StageDecision::Pass);
             let elapsed = stage_start.elapsed();
 
             let (passed, reason) = match &decision {
-                StageDecision::Pass => (true, None),
+                StageDecision::Pass => (true, ignored),
                 StageDecision::Fail { reason } => (false, Some(reason.clone())),
                 StageDecision::Defer { reason, .. } => (false, Some(reason.clone())),
             };
@@ -130,23 +290,25 @@ impl CommitmentGate {
             stage_results.push(result.clone());
             context.previous_stages.push(result);
 
-            // Fail-fast: stop on first failure.
             if let StageDecision::Fail { reason } = decision {
                 return Ok(GateResult {
+                    version: GATE_RESULT_VERSION,
                     decision: Decision::Rejected { reason },
                     policy_hash,
+                    config_digest,
                     stage_results,
                     elapsed: pipeline_start.elapsed(),
                 });
             }
 
-            // Defer: stop and report deferral.
             if let StageDecision::Defer { reason, .. } = decision {
                 return Ok(GateResult {
+                    version: GATE_RESULT_VERSION,
                     decision: Decision::Rejected {
                         reason: format!("deferred: {reason}"),
                     },
                     policy_hash,
+                    config_digest,
                     stage_results,
                     elapsed: pipeline_start.elapsed(),
                 });
@@ -154,90 +316,220 @@ impl CommitmentGate {
         }
 
         Ok(GateResult {
+            version: GATE_RESULT_VERSION,
             decision: Decision::Accepted,
             policy_hash,
+            config_digest,
             stage_results,
             elapsed: pipeline_start.elapsed(),
         })
     }
 
-    /// Evaluate with an explicit context (for advanced use cases where the
-    /// caller provides capabilities and policies).
-    pub fn evaluate_with_context(
+    /// Dry-run a proposal: run every stage, and every rule within a stage,
+    /// without stopping at the first failure, and report what each decided.
+    ///
+    /// Nothing is recorded and `context` is left untouched. Stages run even
+    /// when the gate is permissive, so a policy can be tried out before it
+    /// is enforced; [`GateSimulation::decision`] still reports what the gate
+    /// would actually decide.
+    pub fn simulate(
         &self,
         proposal: &CommitmentProposal,
-        context: &mut GateContext,
-    ) -> Result<GateResult, GateError> {
-        let pipeline_start = Instant::now();
-        let policy_hash = self.compute_policy_hash();
-
-        if self.config.permissive {
-            return Ok(GateResult {
-                decision: Decision::Accepted,
-                policy_hash,
-                stage_results: Vec::new(),
-                elapsed: pipeline_start.elapsed(),
-            });
-        }
-
-        let mut stage_results = Vec::with_capacity(self.stages.len());
+        context: &GateContext,
+    ) -> Result<GateSimulation, GateError> {
+        let _span = tracing::info_span!(
+            "gate.simulate",
+            intent = %proposal.intent,
+            class = ?proposal.class,
+        )
+        .entered();
+        let start = Instant::now();
+        let mut context = context.clone();
+        self.provide(proposal, &mut context)?;
+        let mut stages = Vec::with_capacity(self.stages.len());
 
         for stage in &self.stages {
             let stage_start = Instant::now();
-            let decision = stage.evaluate(proposal, context)?;
+            let (checks, ignored) =
+                self.tolerate(stage.name(), self.isolate(stage, proposal, &context, explain))?;
+            let checks = checks.unwrap_or_else(|| {
+                vec![RuleCheck {
+                    policy: None,
+                    rule: None,
+                    decision: StageDecision::Pass,
+                }]
+            });
             let elapsed = stage_start.elapsed();
-
-            let (passed, reason) = match &decision {
-                StageDecision::Pass => (true, None),
-                StageDecision::Fail { reason } => (false, Some(reason.clone())),
-                StageDecision::Defer { reason, .. } => (false, Some(reason.clone())),
+            let decision = checks
+                .iter()
+                .find(|check| !check.decision.is_pass())
+                .map_or(StageDecision::Pass, |check| check.decision.clone());
+
+            let reason = match &decision {
+                StageDecision::Pass => ignored,
+                StageDecision::Fail { reason } | StageDecision::Defer { reason, .. } => {
+                    Some(reason.clone())
+                }
             };
-
-            let result = StageResult {
+            context.previous_stages.push(StageResult {
                 stage_name: stage.name().to_string(),
-                passed,
+                passed: decision.is_pass(),
                 reason,
                 elapsed,
-            };
-
-            stage_results.push(result.clone());
-            context.previous_stages.push(result);
+            });
+            stages.push(StageSimulation {
+                stage_name: stage.name().to_string(),
+                decision,
+                checks,
+                elapsed,
+            });
+        }
 
-            if let StageDecision::Fail { reason } = decision {
-                return Ok(GateResult {
-                    decision: Decision::Rejected { reason },
-                    policy_hash,
-                    stage_results,
-                    elapsed: pipeline_start.elapsed(),
-                });
+        let decision = if self.config.permissive {
+            Decision::Accepted
+        } else {
+            match stages.iter().map(|s| &s.decision).find(|d| !d.is_pass()) {
+                None | Some(StageDecision::Pass) => Decision::Accepted,
+                Some(StageDecision::Fail { reason }) => Decision::Rejected {
+                    reason: reason.clone(),
+                },
+                Some(StageDecision::Defer { reason, .. }) => Decision::Rejected {
+                    reason: format!("deferred: {reason}"),
+                },
             }
+        };
+
+        Ok(GateSimulation {
+            decision,
+            permissive: self.config.permissive,
+            policy_hash: self.compute_policy_hash(),
+            stages,
+            elapsed: start.elapsed(),
+        })
+    }
 
-            if let StageDecision::Defer { reason, .. } = decision {
-                return Ok(GateResult {
-                    decision: Decision::Rejected {
-                        reason: format!("deferred: {reason}"),
-                    },
-                    policy_hash,
-                    stage_results,
-                    elapsed: pipeline_start.elapsed(),
-                });
-            }
+    /// Add what the context provider knows as of `context.now`: the
+    /// proposer's capabilities, the worldline's policies, the proposer's
+    /// commitments within the recent window, and, for a proposal writing
+    /// gate context, whether it would be the worldline's genesis.
+    fn provide(
+        &self,
+        proposal: &CommitmentProposal,
+        context: &mut GateContext,
+    ) -> Result<(), GateError> {
+        let Some(provider) = &self.provider else {
+            return Ok(());
+        };
+        let now = context.now;
+        let window = u64::try_from(self.config.recent_window.as_millis()).unwrap_or(u64::MAX);
+        let since = TemporalAnchor::new(now.physical_ms.saturating_sub(window), 0, 0);
+
+        context
+            .capabilities
+            .extend(provider.capabilities(&proposal.proposer, &now)?);
+        context
+            .policies
+            .extend(provider.policies(&context.worldline, &now)?);
+        context.recent_commitments = provider.commitment_count(&proposal.proposer, &since, &now)?;
+        if !context.context_keys.is_empty() {
+            context.genesis = provider.is_unwritten(&context.worldline, &now)?;
         }
-
-        Ok(GateResult {
-            decision: Decision::Accepted,
-            policy_hash,
-            stage_results,
-            elapsed: pipeline_start.elapsed(),
-        })
+        Ok(())
     }
 
     /// Compute a BLAKE3 hash of the active policy configuration.
     fn compute_policy_hash(&self) -> [u8; 32] {
-        let hasher = ContentHasher::new("wll-gate-policy-v1");
-        match hasher.hash_json(&self.config.default_policy) {
-            Ok(oid) => *oid.as_bytes(),
-            Err(_) => [0u8; 32],
+        policy_hash(&self.config.default_policy)
+    }
+
+    /// Run `call` on `stage`, turning a panic into a stage error. With a
+    /// stage timeout configured the stage runs on its own thread, which is
+    /// abandoned if it has not answered in time.
+    fn isolate<T: Send + 'static>(
+        &self,
+        stage: &Arc<dyn GateStage>,
+        proposal: &CommitmentProposal,
+        context: &GateContext,
+        call: StageCall<T>,
+    ) -> Result<T, GateError> {
+        let name = stage.name().to_string();
+        let Some(limit) = self.config.stage_timeout else {
+            return catch_panic(&name, || call(stage.as_ref(), proposal, context));
+        };
+
+        let (sender, receiver) = mpsc::channel();
+        let (stage, proposal, context) = (stage.clone(), proposal.clone(), context.clone());
+        let thread_name = name.clone();
+        thread::Builder::new()
+            .name(format!("gate-{name}"))
+            .spawn(move || {
+                let result =
+                    catch_panic(&thread_name, || call(stage.as_ref(), &proposal, &context));
+                let _ = sender.send(result);
+            })
+            .map_err(|e| GateError::stage(&name, format!("cannot start stage thread: {e}")))?;
+        match receiver.recv_timeout(limit) {
+            Ok(result) => result,
+            Err(mpsc::RecvTimeoutError::Timeout) => Err(GateError::StageTimeout {
+                stage: name,
+                after: limit,
+            }),
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                Err(GateError::stage(name, "stage thread exited without a decision"))
+            }
         }
     }
+
+    /// Apply the stage's [`ErrorPolicy`] to `result`: errors of fail-open
+    /// stages become `None`, with the error as the reason to record.
+    fn tolerate<T>(
+        &self,
+        stage: &str,
+        result: Result<T, GateError>,
+    ) -> Result<(Option<T>, Option<String>), GateError> {
+        match result {
+            Ok(value) => Ok((Some(value), None)),
+            Err(e) if self.config.error_policy_for(stage) == ErrorPolicy::FailOpen => {
+                tracing::warn!(stage, error = %e, "ignoring error of fail-open gate stage");
+                Ok((None, Some(format!("error ignored (fail-open): {e}"))))
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// How [`CommitmentGate::isolate`] invokes a stage.
+type StageCall<T> = fn(&dyn GateStage, &CommitmentProposal, &GateContext) -> Result<T, GateError>;
+
+fn evaluate(
+    stage: &dyn GateStage,
+    proposal: &CommitmentProposal,
+    context: &GateContext,
+) -> Result<StageDecision, GateError> {
+    stage.evaluate(proposal, context)
+}
+
+fn explain(
+    stage: &dyn GateStage,
+    proposal: &CommitmentProposal,
+    context: &GateContext,
+) -> Result<Vec<RuleCheck>, GateError> {
+    stage.explain(proposal, context)
+}
+
+fn catch_panic<T>(
+    stage: &str,
+    f: impl FnOnce() -> Result<T, GateError>,
+) -> Result<T, GateError> {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        Err(GateError::stage(stage, format!("panicked: {}", panic_message(&*payload))))
+    })
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("non-string panic payload")
 }
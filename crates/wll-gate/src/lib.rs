@@ -20,25 +20,47 @@
 
 pub mod config;
 pub mod error;
+pub mod evidence;
 pub mod gate;
+pub mod policy_store;
+pub mod provider;
 pub mod stage;
 pub mod stages;
 
 // Re-exports for convenience.
-pub use config::GateConfig;
+pub use config::{ErrorPolicy, GateConfig};
 pub use error::GateError;
-pub use gate::{CommitmentGate, GateResult};
-pub use stage::{CommitmentProposal, GateContext, GateStage, StageDecision, StageResult};
-pub use stages::capability::CapabilityStage;
+#[cfg(feature = "http")]
+pub use evidence::HttpResolver;
+pub use evidence::{
+    EvidenceError, EvidencePin, EvidenceRef, EvidenceResolver, FileResolver, ObjectStoreResolver,
+};
+pub use gate::{
+    CommitmentGate, GateResult, GateSimulation, StageSimulation, GATE_RESULT_VERSION,
+};
+pub use policy_store::{policy_hash, FilePolicyStore, InMemoryPolicyStore, PolicyStore};
+pub use provider::{
+    check_context_change, check_context_entry, is_context_key, ContextProvider,
+    InMemoryContextProvider, LedgerContextProvider, ADMIN_CAPABILITY, CAPABILITY_KEY_PREFIX,
+    POLICY_KEY_PREFIX,
+};
+pub use stage::{
+    ChangeSize, CommitmentProposal, GateContext, GateStage, RuleCheck, StageDecision, StageResult,
+};
+pub use stages::budget::{ChangeBudget, ChangeBudgetStage};
+pub use stages::capability::{check_capability, CapabilityStage};
+pub use stages::evidence::EvidenceStage;
+pub use stages::intent::{references_issue, ConventionalIntent, IntentLintStage};
 pub use stages::policy::{Policy, PolicyRule, PolicyScope, PolicyStage};
 pub use stages::validation::ValidationStage;
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Arc;
     use wll_types::{
-        Capability, CapabilityId, CapabilityScope, CommitmentClass, EvidenceBundle,
-        IdentityMaterial, TemporalAnchor, WorldlineId,
+        AttestationKind, Capability, CapabilityId, CapabilityScope, CommitmentClass,
+        EvidenceBundle, IdentityAttestation, IdentityMaterial, TemporalAnchor, WorldlineId,
     };
 
     /// Helper: create a test proposer.
@@ -507,4 +529,989 @@ mod tests {
         let result2 = gate.evaluate_with_context(&proposal, &mut context2).unwrap();
         assert!(result2.is_accepted());
     }
+
+    // -----------------------------------------------------------------------
+    // 23. Gate settings from config become default policy rules
+    // -----------------------------------------------------------------------
+    #[test]
+    fn config_settings_build_default_policy() {
+        let unset = GateConfig::from_settings(&wll_config::GateSettings::default());
+        assert!(unset.permissive);
+
+        let settings = wll_config::GateSettings {
+            require_evidence: true,
+            max_targets_per_commitment: Some(1),
+            ..Default::default()
+        };
+        let gate = CommitmentGate::with_default_stages(GateConfig::from_settings(&settings));
+        assert!(!gate.config().permissive);
+        assert_eq!(gate.config().default_policy.rules.len(), 2);
+
+        let result = gate.evaluate(&valid_proposal()).unwrap();
+        assert!(!result.is_accepted());
+        let reason = result.stage_results[2].reason.as_deref().unwrap();
+        assert!(reason.contains("evidence"));
+    }
+
+    // -----------------------------------------------------------------------
+    // 24. RequireAttestation gates a class on an attested human proposer
+    // -----------------------------------------------------------------------
+    #[test]
+    fn policy_require_attestation_for_class() {
+        let mut settings = wll_config::GateSettings::default();
+        settings.require_attestation.insert(
+            "PolicyChange".into(),
+            wll_config::AttestationRequirement::Human,
+        );
+        let config = GateConfig::from_settings(&settings);
+        let gate = CommitmentGate::with_default_stages(config.clone());
+
+        let mut proposal = valid_proposal();
+        let context = |attestations: Vec<IdentityAttestation>| {
+            let mut context = GateContext::minimal(test_proposer());
+            context.policies.push(config.default_policy.clone());
+            context.attestations = attestations;
+            context
+        };
+
+        // Other classes are unaffected.
+        let result = gate.evaluate_with_context(&proposal, &mut context(vec![])).unwrap();
+        assert!(result.is_accepted());
+
+        proposal.class = CommitmentClass::PolicyChange;
+        let result = gate.evaluate_with_context(&proposal, &mut context(vec![])).unwrap();
+        assert!(!result.is_accepted());
+        let reason = result.stage_results[2].reason.as_deref().unwrap();
+        assert!(reason.contains("human identity attestation"));
+
+        let attestation = |kind| IdentityAttestation {
+            worldline: test_proposer(),
+            issuer: "https://idp.example".into(),
+            subject: "alice".into(),
+            kind,
+            claims: Default::default(),
+            issued_at: 0,
+            expires_at: None,
+            token_digest: [0; 32],
+        };
+        let workload = attestation(AttestationKind::Workload);
+        let result = gate
+            .evaluate_with_context(&proposal, &mut context(vec![workload]))
+            .unwrap();
+        assert!(!result.is_accepted());
+
+        let human = attestation(AttestationKind::Human);
+        let expired = IdentityAttestation {
+            expires_at: Some(1),
+            ..human.clone()
+        };
+        let result = gate
+            .evaluate_with_context(&proposal, &mut context(vec![expired]))
+            .unwrap();
+        assert!(!result.is_accepted());
+        let result = gate
+            .evaluate_with_context(&proposal, &mut context(vec![human]))
+            .unwrap();
+        assert!(result.is_accepted());
+    }
+
+    // -----------------------------------------------------------------------
+    // 25. EvidenceStage resolves references and checks pins
+    // -----------------------------------------------------------------------
+    #[test]
+    fn evidence_stage_checks_pins() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("review.txt"), b"approved").unwrap();
+        let mut gate = CommitmentGate::new(GateConfig::default());
+        gate.add_stage(Box::new(
+            EvidenceStage::new().with_resolver(std::sync::Arc::new(FileResolver::new(dir.path()))),
+        ));
+        let evaluate = |references: Vec<&str>| {
+            let mut proposal = valid_proposal();
+            proposal.evidence =
+                EvidenceBundle::from_references(references.into_iter().map(String::from).collect());
+            gate.evaluate(&proposal).unwrap()
+        };
+        let pin = |content: &[u8]| {
+            format!(
+                "file://review.txt#sha256={}",
+                hex::encode(wll_crypto::digest(wll_types::HashAlgo::Sha256, content))
+            )
+        };
+
+        assert!(evaluate(vec!["issue://PROJ-1", "file://review.txt"]).is_accepted());
+        assert!(evaluate(vec![&pin(b"approved")]).is_accepted());
+
+        let result = evaluate(vec![&pin(b"rejected")]);
+        assert!(result.stage_results[0].reason.as_deref().unwrap().contains("sha256 pin"));
+        assert!(!evaluate(vec!["file://missing.txt"]).is_accepted());
+        let unresolvable = format!("issue://PROJ-1#blake3={}", "00".repeat(32));
+        assert!(!evaluate(vec![&unresolvable]).is_accepted());
+
+        // A bundle whose digest no longer matches its references.
+        let mut proposal = valid_proposal();
+        proposal.evidence = EvidenceBundle::from_references(vec!["issue://PROJ-1".into()]);
+        proposal.evidence.references.push("issue://PROJ-2".into());
+        assert!(!gate.evaluate(&proposal).unwrap().is_accepted());
+    }
+
+    // -----------------------------------------------------------------------
+    // 26. Unreachable evidence defers rather than fails
+    // -----------------------------------------------------------------------
+    #[test]
+    fn evidence_stage_defers_when_unavailable() {
+        struct Offline;
+        impl EvidenceResolver for Offline {
+            fn schemes(&self) -> &[&str] {
+                &["https"]
+            }
+            fn resolve(&self, reference: &EvidenceRef) -> Result<Vec<u8>, EvidenceError> {
+                Err(EvidenceError::Unavailable(reference.location.clone()))
+            }
+        }
+        let stage = EvidenceStage::new().with_resolver(std::sync::Arc::new(Offline));
+        let mut proposal = valid_proposal();
+        proposal.evidence = EvidenceBundle::from_references(vec!["https://ci.example/r".into()]);
+        let decision = stage
+            .evaluate(&proposal, &GateContext::minimal(test_proposer()))
+            .unwrap();
+        assert!(matches!(decision, StageDecision::Defer { .. }));
+    }
+
+    // -----------------------------------------------------------------------
+    // 27. Attachments must be in the object store at their recorded size
+    // -----------------------------------------------------------------------
+    #[test]
+    fn evidence_stage_checks_attachments() {
+        use wll_store::{Blob, InMemoryObjectStore, ObjectStore};
+
+        let store = std::sync::Arc::new(InMemoryObjectStore::new());
+        let object = store
+            .write(&Blob::new(b"12 passed".to_vec()).to_stored_object())
+            .unwrap();
+        let stage = EvidenceStage::new()
+            .with_resolver(std::sync::Arc::new(ObjectStoreResolver::new(store)));
+        let evaluate = |object, size| {
+            let mut proposal = valid_proposal();
+            let attachment = wll_types::EvidenceAttachment {
+                object,
+                media_type: "text/plain".into(),
+                name: "test.log".into(),
+                size,
+            };
+            proposal.evidence = EvidenceBundle::new(Vec::new(), vec![attachment]);
+            stage
+                .evaluate(&proposal, &GateContext::minimal(test_proposer()))
+                .unwrap()
+        };
+
+        assert!(matches!(evaluate(object, 9), StageDecision::Pass));
+        assert!(matches!(evaluate(object, 10), StageDecision::Fail { .. }));
+        let missing = wll_types::ObjectId::from_bytes(b"missing");
+        assert!(matches!(evaluate(missing, 9), StageDecision::Fail { .. }));
+    }
+
+    // -----------------------------------------------------------------------
+    // 28. Capability expiry is judged at the context's time
+    // -----------------------------------------------------------------------
+    #[test]
+    fn capability_expiry_uses_context_time() {
+        let gate = CommitmentGate::with_default_stages(GateConfig::default());
+        let mut proposal = valid_proposal();
+        proposal.claimed_capabilities = vec!["write".into()];
+
+        let evaluate_at = |physical_ms| {
+            let mut context = GateContext::minimal(proposal.proposer.clone());
+            context.now = TemporalAnchor::new(physical_ms, 0, 0);
+            context.capabilities.push(Capability {
+                id: CapabilityId("write".into()),
+                scope: CapabilityScope::Global,
+                granted_at: TemporalAnchor::zero(),
+                expires_at: Some(TemporalAnchor::new(1_000, 0, 0)),
+            });
+            context.policies.push(Policy::permissive());
+            gate.evaluate_with_context(&proposal, &mut context).unwrap()
+        };
+
+        assert!(evaluate_at(500).is_accepted());
+        assert!(!evaluate_at(2_000).is_accepted());
+    }
+
+    // -----------------------------------------------------------------------
+    // 29. Simulation reports every stage and rule instead of failing fast
+    // -----------------------------------------------------------------------
+    #[test]
+    fn simulate_reports_every_failing_rule() {
+        let gate = CommitmentGate::with_default_stages(GateConfig::default());
+        let mut proposal = valid_proposal();
+        proposal.claimed_capabilities = vec!["deploy".into()];
+        proposal.class = CommitmentClass::PolicyChange;
+
+        let mut context = GateContext::minimal(proposal.proposer.clone());
+        context.policies.push(Policy {
+            id: "release".into(),
+            name: "Release policy".into(),
+            rules: vec![
+                PolicyRule::RequireEvidence,
+                PolicyRule::MaxTargets(5),
+                PolicyRule::RequireReviewFor(CommitmentClass::PolicyChange),
+            ],
+            applies_to: PolicyScope::All,
+        });
+        context.policies.push(Policy {
+            id: "docs".into(),
+            name: "Docs only".into(),
+            rules: vec![PolicyRule::RequireSignature],
+            applies_to: PolicyScope::Path("docs/".into()),
+        });
+
+        let simulation = gate.simulate(&proposal, &context).unwrap();
+        let enforced = gate.evaluate_with_context(&proposal, &mut context.clone()).unwrap();
+        assert_eq!(simulation.decision, enforced.decision);
+        // evaluate stops at the capability stage; simulate keeps going.
+        assert_eq!(enforced.stage_results.len(), 2);
+        assert_eq!(simulation.stages.len(), 3);
+        assert!(!simulation.all_passed());
+
+        let failures: Vec<_> = simulation
+            .failures()
+            .map(|(stage, check)| (stage, check.policy.as_deref(), check.rule.as_deref()))
+            .collect();
+        assert_eq!(
+            failures,
+            vec![
+                ("capability", None, None),
+                ("policy", Some("release"), Some("require_evidence")),
+                ("policy", Some("release"), Some("require_review_for(PolicyChange)")),
+            ]
+        );
+        let policy = &simulation.stages[2];
+        assert_eq!(policy.checks.len(), 3, "the docs policy does not apply");
+        assert!(policy.checks[1].decision.is_pass());
+        assert!(policy.decision.is_fail());
+        assert!(context.previous_stages.is_empty());
+    }
+
+    // -----------------------------------------------------------------------
+    // 30. Simulation runs the stages of a permissive gate too
+    // -----------------------------------------------------------------------
+    #[test]
+    fn simulate_runs_stages_of_a_permissive_gate() {
+        let gate = CommitmentGate::with_default_stages(GateConfig::permissive());
+        let mut context = GateContext::minimal(test_proposer());
+        context.policies.push(Policy {
+            id: "strict".into(),
+            name: "Strict".into(),
+            rules: vec![PolicyRule::RequireEvidence],
+            applies_to: PolicyScope::All,
+        });
+
+        let simulation = gate.simulate(&valid_proposal(), &context).unwrap();
+        assert!(simulation.permissive);
+        assert!(simulation.is_accepted());
+        assert!(!simulation.all_passed());
+        assert_eq!(simulation.failures().count(), 1);
+    }
+
+    // -----------------------------------------------------------------------
+    // 31. File policy store round-trips versions by their recorded hash
+    // -----------------------------------------------------------------------
+    #[test]
+    fn file_policy_store_round_trips_by_recorded_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FilePolicyStore::new(dir.path().join("policies"));
+        let policy = Policy {
+            id: "evidence".into(),
+            name: "Require evidence".into(),
+            rules: vec![PolicyRule::RequireEvidence, PolicyRule::MaxTargets(3)],
+            applies_to: PolicyScope::All,
+        };
+        let gate = CommitmentGate::with_default_stages(GateConfig {
+            default_policy: policy.clone(),
+            ..GateConfig::default()
+        });
+        let recorded = gate.evaluate(&valid_proposal()).unwrap().policy_hash;
+
+        assert!(store.get(&recorded).unwrap().is_none());
+        assert_eq!(store.put(&policy).unwrap(), recorded);
+        assert_eq!(store.put(&policy).unwrap(), recorded);
+        let loaded = store.get(&recorded).unwrap().unwrap();
+        assert_eq!(policy_hash(&loaded), recorded);
+
+        let path = dir.path().join("policies").join(format!("{}.json", hex::encode(recorded)));
+        std::fs::write(&path, serde_json::to_vec(&Policy::permissive()).unwrap()).unwrap();
+        assert!(matches!(store.get(&recorded), Err(GateError::PolicyStore(_))));
+    }
+
+    // -----------------------------------------------------------------------
+    // 32. Path-scoped capabilities must cover every target
+    // -----------------------------------------------------------------------
+    #[test]
+    fn path_scoped_capability_must_cover_every_target() {
+        let gate = CommitmentGate::with_default_stages(GateConfig::default());
+        let grant = |pattern: &str| Capability {
+            id: CapabilityId("write".into()),
+            scope: CapabilityScope::Path(pattern.into()),
+            granted_at: TemporalAnchor::zero(),
+            expires_at: None,
+        };
+        let evaluate = |targets: &[&str], grants: Vec<Capability>| {
+            let mut proposal = valid_proposal();
+            proposal.claimed_capabilities = vec!["write".into()];
+            proposal.targets = targets.iter().map(|t| t.to_string()).collect();
+            let mut context = GateContext::minimal(proposal.proposer.clone());
+            context.capabilities = grants;
+            context.policies.push(Policy::permissive());
+            gate.evaluate_with_context(&proposal, &mut context).unwrap()
+        };
+
+        let result = evaluate(&["src/lib.rs", "src/stages/policy.rs"], vec![grant("src/**")]);
+        assert!(result.is_accepted());
+
+        let result = evaluate(&["src/lib.rs", "docs/guide.md"], vec![grant("src/**")]);
+        let reason = result.stage_results[1].reason.as_deref().unwrap();
+        assert!(reason.contains("\"docs/guide.md\""), "{reason}");
+        assert!(reason.contains("src/**"), "{reason}");
+
+        // Grants of the same capability add up; `*` stays in one segment.
+        let grants = vec![grant("src/**"), grant("docs/*")];
+        assert!(evaluate(&["src/lib.rs", "docs/guide.md"], grants.clone()).is_accepted());
+        let result = evaluate(&["docs/api/index.md"], grants);
+        assert!(!result.is_accepted());
+
+        let result = evaluate(&["src/lib.rs"], vec![grant("src/[")]);
+        let reason = result.stage_results[1].reason.as_deref().unwrap();
+        assert!(reason.contains("invalid path scope"), "{reason}");
+
+        // A malformed grant covers nothing but does not void the others.
+        let grants = vec![grant("src/["), grant("src/**")];
+        assert!(evaluate(&["src/lib.rs"], grants.clone()).is_accepted());
+        let result = evaluate(&["docs/guide.md"], grants);
+        let reason = result.stage_results[1].reason.as_deref().unwrap();
+        assert!(reason.contains("\"docs/guide.md\""), "{reason}");
+        assert!(reason.contains("ignoring invalid path scope \"src/[\""), "{reason}");
+    }
+
+    /// Evaluate a proposal on `targets` claiming `claims`, holding `grants`.
+    fn evaluate_with_grants(
+        targets: &[String],
+        claims: &[&str],
+        grants: Vec<Capability>,
+    ) -> GateResult {
+        let gate = CommitmentGate::with_default_stages(GateConfig::default());
+        let mut proposal = valid_proposal();
+        proposal.claimed_capabilities = claims.iter().map(|c| c.to_string()).collect();
+        proposal.targets = targets.to_vec();
+        let mut context = GateContext::minimal(proposal.proposer.clone());
+        context.capabilities = grants;
+        context.policies.push(Policy::permissive());
+        gate.evaluate_with_context(&proposal, &mut context).unwrap()
+    }
+
+    fn grant(id: &str, scope: CapabilityScope) -> Capability {
+        Capability {
+            id: CapabilityId(id.into()),
+            scope,
+            granted_at: TemporalAnchor::zero(),
+            expires_at: None,
+        }
+    }
+
+    // -----------------------------------------------------------------------
+    // 33. Worldline-scoped capabilities only satisfy that worldline
+    // -----------------------------------------------------------------------
+    #[test]
+    fn worldline_scoped_capability_only_satisfies_its_worldline() {
+        let other = WorldlineId::derive(&IdentityMaterial::GenesisHash([7u8; 32]));
+        let targets = ["src/lib.rs".to_string()];
+
+        let own = grant("write", CapabilityScope::Worldline(test_proposer()));
+        assert!(evaluate_with_grants(&targets, &["write"], vec![own]).is_accepted());
+
+        let foreign = grant("write", CapabilityScope::Worldline(other.clone()));
+        let result = evaluate_with_grants(&targets, &["write"], vec![foreign]);
+        let reason = result.stage_results[1].reason.as_deref().unwrap();
+        assert!(reason.contains(&format!("worldline {other}")), "{reason}");
+        assert!(reason.contains("\"src/lib.rs\""), "{reason}");
+    }
+
+    // -----------------------------------------------------------------------
+    // 34. Cross-worldline targets need a grant scoped to their worldline
+    // -----------------------------------------------------------------------
+    #[test]
+    fn cross_worldline_targets_need_explicitly_scoped_grants() {
+        let other = WorldlineId::derive(&IdentityMaterial::GenesisHash([7u8; 32]));
+        let foreign = format!("wl:{}", other.to_hex());
+        let targets = ["src/lib.rs".to_string(), foreign.clone()];
+
+        // Unclaimed and globally granted capabilities do not reach across.
+        let result = evaluate_with_grants(&targets, &[], vec![]);
+        let reason = result.stage_results[1].reason.as_deref().unwrap();
+        assert!(reason.contains(&foreign), "{reason}");
+        let global = grant("write", CapabilityScope::Global);
+        let result = evaluate_with_grants(&targets, &["write"], vec![global.clone()]);
+        let reason = result.stage_results[1].reason.as_deref().unwrap();
+        assert!(reason.contains("cross-worldline"), "{reason}");
+
+        let scoped = grant("write", CapabilityScope::Worldline(other.clone()));
+        assert!(evaluate_with_grants(&targets, &["write"], vec![global, scoped]).is_accepted());
+
+        // Naming the proposer's own worldline is not a cross-worldline target.
+        let own = [format!("wl:{}", test_proposer().to_hex())];
+        assert!(evaluate_with_grants(&own, &[], vec![]).is_accepted());
+
+        // A malformed worldline target fails rather than passing as a path.
+        for malformed in ["wl:deadbeef", "wl:not-hex"] {
+            let targets = [malformed.to_string()];
+            let global = grant("write", CapabilityScope::Global);
+            for (claimed, grants) in [(&[][..], vec![]), (&["write"][..], vec![global])] {
+                let result = evaluate_with_grants(&targets, claimed, grants);
+                assert!(!result.is_accepted());
+                let reason = result.stage_results[1].reason.as_deref().unwrap();
+                assert!(reason.contains("not a valid worldline id"), "{reason}");
+                assert!(reason.contains(malformed), "{reason}");
+            }
+        }
+    }
+
+    // -----------------------------------------------------------------------
+    // 35. A context provider fills in capabilities, policies, and counts
+    // -----------------------------------------------------------------------
+    #[test]
+    fn context_provider_fills_in_the_context() {
+        /// Fails once the proposer has committed more than once recently.
+        struct RateLimit;
+        impl GateStage for RateLimit {
+            fn name(&self) -> &str {
+                "rate-limit"
+            }
+            fn evaluate(
+                &self,
+                _proposal: &CommitmentProposal,
+                context: &GateContext,
+            ) -> Result<StageDecision, GateError> {
+                Ok(if context.recent_commitments > 1 {
+                    StageDecision::Fail {
+                        reason: format!("{} recent commitments", context.recent_commitments),
+                    }
+                } else {
+                    StageDecision::Pass
+                })
+            }
+        }
+
+        let provider = Arc::new(InMemoryContextProvider::new());
+        let mut gate = CommitmentGate::with_default_stages(GateConfig::default())
+            .with_context_provider(provider.clone());
+        gate.add_stage(Box::new(RateLimit));
+        let mut proposal = valid_proposal();
+        proposal.claimed_capabilities = vec!["deploy".into()];
+        proposal.targets = vec!["a".into(), "b".into()];
+
+        let result = gate.evaluate(&proposal).unwrap();
+        assert!(!result.is_accepted());
+        assert_eq!(result.stage_results[1].stage_name, "capability");
+
+        provider
+            .grant(&test_proposer(), grant("deploy", CapabilityScope::Global))
+            .unwrap();
+        assert!(gate.evaluate(&proposal).unwrap().is_accepted());
+
+        let one_target = Policy {
+            id: "one".into(),
+            name: "One target".into(),
+            rules: vec![PolicyRule::MaxTargets(1)],
+            applies_to: PolicyScope::All,
+        };
+        provider.add_policy(&test_proposer(), one_target).unwrap();
+        let result = gate.evaluate(&proposal).unwrap();
+        assert_eq!(result.stage_results[2].stage_name, "policy");
+        assert!(!result.stage_results[2].passed);
+
+        proposal.targets.pop();
+        let now = TemporalAnchor::now(0);
+        let long_ago = TemporalAnchor::new(now.physical_ms - 2 * 60 * 60 * 1000, 0, 0);
+        for at in [long_ago, now] {
+            provider.record_commitment(&test_proposer(), at).unwrap();
+        }
+        assert!(gate.evaluate(&proposal).unwrap().is_accepted());
+        provider.record_commitment(&test_proposer(), now).unwrap();
+        let result = gate.evaluate(&proposal).unwrap();
+        assert_eq!(
+            result.stage_results[3].reason.as_deref(),
+            Some("2 recent commitments")
+        );
+    }
+
+    // -----------------------------------------------------------------------
+    // 36. The ledger provider reads grants and policies as of a time
+    // -----------------------------------------------------------------------
+    /// Record a commitment of `class` claiming `caps` on the test
+    /// proposer's worldline, and an accepted outcome setting `updates`.
+    fn record_context(
+        ledger: &wll_ledger::InMemoryLedger,
+        class: CommitmentClass,
+        caps: &[&str],
+        updates: Vec<(&str, serde_json::Value)>,
+    ) {
+        use wll_ledger::{LedgerWriter, OutcomeRecord, StateUpdate};
+
+        let commitment = ledger
+            .append_commitment(
+                &wll_ledger::CommitmentProposal {
+                    worldline: test_proposer(),
+                    commitment_id: wll_types::CommitmentId::new(),
+                    class,
+                    intent: "update grants".into(),
+                    requested_caps: caps.iter().map(|c| c.to_string()).collect(),
+                    targets: vec![],
+                    evidence: EvidenceBundle::empty(),
+                    nonce: 1,
+                },
+                &wll_types::commitment::Decision::Accepted,
+                [0u8; 32],
+            )
+            .unwrap();
+        let outcome = OutcomeRecord {
+            effects: vec![],
+            proofs: vec![],
+            state_updates: updates
+                .into_iter()
+                .map(|(key, value)| StateUpdate {
+                    key: key.into(),
+                    value,
+                    namespace: None,
+                })
+                .collect(),
+            metadata: Default::default(),
+        };
+        ledger.append_outcome(commitment.receipt_hash, &outcome).unwrap();
+    }
+
+    #[test]
+    fn ledger_provider_reads_recorded_grants_and_policies() {
+        use wll_ledger::InMemoryLedger;
+
+        let clock = Arc::new(wll_types::ManualClock::new(1_000));
+        let ledger = Arc::new(InMemoryLedger::new(0).with_clock(clock.clone()));
+        let record = |caps: &[&str], updates| {
+            record_context(&ledger, CommitmentClass::PolicyChange, caps, updates)
+        };
+
+        let admin = grant(ADMIN_CAPABILITY, CapabilityScope::Global);
+        let deploy = grant("deploy", CapabilityScope::Global);
+        let policy = Policy::permissive();
+        record(&[], vec![
+            ("capability/admin", serde_json::to_value(&admin).unwrap()),
+            ("capability/deploy", serde_json::to_value(&deploy).unwrap()),
+            ("policy/default", serde_json::to_value(&policy).unwrap()),
+            ("capability/broken", serde_json::json!("not a capability")),
+            (
+                "capability/badglob",
+                serde_json::to_value(grant("deploy", CapabilityScope::Path("src/[".into())))
+                    .unwrap(),
+            ),
+        ]);
+        clock.advance_ms(1_000);
+        record(&[ADMIN_CAPABILITY], vec![("capability/deploy", serde_json::Value::Null)]);
+
+        let provider = LedgerContextProvider::new(ledger.clone());
+        let wl = test_proposer();
+        let first = TemporalAnchor::new(1_000, 0, 0);
+        let second = TemporalAnchor::new(2_000, 0, 0);
+        let caps = provider.capabilities(&wl, &first).unwrap();
+        let ids: Vec<_> = caps.iter().map(|cap| cap.id.0.as_str()).collect();
+        assert_eq!(ids, [ADMIN_CAPABILITY, "deploy"]);
+        assert_eq!(provider.capabilities(&wl, &second).unwrap().len(), 1);
+        assert_eq!(provider.policies(&wl, &second).unwrap().len(), 1);
+        assert_eq!(provider.commitment_count(&wl, &first, &second).unwrap(), 2);
+        assert_eq!(provider.commitment_count(&wl, &second, &second).unwrap(), 1);
+
+        // Through the gate, the grant holds only before it was revoked.
+        let gate = CommitmentGate::with_default_stages(GateConfig::default())
+            .with_context_provider(Arc::new(provider));
+        let mut proposal = valid_proposal();
+        proposal.claimed_capabilities = vec!["deploy".into()];
+        let evaluate_at = |now| {
+            let mut context = GateContext::minimal(wl.clone());
+            context.now = now;
+            gate.evaluate_with_context(&proposal, &mut context).unwrap()
+        };
+        assert!(evaluate_at(first).is_accepted());
+        assert!(!evaluate_at(second).is_accepted());
+    }
+
+    // -----------------------------------------------------------------------
+    // 37. Target allow and deny lists match globs
+    // -----------------------------------------------------------------------
+    #[test]
+    fn policy_target_allow_and_deny_lists() {
+        let stage = PolicyStage;
+        let evaluate = |rule: PolicyRule, targets: &[&str]| {
+            let mut proposal = valid_proposal();
+            proposal.targets = targets.iter().map(|t| t.to_string()).collect();
+            let mut context = GateContext::minimal(proposal.proposer.clone());
+            context.policies.push(Policy {
+                id: "paths".into(),
+                name: "Protected paths".into(),
+                rules: vec![rule],
+                applies_to: PolicyScope::All,
+            });
+            stage.evaluate(&proposal, &context)
+        };
+        let deny = |case_insensitive| PolicyRule::DenyTargets {
+            patterns: vec!["infra/prod/**".into()],
+            case_insensitive,
+        };
+        let allow = PolicyRule::AllowTargets {
+            patterns: vec!["src/*.rs".into(), "docs/**".into()],
+            case_insensitive: false,
+        };
+
+        assert!(evaluate(deny(false), &["infra/prod/db/main.tf"]).unwrap().is_fail());
+        assert!(!evaluate(deny(false), &["infra/staging/main.tf"]).unwrap().is_fail());
+        assert!(!evaluate(deny(false), &["Infra/Prod/main.tf"]).unwrap().is_fail());
+        assert!(evaluate(deny(true), &["Infra/Prod/main.tf"]).unwrap().is_fail());
+
+        assert!(!evaluate(allow.clone(), &["src/lib.rs", "docs/a/b.md"]).unwrap().is_fail());
+        let decision = evaluate(allow, &["src/lib.rs", "src/bin/main.rs"]).unwrap();
+        assert!(decision.is_fail());
+        assert!(format!("{decision:?}").contains("src/bin/main.rs"));
+
+        // An invalid pattern that gets past loading never lets a target
+        // through: it allows nothing, and a deny list holding one fails.
+        let invalid = PolicyRule::DenyTargets { patterns: vec!["a/[".into()], case_insensitive: false };
+        let StageDecision::Fail { reason } = evaluate(invalid, &["b"]).unwrap() else {
+            panic!("an invalid deny pattern passed");
+        };
+        assert!(reason.contains("invalid target pattern \"a/[\""), "{reason}");
+        let allow = PolicyRule::AllowTargets {
+            patterns: vec!["a/[".into(), "src/**".into()],
+            case_insensitive: false,
+        };
+        assert!(!evaluate(allow.clone(), &["src/lib.rs"]).unwrap().is_fail());
+        let decision = evaluate(allow, &["a/b"]).unwrap();
+        assert!(format!("{decision:?}").contains("ignoring invalid target pattern"));
+    }
+
+    // -----------------------------------------------------------------------
+    // 38. Change budgets limit the size of a class's tree changes
+    // -----------------------------------------------------------------------
+    #[test]
+    fn change_budget_rejects_oversized_changes_with_their_measurements() {
+        let mut gate = CommitmentGate::with_default_stages(GateConfig::default());
+        gate.add_stage(Box::new(
+            ChangeBudgetStage::new()
+                .with_budget(CommitmentClass::PolicyChange, ChangeBudget::max_lines(200))
+                .with_budget(CommitmentClass::ContentUpdate, ChangeBudget::max_files(2)),
+        ));
+        assert!(gate.has_stage(ChangeBudgetStage::NAME));
+        let evaluate = |class, change| {
+            let mut proposal = valid_proposal();
+            proposal.class = class;
+            let mut context = GateContext::minimal(test_proposer());
+            context.change = change;
+            gate.evaluate_with_context(&proposal, &mut context).unwrap()
+        };
+        let size = |files, lines_added, lines_removed| {
+            Some(ChangeSize { files, lines_added, lines_removed })
+        };
+
+        assert!(evaluate(CommitmentClass::PolicyChange, size(9, 150, 50)).is_accepted());
+        assert!(evaluate(CommitmentClass::PolicyChange, None).is_accepted());
+        assert!(evaluate(CommitmentClass::StructuralChange, size(90, 900, 0)).is_accepted());
+
+        let result = evaluate(CommitmentClass::PolicyChange, size(1, 300, 50));
+        assert!(!result.is_accepted());
+        let failed = result.stage_results.last().unwrap();
+        assert_eq!(failed.stage_name, "change_budget");
+        assert_eq!(
+            failed.reason.as_deref(),
+            Some(
+                "PolicyChange change of 350 lines (300 added, 50 removed) \
+                 exceeds its budget of 200 lines"
+            )
+        );
+        let result = evaluate(CommitmentClass::ContentUpdate, size(3, 3, 0));
+        let reason = result.stage_results.last().unwrap().reason.clone().unwrap();
+        assert!(reason.contains("3 files exceeds its budget of 2 files"));
+    }
+
+    // -----------------------------------------------------------------------
+    // 39. Stage panics and timeouts are isolated and follow the error policy
+    // -----------------------------------------------------------------------
+    #[test]
+    fn stage_panics_and_timeouts_follow_the_error_policy() {
+        enum Broken {
+            Panics,
+            Hangs,
+        }
+        impl GateStage for Broken {
+            fn name(&self) -> &str {
+                match self {
+                    Broken::Panics => "panics",
+                    Broken::Hangs => "hangs",
+                }
+            }
+            fn evaluate(
+                &self,
+                _proposal: &CommitmentProposal,
+                _context: &GateContext,
+            ) -> Result<StageDecision, GateError> {
+                match self {
+                    Broken::Panics => panic!("stage bug"),
+                    Broken::Hangs => std::thread::sleep(std::time::Duration::from_secs(2)),
+                }
+                Ok(StageDecision::Pass)
+            }
+        }
+        let gate = |stage, config| {
+            let mut gate = CommitmentGate::new(config);
+            gate.add_stage(Box::new(stage));
+            gate.add_stage(Box::new(ValidationStage));
+            gate
+        };
+
+        let err = gate(Broken::Panics, GateConfig::default())
+            .evaluate(&valid_proposal())
+            .unwrap_err();
+        assert_eq!(err, GateError::stage("panics", "panicked: stage bug"));
+
+        let timeout = std::time::Duration::from_millis(50);
+        let mut config = GateConfig {
+            stage_timeout: Some(timeout),
+            ..GateConfig::default()
+        };
+        let err = gate(Broken::Hangs, config.clone())
+            .evaluate(&valid_proposal())
+            .unwrap_err();
+        assert_eq!(
+            err,
+            GateError::StageTimeout {
+                stage: "hangs".into(),
+                after: timeout
+            }
+        );
+
+        config.error_policy = ErrorPolicy::FailOpen;
+        config.stage_error_policy.insert("panics".into(), ErrorPolicy::FailClosed);
+        let result = gate(Broken::Hangs, config.clone())
+            .evaluate(&valid_proposal())
+            .unwrap();
+        assert!(result.is_accepted());
+        assert!(result.stage_results[0].passed);
+        let reason = result.stage_results[0].reason.as_deref().unwrap();
+        assert!(reason.starts_with("error ignored (fail-open): stage 'hangs' timed out"));
+        assert!(gate(Broken::Panics, config).evaluate(&valid_proposal()).is_err());
+    }
+
+    // -----------------------------------------------------------------------
+    // 40. Gate results round-trip through their versioned format
+    // -----------------------------------------------------------------------
+    #[test]
+    fn gate_result_serializes_to_a_versioned_format() {
+        let config = GateConfig::default();
+        let gate = CommitmentGate::with_default_stages(config.clone());
+        let result = gate.evaluate(&valid_proposal()).unwrap();
+        assert_eq!(result.version, GATE_RESULT_VERSION);
+        assert_eq!(result.config_digest, config.digest());
+        let strict = GateConfig {
+            require_evidence: true,
+            ..GateConfig::default()
+        };
+        assert_ne!(strict.digest(), config.digest());
+
+        let json = result.to_json().unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        let stage = &value["stage_results"][0];
+        assert_eq!(stage["stage_name"], "validation");
+        assert_eq!(
+            stage["elapsed_us"].as_u64().unwrap(),
+            result.stage_results[0].elapsed.as_micros() as u64
+        );
+        let read = GateResult::from_json(&json).unwrap();
+        assert_eq!(read.decision, result.decision);
+        assert_eq!(read.stage_results.len(), 3);
+
+        let mut newer = value.clone();
+        newer["version"] = (GATE_RESULT_VERSION + 1).into();
+        assert_eq!(
+            GateResult::from_json(&serde_json::to_vec(&newer).unwrap()).unwrap_err(),
+            GateError::UnsupportedVersion(GATE_RESULT_VERSION + 1)
+        );
+    }
+
+    // -----------------------------------------------------------------------
+    // 41. Intent lint holds intents to the configured conventions
+    // -----------------------------------------------------------------------
+    #[test]
+    fn intent_lint_reports_every_broken_convention() {
+        let mut gate = CommitmentGate::with_default_stages(GateConfig::default());
+        gate.add_stage(Box::new(
+            IntentLintStage::new()
+                .with_types(["feat", "fix"])
+                .with_scopes(["store", "gate"])
+                .with_max_subject_len(40)
+                .with_issue_required(CommitmentClass::PolicyChange),
+        ));
+        let evaluate = |intent: &str, class| {
+            let mut proposal = CommitmentProposal::minimal(test_proposer(), intent);
+            proposal.class = class;
+            gate.evaluate(&proposal).unwrap()
+        };
+
+        assert!(evaluate("feat: add user authentication", CommitmentClass::ContentUpdate)
+            .is_accepted());
+        let closes = "fix(gate)!: tighten rules\n\nCloses #12";
+        assert!(evaluate(closes, CommitmentClass::PolicyChange).is_accepted());
+
+        let result = evaluate("Added stuff", CommitmentClass::ContentUpdate);
+        let failed = result.stage_results.last().unwrap();
+        assert_eq!(failed.stage_name, IntentLintStage::NAME);
+        assert_eq!(
+            failed.reason.as_deref(),
+            Some(
+                "intent breaks the conventions: \"Added stuff\" is not in the form \
+                 \"type(scope): subject\", e.g. \"feat(store): describe the change\""
+            )
+        );
+
+        let long = "chore(docs): reword the whole readme at length";
+        let result = evaluate(long, CommitmentClass::PolicyChange);
+        let reason = result.stage_results.last().unwrap().reason.clone().unwrap();
+        for expected in ["is 46 characters", "type \"chore\"", "scope \"docs\"", "PolicyChange"] {
+            assert!(reason.contains(expected), "{reason}");
+        }
+    }
+
+    #[test]
+    fn conventional_intents_parse_and_issue_references_are_found() {
+        let parsed = ConventionalIntent::parse("feat(api)!: drop v1\n\nbody").unwrap();
+        assert_eq!((parsed.kind, parsed.scope, parsed.breaking), ("feat", Some("api"), true));
+        assert_eq!(parsed.to_string(), "feat(api)!: drop v1");
+        for bad in ["Feat: x", "feat(): x", "feat x", "feat: ", "fix(a)(b): x"] {
+            assert_eq!(ConventionalIntent::parse(bad), None, "{bad}");
+        }
+        assert!(references_issue("fix: crash (PROJ-42)", &[]));
+        assert!(references_issue("fix: crash, see #7.", &[]));
+        assert!(references_issue("fix: crash", &["issue://7".into()]));
+        assert!(!references_issue("fix: utf-8 and x-1 and #", &["doc://spec".into()]));
+    }
+
+    // -----------------------------------------------------------------------
+    // 42. Invalid globs are refused where policies and grants are loaded
+    // -----------------------------------------------------------------------
+    #[test]
+    fn invalid_globs_are_refused_on_load() {
+        let bad_grant = Capability {
+            id: CapabilityId("write".into()),
+            scope: CapabilityScope::Path("src/[".into()),
+            granted_at: TemporalAnchor::new(0, 0, 0),
+            expires_at: None,
+        };
+        let bad_policy = Policy {
+            id: "paths".into(),
+            name: "Protected paths".into(),
+            rules: vec![PolicyRule::DenyTargets {
+                patterns: vec!["infra/**".into(), "a/[".into()],
+                case_insensitive: false,
+            }],
+            applies_to: PolicyScope::All,
+        };
+
+        let err = check_capability(&bad_grant).unwrap_err();
+        assert!(matches!(&err, GateError::Config(m) if m.contains("\"src/[\"")), "{err}");
+        let err = bad_policy.check().unwrap_err();
+        assert!(matches!(&err, GateError::Config(m) if m.contains("\"a/[\"")), "{err}");
+        assert!(Policy::permissive().check().is_ok());
+
+        let provider = InMemoryContextProvider::new();
+        let wl = test_proposer();
+        assert!(matches!(provider.grant(&wl, bad_grant.clone()), Err(GateError::Config(_))));
+        assert!(matches!(provider.add_policy(&wl, bad_policy.clone()), Err(GateError::Config(_))));
+        let now = TemporalAnchor::new(0, 0, 0);
+        assert!(provider.capabilities(&wl, &now).unwrap().is_empty());
+        assert!(provider.policies(&wl, &now).unwrap().is_empty());
+
+        // Commits recording gate context are checked the same way.
+        let grant_json = serde_json::to_value(&bad_grant).unwrap();
+        let policy_json = serde_json::to_value(&bad_policy).unwrap();
+        assert!(check_context_entry("capability/write", &grant_json).is_err());
+        assert!(check_context_entry("policy/paths", &policy_json).is_err());
+        assert!(check_context_entry("policy/paths", &serde_json::json!(3)).is_err());
+        assert!(check_context_entry("policy/paths", &serde_json::Value::Null).is_ok());
+        assert!(check_context_entry("app/config", &grant_json).is_ok());
+    }
+
+    // -----------------------------------------------------------------------
+    // 43. Only admin policy changes may change gate context
+    // -----------------------------------------------------------------------
+    #[test]
+    fn commits_cannot_grant_themselves_capabilities() {
+        use wll_ledger::InMemoryLedger;
+
+        let clock = Arc::new(wll_types::ManualClock::new(1_000));
+        let ledger = Arc::new(InMemoryLedger::new(0).with_clock(clock.clone()));
+        let deploy = serde_json::to_value(grant("deploy", CapabilityScope::Global)).unwrap();
+        let admin = serde_json::to_value(grant(ADMIN_CAPABILITY, CapabilityScope::Global)).unwrap();
+        let policy = serde_json::to_value(Policy::permissive()).unwrap();
+
+        // The genesis seeds a policy; nobody holds admin.
+        record_context(&ledger, CommitmentClass::PolicyChange, &[], vec![("policy/default", policy)]);
+        // A plain commit grants itself deploy and retires the policy; a
+        // policy change does the same without claiming admin, and another
+        // claims admin without holding it.
+        let attempts = [
+            (CommitmentClass::ContentUpdate, &[][..]),
+            (CommitmentClass::PolicyChange, &[][..]),
+            (CommitmentClass::PolicyChange, &[ADMIN_CAPABILITY][..]),
+        ];
+        for (class, caps) in attempts {
+            clock.advance_ms(1_000);
+            record_context(&ledger, class, caps, vec![
+                ("capability/deploy", deploy.clone()),
+                ("capability/admin", admin.clone()),
+                ("policy/default", serde_json::Value::Null),
+            ]);
+        }
+
+        let provider = Arc::new(LedgerContextProvider::new(ledger.clone()));
+        let wl = test_proposer();
+        let now = TemporalAnchor::new(4_000, 0, 0);
+        assert!(provider.capabilities(&wl, &now).unwrap().is_empty());
+        assert_eq!(provider.policies(&wl, &now).unwrap().len(), 1);
+        assert!(!provider.is_unwritten(&wl, &now).unwrap());
+
+        let gate = CommitmentGate::with_default_stages(GateConfig::default())
+            .with_context_provider(provider);
+        let evaluate = |class: CommitmentClass, caps: &[&str], keys: &[&str]| {
+            let mut proposal = valid_proposal();
+            proposal.class = class;
+            proposal.claimed_capabilities = caps.iter().map(|c| c.to_string()).collect();
+            let mut context = GateContext::minimal(wl.clone());
+            context.now = now;
+            context.context_keys = keys.iter().map(|k| k.to_string()).collect();
+            gate.evaluate_with_context(&proposal, &mut context).unwrap()
+        };
+        assert!(!evaluate(CommitmentClass::ContentUpdate, &["deploy"], &[]).is_accepted());
+        let rejected = evaluate(CommitmentClass::ContentUpdate, &[], &["capability/deploy"]);
+        assert!(!rejected.is_accepted());
+        assert_eq!(rejected.stage_results[1].stage_name, "capability");
+        assert!(!evaluate(CommitmentClass::PolicyChange, &[], &["policy/default"]).is_accepted());
+        let unheld = evaluate(CommitmentClass::PolicyChange, &[ADMIN_CAPABILITY], &["policy/x"]);
+        assert!(!unheld.is_accepted());
+        assert!(evaluate(CommitmentClass::ContentUpdate, &[], &[]).is_accepted());
+
+        // A worldline with nothing recorded seeds its context at genesis.
+        let fresh = LedgerContextProvider::new(Arc::new(InMemoryLedger::new(0)));
+        assert!(fresh.is_unwritten(&wl, &now).unwrap());
+        let gate = CommitmentGate::with_default_stages(GateConfig::default())
+            .with_context_provider(Arc::new(fresh));
+        let mut proposal = valid_proposal();
+        proposal.class = CommitmentClass::PolicyChange;
+        let mut context = GateContext::minimal(wl.clone());
+        context.context_keys = vec!["capability/admin".into()];
+        assert!(gate.evaluate_with_context(&proposal, &mut context).unwrap().is_accepted());
+        assert!(context.genesis);
+    }
 }
@@ -0,0 +1,122 @@
+//! Content-addressed storage of policy versions.
+//!
+//! Every commitment receipt records the hash of the policy it was judged
+//! under ([`policy_hash`]). A [`PolicyStore`] keeps the policies behind
+//! those hashes so a past decision can be re-evaluated against the exact
+//! policy version that made it.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use wll_crypto::ContentHasher;
+
+use crate::error::GateError;
+use crate::stages::policy::Policy;
+
+/// BLAKE3 hash identifying a policy version, as recorded in commitment
+/// receipts.
+pub fn policy_hash(policy: &Policy) -> [u8; 32] {
+    let hasher = ContentHasher::new("wll-gate-policy-v1");
+    match hasher.hash_json(policy) {
+        Ok(oid) => *oid.as_bytes(),
+        Err(_) => [0u8; 32],
+    }
+}
+
+/// Policy versions by [`policy_hash`].
+pub trait PolicyStore: Send + Sync {
+    /// The policy whose hash is `hash`, if stored.
+    fn get(&self, hash: &[u8; 32]) -> Result<Option<Policy>, GateError>;
+
+    /// Store `policy`, returning its hash. Storing a version twice is a
+    /// no-op.
+    fn put(&self, policy: &Policy) -> Result<[u8; 32], GateError>;
+}
+
+/// A [`PolicyStore`] held in memory.
+#[derive(Debug, Default)]
+pub struct InMemoryPolicyStore {
+    policies: RwLock<HashMap<[u8; 32], Policy>>,
+}
+
+impl InMemoryPolicyStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl PolicyStore for InMemoryPolicyStore {
+    fn get(&self, hash: &[u8; 32]) -> Result<Option<Policy>, GateError> {
+        let policies = self
+            .policies
+            .read()
+            .map_err(|_| GateError::PolicyStore("lock poisoned".into()))?;
+        Ok(policies.get(hash).cloned())
+    }
+
+    fn put(&self, policy: &Policy) -> Result<[u8; 32], GateError> {
+        let hash = policy_hash(policy);
+        self.policies
+            .write()
+            .map_err(|_| GateError::PolicyStore("lock poisoned".into()))?
+            .entry(hash)
+            .or_insert_with(|| policy.clone());
+        Ok(hash)
+    }
+}
+
+/// A [`PolicyStore`] keeping one `<hash>.json` file per version in a
+/// directory.
+#[derive(Clone, Debug)]
+pub struct FilePolicyStore {
+    dir: PathBuf,
+}
+
+impl FilePolicyStore {
+    /// A store in `dir`, which is created on the first write.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, hash: &[u8; 32]) -> PathBuf {
+        self.dir.join(format!("{}.json", hex::encode(hash)))
+    }
+}
+
+impl PolicyStore for FilePolicyStore {
+    fn get(&self, hash: &[u8; 32]) -> Result<Option<Policy>, GateError> {
+        let path = self.path(hash);
+        let bytes = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(GateError::PolicyStore(format!("{}: {e}", path.display()))),
+        };
+        let policy: Policy = serde_json::from_slice(&bytes)
+            .map_err(|e| GateError::PolicyStore(format!("{}: {e}", path.display())))?;
+        if policy_hash(&policy) != *hash {
+            return Err(GateError::PolicyStore(format!(
+                "{}: content does not match its hash",
+                path.display()
+            )));
+        }
+        Ok(Some(policy))
+    }
+
+    fn put(&self, policy: &Policy) -> Result<[u8; 32], GateError> {
+        let hash = policy_hash(policy);
+        let path = self.path(&hash);
+        if path.is_file() {
+            return Ok(hash);
+        }
+        let io = |e: std::io::Error| GateError::PolicyStore(format!("{}: {e}", path.display()));
+        std::fs::create_dir_all(&self.dir).map_err(io)?;
+        let json = serde_json::to_vec_pretty(policy)
+            .map_err(|e| GateError::PolicyStore(e.to_string()))?;
+        // Write then rename so a reader never sees a partial version.
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, json).map_err(io)?;
+        std::fs::rename(&tmp, &path).map_err(io)?;
+        Ok(hash)
+    }
+}
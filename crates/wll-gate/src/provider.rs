@@ -0,0 +1,428 @@
+//! Context the gate looks up for itself at evaluation time.
+//!
+//! A [`GateContext`](crate::GateContext) assembled by hand can drift from
+//! what the ledger says a worldline holds. A [`ContextProvider`] attached to
+//! the gate ([`CommitmentGate::with_context_provider`]) is queried on every
+//! evaluation instead, for the capabilities granted to the proposer, the
+//! policies active on the worldline, and how many commitments it has made
+//! recently.
+//!
+//! Gate context recorded in the ledger is only as trustworthy as the
+//! commitments allowed to change it: see [`check_context_change`].
+//!
+//! [`CommitmentGate::with_context_provider`]: crate::CommitmentGate::with_context_provider
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
+
+use serde_json::Value;
+use wll_ledger::{LedgerReader, Receipt};
+use wll_types::{Capability, CommitmentClass, TemporalAnchor, WorldlineId};
+
+use crate::error::GateError;
+use crate::stages::capability::check_capability;
+use crate::stages::policy::Policy;
+
+/// State key prefix under which outcomes record capability grants, one key
+/// per grant (e.g. `capability/deploy`). The value is the
+/// [`Capability`] as JSON; `null` revokes it.
+pub const CAPABILITY_KEY_PREFIX: &str = "capability/";
+
+/// State key prefix under which outcomes record active policies, one key
+/// per policy (e.g. `policy/release`). The value is the [`Policy`] as JSON;
+/// `null` retires it.
+pub const POLICY_KEY_PREFIX: &str = "policy/";
+
+/// Capability a commitment claims, and must hold, to change the gate
+/// context of a worldline it does not start.
+pub const ADMIN_CAPABILITY: &str = "admin";
+
+/// Whether `key`, in the default namespace, is gate context.
+pub fn is_context_key(key: &str) -> bool {
+    key.starts_with(CAPABILITY_KEY_PREFIX) || key.starts_with(POLICY_KEY_PREFIX)
+}
+
+/// Check that a commitment of `class` claiming `claimed` may record gate
+/// context, so a commit cannot grant itself capabilities or retire the
+/// policies that hold it back.
+///
+/// Only [`CommitmentClass::PolicyChange`] commitments may, and they must
+/// claim [`ADMIN_CAPABILITY`] unless they are the worldline's `genesis`,
+/// its first commitment, which seeds the context. Holding the claimed
+/// capability is checked where claims are: by the
+/// [`CapabilityStage`](crate::CapabilityStage), and by
+/// [`LedgerContextProvider`] against the grants recorded before.
+pub fn check_context_change(
+    class: &CommitmentClass,
+    claimed: &[String],
+    genesis: bool,
+) -> Result<(), GateError> {
+    if *class != CommitmentClass::PolicyChange {
+        return Err(GateError::CapabilityDenied(format!(
+            "only {} commitments may change gate context, not {class}",
+            CommitmentClass::PolicyChange
+        )));
+    }
+    if !genesis && !claimed.iter().any(|c| c == ADMIN_CAPABILITY) {
+        return Err(GateError::CapabilityDenied(format!(
+            "changing gate context needs the {ADMIN_CAPABILITY} capability"
+        )));
+    }
+    Ok(())
+}
+
+/// Check the value a commit records under `key`, if it is gate context:
+/// a capability grant or policy must parse and hold only valid globs
+/// ([`check_capability`], [`Policy::check`]). `null` removals and other
+/// keys always pass.
+pub fn check_context_entry(key: &str, value: &Value) -> Result<(), GateError> {
+    if value.is_null() {
+        return Ok(());
+    }
+    let malformed = |e: serde_json::Error| GateError::Config(format!("{key}: {e}"));
+    if key.starts_with(CAPABILITY_KEY_PREFIX) {
+        check_capability(&serde_json::from_value(value.clone()).map_err(malformed)?)
+    } else if key.starts_with(POLICY_KEY_PREFIX) {
+        serde_json::from_value::<Policy>(value.clone()).map_err(malformed)?.check()
+    } else {
+        Ok(())
+    }
+}
+
+/// Source of the facts a gate evaluation depends on.
+///
+/// `at` is the evaluation time. Providers that know history answer as of
+/// that time, so a past decision can be re-evaluated in the context it was
+/// made in; times are compared to the millisecond.
+pub trait ContextProvider: Send + Sync {
+    /// Capabilities granted to `worldline`.
+    fn capabilities(
+        &self,
+        worldline: &WorldlineId,
+        at: &TemporalAnchor,
+    ) -> Result<Vec<Capability>, GateError>;
+
+    /// Policies active on `worldline`.
+    fn policies(&self, worldline: &WorldlineId, at: &TemporalAnchor)
+        -> Result<Vec<Policy>, GateError>;
+
+    /// Commitments `worldline` made between `since` and `until`, inclusive.
+    fn commitment_count(
+        &self,
+        worldline: &WorldlineId,
+        since: &TemporalAnchor,
+        until: &TemporalAnchor,
+    ) -> Result<u64, GateError>;
+
+    /// Whether `worldline` had recorded nothing as of `at`, so that its
+    /// next commitment is its genesis ([`check_context_change`]). The
+    /// default answers no, so providers that cannot tell never let a
+    /// commitment seed context without [`ADMIN_CAPABILITY`].
+    fn is_unwritten(&self, _worldline: &WorldlineId, _at: &TemporalAnchor) -> Result<bool, GateError> {
+        Ok(false)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// InMemoryContextProvider
+// ---------------------------------------------------------------------------
+
+/// A [`ContextProvider`] held in memory, filled in by the caller.
+#[derive(Debug, Default)]
+pub struct InMemoryContextProvider {
+    capabilities: RwLock<HashMap<WorldlineId, Vec<Capability>>>,
+    policies: RwLock<HashMap<WorldlineId, Vec<Policy>>>,
+    commitments: RwLock<HashMap<WorldlineId, Vec<TemporalAnchor>>>,
+}
+
+impl InMemoryContextProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Grant `capability` to `worldline`. Fails if its path scope is not
+    /// a valid glob.
+    pub fn grant(&self, worldline: &WorldlineId, capability: Capability) -> Result<(), GateError> {
+        check_capability(&capability)?;
+        write(&self.capabilities)?
+            .entry(worldline.clone())
+            .or_default()
+            .push(capability);
+        Ok(())
+    }
+
+    /// Make `policy` active on `worldline`. Fails if a target pattern is
+    /// not a valid glob.
+    pub fn add_policy(&self, worldline: &WorldlineId, policy: Policy) -> Result<(), GateError> {
+        policy.check()?;
+        write(&self.policies)?
+            .entry(worldline.clone())
+            .or_default()
+            .push(policy);
+        Ok(())
+    }
+
+    /// Record that `worldline` made a commitment at `at`.
+    pub fn record_commitment(
+        &self,
+        worldline: &WorldlineId,
+        at: TemporalAnchor,
+    ) -> Result<(), GateError> {
+        write(&self.commitments)?
+            .entry(worldline.clone())
+            .or_default()
+            .push(at);
+        Ok(())
+    }
+}
+
+impl ContextProvider for InMemoryContextProvider {
+    fn capabilities(
+        &self,
+        worldline: &WorldlineId,
+        _at: &TemporalAnchor,
+    ) -> Result<Vec<Capability>, GateError> {
+        Ok(read(&self.capabilities)?
+            .get(worldline)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn policies(
+        &self,
+        worldline: &WorldlineId,
+        _at: &TemporalAnchor,
+    ) -> Result<Vec<Policy>, GateError> {
+        Ok(read(&self.policies)?
+            .get(worldline)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn commitment_count(
+        &self,
+        worldline: &WorldlineId,
+        since: &TemporalAnchor,
+        until: &TemporalAnchor,
+    ) -> Result<u64, GateError> {
+        Ok(read(&self.commitments)?
+            .get(worldline)
+            .map_or(0, |times| {
+                times.iter().filter(|t| within(t, since, until)).count() as u64
+            }))
+    }
+}
+
+fn read<T>(lock: &RwLock<T>) -> Result<std::sync::RwLockReadGuard<'_, T>, GateError> {
+    lock.read()
+        .map_err(|_| GateError::Context("lock poisoned".into()))
+}
+
+fn write<T>(lock: &RwLock<T>) -> Result<std::sync::RwLockWriteGuard<'_, T>, GateError> {
+    lock.write()
+        .map_err(|_| GateError::Context("lock poisoned".into()))
+}
+
+fn within(t: &TemporalAnchor, since: &TemporalAnchor, until: &TemporalAnchor) -> bool {
+    since.physical_ms <= t.physical_ms && t.physical_ms <= until.physical_ms
+}
+
+// ---------------------------------------------------------------------------
+// LedgerContextProvider
+// ---------------------------------------------------------------------------
+
+/// A [`ContextProvider`] that reads what a worldline's receipts record.
+///
+/// Capabilities and policies are the values accepted outcomes set under
+/// [`CAPABILITY_KEY_PREFIX`] and [`POLICY_KEY_PREFIX`], folded in order the
+/// way replay folds state. Only the default state namespace holds context;
+/// named namespaces are ignored. Only outcomes of commitments allowed to
+/// change context ([`check_context_change`]) count, and a claim of
+/// [`ADMIN_CAPABILITY`] only when a grant of it folded so far was live at
+/// the commitment; other writes are skipped with a warning. A snapshot
+/// stands in for the context only when the receipts it summarizes have
+/// been archived, since it would otherwise repeat writes skipped here.
+/// Commitments are counted from commitment receipts. Entries that do not
+/// parse, or hold globs that do not ([`check_context_entry`]), are skipped
+/// with a warning rather than failing every evaluation.
+///
+/// The last worldline folded is cached until its head moves, so the three
+/// lookups of one evaluation read the ledger once.
+pub struct LedgerContextProvider<R: LedgerReader + ?Sized> {
+    reader: Arc<R>,
+    cache: Mutex<Option<Arc<Folded>>>,
+}
+
+impl<R: LedgerReader + ?Sized> LedgerContextProvider<R> {
+    pub fn new(reader: Arc<R>) -> Self {
+        Self {
+            reader,
+            cache: Mutex::new(None),
+        }
+    }
+
+    /// The worldline's receipts up to `at`, folded.
+    fn fold(&self, worldline: &WorldlineId, at: &TemporalAnchor) -> Result<Arc<Folded>, GateError> {
+        let ledger = |e: wll_ledger::LedgerError| GateError::Context(e.to_string());
+        let head = self
+            .reader
+            .head(worldline)
+            .map_err(ledger)?
+            .map(|head| head.receipt_hash);
+        let mut cache = self
+            .cache
+            .lock()
+            .map_err(|_| GateError::Context("lock poisoned".into()))?;
+        if let Some(folded) = cache.as_ref() {
+            if folded.worldline == *worldline && folded.head == head && folded.at_ms == at.physical_ms
+            {
+                return Ok(folded.clone());
+            }
+        }
+
+        let mut state = BTreeMap::new();
+        let mut commitments = Vec::new();
+        let mut authorized = HashSet::new();
+        let mut unwritten = true;
+        for (index, receipt) in self.reader.read_all(worldline).map_err(ledger)?.into_iter().enumerate() {
+            // Receipts before the first one read were archived.
+            let archived = index == 0 && receipt.prev_hash().is_some();
+            unwritten &= !archived;
+            if receipt.timestamp().physical_ms > at.physical_ms {
+                break;
+            }
+            unwritten = false;
+            match receipt {
+                Receipt::Commitment(c) => {
+                    let genesis = c.prev_hash.is_none();
+                    let allowed = check_context_change(&c.class, &c.requested_caps, genesis)
+                        .is_ok()
+                        && (genesis || holds_admin(&state, &c.timestamp));
+                    if allowed {
+                        authorized.insert(c.receipt_hash);
+                    }
+                    commitments.push(c.timestamp);
+                }
+                Receipt::Outcome(o) if o.accepted => {
+                    let allowed = authorized.contains(&o.commitment_receipt_hash);
+                    for update in o.state_updates {
+                        if update.namespace.is_some() || !is_context_key(&update.key) {
+                            continue;
+                        }
+                        if allowed {
+                            state.insert(update.key, update.value);
+                        } else {
+                            tracing::warn!(
+                                key = %update.key,
+                                seq = o.seq,
+                                "skipping gate context written by an unauthorized commitment"
+                            );
+                        }
+                    }
+                }
+                Receipt::Outcome(_) => {}
+                Receipt::Snapshot(s) if s.namespace.is_none() && archived => {
+                    state = s
+                        .state
+                        .into_iter()
+                        .filter(|(key, _)| is_context_key(key))
+                        .collect();
+                }
+                Receipt::Snapshot(_) => {}
+            }
+        }
+
+        let folded = Arc::new(Folded {
+            worldline: worldline.clone(),
+            head,
+            at_ms: at.physical_ms,
+            capabilities: entries(&state, CAPABILITY_KEY_PREFIX, check_capability),
+            policies: entries(&state, POLICY_KEY_PREFIX, Policy::check),
+            commitments,
+            unwritten,
+        });
+        *cache = Some(folded.clone());
+        Ok(folded)
+    }
+}
+
+impl<R: LedgerReader + ?Sized> ContextProvider for LedgerContextProvider<R> {
+    fn capabilities(
+        &self,
+        worldline: &WorldlineId,
+        at: &TemporalAnchor,
+    ) -> Result<Vec<Capability>, GateError> {
+        Ok(self.fold(worldline, at)?.capabilities.clone())
+    }
+
+    fn policies(
+        &self,
+        worldline: &WorldlineId,
+        at: &TemporalAnchor,
+    ) -> Result<Vec<Policy>, GateError> {
+        Ok(self.fold(worldline, at)?.policies.clone())
+    }
+
+    fn commitment_count(
+        &self,
+        worldline: &WorldlineId,
+        since: &TemporalAnchor,
+        until: &TemporalAnchor,
+    ) -> Result<u64, GateError> {
+        let folded = self.fold(worldline, until)?;
+        Ok(folded
+            .commitments
+            .iter()
+            .filter(|t| within(t, since, until))
+            .count() as u64)
+    }
+
+    fn is_unwritten(&self, worldline: &WorldlineId, at: &TemporalAnchor) -> Result<bool, GateError> {
+        Ok(self.fold(worldline, at)?.unwritten)
+    }
+}
+
+/// A worldline's context as of `at_ms`, with the head it was read at.
+struct Folded {
+    worldline: WorldlineId,
+    head: Option<[u8; 32]>,
+    at_ms: u64,
+    capabilities: Vec<Capability>,
+    policies: Vec<Policy>,
+    commitments: Vec<TemporalAnchor>,
+    /// Nothing was recorded as of `at_ms`.
+    unwritten: bool,
+}
+
+/// Whether `state` grants [`ADMIN_CAPABILITY`], unexpired at `at`.
+fn holds_admin(state: &BTreeMap<String, Value>, at: &TemporalAnchor) -> bool {
+    entries(state, CAPABILITY_KEY_PREFIX, check_capability)
+        .iter()
+        .any(|cap: &Capability| cap.id.0 == ADMIN_CAPABILITY && !cap.is_expired_at(at))
+}
+
+/// The values under `prefix` that parse as `T` and pass `check`; `null`
+/// values are removals.
+fn entries<T: serde::de::DeserializeOwned>(
+    state: &BTreeMap<String, Value>,
+    prefix: &str,
+    check: impl Fn(&T) -> Result<(), GateError>,
+) -> Vec<T> {
+    state
+        .range(prefix.to_string()..)
+        .take_while(|(key, _)| key.starts_with(prefix))
+        .filter(|(_, value)| !value.is_null())
+        .filter_map(|(key, value)| {
+            let entry = serde_json::from_value(value.clone())
+                .map_err(|e| e.to_string())
+                .and_then(|entry| check(&entry).map(|()| entry).map_err(|e| e.to_string()));
+            match entry {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    tracing::warn!(key = %key, error = %e, "skipping malformed gate context entry");
+                    None
+                }
+            }
+        })
+        .collect()
+}
@@ -1,20 +1,19 @@
 use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
-use wll_types::{Capability, WorldlineId};
+use wll_types::{Capability, IdentityAttestation, TemporalAnchor, WorldlineId};
 
 use crate::error::GateError;
 use crate::stages::policy::Policy;
 
 // ---------------------------------------------------------------------------
-// CommitmentProposal - defined locally since wll-ledger is not a dependency
+// CommitmentProposal
 // ---------------------------------------------------------------------------
 
 /// A proposal to commit changes, evaluated by the gate pipeline.
 ///
 /// This is a self-contained type that carries everything the gate needs to
-/// make a decision. When wll-ledger is introduced, this may be re-exported
-/// from there instead.
+/// make a decision, independent of how the ledger records commitments.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CommitmentProposal {
     /// Who is proposing this commitment.
@@ -53,7 +52,7 @@ impl CommitmentProposal {
 // ---------------------------------------------------------------------------
 
 /// The outcome of a single gate stage evaluation.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum StageDecision {
     /// The stage passed; proceed to the next stage.
     Pass,
@@ -83,7 +82,7 @@ impl StageDecision {
 // ---------------------------------------------------------------------------
 
 /// Recorded result from a completed stage evaluation.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct StageResult {
     /// Name of the stage that produced this result.
     pub stage_name: String,
@@ -91,15 +90,66 @@ pub struct StageResult {
     pub passed: bool,
     /// Optional reason (populated on failure or deferral).
     pub reason: Option<String>,
-    /// Wall-clock time the stage took to evaluate.
+    /// Wall-clock time the stage took to evaluate. Serialized as
+    /// `elapsed_us`, in microseconds.
+    #[serde(rename = "elapsed_us", with = "micros")]
     pub elapsed: Duration,
 }
 
+/// Serializes a [`Duration`] as a whole number of microseconds.
+pub(crate) mod micros {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(u64::try_from(duration.as_micros()).unwrap_or(u64::MAX))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_micros)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// RuleCheck
+// ---------------------------------------------------------------------------
+
+/// One check a stage made, as reported by [`GateStage::explain`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleCheck {
+    /// Id of the policy the rule belongs to, for policy rules.
+    pub policy: Option<String>,
+    /// The rule, e.g. `require_evidence`. `None` when the stage reports a
+    /// single overall decision.
+    pub rule: Option<String>,
+    /// What the check decided.
+    pub decision: StageDecision,
+}
+
 // ---------------------------------------------------------------------------
 // GateContext
 // ---------------------------------------------------------------------------
 
+/// Size of the change a proposal makes to the tree, as measured by the
+/// caller from its tree and blob diffs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangeSize {
+    /// Paths added, deleted, modified, renamed, or changed mode.
+    pub files: usize,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+}
+
+impl ChangeSize {
+    /// Lines added plus lines removed.
+    pub fn lines_changed(&self) -> usize {
+        self.lines_added + self.lines_removed
+    }
+}
+
 /// Contextual information available to every gate stage.
+#[derive(Clone)]
 pub struct GateContext {
     /// The worldline being committed to.
     pub worldline: WorldlineId,
@@ -107,8 +157,25 @@ pub struct GateContext {
     pub capabilities: Vec<Capability>,
     /// Active policies that apply.
     pub policies: Vec<Policy>,
+    /// Verified external identities bound to worldlines.
+    pub attestations: Vec<IdentityAttestation>,
     /// Results from stages that have already run in this evaluation.
     pub previous_stages: Vec<StageResult>,
+    /// Time the proposal is evaluated at, for expiry checks.
+    pub now: TemporalAnchor,
+    /// Commitments the proposer made within the gate's recent window, as
+    /// counted by its context provider.
+    pub recent_commitments: u64,
+    /// Size of the tree change the proposal makes, if it makes one and the
+    /// caller measured it.
+    pub change: Option<ChangeSize>,
+    /// Gate context keys (`capability/…`, `policy/…`) the proposal's
+    /// outcome would write, as listed by the caller.
+    pub context_keys: Vec<String>,
+    /// Whether the proposal would be the worldline's first commitment,
+    /// which may seed its gate context without the admin capability. Set
+    /// by the context provider when the proposal writes context.
+    pub genesis: bool,
 }
 
 impl GateContext {
@@ -118,7 +185,13 @@ impl GateContext {
             worldline,
             capabilities: Vec::new(),
             policies: Vec::new(),
+            attestations: Vec::new(),
             previous_stages: Vec::new(),
+            now: TemporalAnchor::now(0),
+            recent_commitments: 0,
+            change: None,
+            context_keys: Vec::new(),
+            genesis: false,
         }
     }
 }
@@ -144,4 +217,23 @@ pub trait GateStage: Send + Sync {
         proposal: &CommitmentProposal,
         context: &GateContext,
     ) -> Result<StageDecision, GateError>;
+
+    /// Every check the stage makes, each with its own decision, without
+    /// stopping at the first failure. Used by
+    /// [`CommitmentGate::simulate`](crate::CommitmentGate::simulate).
+    ///
+    /// The default reports [`evaluate`](Self::evaluate) as one unnamed
+    /// check. Stages made of several rules override it so a dry run can
+    /// name the rule that would fire.
+    fn explain(
+        &self,
+        proposal: &CommitmentProposal,
+        context: &GateContext,
+    ) -> Result<Vec<RuleCheck>, GateError> {
+        Ok(vec![RuleCheck {
+            policy: None,
+            rule: None,
+            decision: self.evaluate(proposal, context)?,
+        }])
+    }
 }
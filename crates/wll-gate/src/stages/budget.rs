@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+
+use wll_config::GateSettings;
+use wll_types::CommitmentClass;
+
+use crate::error::GateError;
+use crate::stage::{ChangeSize, CommitmentProposal, GateContext, GateStage, StageDecision};
+
+/// How large a tree change one commitment may make.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChangeBudget {
+    /// Most paths changed.
+    pub max_files: Option<usize>,
+    /// Most lines added and removed, together.
+    pub max_lines: Option<usize>,
+}
+
+impl ChangeBudget {
+    pub fn max_files(max: usize) -> Self {
+        Self {
+            max_files: Some(max),
+            ..Self::default()
+        }
+    }
+
+    pub fn max_lines(max: usize) -> Self {
+        Self {
+            max_lines: Some(max),
+            ..Self::default()
+        }
+    }
+
+    /// Why `size` is over budget, if it is.
+    fn exceeded_by(&self, size: &ChangeSize) -> Option<String> {
+        if let Some(max) = self.max_files.filter(|&max| size.files > max) {
+            return Some(format!(
+                "{} files exceeds its budget of {max} files",
+                size.files
+            ));
+        }
+        if let Some(max) = self.max_lines.filter(|&max| size.lines_changed() > max) {
+            return Some(format!(
+                "{} lines ({} added, {} removed) exceeds its budget of {max} lines",
+                size.lines_changed(),
+                size.lines_added,
+                size.lines_removed
+            ));
+        }
+        None
+    }
+}
+
+/// Change-budget stage.
+///
+/// Rejects commitments whose tree change is larger than the budget of their
+/// class, so that, say, policy changes stay small enough to review. The
+/// change is measured by the caller and passed in
+/// [`GateContext::change`]; proposals without one, and classes without a
+/// budget, pass.
+#[derive(Clone, Debug, Default)]
+pub struct ChangeBudgetStage {
+    budgets: HashMap<CommitmentClass, ChangeBudget>,
+}
+
+impl ChangeBudgetStage {
+    /// Name the stage reports.
+    pub const NAME: &'static str = "change_budget";
+
+    /// A stage with no budgets; add them with [`Self::with_budget`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The budgets of `[gate.change_budget]`.
+    pub fn from_settings(settings: &GateSettings) -> Self {
+        settings
+            .change_budget
+            .iter()
+            .fold(Self::new(), |stage, (class, budget)| {
+                stage.with_budget(
+                    CommitmentClass::from_name(class),
+                    ChangeBudget {
+                        max_files: budget.max_files,
+                        max_lines: budget.max_lines,
+                    },
+                )
+            })
+    }
+
+    /// Limit commitments of `class` to `budget`.
+    pub fn with_budget(mut self, class: CommitmentClass, budget: ChangeBudget) -> Self {
+        self.budgets.insert(class, budget);
+        self
+    }
+
+    /// Returns `true` if no class has a budget.
+    pub fn is_empty(&self) -> bool {
+        self.budgets.is_empty()
+    }
+}
+
+impl GateStage for ChangeBudgetStage {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn evaluate(
+        &self,
+        proposal: &CommitmentProposal,
+        context: &GateContext,
+    ) -> Result<StageDecision, GateError> {
+        let (Some(budget), Some(size)) = (self.budgets.get(&proposal.class), &context.change)
+        else {
+            return Ok(StageDecision::Pass);
+        };
+        Ok(match budget.exceeded_by(size) {
+            Some(excess) => StageDecision::Fail {
+                reason: format!("{} change of {excess}", proposal.class),
+            },
+            None => StageDecision::Pass,
+        })
+    }
+}
@@ -1,12 +1,40 @@
-use wll_types::TemporalAnchor;
+use globset::{GlobBuilder, GlobMatcher};
+use wll_types::{Capability, CapabilityScope, WorldlineId};
 
 use crate::error::GateError;
+use crate::provider::check_context_change;
 use crate::stage::{CommitmentProposal, GateContext, GateStage, StageDecision};
 
 /// Capability verification stage.
 ///
 /// Checks that the proposer holds every capability they have claimed in the
 /// proposal. Capabilities are matched by ID and must not be expired.
+///
+/// Every target of the proposal must be covered by some unexpired grant of
+/// each claimed capability. What a grant covers depends on its scope:
+///
+/// - [`CapabilityScope::Path`] covers the proposer's targets matching its
+///   glob (`*` stays within one path segment, `**` spans any number).
+/// - [`CapabilityScope::Worldline`] covers targets on that worldline, so a
+///   grant scoped to another worldline never satisfies the proposer's own
+///   targets.
+/// - Global and custom scopes cover the proposer's own targets.
+///
+/// Path globs that do not parse are refused when grants are loaded
+/// ([`check_capability`]). A grant holding one that reaches evaluation
+/// anyway covers nothing; the other grants still count, and a failure names
+/// the ignored glob.
+///
+/// A target written `wl:<hex>` names a worldline; when it is not the
+/// proposer's, the proposal acts across worldlines and is only covered by
+/// grants scoped to that worldline. Such a proposal must claim one. A
+/// `wl:` target that is not a worldline id fails the stage rather than
+/// being read as a path.
+///
+/// A proposal writing gate context ([`GateContext::context_keys`]) must
+/// also be allowed to ([`check_context_change`]): a `PolicyChange` that
+/// claims, and so must hold, the admin capability, unless it is the
+/// worldline's genesis.
 pub struct CapabilityStage;
 
 impl GateStage for CapabilityStage {
@@ -19,24 +47,202 @@ impl GateStage for CapabilityStage {
         proposal: &CommitmentProposal,
         context: &GateContext,
     ) -> Result<StageDecision, GateError> {
+        let targets = proposal
+            .targets
+            .iter()
+            .map(|t| Target::parse(t, &proposal.proposer))
+            .collect::<Result<Vec<_>, _>>();
+        let targets = match targets {
+            Ok(targets) => targets,
+            Err(reason) => return Ok(StageDecision::Fail { reason }),
+        };
+        if let Some(key) = context.context_keys.first() {
+            let allowed = check_context_change(
+                &proposal.class,
+                &proposal.claimed_capabilities,
+                context.genesis,
+            );
+            if let Err(e) = allowed {
+                return Ok(StageDecision::Fail { reason: format!("cannot write {key}: {e}") });
+            }
+        }
+
         if proposal.claimed_capabilities.is_empty() {
-            // Nothing claimed, nothing to verify.
+            // Nothing claimed, nothing to verify -- unless the proposal
+            // reaches into another worldline.
+            if let Some(target) = targets.iter().find(|t| t.worldline.is_some()) {
+                return Ok(StageDecision::Fail {
+                    reason: format!(
+                        "target {:?} is on another worldline; cross-worldline proposals need a \
+                         capability scoped to that worldline",
+                        target.name
+                    ),
+                });
+            }
             return Ok(StageDecision::Pass);
         }
 
-        let now = TemporalAnchor::now(0);
-
         for claimed in &proposal.claimed_capabilities {
-            let held = context.capabilities.iter().any(|cap| {
-                cap.id.0 == *claimed && !cap.is_expired_at(&now)
-            });
-            if !held {
+            let grants: Vec<&Capability> = context
+                .capabilities
+                .iter()
+                .filter(|cap| cap.id.0 == *claimed && !cap.is_expired_at(&context.now))
+                .collect();
+            if grants.is_empty() {
                 return Ok(StageDecision::Fail {
                     reason: format!("proposer lacks required capability: {claimed}"),
                 });
             }
+            if let Some(reason) = uncovered_target(claimed, &grants, &targets, &proposal.proposer)
+            {
+                return Ok(StageDecision::Fail { reason });
+            }
         }
 
         Ok(StageDecision::Pass)
     }
 }
+
+/// A proposal target, with the worldline it names if that is not the
+/// proposer's.
+struct Target<'a> {
+    name: &'a str,
+    worldline: Option<WorldlineId>,
+}
+
+impl<'a> Target<'a> {
+    /// Fails with the reason if `name` starts with `wl:` but is not a
+    /// worldline id.
+    fn parse(name: &'a str, proposer: &WorldlineId) -> Result<Self, String> {
+        if !name.starts_with("wl:") {
+            return Ok(Self { name, worldline: None });
+        }
+        let worldline = WorldlineId::from_hex(name)
+            .map_err(|e| format!("target {name:?} is not a valid worldline id: {e}"))?;
+        Ok(Self {
+            name,
+            worldline: Some(worldline).filter(|wl| wl != proposer),
+        })
+    }
+}
+
+/// What a grant covers, with its path glob compiled.
+enum Coverage<'a> {
+    Own,
+    Path(&'a str, GlobMatcher),
+    Worldline(&'a WorldlineId),
+}
+
+impl Coverage<'_> {
+    fn covers(&self, target: &Target<'_>, proposer: &WorldlineId) -> bool {
+        match (self, &target.worldline) {
+            (Self::Own, None) => true,
+            (Self::Path(_, matcher), None) => matcher.is_match(target.name),
+            (Self::Worldline(wl), None) => *wl == proposer,
+            (Self::Worldline(wl), Some(other)) => *wl == other,
+            (Self::Own | Self::Path(..), Some(_)) => false,
+        }
+    }
+}
+
+impl std::fmt::Display for Coverage<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Own => write!(f, "the proposer's worldline"),
+            Self::Path(pattern, _) => write!(f, "{pattern}"),
+            Self::Worldline(wl) => write!(f, "worldline {wl}"),
+        }
+    }
+}
+
+/// Why `grants` of `claimed` do not cover every target, if they do not.
+///
+/// A proposal without targets is judged as acting on the proposer's own
+/// worldline.
+fn uncovered_target(
+    claimed: &str,
+    grants: &[&Capability],
+    targets: &[Target<'_>],
+    proposer: &WorldlineId,
+) -> Option<String> {
+    let mut coverage = Vec::with_capacity(grants.len());
+    let mut invalid = Vec::new();
+    for grant in grants {
+        coverage.push(match &grant.scope {
+            CapabilityScope::Path(pattern) => match path_matcher(pattern) {
+                Ok(matcher) => Coverage::Path(pattern, matcher),
+                Err(e) => {
+                    invalid.push(format!("{pattern:?} ({e})"));
+                    continue;
+                }
+            },
+            CapabilityScope::Worldline(wl) => Coverage::Worldline(wl),
+            CapabilityScope::Global | CapabilityScope::Custom(_) => Coverage::Own,
+        });
+    }
+    let scopes = || {
+        let mut scopes = coverage.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ");
+        if !invalid.is_empty() {
+            if !scopes.is_empty() {
+                scopes.push_str("; ");
+            }
+            scopes.push_str("ignoring invalid path scope ");
+            scopes.push_str(&invalid.join(", "));
+        }
+        scopes
+    };
+
+    if targets.is_empty() {
+        let own = Target { name: "", worldline: None };
+        if !coverage.iter().any(|c| c.covers(&own, proposer)) {
+            return Some(format!(
+                "capability {claimed} does not cover the proposer's worldline {proposer} \
+                 (scoped to {})",
+                scopes()
+            ));
+        }
+        return None;
+    }
+
+    let target = targets
+        .iter()
+        .find(|target| !coverage.iter().any(|c| c.covers(target, proposer)))?;
+    Some(match &target.worldline {
+        Some(wl) => format!(
+            "capability {claimed} does not cover target {:?} on worldline {wl} (scoped to {}); \
+             cross-worldline targets need a grant scoped to that worldline",
+            target.name,
+            scopes()
+        ),
+        None => format!(
+            "capability {claimed} does not cover target {:?} (scoped to {})",
+            target.name,
+            scopes()
+        ),
+    })
+}
+
+/// Check that `capability` can be enforced: a path scope must be a valid
+/// glob.
+///
+/// Grants are checked where they are loaded (context providers and the
+/// `capability/` state a commit records), so a bad glob is refused when it
+/// is written rather than ignored on evaluation.
+pub fn check_capability(capability: &Capability) -> Result<(), GateError> {
+    if let CapabilityScope::Path(pattern) = &capability.scope {
+        path_matcher(pattern).map_err(|e| {
+            GateError::Config(format!(
+                "capability {}: invalid path scope {pattern:?}: {e}",
+                capability.id.0
+            ))
+        })?;
+    }
+    Ok(())
+}
+
+fn path_matcher(pattern: &str) -> Result<GlobMatcher, globset::Error> {
+    Ok(GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .build()?
+        .compile_matcher())
+}
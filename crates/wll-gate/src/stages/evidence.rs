@@ -0,0 +1,156 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::error::GateError;
+use crate::evidence::{EvidenceError, EvidenceRef, EvidenceResolver};
+use crate::stage::{CommitmentProposal, GateContext, GateStage, StageDecision};
+
+/// Evidence verification stage.
+///
+/// Checks the bundle digest, then resolves every reference whose scheme has
+/// a resolver and compares the content with its pin. References to missing
+/// or mismatching content fail the proposal; a source that can't be reached
+/// defers it. References with no resolver (`issue://`, `doc://`) are
+/// treated as opaque and pass, unless they carry a pin that nothing can
+/// check. Attachments are resolved as `obj://` references when an
+/// object-store resolver is installed, and must have their recorded size.
+pub struct EvidenceStage {
+    resolvers: Vec<Arc<dyn EvidenceResolver>>,
+    retry_after: Duration,
+}
+
+impl EvidenceStage {
+    /// A stage with no resolvers; add them with [`Self::with_resolver`].
+    pub fn new() -> Self {
+        Self {
+            resolvers: Vec::new(),
+            retry_after: Duration::from_secs(60),
+        }
+    }
+
+    /// Resolve references of `resolver`'s schemes with it. Earlier
+    /// resolvers win when schemes overlap.
+    pub fn with_resolver(mut self, resolver: Arc<dyn EvidenceResolver>) -> Self {
+        self.resolvers.push(resolver);
+        self
+    }
+
+    /// Retry delay suggested when a source is unavailable.
+    pub fn with_retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = retry_after;
+        self
+    }
+
+    fn resolver(&self, scheme: &str) -> Option<&dyn EvidenceResolver> {
+        self.resolvers
+            .iter()
+            .find(|r| r.schemes().contains(&scheme))
+            .map(|r| r.as_ref())
+    }
+}
+
+impl Default for EvidenceStage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GateStage for EvidenceStage {
+    fn name(&self) -> &str {
+        "evidence"
+    }
+
+    fn evaluate(
+        &self,
+        proposal: &CommitmentProposal,
+        _context: &GateContext,
+    ) -> Result<StageDecision, GateError> {
+        if !proposal.evidence.verify_digest() {
+            return Ok(StageDecision::Fail {
+                reason: "evidence bundle digest does not match its references".into(),
+            });
+        }
+
+        for uri in &proposal.evidence.references {
+            let reference = match EvidenceRef::parse(uri) {
+                Ok(reference) => reference,
+                Err(e) => {
+                    return Ok(StageDecision::Fail {
+                        reason: e.to_string(),
+                    })
+                }
+            };
+            let Some(resolver) = self.resolver(&reference.scheme) else {
+                if reference.pin.is_some() {
+                    return Ok(StageDecision::Fail {
+                        reason: format!("no resolver can check pinned evidence {uri}"),
+                    });
+                }
+                continue;
+            };
+            match resolver.resolve(&reference) {
+                Ok(content) => {
+                    if let Some(pin) = &reference.pin {
+                        if !pin.matches(&content) {
+                            return Ok(StageDecision::Fail {
+                                reason: format!(
+                                    "evidence {uri} does not match its {} pin",
+                                    pin.algo
+                                ),
+                            });
+                        }
+                    }
+                }
+                Err(EvidenceError::Unavailable(reason)) => {
+                    return Ok(StageDecision::Defer {
+                        reason: format!("evidence {uri} unavailable: {reason}"),
+                        retry_after: self.retry_after,
+                    });
+                }
+                Err(e) => {
+                    return Ok(StageDecision::Fail {
+                        reason: format!("evidence {uri}: {e}"),
+                    });
+                }
+            }
+        }
+
+        if let Some(resolver) = self.resolver("obj") {
+            for attachment in &proposal.evidence.attachments {
+                let location = attachment.object.to_hex();
+                let reference = EvidenceRef {
+                    uri: format!("obj://{location}"),
+                    scheme: "obj".into(),
+                    location,
+                    pin: None,
+                };
+                match resolver.resolve(&reference) {
+                    Ok(content) if content.len() as u64 == attachment.size => {}
+                    Ok(content) => {
+                        return Ok(StageDecision::Fail {
+                            reason: format!(
+                                "attachment {} is {} bytes, expected {}",
+                                attachment.name,
+                                content.len(),
+                                attachment.size
+                            ),
+                        });
+                    }
+                    Err(EvidenceError::Unavailable(reason)) => {
+                        return Ok(StageDecision::Defer {
+                            reason: format!("attachment {} unavailable: {reason}", attachment.name),
+                            retry_after: self.retry_after,
+                        });
+                    }
+                    Err(e) => {
+                        return Ok(StageDecision::Fail {
+                            reason: format!("attachment {}: {e}", attachment.name),
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(StageDecision::Pass)
+    }
+}
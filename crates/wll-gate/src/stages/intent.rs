@@ -0,0 +1,254 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use wll_config::GateSettings;
+use wll_types::CommitmentClass;
+
+use crate::error::GateError;
+use crate::stage::{CommitmentProposal, GateContext, GateStage, StageDecision};
+
+/// The first line of an intent in conventional-commit form:
+/// `type(scope)!: subject`, with the scope and `!` optional.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConventionalIntent<'a> {
+    /// Kind of change, such as `feat` or `fix`: lowercase letters, digits
+    /// and `-`.
+    pub kind: &'a str,
+    pub scope: Option<&'a str>,
+    /// Marked `!`: the change breaks compatibility.
+    pub breaking: bool,
+    pub subject: &'a str,
+}
+
+impl<'a> ConventionalIntent<'a> {
+    /// Parse the first line of `intent`, or `None` if it is not in the
+    /// conventional form.
+    pub fn parse(intent: &'a str) -> Option<Self> {
+        let line = intent.lines().next()?;
+        let (head, subject) = line.split_once(": ")?;
+        let (head, breaking) = match head.strip_suffix('!') {
+            Some(head) => (head, true),
+            None => (head, false),
+        };
+        let (kind, scope) = match head.split_once('(') {
+            Some((kind, scope)) => (kind, Some(scope.strip_suffix(')')?)),
+            None => (head, None),
+        };
+        let is_word = |s: &str| {
+            !s.is_empty()
+                && s.chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        };
+        if !is_word(kind)
+            || scope.is_some_and(|s| s.trim().is_empty() || s.contains(['(', ')']))
+            || subject.trim().is_empty()
+        {
+            return None;
+        }
+        Some(Self { kind, scope, breaking, subject })
+    }
+}
+
+impl fmt::Display for ConventionalIntent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(scope) = self.scope {
+            write!(f, "({scope})")?;
+        }
+        if self.breaking {
+            write!(f, "!")?;
+        }
+        write!(f, ": {}", self.subject)
+    }
+}
+
+/// Whether `intent` or `evidence` references an issue: `#123` or
+/// `PROJ-42` in the intent, or an `issue://` evidence URI.
+pub fn references_issue(intent: &str, evidence: &[String]) -> bool {
+    let is_issue = |word: &str| {
+        let word = word.trim_matches(|c: char| !c.is_ascii_alphanumeric() && c != '#');
+        let digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+        match word.split_once('-') {
+            _ if word.starts_with('#') => digits(&word[1..]),
+            Some((project, number)) => {
+                !project.is_empty()
+                    && project.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+                    && project.starts_with(|c: char| c.is_ascii_uppercase())
+                    && digits(number)
+            }
+            None => false,
+        }
+    };
+    intent.split_whitespace().any(is_issue)
+        || evidence.iter().any(|uri| uri.starts_with("issue://"))
+}
+
+/// Intent-lint stage.
+///
+/// Holds intents to the repository's conventions: the `type(scope): subject`
+/// form with allowed types and scopes, a longest first line, and an issue
+/// reference for commitments of chosen classes. Every convention an intent
+/// breaks is reported in one rejection, with an example of the form where
+/// that helps. With nothing configured every intent passes.
+#[derive(Clone, Debug, Default)]
+pub struct IntentLintStage {
+    conventional: bool,
+    types: Vec<String>,
+    scopes: Vec<String>,
+    require_scope: bool,
+    max_subject_len: Option<usize>,
+    require_issue: HashSet<CommitmentClass>,
+}
+
+impl IntentLintStage {
+    /// Name the stage reports.
+    pub const NAME: &'static str = "intent_lint";
+
+    /// A stage enforcing nothing; add conventions with the `with_` methods.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The conventions of `[gate.intent]`.
+    pub fn from_settings(settings: &GateSettings) -> Self {
+        let intent = &settings.intent;
+        let mut stage = Self::new()
+            .with_types(intent.types.iter().cloned())
+            .with_scopes(intent.scopes.iter().cloned());
+        stage.conventional |= intent.conventional;
+        stage.require_scope = intent.require_scope;
+        stage.max_subject_len = intent.max_subject_len;
+        intent.require_issue.iter().fold(stage, |stage, class| {
+            stage.with_issue_required(CommitmentClass::from_name(class))
+        })
+    }
+
+    /// Require the conventional form.
+    pub fn conventional(mut self) -> Self {
+        self.conventional = true;
+        self
+    }
+
+    /// Require the conventional form with one of `types`.
+    pub fn with_types<S: Into<String>>(mut self, types: impl IntoIterator<Item = S>) -> Self {
+        self.types.extend(types.into_iter().map(Into::into));
+        self.conventional |= !self.types.is_empty();
+        self
+    }
+
+    /// Only allow `scopes`, when an intent has a scope.
+    pub fn with_scopes<S: Into<String>>(mut self, scopes: impl IntoIterator<Item = S>) -> Self {
+        self.scopes.extend(scopes.into_iter().map(Into::into));
+        self
+    }
+
+    /// Require the conventional form with a scope.
+    pub fn with_required_scope(mut self) -> Self {
+        self.conventional = true;
+        self.require_scope = true;
+        self
+    }
+
+    /// Limit the first line to `max` characters.
+    pub fn with_max_subject_len(mut self, max: usize) -> Self {
+        self.max_subject_len = Some(max);
+        self
+    }
+
+    /// Require intents of `class` to reference an issue.
+    pub fn with_issue_required(mut self, class: CommitmentClass) -> Self {
+        self.require_issue.insert(class);
+        self
+    }
+
+    /// Returns `true` if no convention is enforced.
+    pub fn is_empty(&self) -> bool {
+        !self.conventional
+            && self.scopes.is_empty()
+            && self.max_subject_len.is_none()
+            && self.require_issue.is_empty()
+    }
+
+    /// Every convention `proposal` breaks, each as a sentence saying how to
+    /// fix it.
+    pub fn violations(&self, proposal: &CommitmentProposal) -> Vec<String> {
+        let intent = proposal.intent.as_str();
+        let mut violations = Vec::new();
+        let first_line = intent.lines().next().unwrap_or_default();
+        if let Some(max) = self.max_subject_len {
+            let len = first_line.chars().count();
+            if len > max {
+                violations.push(format!(
+                    "the first line is {len} characters; shorten it to at most {max}"
+                ));
+            }
+        }
+        match ConventionalIntent::parse(intent) {
+            None if self.conventional => violations.push(format!(
+                "{first_line:?} is not in the form \"type(scope): subject\", e.g. \"{}\"",
+                self.example()
+            )),
+            None => {}
+            Some(parsed) => {
+                if !self.types.is_empty() && !self.types.iter().any(|t| t == parsed.kind) {
+                    violations.push(format!(
+                        "type {:?} is not one of {}",
+                        parsed.kind,
+                        self.types.join(", ")
+                    ));
+                }
+                let allowed =
+                    |scope: &str| self.scopes.is_empty() || self.scopes.iter().any(|s| s == scope);
+                match parsed.scope {
+                    None if self.require_scope => violations.push(format!(
+                        "a scope is required, e.g. \"{}({}): {}\"",
+                        parsed.kind,
+                        self.scopes.first().map_or("core", String::as_str),
+                        parsed.subject
+                    )),
+                    Some(scope) if !allowed(scope) => violations.push(format!(
+                        "scope {scope:?} is not one of {}",
+                        self.scopes.join(", ")
+                    )),
+                    _ => {}
+                }
+            }
+        }
+        if self.require_issue.contains(&proposal.class)
+            && !references_issue(intent, &proposal.evidence.references)
+        {
+            violations.push(format!(
+                "{} commitments must reference an issue: add \"#123\" or \"PROJ-42\" to the \
+                 intent, or issue:// evidence",
+                proposal.class
+            ));
+        }
+        violations
+    }
+
+    fn example(&self) -> String {
+        let kind = self.types.first().map_or("fix", String::as_str);
+        let scope = self.scopes.first().map_or("core", String::as_str);
+        format!("{kind}({scope}): describe the change")
+    }
+}
+
+impl GateStage for IntentLintStage {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn evaluate(
+        &self,
+        proposal: &CommitmentProposal,
+        _context: &GateContext,
+    ) -> Result<StageDecision, GateError> {
+        let violations = self.violations(proposal);
+        Ok(match violations.is_empty() {
+            true => StageDecision::Pass,
+            false => StageDecision::Fail {
+                reason: format!("intent breaks the conventions: {}", violations.join("; ")),
+            },
+        })
+    }
+}
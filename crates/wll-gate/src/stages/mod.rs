@@ -1,9 +1,15 @@
 //! Built-in gate stages.
 
+pub mod budget;
 pub mod capability;
+pub mod evidence;
+pub mod intent;
 pub mod policy;
 pub mod validation;
 
+pub use budget::{ChangeBudget, ChangeBudgetStage};
 pub use capability::CapabilityStage;
+pub use evidence::EvidenceStage;
+pub use intent::{ConventionalIntent, IntentLintStage};
 pub use policy::PolicyStage;
 pub use validation::ValidationStage;
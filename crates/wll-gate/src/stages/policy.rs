@@ -1,8 +1,11 @@
+use std::fmt;
+
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
 use serde::{Deserialize, Serialize};
-use wll_types::{CommitmentClass, WorldlineId};
+use wll_types::{AttestationKind, CommitmentClass, WorldlineId};
 
 use crate::error::GateError;
-use crate::stage::{CommitmentProposal, GateContext, GateStage, StageDecision};
+use crate::stage::{CommitmentProposal, GateContext, GateStage, RuleCheck, StageDecision};
 
 // ---------------------------------------------------------------------------
 // Policy types
@@ -32,6 +35,30 @@ impl Policy {
         }
     }
 
+    /// Check that every rule can be enforced: target patterns must be
+    /// valid globs.
+    ///
+    /// Policies are checked where they are loaded (context providers and
+    /// the `policy/` state a commit records), so a bad pattern is refused
+    /// when it is written rather than found on evaluation.
+    pub fn check(&self) -> Result<(), GateError> {
+        for rule in &self.rules {
+            if let PolicyRule::AllowTargets { patterns, case_insensitive }
+            | PolicyRule::DenyTargets { patterns, case_insensitive } = rule
+            {
+                for pattern in patterns {
+                    target_glob(pattern, *case_insensitive).map_err(|e| {
+                        GateError::Config(format!(
+                            "policy {:?}: invalid target pattern {pattern:?}: {e}",
+                            self.id
+                        ))
+                    })?;
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Check whether this policy applies to the given proposal.
     pub fn applies(&self, proposal: &CommitmentProposal) -> bool {
         match &self.applies_to {
@@ -63,6 +90,25 @@ pub enum PolicyRule {
     DenyClasses(Vec<CommitmentClass>),
     /// Commits of this class require review (treated as fail without review flag).
     RequireReviewFor(CommitmentClass),
+    /// Commits of this class require the proposer to hold an unexpired
+    /// identity attestation, of `kind` if given.
+    RequireAttestation {
+        class: CommitmentClass,
+        kind: Option<AttestationKind>,
+    },
+    /// Every target must match one of these globs (`infra/**`; `*` stays
+    /// within one path segment).
+    AllowTargets {
+        patterns: Vec<String>,
+        #[serde(default)]
+        case_insensitive: bool,
+    },
+    /// No target may match any of these globs.
+    DenyTargets {
+        patterns: Vec<String>,
+        #[serde(default)]
+        case_insensitive: bool,
+    },
     /// Domain-specific custom rule.
     Custom {
         name: String,
@@ -70,6 +116,37 @@ pub enum PolicyRule {
     },
 }
 
+impl fmt::Display for PolicyRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let classes = |classes: &[CommitmentClass]| {
+            classes.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(", ")
+        };
+        let case = |insensitive| if insensitive { "; case-insensitive" } else { "" };
+        match self {
+            Self::RequireCapability(cap) => write!(f, "require_capability({cap})"),
+            Self::RequireEvidence => write!(f, "require_evidence"),
+            Self::RequireSignature => write!(f, "require_signature"),
+            Self::MaxTargets(max) => write!(f, "max_targets({max})"),
+            Self::AllowedClasses(allowed) => write!(f, "allowed_classes({})", classes(allowed)),
+            Self::DenyClasses(denied) => write!(f, "deny_classes({})", classes(denied)),
+            Self::RequireReviewFor(class) => write!(f, "require_review_for({class})"),
+            Self::RequireAttestation { class, kind: Some(kind) } => {
+                write!(f, "require_attestation({class}, {kind})")
+            }
+            Self::RequireAttestation { class, kind: None } => {
+                write!(f, "require_attestation({class})")
+            }
+            Self::AllowTargets { patterns, case_insensitive } => {
+                write!(f, "allow_targets({}{})", patterns.join(", "), case(*case_insensitive))
+            }
+            Self::DenyTargets { patterns, case_insensitive } => {
+                write!(f, "deny_targets({}{})", patterns.join(", "), case(*case_insensitive))
+            }
+            Self::Custom { name, .. } => write!(f, "custom({name})"),
+        }
+    }
+}
+
 /// Scope controlling when a policy is evaluated.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum PolicyScope {
@@ -91,6 +168,11 @@ pub enum PolicyScope {
 ///
 /// Evaluates every applicable policy against the proposal. All rules in all
 /// applicable policies must pass for the stage to pass.
+///
+/// Target patterns that do not parse are refused when policies are loaded
+/// ([`Policy::check`]). One that reaches evaluation anyway never lets a
+/// proposal through: it allows no target, and a deny rule holding one fails
+/// the stage. Either failure names the pattern.
 pub struct PolicyStage;
 
 impl PolicyStage {
@@ -187,6 +269,66 @@ impl PolicyStage {
                 }
             }
 
+            PolicyRule::RequireAttestation { class, kind } => {
+                if proposal.class != *class {
+                    return Ok(StageDecision::Pass);
+                }
+                let now = context.now.physical_ms / 1000;
+                let attested = context
+                    .attestations
+                    .iter()
+                    .any(|a| a.satisfies(&proposal.proposer, *kind, now));
+                if attested {
+                    Ok(StageDecision::Pass)
+                } else {
+                    let who = match kind {
+                        Some(kind) => format!("a {kind}"),
+                        None => "an".into(),
+                    };
+                    Ok(StageDecision::Fail {
+                        reason: format!(
+                            "commitment class '{}' requires {who} identity attestation",
+                            proposal.class
+                        ),
+                    })
+                }
+            }
+
+            PolicyRule::AllowTargets { patterns, case_insensitive } => {
+                let (globs, invalid) = target_globs(patterns, *case_insensitive)?;
+                match proposal.targets.iter().find(|t| !globs.is_match(t.as_str())) {
+                    Some(target) => Ok(StageDecision::Fail {
+                        reason: format!(
+                            "target {target:?} is outside the allowed paths ({}){}",
+                            patterns.join(", "),
+                            ignoring(&invalid)
+                        ),
+                    }),
+                    None => Ok(StageDecision::Pass),
+                }
+            }
+
+            PolicyRule::DenyTargets { patterns, case_insensitive } => {
+                let (globs, invalid) = target_globs(patterns, *case_insensitive)?;
+                if !invalid.is_empty() {
+                    return Ok(StageDecision::Fail {
+                        reason: format!(
+                            "cannot tell which targets are denied: invalid target pattern {}",
+                            invalid.join(", ")
+                        ),
+                    });
+                }
+                match proposal.targets.iter().find(|t| globs.is_match(t.as_str())) {
+                    Some(target) => Ok(StageDecision::Fail {
+                        reason: format!(
+                            "target {target:?} is denied by policy ({})",
+                            patterns.join(", ")
+                        ),
+                    }),
+                    None => Ok(StageDecision::Pass),
+                }
+            }
+
             PolicyRule::Custom { name, .. } => {
                 // Custom rules pass by default; real implementations would
                 // delegate to a plugin system.
@@ -197,6 +339,41 @@ impl PolicyStage {
     }
 }
 
+/// Compile target path globs with the same syntax as path-scoped
+/// capabilities, returning the patterns that do not parse apart.
+fn target_globs(
+    patterns: &[String],
+    case_insensitive: bool,
+) -> Result<(GlobSet, Vec<String>), GateError> {
+    let mut set = GlobSetBuilder::new();
+    let mut invalid = Vec::new();
+    for pattern in patterns {
+        match target_glob(pattern, case_insensitive) {
+            Ok(glob) => {
+                set.add(glob);
+            }
+            Err(e) => invalid.push(format!("{pattern:?} ({e})")),
+        }
+    }
+    let set = set.build().map_err(|e| GateError::Config(e.to_string()))?;
+    Ok((set, invalid))
+}
+
+fn target_glob(pattern: &str, case_insensitive: bool) -> Result<Glob, globset::Error> {
+    GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .case_insensitive(case_insensitive)
+        .build()
+}
+
+/// A note naming ignored `invalid` patterns, if there are any.
+fn ignoring(invalid: &[String]) -> String {
+    match invalid.is_empty() {
+        true => String::new(),
+        false => format!("; ignoring invalid target pattern {}", invalid.join(", ")),
+    }
+}
+
 impl GateStage for PolicyStage {
     fn name(&self) -> &str {
         "policy"
@@ -222,4 +399,23 @@ impl GateStage for PolicyStage {
 
         Ok(StageDecision::Pass)
     }
+
+    /// One check per rule of every policy that applies to the proposal.
+    fn explain(
+        &self,
+        proposal: &CommitmentProposal,
+        context: &GateContext,
+    ) -> Result<Vec<RuleCheck>, GateError> {
+        let mut checks = Vec::new();
+        for policy in context.policies.iter().filter(|p| p.applies(proposal)) {
+            for rule in &policy.rules {
+                checks.push(RuleCheck {
+                    policy: Some(policy.id.clone()),
+                    rule: Some(rule.to_string()),
+                    decision: Self::evaluate_rule(rule, proposal, context)?,
+                });
+            }
+        }
+        Ok(checks)
+    }
 }
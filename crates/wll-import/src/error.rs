@@ -0,0 +1,46 @@
+use thiserror::Error;
+use wll_types::{Coded, ErrorCode};
+
+#[derive(Debug, Error)]
+pub enum ImportError {
+    /// Running `git` failed or it reported an error.
+    #[error("git: {0}")]
+    Git(String),
+
+    /// `git` returned an object this importer cannot parse.
+    #[error("malformed git object {id}: {reason}")]
+    Malformed { id: String, reason: String },
+
+    /// A branch named for import does not exist in the git repository.
+    #[error("git branch not found: {0}")]
+    BranchNotFound(String),
+
+    /// The target repository already has a branch or tag the import would
+    /// write.
+    #[error("ref already exists: {0}")]
+    RefExists(String),
+
+    #[error("SDK error: {0}")]
+    Sdk(#[from] wll_sdk::SdkError),
+
+    #[error("ref error: {0}")]
+    Ref(#[from] wll_refs::RefError),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl Coded for ImportError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::Git(_) | Self::Io(_) => ErrorCode::Io,
+            Self::Malformed { .. } => ErrorCode::ObjectCorrupt,
+            Self::BranchNotFound(_) => ErrorCode::RefNotFound,
+            Self::RefExists(_) => ErrorCode::RefExists,
+            Self::Sdk(e) => e.code(),
+            Self::Ref(e) => e.code(),
+        }
+    }
+}
+
+pub type ImportResult<T> = Result<T, ImportError>;
@@ -0,0 +1,421 @@
+//! Reading a git repository through the `git` command-line tool.
+//!
+//! Objects come from one long-running `git cat-file --batch` process, so
+//! anything `git` itself can read works: loose or packed, SHA-1 or SHA-256,
+//! bare or with a work tree.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use crate::error::{ImportError, ImportResult};
+
+/// Run `git -C <repo> <args>` and return its trimmed stdout.
+fn git(repo: &Path, args: &[&str]) -> ImportResult<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| ImportError::Git(format!("cannot run git: {e}")))?;
+    if !output.status.success() {
+        return Err(ImportError::Git(format!(
+            "git {} {}: {}",
+            args.join(" "),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_string())
+}
+
+/// A git repository on disk.
+pub(crate) struct GitRepo {
+    path: PathBuf,
+    /// Raw object id length: 20 for SHA-1, 32 for SHA-256.
+    hash_len: usize,
+}
+
+impl GitRepo {
+    pub fn open(path: &Path) -> ImportResult<Self> {
+        git(path, &["rev-parse", "--git-dir"])?;
+        let hash_len = match git(path, &["rev-parse", "--show-object-format"]).as_deref() {
+            Ok("sha256") => 32,
+            _ => 20,
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            hash_len,
+        })
+    }
+
+    /// Every branch and tag as `(full ref name, object id)`.
+    pub fn refs(&self) -> ImportResult<Vec<(String, String)>> {
+        let out = git(
+            &self.path,
+            &[
+                "for-each-ref",
+                "--format=%(objectname) %(refname)",
+                "refs/heads",
+                "refs/tags",
+            ],
+        )?;
+        Ok(out
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .map(|(id, name)| (name.to_string(), id.to_string()))
+            .collect())
+    }
+
+    /// Branch HEAD points at, if it is symbolic.
+    pub fn head_branch(&self) -> Option<String> {
+        git(&self.path, &["symbolic-ref", "-q", "HEAD"])
+            .ok()
+            .and_then(|name| name.strip_prefix("refs/heads/").map(str::to_string))
+    }
+
+    /// Commits reachable from `tips`, every parent before its children.
+    pub fn rev_list(&self, tips: &[String]) -> ImportResult<Vec<String>> {
+        if tips.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut args = vec!["rev-list", "--topo-order", "--reverse"];
+        args.extend(tips.iter().map(String::as_str));
+        Ok(git(&self.path, &args)?
+            .lines()
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Start a reader for this repository's objects.
+    pub fn objects(&self) -> ImportResult<CatFile> {
+        let mut child = Command::new("git")
+            .arg("-C")
+            .arg(&self.path)
+            .args(["cat-file", "--batch"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| ImportError::Git(format!("cannot run git: {e}")))?;
+        let stdin = child.stdin.take();
+        let stdout = child.stdout.take().map(BufReader::new);
+        match (stdin, stdout) {
+            (Some(stdin), Some(stdout)) => Ok(CatFile {
+                child,
+                stdin: Some(stdin),
+                stdout,
+                hash_len: self.hash_len,
+            }),
+            _ => Err(ImportError::Git("cat-file has no pipes".into())),
+        }
+    }
+}
+
+/// Who made a commit or tag, and when.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct GitIdent {
+    pub name: String,
+    pub email: String,
+    /// Seconds since the Unix epoch.
+    pub time: i64,
+    /// UTC offset as written by git, e.g. `+0100`.
+    pub offset: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct GitCommit {
+    pub tree: String,
+    pub parents: Vec<String>,
+    pub author: GitIdent,
+    pub committer: GitIdent,
+    pub message: String,
+}
+
+/// An annotated tag object.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct GitTag {
+    pub object: String,
+    pub tagger: Option<GitIdent>,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct GitTreeEntry {
+    pub mode: u32,
+    pub name: String,
+    pub id: String,
+}
+
+/// Object reader backed by `git cat-file --batch`.
+pub(crate) struct CatFile {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+    hash_len: usize,
+}
+
+impl CatFile {
+    /// Type and contents of object `id`.
+    pub fn read(&mut self, id: &str) -> ImportResult<(String, Vec<u8>)> {
+        let stdin = self
+            .stdin
+            .as_mut()
+            .ok_or_else(|| ImportError::Git("cat-file closed".into()))?;
+        writeln!(stdin, "{id}")?;
+        stdin.flush()?;
+
+        let mut header = String::new();
+        self.stdout.read_line(&mut header)?;
+        let fields: Vec<&str> = header.split_whitespace().collect();
+        let (kind, size) = match fields[..] {
+            [_, kind, size] => (kind.to_string(), size),
+            _ => {
+                return Err(ImportError::Git(format!(
+                    "cannot read object {id}: {}",
+                    header.trim()
+                )))
+            }
+        };
+        let size: usize = size
+            .parse()
+            .map_err(|_| ImportError::Git(format!("bad cat-file header: {}", header.trim())))?;
+        let mut data = vec![0; size + 1];
+        self.stdout.read_exact(&mut data)?;
+        data.pop();
+        Ok((kind, data))
+    }
+
+    fn read_kind(&mut self, id: &str, expected: &str) -> ImportResult<Vec<u8>> {
+        let (kind, data) = self.read(id)?;
+        if kind != expected {
+            return Err(malformed(
+                id,
+                format!("expected a {expected}, found a {kind}"),
+            ));
+        }
+        Ok(data)
+    }
+
+    pub fn commit(&mut self, id: &str) -> ImportResult<GitCommit> {
+        let data = self.read_kind(id, "commit")?;
+        parse_commit(id, &data)
+    }
+
+    pub fn tree(&mut self, id: &str) -> ImportResult<Vec<GitTreeEntry>> {
+        let data = self.read_kind(id, "tree")?;
+        parse_tree(id, &data, self.hash_len)
+    }
+
+    pub fn blob(&mut self, id: &str) -> ImportResult<Vec<u8>> {
+        self.read_kind(id, "blob")
+    }
+
+    /// Follow tag objects from `id` to a commit. Returns the commit and the
+    /// outermost annotated tag, or `None` if the chain ends at a tree or
+    /// blob.
+    pub fn peel(&mut self, id: &str) -> ImportResult<Option<(String, Option<GitTag>)>> {
+        let mut outer = None;
+        let mut current = id.to_string();
+        loop {
+            let (kind, data) = self.read(&current)?;
+            match kind.as_str() {
+                "commit" => return Ok(Some((current, outer))),
+                "tag" => {
+                    let tag = parse_tag(&current, &data)?;
+                    current = tag.object.clone();
+                    outer.get_or_insert(tag);
+                }
+                _ => return Ok(None),
+            }
+        }
+    }
+}
+
+impl Drop for CatFile {
+    fn drop(&mut self) {
+        // Closing stdin ends the batch; git exits on its own.
+        drop(self.stdin.take());
+        let _ = self.child.wait();
+    }
+}
+
+fn malformed(id: &str, reason: impl Into<String>) -> ImportError {
+    ImportError::Malformed {
+        id: id.to_string(),
+        reason: reason.into(),
+    }
+}
+
+/// Split a commit or tag object into its header lines and message.
+/// Continuation lines (e.g. inside `gpgsig`) are skipped.
+fn split_object(data: &[u8]) -> (Vec<(String, String)>, String) {
+    let text = String::from_utf8_lossy(data);
+    let (head, message) = text.split_once("\n\n").unwrap_or((&text, ""));
+    let headers = head
+        .lines()
+        .filter(|line| !line.starts_with(' '))
+        .filter_map(|line| line.split_once(' '))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+    (headers, message.to_string())
+}
+
+fn parse_ident(id: &str, value: &str) -> ImportResult<GitIdent> {
+    let open = value.find('<');
+    let close = value.rfind('>');
+    let (open, close) = match (open, close) {
+        (Some(open), Some(close)) if open < close => (open, close),
+        _ => return Err(malformed(id, format!("bad identity: {value}"))),
+    };
+    let mut when = value[close + 1..].split_whitespace();
+    let time = when.next().and_then(|t| t.parse().ok()).unwrap_or(0);
+    Ok(GitIdent {
+        name: value[..open].trim().to_string(),
+        email: value[open + 1..close].to_string(),
+        time,
+        offset: when.next().unwrap_or("+0000").to_string(),
+    })
+}
+
+pub(crate) fn parse_commit(id: &str, data: &[u8]) -> ImportResult<GitCommit> {
+    let (headers, message) = split_object(data);
+    let mut tree = None;
+    let mut parents = Vec::new();
+    let mut author = None;
+    let mut committer = None;
+    for (key, value) in headers {
+        match key.as_str() {
+            "tree" => tree = Some(value),
+            "parent" => parents.push(value),
+            "author" => author = Some(parse_ident(id, &value)?),
+            "committer" => committer = Some(parse_ident(id, &value)?),
+            _ => {}
+        }
+    }
+    let tree = tree.ok_or_else(|| malformed(id, "commit has no tree"))?;
+    let author = author.ok_or_else(|| malformed(id, "commit has no author"))?;
+    Ok(GitCommit {
+        tree,
+        parents,
+        committer: committer.unwrap_or_else(|| author.clone()),
+        author,
+        message,
+    })
+}
+
+pub(crate) fn parse_tag(id: &str, data: &[u8]) -> ImportResult<GitTag> {
+    let (headers, message) = split_object(data);
+    let mut object = None;
+    let mut tagger = None;
+    for (key, value) in headers {
+        match key.as_str() {
+            "object" => object = Some(value),
+            "tagger" => tagger = Some(parse_ident(id, &value)?),
+            _ => {}
+        }
+    }
+    Ok(GitTag {
+        object: object.ok_or_else(|| malformed(id, "tag has no object"))?,
+        tagger,
+        message,
+    })
+}
+
+/// Parse a binary tree object: `<octal mode> <name>\0<raw id>` repeated.
+pub(crate) fn parse_tree(
+    id: &str,
+    data: &[u8],
+    hash_len: usize,
+) -> ImportResult<Vec<GitTreeEntry>> {
+    let mut entries = Vec::new();
+    let mut rest = data;
+    while !rest.is_empty() {
+        let space = rest
+            .iter()
+            .position(|b| *b == b' ')
+            .ok_or_else(|| malformed(id, "tree entry has no mode"))?;
+        let nul = rest
+            .iter()
+            .position(|b| *b == 0)
+            .filter(|nul| *nul > space && nul + 1 + hash_len <= rest.len())
+            .ok_or_else(|| malformed(id, "truncated tree entry"))?;
+        let mode = std::str::from_utf8(&rest[..space])
+            .ok()
+            .and_then(|mode| u32::from_str_radix(mode, 8).ok())
+            .ok_or_else(|| malformed(id, "bad tree entry mode"))?;
+        let name = std::str::from_utf8(&rest[space + 1..nul])
+            .map_err(|_| malformed(id, "tree entry name is not UTF-8"))?;
+        entries.push(GitTreeEntry {
+            mode,
+            name: name.to_string(),
+            id: hex::encode(&rest[nul + 1..nul + 1 + hash_len]),
+        });
+        rest = &rest[nul + 1 + hash_len..];
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commit_headers_and_message() {
+        let data = b"tree 4b825dc642cb6eb9a060e54bf8d69288fbee4904\n\
+parent 1111111111111111111111111111111111111111\n\
+parent 2222222222222222222222222222222222222222\n\
+author Ada Lovelace <ada@example.com> 1700000000 +0100\n\
+committer Grace Hopper <grace@example.com> 1700000100 -0500\n\
+gpgsig -----BEGIN PGP SIGNATURE-----\n \n parent 3333\n -----END PGP SIGNATURE-----\n\
+\n\
+Merge feature\n\nDetails.\n";
+        let commit = parse_commit("c0ffee", data).unwrap();
+        assert_eq!(commit.tree, "4b825dc642cb6eb9a060e54bf8d69288fbee4904");
+        assert_eq!(commit.parents.len(), 2);
+        assert_eq!(commit.author.name, "Ada Lovelace");
+        assert_eq!(commit.author.email, "ada@example.com");
+        assert_eq!(
+            (commit.author.time, commit.author.offset.as_str()),
+            (1700000000, "+0100")
+        );
+        assert_eq!(commit.committer.email, "grace@example.com");
+        assert_eq!(commit.message, "Merge feature\n\nDetails.\n");
+
+        assert!(parse_commit("c0ffee", b"author A <a@b> 1 +0000\n\nno tree").is_err());
+        assert!(parse_commit("c0ffee", b"tree abc\nauthor nobody\n\nbad author").is_err());
+    }
+
+    #[test]
+    fn annotated_tag() {
+        let data = b"object 1111111111111111111111111111111111111111\ntype commit\ntag v1\n\
+tagger Ada <ada@example.com> 1700000000 +0000\n\nRelease 1\n";
+        let tag = parse_tag("7a9", data).unwrap();
+        assert_eq!(tag.object, "1111111111111111111111111111111111111111");
+        assert_eq!(tag.tagger.unwrap().email, "ada@example.com");
+        assert_eq!(tag.message, "Release 1\n");
+    }
+
+    #[test]
+    fn binary_tree_entries() {
+        let mut data = Vec::new();
+        data.extend_from_slice(b"100644 a.txt\0");
+        data.extend_from_slice(&[0xab; 20]);
+        data.extend_from_slice(b"40000 src\0");
+        data.extend_from_slice(&[0x01; 20]);
+        let entries = parse_tree("7ree", &data, 20).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].mode, 0o100644);
+        assert_eq!(entries[0].id, "ab".repeat(20));
+        assert_eq!(
+            (entries[1].mode, entries[1].name.as_str()),
+            (0o040000, "src")
+        );
+
+        assert!(parse_tree("7ree", &data[..data.len() - 1], 20).is_err());
+        assert!(parse_tree("7ree", b"10x644 a\0", 0).is_err());
+    }
+}
@@ -0,0 +1,572 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use serde::Serialize;
+use wll_refs::{Head, Ref};
+use wll_sdk::{CommitProposal, EntryMode, TreeEntry, Wll};
+use wll_types::{IdentityMaterial, ObjectId, TemporalAnchor, WorldlineId};
+
+use crate::error::{ImportError, ImportResult};
+use crate::git::{CatFile, GitCommit, GitIdent, GitRepo, GitTag};
+
+/// Branch that HEAD follows while commits are replayed. Each commit is
+/// made on top of its first parent by pointing this branch there first.
+const SCRATCH_BRANCH: &str = "wll-import";
+
+/// Mode git uses for submodule entries.
+const GITLINK_MODE: u32 = 0o160000;
+
+/// Which refs [`GitImporter`] brings over.
+#[derive(Clone, Debug)]
+pub struct ImportOptions {
+    /// Branch names to import; empty means every branch.
+    pub branches: Vec<String>,
+    /// Import tags. With a branch filter, only tags pointing into the
+    /// selected branches' history are imported.
+    pub tags: bool,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            branches: Vec::new(),
+            tags: true,
+        }
+    }
+}
+
+impl ImportOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_branches(mut self, branches: Vec<String>) -> Self {
+        self.branches = branches;
+        self
+    }
+
+    pub fn with_tags(mut self, tags: bool) -> Self {
+        self.tags = tags;
+        self
+    }
+}
+
+/// What an import wrote.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ImportReport {
+    pub commits: usize,
+    pub trees: usize,
+    pub blobs: usize,
+    /// Submodule entries, which have no content to import.
+    pub skipped_submodules: usize,
+    pub branches: Vec<String>,
+    pub tags: Vec<String>,
+    /// Worldline derived for each author email.
+    pub authors: BTreeMap<String, WorldlineId>,
+    /// Branch HEAD was left on.
+    pub head: Option<String>,
+}
+
+/// Identity recorded for a git author: a worldline derived from the
+/// repository's own, labelled with the lower-cased email.
+pub fn author_worldline(repo: &WorldlineId, email: &str) -> WorldlineId {
+    WorldlineId::derive(&IdentityMaterial::Derived {
+        parent: *repo.as_bytes(),
+        label: format!("git-author:{}", email.to_lowercase()),
+    })
+}
+
+/// Receipt and committer time for an imported commit.
+struct Imported {
+    receipt_hash: [u8; 32],
+    time: i64,
+}
+
+/// Converts a git repository's history into receipts in a [`Wll`].
+///
+/// Commits are replayed parents first. Each becomes a commitment and
+/// outcome pair whose intent is the commit message and whose outcome
+/// records the imported tree, with the git commit id, parents, author and
+/// author worldline kept as `git.*` outcome metadata. Branches and tags
+/// then point at the outcome receipts of their commits.
+pub struct GitImporter<'a> {
+    wll: &'a Wll,
+    git: GitRepo,
+    options: ImportOptions,
+}
+
+impl<'a> GitImporter<'a> {
+    /// Prepare to import the git repository at `path` into `wll`.
+    pub fn new(wll: &'a Wll, path: impl AsRef<Path>) -> ImportResult<Self> {
+        Ok(Self {
+            wll,
+            git: GitRepo::open(path.as_ref())?,
+            options: ImportOptions::default(),
+        })
+    }
+
+    pub fn with_options(mut self, options: ImportOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Run the import.
+    ///
+    /// Refuses to start if a branch or tag to be imported already exists in
+    /// the target repository. Receipts appended before a later failure stay
+    /// in the ledger, but no branch or tag points at them.
+    pub fn run(self) -> ImportResult<ImportReport> {
+        let mut branches = Vec::new();
+        let mut tags = Vec::new();
+        for (name, id) in self.git.refs()? {
+            if let Some(branch) = name.strip_prefix("refs/heads/") {
+                branches.push((branch.to_string(), id));
+            } else if let Some(tag) = name.strip_prefix("refs/tags/") {
+                tags.push((tag.to_string(), id));
+            }
+        }
+        if !self.options.branches.is_empty() {
+            for wanted in &self.options.branches {
+                if !branches.iter().any(|(name, _)| name == wanted) {
+                    return Err(ImportError::BranchNotFound(wanted.clone()));
+                }
+            }
+            branches.retain(|(name, _)| self.options.branches.contains(name));
+        }
+
+        let mut objects = self.git.objects()?;
+        let mut peeled_tags = Vec::new();
+        if self.options.tags {
+            for (name, id) in tags {
+                match objects.peel(&id)? {
+                    Some((commit, tag)) => peeled_tags.push((name, commit, tag)),
+                    None => {
+                        tracing::warn!(tag = %name, "skipping tag that does not point at a commit")
+                    }
+                }
+            }
+        }
+
+        for (name, _) in &branches {
+            self.ensure_absent(&format!("refs/heads/{name}"))?;
+        }
+        for (name, _, _) in &peeled_tags {
+            self.ensure_absent(&format!("refs/tags/{name}"))?;
+        }
+
+        // With a branch filter, tags only come along if they point into the
+        // selected history; otherwise their commits are imported too.
+        let mut tips: Vec<String> = branches.iter().map(|(_, id)| id.clone()).collect();
+        if self.options.branches.is_empty() {
+            tips.extend(peeled_tags.iter().map(|(_, commit, _)| commit.clone()));
+        }
+        tips.sort();
+        tips.dedup();
+        let order = self.git.rev_list(&tips)?;
+
+        let original_head = self.wll.refs().head()?;
+        let mut report = ImportReport::default();
+        let replayed = self.replay(&mut objects, &order, &mut report);
+        self.restore_head(original_head)?;
+        self.wll
+            .refs()
+            .delete_ref(&format!("refs/heads/{SCRATCH_BRANCH}"))?;
+        let imported = replayed?;
+
+        for (name, id) in &branches {
+            let reference = Ref::Branch {
+                name: name.clone(),
+                worldline: self.wll.worldline().clone(),
+                receipt_hash: imported[id].receipt_hash,
+            };
+            self.wll
+                .refs()
+                .write_ref(&format!("refs/heads/{name}"), &reference)?;
+            report.branches.push(name.clone());
+        }
+        for (name, commit, tag) in peeled_tags {
+            let Some(target) = imported.get(&commit) else {
+                continue;
+            };
+            let (tagger, message, time) = match &tag {
+                Some(GitTag {
+                    tagger: Some(tagger),
+                    message,
+                    ..
+                }) => (
+                    self.author(tagger, &mut report),
+                    message.clone(),
+                    tagger.time,
+                ),
+                Some(tag) => (
+                    self.wll.worldline().clone(),
+                    tag.message.clone(),
+                    target.time,
+                ),
+                None => (self.wll.worldline().clone(), String::new(), target.time),
+            };
+            let reference = Ref::Tag {
+                name: name.clone(),
+                target: target.receipt_hash,
+                tagger,
+                message: message.trim_end().to_string(),
+                timestamp: TemporalAnchor::new(time.max(0) as u64 * 1000, 0, 0),
+                signature: None,
+            };
+            self.wll
+                .refs()
+                .write_ref(&format!("refs/tags/{name}"), &reference)?;
+            report.tags.push(name);
+        }
+
+        let head = self
+            .git
+            .head_branch()
+            .filter(|head| report.branches.contains(head))
+            .or_else(|| report.branches.first().cloned());
+        if let Some(branch) = &head {
+            self.wll.refs().set_head(branch)?;
+        }
+        report.head = head;
+        Ok(report)
+    }
+
+    fn ensure_absent(&self, ref_name: &str) -> ImportResult<()> {
+        match self.wll.refs().read_ref(ref_name)? {
+            Some(existing) if *existing.target_hash() != [0; 32] => {
+                Err(ImportError::RefExists(ref_name.to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn restore_head(&self, head: Option<Head>) -> ImportResult<()> {
+        match head {
+            Some(Head::Symbolic(branch)) => self.wll.refs().set_head(&branch)?,
+            Some(Head::Detached(hash)) => self.wll.refs().set_head_detached(hash)?,
+            None => self.wll.refs().set_head("main")?,
+        }
+        Ok(())
+    }
+
+    /// Derive and remember the worldline for `ident`.
+    fn author(&self, ident: &GitIdent, report: &mut ImportReport) -> WorldlineId {
+        report
+            .authors
+            .entry(ident.email.clone())
+            .or_insert_with(|| author_worldline(self.wll.worldline(), &ident.email))
+            .clone()
+    }
+
+    fn replay(
+        &self,
+        objects: &mut CatFile,
+        order: &[String],
+        report: &mut ImportReport,
+    ) -> ImportResult<HashMap<String, Imported>> {
+        let mut imported = HashMap::with_capacity(order.len());
+        let mut trees = HashMap::new();
+        let mut blobs = HashMap::new();
+        let scratch_ref = format!("refs/heads/{SCRATCH_BRANCH}");
+        for id in order {
+            let commit = objects.commit(id)?;
+            let tree = self.import_tree(objects, &commit.tree, &mut trees, &mut blobs, report)?;
+
+            // A parent outside the imported history (a shallow clone's
+            // boundary) makes this commit a root.
+            let parent = commit
+                .parents
+                .first()
+                .and_then(|parent| imported.get(parent))
+                .map_or([0; 32], |parent: &Imported| parent.receipt_hash);
+            let scratch = Ref::Branch {
+                name: SCRATCH_BRANCH.into(),
+                worldline: self.wll.worldline().clone(),
+                receipt_hash: parent,
+            };
+            self.wll.refs().write_ref(&scratch_ref, &scratch)?;
+            self.wll.refs().set_head(SCRATCH_BRANCH)?;
+
+            let result = self.wll.commit(self.proposal(id, &commit, tree, report))?;
+            imported.insert(
+                id.clone(),
+                Imported {
+                    receipt_hash: result.receipt_hash,
+                    time: commit.committer.time,
+                },
+            );
+            report.commits += 1;
+        }
+        Ok(imported)
+    }
+
+    fn proposal(
+        &self,
+        id: &str,
+        commit: &GitCommit,
+        tree: ObjectId,
+        report: &mut ImportReport,
+    ) -> CommitProposal {
+        let message = commit.message.trim_end();
+        let message = if message.trim().is_empty() {
+            format!("git commit {id}")
+        } else {
+            message.to_string()
+        };
+        let author = &commit.author;
+        let mut proposal = CommitProposal::new(message)
+            .with_tree(tree)
+            .with_metadata("git.commit", id)
+            .with_metadata("git.author", format!("{} <{}>", author.name, author.email))
+            .with_metadata(
+                "git.author_time",
+                format!("{} {}", author.time, author.offset),
+            )
+            .with_metadata("git.author_worldline", self.author(author, report).to_hex());
+        if !commit.parents.is_empty() {
+            proposal = proposal.with_metadata("git.parents", commit.parents.join(" "));
+        }
+        proposal
+    }
+
+    /// Copy git tree `id` and everything under it into the object store.
+    fn import_tree(
+        &self,
+        objects: &mut CatFile,
+        id: &str,
+        trees: &mut HashMap<String, ObjectId>,
+        blobs: &mut HashMap<String, ObjectId>,
+        report: &mut ImportReport,
+    ) -> ImportResult<ObjectId> {
+        if let Some(tree) = trees.get(id) {
+            return Ok(*tree);
+        }
+        let mut entries = Vec::new();
+        for entry in objects.tree(id)? {
+            let (mode, object_id) = match entry.mode {
+                GITLINK_MODE => {
+                    report.skipped_submodules += 1;
+                    continue;
+                }
+                0o040000 => (
+                    EntryMode::Directory,
+                    self.import_tree(objects, &entry.id, trees, blobs, report)?,
+                ),
+                mode => {
+                    let mode = match mode {
+                        0o100755 => EntryMode::Executable,
+                        0o120000 => EntryMode::Symlink,
+                        // Old git versions also wrote 100664 and 100000.
+                        0o100644 | 0o100664 | 0o100000 => EntryMode::Regular,
+                        other => {
+                            return Err(ImportError::Malformed {
+                                id: id.to_string(),
+                                reason: format!("unknown mode {other:o} for {}", entry.name),
+                            })
+                        }
+                    };
+                    let blob = match blobs.get(&entry.id) {
+                        Some(blob) => *blob,
+                        None => {
+                            let blob = self.wll.write_blob(&objects.blob(&entry.id)?)?;
+                            blobs.insert(entry.id.clone(), blob);
+                            report.blobs += 1;
+                            blob
+                        }
+                    };
+                    (mode, blob)
+                }
+            };
+            entries.push(TreeEntry {
+                mode,
+                name: entry.name,
+                object_id,
+            });
+        }
+        let tree = self.wll.write_tree(entries)?;
+        trees.insert(id.to_string(), tree);
+        report.trees += 1;
+        Ok(tree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use super::*;
+    use wll_sdk::Receipt;
+
+    /// Run git in `dir` with a fixed identity and no user or system config.
+    fn git(dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=Ada", "-c", "user.email=ada@example.com"])
+            .args(["-c", "commit.gpgsign=false", "-c", "tag.gpgsign=false"])
+            .args(args)
+            .env("GIT_CONFIG_GLOBAL", "/dev/null")
+            .env("GIT_CONFIG_NOSYSTEM", "1")
+            .output()
+            .expect("git is installed");
+        assert!(output.status.success(), "git {args:?}: {output:?}");
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    }
+
+    fn write(dir: &Path, path: &str, content: &str) {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    /// main: c1 - c2 ------- merge   (tag v1 at c2, annotated v2 at merge)
+    ///              \\       /
+    /// feature:      f1 ----
+    fn sample_repo(dir: &Path) {
+        git(dir, &["init", "-q"]);
+        git(dir, &["symbolic-ref", "HEAD", "refs/heads/main"]);
+        write(dir, "README.md", "hello\n");
+        git(dir, &["add", "."]);
+        git(dir, &["commit", "-q", "-m", "Initial commit"]);
+        write(dir, "src/lib.rs", "pub fn f() {}\n");
+        git(dir, &["add", "."]);
+        git(dir, &["commit", "-q", "-m", "Add lib"]);
+        git(dir, &["tag", "v1"]);
+        git(dir, &["checkout", "-q", "-b", "feature"]);
+        write(dir, "src/feature.rs", "pub fn g() {}\n");
+        git(dir, &["add", "."]);
+        git(
+            dir,
+            &[
+                "commit",
+                "-q",
+                "-m",
+                "Add feature",
+                "--author",
+                "Grace <Grace@Example.com>",
+            ],
+        );
+        git(dir, &["checkout", "-q", "main"]);
+        git(
+            dir,
+            &["merge", "-q", "--no-ff", "-m", "Merge feature", "feature"],
+        );
+        git(dir, &["tag", "-a", "v2", "-m", "Second release"]);
+    }
+
+    fn worldline() -> WorldlineId {
+        WorldlineId::derive(&IdentityMaterial::GenesisHash([6; 32]))
+    }
+
+    fn outcome_metadata(wll: &Wll, receipt_hash: [u8; 32]) -> BTreeMap<String, String> {
+        match wll.ledger().get_by_hash(receipt_hash).unwrap() {
+            Some(Receipt::Outcome(outcome)) => outcome.metadata,
+            other => panic!("expected an outcome, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn imports_commits_branches_tags_and_authors() {
+        let dir = tempfile::tempdir().unwrap();
+        sample_repo(dir.path());
+        let wll = Wll::init_with_worldline(worldline()).unwrap();
+
+        let report = GitImporter::new(&wll, dir.path()).unwrap().run().unwrap();
+        assert_eq!(report.commits, 4);
+        assert_eq!(report.blobs, 3);
+        assert_eq!(report.branches, vec!["feature", "main"]);
+        assert_eq!(report.tags, vec!["v1", "v2"]);
+        assert_eq!(report.head.as_deref(), Some("main"));
+        assert_eq!(wll.receipt_count().unwrap(), 8);
+        assert!(wll.verify().unwrap().is_valid());
+        assert!(wll
+            .refs()
+            .read_ref("refs/heads/wll-import")
+            .unwrap()
+            .is_none());
+
+        let grace = author_worldline(&worldline(), "grace@example.com");
+        assert_eq!(report.authors["Grace@Example.com"], grace);
+        assert_eq!(report.authors.len(), 2);
+
+        let main = *wll
+            .refs()
+            .read_ref("refs/heads/main")
+            .unwrap()
+            .unwrap()
+            .target_hash();
+        let metadata = outcome_metadata(&wll, main);
+        assert_eq!(
+            metadata["git.commit"],
+            git(dir.path(), &["rev-parse", "main"])
+        );
+        assert_eq!(metadata["git.parents"].split(' ').count(), 2);
+        assert_eq!(metadata["git.author"], "Ada <ada@example.com>");
+
+        let feature = *wll
+            .refs()
+            .read_ref("refs/heads/feature")
+            .unwrap()
+            .unwrap()
+            .target_hash();
+        assert_eq!(
+            outcome_metadata(&wll, feature)["git.author_worldline"],
+            grace.to_hex()
+        );
+
+        let tree = wll.read_tree(&wll.head_tree().unwrap().unwrap()).unwrap();
+        let names: Vec<_> = tree.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["README.md", "src"]);
+        let src = wll.read_tree(&tree.entries[1].object_id).unwrap();
+        assert_eq!(src.entries.len(), 2);
+
+        match wll.refs().read_ref("refs/tags/v2").unwrap().unwrap() {
+            Ref::Tag {
+                target, message, ..
+            } => {
+                assert_eq!(target, main);
+                assert_eq!(message, "Second release");
+            }
+            other => panic!("expected a tag, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn branch_filter_limits_history_and_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        sample_repo(dir.path());
+        git(dir.path(), &["branch", "-q", "old", "HEAD~1"]);
+        let wll = Wll::init_with_worldline(worldline()).unwrap();
+
+        let options = ImportOptions::new().with_branches(vec!["old".into()]);
+        let report = GitImporter::new(&wll, dir.path())
+            .unwrap()
+            .with_options(options)
+            .run()
+            .unwrap();
+        assert_eq!(report.commits, 2);
+        assert_eq!(report.tags, vec!["v1"]);
+        assert_eq!(report.head.as_deref(), Some("old"));
+
+        let again = GitImporter::new(&wll, dir.path())
+            .unwrap()
+            .with_options(ImportOptions::new().with_branches(vec!["old".into()]))
+            .run();
+        assert!(matches!(again, Err(ImportError::RefExists(_))));
+        let missing = GitImporter::new(&wll, dir.path())
+            .unwrap()
+            .with_options(ImportOptions::new().with_branches(vec!["nope".into()]))
+            .run();
+        assert!(matches!(missing, Err(ImportError::BranchNotFound(_))));
+    }
+
+    #[test]
+    fn rejects_a_directory_that_is_not_a_git_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        let wll = Wll::init_with_worldline(worldline()).unwrap();
+        assert!(matches!(
+            GitImporter::new(&wll, dir.path()),
+            Err(ImportError::Git(_))
+        ));
+    }
+}
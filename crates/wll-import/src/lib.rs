@@ -0,0 +1,17 @@
+//! Import existing git repositories into the WorldLine Ledger.
+//!
+//! Each git commit becomes a commitment and outcome receipt pair: the commit
+//! message is the intent and the commit's tree is copied into the object
+//! store and recorded by the outcome. Branches and tags become refs, and
+//! every author email maps to a worldline derived from the repository's own
+//! (see [`author_worldline`]).
+//!
+//! The git repository is read with the `git` command-line tool, which must
+//! be on `PATH`.
+
+pub mod error;
+mod git;
+pub mod import;
+
+pub use error::{ImportError, ImportResult};
+pub use import::{author_worldline, GitImporter, ImportOptions, ImportReport};
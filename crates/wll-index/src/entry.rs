@@ -21,6 +21,9 @@ pub struct IndexEntry {
     pub mtime: SystemTime,
     /// Status flags for this entry.
     pub flags: IndexFlags,
+    /// The versions being merged, while the entry is conflicted.
+    #[serde(default)]
+    pub stages: Option<ConflictStages>,
 }
 
 impl IndexEntry {
@@ -38,6 +41,7 @@ impl IndexEntry {
             size,
             mtime: SystemTime::now(),
             flags: IndexFlags::default(),
+            stages: None,
         }
     }
 
@@ -55,7 +59,7 @@ impl IndexEntry {
 }
 
 /// Status flags for an index entry.
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct IndexFlags {
     /// Whether the file is staged for the next commitment.
     pub staged: bool,
@@ -67,13 +71,41 @@ pub struct IndexFlags {
     pub conflict: bool,
 }
 
-impl Default for IndexFlags {
-    fn default() -> Self {
-        Self {
-            staged: false,
-            modified: false,
-            deleted: false,
-            conflict: false,
+/// One version of a conflicted path, numbered as Git numbers index stages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictStage {
+    /// The common ancestor (stage 1).
+    Base = 1,
+    /// The version on the branch being merged into (stage 2).
+    Ours = 2,
+    /// The version being merged in (stage 3).
+    Theirs = 3,
+}
+
+/// Blob ids of the three versions of a conflicted path.
+///
+/// A stage is `None` where that version has no such path: there is no base
+/// when both sides added the path, and no ours or theirs when that side
+/// deleted it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConflictStages {
+    pub base: Option<ObjectId>,
+    pub ours: Option<ObjectId>,
+    pub theirs: Option<ObjectId>,
+}
+
+impl ConflictStages {
+    pub fn new(base: Option<ObjectId>, ours: Option<ObjectId>, theirs: Option<ObjectId>) -> Self {
+        Self { base, ours, theirs }
+    }
+
+    /// The blob at `stage`.
+    pub fn get(&self, stage: ConflictStage) -> Option<ObjectId> {
+        match stage {
+            ConflictStage::Base => self.base,
+            ConflictStage::Ours => self.ours,
+            ConflictStage::Theirs => self.theirs,
         }
     }
 }
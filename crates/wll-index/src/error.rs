@@ -1,6 +1,6 @@
 //! Error types for the index crate.
 
-use wll_types::ObjectId;
+use wll_types::{Coded, ErrorCode, ObjectId};
 
 /// Errors that can occur during index operations.
 #[derive(Debug, thiserror::Error)]
@@ -34,5 +34,18 @@ pub enum IndexError {
     InvalidPath(String),
 }
 
+impl Coded for IndexError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::PathNotFound(_) => ErrorCode::IndexPathNotFound,
+            Self::AlreadyStaged(_) | Self::UnresolvedConflict(_) => ErrorCode::IndexConflict,
+            Self::ObjectNotFound(_) => ErrorCode::ObjectNotFound,
+            Self::Store(e) => e.code(),
+            Self::Serialization(_) => ErrorCode::Serialization,
+            Self::InvalidPath(_) => ErrorCode::InvalidPath,
+        }
+    }
+}
+
 /// Convenience alias for index results.
 pub type IndexResult<T> = Result<T, IndexError>;
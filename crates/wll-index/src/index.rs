@@ -6,14 +6,14 @@
 
 use std::collections::BTreeMap;
 use std::sync::Arc;
-use std::time::SystemTime;
 
+use wll_diff::{merge_blobs, BlobMerge, MergeLabels};
 use wll_store::{
     Blob, EntryMode, ObjectStore, Tree, TreeEntry,
 };
-use wll_types::ObjectId;
+use wll_types::{Clock, ObjectId, SystemClock};
 
-use crate::entry::{IndexEntry, IndexFlags};
+use crate::entry::{ConflictStage, ConflictStages, IndexEntry, IndexFlags};
 use crate::error::{IndexError, IndexResult};
 use crate::status::{FileStatus, StatusEntry, WorkdirStatus};
 
@@ -31,6 +31,8 @@ pub struct Index {
     pub tree_cache: Option<ObjectId>,
     /// The object store for reading/writing blobs and trees.
     store: Arc<dyn ObjectStore>,
+    /// Source of entry modification times.
+    clock: Arc<dyn Clock>,
 }
 
 impl std::fmt::Debug for Index {
@@ -51,9 +53,17 @@ impl Index {
             entries: BTreeMap::new(),
             tree_cache: None,
             store,
+            clock: Arc::new(SystemClock),
         }
     }
 
+    /// Stamp entry modification times from `clock` instead of the system
+    /// clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
     /// Number of entries in the index.
     pub fn len(&self) -> usize {
         self.entries.len()
@@ -97,13 +107,14 @@ impl Index {
             object_id,
             mode,
             size: content.len() as u64,
-            mtime: SystemTime::now(),
+            mtime: self.clock.system_time(),
             flags: IndexFlags {
                 staged: true,
                 modified: false,
                 deleted: false,
                 conflict: false,
             },
+            stages: None,
         };
 
         self.entries.insert(path.to_string(), entry);
@@ -131,13 +142,14 @@ impl Index {
             object_id,
             mode,
             size,
-            mtime: SystemTime::now(),
+            mtime: self.clock.system_time(),
             flags: IndexFlags {
                 staged: true,
                 modified: false,
                 deleted: false,
                 conflict: false,
             },
+            stages: None,
         };
 
         self.entries.insert(path.to_string(), entry);
@@ -203,6 +215,79 @@ impl Index {
         Ok(())
     }
 
+    /// Record a conflict at `path` between the given versions.
+    ///
+    /// The entry points at our version (or, failing that, theirs, then the
+    /// base) until the conflict is resolved.
+    pub fn add_conflict(
+        &mut self,
+        path: &str,
+        stages: ConflictStages,
+        mode: EntryMode,
+    ) -> IndexResult<()> {
+        if path.is_empty() {
+            return Err(IndexError::InvalidPath("empty path".to_string()));
+        }
+        let object_id = stages
+            .ours
+            .or(stages.theirs)
+            .or(stages.base)
+            .ok_or_else(|| IndexError::InvalidPath(format!("no version of {path} to conflict")))?;
+
+        let mut entry = IndexEntry::new(path, object_id, mode, 0);
+        entry.mtime = self.clock.system_time();
+        entry.flags.conflict = true;
+        entry.stages = Some(stages);
+        self.entries.insert(path.to_string(), entry);
+        self.tree_cache = None;
+        Ok(())
+    }
+
+    /// The versions recorded for a conflicted path, if it has any.
+    pub fn conflict_stages(&self, path: &str) -> Option<&ConflictStages> {
+        self.entries.get(path).and_then(|e| e.stages.as_ref())
+    }
+
+    /// Content of one version of a conflicted path, or `None` where that
+    /// version has no such path.
+    pub fn stage_content(&self, path: &str, stage: ConflictStage) -> IndexResult<Option<Vec<u8>>> {
+        let stages = self.staged_conflict(path)?;
+        let Some(object_id) = stages.get(stage) else {
+            return Ok(None);
+        };
+        let stored = self
+            .store
+            .read_shared(&object_id)?
+            .ok_or(IndexError::ObjectNotFound(object_id))?;
+        Ok(Some(Blob::from_stored_object(&stored)?.data))
+    }
+
+    /// Merge the versions of a conflicted path line by line, with conflict
+    /// markers where they disagree.
+    ///
+    /// A missing version merges as empty. Returns `None` when a version is
+    /// binary.
+    pub fn conflict_markers(
+        &self,
+        path: &str,
+        labels: &MergeLabels,
+    ) -> IndexResult<Option<BlobMerge>> {
+        let content = |stage| -> IndexResult<Vec<u8>> {
+            Ok(self.stage_content(path, stage)?.unwrap_or_default())
+        };
+        let base = content(ConflictStage::Base)?;
+        let ours = content(ConflictStage::Ours)?;
+        let theirs = content(ConflictStage::Theirs)?;
+        Ok(merge_blobs(&base, &ours, &theirs, labels))
+    }
+
+    fn staged_conflict(&self, path: &str) -> IndexResult<ConflictStages> {
+        let entry = self.get(path).ok_or_else(|| IndexError::PathNotFound(path.to_string()))?;
+        entry
+            .stages
+            .ok_or_else(|| IndexError::PathNotFound(format!("no conflict stages at path: {path}")))
+    }
+
     /// Resolve a conflict on a file by replacing with the given content.
     pub fn resolve_conflict(
         &mut self,
@@ -224,8 +309,9 @@ impl Index {
         entry.object_id = object_id;
         entry.size = size;
         entry.flags.conflict = false;
+        entry.stages = None;
         entry.flags.staged = true;
-        entry.mtime = SystemTime::now();
+        entry.mtime = self.clock.system_time();
         self.tree_cache = None;
         Ok(())
     }
@@ -326,8 +412,8 @@ impl Index {
     pub fn read_tree(&mut self, tree_id: &ObjectId) -> IndexResult<()> {
         let stored = self
             .store
-            .read(tree_id)?
-            .ok_or_else(|| IndexError::ObjectNotFound(*tree_id))?;
+            .read_shared(tree_id)?
+            .ok_or(IndexError::ObjectNotFound(*tree_id))?;
 
         let tree = Tree::from_stored_object(&stored)
             .map_err(|e| IndexError::Serialization(e.to_string()))?;
@@ -339,13 +425,14 @@ impl Index {
                 object_id: te.object_id,
                 mode: te.mode,
                 size: 0, // size not stored in tree; would need blob lookup
-                mtime: SystemTime::now(),
+                mtime: self.clock.system_time(),
                 flags: IndexFlags {
                     staged: false,
                     modified: false,
                     deleted: false,
                     conflict: false,
                 },
+                stages: None,
             };
             self.entries.insert(te.name.clone(), entry);
         }
@@ -389,6 +476,18 @@ mod tests {
         assert_eq!(entry.mode, EntryMode::Regular);
     }
 
+    #[test]
+    fn mtime_comes_from_clock() {
+        let clock = Arc::new(wll_types::ManualClock::new(42_000));
+        let mut idx = make_index().with_clock(clock);
+        idx.stage_file("a.txt", b"a", EntryMode::Regular).unwrap();
+
+        assert_eq!(
+            idx.get("a.txt").unwrap().mtime,
+            std::time::UNIX_EPOCH + std::time::Duration::from_secs(42)
+        );
+    }
+
     #[test]
     fn stage_file_rejects_empty_path() {
         let mut idx = make_index();
@@ -486,6 +585,28 @@ mod tests {
         assert_eq!(entry.object_id, new_id);
     }
 
+    #[test]
+    fn conflict_stages_give_each_version_and_markers() {
+        let mut idx = make_index();
+        let blob = |content: &[u8]| {
+            Some(idx.store.write(&Blob::new(content.to_vec()).to_stored_object()).unwrap())
+        };
+        let stages =
+            ConflictStages::new(blob(b"a\nb\n"), blob(b"a\nours\n"), blob(b"a\ntheirs\n"));
+        idx.add_conflict("c.txt", stages, EntryMode::Regular).unwrap();
+
+        assert!(idx.has_conflicts());
+        assert_eq!(idx.get("c.txt").unwrap().object_id, stages.ours.unwrap());
+        let theirs = idx.stage_content("c.txt", ConflictStage::Theirs).unwrap();
+        assert_eq!(theirs.as_deref(), Some(&b"a\ntheirs\n"[..]));
+        let merged = idx.conflict_markers("c.txt", &MergeLabels::default()).unwrap().unwrap();
+        assert_eq!(merged.text, "a\n<<<<<<< ours\nours\n=======\ntheirs\n>>>>>>> theirs\n");
+
+        idx.resolve_conflict("c.txt", stages.theirs.unwrap(), 9).unwrap();
+        assert!(idx.conflict_stages("c.txt").is_none());
+        assert!(idx.stage_content("c.txt", ConflictStage::Base).is_err());
+    }
+
     #[test]
     fn status_staged_entries() {
         let mut idx = make_index();
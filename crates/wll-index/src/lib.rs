@@ -9,6 +9,7 @@
 //! - [`Index`] -- The in-memory staging area (BTreeMap-backed)
 //! - [`IndexEntry`] -- A tracked file entry with flags
 //! - [`IndexFlags`] -- Staged/modified/deleted/conflict flags
+//! - [`ConflictStages`] -- Base/ours/theirs versions of a conflicted path
 //! - [`WorkdirStatus`] -- Result of status computation
 //! - [`FileStatus`] -- Kind of change (New, Modified, Deleted, etc.)
 
@@ -17,7 +18,7 @@ pub mod error;
 pub mod index;
 pub mod status;
 
-pub use entry::{IndexEntry, IndexFlags};
+pub use entry::{ConflictStage, ConflictStages, IndexEntry, IndexFlags};
 pub use error::{IndexError, IndexResult};
 pub use index::Index;
 pub use status::{FileStatus, StatusEntry, WorkdirStatus};
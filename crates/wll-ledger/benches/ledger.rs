@@ -0,0 +1,119 @@
+//! Receipt append and hashing, and stream validation.
+
+use std::collections::BTreeMap;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use wll_crypto::HashAlgo;
+use wll_ledger::{
+    canonical_receipt_hash_with, CommitmentProposal, Decision, EffectSummary, InMemoryLedger,
+    LedgerReader, LedgerWriter, OutcomeRecord, Receipt, StateUpdate, StreamValidator,
+};
+use wll_types::{CommitmentClass, CommitmentId, EvidenceBundle, IdentityMaterial, WorldlineId};
+
+fn worldline() -> WorldlineId {
+    WorldlineId::derive(&IdentityMaterial::GenesisHash([7; 32]))
+}
+
+fn proposal(worldline: &WorldlineId, nonce: u64) -> CommitmentProposal {
+    CommitmentProposal {
+        worldline: worldline.clone(),
+        commitment_id: CommitmentId::new(),
+        class: CommitmentClass::ContentUpdate,
+        intent: "benchmark commitment".into(),
+        requested_caps: vec!["cap-write".into()],
+        targets: vec![worldline.clone()],
+        evidence: EvidenceBundle::from_references(vec!["obj://evidence".into()]),
+        nonce,
+    }
+}
+
+fn outcome(value: u64) -> OutcomeRecord {
+    OutcomeRecord {
+        effects: vec![EffectSummary::new("write", "src/lib.rs", "update")],
+        proofs: vec![],
+        state_updates: vec![StateUpdate {
+            key: "counter".into(),
+            value: value.into(),
+            namespace: None,
+        }],
+        metadata: BTreeMap::new(),
+    }
+}
+
+/// A ledger holding `pairs` accepted commitment/outcome pairs.
+fn ledger_with(pairs: u64) -> (InMemoryLedger, WorldlineId) {
+    let ledger = InMemoryLedger::default();
+    let wid = worldline();
+    for nonce in 0..pairs {
+        let commitment = ledger
+            .append_commitment(&proposal(&wid, nonce), &Decision::Accepted, [1; 32])
+            .unwrap();
+        ledger
+            .append_outcome(commitment.receipt_hash, &outcome(nonce))
+            .unwrap();
+    }
+    (ledger, wid)
+}
+
+fn append(c: &mut Criterion) {
+    let mut group = c.benchmark_group("receipt");
+    group.throughput(Throughput::Elements(2));
+    group.bench_function("append_commitment_outcome", |b| {
+        let (ledger, wid) = ledger_with(0);
+        let mut nonce = 0;
+        b.iter_batched(
+            || {
+                nonce += 1;
+                (proposal(&wid, nonce), outcome(nonce))
+            },
+            |(proposal, outcome)| {
+                let commitment = ledger
+                    .append_commitment(&proposal, &Decision::Accepted, [1; 32])
+                    .unwrap();
+                ledger
+                    .append_outcome(commitment.receipt_hash, &outcome)
+                    .unwrap()
+            },
+            BatchSize::SmallInput,
+        );
+    });
+    group.finish();
+
+    let (ledger, wid) = ledger_with(1);
+    let receipts = ledger.read_all(&wid).unwrap();
+    let mut group = c.benchmark_group("receipt_hash");
+    for (name, receipt) in [("commitment", &receipts[0]), ("outcome", &receipts[1])] {
+        for algo in [HashAlgo::Blake3, HashAlgo::Sha256] {
+            group.bench_with_input(
+                BenchmarkId::new(name, algo.name()),
+                receipt,
+                |b, receipt: &Receipt| b.iter(|| canonical_receipt_hash_with(algo, receipt)),
+            );
+        }
+    }
+    group.finish();
+}
+
+fn validate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("validate_stream");
+    for pairs in [500, 5_000] {
+        let (ledger, wid) = ledger_with(pairs);
+        let receipts = ledger.read_all(&wid).unwrap();
+        group.throughput(Throughput::Elements(receipts.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(receipts.len()),
+            &receipts,
+            |b, r| {
+                b.iter(|| {
+                    let report = StreamValidator::validate_receipts(&wid, r);
+                    assert!(report.is_valid());
+                    report
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, append, validate);
+criterion_main!(benches);
@@ -0,0 +1,93 @@
+//! Archival of a worldline's oldest receipts.
+//!
+//! A long-lived stream can move its oldest receipts out of the live ledger
+//! ([`LedgerWriter::archive`](crate::LedgerWriter::archive)). The live
+//! stream then continues from the last archived receipt
+//! ([`LedgerReader::archived_through`](crate::LedgerReader::archived_through)),
+//! so sequence numbers and hash links are unchanged and validation resumes
+//! from that link.
+//!
+//! Not every receipt can be archived through. Outcomes stay with their
+//! commitment and snapshots with their anchor, and a snapshot of the whole
+//! state (not of one namespace) must remain after the cut: replaying the
+//! live stream reaches it and from there derives the same state as
+//! replaying from genesis.
+
+use std::collections::HashMap;
+
+use crate::error::LedgerError;
+use crate::records::Receipt;
+
+/// Check that the live stream `live` can be archived through `through_seq`.
+pub fn check_archive_point(live: &[Receipt], through_seq: u64) -> Result<(), LedgerError> {
+    let refuse = |reason: String| LedgerError::ArchiveBoundary {
+        seq: through_seq,
+        reason,
+    };
+    let (Some(first), Some(last)) = (live.first(), live.last()) else {
+        return Err(refuse("the stream has no live receipts".into()));
+    };
+    if through_seq < first.seq() {
+        return Err(refuse(format!("receipts before seq {} are already archived", first.seq())));
+    }
+    if through_seq >= last.seq() {
+        return Err(refuse("the head receipt must stay live".into()));
+    }
+
+    let seqs = seqs_by_hash(live);
+    let mut carried = false;
+    for receipt in live.iter().filter(|r| r.seq() > through_seq) {
+        match receipt {
+            Receipt::Outcome(o) if seqs.get(&o.commitment_receipt_hash) <= Some(&through_seq) => {
+                return Err(refuse(format!(
+                    "the outcome at seq {} settles a commitment that would be archived",
+                    o.seq
+                )));
+            }
+            Receipt::Snapshot(s) if seqs.get(&s.anchored_receipt_hash) <= Some(&through_seq) => {
+                return Err(refuse(format!(
+                    "the snapshot at seq {} anchors a receipt that would be archived",
+                    s.seq
+                )));
+            }
+            Receipt::Snapshot(s) if s.namespace.is_none() => carried = true,
+            _ => {}
+        }
+    }
+    if !carried {
+        return Err(refuse("no snapshot after it carries the state forward".into()));
+    }
+    Ok(())
+}
+
+/// The latest seq, at most `at_most`, that `live` can be archived through.
+pub fn archive_point(live: &[Receipt], at_most: u64) -> Option<u64> {
+    let seqs = seqs_by_hash(live);
+    // Walking back from the head, track the oldest receipt the suffix
+    // refers to and whether it holds a snapshot.
+    let mut oldest_ref = u64::MAX;
+    let mut carried = false;
+    for pair in live.windows(2).rev() {
+        let (through, next) = (&pair[0], &pair[1]);
+        let referenced = match next {
+            Receipt::Outcome(o) => seqs.get(&o.commitment_receipt_hash),
+            Receipt::Snapshot(s) => seqs.get(&s.anchored_receipt_hash),
+            Receipt::Commitment(_) => None,
+        };
+        // A reference outside the live stream points at archived
+        // receipts, which no cut can repair.
+        oldest_ref = oldest_ref.min(referenced.copied().unwrap_or(match next {
+            Receipt::Commitment(_) => u64::MAX,
+            _ => 0,
+        }));
+        carried |= matches!(next, Receipt::Snapshot(s) if s.namespace.is_none());
+        if through.seq() <= at_most && carried && oldest_ref > through.seq() {
+            return Some(through.seq());
+        }
+    }
+    None
+}
+
+fn seqs_by_hash(live: &[Receipt]) -> HashMap<[u8; 32], u64> {
+    live.iter().map(|r| (r.receipt_hash(), r.seq())).collect()
+}
@@ -0,0 +1,372 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use wll_crypto::{CanonicalEncode, CanonicalEncoder, Digester, HashAlgo};
+
+use crate::error::LedgerError;
+use crate::records::{
+    CommitmentReceipt, EffectSummary, OutcomeReceipt, ProofRef, Receipt, SchemaRef,
+    SnapshotReceipt, StateUpdate,
+};
+
+/// Domain tag for canonically encoded receipt hashes.
+const CANONICAL_DOMAIN: &[u8] = b"wll-receipt-v2:";
+/// Domain tag used by the legacy JSON receipt hash.
+const LEGACY_JSON_DOMAIN: &[u8] = b"wll-receipt-v1:";
+/// Marks the state namespace fields that follow the pre-namespace encoding.
+const NAMESPACE_TAG: u8 = 0xfe;
+/// Marks the state schema references that follow an outcome's metadata.
+const SCHEMA_TAG: u8 = 0xfd;
+/// Marks the payload that follows an effect's description.
+const PAYLOAD_TAG: u8 = 0xfc;
+
+/// Encoding used to derive a receipt's `receipt_hash`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ReceiptEncoding {
+    /// Deterministic length-prefixed binary encoding (current).
+    Canonical,
+    /// `serde_json` serialization of the receipt envelope (legacy streams).
+    LegacyJson,
+}
+
+impl ReceiptEncoding {
+    /// Compute the BLAKE3 hash of `receipt` under this encoding.
+    ///
+    /// The stored `receipt_hash` field is never part of the hash input.
+    pub fn receipt_hash(self, receipt: &Receipt) -> Result<[u8; 32], LedgerError> {
+        match self {
+            Self::Canonical => Ok(canonical_receipt_hash(receipt)),
+            Self::LegacyJson => legacy_json_receipt_hash(receipt),
+        }
+    }
+}
+
+/// Encoding and hash algorithm that together produced a receipt hash.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ReceiptHashFormat {
+    pub encoding: ReceiptEncoding,
+    pub algo: HashAlgo,
+}
+
+impl ReceiptHashFormat {
+    /// Every format a verifier must consider, most preferred first.
+    ///
+    /// Legacy JSON hashes predate algorithm agility and are BLAKE3 only.
+    pub const KNOWN: [ReceiptHashFormat; 3] = [
+        ReceiptHashFormat {
+            encoding: ReceiptEncoding::Canonical,
+            algo: HashAlgo::Blake3,
+        },
+        ReceiptHashFormat {
+            encoding: ReceiptEncoding::Canonical,
+            algo: HashAlgo::Sha256,
+        },
+        ReceiptHashFormat {
+            encoding: ReceiptEncoding::LegacyJson,
+            algo: HashAlgo::Blake3,
+        },
+    ];
+
+    /// Compute the hash of `receipt` in this format.
+    pub fn receipt_hash(self, receipt: &Receipt) -> Result<[u8; 32], LedgerError> {
+        match self.encoding {
+            ReceiptEncoding::Canonical => Ok(canonical_receipt_hash_with(self.algo, receipt)),
+            ReceiptEncoding::LegacyJson => legacy_json_receipt_hash(receipt),
+        }
+    }
+}
+
+/// Canonical (current) receipt hash using BLAKE3.
+pub fn canonical_receipt_hash(receipt: &Receipt) -> [u8; 32] {
+    canonical_receipt_hash_with(HashAlgo::Blake3, receipt)
+}
+
+/// Canonical receipt hash under an explicit hash algorithm.
+///
+/// Non-default algorithms are bound into the hash input so that a digest can
+/// never be reinterpreted as belonging to a different algorithm.
+pub fn canonical_receipt_hash_with(algo: HashAlgo, receipt: &Receipt) -> [u8; 32] {
+    let mut hasher = Digester::new(algo);
+    hasher.update(CANONICAL_DOMAIN);
+    if algo != HashAlgo::Blake3 {
+        hasher.update(algo.name().as_bytes()).update(b":");
+    }
+    hasher.update(&receipt.to_canonical_bytes());
+    hasher.finalize()
+}
+
+/// Legacy receipt hash over the JSON form with a zeroed `receipt_hash`.
+///
+/// Retained so streams written before canonical encoding still verify.
+pub fn legacy_json_receipt_hash(receipt: &Receipt) -> Result<[u8; 32], LedgerError> {
+    let mut zeroed = receipt.clone();
+    zeroed.set_receipt_hash([0; 32]);
+    let encoded =
+        serde_json::to_vec(&zeroed).map_err(|e| LedgerError::Serialization(e.to_string()))?;
+
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(LEGACY_JSON_DOMAIN);
+    hasher.update(&encoded);
+    Ok(*hasher.finalize().as_bytes())
+}
+
+/// Determine which format produced the receipt's stored hash.
+///
+/// Returns `None` when the stored hash matches no known format, i.e. the
+/// receipt has been tampered with.
+pub fn detect_hash_format(receipt: &Receipt) -> Result<Option<ReceiptHashFormat>, LedgerError> {
+    let stored = receipt.receipt_hash();
+    for format in ReceiptHashFormat::KNOWN {
+        if format.receipt_hash(receipt)? == stored {
+            return Ok(Some(format));
+        }
+    }
+    Ok(None)
+}
+
+/// Determine which encoding produced the receipt's stored hash.
+///
+/// Returns `None` when the stored hash matches neither encoding, i.e. the
+/// receipt has been tampered with.
+pub fn detect_encoding(receipt: &Receipt) -> Result<Option<ReceiptEncoding>, LedgerError> {
+    Ok(detect_hash_format(receipt)?.map(|format| format.encoding))
+}
+
+/// Payloads are only encoded when present, so effects written before
+/// payloads existed keep their hashes.
+impl CanonicalEncode for EffectSummary {
+    fn encode_canonical(&self, enc: &mut CanonicalEncoder) {
+        enc.put_str(self.kind.name())
+            .put_str(&self.target)
+            .put_str(&self.description);
+        if let Some(payload) = &self.payload {
+            enc.put_u8(PAYLOAD_TAG).put(payload);
+        }
+    }
+}
+
+impl CanonicalEncode for ProofRef {
+    fn encode_canonical(&self, enc: &mut CanonicalEncoder) {
+        enc.put_str(&self.uri).put_fixed(&self.digest);
+    }
+}
+
+impl CanonicalEncode for SchemaRef {
+    fn encode_canonical(&self, enc: &mut CanonicalEncoder) {
+        enc.put_option(self.namespace.as_ref()).put_fixed(&self.schema_hash);
+    }
+}
+
+/// Namespaces are only encoded when present, so receipts written before
+/// namespaces existed keep their hashes.
+impl CanonicalEncode for StateUpdate {
+    fn encode_canonical(&self, enc: &mut CanonicalEncoder) {
+        enc.put_str(&self.key).put(&self.value);
+        if let Some(namespace) = &self.namespace {
+            enc.put_u8(NAMESPACE_TAG).put_str(namespace);
+        }
+    }
+}
+
+impl CanonicalEncode for CommitmentReceipt {
+    fn encode_canonical(&self, enc: &mut CanonicalEncoder) {
+        enc.put(&self.worldline)
+            .put_u64(self.seq)
+            .put_option(self.prev_hash.as_ref())
+            .put(&self.timestamp)
+            .put_fixed(&self.proposal_hash)
+            .put(&self.commitment_id)
+            .put(&self.class)
+            .put_str(&self.intent)
+            .put_seq(&self.requested_caps)
+            .put(&self.evidence)
+            .put(&self.decision)
+            .put_fixed(&self.policy_hash);
+    }
+}
+
+impl CanonicalEncode for OutcomeReceipt {
+    fn encode_canonical(&self, enc: &mut CanonicalEncoder) {
+        enc.put(&self.worldline)
+            .put_u64(self.seq)
+            .put_option(self.prev_hash.as_ref())
+            .put(&self.timestamp)
+            .put_fixed(&self.commitment_receipt_hash)
+            .put_fixed(&self.outcome_hash)
+            .put_bool(self.accepted)
+            .put_seq(&self.effects)
+            .put_seq(&self.proofs)
+            .put_seq(&self.state_updates);
+        encode_string_map(enc, &self.metadata);
+        if !self.schemas.is_empty() {
+            enc.put_u8(SCHEMA_TAG).put_seq(&self.schemas);
+        }
+    }
+}
+
+impl CanonicalEncode for SnapshotReceipt {
+    fn encode_canonical(&self, enc: &mut CanonicalEncoder) {
+        enc.put(&self.worldline)
+            .put_u64(self.seq)
+            .put_option(self.prev_hash.as_ref())
+            .put(&self.timestamp)
+            .put_fixed(&self.anchored_receipt_hash)
+            .put_fixed(&self.state_hash);
+        encode_value_map(enc, &self.state);
+        if self.namespace.is_some() || !self.namespaces.is_empty() {
+            enc.put_u8(NAMESPACE_TAG).put_option(self.namespace.as_ref());
+            enc.put_u64(self.namespaces.len() as u64);
+            for (name, state) in &self.namespaces {
+                enc.put_str(name);
+                encode_value_map(enc, state);
+            }
+        }
+    }
+}
+
+/// Receipts are tagged by kind; `receipt_hash` is excluded from the encoding.
+impl CanonicalEncode for Receipt {
+    fn encode_canonical(&self, enc: &mut CanonicalEncoder) {
+        match self {
+            Self::Commitment(r) => enc.put_u8(0).put(r),
+            Self::Outcome(r) => enc.put_u8(1).put(r),
+            Self::Snapshot(r) => enc.put_u8(2).put(r),
+        };
+    }
+}
+
+fn encode_string_map(enc: &mut CanonicalEncoder, map: &BTreeMap<String, String>) {
+    enc.put_u64(map.len() as u64);
+    for (key, value) in map {
+        enc.put_str(key).put_str(value);
+    }
+}
+
+fn encode_value_map(enc: &mut CanonicalEncoder, map: &BTreeMap<String, Value>) {
+    enc.put_u64(map.len() as u64);
+    for (key, value) in map {
+        enc.put_str(key).put(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wll_types::identity::IdentityMaterial;
+    use wll_types::{CommitmentId, TemporalAnchor, WorldlineId};
+
+    use super::*;
+    use crate::records::{CommitmentClass, Decision, EvidenceBundle};
+
+    fn commitment_receipt() -> Receipt {
+        Receipt::Commitment(CommitmentReceipt {
+            worldline: WorldlineId::derive(&IdentityMaterial::GenesisHash([4; 32])),
+            seq: 1,
+            receipt_hash: [0; 32],
+            prev_hash: None,
+            timestamp: TemporalAnchor::new(1_000, 0, 0),
+            proposal_hash: [1; 32],
+            commitment_id: CommitmentId::new(),
+            class: CommitmentClass::ContentUpdate,
+            intent: "canonical".into(),
+            requested_caps: vec!["cap".into()],
+            evidence: EvidenceBundle::from_references(vec!["obj://e".into()]),
+            decision: Decision::Accepted,
+            policy_hash: [2; 32],
+        })
+    }
+
+    #[test]
+    fn canonical_hash_ignores_stored_hash() {
+        let mut receipt = commitment_receipt();
+        let before = canonical_receipt_hash(&receipt);
+        receipt.set_receipt_hash([9; 32]);
+        assert_eq!(before, canonical_receipt_hash(&receipt));
+    }
+
+    #[test]
+    fn canonical_and_legacy_hashes_differ() {
+        let receipt = commitment_receipt();
+        assert_ne!(
+            canonical_receipt_hash(&receipt),
+            legacy_json_receipt_hash(&receipt).unwrap()
+        );
+    }
+
+    #[test]
+    fn detect_encoding_recognizes_both_forms() {
+        let mut receipt = commitment_receipt();
+
+        let canonical = canonical_receipt_hash(&receipt);
+        receipt.set_receipt_hash(canonical);
+        assert_eq!(
+            detect_encoding(&receipt).unwrap(),
+            Some(ReceiptEncoding::Canonical)
+        );
+
+        let legacy = legacy_json_receipt_hash(&receipt).unwrap();
+        receipt.set_receipt_hash(legacy);
+        assert_eq!(
+            detect_encoding(&receipt).unwrap(),
+            Some(ReceiptEncoding::LegacyJson)
+        );
+
+        receipt.set_receipt_hash([0xee; 32]);
+        assert_eq!(detect_encoding(&receipt).unwrap(), None);
+    }
+
+    #[test]
+    fn sha256_receipt_hash_is_detected() {
+        let mut receipt = commitment_receipt();
+        let sha = canonical_receipt_hash_with(HashAlgo::Sha256, &receipt);
+        assert_ne!(sha, canonical_receipt_hash(&receipt));
+
+        receipt.set_receipt_hash(sha);
+        assert_eq!(
+            detect_hash_format(&receipt).unwrap(),
+            Some(ReceiptHashFormat {
+                encoding: ReceiptEncoding::Canonical,
+                algo: HashAlgo::Sha256,
+            })
+        );
+    }
+
+    #[test]
+    fn snapshot_state_key_order_is_stable() {
+        let build = |pairs: &[(&str, i64)]| {
+            let state: BTreeMap<String, Value> = pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), Value::from(*v)))
+                .collect();
+            Receipt::Snapshot(SnapshotReceipt {
+                worldline: WorldlineId::derive(&IdentityMaterial::GenesisHash([5; 32])),
+                seq: 3,
+                receipt_hash: [0; 32],
+                prev_hash: Some([1; 32]),
+                timestamp: TemporalAnchor::zero(),
+                anchored_receipt_hash: [1; 32],
+                state_hash: [2; 32],
+                state,
+                namespace: None,
+                namespaces: BTreeMap::new(),
+            })
+        };
+
+        let a = build(&[("x", 1), ("y", 2)]);
+        let b = build(&[("y", 2), ("x", 1)]);
+        assert_eq!(canonical_receipt_hash(&a), canonical_receipt_hash(&b));
+    }
+
+    #[test]
+    fn default_namespace_keeps_the_pre_namespace_encoding() {
+        let update = StateUpdate::new("k", Value::from(1));
+        let mut enc = CanonicalEncoder::new();
+        enc.put_str("k").put(&Value::from(1));
+        assert_eq!(update.to_canonical_bytes(), enc.finish());
+
+        let namespaced = update.clone().in_namespace("billing");
+        assert_ne!(namespaced.to_canonical_bytes(), update.to_canonical_bytes());
+        let json = serde_json::to_value(&update).unwrap();
+        assert_eq!(json, serde_json::json!({"key": "k", "value": 1}));
+    }
+}
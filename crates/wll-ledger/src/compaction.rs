@@ -0,0 +1,175 @@
+//! Compaction of a worldline's oldest receipts into one signed record.
+//!
+//! Compaction ([`LedgerWriter::compact`](crate::LedgerWriter::compact)) is
+//! archival that keeps nothing: the receipts up to a cut leave the ledger
+//! and a [`CompactionReceipt`] takes their place. It embeds the state they
+//! leave behind and the Merkle root of their hashes, and is signed by
+//! whoever compacted. Inclusion proofs taken from the [`Compaction`] before
+//! its receipts are dropped keep verifying against that root.
+//!
+//! The cut follows the archive rules
+//! ([`check_archive_point`](crate::archive::check_archive_point)), so the
+//! live stream still validates and replays on its own. A stream compacted
+//! more than once keeps every compaction receipt, each naming the one
+//! before it.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use wll_crypto::{MerkleProof, MerkleTree, Signature, SigningKey, VerifyingKey};
+use wll_types::{ObjectId, TemporalAnchor, WorldlineId};
+
+use crate::error::LedgerError;
+use crate::records::{NamespacedState, Receipt, ReceiptRef};
+use crate::replay::apply_receipts;
+
+/// Signed stand-in for the compacted receipts of a worldline.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompactionReceipt {
+    pub worldline: WorldlineId,
+    /// Seq of the oldest compacted receipt.
+    pub first_seq: u64,
+    /// The newest compacted receipt, which the live stream continues from.
+    pub through: ReceiptRef,
+    /// `compaction_hash` of the compaction before this one, if any.
+    pub previous: Option<[u8; 32]>,
+    /// Merkle root over the compacted receipt hashes, oldest first.
+    pub merkle_root: [u8; 32],
+    /// State of the default namespace after `through`.
+    pub state: BTreeMap<String, Value>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub namespaces: NamespacedState,
+    pub timestamp: TemporalAnchor,
+    /// Ed25519 key that signed `compaction_hash`.
+    pub signer: [u8; 32],
+    pub signature: Signature,
+    pub compaction_hash: [u8; 32],
+}
+
+/// The fields `compaction_hash` covers.
+#[derive(Serialize)]
+struct Signed<'a> {
+    worldline: &'a WorldlineId,
+    first_seq: u64,
+    through: &'a ReceiptRef,
+    previous: &'a Option<[u8; 32]>,
+    merkle_root: &'a [u8; 32],
+    state: &'a BTreeMap<String, Value>,
+    namespaces: &'a NamespacedState,
+    timestamp: &'a TemporalAnchor,
+    signer: &'a [u8; 32],
+}
+
+impl CompactionReceipt {
+    /// Fold `receipts`, oldest first, into a compaction signed by `key`.
+    ///
+    /// `previous` is the stream's last compaction, which the receipts must
+    /// directly follow; its state is where folding starts.
+    pub fn fold(
+        receipts: &[Receipt],
+        previous: Option<&CompactionReceipt>,
+        timestamp: TemporalAnchor,
+        key: &SigningKey,
+    ) -> Result<Self, LedgerError> {
+        let (Some(first), Some(last)) = (receipts.first(), receipts.last()) else {
+            return Err(LedgerError::ArchiveBoundary {
+                seq: 0,
+                reason: "there are no receipts to compact".into(),
+            });
+        };
+        if let Some(previous) = previous {
+            if first.prev_hash() != Some(previous.through.receipt_hash) {
+                return Err(LedgerError::IntegrityViolation {
+                    seq: first.seq(),
+                    reason: "compacted receipts do not continue the previous compaction".into(),
+                });
+            }
+        }
+        let (state, namespaces) = previous
+            .map(|p| (p.state.clone(), p.namespaces.clone()))
+            .unwrap_or_default();
+        let replay = apply_receipts(first.worldline().clone(), state, namespaces, receipts, 0);
+
+        let mut receipt = Self {
+            worldline: first.worldline().clone(),
+            first_seq: first.seq(),
+            through: ReceiptRef::from(last),
+            previous: previous.map(|p| p.compaction_hash),
+            merkle_root: *merkle_tree(receipts).root().as_bytes(),
+            state: replay.state,
+            namespaces: replay.namespaces,
+            timestamp,
+            signer: key.verifying_key().as_bytes(),
+            signature: Signature::from_bytes([0; 64]),
+            compaction_hash: [0; 32],
+        };
+        receipt.compaction_hash = receipt.compute_hash()?;
+        receipt.signature = key.sign(&receipt.compaction_hash);
+        Ok(receipt)
+    }
+
+    /// Check `compaction_hash` against the receipt's fields and the
+    /// signature against `signer`.
+    pub fn verify(&self) -> Result<(), LedgerError> {
+        let invalid = |reason: &str| LedgerError::IntegrityViolation {
+            seq: self.through.seq,
+            reason: format!("compaction receipt {reason}"),
+        };
+        if self.compute_hash()? != self.compaction_hash {
+            return Err(invalid("hash does not match its contents"));
+        }
+        VerifyingKey::from_bytes(self.signer)
+            .and_then(|key| key.verify(&self.compaction_hash, &self.signature))
+            .map_err(|_| invalid("signature does not verify"))
+    }
+
+    /// Whether `proof` shows a receipt hash among those this compaction
+    /// folded.
+    pub fn verify_inclusion(&self, proof: &MerkleProof) -> bool {
+        proof.root.as_bytes() == &self.merkle_root && proof.verify()
+    }
+
+    fn compute_hash(&self) -> Result<[u8; 32], LedgerError> {
+        let signed = Signed {
+            worldline: &self.worldline,
+            first_seq: self.first_seq,
+            through: &self.through,
+            previous: &self.previous,
+            merkle_root: &self.merkle_root,
+            state: &self.state,
+            namespaces: &self.namespaces,
+            timestamp: &self.timestamp,
+            signer: &self.signer,
+        };
+        let encoded =
+            serde_json::to_vec(&signed).map_err(|e| LedgerError::Serialization(e.to_string()))?;
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(b"wll-compaction-v1:");
+        hasher.update(&encoded);
+        Ok(*hasher.finalize().as_bytes())
+    }
+}
+
+/// A compaction together with the receipts it dropped, for proving their
+/// inclusion before they are gone.
+#[derive(Clone, Debug)]
+pub struct Compaction {
+    pub receipt: CompactionReceipt,
+    /// The compacted receipts, oldest first.
+    pub folded: Vec<Receipt>,
+}
+
+impl Compaction {
+    /// Proof that the folded receipt with hash `receipt_hash` is under the
+    /// compaction's Merkle root, or `None` if it was not folded.
+    pub fn inclusion_proof(&self, receipt_hash: [u8; 32]) -> Option<MerkleProof> {
+        let index = self.folded.iter().position(|r| r.receipt_hash() == receipt_hash)?;
+        merkle_tree(&self.folded).proof(index)
+    }
+}
+
+fn merkle_tree(receipts: &[Receipt]) -> MerkleTree {
+    let leaves = receipts.iter().map(|r| ObjectId::from_hash(r.receipt_hash())).collect();
+    MerkleTree::from_leaves(leaves)
+}
@@ -0,0 +1,356 @@
+//! Structured effects.
+//!
+//! An [`EffectSummary`] says what an outcome did outside the ledger: an
+//! [`EffectKind`] from a fixed taxonomy (or a custom one), the target it
+//! touched, and an optional JSON payload whose keys the kind types
+//! ([`EffectKind::payload_schema`]). Targets are URIs such as
+//! `deploy://prod/api`; tree changes may name a path in the tree instead.
+//! `append_outcome` refuses effects that break these rules
+//! ([`EffectSummary::validate`]).
+//!
+//! [`EffectQuery`] picks effects by kind and target pattern, so automation
+//! can find every outcome that, say, touched `deploy://prod/*`
+//! ([`ProjectionBuilder::outcomes_with_effects`](crate::ProjectionBuilder::outcomes_with_effects)).
+//!
+//! Kinds are stored by name, so receipts written before the taxonomy read
+//! back unchanged, with unknown names as [`EffectKind::Custom`].
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::LedgerError;
+use crate::schema::{KeySchema, StateSchema, ValueKind};
+
+/// What sort of effect an outcome had.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum EffectKind {
+    /// A tree entry was added.
+    Added,
+    /// A tree entry was deleted.
+    Deleted,
+    /// A tree entry's content changed.
+    Modified,
+    /// A tree entry moved.
+    Renamed,
+    /// A tree entry's mode changed.
+    ModeChanged,
+    /// The ledger head was anchored with a time server.
+    TimeAnchor,
+    /// The ledger head was published to a transparency log.
+    LogPublication,
+    /// Software was deployed to the target environment.
+    Deploy,
+    /// A version of the target was released.
+    Release,
+    /// People or systems at the target were notified.
+    Notification,
+    /// A kind of the application's own: lowercase letters, digits and `-`.
+    Custom(String),
+}
+
+impl EffectKind {
+    /// Every kind except [`Custom`](Self::Custom).
+    pub const BUILT_IN: [EffectKind; 10] = [
+        Self::Added,
+        Self::Deleted,
+        Self::Modified,
+        Self::Renamed,
+        Self::ModeChanged,
+        Self::TimeAnchor,
+        Self::LogPublication,
+        Self::Deploy,
+        Self::Release,
+        Self::Notification,
+    ];
+
+    /// The name receipts store the kind under.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Added => "added",
+            Self::Deleted => "deleted",
+            Self::Modified => "modified",
+            Self::Renamed => "renamed",
+            Self::ModeChanged => "mode-changed",
+            Self::TimeAnchor => "time-anchor",
+            Self::LogPublication => "log-publication",
+            Self::Deploy => "deploy",
+            Self::Release => "release",
+            Self::Notification => "notification",
+            Self::Custom(name) => name,
+        }
+    }
+
+    /// The kind named `name`; names that are not built in are custom.
+    pub fn from_name(name: &str) -> Self {
+        Self::BUILT_IN
+            .into_iter()
+            .find(|kind| kind.name() == name)
+            .unwrap_or_else(|| Self::Custom(name.to_string()))
+    }
+
+    /// Returns `true` for the kinds of tree changes, whose targets are
+    /// paths in the tree.
+    pub fn is_tree_change(&self) -> bool {
+        matches!(
+            self,
+            Self::Added | Self::Deleted | Self::Modified | Self::Renamed | Self::ModeChanged
+        )
+    }
+
+    /// Returns `true` for kinds whose targets must be URIs.
+    pub fn requires_uri(&self) -> bool {
+        matches!(self, Self::Deploy | Self::Release | Self::Notification)
+    }
+
+    /// Types of the payload keys this kind defines. Other keys are
+    /// accepted with any value; custom kinds define none.
+    pub fn payload_schema(&self) -> KeySchema {
+        let keys: &[(&str, ValueKind)] = match self {
+            Self::Added => &[("object", ValueKind::String), ("mode", ValueKind::String)],
+            Self::Deleted => &[("object", ValueKind::String)],
+            Self::Modified => &[("old", ValueKind::String), ("new", ValueKind::String)],
+            Self::Renamed => &[("from", ValueKind::String), ("similarity", ValueKind::Number)],
+            Self::ModeChanged => &[("old", ValueKind::String), ("new", ValueKind::String)],
+            Self::TimeAnchor => {
+                &[("midpoint_us", ValueKind::Integer), ("radius_us", ValueKind::Integer)]
+            }
+            Self::LogPublication => &[
+                ("log", ValueKind::String),
+                ("index", ValueKind::Integer),
+                ("tree_size", ValueKind::Integer),
+            ],
+            Self::Deploy => &[("version", ValueKind::String), ("artifact", ValueKind::String)],
+            Self::Release => &[("version", ValueKind::String)],
+            Self::Notification => &[("channel", ValueKind::String)],
+            Self::Custom(_) => &[],
+        };
+        keys.iter()
+            .fold(KeySchema::new(self.name()), |schema, (key, kind)| schema.with_key(*key, *kind))
+            .with_unknown_keys(true)
+    }
+}
+
+impl fmt::Display for EffectKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl From<String> for EffectKind {
+    fn from(name: String) -> Self {
+        Self::from_name(&name)
+    }
+}
+
+impl From<&str> for EffectKind {
+    fn from(name: &str) -> Self {
+        Self::from_name(name)
+    }
+}
+
+impl From<EffectKind> for String {
+    fn from(kind: EffectKind) -> Self {
+        match kind {
+            EffectKind::Custom(name) => name,
+            kind => kind.name().to_string(),
+        }
+    }
+}
+
+/// Audit-readable and machine-readable summary of one externally visible
+/// effect.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EffectSummary {
+    pub kind: EffectKind,
+    /// URI of what the effect touched, or a tree path for tree changes.
+    pub target: String,
+    pub description: String,
+    /// Details for automation, a JSON object typed by the kind's
+    /// [payload schema](EffectKind::payload_schema).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<Value>,
+}
+
+impl EffectSummary {
+    pub fn new(
+        kind: impl Into<EffectKind>,
+        target: impl Into<String>,
+        description: impl Into<String>,
+    ) -> Self {
+        Self {
+            kind: kind.into(),
+            target: target.into(),
+            description: description.into(),
+            payload: None,
+        }
+    }
+
+    pub fn with_payload(mut self, payload: Value) -> Self {
+        self.payload = Some(payload);
+        self
+    }
+
+    /// The scheme of the target, if it is a URI.
+    pub fn scheme(&self) -> Option<&str> {
+        self.target.split_once("://").map(|(scheme, _)| scheme)
+    }
+
+    /// Check the effect against the rules for its kind, or say why not.
+    pub fn validate(&self) -> Result<(), String> {
+        let is_name = |s: &str| {
+            !s.is_empty()
+                && s.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        };
+        if let EffectKind::Custom(name) = &self.kind {
+            if !is_name(name) {
+                return Err(format!(
+                    "kind {name:?} must be lowercase letters, digits and '-'"
+                ));
+            }
+        }
+        if self.target.is_empty() || self.target.contains(char::is_whitespace) {
+            return Err("the target must be non-empty, without whitespace".into());
+        }
+        match self.scheme() {
+            Some(scheme) => {
+                let valid = scheme.starts_with(|c: char| c.is_ascii_lowercase())
+                    && scheme
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "+-.".contains(c));
+                if !valid {
+                    return Err(format!("{scheme:?} is not a URI scheme"));
+                }
+            }
+            None if self.kind.requires_uri() => {
+                return Err(format!(
+                    "{} effects need a target URI, e.g. {}://prod/api",
+                    self.kind, self.kind
+                ));
+            }
+            None => {}
+        }
+        match &self.payload {
+            None => Ok(()),
+            Some(Value::Object(payload)) => {
+                let schema = self.kind.payload_schema();
+                payload.iter().try_for_each(|(key, value)| {
+                    schema
+                        .validate(key, value)
+                        .map_err(|reason| format!("payload key {key:?}: {reason}"))
+                })
+            }
+            Some(_) => Err("the payload must be a JSON object".into()),
+        }
+    }
+}
+
+/// Check every effect of an outcome before it is recorded.
+pub(crate) fn validate_effects(effects: &[EffectSummary]) -> Result<(), LedgerError> {
+    effects.iter().try_for_each(|effect| {
+        effect.validate().map_err(|reason| LedgerError::InvalidEffect {
+            kind: effect.kind.to_string(),
+            target: effect.target.clone(),
+            reason,
+        })
+    })
+}
+
+/// Picks effects by kind and target.
+///
+/// A target pattern matches targets equal to it, with each `*` standing
+/// for any run of characters: `deploy://prod/*` matches every target under
+/// `deploy://prod/`. An empty query matches every effect.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EffectQuery {
+    pub kinds: Vec<EffectKind>,
+    pub target: Option<String>,
+}
+
+impl EffectQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only effects of `kind`; repeat for any of several kinds.
+    pub fn with_kind(mut self, kind: impl Into<EffectKind>) -> Self {
+        self.kinds.push(kind.into());
+        self
+    }
+
+    /// Only effects whose target matches `pattern`.
+    pub fn with_target(mut self, pattern: impl Into<String>) -> Self {
+        self.target = Some(pattern.into());
+        self
+    }
+
+    pub fn matches(&self, effect: &EffectSummary) -> bool {
+        (self.kinds.is_empty() || self.kinds.contains(&effect.kind))
+            && self.target.as_deref().map_or(true, |pattern| glob(pattern, &effect.target))
+    }
+}
+
+/// Whether `text` matches `pattern`, where `*` matches any run of
+/// characters.
+fn glob(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn kinds_are_stored_by_name_and_payloads_typed() {
+        let effect = EffectSummary::new(EffectKind::Deploy, "deploy://prod/api", "rolled out")
+            .with_payload(json!({"version": "2.1.0", "replicas": 3}));
+        let json = serde_json::to_value(&effect).unwrap();
+        assert_eq!(json["kind"], "deploy");
+        assert_eq!(serde_json::from_value::<EffectSummary>(json).unwrap(), effect);
+        assert_eq!(effect.validate(), Ok(()));
+        assert_eq!(EffectKind::from("mode-changed"), EffectKind::ModeChanged);
+        assert_eq!(EffectKind::from("cache-flush"), EffectKind::Custom("cache-flush".into()));
+
+        let plain = EffectSummary::new(EffectKind::Deploy, "prod", "rolled out");
+        assert!(plain.validate().unwrap_err().contains("target URI"));
+        let bad = effect.clone().with_payload(json!({"version": 2}));
+        assert!(bad.validate().unwrap_err().contains("\"version\""));
+        let list = effect.with_payload(json!(["2.1.0"]));
+        assert!(list.validate().is_err());
+        assert!(EffectSummary::new("Bad Kind", "x", "").validate().is_err());
+        assert_eq!(EffectSummary::new(EffectKind::Added, "src/lib.rs", "").validate(), Ok(()));
+    }
+
+    #[test]
+    fn queries_match_kinds_and_target_patterns() {
+        let api = EffectSummary::new(EffectKind::Deploy, "deploy://prod/api", "");
+        let staging = EffectSummary::new(EffectKind::Deploy, "deploy://staging/api", "");
+        let query = EffectQuery::new().with_target("deploy://prod/*");
+        assert!(query.matches(&api));
+        assert!(!query.matches(&staging));
+        assert!(EffectQuery::new().with_target("deploy://*/api").matches(&staging));
+        assert!(!EffectQuery::new().with_target("deploy://prod").matches(&api));
+        assert!(!query.clone().with_kind(EffectKind::Release).matches(&api));
+        assert!(EffectQuery::new().matches(&staging));
+        assert!(glob("a*a", "aa") && !glob("a*a", "a"));
+    }
+}
@@ -1,3 +1,5 @@
+use wll_types::{Coded, ErrorCode};
+
 /// Errors produced by ledger operations.
 #[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
 pub enum LedgerError {
@@ -28,6 +30,65 @@ pub enum LedgerError {
     #[error("worldline not found")]
     WorldlineNotFound,
 
+    #[error("cannot archive through seq {seq}: {reason}")]
+    ArchiveBoundary { seq: u64, reason: String },
+
     #[error("store error: {0}")]
     StoreError(String),
+
+    #[error("I/O error: {0}")]
+    Io(String),
+
+    #[error("state update {key:?} violates its schema: {reason}")]
+    SchemaViolation { namespace: Option<String>, key: String, reason: String },
+
+    #[error("state schema {} is not registered", hex::encode(.0))]
+    UnknownSchema([u8; 32]),
+
+    #[error("{kind} effect on {target:?} is invalid: {reason}")]
+    InvalidEffect { kind: String, target: String, reason: String },
+
+    #[error("not the raft leader{}", leader_hint(.leader))]
+    NotLeader { leader: Option<String> },
+
+    #[error("replication failed: {0}")]
+    Replication(String),
+
+    #[error("writes to {worldline} are fenced: the lease is held by {holder}")]
+    Fenced { worldline: String, holder: String },
+}
+
+impl Coded for LedgerError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::IntegrityViolation { .. } | Self::HashCollision => ErrorCode::LedgerIntegrity,
+            Self::MissingCommitmentReceipt | Self::MissingSnapshotAnchor => {
+                ErrorCode::ReceiptNotFound
+            }
+            Self::CommitmentNotAccepted | Self::CommitmentNotRejected => {
+                ErrorCode::CommitmentState
+            }
+            Self::InvalidRange { .. } => ErrorCode::InvalidRange,
+            Self::Serialization(_) => ErrorCode::Serialization,
+            Self::WorldlineNotFound => ErrorCode::WorldlineNotFound,
+            Self::ArchiveBoundary { .. } => ErrorCode::ArchiveBoundary,
+            Self::StoreError(_) | Self::Io(_) => ErrorCode::Io,
+            Self::SchemaViolation { .. } => ErrorCode::SchemaViolation,
+            Self::UnknownSchema(_) => ErrorCode::UnknownSchema,
+            Self::InvalidEffect { .. } => ErrorCode::InvalidEffect,
+            Self::NotLeader { .. } => ErrorCode::NotLeader,
+            Self::Replication(_) => ErrorCode::ReplicationFailed,
+            Self::Fenced { .. } => ErrorCode::NotPrimary,
+        }
+    }
+}
+
+impl From<std::io::Error> for LedgerError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+fn leader_hint(leader: &Option<String>) -> String {
+    leader.as_ref().map(|leader| format!("; the leader is {leader}")).unwrap_or_default()
 }
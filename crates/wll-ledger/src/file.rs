@@ -0,0 +1,814 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use wll_crypto::{HashAlgo, SigningKey};
+use wll_types::{Clock, WorldlineId};
+
+use crate::compaction::{Compaction, CompactionReceipt};
+use crate::error::LedgerError;
+use crate::memory::InMemoryLedger;
+use crate::records::{
+    CommitmentProposal, CommitmentReceipt, Decision, OutcomeReceipt, OutcomeRecord, Receipt,
+    ReceiptRef, SnapshotInput, SnapshotReceipt,
+};
+use crate::schema::{SchemaRegistry, StateSchema};
+use crate::traits::{LedgerReader, LedgerWriter};
+
+/// Durable ledger backed by an append-only JSON-lines receipt journal.
+///
+/// Every appended receipt is written as one line to the journal and synced
+/// before the append returns. An append whose line cannot be written or
+/// synced is undone, in the journal and in memory, before it fails. On open the journal is replayed through
+/// [`InMemoryLedger::import_receipt`], so hash-chain and receipt-hash checks
+/// run on every load. A trailing line without a newline is the remnant of an
+/// interrupted append and is truncated away.
+///
+/// Archived receipts move to `archive/<worldline>.jsonl` beside the journal
+/// ([`FileLedger::archive_dir`]), in the same format, and the journal is
+/// rewritten without them. On open each archive's last receipt is where its
+/// live stream resumes. Compaction receipts are appended to
+/// `compactions/<worldline>.jsonl` instead, the compacted receipts are
+/// dropped, and on open a stream resumes after its last compaction unless
+/// it was archived further since.
+pub struct FileLedger {
+    path: PathBuf,
+    inner: InMemoryLedger,
+    journal: Mutex<Box<dyn Journal>>,
+}
+
+/// Where appended receipt lines go: the journal file, or in tests a file
+/// that fails on demand.
+trait Journal: Write + Send {
+    fn len(&self) -> std::io::Result<u64>;
+    fn sync(&mut self) -> std::io::Result<()>;
+    fn truncate(&mut self, len: u64) -> std::io::Result<()>;
+}
+
+impl Journal for File {
+    fn len(&self) -> std::io::Result<u64> {
+        Ok(self.metadata()?.len())
+    }
+
+    fn sync(&mut self) -> std::io::Result<()> {
+        self.sync_data()
+    }
+
+    fn truncate(&mut self, len: u64) -> std::io::Result<()> {
+        self.set_len(len)?;
+        self.sync_data()
+    }
+}
+
+impl FileLedger {
+    /// Open (or create) the journal at `path` and load its receipts.
+    pub fn open(path: impl Into<PathBuf>, node_id: u16) -> Result<Self, LedgerError> {
+        Self::open_with(path, InMemoryLedger::new(node_id))
+    }
+
+    /// Open the journal at `path`, appending new receipts with `algo`.
+    pub fn open_with_hash_algo(
+        path: impl Into<PathBuf>,
+        node_id: u16,
+        algo: HashAlgo,
+    ) -> Result<Self, LedgerError> {
+        Self::open_with(path, InMemoryLedger::new(node_id).with_hash_algo(algo))
+    }
+
+    /// Timestamp newly appended receipts from `clock` instead of the system
+    /// clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.inner = self.inner.with_clock(clock);
+        self
+    }
+
+    fn open_with(path: impl Into<PathBuf>, inner: InMemoryLedger) -> Result<Self, LedgerError> {
+        let path = path.into();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        resume_archived(&archive_dir(&path), &inner)?;
+        resume_compacted(&compaction_dir(&path), &inner)?;
+        let valid_len = load_journal(&path, &inner)?;
+        let journal = open_append(&path, valid_len)?;
+
+        Ok(Self {
+            path,
+            inner,
+            journal: Mutex::new(Box::new(journal)),
+        })
+    }
+
+    /// See [`InMemoryLedger::stage`].
+    #[cfg(feature = "raft")]
+    pub(crate) fn stage(
+        &self,
+        worldline: &WorldlineId,
+        from_seq: u64,
+    ) -> Result<InMemoryLedger, LedgerError> {
+        self.inner.stage(worldline, from_seq)
+    }
+
+    /// Location of the receipt journal.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Algorithm used for newly appended receipt hashes.
+    pub fn hash_algo(&self) -> HashAlgo {
+        self.inner.hash_algo()
+    }
+
+    /// Directory holding archived receipts, one `<worldline>.jsonl` file per
+    /// archived stream.
+    pub fn archive_dir(&self) -> PathBuf {
+        archive_dir(&self.path)
+    }
+
+    /// Receipts archived from `worldline`, oldest first.
+    pub fn read_archive(&self, worldline: &WorldlineId) -> Result<Vec<Receipt>, LedgerError> {
+        let mut receipts = Vec::new();
+        read_journal(&archive_path(&self.path, worldline), |receipt| {
+            receipts.push(receipt);
+            Ok(())
+        })?;
+        Ok(receipts)
+    }
+
+    /// Directory holding compaction receipts, one `<worldline>.jsonl` file
+    /// per compacted stream.
+    pub fn compaction_dir(&self) -> PathBuf {
+        compaction_dir(&self.path)
+    }
+
+    /// Replace the journal with the live receipts of every stream and
+    /// reopen `journal` on it.
+    fn rewrite_journal(&self, journal: &mut Box<dyn Journal>) -> Result<(), LedgerError> {
+        // Write then rename so a crash leaves the old journal, whose
+        // archived and compacted receipts are skipped on open.
+        let tmp = self.path.with_extension("jsonl.tmp");
+        let mut rewritten = File::create(&tmp)?;
+        for stream in self.inner.worldlines()? {
+            write_lines(&mut rewritten, &self.inner.read_all(&stream)?)?;
+        }
+        fs::rename(&tmp, &self.path)?;
+        *journal = Box::new(OpenOptions::new().append(true).open(&self.path)?);
+        Ok(())
+    }
+
+    /// Validate hash chain, sequence monotonicity, and receipt attribution.
+    pub fn validate_stream(&self, worldline: &WorldlineId) -> Result<(), LedgerError> {
+        self.inner.validate_stream(worldline)
+    }
+
+    /// Run an in-memory append and persist the resulting receipt, undoing
+    /// the append if it cannot be persisted so memory never runs ahead of
+    /// the journal.
+    ///
+    /// The journal lock is held across both steps so journal order always
+    /// matches in-memory append order.
+    fn append_with<T>(
+        &self,
+        append: impl FnOnce(&InMemoryLedger) -> Result<T, LedgerError>,
+        to_receipt: impl FnOnce(&T) -> Receipt,
+    ) -> Result<T, LedgerError> {
+        let mut journal = self
+            .journal
+            .lock()
+            .map_err(|_| LedgerError::IntegrityViolation {
+                seq: 0,
+                reason: "ledger journal lock poisoned".into(),
+            })?;
+
+        let appended = append(&self.inner)?;
+        let receipt = to_receipt(&appended);
+        if let Err(e) = append_line(journal.as_mut(), &receipt) {
+            self.inner.pop_tip(&receipt)?;
+            return Err(e);
+        }
+        Ok(appended)
+    }
+}
+
+/// Write `receipt` to `journal` as one synced line. If the write or sync
+/// fails the journal is cut back to where it was, so no torn line is left
+/// for the next append to follow.
+fn append_line(journal: &mut dyn Journal, receipt: &Receipt) -> Result<(), LedgerError> {
+    let mut line =
+        serde_json::to_vec(receipt).map_err(|e| LedgerError::Serialization(e.to_string()))?;
+    line.push(b'\n');
+    let len = journal.len()?;
+    if let Err(e) = journal.write_all(&line).and_then(|()| journal.sync()) {
+        journal.truncate(len)?;
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// Replay the journal into `ledger`, returning the byte length of its valid
+/// prefix.
+fn load_journal(path: &Path, ledger: &InMemoryLedger) -> Result<u64, LedgerError> {
+    read_journal(path, |receipt| {
+        // An archival interrupted before the journal was rewritten leaves
+        // receipts that are already in the archive.
+        let archived = ledger.archived_through(receipt.worldline())?;
+        if archived.is_some_and(|base| receipt.seq() <= base.seq) {
+            return Ok(());
+        }
+        ledger.import_receipt(receipt)
+    })
+}
+
+/// Resume each archived stream after the last receipt in its archive file,
+/// trimming a torn trailing line.
+fn resume_archived(dir: &Path, ledger: &InMemoryLedger) -> Result<(), LedgerError> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().map_or(true, |ext| ext != "jsonl") {
+            continue;
+        }
+        let mut last = None;
+        let valid_len = read_journal(&path, |receipt| {
+            last = Some(receipt);
+            Ok(())
+        })?;
+        open_append(&path, valid_len)?;
+        if let Some(last) = last {
+            ledger.resume_after(ReceiptRef::from(&last))?;
+        }
+    }
+    Ok(())
+}
+
+/// Load each compacted stream's compaction receipts and resume it after
+/// the last one, unless it was archived past that, trimming a torn
+/// trailing line.
+fn resume_compacted(dir: &Path, ledger: &InMemoryLedger) -> Result<(), LedgerError> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().map_or(true, |ext| ext != "jsonl") {
+            continue;
+        }
+        let mut last = None;
+        let valid_len = read_lines(&path, |compaction: CompactionReceipt| {
+            last = Some(compaction.through.clone());
+            ledger.restore_compaction(compaction)
+        })?;
+        open_append(&path, valid_len)?;
+        let Some(last) = last else { continue };
+        let archived = ledger.archived_through(&last.worldline)?;
+        if archived.map_or(true, |base| base.seq < last.seq) {
+            ledger.resume_after(last)?;
+        }
+    }
+    Ok(())
+}
+
+fn compaction_dir(journal: &Path) -> PathBuf {
+    journal.with_file_name("compactions")
+}
+
+fn compaction_path(journal: &Path, worldline: &WorldlineId) -> PathBuf {
+    compaction_dir(journal).join(format!("{}.jsonl", worldline.to_hex()))
+}
+
+fn archive_dir(journal: &Path) -> PathBuf {
+    journal.with_file_name("archive")
+}
+
+fn archive_path(journal: &Path, worldline: &WorldlineId) -> PathBuf {
+    archive_dir(journal).join(format!("{}.jsonl", worldline.to_hex()))
+}
+
+/// Open `path` for appending, cut to its first `valid_len` bytes.
+pub(crate) fn open_append(path: &Path, valid_len: u64) -> Result<File, LedgerError> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    if file.metadata()?.len() != valid_len {
+        file.set_len(valid_len)?;
+    }
+    Ok(file)
+}
+
+fn write_lines(file: &mut File, receipts: &[Receipt]) -> Result<(), LedgerError> {
+    let mut lines = Vec::new();
+    for receipt in receipts {
+        serde_json::to_writer(&mut lines, receipt)
+            .map_err(|e| LedgerError::Serialization(e.to_string()))?;
+        lines.push(b'\n');
+    }
+    file.write_all(&lines)?;
+    file.sync_data()?;
+    Ok(())
+}
+
+/// Pass every complete receipt line of the journal at `path` to
+/// `on_receipt`, returning the byte length of the lines read.
+fn read_journal(
+    path: &Path,
+    on_receipt: impl FnMut(Receipt) -> Result<(), LedgerError>,
+) -> Result<u64, LedgerError> {
+    read_lines(path, on_receipt)
+}
+
+/// Pass every complete JSON line of the file at `path` to `on_line`,
+/// returning the byte length of the lines read.
+pub(crate) fn read_lines<T: serde::de::DeserializeOwned>(
+    path: &Path,
+    mut on_line: impl FnMut(T) -> Result<(), LedgerError>,
+) -> Result<u64, LedgerError> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut reader = BufReader::new(file);
+    let mut line = Vec::new();
+    let mut valid_len = 0u64;
+    let mut line_no = 0u64;
+    loop {
+        line.clear();
+        let read = reader.read_until(b'\n', &mut line)?;
+        if read == 0 || line.last() != Some(&b'\n') {
+            break;
+        }
+        line_no += 1;
+
+        let value = serde_json::from_slice(&line).map_err(|e| {
+            LedgerError::Serialization(format!("journal line {line_no}: {e}"))
+        })?;
+        on_line(value)?;
+        valid_len += read as u64;
+    }
+    Ok(valid_len)
+}
+
+impl LedgerWriter for FileLedger {
+    fn append_commitment(
+        &self,
+        proposal: &CommitmentProposal,
+        decision: &Decision,
+        policy_hash: [u8; 32],
+    ) -> Result<CommitmentReceipt, LedgerError> {
+        self.append_with(
+            |inner| inner.append_commitment(proposal, decision, policy_hash),
+            |c| Receipt::Commitment(c.clone()),
+        )
+    }
+
+    fn append_outcome(
+        &self,
+        commitment_receipt_hash: [u8; 32],
+        outcome: &OutcomeRecord,
+    ) -> Result<OutcomeReceipt, LedgerError> {
+        self.append_with(
+            |inner| inner.append_outcome(commitment_receipt_hash, outcome),
+            |o| Receipt::Outcome(o.clone()),
+        )
+    }
+
+    fn append_rejection_outcome(
+        &self,
+        commitment_receipt_hash: [u8; 32],
+        reason: &str,
+    ) -> Result<OutcomeReceipt, LedgerError> {
+        self.append_with(
+            |inner| inner.append_rejection_outcome(commitment_receipt_hash, reason),
+            |o| Receipt::Outcome(o.clone()),
+        )
+    }
+
+    fn append_snapshot(&self, snapshot: &SnapshotInput) -> Result<SnapshotReceipt, LedgerError> {
+        self.append_with(
+            |inner| inner.append_snapshot(snapshot),
+            |s| Receipt::Snapshot(s.clone()),
+        )
+    }
+
+    fn import_receipt(&self, receipt: Receipt) -> Result<(), LedgerError> {
+        let journaled = receipt.clone();
+        self.append_with(|inner| inner.import_receipt(receipt), |_| journaled)
+    }
+
+    /// Appends the archived receipts to the stream's archive file, then
+    /// rewrites the journal with the live receipts of every stream.
+    fn archive(
+        &self,
+        worldline: &WorldlineId,
+        through_seq: u64,
+    ) -> Result<Vec<Receipt>, LedgerError> {
+        let mut journal = self
+            .journal
+            .lock()
+            .map_err(|_| LedgerError::IntegrityViolation {
+                seq: 0,
+                reason: "ledger journal lock poisoned".into(),
+            })?;
+
+        let archived = self.inner.archive(worldline, through_seq)?;
+        let archive = archive_path(&self.path, worldline);
+        fs::create_dir_all(archive_dir(&self.path))?;
+        write_lines(
+            &mut OpenOptions::new().create(true).append(true).open(&archive)?,
+            &archived,
+        )?;
+        self.rewrite_journal(&mut journal)?;
+        Ok(archived)
+    }
+
+    /// Appends the compaction receipt to the stream's compaction file, then
+    /// rewrites the journal with the live receipts of every stream.
+    fn compact(
+        &self,
+        worldline: &WorldlineId,
+        through_seq: u64,
+        key: &SigningKey,
+    ) -> Result<Compaction, LedgerError> {
+        let mut journal = self
+            .journal
+            .lock()
+            .map_err(|_| LedgerError::IntegrityViolation {
+                seq: 0,
+                reason: "ledger journal lock poisoned".into(),
+            })?;
+
+        let compaction = self.inner.compact(worldline, through_seq, key)?;
+        fs::create_dir_all(compaction_dir(&self.path))?;
+        let mut line = serde_json::to_vec(&compaction.receipt)
+            .map_err(|e| LedgerError::Serialization(e.to_string()))?;
+        line.push(b'\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(compaction_path(&self.path, worldline))?;
+        file.write_all(&line)?;
+        file.sync_data()?;
+        self.rewrite_journal(&mut journal)?;
+        Ok(compaction)
+    }
+
+    fn register_schema(
+        &self,
+        worldline: &WorldlineId,
+        namespace: Option<&str>,
+        schema: Arc<dyn StateSchema>,
+    ) -> Result<(), LedgerError> {
+        self.inner.register_schema(worldline, namespace, schema)
+    }
+}
+
+impl LedgerReader for FileLedger {
+    fn head(&self, worldline: &WorldlineId) -> Result<Option<ReceiptRef>, LedgerError> {
+        self.inner.head(worldline)
+    }
+
+    fn read_range(
+        &self,
+        worldline: &WorldlineId,
+        from_seq: u64,
+        to_seq: u64,
+    ) -> Result<Vec<Receipt>, LedgerError> {
+        self.inner.read_range(worldline, from_seq, to_seq)
+    }
+
+    fn read_all(&self, worldline: &WorldlineId) -> Result<Vec<Receipt>, LedgerError> {
+        self.inner.read_all(worldline)
+    }
+
+    fn get_by_hash(&self, hash: [u8; 32]) -> Result<Option<Receipt>, LedgerError> {
+        self.inner.get_by_hash(hash)
+    }
+
+    fn worldlines(&self) -> Result<Vec<WorldlineId>, LedgerError> {
+        self.inner.worldlines()
+    }
+
+    fn receipt_count(&self, worldline: &WorldlineId) -> Result<u64, LedgerError> {
+        self.inner.receipt_count(worldline)
+    }
+
+    fn archived_through(&self, worldline: &WorldlineId) -> Result<Option<ReceiptRef>, LedgerError> {
+        self.inner.archived_through(worldline)
+    }
+
+    fn compactions(
+        &self,
+        worldline: &WorldlineId,
+    ) -> Result<Vec<CompactionReceipt>, LedgerError> {
+        self.inner.compactions(worldline)
+    }
+
+    fn schemas(&self) -> Result<SchemaRegistry, LedgerError> {
+        self.inner.schemas()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde_json::Value;
+    use wll_types::{identity::IdentityMaterial, CommitmentId};
+
+    use super::*;
+    use crate::records::StateUpdate;
+
+    fn worldline() -> WorldlineId {
+        WorldlineId::derive(&IdentityMaterial::GenesisHash([8; 32]))
+    }
+
+    fn commit(ledger: &FileLedger, value: i64) -> OutcomeReceipt {
+        let wid = worldline();
+        let proposal = CommitmentProposal {
+            worldline: wid.clone(),
+            commitment_id: CommitmentId::new(),
+            class: wll_types::CommitmentClass::ContentUpdate,
+            intent: "persist".into(),
+            requested_caps: vec![],
+            targets: vec![wid],
+            evidence: wll_types::EvidenceBundle::empty(),
+            nonce: value as u64,
+        };
+        let c = ledger
+            .append_commitment(&proposal, &Decision::Accepted, [0; 32])
+            .unwrap();
+        let outcome = OutcomeRecord {
+            effects: vec![],
+            proofs: vec![],
+            state_updates: vec![StateUpdate {
+                key: "value".into(),
+                value: Value::from(value),
+                namespace: None,
+            }],
+            metadata: BTreeMap::new(),
+        };
+        ledger.append_outcome(c.receipt_hash, &outcome).unwrap()
+    }
+
+    #[test]
+    fn receipts_survive_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ledger").join("receipts.jsonl");
+
+        let tip = {
+            let ledger = FileLedger::open(&path, 0).unwrap();
+            commit(&ledger, 1);
+            commit(&ledger, 2).receipt_hash
+        };
+
+        let ledger = FileLedger::open(&path, 0).unwrap();
+        assert_eq!(ledger.receipt_count(&worldline()).unwrap(), 4);
+        assert_eq!(ledger.head(&worldline()).unwrap().unwrap().receipt_hash, tip);
+        ledger.validate_stream(&worldline()).unwrap();
+
+        assert_eq!(commit(&ledger, 3).seq, 6);
+        ledger.validate_stream(&worldline()).unwrap();
+    }
+
+    #[test]
+    fn torn_trailing_line_is_discarded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("receipts.jsonl");
+        {
+            let ledger = FileLedger::open(&path, 0).unwrap();
+            commit(&ledger, 1);
+        }
+        let intact = fs::metadata(&path).unwrap().len();
+        OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"{\"Commitment\":{")
+            .unwrap();
+
+        let ledger = FileLedger::open(&path, 0).unwrap();
+        assert_eq!(ledger.receipt_count(&worldline()).unwrap(), 2);
+        assert_eq!(fs::metadata(&path).unwrap().len(), intact);
+    }
+
+    /// The journal file, failing every write halfway through its line or
+    /// every sync after a whole line.
+    struct Failing {
+        file: File,
+        torn: bool,
+    }
+
+    impl Write for Failing {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if self.torn {
+                self.file.write_all(&buf[..buf.len() / 2])?;
+                return Err(std::io::Error::other("disk full"));
+            }
+            self.file.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.file.flush()
+        }
+    }
+
+    impl Journal for Failing {
+        fn len(&self) -> std::io::Result<u64> {
+            self.file.len()
+        }
+
+        fn sync(&mut self) -> std::io::Result<()> {
+            Err(std::io::Error::other("sync failed"))
+        }
+
+        fn truncate(&mut self, len: u64) -> std::io::Result<()> {
+            self.file.truncate(len)
+        }
+    }
+
+    #[test]
+    fn failed_appends_leave_memory_and_journal_as_they_were() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("receipts.jsonl");
+        let ledger = FileLedger::open(&path, 0).unwrap();
+        let tip = commit(&ledger, 1).receipt_hash;
+        let intact = fs::metadata(&path).unwrap().len();
+
+        for torn in [true, false] {
+            let file = OpenOptions::new().append(true).open(&path).unwrap();
+            *ledger.journal.lock().unwrap() = Box::new(Failing { file, torn });
+            let proposal = CommitmentProposal {
+                worldline: worldline(),
+                commitment_id: CommitmentId::new(),
+                class: wll_types::CommitmentClass::ContentUpdate,
+                intent: "lost".into(),
+                requested_caps: vec![],
+                targets: vec![],
+                evidence: wll_types::EvidenceBundle::empty(),
+                nonce: 9,
+            };
+            let err = ledger.append_commitment(&proposal, &Decision::Accepted, [0; 32]);
+            assert!(matches!(err, Err(LedgerError::Io(_))));
+            assert_eq!(ledger.head(&worldline()).unwrap().unwrap().receipt_hash, tip);
+            assert_eq!(fs::metadata(&path).unwrap().len(), intact);
+        }
+
+        let file = OpenOptions::new().append(true).open(&path).unwrap();
+        *ledger.journal.lock().unwrap() = Box::new(file);
+        let tip = commit(&ledger, 2).receipt_hash;
+        let reopened = FileLedger::open(&path, 0).unwrap();
+        assert_eq!(reopened.receipt_count(&worldline()).unwrap(), 4);
+        assert_eq!(reopened.head(&worldline()).unwrap().unwrap().receipt_hash, tip);
+        reopened.validate_stream(&worldline()).unwrap();
+    }
+
+    #[test]
+    fn tampered_journal_fails_to_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("receipts.jsonl");
+        {
+            let ledger = FileLedger::open(&path, 0).unwrap();
+            commit(&ledger, 1);
+        }
+        let journal = fs::read_to_string(&path).unwrap();
+        fs::write(&path, journal.replace("\"persist\"", "\"forged\"")).unwrap();
+
+        let err = FileLedger::open(&path, 0).err().unwrap();
+        assert!(matches!(err, LedgerError::IntegrityViolation { .. }));
+    }
+
+    #[test]
+    fn imported_receipts_are_journaled() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = InMemoryLedger::new(0);
+        let wid = worldline();
+        let c = source
+            .append_commitment(
+                &CommitmentProposal {
+                    worldline: wid.clone(),
+                    commitment_id: CommitmentId::new(),
+                    class: wll_types::CommitmentClass::ContentUpdate,
+                    intent: "from peer".into(),
+                    requested_caps: vec![],
+                    targets: vec![wid.clone()],
+                    evidence: wll_types::EvidenceBundle::empty(),
+                    nonce: 1,
+                },
+                &Decision::Accepted,
+                [0; 32],
+            )
+            .unwrap();
+
+        let path = dir.path().join("receipts.jsonl");
+        FileLedger::open(&path, 0)
+            .unwrap()
+            .import_receipt(Receipt::Commitment(c.clone()))
+            .unwrap();
+
+        let ledger = FileLedger::open(&path, 0).unwrap();
+        assert_eq!(ledger.head(&wid).unwrap().unwrap().receipt_hash, c.receipt_hash);
+    }
+
+    #[test]
+    fn archive_survives_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ledger").join("receipts.jsonl");
+        let wid = worldline();
+        {
+            let ledger = FileLedger::open(&path, 0).unwrap();
+            commit(&ledger, 1);
+            let anchor = commit(&ledger, 2);
+            let mut state = BTreeMap::new();
+            state.insert("value".into(), Value::from(2));
+            ledger
+                .append_snapshot(&SnapshotInput {
+                    worldline: wid.clone(),
+                    anchored_receipt_hash: anchor.receipt_hash,
+                    state,
+                    namespace: None,
+                    namespaces: BTreeMap::new(),
+                })
+                .unwrap();
+            assert_eq!(ledger.archive(&wid, 2).unwrap().len(), 2);
+        }
+
+        let ledger = FileLedger::open(&path, 0).unwrap();
+        assert_eq!(ledger.archived_through(&wid).unwrap().unwrap().seq, 2);
+        assert_eq!(ledger.receipt_count(&wid).unwrap(), 3);
+        assert_eq!(ledger.read_archive(&wid).unwrap().len(), 2);
+        ledger.validate_stream(&wid).unwrap();
+        assert_eq!(commit(&ledger, 3).seq, 7);
+        ledger.validate_stream(&wid).unwrap();
+    }
+
+    #[test]
+    fn compaction_survives_reopen_and_old_receipts_prove_against_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ledger").join("receipts.jsonl");
+        let wid = worldline();
+        let key = SigningKey::from_bytes([9; 32]);
+        let snapshot = |ledger: &FileLedger, anchor: &OutcomeReceipt, value: i64| {
+            let mut state = BTreeMap::new();
+            state.insert("value".into(), Value::from(value));
+            ledger
+                .append_snapshot(&SnapshotInput {
+                    worldline: wid.clone(),
+                    anchored_receipt_hash: anchor.receipt_hash,
+                    state,
+                    namespace: None,
+                    namespaces: BTreeMap::new(),
+                })
+                .unwrap();
+        };
+        let (first, proof) = {
+            let ledger = FileLedger::open(&path, 0).unwrap();
+            let first = commit(&ledger, 1);
+            let anchor = commit(&ledger, 2);
+            snapshot(&ledger, &anchor, 2);
+            let compaction = ledger.compact(&wid, 2, &key).unwrap();
+            assert_eq!(compaction.folded.len(), 2);
+            assert_eq!(compaction.receipt.state["value"], Value::from(1));
+            let proof = compaction.inclusion_proof(first.receipt_hash).unwrap();
+            (compaction.receipt, proof)
+        };
+
+        let ledger = FileLedger::open(&path, 0).unwrap();
+        assert!(!ledger.archive_dir().exists());
+        assert_eq!(ledger.compactions(&wid).unwrap(), vec![first.clone()]);
+        assert_eq!(ledger.archived_through(&wid).unwrap().unwrap().seq, 2);
+        assert_eq!(ledger.receipt_count(&wid).unwrap(), 3);
+        ledger.validate_stream(&wid).unwrap();
+        first.verify().unwrap();
+        assert!(first.verify_inclusion(&proof));
+
+        let anchor = commit(&ledger, 3);
+        snapshot(&ledger, &anchor, 3);
+        let second = ledger.compact(&wid, 5, &key).unwrap().receipt;
+        assert_eq!(second.previous, Some(first.compaction_hash));
+        assert_eq!((second.first_seq, second.state["value"].clone()), (3, Value::from(2)));
+        assert!(!second.verify_inclusion(&proof) && first.verify_inclusion(&proof));
+
+        let mut forged = second.clone();
+        forged.state.insert("value".into(), Value::from(99));
+        assert!(forged.verify().is_err());
+    }
+
+    #[test]
+    fn sha256_journal_reloads() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("receipts.jsonl");
+        {
+            let ledger = FileLedger::open_with_hash_algo(&path, 0, HashAlgo::Sha256).unwrap();
+            commit(&ledger, 1);
+        }
+        let ledger = FileLedger::open(&path, 0).unwrap();
+        ledger.validate_stream(&worldline()).unwrap();
+    }
+}
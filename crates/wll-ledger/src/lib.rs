@@ -2,30 +2,89 @@
 //!
 //! This crate is the heart of WLL. It provides:
 //! - Commitment and outcome receipt types with hash-linked integrity
+//! - Canonical binary receipt encoding with legacy JSON hash compatibility
 //! - `LedgerWriter` / `LedgerReader` trait boundaries
 //! - `InMemoryLedger` implementation for tests and embedding
-//! - Deterministic replay from genesis or snapshot
-//! - Projection builders (latest state, audit index)
-//! - Stream validation (hash chain, sequence, attribution)
+//! - `FileLedger` journal-backed implementation for on-disk repositories
+//!   (`fs` feature, on by default)
+//! - Deterministic replay from genesis or snapshot, with strict replay
+//!   re-evaluating recorded gate decisions
+//! - Projection builders (latest state, audit index), with state split into
+//!   named namespaces
+//! - Typed state schemas checked on append and, by recorded version, on
+//!   replay
+//! - A taxonomy of outcome effects, with typed payloads checked on append
+//!   and queries by kind and target pattern
+//! - Archival of a stream's oldest receipts, leaving a live stream that
+//!   validates and replays from the archive point
+//! - Compaction of a stream's oldest receipts into a signed receipt holding
+//!   their state and Merkle root, against which their inclusion proofs
+//!   still verify
+//! - Stream validation (hash chain, sequence, attribution, linked
+//!   commitments on other worldlines), with outcome proof verification in
+//!   strict mode
+//! - Roughtime time anchors over receipt hashes
+//! - `RaftLedger`, committing appends through a raft log kept by a cluster
+//!   of nodes before acknowledging them (`raft` feature)
 
+pub mod archive;
+pub mod canonical;
+pub mod compaction;
+pub mod effects;
 pub mod error;
+#[cfg(feature = "fs")]
+pub mod file;
 pub mod memory;
 pub mod projection;
+pub mod proof;
+#[cfg(feature = "raft")]
+pub mod raft;
 pub mod records;
 pub mod replay;
+pub mod schema;
+pub mod timestamp;
 pub mod traits;
 pub mod validation;
 
+pub use archive::{archive_point, check_archive_point};
+pub use canonical::{
+    canonical_receipt_hash, canonical_receipt_hash_with, detect_encoding, detect_hash_format,
+    legacy_json_receipt_hash, ReceiptEncoding, ReceiptHashFormat,
+};
+pub use compaction::{Compaction, CompactionReceipt};
+pub use effects::{EffectKind, EffectQuery};
 pub use error::LedgerError;
+#[cfg(feature = "fs")]
+pub use file::FileLedger;
 pub use memory::InMemoryLedger;
+pub use proof::{
+    LogInclusion, MerkleInclusionVerifier, ProofCheck, ProofError, ProofVerifier, ProofVerifiers,
+    SignedStatementVerifier, TransparencyLogVerifier,
+};
 pub use projection::{
-    AuditIndexEntry, AuditIndexProjection, LatestStateProjection, ProjectionBuilder,
+    AuditIndexEntry, AuditIndexProjection, LatestStateProjection, PendingCommitmentsProjection,
+    ProjectionBuilder,
+};
+#[cfg(feature = "raft")]
+pub use raft::{
+    AppendEntries, AppendEntriesReply, Command, Entry, NodeId, RaftConfig, RaftDriver, RaftLedger,
+    RaftService, RaftStatus, RaftTransport, Role, Stage, VoteReply, VoteRequest,
 };
 pub use records::{
-    CommitmentProposal, CommitmentReceipt, Decision, EffectSummary, EvidenceBundle, OutcomeReceipt,
-    OutcomeRecord, ProofRef, Receipt, ReceiptKind, ReceiptRef, SnapshotInput, SnapshotReceipt,
-    StateUpdate,
+    CommitmentProposal, CommitmentReceipt, CrossWorldlineRef, Decision, EffectSummary,
+    EvidenceBundle, OutcomeReceipt, OutcomeRecord, ProofRef, Receipt, ReceiptKind, ReceiptRef,
+    NamespacedState, SchemaRef, SnapshotInput, SnapshotReceipt, StateUpdate,
+    LINK_COUNTERPART_KEY, RECEIPT_URI_SCHEME,
+};
+pub use replay::{
+    DecisionMismatch, DecisionReevaluator, ReplayEngine, ReplayResult, StrictReplayResult,
+};
+pub use schema::{KeySchema, SchemaRegistry, StateSchema, ValueKind};
+pub use timestamp::{
+    anchor_nonce, roughtime_request, verify_roughtime_response, RoughtimeResponder, RoughtimeTime,
+    RoughtimeVerifier,
+};
+pub use traits::{Ledger, LedgerReader, LedgerWriter};
+pub use validation::{
+    OutcomeProofCheck, StreamValidator, ValidationReport, Violation, ViolationKind,
 };
-pub use replay::{ReplayEngine, ReplayResult};
-pub use traits::{LedgerReader, LedgerWriter};
-pub use validation::{StreamValidator, ValidationReport, Violation};
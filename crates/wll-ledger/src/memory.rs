@@ -1,16 +1,31 @@
 use std::collections::{BTreeMap, HashMap, HashSet};
-use std::sync::RwLock;
+use std::sync::{Arc, RwLock};
 
+use wll_crypto::{HashAlgo, SigningKey};
+use wll_types::{Clock, SystemClock};
+
+use crate::archive::check_archive_point;
+use crate::canonical::{canonical_receipt_hash_with, detect_encoding};
+use crate::compaction::{Compaction, CompactionReceipt};
+use crate::effects::validate_effects;
 use crate::error::LedgerError;
 use crate::records::{
     CommitmentProposal, CommitmentReceipt, Decision, OutcomeReceipt, OutcomeRecord, Receipt,
     ReceiptRef, SnapshotInput, SnapshotReceipt,
 };
+use crate::schema::{SchemaRegistry, StateSchema};
 use crate::traits::{LedgerReader, LedgerWriter};
 
 /// In-memory WLL implementation for tests, local demos, and embedding.
+///
+/// A stream that was [archived](LedgerWriter::archive) holds only its live
+/// receipts; the last archived receipt is kept as the link they continue
+/// from. A [compacted](LedgerWriter::compact) stream is one archived with
+/// nothing kept but its compaction receipts.
 pub struct InMemoryLedger {
     node_id: u16,
+    hash_algo: HashAlgo,
+    clock: Arc<dyn Clock>,
     inner: RwLock<LedgerState>,
 }
 
@@ -18,27 +33,85 @@ pub struct InMemoryLedger {
 struct LedgerState {
     streams: HashMap<wll_types::WorldlineId, Vec<Receipt>>,
     hash_index: HashMap<[u8; 32], (wll_types::WorldlineId, usize)>,
+    /// Last archived receipt of each archived stream.
+    archived: HashMap<wll_types::WorldlineId, ReceiptRef>,
+    /// Compaction receipts of each compacted stream, oldest first.
+    compactions: HashMap<wll_types::WorldlineId, Vec<CompactionReceipt>>,
+    schemas: SchemaRegistry,
+}
+
+impl LedgerState {
+    fn archived(&self, worldline: &wll_types::WorldlineId) -> Option<&ReceiptRef> {
+        self.archived.get(worldline)
+    }
+
+    /// Remove the receipts of `worldline` through `through_seq` from its
+    /// live stream, which then continues from the last of them.
+    fn cut(
+        &mut self,
+        worldline: &wll_types::WorldlineId,
+        through_seq: u64,
+    ) -> Result<Vec<Receipt>, LedgerError> {
+        let stream = self.streams.get_mut(worldline).ok_or(LedgerError::WorldlineNotFound)?;
+        check_archive_point(stream, through_seq)?;
+        let count = stream.partition_point(|r| r.seq() <= through_seq);
+        let cut: Vec<Receipt> = stream.drain(..count).collect();
+
+        for receipt in &cut {
+            self.hash_index.remove(&receipt.receipt_hash());
+        }
+        for (index, receipt) in stream.iter().enumerate() {
+            self.hash_index
+                .insert(receipt.receipt_hash(), (worldline.clone(), index));
+        }
+        if let Some(last) = cut.last() {
+            self.archived.insert(worldline.clone(), ReceiptRef::from(last));
+        }
+        Ok(cut)
+    }
 }
 
 impl InMemoryLedger {
     pub fn new(node_id: u16) -> Self {
         Self {
             node_id,
+            hash_algo: HashAlgo::Blake3,
+            clock: Arc::new(SystemClock),
             inner: RwLock::new(LedgerState::default()),
         }
     }
 
+    /// Timestamp newly appended receipts from `clock` instead of the system
+    /// clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Hash newly appended receipts with `algo` instead of BLAKE3.
+    pub fn with_hash_algo(mut self, algo: HashAlgo) -> Self {
+        self.hash_algo = algo;
+        self
+    }
+
+    /// Algorithm used for newly appended receipt hashes.
+    pub fn hash_algo(&self) -> HashAlgo {
+        self.hash_algo
+    }
+
     /// Validate hash chain, sequence monotonicity, and receipt attribution.
     pub fn validate_stream(
         &self,
         worldline: &wll_types::WorldlineId,
     ) -> Result<(), LedgerError> {
         let receipts = self.read_all(worldline)?;
+        let base = self.archived_through(worldline)?;
+        let base_seq = base.as_ref().map_or(0, |b| b.seq);
         let mut seen_receipt_hashes = HashSet::new();
         let mut commitment_hashes = HashSet::new();
 
         for (index, receipt) in receipts.iter().enumerate() {
-            let expected_seq = (index + 1) as u64;
+            let expected_seq = base_seq + (index + 1) as u64;
             if receipt.seq() != expected_seq {
                 return Err(LedgerError::IntegrityViolation {
                     seq: receipt.seq(),
@@ -47,7 +120,7 @@ impl InMemoryLedger {
             }
 
             let expected_prev = if index == 0 {
-                None
+                base.as_ref().map(|b| b.receipt_hash)
             } else {
                 Some(receipts[index - 1].receipt_hash())
             };
@@ -58,8 +131,7 @@ impl InMemoryLedger {
                 });
             }
 
-            let computed_hash = recompute_receipt_hash(receipt)?;
-            if computed_hash != receipt.receipt_hash() {
+            if detect_encoding(receipt)?.is_none() {
                 return Err(LedgerError::IntegrityViolation {
                     seq: receipt.seq(),
                     reason: "receipt hash mismatch".into(),
@@ -100,24 +172,11 @@ impl InMemoryLedger {
         worldline: &wll_types::WorldlineId,
         mut receipt: Receipt,
     ) -> Result<Receipt, LedgerError> {
+        let base = state.archived(worldline).cloned();
         let stream = state.streams.entry(worldline.clone()).or_default();
-        let expected_seq = (stream.len() + 1) as u64;
-        if receipt.seq() != expected_seq {
-            return Err(LedgerError::IntegrityViolation {
-                seq: receipt.seq(),
-                reason: format!("append attempted out of order; expected seq {expected_seq}"),
-            });
-        }
-
-        let expected_prev = stream.last().map(Receipt::receipt_hash);
-        if receipt.prev_hash() != expected_prev {
-            return Err(LedgerError::IntegrityViolation {
-                seq: receipt.seq(),
-                reason: "append attempted with mismatched previous hash".into(),
-            });
-        }
+        check_extends(stream, base.as_ref(), &receipt)?;
 
-        let receipt_hash = recompute_receipt_hash(&receipt)?;
+        let receipt_hash = canonical_receipt_hash_with(self.hash_algo, &receipt);
         if state.hash_index.contains_key(&receipt_hash) {
             return Err(LedgerError::HashCollision);
         }
@@ -132,18 +191,18 @@ impl InMemoryLedger {
     }
 
     fn stream_position(
+        &self,
         state: &LedgerState,
         worldline: &wll_types::WorldlineId,
-        node_id: u16,
     ) -> (u64, Option<[u8; 32]>, wll_types::TemporalAnchor) {
+        let base = state.archived(worldline);
         let last = state.streams.get(worldline).and_then(|s| s.last());
-        let seq = state
-            .streams
-            .get(worldline)
-            .map(|s| (s.len() + 1) as u64)
-            .unwrap_or(1);
-        let prev_hash = last.map(Receipt::receipt_hash);
-        let timestamp = next_anchor(last, node_id);
+        let live = state.streams.get(worldline).map_or(0, Vec::len);
+        let seq = base.map_or(0, |b| b.seq) + (live + 1) as u64;
+        let prev_hash = last
+            .map(Receipt::receipt_hash)
+            .or(base.map(|b| b.receipt_hash));
+        let timestamp = next_anchor(last, self.clock.anchor(self.node_id));
         (seq, prev_hash, timestamp)
     }
 
@@ -170,6 +229,34 @@ impl InMemoryLedger {
     }
 }
 
+/// Ensure `receipt` is the next link after the current tip of `stream`,
+/// which continues from `base` if it was archived.
+fn check_extends(
+    stream: &[Receipt],
+    base: Option<&ReceiptRef>,
+    receipt: &Receipt,
+) -> Result<(), LedgerError> {
+    let expected_seq = base.map_or(0, |b| b.seq) + (stream.len() + 1) as u64;
+    if receipt.seq() != expected_seq {
+        return Err(LedgerError::IntegrityViolation {
+            seq: receipt.seq(),
+            reason: format!("append attempted out of order; expected seq {expected_seq}"),
+        });
+    }
+
+    let expected_prev = stream
+        .last()
+        .map(Receipt::receipt_hash)
+        .or(base.map(|b| b.receipt_hash));
+    if receipt.prev_hash() != expected_prev {
+        return Err(LedgerError::IntegrityViolation {
+            seq: receipt.seq(),
+            reason: "append attempted with mismatched previous hash".into(),
+        });
+    }
+    Ok(())
+}
+
 impl Default for InMemoryLedger {
     fn default() -> Self {
         Self::new(0)
@@ -192,8 +279,7 @@ impl LedgerWriter for InMemoryLedger {
             })?;
 
         let proposal_hash = hash_json(proposal)?;
-        let (seq, prev_hash, timestamp) =
-            Self::stream_position(&state, &proposal.worldline, self.node_id);
+        let (seq, prev_hash, timestamp) = self.stream_position(&state, &proposal.worldline);
 
         let commitment = CommitmentReceipt {
             worldline: proposal.worldline.clone(),
@@ -240,9 +326,10 @@ impl LedgerWriter for InMemoryLedger {
         if !commitment.decision.is_accepted() {
             return Err(LedgerError::CommitmentNotAccepted);
         }
+        validate_effects(&outcome.effects)?;
 
-        let (seq, prev_hash, timestamp) =
-            Self::stream_position(&state, &commitment.worldline, self.node_id);
+        let schemas = state.schemas.check(&commitment.worldline, &outcome.state_updates)?;
+        let (seq, prev_hash, timestamp) = self.stream_position(&state, &commitment.worldline);
 
         let outcome_receipt = OutcomeReceipt {
             worldline: commitment.worldline.clone(),
@@ -257,6 +344,7 @@ impl LedgerWriter for InMemoryLedger {
             proofs: outcome.proofs.clone(),
             state_updates: outcome.state_updates.clone(),
             metadata: outcome.metadata.clone(),
+            schemas,
         };
 
         let receipt = self.append_receipt(
@@ -289,8 +377,7 @@ impl LedgerWriter for InMemoryLedger {
             return Err(LedgerError::CommitmentNotRejected);
         }
 
-        let (seq, prev_hash, timestamp) =
-            Self::stream_position(&state, &commitment.worldline, self.node_id);
+        let (seq, prev_hash, timestamp) = self.stream_position(&state, &commitment.worldline);
 
         let mut metadata = BTreeMap::new();
         metadata.insert("rejection_reason".to_string(), reason.to_string());
@@ -308,6 +395,7 @@ impl LedgerWriter for InMemoryLedger {
             proofs: vec![],
             state_updates: vec![],
             metadata,
+            schemas: vec![],
         };
 
         let receipt = self.append_receipt(
@@ -341,9 +429,12 @@ impl LedgerWriter for InMemoryLedger {
             return Err(LedgerError::MissingSnapshotAnchor);
         }
 
-        let (seq, prev_hash, timestamp) =
-            Self::stream_position(&state, &snapshot.worldline, self.node_id);
-        let state_hash = hash_json(&snapshot.state)?;
+        let (seq, prev_hash, timestamp) = self.stream_position(&state, &snapshot.worldline);
+        let state_hash = if snapshot.namespaces.is_empty() {
+            hash_json(&snapshot.state)?
+        } else {
+            hash_json(&(&snapshot.state, &snapshot.namespaces))?
+        };
 
         let snapshot_receipt = SnapshotReceipt {
             worldline: snapshot.worldline.clone(),
@@ -354,6 +445,8 @@ impl LedgerWriter for InMemoryLedger {
             anchored_receipt_hash: snapshot.anchored_receipt_hash,
             state_hash,
             state: snapshot.state.clone(),
+            namespace: snapshot.namespace.clone(),
+            namespaces: snapshot.namespaces.clone(),
         };
 
         let receipt = self.append_receipt(
@@ -367,6 +460,228 @@ impl LedgerWriter for InMemoryLedger {
             _ => unreachable!(),
         }
     }
+
+    fn import_receipt(&self, receipt: Receipt) -> Result<(), LedgerError> {
+        if detect_encoding(&receipt)?.is_none() {
+            return Err(LedgerError::IntegrityViolation {
+                seq: receipt.seq(),
+                reason: "receipt hash mismatch".into(),
+            });
+        }
+
+        let mut state = self
+            .inner
+            .write()
+            .map_err(|_| LedgerError::IntegrityViolation {
+                seq: 0,
+                reason: "ledger write lock poisoned".into(),
+            })?;
+
+        let receipt_hash = receipt.receipt_hash();
+        if state.hash_index.contains_key(&receipt_hash) {
+            return Err(LedgerError::HashCollision);
+        }
+
+        let worldline = receipt.worldline().clone();
+        let base = state.archived(&worldline).cloned();
+        let stream = state.streams.entry(worldline.clone()).or_default();
+        check_extends(stream, base.as_ref(), &receipt)?;
+        stream.push(receipt);
+        let index = stream.len() - 1;
+        state.hash_index.insert(receipt_hash, (worldline, index));
+        Ok(())
+    }
+
+    fn archive(
+        &self,
+        worldline: &wll_types::WorldlineId,
+        through_seq: u64,
+    ) -> Result<Vec<Receipt>, LedgerError> {
+        let mut state = self
+            .inner
+            .write()
+            .map_err(|_| LedgerError::IntegrityViolation {
+                seq: 0,
+                reason: "ledger write lock poisoned".into(),
+            })?;
+
+        state.cut(worldline, through_seq)
+    }
+
+    fn compact(
+        &self,
+        worldline: &wll_types::WorldlineId,
+        through_seq: u64,
+        key: &SigningKey,
+    ) -> Result<Compaction, LedgerError> {
+        let mut state = self
+            .inner
+            .write()
+            .map_err(|_| LedgerError::IntegrityViolation {
+                seq: 0,
+                reason: "ledger write lock poisoned".into(),
+            })?;
+
+        let previous = state.compactions.get(worldline).and_then(|c| c.last()).cloned();
+        let compacted_through = previous.as_ref().map(|p| p.through.seq);
+        if state.archived(worldline).map(|base| base.seq) != compacted_through {
+            return Err(LedgerError::ArchiveBoundary {
+                seq: through_seq,
+                reason: "receipts archived outside a compaction would be missing from its state"
+                    .into(),
+            });
+        }
+        let stream = state.streams.get(worldline).ok_or(LedgerError::WorldlineNotFound)?;
+        check_archive_point(stream, through_seq)?;
+        let count = stream.partition_point(|r| r.seq() <= through_seq);
+        let timestamp = self.clock.anchor(self.node_id);
+        let receipt = CompactionReceipt::fold(&stream[..count], previous.as_ref(), timestamp, key)?;
+
+        let folded = state.cut(worldline, through_seq)?;
+        state
+            .compactions
+            .entry(worldline.clone())
+            .or_default()
+            .push(receipt.clone());
+        Ok(Compaction { receipt, folded })
+    }
+
+    fn register_schema(
+        &self,
+        worldline: &wll_types::WorldlineId,
+        namespace: Option<&str>,
+        schema: Arc<dyn StateSchema>,
+    ) -> Result<(), LedgerError> {
+        let mut state = self
+            .inner
+            .write()
+            .map_err(|_| LedgerError::IntegrityViolation {
+                seq: 0,
+                reason: "ledger write lock poisoned".into(),
+            })?;
+        state.schemas.register(worldline, namespace, schema);
+        Ok(())
+    }
+}
+
+impl InMemoryLedger {
+    /// Record `compaction`, after checking its hash and signature, as the
+    /// latest compaction of its stream, e.g. when reopening a journal.
+    #[cfg(feature = "fs")]
+    pub(crate) fn restore_compaction(
+        &self,
+        compaction: CompactionReceipt,
+    ) -> Result<(), LedgerError> {
+        compaction.verify()?;
+        let mut state = self
+            .inner
+            .write()
+            .map_err(|_| LedgerError::IntegrityViolation {
+                seq: 0,
+                reason: "ledger write lock poisoned".into(),
+            })?;
+        state
+            .compactions
+            .entry(compaction.worldline.clone())
+            .or_default()
+            .push(compaction);
+        Ok(())
+    }
+
+    /// A scratch copy that appends as this ledger does, holding every
+    /// schema and the receipts of `worldline` from `from_seq` on, e.g. for
+    /// a raft leader to work out receipts before they are committed.
+    #[cfg(feature = "raft")]
+    pub(crate) fn stage(
+        &self,
+        worldline: &wll_types::WorldlineId,
+        from_seq: u64,
+    ) -> Result<Self, LedgerError> {
+        let state = self
+            .inner
+            .read()
+            .map_err(|_| LedgerError::IntegrityViolation {
+                seq: 0,
+                reason: "ledger read lock poisoned".into(),
+            })?;
+        let stream = state.streams.get(worldline).map_or(&[][..], Vec::as_slice);
+        let start = stream.partition_point(|r| r.seq() < from_seq);
+        let base = match start {
+            0 => state.archived(worldline).cloned(),
+            start => Some(ReceiptRef::from(&stream[start - 1])),
+        };
+
+        let mut staged = LedgerState { schemas: state.schemas.clone(), ..Default::default() };
+        let tail = stream[start..].to_vec();
+        for (index, receipt) in tail.iter().enumerate() {
+            staged.hash_index.insert(receipt.receipt_hash(), (worldline.clone(), index));
+        }
+        staged.streams.insert(worldline.clone(), tail);
+        if let Some(base) = base {
+            staged.archived.insert(worldline.clone(), base);
+        }
+        Ok(Self {
+            node_id: self.node_id,
+            hash_algo: self.hash_algo,
+            clock: self.clock.clone(),
+            inner: RwLock::new(staged),
+        })
+    }
+
+    /// Remove `receipt` from the tip of its stream, undoing an append that
+    /// could not be persisted.
+    #[cfg(feature = "fs")]
+    pub(crate) fn pop_tip(&self, receipt: &Receipt) -> Result<(), LedgerError> {
+        let mut state = self
+            .inner
+            .write()
+            .map_err(|_| LedgerError::IntegrityViolation {
+                seq: 0,
+                reason: "ledger write lock poisoned".into(),
+            })?;
+
+        let worldline = receipt.worldline();
+        let hash = receipt.receipt_hash();
+        match state.streams.get_mut(worldline) {
+            Some(stream) if stream.last().map(Receipt::receipt_hash) == Some(hash) => {
+                stream.pop();
+            }
+            _ => {
+                return Err(LedgerError::IntegrityViolation {
+                    seq: receipt.seq(),
+                    reason: "only the tip of a stream can be removed".into(),
+                })
+            }
+        }
+        let emptied = state.streams.get(worldline).is_some_and(Vec::is_empty);
+        if emptied && state.archived(worldline).is_none() {
+            state.streams.remove(worldline);
+        }
+        state.hash_index.remove(&hash);
+        Ok(())
+    }
+
+    /// Continue the empty stream of `base.worldline` from `base`, the last
+    /// receipt archived from it, e.g. when reopening an archived journal.
+    #[cfg(feature = "fs")]
+    pub(crate) fn resume_after(&self, base: ReceiptRef) -> Result<(), LedgerError> {
+        let mut state = self
+            .inner
+            .write()
+            .map_err(|_| LedgerError::IntegrityViolation {
+                seq: 0,
+                reason: "ledger write lock poisoned".into(),
+            })?;
+
+        if state.streams.get(&base.worldline).is_some_and(|s| !s.is_empty()) {
+            return Err(LedgerError::IntegrityViolation {
+                seq: base.seq,
+                reason: "cannot resume a stream that already has receipts".into(),
+            });
+        }
+        state.archived.insert(base.worldline.clone(), base);
+        Ok(())
+    }
 }
 
 impl LedgerReader for InMemoryLedger {
@@ -414,12 +729,14 @@ impl LedgerReader for InMemoryLedger {
             return Ok(vec![]);
         };
 
-        let start = (from_seq - 1) as usize;
-        if start >= stream.len() {
+        // Archived receipts are not returned.
+        let base_seq = state.archived(worldline).map_or(0, |b| b.seq);
+        let start = from_seq.saturating_sub(base_seq + 1) as usize;
+        if start >= stream.len() || to_seq <= base_seq {
             return Ok(vec![]);
         }
 
-        let end_exclusive = to_seq.min(stream.len() as u64) as usize;
+        let end_exclusive = (to_seq - base_seq).min(stream.len() as u64) as usize;
         Ok(stream[start..end_exclusive].to_vec())
     }
 
@@ -490,6 +807,47 @@ impl LedgerReader for InMemoryLedger {
             .map(|s| s.len() as u64)
             .unwrap_or(0))
     }
+
+    fn archived_through(
+        &self,
+        worldline: &wll_types::WorldlineId,
+    ) -> Result<Option<ReceiptRef>, LedgerError> {
+        let state = self
+            .inner
+            .read()
+            .map_err(|_| LedgerError::IntegrityViolation {
+                seq: 0,
+                reason: "ledger read lock poisoned".into(),
+            })?;
+
+        Ok(state.archived(worldline).cloned())
+    }
+
+    fn compactions(
+        &self,
+        worldline: &wll_types::WorldlineId,
+    ) -> Result<Vec<CompactionReceipt>, LedgerError> {
+        let state = self
+            .inner
+            .read()
+            .map_err(|_| LedgerError::IntegrityViolation {
+                seq: 0,
+                reason: "ledger read lock poisoned".into(),
+            })?;
+
+        Ok(state.compactions.get(worldline).cloned().unwrap_or_default())
+    }
+
+    fn schemas(&self) -> Result<SchemaRegistry, LedgerError> {
+        let state = self
+            .inner
+            .read()
+            .map_err(|_| LedgerError::IntegrityViolation {
+                seq: 0,
+                reason: "ledger read lock poisoned".into(),
+            })?;
+        Ok(state.schemas.clone())
+    }
 }
 
 fn hash_json<T: serde::Serialize>(value: &T) -> Result<[u8; 32], LedgerError> {
@@ -498,21 +856,11 @@ fn hash_json<T: serde::Serialize>(value: &T) -> Result<[u8; 32], LedgerError> {
     Ok(*blake3::hash(&encoded).as_bytes())
 }
 
-fn recompute_receipt_hash(receipt: &Receipt) -> Result<[u8; 32], LedgerError> {
-    let mut canonical = receipt.clone();
-    canonical.set_receipt_hash([0; 32]);
-
-    let encoded = serde_json::to_vec(&canonical)
-        .map_err(|e| LedgerError::Serialization(e.to_string()))?;
-
-    let mut hasher = blake3::Hasher::new();
-    hasher.update(b"wll-receipt-v1:");
-    hasher.update(&encoded);
-    Ok(*hasher.finalize().as_bytes())
-}
-
-fn next_anchor(last: Option<&Receipt>, node_id: u16) -> wll_types::TemporalAnchor {
-    let now = wll_types::TemporalAnchor::now(node_id);
+fn next_anchor(
+    last: Option<&Receipt>,
+    now: wll_types::TemporalAnchor,
+) -> wll_types::TemporalAnchor {
+    let node_id = now.node_id;
     match last {
         None => now,
         Some(previous) => {
@@ -555,15 +903,16 @@ mod tests {
 
     fn accepted_outcome(key: &str, value: i64) -> OutcomeRecord {
         OutcomeRecord {
-            effects: vec![crate::records::EffectSummary {
-                kind: "test-effect".into(),
-                target: "test-target".into(),
-                description: "state update".into(),
-            }],
+            effects: vec![crate::records::EffectSummary::new(
+                "test-effect",
+                "test-target",
+                "state update",
+            )],
             proofs: vec![],
             state_updates: vec![crate::records::StateUpdate {
                 key: key.into(),
                 value: Value::from(value),
+                namespace: None,
             }],
             metadata: BTreeMap::new(),
         }
@@ -587,6 +936,36 @@ mod tests {
         ledger.validate_stream(&wid).unwrap();
     }
 
+    #[test]
+    fn import_receipt_rebuilds_stream_from_another_ledger() {
+        let source = InMemoryLedger::default();
+        let wid = worldline(9);
+        let c = source
+            .append_commitment(&commitment(&wid), &Decision::Accepted, [0; 32])
+            .unwrap();
+        source
+            .append_outcome(c.receipt_hash, &accepted_outcome("k", 1))
+            .unwrap();
+
+        let replica = InMemoryLedger::default();
+        let mut receipts = source.read_all(&wid).unwrap();
+        let outcome = receipts.pop().unwrap();
+
+        // Out-of-order import is refused.
+        assert!(replica.import_receipt(outcome.clone()).is_err());
+
+        replica.import_receipt(receipts.pop().unwrap()).unwrap();
+        let mut tampered = outcome.clone();
+        if let Receipt::Outcome(o) = &mut tampered {
+            o.accepted = false;
+        }
+        assert!(replica.import_receipt(tampered).is_err());
+
+        replica.import_receipt(outcome).unwrap();
+        assert_eq!(replica.receipt_count(&wid).unwrap(), 2);
+        replica.validate_stream(&wid).unwrap();
+    }
+
     #[test]
     fn outcome_without_commitment_is_rejected() {
         let ledger = InMemoryLedger::default();
@@ -635,6 +1014,8 @@ mod tests {
                 worldline: wid,
                 anchored_receipt_hash: [9; 32],
                 state,
+                namespace: None,
+                namespaces: BTreeMap::new(),
             })
             .unwrap_err();
 
@@ -668,6 +1049,67 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn validate_stream_accepts_legacy_json_hashes() {
+        let ledger = InMemoryLedger::default();
+        let wid = worldline(7);
+
+        ledger
+            .append_commitment(&commitment(&wid), &Decision::Accepted, [1; 32])
+            .unwrap();
+
+        {
+            let mut guard = ledger.inner.write().unwrap();
+            let stream = guard.streams.get_mut(&wid).unwrap();
+            let legacy = crate::canonical::legacy_json_receipt_hash(&stream[0]).unwrap();
+            stream[0].set_receipt_hash(legacy);
+        }
+
+        assert!(ledger.validate_stream(&wid).is_ok());
+    }
+
+    #[test]
+    fn sha256_ledger_produces_verifiable_stream() {
+        let ledger = InMemoryLedger::new(0).with_hash_algo(HashAlgo::Sha256);
+        let wid = worldline(8);
+
+        let c = ledger
+            .append_commitment(&commitment(&wid), &Decision::Accepted, [1; 32])
+            .unwrap();
+        ledger
+            .append_outcome(c.receipt_hash, &accepted_outcome("n", 1))
+            .unwrap();
+
+        let receipt = ledger.get_by_hash(c.receipt_hash).unwrap().unwrap();
+        let format = crate::canonical::detect_hash_format(&receipt).unwrap().unwrap();
+        assert_eq!(format.algo, HashAlgo::Sha256);
+        assert!(ledger.validate_stream(&wid).is_ok());
+    }
+
+    #[test]
+    fn manual_clock_makes_receipts_reproducible() {
+        let proposal = commitment(&worldline(9));
+        let run = || {
+            let clock = Arc::new(wll_types::ManualClock::new(1_000));
+            let ledger = InMemoryLedger::new(4).with_clock(clock.clone());
+            let c = ledger
+                .append_commitment(&proposal, &Decision::Accepted, [1; 32])
+                .unwrap();
+            clock.set_ms(500);
+            let o = ledger
+                .append_outcome(c.receipt_hash, &accepted_outcome("n", 1))
+                .unwrap();
+            (c, o)
+        };
+
+        let (c, o) = run();
+        assert_eq!(c.timestamp, wll_types::TemporalAnchor::new(1_000, 0, 4));
+        assert_eq!(o.timestamp, wll_types::TemporalAnchor::new(1_000, 1, 4));
+        let (again_c, again_o) = run();
+        assert_eq!(again_c.receipt_hash, c.receipt_hash);
+        assert_eq!(again_o.receipt_hash, o.receipt_hash);
+    }
+
     #[test]
     fn read_range_is_inclusive_and_validated() {
         let ledger = InMemoryLedger::default();
@@ -738,4 +1180,84 @@ mod tests {
         let missing = ledger.get_by_hash([99; 32]).unwrap();
         assert!(missing.is_none());
     }
+
+    #[test]
+    fn archived_stream_continues_from_the_archive_point() {
+        let ledger = InMemoryLedger::default();
+        let wid = worldline(8);
+        let mut outcomes = Vec::new();
+        for value in 1..=2 {
+            let c = ledger
+                .append_commitment(&commitment(&wid), &Decision::Accepted, [0; 32])
+                .unwrap();
+            outcomes.push(ledger.append_outcome(c.receipt_hash, &accepted_outcome("v", value)).unwrap());
+        }
+        let mut state = BTreeMap::new();
+        state.insert("v".into(), Value::from(2));
+        ledger
+            .append_snapshot(&SnapshotInput {
+                worldline: wid.clone(),
+                anchored_receipt_hash: outcomes[1].receipt_hash,
+                state,
+                namespace: None,
+                namespaces: BTreeMap::new(),
+            })
+            .unwrap();
+        let c = ledger
+            .append_commitment(&commitment(&wid), &Decision::Accepted, [0; 32])
+            .unwrap();
+        ledger.append_outcome(c.receipt_hash, &accepted_outcome("w", 3)).unwrap();
+        let replayed = crate::ReplayEngine::replay_from_genesis(&ledger, &wid).unwrap().state;
+
+        // The snapshot keeps its anchor, the outcome its commitment.
+        assert!(matches!(ledger.archive(&wid, 4), Err(LedgerError::ArchiveBoundary { .. })));
+        assert!(matches!(ledger.archive(&wid, 3), Err(LedgerError::ArchiveBoundary { .. })));
+        assert_eq!(crate::archive_point(&ledger.read_all(&wid).unwrap(), 7), Some(2));
+
+        let archived = ledger.archive(&wid, 2).unwrap();
+        assert_eq!(archived.len(), 2);
+        assert_eq!(ledger.archived_through(&wid).unwrap().unwrap().seq, 2);
+        assert_eq!(ledger.receipt_count(&wid).unwrap(), 5);
+        assert_eq!(ledger.read_range(&wid, 1, 3).unwrap().len(), 1);
+        assert!(ledger.get_by_hash(outcomes[0].receipt_hash).unwrap().is_none());
+        ledger.validate_stream(&wid).unwrap();
+        assert!(crate::StreamValidator::validate_stream(&ledger, &wid).unwrap().is_valid());
+        assert_eq!(
+            crate::ReplayEngine::replay_from_genesis(&ledger, &wid).unwrap().state,
+            replayed
+        );
+
+        let next = ledger
+            .append_commitment(&commitment(&wid), &Decision::Accepted, [0; 32])
+            .unwrap();
+        assert_eq!(next.seq, 8);
+        assert!(matches!(ledger.archive(&wid, 8), Err(LedgerError::ArchiveBoundary { .. })));
+    }
+
+    #[test]
+    fn archived_streams_cannot_be_compacted() {
+        let ledger = InMemoryLedger::default();
+        let wid = worldline(9);
+        for value in 1..=3 {
+            let c = ledger
+                .append_commitment(&commitment(&wid), &Decision::Accepted, [0; 32])
+                .unwrap();
+            let outcome = ledger.append_outcome(c.receipt_hash, &accepted_outcome("v", value));
+            ledger
+                .append_snapshot(&SnapshotInput {
+                    worldline: wid.clone(),
+                    anchored_receipt_hash: outcome.unwrap().receipt_hash,
+                    state: BTreeMap::from([("v".to_string(), Value::from(value))]),
+                    namespace: None,
+                    namespaces: BTreeMap::new(),
+                })
+                .unwrap();
+        }
+        let key = SigningKey::from_bytes([4; 32]);
+        ledger.archive(&wid, 3).unwrap();
+        let refused = ledger.compact(&wid, 6, &key);
+        assert!(matches!(refused, Err(LedgerError::ArchiveBoundary { .. })));
+        assert_eq!(ledger.receipt_count(&wid).unwrap(), 6);
+        assert!(ledger.compactions(&wid).unwrap().is_empty());
+    }
 }
@@ -1,10 +1,14 @@
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Bound;
 
 use serde_json::Value;
 use wll_types::{CommitmentId, TemporalAnchor, WorldlineId};
 
 use crate::error::LedgerError;
-use crate::records::{Receipt, ReceiptKind, ReceiptRef};
+use crate::effects::EffectQuery;
+use crate::records::{
+    CommitmentReceipt, NamespacedState, OutcomeReceipt, Receipt, ReceiptKind, ReceiptRef,
+};
 use crate::traits::LedgerReader;
 
 /// Latest worldline state reconstructed from receipts.
@@ -15,7 +19,46 @@ pub struct LatestStateProjection {
     pub latest_commitment: Option<CommitmentId>,
     pub trajectory_length: u64,
     pub last_updated: Option<TemporalAnchor>,
+    /// State of the default namespace.
     pub state: BTreeMap<String, Value>,
+    pub namespaces: NamespacedState,
+}
+
+impl LatestStateProjection {
+    /// State of the namespace `name`, if anything was recorded in it.
+    pub fn namespace(&self, name: &str) -> Option<&BTreeMap<String, Value>> {
+        self.namespaces.get(name)
+    }
+
+    /// Value of `key` in the default namespace.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.state.get(key)
+    }
+
+    /// Keys of the default namespace from `start` (inclusive) to `end`
+    /// (exclusive), in order. Empty if `start` is not before `end`.
+    pub fn range<'a>(
+        &'a self,
+        start: &str,
+        end: &str,
+    ) -> impl Iterator<Item = (&'a String, &'a Value)> + 'a {
+        let keys = if start < end {
+            Some(self.state.range::<str, _>((Bound::Included(start), Bound::Excluded(end))))
+        } else {
+            None
+        };
+        keys.into_iter().flatten()
+    }
+
+    /// Keys of the default namespace starting with `prefix`, in order.
+    pub fn prefix<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Iterator<Item = (&'a String, &'a Value)> + 'a {
+        self.state
+            .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+            .take_while(move |(key, _)| key.starts_with(prefix))
+    }
 }
 
 /// Row in the audit index for compliance/audit workflows.
@@ -37,16 +80,25 @@ pub struct AuditIndexProjection {
     pub entries: Vec<AuditIndexEntry>,
 }
 
+/// Accepted commitments of a worldline still waiting for an outcome.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PendingCommitmentsProjection {
+    pub worldline: WorldlineId,
+    /// Oldest first.
+    pub pending: Vec<CommitmentReceipt>,
+}
+
 /// Deterministic projection builders.
 pub struct ProjectionBuilder;
 
 impl ProjectionBuilder {
-    pub fn latest_state<R: LedgerReader>(
+    pub fn latest_state<R: LedgerReader + ?Sized>(
         reader: &R,
         worldline: &WorldlineId,
     ) -> Result<LatestStateProjection, LedgerError> {
         let receipts = reader.read_all(worldline)?;
         let mut state = BTreeMap::new();
+        let mut namespaces = NamespacedState::new();
         let mut latest_commitment = None;
         let mut last_updated = None;
 
@@ -58,12 +110,12 @@ impl ProjectionBuilder {
                 Receipt::Outcome(o) => {
                     if o.accepted {
                         for update in &o.state_updates {
-                            state.insert(update.key.clone(), update.value.clone());
+                            update.apply_to(&mut state, &mut namespaces);
                         }
                     }
                 }
                 Receipt::Snapshot(s) => {
-                    state = s.state.clone();
+                    s.apply_to(&mut state, &mut namespaces);
                 }
             }
             last_updated = Some(receipt.timestamp());
@@ -76,10 +128,49 @@ impl ProjectionBuilder {
             trajectory_length: receipts.len() as u64,
             last_updated,
             state,
+            namespaces,
         })
     }
 
-    pub fn audit_index<R: LedgerReader>(
+    pub fn pending_commitments<R: LedgerReader + ?Sized>(
+        reader: &R,
+        worldline: &WorldlineId,
+    ) -> Result<PendingCommitmentsProjection, LedgerError> {
+        let receipts = reader.read_all(worldline)?;
+        let resolved: HashSet<[u8; 32]> = receipts
+            .iter()
+            .filter_map(Receipt::as_outcome)
+            .map(|o| o.commitment_receipt_hash)
+            .collect();
+        let pending = receipts
+            .iter()
+            .filter_map(Receipt::as_commitment)
+            .filter(|c| c.decision.is_accepted() && !resolved.contains(&c.receipt_hash))
+            .cloned()
+            .collect();
+        Ok(PendingCommitmentsProjection {
+            worldline: worldline.clone(),
+            pending,
+        })
+    }
+
+    /// Outcomes of `worldline` with an effect `query` matches, oldest first.
+    pub fn outcomes_with_effects<R: LedgerReader + ?Sized>(
+        reader: &R,
+        worldline: &WorldlineId,
+        query: &EffectQuery,
+    ) -> Result<Vec<OutcomeReceipt>, LedgerError> {
+        Ok(reader
+            .read_all(worldline)?
+            .into_iter()
+            .filter_map(|receipt| match receipt {
+                Receipt::Outcome(o) if o.effects.iter().any(|e| query.matches(e)) => Some(o),
+                _ => None,
+            })
+            .collect())
+    }
+
+    pub fn audit_index<R: LedgerReader + ?Sized>(
         reader: &R,
         worldline: &WorldlineId,
     ) -> Result<AuditIndexProjection, LedgerError> {
@@ -156,6 +247,7 @@ mod tests {
     use serde_json::Value;
     use wll_types::{CommitmentId, identity::IdentityMaterial};
 
+    use crate::effects::EffectKind;
     use crate::memory::InMemoryLedger;
     use crate::records::*;
     use crate::traits::LedgerWriter;
@@ -186,6 +278,7 @@ mod tests {
             state_updates: vec![StateUpdate {
                 key: key.to_string(),
                 value: Value::from(value),
+                namespace: None,
             }],
             metadata: BTreeMap::new(),
         }
@@ -210,6 +303,8 @@ mod tests {
                 worldline: wid.clone(),
                 anchored_receipt_hash: o.receipt_hash,
                 state: snap_state,
+                namespace: None,
+                namespaces: BTreeMap::new(),
             })
             .unwrap();
 
@@ -220,6 +315,118 @@ mod tests {
         assert_eq!(first.state.get("balance"), Some(&Value::from(40)));
     }
 
+    #[test]
+    fn namespaces_project_and_snapshot_independently() {
+        let ledger = InMemoryLedger::default();
+        let wid = worldline(3);
+        let c = ledger
+            .append_commitment(&proposal(&wid), &Decision::Accepted, [3; 32])
+            .unwrap();
+        let record = OutcomeRecord {
+            state_updates: vec![
+                StateUpdate::new("plan", Value::from("free")).in_namespace("billing"),
+                StateUpdate::new("plan", Value::from("draft")),
+            ],
+            ..outcome("seats", 5)
+        };
+        let o = ledger.append_outcome(c.receipt_hash, &record).unwrap();
+
+        let projection = ProjectionBuilder::latest_state(&ledger, &wid).unwrap();
+        assert_eq!(projection.state.get("plan"), Some(&Value::from("draft")));
+        assert_eq!(projection.namespace("billing").unwrap()["plan"], Value::from("free"));
+        assert!(projection.namespace("audit").is_none());
+
+        // A billing snapshot leaves the default namespace alone.
+        let snapshot = ledger
+            .append_snapshot(&SnapshotInput {
+                worldline: wid.clone(),
+                anchored_receipt_hash: o.receipt_hash,
+                state: BTreeMap::from([("plan".to_string(), Value::from("pro"))]),
+                namespace: Some("billing".into()),
+                namespaces: BTreeMap::new(),
+            })
+            .unwrap();
+        let projection = ProjectionBuilder::latest_state(&ledger, &wid).unwrap();
+        assert_eq!(projection.state.get("plan"), Some(&Value::from("draft")));
+        assert_eq!(projection.namespace("billing").unwrap()["plan"], Value::from("pro"));
+
+        let tail = crate::ReplayEngine::replay_from_snapshot(&ledger, &snapshot).unwrap();
+        assert_eq!(tail.namespaces, projection.namespaces);
+        assert!(tail.state.is_empty());
+    }
+
+    #[test]
+    fn projection_answers_key_range_and_prefix_queries() {
+        let ledger = InMemoryLedger::default();
+        let wid = worldline(4);
+        let c = ledger
+            .append_commitment(&proposal(&wid), &Decision::Accepted, [4; 32])
+            .unwrap();
+        let record = OutcomeRecord {
+            state_updates: ["flags/a", "flags/b", "flagship", "limits/max"]
+                .into_iter()
+                .map(|key| StateUpdate::new(key, Value::from(1)))
+                .collect(),
+            ..outcome("unused", 0)
+        };
+        ledger.append_outcome(c.receipt_hash, &record).unwrap();
+
+        let projection = ProjectionBuilder::latest_state(&ledger, &wid).unwrap();
+        assert_eq!(projection.get("limits/max"), Some(&Value::from(1)));
+        assert_eq!(projection.get("limits/min"), None);
+        let keys = |it: &mut dyn Iterator<Item = (&String, &Value)>| {
+            it.map(|(k, _)| k.clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(keys(&mut projection.prefix("flags/")), ["flags/a", "flags/b"]);
+        assert_eq!(keys(&mut projection.range("flags/b", "limits")), ["flags/b", "flagship"]);
+        assert!(keys(&mut projection.range("z", "a")).is_empty());
+    }
+
+    #[test]
+    fn pending_commitments_are_accepted_ones_without_outcomes() {
+        let ledger = InMemoryLedger::default();
+        let wid = worldline(5);
+        let append = |decision: &Decision| {
+            ledger.append_commitment(&proposal(&wid), decision, [5; 32]).unwrap()
+        };
+        let done = append(&Decision::Accepted);
+        let waiting = append(&Decision::Accepted);
+        let rejected = append(&Decision::Rejected { reason: "no".into() });
+        ledger.append_outcome(done.receipt_hash, &outcome("x", 1)).unwrap();
+        ledger.append_rejection_outcome(rejected.receipt_hash, "no").unwrap();
+
+        let projection = ProjectionBuilder::pending_commitments(&ledger, &wid).unwrap();
+        assert_eq!(projection.pending, std::slice::from_ref(&waiting));
+
+        ledger.append_outcome(waiting.receipt_hash, &outcome("y", 2)).unwrap();
+        let projection = ProjectionBuilder::pending_commitments(&ledger, &wid).unwrap();
+        assert!(projection.pending.is_empty());
+    }
+
+    #[test]
+    fn outcomes_are_found_by_the_effects_they_had() {
+        let ledger = InMemoryLedger::default();
+        let wid = worldline(6);
+        let deploy = |target: &str| {
+            let c = ledger
+                .append_commitment(&proposal(&wid), &Decision::Accepted, [6; 32])
+                .unwrap();
+            let record = OutcomeRecord {
+                effects: vec![EffectSummary::new(EffectKind::Deploy, target, "rolled out")],
+                ..outcome("unused", 0)
+            };
+            ledger.append_outcome(c.receipt_hash, &record)
+        };
+        let prod = deploy("deploy://prod/api").unwrap();
+        deploy("deploy://staging/api").unwrap();
+        let err = deploy("prod/api").unwrap_err();
+        assert!(matches!(err, LedgerError::InvalidEffect { .. }));
+
+        let query = EffectQuery::new().with_target("deploy://prod/*");
+        let found = ProjectionBuilder::outcomes_with_effects(&ledger, &wid, &query).unwrap();
+        assert_eq!(found, [prod]);
+    }
+
     #[test]
     fn audit_index_contains_all_receipts() {
         let ledger = InMemoryLedger::default();
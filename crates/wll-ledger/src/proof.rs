@@ -0,0 +1,619 @@
+//! Verification of the proofs attached to outcome receipts.
+//!
+//! A [`ProofRef`] points at a proof artifact stored outside the ledger and
+//! pins its BLAKE3 digest. Artifacts are JSON documents whose `type` field
+//! selects a [`ProofVerifier`]; every built-in proof is about the
+//! commitment the outcome settles, identified by its receipt hash:
+//!
+//! - `merkle-inclusion` — [`MerkleInclusionVerifier`]: the commitment
+//!   receipt hash is a leaf of a Merkle tree whose root is anchored.
+//! - `ed25519-statement` — [`SignedStatementVerifier`]: a trusted key
+//!   signed a statement about the commitment.
+//! - `transparency-log` — [`TransparencyLogVerifier`]: the commitment is an
+//!   entry of an RFC 6962 log, under a checkpoint signed by the log. Log
+//!   publications of an earlier receipt name it in their `anchor.receipt`
+//!   metadata instead.
+//!
+//! Fetching artifacts is left to the caller (see `Wll::verify_outcome_proofs`
+//! in `wll-sdk`).
+
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use wll_crypto::{CanonicalEncoder, Digester, HashAlgo, MerkleProof, Signature, VerifyingKey};
+use wll_types::{Coded, ErrorCode, ObjectId};
+
+use crate::records::{OutcomeReceipt, ProofRef};
+use crate::timestamp::ANCHOR_RECEIPT_KEY;
+
+/// Why a proof failed to verify.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ProofError {
+    #[error("proof artifact unavailable: {0}")]
+    Unavailable(String),
+
+    #[error("proof artifact does not match its digest")]
+    DigestMismatch,
+
+    #[error("malformed proof artifact: {0}")]
+    Malformed(String),
+
+    #[error("no verifier for proof type {0:?}")]
+    UnknownType(String),
+
+    #[error("proof is not about this outcome's commitment")]
+    WrongSubject,
+
+    #[error("untrusted proof: {0}")]
+    Untrusted(String),
+
+    #[error("invalid proof: {0}")]
+    Invalid(String),
+
+    /// The artifact was redacted, which its own receipt records.
+    #[error("proof artifact redacted: {0}")]
+    Redacted(String),
+}
+
+impl Coded for ProofError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::Unavailable(_) => ErrorCode::ProofUnavailable,
+            Self::DigestMismatch | Self::Malformed(_) | Self::WrongSubject | Self::Invalid(_) => {
+                ErrorCode::ProofInvalid
+            }
+            Self::UnknownType(_) => ErrorCode::ProofUnknownType,
+            Self::Untrusted(_) => ErrorCode::ProofUntrusted,
+            Self::Redacted(_) => ErrorCode::ObjectRedacted,
+        }
+    }
+}
+
+/// Checks one type of proof artifact.
+pub trait ProofVerifier: Send + Sync {
+    /// Value of the artifact's `type` field this verifier handles.
+    fn proof_type(&self) -> &str;
+
+    /// Check that `artifact` proves its claim about `outcome`'s commitment.
+    fn verify(&self, artifact: &Value, outcome: &OutcomeReceipt) -> Result<(), ProofError>;
+}
+
+/// Result of verifying one proof of an outcome.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProofCheck {
+    pub proof: ProofRef,
+    pub result: Result<(), ProofError>,
+}
+
+impl ProofCheck {
+    /// Returns `true` if the proof verified.
+    pub fn is_valid(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+/// Verifiers for the proof types a repository accepts.
+#[derive(Clone, Default)]
+pub struct ProofVerifiers {
+    verifiers: Vec<Arc<dyn ProofVerifier>>,
+}
+
+impl ProofVerifiers {
+    /// A registry with no verifiers; every proof fails as an unknown type.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handle artifacts of `verifier`'s type with it, replacing any earlier
+    /// verifier for the same type.
+    pub fn with_verifier(mut self, verifier: Arc<dyn ProofVerifier>) -> Self {
+        self.verifiers
+            .retain(|v| v.proof_type() != verifier.proof_type());
+        self.verifiers.push(verifier);
+        self
+    }
+
+    /// Proof types with a verifier.
+    pub fn proof_types(&self) -> Vec<&str> {
+        self.verifiers.iter().map(|v| v.proof_type()).collect()
+    }
+
+    /// Verify the artifact fetched for `proof` against `outcome`.
+    pub fn verify(
+        &self,
+        proof: &ProofRef,
+        artifact: &[u8],
+        outcome: &OutcomeReceipt,
+    ) -> Result<(), ProofError> {
+        if *blake3::hash(artifact).as_bytes() != proof.digest {
+            return Err(ProofError::DigestMismatch);
+        }
+        let value: Value =
+            serde_json::from_slice(artifact).map_err(|e| ProofError::Malformed(e.to_string()))?;
+        let proof_type = value
+            .get("type")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ProofError::Malformed("missing \"type\"".into()))?;
+        let verifier = self
+            .verifiers
+            .iter()
+            .find(|v| v.proof_type() == proof_type)
+            .ok_or_else(|| ProofError::UnknownType(proof_type.to_string()))?;
+        verifier.verify(&value, outcome)
+    }
+}
+
+impl std::fmt::Debug for ProofVerifiers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.proof_types()).finish()
+    }
+}
+
+fn parse<T: for<'de> Deserialize<'de>>(artifact: &Value) -> Result<T, ProofError> {
+    T::deserialize(artifact).map_err(|e| ProofError::Malformed(e.to_string()))
+}
+
+fn hex_array<const N: usize>(field: &str, text: &str) -> Result<[u8; N], ProofError> {
+    let bytes = hex::decode(text).map_err(|e| ProofError::Malformed(format!("{field}: {e}")))?;
+    bytes
+        .try_into()
+        .map_err(|_| ProofError::Malformed(format!("{field}: expected {N} bytes")))
+}
+
+// ---------------------------------------------------------------------------
+// Merkle inclusion
+// ---------------------------------------------------------------------------
+
+/// `merkle-inclusion` proofs: `{"type", "proof"}` where `proof` is a
+/// [`MerkleProof`] whose leaf is the commitment receipt hash and whose root
+/// is one of the anchored roots (e.g. a batch root published elsewhere).
+#[derive(Clone, Debug, Default)]
+pub struct MerkleInclusionVerifier {
+    roots: HashSet<ObjectId>,
+}
+
+#[derive(Deserialize)]
+struct MerkleArtifact {
+    proof: MerkleProof,
+}
+
+impl MerkleInclusionVerifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accept proofs against `root`.
+    pub fn with_anchored_root(mut self, root: ObjectId) -> Self {
+        self.roots.insert(root);
+        self
+    }
+}
+
+impl ProofVerifier for MerkleInclusionVerifier {
+    fn proof_type(&self) -> &str {
+        "merkle-inclusion"
+    }
+
+    fn verify(&self, artifact: &Value, outcome: &OutcomeReceipt) -> Result<(), ProofError> {
+        let MerkleArtifact { proof } = parse(artifact)?;
+        if *proof.leaf.as_bytes() != outcome.commitment_receipt_hash {
+            return Err(ProofError::WrongSubject);
+        }
+        if !self.roots.contains(&proof.root) {
+            return Err(ProofError::Untrusted(format!(
+                "root {} is not anchored",
+                proof.root.short_hex()
+            )));
+        }
+        if !proof.verify() {
+            return Err(ProofError::Invalid("path does not lead to the root".into()));
+        }
+        Ok(())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Signed statements
+// ---------------------------------------------------------------------------
+
+/// `ed25519-statement` proofs: `{"type", "key", "subject", "statement",
+/// "signature"}` with hex-encoded key, subject and signature. `subject` is
+/// the commitment receipt hash and the signature covers
+/// [`statement_message`].
+#[derive(Clone, Debug, Default)]
+pub struct SignedStatementVerifier {
+    keys: Vec<VerifyingKey>,
+}
+
+#[derive(Deserialize)]
+struct StatementArtifact {
+    key: String,
+    subject: String,
+    statement: String,
+    signature: String,
+}
+
+/// Bytes an `ed25519-statement` signature covers.
+pub fn statement_message(subject: &[u8; 32], statement: &str) -> Vec<u8> {
+    let mut enc = CanonicalEncoder::new();
+    enc.put_str("wll-proof-statement-v1")
+        .put_fixed(subject)
+        .put_str(statement);
+    enc.finish()
+}
+
+impl SignedStatementVerifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accept statements signed by `key`.
+    pub fn with_trusted_key(mut self, key: VerifyingKey) -> Self {
+        self.keys.push(key);
+        self
+    }
+}
+
+impl ProofVerifier for SignedStatementVerifier {
+    fn proof_type(&self) -> &str {
+        "ed25519-statement"
+    }
+
+    fn verify(&self, artifact: &Value, outcome: &OutcomeReceipt) -> Result<(), ProofError> {
+        let statement: StatementArtifact = parse(artifact)?;
+        let subject = hex_array::<32>("subject", &statement.subject)?;
+        if subject != outcome.commitment_receipt_hash {
+            return Err(ProofError::WrongSubject);
+        }
+        let key = hex_array::<32>("key", &statement.key)?;
+        let key = self
+            .keys
+            .iter()
+            .find(|k| k.as_bytes() == key)
+            .ok_or_else(|| ProofError::Untrusted(format!("key {}", statement.key)))?;
+        let signature = Signature::from_bytes(hex_array("signature", &statement.signature)?);
+        key.verify(
+            &statement_message(&subject, &statement.statement),
+            &signature,
+        )
+        .map_err(|e| ProofError::Invalid(e.to_string()))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Transparency logs
+// ---------------------------------------------------------------------------
+
+/// `transparency-log` proofs: `{"type"}` plus the fields of a
+/// [`LogInclusion`]. The log entry is the commitment receipt hash, or the
+/// receipt named by the outcome's `anchor.receipt` metadata when the
+/// outcome records a publication of an earlier receipt.
+#[derive(Clone, Debug, Default)]
+pub struct TransparencyLogVerifier {
+    logs: BTreeMap<String, VerifyingKey>,
+}
+
+/// Proof that an entry is at `index` in a log: its RFC 6962 audit path
+/// (hex SHA-256 nodes) to `root`, and the log's Ed25519 signature over
+/// [`checkpoint_message`] for that root.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogInclusion {
+    pub log: String,
+    pub index: u64,
+    pub tree_size: u64,
+    pub inclusion: Vec<String>,
+    pub root: String,
+    pub signature: String,
+}
+
+impl LogInclusion {
+    /// Check that `entry` is included under a checkpoint `key` signed.
+    pub fn verify(&self, key: &VerifyingKey, entry: &[u8]) -> Result<(), ProofError> {
+        let root = hex_array::<32>("root", &self.root)?;
+        let signature = Signature::from_bytes(hex_array("signature", &self.signature)?);
+        key.verify(
+            &checkpoint_message(&self.log, self.tree_size, &root),
+            &signature,
+        )
+        .map_err(|_| ProofError::Invalid("checkpoint signature does not verify".into()))?;
+
+        let path = self
+            .inclusion
+            .iter()
+            .map(|node| hex_array::<32>("inclusion", node))
+            .collect::<Result<Vec<_>, _>>()?;
+        match log_inclusion_root(log_leaf_hash(entry), self.index, self.tree_size, &path) {
+            Some(computed) if computed == root => Ok(()),
+            _ => Err(ProofError::Invalid(format!(
+                "entry {} is not included in the checkpoint",
+                self.index
+            ))),
+        }
+    }
+}
+
+/// Bytes a log signs to commit to its tree of `tree_size` entries.
+pub fn checkpoint_message(log: &str, tree_size: u64, root: &[u8; 32]) -> Vec<u8> {
+    let mut enc = CanonicalEncoder::new();
+    enc.put_str("wll-log-checkpoint-v1")
+        .put_str(log)
+        .put_u64(tree_size)
+        .put_fixed(root);
+    enc.finish()
+}
+
+/// RFC 6962 hash of a log entry.
+pub fn log_leaf_hash(entry: &[u8]) -> [u8; 32] {
+    let mut digester = Digester::new(HashAlgo::Sha256);
+    digester.update(&[0]).update(entry);
+    digester.finalize()
+}
+
+/// RFC 6962 hash of an interior node.
+pub fn log_node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut digester = Digester::new(HashAlgo::Sha256);
+    digester.update(&[1]).update(left).update(right);
+    digester.finalize()
+}
+
+/// Root of the RFC 6962 tree over `entries` and the audit path of
+/// `entries[index]`, for logs small enough to rebuild per request.
+///
+/// # Panics
+///
+/// If `index` is out of range.
+pub fn log_tree_proof(entries: &[[u8; 32]], index: usize) -> ([u8; 32], Vec<[u8; 32]>) {
+    assert!(index < entries.len(), "log index out of range");
+    if entries.len() == 1 {
+        return (log_leaf_hash(&entries[0]), Vec::new());
+    }
+    let split = entries.len().next_power_of_two() / 2;
+    let (left, right) = entries.split_at(split);
+    let (left_root, left_path) = log_tree_proof(left, index.min(split - 1));
+    let (right_root, right_path) =
+        log_tree_proof(right, index.saturating_sub(split).min(right.len() - 1));
+    let mut path = if index < split { left_path } else { right_path };
+    path.push(if index < split { right_root } else { left_root });
+    (log_node_hash(&left_root, &right_root), path)
+}
+
+/// Recompute the root from an RFC 6962 inclusion proof (RFC 9162 §2.1.3.2).
+pub fn log_inclusion_root(
+    leaf_hash: [u8; 32],
+    index: u64,
+    tree_size: u64,
+    path: &[[u8; 32]],
+) -> Option<[u8; 32]> {
+    if index >= tree_size {
+        return None;
+    }
+    let (mut fnode, mut snode) = (index, tree_size - 1);
+    let mut root = leaf_hash;
+    for sibling in path {
+        if snode == 0 {
+            return None;
+        }
+        if fnode & 1 == 1 || fnode == snode {
+            root = log_node_hash(sibling, &root);
+            while fnode & 1 == 0 && fnode != 0 {
+                fnode >>= 1;
+                snode >>= 1;
+            }
+        } else {
+            root = log_node_hash(&root, sibling);
+        }
+        fnode >>= 1;
+        snode >>= 1;
+    }
+    (snode == 0).then_some(root)
+}
+
+impl TransparencyLogVerifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accept entries of the log named `log`, whose checkpoints `key` signs.
+    pub fn with_log(mut self, log: impl Into<String>, key: VerifyingKey) -> Self {
+        self.logs.insert(log.into(), key);
+        self
+    }
+}
+
+impl ProofVerifier for TransparencyLogVerifier {
+    fn proof_type(&self) -> &str {
+        "transparency-log"
+    }
+
+    fn verify(&self, artifact: &Value, outcome: &OutcomeReceipt) -> Result<(), ProofError> {
+        let inclusion: LogInclusion = parse(artifact)?;
+        let key = self
+            .logs
+            .get(&inclusion.log)
+            .ok_or_else(|| ProofError::Untrusted(format!("log {:?}", inclusion.log)))?;
+        let entry = match outcome.metadata.get(ANCHOR_RECEIPT_KEY) {
+            Some(anchored) => hex_array::<32>(ANCHOR_RECEIPT_KEY, anchored)?,
+            None => outcome.commitment_receipt_hash,
+        };
+        inclusion.verify(key, &entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wll_crypto::{MerkleTree, SigningKey};
+    use wll_types::identity::IdentityMaterial;
+    use wll_types::{TemporalAnchor, WorldlineId};
+
+    use super::*;
+
+    fn outcome(commitment: [u8; 32]) -> OutcomeReceipt {
+        OutcomeReceipt {
+            worldline: WorldlineId::derive(&IdentityMaterial::GenesisHash([1; 32])),
+            seq: 2,
+            receipt_hash: [2; 32],
+            prev_hash: Some(commitment),
+            timestamp: TemporalAnchor::new(1, 0, 0),
+            commitment_receipt_hash: commitment,
+            outcome_hash: [3; 32],
+            accepted: true,
+            effects: vec![],
+            proofs: vec![],
+            state_updates: vec![],
+            metadata: BTreeMap::new(),
+            schemas: vec![],
+        }
+    }
+
+    fn check(
+        verifiers: &ProofVerifiers,
+        artifact: &Value,
+        outcome: &OutcomeReceipt,
+    ) -> Result<(), ProofError> {
+        let bytes = serde_json::to_vec(artifact).unwrap();
+        let proof = ProofRef {
+            uri: "obj://proof".into(),
+            digest: *blake3::hash(&bytes).as_bytes(),
+        };
+        verifiers.verify(&proof, &bytes, outcome)
+    }
+
+    #[test]
+    fn registry_checks_digest_and_dispatches_on_type() {
+        let verifiers = ProofVerifiers::new();
+        let outcome = outcome([7; 32]);
+        let artifact = serde_json::json!({"type": "custom"});
+        assert_eq!(
+            check(&verifiers, &artifact, &outcome),
+            Err(ProofError::UnknownType("custom".into()))
+        );
+
+        let proof = ProofRef {
+            uri: "obj://proof".into(),
+            digest: [0; 32],
+        };
+        assert_eq!(
+            verifiers.verify(&proof, b"{}", &outcome),
+            Err(ProofError::DigestMismatch)
+        );
+    }
+
+    #[test]
+    fn merkle_inclusion_against_anchored_root() {
+        let commitment = [7; 32];
+        let tree = MerkleTree::from_leaves(vec![
+            ObjectId::from_bytes(b"a"),
+            ObjectId::from_hash(commitment),
+            ObjectId::from_bytes(b"c"),
+        ]);
+        let artifact = serde_json::json!({
+            "type": "merkle-inclusion",
+            "proof": tree.proof(1).unwrap(),
+        });
+
+        let unanchored =
+            ProofVerifiers::new().with_verifier(Arc::new(MerkleInclusionVerifier::new()));
+        assert!(matches!(
+            check(&unanchored, &artifact, &outcome(commitment)),
+            Err(ProofError::Untrusted(_))
+        ));
+
+        let verifiers = ProofVerifiers::new().with_verifier(Arc::new(
+            MerkleInclusionVerifier::new().with_anchored_root(tree.root()),
+        ));
+        assert_eq!(check(&verifiers, &artifact, &outcome(commitment)), Ok(()));
+        assert_eq!(
+            check(&verifiers, &artifact, &outcome([8; 32])),
+            Err(ProofError::WrongSubject)
+        );
+    }
+
+    #[test]
+    fn signed_statement_requires_trusted_key() {
+        let commitment = [7; 32];
+        let signer = SigningKey::from_bytes([5; 32]);
+        let signature = signer.sign(&statement_message(&commitment, "deployed to prod"));
+        let artifact = serde_json::json!({
+            "type": "ed25519-statement",
+            "key": hex::encode(signer.verifying_key().as_bytes()),
+            "subject": hex::encode(commitment),
+            "statement": "deployed to prod",
+            "signature": hex::encode(signature.to_bytes()),
+        });
+
+        let verifiers = ProofVerifiers::new().with_verifier(Arc::new(
+            SignedStatementVerifier::new().with_trusted_key(signer.verifying_key()),
+        ));
+        assert_eq!(check(&verifiers, &artifact, &outcome(commitment)), Ok(()));
+
+        let mut forged = artifact.clone();
+        forged["statement"] = "deployed to staging".into();
+        assert!(matches!(
+            check(&verifiers, &forged, &outcome(commitment)),
+            Err(ProofError::Invalid(_))
+        ));
+
+        let stranger = ProofVerifiers::new().with_verifier(Arc::new(
+            SignedStatementVerifier::new()
+                .with_trusted_key(SigningKey::from_bytes([6; 32]).verifying_key()),
+        ));
+        assert!(matches!(
+            check(&stranger, &artifact, &outcome(commitment)),
+            Err(ProofError::Untrusted(_))
+        ));
+    }
+
+    #[test]
+    fn transparency_log_inclusion_under_signed_checkpoint() {
+        let commitment = [7; 32];
+        let entries = [[1; 32], [2; 32], [3; 32], commitment, [5; 32]];
+        let log_key = SigningKey::from_bytes([9; 32]);
+        let verifiers = ProofVerifiers::new().with_verifier(Arc::new(
+            TransparencyLogVerifier::new().with_log("rekor", log_key.verifying_key()),
+        ));
+
+        for index in 0..entries.len() {
+            let (root, path) = log_tree_proof(&entries, index);
+            assert_eq!(
+                log_inclusion_root(log_leaf_hash(&entries[index]), index as u64, 5, &path),
+                Some(root)
+            );
+        }
+
+        let (root, path) = log_tree_proof(&entries, 3);
+        let artifact = |index: u64| {
+            serde_json::json!({
+                "type": "transparency-log",
+                "log": "rekor",
+                "index": index,
+                "tree_size": 5,
+                "inclusion": path.iter().map(hex::encode).collect::<Vec<_>>(),
+                "root": hex::encode(root),
+                "signature": hex::encode(log_key.sign(&checkpoint_message("rekor", 5, &root)).to_bytes()),
+            })
+        };
+        assert_eq!(
+            check(&verifiers, &artifact(3), &outcome(commitment)),
+            Ok(())
+        );
+        assert!(matches!(
+            check(&verifiers, &artifact(2), &outcome(commitment)),
+            Err(ProofError::Invalid(_))
+        ));
+
+        let mut publication = outcome([8; 32]);
+        publication
+            .metadata
+            .insert(ANCHOR_RECEIPT_KEY.into(), hex::encode(commitment));
+        assert_eq!(check(&verifiers, &artifact(3), &publication), Ok(()));
+
+        let mut resigned = artifact(3);
+        resigned["signature"] = hex::encode([0; 64]).into();
+        assert!(matches!(
+            check(&verifiers, &resigned, &outcome(commitment)),
+            Err(ProofError::Invalid(_))
+        ));
+    }
+}
@@ -0,0 +1,1154 @@
+//! A ledger replicated through raft (`raft` feature).
+//!
+//! A [`RaftLedger`] wraps a local ledger, its state machine, and appends to
+//! it through a raft log kept by every node of a cluster, usually three or
+//! five. Only the leader takes writes. It runs each append on a scratch
+//! copy of its ledger's tip ([`Stage`]) to learn the receipt, adds the
+//! receipt to the raft log, and answers once a majority of nodes hold the
+//! entry on disk and it has applied it. Every node, the leader included,
+//! imports the receipts of committed entries only, so every node's ledger
+//! holds the same receipts under the same hashes and reads never see an
+//! entry the cluster may yet drop. A write sent to a follower fails with
+//! [`LedgerError::NotLeader`], naming the leader when known.
+//!
+//! Nodes reach each other through a [`RaftTransport`], and answer through
+//! [`RaftService`]; `wll-server` carries both over HTTP. Reads are served
+//! from the local ledger and may trail the leader. State schemas are
+//! registered on each node, and compaction, which signs with a key a single
+//! node holds, is refused.
+//!
+//! A leader cut off from the majority fails its pending writes; once it
+//! hears from the next leader it drops the entries that leader overwrote
+//! and catches up like any follower. An entry that cannot be applied marks
+//! the node diverged, and it fails every call with
+//! [`LedgerError::Replication`] from then on.
+
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::hash::{BuildHasher, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use wll_crypto::SigningKey;
+use wll_types::WorldlineId;
+
+use crate::compaction::{Compaction, CompactionReceipt};
+use crate::error::LedgerError;
+use crate::file::{open_append, read_lines, FileLedger};
+use crate::memory::InMemoryLedger;
+use crate::records::{
+    CommitmentProposal, CommitmentReceipt, Decision, OutcomeReceipt, OutcomeRecord, Receipt,
+    ReceiptRef, SnapshotInput, SnapshotReceipt,
+};
+use crate::schema::{SchemaRegistry, StateSchema};
+use crate::traits::{Ledger, LedgerReader, LedgerWriter};
+
+/// Name of a node, unique in its cluster.
+pub type NodeId = String;
+
+/// Timing of a [`RaftLedger`].
+#[derive(Clone, Debug)]
+pub struct RaftConfig {
+    /// How often the leader sends entries, or empty heartbeats.
+    pub heartbeat: Duration,
+    /// How long a follower waits to hear from a leader before standing for
+    /// election. Each wait is drawn from one to two times this.
+    pub election_timeout: Duration,
+    /// How long a write waits for a majority before failing.
+    pub commit_timeout: Duration,
+    /// Most entries sent to a follower in one request.
+    pub max_batch: usize,
+}
+
+impl Default for RaftConfig {
+    fn default() -> Self {
+        Self {
+            heartbeat: Duration::from_millis(100),
+            election_timeout: Duration::from_secs(1),
+            commit_timeout: Duration::from_secs(5),
+            max_batch: 256,
+        }
+    }
+}
+
+/// What a committed entry does to the ledger.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Command {
+    /// Appended by each new leader, to commit the entries before it.
+    Noop,
+    /// Import this receipt.
+    Append(Box<Receipt>),
+    /// Archive `worldline` through `through_seq`.
+    Archive { worldline: WorldlineId, through_seq: u64 },
+}
+
+/// One raft log entry. Indexes start at 1.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    pub term: u64,
+    pub index: u64,
+    pub command: Command,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppendEntries {
+    pub term: u64,
+    pub leader: NodeId,
+    /// The entry just before `entries`, which the follower must hold.
+    pub prev_index: u64,
+    pub prev_term: u64,
+    pub entries: Vec<Entry>,
+    pub leader_commit: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppendEntriesReply {
+    pub term: u64,
+    pub success: bool,
+    /// On success, the follower's last entry matching the leader's. On
+    /// failure, the last index the leader should try next.
+    pub match_index: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VoteRequest {
+    pub term: u64,
+    pub candidate: NodeId,
+    pub last_index: u64,
+    pub last_term: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VoteReply {
+    pub term: u64,
+    pub granted: bool,
+}
+
+/// Sends raft requests to the other nodes.
+pub trait RaftTransport: Send + Sync {
+    fn append_entries(
+        &self,
+        peer: &str,
+        request: &AppendEntries,
+    ) -> Result<AppendEntriesReply, LedgerError>;
+
+    fn request_vote(&self, peer: &str, request: &VoteRequest) -> Result<VoteReply, LedgerError>;
+}
+
+/// Answers raft requests from the other nodes.
+pub trait RaftService: Send + Sync {
+    fn append_entries(&self, request: &AppendEntries) -> Result<AppendEntriesReply, LedgerError>;
+
+    fn request_vote(&self, request: &VoteRequest) -> Result<VoteReply, LedgerError>;
+
+    fn status(&self) -> RaftStatus;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Follower,
+    Candidate,
+    Leader,
+}
+
+/// A node's view of the cluster.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RaftStatus {
+    pub node: NodeId,
+    pub role: Role,
+    pub term: u64,
+    pub leader: Option<NodeId>,
+    pub last_index: u64,
+    pub commit_index: u64,
+    pub applied_index: u64,
+    /// Set once a committed entry could not be applied to the local ledger.
+    pub diverged: bool,
+}
+
+/// The vote and term a node must remember across restarts.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct HardState {
+    term: u64,
+    voted_for: Option<NodeId>,
+}
+
+/// Raft log and hard state, on disk when given a directory.
+struct RaftLog {
+    dir: Option<PathBuf>,
+    hard: HardState,
+    entries: Vec<Entry>,
+    journal: Option<File>,
+}
+
+impl RaftLog {
+    fn in_memory() -> Self {
+        Self { dir: None, hard: HardState::default(), entries: Vec::new(), journal: None }
+    }
+
+    /// Open the `hard.json` and `log.jsonl` in `dir`, dropping a torn last
+    /// entry.
+    fn open(dir: &Path) -> Result<Self, LedgerError> {
+        fs::create_dir_all(dir)?;
+        let hard = match fs::read(dir.join("hard.json")) {
+            Ok(json) => serde_json::from_slice(&json)
+                .map_err(|e| LedgerError::Serialization(format!("raft hard state: {e}")))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HardState::default(),
+            Err(e) => return Err(e.into()),
+        };
+        let path = dir.join("log.jsonl");
+        let mut entries = Vec::new();
+        let valid_len = read_lines(&path, |entry: Entry| {
+            entries.push(entry);
+            Ok(())
+        })?;
+        let journal = open_append(&path, valid_len)?;
+        Ok(Self { dir: Some(dir.to_path_buf()), hard, entries, journal: Some(journal) })
+    }
+
+    fn last_index(&self) -> u64 {
+        self.entries.len() as u64
+    }
+
+    fn last_term(&self) -> u64 {
+        self.entries.last().map_or(0, |entry| entry.term)
+    }
+
+    /// Term of the entry at `index`; index 0 is before the first entry.
+    fn term_at(&self, index: u64) -> Option<u64> {
+        match index {
+            0 => Some(0),
+            index => self.entry(index).map(|entry| entry.term),
+        }
+    }
+
+    fn entry(&self, index: u64) -> Option<&Entry> {
+        index.checked_sub(1).and_then(|i| self.entries.get(i as usize))
+    }
+
+    fn save_hard(&mut self, hard: HardState) -> Result<(), LedgerError> {
+        if let Some(dir) = &self.dir {
+            let json = serde_json::to_vec(&hard)
+                .map_err(|e| LedgerError::Serialization(e.to_string()))?;
+            let tmp = dir.join("hard.json.tmp");
+            let mut file = File::create(&tmp)?;
+            file.write_all(&json)?;
+            file.sync_all()?;
+            fs::rename(&tmp, dir.join("hard.json"))?;
+        }
+        self.hard = hard;
+        Ok(())
+    }
+
+    fn append(&mut self, entries: Vec<Entry>) -> Result<(), LedgerError> {
+        if let Some(journal) = &mut self.journal {
+            let mut lines = Vec::new();
+            for entry in &entries {
+                serde_json::to_writer(&mut lines, entry)
+                    .map_err(|e| LedgerError::Serialization(e.to_string()))?;
+                lines.push(b'\n');
+            }
+            journal.write_all(&lines)?;
+            journal.sync_data()?;
+        }
+        self.entries.extend(entries);
+        Ok(())
+    }
+
+    /// Drop the entries from `index` on, returning them.
+    fn truncate_from(&mut self, index: u64) -> Result<Vec<Entry>, LedgerError> {
+        let removed = self.entries.split_off(index.saturating_sub(1) as usize);
+        if let Some(dir) = &self.dir {
+            let path = dir.join("log.jsonl");
+            let tmp = dir.join("log.jsonl.tmp");
+            let mut file = File::create(&tmp)?;
+            for entry in &self.entries {
+                let mut line = serde_json::to_vec(entry)
+                    .map_err(|e| LedgerError::Serialization(e.to_string()))?;
+                line.push(b'\n');
+                file.write_all(&line)?;
+            }
+            file.sync_all()?;
+            fs::rename(&tmp, &path)?;
+            self.journal = Some(open_append(&path, fs::metadata(&path)?.len())?);
+        }
+        Ok(removed)
+    }
+}
+
+/// Volatile raft state, behind the node lock.
+struct Node {
+    role: Role,
+    leader: Option<NodeId>,
+    log: RaftLog,
+    commit: u64,
+    applied: u64,
+    /// Index of this leader's first entry of its term.
+    term_start: u64,
+    next_index: HashMap<NodeId, u64>,
+    match_index: HashMap<NodeId, u64>,
+    election_deadline: Instant,
+    diverged: bool,
+}
+
+impl Node {
+    fn term(&self) -> u64 {
+        self.log.hard.term
+    }
+
+    /// Follow whoever is in `term`, if it is newer than ours.
+    fn observe_term(&mut self, term: u64) -> Result<(), LedgerError> {
+        if term > self.term() {
+            self.log.save_hard(HardState { term, voted_for: None })?;
+            self.role = Role::Follower;
+            self.leader = None;
+        }
+        Ok(())
+    }
+}
+
+/// A ledger a [`RaftLedger`] leader can work out its next receipts on
+/// without recording them.
+pub trait Stage: Ledger {
+    /// A scratch copy that appends as this ledger does, holding every
+    /// schema and the receipts of `worldline` from `from_seq` on.
+    fn stage(&self, worldline: &WorldlineId, from_seq: u64)
+        -> Result<InMemoryLedger, LedgerError>;
+}
+
+impl Stage for InMemoryLedger {
+    fn stage(
+        &self,
+        worldline: &WorldlineId,
+        from_seq: u64,
+    ) -> Result<InMemoryLedger, LedgerError> {
+        InMemoryLedger::stage(self, worldline, from_seq)
+    }
+}
+
+impl Stage for FileLedger {
+    fn stage(
+        &self,
+        worldline: &WorldlineId,
+        from_seq: u64,
+    ) -> Result<InMemoryLedger, LedgerError> {
+        FileLedger::stage(self, worldline, from_seq)
+    }
+}
+
+/// A ledger whose appends are committed through raft before they return.
+pub struct RaftLedger<L> {
+    id: NodeId,
+    peers: Vec<NodeId>,
+    config: RaftConfig,
+    transport: Arc<dyn RaftTransport>,
+    machine: L,
+    node: Mutex<Node>,
+    /// Serializes writes on the leader.
+    writes: Mutex<()>,
+}
+
+impl<L: Stage> RaftLedger<L> {
+    /// Node `id` of a cluster with `peers`, keeping its raft log in `dir`
+    /// and applying committed entries to `machine`.
+    pub fn open(
+        id: impl Into<NodeId>,
+        peers: Vec<NodeId>,
+        machine: L,
+        transport: Arc<dyn RaftTransport>,
+        dir: &Path,
+    ) -> Result<Self, LedgerError> {
+        Ok(Self::with_log(id.into(), peers, machine, transport, RaftLog::open(dir)?))
+    }
+
+    /// A node whose raft log is lost when it stops, for tests.
+    pub fn in_memory(
+        id: impl Into<NodeId>,
+        peers: Vec<NodeId>,
+        machine: L,
+        transport: Arc<dyn RaftTransport>,
+    ) -> Self {
+        Self::with_log(id.into(), peers, machine, transport, RaftLog::in_memory())
+    }
+
+    fn with_log(
+        id: NodeId,
+        peers: Vec<NodeId>,
+        machine: L,
+        transport: Arc<dyn RaftTransport>,
+        log: RaftLog,
+    ) -> Self {
+        let config = RaftConfig::default();
+        let node = Node {
+            role: Role::Follower,
+            leader: None,
+            log,
+            commit: 0,
+            applied: 0,
+            term_start: 0,
+            next_index: HashMap::new(),
+            match_index: HashMap::new(),
+            election_deadline: Instant::now() + election_wait(&config),
+            diverged: false,
+        };
+        let peers = peers.into_iter().filter(|peer| *peer != id).collect();
+        Self {
+            id,
+            peers,
+            config,
+            transport,
+            machine,
+            node: Mutex::new(node),
+            writes: Mutex::new(()),
+        }
+    }
+
+    pub fn with_config(mut self, config: RaftConfig) -> Self {
+        let deadline = Instant::now() + election_wait(&config);
+        self.node.get_mut().unwrap_or_else(|e| e.into_inner()).election_deadline = deadline;
+        self.config = config;
+        self
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The local ledger committed entries are applied to.
+    pub fn machine(&self) -> &L {
+        &self.machine
+    }
+
+    fn quorum(&self) -> usize {
+        let members = self.peers.len() + 1;
+        members / 2 + 1
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Node> {
+        self.node.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Send heartbeats if leading, or stand for election once the leader
+    /// has been silent too long. [`start`](Self::start) calls this every
+    /// heartbeat.
+    pub fn tick(&self) {
+        let (role, deadline) = {
+            let node = self.lock();
+            (node.role, node.election_deadline)
+        };
+        if role == Role::Leader {
+            self.replicate();
+        } else if Instant::now() >= deadline {
+            self.stand_for_election();
+        }
+    }
+
+    /// Tick on a background thread until the returned driver is dropped.
+    pub fn start(self: &Arc<Self>) -> RaftDriver
+    where
+        L: 'static,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+        let ledger: Weak<Self> = Arc::downgrade(self);
+        let heartbeat = self.config.heartbeat;
+        let stopping = stop.clone();
+        let thread = std::thread::spawn(move || {
+            while !stopping.load(Ordering::Acquire) {
+                match ledger.upgrade() {
+                    Some(ledger) => ledger.tick(),
+                    None => return,
+                }
+                std::thread::sleep(heartbeat);
+            }
+        });
+        RaftDriver { stop, thread: Some(thread) }
+    }
+
+    fn stand_for_election(&self) {
+        let request = {
+            let mut node = self.lock();
+            let term = node.term() + 1;
+            let hard = HardState { term, voted_for: Some(self.id.clone()) };
+            if let Err(e) = node.log.save_hard(hard) {
+                tracing::warn!("raft node {} cannot stand for election: {e}", self.id);
+                return;
+            }
+            node.role = Role::Candidate;
+            node.leader = None;
+            node.election_deadline = Instant::now() + election_wait(&self.config);
+            VoteRequest {
+                term,
+                candidate: self.id.clone(),
+                last_index: node.log.last_index(),
+                last_term: node.log.last_term(),
+            }
+        };
+        tracing::debug!("raft node {} stands for election in term {}", self.id, request.term);
+        let replies = self.broadcast(|peer| self.transport.request_vote(peer, &request));
+
+        let mut node = self.lock();
+        let mut votes = 1;
+        for reply in replies.into_iter().flatten() {
+            if reply.term > node.term() {
+                if let Err(e) = node.observe_term(reply.term) {
+                    tracing::warn!("raft node {}: {e}", self.id);
+                }
+                return;
+            }
+            votes += usize::from(reply.granted);
+        }
+        if node.role != Role::Candidate || node.term() != request.term || votes < self.quorum() {
+            return;
+        }
+        let index = node.log.last_index() + 1;
+        let noop = Entry { term: request.term, index, command: Command::Noop };
+        if let Err(e) = node.log.append(vec![noop]) {
+            tracing::warn!("raft node {} cannot start its term: {e}", self.id);
+            return;
+        }
+        tracing::info!("raft node {} leads term {}", self.id, request.term);
+        node.role = Role::Leader;
+        node.leader = Some(self.id.clone());
+        node.term_start = index;
+        node.next_index = self.peers.iter().map(|peer| (peer.clone(), index)).collect();
+        node.match_index = self.peers.iter().map(|peer| (peer.clone(), 0)).collect();
+        drop(node);
+        self.replicate();
+    }
+
+    /// Send each follower the entries it lacks, then commit what a
+    /// majority holds. Returns whether this node still leads.
+    fn replicate(&self) -> bool {
+        let (term, requests) = {
+            let node = self.lock();
+            if node.role != Role::Leader {
+                return false;
+            }
+            let requests: HashMap<NodeId, AppendEntries> = self
+                .peers
+                .iter()
+                .map(|peer| {
+                    let next = node.next_index.get(peer).copied().unwrap_or(1).max(1);
+                    let prev_index = next - 1;
+                    let entries = node
+                        .log
+                        .entries
+                        .iter()
+                        .skip(prev_index as usize)
+                        .take(self.config.max_batch)
+                        .cloned()
+                        .collect();
+                    let request = AppendEntries {
+                        term: node.term(),
+                        leader: self.id.clone(),
+                        prev_index,
+                        prev_term: node.log.term_at(prev_index).unwrap_or(0),
+                        entries,
+                        leader_commit: node.commit,
+                    };
+                    (peer.clone(), request)
+                })
+                .collect();
+            (node.term(), requests)
+        };
+        let replies = self.broadcast(|peer| self.transport.append_entries(peer, &requests[peer]));
+
+        let mut node = self.lock();
+        if node.role != Role::Leader || node.term() != term {
+            return false;
+        }
+        for (peer, reply) in self.peers.iter().zip(replies) {
+            let reply = match reply {
+                Ok(reply) => reply,
+                Err(e) => {
+                    tracing::debug!("raft node {} cannot reach {peer}: {e}", self.id);
+                    continue;
+                }
+            };
+            if reply.term > term {
+                if let Err(e) = node.observe_term(reply.term) {
+                    tracing::warn!("raft node {}: {e}", self.id);
+                }
+                return false;
+            }
+            if reply.success {
+                let matched = node.match_index.entry(peer.clone()).or_default();
+                *matched = (*matched).max(reply.match_index);
+                let next = *matched + 1;
+                node.next_index.insert(peer.clone(), next);
+            } else {
+                let next = node.next_index.get(peer).copied().unwrap_or(1);
+                node.next_index.insert(peer.clone(), (reply.match_index + 1).min(next - 1).max(1));
+            }
+        }
+        // Only entries of the current term are committed by counting.
+        let last = node.log.last_index();
+        for index in (node.commit + 1..=last).rev() {
+            if node.log.term_at(index) != Some(term) {
+                break;
+            }
+            let holders = 1 + node.match_index.values().filter(|&&m| m >= index).count();
+            if holders >= self.quorum() {
+                node.commit = index;
+                break;
+            }
+        }
+        self.apply_committed(&mut node);
+        true
+    }
+
+    /// Call `send` for every peer at once, returning replies in peer order.
+    fn broadcast<T: Send>(
+        &self,
+        send: impl Fn(&str) -> Result<T, LedgerError> + Sync,
+    ) -> Vec<Result<T, LedgerError>> {
+        std::thread::scope(|scope| {
+            let calls: Vec<_> =
+                self.peers.iter().map(|peer| scope.spawn(|| send(peer))).collect();
+            calls
+                .into_iter()
+                .map(|call| {
+                    call.join().unwrap_or_else(|_| {
+                        Err(LedgerError::Replication("raft request panicked".into()))
+                    })
+                })
+                .collect()
+        })
+    }
+
+    /// Apply committed entries the local ledger does not hold yet.
+    /// Entries are skipped when the ledger already has their effect, as
+    /// after a restart.
+    fn apply_committed(&self, node: &mut Node) {
+        while node.applied < node.commit && !node.diverged {
+            let index = node.applied + 1;
+            let Some(entry) = node.log.entry(index) else { break };
+            let applied = match &entry.command {
+                Command::Noop => Ok(()),
+                Command::Append(receipt) => match self.machine.get_by_hash(receipt.receipt_hash())
+                {
+                    Ok(Some(_)) => Ok(()),
+                    Ok(None) => self.machine.import_receipt(Receipt::clone(receipt)),
+                    Err(e) => Err(e),
+                },
+                Command::Archive { worldline, through_seq } => {
+                    match self.machine.archived_through(worldline) {
+                        Ok(Some(base)) if base.seq >= *through_seq => Ok(()),
+                        Ok(_) => self.machine.archive(worldline, *through_seq).map(drop),
+                        Err(e) => Err(e),
+                    }
+                }
+            };
+            if let Err(e) = applied {
+                tracing::error!("raft node {} cannot apply entry {index}: {e}", self.id);
+                node.diverged = true;
+                return;
+            }
+            node.applied = index;
+        }
+    }
+
+    fn check_healthy(&self, node: &Node) -> Result<(), LedgerError> {
+        if node.diverged {
+            return Err(LedgerError::Replication(format!(
+                "node {} could not apply a committed entry; rebuild its ledger",
+                self.id
+            )));
+        }
+        Ok(())
+    }
+
+    /// Work out a command with `stage` as the leader, without writing the
+    /// local ledger, log it, and wait until a majority holds it and it is
+    /// applied.
+    fn propose<T>(
+        &self,
+        stage: impl FnOnce(&L) -> Result<(T, Command), LedgerError>,
+    ) -> Result<T, LedgerError> {
+        let _writing = self.writes.lock().unwrap_or_else(|e| e.into_inner());
+        let deadline = Instant::now() + self.config.commit_timeout;
+        // A new leader first commits the entries of earlier terms, so its
+        // ledger holds every receipt before it appends another.
+        let mut node = loop {
+            let node = self.lock();
+            self.check_healthy(&node)?;
+            if node.role != Role::Leader {
+                return Err(LedgerError::NotLeader { leader: node.leader.clone() });
+            }
+            if node.commit >= node.term_start && node.applied == node.log.last_index() {
+                break node;
+            }
+            drop(node);
+            self.wait_for_round(deadline)?;
+        };
+        let (value, command) = stage(&self.machine)?;
+        let index = node.log.last_index() + 1;
+        let entry = Entry { term: node.term(), index, command };
+        node.log.append(vec![entry])?;
+        drop(node);
+
+        loop {
+            let node = self.lock();
+            if node.applied >= index {
+                return Ok(value);
+            }
+            self.check_healthy(&node)?;
+            if node.role != Role::Leader {
+                return Err(LedgerError::Replication(format!(
+                    "lost the leadership before entry {index} was committed"
+                )));
+            }
+            drop(node);
+            self.wait_for_round(deadline)?;
+        }
+    }
+
+    fn wait_for_round(&self, deadline: Instant) -> Result<(), LedgerError> {
+        if Instant::now() >= deadline {
+            return Err(LedgerError::Replication(format!(
+                "no majority within {:?}",
+                self.config.commit_timeout
+            )));
+        }
+        if !self.replicate() {
+            return Ok(());
+        }
+        let node = self.lock();
+        let waiting = node.commit < node.log.last_index();
+        drop(node);
+        if waiting {
+            std::thread::sleep(self.config.heartbeat.min(Duration::from_millis(10)));
+        }
+        Ok(())
+    }
+
+    fn read<T>(&self, read: impl FnOnce(&L) -> Result<T, LedgerError>) -> Result<T, LedgerError> {
+        self.check_healthy(&self.lock())?;
+        read(&self.machine)
+    }
+}
+
+impl<L: Stage> RaftService for RaftLedger<L> {
+    fn append_entries(&self, request: &AppendEntries) -> Result<AppendEntriesReply, LedgerError> {
+        let mut node = self.lock();
+        let reject = |node: &Node, match_index| AppendEntriesReply {
+            term: node.term(),
+            success: false,
+            match_index,
+        };
+        if request.term < node.term() {
+            return Ok(reject(&node, 0));
+        }
+        node.observe_term(request.term)?;
+        node.role = Role::Follower;
+        node.leader = Some(request.leader.clone());
+        node.election_deadline = Instant::now() + election_wait(&self.config);
+
+        if request.prev_index > node.log.last_index() {
+            let last = node.log.last_index();
+            return Ok(reject(&node, last));
+        }
+        if node.log.term_at(request.prev_index) != Some(request.prev_term) {
+            return Ok(reject(&node, request.prev_index - 1));
+        }
+        let mut fresh = Vec::new();
+        for entry in &request.entries {
+            match node.log.term_at(entry.index) {
+                Some(term) if term == entry.term && fresh.is_empty() => {}
+                Some(_) if fresh.is_empty() => {
+                    for removed in node.log.truncate_from(entry.index)? {
+                        let held = match &removed.command {
+                            Command::Append(receipt) => {
+                                self.machine.get_by_hash(receipt.receipt_hash())?.is_some()
+                            }
+                            _ => removed.index <= node.applied,
+                        };
+                        if held {
+                            tracing::error!(
+                                "raft node {} applied entry {} the leader overwrote",
+                                self.id,
+                                removed.index
+                            );
+                            node.diverged = true;
+                        }
+                    }
+                    node.applied = node.applied.min(entry.index - 1);
+                    fresh.push(entry.clone());
+                }
+                _ => fresh.push(entry.clone()),
+            }
+        }
+        node.log.append(fresh)?;
+
+        let matched = request.prev_index + request.entries.len() as u64;
+        if request.leader_commit > node.commit {
+            node.commit = request.leader_commit.min(matched);
+            self.apply_committed(&mut node);
+        }
+        Ok(AppendEntriesReply { term: node.term(), success: true, match_index: matched })
+    }
+
+    fn request_vote(&self, request: &VoteRequest) -> Result<VoteReply, LedgerError> {
+        let mut node = self.lock();
+        node.observe_term(request.term)?;
+        let term = node.term();
+        let up_to_date = (request.last_term, request.last_index)
+            >= (node.log.last_term(), node.log.last_index());
+        let free = node.log.hard.voted_for.as_ref().map_or(true, |v| *v == request.candidate);
+        let granted = request.term == term && free && up_to_date;
+        if granted {
+            node.log.save_hard(HardState { term, voted_for: Some(request.candidate.clone()) })?;
+            node.election_deadline = Instant::now() + election_wait(&self.config);
+        }
+        Ok(VoteReply { term, granted })
+    }
+
+    fn status(&self) -> RaftStatus {
+        let node = self.lock();
+        RaftStatus {
+            node: self.id.clone(),
+            role: node.role,
+            term: node.term(),
+            leader: node.leader.clone(),
+            last_index: node.log.last_index(),
+            commit_index: node.commit,
+            applied_index: node.applied,
+            diverged: node.diverged,
+        }
+    }
+}
+
+/// Keeps a [`RaftLedger`] ticking until dropped.
+pub struct RaftDriver {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for RaftDriver {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// A scratch copy of `ledger` holding the last receipt of `worldline`.
+fn stage_tip<L: Stage>(
+    ledger: &L,
+    worldline: &WorldlineId,
+) -> Result<InMemoryLedger, LedgerError> {
+    let tip = ledger.head(worldline)?.map_or(0, |head| head.seq);
+    ledger.stage(worldline, tip)
+}
+
+/// A scratch copy of `ledger` holding the stream of the receipt `hash`
+/// from that receipt on, or `None` if `ledger` lacks it.
+fn stage_from<L: Stage>(
+    ledger: &L,
+    hash: [u8; 32],
+) -> Option<Result<InMemoryLedger, LedgerError>> {
+    match ledger.get_by_hash(hash) {
+        Ok(Some(receipt)) => Some(ledger.stage(receipt.worldline(), receipt.seq())),
+        Ok(None) => None,
+        Err(e) => Some(Err(e)),
+    }
+}
+
+/// A randomized election timeout, so nodes rarely stand at once.
+fn election_wait(config: &RaftConfig) -> Duration {
+    let base = config.election_timeout;
+    let spread = base.as_nanos().max(1) as u64;
+    let jitter = RandomState::new().build_hasher().finish() % spread;
+    base + Duration::from_nanos(jitter)
+}
+
+impl<L: Stage> LedgerWriter for RaftLedger<L> {
+    fn append_commitment(
+        &self,
+        proposal: &CommitmentProposal,
+        decision: &Decision,
+        policy_hash: [u8; 32],
+    ) -> Result<CommitmentReceipt, LedgerError> {
+        self.propose(|ledger| {
+            let staged = stage_tip(ledger, &proposal.worldline)?;
+            let receipt = staged.append_commitment(proposal, decision, policy_hash)?;
+            let command = Command::Append(Box::new(Receipt::Commitment(receipt.clone())));
+            Ok((receipt, command))
+        })
+    }
+
+    fn append_outcome(
+        &self,
+        commitment_receipt_hash: [u8; 32],
+        outcome: &OutcomeRecord,
+    ) -> Result<OutcomeReceipt, LedgerError> {
+        self.propose(|ledger| {
+            let staged = stage_from(ledger, commitment_receipt_hash)
+                .ok_or(LedgerError::MissingCommitmentReceipt)??;
+            let receipt = staged.append_outcome(commitment_receipt_hash, outcome)?;
+            Ok((receipt.clone(), Command::Append(Box::new(Receipt::Outcome(receipt)))))
+        })
+    }
+
+    fn append_rejection_outcome(
+        &self,
+        commitment_receipt_hash: [u8; 32],
+        reason: &str,
+    ) -> Result<OutcomeReceipt, LedgerError> {
+        self.propose(|ledger| {
+            let staged = stage_from(ledger, commitment_receipt_hash)
+                .ok_or(LedgerError::MissingCommitmentReceipt)??;
+            let receipt = staged.append_rejection_outcome(commitment_receipt_hash, reason)?;
+            Ok((receipt.clone(), Command::Append(Box::new(Receipt::Outcome(receipt)))))
+        })
+    }
+
+    fn append_snapshot(&self, snapshot: &SnapshotInput) -> Result<SnapshotReceipt, LedgerError> {
+        self.propose(|ledger| {
+            let staged = stage_from(ledger, snapshot.anchored_receipt_hash)
+                .ok_or(LedgerError::MissingSnapshotAnchor)??;
+            let receipt = staged.append_snapshot(snapshot)?;
+            Ok((receipt.clone(), Command::Append(Box::new(Receipt::Snapshot(receipt)))))
+        })
+    }
+
+    fn import_receipt(&self, receipt: Receipt) -> Result<(), LedgerError> {
+        self.propose(|ledger| {
+            stage_tip(ledger, receipt.worldline())?.import_receipt(receipt.clone())?;
+            Ok(((), Command::Append(Box::new(receipt))))
+        })
+    }
+
+    fn archive(
+        &self,
+        worldline: &WorldlineId,
+        through_seq: u64,
+    ) -> Result<Vec<Receipt>, LedgerError> {
+        self.propose(|ledger| {
+            let archived = ledger.stage(worldline, 0)?.archive(worldline, through_seq)?;
+            Ok((archived, Command::Archive { worldline: worldline.clone(), through_seq }))
+        })
+    }
+
+    fn compact(
+        &self,
+        _worldline: &WorldlineId,
+        _through_seq: u64,
+        _key: &SigningKey,
+    ) -> Result<Compaction, LedgerError> {
+        Err(LedgerError::Replication("compaction is not replicated; archive instead".into()))
+    }
+
+    fn register_schema(
+        &self,
+        worldline: &WorldlineId,
+        namespace: Option<&str>,
+        schema: Arc<dyn StateSchema>,
+    ) -> Result<(), LedgerError> {
+        self.machine.register_schema(worldline, namespace, schema)
+    }
+}
+
+impl<L: Stage> LedgerReader for RaftLedger<L> {
+    fn head(&self, worldline: &WorldlineId) -> Result<Option<ReceiptRef>, LedgerError> {
+        self.read(|ledger| ledger.head(worldline))
+    }
+
+    fn read_range(
+        &self,
+        worldline: &WorldlineId,
+        from_seq: u64,
+        to_seq: u64,
+    ) -> Result<Vec<Receipt>, LedgerError> {
+        self.read(|ledger| ledger.read_range(worldline, from_seq, to_seq))
+    }
+
+    fn read_all(&self, worldline: &WorldlineId) -> Result<Vec<Receipt>, LedgerError> {
+        self.read(|ledger| ledger.read_all(worldline))
+    }
+
+    fn get_by_hash(&self, hash: [u8; 32]) -> Result<Option<Receipt>, LedgerError> {
+        self.read(|ledger| ledger.get_by_hash(hash))
+    }
+
+    fn worldlines(&self) -> Result<Vec<WorldlineId>, LedgerError> {
+        self.read(|ledger| ledger.worldlines())
+    }
+
+    fn receipt_count(&self, worldline: &WorldlineId) -> Result<u64, LedgerError> {
+        self.read(|ledger| ledger.receipt_count(worldline))
+    }
+
+    fn archived_through(&self, worldline: &WorldlineId) -> Result<Option<ReceiptRef>, LedgerError> {
+        self.read(|ledger| ledger.archived_through(worldline))
+    }
+
+    fn compactions(
+        &self,
+        worldline: &WorldlineId,
+    ) -> Result<Vec<CompactionReceipt>, LedgerError> {
+        self.read(|ledger| ledger.compactions(worldline))
+    }
+
+    fn schemas(&self) -> Result<SchemaRegistry, LedgerError> {
+        self.read(|ledger| ledger.schemas())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use wll_types::identity::IdentityMaterial;
+    use wll_types::CommitmentId;
+
+    use super::*;
+    use crate::memory::InMemoryLedger;
+
+    type Member = RaftLedger<InMemoryLedger>;
+
+    /// Nodes calling each other directly; a node marked down neither sends
+    /// nor answers.
+    #[derive(Default)]
+    struct Mesh {
+        nodes: Mutex<HashMap<NodeId, Weak<Member>>>,
+        down: Mutex<HashSet<NodeId>>,
+    }
+
+    struct Link {
+        from: NodeId,
+        mesh: Arc<Mesh>,
+    }
+
+    impl Link {
+        fn peer(&self, peer: &str) -> Result<Arc<Member>, LedgerError> {
+            let down = self.mesh.down.lock().unwrap();
+            if down.contains(&self.from) || down.contains(peer) {
+                return Err(LedgerError::Io(format!("{peer} is unreachable")));
+            }
+            let nodes = self.mesh.nodes.lock().unwrap();
+            nodes.get(peer).and_then(Weak::upgrade).ok_or_else(|| LedgerError::Io(peer.into()))
+        }
+    }
+
+    impl RaftTransport for Link {
+        fn append_entries(
+            &self,
+            peer: &str,
+            request: &AppendEntries,
+        ) -> Result<AppendEntriesReply, LedgerError> {
+            RaftService::append_entries(&*self.peer(peer)?, request)
+        }
+
+        fn request_vote(
+            &self,
+            peer: &str,
+            request: &VoteRequest,
+        ) -> Result<VoteReply, LedgerError> {
+            RaftService::request_vote(&*self.peer(peer)?, request)
+        }
+    }
+
+    fn cluster(mesh: &Arc<Mesh>) -> Vec<Arc<Member>> {
+        let ids: Vec<NodeId> = ["a", "b", "c"].map(String::from).to_vec();
+        let config = RaftConfig {
+            heartbeat: Duration::from_millis(1),
+            election_timeout: Duration::from_millis(1),
+            commit_timeout: Duration::from_millis(200),
+            max_batch: 2,
+        };
+        ids.iter()
+            .map(|id| {
+                let link = Arc::new(Link { from: id.clone(), mesh: mesh.clone() });
+                let node = Arc::new(
+                    RaftLedger::in_memory(id, ids.clone(), InMemoryLedger::default(), link)
+                        .with_config(config.clone()),
+                );
+                mesh.nodes.lock().unwrap().insert(id.clone(), Arc::downgrade(&node));
+                node
+            })
+            .collect()
+    }
+
+    fn elect(node: &Member) {
+        std::thread::sleep(Duration::from_millis(5));
+        node.tick();
+        assert_eq!(node.status().role, Role::Leader);
+    }
+
+    fn commitment(worldline: &WorldlineId, nonce: u64) -> CommitmentProposal {
+        CommitmentProposal {
+            worldline: worldline.clone(),
+            commitment_id: CommitmentId::new(),
+            class: wll_types::CommitmentClass::ContentUpdate,
+            intent: "replicate".into(),
+            requested_caps: vec![],
+            targets: vec![],
+            evidence: wll_types::EvidenceBundle::from_references(vec![]),
+            nonce,
+        }
+    }
+
+    #[test]
+    fn leader_appends_reach_every_node_and_followers_refuse_writes() {
+        let mesh = Arc::new(Mesh::default());
+        let nodes = cluster(&mesh);
+        let (a, b, c) = (&nodes[0], &nodes[1], &nodes[2]);
+        elect(a);
+        let wid = WorldlineId::derive(&IdentityMaterial::GenesisHash([1; 32]));
+
+        for nonce in 1..=3 {
+            a.append_commitment(&commitment(&wid, nonce), &Decision::Accepted, [0; 32]).unwrap();
+        }
+        a.tick();
+        let receipts = a.read_all(&wid).unwrap();
+        assert_eq!(receipts.len(), 3);
+        for follower in [b, c] {
+            assert_eq!(follower.read_all(&wid).unwrap(), receipts);
+            assert_eq!(follower.status().commit_index, 4);
+        }
+
+        let refused = b.append_commitment(&commitment(&wid, 4), &Decision::Accepted, [0; 32]);
+        assert_eq!(refused.unwrap_err(), LedgerError::NotLeader { leader: Some("a".into()) });
+    }
+
+    #[test]
+    fn cut_off_leader_drops_its_uncommitted_entry_and_catches_up() {
+        let mesh = Arc::new(Mesh::default());
+        let nodes = cluster(&mesh);
+        let (a, b, c) = (&nodes[0], &nodes[1], &nodes[2]);
+        elect(a);
+        let wid = WorldlineId::derive(&IdentityMaterial::GenesisHash([2; 32]));
+        a.append_commitment(&commitment(&wid, 1), &Decision::Accepted, [0; 32]).unwrap();
+
+        // The cut-off leader logs the write but neither applies nor serves it.
+        mesh.down.lock().unwrap().insert("a".into());
+        let lost = a.append_commitment(&commitment(&wid, 2), &Decision::Accepted, [0; 32]);
+        assert!(matches!(lost, Err(LedgerError::Replication(_))));
+        assert_eq!(a.read_all(&wid).unwrap().len(), 1);
+        assert_eq!(a.status().last_index, 3);
+
+        elect(b);
+        b.append_commitment(&commitment(&wid, 3), &Decision::Accepted, [0; 32]).unwrap();
+        b.tick();
+        assert_eq!(c.read_all(&wid).unwrap(), b.read_all(&wid).unwrap());
+
+        mesh.down.lock().unwrap().clear();
+        b.tick();
+        b.tick();
+        let status = a.status();
+        assert_eq!((status.role, status.leader.as_deref()), (Role::Follower, Some("b")));
+        assert!(!status.diverged);
+        assert_eq!(status.applied_index, b.status().commit_index);
+        let receipts = a.read_all(&wid).unwrap();
+        assert_eq!(receipts, b.read_all(&wid).unwrap());
+        assert_eq!(receipts.len(), 2);
+    }
+}
@@ -12,6 +12,8 @@ pub use wll_types::receipt::ReceiptKind;
 /// Commitment class controls policy tiering at the boundary.
 pub use wll_types::CommitmentClass;
 
+pub use crate::effects::EffectSummary;
+
 /// Commitment proposal generated by a WorldLine operator.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CommitmentProposal {
@@ -43,14 +45,6 @@ pub struct CommitmentReceipt {
     pub policy_hash: [u8; 32],
 }
 
-/// Human/audit-readable summary of one externally visible effect.
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
-pub struct EffectSummary {
-    pub kind: String,
-    pub target: String,
-    pub description: String,
-}
-
 /// Proof reference (artifact stored outside the ledger).
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ProofRef {
@@ -58,11 +52,40 @@ pub struct ProofRef {
     pub digest: [u8; 32],
 }
 
+/// State of the named namespaces of a worldline, by namespace.
+pub type NamespacedState = BTreeMap<String, BTreeMap<String, Value>>;
+
 /// Canonical update applied to projected state.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct StateUpdate {
     pub key: String,
     pub value: Value,
+    /// Namespace the key belongs to; the default namespace when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+}
+
+impl StateUpdate {
+    /// Set `key` to `value` in the default namespace.
+    pub fn new(key: impl Into<String>, value: Value) -> Self {
+        Self { key: key.into(), value, namespace: None }
+    }
+
+    /// Move the update into the namespace `name`.
+    pub fn in_namespace(mut self, name: impl Into<String>) -> Self {
+        self.namespace = Some(name.into());
+        self
+    }
+
+    /// Apply the update to the default namespace `state`, or to its own
+    /// namespace in `namespaces`.
+    pub fn apply_to(&self, state: &mut BTreeMap<String, Value>, namespaces: &mut NamespacedState) {
+        let target = match &self.namespace {
+            Some(name) => namespaces.entry(name.clone()).or_default(),
+            None => state,
+        };
+        target.insert(self.key.clone(), self.value.clone());
+    }
 }
 
 /// Input payload for accepted outcomes.
@@ -96,14 +119,36 @@ pub struct OutcomeReceipt {
     pub proofs: Vec<ProofRef>,
     pub state_updates: Vec<StateUpdate>,
     pub metadata: BTreeMap<String, String>,
+    /// Schema versions the state updates were checked against, one per
+    /// namespace that has a schema.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schemas: Vec<SchemaRef>,
+}
+
+/// The [`StateSchema`](crate::StateSchema) version an outcome's updates to
+/// a namespace were checked against.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaRef {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    pub schema_hash: [u8; 32],
 }
 
 /// Input payload for snapshot writes.
+///
+/// A snapshot without a `namespace` captures the whole state: `state` is
+/// the default namespace and `namespaces` the named ones. A snapshot of one
+/// namespace holds its state in `state` and replaces only that namespace.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SnapshotInput {
     pub worldline: WorldlineId,
     pub anchored_receipt_hash: [u8; 32],
     pub state: BTreeMap<String, Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    /// Named namespaces, for whole-state snapshots.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub namespaces: NamespacedState,
 }
 
 /// Immutable snapshot receipt.
@@ -117,6 +162,26 @@ pub struct SnapshotReceipt {
     pub anchored_receipt_hash: [u8; 32],
     pub state_hash: [u8; 32],
     pub state: BTreeMap<String, Value>,
+    /// The namespace this snapshot replaces, or `None` for the whole state.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub namespaces: NamespacedState,
+}
+
+impl SnapshotReceipt {
+    /// Replace the state this snapshot covers: one namespace, or all of it.
+    pub fn apply_to(&self, state: &mut BTreeMap<String, Value>, namespaces: &mut NamespacedState) {
+        match &self.namespace {
+            Some(name) => {
+                namespaces.insert(name.clone(), self.state.clone());
+            }
+            None => {
+                *state = self.state.clone();
+                *namespaces = self.namespaces.clone();
+            }
+        }
+    }
 }
 
 /// Unified receipt envelope for append-only streams.
@@ -135,7 +200,73 @@ pub struct ReceiptRef {
     pub receipt_hash: [u8; 32],
 }
 
+/// Evidence reference scheme citing a receipt, possibly of another worldline:
+/// `receipt://<worldline hex>/<receipt hash hex>`.
+pub const RECEIPT_URI_SCHEME: &str = "receipt://";
+
+/// Outcome metadata key naming, as a `receipt://` reference, the
+/// counterpart commitment of a linked commitment on another worldline.
+/// Linked commitments share a commitment id, and each side's outcome names
+/// the other side's commitment.
+pub const LINK_COUNTERPART_KEY: &str = "link.counterpart";
+
+/// A receipt of one worldline cited by a receipt of another.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CrossWorldlineRef {
+    pub worldline: WorldlineId,
+    pub receipt_hash: [u8; 32],
+}
+
+impl CrossWorldlineRef {
+    pub fn new(worldline: WorldlineId, receipt_hash: [u8; 32]) -> Self {
+        Self {
+            worldline,
+            receipt_hash,
+        }
+    }
+
+    /// The evidence reference citing this receipt.
+    pub fn to_uri(&self) -> String {
+        format!(
+            "{RECEIPT_URI_SCHEME}{}/{}",
+            self.worldline.to_hex(),
+            hex::encode(self.receipt_hash)
+        )
+    }
+
+    /// Parse a `receipt://` evidence reference; `None` for other schemes or
+    /// malformed references.
+    pub fn parse(uri: &str) -> Option<Self> {
+        let (worldline, hash) = uri.strip_prefix(RECEIPT_URI_SCHEME)?.split_once('/')?;
+        let receipt_hash = hex::decode(hash).ok()?.try_into().ok()?;
+        Some(Self::new(WorldlineId::from_hex(worldline).ok()?, receipt_hash))
+    }
+}
+
+impl std::fmt::Display for CrossWorldlineRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}@{}", hex::encode(&self.receipt_hash[..8]), self.worldline)
+    }
+}
+
 impl Receipt {
+    /// Receipts of other worldlines this receipt cites, from the
+    /// `receipt://` references in a commitment's evidence or a linked
+    /// outcome's counterpart ([`LINK_COUNTERPART_KEY`]).
+    pub fn cross_worldline_refs(&self) -> Vec<CrossWorldlineRef> {
+        let uris: Vec<&String> = match self {
+            Self::Commitment(commitment) => commitment.evidence.references.iter().collect(),
+            Self::Outcome(outcome) => {
+                outcome.metadata.get(LINK_COUNTERPART_KEY).into_iter().collect()
+            }
+            Self::Snapshot(_) => return Vec::new(),
+        };
+        uris.into_iter()
+            .filter_map(|uri| CrossWorldlineRef::parse(uri))
+            .filter(|r| &r.worldline != self.worldline())
+            .collect()
+    }
+
     pub fn kind(&self) -> ReceiptKind {
         match self {
             Self::Commitment(_) => ReceiptKind::Commitment,
@@ -273,6 +404,34 @@ mod tests {
         assert_eq!(reference.receipt_hash, [9; 32]);
     }
 
+    #[test]
+    fn cross_worldline_refs_come_from_receipt_evidence() {
+        let cited = CrossWorldlineRef::new(worldline(4), [5; 32]);
+        assert_eq!(CrossWorldlineRef::parse(&cited.to_uri()), Some(cited.clone()));
+        assert_eq!(CrossWorldlineRef::parse("receipt://zz/00"), None);
+        let own = CrossWorldlineRef::new(worldline(3), [6; 32]);
+        let receipt = Receipt::Commitment(CommitmentReceipt {
+            worldline: worldline(3),
+            seq: 1,
+            receipt_hash: [9; 32],
+            prev_hash: None,
+            timestamp: TemporalAnchor::zero(),
+            proposal_hash: [1; 32],
+            commitment_id: CommitmentId::new(),
+            class: CommitmentClass::ContentUpdate,
+            intent: "cite".into(),
+            requested_caps: vec![],
+            evidence: EvidenceBundle::from_references(vec![
+                cited.to_uri(),
+                own.to_uri(),
+                "obj://a".into(),
+            ]),
+            decision: Decision::Accepted,
+            policy_hash: [2; 32],
+        });
+        assert_eq!(receipt.cross_worldline_refs(), vec![cited]);
+    }
+
     #[test]
     fn outcome_hash_is_deterministic() {
         let outcome = OutcomeRecord {
@@ -281,6 +440,7 @@ mod tests {
             state_updates: vec![StateUpdate {
                 key: "k".into(),
                 value: Value::from(42),
+                namespace: None,
             }],
             metadata: BTreeMap::new(),
         };
@@ -304,6 +464,7 @@ mod tests {
             proofs: vec![],
             state_updates: vec![],
             metadata: BTreeMap::new(),
+            schemas: vec![],
         });
 
         assert_eq!(receipt.kind(), ReceiptKind::Outcome);
@@ -1,10 +1,13 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 
 use serde_json::Value;
 use wll_types::WorldlineId;
 
 use crate::error::LedgerError;
-use crate::records::{Receipt, SnapshotReceipt};
+use crate::records::{
+    CommitmentReceipt, Decision, NamespacedState, OutcomeReceipt, Receipt, SnapshotReceipt,
+};
+use crate::schema::SchemaRegistry;
 use crate::traits::LedgerReader;
 
 /// Result of replaying a worldline stream into canonical state.
@@ -13,27 +16,111 @@ pub struct ReplayResult {
     pub worldline: WorldlineId,
     pub applied_outcomes: u64,
     pub evaluated_receipts: u64,
+    /// State of the default namespace.
     pub state: BTreeMap<String, Value>,
+    pub namespaces: NamespacedState,
+}
+
+impl ReplayResult {
+    /// State of the namespace `name`, if anything was recorded in it.
+    pub fn namespace(&self, name: &str) -> Option<&BTreeMap<String, Value>> {
+        self.namespaces.get(name)
+    }
+}
+
+/// Re-derives the gate decision for a recorded commitment, for
+/// [`ReplayEngine::replay_strict`].
+pub trait DecisionReevaluator {
+    /// The decision the gate reaches for `commitment` under the policy
+    /// version named by its `policy_hash`, or `None` if that version is
+    /// unknown. `outcome` is the receipt that settled the commitment, if any.
+    fn reevaluate(
+        &self,
+        commitment: &CommitmentReceipt,
+        outcome: Option<&OutcomeReceipt>,
+    ) -> Result<Option<Decision>, LedgerError>;
+}
+
+/// A commitment whose recorded decision disagrees with re-evaluation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecisionMismatch {
+    pub seq: u64,
+    pub receipt_hash: [u8; 32],
+    pub policy_hash: [u8; 32],
+    pub recorded: Decision,
+    pub reevaluated: Decision,
+}
+
+/// Result of [`ReplayEngine::replay_strict`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StrictReplayResult {
+    pub replay: ReplayResult,
+    /// Commitments whose decision was re-evaluated.
+    pub decisions_checked: u64,
+    /// Sequence numbers of commitments judged under a policy version the
+    /// reevaluator does not know.
+    pub unknown_policies: Vec<u64>,
+    pub mismatches: Vec<DecisionMismatch>,
+}
+
+impl StrictReplayResult {
+    /// Returns `true` if every re-evaluated decision matched the record.
+    pub fn decisions_consistent(&self) -> bool {
+        self.mismatches.is_empty()
+    }
 }
 
 /// Deterministic replay helpers for WLL streams.
 pub struct ReplayEngine;
 
 impl ReplayEngine {
-    pub fn replay_from_genesis<R: LedgerReader>(
+    pub fn replay_from_genesis<R: LedgerReader + ?Sized>(
+        reader: &R,
+        worldline: &WorldlineId,
+    ) -> Result<ReplayResult, LedgerError> {
+        let receipts = reader.read_all(worldline)?;
+        Ok(apply_receipts(
+            worldline.clone(),
+            BTreeMap::new(),
+            NamespacedState::new(),
+            &receipts,
+            0,
+        ))
+    }
+
+    /// Replay from genesis, checking every accepted outcome's state updates
+    /// against the schema versions its receipt records.
+    ///
+    /// Fails with [`LedgerError::SchemaViolation`] at the first update its
+    /// schema rejects, and with [`LedgerError::UnknownSchema`] if `schemas`
+    /// lacks a version a receipt names.
+    pub fn replay_validated<R: LedgerReader + ?Sized>(
         reader: &R,
         worldline: &WorldlineId,
+        schemas: &SchemaRegistry,
     ) -> Result<ReplayResult, LedgerError> {
         let receipts = reader.read_all(worldline)?;
+        for receipt in &receipts {
+            if let Receipt::Outcome(o) = receipt {
+                if o.accepted {
+                    schemas.recheck(&o.schemas, &o.state_updates)?;
+                }
+            }
+        }
         Ok(apply_receipts(
             worldline.clone(),
             BTreeMap::new(),
+            NamespacedState::new(),
             &receipts,
             0,
         ))
     }
 
-    pub fn replay_from_snapshot<R: LedgerReader>(
+    /// Replay from `snapshot` onwards.
+    ///
+    /// A snapshot of one namespace only carries that namespace, so only it
+    /// is complete in the result.
+    pub fn replay_from_snapshot<R: LedgerReader + ?Sized>(
         reader: &R,
         snapshot: &SnapshotReceipt,
     ) -> Result<ReplayResult, LedgerError> {
@@ -43,27 +130,96 @@ impl ReplayEngine {
             .position(|r| r.receipt_hash() == snapshot.anchored_receipt_hash)
             .ok_or(LedgerError::MissingSnapshotAnchor)?;
 
+        let (mut state, mut namespaces) = (BTreeMap::new(), NamespacedState::new());
+        snapshot.apply_to(&mut state, &mut namespaces);
         Ok(apply_receipts(
             snapshot.worldline.clone(),
-            snapshot.state.clone(),
+            state,
+            namespaces,
             &receipts,
             anchor_index + 1,
         ))
     }
 
-    pub fn verify_snapshot_convergence<R: LedgerReader>(
+    /// Replay from genesis, re-evaluating every commitment's gate decision.
+    ///
+    /// Decisions are compared by whether they accept: a recorded acceptance
+    /// the gate would now reject, or the reverse, is a [`DecisionMismatch`],
+    /// pointing at a tampered receipt or a gate that decided wrongly.
+    pub fn replay_strict<R, V>(
+        reader: &R,
+        worldline: &WorldlineId,
+        reevaluator: &V,
+    ) -> Result<StrictReplayResult, LedgerError>
+    where
+        R: LedgerReader + ?Sized,
+        V: DecisionReevaluator + ?Sized,
+    {
+        let receipts = reader.read_all(worldline)?;
+        let replay = apply_receipts(
+            worldline.clone(),
+            BTreeMap::new(),
+            NamespacedState::new(),
+            &receipts,
+            0,
+        );
+
+        let outcomes: HashMap<[u8; 32], &OutcomeReceipt> = receipts
+            .iter()
+            .filter_map(|r| match r {
+                Receipt::Outcome(o) => Some((o.commitment_receipt_hash, o)),
+                _ => None,
+            })
+            .collect();
+        let mut decisions_checked = 0;
+        let mut unknown_policies = Vec::new();
+        let mut mismatches = Vec::new();
+        for receipt in &receipts {
+            let Receipt::Commitment(commitment) = receipt else {
+                continue;
+            };
+            let outcome = outcomes.get(&commitment.receipt_hash).copied();
+            let Some(reevaluated) = reevaluator.reevaluate(commitment, outcome)? else {
+                unknown_policies.push(commitment.seq);
+                continue;
+            };
+            decisions_checked += 1;
+            if reevaluated.is_accepted() != commitment.decision.is_accepted() {
+                mismatches.push(DecisionMismatch {
+                    seq: commitment.seq,
+                    receipt_hash: commitment.receipt_hash,
+                    policy_hash: commitment.policy_hash,
+                    recorded: commitment.decision.clone(),
+                    reevaluated,
+                });
+            }
+        }
+
+        Ok(StrictReplayResult {
+            replay,
+            decisions_checked,
+            unknown_policies,
+            mismatches,
+        })
+    }
+
+    pub fn verify_snapshot_convergence<R: LedgerReader + ?Sized>(
         reader: &R,
         snapshot: &SnapshotReceipt,
     ) -> Result<bool, LedgerError> {
         let full = Self::replay_from_genesis(reader, &snapshot.worldline)?;
         let tail = Self::replay_from_snapshot(reader, snapshot)?;
-        Ok(full.state == tail.state)
+        Ok(match &snapshot.namespace {
+            Some(name) => full.namespace(name) == tail.namespace(name),
+            None => full.state == tail.state && full.namespaces == tail.namespaces,
+        })
     }
 }
 
-fn apply_receipts(
+pub(crate) fn apply_receipts(
     worldline: WorldlineId,
     mut state: BTreeMap<String, Value>,
+    mut namespaces: NamespacedState,
     receipts: &[Receipt],
     start_index: usize,
 ) -> ReplayResult {
@@ -76,13 +232,13 @@ fn apply_receipts(
             Receipt::Outcome(outcome) => {
                 if outcome.accepted {
                     for update in &outcome.state_updates {
-                        state.insert(update.key.clone(), update.value.clone());
+                        update.apply_to(&mut state, &mut namespaces);
                     }
                     applied_outcomes += 1;
                 }
             }
             Receipt::Snapshot(snapshot) => {
-                state = snapshot.state.clone();
+                snapshot.apply_to(&mut state, &mut namespaces);
             }
             Receipt::Commitment(_) => {}
         }
@@ -93,6 +249,7 @@ fn apply_receipts(
         applied_outcomes,
         evaluated_receipts,
         state,
+        namespaces,
     }
 }
 
@@ -107,7 +264,8 @@ mod tests {
     use crate::records::*;
     use crate::traits::LedgerWriter;
 
-    use super::ReplayEngine;
+    use super::{DecisionReevaluator, ReplayEngine};
+    use crate::error::LedgerError;
 
     fn worldline(seed: u8) -> wll_types::WorldlineId {
         wll_types::WorldlineId::derive(&IdentityMaterial::GenesisHash([seed; 32]))
@@ -133,6 +291,7 @@ mod tests {
             state_updates: vec![StateUpdate {
                 key: "balance".into(),
                 value: Value::from(value),
+                namespace: None,
             }],
             metadata: BTreeMap::new(),
         }
@@ -158,6 +317,8 @@ mod tests {
                 worldline: wid.clone(),
                 anchored_receipt_hash: o1.receipt_hash,
                 state: snapshot_state,
+                namespace: None,
+                namespaces: BTreeMap::new(),
             })
             .unwrap();
 
@@ -185,4 +346,50 @@ mod tests {
         assert_eq!(result.evaluated_receipts, 0);
         assert!(result.state.is_empty());
     }
+
+    /// Accepts commitments judged under policy `[1; 32]`, rejects those
+    /// under `[2; 32]`, and knows no other policy.
+    struct PolicyByHash;
+
+    impl DecisionReevaluator for PolicyByHash {
+        fn reevaluate(
+            &self,
+            commitment: &CommitmentReceipt,
+            _outcome: Option<&OutcomeReceipt>,
+        ) -> Result<Option<Decision>, LedgerError> {
+            Ok(match commitment.policy_hash {
+                [1, ..] => Some(Decision::Accepted),
+                [2, ..] => Some(Decision::Rejected { reason: "policy says no".into() }),
+                _ => None,
+            })
+        }
+    }
+
+    #[test]
+    fn strict_replay_flags_decisions_that_do_not_reevaluate() {
+        let ledger = InMemoryLedger::default();
+        let wid = worldline(8);
+
+        let c1 = ledger
+            .append_commitment(&proposal(&wid, 1), &Decision::Accepted, [1; 32])
+            .unwrap();
+        ledger.append_outcome(c1.receipt_hash, &outcome(10)).unwrap();
+        let c2 = ledger
+            .append_commitment(&proposal(&wid, 2), &Decision::Accepted, [2; 32])
+            .unwrap();
+        ledger.append_outcome(c2.receipt_hash, &outcome(20)).unwrap();
+        ledger
+            .append_commitment(&proposal(&wid, 3), &Decision::Accepted, [3; 32])
+            .unwrap();
+
+        let result = ReplayEngine::replay_strict(&ledger, &wid, &PolicyByHash).unwrap();
+        assert_eq!(result.replay, ReplayEngine::replay_from_genesis(&ledger, &wid).unwrap());
+        assert_eq!(result.decisions_checked, 2);
+        assert_eq!(result.unknown_policies, [c2.seq + 2]);
+        assert!(!result.decisions_consistent());
+        assert_eq!(result.mismatches.len(), 1);
+        assert_eq!(result.mismatches[0].seq, c2.seq);
+        assert_eq!(result.mismatches[0].recorded, Decision::Accepted);
+        assert!(!result.mismatches[0].reevaluated.is_accepted());
+    }
 }
@@ -0,0 +1,274 @@
+//! Typed state schemas.
+//!
+//! A [`StateSchema`] decides which state updates an outcome may make in one
+//! namespace of a worldline. Schemas are registered with a ledger
+//! ([`LedgerWriter::register_schema`](crate::LedgerWriter::register_schema))
+//! for a worldline and namespace; `append_outcome` then refuses updates the
+//! schema rejects and records the hash of every schema it checked in the
+//! receipt ([`OutcomeReceipt::schemas`](crate::OutcomeReceipt::schemas)).
+//!
+//! Registering a schema for a namespace that already has one adds a new
+//! version. Earlier versions stay known by hash, so
+//! [`ReplayEngine::replay_validated`](crate::ReplayEngine::replay_validated)
+//! checks each outcome against the version that accepted it.
+//!
+//! Schemas are code, so registrations last as long as the ledger value;
+//! register them again after reopening a ledger.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use wll_types::WorldlineId;
+
+use crate::error::LedgerError;
+use crate::records::{SchemaRef, StateUpdate};
+
+/// Rules for the state updates of one namespace.
+pub trait StateSchema: Send + Sync {
+    /// Identifies this version of the schema in the receipts it checked.
+    fn schema_hash(&self) -> [u8; 32];
+
+    /// Check that `key` may be set to `value`, or say why not.
+    fn validate(&self, key: &str, value: &Value) -> Result<(), String>;
+}
+
+/// JSON type a [`KeySchema`] key holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueKind {
+    Any,
+    Bool,
+    Integer,
+    Number,
+    String,
+    Array,
+    Object,
+}
+
+impl ValueKind {
+    /// Returns `true` if `value` is of this kind.
+    pub fn matches(self, value: &Value) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Bool => value.is_boolean(),
+            Self::Integer => value.is_i64() || value.is_u64(),
+            Self::Number => value.is_number(),
+            Self::String => value.is_string(),
+            Self::Array => value.is_array(),
+            Self::Object => value.is_object(),
+        }
+    }
+}
+
+/// A schema listing the keys a namespace may hold and the type of each.
+///
+/// `null` is accepted for every listed key, since it removes the key.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeySchema {
+    /// Name of the schema, part of its hash.
+    pub name: String,
+    pub keys: BTreeMap<String, ValueKind>,
+    /// Accept keys not listed in `keys`, with any value.
+    #[serde(default)]
+    pub allow_unknown: bool,
+}
+
+impl KeySchema {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into(), keys: BTreeMap::new(), allow_unknown: false }
+    }
+
+    pub fn with_key(mut self, key: impl Into<String>, kind: ValueKind) -> Self {
+        self.keys.insert(key.into(), kind);
+        self
+    }
+
+    pub fn with_unknown_keys(mut self, allow: bool) -> Self {
+        self.allow_unknown = allow;
+        self
+    }
+}
+
+impl StateSchema for KeySchema {
+    fn schema_hash(&self) -> [u8; 32] {
+        let encoded = serde_json::to_vec(self).unwrap_or_default();
+        *blake3::hash(&encoded).as_bytes()
+    }
+
+    fn validate(&self, key: &str, value: &Value) -> Result<(), String> {
+        match self.keys.get(key) {
+            Some(_) if value.is_null() => Ok(()),
+            Some(kind) if kind.matches(value) => Ok(()),
+            Some(kind) => Err(format!("expected {kind:?}, got {value}").to_lowercase()),
+            None if self.allow_unknown => Ok(()),
+            None => Err(format!("key is not in schema {:?}", self.name)),
+        }
+    }
+}
+
+/// The schemas a ledger knows: the current one for each worldline and
+/// namespace, and every version ever registered, by hash.
+#[derive(Clone, Default)]
+pub struct SchemaRegistry {
+    current: HashMap<(WorldlineId, Option<String>), Arc<dyn StateSchema>>,
+    versions: HashMap<[u8; 32], Arc<dyn StateSchema>>,
+}
+
+impl fmt::Debug for SchemaRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SchemaRegistry")
+            .field("current", &self.current.len())
+            .field("versions", &self.versions.len())
+            .finish()
+    }
+}
+
+impl SchemaRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make `schema` the current schema of `namespace` (the default
+    /// namespace when `None`) of `worldline`.
+    pub fn register(
+        &mut self,
+        worldline: &WorldlineId,
+        namespace: Option<&str>,
+        schema: Arc<dyn StateSchema>,
+    ) {
+        self.versions.insert(schema.schema_hash(), schema.clone());
+        self.current.insert((worldline.clone(), namespace.map(str::to_string)), schema);
+    }
+
+    /// The schema new updates to `namespace` of `worldline` must pass.
+    pub fn current(
+        &self,
+        worldline: &WorldlineId,
+        namespace: Option<&str>,
+    ) -> Option<&Arc<dyn StateSchema>> {
+        self.current.get(&(worldline.clone(), namespace.map(str::to_string)))
+    }
+
+    /// A registered schema version by hash.
+    pub fn version(&self, schema_hash: &[u8; 32]) -> Option<&Arc<dyn StateSchema>> {
+        self.versions.get(schema_hash)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.current.is_empty()
+    }
+
+    /// Validate `updates` against the current schemas of `worldline`,
+    /// returning a reference to each schema that checked any of them.
+    pub fn check(
+        &self,
+        worldline: &WorldlineId,
+        updates: &[StateUpdate],
+    ) -> Result<Vec<SchemaRef>, LedgerError> {
+        let mut checked: Vec<SchemaRef> = Vec::new();
+        for update in updates {
+            let Some(schema) = self.current(worldline, update.namespace.as_deref()) else {
+                continue;
+            };
+            validate_update(schema.as_ref(), update)?;
+            if !checked.iter().any(|s| s.namespace == update.namespace) {
+                checked.push(SchemaRef {
+                    namespace: update.namespace.clone(),
+                    schema_hash: schema.schema_hash(),
+                });
+            }
+        }
+        Ok(checked)
+    }
+
+    /// Validate `updates` against the schema versions `schemas` recorded
+    /// for them.
+    pub fn recheck(&self, schemas: &[SchemaRef], updates: &[StateUpdate]) -> Result<(), LedgerError> {
+        for recorded in schemas {
+            let schema = self
+                .version(&recorded.schema_hash)
+                .ok_or(LedgerError::UnknownSchema(recorded.schema_hash))?;
+            for update in updates.iter().filter(|u| u.namespace == recorded.namespace) {
+                validate_update(schema.as_ref(), update)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn validate_update(schema: &dyn StateSchema, update: &StateUpdate) -> Result<(), LedgerError> {
+    schema
+        .validate(&update.key, &update.value)
+        .map_err(|reason| LedgerError::SchemaViolation {
+            namespace: update.namespace.clone(),
+            key: update.key.clone(),
+            reason,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use wll_types::identity::IdentityMaterial;
+    use wll_types::CommitmentId;
+
+    use super::*;
+    use crate::memory::InMemoryLedger;
+    use crate::records::*;
+    use crate::replay::ReplayEngine;
+    use crate::traits::{LedgerReader, LedgerWriter};
+
+    #[test]
+    fn outcomes_are_checked_and_replayed_against_their_schema_version() {
+        let ledger = InMemoryLedger::default();
+        let wid = WorldlineId::derive(&IdentityMaterial::GenesisHash([8; 32]));
+        let v1 = KeySchema::new("billing").with_key("seats", ValueKind::Integer);
+        ledger.register_schema(&wid, Some("billing"), Arc::new(v1.clone())).unwrap();
+
+        let commit = |update: StateUpdate| {
+            let proposal = CommitmentProposal {
+                worldline: wid.clone(),
+                commitment_id: CommitmentId::new(),
+                class: CommitmentClass::ContentUpdate,
+                intent: "schema test".into(),
+                requested_caps: vec![],
+                targets: vec![],
+                evidence: EvidenceBundle::empty(),
+                nonce: 0,
+            };
+            let c = ledger.append_commitment(&proposal, &Decision::Accepted, [0; 32]).unwrap();
+            let record = OutcomeRecord {
+                effects: vec![],
+                proofs: vec![],
+                state_updates: vec![update, StateUpdate::new("free-form", Value::from(1))],
+                metadata: BTreeMap::new(),
+            };
+            ledger.append_outcome(c.receipt_hash, &record)
+        };
+
+        let seats = StateUpdate::new("seats", Value::from(5)).in_namespace("billing");
+        let outcome = commit(seats).unwrap();
+        assert_eq!(outcome.schemas.len(), 1);
+        assert_eq!(outcome.schemas[0].schema_hash, v1.schema_hash());
+
+        let text = StateUpdate::new("seats", Value::from("five")).in_namespace("billing");
+        let err = commit(text).unwrap_err();
+        assert!(matches!(err, LedgerError::SchemaViolation { ref key, .. } if key == "seats"));
+
+        // Version 2 renames the key; the first outcome still replays under v1.
+        let v2 = KeySchema::new("billing").with_key("licenses", ValueKind::Integer);
+        ledger.register_schema(&wid, Some("billing"), Arc::new(v2)).unwrap();
+        let licenses = StateUpdate::new("licenses", Value::from(7)).in_namespace("billing");
+        commit(licenses).unwrap();
+        let schemas = ledger.schemas().unwrap();
+        let replay = ReplayEngine::replay_validated(&ledger, &wid, &schemas).unwrap();
+        assert_eq!(replay.namespace("billing").unwrap().len(), 2);
+
+        let err = ReplayEngine::replay_validated(&ledger, &wid, &SchemaRegistry::new());
+        assert_eq!(err.unwrap_err(), LedgerError::UnknownSchema(v1.schema_hash()));
+    }
+}
@@ -0,0 +1,496 @@
+//! Time anchoring against Roughtime servers.
+//!
+//! A time anchor bounds when a receipt existed without trusting the local
+//! clock: the receipt hash is turned into a Roughtime nonce
+//! ([`anchor_nonce`]), and the server's signed response places that nonce
+//! at a midpoint ± radius. The anchor is recorded as an outcome whose
+//! metadata names the anchored receipt and the time, and whose proof is the
+//! response ([`RoughtimeVerifier`], proof type `roughtime`).
+//!
+//! This implements the original (Google) Roughtime wire format: tag-value
+//! messages, SHA-512 Merkle trees over nonces, and times in microseconds.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::{Digest, Sha512};
+use wll_crypto::{Signature, SigningKey, VerifyingKey};
+
+use crate::proof::{ProofError, ProofVerifier};
+use crate::records::OutcomeReceipt;
+
+/// Outcome metadata key holding the hex hash of the anchored receipt. Log
+/// publications use it too, for the receipt they published.
+pub const ANCHOR_RECEIPT_KEY: &str = "anchor.receipt";
+/// Outcome metadata key holding the Roughtime midpoint, in Unix microseconds.
+pub const ANCHOR_MIDPOINT_KEY: &str = "anchor.midpoint_us";
+/// Outcome metadata key holding the Roughtime radius, in microseconds.
+pub const ANCHOR_RADIUS_KEY: &str = "anchor.radius_us";
+/// Outcome metadata key holding the name of the server that signed the time.
+pub const ANCHOR_SERVER_KEY: &str = "anchor.server";
+
+/// Roughtime tags.
+pub mod tags {
+    pub const SIG: [u8; 4] = *b"SIG\0";
+    pub const NONC: [u8; 4] = *b"NONC";
+    pub const PAD: [u8; 4] = *b"PAD\xff";
+    pub const SREP: [u8; 4] = *b"SREP";
+    pub const CERT: [u8; 4] = *b"CERT";
+    pub const INDX: [u8; 4] = *b"INDX";
+    pub const PATH: [u8; 4] = *b"PATH";
+    pub const ROOT: [u8; 4] = *b"ROOT";
+    pub const MIDP: [u8; 4] = *b"MIDP";
+    pub const RADI: [u8; 4] = *b"RADI";
+    pub const DELE: [u8; 4] = *b"DELE";
+    pub const MINT: [u8; 4] = *b"MINT";
+    pub const MAXT: [u8; 4] = *b"MAXT";
+    pub const PUBK: [u8; 4] = *b"PUBK";
+}
+
+const DELEGATION_CONTEXT: &[u8] = b"RoughTime v1 delegation signature--\0";
+const RESPONSE_CONTEXT: &[u8] = b"RoughTime v1 response signature\0";
+const REQUEST_LEN: usize = 1024;
+
+/// A Roughtime tag-value message. Tags are kept in wire order (ascending as
+/// little-endian `u32`s).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RoughtimeMessage {
+    fields: BTreeMap<u32, Vec<u8>>,
+}
+
+impl RoughtimeMessage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set `tag` to `value`. Values must be a multiple of four bytes long.
+    pub fn with(mut self, tag: [u8; 4], value: Vec<u8>) -> Self {
+        debug_assert!(value.len() % 4 == 0, "roughtime values are 4-byte aligned");
+        self.fields.insert(u32::from_le_bytes(tag), value);
+        self
+    }
+
+    pub fn get(&self, tag: [u8; 4]) -> Option<&[u8]> {
+        self.fields.get(&u32::from_le_bytes(tag)).map(Vec::as_slice)
+    }
+
+    fn require(&self, tag: [u8; 4]) -> Result<&[u8], ProofError> {
+        self.get(tag).ok_or_else(|| {
+            ProofError::Malformed(format!("missing {}", String::from_utf8_lossy(&tag)))
+        })
+    }
+
+    fn require_array<const N: usize>(&self, tag: [u8; 4]) -> Result<[u8; N], ProofError> {
+        self.require(tag)?.try_into().map_err(|_| {
+            ProofError::Malformed(format!(
+                "{} is not {N} bytes",
+                String::from_utf8_lossy(&tag)
+            ))
+        })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&(self.fields.len() as u32).to_le_bytes());
+        let mut offset = 0u32;
+        for value in self
+            .fields
+            .values()
+            .take(self.fields.len().saturating_sub(1))
+        {
+            offset += value.len() as u32;
+            out.extend_from_slice(&offset.to_le_bytes());
+        }
+        for tag in self.fields.keys() {
+            out.extend_from_slice(&tag.to_le_bytes());
+        }
+        for value in self.fields.values() {
+            out.extend_from_slice(value);
+        }
+        out
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, ProofError> {
+        let malformed =
+            |reason: &str| ProofError::Malformed(format!("roughtime message: {reason}"));
+        let word = |at: usize| -> Result<u32, ProofError> {
+            bytes
+                .get(at..at + 4)
+                .map(|w| u32::from_le_bytes(w.try_into().unwrap()))
+                .ok_or_else(|| malformed("truncated header"))
+        };
+
+        let count = word(0)? as usize;
+        if count == 0 {
+            return Ok(Self::new());
+        }
+        let header = count
+            .checked_mul(8)
+            .filter(|&len| len <= bytes.len())
+            .ok_or_else(|| malformed("truncated header"))?;
+        let values = &bytes[header..];
+
+        let mut bounds = vec![0usize];
+        for i in 1..count {
+            bounds.push(word(4 * i)? as usize);
+        }
+        bounds.push(values.len());
+
+        let mut fields = BTreeMap::new();
+        let mut previous_tag = None;
+        for i in 0..count {
+            let tag = word(4 * (count - 1) + 4 + 4 * i)?;
+            if previous_tag.is_some_and(|previous| tag <= previous) {
+                return Err(malformed("tags out of order"));
+            }
+            previous_tag = Some(tag);
+            let (start, end) = (bounds[i], bounds[i + 1]);
+            if start > end || end > values.len() || start % 4 != 0 {
+                return Err(malformed("bad offset"));
+            }
+            fields.insert(tag, values[start..end].to_vec());
+        }
+        Ok(Self { fields })
+    }
+}
+
+/// Time a server signed for a nonce.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RoughtimeTime {
+    /// Unix time in microseconds.
+    pub midpoint_us: u64,
+    /// Uncertainty in microseconds; the true time is within
+    /// `midpoint_us ± radius_us`.
+    pub radius_us: u32,
+}
+
+/// Nonce that commits a Roughtime request to `receipt_hash`.
+pub fn anchor_nonce(receipt_hash: &[u8; 32]) -> [u8; 64] {
+    Sha512::new()
+        .chain_update(b"wll-time-anchor-v1:")
+        .chain_update(receipt_hash)
+        .finalize()
+        .into()
+}
+
+/// A request for `nonce`, padded to the minimum request size.
+pub fn roughtime_request(nonce: &[u8; 64]) -> Vec<u8> {
+    let header = 4 + 4 + 8;
+    RoughtimeMessage::new()
+        .with(tags::NONC, nonce.to_vec())
+        .with(tags::PAD, vec![0; REQUEST_LEN - header - nonce.len()])
+        .encode()
+}
+
+fn leaf_hash(nonce: &[u8]) -> [u8; 64] {
+    Sha512::new()
+        .chain_update([0])
+        .chain_update(nonce)
+        .finalize()
+        .into()
+}
+
+fn node_hash(left: &[u8], right: &[u8]) -> [u8; 64] {
+    Sha512::new()
+        .chain_update([1])
+        .chain_update(left)
+        .chain_update(right)
+        .finalize()
+        .into()
+}
+
+fn read_u64(bytes: [u8; 8]) -> u64 {
+    u64::from_le_bytes(bytes)
+}
+
+/// Check `response` is a reply to `nonce` signed under `key`, the server's
+/// long-term public key, and return the time it vouches for.
+pub fn verify_roughtime_response(
+    response: &[u8],
+    nonce: &[u8; 64],
+    key: &VerifyingKey,
+) -> Result<RoughtimeTime, ProofError> {
+    let message = RoughtimeMessage::decode(response)?;
+
+    let cert = RoughtimeMessage::decode(message.require(tags::CERT)?)?;
+    let dele_bytes = cert.require(tags::DELE)?;
+    let cert_signature = Signature::from_bytes(cert.require_array(tags::SIG)?);
+    key.verify(&[DELEGATION_CONTEXT, dele_bytes].concat(), &cert_signature)
+        .map_err(|_| ProofError::Invalid("delegation signature does not verify".into()))?;
+    let dele = RoughtimeMessage::decode(dele_bytes)?;
+    let online_key = VerifyingKey::from_bytes(dele.require_array(tags::PUBK)?)
+        .map_err(|e| ProofError::Malformed(format!("delegated key: {e}")))?;
+
+    let srep_bytes = message.require(tags::SREP)?;
+    let signature = Signature::from_bytes(message.require_array(tags::SIG)?);
+    online_key
+        .verify(&[RESPONSE_CONTEXT, srep_bytes].concat(), &signature)
+        .map_err(|_| ProofError::Invalid("response signature does not verify".into()))?;
+    let srep = RoughtimeMessage::decode(srep_bytes)?;
+
+    let mut index = u32::from_le_bytes(message.require_array(tags::INDX)?);
+    let path = message.require(tags::PATH)?;
+    if path.len() % 64 != 0 {
+        return Err(ProofError::Malformed("PATH is not a list of hashes".into()));
+    }
+    let mut hash = leaf_hash(nonce);
+    for sibling in path.chunks(64) {
+        hash = if index & 1 == 0 {
+            node_hash(&hash, sibling)
+        } else {
+            node_hash(sibling, &hash)
+        };
+        index >>= 1;
+    }
+    if hash[..] != *srep.require(tags::ROOT)? {
+        return Err(ProofError::WrongSubject);
+    }
+
+    let time = RoughtimeTime {
+        midpoint_us: read_u64(srep.require_array(tags::MIDP)?),
+        radius_us: u32::from_le_bytes(srep.require_array(tags::RADI)?),
+    };
+    let (min, max) = (
+        read_u64(dele.require_array(tags::MINT)?),
+        read_u64(dele.require_array(tags::MAXT)?),
+    );
+    if !(min..=max).contains(&time.midpoint_us) {
+        return Err(ProofError::Invalid(
+            "midpoint is outside the delegation's validity".into(),
+        ));
+    }
+    Ok(time)
+}
+
+/// A minimal Roughtime responder answering one request per response, for
+/// running a private time source and for tests.
+pub struct RoughtimeResponder {
+    online_key: SigningKey,
+    cert: Vec<u8>,
+}
+
+impl RoughtimeResponder {
+    /// A responder whose online key, certified by `long_term_key`, may sign
+    /// midpoints in `[min_us, max_us]`.
+    pub fn new(
+        long_term_key: &SigningKey,
+        online_key: SigningKey,
+        min_us: u64,
+        max_us: u64,
+    ) -> Self {
+        let dele = RoughtimeMessage::new()
+            .with(tags::PUBK, online_key.verifying_key().as_bytes().to_vec())
+            .with(tags::MINT, min_us.to_le_bytes().to_vec())
+            .with(tags::MAXT, max_us.to_le_bytes().to_vec())
+            .encode();
+        let signature = long_term_key.sign(&[DELEGATION_CONTEXT, &dele].concat());
+        let cert = RoughtimeMessage::new()
+            .with(tags::SIG, signature.to_bytes().to_vec())
+            .with(tags::DELE, dele)
+            .encode();
+        Self { online_key, cert }
+    }
+
+    /// Answer `request` with `time`.
+    pub fn respond(&self, request: &[u8], time: RoughtimeTime) -> Result<Vec<u8>, ProofError> {
+        let request = RoughtimeMessage::decode(request)?;
+        let nonce: [u8; 64] = request.require_array(tags::NONC)?;
+        let srep = RoughtimeMessage::new()
+            .with(tags::RADI, time.radius_us.to_le_bytes().to_vec())
+            .with(tags::MIDP, time.midpoint_us.to_le_bytes().to_vec())
+            .with(tags::ROOT, leaf_hash(&nonce).to_vec())
+            .encode();
+        let signature = self.online_key.sign(&[RESPONSE_CONTEXT, &srep].concat());
+        Ok(RoughtimeMessage::new()
+            .with(tags::SIG, signature.to_bytes().to_vec())
+            .with(tags::PATH, Vec::new())
+            .with(tags::SREP, srep)
+            .with(tags::CERT, self.cert.clone())
+            .with(tags::INDX, 0u32.to_le_bytes().to_vec())
+            .encode())
+    }
+}
+
+/// `roughtime` proofs: `{"type", "server", "anchored", "response"}` with
+/// the anchored receipt hash and the raw response hex-encoded. The response
+/// must answer [`anchor_nonce`] of the receipt named in the outcome's
+/// [`ANCHOR_RECEIPT_KEY`] metadata, be signed by the named server's trusted
+/// key, and vouch for the midpoint and radius the metadata records.
+#[derive(Clone, Debug, Default)]
+pub struct RoughtimeVerifier {
+    servers: HashMap<String, VerifyingKey>,
+}
+
+#[derive(Deserialize)]
+struct RoughtimeArtifact {
+    server: String,
+    anchored: String,
+    response: String,
+}
+
+impl RoughtimeVerifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trust times signed by the server named `name` under `key`.
+    pub fn with_server(mut self, name: impl Into<String>, key: VerifyingKey) -> Self {
+        self.servers.insert(name.into(), key);
+        self
+    }
+}
+
+impl ProofVerifier for RoughtimeVerifier {
+    fn proof_type(&self) -> &str {
+        "roughtime"
+    }
+
+    fn verify(&self, artifact: &Value, outcome: &OutcomeReceipt) -> Result<(), ProofError> {
+        let artifact = RoughtimeArtifact::deserialize(artifact)
+            .map_err(|e| ProofError::Malformed(e.to_string()))?;
+        if outcome.metadata.get(ANCHOR_RECEIPT_KEY) != Some(&artifact.anchored) {
+            return Err(ProofError::WrongSubject);
+        }
+        let key = self
+            .servers
+            .get(&artifact.server)
+            .ok_or_else(|| ProofError::Untrusted(format!("server {:?}", artifact.server)))?;
+        let anchored: [u8; 32] = hex::decode(&artifact.anchored)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| ProofError::Malformed("anchored: expected a 32-byte hex hash".into()))?;
+        let response = hex::decode(&artifact.response)
+            .map_err(|e| ProofError::Malformed(format!("response: {e}")))?;
+
+        let time = verify_roughtime_response(&response, &anchor_nonce(&anchored), key)?;
+        let recorded = |key: &str| outcome.metadata.get(key).map(String::as_str);
+        if recorded(ANCHOR_MIDPOINT_KEY) != Some(time.midpoint_us.to_string().as_str())
+            || recorded(ANCHOR_RADIUS_KEY) != Some(time.radius_us.to_string().as_str())
+        {
+            return Err(ProofError::Invalid(
+                "recorded time does not match the signed response".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wll_types::identity::IdentityMaterial;
+    use wll_types::{TemporalAnchor, WorldlineId};
+
+    use super::*;
+
+    const TIME: RoughtimeTime = RoughtimeTime {
+        midpoint_us: 1_700_000_000_000_000,
+        radius_us: 1_000_000,
+    };
+
+    fn responder(long_term: &SigningKey) -> RoughtimeResponder {
+        RoughtimeResponder::new(long_term, SigningKey::from_bytes([2; 32]), 0, u64::MAX)
+    }
+
+    #[test]
+    fn message_roundtrip_keeps_tag_order() {
+        let message = RoughtimeMessage::new()
+            .with(tags::PAD, vec![0; 8])
+            .with(tags::NONC, vec![1; 64])
+            .with(tags::SIG, vec![2; 4]);
+        let bytes = message.encode();
+        assert_eq!(&bytes[..4], &3u32.to_le_bytes());
+        assert_eq!(&bytes[12..16], b"SIG\0");
+        assert_eq!(RoughtimeMessage::decode(&bytes).unwrap(), message);
+        assert_eq!(roughtime_request(&[0; 64]).len(), REQUEST_LEN);
+        assert!(RoughtimeMessage::decode(&bytes[..10]).is_err());
+    }
+
+    #[test]
+    fn verifies_signed_response_for_nonce() {
+        let long_term = SigningKey::from_bytes([1; 32]);
+        let nonce = anchor_nonce(&[7; 32]);
+        let response = responder(&long_term)
+            .respond(&roughtime_request(&nonce), TIME)
+            .unwrap();
+
+        let key = long_term.verifying_key();
+        assert_eq!(verify_roughtime_response(&response, &nonce, &key), Ok(TIME));
+        assert_eq!(
+            verify_roughtime_response(&response, &anchor_nonce(&[8; 32]), &key),
+            Err(ProofError::WrongSubject)
+        );
+        let stranger = SigningKey::from_bytes([3; 32]).verifying_key();
+        assert!(matches!(
+            verify_roughtime_response(&response, &nonce, &stranger),
+            Err(ProofError::Invalid(_))
+        ));
+
+        let expired = RoughtimeResponder::new(&long_term, SigningKey::from_bytes([2; 32]), 0, 1)
+            .respond(&roughtime_request(&nonce), TIME)
+            .unwrap();
+        assert!(matches!(
+            verify_roughtime_response(&expired, &nonce, &key),
+            Err(ProofError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn verifier_checks_recorded_time() {
+        let long_term = SigningKey::from_bytes([1; 32]);
+        let anchored = [7; 32];
+        let response = responder(&long_term)
+            .respond(&roughtime_request(&anchor_nonce(&anchored)), TIME)
+            .unwrap();
+        let artifact = serde_json::json!({
+            "type": "roughtime",
+            "server": "local",
+            "anchored": hex::encode(anchored),
+            "response": hex::encode(response),
+        });
+        let mut outcome = OutcomeReceipt {
+            worldline: WorldlineId::derive(&IdentityMaterial::GenesisHash([1; 32])),
+            seq: 3,
+            receipt_hash: [3; 32],
+            prev_hash: Some([2; 32]),
+            timestamp: TemporalAnchor::new(1, 0, 0),
+            commitment_receipt_hash: [2; 32],
+            outcome_hash: [4; 32],
+            accepted: true,
+            effects: vec![],
+            proofs: vec![],
+            state_updates: vec![],
+            metadata: BTreeMap::from([
+                (ANCHOR_RECEIPT_KEY.to_string(), hex::encode(anchored)),
+                (
+                    ANCHOR_MIDPOINT_KEY.to_string(),
+                    TIME.midpoint_us.to_string(),
+                ),
+                (ANCHOR_RADIUS_KEY.to_string(), TIME.radius_us.to_string()),
+            ]),
+            schemas: vec![],
+        };
+
+        let verifier = RoughtimeVerifier::new().with_server("local", long_term.verifying_key());
+        assert_eq!(verifier.verify(&artifact, &outcome), Ok(()));
+        assert!(matches!(
+            RoughtimeVerifier::new().verify(&artifact, &outcome),
+            Err(ProofError::Untrusted(_))
+        ));
+
+        outcome
+            .metadata
+            .insert(ANCHOR_MIDPOINT_KEY.into(), "0".into());
+        assert!(matches!(
+            verifier.verify(&artifact, &outcome),
+            Err(ProofError::Invalid(_))
+        ));
+        outcome
+            .metadata
+            .insert(ANCHOR_RECEIPT_KEY.into(), hex::encode([8; 32]));
+        assert_eq!(
+            verifier.verify(&artifact, &outcome),
+            Err(ProofError::WrongSubject)
+        );
+    }
+}
@@ -1,10 +1,15 @@
+use std::sync::Arc;
+
+use wll_crypto::SigningKey;
 use wll_types::WorldlineId;
 
+use crate::compaction::{Compaction, CompactionReceipt};
 use crate::error::LedgerError;
 use crate::records::{
     CommitmentProposal, CommitmentReceipt, Decision, OutcomeReceipt, OutcomeRecord, Receipt,
     ReceiptRef, SnapshotInput, SnapshotReceipt,
 };
+use crate::schema::{SchemaRegistry, StateSchema};
 
 /// Write boundary for WorldLine Ledger append operations.
 pub trait LedgerWriter: Send + Sync {
@@ -28,6 +33,49 @@ pub trait LedgerWriter: Send + Sync {
     ) -> Result<OutcomeReceipt, LedgerError>;
 
     fn append_snapshot(&self, snapshot: &SnapshotInput) -> Result<SnapshotReceipt, LedgerError>;
+
+    /// Insert a receipt that was already hashed elsewhere, e.g. when
+    /// replaying a journal or ingesting receipts from a peer.
+    ///
+    /// The receipt must extend the tip of its worldline stream and its stored
+    /// hash must match a known [`ReceiptHashFormat`](crate::ReceiptHashFormat).
+    /// It is stored unchanged.
+    fn import_receipt(&self, receipt: Receipt) -> Result<(), LedgerError>;
+
+    /// Move the receipts of `worldline` up to and including `through_seq`
+    /// out of the live stream, returning them oldest first.
+    ///
+    /// The live stream keeps its sequence numbers and continues from the
+    /// last archived receipt. Fails with [`LedgerError::ArchiveBoundary`]
+    /// unless [`check_archive_point`](crate::archive::check_archive_point)
+    /// accepts `through_seq`.
+    fn archive(&self, worldline: &WorldlineId, through_seq: u64)
+        -> Result<Vec<Receipt>, LedgerError>;
+
+    /// Drop the receipts of `worldline` up to and including `through_seq`
+    /// in favour of a [`CompactionReceipt`] signed by `key`, returning it
+    /// with the dropped receipts.
+    ///
+    /// The cut is checked like [`archive`](Self::archive)'s. Receipts
+    /// archived since the stream's last compaction, or before its first,
+    /// leave a gap the compacted state would miss, so such a stream fails
+    /// with [`LedgerError::ArchiveBoundary`].
+    fn compact(
+        &self,
+        worldline: &WorldlineId,
+        through_seq: u64,
+        key: &SigningKey,
+    ) -> Result<Compaction, LedgerError>;
+
+    /// Check later outcomes' updates to `namespace` of `worldline` (the
+    /// default namespace when `None`) against `schema`, replacing any
+    /// schema registered there before.
+    fn register_schema(
+        &self,
+        worldline: &WorldlineId,
+        namespace: Option<&str>,
+        schema: Arc<dyn StateSchema>,
+    ) -> Result<(), LedgerError>;
 }
 
 /// Read boundary for WorldLine Ledger query/replay operations.
@@ -47,5 +95,22 @@ pub trait LedgerReader: Send + Sync {
 
     fn worldlines(&self) -> Result<Vec<WorldlineId>, LedgerError>;
 
+    /// Number of live receipts; archived receipts are not counted.
     fn receipt_count(&self, worldline: &WorldlineId) -> Result<u64, LedgerError>;
+
+    /// The last archived or compacted receipt of `worldline`, which its
+    /// live stream continues from, or `None` if nothing was archived.
+    fn archived_through(&self, worldline: &WorldlineId) -> Result<Option<ReceiptRef>, LedgerError>;
+
+    /// Every compaction of `worldline`, oldest first.
+    fn compactions(&self, worldline: &WorldlineId)
+        -> Result<Vec<CompactionReceipt>, LedgerError>;
+
+    /// Every state schema registered with this ledger, current and past.
+    fn schemas(&self) -> Result<SchemaRegistry, LedgerError>;
 }
+
+/// Combined read/write ledger boundary, usable as a trait object.
+pub trait Ledger: LedgerReader + LedgerWriter {}
+
+impl<T: LedgerReader + LedgerWriter + ?Sized> Ledger for T {}
@@ -1,9 +1,15 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use wll_types::WorldlineId;
 
+use crate::canonical::detect_encoding;
 use crate::error::LedgerError;
-use crate::records::Receipt;
+use crate::proof::{ProofCheck, ProofError};
+use crate::records::{
+    CommitmentReceipt, CrossWorldlineRef, Decision, OutcomeReceipt, Receipt, ReceiptRef,
+    LINK_COUNTERPART_KEY,
+};
+use crate::timestamp::ANCHOR_RECEIPT_KEY;
 use crate::traits::LedgerReader;
 
 /// Result of stream validation.
@@ -15,6 +21,12 @@ pub struct ValidationReport {
     pub sequence_monotonic: bool,
     pub outcomes_attributed: bool,
     pub snapshots_anchored: bool,
+    /// Whether every outcome proof verified. Only checked in strict mode;
+    /// `true` otherwise.
+    pub proofs_verified: bool,
+    /// Whether every linked outcome's counterpart commitment checked out.
+    /// Only checked when validating a ledger's stream; `true` otherwise.
+    pub links_resolved: bool,
     pub violations: Vec<Violation>,
 }
 
@@ -40,28 +52,87 @@ pub enum ViolationKind {
     HashMismatch,
     UnattributedOutcome,
     UnanchoredSnapshot,
+    InvalidProof,
+    UnresolvedLink,
+}
+
+/// Checks the proofs attached to outcome receipts during strict validation.
+pub trait OutcomeProofCheck {
+    /// Verify every proof of `outcome`.
+    fn check_outcome_proofs(&self, outcome: &OutcomeReceipt) -> Vec<ProofCheck>;
 }
 
 /// Stream integrity validator.
+///
+/// A stream read from a ledger is validated from its last archived receipt
+/// ([`LedgerReader::archived_through`]), which the first live receipt must
+/// link to. Outcomes of linked commitments ([`LINK_COUNTERPART_KEY`]) are
+/// checked against their counterpart with [`Self::validate_link`]. Strict
+/// mode additionally verifies outcome proofs through an
+/// [`OutcomeProofCheck`].
 pub struct StreamValidator;
 
 impl StreamValidator {
     /// Validate a single worldline stream for all invariants.
-    pub fn validate_stream<R: LedgerReader>(
+    pub fn validate_stream<R: LedgerReader + ?Sized>(
         reader: &R,
         worldline: &WorldlineId,
     ) -> Result<ValidationReport, LedgerError> {
         let receipts = reader.read_all(worldline)?;
+        let base = reader.archived_through(worldline)?;
+        let mut report = Self::validate(worldline, base.as_ref(), &receipts, None);
+        Self::check_links(reader, &receipts, &mut report)?;
+        Ok(report)
+    }
+
+    /// Validate a single worldline stream, including outcome proofs.
+    pub fn validate_stream_strict<R: LedgerReader + ?Sized>(
+        reader: &R,
+        worldline: &WorldlineId,
+        proofs: &dyn OutcomeProofCheck,
+    ) -> Result<ValidationReport, LedgerError> {
+        let receipts = reader.read_all(worldline)?;
+        let base = reader.archived_through(worldline)?;
+        let mut report = Self::validate(worldline, base.as_ref(), &receipts, Some(proofs));
+        Self::check_links(reader, &receipts, &mut report)?;
+        Ok(report)
+    }
+
+    /// Validate receipts that are not held in a ledger, e.g. a stream
+    /// fetched from a peer. `receipts` must be the whole stream from seq 1.
+    /// Linked outcomes are not checked, as their counterparts live in other
+    /// streams.
+    pub fn validate_receipts(worldline: &WorldlineId, receipts: &[Receipt]) -> ValidationReport {
+        Self::validate(worldline, None, receipts, None)
+    }
+
+    /// [`Self::validate_receipts`], including outcome proofs.
+    pub fn validate_receipts_strict(
+        worldline: &WorldlineId,
+        receipts: &[Receipt],
+        proofs: &dyn OutcomeProofCheck,
+    ) -> ValidationReport {
+        Self::validate(worldline, None, receipts, Some(proofs))
+    }
+
+    fn validate(
+        worldline: &WorldlineId,
+        base: Option<&ReceiptRef>,
+        receipts: &[Receipt],
+        proofs: Option<&dyn OutcomeProofCheck>,
+    ) -> ValidationReport {
         let mut violations = Vec::new();
         let mut hash_chain_valid = true;
         let mut sequence_monotonic = true;
         let mut outcomes_attributed = true;
         let mut snapshots_anchored = true;
+        let mut proofs_verified = true;
         let mut seen_hashes = HashSet::new();
         let mut commitment_hashes = HashSet::new();
 
+        let base_seq = base.map_or(0, |b| b.seq);
         for (index, receipt) in receipts.iter().enumerate() {
-            let expected_seq = (index + 1) as u64;
+            let expected_seq = base_seq + (index + 1) as u64;
             if receipt.seq() != expected_seq {
                 sequence_monotonic = false;
                 violations.push(Violation {
@@ -76,7 +147,7 @@ impl StreamValidator {
 
             // Check prev_hash link
             let expected_prev = if index == 0 {
-                None
+                base.map(|b| b.receipt_hash)
             } else {
                 Some(receipts[index - 1].receipt_hash())
             };
@@ -89,10 +160,9 @@ impl StreamValidator {
                 });
             }
 
-            // Recompute and verify hash
-            let computed = recompute_hash(receipt);
-            if let Ok(h) = computed {
-                if h != receipt.receipt_hash() {
+            // Recompute and verify hash (canonical or legacy JSON encoding)
+            if let Ok(encoding) = detect_encoding(receipt) {
+                if encoding.is_none() {
                     hash_chain_valid = false;
                     violations.push(Violation {
                         seq: receipt.seq(),
@@ -118,6 +188,41 @@ impl StreamValidator {
                             description: "outcome references missing commitment".into(),
                         });
                     }
+                    // Time anchors and log publications must cover a receipt
+                    // already in the stream.
+                    let anchored = o.metadata.get(ANCHOR_RECEIPT_KEY).map(|hex| {
+                        hex::decode(hex)
+                            .ok()
+                            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+                    });
+                    if let (Some(_), Some(anchored)) = (proofs, anchored) {
+                        if !anchored.is_some_and(|hash| seen_hashes.contains(&hash)) {
+                            proofs_verified = false;
+                            violations.push(Violation {
+                                seq: receipt.seq(),
+                                kind: ViolationKind::InvalidProof,
+                                description: "anchor for a receipt not earlier in the stream"
+                                    .into(),
+                            });
+                        }
+                    }
+                    if let Some(proofs) = proofs.filter(|_| !o.proofs.is_empty()) {
+                        for check in proofs.check_outcome_proofs(o) {
+                            match check.result {
+                                // A redacted artifact is accounted for by
+                                // its redaction receipt, not a broken proof.
+                                Ok(()) | Err(ProofError::Redacted(_)) => {}
+                                Err(e) => {
+                                    proofs_verified = false;
+                                    violations.push(Violation {
+                                        seq: receipt.seq(),
+                                        kind: ViolationKind::InvalidProof,
+                                        description: format!("{}: {e}", check.proof.uri),
+                                    });
+                                }
+                            }
+                        }
+                    }
                 }
                 Receipt::Snapshot(s) => {
                     if !seen_hashes.contains(&s.anchored_receipt_hash) {
@@ -132,19 +237,114 @@ impl StreamValidator {
             }
         }
 
-        Ok(ValidationReport {
+        ValidationReport {
             worldline: worldline.clone(),
             receipt_count: receipts.len() as u64,
             hash_chain_valid,
             sequence_monotonic,
             outcomes_attributed,
             snapshots_anchored,
+            proofs_verified,
+            links_resolved: true,
             violations,
-        })
+        }
+    }
+
+    /// Check that `commitment` may record an outcome linked to the
+    /// commitment `counterpart` names: an accepted commitment of another
+    /// worldline, held by `reader`, with the same commitment id, whose own
+    /// outcome, if recorded, names `commitment` back.
+    ///
+    /// A counterpart on a worldline with archived receipts may have been
+    /// archived, and then cannot be checked; it is taken as resolved.
+    pub fn validate_link<R: LedgerReader + ?Sized>(
+        reader: &R,
+        commitment: &CommitmentReceipt,
+        counterpart: &CrossWorldlineRef,
+    ) -> Result<(), LedgerError> {
+        let broken = |reason: String| LedgerError::IntegrityViolation {
+            seq: commitment.seq,
+            reason,
+        };
+        if counterpart.worldline == commitment.worldline {
+            return Err(broken("linked commitment names its own worldline".into()));
+        }
+        let other = match reader.get_by_hash(counterpart.receipt_hash)? {
+            Some(Receipt::Commitment(c)) if c.worldline == counterpart.worldline => c,
+            None if reader.archived_through(&counterpart.worldline)?.is_some() => return Ok(()),
+            _ => return Err(broken(format!("counterpart commitment {counterpart} not found"))),
+        };
+        if other.commitment_id != commitment.commitment_id {
+            return Err(broken(format!(
+                "counterpart commitment {counterpart} has another commitment id"
+            )));
+        }
+        if other.decision != Decision::Accepted {
+            return Err(broken(format!("counterpart commitment {counterpart} was not accepted")));
+        }
+        let back = CrossWorldlineRef::new(commitment.worldline.clone(), commitment.receipt_hash);
+        let unlinked = reader
+            .read_all(&other.worldline)?
+            .iter()
+            .filter_map(Receipt::as_outcome)
+            .filter(|o| o.commitment_receipt_hash == other.receipt_hash)
+            .any(|o| {
+                o.metadata.get(LINK_COUNTERPART_KEY).and_then(|uri| CrossWorldlineRef::parse(uri))
+                    != Some(back.clone())
+            });
+        if unlinked {
+            return Err(broken(format!(
+                "outcome of counterpart commitment {counterpart} does not link back"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Flag the linked outcomes in `receipts` whose counterpart fails
+    /// [`Self::validate_link`].
+    fn check_links<R: LedgerReader + ?Sized>(
+        reader: &R,
+        receipts: &[Receipt],
+        report: &mut ValidationReport,
+    ) -> Result<(), LedgerError> {
+        let commitments: HashMap<[u8; 32], &CommitmentReceipt> = receipts
+            .iter()
+            .filter_map(Receipt::as_commitment)
+            .map(|c| (c.receipt_hash, c))
+            .collect();
+        for outcome in receipts.iter().filter_map(Receipt::as_outcome) {
+            let Some(uri) = outcome.metadata.get(LINK_COUNTERPART_KEY) else {
+                continue;
+            };
+            // An outcome without its commitment is already unattributed.
+            let Some(commitment) = commitments.get(&outcome.commitment_receipt_hash) else {
+                continue;
+            };
+            let checked = match CrossWorldlineRef::parse(uri) {
+                Some(counterpart) => Self::validate_link(reader, commitment, &counterpart),
+                None => Err(LedgerError::IntegrityViolation {
+                    seq: outcome.seq,
+                    reason: format!("link counterpart {uri:?} is not a receipt reference"),
+                }),
+            };
+            match checked {
+                Ok(()) => {}
+                Err(LedgerError::IntegrityViolation { reason, .. }) => {
+                    report.links_resolved = false;
+                    report.violations.push(Violation {
+                        seq: outcome.seq,
+                        kind: ViolationKind::UnresolvedLink,
+                        description: reason,
+                    });
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
     }
 
     /// Validate all worldlines in the ledger.
-    pub fn validate_all<R: LedgerReader>(
+    pub fn validate_all<R: LedgerReader + ?Sized>(
         reader: &R,
     ) -> Result<Vec<ValidationReport>, LedgerError> {
         let worldlines = reader.worldlines()?;
@@ -156,17 +356,6 @@ impl StreamValidator {
     }
 }
 
-fn recompute_hash(receipt: &Receipt) -> Result<[u8; 32], LedgerError> {
-    let mut canonical = receipt.clone();
-    canonical.set_receipt_hash([0; 32]);
-    let encoded = serde_json::to_vec(&canonical)
-        .map_err(|e| LedgerError::Serialization(e.to_string()))?;
-    let mut hasher = blake3::Hasher::new();
-    hasher.update(b"wll-receipt-v1:");
-    hasher.update(&encoded);
-    Ok(*hasher.finalize().as_bytes())
-}
-
 #[cfg(test)]
 mod tests {
     use std::collections::BTreeMap;
@@ -214,6 +403,7 @@ mod tests {
                     state_updates: vec![StateUpdate {
                         key: "k".into(),
                         value: Value::from(1),
+                        namespace: None,
                     }],
                     metadata: BTreeMap::new(),
                 },
@@ -243,6 +433,88 @@ mod tests {
         assert!(reports.iter().all(|r| r.is_valid()));
     }
 
+    #[test]
+    fn strict_mode_reports_failed_proofs() {
+        struct RejectAll;
+        impl OutcomeProofCheck for RejectAll {
+            fn check_outcome_proofs(&self, outcome: &OutcomeReceipt) -> Vec<ProofCheck> {
+                outcome
+                    .proofs
+                    .iter()
+                    .map(|proof| ProofCheck {
+                        proof: proof.clone(),
+                        result: Err(crate::proof::ProofError::DigestMismatch),
+                    })
+                    .collect()
+            }
+        }
+
+        let ledger = InMemoryLedger::default();
+        let wid = worldline(3);
+        let c = ledger
+            .append_commitment(&proposal(&wid), &Decision::Accepted, [1; 32])
+            .unwrap();
+        ledger
+            .append_outcome(
+                c.receipt_hash,
+                &OutcomeRecord {
+                    effects: vec![],
+                    proofs: vec![ProofRef {
+                        uri: "obj://proof".into(),
+                        digest: [4; 32],
+                    }],
+                    state_updates: vec![],
+                    metadata: BTreeMap::new(),
+                },
+            )
+            .unwrap();
+
+        assert!(StreamValidator::validate_stream(&ledger, &wid).unwrap().is_valid());
+        let report = StreamValidator::validate_stream_strict(&ledger, &wid, &RejectAll).unwrap();
+        assert!(!report.proofs_verified);
+        assert_eq!(report.violations.len(), 1);
+        assert_eq!(report.violations[0].kind, ViolationKind::InvalidProof);
+        assert!(report.violations[0].description.starts_with("obj://proof: "));
+    }
+
+    #[test]
+    fn strict_mode_rejects_dangling_time_anchor() {
+        struct AcceptAll;
+        impl OutcomeProofCheck for AcceptAll {
+            fn check_outcome_proofs(&self, _outcome: &OutcomeReceipt) -> Vec<ProofCheck> {
+                vec![]
+            }
+        }
+
+        let ledger = InMemoryLedger::default();
+        let wid = worldline(4);
+        for anchored in [None, Some([9; 32])] {
+            let c = ledger
+                .append_commitment(&proposal(&wid), &Decision::Accepted, [1; 32])
+                .unwrap();
+            let anchored = anchored.unwrap_or(c.receipt_hash);
+            ledger
+                .append_outcome(
+                    c.receipt_hash,
+                    &OutcomeRecord {
+                        effects: vec![],
+                        proofs: vec![],
+                        state_updates: vec![],
+                        metadata: BTreeMap::from([(
+                            ANCHOR_RECEIPT_KEY.to_string(),
+                            hex::encode(anchored),
+                        )]),
+                    },
+                )
+                .unwrap();
+        }
+
+        let report = StreamValidator::validate_stream_strict(&ledger, &wid, &AcceptAll).unwrap();
+        assert!(!report.proofs_verified);
+        assert_eq!(report.violations.len(), 1);
+        assert_eq!(report.violations[0].seq, 4);
+    }
+
     #[test]
     fn empty_worldline_is_valid() {
         let ledger = InMemoryLedger::default();
@@ -0,0 +1,63 @@
+//! Pack write and read throughput.
+
+use std::path::Path;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use wll_pack::{PackReader, PackWriter, PackWriterOptions};
+use wll_store::{ObjectKind, StoredObject};
+
+/// `count` blobs of `size` bytes: repetitive text with a per-object
+/// header, so zstd has something to do without every object being equal.
+fn objects(count: usize, size: usize) -> Vec<StoredObject> {
+    (0..count)
+        .map(|index| {
+            let mut data = format!("object {index}\n").into_bytes();
+            let line = format!("fn item_{index}() -> usize {{ {index} }}\n");
+            while data.len() < size {
+                data.extend_from_slice(line.as_bytes());
+            }
+            data.truncate(size);
+            StoredObject::new(ObjectKind::Blob, data)
+        })
+        .collect()
+}
+
+fn write_pack(objects: &[StoredObject], threads: usize) -> Vec<u8> {
+    let options = PackWriterOptions { threads, ..PackWriterOptions::default() };
+    let mut writer = PackWriter::new(Path::new("bench.pack")).with_options(options);
+    for object in objects {
+        writer.add_stored_object(object);
+    }
+    writer.finish_to_bytes().unwrap().0
+}
+
+fn pack(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pack");
+    for (count, size) in [(1_000, 1024), (100, 64 * 1024)] {
+        let objects = objects(count, size);
+        let label = format!("{count}x{size}");
+        group.throughput(Throughput::Bytes((count * size) as u64));
+
+        group.bench_with_input(BenchmarkId::new("write", &label), &objects, |b, objects| {
+            b.iter(|| write_pack(objects, 0))
+        });
+        group.bench_with_input(BenchmarkId::new("write-serial", &label), &objects, |b, objects| {
+            b.iter(|| write_pack(objects, 1))
+        });
+
+        let bytes = write_pack(&objects, 0);
+        let ids: Vec<_> = objects.iter().map(StoredObject::compute_id).collect();
+        group.bench_with_input(BenchmarkId::new("read", &label), &bytes, |b, bytes| {
+            b.iter(|| {
+                let reader = PackReader::from_pack_bytes(bytes.clone()).unwrap();
+                for id in &ids {
+                    reader.read_object(id).unwrap().unwrap();
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, pack);
+criterion_main!(benches);
@@ -0,0 +1,109 @@
+//! Packing loose objects automatically once there are too many of them.
+//!
+//! [`PackManager::auto_pack`](crate::PackManager::auto_pack) measures the
+//! loose objects of a [`FileObjectStore`] that no loaded pack holds yet and,
+//! when an [`AutoPackPolicy`] threshold is exceeded, packs them into a new
+//! pack. Objects written within the policy's exclusion window stay loose
+//! until a later run, so objects a commit is still writing are not split
+//! across a pack and the loose store.
+
+use std::time::{Duration, SystemTime};
+
+use wll_store::FileObjectStore;
+use wll_types::ObjectId;
+
+use crate::error::PackResult;
+
+/// When loose objects get packed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AutoPackPolicy {
+    /// Pack once more loose objects than this are in no pack. `None`
+    /// disables the count threshold.
+    pub max_loose_objects: Option<usize>,
+    /// Pack once the loose objects in no pack take more bytes on disk than
+    /// this. `None` disables the size threshold.
+    pub max_loose_bytes: Option<u64>,
+    /// Loose objects modified more recently than this are left loose.
+    pub min_age: Duration,
+}
+
+impl Default for AutoPackPolicy {
+    fn default() -> Self {
+        Self {
+            max_loose_objects: Some(1024),
+            max_loose_bytes: Some(64 * 1024 * 1024),
+            min_age: Duration::from_secs(60),
+        }
+    }
+}
+
+impl AutoPackPolicy {
+    /// A policy that never packs.
+    pub fn disabled() -> Self {
+        Self { max_loose_objects: None, max_loose_bytes: None, ..Self::default() }
+    }
+
+    pub fn with_max_loose_objects(mut self, max: Option<usize>) -> Self {
+        self.max_loose_objects = max;
+        self
+    }
+
+    pub fn with_max_loose_bytes(mut self, max: Option<u64>) -> Self {
+        self.max_loose_bytes = max;
+        self
+    }
+
+    pub fn with_min_age(mut self, min_age: Duration) -> Self {
+        self.min_age = min_age;
+        self
+    }
+
+    /// Whether `loose` exceeds a threshold of this policy.
+    pub fn exceeded_by(&self, loose: &LooseObjects) -> bool {
+        self.max_loose_objects.is_some_and(|max| loose.objects > max)
+            || self.max_loose_bytes.is_some_and(|max| loose.bytes > max)
+    }
+}
+
+/// Loose objects of a store that no loaded pack holds.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LooseObjects {
+    pub objects: usize,
+    /// Size on disk of their files.
+    pub bytes: u64,
+    /// Those older than the policy's `min_age`, which a pack may take.
+    pub eligible: Vec<ObjectId>,
+}
+
+impl LooseObjects {
+    /// Measure the loose objects of `store` for which `packed` is false,
+    /// counting as eligible those last modified before `now - min_age`.
+    pub(crate) fn measure(
+        store: &FileObjectStore,
+        packed: impl Fn(&ObjectId) -> bool,
+        min_age: Duration,
+        now: SystemTime,
+    ) -> PackResult<Self> {
+        let mut loose = Self::default();
+        for id in store.all_ids()? {
+            if packed(&id) {
+                continue;
+            }
+            // A file removed since the listing is simply no longer loose.
+            let Ok(metadata) = std::fs::metadata(store.object_path(&id)) else {
+                continue;
+            };
+            loose.objects += 1;
+            loose.bytes += metadata.len();
+            let age = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .unwrap_or_default();
+            if age >= min_age {
+                loose.eligible.push(id);
+            }
+        }
+        Ok(loose)
+    }
+}
@@ -0,0 +1,122 @@
+use wll_types::ObjectId;
+
+/// Bits per object: with [`HASHES`] probes this gives roughly a 1% false
+/// positive rate.
+const BITS_PER_OBJECT: usize = 10;
+
+/// Probes per object.
+const HASHES: u32 = 7;
+
+/// Bloom filter over object ids.
+///
+/// Object ids are already uniform BLAKE3 digests, so the probe positions are
+/// derived from the id bytes by double hashing rather than hashing again.
+/// A negative answer is definite; a positive one still needs a real lookup.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BloomFilter {
+    words: Vec<u64>,
+    hashes: u32,
+}
+
+impl BloomFilter {
+    /// An empty filter sized for `expected` objects.
+    pub fn with_capacity(expected: usize) -> Self {
+        let bits = expected.max(1).saturating_mul(BITS_PER_OBJECT);
+        Self {
+            words: vec![0; bits.div_ceil(64)],
+            hashes: HASHES,
+        }
+    }
+
+    /// A filter holding every id in `ids`.
+    pub fn from_ids<'a>(ids: impl ExactSizeIterator<Item = &'a ObjectId>) -> Self {
+        let mut filter = Self::with_capacity(ids.len());
+        for id in ids {
+            filter.insert(id);
+        }
+        filter
+    }
+
+    /// Rebuild a filter from its serialized parts. Returns `None` if they
+    /// could not have come from [`BloomFilter::with_capacity`].
+    pub fn from_parts(words: Vec<u64>, hashes: u32) -> Option<Self> {
+        if words.is_empty() || hashes == 0 || hashes > 32 {
+            return None;
+        }
+        Some(Self { words, hashes })
+    }
+
+    /// The bit array, 64 bits per word.
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    /// Probes per object.
+    pub fn hashes(&self) -> u32 {
+        self.hashes
+    }
+
+    /// Record `id` as present.
+    pub fn insert(&mut self, id: &ObjectId) {
+        for bit in self.probes(id) {
+            self.words[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+
+    /// Whether `id` may be present. `false` means it is definitely absent.
+    pub fn may_contain(&self, id: &ObjectId) -> bool {
+        self.probes(id)
+            .all(|bit| self.words[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+
+    fn probes(&self, id: &ObjectId) -> impl Iterator<Item = usize> {
+        let bytes = id.as_bytes();
+        let h1 = u64::from_le_bytes(bytes[0..8].try_into().unwrap());
+        let h2 = u64::from_le_bytes(bytes[8..16].try_into().unwrap()) | 1;
+        let bits = (self.words.len() * 64) as u64;
+        (0..u64::from(self.hashes))
+            .map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % bits) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(range: std::ops::Range<u32>) -> Vec<ObjectId> {
+        range.map(|i| ObjectId::from_bytes(&i.to_le_bytes())).collect()
+    }
+
+    #[test]
+    fn inserted_ids_are_always_found() {
+        let present = ids(0..1000);
+        let filter = BloomFilter::from_ids(present.iter());
+        assert!(present.iter().all(|id| filter.may_contain(id)));
+    }
+
+    #[test]
+    fn false_positive_rate_is_low() {
+        let filter = BloomFilter::from_ids(ids(0..10_000).iter());
+        let false_positives = ids(10_000..20_000)
+            .iter()
+            .filter(|id| filter.may_contain(id))
+            .count();
+        assert!(false_positives < 300, "{false_positives} false positives in 10000");
+    }
+
+    #[test]
+    fn empty_filter_contains_nothing() {
+        let filter = BloomFilter::with_capacity(0);
+        assert!(!filter.may_contain(&ObjectId::from_bytes(b"anything")));
+    }
+
+    #[test]
+    fn from_parts_rejects_impossible_filters() {
+        assert!(BloomFilter::from_parts(vec![], HASHES).is_none());
+        assert!(BloomFilter::from_parts(vec![0], 0).is_none());
+        assert!(BloomFilter::from_parts(vec![0], 33).is_none());
+        let filter = BloomFilter::from_ids(ids(0..10).iter());
+        let parts = BloomFilter::from_parts(filter.words().to_vec(), filter.hashes());
+        assert_eq!(parts, Some(filter));
+    }
+}
@@ -0,0 +1,165 @@
+//! Binary deltas between two versions of an object.
+//!
+//! A delta rebuilds a target from a base it shares most of its bytes with,
+//! which is the common case for a file changed by one commitment. The
+//! encoding is two varints, the base and target lengths, followed by
+//! instructions:
+//!
+//! ```text
+//! 0x00 <len> <bytes>       insert `len` literal bytes
+//! 0x01 <offset> <len>      copy `len` bytes of the base from `offset`
+//! ```
+//!
+//! Matches are found through an index of fixed-size base blocks and then
+//! extended in both directions, which finds every shared run longer than
+//! two blocks.
+
+use std::collections::HashMap;
+
+use crate::error::{PackError, PackResult};
+use crate::writer::{decode_varint, encode_varint};
+
+/// Size of the base blocks matches are looked up by.
+const BLOCK: usize = 16;
+
+const INSERT: u8 = 0x00;
+const COPY: u8 = 0x01;
+
+/// Encode `target` as a delta against `base`.
+pub fn compute_delta(base: &[u8], target: &[u8]) -> Vec<u8> {
+    let mut delta = Vec::new();
+    encode_varint(&mut delta, base.len() as u64);
+    encode_varint(&mut delta, target.len() as u64);
+
+    let mut blocks: HashMap<&[u8], usize> = HashMap::new();
+    for (i, block) in base.chunks_exact(BLOCK).enumerate() {
+        blocks.entry(block).or_insert(i * BLOCK);
+    }
+
+    let mut literal_start = 0;
+    let mut pos = 0;
+    while pos + BLOCK <= target.len() {
+        let Some(&found) = blocks.get(&target[pos..pos + BLOCK]) else {
+            pos += 1;
+            continue;
+        };
+        // Grow the match back into the pending literal, then forward.
+        let (mut from, mut start) = (found, pos);
+        while from > 0 && start > literal_start && base[from - 1] == target[start - 1] {
+            from -= 1;
+            start -= 1;
+        }
+        let mut len = pos + BLOCK - start;
+        while from + len < base.len()
+            && start + len < target.len()
+            && base[from + len] == target[start + len]
+        {
+            len += 1;
+        }
+        push_insert(&mut delta, &target[literal_start..start]);
+        delta.push(COPY);
+        encode_varint(&mut delta, from as u64);
+        encode_varint(&mut delta, len as u64);
+        pos = start + len;
+        literal_start = pos;
+    }
+    push_insert(&mut delta, &target[literal_start..]);
+    delta
+}
+
+fn push_insert(delta: &mut Vec<u8>, literal: &[u8]) {
+    if literal.is_empty() {
+        return;
+    }
+    delta.push(INSERT);
+    encode_varint(delta, literal.len() as u64);
+    delta.extend_from_slice(literal);
+}
+
+/// Rebuild the target a delta from [`compute_delta`] encodes.
+///
+/// Every instruction is bounds-checked against the base and the delta, and
+/// the output may not grow past the target length the delta declares.
+pub fn apply_delta(base: &[u8], delta: &[u8]) -> PackResult<Vec<u8>> {
+    let invalid = |reason: &str| PackError::InvalidDelta(reason.into());
+    let mut pos = 0;
+    let varint = |pos: &mut usize| -> PackResult<usize> {
+        let (value, consumed) = decode_varint(delta.get(*pos..).unwrap_or_default())?;
+        *pos += consumed;
+        usize::try_from(value).map_err(|_| invalid("length out of range"))
+    };
+
+    if varint(&mut pos)? != base.len() {
+        return Err(invalid("base length does not match the base"));
+    }
+    let target_len = varint(&mut pos)?;
+    let mut target = Vec::with_capacity(target_len.min(base.len() + delta.len()));
+    while pos < delta.len() {
+        let op = delta[pos];
+        pos += 1;
+        let bytes = match op {
+            INSERT => {
+                let len = varint(&mut pos)?;
+                let bytes = pos
+                    .checked_add(len)
+                    .and_then(|end| delta.get(pos..end))
+                    .ok_or_else(|| invalid("insert runs past the delta"))?;
+                pos += len;
+                bytes
+            }
+            COPY => {
+                let from = varint(&mut pos)?;
+                let len = varint(&mut pos)?;
+                from.checked_add(len)
+                    .and_then(|end| base.get(from..end))
+                    .ok_or_else(|| invalid("copy runs past the base"))?
+            }
+            other => {
+                return Err(PackError::InvalidDelta(format!("unknown instruction {other}")))
+            }
+        };
+        if target.len() + bytes.len() > target_len {
+            return Err(invalid("target longer than declared"));
+        }
+        target.extend_from_slice(bytes);
+    }
+    if target.len() != target_len {
+        return Err(invalid("target shorter than declared"));
+    }
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delta_roundtrip_is_small_for_a_small_edit() {
+        let base: Vec<u8> = (0..4000).flat_map(|i| format!("line {i}\n").into_bytes()).collect();
+        let mut target = base.clone();
+        target.splice(20_000..20_000, b"an inserted line\n".iter().copied());
+        target.truncate(target.len() - 300);
+        target.extend_from_slice(b"new tail");
+
+        let delta = compute_delta(&base, &target);
+        assert!(delta.len() < 100, "delta is {} bytes", delta.len());
+        assert_eq!(apply_delta(&base, &delta).unwrap(), target);
+        assert_eq!(apply_delta(b"", &compute_delta(b"", b"abc")).unwrap(), b"abc");
+        assert!(apply_delta(&base[1..], &delta).is_err());
+    }
+
+    #[test]
+    fn apply_delta_rejects_out_of_bounds_instructions() {
+        let mut delta = Vec::new();
+        encode_varint(&mut delta, 4);
+        encode_varint(&mut delta, 8);
+        delta.extend_from_slice(&[COPY, 2, 8]);
+        assert!(matches!(apply_delta(b"abcd", &delta), Err(PackError::InvalidDelta(_))));
+
+        let mut delta = Vec::new();
+        encode_varint(&mut delta, 4);
+        encode_varint(&mut delta, 2);
+        delta.extend_from_slice(&[COPY, 0, 4]);
+        assert!(apply_delta(b"abcd", &delta).is_err());
+    }
+}
@@ -12,6 +12,9 @@ pub enum PackObjectKind {
 }
 
 impl PackObjectKind {
+    /// Type byte of delta entries, which are followed by their base id.
+    pub const DELTA_TYPE_BYTE: u8 = 6;
+
     /// Serialize to a type byte for the pack format.
     pub fn type_byte(&self) -> u8 {
         match self {
@@ -20,7 +23,8 @@ impl PackObjectKind {
             Self::Full(ObjectKind::Receipt) => 3,
             Self::Full(ObjectKind::Snapshot) => 4,
             Self::Full(ObjectKind::Pack) => 5,
-            Self::Delta { .. } => 6,
+            Self::Delta { .. } => Self::DELTA_TYPE_BYTE,
+            Self::Full(ObjectKind::Tombstone) => 7,
         }
     }
 
@@ -32,6 +36,7 @@ impl PackObjectKind {
             3 => Some(Self::Full(ObjectKind::Receipt)),
             4 => Some(Self::Full(ObjectKind::Snapshot)),
             5 => Some(Self::Full(ObjectKind::Pack)),
+            7 => Some(Self::Full(ObjectKind::Tombstone)),
             _ => None,
         }
     }
@@ -91,10 +96,17 @@ mod tests {
         assert_eq!(kind.type_byte(), 6);
     }
 
+    #[test]
+    fn type_byte_roundtrip_tombstone() {
+        let kind = PackObjectKind::Full(ObjectKind::Tombstone);
+        assert_eq!(kind.type_byte(), 7);
+        assert_eq!(PackObjectKind::from_type_byte(7), Some(kind));
+    }
+
     #[test]
     fn from_type_byte_unknown() {
         assert!(PackObjectKind::from_type_byte(0).is_none());
-        assert!(PackObjectKind::from_type_byte(7).is_none());
+        assert!(PackObjectKind::from_type_byte(8).is_none());
         assert!(PackObjectKind::from_type_byte(255).is_none());
     }
 }
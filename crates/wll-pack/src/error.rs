@@ -1,5 +1,6 @@
 use thiserror::Error;
-use wll_types::ObjectId;
+use wll_store::StoreError;
+use wll_types::{Coded, ErrorCode, ObjectId};
 
 #[derive(Debug, Error)]
 pub enum PackError {
@@ -30,6 +31,9 @@ pub enum PackError {
     #[error("delta base not found: {0}")]
     DeltaBaseNotFound(ObjectId),
 
+    #[error("invalid delta: {0}")]
+    InvalidDelta(String),
+
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -38,6 +42,30 @@ pub enum PackError {
 
     #[error("index corrupted: {0}")]
     IndexCorrupted(String),
+
+    #[error("object store error: {0}")]
+    Store(#[from] StoreError),
+}
+
+impl Coded for PackError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::InvalidMagic { .. }
+            | Self::ChecksumMismatch
+            | Self::CorruptEntry { .. }
+            | Self::CrcMismatch { .. }
+            | Self::DecompressionFailed(_)
+            | Self::DeltaBaseNotFound(_)
+            | Self::InvalidDelta(_)
+            | Self::IndexCorrupted(_) => ErrorCode::PackCorrupt,
+            Self::UnsupportedVersion(_) => ErrorCode::Unsupported,
+            Self::ObjectNotFound(_) => ErrorCode::PackObjectNotFound,
+            Self::CompressionFailed(_) => ErrorCode::Internal,
+            Self::Io(_) => ErrorCode::Io,
+            Self::Serialization(_) => ErrorCode::Serialization,
+            Self::Store(e) => e.code(),
+        }
+    }
 }
 
 pub type PackResult<T> = Result<T, PackError>;
@@ -1,5 +1,6 @@
-use wll_types::ObjectId;
+use wll_types::{FormatSpec, HashAlgo, ObjectId};
 
+use crate::bloom::BloomFilter;
 use crate::error::{PackError, PackResult};
 
 /// Pack index for fast random-access lookups.
@@ -9,7 +10,13 @@ use crate::error::{PackError, PackResult};
 /// - Sorted ObjectId array
 /// - CRC32 array (parallel)
 /// - Offset array (parallel)
+/// - Hash algorithm tag array (parallel, one byte each; version 2 on)
 /// - Pack checksum
+/// - Bloom filter trailer: `BLOM`, probe count, word count, words
+///
+/// Version 1 has no algorithm tags, so every id reads back as BLAKE3, and
+/// its bloom trailer is optional: readers that predate the trailer stop at
+/// the pack checksum. Version 2 always carries the trailer.
 #[derive(Clone, Debug)]
 pub struct PackIndex {
     pub fan_out: [u32; 256],
@@ -17,8 +24,17 @@ pub struct PackIndex {
     pub crc32s: Vec<u32>,
     pub offsets: Vec<u64>,
     pub pack_checksum: [u8; 32],
+    /// Answers "definitely absent" without a binary search. `None` for
+    /// indexes written before the filter existed.
+    pub bloom: Option<BloomFilter>,
 }
 
+/// Magic opening the bloom filter trailer.
+const BLOOM_MAGIC: &[u8; 4] = b"BLOM";
+
+/// Versions of the `.idx` format.
+pub const INDEX_FORMAT: FormatSpec = FormatSpec { name: "pack-index", current: 2, oldest: 1 };
+
 impl PackIndex {
     /// Build an index from (id, crc32, offset) entries and a pack checksum.
     pub fn build(mut entries: Vec<(ObjectId, u32, u64)>, pack_checksum: [u8; 32]) -> Self {
@@ -38,22 +54,27 @@ impl PackIndex {
         // Build fan-out: fan_out[i] = count of objects with first byte <= i
         for (i, id) in object_ids.iter().enumerate() {
             let first_byte = id.as_bytes()[0] as usize;
-            for slot in first_byte..256 {
-                fan_out[slot] = (i + 1) as u32;
+            for slot in fan_out.iter_mut().skip(first_byte) {
+                *slot = (i + 1) as u32;
             }
         }
 
+        let bloom = Some(BloomFilter::from_ids(object_ids.iter()));
         Self {
             fan_out,
             object_ids,
             crc32s,
             offsets,
             pack_checksum,
+            bloom,
         }
     }
 
     /// Look up an object's (offset, crc32) by ID.
     pub fn lookup(&self, id: &ObjectId) -> Option<(u64, u32)> {
+        if self.bloom.as_ref().is_some_and(|bloom| !bloom.may_contain(id)) {
+            return None;
+        }
         let first_byte = id.as_bytes()[0] as usize;
         let start = if first_byte == 0 {
             0
@@ -62,11 +83,11 @@ impl PackIndex {
         };
         let end = self.fan_out[first_byte] as usize;
 
-        let range = &self.object_ids[start..end];
+        let range = self.object_ids.get(start..end)?;
         match range.binary_search_by(|probe| probe.as_bytes().cmp(id.as_bytes())) {
             Ok(pos) => {
                 let idx = start + pos;
-                Some((self.offsets[idx], self.crc32s[idx]))
+                Some((*self.offsets.get(idx)?, *self.crc32s.get(idx)?))
             }
             Err(_) => None,
         }
@@ -82,11 +103,28 @@ impl PackIndex {
         self.lookup(id).is_some()
     }
 
-    /// Serialize to bytes.
+    /// Serialize to bytes in the current version.
     pub fn to_bytes(&self) -> PackResult<Vec<u8>> {
+        self.to_bytes_as(INDEX_FORMAT.current)
+    }
+
+    /// Serialize to bytes in `version`, which must be readable. Version 1
+    /// can only hold BLAKE3 ids.
+    pub fn to_bytes_as(&self, version: u32) -> PackResult<Vec<u8>> {
+        if !INDEX_FORMAT.reads(version) {
+            return Err(PackError::UnsupportedVersion(version));
+        }
+        let tagged = version >= 2;
+        let untaggable = |id: &&ObjectId| !tagged && id.algo() != HashAlgo::Blake3;
+        if let Some(id) = self.object_ids.iter().find(untaggable) {
+            return Err(PackError::Serialization(format!(
+                "pack index v1 cannot hold {} id {id}",
+                id.algo()
+            )));
+        }
         let mut buf = Vec::new();
         buf.extend_from_slice(b"WLLI");
-        buf.extend_from_slice(&1u32.to_be_bytes());
+        buf.extend_from_slice(&version.to_be_bytes());
         for &count in &self.fan_out {
             buf.extend_from_slice(&count.to_be_bytes());
         }
@@ -99,25 +137,40 @@ impl PackIndex {
         for &offset in &self.offsets {
             buf.extend_from_slice(&offset.to_be_bytes());
         }
+        if tagged {
+            buf.extend(self.object_ids.iter().map(|id| id.algo().tag()));
+        }
         buf.extend_from_slice(&self.pack_checksum);
+        let built;
+        let bloom = match &self.bloom {
+            None if tagged => {
+                built = BloomFilter::from_ids(self.object_ids.iter());
+                Some(&built)
+            }
+            bloom => bloom.as_ref(),
+        };
+        if let Some(bloom) = bloom {
+            buf.extend_from_slice(BLOOM_MAGIC);
+            buf.extend_from_slice(&bloom.hashes().to_be_bytes());
+            buf.extend_from_slice(&(bloom.words().len() as u32).to_be_bytes());
+            for &word in bloom.words() {
+                buf.extend_from_slice(&word.to_be_bytes());
+            }
+        }
         Ok(buf)
     }
 
     /// Deserialize from bytes.
+    ///
+    /// Index files are read from disk and received from peers, so every
+    /// length is checked and the fan-out table must agree with the sorted
+    /// ids; anything else is [`PackError::IndexCorrupted`].
     pub fn from_bytes(data: &[u8]) -> PackResult<Self> {
-        if data.len() < 8 {
-            return Err(PackError::IndexCorrupted("too short".into()));
-        }
-        if &data[0..4] != b"WLLI" {
-            return Err(PackError::InvalidMagic {
-                expected: "WLLI".into(),
-                actual: String::from_utf8_lossy(&data[0..4]).into(),
-            });
-        }
-        let version = u32::from_be_bytes(data[4..8].try_into().unwrap());
-        if version != 1 {
+        let version = Self::format_version(data)?;
+        if !INDEX_FORMAT.reads(version) {
             return Err(PackError::UnsupportedVersion(version));
         }
+        let tagged = version >= 2;
 
         let mut pos = 8;
         if data.len() < pos + 256 * 4 {
@@ -125,38 +178,87 @@ impl PackIndex {
         }
         let mut fan_out = [0u32; 256];
         for entry in &mut fan_out {
-            *entry = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap());
+            *entry = u32::from_be_bytes(read_array(data, pos, "fan-out")?);
             pos += 4;
         }
+        if fan_out.windows(2).any(|pair| pair[0] > pair[1]) {
+            return Err(PackError::IndexCorrupted("fan-out not monotonic".into()));
+        }
 
         let count = fan_out[255] as usize;
-        let needed = pos + count * 32 + count * 4 + count * 8 + 32;
-        if data.len() < needed {
+        let needed = count
+            .checked_mul(32 + 4 + 8 + usize::from(tagged))
+            .and_then(|len| len.checked_add(pos + 32));
+        if needed.map_or(true, |needed| data.len() < needed) {
             return Err(PackError::IndexCorrupted("data truncated".into()));
         }
 
-        let mut object_ids = Vec::with_capacity(count);
-        for _ in 0..count {
-            let mut hash = [0u8; 32];
-            hash.copy_from_slice(&data[pos..pos + 32]);
-            object_ids.push(ObjectId::from_hash(hash));
+        let mut object_ids: Vec<ObjectId> = Vec::with_capacity(count);
+        for i in 0..count {
+            let id = ObjectId::from_hash(read_array(data, pos, "object ids")?);
+            let first_byte = id.as_bytes()[0] as usize;
+            let bucket_start = if first_byte == 0 {
+                0
+            } else {
+                fan_out[first_byte - 1] as usize
+            };
+            if i < bucket_start || i >= fan_out[first_byte] as usize {
+                return Err(PackError::IndexCorrupted(format!(
+                    "object {i} outside its fan-out bucket"
+                )));
+            }
+            if object_ids
+                .last()
+                .is_some_and(|prev| prev.as_bytes() > id.as_bytes())
+            {
+                return Err(PackError::IndexCorrupted(format!(
+                    "object {i} out of order"
+                )));
+            }
+            object_ids.push(id);
             pos += 32;
         }
 
         let mut crc32s = Vec::with_capacity(count);
         for _ in 0..count {
-            crc32s.push(u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()));
+            crc32s.push(u32::from_be_bytes(read_array(data, pos, "crc32s")?));
             pos += 4;
         }
 
         let mut offsets = Vec::with_capacity(count);
         for _ in 0..count {
-            offsets.push(u64::from_be_bytes(data[pos..pos + 8].try_into().unwrap()));
+            offsets.push(u64::from_be_bytes(read_array(data, pos, "offsets")?));
             pos += 8;
         }
 
-        let mut pack_checksum = [0u8; 32];
-        pack_checksum.copy_from_slice(&data[pos..pos + 32]);
+        if tagged {
+            for id in &mut object_ids {
+                let [tag] = read_array(data, pos, "hash algorithms")?;
+                let algo = HashAlgo::from_tag(tag)
+                    .map_err(|e| PackError::IndexCorrupted(e.to_string()))?;
+                *id = ObjectId::from_algo_hash(algo, *id.as_bytes());
+                pos += 1;
+            }
+        }
+
+        let pack_checksum = read_array(data, pos, "pack checksum")?;
+        pos += 32;
+
+        let bloom = if data.get(pos..pos + 4) == Some(BLOOM_MAGIC) {
+            let bloom = read_bloom(data, pos + 4)?;
+            // A filter that misses a listed object would hide it from every
+            // lookup, so it has to cover the whole index to be trusted.
+            if let Some(i) = object_ids.iter().position(|id| !bloom.may_contain(id)) {
+                return Err(PackError::IndexCorrupted(format!(
+                    "bloom filter misses object {i}"
+                )));
+            }
+            Some(bloom)
+        } else if tagged {
+            return Err(PackError::IndexCorrupted("bloom filter missing".into()));
+        } else {
+            None
+        };
 
         Ok(Self {
             fan_out,
@@ -164,8 +266,57 @@ impl PackIndex {
             crc32s,
             offsets,
             pack_checksum,
+            bloom,
         })
     }
+
+    /// The version an index's bytes declare, without reading the rest.
+    pub fn format_version(data: &[u8]) -> PackResult<u32> {
+        if data.len() < 8 {
+            return Err(PackError::IndexCorrupted("too short".into()));
+        }
+        if &data[0..4] != b"WLLI" {
+            return Err(PackError::InvalidMagic {
+                expected: "WLLI".into(),
+                actual: String::from_utf8_lossy(&data[0..4]).into(),
+            });
+        }
+        Ok(u32::from_be_bytes(read_array(data, 4, "version")?))
+    }
+
+    /// Rewrite an index in an older readable version in the current one.
+    /// `None` when it is already current.
+    pub fn upgrade(data: &[u8]) -> PackResult<Option<Vec<u8>>> {
+        if !INDEX_FORMAT.is_outdated(Self::format_version(data)?) {
+            return Ok(None);
+        }
+        Self::from_bytes(data)?.to_bytes().map(Some)
+    }
+}
+
+/// The bloom filter whose probe count starts at `pos`.
+fn read_bloom(data: &[u8], mut pos: usize) -> PackResult<BloomFilter> {
+    let hashes = u32::from_be_bytes(read_array(data, pos, "bloom filter")?);
+    let count = u32::from_be_bytes(read_array(data, pos + 4, "bloom filter")?) as usize;
+    pos += 8;
+    if data.len().saturating_sub(pos) / 8 < count {
+        return Err(PackError::IndexCorrupted("bloom filter truncated".into()));
+    }
+    let mut words = Vec::with_capacity(count);
+    for _ in 0..count {
+        words.push(u64::from_be_bytes(read_array(data, pos, "bloom filter")?));
+        pos += 8;
+    }
+    BloomFilter::from_parts(words, hashes)
+        .ok_or_else(|| PackError::IndexCorrupted("invalid bloom filter".into()))
+}
+
+/// The `N` bytes of `data` at `pos`, or [`PackError::IndexCorrupted`] naming
+/// the truncated `section`.
+fn read_array<const N: usize>(data: &[u8], pos: usize, section: &str) -> PackResult<[u8; N]> {
+    data.get(pos..pos.saturating_add(N))
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| PackError::IndexCorrupted(format!("{section} truncated")))
 }
 
 #[cfg(test)]
@@ -247,6 +398,69 @@ mod tests {
         }
     }
 
+    #[test]
+    fn bloom_filter_roundtrips_through_idx() {
+        let ids = make_ids(300);
+        let entries: Vec<_> = ids.iter().map(|id| (*id, 0, 0)).collect();
+        let idx = PackIndex::build(entries, [0u8; 32]);
+        let idx2 = PackIndex::from_bytes(&idx.to_bytes().unwrap()).unwrap();
+        assert!(idx2.bloom.is_some());
+        assert_eq!(idx2.bloom, idx.bloom);
+        assert!(ids.iter().all(|id| idx2.contains(id)));
+        assert!(!idx2.contains(&ObjectId::from_bytes(b"absent")));
+    }
+
+    #[test]
+    fn v1_index_without_bloom_still_loads() {
+        let ids = make_ids(20);
+        let entries: Vec<_> = ids.iter().map(|id| (*id, 0, 0)).collect();
+        let mut idx = PackIndex::build(entries, [0u8; 32]);
+        idx.bloom = None;
+        let idx2 = PackIndex::from_bytes(&idx.to_bytes_as(1).unwrap()).unwrap();
+        assert!(idx2.bloom.is_none());
+        assert!(ids.iter().all(|id| idx2.contains(id)));
+    }
+
+    #[test]
+    fn v1_indexes_upgrade_to_v2_with_algorithms_and_bloom() {
+        let sha = ObjectId::from_algo_hash(HashAlgo::Sha256, [7; 32]);
+        let ids = make_ids(20);
+        let mut idx = PackIndex::build(ids.iter().map(|id| (*id, 0, 0)).collect(), [0u8; 32]);
+        idx.bloom = None;
+        let v1 = idx.to_bytes_as(1).unwrap();
+
+        let v2 = PackIndex::upgrade(&v1).unwrap().unwrap();
+        assert_eq!(PackIndex::format_version(&v2).unwrap(), 2);
+        assert!(PackIndex::upgrade(&v2).unwrap().is_none());
+        let upgraded = PackIndex::from_bytes(&v2).unwrap();
+        assert!(upgraded.bloom.is_some());
+        assert_eq!(upgraded.object_ids, ids);
+
+        let mixed = PackIndex::build(vec![(ids[0], 0, 0), (sha, 0, 0)], [0u8; 32]);
+        assert!(matches!(mixed.to_bytes_as(1), Err(PackError::Serialization(_))));
+        let read = PackIndex::from_bytes(&mixed.to_bytes().unwrap()).unwrap();
+        assert!(read.object_ids.contains(&sha) && read.contains(&sha));
+    }
+
+    #[test]
+    fn v2_index_without_bloom_is_rejected() {
+        let idx = PackIndex::build(make_ids(3).into_iter().map(|id| (id, 0, 0)).collect(), [0; 32]);
+        let bytes = idx.to_bytes().unwrap();
+        let trailer = 4 + 4 + 4 + idx.bloom.as_ref().unwrap().words().len() * 8;
+        let err = PackIndex::from_bytes(&bytes[..bytes.len() - trailer]).unwrap_err();
+        assert!(matches!(err, PackError::IndexCorrupted(_)));
+    }
+
+    #[test]
+    fn bloom_missing_an_object_is_rejected() {
+        let ids = make_ids(20);
+        let entries: Vec<_> = ids.iter().map(|id| (*id, 0, 0)).collect();
+        let mut idx = PackIndex::build(entries, [0u8; 32]);
+        idx.bloom = Some(BloomFilter::with_capacity(20));
+        let err = PackIndex::from_bytes(&idx.to_bytes().unwrap()).unwrap_err();
+        assert!(matches!(err, PackError::IndexCorrupted(_)));
+    }
+
     #[test]
     fn from_bytes_bad_magic() {
         let err = PackIndex::from_bytes(b"BADMxxxxxxxx").unwrap_err();
@@ -6,24 +6,47 @@
 //! # Architecture
 //!
 //! - **Pack file** (`.pack`): concatenated compressed objects with a BLAKE3 checksum
-//! - **Pack index** (`.idx`): fan-out table + sorted IDs for O(log n) lookups
-//! - **PackWriter**: builds packs from loose objects
-//! - **PackReader**: random-access reading using the index
-//! - **PackManager**: manages multiple packs, repack, and GC
+//! - **Pack index** (`.idx`): fan-out table + sorted IDs for O(log n) lookups,
+//!   with a bloom filter that answers most misses in O(1)
+//! - **PackWriter**: builds packs from loose objects, optionally as deltas
+//!   against bases the pack need not contain (thin packs)
+//! - **PackReader**: random-access reading using the index, fixing of
+//!   received thin packs, and full verification of a pack against its
+//!   checksum and index
+//! - **PackManager**: manages multiple packs, repack, GC, and automatic
+//!   packing of loose objects past an [`AutoPackPolicy`] threshold
+//! - **MultiPackIndex** (`multi-pack-index`): one memory-mapped lookup
+//!   table over every pack, kept up to date by the manager
+//!
+//! Tombstones of redacted objects are packed like any other object and
+//! indexed under the id of the object they replace.
 
+pub mod autopack;
+pub mod bloom;
+pub mod delta;
 pub mod entry;
 pub mod error;
 pub mod index;
 pub mod manager;
+pub mod midx;
 pub mod reader;
+pub mod verify;
 pub mod writer;
 
+pub use autopack::{AutoPackPolicy, LooseObjects};
+pub use bloom::BloomFilter;
+pub use delta::{apply_delta, compute_delta};
 pub use entry::{PackEntry, PackObjectKind};
 pub use error::{PackError, PackResult};
-pub use index::PackIndex;
+pub use index::{PackIndex, INDEX_FORMAT};
 pub use manager::{GcReport, PackManager};
-pub use reader::PackReader;
-pub use writer::{PackFile, PackWriter};
+pub use midx::{MidxEntry, MidxPack, MultiPackIndex, MIDX_FORMAT};
+pub use reader::{PackReader, PACK_FORMAT};
+pub use verify::{EntryCheck, EntryProblem, PackVerification};
+pub use writer::{PackFile, PackWriter, PackWriterOptions};
+
+/// Every on-disk format this crate writes, at the versions it reads.
+pub const FORMATS: [wll_types::FormatSpec; 3] = [PACK_FORMAT, INDEX_FORMAT, MIDX_FORMAT];
 
 #[cfg(test)]
 mod tests {
@@ -158,6 +181,153 @@ mod tests {
         assert_eq!(obj.data, b"disk roundtrip");
     }
 
+    #[test]
+    fn pack_bytes_without_index_roundtrip() {
+        let objects: Vec<StoredObject> = (0..5)
+            .map(|i| make_blob(format!("wire-{i}").as_bytes()))
+            .collect();
+        let mut writer = PackWriter::new(std::path::Path::new("/tmp/test-pack"));
+        for obj in &objects {
+            writer.add_stored_object(obj);
+        }
+        let (bytes, _) = writer.finish_to_bytes().unwrap();
+
+        let reader = PackReader::from_pack_bytes(bytes).unwrap();
+        assert_eq!(reader.object_count(), 5);
+        for obj in &objects {
+            assert_eq!(reader.read_object(&obj.compute_id()).unwrap().as_ref(), Some(obj));
+        }
+    }
+
+    #[test]
+    fn tombstone_is_indexed_under_the_redacted_id() {
+        let blob = make_blob(b"redacted content");
+        let id = blob.compute_id();
+        let key = wll_crypto::SigningKey::from_bytes([3; 32]);
+        let message = wll_store::Tombstone::signed_message(&id, ObjectKind::Blob, blob.size, "gdpr");
+        let tombstone = wll_store::Tombstone {
+            original: id,
+            original_kind: ObjectKind::Blob,
+            original_size: blob.size,
+            reason: "gdpr".into(),
+            signer: key.verifying_key().as_bytes(),
+            signature: key.sign(&message),
+            receipt_hash: [2; 32],
+        }
+        .to_stored_object()
+        .unwrap();
+
+        let mut writer = PackWriter::new(std::path::Path::new("/tmp/test-pack"));
+        writer.add_stored_object(&tombstone);
+        writer.add_stored_object(&make_blob(b"kept"));
+        let (bytes, _) = writer.finish_to_bytes().unwrap();
+
+        let reader = PackReader::from_pack_bytes(bytes).unwrap();
+        assert!(reader.contains(&id));
+        assert_eq!(reader.read_object(&id).unwrap(), Some(tombstone));
+    }
+
+    #[test]
+    fn pack_bytes_with_bad_checksum_rejected() {
+        let mut writer = PackWriter::new(std::path::Path::new("/tmp/test-pack"));
+        writer.add_stored_object(&make_blob(b"tamper"));
+        let (mut bytes, _) = writer.finish_to_bytes().unwrap();
+        bytes[13] ^= 0xff;
+        let err = PackReader::from_pack_bytes(bytes).unwrap_err();
+        assert!(matches!(err, PackError::ChecksumMismatch));
+    }
+
+    #[test]
+    fn verify_reports_every_bad_entry() {
+        let mut writer = PackWriter::new(std::path::Path::new("/tmp/test-pack"));
+        for content in [&b"first"[..], b"second", b"third"] {
+            writer.add_stored_object(&make_blob(content));
+        }
+        let (mut bytes, mut index) = writer.finish_to_bytes().unwrap();
+        let clean = PackReader::from_bytes(bytes.clone(), index.clone()).unwrap().verify();
+        assert!(clean.is_ok());
+        assert_eq!(clean.entries.len(), 3);
+
+        // Damage the last entry's data and misrecord another entry's CRC.
+        let body_len = bytes.len() - 32;
+        bytes[body_len - 1] ^= 0xff;
+        let last = index.offsets.iter().position(|&o| o == clean.entries[2].offset).unwrap();
+        let first = index.offsets.iter().position(|&o| o == 12).unwrap();
+        index.crc32s[first] ^= 1;
+        let report = PackReader::from_bytes(bytes, index.clone()).unwrap().verify();
+
+        assert!(!report.is_ok());
+        assert!(!report.checksum_ok);
+        assert!(report.index_checksum_ok);
+        assert_eq!(report.entries.len(), 3);
+        assert!(report.entries[1].is_ok());
+        assert_eq!(report.entries[0].id, Some(index.object_ids[first]));
+        assert!(matches!(
+            report.entries[0].problems[..],
+            [EntryProblem::CrcMismatch { expected, .. }] if expected == index.crc32s[first]
+        ));
+        assert_eq!(report.entries[2].id, Some(index.object_ids[last]));
+        assert!(matches!(report.entries[2].problems[0], EntryProblem::CrcMismatch { .. }));
+        assert_eq!(report.failures().count(), 2);
+    }
+
+    #[test]
+    fn thin_pack_is_fixed_with_the_receivers_bases() {
+        let shared = "shared line\n".repeat(500);
+        let text = |edit: &str| make_blob(format!("{shared}{edit}").as_bytes());
+        let (old, new, newer) = (text("v1"), text("v2"), text("v3"));
+
+        let mut writer = PackWriter::new(std::path::Path::new("/tmp/test-pack"));
+        writer.add_delta_object(&new, &old);
+        writer.add_delta_object(&newer, &new);
+        let (thin, _) = writer.finish_to_bytes().unwrap();
+        assert!(thin.len() < old.data.len() / 4, "thin pack is {} bytes", thin.len());
+
+        assert!(matches!(
+            PackReader::from_pack_bytes(thin.clone()),
+            Err(PackError::DeltaBaseNotFound(base)) if base == old.compute_id()
+        ));
+        let held = |id: &ObjectId| (*id == old.compute_id()).then(|| old.clone());
+        let fixed = PackReader::from_thin_pack_bytes(thin, held).unwrap();
+        assert_eq!(fixed.object_count(), 3);
+        assert!(fixed.verify().is_ok());
+        for obj in [&old, &new, &newer] {
+            assert_eq!(fixed.read_object(&obj.compute_id()).unwrap().as_ref(), Some(obj));
+        }
+
+        // The fixed pack stands on its own.
+        let reopened = PackReader::from_pack_bytes(fixed.pack_data.clone()).unwrap();
+        assert_eq!(reopened.index().object_ids, fixed.index().object_ids);
+    }
+
+    #[test]
+    fn worker_count_does_not_change_the_pack() {
+        let objects: Vec<StoredObject> = (0..64)
+            .map(|i| make_blob(format!("object {i} ").repeat(i + 1).as_bytes()))
+            .collect();
+        let write = |threads| {
+            let options = PackWriterOptions { threads, ..PackWriterOptions::default() };
+            let mut writer =
+                PackWriter::new(std::path::Path::new("/tmp/test-pack")).with_options(options);
+            for (i, obj) in objects.iter().enumerate() {
+                if i % 2 == 0 {
+                    writer.add_stored_object(obj);
+                } else {
+                    writer.add_object(obj.compute_id(), obj.kind, &obj.data);
+                }
+            }
+            writer.finish_to_bytes().unwrap().0
+        };
+
+        let serial = write(1);
+        assert_eq!(write(0), serial);
+        assert_eq!(write(4), serial);
+        let reader = PackReader::from_pack_bytes(serial).unwrap();
+        for obj in &objects {
+            assert_eq!(reader.read_object(&obj.compute_id()).unwrap().as_ref(), Some(obj));
+        }
+    }
+
     #[test]
     fn large_object_roundtrip() {
         let large_data = vec![0xABu8; 100_000];
@@ -175,4 +345,123 @@ mod tests {
         let obj = reader.read_object(&id).unwrap().unwrap();
         assert_eq!(obj.data, large_data);
     }
+
+    mod props {
+        use proptest::prelude::*;
+
+        use super::*;
+
+        fn blobs() -> impl Strategy<Value = Vec<Vec<u8>>> {
+            prop::collection::vec(prop::collection::vec(any::<u8>(), 0..256), 0..8)
+        }
+
+        fn pack_of(blobs: &[Vec<u8>]) -> (Vec<u8>, PackIndex) {
+            let mut writer = PackWriter::new(std::path::Path::new("/tmp/prop-pack"));
+            for blob in blobs {
+                writer.add_stored_object(&make_blob(blob));
+            }
+            writer.finish_to_bytes().unwrap()
+        }
+
+        /// Recompute the trailing checksum so mutations reach the entry
+        /// parser instead of stopping at the checksum check.
+        fn reseal(pack: &mut [u8]) {
+            if pack.len() >= 32 {
+                let body_len = pack.len() - 32;
+                let checksum = *blake3::hash(&pack[..body_len]).as_bytes();
+                pack[body_len..].copy_from_slice(&checksum);
+            }
+        }
+
+        proptest! {
+            #[test]
+            fn pack_roundtrips(blobs in blobs()) {
+                let (pack, index) = pack_of(&blobs);
+                let reread = PackIndex::from_bytes(&index.to_bytes().unwrap()).unwrap();
+                let scanned = PackReader::from_pack_bytes(pack.clone()).unwrap();
+                let reader = PackReader::from_bytes(pack, reread).unwrap();
+
+                for blob in &blobs {
+                    let id = make_blob(blob).compute_id();
+                    let object = reader.read_object(&id).unwrap().unwrap();
+                    prop_assert_eq!(&object.data, blob);
+                    prop_assert_eq!(scanned.read_object(&id).unwrap().unwrap().data, object.data);
+                }
+            }
+
+            #[test]
+            fn mutated_packs_fail_cleanly(
+                blobs in blobs(),
+                flips in prop::collection::vec((any::<prop::sample::Index>(), 1..=255u8), 1..8),
+                cut in any::<prop::sample::Index>(),
+            ) {
+                let (mut pack, index) = pack_of(&blobs);
+                for (at, mask) in flips {
+                    let at = at.index(pack.len());
+                    pack[at] ^= mask;
+                }
+                pack.truncate(cut.index(pack.len() + 1));
+                reseal(&mut pack);
+
+                if let Ok(reader) = PackReader::from_pack_bytes(pack.clone()) {
+                    for id in reader.object_ids() {
+                        let _ = reader.read_object(id);
+                    }
+                }
+                if let Ok(reader) = PackReader::from_bytes(pack, index) {
+                    for id in reader.object_ids().to_vec() {
+                        let _ = reader.read_object(&id);
+                    }
+                }
+            }
+
+            #[test]
+            fn mutated_indexes_fail_cleanly(
+                blobs in blobs(),
+                flips in prop::collection::vec((any::<prop::sample::Index>(), 1..=255u8), 1..8),
+                probe in any::<[u8; 32]>(),
+            ) {
+                let (_, index) = pack_of(&blobs);
+                let mut bytes = index.to_bytes().unwrap();
+                for (at, mask) in flips {
+                    let at = at.index(bytes.len());
+                    bytes[at] ^= mask;
+                }
+                if let Ok(index) = PackIndex::from_bytes(&bytes) {
+                    for id in &index.object_ids {
+                        prop_assert!(index.contains(id));
+                    }
+                    let _ = index.lookup(&ObjectId::from_hash(probe));
+                }
+            }
+
+            #[test]
+            fn arbitrary_bytes_fail_cleanly(data in prop::collection::vec(any::<u8>(), 0..2048)) {
+                let _ = PackIndex::from_bytes(&data);
+                let mut sealed = data.clone();
+                reseal(&mut sealed);
+                let _ = PackReader::from_pack_bytes(sealed);
+            }
+        }
+
+        #[test]
+        fn oversized_entry_count_is_rejected() {
+            let (mut pack, _) = pack_of(&[b"one".to_vec()]);
+            pack[8..12].copy_from_slice(&u32::MAX.to_be_bytes());
+            reseal(&mut pack);
+            assert!(PackReader::from_pack_bytes(pack).is_err());
+        }
+
+        #[test]
+        fn non_monotonic_fan_out_is_rejected() {
+            let (_, index) = pack_of(&[b"one".to_vec(), b"two".to_vec()]);
+            let mut bytes = index.to_bytes().unwrap();
+            // Raise the first bucket above the total count.
+            bytes[8..12].copy_from_slice(&100u32.to_be_bytes());
+            assert!(matches!(
+                PackIndex::from_bytes(&bytes),
+                Err(PackError::IndexCorrupted(_))
+            ));
+        }
+    }
 }
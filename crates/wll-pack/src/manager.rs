@@ -1,12 +1,17 @@
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-use wll_store::{ObjectStore, StoredObject};
+use wll_store::{FileObjectStore, ObjectStore, StoredObject};
 use wll_types::ObjectId;
 
+use crate::autopack::{AutoPackPolicy, LooseObjects};
+use crate::bloom::BloomFilter;
 use crate::error::PackResult;
+use crate::index::PackIndex;
+use crate::midx::MultiPackIndex;
 use crate::reader::PackReader;
-use crate::writer::{PackFile, PackWriter};
+use crate::writer::{PackFile, PackWriter, PackWriterOptions};
 
 /// Result of garbage collection.
 #[derive(Clone, Debug)]
@@ -20,6 +25,18 @@ pub struct GcReport {
 pub struct PackManager {
     pack_dir: PathBuf,
     packs: Vec<PackReader>,
+    /// Where each of `packs` was loaded from.
+    pack_paths: Vec<PathBuf>,
+    /// Every object in every loaded pack, so an absent object is ruled out
+    /// without touching the per-pack indexes.
+    existence: BloomFilter,
+    /// One index over every loaded pack, if the one on disk covers exactly
+    /// the loaded packs; otherwise lookups go through each pack's index.
+    midx: Option<MultiPackIndex>,
+    /// Position in `packs` of each pack `midx` numbers.
+    midx_packs: Vec<usize>,
+    writer_options: PackWriterOptions,
+    auto_pack: AutoPackPolicy,
 }
 
 impl PackManager {
@@ -27,6 +44,7 @@ impl PackManager {
     pub fn load(wll_dir: &Path) -> PackResult<Self> {
         let pack_dir = wll_dir.join("objects").join("pack");
         let mut packs = Vec::new();
+        let mut pack_paths = Vec::new();
 
         if pack_dir.exists() {
             for entry in std::fs::read_dir(&pack_dir)? {
@@ -34,7 +52,10 @@ impl PackManager {
                 let path = entry.path();
                 if path.extension().map(|e| e == "pack").unwrap_or(false) {
                     match PackReader::open(&path) {
-                        Ok(reader) => packs.push(reader),
+                        Ok(reader) => {
+                            packs.push(reader);
+                            pack_paths.push(path);
+                        }
                         Err(e) => {
                             tracing::warn!("skipping corrupt pack {:?}: {}", path, e);
                         }
@@ -43,7 +64,27 @@ impl PackManager {
             }
         }
 
-        Ok(Self { pack_dir, packs })
+        let existence = existence_filter(&packs);
+        let mut manager = Self {
+            pack_dir,
+            packs,
+            pack_paths,
+            existence,
+            midx: None,
+            midx_packs: Vec::new(),
+            writer_options: PackWriterOptions::default(),
+            auto_pack: AutoPackPolicy::default(),
+        };
+        if let Some(midx) = manager.stored_midx() {
+            match manager.midx_positions(&midx) {
+                Some(positions) => {
+                    manager.midx_packs = positions;
+                    manager.midx = Some(midx);
+                }
+                None => tracing::debug!("multi-pack index is stale, using per-pack indexes"),
+            }
+        }
+        Ok(manager)
     }
 
     /// Create an empty pack manager (for testing).
@@ -51,11 +92,39 @@ impl PackManager {
         Self {
             pack_dir: PathBuf::new(),
             packs: Vec::new(),
+            pack_paths: Vec::new(),
+            existence: BloomFilter::with_capacity(0),
+            midx: None,
+            midx_packs: Vec::new(),
+            writer_options: PackWriterOptions::default(),
+            auto_pack: AutoPackPolicy::default(),
         }
     }
 
+    /// Use `options` for the packs written by [`repack`](Self::repack).
+    pub fn with_writer_options(mut self, options: PackWriterOptions) -> Self {
+        self.writer_options = options;
+        self
+    }
+
+    /// Use `policy` to decide when [`auto_pack`](Self::auto_pack) packs.
+    pub fn with_auto_pack(mut self, policy: AutoPackPolicy) -> Self {
+        self.auto_pack = policy;
+        self
+    }
+
     /// Read an object from any loaded pack.
     pub fn read_object(&self, id: &ObjectId) -> PackResult<Option<StoredObject>> {
+        if !self.existence.may_contain(id) {
+            return Ok(None);
+        }
+        if let Some(midx) = &self.midx {
+            let Some(entry) = midx.lookup(id) else {
+                return Ok(None);
+            };
+            let pack = &self.packs[self.midx_packs[entry.pack as usize]];
+            return pack.read_at_offset(entry.offset, entry.crc32).map(Some);
+        }
         for pack in &self.packs {
             if let Some(obj) = pack.read_object(id)? {
                 return Ok(Some(obj));
@@ -64,9 +133,36 @@ impl PackManager {
         Ok(None)
     }
 
+    /// Read an object from any loaded pack, sharing the decoded copy with
+    /// every other reader still holding it.
+    pub fn read_shared(&self, id: &ObjectId) -> PackResult<Option<Arc<StoredObject>>> {
+        if !self.existence.may_contain(id) {
+            return Ok(None);
+        }
+        if let Some(midx) = &self.midx {
+            let Some(entry) = midx.lookup(id) else {
+                return Ok(None);
+            };
+            let pack = &self.packs[self.midx_packs[entry.pack as usize]];
+            return pack.read_shared_at(id, entry.offset, entry.crc32).map(Some);
+        }
+        for pack in &self.packs {
+            if let Some(obj) = pack.read_shared(id)? {
+                return Ok(Some(obj));
+            }
+        }
+        Ok(None)
+    }
+
     /// Check containment across all packs.
     pub fn contains(&self, id: &ObjectId) -> bool {
-        self.packs.iter().any(|p| p.contains(id))
+        if !self.existence.may_contain(id) {
+            return false;
+        }
+        match &self.midx {
+            Some(midx) => midx.contains(id),
+            None => self.packs.iter().any(|p| p.contains(id)),
+        }
     }
 
     /// Total objects across all packs.
@@ -74,13 +170,37 @@ impl PackManager {
         self.packs.iter().map(|p| p.object_count()).sum()
     }
 
+    /// Every object across all packs; an object in several packs repeats.
+    pub fn object_ids(&self) -> impl Iterator<Item = &ObjectId> {
+        self.packs.iter().flat_map(PackReader::object_ids)
+    }
+
     /// Number of loaded packs.
     pub fn pack_count(&self) -> usize {
         self.packs.len()
     }
 
-    /// Repack objects from a store into a single pack.
-    pub fn repack(&self, store: &dyn ObjectStore, objects: &[ObjectId]) -> PackResult<PackFile> {
+    /// Size on disk of every loaded pack and its index.
+    pub fn pack_bytes(&self) -> u64 {
+        self.pack_paths
+            .iter()
+            .map(|path| file_size(path) + file_size(&path.with_extension("idx")))
+            .sum()
+    }
+
+    /// Whether lookups go through a multi-pack index rather than each
+    /// pack's own index.
+    pub fn uses_multi_pack_index(&self) -> bool {
+        self.midx.is_some()
+    }
+
+    /// Repack objects from a store into a single pack, load it, and bring
+    /// the multi-pack index up to date.
+    pub fn repack(
+        &mut self,
+        store: &dyn ObjectStore,
+        objects: &[ObjectId],
+    ) -> PackResult<PackFile> {
         std::fs::create_dir_all(&self.pack_dir)?;
         let ts = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
@@ -88,16 +208,112 @@ impl PackManager {
             .as_millis();
         let pack_path = self.pack_dir.join(format!("pack-{ts}"));
 
-        let mut writer = PackWriter::new(&pack_path);
+        let mut writer =
+            PackWriter::new(&pack_path).with_options(self.writer_options.clone());
         for id in objects {
             if let Ok(Some(obj)) = store.read(id) {
                 writer.add_stored_object(&obj);
             }
         }
-        writer.finish()
+        let file = writer.finish()?;
+        self.packs.push(PackReader::open(&file.pack_path)?);
+        self.pack_paths.push(file.pack_path.clone());
+        self.existence = existence_filter(&self.packs);
+        self.write_multi_pack_index()?;
+        Ok(file)
+    }
+
+    /// The loose objects of `store` that no loaded pack holds, measured
+    /// against the auto-pack policy's exclusion window.
+    pub fn loose_objects(&self, store: &FileObjectStore) -> PackResult<LooseObjects> {
+        let now = std::time::SystemTime::now();
+        LooseObjects::measure(store, |id| self.contains(id), self.auto_pack.min_age, now)
+    }
+
+    /// Pack the loose objects of `store` that no loaded pack holds, if they
+    /// exceed a threshold of the auto-pack policy, and return the new pack.
+    ///
+    /// Objects younger than the policy's `min_age` are left for a later
+    /// run. The loose files stay where they are, since the store reads
+    /// only those, but packed objects no longer count towards the
+    /// thresholds.
+    pub fn auto_pack(&mut self, store: &FileObjectStore) -> PackResult<Option<PackFile>> {
+        let loose = self.loose_objects(store)?;
+        if !self.auto_pack.exceeded_by(&loose) || loose.eligible.is_empty() {
+            return Ok(None);
+        }
+        tracing::debug!(
+            objects = loose.objects,
+            bytes = loose.bytes,
+            packing = loose.eligible.len(),
+            "loose objects over the auto-pack threshold"
+        );
+        self.repack(store, &loose.eligible).map(Some)
+    }
+
+    /// Index every loaded pack in one multi-pack index and write it to the
+    /// pack directory, or remove it if there are no packs.
+    ///
+    /// Objects of packs the current index, or the one on disk, already
+    /// covers are carried over rather than read from their pack indexes.
+    pub fn write_multi_pack_index(&mut self) -> PackResult<()> {
+        let path = self.pack_dir.join(MultiPackIndex::FILE_NAME);
+        if self.packs.is_empty() {
+            self.midx = None;
+            self.midx_packs.clear();
+            if path.exists() {
+                std::fs::remove_file(&path)?;
+            }
+            return Ok(());
+        }
+        let previous = self.midx.take().or_else(|| self.stored_midx());
+        let names: Vec<String> = self.pack_paths.iter().map(|path| file_name(path)).collect();
+        let packs: Vec<(&str, &PackIndex)> = names
+            .iter()
+            .map(String::as_str)
+            .zip(self.packs.iter().map(PackReader::index))
+            .collect();
+        let midx = MultiPackIndex::build(&packs, previous.as_ref());
+        // Drop the old map before its file is replaced.
+        drop(previous);
+        midx.write(&path)?;
+        self.midx_packs = (0..self.packs.len()).collect();
+        self.midx = Some(midx);
+        Ok(())
+    }
+
+    /// The multi-pack index in the pack directory, if there is a readable
+    /// one.
+    fn stored_midx(&self) -> Option<MultiPackIndex> {
+        let path = self.pack_dir.join(MultiPackIndex::FILE_NAME);
+        if !path.exists() {
+            return None;
+        }
+        MultiPackIndex::open(&path)
+            .map_err(|e| tracing::warn!("ignoring unreadable multi-pack index: {e}"))
+            .ok()
+    }
+
+    /// Position in `packs` of each pack `midx` numbers, if it covers exactly
+    /// the loaded packs.
+    fn midx_positions(&self, midx: &MultiPackIndex) -> Option<Vec<usize>> {
+        if midx.packs().len() != self.packs.len() {
+            return None;
+        }
+        midx.packs()
+            .iter()
+            .map(|covered| {
+                self.packs.iter().zip(&self.pack_paths).position(|(pack, path)| {
+                    file_name(path) == covered.name
+                        && pack.index().pack_checksum == covered.checksum
+                })
+            })
+            .collect()
     }
 
     /// Garbage collect: report unreachable objects.
+    ///
+    /// Nothing is removed; [`collect`](Self::collect) removes them.
     pub fn gc(&self, reachable: &HashSet<ObjectId>) -> GcReport {
         let mut objects_removed = 0;
         for pack in &self.packs {
@@ -113,6 +329,79 @@ impl PackManager {
             bytes_freed: 0,
         }
     }
+
+    /// Garbage collect: rewrite every pack holding objects outside
+    /// `reachable` without them. Packs left with no objects are deleted.
+    pub fn collect(&mut self, reachable: &HashSet<ObjectId>) -> PackResult<GcReport> {
+        let mut report = GcReport {
+            objects_removed: 0,
+            packs_removed: 0,
+            bytes_freed: 0,
+        };
+        let ts = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+
+        // Pack positions are about to change.
+        self.midx = None;
+        let packs = std::mem::take(&mut self.packs);
+        let paths = std::mem::take(&mut self.pack_paths);
+        for (n, (pack, path)) in packs.into_iter().zip(paths).enumerate() {
+            let keep: Vec<&ObjectId> =
+                pack.object_ids().iter().filter(|id| reachable.contains(id)).collect();
+            if keep.len() == pack.object_count() {
+                self.packs.push(pack);
+                self.pack_paths.push(path);
+                continue;
+            }
+            report.objects_removed += pack.object_count() - keep.len();
+            let index_path = path.with_extension("idx");
+            let old_size = file_size(&path) + file_size(&index_path);
+
+            let mut new_size = 0;
+            if keep.is_empty() {
+                report.packs_removed += 1;
+            } else {
+                let mut writer = PackWriter::new(&self.pack_dir.join(format!("pack-{ts}-{n}")))
+                    .with_options(self.writer_options.clone());
+                for id in keep {
+                    if let Some(obj) = pack.read_object(id)? {
+                        writer.add_stored_object(&obj);
+                    }
+                }
+                let file = writer.finish()?;
+                new_size = file_size(&file.pack_path) + file_size(&file.index_path);
+                self.packs.push(PackReader::open(&file.pack_path)?);
+                self.pack_paths.push(file.pack_path);
+            }
+            std::fs::remove_file(&path)?;
+            std::fs::remove_file(&index_path)?;
+            report.bytes_freed += old_size.saturating_sub(new_size);
+        }
+
+        self.existence = existence_filter(&self.packs);
+        self.write_multi_pack_index()?;
+        Ok(report)
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map_or_else(String::new, |name| name.to_string_lossy().into_owned())
+}
+
+fn file_size(path: &Path) -> u64 {
+    std::fs::metadata(path).map_or(0, |m| m.len())
+}
+
+/// One filter over the objects of all `packs`.
+fn existence_filter(packs: &[PackReader]) -> BloomFilter {
+    let total = packs.iter().map(PackReader::object_count).sum();
+    let mut filter = BloomFilter::with_capacity(total);
+    for id in packs.iter().flat_map(PackReader::object_ids) {
+        filter.insert(id);
+    }
+    filter
 }
 
 #[cfg(test)]
@@ -134,10 +423,169 @@ mod tests {
         assert!(result.is_none());
     }
 
+    #[test]
+    fn shared_reads_decode_once_for_every_holder() {
+        let dir = tempfile::tempdir().unwrap();
+        let pack_dir = dir.path().join("objects").join("pack");
+        std::fs::create_dir_all(&pack_dir).unwrap();
+        let obj = StoredObject::new(wll_store::ObjectKind::Blob, b"shared".to_vec());
+        let mut writer = PackWriter::new(&pack_dir.join("pack-0"));
+        writer.add_stored_object(&obj);
+        writer.finish().unwrap();
+
+        let mgr = PackManager::load(dir.path()).unwrap();
+        let id = obj.compute_id();
+        let shared = mgr.read_shared(&id).unwrap().unwrap();
+        assert!(Arc::ptr_eq(&shared, &mgr.read_shared(&id).unwrap().unwrap()));
+        assert_eq!(*shared, obj);
+        assert!(mgr.read_shared(&ObjectId::null()).unwrap().is_none());
+    }
+
+    #[test]
+    fn existence_filter_covers_every_loaded_pack() {
+        let dir = tempfile::tempdir().unwrap();
+        let pack_dir = dir.path().join("objects").join("pack");
+        std::fs::create_dir_all(&pack_dir).unwrap();
+        let mut objects = Vec::new();
+        for pack in 0..3 {
+            let mut writer = PackWriter::new(&pack_dir.join(format!("pack-{pack}")));
+            for i in 0..50 {
+                let obj = StoredObject::new(
+                    wll_store::ObjectKind::Blob,
+                    format!("pack {pack} object {i}").into_bytes(),
+                );
+                writer.add_stored_object(&obj);
+                objects.push(obj);
+            }
+            writer.finish().unwrap();
+        }
+
+        let mgr = PackManager::load(dir.path()).unwrap();
+        assert_eq!(mgr.pack_count(), 3);
+        for obj in &objects {
+            let id = obj.compute_id();
+            assert!(mgr.existence.may_contain(&id));
+            assert_eq!(mgr.read_object(&id).unwrap().as_ref(), Some(obj));
+        }
+        let id = objects[70].compute_id();
+        let shared = mgr.read_shared(&id).unwrap().unwrap();
+        assert!(Arc::ptr_eq(&shared, &mgr.read_shared(&id).unwrap().unwrap()));
+        assert_eq!(*shared, objects[70]);
+        let absent = ObjectId::from_bytes(b"never packed");
+        assert!(!mgr.contains(&absent));
+        assert!(mgr.read_object(&absent).unwrap().is_none());
+    }
+
+    #[test]
+    fn repack_maintains_the_multi_pack_index_and_stale_ones_are_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let pack_dir = dir.path().join("objects").join("pack");
+        std::fs::create_dir_all(&pack_dir).unwrap();
+        let store = wll_store::InMemoryObjectStore::new();
+        let blob = |text: &str| {
+            let obj = StoredObject::new(wll_store::ObjectKind::Blob, text.as_bytes().to_vec());
+            store.write(&obj).unwrap()
+        };
+        let mut writer = PackWriter::new(&pack_dir.join("pack-0"));
+        writer.add_stored_object(&store.read(&blob("loose")).unwrap().unwrap());
+        writer.finish().unwrap();
+
+        let mut mgr = PackManager::load(dir.path()).unwrap();
+        assert!(!mgr.uses_multi_pack_index());
+        let ids = [blob("one"), blob("two")];
+        mgr.repack(&store, &ids).unwrap();
+        assert!(mgr.uses_multi_pack_index());
+
+        let reloaded = PackManager::load(dir.path()).unwrap();
+        assert!(reloaded.uses_multi_pack_index());
+        for id in ids.iter().chain([&blob("loose")]) {
+            assert_eq!(reloaded.read_object(id).unwrap(), store.read(id).unwrap());
+            let shared = reloaded.read_shared(id).unwrap().unwrap();
+            assert!(Arc::ptr_eq(&shared, &reloaded.read_shared(id).unwrap().unwrap()));
+        }
+        assert!(!reloaded.contains(&ObjectId::from_bytes(b"absent")));
+
+        // A pack written behind the manager's back makes the index stale.
+        let mut writer = PackWriter::new(&pack_dir.join("pack-9"));
+        writer.add_stored_object(&store.read(&blob("late")).unwrap().unwrap());
+        writer.finish().unwrap();
+        let mut stale = PackManager::load(dir.path()).unwrap();
+        assert!(!stale.uses_multi_pack_index());
+        assert!(stale.contains(&blob("late")));
+        stale.write_multi_pack_index().unwrap();
+        assert!(PackManager::load(dir.path()).unwrap().uses_multi_pack_index());
+    }
+
+    #[test]
+    fn auto_pack_packs_loose_objects_past_the_threshold_except_recent_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileObjectStore::open(dir.path().join("objects")).unwrap();
+        let blob = |text: &str| {
+            let obj = StoredObject::new(wll_store::ObjectKind::Blob, text.as_bytes().to_vec());
+            store.write(&obj).unwrap()
+        };
+        let policy = AutoPackPolicy::default()
+            .with_max_loose_objects(Some(2))
+            .with_max_loose_bytes(None)
+            .with_min_age(std::time::Duration::from_secs(600));
+        let mut mgr = PackManager::load(dir.path()).unwrap().with_auto_pack(policy);
+        let old = [blob("one"), blob("two")];
+        assert!(mgr.auto_pack(&store).unwrap().is_none());
+
+        let recent = blob("three");
+        assert!(mgr.auto_pack(&store).unwrap().is_none(), "everything is too recent");
+        let an_hour_ago = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+        for id in &old {
+            let file = std::fs::File::options().write(true).open(store.object_path(id)).unwrap();
+            file.set_modified(an_hour_ago).unwrap();
+        }
+        let pack = mgr.auto_pack(&store).unwrap().unwrap();
+        assert_eq!(pack.object_count, 2);
+        assert!(old.iter().all(|id| mgr.contains(id)) && !mgr.contains(&recent));
+
+        let loose = mgr.loose_objects(&store).unwrap();
+        assert_eq!((loose.objects, loose.eligible.len()), (1, 0));
+        assert!(PackManager::load(dir.path()).unwrap().uses_multi_pack_index());
+    }
+
     #[test]
     fn gc_empty() {
         let mgr = PackManager::empty();
         let report = mgr.gc(&HashSet::new());
         assert_eq!(report.objects_removed, 0);
     }
+
+    #[test]
+    fn collect_rewrites_packs_without_unreachable_objects() {
+        let dir = tempfile::tempdir().unwrap();
+        let pack_dir = dir.path().join("objects").join("pack");
+        std::fs::create_dir_all(&pack_dir).unwrap();
+        let mut ids = Vec::new();
+        for pack in 0..2 {
+            let mut writer = PackWriter::new(&pack_dir.join(format!("pack-{pack}")));
+            for i in 0..3 {
+                let obj = StoredObject::new(
+                    wll_store::ObjectKind::Blob,
+                    format!("pack {pack} object {i}").into_bytes(),
+                );
+                writer.add_stored_object(&obj);
+                ids.push(obj.compute_id());
+            }
+            writer.finish().unwrap();
+        }
+
+        // Keep one object of the first pack and none of the second.
+        let reachable: HashSet<ObjectId> = [ids[0]].into();
+        let mut mgr = PackManager::load(dir.path()).unwrap();
+        assert_eq!(mgr.gc(&reachable).objects_removed, 5);
+        let report = mgr.collect(&reachable).unwrap();
+        assert_eq!((report.objects_removed, report.packs_removed), (5, 1));
+        assert!(report.bytes_freed > 0);
+        assert_eq!(mgr.total_objects(), 1);
+        assert!(mgr.read_object(&ids[0]).unwrap().is_some());
+        assert!(!mgr.contains(&ids[1]));
+
+        let reloaded = PackManager::load(dir.path()).unwrap();
+        assert_eq!((reloaded.pack_count(), reloaded.total_objects()), (1, 1));
+    }
 }
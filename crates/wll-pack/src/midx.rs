@@ -0,0 +1,450 @@
+//! Multi-pack index: one lookup table over every pack in a directory.
+//!
+//! Without it, finding an object means asking each pack index in turn. The
+//! multi-pack index maps every object id to the pack holding it and the
+//! entry's offset and CRC32 there, so a lookup is one binary search however
+//! many packs there are. Its tables have fixed-width rows and are searched
+//! in place, so the file is memory-mapped rather than parsed:
+//!
+//! ```text
+//! "WLLM" | version | pack count
+//! per pack: pack checksum (32) | name length (u16) | file name
+//! fan-out: 256 × u32
+//! ids: n × 32 | pack numbers: n × u32 | offsets: n × u64 | CRC32s: n × u32
+//! BLAKE3 checksum of everything above
+//! ```
+//!
+//! The index names the packs it covers by file name and pack checksum. It
+//! is stale once that list differs from the packs on disk, and readers then
+//! fall back to the per-pack indexes.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+
+use memmap2::Mmap;
+use wll_types::{FormatSpec, ObjectId};
+
+use crate::error::{PackError, PackResult};
+use crate::index::PackIndex;
+
+const MAGIC: &[u8; 4] = b"WLLM";
+
+/// Versions of the `multi-pack-index` format.
+pub const MIDX_FORMAT: FormatSpec =
+    FormatSpec { name: "multi-pack-index", current: 1, oldest: 1 };
+
+/// Bytes of one object's rows across the four tables.
+const ROW: usize = 32 + 4 + 8 + 4;
+
+/// Where the multi-pack index places an object.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MidxEntry {
+    /// Number of the pack in [`MultiPackIndex::packs`].
+    pub pack: u32,
+    pub offset: u64,
+    pub crc32: u32,
+}
+
+/// A pack covered by a multi-pack index.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MidxPack {
+    /// File name of the pack, such as `pack-1700000000000.pack`.
+    pub name: String,
+    pub checksum: [u8; 32],
+}
+
+enum Bytes {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+impl Bytes {
+    fn as_slice(&self) -> &[u8] {
+        match self {
+            Self::Mapped(map) => map,
+            Self::Owned(bytes) => bytes,
+        }
+    }
+}
+
+/// Index over the objects of several packs.
+pub struct MultiPackIndex {
+    bytes: Bytes,
+    packs: Vec<MidxPack>,
+    fan_out: [u32; 256],
+    /// Offset of the id table.
+    ids_at: usize,
+}
+
+impl std::fmt::Debug for MultiPackIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MultiPackIndex")
+            .field("packs", &self.packs)
+            .field("objects", &self.object_count())
+            .finish()
+    }
+}
+
+impl MultiPackIndex {
+    /// Name of the index file in the pack directory.
+    pub const FILE_NAME: &'static str = "multi-pack-index";
+
+    /// Index the objects of `packs`, given by file name and index.
+    ///
+    /// If every pack `previous` covers is still among `packs`, under the
+    /// same name and checksum, its rows are carried over instead of being
+    /// read again from the pack indexes, so adding a pack only merges that
+    /// pack's objects into the rest. An object held by several packs is
+    /// listed once, so when a pack is dropped the rows are rebuilt from
+    /// scratch: an object listed under it may be in another pack too.
+    pub fn build(packs: &[(&str, &PackIndex)], previous: Option<&MultiPackIndex>) -> Self {
+        let previous = previous.filter(|midx| {
+            midx.packs.iter().all(|covered| {
+                packs.iter().any(|(name, index)| {
+                    *name == covered.name && index.pack_checksum == covered.checksum
+                })
+            })
+        });
+        let mut renumber = HashMap::new();
+        let mut added = Vec::new();
+        for (n, (name, index)) in packs.iter().enumerate() {
+            let covered = previous.and_then(|midx| midx.pack_number(name, &index.pack_checksum));
+            match covered {
+                Some(old) => {
+                    renumber.insert(old, n as u32);
+                }
+                None => added.extend((0..index.object_ids.len()).map(|i| {
+                    let entry = MidxEntry {
+                        pack: n as u32,
+                        offset: index.offsets[i],
+                        crc32: index.crc32s[i],
+                    };
+                    (index.object_ids[i], entry)
+                })),
+            }
+        }
+        added.sort_by_key(|(id, entry)| (*id.as_bytes(), entry.pack));
+
+        let carried: Vec<(ObjectId, MidxEntry)> = previous
+            .map(|midx| {
+                (0..midx.object_count())
+                    .filter_map(|i| {
+                        let (id, entry) = midx.row(i);
+                        let pack = *renumber.get(&entry.pack)?;
+                        Some((id, MidxEntry { pack, ..entry }))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut entries: Vec<(ObjectId, MidxEntry)> =
+            Vec::with_capacity(carried.len() + added.len());
+        let mut carried = carried.into_iter().peekable();
+        let mut added = added.into_iter().peekable();
+        loop {
+            let next = match (carried.peek(), added.peek()) {
+                (Some(a), Some(b)) if (a.0.as_bytes(), a.1.pack) <= (b.0.as_bytes(), b.1.pack) => {
+                    carried.next()
+                }
+                (Some(_), Some(_)) => added.next(),
+                (Some(_), None) => carried.next(),
+                (None, _) => added.next(),
+            };
+            let Some(next) = next else { break };
+            if entries.last().map_or(true, |(last, _)| *last != next.0) {
+                entries.push(next);
+            }
+        }
+
+        let packs = packs
+            .iter()
+            .map(|(name, index)| MidxPack {
+                name: name.to_string(),
+                checksum: index.pack_checksum,
+            })
+            .collect();
+        Self::encode(packs, &entries)
+    }
+
+    fn encode(packs: Vec<MidxPack>, entries: &[(ObjectId, MidxEntry)]) -> Self {
+        let mut buf = Vec::with_capacity(12 + 1024 + entries.len() * ROW + 32);
+        buf.extend_from_slice(MAGIC);
+        buf.extend_from_slice(&MIDX_FORMAT.current.to_be_bytes());
+        buf.extend_from_slice(&(packs.len() as u32).to_be_bytes());
+        for pack in &packs {
+            buf.extend_from_slice(&pack.checksum);
+            buf.extend_from_slice(&(pack.name.len() as u16).to_be_bytes());
+            buf.extend_from_slice(pack.name.as_bytes());
+        }
+
+        let mut fan_out = [0u32; 256];
+        for (id, _) in entries {
+            fan_out[id.as_bytes()[0] as usize] += 1;
+        }
+        let mut total = 0;
+        for slot in &mut fan_out {
+            total += *slot;
+            *slot = total;
+            buf.extend_from_slice(&total.to_be_bytes());
+        }
+
+        let ids_at = buf.len();
+        for (id, _) in entries {
+            buf.extend_from_slice(id.as_bytes());
+        }
+        for (_, entry) in entries {
+            buf.extend_from_slice(&entry.pack.to_be_bytes());
+        }
+        for (_, entry) in entries {
+            buf.extend_from_slice(&entry.offset.to_be_bytes());
+        }
+        for (_, entry) in entries {
+            buf.extend_from_slice(&entry.crc32.to_be_bytes());
+        }
+        let checksum = *blake3::hash(&buf).as_bytes();
+        buf.extend_from_slice(&checksum);
+
+        Self {
+            bytes: Bytes::Owned(buf),
+            packs,
+            fan_out,
+            ids_at,
+        }
+    }
+
+    /// Map the index file at `path`.
+    pub fn open(path: &Path) -> PackResult<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the file is only ever replaced by renaming a new one over
+        // it, never written in place, so the mapped contents do not change
+        // while the map is alive.
+        let map = unsafe { Mmap::map(&file)? };
+        Self::parse(Bytes::Mapped(map))
+    }
+
+    /// Read an index from bytes, such as those of [`Self::to_bytes`].
+    pub fn from_bytes(bytes: Vec<u8>) -> PackResult<Self> {
+        Self::parse(Bytes::Owned(bytes))
+    }
+
+    /// The version an index's bytes declare, without reading the rest.
+    pub fn format_version(data: &[u8]) -> PackResult<u32> {
+        if data.get(..4) != Some(MAGIC) {
+            return Err(PackError::InvalidMagic {
+                expected: "WLLM".into(),
+                actual: String::from_utf8_lossy(data.get(..4).unwrap_or(data)).into(),
+            });
+        }
+        read_array(data, 4)
+            .map(u32::from_be_bytes)
+            .ok_or_else(|| PackError::IndexCorrupted("multi-pack index too short".into()))
+    }
+
+    /// Check the layout and checksum, then every row's pack number.
+    fn parse(bytes: Bytes) -> PackResult<Self> {
+        let (packs, fan_out, ids_at) = layout(bytes.as_slice())?;
+        let midx = Self {
+            bytes,
+            packs,
+            fan_out,
+            ids_at,
+        };
+        let pack_count = midx.packs.len() as u32;
+        if (0..midx.object_count()).any(|i| midx.row(i).1.pack >= pack_count) {
+            return Err(PackError::IndexCorrupted(
+                "multi-pack index names a pack it does not list".into(),
+            ));
+        }
+        Ok(midx)
+    }
+
+    /// Write the index to `path`, replacing any index there atomically.
+    pub fn write(&self, path: &Path) -> PackResult<()> {
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, self.to_bytes())?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// The encoded index, as written to disk.
+    pub fn to_bytes(&self) -> &[u8] {
+        self.bytes.as_slice()
+    }
+
+    /// The packs covered, numbered by position.
+    pub fn packs(&self) -> &[MidxPack] {
+        &self.packs
+    }
+
+    /// Number of the pack named `name` with checksum `checksum`, if covered.
+    pub fn pack_number(&self, name: &str, checksum: &[u8; 32]) -> Option<u32> {
+        self.packs
+            .iter()
+            .position(|pack| pack.name == name && &pack.checksum == checksum)
+            .map(|n| n as u32)
+    }
+
+    pub fn object_count(&self) -> usize {
+        self.fan_out[255] as usize
+    }
+
+    /// Where `id` is, if any covered pack holds it.
+    pub fn lookup(&self, id: &ObjectId) -> Option<MidxEntry> {
+        let first = id.as_bytes()[0] as usize;
+        let mut low = if first == 0 { 0 } else { self.fan_out[first - 1] as usize };
+        let mut high = self.fan_out[first] as usize;
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match self.id(mid).as_slice().cmp(id.as_bytes()) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => return Some(self.row(mid).1),
+            }
+        }
+        None
+    }
+
+    pub fn contains(&self, id: &ObjectId) -> bool {
+        self.lookup(id).is_some()
+    }
+
+    fn id(&self, i: usize) -> [u8; 32] {
+        self.field(self.ids_at, i)
+    }
+
+    /// The id and placement of the `i`th object.
+    fn row(&self, i: usize) -> (ObjectId, MidxEntry) {
+        let n = self.object_count();
+        let packs_at = self.ids_at + n * 32;
+        let offsets_at = packs_at + n * 4;
+        let crcs_at = offsets_at + n * 8;
+        let entry = MidxEntry {
+            pack: u32::from_be_bytes(self.field(packs_at, i)),
+            offset: u64::from_be_bytes(self.field(offsets_at, i)),
+            crc32: u32::from_be_bytes(self.field(crcs_at, i)),
+        };
+        (ObjectId::from_hash(self.id(i)), entry)
+    }
+
+    /// The `i`th `N`-byte row of the table starting at `table`. [`Self::parse`]
+    /// has checked that every table holds a row for every object.
+    fn field<const N: usize>(&self, table: usize, i: usize) -> [u8; N] {
+        read_array(self.bytes.as_slice(), table + i * N).unwrap_or([0; N])
+    }
+}
+
+/// Check the magic, checksum and table sizes of an encoded index, returning
+/// its pack list, fan-out table and the offset of its id table.
+fn layout(data: &[u8]) -> PackResult<(Vec<MidxPack>, [u32; 256], usize)> {
+    let corrupt = |reason: &str| PackError::IndexCorrupted(format!("multi-pack index {reason}"));
+    if data.get(..4) != Some(MAGIC) {
+        return Err(PackError::InvalidMagic {
+            expected: "WLLM".into(),
+            actual: String::from_utf8_lossy(data.get(..4).unwrap_or(data)).into(),
+        });
+    }
+    let body_len = data.len().checked_sub(32).ok_or_else(|| corrupt("too short"))?;
+    if blake3::hash(&data[..body_len]).as_bytes() != &data[body_len..] {
+        return Err(PackError::ChecksumMismatch);
+    }
+    let data = &data[..body_len];
+    let header = |pos| read_array(data, pos).map(u32::from_be_bytes);
+    let version = header(4).ok_or_else(|| corrupt("too short"))?;
+    if !MIDX_FORMAT.reads(version) {
+        return Err(PackError::UnsupportedVersion(version));
+    }
+
+    let pack_count = header(8).ok_or_else(|| corrupt("too short"))?;
+    let mut pos = 12;
+    let mut packs = Vec::new();
+    for _ in 0..pack_count {
+        let truncated = || corrupt("pack list truncated");
+        let checksum = read_array(data, pos).ok_or_else(truncated)?;
+        let len = read_array(data, pos + 32).map(u16::from_be_bytes).ok_or_else(truncated)?;
+        pos += 34;
+        let name = data
+            .get(pos..pos + len as usize)
+            .and_then(|name| std::str::from_utf8(name).ok())
+            .ok_or_else(|| corrupt("pack name invalid"))?;
+        packs.push(MidxPack {
+            name: name.to_string(),
+            checksum,
+        });
+        pos += len as usize;
+    }
+
+    let mut fan_out = [0u32; 256];
+    for slot in &mut fan_out {
+        *slot = header(pos).ok_or_else(|| corrupt("fan-out truncated"))?;
+        pos += 4;
+    }
+    if fan_out.windows(2).any(|pair| pair[0] > pair[1]) {
+        return Err(corrupt("fan-out not monotonic"));
+    }
+    let count = fan_out[255] as usize;
+    if count.checked_mul(ROW).and_then(|len| len.checked_add(pos)) != Some(data.len()) {
+        return Err(corrupt("table size does not match its object count"));
+    }
+    Ok((packs, fan_out, pos))
+}
+
+fn read_array<const N: usize>(data: &[u8], pos: usize) -> Option<[u8; N]> {
+    data.get(pos..pos.checked_add(N)?)?.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(ids: &[u8], checksum: u8) -> PackIndex {
+        let entries = ids
+            .iter()
+            .map(|&n| (ObjectId::from_bytes(&[n]), u32::from(n), 12 + u64::from(n)))
+            .collect();
+        PackIndex::build(entries, [checksum; 32])
+    }
+
+    #[test]
+    fn lookups_span_every_pack_and_survive_a_rebuild() {
+        let (a, b) = (index(&[1, 2, 3], 1), index(&[3, 4], 2));
+        let midx = MultiPackIndex::build(&[("a.pack", &a), ("b.pack", &b)], None);
+        assert_eq!(midx.object_count(), 4);
+        let four = midx.lookup(&ObjectId::from_bytes(&[4])).unwrap();
+        assert_eq!(four, MidxEntry { pack: 1, offset: 16, crc32: 4 });
+        assert!(!midx.contains(&ObjectId::from_bytes(&[9])));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(MultiPackIndex::FILE_NAME);
+        midx.write(&path).unwrap();
+        let mapped = MultiPackIndex::open(&path).unwrap();
+        assert_eq!(mapped.to_bytes(), midx.to_bytes());
+
+        // Adding pack `c` carries `a` and `b` over, renumbered.
+        let c = index(&[4, 5], 3);
+        let packs = [("c.pack", &c), ("a.pack", &a), ("b.pack", &b)];
+        let grown = MultiPackIndex::build(&packs, Some(&mapped));
+        assert_eq!(grown.object_count(), 5);
+        assert_eq!(grown.lookup(&ObjectId::from_bytes(&[3])).unwrap().pack, 1);
+        assert_eq!(grown.lookup(&ObjectId::from_bytes(&[5])).unwrap().pack, 0);
+        assert_eq!(grown.to_bytes(), MultiPackIndex::build(&packs, None).to_bytes());
+
+        // Dropping `a` must not lose object 3, which `b` holds too.
+        let shrunk = MultiPackIndex::build(&[("b.pack", &b)], Some(&grown));
+        assert_eq!(shrunk.lookup(&ObjectId::from_bytes(&[3])).unwrap().pack, 0);
+        assert!(!shrunk.contains(&ObjectId::from_bytes(&[1])));
+    }
+
+    #[test]
+    fn damaged_index_is_rejected() {
+        let midx = MultiPackIndex::build(&[("a.pack", &index(&[1, 2], 1))], None);
+        let mut bytes = midx.to_bytes().to_vec();
+        let at = bytes.len() - 40;
+        bytes[at] ^= 1;
+        assert!(matches!(
+            MultiPackIndex::from_bytes(bytes),
+            Err(PackError::ChecksumMismatch)
+        ));
+        assert!(MultiPackIndex::from_bytes(b"WLLM".to_vec()).is_err());
+    }
+}
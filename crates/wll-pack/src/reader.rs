@@ -1,38 +1,172 @@
-use wll_store::StoredObject;
-use wll_types::ObjectId;
+use std::collections::BTreeSet;
+use std::io::Read;
+use std::ops::Range;
+use std::sync::Arc;
 
+use wll_store::{SharedReads, StoredObject};
+use wll_types::{FormatSpec, ObjectId};
+
+use crate::delta::apply_delta;
 use crate::entry::PackObjectKind;
 use crate::error::{PackError, PackResult};
 use crate::index::PackIndex;
-use crate::writer::decode_varint;
+use crate::writer::{decode_varint, write_entry, PackWriterOptions};
+
+/// Longest chain of deltas resolved before an entry is deemed corrupt.
+const MAX_DELTA_DEPTH: usize = 50;
+
+/// Versions of the `.pack` format.
+pub const PACK_FORMAT: FormatSpec = FormatSpec { name: "pack", current: 1, oldest: 1 };
 
 /// Reads objects from a pack file using an index for random access.
+///
+/// Entries are compressed, so every read decodes a fresh copy;
+/// [`read_shared`](Self::read_shared) decodes once for all the readers
+/// holding an object at the time.
 #[derive(Debug)]
 pub struct PackReader {
-    pack_data: Vec<u8>,
-    index: PackIndex,
+    pub(crate) pack_data: Vec<u8>,
+    pub(crate) index: PackIndex,
+    shared: SharedReads,
 }
 
 impl PackReader {
     /// Open from raw bytes.
     pub fn from_bytes(pack_data: Vec<u8>, index: PackIndex) -> PackResult<Self> {
-        if pack_data.len() < 12 {
+        check_header(&pack_data)?;
+        Ok(Self { pack_data, index, shared: SharedReads::new() })
+    }
+
+    /// Open a pack received without its index, rebuilding the index by
+    /// scanning every entry.
+    ///
+    /// This is how packs arrive over the wire. The trailing checksum is
+    /// verified and each entry is decompressed once to recompute its object
+    /// id, so a pack whose contents do not match its ids is rejected here.
+    pub fn from_pack_bytes(pack_data: Vec<u8>) -> PackResult<Self> {
+        Self::from_thin_pack_bytes(pack_data, |_| None)
+    }
+
+    /// Open a received pack that may be thin, fixing it first.
+    ///
+    /// A thin pack holds deltas against bases it does not contain, objects
+    /// the sender knows the receiver has. Each missing base is looked up
+    /// with `bases` and appended as a full entry, updating the object count
+    /// and trailing checksum, so the reader ends up with a self-contained
+    /// pack that can be stored as is. A base that `bases` cannot supply
+    /// fails with [`PackError::DeltaBaseNotFound`].
+    pub fn from_thin_pack_bytes(
+        pack_data: Vec<u8>,
+        bases: impl Fn(&ObjectId) -> Option<StoredObject>,
+    ) -> PackResult<Self> {
+        check_header(&pack_data)?;
+        if pack_data.len() < 12 + 32 {
             return Err(PackError::CorruptEntry {
                 offset: 0,
                 reason: "pack data too short".into(),
             });
         }
-        if &pack_data[0..4] != b"WLLP" {
-            return Err(PackError::InvalidMagic {
-                expected: "WLLP".into(),
-                actual: String::from_utf8_lossy(&pack_data[0..4]).into(),
+
+        let body_len = pack_data.len() - 32;
+        let checksum: [u8; 32] = pack_data[body_len..].try_into().unwrap();
+        if *blake3::hash(&pack_data[..body_len]).as_bytes() != checksum {
+            return Err(PackError::ChecksumMismatch);
+        }
+
+        let count = u32::from_be_bytes(pack_data[8..12].try_into().unwrap());
+        // Every entry takes at least a type byte and two size bytes, so a
+        // count beyond that is a lie and must not size the allocation.
+        let max_entries = (body_len - 12) / 3;
+        let mut scanner = Self {
+            pack_data,
+            index: PackIndex::build(vec![], checksum),
+            shared: SharedReads::new(),
+        };
+        let mut entries = Vec::with_capacity((count as usize).min(max_entries));
+        let mut deltas = Vec::new();
+        let mut pos = 12usize;
+        for _ in 0..count {
+            let offset = pos as u64;
+            let (crc, next) = scanner.entry_bounds(pos, body_len)?;
+            match entry_header(&scanner.pack_data, pos)?.kind {
+                PackObjectKind::Delta { base } => deltas.push((base, crc, offset)),
+                PackObjectKind::Full(_) => {
+                    let object = scanner.read_at_offset(offset, crc)?;
+                    entries.push((object.compute_id(), crc, offset));
+                }
+            }
+            pos = next;
+        }
+        if pos != body_len {
+            return Err(PackError::CorruptEntry {
+                offset: pos as u64,
+                reason: "trailing data after last entry".into(),
             });
         }
-        let version = u32::from_be_bytes(pack_data[4..8].try_into().unwrap());
-        if version != 1 {
-            return Err(PackError::UnsupportedVersion(version));
+
+        // A delta's id is known once its base is indexed. Bases the pack
+        // lacks are appended only when no delta left can be resolved.
+        let mut appended = 0u32;
+        while !deltas.is_empty() {
+            scanner.index = PackIndex::build(entries.clone(), checksum);
+            let waiting = deltas.len();
+            let mut pending = Vec::new();
+            for (base, crc, offset) in deltas {
+                if scanner.index.contains(&base) {
+                    let object = scanner.read_at_offset(offset, crc)?;
+                    entries.push((object.compute_id(), crc, offset));
+                } else {
+                    pending.push((base, crc, offset));
+                }
+            }
+            if pending.len() == waiting {
+                if appended == 0 {
+                    scanner.pack_data.truncate(body_len);
+                }
+                let missing: BTreeSet<ObjectId> = pending.iter().map(|(base, ..)| *base).collect();
+                let before = appended;
+                for base in &missing {
+                    if let Some(object) = bases(base).filter(|o| o.compute_id() == *base) {
+                        let (crc, offset) = scanner.append_full(&object)?;
+                        entries.push((*base, crc, offset));
+                        appended += 1;
+                    }
+                }
+                if appended == before {
+                    return Err(PackError::DeltaBaseNotFound(pending[0].0));
+                }
+            }
+            deltas = pending;
+        }
+
+        let mut checksum = checksum;
+        if appended > 0 {
+            scanner.pack_data[8..12].copy_from_slice(&(count + appended).to_be_bytes());
+            checksum = *blake3::hash(&scanner.pack_data).as_bytes();
+            scanner.pack_data.extend_from_slice(&checksum);
+            tracing::debug!(bases = appended, "fixed thin pack");
         }
-        Ok(Self { pack_data, index })
+        scanner.index = PackIndex::build(entries, checksum);
+        Ok(scanner)
+    }
+
+    /// Append `object` as a full entry to a pack whose trailer has been
+    /// cut off, returning the entry's CRC and offset.
+    fn append_full(&mut self, object: &StoredObject) -> PackResult<(u32, u64)> {
+        let level = PackWriterOptions::default().compression_level;
+        let compressed = zstd::encode_all(object.data.as_slice(), level)
+            .map_err(|e| PackError::CompressionFailed(e.to_string()))?;
+        let offset = self.pack_data.len() as u64;
+        let kind = PackObjectKind::Full(object.kind);
+        write_entry(&mut self.pack_data, kind, object.data.len(), &compressed);
+        Ok((crc32fast::hash(&compressed), offset))
+    }
+
+    /// CRC and end position of the entry starting at `pos`.
+    pub(crate) fn entry_bounds(&self, pos: usize, body_len: usize) -> PackResult<(u32, usize)> {
+        let data = &self.pack_data[..body_len];
+        let header = entry_header(data, pos)?;
+        Ok((crc32fast::hash(&data[header.data.clone()]), header.data.end))
     }
 
     /// Open from disk paths.
@@ -44,6 +178,12 @@ impl PackReader {
         Self::from_bytes(pack_data, index)
     }
 
+    /// The version a pack's bytes declare, without reading the rest.
+    pub fn format_version(data: &[u8]) -> PackResult<u32> {
+        check_magic(data)?;
+        Ok(u32::from_be_bytes(data[4..8].try_into().unwrap()))
+    }
+
     /// Read an object by ID.
     pub fn read_object(&self, id: &ObjectId) -> PackResult<Option<StoredObject>> {
         let (offset, expected_crc) = match self.index.lookup(id) {
@@ -54,6 +194,24 @@ impl PackReader {
         Ok(Some(obj))
     }
 
+    /// Read an object by ID, sharing the decoded copy with every other
+    /// reader still holding it.
+    pub fn read_shared(&self, id: &ObjectId) -> PackResult<Option<Arc<StoredObject>>> {
+        self.shared.get_or_load(id, || self.read_object(id))
+    }
+
+    /// [`read_shared`](Self::read_shared) for the entry of `id` at `offset`.
+    pub(crate) fn read_shared_at(
+        &self,
+        id: &ObjectId,
+        offset: u64,
+        expected_crc: u32,
+    ) -> PackResult<Arc<StoredObject>> {
+        let load = || self.read_at_offset(offset, expected_crc).map(Some);
+        let object = self.shared.get_or_load(id, load)?;
+        Ok(object.expect("loaded objects are always present"))
+    }
+
     /// Check containment.
     pub fn contains(&self, id: &ObjectId) -> bool {
         self.index.contains(id)
@@ -74,41 +232,19 @@ impl PackReader {
         &self.index.object_ids
     }
 
-    fn read_at_offset(&self, offset: u64, expected_crc: u32) -> PackResult<StoredObject> {
-        let data = &self.pack_data;
-        let mut pos = offset as usize;
-
-        if pos >= data.len() {
-            return Err(PackError::CorruptEntry {
-                offset,
-                reason: "offset beyond pack data".into(),
-            });
-        }
-
-        let type_byte = data[pos];
-        pos += 1;
-
-        let kind = PackObjectKind::from_type_byte(type_byte).ok_or_else(|| {
-            PackError::CorruptEntry {
-                offset,
-                reason: format!("unknown type byte: {type_byte}"),
-            }
-        })?;
-
-        let (uncompressed_size, consumed) = decode_varint(&data[pos..])?;
-        pos += consumed;
+    pub(crate) fn read_at_offset(
+        &self,
+        offset: u64,
+        expected_crc: u32,
+    ) -> PackResult<StoredObject> {
+        self.read_entry(offset, expected_crc, 0)
+    }
 
-        let (compressed_size, consumed) = decode_varint(&data[pos..])?;
-        pos += consumed;
-
-        let end = pos + compressed_size as usize;
-        if end > data.len() {
-            return Err(PackError::CorruptEntry {
-                offset,
-                reason: "compressed data extends beyond pack".into(),
-            });
-        }
-        let compressed = &data[pos..end];
+    /// Read the entry at `offset`, resolving a delta through its base,
+    /// which is itself `depth` deltas away from the object asked for.
+    fn read_entry(&self, offset: u64, expected_crc: u32, depth: usize) -> PackResult<StoredObject> {
+        let header = entry_header(&self.pack_data, offset as usize)?;
+        let compressed = &self.pack_data[header.data];
 
         let actual_crc = crc32fast::hash(compressed);
         if actual_crc != expected_crc {
@@ -117,7 +253,16 @@ impl PackReader {
             });
         }
 
-        let decompressed = zstd::decode_all(compressed)
+        // Stop one byte past the recorded size so a small entry can't
+        // inflate into an arbitrarily large buffer before the check below.
+        let uncompressed_size = header.size;
+        let mut decompressed = Vec::new();
+        zstd::stream::read::Decoder::new(compressed)
+            .and_then(|decoder| {
+                decoder
+                    .take(uncompressed_size.saturating_add(1))
+                    .read_to_end(&mut decompressed)
+            })
             .map_err(|e| PackError::DecompressionFailed(e.to_string()))?;
 
         if decompressed.len() != uncompressed_size as usize {
@@ -130,16 +275,96 @@ impl PackReader {
             });
         }
 
-        let object_kind = match kind {
-            PackObjectKind::Full(k) => k,
-            PackObjectKind::Delta { .. } => {
-                return Err(PackError::CorruptEntry {
-                    offset,
-                    reason: "delta resolution not supported".into(),
-                });
+        match header.kind {
+            PackObjectKind::Full(kind) => Ok(StoredObject::new(kind, decompressed)),
+            PackObjectKind::Delta { base } => {
+                if depth >= MAX_DELTA_DEPTH {
+                    return Err(PackError::CorruptEntry {
+                        offset,
+                        reason: format!("delta chain longer than {MAX_DELTA_DEPTH}"),
+                    });
+                }
+                let (base_offset, base_crc) =
+                    self.index.lookup(&base).ok_or(PackError::DeltaBaseNotFound(base))?;
+                let base = self.read_entry(base_offset, base_crc, depth + 1)?;
+                let data = apply_delta(&base.data, &decompressed)?;
+                Ok(StoredObject::new(base.kind, data))
             }
-        };
+        }
+    }
+}
+
+/// The parsed header of one pack entry.
+struct EntryHeader {
+    kind: PackObjectKind,
+    /// Uncompressed size of the entry's data.
+    size: u64,
+    /// Where the compressed data lies in the pack.
+    data: Range<usize>,
+}
+
+/// Parse the header of the entry at `pos`, checking that its compressed
+/// data lies within `data`.
+fn entry_header(data: &[u8], pos: usize) -> PackResult<EntryHeader> {
+    let offset = pos as u64;
+    let corrupt = |reason: String| PackError::CorruptEntry { offset, reason };
+    let type_byte = *data
+        .get(pos)
+        .ok_or_else(|| corrupt("offset beyond pack data".into()))?;
+    let mut pos = pos + 1;
+    let (size, consumed) = decode_varint(&data[pos..])?;
+    pos += consumed;
+    let (compressed_size, consumed) = decode_varint(&data[pos..])?;
+    pos += consumed;
 
-        Ok(StoredObject::new(object_kind, decompressed))
+    let kind = match PackObjectKind::from_type_byte(type_byte) {
+        Some(kind) => kind,
+        None if type_byte == PackObjectKind::DELTA_TYPE_BYTE => {
+            let base: [u8; 32] = data
+                .get(pos..pos + 32)
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or_else(|| corrupt("truncated delta base id".into()))?;
+            pos += 32;
+            PackObjectKind::Delta {
+                base: ObjectId::from_hash(base),
+            }
+        }
+        None => return Err(corrupt(format!("unknown type byte: {type_byte}"))),
+    };
+
+    let end = pos
+        .checked_add(compressed_size as usize)
+        .filter(|end| *end <= data.len())
+        .ok_or_else(|| corrupt("compressed data extends beyond pack".into()))?;
+    Ok(EntryHeader {
+        kind,
+        size,
+        data: pos..end,
+    })
+}
+
+/// Validate the magic and version at the start of a pack.
+fn check_header(data: &[u8]) -> PackResult<()> {
+    let version = PackReader::format_version(data)?;
+    if !PACK_FORMAT.reads(version) {
+        return Err(PackError::UnsupportedVersion(version));
+    }
+    Ok(())
+}
+
+/// Validate the length and magic at the start of a pack.
+fn check_magic(data: &[u8]) -> PackResult<()> {
+    if data.len() < 12 {
+        return Err(PackError::CorruptEntry {
+            offset: 0,
+            reason: "pack data too short".into(),
+        });
+    }
+    if &data[0..4] != b"WLLP" {
+        return Err(PackError::InvalidMagic {
+            expected: "WLLP".into(),
+            actual: String::from_utf8_lossy(&data[0..4]).into(),
+        });
     }
+    Ok(())
 }
@@ -0,0 +1,191 @@
+//! Full verification of a pack and its index.
+//!
+//! [`PackReader::verify`] checks everything a reader would otherwise only
+//! check lazily, one object at a time: the trailing checksum, each entry's
+//! CRC32 against the index, and each object's content hash against the id
+//! it is indexed under. Every entry gets a result, so one bad entry does not
+//! hide the others.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use wll_types::ObjectId;
+
+use crate::reader::PackReader;
+
+/// What is wrong with one pack entry.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "problem")]
+pub enum EntryProblem {
+    /// The compressed data's CRC32 differs from the one in the index.
+    CrcMismatch { expected: u32, actual: u32 },
+    /// The object's content hashes to a different id than it is indexed
+    /// under.
+    IdMismatch { actual: ObjectId },
+    /// The pack holds the entry but the index does not list it.
+    NotIndexed,
+    /// The index lists an offset at which no entry starts.
+    NoEntryAtOffset,
+    /// The entry cannot be parsed or decompressed.
+    Corrupt { reason: String },
+}
+
+impl fmt::Display for EntryProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CrcMismatch { expected, actual } => {
+                write!(f, "CRC32 {actual:08x} does not match indexed {expected:08x}")
+            }
+            Self::IdMismatch { actual } => write!(f, "content hashes to {actual}"),
+            Self::NotIndexed => write!(f, "entry is missing from the index"),
+            Self::NoEntryAtOffset => write!(f, "no entry starts at the indexed offset"),
+            Self::Corrupt { reason } => write!(f, "corrupt entry: {reason}"),
+        }
+    }
+}
+
+/// The verification result of one pack entry.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryCheck {
+    /// Offset of the entry in the pack.
+    pub offset: u64,
+    /// The id the index lists for the entry, else the id its content
+    /// hashes to, if it could be read.
+    pub id: Option<ObjectId>,
+    /// Empty if the entry is sound.
+    pub problems: Vec<EntryProblem>,
+}
+
+impl EntryCheck {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// The result of [`PackReader::verify`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackVerification {
+    /// The trailing checksum matches the pack contents.
+    pub checksum_ok: bool,
+    /// The checksum recorded in the index matches the pack's.
+    pub index_checksum_ok: bool,
+    /// Every entry walked or indexed, in pack order.
+    pub entries: Vec<EntryCheck>,
+}
+
+impl PackVerification {
+    /// Returns `true` if the checksums and every entry are sound.
+    pub fn is_ok(&self) -> bool {
+        self.checksum_ok && self.index_checksum_ok && self.entries.iter().all(EntryCheck::is_ok)
+    }
+
+    /// Entries with at least one problem.
+    pub fn failures(&self) -> impl Iterator<Item = &EntryCheck> {
+        self.entries.iter().filter(|entry| !entry.is_ok())
+    }
+}
+
+impl PackReader {
+    /// Check the whole pack against its checksum and index, reporting
+    /// every entry instead of stopping at the first error.
+    ///
+    /// Entries are walked in pack order from the header. If an entry is too
+    /// damaged to find where the next one starts, the walk stops there and
+    /// the remaining indexed entries are checked at their indexed offsets.
+    pub fn verify(&self) -> PackVerification {
+        let data = &self.pack_data;
+        let index = &self.index;
+        let Some(body_len) = data.len().checked_sub(32).filter(|&len| len >= 12) else {
+            return PackVerification {
+                checksum_ok: false,
+                index_checksum_ok: false,
+                entries: Vec::new(),
+            };
+        };
+        let checksum: [u8; 32] = data[body_len..].try_into().unwrap_or([0; 32]);
+
+        let indexed: HashMap<u64, (ObjectId, u32)> = index
+            .offsets
+            .iter()
+            .zip(index.object_ids.iter().zip(&index.crc32s))
+            .map(|(&offset, (&id, &crc))| (offset, (id, crc)))
+            .collect();
+        let mut entries = Vec::with_capacity(indexed.len());
+        let mut walked = HashSet::new();
+
+        let count = u32::from_be_bytes(data[8..12].try_into().unwrap_or([0; 4]));
+        let mut pos = 12usize;
+        for _ in 0..count {
+            let offset = pos as u64;
+            walked.insert(offset);
+            match self.entry_bounds(pos, body_len) {
+                Ok((crc, next)) => {
+                    entries.push(self.check_entry(offset, crc, indexed.get(&offset)));
+                    pos = next;
+                }
+                Err(e) => {
+                    entries.push(EntryCheck {
+                        offset,
+                        id: indexed.get(&offset).map(|(id, _)| *id),
+                        problems: vec![EntryProblem::Corrupt { reason: e.to_string() }],
+                    });
+                    break;
+                }
+            }
+        }
+
+        let mut rest: Vec<(&u64, &(ObjectId, u32))> =
+            indexed.iter().filter(|(offset, _)| !walked.contains(offset)).collect();
+        rest.sort_by_key(|(offset, _)| **offset);
+        for (&offset, entry) in rest {
+            let bounds = usize::try_from(offset)
+                .ok()
+                .filter(|&pos| pos >= 12)
+                .and_then(|pos| self.entry_bounds(pos, body_len).ok());
+            let mut check = match bounds {
+                Some((crc, _)) => self.check_entry(offset, crc, Some(entry)),
+                None => EntryCheck { offset, id: Some(entry.0), problems: Vec::new() },
+            };
+            check.problems.insert(0, EntryProblem::NoEntryAtOffset);
+            entries.push(check);
+        }
+        entries.sort_by_key(|entry| entry.offset);
+
+        PackVerification {
+            checksum_ok: *blake3::hash(&data[..body_len]).as_bytes() == checksum,
+            index_checksum_ok: index.pack_checksum == checksum,
+            entries,
+        }
+    }
+
+    /// Check the entry at `offset`, whose data has CRC32 `crc`, against
+    /// its index record.
+    fn check_entry(&self, offset: u64, crc: u32, indexed: Option<&(ObjectId, u32)>) -> EntryCheck {
+        let mut problems = Vec::new();
+        match indexed {
+            Some(&(_, expected)) if expected != crc => {
+                problems.push(EntryProblem::CrcMismatch { expected, actual: crc });
+            }
+            Some(_) => {}
+            None => problems.push(EntryProblem::NotIndexed),
+        }
+        let actual = match self.read_at_offset(offset, crc) {
+            Ok(object) => Some(object.compute_id()),
+            Err(e) => {
+                problems.push(EntryProblem::Corrupt { reason: e.to_string() });
+                None
+            }
+        };
+        if let (Some(&(id, _)), Some(actual)) = (indexed, actual) {
+            if id != actual {
+                problems.push(EntryProblem::IdMismatch { actual });
+            }
+        }
+        EntryCheck {
+            offset,
+            id: indexed.map(|(id, _)| *id).or(actual),
+            problems,
+        }
+    }
+}
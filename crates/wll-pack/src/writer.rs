@@ -1,11 +1,15 @@
+use std::io::Write;
 use std::path::{Path, PathBuf};
 
-use wll_store::{ObjectKind, StoredObject};
+use rayon::prelude::*;
+use wll_store::{ObjectKind, StoredObject, WriteOptions};
 use wll_types::ObjectId;
 
+use crate::delta::compute_delta;
 use crate::entry::{PackEntry, PackObjectKind};
 use crate::error::{PackError, PackResult};
 use crate::index::PackIndex;
+use crate::reader::PACK_FORMAT;
 
 /// Result of writing a pack file.
 #[derive(Clone, Debug)]
@@ -16,10 +20,83 @@ pub struct PackFile {
     pub checksum: [u8; 32],
 }
 
+/// Tuning for how a [`PackWriter`] compresses its objects.
+#[derive(Clone, Debug)]
+pub struct PackWriterOptions {
+    /// Worker threads for hashing and compression. `0` uses rayon's global
+    /// pool (one thread per core); `1` does all the work on the caller.
+    pub threads: usize,
+    /// zstd compression level.
+    pub compression_level: i32,
+    /// Flushing and read-back of the pack and index files
+    /// [`finish`](PackWriter::finish) writes.
+    pub durability: WriteOptions,
+}
+
+impl Default for PackWriterOptions {
+    fn default() -> Self {
+        Self {
+            threads: 0,
+            compression_level: 3,
+            durability: WriteOptions::default(),
+        }
+    }
+}
+
+/// An object waiting to be written. Stored objects are hashed, and deltas
+/// computed, by the compression workers rather than when they are queued.
+enum Queued {
+    Entry(PackEntry),
+    Stored(StoredObject),
+    Delta {
+        object: StoredObject,
+        base_id: ObjectId,
+        base: Vec<u8>,
+    },
+}
+
+/// An object after hashing and compression, ready for the ordered writer.
+struct Compressed {
+    id: ObjectId,
+    kind: PackObjectKind,
+    size: usize,
+    data: Vec<u8>,
+    crc32: u32,
+}
+
+impl Queued {
+    fn compress(&self, level: i32) -> PackResult<Compressed> {
+        let delta;
+        let (id, kind, data) = match self {
+            Self::Entry(entry) => (entry.id, entry.kind, &entry.data),
+            Self::Stored(obj) => (obj.compute_id(), PackObjectKind::Full(obj.kind), &obj.data),
+            Self::Delta { object, base_id, base } => {
+                delta = compute_delta(base, &object.data);
+                // A delta that saves nothing is written as the full object.
+                if delta.len() < object.data.len() {
+                    (object.compute_id(), PackObjectKind::Delta { base: *base_id }, &delta)
+                } else {
+                    (object.compute_id(), PackObjectKind::Full(object.kind), &object.data)
+                }
+            }
+        };
+        let compressed = zstd::encode_all(data.as_slice(), level)
+            .map_err(|e| PackError::CompressionFailed(e.to_string()))?;
+        Ok(Compressed {
+            id,
+            kind,
+            size: data.len(),
+            crc32: crc32fast::hash(&compressed),
+            data: compressed,
+        })
+    }
+}
+
 /// Builds a pack file from a collection of objects.
 pub struct PackWriter {
     path: PathBuf,
-    entries: Vec<PackEntry>,
+    entries: Vec<Queued>,
+    options: PackWriterOptions,
 }
 
 impl PackWriter {
@@ -28,23 +105,48 @@ impl PackWriter {
         Self {
             path: path.to_path_buf(),
             entries: Vec::new(),
+            options: PackWriterOptions::default(),
         }
     }
 
+    /// Use `options` when compressing.
+    pub fn with_options(mut self, options: PackWriterOptions) -> Self {
+        self.options = options;
+        self
+    }
+
     /// Add an object to the pack.
     pub fn add_object(&mut self, id: ObjectId, kind: ObjectKind, data: &[u8]) {
-        self.entries.push(PackEntry {
+        self.entries.push(Queued::Entry(PackEntry {
             id,
             kind: PackObjectKind::Full(kind),
             data: data.to_vec(),
             crc32: 0, // computed at write time
-        });
+        }));
     }
 
     /// Add a stored object directly.
     pub fn add_stored_object(&mut self, obj: &StoredObject) {
-        let id = obj.compute_id();
-        self.add_object(id, obj.kind, &obj.data);
+        self.entries.push(Queued::Stored(obj.clone()));
+    }
+
+    /// Add `obj` as a delta against `base`, if the delta is smaller.
+    ///
+    /// `base` need not be in the pack: a pack with deltas against objects
+    /// it does not contain is *thin*, smaller to send to a receiver known
+    /// to hold the bases, which fixes it on arrival with
+    /// [`PackReader::from_thin_pack_bytes`](crate::PackReader::from_thin_pack_bytes).
+    /// Objects of different kinds and tombstones are added in full.
+    pub fn add_delta_object(&mut self, obj: &StoredObject, base: &StoredObject) {
+        if obj.kind != base.kind || obj.kind == ObjectKind::Tombstone {
+            self.add_stored_object(obj);
+            return;
+        }
+        self.entries.push(Queued::Delta {
+            object: obj.clone(),
+            base_id: base.compute_id(),
+            base: base.data.clone(),
+        });
     }
 
     /// Number of objects queued.
@@ -62,10 +164,14 @@ impl PackWriter {
         let pack_path = self.path.with_extension("pack");
         let index_path = self.path.with_extension("idx");
 
+        let durability = self.options.durability;
         let (pack_data, index) = self.build_pack_bytes()?;
 
-        std::fs::write(&pack_path, &pack_data)?;
-        std::fs::write(&index_path, &index.to_bytes()?)?;
+        write_file(&pack_path, &pack_data, &durability)?;
+        write_file(&index_path, &index.to_bytes()?, &durability)?;
+        if let Some(dir) = pack_path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            durability.sync_dir(dir)?;
+        }
 
         Ok(PackFile {
             pack_path,
@@ -81,38 +187,27 @@ impl PackWriter {
     }
 
     fn build_pack_bytes(self) -> PackResult<(Vec<u8>, PackIndex)> {
-        let mut pack_data = Vec::new();
-        let mut index_entries = Vec::new();
+        let span = tracing::info_span!(
+            "pack.write",
+            objects = self.entries.len(),
+            bytes = tracing::field::Empty,
+        );
+        let _entered = span.enter();
+        let compressed = self.compress_all()?;
+        let body: usize = compressed.iter().map(|c| c.data.len() + 64).sum();
+        let mut pack_data = Vec::with_capacity(12 + body + 32);
+        let mut index_entries = Vec::with_capacity(compressed.len());
 
         // Header: magic + version + object count
         pack_data.extend_from_slice(b"WLLP");
-        pack_data.extend_from_slice(&1u32.to_be_bytes());
+        pack_data.extend_from_slice(&PACK_FORMAT.current.to_be_bytes());
         pack_data.extend_from_slice(&(self.entries.len() as u32).to_be_bytes());
 
-        for entry in &self.entries {
+        // Workers finish out of order; entries are written in queue order.
+        for entry in compressed {
             let offset = pack_data.len() as u64;
-
-            // Type byte
-            pack_data.push(entry.kind.type_byte());
-
-            // Compress data
-            let compressed = zstd::encode_all(entry.data.as_slice(), 3)
-                .map_err(|e| PackError::CompressionFailed(e.to_string()))?;
-
-            // Varint: uncompressed size
-            encode_varint(&mut pack_data, entry.data.len() as u64);
-            // Varint: compressed size
-            encode_varint(&mut pack_data, compressed.len() as u64);
-
-            // If delta, write base ID (not used yet but format supports it)
-            if let PackObjectKind::Delta { base } = &entry.kind {
-                pack_data.extend_from_slice(base.as_bytes());
-            }
-
-            let crc = crc32fast::hash(&compressed);
-            pack_data.extend_from_slice(&compressed);
-
-            index_entries.push((entry.id, crc, offset));
+            write_entry(&mut pack_data, entry.kind, entry.size, &entry.data);
+            index_entries.push((entry.id, entry.crc32, offset));
         }
 
         // Pack trailer: BLAKE3 checksum of everything so far
@@ -120,8 +215,55 @@ impl PackWriter {
         pack_data.extend_from_slice(&checksum);
 
         let index = PackIndex::build(index_entries, checksum);
+        span.record("bytes", pack_data.len());
         Ok((pack_data, index))
     }
+
+    /// Hash and compress every queued object on the configured workers,
+    /// returning the results in queue order.
+    fn compress_all(&self) -> PackResult<Vec<Compressed>> {
+        let level = self.options.compression_level;
+        let work = || {
+            self.entries
+                .par_iter()
+                .map(|entry| entry.compress(level))
+                .collect::<PackResult<Vec<_>>>()
+        };
+        match self.options.threads {
+            0 => work(),
+            1 => self.entries.iter().map(|entry| entry.compress(level)).collect(),
+            threads => rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .map_err(std::io::Error::other)?
+                .install(work),
+        }
+    }
+}
+
+/// Write `bytes` to `path`, flushing and reading them back as `options` ask.
+fn write_file(path: &Path, bytes: &[u8], options: &WriteOptions) -> PackResult<()> {
+    let mut file = std::fs::File::create(path)?;
+    file.write_all(bytes)?;
+    if options.fsync {
+        file.sync_all()?;
+    }
+    if options.verify && blake3::hash(&std::fs::read(path)?) != blake3::hash(bytes) {
+        return Err(PackError::ChecksumMismatch);
+    }
+    Ok(())
+}
+
+/// Append one entry: type byte, uncompressed and compressed sizes, the base
+/// id of a delta, then the compressed data.
+pub(crate) fn write_entry(pack: &mut Vec<u8>, kind: PackObjectKind, size: usize, data: &[u8]) {
+    pack.push(kind.type_byte());
+    encode_varint(pack, size as u64);
+    encode_varint(pack, data.len() as u64);
+    if let PackObjectKind::Delta { base } = kind {
+        pack.extend_from_slice(base.as_bytes());
+    }
+    pack.extend_from_slice(data);
 }
 
 /// Encode a u64 as a variable-length integer.
@@ -205,4 +347,17 @@ mod tests {
         let err = decode_varint(&[0x80]).unwrap_err();
         assert!(matches!(err, PackError::CorruptEntry { .. }));
     }
+
+    #[test]
+    fn durable_finish_writes_a_pack_that_reads_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let options =
+            PackWriterOptions { durability: WriteOptions::durable(), ..Default::default() };
+        let mut writer = PackWriter::new(&dir.path().join("pack-durable")).with_options(options);
+        let blob = StoredObject::new(ObjectKind::Blob, b"flushed".to_vec());
+        writer.add_stored_object(&blob);
+        let file = writer.finish().unwrap();
+        let reader = crate::PackReader::open(&file.pack_path).unwrap();
+        assert_eq!(reader.read_object(&blob.compute_id()).unwrap(), Some(blob));
+    }
 }
@@ -1,19 +1,18 @@
 use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
+use wll_crypto::Secret;
 
 /// Authentication method for connecting to a remote.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub enum AuthMethod {
-    Bearer(String),
+    /// A bearer token, redacted in `Debug` output.
+    Bearer(Secret),
     SshKey { key_path: PathBuf },
     MutualTls { cert_path: PathBuf, key_path: PathBuf },
+    #[default]
     Anonymous,
 }
 
-impl Default for AuthMethod {
-    fn default() -> Self { Self::Anonymous }
-}
-
 impl AuthMethod {
     pub fn is_authenticated(&self) -> bool {
         !matches!(self, Self::Anonymous)
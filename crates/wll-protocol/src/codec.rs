@@ -1,33 +1,228 @@
+use std::io::{self, IoSlice, Write};
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use wll_types::DecodeLimits;
+
 use crate::error::{ProtocolError, ProtocolResult};
-use crate::message::{WllMessage, MAX_MESSAGE_SIZE};
+use crate::message::{WllMessage, MAX_MESSAGE_SIZE, PACK_CHUNK_TAG};
+
+/// Bytes in a frame header: the 4-byte length and the 1-byte tag.
+pub const HEADER_LEN: usize = 5;
+
+/// Packs larger than this are sent as a `PackStream` followed by raw
+/// `PackChunk` frames instead of a single `PackData` message.
+pub const PACK_STREAM_THRESHOLD: usize = 1024 * 1024;
+
+/// Payload size of each `PackChunk` frame in a pack stream.
+pub const PACK_CHUNK_SIZE: usize = 256 * 1024;
+
+/// One encoded frame, with the header and payload held apart so the payload
+/// can be written straight from the buffer that owns it.
+#[derive(Clone, Debug)]
+pub struct Frame {
+    header: [u8; HEADER_LEN],
+    payload: Bytes,
+}
+
+impl Frame {
+    fn new(tag: u8, payload: Bytes) -> Self {
+        let mut header = [0u8; HEADER_LEN];
+        header[..4].copy_from_slice(&((payload.len() + 1) as u32).to_be_bytes());
+        header[4] = tag;
+        Self { header, payload }
+    }
+
+    /// The message type tag carried in the header.
+    pub fn tag(&self) -> u8 {
+        self.header[4]
+    }
+
+    /// The frame payload, without the header.
+    pub fn payload(&self) -> &Bytes {
+        &self.payload
+    }
+
+    /// Total bytes this frame occupies on the wire.
+    pub fn wire_len(&self) -> usize {
+        HEADER_LEN + self.payload.len()
+    }
+
+    /// Header and payload as slices for a vectored write.
+    pub fn io_slices(&self) -> [IoSlice<'_>; 2] {
+        [IoSlice::new(&self.header), IoSlice::new(&self.payload)]
+    }
+
+    /// The slices still to be written after the first `written` bytes.
+    fn unwritten(&self, written: usize) -> [IoSlice<'_>; 2] {
+        [
+            IoSlice::new(&self.header[written.min(HEADER_LEN)..]),
+            IoSlice::new(&self.payload[written.saturating_sub(HEADER_LEN)..]),
+        ]
+    }
+
+    /// Copy the frame into one contiguous buffer.
+    pub fn to_bytes(&self) -> Bytes {
+        let mut buf = BytesMut::with_capacity(self.wire_len());
+        buf.put_slice(&self.header);
+        buf.put_slice(&self.payload);
+        buf.freeze()
+    }
+
+    /// Write the whole frame with vectored writes.
+    pub fn write_to<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let mut written = 0;
+        while written < self.wire_len() {
+            match out.write_vectored(&self.unwritten(written)) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => written += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the whole frame to an async writer with vectored writes.
+    pub async fn write_to_async<W: AsyncWrite + Unpin>(&self, out: &mut W) -> io::Result<()> {
+        let mut written = 0;
+        while written < self.wire_len() {
+            match out.write_vectored(&self.unwritten(written)).await {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => written += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
 
 /// Codec for encoding/decoding WLL protocol messages.
 pub struct WllCodec;
 
 impl WllCodec {
-    /// Encode a message with framing: [4 bytes len][1 byte tag][payload]
-    pub fn encode(msg: &WllMessage) -> ProtocolResult<Vec<u8>> {
-        let payload = bincode::serialize(msg)
+    /// Encode a message with framing: `[4 bytes len][1 byte tag][payload]`
+    pub fn encode(msg: &WllMessage) -> ProtocolResult<Bytes> {
+        let mut buf = BytesMut::new();
+        Self::encode_into(msg, &mut buf)?;
+        Ok(buf.freeze())
+    }
+
+    /// Append a framed message to `dst`, serializing straight into it.
+    pub fn encode_into(msg: &WllMessage, dst: &mut BytesMut) -> ProtocolResult<()> {
+        if let WllMessage::PackChunk { data } = msg {
+            let frame = Frame::new(PACK_CHUNK_TAG, data.clone());
+            dst.reserve(frame.wire_len());
+            dst.put_slice(&frame.header);
+            dst.put_slice(&frame.payload);
+            return Ok(());
+        }
+        let size = bincode::serialized_size(msg)
+            .map_err(|e| ProtocolError::Serialization(e.to_string()))? as usize;
+        if size > MAX_MESSAGE_SIZE {
+            return Err(ProtocolError::MessageTooLarge { size, max: MAX_MESSAGE_SIZE });
+        }
+        dst.reserve(HEADER_LEN + size);
+        dst.put_u32((size + 1) as u32);
+        dst.put_u8(msg.type_tag());
+        let start = dst.len();
+        bincode::serialize_into(dst.writer(), msg)
             .map_err(|e| ProtocolError::Serialization(e.to_string()))?;
-        if payload.len() > MAX_MESSAGE_SIZE {
-            return Err(ProtocolError::MessageTooLarge {
-                size: payload.len(),
-                max: MAX_MESSAGE_SIZE,
-            });
+        debug_assert_eq!(dst.len() - start, size);
+        Ok(())
+    }
+
+    /// Encode a message as a [`Frame`]. `PackChunk` payloads are shared with
+    /// the caller's buffer rather than copied.
+    pub fn frame(msg: &WllMessage) -> ProtocolResult<Frame> {
+        if let WllMessage::PackChunk { data } = msg {
+            return Ok(Frame::new(PACK_CHUNK_TAG, data.clone()));
+        }
+        let mut buf = BytesMut::new();
+        Self::encode_into(msg, &mut buf)?;
+        let payload = buf.split_off(HEADER_LEN).freeze();
+        Ok(Frame { header: buf[..].try_into().unwrap(), payload })
+    }
+
+    /// Frames that carry `pack`. Packs up to [`PACK_STREAM_THRESHOLD`] go as
+    /// one `PackData`; larger ones as a `PackStream` announcing the length,
+    /// then `PackChunk` frames that slice `pack` without copying it.
+    pub fn pack_frames(pack: Bytes) -> ProtocolResult<Vec<Frame>> {
+        if pack.len() <= PACK_STREAM_THRESHOLD {
+            return Ok(vec![Self::frame(&WllMessage::PackData { pack_bytes: pack.to_vec() })?]);
         }
-        let len = (payload.len() + 1) as u32;
-        let mut buf = Vec::with_capacity(4 + 1 + payload.len());
-        buf.extend_from_slice(&len.to_be_bytes());
-        buf.push(msg.type_tag());
-        buf.extend_from_slice(&payload);
-        Ok(buf)
+        let mut frames = Vec::with_capacity(1 + pack.len().div_ceil(PACK_CHUNK_SIZE));
+        frames.push(Self::frame(&WllMessage::PackStream { total_len: pack.len() as u64 })?);
+        let mut rest = pack;
+        while !rest.is_empty() {
+            let chunk = rest.split_to(rest.len().min(PACK_CHUNK_SIZE));
+            frames.push(Frame::new(PACK_CHUNK_TAG, chunk));
+        }
+        Ok(frames)
     }
 
-    /// Decode a framed message. Returns (message, bytes_consumed).
+    /// Decode a framed message under the default [`DecodeLimits`]. Returns
+    /// (message, bytes_consumed).
     pub fn decode(data: &[u8]) -> ProtocolResult<(WllMessage, usize)> {
-        if data.len() < 5 {
+        Self::decode_with_limits(data, &DecodeLimits::DEFAULT)
+    }
+
+    /// Decode a framed message, refusing one whose contents exceed `limits`
+    /// (see [`WllMessage::check_limits`]).
+    pub fn decode_with_limits(
+        data: &[u8],
+        limits: &DecodeLimits,
+    ) -> ProtocolResult<(WllMessage, usize)> {
+        if data.len() < HEADER_LEN {
             return Err(ProtocolError::FramingError("too short".into()));
         }
+        let total = Self::frame_len(data)?;
+        if data.len() < total {
+            return Err(ProtocolError::FramingError(format!(
+                "incomplete: have {}, need {}", data.len(), total
+            )));
+        }
+        let payload = &data[HEADER_LEN..total];
+        if data[4] == PACK_CHUNK_TAG {
+            let data = Bytes::copy_from_slice(payload);
+            return Ok((WllMessage::PackChunk { data }, total));
+        }
+        Ok((Self::decode_tagged(data[4], payload, limits)?, total))
+    }
+
+    /// Decode the next frame from a receive buffer, removing it from `src`.
+    /// Returns `None` until a whole frame has arrived. `PackChunk` data is
+    /// split off `src` without copying.
+    pub fn decode_frame(src: &mut BytesMut) -> ProtocolResult<Option<WllMessage>> {
+        Self::decode_frame_with_limits(src, &DecodeLimits::DEFAULT)
+    }
+
+    /// [`Self::decode_frame`], refusing a message whose contents exceed
+    /// `limits`.
+    pub fn decode_frame_with_limits(
+        src: &mut BytesMut,
+        limits: &DecodeLimits,
+    ) -> ProtocolResult<Option<WllMessage>> {
+        if src.len() < HEADER_LEN {
+            return Ok(None);
+        }
+        let total = Self::frame_len(src)?;
+        if src.len() < total {
+            src.reserve(total - src.len());
+            return Ok(None);
+        }
+        let mut frame = src.split_to(total);
+        let tag = frame[4];
+        frame.advance(HEADER_LEN);
+        if tag == PACK_CHUNK_TAG {
+            return Ok(Some(WllMessage::PackChunk { data: frame.freeze() }));
+        }
+        Self::decode_tagged(tag, &frame, limits).map(Some)
+    }
+
+    /// Validate a frame header and return the full frame length.
+    fn frame_len(data: &[u8]) -> ProtocolResult<usize> {
         let len = u32::from_be_bytes(data[0..4].try_into().unwrap()) as usize;
         if len < 1 {
             return Err(ProtocolError::FramingError("zero-length frame".into()));
@@ -35,16 +230,19 @@ impl WllCodec {
         if len - 1 > MAX_MESSAGE_SIZE {
             return Err(ProtocolError::MessageTooLarge { size: len - 1, max: MAX_MESSAGE_SIZE });
         }
-        let total = 4 + len;
-        if data.len() < total {
-            return Err(ProtocolError::FramingError(format!(
-                "incomplete: have {}, need {}", data.len(), total
-            )));
+        Ok(4 + len)
+    }
+
+    fn decode_tagged(
+        tag: u8,
+        payload: &[u8],
+        limits: &DecodeLimits,
+    ) -> ProtocolResult<WllMessage> {
+        let msg = Self::decode_payload_with_limits(payload, limits)?;
+        if msg.type_tag() != tag {
+            return Err(ProtocolError::InvalidMessageType(tag));
         }
-        let payload = &data[5..total];
-        let msg: WllMessage = bincode::deserialize(payload)
-            .map_err(|e| ProtocolError::Deserialization(e.to_string()))?;
-        Ok((msg, total))
+        Ok(msg)
     }
 
     /// Encode payload only (no framing).
@@ -52,9 +250,24 @@ impl WllCodec {
         bincode::serialize(msg).map_err(|e| ProtocolError::Serialization(e.to_string()))
     }
 
-    /// Decode payload only (no framing).
+    /// Decode payload only (no framing), under the default [`DecodeLimits`].
     pub fn decode_payload(data: &[u8]) -> ProtocolResult<WllMessage> {
-        bincode::deserialize(data).map_err(|e| ProtocolError::Deserialization(e.to_string()))
+        Self::decode_payload_with_limits(data, &DecodeLimits::DEFAULT)
+    }
+
+    /// Decode payload only (no framing), refusing a message whose contents
+    /// exceed `limits`.
+    ///
+    /// Bincode allocates no more than the payload holds, which the frame
+    /// size caps, so the limits are checked on the decoded message.
+    pub fn decode_payload_with_limits(
+        data: &[u8],
+        limits: &DecodeLimits,
+    ) -> ProtocolResult<WllMessage> {
+        let msg: WllMessage = bincode::deserialize(data)
+            .map_err(|e| ProtocolError::Deserialization(e.to_string()))?;
+        msg.check_limits(limits)?;
+        Ok(msg)
     }
 }
 
@@ -62,7 +275,9 @@ impl WllCodec {
 mod tests {
     use super::*;
     use crate::message::*;
-    use wll_types::{ObjectId, WorldlineId};
+    use crate::consistency::ConsistencyToken;
+    use crate::page::{PageDirection, ReceiptCursor};
+    use wll_types::{Coded, ErrorCode, LimitExceeded, ObjectId, WorldlineId};
     use wll_types::identity::IdentityMaterial;
 
     fn wl() -> WorldlineId {
@@ -94,10 +309,12 @@ mod tests {
 
     roundtrip_test!(list_refs_request_roundtrip, WllMessage::ListRefsRequest {
         prefix: Some("refs/heads/".into()),
+        min_position: Some(ConsistencyToken::new().with_position(wl(), 7, [7; 32])),
     });
 
     roundtrip_test!(list_refs_response_roundtrip, WllMessage::ListRefsResponse {
         refs: vec![("main".into(), [1u8; 32])],
+        consistency: Some(ConsistencyToken::new().with_position(wl(), 9, [9; 32])),
     });
 
     roundtrip_test!(want_request_roundtrip, WllMessage::WantRequest {
@@ -110,6 +327,33 @@ mod tests {
         common: vec![ObjectId::null()],
     });
 
+    #[test]
+    fn frames_written_by_0_1_0_still_decode() {
+        // A `WantRequest` then an `AckResponse`, encoded by the 0.1.0 codec.
+        let data = include_bytes!("../tests/fixtures/v0.1.0-frames.bin");
+        let (want, used) = WllCodec::decode(data).unwrap();
+        let (ack, rest) = WllCodec::decode(&data[used..]).unwrap();
+        assert_eq!(used + rest, data.len());
+
+        let WllMessage::WantRequest { wants, haves, depth } = want else {
+            panic!("expected a want request, got {want:?}");
+        };
+        assert_eq!(wants, [ObjectId::from_bytes(b"want-1"), ObjectId::from_bytes(b"want-2")]);
+        assert_eq!(haves, [ObjectId::from_bytes(b"have")]);
+        assert_eq!(depth, Some(3));
+        let WllMessage::AckResponse { common } = ack else {
+            panic!("expected an ack response, got {ack:?}");
+        };
+        assert_eq!(common, [ObjectId::from_bytes(b"have")]);
+
+        // The same messages encode to the same bytes today.
+        let mut again = WllCodec::encode(&WllMessage::WantRequest { wants, haves, depth })
+            .unwrap()
+            .to_vec();
+        again.extend_from_slice(&WllCodec::encode(&WllMessage::AckResponse { common }).unwrap());
+        assert_eq!(again, data);
+    }
+
     roundtrip_test!(pack_data_roundtrip, WllMessage::PackData {
         pack_bytes: vec![1, 2, 3, 4, 5],
     });
@@ -137,6 +381,7 @@ mod tests {
             new_hash: [2; 32],
             force: false,
         }],
+        atomic: true,
     });
 
     roundtrip_test!(ref_update_response_roundtrip, WllMessage::RefUpdateResponse {
@@ -144,6 +389,27 @@ mod tests {
             RefUpdateResultMsg::Ok { name: "main".into() },
             RefUpdateResultMsg::Rejected { name: "dev".into(), reason: "non-ff".into() },
         ],
+        consistency: None,
+    });
+
+    roundtrip_test!(pack_stream_roundtrip, WllMessage::PackStream { total_len: 1 << 40 });
+
+    roundtrip_test!(receipt_page_request_roundtrip, WllMessage::ReceiptPageRequest {
+        worldline: wl(),
+        cursor: Some(ReceiptCursor { seq: 40, receipt_hash: [4; 32] }),
+        limit: 20,
+        direction: PageDirection::Backward,
+    });
+
+    roundtrip_test!(receipt_page_response_roundtrip, WllMessage::ReceiptPageResponse {
+        worldline: wl(),
+        receipts_data: b"[]".to_vec(),
+        count: 0,
+        next: None,
+    });
+
+    roundtrip_test!(pack_chunk_roundtrip, WllMessage::PackChunk {
+        data: Bytes::from_static(b"raw pack bytes"),
     });
 
     roundtrip_test!(error_roundtrip, WllMessage::Error {
@@ -151,22 +417,58 @@ mod tests {
         message: "not found".into(),
     });
 
+    #[test]
+    fn error_messages_carry_stable_codes() {
+        let sent = WllMessage::error(&ProtocolError::MessageTooLarge { size: 9, max: 8 });
+        let encoded = WllCodec::encode(&sent).unwrap();
+        let (WllMessage::Error { code, message }, _) = WllCodec::decode(&encoded).unwrap() else {
+            panic!("expected an Error message");
+        };
+        let received = ProtocolError::RemoteError { code, message };
+        assert_eq!(received.code(), wll_types::ErrorCode::MessageTooLarge);
+        let unknown = ProtocolError::RemoteError { code: 404, message: String::new() };
+        assert_eq!(unknown.code(), wll_types::ErrorCode::RemoteError);
+    }
+
+    #[test]
+    fn ref_prefixes_name_one_ref_or_a_namespace() {
+        assert!(ref_in_prefix("refs/heads/main", "refs/heads/main"));
+        assert!(!ref_in_prefix("refs/heads/main-old", "refs/heads/main"));
+        assert!(ref_in_prefix("refs/tags/v1", "refs/tags/"));
+        assert!(ref_in_prefix("refs/tags/v1", "refs/tags"));
+        assert!(ref_in_prefix("refs/tags/v1", ""));
+    }
+
     #[test]
     fn type_tags_unique() {
         let msgs: Vec<WllMessage> = vec![
             WllMessage::Hello { version: 1, capabilities: vec![] },
             WllMessage::HelloAck { version: 1, capabilities: vec![] },
-            WllMessage::ListRefsRequest { prefix: None },
-            WllMessage::ListRefsResponse { refs: vec![] },
+            WllMessage::ListRefsRequest { prefix: None, min_position: None },
+            WllMessage::ListRefsResponse { refs: vec![], consistency: None },
             WllMessage::WantRequest { wants: vec![], haves: vec![], depth: None },
             WllMessage::AckResponse { common: vec![] },
             WllMessage::PackData { pack_bytes: vec![] },
             WllMessage::PackAck { checksum: [0; 32], object_count: 0 },
             WllMessage::ReceiptBatch { worldline: wl(), receipts_data: vec![], count: 0 },
             WllMessage::ReceiptAck { worldline: wl(), through_seq: 0 },
-            WllMessage::RefUpdateRequest { updates: vec![] },
-            WllMessage::RefUpdateResponse { results: vec![] },
+            WllMessage::RefUpdateRequest { updates: vec![], atomic: false },
+            WllMessage::RefUpdateResponse { results: vec![], consistency: None },
             WllMessage::Error { code: 0, message: String::new() },
+            WllMessage::PackStream { total_len: 0 },
+            WllMessage::PackChunk { data: Bytes::new() },
+            WllMessage::ReceiptPageRequest {
+                worldline: wl(),
+                cursor: None,
+                limit: 0,
+                direction: PageDirection::Forward,
+            },
+            WllMessage::ReceiptPageResponse {
+                worldline: wl(),
+                receipts_data: vec![],
+                count: 0,
+                next: None,
+            },
         ];
         let mut tags: Vec<u8> = msgs.iter().map(|m| m.type_tag()).collect();
         let len = tags.len();
@@ -196,6 +498,156 @@ mod tests {
         assert!(matches!(err, ProtocolError::FramingError(_)));
     }
 
+    #[test]
+    fn decode_rejects_mismatched_tag() {
+        let msg = WllMessage::Hello { version: 1, capabilities: vec![] };
+        let mut encoded = WllCodec::encode(&msg).unwrap().to_vec();
+        encoded[4] = WllMessage::Error { code: 0, message: String::new() }.type_tag();
+        let err = WllCodec::decode(&encoded).unwrap_err();
+        assert!(matches!(err, ProtocolError::InvalidMessageType(_)));
+    }
+
+    #[test]
+    fn frame_matches_contiguous_encoding() {
+        let msg = WllMessage::Hello { version: 1, capabilities: vec!["pack-v1".into()] };
+        let frame = WllCodec::frame(&msg).unwrap();
+        let encoded = WllCodec::encode(&msg).unwrap();
+        assert_eq!(frame.tag(), msg.type_tag());
+        assert_eq!(frame.to_bytes(), encoded);
+
+        let mut written = Vec::new();
+        frame.write_to(&mut written).unwrap();
+        assert_eq!(written, encoded);
+    }
+
+    #[test]
+    fn frames_survive_short_writes() {
+        struct Trickle(Vec<u8>);
+        impl Write for Trickle {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                let n = buf.len().min(3);
+                self.0.extend_from_slice(&buf[..n]);
+                Ok(n)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let frame = WllCodec::frame(&WllMessage::PackChunk {
+            data: Bytes::from_static(b"a chunk longer than one write"),
+        })
+        .unwrap();
+        let mut out = Trickle(Vec::new());
+        frame.write_to(&mut out).unwrap();
+        assert_eq!(out.0, frame.to_bytes());
+    }
+
+    #[tokio::test]
+    async fn frames_write_to_async_writers() {
+        let pack = Bytes::from(vec![9u8; 1000]);
+        let frame = WllCodec::frame(&WllMessage::PackChunk { data: pack }).unwrap();
+        let mut written = Vec::new();
+        frame.write_to_async(&mut written).await.unwrap();
+        assert_eq!(written.len(), frame.wire_len());
+        assert_eq!(&written[HEADER_LEN..], &frame.payload()[..]);
+    }
+
+    #[test]
+    fn small_packs_go_as_one_pack_data() {
+        let frames = WllCodec::pack_frames(Bytes::from(vec![1u8; 64])).unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].tag(), WllMessage::PackData { pack_bytes: vec![] }.type_tag());
+    }
+
+    #[test]
+    fn large_packs_stream_in_chunks_without_copying() {
+        let pack: Bytes = (0..PACK_STREAM_THRESHOLD + PACK_CHUNK_SIZE / 2)
+            .map(|i| i as u8)
+            .collect::<Vec<_>>()
+            .into();
+        let frames = WllCodec::pack_frames(pack.clone()).unwrap();
+        assert_eq!(frames.len(), 1 + pack.len().div_ceil(PACK_CHUNK_SIZE));
+        assert_eq!(frames[1].payload().as_ptr(), pack.as_ptr());
+
+        let mut wire = Vec::new();
+        for frame in &frames {
+            frame.write_to(&mut wire).unwrap();
+        }
+        let mut src = BytesMut::from(&wire[..]);
+        let Some(WllMessage::PackStream { total_len }) = WllCodec::decode_frame(&mut src).unwrap()
+        else {
+            panic!("expected a PackStream header");
+        };
+        let mut received = Vec::new();
+        while received.len() < total_len as usize {
+            match WllCodec::decode_frame(&mut src).unwrap() {
+                Some(WllMessage::PackChunk { data }) => received.extend_from_slice(&data),
+                other => panic!("expected a PackChunk, got {other:?}"),
+            }
+        }
+        assert!(src.is_empty());
+        assert_eq!(received, pack);
+    }
+
+    #[test]
+    fn decode_frame_waits_for_whole_frames() {
+        let first = WllMessage::ListRefsRequest {
+            prefix: Some("refs/heads/".into()),
+            min_position: None,
+        };
+        let second = WllMessage::PackChunk { data: Bytes::from_static(b"chunk") };
+        let mut wire = BytesMut::new();
+        WllCodec::encode_into(&first, &mut wire).unwrap();
+        WllCodec::encode_into(&second, &mut wire).unwrap();
+
+        let mut src = BytesMut::new();
+        let mut decoded = Vec::new();
+        for byte in wire.iter() {
+            src.put_u8(*byte);
+            if let Some(msg) = WllCodec::decode_frame(&mut src).unwrap() {
+                decoded.push(msg);
+            }
+        }
+        assert!(src.is_empty());
+        assert_eq!(decoded.len(), 2);
+        assert!(matches!(
+            &decoded[0],
+            WllMessage::ListRefsRequest { prefix: Some(p), .. } if p == "refs/heads/"
+        ));
+        assert!(matches!(&decoded[1], WllMessage::PackChunk { data } if &data[..] == b"chunk"));
+    }
+
+    #[test]
+    fn messages_beyond_the_limits_are_refused() {
+        let limits = DecodeLimits::new().with_max_array_len(2).with_max_depth(4);
+        let wants = WllMessage::WantRequest {
+            wants: vec![ObjectId::from_bytes(b"a"); 3],
+            haves: vec![],
+            depth: None,
+        };
+        let encoded = WllCodec::encode(&wants).unwrap();
+        assert!(WllCodec::decode(&encoded).is_ok());
+        let err = WllCodec::decode_with_limits(&encoded, &limits).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::LimitExceeded);
+
+        // Receipts travel as JSON inside the message, and are scanned too.
+        let batch = WllMessage::ReceiptBatch {
+            worldline: wl(),
+            receipts_data: [b"[".repeat(5), b"]".repeat(5)].concat(),
+            count: 1,
+        };
+        let mut src = BytesMut::from(&WllCodec::encode(&batch).unwrap()[..]);
+        let err = WllCodec::decode_frame_with_limits(&mut src, &limits).unwrap_err();
+        assert!(matches!(err, ProtocolError::LimitExceeded(LimitExceeded::Depth { max: 4 })));
+    }
+
+    #[test]
+    fn pack_chunks_have_no_bincode_payload() {
+        let err = WllCodec::encode_payload(&WllMessage::PackChunk { data: Bytes::new() });
+        assert!(err.is_err());
+    }
+
     #[test]
     fn payload_roundtrip() {
         let msg = WllMessage::Hello { version: 1, capabilities: vec!["test".into()] };
@@ -209,4 +661,72 @@ mod tests {
         assert_eq!(capabilities::PACK_V1, "pack-v1");
         assert_eq!(capabilities::RECEIPT_CHAIN, "receipt-chain");
     }
+
+    mod props {
+        use proptest::prelude::*;
+
+        use super::*;
+
+        fn messages() -> impl Strategy<Value = WllMessage> {
+            let id = any::<[u8; 32]>().prop_map(ObjectId::from_hash);
+            prop_oneof![
+                (any::<u32>(), prop::collection::vec(".{0,16}", 0..4)).prop_map(
+                    |(version, capabilities)| WllMessage::Hello { version, capabilities }
+                ),
+                (
+                    prop::collection::vec(id.clone(), 0..8),
+                    prop::collection::vec(id, 0..8),
+                    any::<Option<u32>>(),
+                )
+                    .prop_map(|(wants, haves, depth)| WllMessage::WantRequest {
+                        wants,
+                        haves,
+                        depth,
+                    }),
+                prop::collection::vec(any::<u8>(), 0..256)
+                    .prop_map(|pack_bytes| WllMessage::PackData { pack_bytes }),
+                (any::<u32>(), ".{0,32}")
+                    .prop_map(|(code, message)| WllMessage::Error { code, message }),
+            ]
+        }
+
+        proptest! {
+            #[test]
+            fn frames_roundtrip(msg in messages()) {
+                let encoded = WllCodec::encode(&msg).unwrap();
+                let (decoded, consumed) = WllCodec::decode(&encoded).unwrap();
+                prop_assert_eq!(consumed, encoded.len());
+                prop_assert_eq!(WllCodec::encode(&decoded).unwrap(), encoded);
+            }
+
+            #[test]
+            fn truncated_frames_are_rejected(
+                msg in messages(),
+                cut in any::<prop::sample::Index>(),
+            ) {
+                let encoded = WllCodec::encode(&msg).unwrap();
+                let cut = cut.index(encoded.len());
+                prop_assert!(WllCodec::decode(&encoded[..cut]).is_err());
+            }
+
+            #[test]
+            fn mutated_frames_never_panic(
+                msg in messages(),
+                flips in prop::collection::vec((any::<prop::sample::Index>(), 1..=255u8), 1..8),
+            ) {
+                let mut encoded = WllCodec::encode(&msg).unwrap().to_vec();
+                for (at, mask) in flips {
+                    let at = at.index(encoded.len());
+                    encoded[at] ^= mask;
+                }
+                let _ = WllCodec::decode(&encoded);
+            }
+
+            #[test]
+            fn arbitrary_bytes_never_panic(data in prop::collection::vec(any::<u8>(), 0..1024)) {
+                let _ = WllCodec::decode(&data);
+                let _ = WllCodec::decode_payload(&data);
+            }
+        }
+    }
 }
@@ -0,0 +1,99 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use wll_types::WorldlineId;
+
+/// Where a worldline's receipt stream ends on the server that answered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LedgerPosition {
+    pub seq: u64,
+    pub receipt_hash: [u8; 32],
+}
+
+/// A server's ledger position for each worldline it holds.
+///
+/// Replicas report one with their responses so a client can tell how far
+/// they have caught up. A client that needs to read its own writes keeps
+/// the token the primary returned and asks for reads that [`covers`]
+/// it, retrying or going to the primary while a replica lags.
+///
+/// [`covers`]: Self::covers
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConsistencyToken {
+    pub positions: BTreeMap<WorldlineId, LedgerPosition>,
+}
+
+impl ConsistencyToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_position(mut self, worldline: WorldlineId, seq: u64, receipt_hash: [u8; 32]) -> Self {
+        self.positions.insert(worldline, LedgerPosition { seq, receipt_hash });
+        self
+    }
+
+    pub fn position(&self, worldline: &WorldlineId) -> Option<&LedgerPosition> {
+        self.positions.get(worldline)
+    }
+
+    /// Whether no position is recorded, so any server satisfies it.
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Worldlines for which this token is behind `required`.
+    pub fn lagging<'a>(&self, required: &'a ConsistencyToken) -> Vec<&'a WorldlineId> {
+        required
+            .positions
+            .iter()
+            .filter(|(worldline, wanted)| {
+                self.position(worldline).map_or(true, |held| held.seq < wanted.seq)
+            })
+            .map(|(worldline, _)| worldline)
+            .collect()
+    }
+
+    /// Whether this token is at or past `required` on every worldline.
+    pub fn covers(&self, required: &ConsistencyToken) -> bool {
+        self.lagging(required).is_empty()
+    }
+
+    /// Raise every position to the later of this token's and `other`'s.
+    pub fn merge(&mut self, other: &ConsistencyToken) {
+        for (worldline, position) in &other.positions {
+            let held = self.positions.entry(worldline.clone()).or_insert(*position);
+            if held.seq < position.seq {
+                *held = *position;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wll_types::IdentityMaterial;
+
+    fn wl(seed: u8) -> WorldlineId {
+        WorldlineId::derive(&IdentityMaterial::GenesisHash([seed; 32]))
+    }
+
+    #[test]
+    fn covers_requires_every_worldline_at_or_past_the_position() {
+        let required = ConsistencyToken::new().with_position(wl(1), 5, [5; 32]);
+        let behind = ConsistencyToken::new().with_position(wl(1), 4, [4; 32]);
+        let ahead = ConsistencyToken::new()
+            .with_position(wl(1), 6, [6; 32])
+            .with_position(wl(2), 1, [1; 32]);
+
+        assert!(!behind.covers(&required));
+        assert_eq!(behind.lagging(&required), vec![&wl(1)]);
+        assert!(ahead.covers(&required) && ahead.covers(&ConsistencyToken::new()));
+        assert!(!ConsistencyToken::new().covers(&required));
+
+        let mut merged = behind;
+        merged.merge(&ahead);
+        assert_eq!(merged, ahead);
+    }
+}
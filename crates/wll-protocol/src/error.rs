@@ -1,4 +1,5 @@
 use thiserror::Error;
+use wll_types::{Coded, ErrorCode, LimitExceeded};
 
 #[derive(Debug, Error)]
 pub enum ProtocolError {
@@ -20,6 +21,9 @@ pub enum ProtocolError {
     #[error("deserialization error: {0}")]
     Deserialization(String),
 
+    #[error("message exceeds decoding limits: {0}")]
+    LimitExceeded(#[from] LimitExceeded),
+
     #[error("protocol error: code={code}, message={message}")]
     RemoteError { code: u32, message: String },
 
@@ -27,4 +31,24 @@ pub enum ProtocolError {
     Io(#[from] std::io::Error),
 }
 
+impl Coded for ProtocolError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::InvalidMessageType(_) | Self::FramingError(_) | Self::Deserialization(_) => {
+                ErrorCode::ProtocolMalformed
+            }
+            Self::MessageTooLarge { .. } => ErrorCode::MessageTooLarge,
+            Self::LimitExceeded(e) => e.code(),
+            Self::VersionMismatch { .. } => ErrorCode::VersionMismatch,
+            Self::Serialization(_) => ErrorCode::Serialization,
+            // The peer's own code, when it sent one we know.
+            Self::RemoteError { code, .. } => u16::try_from(*code)
+                .ok()
+                .and_then(ErrorCode::from_number)
+                .unwrap_or(ErrorCode::RemoteError),
+            Self::Io(_) => ErrorCode::Io,
+        }
+    }
+}
+
 pub type ProtocolResult<T> = Result<T, ProtocolError>;
@@ -5,15 +5,19 @@
 
 pub mod auth;
 pub mod codec;
+pub mod consistency;
 pub mod endpoint;
 pub mod error;
 pub mod message;
+pub mod page;
 
 pub use auth::AuthMethod;
-pub use codec::WllCodec;
+pub use codec::{Frame, WllCodec, PACK_CHUNK_SIZE, PACK_STREAM_THRESHOLD};
+pub use consistency::{ConsistencyToken, LedgerPosition};
 pub use endpoint::{endpoints, HealthResponse};
 pub use error::{ProtocolError, ProtocolResult};
 pub use message::{
-    RefUpdateMsg, RefUpdateResultMsg, WllMessage, PROTOCOL_VERSION, MAX_MESSAGE_SIZE,
-    capabilities,
+    ref_in_prefix, RefUpdateMsg, RefUpdateResultMsg, WllMessage, PROTOCOL_VERSION,
+    MAX_MESSAGE_SIZE, PACK_CHUNK_TAG, capabilities,
 };
+pub use page::{PageDirection, ReceiptCursor, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
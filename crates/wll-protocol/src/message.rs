@@ -1,25 +1,79 @@
+use std::fmt;
+
+use bytes::Bytes;
 use serde::{Deserialize, Serialize};
-use wll_types::{ObjectId, WorldlineId};
+use wll_types::{Coded, DecodeLimits, LimitExceeded, ObjectId, WorldlineId};
+
+use crate::consistency::ConsistencyToken;
+use crate::page::{PageDirection, ReceiptCursor};
 
 pub const PROTOCOL_VERSION: u32 = 1;
 pub const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
 
+/// Type tag of `PackChunk` frames, whose payload is not bincode.
+pub const PACK_CHUNK_TAG: u8 = 14;
+
 /// All message types in the WLL protocol.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum WllMessage {
     Hello { version: u32, capabilities: Vec<String> },
     HelloAck { version: u32, capabilities: Vec<String> },
-    ListRefsRequest { prefix: Option<String> },
-    ListRefsResponse { refs: Vec<(String, [u8; 32])> },
+    /// `prefix` narrows the listing to one ref or namespace (see
+    /// [`ref_in_prefix`]), as a single-branch clone asks. With
+    /// `min_position` set, a replica that has not reached it answers with a
+    /// `sync.replica_behind` error instead of stale refs.
+    ListRefsRequest { prefix: Option<String>, min_position: Option<ConsistencyToken> },
+    /// `consistency` is the answering server's ledger position.
+    ListRefsResponse { refs: Vec<(String, [u8; 32])>, consistency: Option<ConsistencyToken> },
     WantRequest { wants: Vec<ObjectId>, haves: Vec<ObjectId>, depth: Option<u32> },
     AckResponse { common: Vec<ObjectId> },
     PackData { pack_bytes: Vec<u8> },
     PackAck { checksum: [u8; 32], object_count: u32 },
     ReceiptBatch { worldline: WorldlineId, receipts_data: Vec<u8>, count: u32 },
     ReceiptAck { worldline: WorldlineId, through_seq: u64 },
-    RefUpdateRequest { updates: Vec<RefUpdateMsg> },
-    RefUpdateResponse { results: Vec<RefUpdateResultMsg> },
+    /// With `atomic` set, the server applies the push's receipts and every
+    /// update together or rejects them all (see
+    /// [`capabilities::ATOMIC_PUSH`]).
+    RefUpdateRequest { updates: Vec<RefUpdateMsg>, atomic: bool },
+    /// `consistency` is the server's position after the update, for the
+    /// pusher to require of later reads.
+    RefUpdateResponse { results: Vec<RefUpdateResultMsg>, consistency: Option<ConsistencyToken> },
     Error { code: u32, message: String },
+    /// Announces a pack sent as `total_len` bytes of `PackChunk` frames.
+    PackStream { total_len: u64 },
+    /// Up to `limit` receipts of `worldline` from `cursor` on, in
+    /// `direction` (see [`capabilities::RECEIPT_PAGES`]).
+    ReceiptPageRequest {
+        worldline: WorldlineId,
+        cursor: Option<ReceiptCursor>,
+        limit: u32,
+        direction: PageDirection,
+    },
+    /// `receipts_data` holds `count` JSON-encoded receipts in page order.
+    /// `next` is the cursor of the following page, unset on the last one.
+    ReceiptPageResponse {
+        worldline: WorldlineId,
+        receipts_data: Vec<u8>,
+        count: u32,
+        next: Option<ReceiptCursor>,
+    },
+    // Skipped variants must come last: bincode numbers variants by their
+    // position, counting skipped ones when encoding but not when decoding.
+    /// A slice of a streamed pack. Its frame payload is the raw bytes, so
+    /// the codec frames it directly rather than through bincode.
+    #[serde(skip)]
+    PackChunk { data: Bytes },
+}
+
+/// Whether ref `name` is listed for a `ListRefsRequest` with `prefix`:
+/// the ref it names and any ref below it, so `refs/heads/main` lists one
+/// branch and `refs/tags` every tag. An empty prefix lists every ref.
+pub fn ref_in_prefix(name: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    prefix.is_empty()
+        || name
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -37,6 +91,76 @@ pub enum RefUpdateResultMsg {
 }
 
 impl WllMessage {
+    /// An `Error` message reporting `error` under its stable
+    /// [`ErrorCode`](wll_types::ErrorCode) number.
+    pub fn error<E: Coded + fmt::Display>(error: &E) -> Self {
+        Self::Error {
+            code: u32::from(error.code().number()),
+            message: error.to_string(),
+        }
+    }
+
+    /// Check the message's strings, lists and consistency token against
+    /// `limits`, and its JSON receipts with [`DecodeLimits::check_json`].
+    pub fn check_limits(&self, limits: &DecodeLimits) -> Result<(), LimitExceeded> {
+        let strings = |strings: &[String]| {
+            limits.check_array(strings.len())?;
+            strings.iter().try_for_each(|s| limits.check_string(s.len()))
+        };
+        let token = |token: &Option<ConsistencyToken>| match token {
+            Some(token) => limits.check_map(token.positions.len()),
+            None => Ok(()),
+        };
+        match self {
+            Self::Hello { capabilities, .. } | Self::HelloAck { capabilities, .. } => {
+                strings(capabilities)
+            }
+            Self::ListRefsRequest { prefix, min_position } => {
+                limits.check_string(prefix.as_ref().map_or(0, String::len))?;
+                token(min_position)
+            }
+            Self::ListRefsResponse { refs, consistency } => {
+                limits.check_array(refs.len())?;
+                refs.iter().try_for_each(|(name, _)| limits.check_string(name.len()))?;
+                token(consistency)
+            }
+            Self::WantRequest { wants, haves, .. } => {
+                limits.check_array(wants.len())?;
+                limits.check_array(haves.len())
+            }
+            Self::AckResponse { common } => limits.check_array(common.len()),
+            Self::ReceiptBatch { receipts_data, count, .. }
+            | Self::ReceiptPageResponse { receipts_data, count, .. } => {
+                limits.check_array(*count as usize)?;
+                limits.check_json(receipts_data)
+            }
+            Self::RefUpdateRequest { updates, .. } => {
+                limits.check_array(updates.len())?;
+                updates.iter().try_for_each(|u| limits.check_string(u.name.len()))
+            }
+            Self::RefUpdateResponse { results, consistency } => {
+                limits.check_array(results.len())?;
+                for result in results {
+                    match result {
+                        RefUpdateResultMsg::Ok { name } => limits.check_string(name.len())?,
+                        RefUpdateResultMsg::Rejected { name, reason } => {
+                            limits.check_string(name.len())?;
+                            limits.check_string(reason.len())?;
+                        }
+                    }
+                }
+                token(consistency)
+            }
+            Self::Error { message, .. } => limits.check_string(message.len()),
+            Self::PackData { .. }
+            | Self::PackAck { .. }
+            | Self::ReceiptAck { .. }
+            | Self::PackStream { .. }
+            | Self::ReceiptPageRequest { .. }
+            | Self::PackChunk { .. } => Ok(()),
+        }
+    }
+
     pub fn type_tag(&self) -> u8 {
         match self {
             Self::Hello { .. } => 1,
@@ -51,6 +175,10 @@ impl WllMessage {
             Self::ReceiptAck { .. } => 10,
             Self::RefUpdateRequest { .. } => 11,
             Self::RefUpdateResponse { .. } => 12,
+            Self::PackStream { .. } => 13,
+            Self::PackChunk { .. } => PACK_CHUNK_TAG,
+            Self::ReceiptPageRequest { .. } => 15,
+            Self::ReceiptPageResponse { .. } => 16,
             Self::Error { .. } => 255,
         }
     }
@@ -69,6 +197,10 @@ impl WllMessage {
             Self::ReceiptAck { .. } => "ReceiptAck",
             Self::RefUpdateRequest { .. } => "RefUpdateRequest",
             Self::RefUpdateResponse { .. } => "RefUpdateResponse",
+            Self::PackStream { .. } => "PackStream",
+            Self::PackChunk { .. } => "PackChunk",
+            Self::ReceiptPageRequest { .. } => "ReceiptPageRequest",
+            Self::ReceiptPageResponse { .. } => "ReceiptPageResponse",
             Self::Error { .. } => "Error",
         }
     }
@@ -79,4 +211,11 @@ pub mod capabilities {
     pub const RECEIPT_CHAIN: &str = "receipt-chain";
     pub const DELTA_COMPRESSION: &str = "delta-compression";
     pub const SHALLOW_CLONE: &str = "shallow-clone";
+    /// The server stages a push and applies it all or not at all.
+    pub const ATOMIC_PUSH: &str = "atomic-push";
+    /// The server reports its ledger position and honours `min_position`.
+    pub const CONSISTENCY_TOKENS: &str = "consistency-tokens";
+    /// The server answers `ReceiptPageRequest`s, so clients can browse
+    /// history without cloning.
+    pub const RECEIPT_PAGES: &str = "receipt-pages";
 }
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+/// Receipts a `ReceiptPageRequest` returns when it asks for none.
+pub const DEFAULT_PAGE_SIZE: u32 = 100;
+/// Most receipts one `ReceiptPageResponse` carries, whatever was asked.
+pub const MAX_PAGE_SIZE: u32 = 1000;
+
+/// Position in a receipt stream that the next page continues from: the
+/// last receipt of the previous page.
+///
+/// The hash pins the cursor to the history it was taken from, so a server
+/// whose stream no longer holds that receipt at `seq` refuses it instead of
+/// paging through a different history.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReceiptCursor {
+    pub seq: u64,
+    pub receipt_hash: [u8; 32],
+}
+
+/// Which way a page runs from its cursor.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PageDirection {
+    /// Oldest first, starting after the cursor, or at the oldest live
+    /// receipt without one.
+    #[default]
+    Forward,
+    /// Newest first, starting before the cursor, or at the head without
+    /// one.
+    Backward,
+}
@@ -1,6 +1,7 @@
 //! Error types for reference operations.
 
 use thiserror::Error;
+use wll_types::{Coded, ErrorCode};
 
 /// Errors that can occur during reference operations.
 #[derive(Debug, Error)]
@@ -29,6 +30,10 @@ pub enum RefError {
     #[error("cannot delete current branch: {name}")]
     DeleteCurrentBranch { name: String },
 
+    /// A ref no longer points where an update expected it to.
+    #[error("ref changed since it was read: {name}")]
+    Stale { name: String },
+
     /// Serialization or deserialization failure.
     #[error("serialization error: {0}")]
     Serialization(String),
@@ -38,5 +43,21 @@ pub enum RefError {
     Io(#[from] std::io::Error),
 }
 
+impl Coded for RefError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::NotFound { .. } => ErrorCode::RefNotFound,
+            Self::AlreadyExists { .. } => ErrorCode::RefExists,
+            Self::InvalidBranchName { .. } => ErrorCode::RefInvalidName,
+            Self::TagImmutable { .. } => ErrorCode::TagImmutable,
+            Self::DetachedHead => ErrorCode::DetachedHead,
+            Self::DeleteCurrentBranch { .. } => ErrorCode::DeleteCurrentBranch,
+            Self::Stale { .. } => ErrorCode::RefStale,
+            Self::Serialization(_) => ErrorCode::Serialization,
+            Self::Io(_) => ErrorCode::Io,
+        }
+    }
+}
+
 /// Convenience type alias for ref operations.
 pub type Result<T> = std::result::Result<T, RefError>;
@@ -0,0 +1,349 @@
+//! Filesystem-backed reference store.
+//!
+//! [`FileRefStore`] keeps one JSON file per ref under the repository
+//! directory (e.g. `.wll/refs/heads/main`) and HEAD in `.wll/HEAD`, mirroring
+//! git's loose ref layout. Every write goes through a temporary file that is
+//! renamed into place, so a crash never leaves a half-written ref behind.
+//! Transactions stage every ref's temporary file before renaming any, and put
+//! back the refs already renamed if a later rename fails.
+
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::{RefError, Result};
+use crate::names::{validate_branch_name, validate_tag_name};
+use crate::traits::RefStore;
+use crate::transaction::RefTransaction;
+use crate::types::{Head, Ref};
+
+/// A [`RefStore`] that persists refs as files under a repository directory.
+#[derive(Debug)]
+pub struct FileRefStore {
+    root: PathBuf,
+    /// Serializes read-modify-write sequences (tag immutability, HEAD checks).
+    write_lock: Mutex<()>,
+}
+
+impl FileRefStore {
+    /// Open a ref store rooted at `root` (normally the `.wll` directory).
+    pub fn open(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        fs::create_dir_all(root.join("refs"))?;
+        Ok(Self {
+            root,
+            write_lock: Mutex::new(()),
+        })
+    }
+
+    /// The repository directory holding `refs/` and `HEAD`.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn ref_path(&self, name: &str) -> Result<PathBuf> {
+        let relative = Path::new(name);
+        let well_formed = name.starts_with("refs/")
+            && relative
+                .components()
+                .all(|c| matches!(c, Component::Normal(_)));
+        if !well_formed {
+            return Err(RefError::InvalidBranchName {
+                name: name.to_string(),
+                reason: "ref names must be relative paths under refs/".into(),
+            });
+        }
+        Ok(self.root.join(relative))
+    }
+
+    fn head_path(&self) -> PathBuf {
+        self.root.join("HEAD")
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, ()>> {
+        self.write_lock
+            .lock()
+            .map_err(|e| RefError::Serialization(format!("lock poisoned: {e}")))
+    }
+}
+
+impl RefStore for FileRefStore {
+    fn read_ref(&self, name: &str) -> Result<Option<Ref>> {
+        read_json(&self.ref_path(name)?)
+    }
+
+    fn write_ref(&self, name: &str, reference: &Ref) -> Result<()> {
+        match reference {
+            Ref::Branch { name: bname, .. } => validate_branch_name(bname)?,
+            Ref::Tag { name: tname, .. } => validate_tag_name(tname)?,
+            Ref::Remote { branch, .. } => validate_branch_name(branch)?,
+        }
+
+        let path = self.ref_path(name)?;
+        let _guard = self.lock()?;
+
+        // Tags are immutable: if a tag already exists at this name, reject.
+        if reference.is_tag() {
+            if let Some(existing) = read_json::<Ref>(&path)? {
+                if existing.is_tag() {
+                    return Err(RefError::TagImmutable {
+                        name: name.to_string(),
+                    });
+                }
+            }
+        }
+
+        write_json(&path, reference)
+    }
+
+    fn delete_ref(&self, name: &str) -> Result<bool> {
+        let path = self.ref_path(name)?;
+        let _guard = self.lock()?;
+
+        if let Some(Head::Symbolic(current)) = read_json::<Head>(&self.head_path())? {
+            if name == format!("refs/heads/{current}") {
+                return Err(RefError::DeleteCurrentBranch { name: current });
+            }
+        }
+
+        match fs::remove_file(path) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn list_refs(&self, prefix: &str) -> Result<Vec<(String, Ref)>> {
+        let mut names = Vec::new();
+        collect_ref_names(&self.root.join("refs"), "refs", &mut names)?;
+
+        let mut result = Vec::new();
+        for name in names.into_iter().filter(|n| n.starts_with(prefix)) {
+            if let Some(reference) = self.read_ref(&name)? {
+                result.push((name, reference));
+            }
+        }
+        result.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(result)
+    }
+
+    fn commit_transaction(&self, transaction: &RefTransaction) -> Result<()> {
+        let _guard = self.lock()?;
+        transaction.check(|name| read_json(&self.ref_path(name)?))?;
+
+        let mut staged = Vec::new();
+        for update in &transaction.updates {
+            let path = self.ref_path(&update.name)?;
+            let previous = match fs::read(&path) {
+                Ok(bytes) => Some(bytes),
+                Err(e) if e.kind() == ErrorKind::NotFound => None,
+                Err(e) => return Err(e.into()),
+            };
+            staged.push((stage_json(&path, &update.new)?, path, previous));
+        }
+
+        let mut applied: Vec<(PathBuf, Option<Vec<u8>>)> = Vec::new();
+        for (tmp, path, previous) in staged {
+            if let Err(e) = tmp.persist(&path) {
+                for (path, previous) in applied.into_iter().rev() {
+                    let _ = match previous {
+                        Some(bytes) => fs::write(&path, bytes),
+                        None => fs::remove_file(&path),
+                    };
+                }
+                return Err(RefError::Io(e.error));
+            }
+            applied.push((path, previous));
+        }
+        Ok(())
+    }
+
+    fn head(&self) -> Result<Option<Head>> {
+        read_json(&self.head_path())
+    }
+
+    fn set_head(&self, branch: &str) -> Result<()> {
+        validate_branch_name(branch)?;
+        let _guard = self.lock()?;
+        write_json(&self.head_path(), &Head::Symbolic(branch.to_string()))
+    }
+
+    fn set_head_detached(&self, receipt_hash: [u8; 32]) -> Result<()> {
+        let _guard = self.lock()?;
+        write_json(&self.head_path(), &Head::Detached(receipt_hash))
+    }
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<Option<T>> {
+    match fs::read(path) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| RefError::Serialization(format!("{}: {e}", path.display()))),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    stage_json(path, value)?
+        .persist(path)
+        .map_err(|e| RefError::Io(e.error))?;
+    Ok(())
+}
+
+/// Write `value` to a synced temporary file next to `path`, ready to be
+/// renamed over it.
+fn stage_json<T: Serialize>(path: &Path, value: &T) -> Result<tempfile::NamedTempFile> {
+    let bytes =
+        serde_json::to_vec_pretty(value).map_err(|e| RefError::Serialization(e.to_string()))?;
+    let dir = path.parent().expect("ref paths always have a parent");
+    fs::create_dir_all(dir)?;
+
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+    tmp.write_all(&bytes)?;
+    tmp.as_file().sync_all()?;
+    Ok(tmp)
+}
+
+fn collect_ref_names(dir: &Path, prefix: &str, out: &mut Vec<String>) -> Result<()> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    for entry in entries {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        // Skip in-flight temporary files from concurrent writers.
+        if file_name.starts_with(".tmp") {
+            continue;
+        }
+        let name = format!("{prefix}/{file_name}");
+        if entry.file_type()?.is_dir() {
+            collect_ref_names(&entry.path(), &name, out)?;
+        } else {
+            out.push(name);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wll_types::{TemporalAnchor, WorldlineId};
+
+    fn branch(name: &str, hash: [u8; 32]) -> Ref {
+        Ref::Branch {
+            name: name.to_string(),
+            worldline: WorldlineId::from_raw([1u8; 32]),
+            receipt_hash: hash,
+        }
+    }
+
+    fn tag(name: &str) -> Ref {
+        Ref::Tag {
+            name: name.to_string(),
+            target: [3u8; 32],
+            tagger: WorldlineId::from_raw([1u8; 32]),
+            message: "release".into(),
+            timestamp: TemporalAnchor::new(1000, 0, 0),
+            signature: None,
+        }
+    }
+
+    #[test]
+    fn refs_and_head_survive_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let store = FileRefStore::open(dir.path()).unwrap();
+            store
+                .write_ref("refs/heads/main", &branch("main", [7u8; 32]))
+                .unwrap();
+            store.set_head("main").unwrap();
+        }
+
+        let store = FileRefStore::open(dir.path()).unwrap();
+        let main = store.read_ref("refs/heads/main").unwrap().unwrap();
+        assert_eq!(*main.target_hash(), [7u8; 32]);
+        assert_eq!(store.head().unwrap(), Some(Head::Symbolic("main".into())));
+    }
+
+    #[test]
+    fn nested_branch_names_are_listed() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileRefStore::open(dir.path()).unwrap();
+        store
+            .write_ref("refs/heads/main", &branch("main", [1u8; 32]))
+            .unwrap();
+        store
+            .write_ref("refs/heads/feature/auth", &branch("feature/auth", [2u8; 32]))
+            .unwrap();
+        store.write_ref("refs/tags/v1", &tag("v1")).unwrap();
+
+        let names: Vec<String> = store.branches().unwrap().into_iter().map(|(n, _)| n).collect();
+        assert_eq!(names, vec!["refs/heads/feature/auth", "refs/heads/main"]);
+        assert_eq!(store.tags().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn transactions_leave_no_staged_files_behind() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileRefStore::open(dir.path()).unwrap();
+        store.write_ref("refs/tags/v1", &tag("v1")).unwrap();
+
+        let refused = RefTransaction::new()
+            .update("refs/heads/main", None, branch("main", [1u8; 32]))
+            .update("refs/tags/v1", Some([3u8; 32]), tag("v1"));
+        let err = store.commit_transaction(&refused).unwrap_err();
+        assert!(matches!(err, RefError::TagImmutable { .. }));
+        assert!(store.read_ref("refs/heads/main").unwrap().is_none());
+
+        let tx = RefTransaction::new()
+            .update("refs/heads/main", None, branch("main", [1u8; 32]))
+            .update("refs/heads/dev", None, branch("dev", [2u8; 32]));
+        store.commit_transaction(&tx).unwrap();
+        assert_eq!(store.branches().unwrap().len(), 2);
+        let heads = std::fs::read_dir(dir.path().join("refs/heads")).unwrap().count();
+        assert_eq!(heads, 2);
+    }
+
+    #[test]
+    fn tags_are_immutable_on_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileRefStore::open(dir.path()).unwrap();
+        store.write_ref("refs/tags/v1", &tag("v1")).unwrap();
+        let err = store.write_ref("refs/tags/v1", &tag("v1")).unwrap_err();
+        assert!(matches!(err, RefError::TagImmutable { .. }));
+    }
+
+    #[test]
+    fn cannot_delete_current_branch() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileRefStore::open(dir.path()).unwrap();
+        store
+            .write_ref("refs/heads/main", &branch("main", [1u8; 32]))
+            .unwrap();
+        store.set_head("main").unwrap();
+
+        let err = store.delete_ref("refs/heads/main").unwrap_err();
+        assert!(matches!(err, RefError::DeleteCurrentBranch { .. }));
+
+        store.set_head_detached([9u8; 32]).unwrap();
+        assert!(store.delete_ref("refs/heads/main").unwrap());
+        assert!(!store.delete_ref("refs/heads/main").unwrap());
+    }
+
+    #[test]
+    fn path_traversal_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileRefStore::open(dir.path()).unwrap();
+        assert!(store.read_ref("refs/../HEAD").is_err());
+        assert!(store.read_ref("/etc/passwd").is_err());
+    }
+}
@@ -21,16 +21,22 @@
 //! - [`types`] — Core ref types: [`Ref`], [`BranchInfo`], [`Head`]
 //! - [`traits`] — The [`RefStore`] trait defining the storage interface
 //! - [`names`] — Branch/tag name validation
+//! - [`transaction`] — All-or-nothing multi-ref updates: [`RefTransaction`]
 //! - [`memory`] — In-memory [`InMemoryRefStore`] for tests
+//! - [`file`](mod@file) — On-disk [`FileRefStore`] for repositories
 
 pub mod error;
+pub mod file;
 pub mod memory;
 pub mod names;
 pub mod traits;
+pub mod transaction;
 pub mod types;
 
 pub use error::{RefError, Result};
+pub use file::FileRefStore;
 pub use memory::InMemoryRefStore;
 pub use names::{validate_branch_name, validate_remote_name, validate_tag_name};
 pub use traits::RefStore;
+pub use transaction::{RefTransaction, RefTxUpdate};
 pub use types::{BranchInfo, Head, Ref};
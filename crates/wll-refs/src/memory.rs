@@ -10,6 +10,7 @@ use std::sync::RwLock;
 use crate::error::{RefError, Result};
 use crate::names::{validate_branch_name, validate_tag_name};
 use crate::traits::RefStore;
+use crate::transaction::RefTransaction;
 use crate::types::{Head, Ref};
 
 /// An in-memory implementation of [`RefStore`].
@@ -114,6 +115,17 @@ impl RefStore for InMemoryRefStore {
         Ok(result)
     }
 
+    fn commit_transaction(&self, transaction: &RefTransaction) -> Result<()> {
+        let mut refs = self.refs.write().map_err(|e| {
+            RefError::Serialization(format!("lock poisoned: {e}"))
+        })?;
+        transaction.check(|name| Ok(refs.get(name).cloned()))?;
+        for update in &transaction.updates {
+            refs.insert(update.name.clone(), update.new.clone());
+        }
+        Ok(())
+    }
+
     fn head(&self) -> Result<Option<Head>> {
         let head = self.head.read().map_err(|e| {
             RefError::Serialization(format!("lock poisoned: {e}"))
@@ -186,6 +198,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn transaction_applies_all_updates_or_none() {
+        let store = InMemoryRefStore::new();
+        store.write_ref("refs/heads/main", &test_branch("main", [1u8; 32])).unwrap();
+
+        let stale = RefTransaction::new()
+            .update("refs/heads/dev", None, test_branch("dev", [2u8; 32]))
+            .update("refs/heads/main", Some([9u8; 32]), test_branch("main", [3u8; 32]));
+        assert!(matches!(
+            store.commit_transaction(&stale),
+            Err(RefError::Stale { name }) if name == "refs/heads/main"
+        ));
+        assert!(store.read_ref("refs/heads/dev").unwrap().is_none());
+
+        let fresh = RefTransaction::new()
+            .update("refs/heads/dev", None, test_branch("dev", [2u8; 32]))
+            .update("refs/heads/main", Some([1u8; 32]), test_branch("main", [3u8; 32]));
+        store.commit_transaction(&fresh).unwrap();
+        let main = store.read_ref("refs/heads/main").unwrap().unwrap();
+        assert_eq!(main.target_hash(), &[3u8; 32]);
+        assert!(store.read_ref("refs/heads/dev").unwrap().is_some());
+    }
+
     // ---- Test 1: Create and read a branch ref ----
     #[test]
     fn create_and_read_branch_ref() {
@@ -4,6 +4,7 @@
 //! provide named reference management for the WorldLine Ledger.
 
 use crate::error::Result;
+use crate::transaction::RefTransaction;
 use crate::types::{Head, Ref};
 
 /// Storage backend for named references.
@@ -38,6 +39,12 @@ pub trait RefStore: Send + Sync {
     /// Pass `""` to list all refs. Pass `"refs/heads/"` for branches only.
     fn list_refs(&self, prefix: &str) -> Result<Vec<(String, Ref)>>;
 
+    /// Apply every update in `transaction` or none of them.
+    ///
+    /// Fails with [`RefError::Stale`](crate::RefError::Stale) if any ref no
+    /// longer points where its update expects, leaving all refs unchanged.
+    fn commit_transaction(&self, transaction: &RefTransaction) -> Result<()>;
+
     /// Read the current HEAD state.
     ///
     /// Returns `Ok(None)` if HEAD has not been set.
@@ -0,0 +1,88 @@
+//! All-or-nothing updates to several refs.
+//!
+//! A [`RefTransaction`] lists compare-and-swap updates: each names the
+//! receipt its ref must still point at. [`RefStore::commit_transaction`]
+//! checks every expectation and name before writing anything, so either all
+//! refs move or none do.
+//!
+//! [`RefStore::commit_transaction`]: crate::RefStore::commit_transaction
+
+use crate::error::{RefError, Result};
+use crate::names::{validate_branch_name, validate_tag_name};
+use crate::types::Ref;
+
+/// One compare-and-swap in a [`RefTransaction`].
+#[derive(Clone, Debug)]
+pub struct RefTxUpdate {
+    /// Canonical ref name (e.g. "refs/heads/main").
+    pub name: String,
+    /// Receipt hash the ref must point at for the update to apply; `None`
+    /// requires the ref to be missing or unborn.
+    pub expected: Option<[u8; 32]>,
+    pub new: Ref,
+}
+
+/// Ref updates applied together by
+/// [`RefStore::commit_transaction`](crate::RefStore::commit_transaction).
+#[derive(Clone, Debug, Default)]
+pub struct RefTransaction {
+    pub updates: Vec<RefTxUpdate>,
+}
+
+impl RefTransaction {
+    /// Create an empty transaction.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Move `name` from `expected` to `new`.
+    pub fn update(mut self, name: impl Into<String>, expected: Option<[u8; 32]>, new: Ref) -> Self {
+        self.updates.push(RefTxUpdate {
+            name: name.into(),
+            expected,
+            new,
+        });
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.updates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.updates.is_empty()
+    }
+
+    /// Check every update against the refs as they stand, read through
+    /// `current`: names must be valid, tags stay immutable, and each ref
+    /// must still point where the update expects.
+    pub(crate) fn check(&self, current: impl Fn(&str) -> Result<Option<Ref>>) -> Result<()> {
+        for update in &self.updates {
+            match &update.new {
+                Ref::Branch { name, .. } => validate_branch_name(name)?,
+                Ref::Tag { name, .. } => validate_tag_name(name)?,
+                Ref::Remote { branch, .. } => validate_branch_name(branch)?,
+            }
+            if self.updates.iter().filter(|u| u.name == update.name).count() > 1 {
+                return Err(RefError::Stale {
+                    name: update.name.clone(),
+                });
+            }
+            let existing = current(&update.name)?;
+            if update.new.is_tag() && existing.as_ref().is_some_and(Ref::is_tag) {
+                return Err(RefError::TagImmutable {
+                    name: update.name.clone(),
+                });
+            }
+            let tip = existing
+                .map(|r| *r.target_hash())
+                .filter(|hash| *hash != [0; 32]);
+            if tip != update.expected {
+                return Err(RefError::Stale {
+                    name: update.name.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,1092 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tracing::Instrument;
+use wll_config::CrossWorldlinePolicy;
+use wll_ledger::{anchor_nonce, Receipt, ValidationReport};
+use wll_sync::{
+    FetchResult, HaveNegotiator, MergeStatus, Negotiation, NegotiationEngine, PullResult,
+    PushResult, RefSpec, RefUpdate, RemoteTransport, SyncError, SyncVerifier, DEFAULT_MAX_ROUNDS,
+};
+
+use crate::commit::{CommitProposal, CommitResult, ReceiptSummary};
+use crate::error::{SdkError, SdkResult};
+use crate::offline::ReconcileReport;
+use crate::remote::{trust_roots, PushTarget};
+use crate::repository::Wll;
+use crate::time_anchor::{RoughtimeServer, TimeAnchor};
+use crate::transparency::{HttpTransparencyLog, LogPublication, TransparencyLog};
+
+/// Async facade over [`Wll`] for use inside a Tokio runtime.
+///
+/// Repository operations run on the blocking thread pool via
+/// [`tokio::task::spawn_blocking`], so file-backed stores and ledgers never
+/// stall the async executor. Push and pull talk to a [`RemoteTransport`]
+/// directly and only hop to the blocking pool for local ledger and store
+/// work.
+///
+/// `AsyncWll` is `Send + Sync + Clone`; clones share one repository, and
+/// every returned future is `Send`, so a handle can live in axum state and
+/// be used from any task. Concurrent commits are serialized by the
+/// underlying ledger, not by this type.
+#[derive(Clone)]
+pub struct AsyncWll {
+    inner: Arc<Wll>,
+}
+
+impl AsyncWll {
+    /// Wrap a repository for async use.
+    pub fn new(wll: Wll) -> Self {
+        Self {
+            inner: Arc::new(wll),
+        }
+    }
+
+    /// Share a repository other handles also use, such as one served to
+    /// them through a [`LocalRemote`](crate::LocalRemote).
+    pub fn shared(wll: Arc<Wll>) -> Self {
+        Self { inner: wll }
+    }
+
+    /// The wrapped repository, for synchronous calls that are cheap enough
+    /// to make on the runtime (accessors, in-memory backends).
+    pub fn blocking(&self) -> &Wll {
+        &self.inner
+    }
+
+    /// Run `f` against the repository on the blocking thread pool.
+    async fn run<T, F>(&self, f: F) -> SdkResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Wll) -> SdkResult<T> + Send + 'static,
+    {
+        let wll = Arc::clone(&self.inner);
+        let span = tracing::Span::current();
+        tokio::task::spawn_blocking(move || span.in_scope(|| f(&wll)))
+            .await?
+    }
+
+    /// See [`Wll::commit`].
+    pub async fn commit(&self, proposal: CommitProposal) -> SdkResult<CommitResult> {
+        self.run(move |wll| wll.commit(proposal)).await
+    }
+
+    /// See [`Wll::log`].
+    pub async fn log(&self, limit: usize) -> SdkResult<Vec<ReceiptSummary>> {
+        self.run(move |wll| wll.log(limit)).await
+    }
+
+    /// See [`Wll::verify`].
+    pub async fn verify(&self) -> SdkResult<ValidationReport> {
+        self.run(|wll| wll.verify()).await
+    }
+
+    /// Push `refspec.src` to `refspec.dst` on the remote.
+    ///
+    /// Sends the objects reachable from the pushed tree commits as one pack,
+    /// then the receipts the remote is missing, then the ref update. Short
+    /// names are taken to be branches. Unless the refspec is forced, the
+    /// remote ref must be an ancestor of the local one.
+    ///
+    /// Runs in a `sync.push` span. The transport sees the push's trace
+    /// context through [`wll_telemetry::current`], continuing the caller's
+    /// trace if there is one.
+    pub async fn push(
+        &self,
+        transport: &dyn RemoteTransport,
+        refspec: &RefSpec,
+    ) -> SdkResult<PushResult> {
+        let span = tracing::info_span!(
+            "sync.push",
+            src = %refspec.src,
+            dst = %refspec.dst,
+            trace_id = tracing::field::Empty,
+        );
+        let trace = wll_telemetry::attach(&span, wll_telemetry::current());
+        let push = self.push_traced(transport, std::slice::from_ref(refspec), false);
+        wll_telemetry::scope(trace, push.instrument(span)).await
+    }
+
+    /// Push every refspec in `refspecs` as one transaction.
+    ///
+    /// The pack, the receipts, and all ref updates go to the remote in a
+    /// single [`RemoteTransport::push_atomic`] call; the remote applies all of
+    /// them or none, so a rejected update leaves every remote ref where it
+    /// was. [`PushResult::atomic`] is set, and `rejected` lists every update
+    /// when the push was refused. Fails if the transport cannot push
+    /// atomically.
+    ///
+    /// Runs in a `sync.push` span like [`push`](Self::push).
+    pub async fn push_atomic(
+        &self,
+        transport: &dyn RemoteTransport,
+        refspecs: &[RefSpec],
+    ) -> SdkResult<PushResult> {
+        let span = tracing::info_span!(
+            "sync.push",
+            refs = refspecs.len(),
+            atomic = true,
+            trace_id = tracing::field::Empty,
+        );
+        let trace = wll_telemetry::attach(&span, wll_telemetry::current());
+        let push = self.push_traced(transport, refspecs, true);
+        wll_telemetry::scope(trace, push.instrument(span)).await
+    }
+
+    async fn push_traced(
+        &self,
+        transport: &dyn RemoteTransport,
+        refspecs: &[RefSpec],
+        atomic: bool,
+    ) -> SdkResult<PushResult> {
+        let remote_refs = transport
+            .list_refs()
+            .instrument(tracing::info_span!("sync.negotiate"))
+            .await?;
+        let mut targets = Vec::new();
+        for refspec in refspecs {
+            let remote_ref = full_ref_name(&refspec.dst)?;
+            targets.push(PushTarget {
+                local_ref: full_ref_name(&refspec.src)?,
+                remote_tip: find_tip(&remote_refs, &remote_ref),
+                remote_ref,
+                force: refspec.force,
+            });
+        }
+
+        let outgoing = self
+            .run(move |wll| {
+                let outgoing = wll.prepare_push(&targets)?;
+                if let Some(outgoing) = &outgoing {
+                    wll.pre_push(&outgoing.updates, outgoing.receipts.len())?;
+                }
+                Ok(outgoing)
+            })
+            .await?;
+        let Some(outgoing) = outgoing else {
+            return Ok(PushResult {
+                atomic,
+                ..PushResult::default()
+            });
+        };
+
+        let rejected = if atomic {
+            transport
+                .push_atomic(&outgoing.pack, &outgoing.receipts, &outgoing.updates)
+                .await?
+        } else {
+            if outgoing.object_count > 0 {
+                transport.push_pack(&outgoing.pack).await?;
+            }
+            if !outgoing.receipts.is_empty() {
+                transport.push_receipts(&outgoing.receipts).await?;
+            }
+            transport.update_refs(&outgoing.updates).await?
+        };
+
+        let refs_updated = if atomic && !rejected.is_empty() {
+            Vec::new()
+        } else {
+            outgoing
+                .updates
+                .into_iter()
+                .filter(|update| rejected.iter().all(|r| r.name != update.name))
+                .collect()
+        };
+        Ok(PushResult {
+            objects_sent: outgoing.object_count,
+            receipts_sent: outgoing.receipts.len(),
+            refs_updated,
+            rejected,
+            bytes_transferred: outgoing.pack.len() as u64,
+            atomic,
+        })
+    }
+
+    /// Fetch `refspec.src` from the remote and fast-forward `refspec.dst`.
+    ///
+    /// Lists only `refspec.src` on the remote
+    /// ([`RemoteTransport::list_refs_matching`]), then asks for receipts
+    /// after the local stream head and for the trees those receipts
+    /// reference, stores the objects before importing the receipts, and
+    /// only then moves the local ref. Before asking for
+    /// objects it negotiates the history both sides share (see
+    /// [`HaveNegotiator`]), so objects that history already holds are
+    /// left out; `sync.negotiation_rounds` caps the rounds. Receipts that do not
+    /// extend the local stream are rejected by the ledger.
+    ///
+    /// Receipts citing receipts of other worldlines (`receipt://` evidence)
+    /// are handled per `sync.cross_worldline`: by default the cited
+    /// worldlines are fetched from the same remote and imported first, and
+    /// a reference still unresolved fails the pull.
+    ///
+    /// Runs in a `sync.pull` span, propagated like [`push`](Self::push).
+    pub async fn pull(
+        &self,
+        transport: &dyn RemoteTransport,
+        refspec: &RefSpec,
+    ) -> SdkResult<PullResult> {
+        let span = tracing::info_span!(
+            "sync.pull",
+            src = %refspec.src,
+            dst = %refspec.dst,
+            trace_id = tracing::field::Empty,
+        );
+        let trace = wll_telemetry::attach(&span, wll_telemetry::current());
+        wll_telemetry::scope(trace, self.pull_traced(transport, refspec).instrument(span)).await
+    }
+
+    /// Replay commits queued while offline (see [`Wll::queue_commit`]).
+    ///
+    /// Pulls `refspec` so the gate judges each queued commit against the
+    /// remote's history, replays the current branch's queue with
+    /// [`Wll::replay_queued`], and pushes `refspec` if any commit was
+    /// accepted. A remote that does not have the branch yet is not pulled.
+    pub async fn reconcile(
+        &self,
+        transport: &dyn RemoteTransport,
+        refspec: &RefSpec,
+    ) -> SdkResult<ReconcileReport> {
+        let pull = match self.pull(transport, refspec).await {
+            Ok(pull) => Some(pull),
+            Err(SdkError::BranchNotFound(_)) => None,
+            Err(e) => return Err(e),
+        };
+        let mut report = self.run(|wll| wll.replay_queued()).await?;
+        report.pull = pull;
+        if report.accepted() > 0 {
+            report.push = Some(self.push(transport, refspec).await?);
+        }
+        Ok(report)
+    }
+
+    async fn pull_traced(
+        &self,
+        transport: &dyn RemoteTransport,
+        refspec: &RefSpec,
+    ) -> SdkResult<PullResult> {
+        let remote_ref = full_ref_name(&refspec.src)?;
+        let local_ref = full_ref_name(&refspec.dst)?;
+        let remote_refs = transport
+            .list_refs_matching(&remote_ref)
+            .instrument(tracing::info_span!("sync.negotiate"))
+            .await?;
+        let remote_tip = find_tip(&remote_refs, &remote_ref)
+            .ok_or_else(|| SdkError::BranchNotFound(remote_ref.clone()))?;
+
+        let (worldline, base, local_tips, settings) = self
+            .run(|wll| {
+                let head = wll.ledger().head(wll.worldline())?;
+                Ok((
+                    wll.worldline().clone(),
+                    head.map(|h| (h.seq, h.receipt_hash)),
+                    wll.ref_tips()?,
+                    wll.config()?.sync()?,
+                ))
+            })
+            .await?;
+        let policy = settings.cross_worldline;
+        let receipts = transport
+            .fetch_receipts(std::slice::from_ref(&worldline), base.map(|(seq, _)| seq))
+            .await?;
+        let verification =
+            SyncVerifier::verify_with_roots(&receipts, &worldline, base, &trust_roots(&settings)?);
+        if !verification.chain_valid {
+            let violations = verification.violations.join("; ");
+            return Err(SyncError::VerificationFailed(violations).into());
+        }
+        for warning in &verification.warnings {
+            tracing::warn!("fetched receipts: {warning}");
+        }
+
+        let (mut receipts, mut unresolved) = self
+            .run(move |wll| {
+                let unresolved = wll.unresolved_references(&receipts, &[]);
+                Ok((receipts, unresolved))
+            })
+            .await?;
+        let mut dependencies: Vec<Receipt> = Vec::new();
+        if policy == CrossWorldlinePolicy::Fetch {
+            // Fetched receipts may cite further worldlines in turn.
+            let mut fetched = HashSet::from([worldline.clone()]);
+            loop {
+                let wanted: Vec<_> = unresolved
+                    .iter()
+                    .map(|u| u.reference.worldline.clone())
+                    .filter(|w| fetched.insert(w.clone()))
+                    .collect();
+                if wanted.is_empty() {
+                    break;
+                }
+                for cited in wanted {
+                    let since = {
+                        let cited = cited.clone();
+                        self.run(move |wll| wll.worldline_head(&cited)).await?
+                    };
+                    dependencies.extend(
+                        transport
+                            .fetch_receipts(std::slice::from_ref(&cited), since)
+                            .await?,
+                    );
+                }
+                (receipts, dependencies, unresolved) = self
+                    .run(move |wll| {
+                        let unresolved = wll.unresolved_references(&receipts, &dependencies);
+                        Ok((receipts, dependencies, unresolved))
+                    })
+                    .await?;
+            }
+        }
+        SyncVerifier::enforce_reference_policy(&unresolved, policy)?;
+
+        let (receipts, dependencies, wants) = self
+            .run(move |wll| {
+                let mut wants = wll.receipt_objects(&receipts)?;
+                wants.extend(wll.receipt_objects(&dependencies)?);
+                wants.retain(|id| !matches!(wll.store().exists(id), Ok(true)));
+                Ok((receipts, dependencies, wants))
+            })
+            .await?;
+        let (pack, mut negotiation) = if wants.is_empty() {
+            (None, Negotiation::default())
+        } else {
+            let rounds = settings.negotiation_rounds.unwrap_or(DEFAULT_MAX_ROUNDS);
+            let negotiation = self
+                .negotiate(transport, local_tips, remote_ref.clone(), remote_tip, rounds)
+                .instrument(tracing::info_span!("sync.negotiate"))
+                .await?;
+            let pack = transport.fetch_objects(&wants, &negotiation.common).await?;
+            (Some(pack), negotiation)
+        };
+
+        let bytes_transferred = pack.as_ref().map_or(0, |p| p.len() as u64);
+        let receipts_received = receipts.len();
+        self.run(move |wll| {
+            let quarantine = wll.quarantine_pack(pack)?;
+            let objects_received = quarantine.len();
+            let receipts = wll.stage_receipts(receipts, &quarantine)?;
+            quarantine.migrate()?;
+            negotiation.objects_skipped =
+                wll.reachable_count(&wants).saturating_sub(objects_received);
+            let cross_worldline_receipts = wll.import_dependencies(dependencies)?;
+            wll.import_receipts(receipts)?;
+            let old_hash = wll.ref_tip(&local_ref)?;
+            let merge_status = wll.fast_forward(&local_ref, remote_tip)?;
+            let refs_updated = if merge_status == MergeStatus::FastForward {
+                vec![RefUpdate {
+                    name: local_ref,
+                    old_hash,
+                    new_hash: remote_tip,
+                }]
+            } else {
+                Vec::new()
+            };
+            Ok(PullResult {
+                fetch: FetchResult {
+                    objects_received,
+                    receipts_received,
+                    refs_updated,
+                    bytes_transferred,
+                    cross_worldline_receipts,
+                    unresolved_references: unresolved,
+                    negotiation,
+                    verification: Some(verification),
+                },
+                merge_status,
+            })
+        })
+        .await
+    }
+
+    /// Find the newest history shared with the remote, in at most
+    /// `max_rounds` rounds of haves.
+    async fn negotiate(
+        &self,
+        transport: &dyn RemoteTransport,
+        local_tips: Vec<(String, [u8; 32])>,
+        remote_ref: String,
+        remote_tip: [u8; 32],
+        max_rounds: usize,
+    ) -> SdkResult<Negotiation> {
+        let wants = NegotiationEngine::compute_wants(&local_tips, &[(remote_ref, remote_tip)]);
+        let histories = self.run(move |wll| wll.have_histories(&local_tips)).await?;
+        let mut negotiator = HaveNegotiator::new(histories).with_max_rounds(max_rounds);
+        while let Some(haves) = negotiator.next_round() {
+            let common = transport.ack_haves(&haves).await?;
+            negotiator.acknowledge(&haves, &common);
+        }
+        Ok(negotiator.finish(wants))
+    }
+
+    /// Anchor the ledger head with a signed time from `server`.
+    ///
+    /// Returns `None` without contacting the server when there is nothing
+    /// new to anchor (see [`Wll::time_anchor_target`]).
+    pub async fn anchor_time(&self, server: &RoughtimeServer) -> SdkResult<Option<TimeAnchor>> {
+        let Some(anchored) = self.run(|wll| wll.time_anchor_target()).await? else {
+            return Ok(None);
+        };
+        let response = server
+            .query(&anchor_nonce(&anchored), ANCHOR_TIMEOUT)
+            .await?;
+        let server = server.clone();
+        self.run(move |wll| wll.record_time_anchor(&server, anchored, &response))
+            .await
+            .map(Some)
+    }
+
+    /// Anchor the head every `interval` until the task is aborted, trying
+    /// `servers` in order until one answers.
+    pub fn spawn_time_anchoring(
+        &self,
+        servers: Vec<RoughtimeServer>,
+        interval: Duration,
+    ) -> JoinHandle<()> {
+        let wll = self.clone();
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                ticks.tick().await;
+                for server in &servers {
+                    match wll.anchor_time(server).await {
+                        Ok(_) => break,
+                        Err(e) => tracing::warn!(server = %server.name, "time anchor failed: {e}"),
+                    }
+                }
+            }
+        })
+    }
+
+    /// [`Self::spawn_time_anchoring`] with the servers and interval from
+    /// `[timestamp]`, or `None` if either is unset.
+    pub fn spawn_configured_time_anchoring(&self) -> SdkResult<Option<JoinHandle<()>>> {
+        let settings = self.inner.config()?.timestamp()?;
+        let servers = RoughtimeServer::all_from_settings(&settings)?;
+        Ok(match settings.interval_secs {
+            Some(secs) if secs > 0 && !servers.is_empty() => {
+                Some(self.spawn_time_anchoring(servers, Duration::from_secs(secs)))
+            }
+            _ => None,
+        })
+    }
+
+    /// See [`Wll::publish_head`].
+    pub async fn publish_head(
+        &self,
+        log: Arc<dyn TransparencyLog>,
+    ) -> SdkResult<Option<LogPublication>> {
+        self.run(move |wll| wll.publish_head(log.as_ref())).await
+    }
+
+    /// Publish the head to every log in `logs` every `interval` until the
+    /// task is aborted.
+    pub fn spawn_log_publishing(
+        &self,
+        logs: Vec<Arc<dyn TransparencyLog>>,
+        interval: Duration,
+    ) -> JoinHandle<()> {
+        let wll = self.clone();
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                ticks.tick().await;
+                for log in &logs {
+                    if let Err(e) = wll.publish_head(Arc::clone(log)).await {
+                        tracing::warn!(log = %log.name(), "log publication failed: {e}");
+                    }
+                }
+            }
+        })
+    }
+
+    /// [`Self::spawn_log_publishing`] with the logs and interval from
+    /// `[transparency]`, or `None` if either is unset.
+    pub fn spawn_configured_log_publishing(&self) -> SdkResult<Option<JoinHandle<()>>> {
+        let settings = self.inner.config()?.transparency()?;
+        let logs: Vec<Arc<dyn TransparencyLog>> = HttpTransparencyLog::all_from_settings(&settings)?
+            .into_iter()
+            .map(|log| Arc::new(log) as Arc<dyn TransparencyLog>)
+            .collect();
+        Ok(match settings.interval_secs {
+            Some(secs) if secs > 0 && !logs.is_empty() => {
+                Some(self.spawn_log_publishing(logs, Duration::from_secs(secs)))
+            }
+            _ => None,
+        })
+    }
+}
+
+/// How long [`AsyncWll::anchor_time`] waits for a server's reply.
+const ANCHOR_TIMEOUT: Duration = Duration::from_secs(5);
+
+impl From<Wll> for AsyncWll {
+    fn from(wll: Wll) -> Self {
+        Self::new(wll)
+    }
+}
+
+/// Expand a refspec side to a full ref name; bare names are branches.
+fn full_ref_name(name: &str) -> SdkResult<String> {
+    if name.contains('*') {
+        return Err(SdkError::InvalidOperation(format!(
+            "wildcard refspec {name} is not supported"
+        )));
+    }
+    Ok(if name.starts_with("refs/") {
+        name.to_string()
+    } else {
+        format!("refs/heads/{name}")
+    })
+}
+
+fn find_tip(refs: &[(String, [u8; 32])], name: &str) -> Option<[u8; 32]> {
+    refs.iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, hash)| *hash)
+        .filter(|hash| *hash != [0; 32])
+}
+
+#[allow(dead_code)]
+fn assert_send_sync<T: Send + Sync>() {}
+
+// Embedding in a multi-threaded runtime depends on these bounds.
+const _: fn() = || {
+    assert_send_sync::<Wll>();
+    assert_send_sync::<AsyncWll>();
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+    use wll_ledger::{CrossWorldlineRef, Receipt};
+    use wll_pack::PackWriter;
+    use wll_store::{Blob, EntryMode, Tree, TreeEntry};
+    use wll_protocol::ConsistencyToken;
+    use wll_sync::{RefRejection, ReplicaTransport, SyncError, SyncResult, TrustAnchor};
+    use crate::config::RepoConfig;
+    use wll_telemetry::TraceContext;
+    use wll_types::{IdentityMaterial, ObjectId, WorldlineId};
+
+    /// Transport that serves another in-process repository, noting the
+    /// trace context each ref listing arrives with.
+    struct Loopback {
+        repo: Arc<Wll>,
+        traces: Mutex<Vec<Option<TraceContext>>>,
+    }
+
+    impl Loopback {
+        fn new(repo: Arc<Wll>) -> Self {
+            Self {
+                repo,
+                traces: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    fn remote_err(e: SdkError) -> SyncError {
+        SyncError::RemoteError(e.to_string())
+    }
+
+    #[async_trait]
+    impl RemoteTransport for Loopback {
+        async fn list_refs(&self) -> SyncResult<Vec<(String, [u8; 32])>> {
+            self.traces.lock().unwrap().push(wll_telemetry::current());
+            self.repo.ref_tips().map_err(remote_err)
+        }
+
+        async fn fetch_objects(
+            &self,
+            wants: &[ObjectId],
+            haves: &[ObjectId],
+        ) -> SyncResult<Vec<u8>> {
+            let common: Vec<[u8; 32]> = haves.iter().map(|id| *id.as_bytes()).collect();
+            self.repo
+                .pack_objects_for(wants, &common)
+                .map(|(pack, _)| pack)
+                .map_err(remote_err)
+        }
+
+        async fn push_atomic(
+            &self,
+            pack_bytes: &[u8],
+            receipts: &[Receipt],
+            updates: &[RefUpdate],
+        ) -> SyncResult<Vec<RefRejection>> {
+            self.repo
+                .accept_atomic_push(pack_bytes.to_vec(), receipts.to_vec(), updates)
+                .map_err(remote_err)
+        }
+
+        async fn consistency(&self) -> SyncResult<Option<ConsistencyToken>> {
+            self.repo.consistency_token().map(Some).map_err(remote_err)
+        }
+
+        async fn ack_haves(&self, haves: &[ObjectId]) -> SyncResult<Vec<ObjectId>> {
+            let mut common = Vec::new();
+            for id in haves {
+                if self.repo.ledger().get_by_hash(*id.as_bytes())?.is_some() {
+                    common.push(*id);
+                }
+            }
+            Ok(common)
+        }
+
+        async fn fetch_receipts(
+            &self,
+            worldlines: &[WorldlineId],
+            since: Option<u64>,
+        ) -> SyncResult<Vec<Receipt>> {
+            let mut receipts = Vec::new();
+            for worldline in worldlines {
+                receipts.extend(
+                    self.repo
+                        .ledger()
+                        .read_all(worldline)?
+                        .into_iter()
+                        .filter(|r| Some(r.seq()) > since),
+                );
+            }
+            Ok(receipts)
+        }
+
+        async fn push_pack(&self, pack_bytes: &[u8]) -> SyncResult<()> {
+            let quarantine = self
+                .repo
+                .quarantine_pack(Some(pack_bytes.to_vec()))
+                .map_err(remote_err)?;
+            quarantine.migrate().map(|_| ()).map_err(|e| remote_err(e.into()))
+        }
+
+        async fn push_receipts(&self, receipts: &[Receipt]) -> SyncResult<()> {
+            self.repo
+                .import_receipts(receipts.to_vec())
+                .map(|_| ())
+                .map_err(remote_err)
+        }
+
+        async fn update_refs(&self, updates: &[RefUpdate]) -> SyncResult<Vec<RefRejection>> {
+            let mut rejected = Vec::new();
+            for update in updates {
+                if let Err(e) = self.repo.fast_forward(&update.name, update.new_hash) {
+                    rejected.push(RefRejection {
+                        name: update.name.clone(),
+                        reason: e.to_string(),
+                    });
+                }
+            }
+            Ok(rejected)
+        }
+    }
+
+    fn worldline() -> WorldlineId {
+        WorldlineId::derive(&IdentityMaterial::GenesisHash([9; 32]))
+    }
+
+    fn repo() -> AsyncWll {
+        AsyncWll::new(Wll::init_with_worldline(worldline()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn commit_log_and_verify() {
+        let wll = repo();
+        wll.commit(CommitProposal::new("first")).await.unwrap();
+        wll.commit(CommitProposal::new("second")).await.unwrap();
+
+        let log = wll.log(10).await.unwrap();
+        assert_eq!(log.len(), 4);
+        assert_eq!(log[1].intent.as_deref(), Some("second"));
+        assert!(wll.verify().await.unwrap().is_valid());
+    }
+
+    #[tokio::test]
+    async fn push_then_pull_moves_receipts_and_trees() {
+        let local = repo();
+        let mut index = local.blocking().new_index();
+        index
+            .stage_file("src/lib.rs", b"pub fn f() {}", EntryMode::Regular)
+            .unwrap();
+        let tree = local
+            .blocking()
+            .commit_tree(&mut index, CommitProposal::new("tree"))
+            .unwrap()
+            .tree
+            .unwrap();
+        local.commit(CommitProposal::new("note")).await.unwrap();
+
+        let server = Arc::new(Wll::init_with_worldline(worldline()).unwrap());
+        let remote = Loopback::new(server.clone());
+        let pushed = local
+            .push(&remote, &RefSpec::new("main", "main"))
+            .await
+            .unwrap();
+        assert_eq!(pushed.receipts_sent, 4);
+        assert!(pushed.objects_sent >= 2, "tree and blob");
+        assert!(pushed.rejected.is_empty());
+        assert_eq!(server.receipt_count().unwrap(), 4);
+        assert!(server.read_tree(&tree).is_ok());
+
+        let again = local
+            .push(&remote, &RefSpec::new("main", "main"))
+            .await
+            .unwrap();
+        assert_eq!(again.receipts_sent, 0);
+
+        let clone = repo();
+        let pulled = clone
+            .pull(&remote, &RefSpec::new("main", "main"))
+            .await
+            .unwrap();
+        assert_eq!(pulled.merge_status, MergeStatus::FastForward);
+        assert_eq!(pulled.fetch.receipts_received, 4);
+        assert!(clone.blocking().read_tree(&tree).is_ok());
+        assert_eq!(
+            clone.blocking().ref_tip("refs/heads/main").unwrap(),
+            local.blocking().ref_tip("refs/heads/main").unwrap()
+        );
+        assert_eq!(clone.blocking().provenance_len(), 4);
+        assert!(clone.verify().await.unwrap().is_valid());
+
+        let noop = clone
+            .pull(&remote, &RefSpec::new("main", "main"))
+            .await
+            .unwrap();
+        assert_eq!(noop.merge_status, MergeStatus::UpToDate);
+    }
+
+    #[tokio::test]
+    async fn pull_fetches_cited_worldlines_first() {
+        let other = Wll::init().unwrap();
+        other.commit(CommitProposal::new("upstream")).unwrap();
+        let cited_receipts = other.ledger().read_all(other.worldline()).unwrap();
+        let cited = CrossWorldlineRef::new(
+            other.worldline().clone(),
+            cited_receipts[0].receipt_hash(),
+        );
+
+        let local = repo();
+        local
+            .commit(CommitProposal::new("uses upstream").with_evidence(cited.to_uri()))
+            .await
+            .unwrap();
+        let server = Arc::new(Wll::init_with_worldline(worldline()).unwrap());
+        server.import_dependencies(cited_receipts).unwrap();
+        let remote = Loopback::new(server.clone());
+        let spec = RefSpec::new("main", "main");
+        local.push(&remote, &spec).await.unwrap();
+
+        let clone = repo();
+        let pulled = clone.pull(&remote, &spec).await.unwrap();
+        assert_eq!(pulled.fetch.cross_worldline_receipts, 2);
+        assert!(pulled.fetch.unresolved_references.is_empty());
+        let found = clone.blocking().ledger().get_by_hash(cited.receipt_hash).unwrap();
+        assert_eq!(found.unwrap().worldline(), other.worldline());
+
+        // A reference the remote cannot supply fails the pull.
+        let missing = CrossWorldlineRef::new(other.worldline().clone(), [7; 32]);
+        local
+            .commit(CommitProposal::new("dangling").with_evidence(missing.to_uri()))
+            .await
+            .unwrap();
+        local.push(&remote, &spec).await.unwrap();
+        let err = clone.pull(&remote, &spec).await.unwrap_err();
+        assert!(matches!(err, SdkError::Sync(SyncError::VerificationFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn pull_requires_a_configured_trusted_genesis() {
+        let server = Arc::new(Wll::init_with_worldline(worldline()).unwrap());
+        server.commit(CommitProposal::new("first")).unwrap();
+        let genesis = server.ledger().read_range(&worldline(), 1, 1).unwrap()[0].receipt_hash();
+        let remote = Loopback::new(server);
+        let spec = RefSpec::new("main", "main");
+
+        let dir = tempfile::tempdir().unwrap();
+        let local = Wll::init_at_with_config(dir.path(), RepoConfig::new(worldline())).unwrap();
+        let config_path = local.repo_dir().unwrap().join("config");
+        let config = std::fs::read_to_string(&config_path).unwrap();
+        let trust = |hash: [u8; 32]| {
+            let section = format!("\n[sync]\ntrusted_genesis = [\"{}\"]\n", hex::encode(hash));
+            std::fs::write(&config_path, format!("{config}{section}")).unwrap();
+        };
+        let local = AsyncWll::new(local);
+
+        trust([7; 32]);
+        let err = local.pull(&remote, &spec).await.unwrap_err();
+        assert!(matches!(err, SdkError::Sync(SyncError::VerificationFailed(_))));
+        assert_eq!(local.blocking().receipt_count().unwrap(), 0);
+
+        trust(genesis);
+        let pulled = local.pull(&remote, &spec).await.unwrap();
+        let verification = pulled.fetch.verification.unwrap();
+        assert_eq!(verification.anchor, Some(TrustAnchor::Genesis { receipt_hash: genesis }));
+        assert!(verification.warnings.is_empty());
+    }
+
+    #[tokio::test]
+    async fn reconcile_replays_queued_commits_on_top_of_the_remote() {
+        let server = Arc::new(Wll::init_with_worldline(worldline()).unwrap());
+        let remote = Loopback::new(server.clone());
+        let spec = RefSpec::new("main", "main");
+        let offline = repo();
+        offline.blocking().queue_commit(CommitProposal::new("made offline")).unwrap();
+        assert_eq!(offline.blocking().receipt_count().unwrap(), 0);
+
+        let other = repo();
+        other.commit(CommitProposal::new("made online")).await.unwrap();
+        other.push(&remote, &spec).await.unwrap();
+
+        let report = offline.reconcile(&remote, &spec).await.unwrap();
+        assert!(report.pull.is_some() && report.push.is_some());
+        assert_eq!((report.accepted(), report.remaining), (1, 0));
+        assert!(offline.blocking().pending_commits().unwrap().is_empty());
+        assert_eq!(server.receipt_count().unwrap(), 4);
+        assert_eq!(server.log(2).unwrap()[1].intent.as_deref(), Some("made offline"));
+    }
+
+    #[tokio::test]
+    async fn pull_leaves_out_objects_of_the_shared_history() {
+        let local = repo();
+        let mut index = local.blocking().new_index();
+        for n in 0..20 {
+            let path = format!("src/m{n}.rs");
+            index
+                .stage_file(&path, format!("// {n}").as_bytes(), EntryMode::Regular)
+                .unwrap();
+        }
+        for n in 0..5 {
+            local
+                .blocking()
+                .commit_tree(&mut index, CommitProposal::new(format!("base {n}")))
+                .unwrap();
+        }
+        let server = Arc::new(Wll::init_with_worldline(worldline()).unwrap());
+        let remote = Loopback::new(server.clone());
+        let spec = RefSpec::new("main", "main");
+        local.push(&remote, &spec).await.unwrap();
+        let clone = repo();
+        clone.pull(&remote, &spec).await.unwrap();
+
+        index
+            .stage_file("src/m0.rs", b"// changed", EntryMode::Regular)
+            .unwrap();
+        let tree = local
+            .blocking()
+            .commit_tree(&mut index, CommitProposal::new("change one file"))
+            .unwrap()
+            .tree
+            .unwrap();
+        let pushed = local.push(&remote, &spec).await.unwrap();
+        assert_eq!(pushed.objects_sent, 2, "the new tree and the changed blob");
+
+        let pulled = clone.pull(&remote, &spec).await.unwrap();
+        let negotiation = &pulled.fetch.negotiation;
+        assert_eq!(negotiation.common.len(), 1);
+        assert!(negotiation.rounds >= 1);
+        assert!(negotiation.haves_saved() > 0);
+        assert_eq!(pulled.fetch.objects_received, 2);
+        assert_eq!(negotiation.objects_skipped, 19);
+        assert!(clone.blocking().read_tree(&tree).is_ok());
+        assert!(clone.verify().await.unwrap().is_valid());
+    }
+
+    #[tokio::test]
+    async fn changed_files_travel_as_deltas_against_the_remote_copy() {
+        let local = repo();
+        let mut index = local.blocking().new_index();
+        let source: String = (0..2000).map(|n| format!("fn f{n}() {{}}\n")).collect();
+        index.stage_file("src/lib.rs", source.as_bytes(), EntryMode::Regular).unwrap();
+        local.blocking().commit_tree(&mut index, CommitProposal::new("add")).unwrap();
+        let server = Arc::new(Wll::init_with_worldline(worldline()).unwrap());
+        let remote = Loopback::new(server.clone());
+        let spec = RefSpec::new("main", "main");
+        let first = local.push(&remote, &spec).await.unwrap();
+
+        let changed = source.replace("fn f1000() {}", "fn f1000() { todo!() }");
+        index.stage_file("src/lib.rs", changed.as_bytes(), EntryMode::Regular).unwrap();
+        local.blocking().commit_tree(&mut index, CommitProposal::new("edit")).unwrap();
+        let second = local.push(&remote, &spec).await.unwrap();
+        assert_eq!(second.objects_sent, 2);
+        assert!(second.bytes_transferred * 4 < first.bytes_transferred);
+
+        let blob = Blob::new(changed.into_bytes()).to_stored_object().compute_id();
+        assert!(server.read_blob(&blob).is_ok());
+        assert!(server.verify().unwrap().is_valid());
+    }
+
+    #[tokio::test]
+    async fn atomic_push_applies_every_ref_or_none() {
+        let local = repo();
+        local.commit(CommitProposal::new("first")).await.unwrap();
+        local.blocking().create_branch("release").unwrap();
+        local.commit(CommitProposal::new("second")).await.unwrap();
+        let server = Arc::new(Wll::init_with_worldline(worldline()).unwrap());
+        let remote = Loopback::new(server.clone());
+        let both = [RefSpec::new("main", "main"), RefSpec::new("release", "release")];
+
+        let pushed = local.push_atomic(&remote, &both).await.unwrap();
+        assert!(pushed.atomic && pushed.rejected.is_empty());
+        assert_eq!((pushed.refs_updated.len(), pushed.receipts_sent), (2, 4));
+        assert_eq!(server.receipt_count().unwrap(), 4);
+
+        // One update expects a release tip the remote no longer has, so the
+        // push changes neither ref and appends nothing.
+        local.commit(CommitProposal::new("third")).await.unwrap();
+        let moved = local.blocking().ref_tip("refs/heads/main").unwrap().unwrap();
+        let stale = RefUpdate {
+            name: "refs/heads/release".into(),
+            old_hash: Some([1; 32]),
+            new_hash: server.ref_tip("refs/heads/release").unwrap().unwrap(),
+        };
+        let main = RefUpdate {
+            name: "refs/heads/main".into(),
+            old_hash: server.ref_tip("refs/heads/main").unwrap(),
+            new_hash: moved,
+        };
+        let receipts = local.blocking().ledger().read_range(&worldline(), 5, 6).unwrap();
+        let rejected = remote
+            .push_atomic(&local.blocking().pack_objects(&[]).unwrap().0, &receipts, &[main, stale])
+            .await
+            .unwrap();
+        assert_eq!(rejected.len(), 2);
+        assert!(rejected.iter().any(|r| r.reason == "atomic push aborted"));
+        assert_eq!(server.receipt_count().unwrap(), 4);
+        assert_ne!(server.ref_tip("refs/heads/main").unwrap(), Some(moved));
+    }
+
+    #[tokio::test]
+    async fn rejected_pack_leaves_nothing_in_the_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = Arc::new(Wll::init_at(dir.path()).unwrap());
+        let remote = Loopback::new(server.clone());
+        let missing = Blob::new(b"never sent".to_vec()).to_stored_object();
+        let entry = TreeEntry::new(EntryMode::Regular, "a", missing.compute_id());
+        let tree = Tree::new(vec![entry]).to_stored_object().unwrap();
+        let mut writer = PackWriter::new(std::path::Path::new("transfer"));
+        writer.add_stored_object(&tree);
+        let (pack, _) = writer.finish_to_bytes().unwrap();
+
+        let err = remote.push_pack(&pack).await.unwrap_err();
+        assert!(err.to_string().contains("missing object"));
+        assert!(!server.store().exists(&tree.compute_id()).unwrap());
+        let quarantine = server.store().quarantine_dir().unwrap();
+        assert_eq!(std::fs::read_dir(quarantine).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn replica_reads_wait_for_the_position_of_earlier_writes() {
+        let primary = Arc::new(Wll::init_with_worldline(worldline()).unwrap());
+        let replica = Arc::new(Wll::init_with_worldline(worldline()).unwrap());
+        let spec = RefSpec::new("main", "main");
+        let delay = std::time::Duration::from_millis(1);
+        let connect = || {
+            ReplicaTransport::new(Box::new(Loopback::new(replica.clone())))
+                .with_primary(Box::new(Loopback::new(primary.clone())))
+                .with_retries(1, delay)
+        };
+
+        let local = repo();
+        local.commit(CommitProposal::new("written")).await.unwrap();
+        let writes = connect();
+        local.push(&writes, &spec).await.unwrap();
+        assert_eq!(primary.receipt_count().unwrap(), 2);
+        assert_eq!(replica.receipt_count().unwrap(), 0);
+        let token = writes.required();
+        assert_eq!(token.position(&worldline()).map(|p| p.seq), Some(2));
+
+        // The replica has not caught up, so the read goes to the primary.
+        let clone = repo();
+        clone.pull(&connect().require(&token), &spec).await.unwrap();
+        assert_eq!(clone.blocking().receipt_count().unwrap(), 2);
+
+        let alone = ReplicaTransport::new(Box::new(Loopback::new(replica.clone())))
+            .with_retries(1, delay)
+            .require(&token);
+        let err = repo().pull(&alone, &spec).await.unwrap_err();
+        assert!(matches!(err, SdkError::Sync(SyncError::ReplicaBehind(_))));
+    }
+
+    #[tokio::test]
+    async fn push_rejects_unknown_remote_tip() {
+        let local = repo();
+        local.commit(CommitProposal::new("local")).await.unwrap();
+
+        let other = Wll::init().unwrap();
+        other.commit(CommitProposal::new("elsewhere")).unwrap();
+        let remote = Loopback::new(Arc::new(other));
+
+        let err = local
+            .push(&remote, &RefSpec::new("main", "main"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, SdkError::Sync(SyncError::NotFastForward(_))));
+    }
+
+    #[tokio::test]
+    async fn pre_push_hook_vetoes_before_anything_is_sent() {
+        use crate::hooks::{HookOutcome, HookPayload, HookPoint};
+
+        let wll = Wll::init_with_worldline(worldline()).unwrap().with_hook(
+            HookPoint::PrePush,
+            |payload: &HookPayload| match payload.refs[0].name.as_str() {
+                "refs/heads/release" => HookOutcome::veto("release is frozen"),
+                _ => HookOutcome::Allow,
+            },
+        );
+        let local = AsyncWll::new(wll);
+        local.commit(CommitProposal::new("local")).await.unwrap();
+        let server = Arc::new(Wll::init_with_worldline(worldline()).unwrap());
+        let remote = Loopback::new(server.clone());
+
+        let err = local.push(&remote, &RefSpec::new("main", "release")).await.unwrap_err();
+        let frozen =
+            matches!(&err, SdkError::CommitmentRejected { reason, .. } if reason.contains("frozen"));
+        assert!(frozen, "{err}");
+        assert_eq!(server.receipt_count().unwrap(), 0);
+        assert_eq!(local.blocking().receipt_count().unwrap(), 4);
+
+        // The recorded veto follows the branch tip, so it is not pushed.
+        local.push(&remote, &RefSpec::new("main", "main")).await.unwrap();
+        assert_eq!(server.receipt_count().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn push_and_pull_carry_trace_context_to_the_transport() {
+        let local = repo();
+        local.commit(CommitProposal::new("traced")).await.unwrap();
+        let remote = Loopback::new(Arc::new(Wll::init_with_worldline(worldline()).unwrap()));
+        let spec = RefSpec::new("main", "main");
+
+        let caller = TraceContext::new_root();
+        wll_telemetry::scope(caller, local.push(&remote, &spec))
+            .await
+            .unwrap();
+        repo().pull(&remote, &spec).await.unwrap();
+
+        let traces = remote.traces.lock().unwrap().clone();
+        let [Some(pushed), Some(pulled)] = traces[..] else {
+            panic!("expected two traced listings, got {traces:?}");
+        };
+        assert_eq!(pushed.trace_id, caller.trace_id);
+        assert_ne!(pushed.parent_id, caller.parent_id);
+        assert_ne!(pulled.trace_id, caller.trace_id);
+    }
+
+    #[tokio::test]
+    async fn futures_are_send() {
+        fn assert_send<T: Send>(_: &T) {}
+        let wll = repo();
+        let remote = Loopback::new(Arc::new(Wll::init().unwrap()));
+        let spec = RefSpec::new("main", "main");
+        assert_send(&wll.commit(CommitProposal::new("x")));
+        assert_send(&wll.push(&remote, &spec));
+        assert_send(&wll.pull(&remote, &spec));
+    }
+}
@@ -0,0 +1,122 @@
+//! Evidence artifacts stored in the repository.
+//!
+//! [`Wll::attach_evidence`] writes an artifact (test log, review approval,
+//! SBOM) as a blob and returns the [`EvidenceAttachment`] to add to a
+//! proposal with [`CommitProposal::with_attachment`]. The commitment receipt
+//! records the blob id, so the evidence stays available after the external
+//! system it came from has moved on.
+//!
+//! [`CommitProposal::with_attachment`]: crate::CommitProposal::with_attachment
+
+use wll_types::EvidenceAttachment;
+
+use crate::error::{SdkError, SdkResult};
+use crate::repository::Wll;
+
+impl Wll {
+    /// Store `data` as a blob and describe it as an evidence attachment.
+    ///
+    /// `name` is the file name the artifact is extracted as and may not
+    /// contain path separators.
+    pub fn attach_evidence(
+        &self,
+        name: &str,
+        media_type: &str,
+        data: &[u8],
+    ) -> SdkResult<EvidenceAttachment> {
+        if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+            return Err(SdkError::InvalidOperation(format!(
+                "invalid attachment name: {name:?}"
+            )));
+        }
+        if media_type.is_empty() {
+            return Err(SdkError::InvalidOperation(
+                "attachment media type is empty".into(),
+            ));
+        }
+        Ok(EvidenceAttachment {
+            object: self.write_blob(data)?,
+            media_type: media_type.to_string(),
+            name: name.to_string(),
+            size: data.len() as u64,
+        })
+    }
+
+    /// Read the artifact behind `attachment`, checking its recorded size.
+    pub fn read_attachment(&self, attachment: &EvidenceAttachment) -> SdkResult<Vec<u8>> {
+        let data = self.read_blob(&attachment.object)?;
+        if data.len() as u64 != attachment.size {
+            return Err(SdkError::InvalidOperation(format!(
+                "attachment {} is {} bytes, expected {}",
+                attachment.name,
+                data.len(),
+                attachment.size
+            )));
+        }
+        Ok(data)
+    }
+
+    /// Fail unless every attachment's blob is in the store.
+    pub(crate) fn check_attachments(&self, attachments: &[EvidenceAttachment]) -> SdkResult<()> {
+        for attachment in attachments {
+            if !self.store().exists(&attachment.object)? {
+                return Err(SdkError::ObjectNotFound(attachment.object.to_hex()));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commit::CommitProposal;
+    use crate::ReceiptDetails;
+
+    #[test]
+    fn attachments_are_recorded_and_readable() {
+        let wll = Wll::init().unwrap();
+        let log = wll
+            .attach_evidence("test.log", "text/plain", b"42 passed\n")
+            .unwrap();
+        let result = wll
+            .commit(
+                CommitProposal::new("ship it")
+                    .with_evidence("issue://7")
+                    .with_attachment(log.clone()),
+            )
+            .unwrap();
+
+        let evidence = &result.commitment_receipt.evidence;
+        assert_eq!(evidence.references, vec!["issue://7"]);
+        assert_eq!(evidence.attachments, vec![log.clone()]);
+        assert!(evidence.verify_digest());
+
+        let details: ReceiptDetails = wll.receipt_details(&result.receipt_hash).unwrap();
+        let recorded = &details.commitment.unwrap().evidence.attachments[0];
+        assert_eq!(recorded.media_type, "text/plain");
+        assert_eq!(wll.read_attachment(recorded).unwrap(), b"42 passed\n");
+    }
+
+    #[test]
+    fn attachment_names_and_blobs_are_checked() {
+        let wll = Wll::init().unwrap();
+        assert!(wll.attach_evidence("../x", "text/plain", b"").is_err());
+        assert!(wll.attach_evidence("log", "", b"").is_err());
+
+        let other = Wll::init().unwrap();
+        let missing = other
+            .attach_evidence("sbom.json", "application/spdx+json", b"{}")
+            .unwrap();
+        let err = wll
+            .commit(CommitProposal::new("unbacked").with_attachment(missing.clone()))
+            .unwrap_err();
+        assert!(matches!(err, SdkError::ObjectNotFound(_)));
+
+        let mut resized = wll
+            .attach_evidence("sbom.json", "application/spdx+json", b"{}")
+            .unwrap();
+        resized.size = 10;
+        assert!(wll.read_attachment(&resized).is_err());
+    }
+}
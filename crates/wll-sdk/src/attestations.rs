@@ -0,0 +1,175 @@
+//! External identities bound to the repository's worldline.
+//!
+//! An [`IdentityAttestation`] records that an identity token (an OIDC
+//! subject, a CI job identity) was verified for this worldline. The gate
+//! sees every unexpired attestation, so `[gate.require_attestation]` can
+//! demand one for risky commitment classes.
+//!
+//! Persistent repositories keep them in `.wll/attestations.json`.
+
+use std::path::{Path, PathBuf};
+
+use wll_types::IdentityAttestation;
+
+use crate::error::{SdkError, SdkResult};
+use crate::repository::Wll;
+
+const ATTESTATIONS_FILE: &str = "attestations.json";
+
+/// Read the attestations saved in `repo_dir`, if any.
+pub(crate) fn load(repo_dir: &Path) -> SdkResult<Vec<IdentityAttestation>> {
+    match std::fs::read(repo_dir.join(ATTESTATIONS_FILE)) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map_err(|e| SdkError::Internal(format!("{ATTESTATIONS_FILE}: {e}"))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+impl Wll {
+    /// Attestations bound to this worldline, including expired ones.
+    pub fn attestations(&self) -> SdkResult<Vec<IdentityAttestation>> {
+        Ok(self
+            .attestation_list()
+            .read()
+            .map_err(|_| SdkError::Internal("attestation lock poisoned".into()))?
+            .clone())
+    }
+
+    /// Bind a verified external identity to this worldline.
+    ///
+    /// The caller is responsible for verifying the token the attestation was
+    /// made from (see `OidcVerifier` in `wll-server`). An attestation for the
+    /// same issuer and subject replaces the previous one.
+    pub fn attest(&self, attestation: IdentityAttestation) -> SdkResult<()> {
+        if attestation.worldline != *self.worldline() {
+            return Err(SdkError::InvalidOperation(format!(
+                "attestation is for worldline {}, not {}",
+                attestation.worldline.short_id(),
+                self.worldline().short_id()
+            )));
+        }
+        if !attestation.is_valid_at(self.clock().now_secs()) {
+            return Err(SdkError::InvalidOperation(format!(
+                "attestation for {} from {} has expired",
+                attestation.subject, attestation.issuer
+            )));
+        }
+        self.update_attestations(|list| {
+            list.retain(|a| a.issuer != attestation.issuer || a.subject != attestation.subject);
+            list.push(attestation);
+        })
+    }
+
+    /// Remove the attestation for `subject` at `issuer`, returning it.
+    pub fn revoke_attestation(
+        &self,
+        issuer: &str,
+        subject: &str,
+    ) -> SdkResult<Option<IdentityAttestation>> {
+        let mut removed = None;
+        self.update_attestations(|list| {
+            if let Some(index) = list
+                .iter()
+                .position(|a| a.issuer == issuer && a.subject == subject)
+            {
+                removed = Some(list.remove(index));
+            }
+        })?;
+        Ok(removed)
+    }
+
+    /// Attestations the gate may count for a commit made now.
+    pub(crate) fn valid_attestations(&self) -> SdkResult<Vec<IdentityAttestation>> {
+        let now = self.clock().now_secs();
+        let mut list = self.attestations()?;
+        list.retain(|a| a.is_valid_at(now));
+        Ok(list)
+    }
+
+    fn update_attestations(&self, f: impl FnOnce(&mut Vec<IdentityAttestation>)) -> SdkResult<()> {
+        let mut list = self
+            .attestation_list()
+            .write()
+            .map_err(|_| SdkError::Internal("attestation lock poisoned".into()))?;
+        f(&mut list);
+        if let Some(path) = self.attestations_path() {
+            let json =
+                serde_json::to_vec_pretty(&*list)?;
+            std::fs::write(path, json)?;
+        }
+        Ok(())
+    }
+
+    fn attestations_path(&self) -> Option<PathBuf> {
+        self.repo_dir().map(|dir| dir.join(ATTESTATIONS_FILE))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use wll_types::{AttestationKind, Clock, CommitmentClass, SystemClock, WorldlineId};
+
+    use super::*;
+    use crate::commit::CommitProposal;
+
+    fn attestation(worldline: &WorldlineId, subject: &str) -> IdentityAttestation {
+        IdentityAttestation {
+            worldline: worldline.clone(),
+            issuer: "https://idp.example".into(),
+            subject: subject.into(),
+            kind: AttestationKind::Human,
+            claims: BTreeMap::new(),
+            issued_at: SystemClock.now_secs(),
+            expires_at: Some(SystemClock.now_secs() + 3600),
+            token_digest: IdentityAttestation::digest_token("header.claims.sig"),
+        }
+    }
+
+    #[test]
+    fn attestations_persist_and_unlock_gated_classes() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = Wll::init_at(dir.path())
+            .unwrap()
+            .repo_dir()
+            .unwrap()
+            .join("config");
+        let mut text = std::fs::read_to_string(&config_path).unwrap();
+        text.push_str("\n[gate.require_attestation]\nPolicyChange = \"human\"\n");
+        std::fs::write(&config_path, text).unwrap();
+
+        let wll = Wll::open(dir.path()).unwrap();
+        let policy_change =
+            || CommitProposal::new("tighten review").with_class(CommitmentClass::PolicyChange);
+
+        assert!(matches!(
+            wll.commit(policy_change()),
+            Err(SdkError::CommitmentRejected { .. })
+        ));
+
+        let other = WorldlineId::derive(&wll_types::IdentityMaterial::GenesisHash([9; 32]));
+        assert!(wll.attest(attestation(&other, "alice")).is_err());
+        let mut expired = attestation(wll.worldline(), "alice");
+        expired.expires_at = Some(1);
+        assert!(wll.attest(expired).is_err());
+
+        wll.attest(attestation(wll.worldline(), "alice")).unwrap();
+        wll.attest(attestation(wll.worldline(), "alice")).unwrap();
+        assert_eq!(wll.attestations().unwrap().len(), 1);
+        wll.commit(policy_change()).unwrap();
+
+        let reopened = Wll::open(dir.path()).unwrap();
+        assert_eq!(reopened.attestations().unwrap()[0].subject, "alice");
+        let revoked = reopened
+            .revoke_attestation("https://idp.example", "alice")
+            .unwrap();
+        assert!(revoked.is_some());
+        assert!(Wll::open(dir.path())
+            .unwrap()
+            .attestations()
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,255 @@
+use std::sync::Arc;
+
+use wll_dag::DagStorage;
+use wll_fabric::EventFabric;
+use wll_gate::{CommitmentGate, PolicyStore};
+use wll_ledger::Ledger;
+use wll_refs::RefStore;
+use wll_store::ObjectStore;
+use wll_types::{Clock, WorldlineId};
+
+use crate::error::SdkResult;
+use crate::repository::Wll;
+
+/// Composes a [`Wll`] from caller-supplied backends.
+///
+/// Every component is optional; anything left unset falls back to the
+/// in-memory implementation used by [`Wll::init`], a random worldline, and
+/// the permissive default gate. When the ref store has no HEAD yet, `build`
+/// creates `main` and checks it out; existing refs are left untouched.
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use wll_sdk::Wll;
+/// use wll_store::InMemoryObjectStore;
+///
+/// let store = Arc::new(InMemoryObjectStore::new());
+/// let wll = Wll::builder().with_store(store.clone()).build().unwrap();
+/// wll.write_blob(b"shared").unwrap();
+/// assert_eq!(store.len(), 1);
+/// ```
+#[derive(Default)]
+pub struct WllBuilder {
+    pub(crate) worldline: Option<WorldlineId>,
+    pub(crate) store: Option<Arc<dyn ObjectStore>>,
+    pub(crate) ledger: Option<Arc<dyn Ledger>>,
+    pub(crate) refs: Option<Arc<dyn RefStore>>,
+    pub(crate) gate: Option<CommitmentGate>,
+    pub(crate) policies: Option<Arc<dyn PolicyStore>>,
+    pub(crate) fabric: Option<Arc<EventFabric>>,
+    pub(crate) dag_storage: Option<Box<dyn DagStorage>>,
+    pub(crate) clock: Option<Arc<dyn Clock>>,
+}
+
+impl WllBuilder {
+    /// Start with every component unset.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record receipts on this worldline.
+    pub fn with_worldline(mut self, worldline: WorldlineId) -> Self {
+        self.worldline = Some(worldline);
+        self
+    }
+
+    /// Store blobs and trees in `store`.
+    pub fn with_store(mut self, store: Arc<dyn ObjectStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Append receipts to `ledger`.
+    pub fn with_ledger(mut self, ledger: Arc<dyn Ledger>) -> Self {
+        self.ledger = Some(ledger);
+        self
+    }
+
+    /// Keep branches, tags and HEAD in `refs`.
+    pub fn with_refs(mut self, refs: Arc<dyn RefStore>) -> Self {
+        self.refs = Some(refs);
+        self
+    }
+
+    /// Evaluate every commit with `gate`.
+    pub fn with_gate(mut self, gate: CommitmentGate) -> Self {
+        self.gate = Some(gate);
+        self
+    }
+
+    /// Keep the policy versions commits are judged under in `policies`.
+    pub fn with_policy_store(mut self, policies: Arc<dyn PolicyStore>) -> Self {
+        self.policies = Some(policies);
+        self
+    }
+
+    /// Publish commit events to `fabric`.
+    pub fn with_fabric(mut self, fabric: Arc<EventFabric>) -> Self {
+        self.fabric = Some(fabric);
+        self
+    }
+
+    /// Persist the provenance DAG through `storage`.
+    pub fn with_dag_storage(mut self, storage: Box<dyn DagStorage>) -> Self {
+        self.dag_storage = Some(storage);
+        self
+    }
+
+    /// Read the time from `clock` instead of the system clock.
+    ///
+    /// It stamps receipts in the default in-memory ledger, index entries,
+    /// commit nonces, and the time gate stages and attestations are checked
+    /// at. An injected ledger or fabric keeps its own clock; give it the same
+    /// one with its `with_clock`.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Assemble the repository.
+    pub fn build(self) -> SdkResult<Wll> {
+        Wll::from_builder(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wll_fabric::{EventKind, EventPayload, FabricConfig};
+    use wll_gate::GateConfig;
+    use wll_ledger::{InMemoryLedger, LedgerReader};
+    use wll_refs::InMemoryRefStore;
+    use wll_types::IdentityMaterial;
+
+    use crate::commit::CommitProposal;
+    use crate::error::SdkError;
+
+    fn worldline() -> WorldlineId {
+        WorldlineId::derive(&IdentityMaterial::GenesisHash([5; 32]))
+    }
+
+    #[test]
+    fn injected_backends_receive_writes() {
+        let ledger = Arc::new(InMemoryLedger::default());
+        let refs = Arc::new(InMemoryRefStore::new());
+        let wll = WllBuilder::new()
+            .with_worldline(worldline())
+            .with_ledger(ledger.clone())
+            .with_refs(refs.clone())
+            .build()
+            .unwrap();
+
+        let result = wll.commit(CommitProposal::new("injected")).unwrap();
+
+        assert_eq!(ledger.receipt_count(&worldline()).unwrap(), 2);
+        let main = refs.read_ref("refs/heads/main").unwrap().unwrap();
+        assert_eq!(*main.target_hash(), result.outcome_receipt.receipt_hash);
+    }
+
+    #[test]
+    fn second_handle_over_shared_backends_sees_history() {
+        let ledger: Arc<dyn Ledger> = Arc::new(InMemoryLedger::default());
+        let refs: Arc<dyn RefStore> = Arc::new(InMemoryRefStore::new());
+        let first = WllBuilder::new()
+            .with_worldline(worldline())
+            .with_ledger(ledger.clone())
+            .with_refs(refs.clone())
+            .build()
+            .unwrap();
+        first.commit(CommitProposal::new("one")).unwrap();
+        first.create_branch("feature").unwrap();
+
+        let second = WllBuilder::new()
+            .with_worldline(worldline())
+            .with_ledger(ledger)
+            .with_refs(refs)
+            .build()
+            .unwrap();
+        assert_eq!(second.receipt_count().unwrap(), 2);
+        assert_eq!(second.provenance_len(), 2);
+        assert_eq!(second.list_branches().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn injected_gate_is_used() {
+        let gate = CommitmentGate::with_default_stages(GateConfig {
+            default_policy: wll_gate::Policy {
+                id: "evidence".into(),
+                name: "Require evidence".into(),
+                rules: vec![wll_gate::PolicyRule::RequireEvidence],
+                applies_to: wll_gate::PolicyScope::All,
+            },
+            ..GateConfig::default()
+        });
+        let wll = WllBuilder::new().with_gate(gate).build().unwrap();
+        let err = wll.commit(CommitProposal::new("no evidence")).unwrap_err();
+        assert!(matches!(err, SdkError::CommitmentRejected { .. }));
+    }
+
+    #[test]
+    fn injected_clock_stamps_receipts_and_index() {
+        let clock = Arc::new(wll_types::ManualClock::new(1_700_000_000_000));
+        let wll = WllBuilder::new()
+            .with_worldline(worldline())
+            .with_clock(clock.clone())
+            .build()
+            .unwrap();
+
+        let first = wll.commit(CommitProposal::new("first")).unwrap();
+        clock.advance_ms(5);
+        let second = wll.commit(CommitProposal::new("second")).unwrap();
+
+        let anchor = |ms, logical| wll_types::TemporalAnchor::new(ms, logical, 0);
+        assert_eq!(first.commitment_receipt.timestamp, anchor(1_700_000_000_000, 0));
+        assert_eq!(first.outcome_receipt.timestamp, anchor(1_700_000_000_000, 1));
+        assert_eq!(second.outcome_receipt.timestamp, anchor(1_700_000_000_005, 1));
+
+        let mut index = wll.new_index();
+        index
+            .stage_file("a.txt", b"a", wll_store::EntryMode::Regular)
+            .unwrap();
+        assert_eq!(index.get("a.txt").unwrap().mtime, clock.system_time());
+    }
+
+    #[test]
+    fn commits_are_published_to_fabric() {
+        let dir = tempfile::tempdir().unwrap();
+        let fabric = Arc::new(
+            EventFabric::new(&dir.path().join("fabric.wal"), FabricConfig::default()).unwrap(),
+        );
+        let wll = WllBuilder::new()
+            .with_worldline(worldline())
+            .with_fabric(fabric.clone())
+            .build()
+            .unwrap();
+
+        wll.commit(CommitProposal::new("first")).unwrap();
+        let second = wll.commit(CommitProposal::new("second")).unwrap();
+
+        let events = fabric.recover().unwrap();
+        let kinds: Vec<EventKind> = events.iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                EventKind::CommitmentDecided,
+                EventKind::OutcomeRecorded,
+                EventKind::RefUpdated,
+                EventKind::CommitmentDecided,
+                EventKind::OutcomeRecorded,
+                EventKind::RefUpdated,
+            ]
+        );
+        match &events[5].payload {
+            EventPayload::RefUpdate {
+                ref_name,
+                old_target,
+                new_target,
+            } => {
+                assert_eq!(ref_name, "refs/heads/main");
+                assert!(old_target.is_some());
+                assert_eq!(new_target.as_bytes(), &second.outcome_receipt.receipt_hash);
+            }
+            other => panic!("unexpected payload {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,626 @@
+//! Single-file bundles for moving history without a network.
+//!
+//! A bundle carries branch refs, a contiguous range of one worldline's
+//! receipts, the notes on receipts, and a pack of every object those
+//! receipts and notes refer to. It ends
+//! with a BLAKE3 checksum over everything before it and an optional Ed25519
+//! signature over that checksum, so a bundle carried across an air gap can
+//! be checked before anything is written:
+//!
+//! ```text
+//! magic        "WLLBDL\0\x01"
+//! header_len   u32 BE
+//! header       JSON: worldline, refs, receipts, notes tree id if any
+//! pack_len     u64 BE
+//! pack         pack file bytes, trailing pack checksum included
+//! checksum     32 bytes, BLAKE3 of all of the above
+//! signed       u8: 0 or 1
+//! [public_key  32 bytes]
+//! [signature   64 bytes over "wll-bundle-v1\0" ++ checksum]
+//! ```
+//!
+//! A bundle that starts past receipt 1 has a prerequisite: the receiving
+//! repository must already hold the receipt the range builds on, just as a
+//! git bundle can require commits the receiver already has.
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use wll_crypto::{Signature, SigningKey, VerifyingKey};
+use wll_ledger::Receipt;
+use wll_pack::PackReader;
+use wll_sync::MergeStatus;
+use wll_types::{ObjectId, WorldlineId};
+use zeroize::Zeroizing;
+
+use crate::error::{SdkError, SdkResult};
+use crate::repository::Wll;
+use crate::time_anchor::parse_public_key;
+
+/// Leading bytes of every bundle file, including the format version.
+const MAGIC: &[u8; 8] = b"WLLBDL\x00\x01";
+
+/// Domain separator prepended to the checksum before signing.
+const SIGNATURE_CONTEXT: &[u8] = b"wll-bundle-v1\x00";
+
+/// A branch carried by a bundle.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleRef {
+    /// Full ref name, `refs/heads/<branch>`.
+    pub name: String,
+    /// Receipt the branch points at.
+    pub receipt_hash: [u8; 32],
+}
+
+/// Who signed a bundle, and the signature they made.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BundleSignature {
+    pub public_key: VerifyingKey,
+    pub signature: Signature,
+}
+
+/// Which branches and receipts [`Wll::create_bundle`] packs up.
+#[derive(Clone, Debug, Default)]
+pub struct BundleOptions {
+    /// Branch names (short or `refs/heads/…`); empty means every branch.
+    pub branches: Vec<String>,
+    /// First receipt sequence number to include (default 1).
+    pub from_seq: Option<u64>,
+    /// Last receipt sequence number to include (default: the newest branch
+    /// tip).
+    pub to_seq: Option<u64>,
+}
+
+impl BundleOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bundle only these branches.
+    pub fn with_branches(mut self, branches: Vec<String>) -> Self {
+        self.branches = branches;
+        self
+    }
+
+    /// Bundle receipts `from_seq..=to_seq`; `None` keeps the default bound.
+    pub fn with_range(mut self, from_seq: Option<u64>, to_seq: Option<u64>) -> Self {
+        self.from_seq = from_seq;
+        self.to_seq = to_seq;
+        self
+    }
+}
+
+/// Refs, receipts and objects for one worldline, ready to be written to or
+/// read from a bundle file.
+#[derive(Clone, Debug)]
+pub struct Bundle {
+    pub worldline: WorldlineId,
+    pub refs: Vec<BundleRef>,
+    /// Contiguous receipts in sequence order.
+    pub receipts: Vec<Receipt>,
+    /// The sender's notes tree ([`Wll::notes_root`]).
+    pub notes: Option<ObjectId>,
+    /// Pack holding every object the receipts and notes refer to.
+    pub pack: Vec<u8>,
+    pub signature: Option<BundleSignature>,
+}
+
+/// Borrowed form of the bundle header, for writing without cloning.
+#[derive(Serialize)]
+struct HeaderRef<'a> {
+    worldline: &'a WorldlineId,
+    refs: &'a [BundleRef],
+    receipts: &'a [Receipt],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    notes: &'a Option<ObjectId>,
+}
+
+#[derive(Deserialize)]
+struct Header {
+    worldline: WorldlineId,
+    refs: Vec<BundleRef>,
+    receipts: Vec<Receipt>,
+    #[serde(default)]
+    notes: Option<ObjectId>,
+}
+
+impl Bundle {
+    /// Everything the checksum covers.
+    fn content(&self) -> SdkResult<Vec<u8>> {
+        let header = serde_json::to_vec(&HeaderRef {
+            worldline: &self.worldline,
+            refs: &self.refs,
+            receipts: &self.receipts,
+            notes: &self.notes,
+        })?;
+        let header_len = u32::try_from(header.len())
+            .map_err(|_| SdkError::Bundle("header exceeds 4 GiB".into()))?;
+        let mut out = Vec::with_capacity(MAGIC.len() + 12 + header.len() + self.pack.len());
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&header_len.to_be_bytes());
+        out.extend_from_slice(&header);
+        out.extend_from_slice(&(self.pack.len() as u64).to_be_bytes());
+        out.extend_from_slice(&self.pack);
+        Ok(out)
+    }
+
+    /// BLAKE3 checksum of the bundle contents; this is what gets signed.
+    pub fn checksum(&self) -> SdkResult<[u8; 32]> {
+        Ok(*blake3::hash(&self.content()?).as_bytes())
+    }
+
+    /// Sign the bundle with `key`, replacing any earlier signature.
+    pub fn sign(&mut self, key: &SigningKey) -> SdkResult<()> {
+        let message = signed_message(&self.checksum()?);
+        self.signature = Some(BundleSignature {
+            public_key: key.verifying_key(),
+            signature: key.sign(&message),
+        });
+        Ok(())
+    }
+
+    /// Require a signature by one of `trusted`.
+    ///
+    /// [`Bundle::from_bytes`] already rejects a signature that does not
+    /// match its key; this checks that the key is one the caller trusts.
+    pub fn verify_signer(&self, trusted: &[VerifyingKey]) -> SdkResult<()> {
+        let signed = self
+            .signature
+            .as_ref()
+            .ok_or_else(|| SdkError::Bundle("bundle is not signed".into()))?;
+        if !trusted.contains(&signed.public_key) {
+            return Err(SdkError::Bundle(format!(
+                "bundle is signed by untrusted key {}",
+                hex::encode(signed.public_key.as_bytes())
+            )));
+        }
+        Ok(())
+    }
+
+    /// Serialize to the bundle file format.
+    pub fn to_bytes(&self) -> SdkResult<Vec<u8>> {
+        let mut out = self.content()?;
+        let checksum = *blake3::hash(&out).as_bytes();
+        out.extend_from_slice(&checksum);
+        match &self.signature {
+            None => out.push(0),
+            Some(signed) => {
+                out.push(1);
+                out.extend_from_slice(&signed.public_key.as_bytes());
+                out.extend_from_slice(&signed.signature.to_bytes());
+            }
+        }
+        Ok(out)
+    }
+
+    /// Parse a bundle file, checking its checksum and, if it is signed, that
+    /// the signature matches the embedded key.
+    pub fn from_bytes(bytes: &[u8]) -> SdkResult<Self> {
+        let mut cursor = Cursor { bytes, pos: 0 };
+        if cursor.take(MAGIC.len())? != MAGIC {
+            return Err(SdkError::Bundle("not a WLL bundle".into()));
+        }
+        let header_len = u32::from_be_bytes(cursor.array()?) as usize;
+        let header = cursor.take(header_len)?;
+        let pack_len = usize::try_from(u64::from_be_bytes(cursor.array()?))
+            .map_err(|_| SdkError::Bundle("truncated".into()))?;
+        let pack = cursor.take(pack_len)?;
+
+        let checksum = *blake3::hash(&bytes[..cursor.pos]).as_bytes();
+        if cursor.array::<32>()? != checksum {
+            return Err(SdkError::Bundle("checksum mismatch".into()));
+        }
+        let signature = match cursor.array::<1>()? {
+            [0] => None,
+            [1] => {
+                let public_key = VerifyingKey::from_bytes(cursor.array()?)
+                    .map_err(|e| SdkError::Bundle(format!("signer key: {e}")))?;
+                let signature = Signature::from_bytes(cursor.array()?);
+                public_key
+                    .verify(&signed_message(&checksum), &signature)
+                    .map_err(|e| SdkError::Bundle(e.to_string()))?;
+                Some(BundleSignature {
+                    public_key,
+                    signature,
+                })
+            }
+            [flag] => return Err(SdkError::Bundle(format!("unknown signature flag {flag}"))),
+        };
+        if cursor.pos != bytes.len() {
+            return Err(SdkError::Bundle("trailing bytes after signature".into()));
+        }
+
+        let header: Header = serde_json::from_slice(header)
+            .map_err(|e| SdkError::Bundle(format!("malformed header: {e}")))?;
+        Ok(Self {
+            worldline: header.worldline,
+            refs: header.refs,
+            receipts: header.receipts,
+            notes: header.notes,
+            pack: pack.to_vec(),
+            signature,
+        })
+    }
+}
+
+/// Read a signing key stored as its 32-byte secret in hex.
+pub fn read_signing_key(path: &Path) -> SdkResult<SigningKey> {
+    // The text and decoded bytes are zeroized once the key is built.
+    let encoded = Zeroizing::new(std::fs::read_to_string(path)?);
+    let bytes = Zeroizing::new(hex::decode(encoded.trim()).unwrap_or_default());
+    <[u8; 32]>::try_from(bytes.as_slice())
+        .ok()
+        .map(SigningKey::from_bytes)
+        .ok_or_else(|| SdkError::Config(format!("{}: expected a 32-byte hex key", path.display())))
+}
+
+/// Parse a trusted signer's public key given as hex or base64.
+pub fn parse_trusted_key(encoded: &str) -> SdkResult<VerifyingKey> {
+    parse_public_key("trusted key", encoded)
+}
+
+fn signed_message(checksum: &[u8; 32]) -> Vec<u8> {
+    [SIGNATURE_CONTEXT, checksum].concat()
+}
+
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, len: usize) -> SdkResult<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| SdkError::Bundle("truncated".into()))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> SdkResult<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+}
+
+/// What [`Wll::verify_bundle`] found.
+#[derive(Clone, Debug, Serialize)]
+pub struct BundleSummary {
+    pub worldline: WorldlineId,
+    /// Sequence number of the first bundled receipt.
+    pub from_seq: u64,
+    /// Sequence number of the last bundled receipt.
+    pub to_seq: u64,
+    pub receipts: usize,
+    /// Receipts this repository does not have yet.
+    pub new_receipts: usize,
+    pub objects: usize,
+    pub refs: Vec<BundleRef>,
+    /// Hex public key of the signer, if the bundle is signed.
+    pub signed_by: Option<String>,
+}
+
+/// Result of [`Wll::unbundle`].
+#[derive(Clone, Debug, Serialize)]
+pub struct UnbundleReport {
+    pub objects: usize,
+    pub receipts_imported: usize,
+    /// Branches that moved forward.
+    pub refs_updated: Vec<String>,
+    /// Notes that were new here.
+    pub notes_merged: usize,
+}
+
+impl Wll {
+    /// Pack branches and receipts of this worldline into a [`Bundle`].
+    ///
+    /// Branches whose tip lies past `to_seq` are left out unless named
+    /// explicitly, which is an error.
+    pub fn create_bundle(&self, options: &BundleOptions) -> SdkResult<Bundle> {
+        let branches: Vec<(String, [u8; 32])> = if options.branches.is_empty() {
+            self.ref_tips()?
+                .into_iter()
+                .filter(|(name, _)| name.starts_with("refs/heads/"))
+                .collect()
+        } else {
+            options
+                .branches
+                .iter()
+                .map(|branch| {
+                    let name = if branch.starts_with("refs/heads/") {
+                        branch.clone()
+                    } else {
+                        format!("refs/heads/{branch}")
+                    };
+                    let tip = self
+                        .ref_tip(&name)?
+                        .ok_or_else(|| SdkError::BranchNotFound(branch.clone()))?;
+                    Ok((name, tip))
+                })
+                .collect::<SdkResult<_>>()?
+        };
+
+        let mut tips = Vec::with_capacity(branches.len());
+        for (name, tip) in branches {
+            let seq = self
+                .local_seq(tip)?
+                .ok_or_else(|| SdkError::ObjectNotFound(hex::encode(tip)))?;
+            tips.push((name, tip, seq));
+        }
+        let from_seq = options.from_seq.unwrap_or(1);
+        let to_seq = match options.to_seq {
+            Some(seq) => seq,
+            None => tips.iter().map(|(_, _, seq)| *seq).max().unwrap_or(0),
+        };
+        if from_seq == 0 || from_seq > to_seq {
+            return Err(SdkError::InvalidOperation(format!(
+                "empty receipt range {from_seq}..={to_seq}"
+            )));
+        }
+
+        let mut refs = Vec::new();
+        for (name, receipt_hash, seq) in tips {
+            if seq <= to_seq {
+                refs.push(BundleRef { name, receipt_hash });
+            } else if !options.branches.is_empty() {
+                return Err(SdkError::InvalidOperation(format!(
+                    "{name} points past receipt {to_seq}"
+                )));
+            }
+        }
+        let receipts = self
+            .ledger()
+            .read_range(self.worldline(), from_seq, to_seq)?;
+        let notes = self.notes_root()?;
+        let mut roots = self.receipt_objects(&receipts)?;
+        roots.extend(notes);
+        let (pack, _) = self.pack_objects(&roots)?;
+        Ok(Bundle {
+            worldline: self.worldline().clone(),
+            refs,
+            receipts,
+            notes,
+            pack,
+            signature: None,
+        })
+    }
+
+    /// Check that `bundle` can be applied to this repository: it is for this
+    /// worldline, its receipts chain onto what is already here, its pack is
+    /// intact and holds every object the receipts need, and each ref points
+    /// at a receipt that will exist afterwards.
+    pub fn verify_bundle(&self, bundle: &Bundle) -> SdkResult<BundleSummary> {
+        self.check_bundle(bundle).map(|(summary, _)| summary)
+    }
+
+    fn check_bundle(&self, bundle: &Bundle) -> SdkResult<(BundleSummary, PackReader)> {
+        if bundle.worldline != *self.worldline() {
+            return Err(SdkError::Bundle(format!(
+                "bundle is for worldline {}, this repository is {}",
+                bundle.worldline,
+                self.worldline()
+            )));
+        }
+        let (first, last) = match (bundle.receipts.first(), bundle.receipts.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Err(SdkError::Bundle("bundle holds no receipts".into())),
+        };
+        for pair in bundle.receipts.windows(2) {
+            if pair[1].seq() != pair[0].seq() + 1
+                || pair[1].prev_hash() != Some(pair[0].receipt_hash())
+            {
+                return Err(SdkError::Bundle(format!(
+                    "receipt {} does not follow receipt {}",
+                    pair[1].seq(),
+                    pair[0].seq()
+                )));
+            }
+        }
+        if let Some(prerequisite) = first.prev_hash() {
+            if self.local_seq(prerequisite)? != Some(first.seq() - 1) {
+                return Err(SdkError::Bundle(format!(
+                    "requires receipt {} ({}), which this repository does not have",
+                    first.seq() - 1,
+                    hex::encode(prerequisite)
+                )));
+            }
+        }
+
+        let reader = PackReader::from_pack_bytes(bundle.pack.clone())?;
+        let mut needed = self.receipt_objects(&bundle.receipts)?;
+        needed.extend(bundle.notes);
+        for id in needed {
+            if !reader.contains(&id) && !self.store().exists(&id)? {
+                return Err(SdkError::Bundle(format!("pack is missing object {id}")));
+            }
+        }
+
+        let bundled: HashSet<[u8; 32]> =
+            bundle.receipts.iter().map(Receipt::receipt_hash).collect();
+        for r in &bundle.refs {
+            if !r.name.starts_with("refs/heads/") {
+                return Err(SdkError::Bundle(format!("{} is not a branch", r.name)));
+            }
+            if !bundled.contains(&r.receipt_hash) && self.local_seq(r.receipt_hash)?.is_none() {
+                return Err(SdkError::Bundle(format!(
+                    "{} points at unknown receipt {}",
+                    r.name,
+                    hex::encode(r.receipt_hash)
+                )));
+            }
+        }
+
+        let mut new_receipts = 0;
+        for receipt in &bundle.receipts {
+            if self.ledger().get_by_hash(receipt.receipt_hash())?.is_none() {
+                new_receipts += 1;
+            }
+        }
+        let summary = BundleSummary {
+            worldline: bundle.worldline.clone(),
+            from_seq: first.seq(),
+            to_seq: last.seq(),
+            receipts: bundle.receipts.len(),
+            new_receipts,
+            objects: reader.object_count(),
+            refs: bundle.refs.clone(),
+            signed_by: bundle
+                .signature
+                .as_ref()
+                .map(|signed| hex::encode(signed.public_key.as_bytes())),
+        };
+        Ok((summary, reader))
+    }
+
+    /// Verify `bundle`, then store its objects, import its receipts,
+    /// fast-forward its branches and merge its notes.
+    pub fn unbundle(&self, bundle: Bundle) -> SdkResult<UnbundleReport> {
+        let (_, reader) = self.check_bundle(&bundle)?;
+        let objects = self.store_pack(&reader)?;
+        let receipts_imported = self.import_receipts(bundle.receipts)?;
+        let mut refs_updated = Vec::new();
+        for r in bundle.refs {
+            if self.fast_forward(&r.name, r.receipt_hash)? == MergeStatus::FastForward {
+                refs_updated.push(r.name);
+            }
+        }
+        let notes_merged = match &bundle.notes {
+            Some(notes) => self.merge_notes(notes)?,
+            None => 0,
+        };
+        Ok(UnbundleReport {
+            objects,
+            receipts_imported,
+            refs_updated,
+            notes_merged,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wll_store::EntryMode;
+    use wll_types::IdentityMaterial;
+
+    use crate::CommitProposal;
+
+    fn worldline() -> WorldlineId {
+        WorldlineId::derive(&IdentityMaterial::GenesisHash([4; 32]))
+    }
+
+    fn repo() -> Wll {
+        Wll::init_with_worldline(worldline()).unwrap()
+    }
+
+    fn commit_file(wll: &Wll, path: &str, content: &[u8]) -> wll_types::ObjectId {
+        let mut index = wll.new_index();
+        index.stage_file(path, content, EntryMode::Regular).unwrap();
+        wll.commit_tree(&mut index, CommitProposal::new(path))
+            .unwrap()
+            .tree
+            .unwrap()
+    }
+
+    #[test]
+    fn unbundle_reproduces_history_in_another_repository() {
+        let source = repo();
+        let tree = commit_file(&source, "a.txt", b"alpha");
+        source.commit(CommitProposal::new("note")).unwrap();
+
+        let mut bundle = source.create_bundle(&BundleOptions::new()).unwrap();
+        let key = SigningKey::from_bytes([7; 32]);
+        bundle.sign(&key).unwrap();
+        let bundle = Bundle::from_bytes(&bundle.to_bytes().unwrap()).unwrap();
+        bundle.verify_signer(&[key.verifying_key()]).unwrap();
+
+        let target = repo();
+        let summary = target.verify_bundle(&bundle).unwrap();
+        assert_eq!((summary.from_seq, summary.to_seq), (1, 4));
+        assert_eq!(summary.new_receipts, 4);
+        assert_eq!(summary.refs[0].name, "refs/heads/main");
+
+        let report = target.unbundle(bundle).unwrap();
+        assert_eq!(report.receipts_imported, 4);
+        assert_eq!(report.refs_updated, vec!["refs/heads/main".to_string()]);
+        assert!(target.read_tree(&tree).is_ok());
+        assert_eq!(
+            target.ref_tip("refs/heads/main").unwrap(),
+            source.ref_tip("refs/heads/main").unwrap()
+        );
+        assert!(target.verify().unwrap().is_valid());
+    }
+
+    #[test]
+    fn incremental_bundle_needs_its_prerequisite() {
+        let source = repo();
+        source.commit(CommitProposal::new("first")).unwrap();
+        let target = repo();
+        target
+            .unbundle(source.create_bundle(&BundleOptions::new()).unwrap())
+            .unwrap();
+        commit_file(&source, "b.txt", b"beta");
+
+        let options = BundleOptions::new().with_range(Some(3), None);
+        let bundle = source.create_bundle(&options).unwrap();
+        assert_eq!(bundle.receipts.len(), 2);
+
+        let err = repo().verify_bundle(&bundle).unwrap_err();
+        assert!(err.to_string().contains("requires receipt 2"), "{err}");
+
+        let report = target.unbundle(bundle).unwrap();
+        assert_eq!(report.receipts_imported, 2);
+        assert_eq!(target.receipt_count().unwrap(), 4);
+    }
+
+    #[test]
+    fn damaged_or_untrusted_bundles_are_rejected() {
+        let source = repo();
+        source.commit(CommitProposal::new("first")).unwrap();
+        let mut bundle = source.create_bundle(&BundleOptions::new()).unwrap();
+        assert!(bundle.verify_signer(&[]).is_err());
+
+        bundle.sign(&SigningKey::from_bytes([1; 32])).unwrap();
+        let bytes = bundle.to_bytes().unwrap();
+        let stranger = SigningKey::from_bytes([2; 32]).verifying_key();
+        let err = Bundle::from_bytes(&bytes)
+            .unwrap()
+            .verify_signer(&[stranger]);
+        assert!(matches!(err, Err(SdkError::Bundle(_))));
+
+        let mut flipped = bytes.clone();
+        flipped[20] ^= 1;
+        assert!(matches!(
+            Bundle::from_bytes(&flipped),
+            Err(SdkError::Bundle(_))
+        ));
+        let mut forged = bytes.clone();
+        let last = forged.len() - 1;
+        forged[last] ^= 1;
+        assert!(Bundle::from_bytes(&forged).is_err());
+        assert!(Bundle::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        let foreign = Wll::init().unwrap();
+        assert!(foreign.verify_bundle(&bundle).is_err());
+    }
+
+    #[test]
+    fn named_branch_must_fit_the_range() {
+        let source = repo();
+        source.commit(CommitProposal::new("first")).unwrap();
+        source.commit(CommitProposal::new("second")).unwrap();
+        let options = BundleOptions::new()
+            .with_branches(vec!["main".into()])
+            .with_range(None, Some(2));
+        assert!(source.create_bundle(&options).is_err());
+
+        let bundle = source
+            .create_bundle(&BundleOptions::new().with_range(None, Some(2)))
+            .unwrap();
+        assert!(bundle.refs.is_empty());
+        assert_eq!(bundle.receipts.len(), 2);
+    }
+}
@@ -1,15 +1,36 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
-use wll_types::{CommitmentClass, ObjectId};
+use wll_crypto::EnvelopePublicKey;
+use wll_types::{CommitmentClass, EvidenceAttachment, ObjectId, Reversibility};
+use wll_diff::TreeDiff;
+use wll_gate::{ConventionalIntent, GateResult};
 use wll_ledger::{CommitmentReceipt, OutcomeReceipt};
 
+/// Outcome metadata key holding how reversible a commit's effects are.
+pub(crate) const REVERSIBILITY_KEY: &str = "reversibility";
+
 /// Simplified commit proposal for SDK users.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CommitProposal {
     pub message: String,
     pub intent: Option<String>,
     pub class: Option<CommitmentClass>,
     pub evidence: Vec<String>,
+    /// Evidence artifacts stored in the repository, from
+    /// [`Wll::attach_evidence`](crate::Wll::attach_evidence).
+    pub attachments: Vec<EvidenceAttachment>,
     pub tree: Option<ObjectId>,
+    /// Paths or object references this commit affects.
+    pub targets: Vec<String>,
+    /// Capabilities claimed for this commit, checked by the gate.
+    pub capabilities: Vec<String>,
+    /// Free-form metadata recorded on the outcome receipt.
+    pub metadata: BTreeMap<String, String>,
+    /// Keys the intent and state are sealed to; none records them in the
+    /// clear. See [`crate::sealed`].
+    #[serde(default)]
+    pub recipients: Vec<EnvelopePublicKey>,
 }
 
 impl CommitProposal {
@@ -19,10 +40,22 @@ impl CommitProposal {
             intent: None,
             class: None,
             evidence: Vec::new(),
+            attachments: Vec::new(),
             tree: None,
+            targets: Vec::new(),
+            capabilities: Vec::new(),
+            metadata: BTreeMap::new(),
+            recipients: Vec::new(),
         }
     }
 
+    /// A proposal whose message is in conventional form,
+    /// `kind(scope): subject`, as `[gate.intent]` conventions expect.
+    pub fn conventional(kind: &str, scope: Option<&str>, subject: &str) -> Self {
+        let intent = ConventionalIntent { kind, scope, breaking: false, subject };
+        Self::new(intent.to_string())
+    }
+
     pub fn with_intent(mut self, intent: impl Into<String>) -> Self {
         self.intent = Some(intent.into());
         self
@@ -38,11 +71,50 @@ impl CommitProposal {
         self
     }
 
+    /// Reference an issue, such as `PROJ-42` or `#123`, as `issue://`
+    /// evidence.
+    pub fn with_issue(self, issue: &str) -> Self {
+        self.with_evidence(format!("issue://{}", issue.trim_start_matches('#')))
+    }
+
+    pub fn with_attachment(mut self, attachment: EvidenceAttachment) -> Self {
+        self.attachments.push(attachment);
+        self
+    }
+
     pub fn with_tree(mut self, tree: ObjectId) -> Self {
         self.tree = Some(tree);
         self
     }
 
+    pub fn with_target(mut self, target: impl Into<String>) -> Self {
+        self.targets.push(target.into());
+        self
+    }
+
+    pub fn with_capability(mut self, capability: impl Into<String>) -> Self {
+        self.capabilities.push(capability.into());
+        self
+    }
+
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// Record how reversible the commit's effects are, in the outcome
+    /// metadata. Impact reports rank irreversible descendants first.
+    pub fn with_reversibility(self, reversibility: Reversibility) -> Self {
+        self.with_metadata(REVERSIBILITY_KEY, reversibility.to_string())
+    }
+
+    /// Seal the intent and state to `key`, so only holders of its secret
+    /// key can read them. Repeat for more recipients.
+    pub fn with_recipient(mut self, key: EnvelopePublicKey) -> Self {
+        self.recipients.push(key);
+        self
+    }
+
     pub fn effective_intent(&self) -> &str {
         self.intent.as_deref().unwrap_or(&self.message)
     }
@@ -58,6 +130,12 @@ pub struct CommitResult {
     pub commitment_receipt: CommitmentReceipt,
     pub outcome_receipt: OutcomeReceipt,
     pub receipt_hash: [u8; 32],
+    /// The gate evaluation that accepted this commit.
+    pub gate_result: GateResult,
+    /// Root tree captured by this commit, if any.
+    pub tree: Option<ObjectId>,
+    /// Changes against the previous head tree, for tree commits.
+    pub tree_diff: Option<TreeDiff>,
 }
 
 /// Summary of a receipt for log display.
@@ -87,6 +165,24 @@ mod tests {
         assert_eq!(p.evidence, vec!["issue://1"]);
     }
 
+    #[test]
+    fn conventional_proposals_reference_issues() {
+        let p = CommitProposal::conventional("fix", Some("store"), "handle empty packs")
+            .with_issue("#42");
+        assert_eq!(p.message, "fix(store): handle empty packs");
+        assert_eq!(p.evidence, vec!["issue://42"]);
+        assert_eq!(CommitProposal::conventional("docs", None, "typo").message, "docs: typo");
+    }
+
+    #[test]
+    fn proposal_targets_and_capabilities() {
+        let p = CommitProposal::new("scoped")
+            .with_target("src/lib.rs")
+            .with_capability("write");
+        assert_eq!(p.targets, vec!["src/lib.rs"]);
+        assert_eq!(p.capabilities, vec!["write"]);
+    }
+
     #[test]
     fn effective_intent_fallback() {
         let p = CommitProposal::new("fallback message");
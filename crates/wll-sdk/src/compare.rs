@@ -0,0 +1,148 @@
+//! Comparing the histories behind two revisions, for release notes and
+//! branch review.
+//!
+//! [`Wll::diff_refs`] works like git's left-right log: it splits the
+//! receipts reachable from either revision into those only the left one
+//! reaches and those only the right one reaches, lists the commitments
+//! among them, and diffs the trees the two revisions last recorded.
+
+use serde::Serialize;
+use wll_diff::{diff_trees, TreeDiff};
+use wll_ledger::Receipt;
+use wll_types::{CommitmentClass, ObjectId};
+
+use crate::error::{SdkError, SdkResult};
+use crate::repository::Wll;
+
+/// A commitment that only one side of a [`RefDiff`] reaches.
+#[derive(Clone, Debug, Serialize)]
+pub struct UniqueCommitment {
+    pub seq: u64,
+    pub receipt_hash: [u8; 32],
+    pub intent: String,
+    pub class: CommitmentClass,
+    pub accepted: bool,
+    pub timestamp_ms: u64,
+}
+
+/// How the histories of two revisions differ.
+#[derive(Clone, Debug, Serialize)]
+pub struct RefDiff {
+    /// The receipt the left revision resolved to.
+    pub left: [u8; 32],
+    /// The receipt the right revision resolved to.
+    pub right: [u8; 32],
+    /// The newest receipt both sides reach, if they share history.
+    pub merge_base: Option<[u8; 32]>,
+    /// Receipts only the right side reaches.
+    pub ahead: usize,
+    /// Receipts only the left side reaches.
+    pub behind: usize,
+    /// Commitments only the left side reaches, newest first.
+    pub left_only: Vec<UniqueCommitment>,
+    /// Commitments only the right side reaches, newest first.
+    pub right_only: Vec<UniqueCommitment>,
+    /// Changes from the left side's tree to the right side's, when the
+    /// right side has recorded a tree.
+    pub tree_diff: Option<TreeDiff>,
+}
+
+impl RefDiff {
+    /// Whether both revisions reach the same receipts.
+    pub fn is_even(&self) -> bool {
+        self.ahead == 0 && self.behind == 0
+    }
+}
+
+impl Wll {
+    /// Compare the histories behind revisions `left` and `right`, each
+    /// accepted by [`resolve`](Self::resolve).
+    ///
+    /// `ahead` and `behind` count the receipts of `right` relative to
+    /// `left`, as `git rev-list --left-right --count left...right` does
+    /// for commits.
+    pub fn diff_refs(&self, left: &str, right: &str) -> SdkResult<RefDiff> {
+        let (left, right) = (self.resolve(left)?, self.resolve(right)?);
+        let (left_id, right_id) = (ObjectId::from_hash(left), ObjectId::from_hash(right));
+        let (behind, ahead) = {
+            let graph = self.read_graph()?;
+            for id in [&left_id, &right_id] {
+                if !graph.contains(id) {
+                    return Err(SdkError::ObjectNotFound(id.to_hex()));
+                }
+            }
+            (graph.range(&right_id, &left_id), graph.range(&left_id, &right_id))
+        };
+
+        let tree_diff = match self.tree_at(right)? {
+            Some(tree) => Some(diff_trees(self.store(), self.tree_at(left)?.as_ref(), &tree)?),
+            None => None,
+        };
+        Ok(RefDiff {
+            left,
+            right,
+            merge_base: self.merge_base(&left, &right)?,
+            ahead: ahead.len(),
+            behind: behind.len(),
+            left_only: self.unique_commitments(&behind)?,
+            right_only: self.unique_commitments(&ahead)?,
+            tree_diff,
+        })
+    }
+
+    /// The commitments among `receipts`, in the same order.
+    fn unique_commitments(&self, receipts: &[ObjectId]) -> SdkResult<Vec<UniqueCommitment>> {
+        let mut commitments = Vec::new();
+        for id in receipts {
+            if let Some(Receipt::Commitment(c)) = self.ledger().get_by_hash(*id.as_bytes())? {
+                commitments.push(UniqueCommitment {
+                    seq: c.seq,
+                    receipt_hash: c.receipt_hash,
+                    accepted: c.decision.is_accepted(),
+                    timestamp_ms: c.timestamp.physical_ms,
+                    intent: c.intent,
+                    class: c.class,
+                });
+            }
+        }
+        Ok(commitments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wll_diff::TreeChange;
+    use wll_store::{EntryMode, TreeEntry};
+
+    use super::*;
+    use crate::commit::CommitProposal;
+
+    #[test]
+    fn branch_ahead_of_release_lists_its_commitments_and_changes() {
+        let wll = Wll::init().unwrap();
+        let blob = wll.write_blob(b"v1\n").unwrap();
+        let v1 = wll.write_tree(vec![TreeEntry::new(EntryMode::Regular, "a.txt", blob)]).unwrap();
+        wll.commit(CommitProposal::new("release 1").with_tree(v1)).unwrap();
+        wll.create_branch("release").unwrap();
+
+        let blob = wll.write_blob(b"v2\n").unwrap();
+        let v2 = wll.write_tree(vec![TreeEntry::new(EntryMode::Regular, "a.txt", blob)]).unwrap();
+        wll.commit(CommitProposal::new("fix: bump a").with_tree(v2)).unwrap();
+        wll.commit(CommitProposal::new("docs: note")).unwrap();
+
+        let diff = wll.diff_refs("release", "main").unwrap();
+        assert_eq!((diff.ahead, diff.behind), (4, 0));
+        assert_eq!(diff.merge_base, Some(diff.left));
+        let intents: Vec<_> = diff.right_only.iter().map(|c| c.intent.as_str()).collect();
+        assert_eq!(intents, ["docs: note", "fix: bump a"]);
+        assert_eq!(diff.right_only[0].class, CommitmentClass::ContentUpdate);
+        assert!(diff.left_only.is_empty());
+        let changes = diff.tree_diff.unwrap().changes;
+        assert!(matches!(&changes[..], [TreeChange::Modified { path, .. }] if path == "a.txt"));
+
+        let reverse = wll.diff_refs("main", "release").unwrap();
+        assert_eq!((reverse.ahead, reverse.behind), (0, 4));
+        assert_eq!(reverse.left_only.len(), 2);
+        assert!(wll.diff_refs("main", "HEAD").unwrap().is_even());
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use wll_config::RemoteSettings;
+use wll_sync::RemoteConfig;
+use wll_types::{HashAlgo, WorldlineId};
+
+use crate::error::{SdkError, SdkResult};
+
+/// Name of the repository metadata directory.
+pub const WLL_DIR: &str = ".wll";
+/// Current on-disk repository layout version.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Repository configuration persisted in `.wll/config`.
+///
+/// This covers the `[core]` and `[remote.*]` tables the repository needs to
+/// open. Other settings in the same file (`[user]`, `[gate]`, ...) are read
+/// through [`wll_config::LayeredConfig`] and left untouched by [`Self::save`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RepoConfig {
+    /// Layout version of the `.wll` directory.
+    pub format_version: u32,
+    /// Worldline whose receipt stream this repository records.
+    pub worldline: WorldlineId,
+    /// Hash algorithm used for new receipts.
+    pub hash_algo: HashAlgo,
+    /// HLC node id stamped into new receipts.
+    pub node_id: u16,
+    /// Configured remotes, in name order.
+    pub remotes: Vec<RemoteConfig>,
+}
+
+/// TOML shape of the config file; ids and algorithms are kept as strings.
+#[derive(Serialize, Deserialize)]
+struct ConfigFile {
+    core: CoreSection,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    remote: BTreeMap<String, RemoteSettings>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CoreSection {
+    format_version: u32,
+    worldline: String,
+    #[serde(default = "default_hash_algo")]
+    hash_algo: String,
+    #[serde(default)]
+    node_id: u16,
+}
+
+fn default_hash_algo() -> String {
+    HashAlgo::default().name().to_string()
+}
+
+impl RepoConfig {
+    /// Default configuration for a new repository on `worldline`.
+    pub fn new(worldline: WorldlineId) -> Self {
+        Self {
+            format_version: FORMAT_VERSION,
+            worldline,
+            hash_algo: HashAlgo::default(),
+            node_id: 0,
+            remotes: Vec::new(),
+        }
+    }
+
+    /// The remote called `name`, if configured.
+    pub fn remote(&self, name: &str) -> Option<&RemoteConfig> {
+        self.remotes.iter().find(|r| r.name == name)
+    }
+
+    /// Render the config as TOML.
+    pub fn to_toml(&self) -> SdkResult<String> {
+        toml::to_string(&self.to_file()).map_err(|e| SdkError::Config(e.to_string()))
+    }
+
+    fn to_file(&self) -> ConfigFile {
+        ConfigFile {
+            core: CoreSection {
+                format_version: self.format_version,
+                worldline: self.worldline.to_hex(),
+                hash_algo: self.hash_algo.name().to_string(),
+                node_id: self.node_id,
+            },
+            remote: self
+                .remotes
+                .iter()
+                .map(|r| (r.name.clone(), r.to_settings()))
+                .collect(),
+        }
+    }
+
+    /// Parse a config from TOML text.
+    pub fn from_toml(text: &str) -> SdkResult<Self> {
+        let file: ConfigFile = toml::from_str(text).map_err(|e| SdkError::Config(e.to_string()))?;
+        let core = file.core;
+        if core.format_version > FORMAT_VERSION {
+            return Err(SdkError::Config(format!(
+                "repository format version {} is newer than supported version {FORMAT_VERSION}",
+                core.format_version
+            )));
+        }
+        let worldline = WorldlineId::from_hex(&core.worldline)
+            .map_err(|e| SdkError::Config(format!("core.worldline: {e}")))?;
+        let hash_algo = core
+            .hash_algo
+            .parse()
+            .map_err(|e| SdkError::Config(format!("core.hash_algo: {e}")))?;
+        let remotes = file
+            .remote
+            .into_iter()
+            .map(|(name, settings)| {
+                RemoteConfig::from_settings(&name, &settings)
+                    .map_err(|e| SdkError::Config(e.to_string()))
+            })
+            .collect::<SdkResult<_>>()?;
+        Ok(Self {
+            format_version: core.format_version,
+            worldline,
+            hash_algo,
+            node_id: core.node_id,
+            remotes,
+        })
+    }
+
+    /// Read the config file at `path`.
+    pub fn load(path: &Path) -> SdkResult<Self> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+
+    /// Write the config file to `path`, keeping any tables other than
+    /// `[core]` and `[remote.*]` already in it.
+    pub fn save(&self, path: &Path) -> SdkResult<()> {
+        let mut table = match std::fs::read_to_string(path) {
+            Ok(text) => text
+                .parse::<toml::Table>()
+                .map_err(|e| SdkError::Config(e.to_string()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => toml::Table::new(),
+            Err(e) => return Err(e.into()),
+        };
+        table.remove("remote");
+        let own = toml::Table::try_from(self.to_file()).map_err(|e| SdkError::Config(e.to_string()))?;
+        table.extend(own);
+        let text = toml::to_string(&table).map_err(|e| SdkError::Config(e.to_string()))?;
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wll_types::IdentityMaterial;
+
+    #[test]
+    fn toml_roundtrip() {
+        let mut config =
+            RepoConfig::new(WorldlineId::derive(&IdentityMaterial::GenesisHash([3; 32])));
+        config.hash_algo = HashAlgo::Sha256;
+        config.node_id = 7;
+        config
+            .remotes
+            .push(RemoteConfig::new("origin", "wll://example.com/repo"));
+
+        let text = config.to_toml().unwrap();
+        assert!(text.contains("[core]"));
+        assert!(text.contains("[remote.origin]"));
+        assert_eq!(RepoConfig::from_toml(&text).unwrap(), config);
+    }
+
+    #[test]
+    fn newer_format_is_rejected() {
+        let wid = WorldlineId::derive(&IdentityMaterial::GenesisHash([3; 32]));
+        let text = format!(
+            "[core]\nformat_version = {}\nworldline = \"{}\"\n",
+            FORMAT_VERSION + 1,
+            wid.to_hex()
+        );
+        assert!(matches!(
+            RepoConfig::from_toml(&text),
+            Err(SdkError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn remote_sections_parse_fetch_refspecs() {
+        let wid = WorldlineId::derive(&IdentityMaterial::GenesisHash([3; 32]));
+        let text = format!(
+            "[core]\nformat_version = 1\nworldline = \"{}\"\n\n\
+             [remote.upstream]\nurl = \"wll://u\"\nfetch = [\"refs/heads/main:refs/remotes/upstream/main\"]\n\n\
+             [remote.origin]\nurl = \"wll://o\"\n",
+            wid.to_hex()
+        );
+        let config = RepoConfig::from_toml(&text).unwrap();
+        let names: Vec<&str> = config.remotes.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["origin", "upstream"]);
+        assert_eq!(config.remote("origin").unwrap().fetch[0].dst, "refs/remotes/origin/*");
+        assert_eq!(config.remote("upstream").unwrap().fetch.len(), 1);
+
+        let bad = text.replace("[remote.origin]", "[remote.\"a/b\"]");
+        assert!(matches!(RepoConfig::from_toml(&bad), Err(SdkError::Config(_))));
+    }
+
+    #[test]
+    fn save_keeps_other_tables() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config");
+        let mut config =
+            RepoConfig::new(WorldlineId::derive(&IdentityMaterial::GenesisHash([3; 32])));
+        config.remotes.push(RemoteConfig::new("origin", "wll://o"));
+        config.save(&path).unwrap();
+
+        let mut text = std::fs::read_to_string(&path).unwrap();
+        text.push_str("\n[user]\nname = \"Ada\"\n");
+        std::fs::write(&path, text).unwrap();
+
+        config.remotes.clear();
+        config.save(&path).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.contains("[user]"));
+        assert!(!text.contains("[remote"));
+        assert_eq!(RepoConfig::load(&path).unwrap(), config);
+    }
+}
@@ -0,0 +1,259 @@
+//! Commitments whose outcomes are recorded later.
+//!
+//! [`Wll::commit`] records a commitment and its outcome together, which
+//! suits work done before committing. Work done after the decision, often
+//! by another process, is split in two: [`Wll::propose`] runs the proposal
+//! through the hooks and the gate and records only the commitment, and
+//! [`Wll::record_outcome`] records the outcome once the work is done and
+//! moves the current branch to it. Until then the commitment is listed by
+//! [`Wll::pending_commitments`].
+//!
+//! An [`OutcomeTimeout`] bounds the wait: a commitment pending for longer
+//! is given a failure outcome, which changes no state and names the reason
+//! under [`OUTCOME_FAILURE_KEY`]. Expired commitments are failed when the
+//! next one is proposed, when an outcome is recorded for one of them, and
+//! by [`Wll::expire_pending`].
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use wll_config::OutcomeSettings;
+use wll_ledger::{CommitmentReceipt, OutcomeReceipt, OutcomeRecord, ProjectionBuilder, Receipt};
+use wll_types::CommitmentId;
+
+use crate::commit::CommitProposal;
+use crate::error::{SdkError, SdkResult};
+use crate::hooks::{HookPayload, HookPoint};
+use crate::repository::Wll;
+use crate::sealed::sealed_recipients;
+
+/// Outcome metadata key naming why the work of a commitment failed.
+pub const OUTCOME_FAILURE_KEY: &str = "failure_reason";
+
+/// An accepted commitment whose outcome is still to be recorded.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PendingCommitment {
+    /// Hash of the commitment receipt, which the outcome refers to.
+    pub receipt_hash: [u8; 32],
+    pub commitment: CommitmentReceipt,
+    /// The outcome [`commit`](Wll::commit) would have recorded: the
+    /// message, the tree and its changes, and the proposal's metadata.
+    /// Add what the work did and pass it to
+    /// [`record_outcome`](Wll::record_outcome).
+    pub outcome: OutcomeRecord,
+}
+
+impl PendingCommitment {
+    pub fn commitment_id(&self) -> &CommitmentId {
+        &self.commitment.commitment_id
+    }
+}
+
+impl From<&PendingCommitment> for [u8; 32] {
+    fn from(pending: &PendingCommitment) -> Self {
+        pending.receipt_hash
+    }
+}
+
+impl From<PendingCommitment> for [u8; 32] {
+    fn from(pending: PendingCommitment) -> Self {
+        pending.receipt_hash
+    }
+}
+
+/// How long a commitment may wait for its outcome.
+///
+/// The default sets no limit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutcomeTimeout {
+    pub after: Option<Duration>,
+}
+
+impl OutcomeTimeout {
+    /// Wait forever.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail commitments still pending `after` they were recorded.
+    pub fn after(after: Duration) -> Self {
+        Self { after: Some(after) }
+    }
+
+    /// The timeout configured in `[outcomes]`.
+    pub fn from_settings(settings: &OutcomeSettings) -> Self {
+        Self {
+            after: settings.timeout_secs.map(Duration::from_secs),
+        }
+    }
+
+    /// Whether `commitment` has waited too long at `now_ms`.
+    pub fn is_expired(&self, commitment: &CommitmentReceipt, now_ms: u64) -> bool {
+        self.after.is_some_and(|after| {
+            let waited = now_ms.saturating_sub(commitment.timestamp.physical_ms);
+            u128::from(waited) >= after.as_millis()
+        })
+    }
+}
+
+/// An outcome recording that the work of a commitment failed for `reason`.
+pub fn failure_outcome(reason: impl Into<String>) -> OutcomeRecord {
+    OutcomeRecord {
+        effects: vec![],
+        proofs: vec![],
+        state_updates: vec![],
+        metadata: BTreeMap::from([(OUTCOME_FAILURE_KEY.to_string(), reason.into())]),
+    }
+}
+
+impl Wll {
+    /// Run `proposal` through the pre-commit hooks and the gate and record
+    /// the commitment, leaving the outcome to
+    /// [`record_outcome`](Self::record_outcome).
+    ///
+    /// A rejection is recorded and returned as by [`commit`](Self::commit).
+    /// Commitments past the [outcome timeout](Self::outcome_timeout) are
+    /// failed first.
+    pub fn propose(&self, proposal: CommitProposal) -> SdkResult<PendingCommitment> {
+        self.expire_pending(self.outcome_timeout())?;
+        let gated = self.gate_commit(proposal, Vec::new(), CommitmentId::new())?;
+        let commitment = self.append_gated(&gated)?;
+        self.record_provenance(&[Receipt::Commitment(commitment.clone())])?;
+        self.emit_decision(&commitment)?;
+        Ok(PendingCommitment {
+            receipt_hash: commitment.receipt_hash,
+            commitment,
+            outcome: gated.outcome_record(),
+        })
+    }
+
+    /// Record `outcome` for a pending commitment, given as a
+    /// [`PendingCommitment`] or its receipt hash, and move the current
+    /// branch to it. The post-commit hooks run as for a commit, and the
+    /// state of a [sealed](crate::sealed) commitment is sealed to the same
+    /// recipients.
+    ///
+    /// Fails if the commitment is not pending: unknown, rejected, already
+    /// given an outcome, or failed by the outcome timeout, which this call
+    /// applies first.
+    pub fn record_outcome(
+        &self,
+        commitment: impl Into<[u8; 32]>,
+        mut outcome: OutcomeRecord,
+    ) -> SdkResult<OutcomeReceipt> {
+        let hash = commitment.into();
+        self.expire_pending(self.outcome_timeout())?;
+        let pending = self.pending_commitments()?;
+        let Some(commitment) = pending.iter().find(|c| c.receipt_hash == hash) else {
+            return Err(match self.ledger().get_by_hash(hash)? {
+                None => SdkError::ObjectNotFound(hex::encode(hash)),
+                Some(_) => SdkError::InvalidOperation(format!(
+                    "commitment {} is not waiting for an outcome",
+                    hex::encode(hash)
+                )),
+            });
+        };
+        let recipients = sealed_recipients(&commitment.intent)?;
+        self.seal_state(&mut outcome.state_updates, &recipients)?;
+        let receipt = self.append_outcome(hash, &outcome)?;
+
+        let branch = self.current_branch()?;
+        let mut payload = HookPayload::new(HookPoint::PostCommit, self.worldline().clone(), branch);
+        payload.receipt_hash = Some(hex::encode(receipt.receipt_hash));
+        if let Some(reason) = self.hooks().run(&payload) {
+            tracing::warn!("post-commit hook failed: {reason}");
+        }
+        Ok(receipt)
+    }
+
+    /// Accepted commitments of this worldline without an outcome, oldest
+    /// first.
+    pub fn pending_commitments(&self) -> SdkResult<Vec<CommitmentReceipt>> {
+        Ok(ProjectionBuilder::pending_commitments(self.ledger(), self.worldline())?.pending)
+    }
+
+    /// Record a failure outcome for every commitment pending longer than
+    /// `timeout` allows, returning those outcomes.
+    pub fn expire_pending(&self, timeout: &OutcomeTimeout) -> SdkResult<Vec<OutcomeReceipt>> {
+        let Some(after) = timeout.after else {
+            return Ok(Vec::new());
+        };
+        let now_ms = self.clock().now_ms();
+        let reason = format!("no outcome within {}s", after.as_secs());
+        self.pending_commitments()?
+            .iter()
+            .filter(|c| timeout.is_expired(c, now_ms))
+            .map(|c| self.append_outcome(c.receipt_hash, &failure_outcome(reason.clone())))
+            .collect()
+    }
+
+    /// Append `outcome` for the commitment `hash`, record its provenance,
+    /// and move the current branch to it.
+    fn append_outcome(&self, hash: [u8; 32], outcome: &OutcomeRecord) -> SdkResult<OutcomeReceipt> {
+        let receipt = self.ledger().append_outcome(hash, outcome)?;
+        self.record_provenance(&[Receipt::Outcome(receipt.clone())])?;
+        let ref_update = self.advance_branch(&self.current_branch()?, receipt.receipt_hash)?;
+        self.emit_commit_events(None, &receipt, Some(ref_update))?;
+        Ok(receipt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::Value;
+    use wll_ledger::StateUpdate;
+    use wll_types::ManualClock;
+
+    use super::*;
+    use crate::builder::WllBuilder;
+
+    #[test]
+    fn outcomes_recorded_later_complete_their_commitments() {
+        let wll = Wll::init().unwrap();
+        let pending = wll.propose(CommitProposal::new("deploy v2")).unwrap();
+        assert_eq!(wll.pending_commitments().unwrap(), std::slice::from_ref(&pending.commitment));
+        assert!(wll.latest_state().unwrap().get("message").is_none());
+
+        let mut outcome = pending.outcome.clone();
+        outcome.state_updates.push(StateUpdate::new("deployed", Value::from("v2")));
+        let receipt = wll.record_outcome(&pending, outcome.clone()).unwrap();
+        assert_eq!(receipt.commitment_receipt_hash, pending.receipt_hash);
+        assert!(wll.pending_commitments().unwrap().is_empty());
+
+        let state = wll.latest_state().unwrap();
+        assert_eq!(state.get("message"), Some(&Value::from("deploy v2")));
+        assert_eq!(state.get("deployed"), Some(&Value::from("v2")));
+        assert_eq!(wll.log(1).unwrap()[0].receipt_hash, receipt.receipt_hash);
+        assert!(wll.verify().unwrap().is_valid());
+
+        let err = wll.record_outcome(pending.receipt_hash, outcome).unwrap_err();
+        assert!(matches!(err, SdkError::InvalidOperation(_)));
+    }
+
+    #[test]
+    fn commitments_past_the_timeout_are_failed() {
+        let clock = Arc::new(ManualClock::new(1_700_000_000_000));
+        let wll = WllBuilder::new()
+            .with_clock(clock.clone())
+            .build()
+            .unwrap()
+            .with_outcome_timeout(OutcomeTimeout::after(Duration::from_secs(60)));
+        let stale = wll.propose(CommitProposal::new("slow job")).unwrap();
+        clock.advance_ms(30_000);
+        assert!(wll.expire_pending(wll.outcome_timeout()).unwrap().is_empty());
+
+        clock.advance_ms(30_000);
+        let fresh = wll.propose(CommitProposal::new("next job")).unwrap();
+        assert_eq!(wll.pending_commitments().unwrap(), [fresh.commitment]);
+
+        let receipts = wll.ledger().read_all(wll.worldline()).unwrap();
+        let failed = receipts.iter().find_map(Receipt::as_outcome).unwrap();
+        assert_eq!(failed.commitment_receipt_hash, stale.receipt_hash);
+        assert_eq!(failed.metadata[OUTCOME_FAILURE_KEY], "no outcome within 60s");
+        let err = wll.record_outcome(&stale, stale.outcome.clone()).unwrap_err();
+        assert!(matches!(err, SdkError::InvalidOperation(_)));
+    }
+}
@@ -1,4 +1,5 @@
 use thiserror::Error;
+use wll_types::{Coded, ErrorCode};
 
 #[derive(Debug, Error)]
 pub enum SdkError {
@@ -14,8 +15,17 @@ pub enum SdkError {
     #[error("invalid operation: {0}")]
     InvalidOperation(String),
 
-    #[error("commitment rejected: {0}")]
-    CommitmentRejected(String),
+    /// The policy gate declined the commit. The rejected commitment and its
+    /// rejection outcome are still recorded; `receipt_hash` is the outcome.
+    #[error("commitment rejected{}: {reason}", stage.as_ref().map(|s| format!(" by {s} stage")).unwrap_or_default())]
+    CommitmentRejected {
+        reason: String,
+        stage: Option<String>,
+        receipt_hash: [u8; 32],
+    },
+
+    #[error("gate error: {0}")]
+    Gate(#[from] wll_gate::GateError),
 
     #[error("store error: {0}")]
     Store(#[from] wll_store::StoreError),
@@ -26,8 +36,102 @@ pub enum SdkError {
     #[error("ref error: {0}")]
     Ref(#[from] wll_refs::RefError),
 
+    #[error("index error: {0}")]
+    Index(#[from] wll_index::IndexError),
+
+    #[error("diff error: {0}")]
+    Diff(#[from] wll_diff::DiffError),
+
+    #[error("pack error: {0}")]
+    Pack(#[from] wll_pack::PackError),
+
+    #[error("sync error: {0}")]
+    Sync(#[from] wll_sync::SyncError),
+
+    #[error("fabric error: {0}")]
+    Fabric(#[from] wll_fabric::FabricError),
+
+    #[error("DAG error: {0}")]
+    Dag(#[from] wll_dag::DagError),
+
+    /// A sealed intent or state value could not be sealed or opened.
+    #[error("sealed value: {0}")]
+    Envelope(#[from] wll_crypto::EnvelopeError),
+
+    #[error("transparency log error: {0}")]
+    TransparencyLog(String),
+
+    /// A bundle could not be read or failed verification.
+    #[error("invalid bundle: {0}")]
+    Bundle(String),
+
+    #[error("export failed: {0}")]
+    Export(String),
+
+    /// The search index could not be opened, updated or read.
+    #[error("search index error: {0}")]
+    Search(String),
+
+    #[error("invalid search query: {0}")]
+    InvalidQuery(String),
+
+    #[error("unsupported: {0}")]
+    Unsupported(String),
+
+    #[error("config error: {0}")]
+    Config(String),
+
+    /// Reading or writing layered configuration failed.
+    #[error("config error: {0}")]
+    Settings(#[from] wll_config::ConfigError),
+
+    #[error("serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    /// A blocking task spawned by [`AsyncWll`](crate::AsyncWll) panicked or
+    /// was cancelled.
+    #[error("background task failed: {0}")]
+    TaskFailed(#[from] tokio::task::JoinError),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
     #[error("internal error: {0}")]
     Internal(String),
 }
 
+impl Coded for SdkError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::NotInitialized(_) => ErrorCode::RepoNotInitialized,
+            Self::BranchNotFound(_) => ErrorCode::RefNotFound,
+            Self::ObjectNotFound(_) => ErrorCode::ObjectNotFound,
+            Self::InvalidOperation(_) => ErrorCode::InvalidOperation,
+            Self::CommitmentRejected { .. } => ErrorCode::GateRejected,
+            Self::Gate(e) => e.code(),
+            Self::Store(e) => e.code(),
+            Self::Ledger(e) => e.code(),
+            Self::Ref(e) => e.code(),
+            Self::Index(e) => e.code(),
+            Self::Diff(e) => e.code(),
+            Self::Pack(e) => e.code(),
+            Self::Sync(e) => e.code(),
+            Self::Fabric(e) => e.code(),
+            Self::Dag(e) => e.code(),
+            Self::Envelope(e) => e.code(),
+            Self::TransparencyLog(_) => ErrorCode::TransparencyLog,
+            Self::Bundle(_) => ErrorCode::BundleInvalid,
+            Self::Export(_) => ErrorCode::Serialization,
+            Self::Search(_) => ErrorCode::SearchIndex,
+            Self::InvalidQuery(_) => ErrorCode::SearchQuery,
+            Self::Unsupported(_) => ErrorCode::Unsupported,
+            Self::Config(_) => ErrorCode::ConfigInvalid,
+            Self::Settings(e) => e.code(),
+            Self::Serialization(_) => ErrorCode::Serialization,
+            Self::TaskFailed(_) | Self::Internal(_) => ErrorCode::Internal,
+            Self::Io(_) => ErrorCode::Io,
+        }
+    }
+}
+
 pub type SdkResult<T> = Result<T, SdkError>;
@@ -0,0 +1,575 @@
+//! Receipt export for analytics.
+//!
+//! `Wll::export_receipts` streams one worldline's receipts, optionally
+//! limited to a time range, as flat [`ExportRecord`] rows. The rows are
+//! written as JSON Lines, or as Parquet when the crate is built with the
+//! `arrow` feature. Both formats share one column schema; a column that does
+//! not apply to a receipt's kind is null.
+//!
+//! | Column | Type | Kinds |
+//! |---|---|---|
+//! | `worldline` | string (hex) | all |
+//! | `seq` | u64 | all |
+//! | `kind` | string: `commitment`, `outcome`, `snapshot` | all |
+//! | `receipt_hash` | string (hex) | all |
+//! | `prev_hash` | string (hex), null for the first receipt | all |
+//! | `timestamp_ms` | u64, milliseconds since the UNIX epoch | all |
+//! | `timestamp_logical` | u32 | all |
+//! | `commitment_id` | string (UUID) | commitment |
+//! | `class` | string, e.g. `ContentUpdate`, `Custom(deploy)` | commitment |
+//! | `intent` | string | commitment |
+//! | `requested_caps` | list of string | commitment |
+//! | `evidence` | list of string (reference URIs) | commitment |
+//! | `decision` | string: `accepted`, `rejected`, `deferred` | commitment |
+//! | `decision_reason` | string | commitment (rejected, deferred) |
+//! | `policy_hash` | string (hex) | commitment |
+//! | `commitment_receipt_hash` | string (hex) | outcome |
+//! | `outcome_hash` | string (hex) | outcome |
+//! | `accepted` | bool | outcome |
+//! | `effects` | list of struct `{kind, target, description}` | outcome |
+//! | `proofs` | list of string (proof URIs) | outcome |
+//! | `metadata` | map of string to string | outcome |
+//! | `anchored_receipt_hash` | string (hex) | snapshot |
+//! | `state_hash` | string (hex) | snapshot |
+//!
+//! Columns are only ever added to this schema, never renamed or removed.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use serde::{Deserialize, Serialize};
+use wll_ledger::{Decision, EffectSummary, Receipt};
+use wll_types::WorldlineId;
+
+use crate::error::SdkResult;
+use crate::repository::Wll;
+
+/// Receipts read from the ledger, and written, per batch.
+const EXPORT_BATCH: u64 = 1024;
+
+/// File format written by [`Wll::export_receipts`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// One JSON object per line.
+    #[default]
+    Jsonl,
+    /// Apache Parquet, zstd-compressed, one row group per batch. Needs the
+    /// `arrow` feature.
+    Parquet,
+}
+
+/// What [`Wll::export_receipts`] exports.
+#[derive(Clone, Debug, Default)]
+pub struct ExportOptions {
+    /// Worldline to export. Defaults to the repository's.
+    pub worldline: Option<WorldlineId>,
+    /// Earliest receipt timestamp to include, in milliseconds (inclusive).
+    pub since_ms: Option<u64>,
+    /// Latest receipt timestamp to include, in milliseconds (exclusive).
+    pub until_ms: Option<u64>,
+    pub format: ExportFormat,
+}
+
+impl ExportOptions {
+    pub fn new(format: ExportFormat) -> Self {
+        Self { format, ..Self::default() }
+    }
+
+    pub fn with_worldline(mut self, worldline: WorldlineId) -> Self {
+        self.worldline = Some(worldline);
+        self
+    }
+
+    pub fn with_time_range(mut self, since_ms: Option<u64>, until_ms: Option<u64>) -> Self {
+        self.since_ms = since_ms;
+        self.until_ms = until_ms;
+        self
+    }
+
+    fn includes(&self, timestamp_ms: u64) -> bool {
+        self.since_ms.map_or(true, |since| timestamp_ms >= since)
+            && self.until_ms.map_or(true, |until| timestamp_ms < until)
+    }
+}
+
+/// One exported receipt. See the [module docs](self) for the schema.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportRecord {
+    pub worldline: String,
+    pub seq: u64,
+    pub kind: String,
+    pub receipt_hash: String,
+    pub prev_hash: Option<String>,
+    pub timestamp_ms: u64,
+    pub timestamp_logical: u32,
+    pub commitment_id: Option<String>,
+    pub class: Option<String>,
+    pub intent: Option<String>,
+    pub requested_caps: Option<Vec<String>>,
+    pub evidence: Option<Vec<String>>,
+    pub decision: Option<String>,
+    pub decision_reason: Option<String>,
+    pub policy_hash: Option<String>,
+    pub commitment_receipt_hash: Option<String>,
+    pub outcome_hash: Option<String>,
+    pub accepted: Option<bool>,
+    pub effects: Option<Vec<EffectSummary>>,
+    pub proofs: Option<Vec<String>>,
+    pub metadata: Option<BTreeMap<String, String>>,
+    pub anchored_receipt_hash: Option<String>,
+    pub state_hash: Option<String>,
+}
+
+impl From<&Receipt> for ExportRecord {
+    fn from(receipt: &Receipt) -> Self {
+        let timestamp = receipt.timestamp();
+        let mut record = Self {
+            worldline: receipt.worldline().to_hex(),
+            seq: receipt.seq(),
+            kind: receipt.kind().to_string().to_lowercase(),
+            receipt_hash: hex::encode(receipt.receipt_hash()),
+            prev_hash: receipt.prev_hash().map(hex::encode),
+            timestamp_ms: timestamp.physical_ms,
+            timestamp_logical: timestamp.logical,
+            ..Self::default()
+        };
+        match receipt {
+            Receipt::Commitment(c) => {
+                let (decision, reason) = match &c.decision {
+                    Decision::Accepted => ("accepted", None),
+                    Decision::Rejected { reason } => ("rejected", Some(reason.clone())),
+                    Decision::Deferred { reason, .. } => ("deferred", Some(reason.clone())),
+                };
+                record.commitment_id = Some(c.commitment_id.to_string());
+                record.class = Some(c.class.to_string());
+                record.intent = Some(c.intent.clone());
+                record.requested_caps = Some(c.requested_caps.clone());
+                record.evidence = Some(c.evidence.references.clone());
+                record.decision = Some(decision.into());
+                record.decision_reason = reason;
+                record.policy_hash = Some(hex::encode(c.policy_hash));
+            }
+            Receipt::Outcome(o) => {
+                record.commitment_receipt_hash = Some(hex::encode(o.commitment_receipt_hash));
+                record.outcome_hash = Some(hex::encode(o.outcome_hash));
+                record.accepted = Some(o.accepted);
+                record.effects = Some(o.effects.clone());
+                record.proofs = Some(o.proofs.iter().map(|p| p.uri.clone()).collect());
+                record.metadata = Some(o.metadata.clone());
+            }
+            Receipt::Snapshot(s) => {
+                record.anchored_receipt_hash = Some(hex::encode(s.anchored_receipt_hash));
+                record.state_hash = Some(hex::encode(s.state_hash));
+            }
+        }
+        record
+    }
+}
+
+/// What an export wrote.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportSummary {
+    pub worldline: WorldlineId,
+    pub format: ExportFormat,
+    pub receipts: u64,
+    pub first_seq: Option<u64>,
+    pub last_seq: Option<u64>,
+}
+
+/// Destination for batches of rows.
+trait RecordSink {
+    fn write_batch(&mut self, records: &[ExportRecord]) -> SdkResult<()>;
+    fn finish(self) -> SdkResult<()>;
+}
+
+struct JsonlSink<W: Write> {
+    out: W,
+}
+
+impl<W: Write> RecordSink for JsonlSink<W> {
+    fn write_batch(&mut self, records: &[ExportRecord]) -> SdkResult<()> {
+        for record in records {
+            serde_json::to_writer(&mut self.out, record)?;
+            self.out.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> SdkResult<()> {
+        Ok(self.out.flush()?)
+    }
+}
+
+impl Wll {
+    /// Stream a worldline's receipts to `out` in `options.format`, oldest
+    /// first, reading the ledger in batches so memory use does not grow
+    /// with its length.
+    pub fn export_receipts<W: Write + Send>(
+        &self,
+        options: &ExportOptions,
+        out: W,
+    ) -> SdkResult<ExportSummary> {
+        match options.format {
+            ExportFormat::Jsonl => self.export_into(options, JsonlSink { out }),
+            #[cfg(feature = "arrow")]
+            ExportFormat::Parquet => self.export_into(options, parquet_sink::ParquetSink::new(out)?),
+            #[cfg(not(feature = "arrow"))]
+            ExportFormat::Parquet => Err(crate::SdkError::Unsupported(
+                "Parquet export needs wll-sdk built with the `arrow` feature".into(),
+            )),
+        }
+    }
+
+    fn export_into(
+        &self,
+        options: &ExportOptions,
+        mut sink: impl RecordSink,
+    ) -> SdkResult<ExportSummary> {
+        let worldline = options.worldline.clone().unwrap_or_else(|| self.worldline().clone());
+        let mut summary = ExportSummary {
+            worldline: worldline.clone(),
+            format: options.format,
+            receipts: 0,
+            first_seq: None,
+            last_seq: None,
+        };
+        let head = self.ledger().head(&worldline)?.map_or(0, |head| head.seq);
+        let mut from = 1;
+        while from <= head {
+            let to = head.min(from + EXPORT_BATCH - 1);
+            let records: Vec<ExportRecord> = self
+                .ledger()
+                .read_range(&worldline, from, to)?
+                .iter()
+                .filter(|receipt| options.includes(receipt.timestamp().physical_ms))
+                .map(ExportRecord::from)
+                .collect();
+            if let (Some(first), Some(last)) = (records.first(), records.last()) {
+                summary.first_seq.get_or_insert(first.seq);
+                summary.last_seq = Some(last.seq);
+                summary.receipts += records.len() as u64;
+                sink.write_batch(&records)?;
+            }
+            from = to + 1;
+        }
+        sink.finish()?;
+        Ok(summary)
+    }
+}
+
+#[cfg(feature = "arrow")]
+mod parquet_sink {
+    use std::io::Write;
+    use std::sync::Arc;
+
+    use arrow_array::builder::{ListBuilder, MapBuilder, StringBuilder, StructBuilder};
+    use arrow_array::{ArrayRef, BooleanArray, RecordBatch, StringArray, UInt32Array, UInt64Array};
+    use arrow_schema::{DataType, Field, Fields, Schema, SchemaRef};
+    use parquet::arrow::ArrowWriter;
+    use parquet::basic::{Compression, ZstdLevel};
+    use parquet::file::properties::WriterProperties;
+
+    use super::{ExportRecord, RecordSink};
+    use crate::error::{SdkError, SdkResult};
+
+    fn export_error(e: impl std::fmt::Display) -> SdkError {
+        SdkError::Export(e.to_string())
+    }
+
+    fn effect_fields() -> Fields {
+        Fields::from(vec![
+            Field::new("kind", DataType::Utf8, false),
+            Field::new("target", DataType::Utf8, false),
+            Field::new("description", DataType::Utf8, false),
+        ])
+    }
+
+    fn string_list() -> DataType {
+        DataType::List(Arc::new(Field::new("item", DataType::Utf8, false)))
+    }
+
+    fn metadata_map() -> DataType {
+        let entries = Fields::from(vec![
+            Field::new("keys", DataType::Utf8, false),
+            Field::new("values", DataType::Utf8, false),
+        ]);
+        DataType::Map(Arc::new(Field::new("entries", DataType::Struct(entries), false)), false)
+    }
+
+    /// The Arrow form of the schema in the module docs.
+    pub(super) fn schema() -> Schema {
+        let hash = |name: &str, nullable| Field::new(name, DataType::Utf8, nullable);
+        Schema::new(vec![
+            hash("worldline", false),
+            Field::new("seq", DataType::UInt64, false),
+            Field::new("kind", DataType::Utf8, false),
+            hash("receipt_hash", false),
+            hash("prev_hash", true),
+            Field::new("timestamp_ms", DataType::UInt64, false),
+            Field::new("timestamp_logical", DataType::UInt32, false),
+            Field::new("commitment_id", DataType::Utf8, true),
+            Field::new("class", DataType::Utf8, true),
+            Field::new("intent", DataType::Utf8, true),
+            Field::new("requested_caps", string_list(), true),
+            Field::new("evidence", string_list(), true),
+            Field::new("decision", DataType::Utf8, true),
+            Field::new("decision_reason", DataType::Utf8, true),
+            hash("policy_hash", true),
+            hash("commitment_receipt_hash", true),
+            hash("outcome_hash", true),
+            Field::new("accepted", DataType::Boolean, true),
+            Field::new(
+                "effects",
+                DataType::List(Arc::new(Field::new(
+                    "item",
+                    DataType::Struct(effect_fields()),
+                    false,
+                ))),
+                true,
+            ),
+            Field::new("proofs", string_list(), true),
+            Field::new("metadata", metadata_map(), true),
+            hash("anchored_receipt_hash", true),
+            hash("state_hash", true),
+        ])
+    }
+
+    pub(super) struct ParquetSink<W: Write + Send> {
+        writer: ArrowWriter<W>,
+        schema: SchemaRef,
+    }
+
+    impl<W: Write + Send> ParquetSink<W> {
+        pub(super) fn new(out: W) -> SdkResult<Self> {
+            let schema = Arc::new(schema());
+            let props = WriterProperties::builder()
+                .set_compression(Compression::ZSTD(ZstdLevel::default()))
+                .build();
+            let writer =
+                ArrowWriter::try_new(out, schema.clone(), Some(props)).map_err(export_error)?;
+            Ok(Self { writer, schema })
+        }
+    }
+
+    fn strings<'a>(values: impl Iterator<Item = Option<&'a str>>) -> ArrayRef {
+        Arc::new(values.collect::<StringArray>())
+    }
+
+    fn string_lists<'a>(
+        values: impl Iterator<Item = Option<&'a Vec<String>>>,
+    ) -> ArrayRef {
+        let mut builder = ListBuilder::new(StringBuilder::new())
+            .with_field(Arc::new(Field::new("item", DataType::Utf8, false)));
+        for value in values {
+            match value {
+                Some(items) => {
+                    for item in items {
+                        builder.values().append_value(item);
+                    }
+                    builder.append(true);
+                }
+                None => builder.append(false),
+            }
+        }
+        Arc::new(builder.finish())
+    }
+
+    fn effects(records: &[ExportRecord]) -> ArrayRef {
+        let values = StructBuilder::from_fields(effect_fields(), 0);
+        let mut builder = ListBuilder::new(values).with_field(Arc::new(Field::new(
+            "item",
+            DataType::Struct(effect_fields()),
+            false,
+        )));
+        for record in records {
+            match &record.effects {
+                Some(effects) => {
+                    let item = builder.values();
+                    for effect in effects {
+                        for (i, text) in [effect.kind.name(), &effect.target, &effect.description]
+                            .into_iter()
+                            .enumerate()
+                        {
+                            item.field_builder::<StringBuilder>(i)
+                                .expect("effect fields are strings")
+                                .append_value(text);
+                        }
+                        item.append(true);
+                    }
+                    builder.append(true);
+                }
+                None => builder.append(false),
+            }
+        }
+        Arc::new(builder.finish())
+    }
+
+    fn metadata(records: &[ExportRecord]) -> SdkResult<ArrayRef> {
+        let mut builder = MapBuilder::new(None, StringBuilder::new(), StringBuilder::new())
+            .with_values_field(Arc::new(Field::new("values", DataType::Utf8, false)));
+        for record in records {
+            match &record.metadata {
+                Some(map) => {
+                    for (key, value) in map {
+                        builder.keys().append_value(key);
+                        builder.values().append_value(value);
+                    }
+                    builder.append(true).map_err(export_error)?;
+                }
+                None => builder.append(false).map_err(export_error)?,
+            }
+        }
+        Ok(Arc::new(builder.finish()))
+    }
+
+    impl<W: Write + Send> RecordSink for ParquetSink<W> {
+        fn write_batch(&mut self, records: &[ExportRecord]) -> SdkResult<()> {
+            let columns: Vec<ArrayRef> = vec![
+                strings(records.iter().map(|r| Some(r.worldline.as_str()))),
+                Arc::new(records.iter().map(|r| r.seq).collect::<UInt64Array>()),
+                strings(records.iter().map(|r| Some(r.kind.as_str()))),
+                strings(records.iter().map(|r| Some(r.receipt_hash.as_str()))),
+                strings(records.iter().map(|r| r.prev_hash.as_deref())),
+                Arc::new(records.iter().map(|r| r.timestamp_ms).collect::<UInt64Array>()),
+                Arc::new(records.iter().map(|r| r.timestamp_logical).collect::<UInt32Array>()),
+                strings(records.iter().map(|r| r.commitment_id.as_deref())),
+                strings(records.iter().map(|r| r.class.as_deref())),
+                strings(records.iter().map(|r| r.intent.as_deref())),
+                string_lists(records.iter().map(|r| r.requested_caps.as_ref())),
+                string_lists(records.iter().map(|r| r.evidence.as_ref())),
+                strings(records.iter().map(|r| r.decision.as_deref())),
+                strings(records.iter().map(|r| r.decision_reason.as_deref())),
+                strings(records.iter().map(|r| r.policy_hash.as_deref())),
+                strings(records.iter().map(|r| r.commitment_receipt_hash.as_deref())),
+                strings(records.iter().map(|r| r.outcome_hash.as_deref())),
+                Arc::new(records.iter().map(|r| r.accepted).collect::<BooleanArray>()),
+                effects(records),
+                string_lists(records.iter().map(|r| r.proofs.as_ref())),
+                metadata(records)?,
+                strings(records.iter().map(|r| r.anchored_receipt_hash.as_deref())),
+                strings(records.iter().map(|r| r.state_hash.as_deref())),
+            ];
+            let batch = RecordBatch::try_new(self.schema.clone(), columns).map_err(export_error)?;
+            self.writer.write(&batch).map_err(export_error)?;
+            // One row group per batch keeps the writer's buffer bounded.
+            self.writer.flush().map_err(export_error)
+        }
+
+        fn finish(self) -> SdkResult<()> {
+            self.writer.close().map_err(export_error)?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wll_types::IdentityMaterial;
+
+    use crate::CommitProposal;
+
+    fn repo() -> Wll {
+        Wll::init_with_worldline(WorldlineId::derive(&IdentityMaterial::GenesisHash([6; 32])))
+            .unwrap()
+    }
+
+    fn export(wll: &Wll, options: &ExportOptions) -> (ExportSummary, Vec<ExportRecord>) {
+        let mut buffer = Vec::new();
+        let summary = wll.export_receipts(options, &mut buffer).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        let records = text.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        (summary, records)
+    }
+
+    #[test]
+    fn jsonl_rows_follow_the_schema() {
+        let wll = repo();
+        wll.commit(
+            CommitProposal::new("deploy")
+                .with_intent("release v2")
+                .with_evidence("https://ci.example.com/run/7")
+                .with_metadata("ticket", "OPS-12"),
+        )
+        .unwrap();
+
+        let (summary, records) = export(&wll, &ExportOptions::default());
+        assert_eq!((summary.receipts, summary.first_seq, summary.last_seq), (2, Some(1), Some(2)));
+        assert_eq!(summary.worldline, *wll.worldline());
+
+        let commitment = &records[0];
+        assert_eq!(commitment.kind, "commitment");
+        assert_eq!(commitment.prev_hash, None);
+        assert_eq!(commitment.intent.as_deref(), Some("release v2"));
+        assert_eq!(commitment.decision.as_deref(), Some("accepted"));
+        assert_eq!(commitment.class.as_deref(), Some("ContentUpdate"));
+        assert_eq!(commitment.evidence, Some(vec!["https://ci.example.com/run/7".to_string()]));
+        assert!(commitment.policy_hash.is_some());
+        assert_eq!(commitment.accepted, None);
+
+        let outcome = &records[1];
+        assert_eq!(outcome.kind, "outcome");
+        assert_eq!(outcome.prev_hash.as_deref(), Some(commitment.receipt_hash.as_str()));
+        assert_eq!(outcome.commitment_receipt_hash.as_deref(), Some(commitment.receipt_hash.as_str()));
+        assert_eq!(outcome.accepted, Some(true));
+        assert_eq!(outcome.metadata.as_ref().unwrap()["ticket"], "OPS-12");
+        assert_eq!(outcome.decision, None);
+
+        // Every line carries every column, null where it does not apply.
+        let line = serde_json::to_value(outcome).unwrap();
+        assert_eq!(line.as_object().unwrap().len(), 23);
+        assert!(line["policy_hash"].is_null());
+    }
+
+    #[test]
+    fn time_range_and_batches() {
+        let wll = repo();
+        for i in 0..3 {
+            wll.commit(CommitProposal::new(format!("change {i}"))).unwrap();
+        }
+        let (_, all) = export(&wll, &ExportOptions::default());
+        assert_eq!(all.len(), 6);
+
+        let since = all[2].timestamp_ms;
+        let until = all[5].timestamp_ms;
+        let options = ExportOptions::default().with_time_range(Some(since), Some(until));
+        let (summary, records) = export(&wll, &options);
+        assert!(records
+            .iter()
+            .all(|r| r.timestamp_ms >= since && r.timestamp_ms < until));
+        assert_eq!(summary.receipts, records.len() as u64);
+
+        let other = WorldlineId::derive(&IdentityMaterial::GenesisHash([9; 32]));
+        let (summary, records) = export(&wll, &ExportOptions::default().with_worldline(other));
+        assert_eq!((summary.receipts, records.len()), (0, 0));
+    }
+
+    #[cfg(not(feature = "arrow"))]
+    #[test]
+    fn parquet_needs_the_arrow_feature() {
+        let err = repo()
+            .export_receipts(&ExportOptions::new(ExportFormat::Parquet), std::io::sink())
+            .unwrap_err();
+        assert!(matches!(err, crate::SdkError::Unsupported(_)));
+    }
+
+    #[cfg(feature = "arrow")]
+    #[test]
+    fn parquet_round_trips_through_the_schema() {
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let wll = repo();
+        wll.commit(CommitProposal::new("one").with_metadata("k", "v")).unwrap();
+        let mut file = tempfile::tempfile().unwrap();
+        let summary = wll
+            .export_receipts(&ExportOptions::new(ExportFormat::Parquet), &mut file)
+            .unwrap();
+        assert_eq!(summary.receipts, 2);
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+        assert_eq!(reader.schema().as_ref(), &parquet_sink::schema());
+        let rows: usize = reader.build().unwrap().map(|b| b.unwrap().num_rows()).sum();
+        assert_eq!(rows, 2);
+    }
+}
@@ -0,0 +1,157 @@
+//! Forking a worldline into a new one that records where it came from.
+//!
+//! [`Wll::fork`] starts a worldline whose genesis commitment cites the
+//! source's head receipt as `receipt://` evidence, so the provenance DAG
+//! links the two with a cross-worldline parent. The genesis outcome copies
+//! the source's replayed state, and the fork then evolves on its own:
+//! nothing it records reaches the source worldline, nor the other way
+//! round.
+
+use serde_json::Value;
+use wll_gate::CommitmentGate;
+use wll_ledger::{
+    CommitmentProposal, CrossWorldlineRef, Decision, EvidenceBundle, OutcomeRecord, StateUpdate,
+};
+use wll_refs::Ref;
+use wll_types::{CommitmentClass, CommitmentId, IdentityMaterial, WorldlineId};
+
+use crate::builder::WllBuilder;
+use crate::error::{SdkError, SdkResult};
+use crate::repository::Wll;
+
+impl Wll {
+    /// Fork this worldline at its head into the worldline derived from
+    /// `material`.
+    ///
+    /// The returned handle shares this repository's object store, ledger,
+    /// policies, and fabric, with refs of its own whose `main` points at
+    /// the fork's genesis. Its gate runs the default stages under this
+    /// gate's configuration. Fails if the source has no receipts yet or the
+    /// derived worldline already has some.
+    pub fn fork(&self, material: &IdentityMaterial) -> SdkResult<Wll> {
+        let worldline = WorldlineId::derive(material);
+        if self.ledger().head(&worldline)?.is_some() {
+            return Err(SdkError::InvalidOperation(format!(
+                "worldline {worldline} already exists"
+            )));
+        }
+        let Some(source) = self.ledger().head(self.worldline())? else {
+            return Err(SdkError::InvalidOperation(format!(
+                "worldline {} has no receipts to fork from",
+                self.worldline()
+            )));
+        };
+
+        let cited = CrossWorldlineRef::new(self.worldline().clone(), source.receipt_hash);
+        let proposal = CommitmentProposal {
+            worldline: worldline.clone(),
+            commitment_id: CommitmentId::new(),
+            class: CommitmentClass::IdentityOperation,
+            intent: format!("fork of {} at seq {}", self.worldline(), source.seq),
+            requested_caps: vec![],
+            targets: vec![worldline.clone()],
+            evidence: EvidenceBundle::new(vec![cited.to_uri()], vec![]),
+            nonce: self.time_nonce(),
+        };
+        let commitment = self.ledger().append_commitment(&proposal, &Decision::Accepted, [0; 32])?;
+        let outcome = self.ledger().append_outcome(
+            commitment.receipt_hash,
+            &OutcomeRecord {
+                effects: vec![],
+                proofs: vec![],
+                state_updates: snapshot_updates(self, &proposal.intent)?,
+                metadata: Default::default(),
+            },
+        )?;
+
+        let mut builder = WllBuilder::new()
+            .with_worldline(worldline.clone())
+            .with_store(self.shared_store())
+            .with_ledger(self.shared_ledger())
+            .with_gate(CommitmentGate::with_default_stages(self.gate().config().clone()))
+            .with_policy_store(self.shared_policies())
+            .with_clock(self.clock().clone());
+        if let Some(fabric) = self.fabric() {
+            builder = builder.with_fabric(fabric.clone());
+        }
+        let fork = builder.build()?.with_proof_verifiers(self.proof_verifiers().clone());
+        let branch = fork.current_branch()?;
+        fork.refs().write_ref(
+            &format!("refs/heads/{branch}"),
+            &Ref::Branch { name: branch, worldline, receipt_hash: outcome.receipt_hash },
+        )?;
+        Ok(fork)
+    }
+}
+
+/// State updates that reproduce `wll`'s replayed state, with `message` set
+/// to `message`.
+fn snapshot_updates(wll: &Wll, message: &str) -> SdkResult<Vec<StateUpdate>> {
+    let replay = wll.replay()?;
+    let mut updates = vec![StateUpdate {
+        key: "message".into(),
+        value: Value::String(message.to_string()),
+        namespace: None,
+    }];
+    let default = replay.state.into_iter().map(|entry| (None, entry));
+    let namespaced = replay.namespaces.into_iter().flat_map(|(namespace, state)| {
+        state.into_iter().map(move |entry| (Some(namespace.clone()), entry))
+    });
+    updates.extend(
+        default
+            .chain(namespaced)
+            .filter(|(namespace, (key, _))| namespace.is_some() || key != "message")
+            .map(|(namespace, (key, value))| StateUpdate { key, value, namespace }),
+    );
+    Ok(updates)
+}
+
+#[cfg(test)]
+mod tests {
+    use wll_dag::ParentRef;
+    use wll_types::ObjectId;
+
+    use super::*;
+    use crate::commit::CommitProposal;
+
+    #[test]
+    fn fork_copies_state_and_links_its_genesis_to_the_source_head() {
+        let source = Wll::init().unwrap();
+        let tree = source.write_tree(vec![]).unwrap();
+        source.commit(CommitProposal::new("base").with_tree(tree)).unwrap();
+        source
+            .commit_with_state(
+                CommitProposal::new("experiment setup"),
+                vec![StateUpdate {
+                    key: "lr".into(),
+                    value: Value::from(3),
+                    namespace: Some("app".into()),
+                }],
+            )
+            .unwrap();
+        let head = source.ledger().head(source.worldline()).unwrap().unwrap();
+
+        let material = IdentityMaterial::Derived {
+            parent: *source.worldline().as_bytes(),
+            label: "experiment".into(),
+        };
+        let fork = source.fork(&material).unwrap();
+
+        assert_eq!(fork.worldline(), &WorldlineId::derive(&material));
+        assert_eq!(fork.head_tree().unwrap(), Some(tree));
+        let (forked, original) = (fork.replay().unwrap(), source.replay().unwrap());
+        assert_eq!(forked.namespaces, original.namespaces);
+        assert_eq!(forked.state.get("tree"), original.state.get("tree"));
+
+        let dag = fork.provenance().unwrap();
+        let genesis = fork.ledger().read_all(fork.worldline()).unwrap().remove(0);
+        let node = dag.get_node(&ObjectId::from_hash(genesis.receipt_hash())).unwrap();
+        assert!(node.parents.contains(&ParentRef::cross_worldline(ObjectId::from_hash(
+            head.receipt_hash
+        ))));
+
+        fork.commit(CommitProposal::new("diverge")).unwrap();
+        assert_eq!(source.receipt_count().unwrap(), 4);
+        assert!(matches!(source.fork(&material), Err(SdkError::InvalidOperation(_))));
+    }
+}
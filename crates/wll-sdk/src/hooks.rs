@@ -0,0 +1,384 @@
+//! Client-side hooks run around commits and pushes.
+//!
+//! A hook is a Rust callback or an external program registered for a
+//! [`HookPoint`]. Each gets a [`HookPayload`] describing the operation;
+//! programs read it as JSON on stdin. Pre-commit and pre-push hooks can
+//! veto the operation. A veto is recorded like a gate rejection: a rejected
+//! commitment and its rejection outcome are appended to the ledger, and
+//! the operation fails with [`SdkError::CommitmentRejected`] naming the
+//! hook. Post-commit hooks run after the commit is recorded; their
+//! failures are logged and otherwise ignored.
+
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+
+use serde::Serialize;
+use wll_config::HookSettings;
+use wll_diff::TreeDiff;
+use wll_ledger::CommitmentProposal;
+use wll_sync::RefUpdate;
+use wll_types::commitment::Decision;
+use wll_types::{CommitmentClass, CommitmentId, EvidenceBundle, WorldlineId};
+
+use crate::commit::CommitProposal;
+use crate::error::{SdkError, SdkResult};
+use crate::repository::Wll;
+
+/// Where in an operation a hook runs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HookPoint {
+    /// Before the gate judges a commit; can veto it.
+    PreCommit,
+    /// After a commit is accepted and its branch moved.
+    PostCommit,
+    /// Before anything is sent to the remote; can veto the push.
+    PrePush,
+}
+
+impl HookPoint {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::PreCommit => "pre-commit",
+            Self::PostCommit => "post-commit",
+            Self::PrePush => "pre-push",
+        }
+    }
+}
+
+impl fmt::Display for HookPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// What a hook is told about the operation.
+#[derive(Clone, Debug, Serialize)]
+pub struct HookPayload {
+    pub hook: HookPoint,
+    pub worldline: WorldlineId,
+    pub branch: String,
+    /// The commit being made (commit hooks).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proposal: Option<CommitProposal>,
+    /// The change the commit's tree makes to the head tree.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<TreeDiff>,
+    /// The accepted outcome receipt, hex (post-commit).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub receipt_hash: Option<String>,
+    /// The remote refs a push moves (pre-push).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub refs: Vec<PushedRef>,
+    /// Receipts the push sends (pre-push).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub receipts: Option<usize>,
+}
+
+impl HookPayload {
+    pub fn new(hook: HookPoint, worldline: WorldlineId, branch: impl Into<String>) -> Self {
+        Self {
+            hook,
+            worldline,
+            branch: branch.into(),
+            proposal: None,
+            diff: None,
+            receipt_hash: None,
+            refs: Vec::new(),
+            receipts: None,
+        }
+    }
+}
+
+/// A remote ref a push moves, hashes in hex.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct PushedRef {
+    pub name: String,
+    pub old: Option<String>,
+    pub new: String,
+}
+
+impl From<&RefUpdate> for PushedRef {
+    fn from(update: &RefUpdate) -> Self {
+        Self {
+            name: update.name.clone(),
+            old: update.old_hash.map(hex::encode),
+            new: hex::encode(update.new_hash),
+        }
+    }
+}
+
+/// A hook's answer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HookOutcome {
+    Allow,
+    /// Stop the operation. Ignored at [`HookPoint::PostCommit`].
+    Veto { reason: String },
+}
+
+impl HookOutcome {
+    pub fn veto(reason: impl Into<String>) -> Self {
+        Self::Veto { reason: reason.into() }
+    }
+}
+
+/// Code run at a [`HookPoint`].
+///
+/// Closures taking a `&HookPayload` and returning a [`HookOutcome`] are
+/// hooks.
+pub trait ClientHook: Send + Sync {
+    fn run(&self, payload: &HookPayload) -> SdkResult<HookOutcome>;
+}
+
+impl<F> ClientHook for F
+where
+    F: Fn(&HookPayload) -> HookOutcome + Send + Sync,
+{
+    fn run(&self, payload: &HookPayload) -> SdkResult<HookOutcome> {
+        Ok(self(payload))
+    }
+}
+
+/// Runs an external program with the payload as JSON on stdin.
+///
+/// The program runs in the working tree with `WLL_HOOK` set to the hook
+/// point. A non-zero exit is a veto, with the program's stderr as the
+/// reason.
+#[derive(Clone, Debug)]
+pub struct CommandHook {
+    pub program: PathBuf,
+    pub work_dir: Option<PathBuf>,
+}
+
+impl CommandHook {
+    pub fn new(program: impl Into<PathBuf>) -> Self {
+        Self { program: program.into(), work_dir: None }
+    }
+
+    /// Run the program in `dir`, and resolve a relative program path there.
+    pub fn with_work_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.work_dir = Some(dir.into());
+        self
+    }
+}
+
+impl ClientHook for CommandHook {
+    fn run(&self, payload: &HookPayload) -> SdkResult<HookOutcome> {
+        let program = match &self.work_dir {
+            Some(dir) if self.program.components().count() > 1 => dir.join(&self.program),
+            _ => self.program.clone(),
+        };
+        let mut command = Command::new(&program);
+        command
+            .env("WLL_HOOK", payload.hook.name())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped());
+        if let Some(dir) = &self.work_dir {
+            command.current_dir(dir);
+        }
+        let mut child = command.spawn().map_err(|e| {
+            SdkError::InvalidOperation(format!("{} hook {}: {e}", payload.hook, program.display()))
+        })?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // A hook that exits without reading its input is not an error.
+            match stdin.write_all(&serde_json::to_vec(payload)?) {
+                Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => return Err(e.into()),
+                _ => {}
+            }
+        }
+        let output = child.wait_with_output()?;
+        if output.status.success() {
+            return Ok(HookOutcome::Allow);
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        Ok(HookOutcome::veto(if stderr.is_empty() {
+            format!("{} exited with {}", program.display(), output.status)
+        } else {
+            stderr
+        }))
+    }
+}
+
+/// The hooks registered on a repository, run in registration order.
+#[derive(Clone, Default)]
+pub struct Hooks {
+    hooks: Vec<(HookPoint, Arc<dyn ClientHook>)>,
+}
+
+impl fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.hooks.iter().map(|(point, _)| point)).finish()
+    }
+}
+
+impl Hooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The `[hooks]` programs, run in `work_tree`.
+    pub fn from_settings(settings: &HookSettings, work_tree: &Path) -> Self {
+        let mut hooks = Self::new();
+        let points = [
+            (HookPoint::PreCommit, &settings.pre_commit),
+            (HookPoint::PostCommit, &settings.post_commit),
+            (HookPoint::PrePush, &settings.pre_push),
+        ];
+        for (point, programs) in points {
+            for program in programs {
+                let hook = CommandHook::new(program).with_work_dir(work_tree);
+                hooks.register(point, Arc::new(hook));
+            }
+        }
+        hooks
+    }
+
+    pub fn register(&mut self, point: HookPoint, hook: Arc<dyn ClientHook>) {
+        self.hooks.push((point, hook));
+    }
+
+    /// Whether any hook runs at `point`.
+    pub fn has(&self, point: HookPoint) -> bool {
+        self.hooks.iter().any(|(p, _)| *p == point)
+    }
+
+    /// Run the hooks for `payload.hook` until one vetoes, returning its
+    /// reason.
+    ///
+    /// A hook that fails to run vetoes with the error as the reason.
+    pub fn run(&self, payload: &HookPayload) -> Option<String> {
+        for (_, hook) in self.hooks.iter().filter(|(p, _)| *p == payload.hook) {
+            match hook.run(payload) {
+                Ok(HookOutcome::Allow) => {}
+                Ok(HookOutcome::Veto { reason }) => return Some(reason),
+                Err(e) => return Some(e.to_string()),
+            }
+        }
+        None
+    }
+}
+
+impl Wll {
+    /// Run `hook` at `point`, after the hooks already registered.
+    pub fn with_hook(mut self, point: HookPoint, hook: impl ClientHook + 'static) -> Self {
+        self.hooks_mut().register(point, Arc::new(hook));
+        self
+    }
+
+    /// Run the pre-push hooks for a push making `updates` and sending
+    /// `receipts` receipts. A veto is recorded as a rejected read-only
+    /// commitment naming the refs.
+    pub(crate) fn pre_push(&self, updates: &[RefUpdate], receipts: usize) -> SdkResult<()> {
+        let branch = self.current_branch().unwrap_or_default();
+        let mut payload = HookPayload::new(HookPoint::PrePush, self.worldline().clone(), branch);
+        payload.refs = updates.iter().map(PushedRef::from).collect();
+        payload.receipts = Some(receipts);
+        let refs: Vec<&str> = updates.iter().map(|u| u.name.as_str()).collect();
+        self.run_hooks(&payload, || CommitmentProposal {
+            worldline: self.worldline().clone(),
+            commitment_id: CommitmentId::new(),
+            class: CommitmentClass::ReadOnly,
+            intent: format!("push {}", refs.join(", ")),
+            requested_caps: Vec::new(),
+            targets: vec![self.worldline().clone()],
+            evidence: EvidenceBundle::from_references(Vec::new()),
+            nonce: self.time_nonce(),
+        })
+    }
+
+    /// Run the hooks for `payload.hook`. A veto is recorded as a rejected
+    /// commitment for `proposal` and returned as the error, its reason
+    /// naming the hook point.
+    pub(crate) fn run_hooks(
+        &self,
+        payload: &HookPayload,
+        proposal: impl FnOnce() -> CommitmentProposal,
+    ) -> SdkResult<()> {
+        if !self.hooks().has(payload.hook) {
+            return Ok(());
+        }
+        let Some(reason) = self.hooks().run(payload) else {
+            return Ok(());
+        };
+        if payload.hook == HookPoint::PostCommit {
+            tracing::warn!("post-commit hook failed: {reason}");
+            return Ok(());
+        }
+        let reason = format!("{} hook: {reason}", payload.hook);
+        let policy_hash = wll_gate::policy_hash(&self.gate().config().default_policy);
+        let decision = Decision::Rejected { reason };
+        Err(self.record_rejection(&proposal(), &decision, policy_hash, None)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wll_store::EntryMode;
+
+    #[test]
+    fn pre_commit_veto_is_recorded_and_post_commit_sees_the_receipt() {
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let log = seen.clone();
+        let wll = Wll::init()
+            .unwrap()
+            .with_hook(HookPoint::PreCommit, |payload: &HookPayload| {
+                let diff = payload.diff.as_ref().map_or(0, |d| d.changes.len());
+                match payload.proposal.as_ref().map(|p| p.message.as_str()) {
+                    Some("wip") => HookOutcome::veto(format!("no wip commits ({diff} changes)")),
+                    _ => HookOutcome::Allow,
+                }
+            })
+            .with_hook(HookPoint::PostCommit, move |payload: &HookPayload| {
+                log.lock().unwrap().push(payload.receipt_hash.clone().unwrap());
+                HookOutcome::veto("ignored after the commit")
+            });
+
+        let mut index = wll.new_index();
+        index.stage_file("a.txt", b"a", EntryMode::Regular).unwrap();
+        let err = wll.commit_tree(&mut index, CommitProposal::new("wip")).unwrap_err();
+        let SdkError::CommitmentRejected { reason, stage, receipt_hash } = err else {
+            panic!("expected a rejection, got {err}");
+        };
+        assert_eq!(reason, "pre-commit hook: no wip commits (1 changes)");
+        assert_eq!(stage, None);
+        assert!(matches!(wll.show(&receipt_hash).unwrap(), wll_ledger::Receipt::Outcome(_)));
+        assert_eq!(wll.receipt_count().unwrap(), 2);
+        assert!(seen.lock().unwrap().is_empty());
+
+        let result = wll.commit(CommitProposal::new("done")).unwrap();
+        assert_eq!(*seen.lock().unwrap(), vec![hex::encode(result.receipt_hash)]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn command_hook_reads_json_and_vetoes_on_failure() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let script = "#!/bin/sh\n\
+            grep -q '\"hook\":\"pre-push\"' || exit 0\n\
+            echo \"$WLL_HOOK: main is frozen\" >&2\n\
+            exit 1\n";
+        std::fs::create_dir(dir.path().join("hooks")).unwrap();
+        std::fs::write(dir.path().join("hooks/check"), script).unwrap();
+        let mode = std::fs::Permissions::from_mode(0o755);
+        std::fs::set_permissions(dir.path().join("hooks/check"), mode).unwrap();
+
+        let settings = HookSettings {
+            pre_commit: vec!["hooks/check".into()],
+            pre_push: vec!["hooks/check".into()],
+            ..HookSettings::default()
+        };
+        let hooks = Hooks::from_settings(&settings, dir.path());
+        let worldline = WorldlineId::ephemeral();
+        let commit = HookPayload::new(HookPoint::PreCommit, worldline.clone(), "main");
+        assert_eq!(hooks.run(&commit), None);
+        let push = HookPayload::new(HookPoint::PrePush, worldline, "main");
+        assert_eq!(hooks.run(&push).as_deref(), Some("pre-push: main is frozen"));
+    }
+}
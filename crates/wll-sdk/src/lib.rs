@@ -3,15 +3,88 @@
 //! Provides a unified API for programmatic access to all WLL subsystems.
 //! This is the main entry point for applications embedding WLL.
 
+pub mod async_wll;
+mod attachments;
+mod attestations;
+pub mod builder;
+pub mod bundle;
 pub mod commit;
+pub mod compare;
+pub mod config;
+pub mod deferred;
 pub mod error;
+pub mod export;
+mod fork;
+pub mod hooks;
+mod linked;
+pub mod local_clone;
+pub mod local_remote;
+pub mod maintenance;
+pub mod migrate;
+pub mod notes;
+pub mod offline;
+mod proofs;
+pub mod redaction;
+mod remote;
+mod remotes;
 pub mod repository;
+pub mod retention;
+pub mod rollback;
+pub mod sealed;
+pub mod search;
+pub mod show;
+pub mod stats;
+pub mod template;
+pub mod time_anchor;
+pub mod transparency;
+pub mod watch;
+pub mod workspace;
 
+pub use async_wll::AsyncWll;
+pub use builder::WllBuilder;
+pub use bundle::{Bundle, BundleOptions, BundleRef, BundleSummary, UnbundleReport};
 pub use commit::{CommitProposal, CommitResult, ReceiptSummary};
+pub use compare::{RefDiff, UniqueCommitment};
+pub use config::RepoConfig;
+pub use deferred::{OutcomeTimeout, PendingCommitment};
 pub use error::{SdkError, SdkResult};
+pub use export::{ExportFormat, ExportOptions, ExportRecord, ExportSummary};
+pub use linked::LinkedCommit;
+pub use local_clone::{LocalCloneOptions, LocalCloneReport};
+pub use local_remote::LocalRemote;
+pub use maintenance::{RepackReport, ScrubReport};
+pub use migrate::{FormatFile, MigrateOptions, MigrationReport};
+pub use notes::{Note, StoredNote};
+pub use offline::{QueuedCommit, ReconcileReport, ReplayStatus, ReplayedCommit};
+pub use redaction::Redaction;
 pub use repository::Wll;
+pub use retention::{RetentionPolicy, RetentionReport};
+pub use rollback::{RollbackPlan, RollbackStep};
+#[cfg(feature = "search")]
+pub use search::SearchIndex;
+pub use search::SearchHit;
+pub use show::ReceiptDetails;
+pub use stats::{KindStats, LedgerLength, ObjectSize, RepoStats};
+pub use template::{CapabilityGrant, RepoTemplate};
+pub use time_anchor::{RoughtimeServer, TimeAnchor};
+pub use transparency::{HttpTransparencyLog, LogPublication, TransparencyLog};
+pub use watch::{StateChange, StateWatch};
+pub use workspace::{MemberClone, MemberStatus, PinState, WorkspaceManifest, WorkspaceMember};
 
 // Re-export key types
-pub use wll_types::{ObjectId, WorldlineId, CommitmentClass, CommitmentId};
+pub use wll_types::{ObjectId, WorldlineId, CommitmentClass, CommitmentId, IdentityAttestation, AttestationKind, EvidenceAttachment, Reversibility};
+pub use wll_types::{Coded, ErrorCategory, ErrorCode};
+pub use wll_crypto::{EnvelopePublicKey, EnvelopeSecretKey};
 pub use wll_store::{Tree, TreeEntry, EntryMode, Blob};
-pub use wll_ledger::{Receipt, ValidationReport};
+pub use wll_ledger::{
+    CrossWorldlineRef, DecisionMismatch, EffectKind, EffectQuery, EffectSummary, KeySchema,
+    LatestStateProjection, Receipt, StateSchema, StateUpdate, StrictReplayResult, ValidationReport,
+    ValueKind,
+};
+pub use wll_index::Index;
+pub use wll_diff::{TreeChange, TreeDiff};
+pub use wll_dag::{BisectStep, ImpactReport};
+pub use wll_gate::{
+    GateSimulation, Policy, PolicyRule, PolicyScope, PolicyStore, RuleCheck, StageSimulation,
+};
+pub use wll_sync::{Credential, CredentialManager, RefSpec, RemoteConfig};
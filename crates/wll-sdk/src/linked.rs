@@ -0,0 +1,210 @@
+//! Commits spanning two worldlines, as transfers between them need.
+//!
+//! [`Wll::commit_linked`] records one commitment on each of two worldlines
+//! held by the same ledger. Both commitments share a commitment id, and each
+//! outcome names the other side's commitment under [`LINK_COUNTERPART_KEY`],
+//! so [`StreamValidator`] accepts neither outcome without its counterpart
+//! ([`StreamValidator::validate_link`]). Both proposals pass their gates
+//! before either commitment is recorded: a rejection on one side records
+//! that rejection and nothing on the other.
+
+use std::sync::Arc;
+
+use wll_ledger::{CrossWorldlineRef, StateUpdate, StreamValidator, LINK_COUNTERPART_KEY};
+use wll_types::CommitmentId;
+
+use crate::commit::{CommitProposal, CommitResult};
+use crate::error::{SdkError, SdkResult};
+use crate::repository::Wll;
+
+/// The two commits of a linked commit.
+#[derive(Clone, Debug)]
+pub struct LinkedCommit {
+    /// Commitment id both commitments carry.
+    pub commitment_id: CommitmentId,
+    /// The commit on the worldline `commit_linked` was called on.
+    pub local: CommitResult,
+    /// The commit on the counterpart worldline.
+    pub counterpart: CommitResult,
+}
+
+impl Wll {
+    /// Commit `proposal` here and `counterpart_proposal` on `counterpart`'s
+    /// worldline as one linked operation.
+    ///
+    /// Both handles must share a ledger, as [`fork`](Self::fork) handles do.
+    pub fn commit_linked(
+        &self,
+        proposal: CommitProposal,
+        counterpart: &Wll,
+        counterpart_proposal: CommitProposal,
+    ) -> SdkResult<LinkedCommit> {
+        self.commit_linked_with_state(
+            proposal,
+            Vec::new(),
+            counterpart,
+            counterpart_proposal,
+            Vec::new(),
+        )
+    }
+
+    /// [`commit_linked`](Self::commit_linked), with each side's `state`
+    /// recorded in its outcome as by
+    /// [`commit_with_state`](Self::commit_with_state).
+    pub fn commit_linked_with_state(
+        &self,
+        proposal: CommitProposal,
+        state: Vec<StateUpdate>,
+        counterpart: &Wll,
+        counterpart_proposal: CommitProposal,
+        counterpart_state: Vec<StateUpdate>,
+    ) -> SdkResult<LinkedCommit> {
+        if counterpart.worldline() == self.worldline() {
+            return Err(SdkError::InvalidOperation(
+                "a linked commit needs two different worldlines".into(),
+            ));
+        }
+        if !Arc::ptr_eq(&self.shared_ledger(), &counterpart.shared_ledger()) {
+            return Err(SdkError::InvalidOperation(
+                "a linked commit needs both worldlines in one ledger".into(),
+            ));
+        }
+
+        let commitment_id = CommitmentId::new();
+        let mut local = self.gate_commit(proposal, state, commitment_id.clone())?;
+        let mut remote =
+            counterpart.gate_commit(counterpart_proposal, counterpart_state, commitment_id.clone())?;
+        let local_commitment = self.append_gated(&local)?;
+        let remote_commitment = counterpart.append_gated(&remote)?;
+
+        let pairs = [
+            (&mut local, &local_commitment, &remote_commitment),
+            (&mut remote, &remote_commitment, &local_commitment),
+        ];
+        for (gated, commitment, other) in pairs {
+            let cited = CrossWorldlineRef::new(other.worldline.clone(), other.receipt_hash);
+            StreamValidator::validate_link(self.ledger(), commitment, &cited)?;
+            gated.proposal.metadata.insert(LINK_COUNTERPART_KEY.into(), cited.to_uri());
+        }
+        Ok(LinkedCommit {
+            commitment_id,
+            local: self.record_commit(local, local_commitment)?,
+            counterpart: counterpart.record_commit(remote, remote_commitment)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+    use wll_dag::ParentRef;
+    use wll_ledger::{
+        CommitmentProposal, Decision, EvidenceBundle, OutcomeRecord, ViolationKind,
+    };
+    use wll_types::{IdentityMaterial, ObjectId};
+
+    use super::*;
+
+    fn accounts() -> (Wll, Wll) {
+        let alice = Wll::init().unwrap();
+        alice.commit(CommitProposal::new("open")).unwrap();
+        let bob = alice
+            .fork(&IdentityMaterial::Derived {
+                parent: *alice.worldline().as_bytes(),
+                label: "bob".into(),
+            })
+            .unwrap();
+        (alice, bob)
+    }
+
+    fn balance(delta: i64) -> Vec<StateUpdate> {
+        vec![StateUpdate::new("balance", Value::from(delta)).in_namespace("ledger")]
+    }
+
+    #[test]
+    fn linked_commit_records_both_sides_referencing_each_other() {
+        let (alice, bob) = accounts();
+
+        let linked = alice
+            .commit_linked_with_state(
+                CommitProposal::new("send 5"),
+                balance(-5),
+                &bob,
+                CommitProposal::new("receive 5"),
+                balance(5),
+            )
+            .unwrap();
+
+        let (local, remote) = (&linked.local, &linked.counterpart);
+        assert_eq!(local.commitment_receipt.commitment_id, linked.commitment_id);
+        assert_eq!(remote.commitment_receipt.commitment_id, linked.commitment_id);
+        let names = |result: &CommitResult| {
+            CrossWorldlineRef::parse(&result.outcome_receipt.metadata[LINK_COUNTERPART_KEY])
+                .unwrap()
+                .receipt_hash
+        };
+        assert_eq!(names(local), remote.commitment_receipt.receipt_hash);
+        assert_eq!(names(remote), local.commitment_receipt.receipt_hash);
+        assert!(alice.verify().unwrap().links_resolved);
+        assert!(bob.verify().unwrap().is_valid());
+        assert_eq!(bob.replay().unwrap().namespace("ledger").unwrap()["balance"], 5);
+
+        let node = bob
+            .provenance()
+            .unwrap()
+            .get_node(&ObjectId::from_hash(remote.receipt_hash))
+            .unwrap()
+            .clone();
+        let cited = ObjectId::from_hash(local.commitment_receipt.receipt_hash);
+        assert!(node.parents.contains(&ParentRef::cross_worldline(cited)));
+    }
+
+    #[test]
+    fn outcome_its_counterpart_does_not_name_back_fails_validation() {
+        let (alice, bob) = accounts();
+        let linked = alice
+            .commit_linked(CommitProposal::new("send"), &bob, CommitProposal::new("receive"))
+            .unwrap();
+        let counterpart = &linked.counterpart.commitment_receipt;
+        let missing = CrossWorldlineRef::new(bob.worldline().clone(), [7; 32]);
+        let local = &linked.local.commitment_receipt;
+        assert!(StreamValidator::validate_link(alice.ledger(), local, &missing).is_err());
+
+        // A second commitment replaying the link, which bob never names.
+        let ledger = alice.ledger();
+        let replayed = ledger
+            .append_commitment(
+                &CommitmentProposal {
+                    worldline: alice.worldline().clone(),
+                    commitment_id: linked.commitment_id.clone(),
+                    class: local.class.clone(),
+                    intent: "send again".into(),
+                    requested_caps: vec![],
+                    targets: vec![alice.worldline().clone()],
+                    evidence: EvidenceBundle::empty(),
+                    nonce: 99,
+                },
+                &Decision::Accepted,
+                [0; 32],
+            )
+            .unwrap();
+        let cited = CrossWorldlineRef::new(bob.worldline().clone(), counterpart.receipt_hash);
+        ledger
+            .append_outcome(
+                replayed.receipt_hash,
+                &OutcomeRecord {
+                    effects: vec![],
+                    proofs: vec![],
+                    state_updates: vec![],
+                    metadata: [(LINK_COUNTERPART_KEY.to_string(), cited.to_uri())].into(),
+                },
+            )
+            .unwrap();
+
+        let report = alice.verify().unwrap();
+        assert!(!report.links_resolved);
+        assert_eq!(report.violations.len(), 1);
+        assert_eq!(report.violations[0].kind, ViolationKind::UnresolvedLink);
+        assert!(bob.verify().unwrap().is_valid());
+    }
+}
@@ -0,0 +1,234 @@
+//! Clones of a repository on the same machine.
+//!
+//! Object files are content-addressed and never change once written, so a
+//! local clone hardlinks them from the source instead of copying: loose
+//! objects and pack files alike. Where a hardlink cannot be made (the
+//! destination is on another filesystem, or the filesystem has none) the
+//! file is copied; on Linux the copy goes through `copy_file_range`, which
+//! filesystems with reflinks (Btrfs, XFS) turn into a copy-on-write clone.
+//! The ledger, refs, and everything else that changes are always copied.
+//!
+//! A hardlinked object is one file shared with the source, so damage to it
+//! shows on both sides. [`Wll::dissociate`] gives a clone private copies of
+//! every object file it still shares.
+
+use std::ffi::OsString;
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+use wll_sync::RemoteConfig;
+
+use crate::config::{RepoConfig, WLL_DIR};
+use crate::error::{SdkError, SdkResult};
+use crate::repository::{find_repo_dir, Wll};
+
+/// Entries of the source repository not copied as they are: the clone's
+/// config is written fresh, objects are shared, and identities and offline
+/// commits stay with the source.
+const NOT_COPIED: [&str; 4] = ["config", "objects", "attestations.json", "pending.json"];
+
+/// How [`Wll::clone_local`] brings over object files.
+#[derive(Clone, Debug)]
+pub struct LocalCloneOptions {
+    /// Hardlink object files from the source where possible (default).
+    /// When unset every object file is copied.
+    pub share_objects: bool,
+}
+
+impl Default for LocalCloneOptions {
+    fn default() -> Self {
+        Self { share_objects: true }
+    }
+}
+
+impl LocalCloneOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_shared_objects(mut self, share: bool) -> Self {
+        self.share_objects = share;
+        self
+    }
+}
+
+/// Result of [`Wll::clone_local`].
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct LocalCloneReport {
+    /// Object and pack files hardlinked from the source.
+    pub objects_linked: usize,
+    /// Object and pack files copied, reflinked where the filesystem can.
+    pub objects_copied: usize,
+    pub bytes_copied: u64,
+}
+
+impl Wll {
+    /// Clone the repository at `source` into `<dest>/.wll`, sharing its
+    /// object files as `options` allows.
+    ///
+    /// The clone records the same worldline, receipts, refs and HEAD as the
+    /// source, and an `origin` remote pointing back at it.
+    pub fn clone_local(
+        source: impl AsRef<Path>,
+        dest: impl AsRef<Path>,
+        options: &LocalCloneOptions,
+    ) -> SdkResult<(Self, LocalCloneReport)> {
+        let source_dir = find_repo_dir(source.as_ref())?;
+        let source_config = RepoConfig::load(&source_dir.join("config"))?;
+        let repo_dir = dest.as_ref().join(WLL_DIR);
+        if repo_dir.exists() {
+            return Err(SdkError::InvalidOperation(format!(
+                "repository already exists at {}",
+                repo_dir.display()
+            )));
+        }
+
+        let mut report = LocalCloneReport::default();
+        let cloned = clone_dir(&source_dir, &repo_dir, &NOT_COPIED, None, &mut report)
+            .and_then(|()| {
+                let (from, to) = (source_dir.join("objects"), repo_dir.join("objects"));
+                let share = Some(options.share_objects);
+                clone_dir(&from, &to, &["quarantine"], share, &mut report)
+            });
+        if let Err(e) = cloned {
+            let _ = fs::remove_dir_all(&repo_dir);
+            return Err(e);
+        }
+
+        let origin = fs::canonicalize(&source_dir)?;
+        let origin = origin.parent().filter(|_| origin.ends_with(WLL_DIR)).unwrap_or(&origin);
+        let mut config = RepoConfig::new(source_config.worldline);
+        config.hash_algo = source_config.hash_algo;
+        config.node_id = source_config.node_id;
+        config.remotes.push(RemoteConfig::new("origin", origin.display().to_string()));
+        config.save(&repo_dir.join("config"))?;
+        Ok((Self::open(&repo_dir)?, report))
+    }
+
+    /// Replace every object file this repository shares with another
+    /// through a hardlink by a private copy, returning how many there were.
+    pub fn dissociate(&self) -> SdkResult<usize> {
+        let objects = self
+            .repo_dir()
+            .ok_or_else(|| {
+                SdkError::InvalidOperation("only repositories on disk share objects".into())
+            })?
+            .join("objects");
+        let mut count = 0;
+        if objects.is_dir() {
+            dissociate_dir(&objects, &mut count)?;
+        }
+        Ok(count)
+    }
+}
+
+/// Recreate `from` at `to`, leaving out entries of `from` named in `skip`.
+///
+/// Files are copied, or with `objects` set count as object files and are
+/// hardlinked when it is `Some(true)`.
+fn clone_dir(
+    from: &Path,
+    to: &Path,
+    skip: &[&str],
+    objects: Option<bool>,
+    report: &mut LocalCloneReport,
+) -> SdkResult<()> {
+    fs::create_dir_all(to)?;
+    if !from.is_dir() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if skip.iter().any(|s| name == *s) {
+            continue;
+        }
+        let (src, dst) = (entry.path(), to.join(&name));
+        if entry.file_type()?.is_dir() {
+            clone_dir(&src, &dst, &[], objects, report)?;
+        } else if objects.is_none() {
+            fs::copy(&src, &dst)?;
+        } else if objects == Some(true) && fs::hard_link(&src, &dst).is_ok() {
+            report.objects_linked += 1;
+        } else {
+            report.bytes_copied += fs::copy(&src, &dst)?;
+            report.objects_copied += 1;
+        }
+    }
+    Ok(())
+}
+
+fn dissociate_dir(dir: &Path, count: &mut usize) -> SdkResult<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            dissociate_dir(&path, count)?;
+        } else if is_shared(&entry.metadata()?) {
+            let mut private = OsString::from(path.as_os_str());
+            private.push(".private");
+            fs::copy(&path, &private)?;
+            fs::rename(&private, &path)?;
+            *count += 1;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn is_shared(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    metadata.nlink() > 1
+}
+
+/// Without link counts, every file is taken to be shared.
+#[cfg(not(unix))]
+fn is_shared(_: &fs::Metadata) -> bool {
+    true
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::commit::CommitProposal;
+    use wll_store::EntryMode;
+
+    #[test]
+    fn local_clone_shares_objects_until_dissociated() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = Wll::init_at(dir.path().join("source")).unwrap();
+        let mut index = source.new_index();
+        index.stage_file("a.txt", b"shared", EntryMode::Regular).unwrap();
+        let tree = source.commit_tree(&mut index, CommitProposal::new("first")).unwrap().tree;
+        source.queue_commit(CommitProposal::new("offline")).unwrap();
+
+        let source_path = dir.path().join("source");
+        let (clone, report) =
+            Wll::clone_local(&source_path, dir.path().join("clone"), &LocalCloneOptions::new())
+                .unwrap();
+        assert!(report.objects_linked >= 2);
+        assert_eq!(report.objects_copied, 0);
+        assert_eq!(clone.worldline(), source.worldline());
+        assert_eq!(clone.head_tree().unwrap(), tree);
+        assert!(clone.pending_commits().unwrap().is_empty());
+        let origin = source_path.canonicalize().unwrap().display().to_string();
+        assert_eq!(clone.remote("origin").unwrap().url, origin);
+
+        clone.commit(CommitProposal::new("only in the clone")).unwrap();
+        assert_eq!((clone.receipt_count().unwrap(), source.receipt_count().unwrap()), (4, 2));
+
+        assert_eq!(clone.dissociate().unwrap(), report.objects_linked);
+        assert_eq!(clone.dissociate().unwrap(), 0);
+        assert_eq!(clone.read_tree(&tree.unwrap()).unwrap().entries.len(), 1);
+
+        let copied = Wll::clone_local(
+            &source_path,
+            dir.path().join("copy"),
+            &LocalCloneOptions::new().with_shared_objects(false),
+        )
+        .unwrap()
+        .1;
+        assert_eq!((copied.objects_linked, copied.objects_copied), (0, report.objects_linked));
+    }
+}
@@ -0,0 +1,107 @@
+//! A repository in the same process used as a remote.
+//!
+//! [`LocalRemote`] answers [`RemoteTransport`] calls from another [`Wll`]
+//! directly, with the same repository-side halves a server runs, so two
+//! in-process repositories can push and pull without a network. Tests and
+//! simulations use it to stand in for a server.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use wll_ledger::Receipt;
+use wll_protocol::ConsistencyToken;
+use wll_sync::{RefRejection, RefUpdate, RemoteTransport, SyncError, SyncResult};
+use wll_types::{ObjectId, WorldlineId};
+
+use crate::error::SdkError;
+use crate::repository::Wll;
+
+/// [`RemoteTransport`] serving an in-process repository.
+#[derive(Clone)]
+pub struct LocalRemote {
+    repo: Arc<Wll>,
+}
+
+impl LocalRemote {
+    pub fn new(repo: Arc<Wll>) -> Self {
+        Self { repo }
+    }
+
+    /// The repository being served.
+    pub fn repo(&self) -> &Arc<Wll> {
+        &self.repo
+    }
+}
+
+fn remote_err(e: SdkError) -> SyncError {
+    SyncError::RemoteError(e.to_string())
+}
+
+#[async_trait]
+impl RemoteTransport for LocalRemote {
+    async fn list_refs(&self) -> SyncResult<Vec<(String, [u8; 32])>> {
+        self.repo.ref_tips().map_err(remote_err)
+    }
+
+    async fn fetch_objects(&self, wants: &[ObjectId], haves: &[ObjectId]) -> SyncResult<Vec<u8>> {
+        let common: Vec<[u8; 32]> = haves.iter().map(|id| *id.as_bytes()).collect();
+        self.repo.pack_objects_for(wants, &common).map(|(pack, _)| pack).map_err(remote_err)
+    }
+
+    async fn fetch_receipts(
+        &self,
+        worldlines: &[WorldlineId],
+        since: Option<u64>,
+    ) -> SyncResult<Vec<Receipt>> {
+        let mut receipts = Vec::new();
+        for worldline in worldlines {
+            let stream = self.repo.ledger().read_all(worldline)?;
+            receipts.extend(stream.into_iter().filter(|r| Some(r.seq()) > since));
+        }
+        Ok(receipts)
+    }
+
+    async fn push_pack(&self, pack_bytes: &[u8]) -> SyncResult<()> {
+        let quarantine = self.repo.quarantine_pack(Some(pack_bytes.to_vec())).map_err(remote_err)?;
+        quarantine.migrate().map(drop).map_err(|e| remote_err(e.into()))
+    }
+
+    async fn push_receipts(&self, receipts: &[Receipt]) -> SyncResult<()> {
+        self.repo.import_receipts(receipts.to_vec()).map(drop).map_err(remote_err)
+    }
+
+    async fn update_refs(&self, updates: &[RefUpdate]) -> SyncResult<Vec<RefRejection>> {
+        let mut rejected = Vec::new();
+        for update in updates {
+            if let Err(e) = self.repo.fast_forward(&update.name, update.new_hash) {
+                rejected.push(RefRejection { name: update.name.clone(), reason: e.to_string() });
+            }
+        }
+        Ok(rejected)
+    }
+
+    async fn push_atomic(
+        &self,
+        pack_bytes: &[u8],
+        receipts: &[Receipt],
+        updates: &[RefUpdate],
+    ) -> SyncResult<Vec<RefRejection>> {
+        self.repo
+            .accept_atomic_push(pack_bytes.to_vec(), receipts.to_vec(), updates)
+            .map_err(remote_err)
+    }
+
+    async fn ack_haves(&self, haves: &[ObjectId]) -> SyncResult<Vec<ObjectId>> {
+        let mut common = Vec::new();
+        for id in haves {
+            if self.repo.ledger().get_by_hash(*id.as_bytes())?.is_some() {
+                common.push(*id);
+            }
+        }
+        Ok(common)
+    }
+
+    async fn consistency(&self) -> SyncResult<Option<ConsistencyToken>> {
+        self.repo.consistency_token().map(Some).map_err(remote_err)
+    }
+}
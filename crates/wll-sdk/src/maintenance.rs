@@ -0,0 +1,165 @@
+//! Housekeeping a host runs on a schedule.
+//!
+//! [`Wll::repack`] packs loose objects, [`Wll::scrub`] re-reads every
+//! object, receipt stream and pack looking for damage, and
+//! [`Wll::checkpoint_dag`] drops provenance older than any live receipt.
+//! Together with retention ([`Wll::apply_retention`]) these are the tasks
+//! `wll serve` schedules under `[maintenance]`.
+
+use std::path::PathBuf;
+
+use serde::Serialize;
+use wll_ledger::StreamValidator;
+use wll_pack::{PackManager, PackReader};
+use wll_types::TemporalAnchor;
+
+use crate::error::SdkResult;
+use crate::repository::Wll;
+
+/// What [`Wll::repack`] did.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct RepackReport {
+    pub objects_packed: u64,
+    /// The pack written, unless every object was already packed.
+    pub pack: Option<PathBuf>,
+}
+
+/// What [`Wll::scrub`] checked and what it found wrong.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ScrubReport {
+    pub objects_checked: u64,
+    pub receipts_checked: u64,
+    pub packs_checked: u64,
+    /// One line per damaged object, stream or pack.
+    pub problems: Vec<String>,
+}
+
+impl ScrubReport {
+    pub fn is_clean(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl Wll {
+    /// Write the loose objects no pack holds yet into a new pack. The
+    /// loose copies stay in place. Repositories not on disk have no packs
+    /// and report nothing packed.
+    pub fn repack(&self) -> SdkResult<RepackReport> {
+        let Some(dir) = self.repo_dir() else {
+            return Ok(RepackReport::default());
+        };
+        let mut packs = PackManager::load(dir)?;
+        let loose: Vec<_> = self
+            .store()
+            .list_ids()?
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|id| !packs.contains(id))
+            .collect();
+        if loose.is_empty() {
+            return Ok(RepackReport::default());
+        }
+        let pack = packs.repack(self.store(), &loose)?;
+        Ok(RepackReport { objects_packed: loose.len() as u64, pack: Some(pack.pack_path) })
+    }
+
+    /// Check that every loose object reads back under its id, every
+    /// worldline's receipt stream validates, and every pack matches its
+    /// checksums. Damage is reported, not repaired.
+    pub fn scrub(&self) -> SdkResult<ScrubReport> {
+        let mut report = self.scrub_store()?;
+        for worldline in self.ledger().worldlines()? {
+            let stream = StreamValidator::validate_stream(self.ledger(), &worldline)?;
+            report.receipts_checked += stream.receipt_count;
+            for violation in &stream.violations {
+                report.problems.push(format!(
+                    "worldline {worldline} seq {}: {}",
+                    violation.seq, violation.description
+                ));
+            }
+        }
+        Ok(report)
+    }
+
+    /// [`scrub`](Self::scrub) without the receipt streams: only objects
+    /// and packs are checked.
+    pub fn scrub_store(&self) -> SdkResult<ScrubReport> {
+        let mut report = ScrubReport::default();
+        for id in self.store().list_ids()?.unwrap_or_default() {
+            report.objects_checked += 1;
+            match self.store().read(&id) {
+                Ok(Some(object)) if object.compute_id() == id => {}
+                Ok(Some(_)) => report.problems.push(format!("object {id}: content hash mismatch")),
+                Ok(None) => report.problems.push(format!("object {id}: listed but missing")),
+                Err(e) => report.problems.push(format!("object {id}: {e}")),
+            }
+        }
+        let pack_dir = self.repo_dir().map(|dir| dir.join("objects").join("pack"));
+        if let Some(pack_dir) = pack_dir.filter(|dir| dir.is_dir()) {
+            for entry in std::fs::read_dir(pack_dir)? {
+                let path = entry?.path();
+                if path.extension().map_or(true, |ext| ext != "pack") {
+                    continue;
+                }
+                report.packs_checked += 1;
+                let damaged = match PackReader::open(&path) {
+                    Ok(pack) => !pack.verify().is_ok(),
+                    Err(_) => true,
+                };
+                if damaged {
+                    report.problems.push(format!("pack {}: verification failed", path.display()));
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Prune provenance older than the oldest live receipt of any
+    /// worldline in the ledger, returning the number of nodes pruned.
+    pub fn checkpoint_dag(&self) -> SdkResult<usize> {
+        let mut horizon: Option<TemporalAnchor> = None;
+        for worldline in self.ledger().worldlines()? {
+            let first = self.ledger().archived_through(&worldline)?.map_or(1, |base| base.seq + 1);
+            if let Some(oldest) = self.ledger().read_range(&worldline, first, first)?.first() {
+                let timestamp = oldest.timestamp();
+                horizon = Some(horizon.map_or(timestamp, |h| h.min(timestamp)));
+            }
+        }
+        match horizon {
+            Some(horizon) => self.checkpoint_provenance(&horizon),
+            None => Ok(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wll_store::EntryMode;
+
+    use super::*;
+    use crate::commit::CommitProposal;
+
+    #[test]
+    fn repack_scrub_and_checkpoint_leave_a_clean_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        let wll = Wll::init_at(dir.path()).unwrap();
+        for n in 0..3 {
+            let mut index = wll.new_index();
+            index.stage_file("n.txt", format!("{n}").as_bytes(), EntryMode::Regular).unwrap();
+            wll.commit_tree(&mut index, CommitProposal::new(format!("commit {n}"))).unwrap();
+        }
+
+        let repacked = wll.repack().unwrap();
+        assert!(repacked.objects_packed > 0 && repacked.pack.is_some());
+        assert_eq!(wll.repack().unwrap(), RepackReport::default());
+
+        let scrub = wll.scrub().unwrap();
+        assert!(scrub.is_clean(), "{:?}", scrub.problems);
+        assert_eq!((scrub.receipts_checked, scrub.packs_checked), (6, 1));
+        assert_eq!(scrub.objects_checked, repacked.objects_packed);
+
+        // Every receipt is live, so all of the provenance stays.
+        assert_eq!(wll.checkpoint_dag().unwrap(), 0);
+        assert_eq!(wll.provenance().unwrap().len(), 6);
+    }
+}
@@ -0,0 +1,268 @@
+//! Upgrading on-disk artifacts to the formats this build writes.
+//!
+//! Every versioned file format declares a [`FormatSpec`]: packs, pack
+//! indexes and the multi-pack index in `wll-pack`, WAL segments in
+//! `wll-fabric`. Readers accept anything from a format's oldest version
+//! through its current one, so a repository keeps working across an
+//! upgrade. [`Wll::migrate`] rewrites the older files in the current
+//! version, so that support for their version can later be dropped, and
+//! reports any file written by a newer build.
+//!
+//! Upgraded files replace the originals by rename, after the originals are
+//! copied to `.wll/migrate-backup/<ms>/` (beside the segments, for an
+//! in-memory repository). WAL segments are not part of the repository;
+//! callers name them, and must not have them open in a running fabric.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use wll_fabric::{WalEntry, WAL_FORMAT};
+use wll_pack::{
+    MultiPackIndex, PackIndex, PackReader, PackResult, INDEX_FORMAT, MIDX_FORMAT, PACK_FORMAT,
+};
+use wll_types::FormatSpec;
+
+use crate::error::SdkResult;
+use crate::repository::Wll;
+
+/// Directory under `.wll` holding the originals of migrated files.
+pub const BACKUP_DIR: &str = "migrate-backup";
+
+/// What [`Wll::migrate`] rewrites, and whether it keeps the originals.
+#[derive(Clone, Debug)]
+pub struct MigrateOptions {
+    /// Report what would be upgraded without writing anything.
+    pub dry_run: bool,
+    /// Copy each file to the backup directory before replacing it
+    /// (default).
+    pub backup: bool,
+    /// WAL segments to upgrade along with the repository's own files.
+    pub wal_segments: Vec<PathBuf>,
+}
+
+impl Default for MigrateOptions {
+    fn default() -> Self {
+        Self { dry_run: false, backup: true, wal_segments: Vec::new() }
+    }
+}
+
+impl MigrateOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn with_backup(mut self, backup: bool) -> Self {
+        self.backup = backup;
+        self
+    }
+
+    pub fn with_wal_segment(mut self, path: impl Into<PathBuf>) -> Self {
+        self.wal_segments.push(path.into());
+        self
+    }
+}
+
+/// A file and the version of its format it was found in.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct FormatFile {
+    pub path: PathBuf,
+    /// Name of the format, as in its [`FormatSpec`].
+    pub format: &'static str,
+    pub version: u32,
+}
+
+/// Result of [`Wll::migrate`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct MigrationReport {
+    /// Versioned files looked at.
+    pub checked: usize,
+    /// Files rewritten in the current version, or that would be in a dry
+    /// run, with the version they were in.
+    pub upgraded: Vec<FormatFile>,
+    /// Files in a version newer than this build reads, left as they are.
+    pub unsupported: Vec<FormatFile>,
+    /// Where the originals were copied, if anything was upgraded.
+    pub backup: Option<PathBuf>,
+}
+
+impl MigrationReport {
+    /// Whether every file was in a version this build reads.
+    pub fn is_supported(&self) -> bool {
+        self.unsupported.is_empty()
+    }
+}
+
+/// Rewrites a file's bytes in the current version, or `None` if they are.
+type Upgrade = fn(&[u8]) -> PackResult<Option<Vec<u8>>>;
+
+/// A versioned file: its format, the version it declares, and for formats
+/// with an older version to upgrade from, the rewrite.
+struct Artifact {
+    path: PathBuf,
+    spec: FormatSpec,
+    version: fn(&[u8]) -> PackResult<u32>,
+    upgrade: Option<Upgrade>,
+}
+
+impl Wll {
+    /// Upgrade every pack, pack index and multi-pack index of the
+    /// repository, and the WAL segments `options` names, to the current
+    /// version of its format. In-memory repositories have no files of
+    /// their own and only their WAL segments are looked at.
+    pub fn migrate(&self, options: &MigrateOptions) -> SdkResult<MigrationReport> {
+        let mut artifacts = Vec::new();
+        let pack_dir = self.repo_dir().map(|dir| dir.join("objects").join("pack"));
+        if let Some(pack_dir) = pack_dir.filter(|dir| dir.is_dir()) {
+            let mut paths: Vec<PathBuf> =
+                fs::read_dir(pack_dir)?.map(|e| e.map(|e| e.path())).collect::<Result<_, _>>()?;
+            paths.sort();
+            for path in paths {
+                let (spec, version, upgrade): (_, fn(&[u8]) -> _, _) =
+                    match path.extension().and_then(|ext| ext.to_str()) {
+                        Some("pack") => (PACK_FORMAT, PackReader::format_version, None),
+                        Some("idx") => (
+                            INDEX_FORMAT,
+                            PackIndex::format_version,
+                            Some(PackIndex::upgrade as Upgrade),
+                        ),
+                        _ if path.ends_with(MultiPackIndex::FILE_NAME) => {
+                            (MIDX_FORMAT, MultiPackIndex::format_version, None)
+                        }
+                        _ => continue,
+                    };
+                artifacts.push(Artifact { path, spec, version, upgrade });
+            }
+        }
+        for path in &options.wal_segments {
+            artifacts.push(Artifact {
+                path: path.clone(),
+                spec: WAL_FORMAT,
+                version: |data| Ok(WalEntry::segment_version(data)),
+                upgrade: Some(|data| Ok(WalEntry::upgrade_segment(data))),
+            });
+        }
+
+        let mut report = MigrationReport { checked: artifacts.len(), ..Default::default() };
+        let mut upgrades = Vec::new();
+        for artifact in artifacts {
+            let data = fs::read(&artifact.path)?;
+            let version = (artifact.version)(&data)?;
+            let file =
+                FormatFile { path: artifact.path.clone(), format: artifact.spec.name, version };
+            if !artifact.spec.reads(version) {
+                report.unsupported.push(file);
+            } else if let Some(upgraded) = artifact.upgrade.map(|f| f(&data)).transpose()?.flatten()
+            {
+                report.upgraded.push(file);
+                upgrades.push((artifact.path, upgraded));
+            }
+        }
+        if options.dry_run || upgrades.is_empty() {
+            return Ok(report);
+        }
+
+        if options.backup {
+            // Without a `.wll`, backups go beside the first segment.
+            let base = match self.repo_dir() {
+                Some(dir) => dir.to_path_buf(),
+                None => upgrades[0].0.parent().map(Path::to_path_buf).unwrap_or_default(),
+            };
+            let backup = base.join(BACKUP_DIR).join(self.clock().now_ms().to_string());
+            for (path, _) in &upgrades {
+                let name = match self.repo_dir().and_then(|dir| path.strip_prefix(dir).ok()) {
+                    Some(relative) => relative.to_path_buf(),
+                    None => Path::new("wal").join(path.file_name().unwrap_or_default()),
+                };
+                let target = backup.join(name);
+                fs::create_dir_all(target.parent().unwrap_or(&backup))?;
+                fs::copy(path, target)?;
+            }
+            report.backup = Some(backup);
+        }
+        for (path, upgraded) in upgrades {
+            let staged = path.with_extension("migrating");
+            fs::write(&staged, upgraded)?;
+            fs::File::open(&staged)?.sync_all()?;
+            fs::rename(&staged, &path)?;
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wll_fabric::{EventKind, EventPayload, FabricEvent};
+    use wll_store::EntryMode;
+    use wll_types::TemporalAnchor;
+
+    use super::*;
+    use crate::commit::CommitProposal;
+
+    #[test]
+    fn outdated_indexes_and_segments_are_upgraded_with_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let wll = Wll::init_at(dir.path()).unwrap();
+        let mut index = wll.new_index();
+        index.stage_file("a.txt", b"a", EntryMode::Regular).unwrap();
+        wll.commit_tree(&mut index, CommitProposal::new("add a")).unwrap();
+        let pack = wll.repack().unwrap().pack.unwrap();
+
+        // Downgrade the index to version 1 and write a version 1 segment.
+        let idx_path = pack.with_extension("idx");
+        let v1 = PackIndex::from_bytes(&fs::read(&idx_path).unwrap()).unwrap().to_bytes_as(1);
+        fs::write(&idx_path, v1.unwrap()).unwrap();
+        let event = FabricEvent::new(
+            TemporalAnchor::new(1, 0, 1),
+            wll.worldline().clone(),
+            EventKind::SyncStarted,
+            EventPayload::Empty,
+        );
+        let wal_path = dir.path().join("fabric.wal");
+        let segment = WalEntry { event }.encode().unwrap();
+        fs::write(&wal_path, &segment).unwrap();
+
+        let options = MigrateOptions::new().with_wal_segment(&wal_path);
+        let planned = wll.migrate(&options.clone().with_dry_run(true)).unwrap();
+        assert_eq!(planned.upgraded.len(), 2);
+        assert!(planned.backup.is_none());
+        assert_eq!(PackIndex::format_version(&fs::read(&idx_path).unwrap()).unwrap(), 1);
+
+        let report = wll.migrate(&options).unwrap();
+        assert_eq!(report.upgraded, planned.upgraded);
+        assert!(report.is_supported());
+        assert_eq!(PackIndex::format_version(&fs::read(&idx_path).unwrap()).unwrap(), 2);
+        assert_eq!(WalEntry::segment_version(&fs::read(&wal_path).unwrap()), 2);
+        assert_eq!(WalEntry::decode_segment(&fs::read(&wal_path).unwrap()).unwrap().len(), 1);
+        assert!(PackReader::open(&pack).unwrap().verify().is_ok());
+
+        let backup = report.backup.unwrap();
+        let kept = backup.join(idx_path.strip_prefix(wll.repo_dir().unwrap()).unwrap());
+        assert_eq!(PackIndex::format_version(&fs::read(kept).unwrap()).unwrap(), 1);
+        assert_eq!(fs::read(backup.join("wal").join("fabric.wal")).unwrap(), segment);
+
+        let again = wll.migrate(&options).unwrap();
+        assert!(again.upgraded.is_empty() && again.backup.is_none());
+        assert_eq!(again.checked, report.checked);
+    }
+
+    #[test]
+    fn files_from_a_newer_version_are_reported_and_left_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let wal_path = dir.path().join("fabric.wal");
+        let mut newer = b"WLLW".to_vec();
+        newer.extend_from_slice(&(WAL_FORMAT.current + 1).to_le_bytes());
+        fs::write(&wal_path, &newer).unwrap();
+
+        let wll = Wll::init().unwrap();
+        let report = wll.migrate(&MigrateOptions::new().with_wal_segment(&wal_path)).unwrap();
+        assert!(!report.is_supported());
+        assert_eq!(report.unsupported[0].version, WAL_FORMAT.current + 1);
+        assert_eq!(fs::read(&wal_path).unwrap(), newer);
+    }
+}
@@ -0,0 +1,228 @@
+//! Notes attached to receipts after the fact, as with git notes.
+//!
+//! Receipts never change once recorded, but post-incident notes and links
+//! to follow-up work often come later. A [`Note`] is stored as a JSON blob
+//! and filed in the notes tree under the receipt it annotates:
+//!
+//! ```text
+//! <notes tree>
+//!   <receipt hash>/        one subtree per annotated receipt
+//!     <note id>            note blob
+//! ```
+//!
+//! Notes are content-addressed and never edited, so the notes of two
+//! replicas merge by union ([`Wll::merge_notes`]) without conflicts.
+//! Bundles carry the notes tree along. Persistent repositories keep its id
+//! in `.wll/NOTES`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use wll_store::{EntryMode, TreeEntry};
+use wll_types::{ObjectId, TemporalAnchor};
+
+use crate::error::{SdkError, SdkResult};
+use crate::repository::Wll;
+
+const NOTES_FILE: &str = "NOTES";
+
+/// Information attached to a receipt after it was recorded.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Note {
+    pub receipt_hash: [u8; 32],
+    pub text: String,
+    /// Links to related material, such as follow-up issues.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    pub created: TemporalAnchor,
+}
+
+impl Note {
+    pub fn with_link(mut self, link: impl Into<String>) -> Self {
+        self.links.push(link.into());
+        self
+    }
+
+    pub fn with_author(mut self, author: impl Into<String>) -> Self {
+        self.author = Some(author.into());
+        self
+    }
+}
+
+/// A note and the id of the blob holding it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct StoredNote {
+    pub id: ObjectId,
+    pub note: Note,
+}
+
+/// Note ids filed under each annotated receipt, by receipt hash in hex.
+type NoteIndex = BTreeMap<String, BTreeSet<ObjectId>>;
+
+/// Read the notes tree id saved in `repo_dir`, if any.
+pub(crate) fn load(repo_dir: &Path) -> SdkResult<Option<ObjectId>> {
+    match std::fs::read_to_string(repo_dir.join(NOTES_FILE)) {
+        Ok(hex) => ObjectId::from_hex(hex.trim())
+            .map(Some)
+            .map_err(|e| SdkError::Internal(format!("{NOTES_FILE}: {e}"))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+impl Wll {
+    /// A note on `receipt_hash`, timestamped by the repository clock. Record
+    /// it with [`add_note`](Self::add_note).
+    pub fn new_note(&self, receipt_hash: [u8; 32], text: impl Into<String>) -> Note {
+        Note {
+            receipt_hash,
+            text: text.into(),
+            links: Vec::new(),
+            author: None,
+            created: self.clock().anchor(0),
+        }
+    }
+
+    /// Store `note` and file it under its receipt, which must exist.
+    pub fn add_note(&self, note: Note) -> SdkResult<StoredNote> {
+        if self.ledger().get_by_hash(note.receipt_hash)?.is_none() {
+            return Err(SdkError::ObjectNotFound(hex::encode(note.receipt_hash)));
+        }
+        let id = self.write_blob(&serde_json::to_vec(&note)?)?;
+        self.update_notes(|index| {
+            let filed = index.entry(hex::encode(note.receipt_hash)).or_default();
+            Ok(usize::from(filed.insert(id)))
+        })?;
+        Ok(StoredNote { id, note })
+    }
+
+    /// Notes on `receipt_hash`, oldest first.
+    pub fn notes(&self, receipt_hash: &[u8; 32]) -> SdkResult<Vec<StoredNote>> {
+        let Some(root) = self.notes_root()? else {
+            return Ok(Vec::new());
+        };
+        let mut index = self.note_index(Some(root))?;
+        let ids = index.remove(&hex::encode(receipt_hash)).unwrap_or_default();
+        let mut notes = Vec::with_capacity(ids.len());
+        for id in ids {
+            let note = serde_json::from_slice(&self.read_blob(&id)?)?;
+            notes.push(StoredNote { id, note });
+        }
+        notes.sort_by(|a, b| a.note.created.cmp(&b.note.created).then(a.id.cmp(&b.id)));
+        Ok(notes)
+    }
+
+    /// Id of the notes tree, if any note has been added or merged.
+    pub fn notes_root(&self) -> SdkResult<Option<ObjectId>> {
+        Ok(*self
+            .notes_lock()
+            .read()
+            .map_err(|_| SdkError::Internal("notes lock poisoned".into()))?)
+    }
+
+    /// Add every note in the notes tree `other` (another replica's
+    /// [`notes_root`](Self::notes_root), its objects already in the store)
+    /// to this repository's notes. Returns how many were new here.
+    pub fn merge_notes(&self, other: &ObjectId) -> SdkResult<usize> {
+        let theirs = self.note_index(Some(*other))?;
+        self.update_notes(|ours| {
+            let mut added = 0;
+            for (receipt, ids) in theirs {
+                let filed = ours.entry(receipt).or_default();
+                for id in ids {
+                    if !self.store().exists(&id)? {
+                        return Err(SdkError::ObjectNotFound(id.to_hex()));
+                    }
+                    added += usize::from(filed.insert(id));
+                }
+            }
+            Ok(added)
+        })
+    }
+
+    fn note_index(&self, root: Option<ObjectId>) -> SdkResult<NoteIndex> {
+        let mut index = NoteIndex::new();
+        let Some(root) = root else {
+            return Ok(index);
+        };
+        for entry in self.read_tree(&root)?.entries {
+            let notes = self.read_tree(&entry.object_id)?.entries;
+            index.insert(entry.name, notes.iter().map(|note| note.object_id).collect());
+        }
+        Ok(index)
+    }
+
+    /// Apply `f` to the note index and, if it reports any notes added,
+    /// write the new notes tree.
+    fn update_notes(
+        &self,
+        f: impl FnOnce(&mut NoteIndex) -> SdkResult<usize>,
+    ) -> SdkResult<usize> {
+        let mut current = self
+            .notes_lock()
+            .write()
+            .map_err(|_| SdkError::Internal("notes lock poisoned".into()))?;
+        let mut index = self.note_index(*current)?;
+        let added = f(&mut index)?;
+        if added == 0 {
+            return Ok(0);
+        }
+        let mut receipts = Vec::with_capacity(index.len());
+        for (receipt, ids) in &index {
+            let notes = ids
+                .iter()
+                .map(|id| TreeEntry::new(EntryMode::Regular, id.to_hex(), *id))
+                .collect();
+            let subtree = self.write_tree(notes)?;
+            receipts.push(TreeEntry::new(EntryMode::Directory, receipt.clone(), subtree));
+        }
+        let root = self.write_tree(receipts)?;
+        if let Some(path) = self.notes_path() {
+            std::fs::write(path, root.to_hex())?;
+        }
+        *current = Some(root);
+        Ok(added)
+    }
+
+    fn notes_path(&self) -> Option<PathBuf> {
+        self.repo_dir().map(|dir| dir.join(NOTES_FILE))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BundleOptions, CommitProposal};
+
+    #[test]
+    fn notes_persist_show_up_and_merge_through_bundles() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = Wll::init_at(dir.path()).unwrap();
+        let receipt = source.commit(CommitProposal::new("deploy")).unwrap().outcome_receipt;
+        let target = Wll::init_with_worldline(source.worldline().clone()).unwrap();
+        target.unbundle(source.create_bundle(&BundleOptions::new()).unwrap()).unwrap();
+
+        let note = source
+            .new_note(receipt.receipt_hash, "caused the outage")
+            .with_link("https://issues.example/42");
+        let ours = source.add_note(note).unwrap();
+        let theirs = target.add_note(target.new_note(receipt.receipt_hash, "fixed")).unwrap();
+        assert!(source.add_note(source.new_note([0; 32], "nowhere")).is_err());
+
+        let reopened = Wll::open(dir.path()).unwrap();
+        let details = reopened.receipt_details(&receipt.receipt_hash).unwrap();
+        assert_eq!(details.notes.len(), 1);
+        assert_eq!(details.notes[0], ours);
+
+        let bundle = source.create_bundle(&BundleOptions::new()).unwrap();
+        let bundle = crate::Bundle::from_bytes(&bundle.to_bytes().unwrap()).unwrap();
+        assert_eq!(target.unbundle(bundle.clone()).unwrap().notes_merged, 1);
+        assert_eq!(target.unbundle(bundle).unwrap().notes_merged, 0);
+        let ids: BTreeSet<ObjectId> =
+            target.notes(&receipt.receipt_hash).unwrap().iter().map(|n| n.id).collect();
+        assert_eq!(ids, BTreeSet::from([ours.id, theirs.id]));
+    }
+}
@@ -0,0 +1,357 @@
+//! Commits made without a connection to the remote.
+//!
+//! [`Wll::queue_commit`] records a proposal in a pending queue instead of
+//! the ledger, so nothing is judged or numbered until it can be judged
+//! against the remote's history. Queued tree commits form a provisional
+//! branch: each is diffed against the tree the previous one left, starting
+//! from the branch head when the queue was begun.
+//!
+//! [`AsyncWll::reconcile`](crate::AsyncWll::reconcile) pulls, replays the
+//! queue in order through the gate, which now sees the remote's policies
+//! and grants, and pushes what was accepted. A queued tree is merged with
+//! whatever the remote changed since it was made; changes to the same path
+//! on both sides stop the replay, leaving that commit and the ones after it
+//! queued for a manual merge.
+//!
+//! Persistent repositories keep the queue in `.wll/pending.json`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use wll_index::Index;
+use wll_store::{EntryMode, TreeEntry};
+use wll_sync::{PullResult, PushResult};
+use wll_types::ObjectId;
+
+use crate::commit::CommitProposal;
+use crate::error::{SdkError, SdkResult};
+use crate::repository::Wll;
+
+const PENDING_FILE: &str = "pending.json";
+
+/// A commit waiting to be replayed against the remote.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QueuedCommit {
+    /// Position in the queue, unique for the queue's lifetime.
+    pub id: u64,
+    /// Branch the commit was made on.
+    pub branch: String,
+    /// Tree the proposal's tree was made from, if it has one.
+    pub base_tree: Option<ObjectId>,
+    pub proposal: CommitProposal,
+    pub queued_at_ms: u64,
+}
+
+/// What replaying one queued commit came to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReplayStatus {
+    /// Committed. `merged` is set when the tree was combined with changes
+    /// the remote made after the commit was queued.
+    Accepted { receipt_hash: [u8; 32], merged: bool },
+    /// Refused by the gate; the rejection is recorded in the ledger.
+    Rejected { reason: String, stage: Option<String> },
+    /// The remote changed the same paths. The commit stays queued.
+    NeedsMerge { paths: Vec<String> },
+}
+
+/// One queued commit and how its replay went.
+#[derive(Clone, Debug)]
+pub struct ReplayedCommit {
+    pub id: u64,
+    pub message: String,
+    pub status: ReplayStatus,
+}
+
+/// Result of replaying the pending queue.
+#[derive(Clone, Debug, Default)]
+pub struct ReconcileReport {
+    /// Queued commits in the order they were replayed.
+    pub commits: Vec<ReplayedCommit>,
+    /// Commits still queued: those needing a merge, the ones queued after
+    /// them, and those made on other branches.
+    pub remaining: usize,
+    /// The pull that brought in the remote's history, if the remote has
+    /// the branch.
+    pub pull: Option<PullResult>,
+    /// The push of the accepted commits, if any were.
+    pub push: Option<PushResult>,
+}
+
+impl ReconcileReport {
+    pub fn accepted(&self) -> usize {
+        self.count(|s| matches!(s, ReplayStatus::Accepted { .. }))
+    }
+
+    pub fn rejected(&self) -> usize {
+        self.count(|s| matches!(s, ReplayStatus::Rejected { .. }))
+    }
+
+    pub fn needs_merge(&self) -> usize {
+        self.count(|s| matches!(s, ReplayStatus::NeedsMerge { .. }))
+    }
+
+    fn count(&self, f: impl Fn(&ReplayStatus) -> bool) -> usize {
+        self.commits.iter().filter(|c| f(&c.status)).count()
+    }
+}
+
+/// Read the queue saved in `repo_dir`, if any.
+pub(crate) fn load(repo_dir: &Path) -> SdkResult<Vec<QueuedCommit>> {
+    match std::fs::read(repo_dir.join(PENDING_FILE)) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map_err(|e| SdkError::Internal(format!("{PENDING_FILE}: {e}"))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+impl Wll {
+    /// Queue `proposal` to be committed once the remote is reachable.
+    ///
+    /// Nothing is written to the ledger and no branch moves. Attachments
+    /// must already be stored, as for [`commit`](Self::commit).
+    pub fn queue_commit(&self, proposal: CommitProposal) -> SdkResult<QueuedCommit> {
+        self.check_attachments(&proposal.attachments)?;
+        let base_tree = match proposal.tree {
+            Some(_) => self.provisional_tree()?,
+            None => None,
+        };
+        let queued = QueuedCommit {
+            id: 0,
+            branch: self.current_branch()?,
+            base_tree,
+            proposal,
+            queued_at_ms: self.clock().now_ms(),
+        };
+        let mut stored = None;
+        self.update_pending(|queue| {
+            let id = queue.last().map_or(1, |last| last.id + 1);
+            queue.push(QueuedCommit { id, ..queued });
+            stored = queue.last().cloned();
+        })?;
+        Ok(stored.expect("queued commit was just pushed"))
+    }
+
+    /// Write the staged tree from `index` and queue it, as
+    /// [`commit_tree`](Self::commit_tree) would commit it.
+    pub fn queue_commit_tree(
+        &self,
+        index: &mut Index,
+        proposal: CommitProposal,
+    ) -> SdkResult<QueuedCommit> {
+        let tree = index.write_tree()?;
+        self.queue_commit(proposal.with_tree(tree))
+    }
+
+    /// Queued commits, oldest first.
+    pub fn pending_commits(&self) -> SdkResult<Vec<QueuedCommit>> {
+        Ok(self
+            .pending_list()
+            .read()
+            .map_err(|_| SdkError::Internal("pending queue lock poisoned".into()))?
+            .clone())
+    }
+
+    /// Tree at the end of the current branch's queue, or the head tree if
+    /// nothing with a tree is queued on it.
+    pub fn provisional_tree(&self) -> SdkResult<Option<ObjectId>> {
+        let branch = self.current_branch()?;
+        let queued = self
+            .pending_commits()?
+            .into_iter()
+            .rev()
+            .find(|c| c.branch == branch && c.proposal.tree.is_some());
+        match queued {
+            Some(commit) => Ok(commit.proposal.tree),
+            None => self.head_tree(),
+        }
+    }
+
+    /// Drop a queued commit, returning it. Used to give up on a commit
+    /// that needs a merge once its changes have been committed by hand.
+    pub fn discard_queued(&self, id: u64) -> SdkResult<Option<QueuedCommit>> {
+        let mut removed = None;
+        self.update_pending(|queue| {
+            if let Some(index) = queue.iter().position(|c| c.id == id) {
+                removed = Some(queue.remove(index));
+            }
+        })?;
+        Ok(removed)
+    }
+
+    /// Commit the current branch's queue in order.
+    ///
+    /// A queued tree is merged onto the head tree when the head has moved
+    /// since it was made. Accepted and rejected commits leave the queue; the
+    /// first one needing a merge stops the replay.
+    pub fn replay_queued(&self) -> SdkResult<ReconcileReport> {
+        let branch = self.current_branch()?;
+        let mut report = ReconcileReport::default();
+        for queued in self.pending_commits()? {
+            if queued.branch != branch {
+                continue;
+            }
+            let mut proposal = queued.proposal.clone();
+            let mut merged = false;
+            if let Some(tree) = proposal.tree {
+                let head = self.head_tree()?;
+                if head != queued.base_tree {
+                    let base = queued.base_tree.as_ref();
+                    match self.merge_queued_tree(base, head.as_ref(), &tree)? {
+                        Ok(merged_tree) => {
+                            proposal.tree = Some(merged_tree);
+                            merged = true;
+                        }
+                        Err(paths) => {
+                            report.commits.push(ReplayedCommit {
+                                id: queued.id,
+                                message: queued.proposal.message,
+                                status: ReplayStatus::NeedsMerge { paths },
+                            });
+                            break;
+                        }
+                    }
+                }
+            }
+            let status = match self.commit(proposal) {
+                Ok(result) => ReplayStatus::Accepted {
+                    receipt_hash: result.receipt_hash,
+                    merged,
+                },
+                Err(SdkError::CommitmentRejected { reason, stage, .. }) => {
+                    ReplayStatus::Rejected { reason, stage }
+                }
+                Err(e) => return Err(e),
+            };
+            self.update_pending(|queue| queue.retain(|c| c.id != queued.id))?;
+            report.commits.push(ReplayedCommit {
+                id: queued.id,
+                message: queued.proposal.message,
+                status,
+            });
+        }
+        report.remaining = self.pending_commits()?.len();
+        Ok(report)
+    }
+
+    /// Three-way merge of `ours` and `head` from their common `base`.
+    ///
+    /// Returns the merged tree, or the paths both sides changed
+    /// differently.
+    fn merge_queued_tree(
+        &self,
+        base: Option<&ObjectId>,
+        head: Option<&ObjectId>,
+        ours: &ObjectId,
+    ) -> SdkResult<Result<ObjectId, Vec<String>>> {
+        type Entries = BTreeMap<String, (ObjectId, EntryMode)>;
+        let entries = |tree: Option<&ObjectId>| -> SdkResult<Entries> {
+            let Some(tree) = tree else { return Ok(BTreeMap::new()) };
+            Ok(self
+                .read_tree(tree)?
+                .entries
+                .into_iter()
+                .map(|e| (e.name, (e.object_id, e.mode)))
+                .collect())
+        };
+        let (base, head, ours) = (entries(base)?, entries(head)?, entries(Some(ours))?);
+        let paths: BTreeSet<&String> = base.keys().chain(head.keys()).chain(ours.keys()).collect();
+
+        let mut merged = Vec::new();
+        let mut conflicts = Vec::new();
+        for path in paths {
+            let (b, h, o) = (base.get(path), head.get(path), ours.get(path));
+            let side = if o == b || o == h {
+                h
+            } else if h == b {
+                o
+            } else {
+                conflicts.push(path.clone());
+                continue;
+            };
+            if let Some((id, mode)) = side {
+                merged.push(TreeEntry::new(*mode, path.as_str(), *id));
+            }
+        }
+        if !conflicts.is_empty() {
+            return Ok(Err(conflicts));
+        }
+        Ok(Ok(self.write_tree(merged)?))
+    }
+
+    pub(crate) fn update_pending(&self, f: impl FnOnce(&mut Vec<QueuedCommit>)) -> SdkResult<()> {
+        let mut queue = self
+            .pending_list()
+            .write()
+            .map_err(|_| SdkError::Internal("pending queue lock poisoned".into()))?;
+        f(&mut queue);
+        if let Some(path) = self.pending_path() {
+            if queue.is_empty() {
+                match std::fs::remove_file(&path) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                    _ => {}
+                }
+            } else {
+                std::fs::write(path, serde_json::to_vec_pretty(&*queue)?)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn pending_path(&self) -> Option<PathBuf> {
+        self.repo_dir().map(|dir| dir.join(PENDING_FILE))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queued_trees_merge_onto_a_moved_head_or_stop_on_conflict() {
+        let dir = tempfile::tempdir().unwrap();
+        let wll = Wll::init_at(dir.path()).unwrap();
+        let mut index = wll.new_index();
+        index.stage_file("a.txt", b"a", EntryMode::Regular).unwrap();
+        index.stage_file("b.txt", b"b", EntryMode::Regular).unwrap();
+        wll.commit_tree(&mut index, CommitProposal::new("base")).unwrap();
+
+        index.stage_file("a.txt", b"a offline", EntryMode::Regular).unwrap();
+        let first = wll.queue_commit_tree(&mut index, CommitProposal::new("edit a")).unwrap();
+        index.stage_file("b.txt", b"b offline", EntryMode::Regular).unwrap();
+        wll.queue_commit_tree(&mut index, CommitProposal::new("edit b")).unwrap();
+        assert_eq!(first.base_tree, wll.head_tree().unwrap());
+        assert_eq!(wll.receipt_count().unwrap(), 2);
+        assert_eq!(Wll::open(dir.path()).unwrap().pending_commits().unwrap().len(), 2);
+
+        // Meanwhile b.txt changed upstream and c.txt appeared.
+        let mut upstream = wll.new_index();
+        for (path, content) in [("a.txt", "a"), ("b.txt", "b upstream"), ("c.txt", "c")] {
+            upstream.stage_file(path, content.as_bytes(), EntryMode::Regular).unwrap();
+        }
+        wll.commit_tree(&mut upstream, CommitProposal::new("upstream")).unwrap();
+
+        let report = wll.replay_queued().unwrap();
+        assert_eq!((report.accepted(), report.needs_merge(), report.remaining), (1, 1, 1));
+        assert!(matches!(
+            report.commits[0].status,
+            ReplayStatus::Accepted { merged: true, .. }
+        ));
+        assert_eq!(
+            report.commits[1].status,
+            ReplayStatus::NeedsMerge { paths: vec!["b.txt".into()] }
+        );
+        let names: Vec<String> = wll
+            .read_tree(&wll.head_tree().unwrap().unwrap())
+            .unwrap()
+            .entries
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(names, ["a.txt", "b.txt", "c.txt"]);
+
+        wll.discard_queued(2).unwrap();
+        assert!(!dir.path().join(".wll").join(PENDING_FILE).exists());
+    }
+}
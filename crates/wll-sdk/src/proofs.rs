@@ -0,0 +1,168 @@
+//! Verification of the proofs attached to outcome receipts.
+//!
+//! Proof artifacts are fetched like evidence: `file://` paths in the work
+//! tree, `obj://` objects, and `http(s)://` URLs, each pinned by the digest
+//! in its [`ProofRef`]. The repository's [`ProofVerifiers`] then check what
+//! each artifact claims; see [`Wll::with_proof_verifiers`].
+//!
+//! [`ProofVerifiers`]: wll_ledger::ProofVerifiers
+
+use std::sync::Arc;
+
+use wll_gate::{
+    EvidenceError, EvidencePin, EvidenceRef, EvidenceResolver, FileResolver, HttpResolver,
+    ObjectStoreResolver,
+};
+use wll_ledger::{
+    OutcomeProofCheck, OutcomeReceipt, ProofCheck, ProofError, ProofRef, StreamValidator,
+    ValidationReport,
+};
+use wll_types::HashAlgo;
+
+use crate::error::SdkResult;
+use crate::repository::Wll;
+
+impl Wll {
+    /// Fetch and verify every proof attached to `outcome`.
+    pub fn verify_outcome_proofs(&self, outcome: &OutcomeReceipt) -> Vec<ProofCheck> {
+        let resolvers = self.proof_resolvers();
+        outcome
+            .proofs
+            .iter()
+            .map(|proof| ProofCheck {
+                proof: proof.clone(),
+                result: fetch(&resolvers, proof)
+                    .and_then(|artifact| self.proof_verifiers().verify(proof, &artifact, outcome)),
+            })
+            .collect()
+    }
+
+    /// [`Wll::verify`], also verifying every outcome proof.
+    pub fn verify_strict(&self) -> SdkResult<ValidationReport> {
+        Ok(StreamValidator::validate_stream_strict(
+            self.ledger(),
+            self.worldline(),
+            self,
+        )?)
+    }
+
+    fn proof_resolvers(&self) -> Vec<Arc<dyn EvidenceResolver>> {
+        let mut resolvers: Vec<Arc<dyn EvidenceResolver>> =
+            vec![Arc::new(ObjectStoreResolver::new(self.shared_store()))];
+        if let Some(work_tree) = self.repo_dir().and_then(|dir| dir.parent()) {
+            resolvers.push(Arc::new(FileResolver::new(work_tree)));
+        }
+        resolvers.push(Arc::new(HttpResolver::new()));
+        resolvers
+    }
+}
+
+impl OutcomeProofCheck for Wll {
+    fn check_outcome_proofs(&self, outcome: &OutcomeReceipt) -> Vec<ProofCheck> {
+        self.verify_outcome_proofs(outcome)
+    }
+}
+
+/// Fetch the artifact behind `proof`, pinned to its BLAKE3 digest.
+fn fetch(resolvers: &[Arc<dyn EvidenceResolver>], proof: &ProofRef) -> Result<Vec<u8>, ProofError> {
+    let mut reference =
+        EvidenceRef::parse(&proof.uri).map_err(|e| ProofError::Malformed(e.to_string()))?;
+    reference.pin = Some(EvidencePin {
+        algo: HashAlgo::Blake3,
+        digest: proof.digest,
+    });
+    let resolver = resolvers
+        .iter()
+        .find(|r| r.schemes().contains(&reference.scheme.as_str()))
+        .ok_or_else(|| ProofError::Unavailable(format!("no resolver for {}", proof.uri)))?;
+    resolver.resolve(&reference).map_err(|e| match e {
+        EvidenceError::Invalid(reason) => ProofError::Malformed(reason),
+        EvidenceError::Redacted(reason) => ProofError::Redacted(reason),
+        other => ProofError::Unavailable(other.to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use wll_crypto::SigningKey;
+    use wll_ledger::proof::statement_message;
+    use wll_ledger::{OutcomeRecord, SignedStatementVerifier};
+
+    use super::*;
+    use crate::commit::CommitProposal;
+
+    /// Commit, then append an outcome for that commitment whose proof is
+    /// the artifact built from its receipt hash, stored as a blob.
+    fn outcome_with_proof(wll: &Wll, artifact: impl Fn(&[u8; 32]) -> Vec<u8>) -> OutcomeReceipt {
+        let commitment = wll
+            .commit(CommitProposal::new("deploy"))
+            .unwrap()
+            .commitment_receipt;
+        let artifact = artifact(&commitment.receipt_hash);
+        let blob = wll.write_blob(&artifact).unwrap();
+        wll.ledger()
+            .append_outcome(
+                commitment.receipt_hash,
+                &OutcomeRecord {
+                    effects: vec![],
+                    proofs: vec![ProofRef {
+                        uri: format!("obj://{}", blob.to_hex()),
+                        digest: *blake3::hash(&artifact).as_bytes(),
+                    }],
+                    state_updates: vec![],
+                    metadata: BTreeMap::new(),
+                },
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn strict_verification_checks_outcome_proofs() {
+        let signer = SigningKey::from_bytes([3; 32]);
+        let wll = Wll::init().unwrap();
+        let outcome = outcome_with_proof(&wll, |subject| {
+            let signature = signer.sign(&statement_message(subject, "deployed"));
+            serde_json::to_vec(&serde_json::json!({
+                "type": "ed25519-statement",
+                "key": hex::encode(signer.verifying_key().as_bytes()),
+                "subject": hex::encode(subject),
+                "statement": "deployed",
+                "signature": hex::encode(signature.to_bytes()),
+            }))
+            .unwrap()
+        });
+
+        assert!(wll.verify().unwrap().is_valid());
+        let checks = wll.verify_outcome_proofs(&outcome);
+        assert!(matches!(checks[0].result, Err(ProofError::UnknownType(_))));
+        assert!(!wll.verify_strict().unwrap().proofs_verified);
+
+        let wll = wll.with_proof_verifiers(wll_ledger::ProofVerifiers::new().with_verifier(
+            Arc::new(SignedStatementVerifier::new().with_trusted_key(signer.verifying_key())),
+        ));
+        assert!(wll.verify_outcome_proofs(&outcome)[0].is_valid());
+        assert!(wll.verify_strict().unwrap().is_valid());
+    }
+
+    #[test]
+    fn missing_artifacts_are_unavailable() {
+        let wll = Wll::init().unwrap();
+        let outcome = outcome_with_proof(&wll, |_| b"{}".to_vec());
+        let mut missing = outcome.clone();
+        missing.proofs[0].uri = format!(
+            "obj://{}",
+            wll_types::ObjectId::from_bytes(b"gone").to_hex()
+        );
+        missing.proofs.push(ProofRef {
+            uri: "issue://PROJ-1".into(),
+            digest: [0; 32],
+        });
+
+        let checks = wll.verify_outcome_proofs(&missing);
+        assert!(checks
+            .iter()
+            .all(|c| matches!(c.result, Err(ProofError::Unavailable(_)))));
+    }
+}
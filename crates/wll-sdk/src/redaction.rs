@@ -0,0 +1,242 @@
+//! Redaction of object content.
+//!
+//! Content sometimes has to go while the history that references it stays.
+//! [`Wll::redact`] commits a signed redaction receipt through the gate, then
+//! replaces the object's payload with a [`Tombstone`] stored under the same
+//! id, so trees, receipts, and the hash chain are untouched. Reading the
+//! content afterwards fails with [`StoreError::Redacted`].
+//!
+//! The tombstone records what was removed, why, who signed the removal, and
+//! the receipt that recorded it; [`Wll::verify_redaction`] checks it against
+//! that receipt. Tombstones travel in packs like other objects, but a peer
+//! that already holds the content keeps it until it redacts it too.
+
+use wll_crypto::{Signature, SigningKey, VerifyingKey};
+use wll_ledger::{OutcomeReceipt, Receipt};
+use wll_store::{ObjectKind, StoreError, Tombstone};
+use wll_types::{CommitmentClass, ObjectId};
+
+use crate::commit::CommitProposal;
+use crate::error::{SdkError, SdkResult};
+use crate::repository::Wll;
+
+/// Commitment class of redaction receipts, for policies that govern them.
+pub const REDACTION_CLASS: &str = "redaction";
+/// Outcome metadata key holding the hex id of the redacted object.
+pub const REDACTION_OBJECT_KEY: &str = "redaction.object";
+/// Outcome metadata key holding the redacted object's size in bytes.
+pub const REDACTION_SIZE_KEY: &str = "redaction.size";
+/// Outcome metadata key holding why the content was removed.
+pub const REDACTION_REASON_KEY: &str = "redaction.reason";
+/// Outcome metadata key holding the redactor's hex Ed25519 public key.
+pub const REDACTION_SIGNER_KEY: &str = "redaction.signer";
+/// Outcome metadata key holding the redactor's hex signature.
+pub const REDACTION_SIGNATURE_KEY: &str = "redaction.signature";
+
+/// A redaction recorded in the ledger.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Redaction {
+    /// The object whose content was removed.
+    pub object: ObjectId,
+    /// Size of the removed content in bytes.
+    pub size: u64,
+    /// Why the content was removed.
+    pub reason: String,
+    /// Who signed the redaction.
+    pub signer: VerifyingKey,
+    /// Signature over [`Tombstone::signed_message`].
+    pub signature: Signature,
+    /// Hash of the outcome receipt that recorded the redaction.
+    pub receipt_hash: [u8; 32],
+}
+
+impl Redaction {
+    /// The redaction recorded by `outcome`, if it is an accepted one.
+    pub fn from_outcome(outcome: &OutcomeReceipt) -> Option<Self> {
+        if !outcome.accepted {
+            return None;
+        }
+        let get = |key| outcome.metadata.get(key);
+        let signer: [u8; 32] = hex::decode(get(REDACTION_SIGNER_KEY)?)
+            .ok()?
+            .try_into()
+            .ok()?;
+        let signature: [u8; 64] = hex::decode(get(REDACTION_SIGNATURE_KEY)?)
+            .ok()?
+            .try_into()
+            .ok()?;
+        Some(Self {
+            object: ObjectId::from_hex(get(REDACTION_OBJECT_KEY)?).ok()?,
+            size: get(REDACTION_SIZE_KEY)?.parse().ok()?,
+            reason: get(REDACTION_REASON_KEY)?.clone(),
+            signer: VerifyingKey::from_bytes(signer).ok()?,
+            signature: Signature::from_bytes(signature),
+            receipt_hash: outcome.receipt_hash,
+        })
+    }
+
+    /// The tombstone this redaction leaves in place of a blob.
+    pub fn tombstone(&self) -> Tombstone {
+        Tombstone {
+            original: self.object,
+            original_kind: ObjectKind::Blob,
+            original_size: self.size,
+            reason: self.reason.clone(),
+            signer: self.signer.as_bytes(),
+            signature: self.signature.clone(),
+            receipt_hash: self.receipt_hash,
+        }
+    }
+}
+
+impl Wll {
+    /// Remove the content of blob `id`, keeping its history.
+    ///
+    /// `key` signs the redaction. The redaction is committed like any
+    /// change, as class [`REDACTION_CLASS`] targeting `obj://<id>`, so the
+    /// gate decides whether it may happen; the current tree is carried
+    /// over unchanged. Only then is the blob replaced by its tombstone.
+    pub fn redact(&self, id: &ObjectId, reason: &str, key: &SigningKey) -> SdkResult<Redaction> {
+        let object = self
+            .store()
+            .read_shared(id)?
+            .ok_or_else(|| SdkError::ObjectNotFound(id.to_hex()))?;
+        match object.kind {
+            ObjectKind::Blob => {}
+            ObjectKind::Tombstone => {
+                return Err(SdkError::InvalidOperation(format!(
+                    "object {} is already redacted",
+                    id.to_hex()
+                )))
+            }
+            other => {
+                return Err(SdkError::InvalidOperation(format!(
+                    "only blobs can be redacted; {} is a {other}",
+                    id.to_hex()
+                )))
+            }
+        }
+
+        let signer = key.verifying_key();
+        let message = Tombstone::signed_message(id, ObjectKind::Blob, object.size, reason);
+        let signature = key.sign(&message);
+        let mut proposal = CommitProposal::new(format!("redact {}: {reason}", id.to_hex()))
+            .with_class(CommitmentClass::Custom(REDACTION_CLASS.into()))
+            .with_target(format!("obj://{}", id.to_hex()))
+            .with_metadata(REDACTION_OBJECT_KEY, id.to_hex())
+            .with_metadata(REDACTION_SIZE_KEY, object.size.to_string())
+            .with_metadata(REDACTION_REASON_KEY, reason)
+            .with_metadata(REDACTION_SIGNER_KEY, hex::encode(signer.as_bytes()))
+            .with_metadata(REDACTION_SIGNATURE_KEY, hex::encode(signature.to_bytes()));
+        if let Some(tree) = self.head_tree()? {
+            proposal = proposal.with_tree(tree);
+        }
+        let result = self.commit(proposal)?;
+
+        let redaction = Redaction {
+            object: *id,
+            size: object.size,
+            reason: reason.to_string(),
+            signer,
+            signature,
+            receipt_hash: result.receipt_hash,
+        };
+        self.store().redact(&redaction.tombstone())?;
+        Ok(redaction)
+    }
+
+    /// Every redaction recorded on this worldline, oldest first.
+    pub fn redactions(&self) -> SdkResult<Vec<Redaction>> {
+        Ok(self
+            .ledger()
+            .read_all(self.worldline())?
+            .iter()
+            .filter_map(|receipt| match receipt {
+                Receipt::Outcome(outcome) => Redaction::from_outcome(outcome),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// The redaction that left `id` a tombstone, or `None` if its content
+    /// is still present.
+    ///
+    /// Fails if the tombstone's signature does not verify or it does not
+    /// match the redaction receipt it names, which means it was not made by
+    /// [`Wll::redact`] in this repository's history.
+    pub fn verify_redaction(&self, id: &ObjectId) -> SdkResult<Option<Redaction>> {
+        let object = self
+            .store()
+            .read_shared(id)?
+            .ok_or_else(|| SdkError::ObjectNotFound(id.to_hex()))?;
+        if !object.is_tombstone() {
+            return Ok(None);
+        }
+        let tombstone = Tombstone::from_stored_object(&object)?;
+        tombstone.verify_signature()?;
+
+        let corrupt = |reason: &str| {
+            SdkError::Store(StoreError::CorruptObject {
+                id: *id,
+                reason: reason.to_string(),
+            })
+        };
+        let redaction = match self.ledger().get_by_hash(tombstone.receipt_hash)? {
+            Some(Receipt::Outcome(outcome)) => Redaction::from_outcome(&outcome),
+            _ => None,
+        }
+        .ok_or_else(|| corrupt("tombstone names no redaction receipt"))?;
+        if redaction.tombstone() != tombstone {
+            return Err(corrupt("tombstone does not match its redaction receipt"));
+        }
+        Ok(Some(redaction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wll_store::EntryMode;
+
+    #[test]
+    fn redacted_blob_leaves_a_verifiable_tombstone() {
+        let wll = Wll::init().unwrap();
+        let mut index = wll.new_index();
+        index.stage_file("secret.txt", b"api-key=hunter2", EntryMode::Regular).unwrap();
+        index.stage_file("README.md", b"hello", EntryMode::Regular).unwrap();
+        let committed = wll.commit_tree(&mut index, CommitProposal::new("add files")).unwrap();
+        let tree = committed.tree.unwrap();
+        let secret = wll.read_tree(&tree).unwrap().get("secret.txt").unwrap().object_id;
+
+        let key = SigningKey::from_bytes([4; 32]);
+        let redaction = wll.redact(&secret, "leaked credential", &key).unwrap();
+        assert_eq!(redaction.size, 15);
+        assert_eq!(wll.redactions().unwrap(), vec![redaction.clone()]);
+
+        // The content is gone; the history and the tree still hold.
+        assert!(matches!(
+            wll.read_blob(&secret),
+            Err(SdkError::Store(StoreError::Redacted { .. }))
+        ));
+        assert_eq!(wll.head_tree().unwrap(), Some(tree));
+        assert!(wll.verify().unwrap().is_valid());
+        assert!(wll.verify_strict().unwrap().is_valid());
+        assert_eq!(wll.verify_redaction(&secret).unwrap(), Some(redaction));
+        assert_eq!(wll.verify_redaction(&tree).unwrap(), None);
+        assert!(matches!(
+            wll.redact(&secret, "again", &key),
+            Err(SdkError::InvalidOperation(_))
+        ));
+        assert!(matches!(wll.redact(&tree, "tree", &key), Err(SdkError::InvalidOperation(_))));
+
+        // A tombstone not backed by a receipt is caught.
+        let readme = wll.read_tree(&tree).unwrap().get("README.md").unwrap().object_id;
+        let mut forged = wll.redactions().unwrap()[0].tombstone();
+        forged.original = readme;
+        forged.original_size = 5;
+        let message = Tombstone::signed_message(&readme, ObjectKind::Blob, 5, &forged.reason);
+        forged.signature = key.sign(&message);
+        wll.store().redact(&forged).unwrap();
+        assert!(wll.verify_redaction(&readme).is_err());
+    }
+}
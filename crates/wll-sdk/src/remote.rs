@@ -0,0 +1,638 @@
+//! Repository-side halves of push and pull.
+//!
+//! [`AsyncWll`](crate::AsyncWll) drives the conversation with a
+//! [`RemoteTransport`](wll_sync::RemoteTransport); the methods here do the
+//! blocking ledger, ref and object-store work between those calls. Branches
+//! of one worldline share a single receipt stream, so "fast-forward" means
+//! the old tip appears earlier in that stream than the new one.
+//!
+//! Received packs are unpacked into a [`Quarantine`] in front of the object
+//! store. Objects move into the store only after they, and the receipts
+//! that arrived with them, pass verification; a failed transfer drops the
+//! quarantine and leaves the store as it was.
+//!
+//! Packs sent to a peer whose tips are known are thin: a changed file goes
+//! as a delta against its version in a tree the peer already holds, and the
+//! receiving side appends those bases from its own store before unpacking.
+//!
+//! [`Wll::accept_atomic_push`] is the receiving half of an atomic push: the
+//! pack and receipts are staged and checked together with every ref update
+//! before any of them touches the repository.
+
+use std::path::Path;
+
+use std::collections::{BTreeMap, HashMap};
+
+use wll_config::SyncSettings;
+use wll_ledger::{detect_encoding, Receipt};
+use wll_pack::{PackReader, PackWriter};
+use wll_protocol::ConsistencyToken;
+use wll_refs::{Ref, RefTransaction};
+use wll_store::{EntryMode, ObjectKind, ObjectStore, Quarantine, Tree};
+use wll_sync::{
+    MergeStatus, NegotiationEngine, RefRejection, RefUpdate, SyncError, SyncVerifier,
+    TrustRoots, UnresolvedReference,
+};
+use wll_types::{ObjectId, WorldlineId};
+
+use crate::error::{SdkError, SdkResult};
+use crate::repository::{outcome_tree, Wll};
+use crate::time_anchor::parse_public_key;
+
+/// Everything a push sends.
+pub(crate) struct OutgoingPush {
+    pub updates: Vec<RefUpdate>,
+    pub receipts: Vec<Receipt>,
+    pub pack: Vec<u8>,
+    pub object_count: usize,
+}
+
+/// One ref a push moves.
+pub(crate) struct PushTarget {
+    pub local_ref: String,
+    pub remote_ref: String,
+    /// Where the remote ref points now.
+    pub remote_tip: Option<[u8; 32]>,
+    pub force: bool,
+}
+
+impl Wll {
+    /// Receipt hash `ref_name` points at, or `None` for a missing or unborn ref.
+    pub(crate) fn ref_tip(&self, ref_name: &str) -> SdkResult<Option<[u8; 32]>> {
+        Ok(self
+            .refs()
+            .read_ref(ref_name)?
+            .map(|r| *r.target_hash())
+            .filter(|hash| *hash != [0; 32]))
+    }
+
+    /// Every ref with a tip, as `(full name, receipt hash)` pairs.
+    pub(crate) fn ref_tips(&self) -> SdkResult<Vec<(String, [u8; 32])>> {
+        Ok(self
+            .refs()
+            .list_refs("refs/")?
+            .into_iter()
+            .map(|(name, r)| (name, *r.target_hash()))
+            .filter(|(_, hash)| *hash != [0; 32])
+            .collect())
+    }
+
+    /// Sequence number of `receipt_hash` in this worldline's stream.
+    pub(crate) fn local_seq(&self, receipt_hash: [u8; 32]) -> SdkResult<Option<u64>> {
+        Ok(self
+            .ledger()
+            .get_by_hash(receipt_hash)?
+            .filter(|r| r.worldline() == self.worldline())
+            .map(|r| r.seq()))
+    }
+
+    /// Where each worldline's receipt stream in this repository ends.
+    ///
+    /// A server answers with this so clients can tell whether it has
+    /// caught up with a write they made elsewhere.
+    pub fn consistency_token(&self) -> SdkResult<ConsistencyToken> {
+        let mut token = ConsistencyToken::new();
+        for worldline in self.ledger().worldlines()? {
+            if let Some(head) = self.ledger().head(&worldline)? {
+                token = token.with_position(worldline, head.seq, head.receipt_hash);
+            }
+        }
+        Ok(token)
+    }
+
+    /// Collect the receipts and objects needed to move each target's
+    /// remote ref from its remote tip to the tip of its local ref.
+    ///
+    /// Returns `None` when the remote is already there. Unless a target is
+    /// forced, a remote tip that is unknown locally or ahead of the local tip
+    /// is rejected as a non-fast-forward.
+    pub(crate) fn prepare_push(&self, targets: &[PushTarget]) -> SdkResult<Option<OutgoingPush>> {
+        let mut updates = Vec::new();
+        let mut receipts = BTreeMap::new();
+        for target in targets {
+            let tip = self
+                .ref_tip(&target.local_ref)?
+                .ok_or_else(|| SdkError::BranchNotFound(target.local_ref.clone()))?;
+            if target.remote_tip == Some(tip) {
+                continue;
+            }
+            let tip_seq = self
+                .local_seq(tip)?
+                .ok_or_else(|| SdkError::ObjectNotFound(hex::encode(tip)))?;
+
+            let since = match target.remote_tip {
+                None => 0,
+                Some(remote) => match self.local_seq(remote)? {
+                    Some(seq) if seq <= tip_seq => seq,
+                    Some(_) if target.force => tip_seq,
+                    None if target.force => 0,
+                    _ => return Err(SyncError::NotFastForward(target.remote_ref.clone()).into()),
+                },
+            };
+            if since < tip_seq {
+                for receipt in self.ledger().read_range(self.worldline(), since + 1, tip_seq)? {
+                    receipts.entry(receipt.seq()).or_insert(receipt);
+                }
+            }
+            updates.push(RefUpdate {
+                name: target.remote_ref.clone(),
+                old_hash: target.remote_tip,
+                new_hash: tip,
+            });
+        }
+        if updates.is_empty() {
+            return Ok(None);
+        }
+
+        // The remote holds everything up to each of its tips.
+        let receipts: Vec<Receipt> = receipts.into_values().collect();
+        let remote_tips: Vec<[u8; 32]> = targets.iter().filter_map(|t| t.remote_tip).collect();
+        let (pack, object_count) =
+            self.pack_objects_for(&self.receipt_objects(&receipts)?, &remote_tips)?;
+        Ok(Some(OutgoingPush {
+            updates,
+            receipts,
+            pack,
+            object_count,
+        }))
+    }
+
+    /// Apply a pushed pack, receipts, and ref updates as one transaction,
+    /// for serving [`RemoteTransport::push_atomic`].
+    ///
+    /// The pack is quarantined and the receipts staged: the pack's objects
+    /// must verify, the receipts must extend this worldline's stream with
+    /// every object they refer to present and decisions this repository's
+    /// gate agrees with, and each update must find its ref where the pusher
+    /// saw it and fast-forward it. Only when all of that holds are the
+    /// objects migrated, the receipts imported, and the refs moved in one
+    /// [`RefTransaction`]. Otherwise the quarantine is discarded and every
+    /// update is returned as rejected.
+    ///
+    /// [`RemoteTransport::push_atomic`]: wll_sync::RemoteTransport::push_atomic
+    pub fn accept_atomic_push(
+        &self,
+        pack: Vec<u8>,
+        receipts: Vec<Receipt>,
+        updates: &[RefUpdate],
+    ) -> SdkResult<Vec<RefRejection>> {
+        let reject_all = |reason: String| {
+            updates
+                .iter()
+                .map(|update| RefRejection {
+                    name: update.name.clone(),
+                    reason: reason.clone(),
+                })
+                .collect()
+        };
+        let quarantine = match self.quarantine_pack(Some(pack)) {
+            Ok(quarantine) => quarantine,
+            Err(e) => return Ok(reject_all(format!("pack refused: {e}"))),
+        };
+        let receipts = match self.stage_receipts(receipts, &quarantine) {
+            Ok(receipts) => receipts,
+            Err(e) => return Ok(reject_all(format!("receipts refused: {e}"))),
+        };
+
+        let mut transaction = RefTransaction::new();
+        let mut rejected = Vec::new();
+        for update in updates {
+            match self.stage_ref_update(update, &receipts) {
+                Ok(reference) => {
+                    transaction = transaction.update(&update.name, update.old_hash, reference)
+                }
+                Err(e) => rejected.push(RefRejection {
+                    name: update.name.clone(),
+                    reason: e.to_string(),
+                }),
+            }
+        }
+        if !rejected.is_empty() {
+            for update in updates {
+                if rejected.iter().all(|r| r.name != update.name) {
+                    rejected.push(RefRejection {
+                        name: update.name.clone(),
+                        reason: "atomic push aborted".into(),
+                    });
+                }
+            }
+            return Ok(rejected);
+        }
+
+        quarantine.migrate()?;
+        self.import_receipts(receipts)?;
+        self.refs().commit_transaction(&transaction)?;
+        Ok(Vec::new())
+    }
+
+    /// Unpack a received pack into a [`Quarantine`] in front of the store
+    /// and verify its objects. Nothing reaches the store until the
+    /// quarantine is migrated; without a pack it starts empty. A thin pack
+    /// is fixed with delta bases from the store, and objects the store
+    /// already holds, those bases among them, are not quarantined again.
+    /// Structured objects are checked against `limits.objects` before
+    /// they are decoded.
+    pub(crate) fn quarantine_pack(&self, pack: Option<Vec<u8>>) -> SdkResult<Quarantine<'_>> {
+        let limits = self.config()?.limits()?.objects;
+        let quarantine = Quarantine::new(self.store())?.with_limits(limits);
+        if let Some(pack) = pack {
+            let reader =
+                PackReader::from_thin_pack_bytes(pack, |id| self.store().read(id).ok().flatten())?;
+            for id in reader.object_ids() {
+                if self.store().exists(id)? {
+                    continue;
+                }
+                if let Some(object) = reader.read_object(id)? {
+                    quarantine.write(&object)?;
+                }
+            }
+            quarantine.verify()?;
+        }
+        Ok(quarantine)
+    }
+
+    /// Check that `receipts` extend this worldline's stream, that the
+    /// objects they refer to are in `objects`, and that the gate agrees
+    /// with every decision it can re-run, dropping receipts already
+    /// present. Nothing is written.
+    pub(crate) fn stage_receipts(
+        &self,
+        receipts: Vec<Receipt>,
+        objects: &dyn ObjectStore,
+    ) -> SdkResult<Vec<Receipt>> {
+        let mut head = self
+            .ledger()
+            .head(self.worldline())?
+            .map(|h| (h.seq, h.receipt_hash));
+        let mut fresh = Vec::new();
+        for receipt in receipts {
+            if receipt.worldline() != self.worldline() {
+                return Err(SdkError::InvalidOperation(format!(
+                    "received receipt for foreign worldline {}",
+                    receipt.worldline()
+                )));
+            }
+            if self.ledger().get_by_hash(receipt.receipt_hash())?.is_some() {
+                continue;
+            }
+            let expected_seq = head.map_or(1, |(seq, _)| seq + 1);
+            if receipt.seq() != expected_seq || receipt.prev_hash() != head.map(|(_, h)| h) {
+                return Err(SdkError::InvalidOperation(format!(
+                    "receipt at seq {} does not extend the stream at seq {}",
+                    receipt.seq(),
+                    expected_seq - 1
+                )));
+            }
+            if detect_encoding(&receipt)?.is_none() {
+                return Err(SdkError::InvalidOperation(format!(
+                    "receipt at seq {} does not match its hash",
+                    receipt.seq()
+                )));
+            }
+            head = Some((receipt.seq(), receipt.receipt_hash()));
+            fresh.push(receipt);
+        }
+        for id in self.receipt_objects(&fresh)? {
+            if !objects.exists(&id)? {
+                return Err(SdkError::ObjectNotFound(id.to_hex()));
+            }
+        }
+        for receipt in &fresh {
+            let Receipt::Commitment(commitment) = receipt else {
+                continue;
+            };
+            let outcome = fresh.iter().find_map(|r| match r {
+                Receipt::Outcome(o) if o.commitment_receipt_hash == commitment.receipt_hash => {
+                    Some(o)
+                }
+                _ => None,
+            });
+            // Commitments under a policy version this repository lacks are
+            // taken as recorded, as strict replay does.
+            if let Some(decision) = self.reevaluate(commitment, outcome)? {
+                if decision.is_accepted() != commitment.decision.is_accepted() {
+                    return Err(SdkError::InvalidOperation(format!(
+                        "the gate does not agree with the decision recorded at seq {}",
+                        commitment.seq
+                    )));
+                }
+            }
+        }
+        Ok(fresh)
+    }
+
+    /// The ref `update` would write, if it finds its ref where the pusher
+    /// saw it and fast-forwards it to a receipt local or in `staged`.
+    fn stage_ref_update(&self, update: &RefUpdate, staged: &[Receipt]) -> SdkResult<Ref> {
+        let current = self.ref_tip(&update.name)?;
+        if current != update.old_hash {
+            return Err(wll_refs::RefError::Stale {
+                name: update.name.clone(),
+            }
+            .into());
+        }
+        let new_seq = match self.local_seq(update.new_hash)? {
+            Some(seq) => seq,
+            None => staged
+                .iter()
+                .find(|r| r.receipt_hash() == update.new_hash)
+                .map(Receipt::seq)
+                .ok_or_else(|| SdkError::ObjectNotFound(hex::encode(update.new_hash)))?,
+        };
+        if let Some(current) = current {
+            if !matches!(self.local_seq(current)?, Some(seq) if seq <= new_seq) {
+                return Err(SyncError::NotFastForward(update.name.clone()).into());
+            }
+        }
+        self.tip_ref(&update.name, update.new_hash)
+    }
+
+    /// Objects `receipts` refer to: tree snapshots recorded by outcomes and
+    /// evidence attached to commitments.
+    pub(crate) fn receipt_objects(&self, receipts: &[Receipt]) -> SdkResult<Vec<ObjectId>> {
+        let mut objects = Vec::new();
+        for receipt in receipts {
+            match receipt {
+                Receipt::Commitment(commitment) => objects.extend(
+                    commitment.evidence.attachments.iter().map(|a| a.object),
+                ),
+                Receipt::Outcome(outcome) => objects.extend(outcome_tree(outcome)?),
+                Receipt::Snapshot(_) => {}
+            }
+        }
+        Ok(objects)
+    }
+
+    /// Pack `roots` and every object reachable from them.
+    pub(crate) fn pack_objects(&self, roots: &[ObjectId]) -> SdkResult<(Vec<u8>, usize)> {
+        self.pack_objects_for(roots, &[])
+    }
+
+    /// Pack what `roots` reach beyond the trees recorded up to each of
+    /// `common`, receipts the receiving side already holds.
+    ///
+    /// The pack is thin: a blob whose path also holds a blob in one of those
+    /// trees is sent as a delta against it.
+    pub(crate) fn pack_objects_for(
+        &self,
+        roots: &[ObjectId],
+        common: &[[u8; 32]],
+    ) -> SdkResult<(Vec<u8>, usize)> {
+        let mut held = Vec::new();
+        for receipt_hash in common {
+            held.extend(self.tree_at(*receipt_hash)?);
+        }
+        let bases = self.delta_bases(roots, &held)?;
+        let mut writer = PackWriter::new(Path::new("transfer"));
+        for id in NegotiationEngine::objects_to_send(roots, &held, |id| self.object_children(id)) {
+            let object = self
+                .store()
+                .read_shared(&id)?
+                .ok_or_else(|| SdkError::ObjectNotFound(id.to_hex()))?;
+            match bases.get(&id).map(|base| self.store().read_shared(base)).transpose()? {
+                Some(Some(base)) => writer.add_delta_object(&object, &base),
+                _ => writer.add_stored_object(&object),
+            }
+        }
+        let count = writer.len();
+        let (bytes, _) = writer.finish_to_bytes()?;
+        Ok((bytes, count))
+    }
+
+    /// For each blob in the trees among `roots`, the blob at the same path
+    /// in the first of the `held` trees that has one, if they differ.
+    fn delta_bases(
+        &self,
+        roots: &[ObjectId],
+        held: &[ObjectId],
+    ) -> SdkResult<HashMap<ObjectId, ObjectId>> {
+        let mut theirs = HashMap::new();
+        for tree in held {
+            self.blob_paths(tree, "", &mut theirs)?;
+        }
+        if theirs.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let mut ours = HashMap::new();
+        for root in roots {
+            self.blob_paths(root, "", &mut ours)?;
+        }
+        Ok(ours
+            .into_iter()
+            .filter_map(|(path, id)| {
+                theirs.get(&path).filter(|base| **base != id).map(|base| (id, *base))
+            })
+            .collect())
+    }
+
+    /// Add the blobs under `tree` to `paths`, keeping paths already there.
+    /// Objects other than trees add nothing.
+    fn blob_paths(
+        &self,
+        tree: &ObjectId,
+        prefix: &str,
+        paths: &mut HashMap<String, ObjectId>,
+    ) -> SdkResult<()> {
+        let Some(object) = self.store().read_shared(tree)? else {
+            return Ok(());
+        };
+        if object.kind != ObjectKind::Tree {
+            return Ok(());
+        }
+        for entry in Tree::from_stored_object(&object)?.entries {
+            let path = format!("{prefix}{}", entry.name);
+            if entry.mode == EntryMode::Directory {
+                self.blob_paths(&entry.object_id, &format!("{path}/"), paths)?;
+            } else {
+                paths.entry(path).or_insert(entry.object_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of objects `roots` reach.
+    pub(crate) fn reachable_count(&self, roots: &[ObjectId]) -> usize {
+        NegotiationEngine::objects_to_send(roots, &[], |id| self.object_children(id)).len()
+    }
+
+    /// Entries of a tree; nothing for other or unreadable objects.
+    fn object_children(&self, id: &ObjectId) -> Vec<ObjectId> {
+        match self.store().read_shared(id) {
+            Ok(Some(object)) if object.kind == ObjectKind::Tree => Tree::from_stored_object(&object)
+                .map(|tree| tree.entries.iter().map(|entry| entry.object_id).collect())
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Tree last recorded by the history ending at `receipt_hash`.
+    pub(crate) fn tree_at(&self, receipt_hash: [u8; 32]) -> SdkResult<Option<ObjectId>> {
+        let graph = self.read_graph()?;
+        for id in graph.walk(&ObjectId::from_hash(receipt_hash)) {
+            if let Some(Receipt::Outcome(outcome)) = self.ledger().get_by_hash(*id.as_bytes())? {
+                if let Some(tree) = outcome_tree(&outcome)? {
+                    return Ok(Some(tree));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Each of `tips` followed by its ancestors, newest first, for a
+    /// [`HaveNegotiator`](wll_sync::HaveNegotiator).
+    pub(crate) fn have_histories(
+        &self,
+        tips: &[(String, [u8; 32])],
+    ) -> SdkResult<Vec<Vec<ObjectId>>> {
+        let graph = self.read_graph()?;
+        Ok(tips
+            .iter()
+            .map(|(_, tip)| graph.walk(&ObjectId::from_hash(*tip)).collect())
+            .collect())
+    }
+
+    /// Write every object in an opened pack to the store.
+    pub(crate) fn store_pack(&self, reader: &PackReader) -> SdkResult<usize> {
+        for id in reader.object_ids() {
+            if let Some(object) = reader.read_object(id)? {
+                self.store().write(&object)?;
+            }
+        }
+        Ok(reader.object_count())
+    }
+
+    /// Import receipts received from a peer, skipping ones already present.
+    ///
+    /// The ledger checks that each receipt extends the local stream, so a
+    /// diverged history fails here rather than being spliced in.
+    pub(crate) fn import_receipts(&self, receipts: Vec<Receipt>) -> SdkResult<usize> {
+        let mut imported = Vec::new();
+        for receipt in receipts {
+            if receipt.worldline() != self.worldline() {
+                return Err(SdkError::InvalidOperation(format!(
+                    "received receipt for foreign worldline {}",
+                    receipt.worldline()
+                )));
+            }
+            if self.ledger().get_by_hash(receipt.receipt_hash())?.is_some() {
+                continue;
+            }
+            self.ledger().import_receipt(receipt.clone())?;
+            imported.push(receipt);
+        }
+        self.record_provenance(&imported)?;
+        Ok(imported.len())
+    }
+
+    /// Cross-worldline references made by `received` and `dependencies`
+    /// that neither they nor the local ledger resolve.
+    pub(crate) fn unresolved_references(
+        &self,
+        received: &[Receipt],
+        dependencies: &[Receipt],
+    ) -> Vec<UnresolvedReference> {
+        SyncVerifier::unresolved_references(received, dependencies, |reference| {
+            matches!(
+                self.ledger().get_by_hash(reference.receipt_hash),
+                Ok(Some(r)) if *r.worldline() == reference.worldline
+            )
+        })
+    }
+
+    /// Sequence number of the newest local receipt of `worldline`.
+    pub(crate) fn worldline_head(&self, worldline: &WorldlineId) -> SdkResult<Option<u64>> {
+        Ok(self.ledger().head(worldline)?.map(|h| h.seq))
+    }
+
+    /// Import receipts of other worldlines that received receipts cite,
+    /// skipping ones already present.
+    ///
+    /// They are kept in the ledger for reference only; the provenance DAG
+    /// tracks this worldline alone.
+    pub(crate) fn import_dependencies(&self, receipts: Vec<Receipt>) -> SdkResult<usize> {
+        let mut imported = 0;
+        for receipt in receipts {
+            if receipt.worldline() == self.worldline() {
+                return Err(SdkError::InvalidOperation(format!(
+                    "dependency receipt at seq {} belongs to this worldline",
+                    receipt.seq()
+                )));
+            }
+            if self.ledger().get_by_hash(receipt.receipt_hash())?.is_some() {
+                continue;
+            }
+            self.ledger().import_receipt(receipt)?;
+            imported += 1;
+        }
+        Ok(imported)
+    }
+
+    /// Move `ref_name` forward to `new_tip`.
+    ///
+    /// A ref already at or past `new_tip` is left alone. Only branch
+    /// (`refs/heads/…`) and remote-tracking (`refs/remotes/<remote>/…`)
+    /// refs can be updated this way.
+    pub(crate) fn fast_forward(&self, ref_name: &str, new_tip: [u8; 32]) -> SdkResult<MergeStatus> {
+        let new_seq = self
+            .local_seq(new_tip)?
+            .ok_or_else(|| SdkError::ObjectNotFound(hex::encode(new_tip)))?;
+        if let Some(current) = self.ref_tip(ref_name)? {
+            match self.local_seq(current)? {
+                Some(seq) if seq >= new_seq => return Ok(MergeStatus::UpToDate),
+                Some(_) => {}
+                None => return Err(SyncError::NotFastForward(ref_name.to_string()).into()),
+            }
+        }
+
+        let reference = self.tip_ref(ref_name, new_tip)?;
+        self.refs().write_ref(ref_name, &reference)?;
+        Ok(MergeStatus::FastForward)
+    }
+
+    /// `ref_name` pointing at `tip`. Only branch (`refs/heads/…`) and
+    /// remote-tracking (`refs/remotes/<remote>/…`) refs are moved by sync.
+    fn tip_ref(&self, ref_name: &str, tip: [u8; 32]) -> SdkResult<Ref> {
+        if let Some(branch) = ref_name.strip_prefix("refs/heads/") {
+            Ok(Ref::Branch {
+                name: branch.to_string(),
+                worldline: self.worldline().clone(),
+                receipt_hash: tip,
+            })
+        } else if let Some((remote, branch)) = ref_name
+            .strip_prefix("refs/remotes/")
+            .and_then(|rest| rest.split_once('/'))
+        {
+            Ok(Ref::Remote {
+                remote: remote.to_string(),
+                branch: branch.to_string(),
+                worldline: self.worldline().clone(),
+                receipt_hash: tip,
+            })
+        } else {
+            Err(SdkError::InvalidOperation(format!(
+                "cannot fast-forward {ref_name}: not a branch or remote-tracking ref"
+            )))
+        }
+    }
+}
+
+/// Trust roots named by the `[sync]` section.
+pub(crate) fn trust_roots(settings: &SyncSettings) -> SdkResult<TrustRoots> {
+    let receipt_hash = |key: &str, encoded: &String| {
+        hex::decode(encoded.trim())
+            .ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .ok_or_else(|| SdkError::Config(format!("{key}: expected a 32-byte hex hash")))
+    };
+    let mut roots = TrustRoots::new();
+    for encoded in &settings.trusted_genesis {
+        roots = roots.with_genesis(receipt_hash("sync.trusted_genesis", encoded)?);
+    }
+    for encoded in &settings.trusted_snapshots {
+        roots = roots.with_snapshot(receipt_hash("sync.trusted_snapshots", encoded)?);
+    }
+    for encoded in &settings.trusted_keys {
+        roots = roots.with_key(parse_public_key("sync.trusted_keys", encoded)?);
+    }
+    Ok(roots)
+}
@@ -0,0 +1,209 @@
+//! Named remotes persisted in `.wll/config`.
+//!
+//! A remote records a URL and the fetch refspecs that map its branches to
+//! local tracking refs, so sync commands can be given a name instead of a
+//! URL. Remotes only exist for persistent repositories.
+//!
+//! Lookups see remotes from every config level, so a `[remote.*]` table in
+//! the user config is available in all repositories. Changes are written to
+//! the repository config only.
+
+use std::path::PathBuf;
+
+use wll_refs::validate_remote_name;
+use wll_config::ConfigPaths;
+use wll_sync::{CredentialManager, RemoteConfig};
+
+use crate::config::RepoConfig;
+use crate::error::{SdkError, SdkResult};
+use crate::repository::Wll;
+
+impl Wll {
+    /// Configured remotes from all config levels, in name order.
+    pub fn remotes(&self) -> SdkResult<Vec<RemoteConfig>> {
+        self.config_path()?;
+        self.config()?
+            .remotes()?
+            .iter()
+            .map(|(name, settings)| Ok(RemoteConfig::from_settings(name, settings)?))
+            .collect()
+    }
+
+    /// The remote called `name`.
+    pub fn remote(&self, name: &str) -> SdkResult<RemoteConfig> {
+        self.remotes()?
+            .into_iter()
+            .find(|r| r.name == name)
+            .ok_or_else(|| SdkError::InvalidOperation(format!("no such remote: {name}")))
+    }
+
+    /// Credential lookup for remotes, configured by the `[credential]`
+    /// section. The `store` helper keeps tokens beside the user config.
+    pub fn credentials(&self) -> SdkResult<CredentialManager> {
+        let settings = self.config()?.credential()?;
+        let store = ConfigPaths::standard(self.repo_dir()).credential_store();
+        Ok(CredentialManager::from_settings(&settings, store)?)
+    }
+
+    /// Add `remote` to the repository config.
+    pub fn add_remote(&self, remote: RemoteConfig) -> SdkResult<()> {
+        validate_remote_name(&remote.name)?;
+        self.update_config(|config| {
+            if config.remote(&remote.name).is_some() {
+                return Err(SdkError::InvalidOperation(format!(
+                    "remote {} already exists",
+                    remote.name
+                )));
+            }
+            config.remotes.push(remote);
+            config.remotes.sort_by(|a, b| a.name.cmp(&b.name));
+            Ok(())
+        })
+    }
+
+    /// Change the URL of the remote called `name`.
+    pub fn set_remote_url(&self, name: &str, url: impl Into<String>) -> SdkResult<()> {
+        let url = url.into();
+        self.update_config(|config| {
+            let remote = config
+                .remotes
+                .iter_mut()
+                .find(|r| r.name == name)
+                .ok_or_else(|| SdkError::InvalidOperation(format!("no such remote: {name}")))?;
+            remote.url = url;
+            Ok(())
+        })
+    }
+
+    /// Remove the remote called `name` along with its tracking refs.
+    pub fn remove_remote(&self, name: &str) -> SdkResult<RemoteConfig> {
+        let removed = self.update_config(|config| {
+            let index = config
+                .remotes
+                .iter()
+                .position(|r| r.name == name)
+                .ok_or_else(|| SdkError::InvalidOperation(format!("no such remote: {name}")))?;
+            Ok(config.remotes.remove(index))
+        })?;
+
+        let refs = self.refs();
+        let mut tracking = refs.list_refs(&format!("refs/remotes/{name}/"))?;
+        for spec in &removed.fetch {
+            if let Some(prefix) = spec.dst.strip_suffix('*') {
+                tracking.extend(refs.list_refs(prefix)?);
+            }
+        }
+        for (ref_name, _) in tracking {
+            refs.delete_ref(&ref_name)?;
+        }
+        Ok(removed)
+    }
+
+    fn config_path(&self) -> SdkResult<PathBuf> {
+        self.repo_dir()
+            .map(|dir| dir.join("config"))
+            .ok_or_else(|| {
+                SdkError::InvalidOperation("remotes require a repository on disk".into())
+            })
+    }
+
+    /// Load the config, apply `edit`, and write it back if `edit` succeeds.
+    fn update_config<T>(&self, edit: impl FnOnce(&mut RepoConfig) -> SdkResult<T>) -> SdkResult<T> {
+        let path = self.config_path()?;
+        let mut config = RepoConfig::load(&path)?;
+        let value = edit(&mut config)?;
+        config.save(&path)?;
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wll_refs::Ref;
+    use wll_sync::RefSpec;
+
+    use super::*;
+
+    #[test]
+    fn remotes_persist_across_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let wll = Wll::init_at(dir.path()).unwrap();
+        wll.add_remote(RemoteConfig::new("origin", "wll://a"))
+            .unwrap();
+        wll.add_remote(
+            RemoteConfig::new("backup", "wll://b").with_fetch(vec![RefSpec::new(
+                "refs/heads/main",
+                "refs/remotes/backup/main",
+            )]),
+        )
+        .unwrap();
+        wll.set_remote_url("origin", "wll://c").unwrap();
+        drop(wll);
+
+        let wll = Wll::open(dir.path()).unwrap();
+        let remotes = wll.remotes().unwrap();
+        let names: Vec<&str> = remotes.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["backup", "origin"]);
+        assert_eq!(wll.remote("origin").unwrap().url, "wll://c");
+        assert_eq!(wll.remote("backup").unwrap().fetch.len(), 1);
+    }
+
+    #[test]
+    fn add_rejects_duplicates_and_bad_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let wll = Wll::init_at(dir.path()).unwrap();
+        wll.add_remote(RemoteConfig::new("origin", "wll://a"))
+            .unwrap();
+        assert!(wll
+            .add_remote(RemoteConfig::new("origin", "wll://b"))
+            .is_err());
+        assert!(wll.add_remote(RemoteConfig::new("a/b", "wll://b")).is_err());
+        assert!(wll.set_remote_url("missing", "wll://x").is_err());
+    }
+
+    #[test]
+    fn remove_deletes_tracking_refs() {
+        let dir = tempfile::tempdir().unwrap();
+        let wll = Wll::init_at(dir.path()).unwrap();
+        wll.add_remote(RemoteConfig::new("origin", "wll://a"))
+            .unwrap();
+        let tracking = Ref::Remote {
+            remote: "origin".into(),
+            branch: "main".into(),
+            worldline: wll.worldline().clone(),
+            receipt_hash: [1; 32],
+        };
+        wll.refs()
+            .write_ref("refs/remotes/origin/main", &tracking)
+            .unwrap();
+
+        let removed = wll.remove_remote("origin").unwrap();
+        assert_eq!(removed.url, "wll://a");
+        assert!(wll.remotes().unwrap().is_empty());
+        assert!(wll
+            .refs()
+            .read_ref("refs/remotes/origin/main")
+            .unwrap()
+            .is_none());
+        assert!(wll.remove_remote("origin").is_err());
+    }
+
+    #[test]
+    fn credentials_follow_repository_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let wll = Wll::init_at(dir.path()).unwrap();
+        let config_path = wll.repo_dir().unwrap().join("config");
+        let mut text = std::fs::read_to_string(&config_path).unwrap();
+        text.push_str("\n[credential]\nhelper = \"keychain\"\n");
+        std::fs::write(&config_path, text).unwrap();
+
+        let manager = wll.credentials().unwrap();
+        assert_eq!(manager.sources_for("https://h/r"), ["env", "keychain"]);
+    }
+
+    #[test]
+    fn in_memory_repository_has_no_remotes() {
+        let wll = Wll::init().unwrap();
+        assert!(matches!(wll.remotes(), Err(SdkError::InvalidOperation(_))));
+    }
+}
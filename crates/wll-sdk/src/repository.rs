@@ -1,66 +1,531 @@
-use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
 
 use serde_json::Value;
+use wll_crypto::EnvelopeSecretKey;
 use wll_types::{
-    CommitmentId, IdentityMaterial, ObjectId, WorldlineId,
+    Clock, CommitmentId, IdentityAttestation, IdentityMaterial, ObjectId, Reversibility,
+    SystemClock, TemporalAnchor, WorldlineId,
 };
-use wll_store::{Blob, InMemoryObjectStore, ObjectStore, Tree, TreeEntry};
+use wll_store::{
+    Blob, EntryMode, FileObjectStore, InMemoryObjectStore, ObjectStore, Tree, TreeEntry,
+};
+use wll_diff::{diff_blobs, diff_trees, TreeChange, TreeDiff};
+use wll_index::Index;
 use wll_ledger::{
-    CommitmentProposal, Decision, EvidenceBundle, InMemoryLedger, LedgerReader, LedgerWriter,
-    OutcomeRecord, Receipt, ReplayEngine, ReplayResult, LatestStateProjection,
-    ProjectionBuilder, StateUpdate, StreamValidator, ValidationReport,
+    CommitmentProposal, CommitmentReceipt, Decision, EffectKind, EffectQuery, EffectSummary,
+    EvidenceBundle, FileLedger, InMemoryLedger, Ledger,
+    OutcomeReceipt, OutcomeRecord, Receipt, ReceiptKind, ReplayEngine, ReplayResult,
+    LatestStateProjection, ProjectionBuilder, ProofVerifiers, StateUpdate, StreamValidator,
+    ValidationReport, DecisionReevaluator, LedgerError, StateSchema, StrictReplayResult,
+};
+use wll_refs::{FileRefStore, Head, InMemoryRefStore, Ref, RefStore};
+use wll_fabric::{EventFabric, EventKind, EventPayload};
+use wll_config::{ConfigPaths, LayeredConfig};
+use wll_gate::{
+    ChangeBudgetStage, ChangeSize, CommitmentGate, CommitmentProposal as GateProposal,
+    EvidenceStage, FilePolicyStore, FileResolver, GateConfig, GateContext, GateError, GateSimulation,
+    HttpResolver, InMemoryPolicyStore, IntentLintStage, LedgerContextProvider,
+    ObjectStoreResolver, Policy, GateResult, PolicyStore, ADMIN_CAPABILITY,
+};
+use wll_dag::{
+    BisectStep, CausalRelation, CommitGraph, DagNode, DagNodeMetadata, DagStorage,
+    FileDagStorage, ParentRef, ProvenanceDag,
 };
-use wll_refs::{Head, InMemoryRefStore, Ref, RefStore};
-use wll_dag::ProvenanceDag;
 
-use crate::commit::{CommitProposal as SdkProposal, CommitResult, ReceiptSummary};
+use crate::builder::WllBuilder;
+use crate::commit::{
+    CommitProposal as SdkProposal, CommitResult, ReceiptSummary, REVERSIBILITY_KEY,
+};
+use crate::config::{RepoConfig, WLL_DIR};
+use crate::deferred::OutcomeTimeout;
 use crate::error::{SdkError, SdkResult};
+use crate::hooks::{HookPayload, HookPoint, Hooks};
+use crate::offline::QueuedCommit;
+use crate::sealed::{if_held, seal_text};
+use crate::time_anchor::RoughtimeServer;
+use crate::transparency::HttpTransparencyLog;
 
 /// High-level WLL repository API.
+///
+/// A `Wll` is either purely in-memory ([`Wll::init`]) or backed by a `.wll`
+/// directory on disk ([`Wll::init_at`] / [`Wll::open`]):
+///
+/// ```text
+/// .wll/
+///   config                 repository config (TOML)
+///   HEAD                   current branch or detached receipt hash
+///   refs/                  branches, tags, remote refs
+///   objects/               loose content-addressed objects
+///   ledger/receipts.jsonl  append-only receipt journal
+///   dag                    provenance DAG cache (rebuilt from receipts)
+///   commit-graph           generation numbers and parents for history queries
+///   attestations.json      external identities bound to the worldline
+///   pending.json           commits queued while offline
+///   NOTES                  id of the tree of notes on receipts
+///   policies/              gate policy versions by hash, for strict replay
+/// ```
 pub struct Wll {
     worldline: WorldlineId,
-    store: InMemoryObjectStore,
-    ledger: InMemoryLedger,
-    refs: InMemoryRefStore,
-    _dag: ProvenanceDag,
+    store: Arc<dyn ObjectStore>,
+    ledger: Arc<dyn Ledger>,
+    refs: Arc<dyn RefStore>,
+    dag: RwLock<ProvenanceDag>,
+    graph: RwLock<CommitGraph>,
+    dag_storage: Option<Box<dyn DagStorage>>,
+    repo_dir: Option<PathBuf>,
+    gate: CommitmentGate,
+    policies: Arc<dyn PolicyStore>,
+    fabric: Option<Arc<EventFabric>>,
+    attestations: RwLock<Vec<IdentityAttestation>>,
+    pending: RwLock<Vec<QueuedCommit>>,
+    notes: RwLock<Option<ObjectId>>,
+    proof_verifiers: ProofVerifiers,
+    hooks: Hooks,
+    outcome_timeout: OutcomeTimeout,
+    envelope_keys: Vec<EnvelopeSecretKey>,
+    clock: Arc<dyn Clock>,
+}
+
+/// A commit the gate accepted whose receipts are not recorded yet.
+pub(crate) struct GatedCommit {
+    pub(crate) proposal: SdkProposal,
+    state_updates: Vec<StateUpdate>,
+    tree_diff: Option<TreeDiff>,
+    ledger_proposal: CommitmentProposal,
+    pub(crate) gate_result: GateResult,
+    branch: String,
+    payload: HookPayload,
+}
+
+impl GatedCommit {
+    /// The outcome committing it records: the message, the tree and its
+    /// changes, the state, and the proposal's metadata.
+    pub(crate) fn outcome_record(&self) -> OutcomeRecord {
+        let changes = self.tree_diff.iter().flat_map(|d| d.changes.iter());
+        OutcomeRecord {
+            effects: changes.map(change_effect).collect(),
+            proofs: vec![],
+            state_updates: self.state_updates.clone(),
+            metadata: self.proposal.metadata.clone(),
+        }
+    }
 }
 
 impl Wll {
+    /// Start composing a repository from custom backends.
+    pub fn builder() -> WllBuilder {
+        WllBuilder::new()
+    }
+
     /// Initialize a new WLL repository with a random worldline.
     pub fn init() -> SdkResult<Self> {
-        let seed = time_based_seed();
-        let worldline = WorldlineId::derive(&IdentityMaterial::GenesisHash(seed));
-        Self::init_inner(worldline)
+        WllBuilder::new().build()
     }
 
     /// Initialize with a specific worldline ID.
     pub fn init_with_worldline(worldline: WorldlineId) -> SdkResult<Self> {
-        Self::init_inner(worldline)
+        WllBuilder::new().with_worldline(worldline).build()
+    }
+
+    /// Initialize a persistent repository in `<path>/.wll` with a random
+    /// worldline.
+    pub fn init_at(path: impl AsRef<Path>) -> SdkResult<Self> {
+        Self::init_at_with_config(path, RepoConfig::new(random_worldline()))
+    }
+
+    /// Initialize a persistent repository in `<path>/.wll` using `config`.
+    ///
+    /// Fails if a repository already exists at that location.
+    pub fn init_at_with_config(path: impl AsRef<Path>, config: RepoConfig) -> SdkResult<Self> {
+        let repo_dir = path.as_ref().join(WLL_DIR);
+        let config_path = repo_dir.join("config");
+        if config_path.exists() {
+            return Err(SdkError::InvalidOperation(format!(
+                "repository already exists at {}",
+                repo_dir.display()
+            )));
+        }
+
+        std::fs::create_dir_all(&repo_dir)?;
+        config.save(&config_path)?;
+        Self::open_dir(repo_dir, config)
+    }
+
+    /// Open an existing persistent repository.
+    ///
+    /// `path` may be the working directory containing `.wll` or the `.wll`
+    /// directory itself.
+    pub fn open(path: impl AsRef<Path>) -> SdkResult<Self> {
+        let repo_dir = find_repo_dir(path.as_ref())?;
+        let config = RepoConfig::load(&repo_dir.join("config"))?;
+        Self::open_dir(repo_dir, config)
+    }
+
+    /// Open the repository containing `start`, searching parent directories
+    /// for a `.wll` directory the way `git` discovers its repository.
+    pub fn discover(start: impl AsRef<Path>) -> SdkResult<Self> {
+        let start = start.as_ref();
+        start
+            .ancestors()
+            .find(|dir| dir.join(WLL_DIR).join("config").is_file())
+            .map(Self::open)
+            .unwrap_or_else(|| Err(SdkError::NotInitialized(start.display().to_string())))
+    }
+
+    pub(crate) fn open_dir(repo_dir: PathBuf, config: RepoConfig) -> SdkResult<Self> {
+        let store: Arc<dyn ObjectStore> = Arc::new(FileObjectStore::open(repo_dir.join("objects"))?);
+        let refs = FileRefStore::open(&repo_dir)?;
+        let ledger = FileLedger::open_with_hash_algo(
+            repo_dir.join("ledger").join("receipts.jsonl"),
+            config.node_id,
+            config.hash_algo,
+        )?;
+        let settings = LayeredConfig::load(&ConfigPaths::standard(Some(&repo_dir)))?;
+        let gate_settings = settings.gate()?;
+        let mut gate = CommitmentGate::with_default_stages(GateConfig::from_settings(&gate_settings));
+        if gate_settings.verify_evidence {
+            let work_tree = repo_dir.parent().unwrap_or(&repo_dir);
+            gate.add_stage(Box::new(
+                EvidenceStage::new()
+                    .with_resolver(Arc::new(FileResolver::new(work_tree)))
+                    .with_resolver(Arc::new(ObjectStoreResolver::new(store.clone())))
+                    .with_resolver(Arc::new(HttpResolver::new())),
+            ));
+        }
+        let budgets = ChangeBudgetStage::from_settings(&gate_settings);
+        if !budgets.is_empty() {
+            gate.add_stage(Box::new(budgets));
+        }
+        let conventions = IntentLintStage::from_settings(&gate_settings);
+        if !conventions.is_empty() {
+            gate.add_stage(Box::new(conventions));
+        }
+
+        let mut wll = WllBuilder::new()
+            .with_worldline(config.worldline)
+            .with_store(store)
+            .with_ledger(Arc::new(ledger))
+            .with_refs(Arc::new(refs))
+            .with_dag_storage(Box::new(FileDagStorage::new(repo_dir.join("dag"))))
+            .with_gate(gate)
+            .with_policy_store(Arc::new(FilePolicyStore::new(repo_dir.join("policies"))))
+            .build()?;
+        let time_servers = RoughtimeServer::all_from_settings(&settings.timestamp()?)?;
+        if !time_servers.is_empty() {
+            wll.proof_verifiers = wll.proof_verifiers
+                .with_verifier(Arc::new(crate::time_anchor::roughtime_verifier(&time_servers)));
+        }
+        let logs = HttpTransparencyLog::all_from_settings(&settings.transparency()?)?;
+        if !logs.is_empty() {
+            wll.proof_verifiers = wll.proof_verifiers
+                .with_verifier(Arc::new(crate::transparency::log_verifier(&logs)));
+        }
+        wll.attestations = RwLock::new(crate::attestations::load(&repo_dir)?);
+        wll.pending = RwLock::new(crate::offline::load(&repo_dir)?);
+        wll.notes = RwLock::new(crate::notes::load(&repo_dir)?);
+        let work_tree = repo_dir.parent().unwrap_or(&repo_dir);
+        wll.hooks = Hooks::from_settings(&settings.hooks()?, work_tree);
+        wll.outcome_timeout = OutcomeTimeout::from_settings(&settings.outcomes()?);
+        wll.repo_dir = Some(repo_dir);
+        Ok(wll)
+    }
+
+    /// Assemble a repository from builder parts, filling gaps with
+    /// in-memory defaults.
+    pub(crate) fn from_builder(builder: WllBuilder) -> SdkResult<Self> {
+        let worldline = builder.worldline.unwrap_or_else(random_worldline);
+        let clock = builder.clock.unwrap_or_else(|| Arc::new(SystemClock));
+        let ledger: Arc<dyn Ledger> = builder
+            .ledger
+            .unwrap_or_else(|| Arc::new(InMemoryLedger::default().with_clock(clock.clone())));
+
+        // The DAG is derived data: rebuild it whenever it is missing or
+        // disagrees with the ledger.
+        let receipts = ledger.read_all(&worldline)?;
+        let mut dag = match &builder.dag_storage {
+            Some(storage) => storage.load().unwrap_or_default(),
+            None => ProvenanceDag::new(),
+        };
+        let in_sync = dag.worldline_history(&worldline).len() == receipts.len()
+            && receipts
+                .iter()
+                .all(|r| dag.get_node(&receipt_object_id(r)).is_some());
+        if !in_sync {
+            dag = ProvenanceDag::new();
+            for receipt in &receipts {
+                // Edges to archived receipts are dropped, as a checkpoint
+                // does.
+                let mut node = dag_node(receipt);
+                for cited in cited_nodes(&dag, ledger.as_ref(), &mut node)? {
+                    dag.add_node(cited)?;
+                }
+                node.parents.retain(|p| dag.get_node(&p.target).is_some());
+                dag.add_node(node)?;
+            }
+            if let Some(storage) = &builder.dag_storage {
+                storage.save(&dag)?;
+            }
+        }
+        let last = receipts.last().map(receipt_object_id);
+        let graph = match &builder.dag_storage {
+            Some(storage) => storage.load_graph().ok().flatten(),
+            None => None,
+        }
+        .filter(|graph| graph.len() == dag.len() && graph.last() == last.as_ref());
+        let graph = match graph {
+            Some(graph) => graph,
+            None => {
+                let graph = CommitGraph::from_dag(&dag);
+                if let Some(storage) = &builder.dag_storage {
+                    storage.save_graph(&graph)?;
+                }
+                graph
+            }
+        };
+
+        let wll = Self {
+            worldline,
+            store: builder
+                .store
+                .unwrap_or_else(|| Arc::new(InMemoryObjectStore::new())),
+            ledger: ledger.clone(),
+            refs: builder
+                .refs
+                .unwrap_or_else(|| Arc::new(InMemoryRefStore::new())),
+            dag: RwLock::new(dag),
+            graph: RwLock::new(graph),
+            dag_storage: builder.dag_storage,
+            repo_dir: None,
+            gate: ledger_context(builder.gate.unwrap_or_else(default_gate), &ledger),
+            policies: builder
+                .policies
+                .unwrap_or_else(|| Arc::new(InMemoryPolicyStore::new())),
+            fabric: builder.fabric,
+            attestations: RwLock::new(Vec::new()),
+            pending: RwLock::new(Vec::new()),
+            notes: RwLock::new(None),
+            proof_verifiers: ProofVerifiers::new(),
+            hooks: Hooks::new(),
+            outcome_timeout: OutcomeTimeout::new(),
+            envelope_keys: Vec::new(),
+            clock,
+        };
+        if wll.refs.head()?.is_none() {
+            wll.create_main_branch()?;
+        }
+        Ok(wll)
+    }
+
+    /// Replace the policy gate every commit is evaluated by.
+    ///
+    /// Repositories start with the default stage pipeline in permissive mode,
+    /// matching `wll init`; persistent ones apply the `[gate]` config section.
+    /// A gate without a context provider reads capabilities and policies from
+    /// the repository's ledger.
+    pub fn with_gate(mut self, gate: CommitmentGate) -> Self {
+        self.gate = ledger_context(gate, &self.ledger);
+        self
+    }
+
+    /// The policy gate commits are routed through.
+    pub fn gate(&self) -> &CommitmentGate {
+        &self.gate
+    }
+
+    /// Every policy version a commit has been judged under, by the hash
+    /// its commitment receipt records.
+    pub fn policy_store(&self) -> &dyn PolicyStore {
+        self.policies.as_ref()
+    }
+
+    /// Replace the verifiers outcome proofs are checked with.
+    ///
+    /// Repositories start with none, so strict verification fails on any
+    /// outcome that carries proofs until the accepted proof types and their
+    /// trust anchors are configured.
+    pub fn with_proof_verifiers(mut self, verifiers: ProofVerifiers) -> Self {
+        self.proof_verifiers = verifiers;
+        self
+    }
+
+    /// The verifiers outcome proofs are checked with.
+    pub fn proof_verifiers(&self) -> &ProofVerifiers {
+        &self.proof_verifiers
+    }
+
+    /// The hooks run around commits and pushes: the `[hooks]` programs for
+    /// repositories on disk, then those added with
+    /// [`with_hook`](Self::with_hook).
+    pub fn hooks(&self) -> &Hooks {
+        &self.hooks
+    }
+
+    pub(crate) fn hooks_mut(&mut self) -> &mut Hooks {
+        &mut self.hooks
+    }
+
+    /// Replace the timeout after which a commitment
+    /// [proposed](Self::propose) without an outcome is recorded as failed.
+    ///
+    /// Repositories start with none; persistent ones apply `[outcomes]`.
+    pub fn with_outcome_timeout(mut self, timeout: OutcomeTimeout) -> Self {
+        self.outcome_timeout = timeout;
+        self
+    }
+
+    /// When commitments waiting for an outcome are recorded as failed.
+    pub fn outcome_timeout(&self) -> &OutcomeTimeout {
+        &self.outcome_timeout
+    }
+
+    /// Also open intents and state sealed to `key`. See [`crate::sealed`].
+    pub fn with_envelope_key(mut self, key: EnvelopeSecretKey) -> Self {
+        self.envelope_keys.push(key);
+        self
+    }
+
+    /// The keys sealed intents and state are opened with.
+    pub(crate) fn envelope_keys(&self) -> &[EnvelopeSecretKey] {
+        &self.envelope_keys
+    }
+
+    /// The clock commits, index entries, and gate checks read time from.
+    pub fn clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+
+    pub(crate) fn attestation_list(&self) -> &RwLock<Vec<IdentityAttestation>> {
+        &self.attestations
+    }
+
+    pub(crate) fn pending_list(&self) -> &RwLock<Vec<QueuedCommit>> {
+        &self.pending
+    }
+
+    pub(crate) fn notes_lock(&self) -> &RwLock<Option<ObjectId>> {
+        &self.notes
+    }
+
+    pub(crate) fn shared_store(&self) -> Arc<dyn ObjectStore> {
+        self.store.clone()
+    }
+
+    pub(crate) fn shared_policies(&self) -> Arc<dyn PolicyStore> {
+        self.policies.clone()
     }
 
-    fn init_inner(worldline: WorldlineId) -> SdkResult<Self> {
-        let store = InMemoryObjectStore::new();
-        let ledger = InMemoryLedger::default();
-        let refs = InMemoryRefStore::new();
+    pub(crate) fn time_nonce(&self) -> u64 {
+        self.clock.now_nanos()
+    }
 
-        // Create the main branch ref
+    fn create_main_branch(&self) -> SdkResult<()> {
         let branch_ref = Ref::Branch {
             name: "main".into(),
-            worldline: worldline.clone(),
+            worldline: self.worldline.clone(),
             receipt_hash: [0; 32],
         };
-        refs.write_ref("refs/heads/main", &branch_ref)
-            .map_err(|e| SdkError::Internal(e.to_string()))?;
-        refs.set_head("main")
-            .map_err(|e| SdkError::Internal(e.to_string()))?;
+        self.refs.write_ref("refs/heads/main", &branch_ref)?;
+        self.refs.set_head("main")?;
+        Ok(())
+    }
 
-        Ok(Self {
-            worldline,
-            store,
-            ledger,
-            refs,
-            _dag: ProvenanceDag::new(),
-        })
+    /// Publish a commit's decision, outcome and (if the branch moved) ref
+    /// update to the event fabric, when one is attached. `commitment` is
+    /// `None` when its decision was published by [`propose`](Self::propose).
+    pub(crate) fn emit_commit_events(
+        &self,
+        commitment: Option<&CommitmentReceipt>,
+        outcome: &OutcomeReceipt,
+        ref_update: Option<(String, Option<[u8; 32]>)>,
+    ) -> SdkResult<()> {
+        let Some(fabric) = &self.fabric else {
+            return Ok(());
+        };
+
+        let mut events: Vec<_> = commitment.map(|c| self.decision_event(c)).into_iter().collect();
+        events.push((
+            self.worldline.clone(),
+            EventKind::OutcomeRecorded,
+            EventPayload::ObjectRef {
+                object_id: ObjectId::from_hash(outcome.receipt_hash),
+                receipt_kind: ReceiptKind::Outcome,
+            },
+        ));
+        if let Some((ref_name, old_tip)) = ref_update {
+            events.push((
+                self.worldline.clone(),
+                EventKind::RefUpdated,
+                EventPayload::RefUpdate {
+                    ref_name,
+                    old_target: old_tip.map(ObjectId::from_hash),
+                    new_target: ObjectId::from_hash(outcome.receipt_hash),
+                },
+            ));
+        }
+        fabric.emit_batch(events)?;
+        Ok(())
+    }
+
+    /// Publish the decision on `commitment` alone, for a commitment whose
+    /// outcome is recorded later.
+    pub(crate) fn emit_decision(&self, commitment: &CommitmentReceipt) -> SdkResult<()> {
+        if let Some(fabric) = &self.fabric {
+            fabric.emit_batch(vec![self.decision_event(commitment)])?;
+        }
+        Ok(())
+    }
+
+    fn decision_event(
+        &self,
+        commitment: &CommitmentReceipt,
+    ) -> (WorldlineId, EventKind, EventPayload) {
+        (
+            self.worldline.clone(),
+            EventKind::CommitmentDecided,
+            EventPayload::Commitment {
+                commitment_id: commitment.commitment_id.clone(),
+                description: commitment.intent.clone(),
+            },
+        )
+    }
+
+    /// Record newly appended receipts in the provenance DAG.
+    pub(crate) fn record_provenance(&self, receipts: &[Receipt]) -> SdkResult<()> {
+        let mut dag = self.dag.write()
+            .map_err(|_| SdkError::Internal("DAG lock poisoned".into()))?;
+        let mut graph = self.graph.write()
+            .map_err(|_| SdkError::Internal("commit graph lock poisoned".into()))?;
+        for receipt in receipts {
+            let mut node = dag_node(receipt);
+            for cited in cited_nodes(&dag, self.ledger.as_ref(), &mut node)? {
+                graph.append(&cited)?;
+                dag.add_node(cited)?;
+            }
+            graph.append(&node)?;
+            dag.add_node(node)?;
+        }
+        if let Some(storage) = &self.dag_storage {
+            storage.save(&dag)?;
+            storage.save_graph(&graph)?;
+        }
+        Ok(())
+    }
+
+    /// Prune provenance older than `horizon`, the timestamp of the oldest
+    /// live receipt, returning the number of nodes pruned.
+    pub(crate) fn checkpoint_provenance(&self, horizon: &TemporalAnchor) -> SdkResult<usize> {
+        let mut dag = self.dag.write()
+            .map_err(|_| SdkError::Internal("DAG lock poisoned".into()))?;
+        let mut graph = self.graph.write()
+            .map_err(|_| SdkError::Internal("commit graph lock poisoned".into()))?;
+        let pruned = dag.checkpoint(horizon);
+        *graph = CommitGraph::from_dag(&dag);
+        if let Some(storage) = &self.dag_storage {
+            storage.save(&dag)?;
+            storage.save_graph(&graph)?;
+        }
+        Ok(pruned)
     }
 
     // ---- Content operations ----
@@ -73,7 +538,7 @@ impl Wll {
     }
 
     pub fn read_blob(&self, id: &ObjectId) -> SdkResult<Vec<u8>> {
-        let obj = self.store.read(id)?
+        let obj = self.store.read_shared(id)?
             .ok_or_else(|| SdkError::ObjectNotFound(id.to_hex()))?;
         let blob = Blob::from_stored_object(&obj)?;
         Ok(blob.data)
@@ -87,7 +552,7 @@ impl Wll {
     }
 
     pub fn read_tree(&self, id: &ObjectId) -> SdkResult<Tree> {
-        let obj = self.store.read(id)?
+        let obj = self.store.read_shared(id)?
             .ok_or_else(|| SdkError::ObjectNotFound(id.to_hex()))?;
         let tree = Tree::from_stored_object(&obj)?;
         Ok(tree)
@@ -95,68 +560,388 @@ impl Wll {
 
     // ---- Commitment operations ----
 
-    pub fn commit(&self, proposal: SdkProposal) -> SdkResult<CommitResult> {
-        let evidence = if proposal.evidence.is_empty() {
-            EvidenceBundle::empty()
-        } else {
-            EvidenceBundle::from_references(proposal.evidence.clone())
+    /// Create an empty staging index backed by this repository's store.
+    pub fn new_index(&self) -> Index {
+        Index::new(Arc::clone(&self.store)).with_clock(self.clock.clone())
+    }
+
+    /// Tree recorded by the commit at the tip of the current branch.
+    ///
+    /// Each tree commit stores its root tree under the `tree` state key of
+    /// its outcome receipt; that key is the branch's snapshot pointer.
+    pub fn head_tree(&self) -> SdkResult<Option<ObjectId>> {
+        let branch = self.current_branch()?;
+        let tip = match self.refs.read_ref(&format!("refs/heads/{branch}"))? {
+            Some(reference) if *reference.target_hash() != [0; 32] => *reference.target_hash(),
+            _ => return Ok(None),
         };
+        match self.ledger.get_by_hash(tip)? {
+            Some(Receipt::Outcome(outcome)) => outcome_tree(&outcome),
+            _ => Ok(None),
+        }
+    }
+
+    /// Write the staged tree from `index` and commit it.
+    ///
+    /// The outcome receipt records the tree id as the new snapshot pointer
+    /// and one effect per [`TreeChange`] against the previous head tree.
+    pub fn commit_tree(&self, index: &mut Index, proposal: SdkProposal) -> SdkResult<CommitResult> {
+        let tree = index.write_tree()?;
+        self.commit(proposal.with_tree(tree))
+    }
+
+    pub fn commit(&self, proposal: SdkProposal) -> SdkResult<CommitResult> {
+        self.commit_with_state(proposal, Vec::new())
+    }
+
+    /// [`commit`](Self::commit), with `state` recorded in the outcome
+    /// after the message and tree.
+    ///
+    /// The gate reads capability grants and policies from the
+    /// `capability/` and `policy/` keys of the default namespace; keep
+    /// application state in a namespace of its own. Values there that the
+    /// gate could not load, such as a grant or policy with an invalid glob,
+    /// fail the commit before anything is recorded, as does writing them
+    /// from a commit the gate would not let change its context
+    /// ([`wll_gate::check_context_change`]): anything but a
+    /// `PolicyChange` claiming the admin capability the worldline holds,
+    /// or its first commit.
+    pub fn commit_with_state(
+        &self,
+        proposal: SdkProposal,
+        state: Vec<StateUpdate>,
+    ) -> SdkResult<CommitResult> {
+        let gated = self.gate_commit(proposal, state, CommitmentId::new())?;
+        let commitment = self.append_gated(&gated)?;
+        self.record_commit(gated, commitment)
+    }
 
+    /// Run `proposal` through the pre-commit hooks and the gate, recording
+    /// a rejection as [`commit`](Self::commit) does but nothing else.
+    pub(crate) fn gate_commit(
+        &self,
+        proposal: SdkProposal,
+        state: Vec<StateUpdate>,
+        commitment_id: CommitmentId,
+    ) -> SdkResult<GatedCommit> {
+        self.check_attachments(&proposal.attachments)?;
+        let evidence = EvidenceBundle::new(proposal.evidence.clone(), proposal.attachments.clone());
+
+        let mut state_updates = vec![StateUpdate {
+            key: "message".into(),
+            value: Value::String(proposal.message.clone()),
+            namespace: None,
+        }];
+        if let Some(tree) = &proposal.tree {
+            state_updates.push(StateUpdate {
+                key: TREE_STATE_KEY.into(),
+                value: Value::String(tree.to_hex()),
+                namespace: None,
+            });
+        }
+        state_updates.extend(state);
+        // Checked up front so a schema violation or gate context the gate
+        // could not load leaves no commitment without an outcome.
+        self.ledger.schemas()?.check(&self.worldline, &state_updates)?;
+        for update in state_updates.iter().filter(|u| u.namespace.is_none()) {
+            wll_gate::check_context_entry(&update.key, &update.value)?;
+        }
+        let context_keys: Vec<String> = state_updates
+            .iter()
+            .filter(|u| u.namespace.is_none() && wll_gate::is_context_key(&u.key))
+            .map(|u| u.key.clone())
+            .collect();
+        let genesis = !context_keys.is_empty() && self.check_context_change(&proposal)?;
+        self.seal_state(&mut state_updates, &proposal.recipients)?;
+
+        let tree_diff = self.proposal_diff(&proposal)?;
+        let (gate_proposal, mut context) =
+            self.gate_input(&proposal, evidence.clone(), tree_diff.as_ref())?;
+        context.context_keys = context_keys;
+        context.genesis = genesis;
         let ledger_proposal = CommitmentProposal {
             worldline: self.worldline.clone(),
-            commitment_id: CommitmentId::new(),
-            class: proposal.effective_class(),
-            intent: proposal.effective_intent().to_string(),
-            requested_caps: vec![],
+            commitment_id,
+            class: gate_proposal.class.clone(),
+            intent: seal_text(&gate_proposal.intent, &proposal.recipients)?,
+            requested_caps: gate_proposal.claimed_capabilities.clone(),
             targets: vec![self.worldline.clone()],
             evidence,
-            nonce: time_nonce(),
+            nonce: self.time_nonce(),
         };
+        let branch = self.current_branch()?;
+        let mut payload = HookPayload::new(HookPoint::PreCommit, self.worldline.clone(), &branch);
+        payload.proposal = Some(proposal.clone());
+        payload.diff = tree_diff.clone();
+        self.run_hooks(&payload, || ledger_proposal.clone())?;
+
+        let gate_result = self.gate.evaluate_with_context(&gate_proposal, &mut context)?;
+        self.policies.put(&self.gate.config().default_policy)?;
+
+        if let Decision::Rejected { .. } = &gate_result.decision {
+            let stage = gate_result
+                .stage_results
+                .iter()
+                .find(|r| !r.passed)
+                .map(|r| r.stage_name.as_str());
+            return Err(self.record_rejection(
+                &ledger_proposal,
+                &gate_result.decision,
+                gate_result.policy_hash,
+                stage,
+            )?);
+        }
+
+        Ok(GatedCommit {
+            proposal,
+            state_updates,
+            tree_diff,
+            ledger_proposal,
+            gate_result,
+            branch,
+            payload,
+        })
+    }
 
+    /// Record the commitment of a commit the gate accepted.
+    pub(crate) fn append_gated(&self, gated: &GatedCommit) -> SdkResult<CommitmentReceipt> {
         let commitment = self.ledger.append_commitment(
-            &ledger_proposal,
-            &Decision::Accepted,
-            [0; 32],
+            &gated.ledger_proposal,
+            &gated.gate_result.decision,
+            gated.gate_result.policy_hash,
         )?;
+        Ok(commitment)
+    }
 
-        let outcome_record = OutcomeRecord {
-            effects: vec![],
-            proofs: vec![],
-            state_updates: vec![StateUpdate {
-                key: "message".into(),
-                value: Value::String(proposal.message.clone()),
-            }],
-            metadata: BTreeMap::new(),
-        };
+    /// Record the outcome of `gated` under `commitment`, then move the
+    /// branch and run the post-commit hooks.
+    pub(crate) fn record_commit(
+        &self,
+        gated: GatedCommit,
+        commitment: CommitmentReceipt,
+    ) -> SdkResult<CommitResult> {
+        let outcome_record = gated.outcome_record();
+        let GatedCommit {
+            proposal,
+            tree_diff,
+            ledger_proposal,
+            gate_result,
+            branch,
+            mut payload,
+            ..
+        } = gated;
 
         let outcome = self.ledger.append_outcome(
             commitment.receipt_hash,
             &outcome_record,
         )?;
 
-        // Update branch tip
-        let branch = self.current_branch()?;
-        let branch_ref = Ref::Branch {
-            name: branch.clone(),
-            worldline: self.worldline.clone(),
-            receipt_hash: outcome.receipt_hash,
-        };
-        self.refs.write_ref(&format!("refs/heads/{branch}"), &branch_ref)
-            .map_err(|e| SdkError::Internal(e.to_string()))?;
+        self.record_provenance(&[
+            Receipt::Commitment(commitment.clone()),
+            Receipt::Outcome(outcome.clone()),
+        ])?;
+
+        let ref_update = self.advance_branch(&branch, outcome.receipt_hash)?;
+        self.emit_commit_events(Some(&commitment), &outcome, Some(ref_update))?;
+
+        payload.hook = HookPoint::PostCommit;
+        payload.receipt_hash = Some(hex::encode(outcome.receipt_hash));
+        self.run_hooks(&payload, || ledger_proposal.clone())?;
 
         Ok(CommitResult {
             receipt_hash: outcome.receipt_hash,
             commitment_receipt: commitment,
             outcome_receipt: outcome,
+            gate_result,
+            tree: proposal.tree,
+            tree_diff,
+        })
+    }
+
+    /// Point `branch` at `tip`, returning the ref name and the tip it had.
+    pub(crate) fn advance_branch(
+        &self,
+        branch: &str,
+        tip: [u8; 32],
+    ) -> SdkResult<(String, Option<[u8; 32]>)> {
+        let ref_name = format!("refs/heads/{branch}");
+        let old_tip = self.refs.read_ref(&ref_name)?
+            .map(|r| *r.target_hash())
+            .filter(|hash| *hash != [0; 32]);
+        let branch_ref = Ref::Branch {
+            name: branch.to_string(),
+            worldline: self.worldline.clone(),
+            receipt_hash: tip,
+        };
+        self.refs.write_ref(&ref_name, &branch_ref)?;
+        Ok((ref_name, old_tip))
+    }
+
+    /// Record `proposal` as rejected with `decision`, and the rejection
+    /// outcome, returning the error the rejected operation fails with.
+    pub(crate) fn record_rejection(
+        &self,
+        proposal: &CommitmentProposal,
+        decision: &Decision,
+        policy_hash: [u8; 32],
+        stage: Option<&str>,
+    ) -> SdkResult<SdkError> {
+        let Decision::Rejected { reason } = decision else {
+            return Err(SdkError::Internal("recording a rejection that was not one".into()));
+        };
+        let commitment = self.ledger.append_commitment(proposal, decision, policy_hash)?;
+        let outcome = self.ledger.append_rejection_outcome(commitment.receipt_hash, reason)?;
+        self.record_provenance(&[
+            Receipt::Commitment(commitment.clone()),
+            Receipt::Outcome(outcome.clone()),
+        ])?;
+        self.emit_commit_events(Some(&commitment), &outcome, None)?;
+        Ok(SdkError::CommitmentRejected {
+            reason: reason.clone(),
+            stage: stage.map(str::to_string),
+            receipt_hash: outcome.receipt_hash,
         })
     }
 
+    /// Dry-run `proposal` through the gate, as [`commit`](Self::commit)
+    /// would, with `extra_policies` applied alongside the configured one.
+    ///
+    /// Every stage and policy rule is evaluated and reported, even after
+    /// one fails and even when the gate is permissive. Nothing is written.
+    pub fn check_commit(
+        &self,
+        proposal: &SdkProposal,
+        extra_policies: &[Policy],
+    ) -> SdkResult<GateSimulation> {
+        self.check_attachments(&proposal.attachments)?;
+        let evidence = EvidenceBundle::new(proposal.evidence.clone(), proposal.attachments.clone());
+        let tree_diff = self.proposal_diff(proposal)?;
+        let (gate_proposal, mut context) = self.gate_input(proposal, evidence, tree_diff.as_ref())?;
+        context.policies.extend(extra_policies.iter().cloned());
+        Ok(self.gate.simulate(&gate_proposal, &context)?)
+    }
+
+    /// The change `proposal`'s tree makes to the head tree.
+    fn proposal_diff(&self, proposal: &SdkProposal) -> SdkResult<Option<TreeDiff>> {
+        match &proposal.tree {
+            Some(tree) => Ok(Some(diff_trees(self.store.as_ref(), self.head_tree()?.as_ref(), tree)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The gate's view of `proposal`, and the context it is judged in.
+    ///
+    /// Targets are the proposal's own, else the paths the tree changes,
+    /// else the tree or worldline id.
+    /// Refuse `proposal` writing gate context unless the gate would let it
+    /// ([`wll_gate::check_context_change`]), and unless the worldline holds
+    /// the admin capability it claims. Returns whether it is the
+    /// worldline's genesis.
+    fn check_context_change(&self, proposal: &SdkProposal) -> SdkResult<bool> {
+        let genesis = self.ledger.head(&self.worldline)?.is_none();
+        wll_gate::check_context_change(&proposal.effective_class(), &proposal.capabilities, genesis)?;
+        if genesis {
+            return Ok(true);
+        }
+        let now = self.clock.anchor(0);
+        let held = match self.gate.context_provider() {
+            Some(provider) => provider.capabilities(&self.worldline, &now)?,
+            None => Vec::new(),
+        };
+        if !held.iter().any(|cap| cap.id.0 == ADMIN_CAPABILITY && !cap.is_expired_at(&now)) {
+            return Err(GateError::CapabilityDenied(format!(
+                "changing gate context needs the {ADMIN_CAPABILITY} capability, which {} does \
+                 not hold",
+                self.worldline
+            ))
+            .into());
+        }
+        Ok(false)
+    }
+
+    fn gate_input(
+        &self,
+        proposal: &SdkProposal,
+        evidence: EvidenceBundle,
+        tree_diff: Option<&TreeDiff>,
+    ) -> SdkResult<(GateProposal, GateContext)> {
+        let changed_paths: Vec<String> = tree_diff
+            .iter()
+            .flat_map(|diff| diff.changes.iter().map(|c| change_path(c).to_string()))
+            .collect();
+        let targets = if !proposal.targets.is_empty() {
+            proposal.targets.clone()
+        } else if !changed_paths.is_empty() {
+            changed_paths
+        } else if let Some(tree) = proposal.tree {
+            vec![tree.to_hex()]
+        } else {
+            vec![self.worldline.to_hex()]
+        };
+
+        let gate_proposal = GateProposal {
+            proposer: self.worldline.clone(),
+            intent: proposal.effective_intent().to_string(),
+            class: proposal.effective_class(),
+            targets,
+            evidence,
+            claimed_capabilities: proposal.capabilities.clone(),
+            signature: None,
+        };
+        let mut context = GateContext::minimal(self.worldline.clone());
+        context.now = self.clock.anchor(0);
+        context.policies.push(self.gate.config().default_policy.clone());
+        context.attestations = self.valid_attestations()?;
+        if let Some(diff) = tree_diff.filter(|_| self.gate.has_stage(ChangeBudgetStage::NAME)) {
+            context.change = Some(self.change_size(diff)?);
+        }
+        Ok((gate_proposal, context))
+    }
+
+    /// Files and lines `diff` changes. Line counts come from diffing the
+    /// blobs on either side; renames and mode changes count as files only.
+    fn change_size(&self, diff: &TreeDiff) -> SdkResult<ChangeSize> {
+        let mut size = ChangeSize { files: diff.changes.len(), ..ChangeSize::default() };
+        for change in &diff.changes {
+            let (old, new) = match change {
+                TreeChange::Added { new_id, mode, .. } if *mode != EntryMode::Directory => {
+                    (Vec::new(), self.read_blob(new_id)?)
+                }
+                TreeChange::Deleted { old_id, mode, .. } if *mode != EntryMode::Directory => {
+                    (self.read_blob(old_id)?, Vec::new())
+                }
+                TreeChange::Modified { old_id, new_id, mode, .. }
+                    if *mode != EntryMode::Directory =>
+                {
+                    (self.read_blob(old_id)?, self.read_blob(new_id)?)
+                }
+                _ => continue,
+            };
+            let lines = diff_blobs(&old, &new);
+            size.lines_added += lines.additions();
+            size.lines_removed += lines.deletions();
+        }
+        Ok(size)
+    }
+
+    /// The latest `limit` receipts, newest first.
+    ///
+    /// Walks the commit graph back from the worldline head, so only the
+    /// receipts listed are read from the ledger.
     pub fn log(&self, limit: usize) -> SdkResult<Vec<ReceiptSummary>> {
-        let receipts = self.ledger.read_all(&self.worldline)?;
+        let Some(head) = self.ledger.head(&self.worldline)? else {
+            return Ok(Vec::new());
+        };
+        let receipts = self
+            .history(&head.receipt_hash, limit)?
+            .into_iter()
+            .map(|hash| {
+                self.ledger.get_by_hash(hash)?
+                    .ok_or_else(|| SdkError::ObjectNotFound(hex::encode(hash)))
+            })
+            .collect::<SdkResult<Vec<_>>>()?;
         let summaries = receipts
             .iter()
-            .rev()
-            .take(limit)
             .map(|r| {
                 let (intent, accepted) = match r {
                     Receipt::Commitment(c) => (Some(c.intent.clone()), Some(c.decision.is_accepted())),
@@ -176,6 +961,47 @@ impl Wll {
         Ok(summaries)
     }
 
+    // ---- History queries ----
+
+    /// Receipt hashes of `tip` and its causal ancestors, newest first, at
+    /// most `limit` of them. Every receipt comes before its parents.
+    pub fn history(&self, tip: &[u8; 32], limit: usize) -> SdkResult<Vec<[u8; 32]>> {
+        let graph = self.read_graph()?;
+        Ok(graph
+            .walk(&ObjectId::from_hash(*tip))
+            .take(limit)
+            .map(|id| *id.as_bytes())
+            .collect())
+    }
+
+    /// The newest receipt that both `a` and `b` descend from.
+    pub fn merge_base(&self, a: &[u8; 32], b: &[u8; 32]) -> SdkResult<Option<[u8; 32]>> {
+        let graph = self.read_graph()?;
+        Ok(graph
+            .merge_base(&ObjectId::from_hash(*a), &ObjectId::from_hash(*b))
+            .map(|id| *id.as_bytes()))
+    }
+
+    /// Whether `ancestor` is `descendant` or one of its causal ancestors.
+    pub fn is_ancestor(&self, ancestor: &[u8; 32], descendant: &[u8; 32]) -> SdkResult<bool> {
+        let graph = self.read_graph()?;
+        Ok(graph.is_ancestor(&ObjectId::from_hash(*ancestor), &ObjectId::from_hash(*descendant)))
+    }
+
+    /// The receipt to test next when hunting for the first bad one between
+    /// the `good` receipts and `bad`. See [`CommitGraph::bisect`].
+    pub fn bisect(&self, good: &[[u8; 32]], bad: &[u8; 32]) -> SdkResult<Option<BisectStep>> {
+        let good: Vec<ObjectId> = good.iter().copied().map(ObjectId::from_hash).collect();
+        let graph = self.read_graph()?;
+        Ok(graph.bisect(&good, &ObjectId::from_hash(*bad)))
+    }
+
+    pub(crate) fn read_graph(&self) -> SdkResult<std::sync::RwLockReadGuard<'_, CommitGraph>> {
+        self.graph
+            .read()
+            .map_err(|_| SdkError::Internal("commit graph lock poisoned".into()))
+    }
+
     pub fn show(&self, receipt_hash: &[u8; 32]) -> SdkResult<Receipt> {
         let receipt = self.ledger.get_by_hash(*receipt_hash)?
             .ok_or_else(|| SdkError::ObjectNotFound(hex::encode(receipt_hash)))?;
@@ -192,25 +1018,21 @@ impl Wll {
             worldline: self.worldline.clone(),
             receipt_hash: tip,
         };
-        self.refs.write_ref(&format!("refs/heads/{name}"), &branch_ref)
-            .map_err(|e| SdkError::Internal(e.to_string()))?;
+        self.refs.write_ref(&format!("refs/heads/{name}"), &branch_ref)?;
         Ok(())
     }
 
     pub fn switch_branch(&self, name: &str) -> SdkResult<()> {
-        let existing = self.refs.read_ref(&format!("refs/heads/{name}"))
-            .map_err(|e| SdkError::Internal(e.to_string()))?;
+        let existing = self.refs.read_ref(&format!("refs/heads/{name}"))?;
         if existing.is_none() {
             return Err(SdkError::BranchNotFound(name.into()));
         }
-        self.refs.set_head(name)
-            .map_err(|e| SdkError::Internal(e.to_string()))?;
+        self.refs.set_head(name)?;
         Ok(())
     }
 
     pub fn current_branch(&self) -> SdkResult<String> {
-        let head = self.refs.head()
-            .map_err(|e| SdkError::Internal(e.to_string()))?;
+        let head = self.refs.head()?;
         match head {
             Some(Head::Symbolic(name)) => Ok(name),
             Some(Head::Detached(_)) => Err(SdkError::InvalidOperation("HEAD is detached".into())),
@@ -219,33 +1041,158 @@ impl Wll {
     }
 
     pub fn list_branches(&self) -> SdkResult<Vec<String>> {
-        let branches = self.refs.branches()
-            .map_err(|e| SdkError::Internal(e.to_string()))?;
+        let branches = self.refs.branches()?;
         Ok(branches.into_iter().map(|(name, _)| name).collect())
     }
 
     // ---- Provenance queries ----
 
     pub fn verify(&self) -> SdkResult<ValidationReport> {
-        let report = StreamValidator::validate_stream(&self.ledger, &self.worldline)?;
+        let report = StreamValidator::validate_stream(self.ledger.as_ref(), &self.worldline)?;
         Ok(report)
     }
 
     pub fn replay(&self) -> SdkResult<ReplayResult> {
-        let result = ReplayEngine::replay_from_genesis(&self.ledger, &self.worldline)?;
+        let result = ReplayEngine::replay_from_genesis(self.ledger.as_ref(), &self.worldline)?;
+        Ok(result)
+    }
+
+    /// Check later commits' state updates to `namespace` (the default
+    /// namespace when `None`) against `schema`. Commits the schema rejects
+    /// fail before anything is recorded.
+    ///
+    /// A schema for the default namespace must accept the `message` and
+    /// `tree` keys every commit sets.
+    pub fn register_state_schema(
+        &self,
+        namespace: Option<&str>,
+        schema: Arc<dyn StateSchema>,
+    ) -> SdkResult<()> {
+        self.ledger.register_schema(&self.worldline, namespace, schema)?;
+        Ok(())
+    }
+
+    /// Replay from genesis, checking each outcome's state updates against
+    /// the schema versions its receipt records.
+    pub fn replay_validated(&self) -> SdkResult<ReplayResult> {
+        let schemas = self.ledger.schemas()?;
+        let result = ReplayEngine::replay_validated(self.ledger.as_ref(), &self.worldline, &schemas)?;
+        Ok(result)
+    }
+
+    /// Replay from genesis, re-running the gate on every commitment under
+    /// the policy version it was judged by and flagging decisions that do
+    /// not match.
+    ///
+    /// The proposal is rebuilt from the record: its targets are the paths
+    /// the outcome changed, else its tree, else the worldline, and only
+    /// attestations issued and unexpired at the receipt's time count.
+    /// Explicit targets are not recorded, and stages that look outside the
+    /// ledger (evidence resolution) see the world as it is now. Commitments
+    /// under a policy version missing from the [`policy_store`](Self::policy_store),
+    /// such as ones pulled from another repository, are listed rather than
+    /// checked.
+    pub fn replay_strict(&self) -> SdkResult<StrictReplayResult> {
+        let result = ReplayEngine::replay_strict(
+            self.ledger.as_ref(),
+            &self.worldline,
+            &GateReplay(self),
+        )?;
         Ok(result)
     }
 
+    pub(crate) fn reevaluate(
+        &self,
+        commitment: &CommitmentReceipt,
+        outcome: Option<&OutcomeReceipt>,
+    ) -> SdkResult<Option<Decision>> {
+        let Some(policy) = self.policies.get(&commitment.policy_hash)? else {
+            return Ok(None);
+        };
+        let Some(intent) = if_held(self.open_text(&commitment.intent))? else {
+            return Ok(None);
+        };
+        let mut targets: Vec<String> = outcome
+            .iter()
+            .flat_map(|o| o.effects.iter().map(|e| e.target.clone()))
+            .collect();
+        if targets.is_empty() {
+            let tree = outcome.and_then(|o| {
+                o.state_updates
+                    .iter()
+                    .find(|update| update.namespace.is_none() && update.key == TREE_STATE_KEY)
+                    .and_then(|update| update.value.as_str())
+            });
+            targets.push(tree.map_or_else(|| self.worldline.to_hex(), str::to_string));
+        }
+
+        let proposal = GateProposal {
+            proposer: commitment.worldline.clone(),
+            intent,
+            class: commitment.class.clone(),
+            targets,
+            evidence: commitment.evidence.clone(),
+            claimed_capabilities: commitment.requested_caps.clone(),
+            signature: None,
+        };
+        let at_secs = commitment.timestamp.physical_ms / 1000;
+        let mut context = GateContext::minimal(commitment.worldline.clone());
+        context.now = commitment.timestamp;
+        context.policies.push(policy);
+        context.attestations = self.attestations()?;
+        context
+            .attestations
+            .retain(|a| a.issued_at <= at_secs && a.is_valid_at(at_secs));
+        Ok(Some(self.gate.evaluate_with_context(&proposal, &mut context)?.decision))
+    }
+
     pub fn latest_state(&self) -> SdkResult<LatestStateProjection> {
-        let projection = ProjectionBuilder::latest_state(&self.ledger, &self.worldline)?;
+        let projection = ProjectionBuilder::latest_state(self.ledger.as_ref(), &self.worldline)?;
         Ok(projection)
     }
 
+    /// Outcomes with an effect `query` matches, oldest first: for example
+    /// every deployment to `deploy://prod/*`.
+    pub fn outcomes_with_effects(&self, query: &EffectQuery) -> SdkResult<Vec<OutcomeReceipt>> {
+        let ledger = self.ledger.as_ref();
+        Ok(ProjectionBuilder::outcomes_with_effects(ledger, &self.worldline, query)?)
+    }
+
     // ---- Accessors ----
 
     pub fn worldline(&self) -> &WorldlineId { &self.worldline }
-    pub fn store(&self) -> &InMemoryObjectStore { &self.store }
-    pub fn ledger(&self) -> &InMemoryLedger { &self.ledger }
+    pub fn store(&self) -> &dyn ObjectStore { self.store.as_ref() }
+    pub fn ledger(&self) -> &dyn Ledger { self.ledger.as_ref() }
+    /// The ledger, for serving alongside this handle.
+    pub fn shared_ledger(&self) -> Arc<dyn Ledger> { self.ledger.clone() }
+    pub fn refs(&self) -> &dyn RefStore { self.refs.as_ref() }
+    /// The ref store, for serving alongside this handle.
+    pub fn shared_refs(&self) -> Arc<dyn RefStore> { self.refs.clone() }
+
+    /// The event fabric commits are published to, if one is attached.
+    pub fn fabric(&self) -> Option<&Arc<EventFabric>> { self.fabric.as_ref() }
+
+    /// The `.wll` directory backing this repository, if it is persistent.
+    pub fn repo_dir(&self) -> Option<&Path> { self.repo_dir.as_deref() }
+
+    /// System, user, and repository settings merged. In-memory repositories
+    /// see only the system and user levels.
+    pub fn config(&self) -> SdkResult<LayeredConfig> {
+        Ok(LayeredConfig::load(&ConfigPaths::standard(self.repo_dir()))?)
+    }
+
+    /// Number of receipts recorded in the provenance DAG.
+    pub fn provenance_len(&self) -> usize {
+        self.dag.read().map(|dag| dag.len()).unwrap_or(0)
+    }
+
+    /// Snapshot of the provenance DAG for graph queries and rendering.
+    pub fn provenance(&self) -> SdkResult<ProvenanceDag> {
+        self.dag
+            .read()
+            .map(|dag| dag.clone())
+            .map_err(|_| SdkError::Internal("DAG lock poisoned".into()))
+    }
 
     pub fn receipt_count(&self) -> SdkResult<u64> {
         let count = self.ledger.receipt_count(&self.worldline)?;
@@ -253,6 +1200,195 @@ impl Wll {
     }
 }
 
+/// State key under which tree commits record their root tree.
+pub(crate) const TREE_STATE_KEY: &str = "tree";
+
+/// Tree snapshot recorded by an outcome, if it was a tree commit.
+pub(crate) fn outcome_tree(outcome: &OutcomeReceipt) -> SdkResult<Option<ObjectId>> {
+    outcome
+        .state_updates
+        .iter()
+        .rev()
+        .find(|update| update.namespace.is_none() && update.key == TREE_STATE_KEY)
+        .and_then(|update| update.value.as_str())
+        .map(ObjectId::from_hex)
+        .transpose()
+        .map_err(|e| SdkError::Internal(format!("invalid tree pointer: {e}")))
+}
+
+/// Re-evaluates recorded decisions with a repository's gate and policy
+/// store, for [`Wll::replay_strict`].
+struct GateReplay<'a>(&'a Wll);
+
+impl DecisionReevaluator for GateReplay<'_> {
+    fn reevaluate(
+        &self,
+        commitment: &CommitmentReceipt,
+        outcome: Option<&OutcomeReceipt>,
+    ) -> Result<Option<Decision>, LedgerError> {
+        self.0
+            .reevaluate(commitment, outcome)
+            .map_err(|e| LedgerError::StoreError(e.to_string()))
+    }
+}
+
+/// The `.wll` directory of the repository at `path`, which may be the
+/// working directory containing it or the directory itself.
+pub(crate) fn find_repo_dir(path: &Path) -> SdkResult<PathBuf> {
+    if path.join(WLL_DIR).join("config").is_file() {
+        Ok(path.join(WLL_DIR))
+    } else if path.join("config").is_file() {
+        Ok(path.to_path_buf())
+    } else {
+        Err(SdkError::NotInitialized(path.display().to_string()))
+    }
+}
+
+fn change_path(change: &TreeChange) -> &str {
+    match change {
+        TreeChange::Added { path, .. }
+        | TreeChange::Deleted { path, .. }
+        | TreeChange::Modified { path, .. }
+        | TreeChange::ModeChanged { path, .. } => path,
+        TreeChange::Renamed { new_path, .. } => new_path,
+    }
+}
+
+fn change_effect(change: &TreeChange) -> EffectSummary {
+    let (kind, description, payload) = match change {
+        TreeChange::Added { new_id, mode, .. } => (
+            EffectKind::Added,
+            format!("-> {}", new_id.short_hex()),
+            serde_json::json!({"object": new_id.to_hex(), "mode": mode.to_string()}),
+        ),
+        TreeChange::Deleted { old_id, .. } => (
+            EffectKind::Deleted,
+            format!("{} ->", old_id.short_hex()),
+            serde_json::json!({"object": old_id.to_hex()}),
+        ),
+        TreeChange::Modified { old_id, new_id, .. } => (
+            EffectKind::Modified,
+            format!("{} -> {}", old_id.short_hex(), new_id.short_hex()),
+            serde_json::json!({"old": old_id.to_hex(), "new": new_id.to_hex()}),
+        ),
+        TreeChange::Renamed { old_path, similarity, .. } => (
+            EffectKind::Renamed,
+            format!("from {old_path} ({:.0}% similar)", similarity * 100.0),
+            serde_json::json!({"from": old_path, "similarity": similarity}),
+        ),
+        TreeChange::ModeChanged { old_mode, new_mode, .. } => (
+            EffectKind::ModeChanged,
+            format!("{old_mode} -> {new_mode}"),
+            serde_json::json!({"old": old_mode.to_string(), "new": new_mode.to_string()}),
+        ),
+    };
+    EffectSummary::new(kind, change_path(change), description).with_payload(payload)
+}
+
+fn default_gate() -> CommitmentGate {
+    CommitmentGate::with_default_stages(GateConfig::permissive())
+}
+
+/// `gate`, looking its context up in `ledger` unless it has a provider.
+fn ledger_context(gate: CommitmentGate, ledger: &Arc<dyn Ledger>) -> CommitmentGate {
+    if gate.context_provider().is_some() {
+        return gate;
+    }
+    gate.with_context_provider(Arc::new(LedgerContextProvider::new(ledger.clone())))
+}
+
+pub(crate) fn random_worldline() -> WorldlineId {
+    WorldlineId::derive(&IdentityMaterial::GenesisHash(time_based_seed()))
+}
+
+fn receipt_object_id(receipt: &Receipt) -> ObjectId {
+    ObjectId::from_hash(receipt.receipt_hash())
+}
+
+/// Build the provenance DAG node for a receipt.
+///
+/// Outcomes point at their commitment, snapshots at their anchor, and every
+/// receipt at its stream predecessor unless that edge is already present.
+/// Commitments also point at the receipts of other worldlines their
+/// evidence cites (`receipt://` references).
+fn dag_node(receipt: &Receipt) -> DagNode {
+    let mut parents: Vec<ParentRef> = receipt
+        .cross_worldline_refs()
+        .into_iter()
+        .map(|cited| ParentRef::cross_worldline(ObjectId::from_hash(cited.receipt_hash)))
+        .collect();
+    match receipt {
+        Receipt::Commitment(_) => {}
+        Receipt::Outcome(o) => parents.push(ParentRef::new(
+            ObjectId::from_hash(o.commitment_receipt_hash),
+            CausalRelation::CommitmentToOutcome,
+        )),
+        Receipt::Snapshot(s) => parents.push(ParentRef::new(
+            ObjectId::from_hash(s.anchored_receipt_hash),
+            CausalRelation::SnapshotAnchor,
+        )),
+    }
+    if let Some(prev) = receipt.prev_hash().map(ObjectId::from_hash) {
+        if parents.iter().all(|p| p.target != prev) {
+            parents.push(ParentRef::sequential(prev));
+        }
+    }
+
+    let metadata = match receipt {
+        Receipt::Commitment(c) => {
+            DagNodeMetadata::with_description(c.intent.clone()).with_class(&c.class)
+        }
+        Receipt::Outcome(o) => {
+            let reversibility = o.metadata.get(REVERSIBILITY_KEY);
+            match reversibility.and_then(|name| Reversibility::from_name(name)) {
+                Some(reversibility) => DagNodeMetadata::empty().with_reversibility(&reversibility),
+                None => DagNodeMetadata::empty(),
+            }
+        }
+        Receipt::Snapshot(_) => DagNodeMetadata::empty(),
+    };
+
+    DagNode {
+        id: receipt_object_id(receipt),
+        worldline: receipt.worldline().clone(),
+        seq: receipt.seq(),
+        kind: receipt.kind(),
+        timestamp: receipt.timestamp(),
+        parents,
+        metadata,
+    }
+}
+
+/// Nodes for the receipts of other worldlines `node` cites that `ledger`
+/// holds and `dag` lacks, without parents of their own, to add before it.
+/// Citations the ledger cannot resolve are dropped from `node`.
+fn cited_nodes(
+    dag: &ProvenanceDag,
+    ledger: &dyn Ledger,
+    node: &mut DagNode,
+) -> SdkResult<Vec<DagNode>> {
+    let mut cited: Vec<DagNode> = Vec::new();
+    let mut unresolved = Vec::new();
+    for parent in &node.parents {
+        if parent.relation != CausalRelation::CrossWorldline
+            || dag.get_node(&parent.target).is_some()
+            || cited.iter().any(|c| c.id == parent.target)
+        {
+            continue;
+        }
+        match ledger.get_by_hash(*parent.target.as_bytes())? {
+            Some(receipt) => {
+                let mut cited_node = dag_node(&receipt);
+                cited_node.parents.clear();
+                cited.push(cited_node);
+            }
+            None => unresolved.push(parent.target),
+        }
+    }
+    node.parents.retain(|p| !unresolved.contains(&p.target));
+    Ok(cited)
+}
+
 fn time_based_seed() -> [u8; 32] {
     use std::time::{SystemTime, UNIX_EPOCH};
     let t = SystemTime::now()
@@ -265,18 +1401,11 @@ fn time_based_seed() -> [u8; 32] {
     *blake3::hash(&seed).as_bytes()
 }
 
-fn time_nonce() -> u64 {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_nanos() as u64
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
-    use wll_store::EntryMode;
+    use wll_ledger::KeySchema;
+    use wll_types::{Coded, CommitmentClass, ErrorCode};
 
     fn wl_seed(seed: u8) -> WorldlineId {
         WorldlineId::derive(&IdentityMaterial::GenesisHash([seed; 32]))
@@ -428,4 +1557,573 @@ mod tests {
         let state = wll.latest_state().unwrap();
         assert!(state.trajectory_length > 0);
     }
+
+    #[test]
+    fn commit_records_provenance() {
+        let wll = Wll::init().unwrap();
+        wll.commit(SdkProposal::new("tracked")).unwrap();
+        assert_eq!(wll.provenance_len(), 2);
+        assert!(wll.repo_dir().is_none());
+    }
+
+    #[test]
+    fn impact_report_weighs_class_and_reversibility() {
+        let wll = Wll::init().unwrap();
+        let first = wll.commit(SdkProposal::new("start")).unwrap();
+        let drop = SdkProposal::new("drop tables")
+            .with_class(CommitmentClass::StructuralChange)
+            .with_reversibility(Reversibility::Irreversible);
+        let dropped = wll.commit(drop).unwrap();
+
+        let report = wll.provenance().unwrap().impact_report(&receipt_object_id(
+            &Receipt::Outcome(first.outcome_receipt),
+        ));
+        assert_eq!(report.irreversible, vec![ObjectId::from_hash(dropped.receipt_hash)]);
+        assert_eq!(report.max_risk_level, CommitmentClass::StructuralChange.risk_level());
+    }
+
+    #[test]
+    fn init_at_then_open_restores_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let (worldline, blob_id, tip) = {
+            let wll = Wll::init_at(dir.path()).unwrap();
+            let blob_id = wll.write_blob(b"on disk").unwrap();
+            wll.commit(SdkProposal::new("first")).unwrap();
+            let tip = wll.commit(SdkProposal::new("second")).unwrap().receipt_hash;
+            wll.create_branch("dev").unwrap();
+            (wll.worldline().clone(), blob_id, tip)
+        };
+
+        let wll = Wll::open(dir.path()).unwrap();
+        assert_eq!(wll.worldline(), &worldline);
+        assert_eq!(wll.repo_dir(), Some(dir.path().join(".wll").as_path()));
+        assert_eq!(wll.read_blob(&blob_id).unwrap(), b"on disk");
+        assert_eq!(wll.receipt_count().unwrap(), 4);
+        assert_eq!(wll.current_branch().unwrap(), "main");
+        assert_eq!(wll.list_branches().unwrap().len(), 2);
+        let main = wll.refs.read_ref("refs/heads/main").unwrap().unwrap();
+        assert_eq!(*main.target_hash(), tip);
+        assert_eq!(wll.provenance_len(), 4);
+        assert!(wll.verify().unwrap().is_valid());
+
+        let next = wll.commit(SdkProposal::new("third")).unwrap();
+        assert_eq!(next.commitment_receipt.seq, 5);
+    }
+
+    #[test]
+    fn open_accepts_wll_dir_and_rebuilds_missing_dag() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let wll = Wll::init_at(dir.path()).unwrap();
+            wll.commit(SdkProposal::new("one")).unwrap();
+        }
+        std::fs::remove_file(dir.path().join(".wll").join("dag")).unwrap();
+
+        let wll = Wll::open(dir.path().join(".wll")).unwrap();
+        assert_eq!(wll.provenance_len(), 2);
+        assert!(dir.path().join(".wll").join("dag").is_file());
+    }
+
+    #[test]
+    fn open_rebuilds_a_stale_commit_graph() {
+        let dir = tempfile::tempdir().unwrap();
+        let graph_path = dir.path().join(".wll").join("commit-graph");
+        {
+            let wll = Wll::init_at(dir.path()).unwrap();
+            wll.commit(SdkProposal::new("one")).unwrap();
+        }
+        let stale = std::fs::read(&graph_path).unwrap();
+        {
+            let wll = Wll::open(dir.path()).unwrap();
+            wll.commit(SdkProposal::new("two")).unwrap();
+        }
+        std::fs::write(&graph_path, stale).unwrap();
+
+        let wll = Wll::open(dir.path()).unwrap();
+        assert_eq!(wll.log(10).unwrap().len(), 4);
+        let saved = CommitGraph::from_bytes(&std::fs::read(&graph_path).unwrap()).unwrap();
+        assert_eq!(saved.len(), 4);
+    }
+
+    #[test]
+    fn history_queries_follow_the_commit_graph() {
+        let wll = Wll::init().unwrap();
+        let first = wll.commit(SdkProposal::new("first")).unwrap().receipt_hash;
+        let second = wll.commit(SdkProposal::new("second")).unwrap().receipt_hash;
+        let third = wll.commit(SdkProposal::new("third")).unwrap().receipt_hash;
+
+        let history = wll.history(&third, usize::MAX).unwrap();
+        assert_eq!(history.len(), 6);
+        assert_eq!(history[0], third);
+        assert!(wll.is_ancestor(&first, &third).unwrap());
+        assert!(!wll.is_ancestor(&third, &first).unwrap());
+        assert_eq!(wll.merge_base(&second, &third).unwrap(), Some(second));
+
+        let step = wll.bisect(&[first], &third).unwrap().unwrap();
+        assert_eq!(step.candidates, 4);
+        assert!(wll.is_ancestor(&first, step.next.as_bytes()).unwrap());
+        assert!(wll.bisect(&[third], &second).unwrap().is_none());
+    }
+
+    #[test]
+    fn open_without_repository_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let err = Wll::open(dir.path()).err().unwrap();
+        assert!(matches!(err, SdkError::NotInitialized(_)));
+    }
+
+    #[test]
+    fn discover_walks_up_from_subdirectory() {
+        let dir = tempfile::tempdir().unwrap();
+        let worldline = Wll::init_at(dir.path()).unwrap().worldline().clone();
+        let nested = dir.path().join("src").join("deep");
+        std::fs::create_dir_all(&nested).unwrap();
+
+        assert_eq!(Wll::discover(&nested).unwrap().worldline(), &worldline);
+        let outside = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            Wll::discover(outside.path()).err().unwrap(),
+            SdkError::NotInitialized(_)
+        ));
+    }
+
+    #[test]
+    fn init_at_refuses_existing_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        Wll::init_at(dir.path()).unwrap();
+        let err = Wll::init_at(dir.path()).err().unwrap();
+        assert!(matches!(err, SdkError::InvalidOperation(_)));
+    }
+
+    #[test]
+    fn init_at_with_config_honours_hash_algo() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = RepoConfig::new(wl_seed(9));
+        config.hash_algo = wll_types::HashAlgo::Sha256;
+        {
+            let wll = Wll::init_at_with_config(dir.path(), config).unwrap();
+            wll.commit(SdkProposal::new("sha")).unwrap();
+        }
+        let wll = Wll::open(dir.path()).unwrap();
+        assert_eq!(wll.worldline(), &wl_seed(9));
+        assert!(wll.verify().unwrap().is_valid());
+    }
+
+    fn evidence_gate() -> CommitmentGate {
+        let config = GateConfig {
+            default_policy: wll_gate::Policy {
+                id: "evidence".into(),
+                name: "Require evidence".into(),
+                rules: vec![wll_gate::PolicyRule::RequireEvidence],
+                applies_to: wll_gate::PolicyScope::All,
+            },
+            ..GateConfig::default()
+        };
+        CommitmentGate::with_default_stages(config)
+    }
+
+    #[test]
+    fn commit_records_gate_policy_hash() {
+        let wll = Wll::init().unwrap().with_gate(evidence_gate());
+        let result = wll
+            .commit(SdkProposal::new("documented").with_evidence("issue://7"))
+            .unwrap();
+        assert!(result.gate_result.is_accepted());
+        assert_ne!(result.commitment_receipt.policy_hash, [0; 32]);
+        assert_eq!(
+            result.commitment_receipt.policy_hash,
+            result.gate_result.policy_hash
+        );
+    }
+
+    #[test]
+    fn open_applies_gate_config_section() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = Wll::init_at(dir.path()).unwrap().repo_dir().unwrap().join("config");
+        let mut text = std::fs::read_to_string(&config_path).unwrap();
+        text.push_str("\n[gate]\nrequire_evidence = true\n");
+        std::fs::write(&config_path, text).unwrap();
+
+        let wll = Wll::open(dir.path()).unwrap();
+        assert!(!wll.gate().config().permissive);
+        assert!(wll.config().unwrap().gate().unwrap().require_evidence);
+        assert!(matches!(
+            wll.commit(SdkProposal::new("no evidence")),
+            Err(SdkError::CommitmentRejected { .. })
+        ));
+        wll.commit(SdkProposal::new("documented").with_evidence("issue://7"))
+            .unwrap();
+    }
+
+    #[test]
+    fn open_applies_intent_conventions() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = Wll::init_at(dir.path()).unwrap().repo_dir().unwrap().join("config");
+        let mut text = std::fs::read_to_string(&config_path).unwrap();
+        text.push_str("\n[gate.intent]\ntypes = [\"feat\", \"fix\"]\n");
+        text.push_str("require_issue = [\"PolicyChange\"]\n");
+        std::fs::write(&config_path, text).unwrap();
+
+        let wll = Wll::open(dir.path()).unwrap();
+        assert!(matches!(
+            wll.commit(SdkProposal::new("tweak things")),
+            Err(SdkError::CommitmentRejected { .. })
+        ));
+        let policy = SdkProposal::conventional("fix", Some("gate"), "tighten rules")
+            .with_class(CommitmentClass::PolicyChange);
+        assert!(wll.commit(policy.clone()).is_err());
+        wll.commit(policy.with_issue("SEC-9")).unwrap();
+    }
+
+    #[test]
+    fn received_trees_beyond_the_object_limits_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = Wll::init_at(dir.path()).unwrap().repo_dir().unwrap().join("config");
+        let mut text = std::fs::read_to_string(&config_path).unwrap();
+        text.push_str("\n[limits.objects]\nmax_array_len = 1\n");
+        std::fs::write(&config_path, text).unwrap();
+
+        let blob = Blob::new(b"x".to_vec()).to_stored_object().compute_id();
+        let tree = Tree::new(vec![
+            TreeEntry::new(EntryMode::Regular, "a", blob),
+            TreeEntry::new(EntryMode::Regular, "b", blob),
+        ]);
+        let mut writer = wll_pack::PackWriter::new(&dir.path().join("in.pack"));
+        writer.add_stored_object(&tree.to_stored_object().unwrap());
+        let (pack, _) = writer.finish_to_bytes().unwrap();
+
+        let wll = Wll::open(dir.path()).unwrap();
+        let err = wll.quarantine_pack(Some(pack)).err().unwrap();
+        assert_eq!(err.code(), ErrorCode::LimitExceeded);
+    }
+
+    #[test]
+    fn verify_evidence_resolves_work_tree_files_and_objects() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = Wll::init_at(dir.path()).unwrap().repo_dir().unwrap().join("config");
+        let mut text = std::fs::read_to_string(&config_path).unwrap();
+        text.push_str("\n[gate]\nverify_evidence = true\n");
+        std::fs::write(&config_path, text).unwrap();
+        std::fs::write(dir.path().join("REVIEW.md"), "lgtm").unwrap();
+
+        let wll = Wll::open(dir.path()).unwrap();
+        let pin = hex::encode(blake3::hash(b"lgtm").as_bytes());
+        wll.commit(SdkProposal::new("reviewed").with_evidence(format!("file://REVIEW.md#blake3={pin}")))
+            .unwrap();
+        let log = wll.write_blob(b"test log").unwrap();
+        wll.commit(SdkProposal::new("tested").with_evidence(format!("obj://{}", log.to_hex())))
+            .unwrap();
+
+        std::fs::write(dir.path().join("REVIEW.md"), "changes requested").unwrap();
+        let err = wll
+            .commit(SdkProposal::new("stale").with_evidence(format!("file://REVIEW.md#blake3={pin}")))
+            .unwrap_err();
+        assert!(matches!(err, SdkError::CommitmentRejected { stage: Some(ref s), .. } if s == "evidence"));
+    }
+
+    #[test]
+    fn gate_rejection_is_recorded_and_typed() {
+        let wll = Wll::init().unwrap().with_gate(evidence_gate());
+        let err = wll.commit(SdkProposal::new("no evidence")).unwrap_err();
+
+        let SdkError::CommitmentRejected { reason, stage, receipt_hash } = err else {
+            panic!("expected rejection, got {err:?}");
+        };
+        assert!(reason.contains("evidence"));
+        assert_eq!(stage.as_deref(), Some("policy"));
+
+        // The rejected commitment and its outcome are on the record...
+        assert_eq!(wll.receipt_count().unwrap(), 2);
+        let outcome = wll.show(&receipt_hash).unwrap();
+        assert!(!outcome.as_outcome().unwrap().accepted);
+        assert!(wll.verify().unwrap().is_valid());
+
+        // ...but the branch does not advance.
+        let main = wll.refs.read_ref("refs/heads/main").unwrap().unwrap();
+        assert_eq!(*main.target_hash(), [0; 32]);
+    }
+
+    #[test]
+    fn check_commit_reports_rules_without_recording() {
+        let wll = Wll::init().unwrap().with_gate(evidence_gate());
+        let extra = wll_gate::Policy {
+            id: "small".into(),
+            name: "Small changes".into(),
+            rules: vec![wll_gate::PolicyRule::MaxTargets(1)],
+            applies_to: wll_gate::PolicyScope::All,
+        };
+        let proposal = SdkProposal::new("wide")
+            .with_target("a.txt")
+            .with_target("b.txt");
+
+        let simulation = wll.check_commit(&proposal, &[extra]).unwrap();
+        assert!(!simulation.is_accepted());
+        let failures: Vec<_> = simulation
+            .failures()
+            .map(|(stage, check)| (stage, check.policy.as_deref(), check.rule.clone()))
+            .collect();
+        assert_eq!(
+            failures,
+            [
+                ("policy", Some("evidence"), Some("require_evidence".to_string())),
+                ("policy", Some("small"), Some("max_targets(1)".to_string())),
+            ]
+        );
+        assert_eq!(wll.receipt_count().unwrap(), 0);
+
+        let documented = proposal.with_evidence("issue://7");
+        assert!(wll.check_commit(&documented, &[]).unwrap().is_accepted());
+    }
+
+    #[test]
+    fn strict_replay_flags_decisions_the_recorded_policy_contradicts() {
+        let ledger: Arc<dyn Ledger> = Arc::new(InMemoryLedger::default());
+        let refs: Arc<dyn RefStore> = Arc::new(InMemoryRefStore::new());
+        let policies: Arc<dyn PolicyStore> = Arc::new(InMemoryPolicyStore::new());
+        let open = |gate: CommitmentGate, policies: Arc<dyn PolicyStore>| {
+            Wll::builder()
+                .with_worldline(wl_seed(11))
+                .with_ledger(ledger.clone())
+                .with_refs(refs.clone())
+                .with_gate(gate)
+                .with_policy_store(policies)
+                .build()
+                .unwrap()
+        };
+
+        let wll = open(evidence_gate(), policies.clone());
+        wll.commit(SdkProposal::new("documented").with_evidence("issue://7")).unwrap();
+        wll.commit(SdkProposal::new("undocumented")).unwrap_err();
+        let result = wll.replay_strict().unwrap();
+        assert_eq!(result.decisions_checked, 2);
+        assert!(result.decisions_consistent());
+        assert!(result.unknown_policies.is_empty());
+
+        // A gate that records the evidence policy but enforces nothing.
+        let mut buggy = evidence_gate().config().clone();
+        buggy.permissive = true;
+        let accepted = open(CommitmentGate::with_default_stages(buggy), policies.clone())
+            .commit(SdkProposal::new("slipped through"))
+            .unwrap();
+        // A commitment judged under a policy this store never saw.
+        open(default_gate(), Arc::new(InMemoryPolicyStore::new()))
+            .commit(SdkProposal::new("elsewhere"))
+            .unwrap();
+
+        let result = wll.replay_strict().unwrap();
+        assert_eq!(result.decisions_checked, 3);
+        assert_eq!(result.unknown_policies.len(), 1);
+        assert_eq!(result.mismatches.len(), 1);
+        let mismatch = &result.mismatches[0];
+        assert_eq!(mismatch.seq, accepted.commitment_receipt.seq);
+        assert_eq!(mismatch.recorded, Decision::Accepted);
+        assert!(matches!(&mismatch.reevaluated, Decision::Rejected { reason } if reason.contains("evidence")));
+        assert_eq!(result.replay, wll.replay().unwrap());
+    }
+
+    #[test]
+    fn unheld_capability_is_rejected_by_capability_stage() {
+        let gate = CommitmentGate::with_default_stages(GateConfig::default());
+        let wll = Wll::init().unwrap().with_gate(gate);
+        let err = wll
+            .commit(SdkProposal::new("deploy").with_capability("deploy"))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            SdkError::CommitmentRejected { stage: Some(ref s), .. } if s == "capability"
+        ));
+    }
+
+    #[test]
+    fn capability_granted_in_the_ledger_is_honoured() {
+        let worldline = wl_seed(12);
+        let ledger: Arc<dyn Ledger> = Arc::new(InMemoryLedger::default());
+        let grant = wll_types::Capability {
+            id: wll_types::CapabilityId("deploy".into()),
+            scope: wll_types::CapabilityScope::Global,
+            granted_at: wll_types::TemporalAnchor::zero(),
+            expires_at: None,
+        };
+        let commitment = ledger
+            .append_commitment(
+                &wll_ledger::CommitmentProposal {
+                    worldline: worldline.clone(),
+                    commitment_id: wll_types::CommitmentId::new(),
+                    class: wll_types::CommitmentClass::PolicyChange,
+                    intent: "grant deploy".into(),
+                    requested_caps: vec![],
+                    targets: vec![],
+                    evidence: EvidenceBundle::empty(),
+                    nonce: 0,
+                },
+                &Decision::Accepted,
+                [0u8; 32],
+            )
+            .unwrap();
+        ledger
+            .append_outcome(
+                commitment.receipt_hash,
+                &OutcomeRecord {
+                    effects: vec![],
+                    proofs: vec![],
+                    state_updates: vec![StateUpdate {
+                        key: format!("{}deploy", wll_gate::CAPABILITY_KEY_PREFIX),
+                        value: serde_json::to_value(&grant).unwrap(),
+                        namespace: None,
+                    }],
+                    metadata: Default::default(),
+                },
+            )
+            .unwrap();
+
+        let wll = Wll::builder()
+            .with_worldline(worldline)
+            .with_ledger(ledger)
+            .with_gate(CommitmentGate::with_default_stages(GateConfig::default()))
+            .build()
+            .unwrap();
+        wll.commit(SdkProposal::new("deploy").with_capability("deploy"))
+            .unwrap();
+    }
+
+    #[test]
+    fn unprivileged_commits_cannot_write_gate_context() {
+        let grant = |id: &str| StateUpdate {
+            key: format!("{}{id}", wll_gate::CAPABILITY_KEY_PREFIX),
+            value: serde_json::to_value(wll_types::Capability {
+                id: wll_types::CapabilityId(id.into()),
+                scope: wll_types::CapabilityScope::Global,
+                granted_at: wll_types::TemporalAnchor::zero(),
+                expires_at: None,
+            })
+            .unwrap(),
+            namespace: None,
+        };
+        let policy_change = |message| SdkProposal::new(message).with_class(CommitmentClass::PolicyChange);
+        let held = |wll: &Wll| {
+            let now = wll.clock().anchor(0);
+            let provider = wll.gate().context_provider().unwrap();
+            let caps = provider.capabilities(wll.worldline(), &now).unwrap();
+            caps.into_iter().map(|cap| cap.id.0).collect::<Vec<_>>()
+        };
+
+        let wll = Wll::init().unwrap();
+        wll.commit(SdkProposal::new("first")).unwrap();
+        let attempts = [
+            SdkProposal::new("grant myself deploy"),
+            policy_change("grant myself deploy"),
+            policy_change("grant myself deploy").with_capability(ADMIN_CAPABILITY),
+        ];
+        for proposal in attempts {
+            let err = wll.commit_with_state(proposal, vec![grant("deploy")]).unwrap_err();
+            assert!(matches!(err, SdkError::Gate(GateError::CapabilityDenied(_))), "{err}");
+        }
+        assert_eq!(wll.log(10).unwrap().len(), 2);
+        assert!(held(&wll).is_empty());
+
+        // A worldline whose genesis granted admin may change its context.
+        let wll = Wll::init().unwrap();
+        wll.commit_with_state(policy_change("genesis"), vec![grant(ADMIN_CAPABILITY)]).unwrap();
+        let proposal = policy_change("grant deploy").with_capability(ADMIN_CAPABILITY);
+        wll.commit_with_state(proposal, vec![grant("deploy")]).unwrap();
+        assert_eq!(held(&wll), [ADMIN_CAPABILITY, "deploy"]);
+    }
+
+    #[test]
+    fn commit_tree_links_staged_content() {
+        let wll = Wll::init().unwrap();
+        let mut index = wll.new_index();
+        index.stage_file("README.md", b"hello", EntryMode::Regular).unwrap();
+        index.stage_file("src/lib.rs", b"fn main() {}", EntryMode::Regular).unwrap();
+
+        let result = wll.commit_tree(&mut index, SdkProposal::new("add files")).unwrap();
+        let tree = result.tree.unwrap();
+        assert_eq!(wll.head_tree().unwrap(), Some(tree));
+        assert_eq!(wll.read_tree(&tree).unwrap().len(), 2);
+
+        let effects = &result.outcome_receipt.effects;
+        assert_eq!(effects.len(), 2);
+        assert!(effects.iter().all(|e| e.kind == EffectKind::Added));
+        assert_eq!(result.tree_diff.unwrap().len(), 2);
+
+        let state = wll.latest_state().unwrap();
+        assert_eq!(state.state["tree"], Value::String(tree.to_hex()));
+    }
+
+    #[test]
+    fn second_tree_commit_diffs_against_head_tree() {
+        let wll = Wll::init().unwrap();
+        let mut index = wll.new_index();
+        index.stage_file("a.txt", b"one", EntryMode::Regular).unwrap();
+        index.stage_file("b.txt", b"keep", EntryMode::Regular).unwrap();
+        wll.commit_tree(&mut index, SdkProposal::new("first")).unwrap();
+
+        index.stage_file("a.txt", b"two", EntryMode::Regular).unwrap();
+        let result = wll.commit_tree(&mut index, SdkProposal::new("second")).unwrap();
+
+        let effects = &result.outcome_receipt.effects;
+        assert_eq!(effects.len(), 1);
+        assert_eq!(effects[0].kind, EffectKind::Modified);
+        assert_eq!(effects[0].target, "a.txt");
+        let payload = effects[0].payload.as_ref().unwrap();
+        assert_eq!(payload["new"], Value::String(wll.write_blob(b"two").unwrap().to_hex()));
+
+        let query = EffectQuery::new().with_kind(EffectKind::Modified).with_target("a.*");
+        let found = wll.outcomes_with_effects(&query).unwrap();
+        assert_eq!(found, [result.outcome_receipt]);
+        assert_eq!(
+            result.gate_result.stage_results.len(),
+            0,
+            "default gate is permissive"
+        );
+    }
+
+    #[test]
+    fn change_budget_measures_the_staged_diff() {
+        let mut gate = CommitmentGate::with_default_stages(GateConfig::default());
+        gate.add_stage(Box::new(ChangeBudgetStage::new().with_budget(
+            CommitmentClass::PolicyChange,
+            wll_gate::ChangeBudget::max_lines(2),
+        )));
+        let wll = Wll::init().unwrap().with_gate(gate);
+        let mut index = wll.new_index();
+        index.stage_file("policy.toml", b"a\nb\n", EntryMode::Regular).unwrap();
+        let policy = || SdkProposal::new("policy").with_class(CommitmentClass::PolicyChange);
+        wll.commit_tree(&mut index, policy()).unwrap();
+
+        index.stage_file("policy.toml", b"a\nc\nd\n", EntryMode::Regular).unwrap();
+        let err = wll.commit_tree(&mut index, policy()).unwrap_err();
+        assert!(matches!(
+            err,
+            SdkError::CommitmentRejected { ref reason, stage: Some(ref s), .. }
+                if s == "change_budget" && reason.contains("3 lines (2 added, 1 removed)")
+        ));
+        wll.commit_tree(&mut index, SdkProposal::new("content")).unwrap();
+    }
+
+    #[test]
+    fn message_only_commit_has_no_tree() {
+        let wll = Wll::init().unwrap();
+        let result = wll.commit(SdkProposal::new("note")).unwrap();
+        assert!(result.tree.is_none());
+        assert!(result.outcome_receipt.effects.is_empty());
+        assert_eq!(wll.head_tree().unwrap(), None);
+    }
+
+    #[test]
+    fn schema_violation_records_nothing() {
+        let wll = Wll::init().unwrap();
+        let schema = KeySchema::new("limits").with_key("max", wll_ledger::ValueKind::Integer);
+        wll.register_state_schema(Some("limits"), Arc::new(schema)).unwrap();
+
+        let bad = StateUpdate::new("max", Value::from("ten")).in_namespace("limits");
+        let err = wll.commit_with_state(SdkProposal::new("bad"), vec![bad]).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::SchemaViolation);
+        assert_eq!(wll.receipt_count().unwrap(), 0);
+
+        let good = StateUpdate::new("max", Value::from(10)).in_namespace("limits");
+        let result = wll.commit_with_state(SdkProposal::new("good"), vec![good]).unwrap();
+        assert_eq!(result.outcome_receipt.schemas.len(), 1);
+        assert_eq!(wll.replay_validated().unwrap().namespace("limits").unwrap().len(), 1);
+    }
 }
@@ -0,0 +1,437 @@
+//! Retention: how much history a repository keeps.
+//!
+//! A [`RetentionPolicy`] keeps the newest receipts by count, by age, or
+//! both. Everything older is pruned together: the receipts are archived out
+//! of the live ledger, the provenance DAG is checkpointed at the oldest live
+//! receipt, and objects only archived receipts referred to are removed from
+//! the object store and from packs. [`Wll::plan_retention`] reports what
+//! that would remove; [`Wll::apply_retention`] removes it.
+//!
+//! Some receipts are always kept, which can hold the cut earlier than the
+//! policy allows:
+//!
+//! - receipts a tag, branch, or detached HEAD points at, and everything
+//!   after them, since the hash chain cannot skip receipts;
+//! - snapshot anchors, and the commitments of kept outcomes (see
+//!   [`wll_ledger::archive`]).
+//!
+//! The live stream must also hold a snapshot after the cut, so replaying it
+//! still derives the full state. When it holds none, applying the policy
+//! first appends one of the current state, anchored at the head.
+
+use std::collections::{BTreeMap, HashSet};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use wll_config::RetentionSettings;
+use wll_ledger::{
+    archive_point, check_archive_point, Receipt, SnapshotInput, SnapshotReceipt,
+};
+use wll_pack::PackManager;
+use wll_refs::Head;
+use wll_store::{ObjectKind, Tree};
+use wll_types::{ObjectId, WorldlineId};
+
+use crate::error::{SdkError, SdkResult};
+use crate::repository::Wll;
+
+/// How much of a worldline's history to keep.
+///
+/// A receipt is pruned only when it falls outside every window that is set;
+/// the default sets none and keeps everything.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// Keep at least this many of the newest receipts.
+    pub keep_receipts: Option<u64>,
+    /// Keep receipts younger than this.
+    pub keep_for: Option<Duration>,
+}
+
+impl RetentionPolicy {
+    /// Keep everything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep at least the `count` newest receipts.
+    pub fn with_keep_receipts(mut self, count: u64) -> Self {
+        self.keep_receipts = Some(count);
+        self
+    }
+
+    /// Keep receipts younger than `age`.
+    pub fn with_keep_for(mut self, age: Duration) -> Self {
+        self.keep_for = Some(age);
+        self
+    }
+
+    /// The policy configured in `[retention]`.
+    pub fn from_settings(settings: &RetentionSettings) -> Self {
+        Self {
+            keep_receipts: settings.keep_receipts,
+            keep_for: settings
+                .keep_days
+                .map(|days| Duration::from_secs(days.saturating_mul(24 * 60 * 60))),
+        }
+    }
+
+    /// Seq of the newest of `live` outside every window at `now_ms`, if any.
+    fn prunable_through(&self, live: &[Receipt], now_ms: u64) -> Option<u64> {
+        if self.keep_receipts.is_none() && self.keep_for.is_none() {
+            return None;
+        }
+        let by_count = match self.keep_receipts {
+            Some(keep) => {
+                let prunable = (live.len() as u64).saturating_sub(keep) as usize;
+                live.get(prunable.checked_sub(1)?).map(Receipt::seq)
+            }
+            None => Some(u64::MAX),
+        };
+        let by_age = match self.keep_for {
+            Some(age) => {
+                let cutoff = now_ms.saturating_sub(age.as_millis() as u64);
+                live.iter()
+                    .take_while(|r| r.timestamp().physical_ms < cutoff)
+                    .last()
+                    .map(Receipt::seq)
+            }
+            None => Some(u64::MAX),
+        };
+        by_count.min(by_age)
+    }
+}
+
+/// What a retention run prunes, or would prune.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionReport {
+    pub worldline: WorldlineId,
+    /// `false` for a [plan](Wll::plan_retention), which changes nothing.
+    pub applied: bool,
+    /// Seq of the newest receipt archived.
+    pub archived_through: Option<u64>,
+    pub receipts_archived: u64,
+    /// Whether a snapshot of the current state is appended before the cut.
+    pub snapshot: bool,
+    pub dag_nodes_pruned: u64,
+    /// Objects removed from the object store.
+    pub objects_removed: Vec<ObjectId>,
+    /// Objects removed from packs.
+    pub pack_objects_removed: u64,
+    /// Pack bytes freed; only known once applied.
+    pub pack_bytes_freed: u64,
+    /// Why less was pruned than the policy alone allows.
+    pub held_back: Option<String>,
+}
+
+/// A computed cut: the live stream and how much of it goes.
+struct Plan {
+    live: Vec<Receipt>,
+    /// Number of receipts at the front of `live` that are archived.
+    archived: usize,
+    /// Whether a snapshot at the head is needed for the cut.
+    snapshot: bool,
+    held_back: Option<String>,
+    objects: Vec<ObjectId>,
+}
+
+impl Wll {
+    /// Report what [`apply_retention`](Self::apply_retention) would prune
+    /// under `policy`, without changing anything.
+    pub fn plan_retention(&self, policy: &RetentionPolicy) -> SdkResult<RetentionReport> {
+        let plan = self.retention_plan(policy)?;
+        let removed: HashSet<ObjectId> = plan.objects.iter().copied().collect();
+        let pack_objects_removed = match self.packs()? {
+            Some(packs) => {
+                let reachable = pack_survivors(&packs, &removed);
+                packs.gc(&reachable).objects_removed as u64
+            }
+            None => 0,
+        };
+        let dag = self.provenance()?;
+        let dag_nodes_pruned = plan.live[..plan.archived]
+            .iter()
+            .filter(|r| dag.get_node(&ObjectId::from_hash(r.receipt_hash())).is_some())
+            .count() as u64;
+        Ok(self.retention_report(plan, false, dag_nodes_pruned, pack_objects_removed, 0))
+    }
+
+    /// Prune the history `policy` does not keep: archive the receipts,
+    /// checkpoint the provenance DAG, and remove the objects only they
+    /// referred to from the store and packs.
+    pub fn apply_retention(&self, policy: &RetentionPolicy) -> SdkResult<RetentionReport> {
+        let plan = self.retention_plan(policy)?;
+        if plan.archived == 0 {
+            return Ok(self.retention_report(plan, true, 0, 0, 0));
+        }
+
+        if plan.snapshot {
+            let head = plan.live.last().map(Receipt::receipt_hash).unwrap_or_default();
+            let replay = self.replay()?;
+            let snapshot = self.ledger().append_snapshot(&SnapshotInput {
+                worldline: self.worldline().clone(),
+                anchored_receipt_hash: head,
+                state: replay.state,
+                namespace: None,
+                namespaces: replay.namespaces,
+            })?;
+            self.record_provenance(&[Receipt::Snapshot(snapshot)])?;
+        }
+
+        // The ledger is authoritative and goes first; the DAG is rebuilt
+        // from it if a later step does not happen.
+        let through = plan.live[plan.archived - 1].seq();
+        self.ledger().archive(self.worldline(), through)?;
+        let horizon = plan.live[plan.archived].timestamp();
+        let dag_nodes_pruned = self.checkpoint_provenance(&horizon)? as u64;
+
+        for id in &plan.objects {
+            self.store().delete(id)?;
+        }
+        let removed: HashSet<ObjectId> = plan.objects.iter().copied().collect();
+        let (pack_objects_removed, pack_bytes_freed) = match self.packs()? {
+            Some(mut packs) => {
+                let reachable = pack_survivors(&packs, &removed);
+                let report = packs.collect(&reachable)?;
+                (report.objects_removed as u64, report.bytes_freed)
+            }
+            None => (0, 0),
+        };
+        Ok(self.retention_report(
+            plan,
+            true,
+            dag_nodes_pruned,
+            pack_objects_removed,
+            pack_bytes_freed,
+        ))
+    }
+
+    fn retention_plan(&self, policy: &RetentionPolicy) -> SdkResult<Plan> {
+        let live = self.ledger().read_all(self.worldline())?;
+        let mut plan = Plan {
+            live,
+            archived: 0,
+            snapshot: false,
+            held_back: None,
+            objects: Vec::new(),
+        };
+        let Some(wanted) = policy.prunable_through(&plan.live, self.clock().now_ms()) else {
+            return Ok(plan);
+        };
+
+        let mut limit = wanted;
+        for (name, target) in self.pinned_receipts()? {
+            if let Some(pinned) = plan.live.iter().find(|r| r.receipt_hash() == target) {
+                if pinned.seq() <= limit {
+                    limit = pinned.seq().saturating_sub(1);
+                    plan.held_back = Some(format!("{name} points at seq {}", pinned.seq()));
+                }
+            }
+        }
+        let mut through = archive_point(&plan.live, limit);
+        if through < Some(limit) {
+            let mut snapshotted = plan.live.clone();
+            snapshotted.extend(head_snapshot(&plan.live));
+            let with_snapshot = archive_point(&snapshotted, limit);
+            if with_snapshot > through {
+                through = with_snapshot;
+                plan.snapshot = true;
+            }
+            if through < Some(limit) {
+                let stream = if plan.snapshot { &snapshotted } else { &plan.live };
+                if let Err(refused) = check_archive_point(stream, limit) {
+                    plan.held_back = Some(refused.to_string());
+                }
+            }
+        }
+        let Some(through) = through else {
+            return Ok(plan);
+        };
+        plan.archived = plan.live.partition_point(|r| r.seq() <= through);
+
+        // Objects reachable only through archived receipts go with them.
+        let (archived, kept) = plan.live.split_at(plan.archived);
+        let mut kept_roots = self.receipt_objects(kept)?;
+        for worldline in self.ledger().worldlines()? {
+            if worldline != *self.worldline() {
+                kept_roots.extend(self.receipt_objects(&self.ledger().read_all(&worldline)?)?);
+            }
+        }
+        kept_roots.extend(self.head_tree()?);
+        kept_roots.extend(self.notes_root()?);
+        let kept = self.reachable_objects(kept_roots)?;
+        let mut objects: Vec<ObjectId> = self
+            .reachable_objects(self.receipt_objects(archived)?)?
+            .into_iter()
+            .filter(|id| !kept.contains(id))
+            .collect();
+        objects.sort();
+        plan.objects = objects;
+        Ok(plan)
+    }
+
+    fn retention_report(
+        &self,
+        plan: Plan,
+        applied: bool,
+        dag_nodes_pruned: u64,
+        pack_objects_removed: u64,
+        pack_bytes_freed: u64,
+    ) -> RetentionReport {
+        RetentionReport {
+            worldline: self.worldline().clone(),
+            applied,
+            archived_through: plan.archived.checked_sub(1).map(|i| plan.live[i].seq()),
+            receipts_archived: plan.archived as u64,
+            snapshot: plan.snapshot,
+            dag_nodes_pruned,
+            objects_removed: plan.objects,
+            pack_objects_removed,
+            pack_bytes_freed,
+            held_back: plan.held_back,
+        }
+    }
+
+    /// Receipts that must stay live, by the ref that points at them.
+    fn pinned_receipts(&self) -> SdkResult<Vec<(String, [u8; 32])>> {
+        let mut pinned: Vec<(String, [u8; 32])> = self
+            .refs()
+            .list_refs("")?
+            .into_iter()
+            .map(|(name, reference)| (name, *reference.target_hash()))
+            .collect();
+        if let Some(Head::Detached(target)) = self.refs().head()? {
+            pinned.push(("HEAD".into(), target));
+        }
+        Ok(pinned)
+    }
+
+    /// `roots` and every object reachable from them that is in the store.
+    fn reachable_objects(&self, roots: Vec<ObjectId>) -> SdkResult<HashSet<ObjectId>> {
+        let mut seen = HashSet::new();
+        let mut pending = roots;
+        while let Some(id) = pending.pop() {
+            if !seen.insert(id) {
+                continue;
+            }
+            let Some(object) = self.store().read_shared(&id)? else {
+                continue;
+            };
+            if object.kind == ObjectKind::Tree {
+                let tree = Tree::from_stored_object(&object)?;
+                pending.extend(tree.entries.iter().map(|entry| entry.object_id));
+            }
+        }
+        Ok(seen)
+    }
+
+    /// The repository's packs, if it is on disk.
+    fn packs(&self) -> SdkResult<Option<PackManager>> {
+        self.repo_dir()
+            .map(PackManager::load)
+            .transpose()
+            .map_err(SdkError::from)
+    }
+}
+
+/// Stand-in for the snapshot [`Wll::apply_retention`] would append at the
+/// head of `live`, for planning where the stream can be cut.
+fn head_snapshot(live: &[Receipt]) -> Option<Receipt> {
+    let head = live.last()?;
+    Some(Receipt::Snapshot(SnapshotReceipt {
+        worldline: head.worldline().clone(),
+        seq: head.seq() + 1,
+        receipt_hash: [0; 32],
+        prev_hash: Some(head.receipt_hash()),
+        timestamp: head.timestamp(),
+        anchored_receipt_hash: head.receipt_hash(),
+        state_hash: [0; 32],
+        state: BTreeMap::new(),
+        namespace: None,
+        namespaces: BTreeMap::new(),
+    }))
+}
+
+/// Pack objects that survive removing `removed`.
+fn pack_survivors(packs: &PackManager, removed: &HashSet<ObjectId>) -> HashSet<ObjectId> {
+    packs
+        .object_ids()
+        .filter(|id| !removed.contains(id))
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wll_refs::Ref;
+    use wll_store::EntryMode;
+
+    use crate::commit::CommitProposal;
+    use crate::error::SdkError;
+
+    #[test]
+    fn retention_archives_receipts_and_collects_their_objects() {
+        let dir = tempfile::tempdir().unwrap();
+        let wll = Wll::init_at(dir.path()).unwrap();
+        let mut trees = Vec::new();
+        for version in 1..=4 {
+            let mut index = wll.new_index();
+            let content = format!("version {version}");
+            index.stage_file("data.txt", content.as_bytes(), EntryMode::Regular).unwrap();
+            let proposal = CommitProposal::new(format!("v{version}"));
+            trees.push(wll.commit_tree(&mut index, proposal).unwrap().tree.unwrap());
+        }
+        let blob = |tree| wll.read_tree(tree).unwrap().get("data.txt").unwrap().object_id;
+        let first_blob = blob(&trees[0]);
+        let mut packs = PackManager::load(wll.repo_dir().unwrap()).unwrap();
+        packs.repack(wll.store(), &[first_blob, blob(&trees[1])]).unwrap();
+        let state = wll.replay().unwrap().state;
+
+        // v2's outcome is tagged, so v2 stays live.
+        let v2 = wll.ledger().read_all(wll.worldline()).unwrap()[3].clone();
+        let tag = Ref::Tag {
+            name: "v2".into(),
+            target: v2.receipt_hash(),
+            tagger: wll.worldline().clone(),
+            message: String::new(),
+            timestamp: v2.timestamp(),
+            signature: None,
+        };
+        wll.refs().write_ref(&tag.canonical_name(), &tag).unwrap();
+
+        assert_eq!(wll.plan_retention(&RetentionPolicy::new()).unwrap().receipts_archived, 0);
+        let policy = RetentionPolicy::new().with_keep_receipts(2);
+        let plan = wll.plan_retention(&policy).unwrap();
+        assert!(!plan.applied);
+        assert_eq!(plan.archived_through, Some(2));
+        assert!(plan.snapshot);
+        assert!(plan.held_back.is_some());
+        let mut removed = vec![first_blob, trees[0]];
+        removed.sort();
+        assert_eq!(plan.objects_removed, removed);
+        assert_eq!(plan.pack_objects_removed, 1);
+        assert_eq!(wll.receipt_count().unwrap(), 8);
+
+        let applied = wll.apply_retention(&policy).unwrap();
+        assert!(applied.applied);
+        assert_eq!(applied.objects_removed, plan.objects_removed);
+        assert!(applied.dag_nodes_pruned > 0);
+        assert!(applied.pack_bytes_freed > 0);
+        assert_eq!(wll.receipt_count().unwrap(), 7);
+        assert!(wll.verify().unwrap().is_valid());
+        assert_eq!(wll.replay().unwrap().state, state);
+        assert!(matches!(wll.read_blob(&first_blob), Err(SdkError::ObjectNotFound(_))));
+        assert_eq!(wll.read_blob(&blob(&trees[3])).unwrap(), b"version 4");
+        let packs = PackManager::load(wll.repo_dir().unwrap()).unwrap();
+        assert!(!packs.contains(&first_blob));
+        assert!(packs.contains(&blob(&trees[1])));
+
+        drop(wll);
+        let wll = Wll::open(dir.path()).unwrap();
+        assert_eq!(wll.receipt_count().unwrap(), 7);
+        assert!(wll.verify().unwrap().is_valid());
+        assert_eq!(wll.apply_retention(&policy).unwrap().receipts_archived, 0);
+        let next = wll.commit(CommitProposal::new("v5")).unwrap();
+        assert_eq!(next.commitment_receipt.seq, 10);
+    }
+}
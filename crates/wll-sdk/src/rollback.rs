@@ -0,0 +1,314 @@
+//! Undoing a receipt and everything downstream of it.
+//!
+//! [`Wll::plan_rollback`] walks the [`ImpactReport`] of a receipt through
+//! the provenance DAG and turns each downstream outcome into
+//! [`RollbackStep`]s, newest first so every step sees the state the next
+//! one restores. Reversible outcomes get a compensating commit that puts
+//! back the tree and the state they changed; irreversible ones, and those
+//! recorded on other worldlines, get a step listing the effects someone
+//! has to undo by hand. Partially reversible outcomes get both, the manual
+//! step covering the effects outside the tree. [`Wll::execute_rollback_step`]
+//! runs the steps one at a time.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use wll_dag::ImpactReport;
+use wll_ledger::{
+    CrossWorldlineRef, EffectSummary, NamespacedState, OutcomeReceipt, Receipt, ReceiptKind,
+    StateUpdate,
+};
+use wll_types::{ObjectId, Reversibility, WorldlineId};
+
+use crate::commit::{CommitProposal, CommitResult, REVERSIBILITY_KEY};
+use crate::deferred::OUTCOME_FAILURE_KEY;
+use crate::error::{SdkError, SdkResult};
+use crate::repository::{Wll, TREE_STATE_KEY};
+
+/// Outcome metadata key naming the receipt a compensating commit undoes.
+pub const ROLLBACK_OF_KEY: &str = "rollback_of";
+
+/// One step of a [`RollbackPlan`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum RollbackStep {
+    /// Commit `proposal` with `state` to undo the outcome `undoes`.
+    Compensate {
+        undoes: [u8; 32],
+        /// Restores the tree the outcome replaced, if it changed the tree.
+        proposal: CommitProposal,
+        /// The values the outcome's state updates overwrote, `null` for
+        /// keys it created.
+        state: Vec<StateUpdate>,
+    },
+    /// Effects of the outcome `undoes` that no commit can undo.
+    Manual {
+        undoes: [u8; 32],
+        worldline: WorldlineId,
+        effects: Vec<EffectSummary>,
+        reason: String,
+    },
+}
+
+impl RollbackStep {
+    /// Hash of the outcome receipt this step undoes.
+    pub fn undoes(&self) -> [u8; 32] {
+        match self {
+            Self::Compensate { undoes, .. } | Self::Manual { undoes, .. } => *undoes,
+        }
+    }
+
+    /// Returns `true` if the step needs someone to act outside the ledger.
+    pub fn is_manual(&self) -> bool {
+        matches!(self, Self::Manual { .. })
+    }
+}
+
+/// Ordered steps undoing a receipt and everything downstream of it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RollbackPlan {
+    /// The receipt being undone.
+    pub origin: [u8; 32],
+    pub impact: ImpactReport,
+    /// Newest outcome first; run them in this order.
+    pub steps: Vec<RollbackStep>,
+}
+
+impl RollbackPlan {
+    /// Returns `true` if every step is a compensating commit.
+    pub fn is_automatic(&self) -> bool {
+        !self.steps.iter().any(RollbackStep::is_manual)
+    }
+
+    /// The steps needing manual intervention.
+    pub fn manual_steps(&self) -> impl Iterator<Item = &RollbackStep> {
+        self.steps.iter().filter(|step| step.is_manual())
+    }
+}
+
+impl Wll {
+    /// Plan undoing the receipt `hash` and every receipt downstream of it
+    /// in the provenance DAG.
+    ///
+    /// Outcomes are undone by what their commit recorded under
+    /// [`with_reversibility`](CommitProposal::with_reversibility), reversible
+    /// when unset. Commitments are undone through their outcomes, and
+    /// failure outcomes, which changed nothing, are skipped.
+    pub fn plan_rollback(&self, hash: [u8; 32]) -> SdkResult<RollbackPlan> {
+        let dag = self.provenance()?;
+        let origin = ObjectId::from_hash(hash);
+        let origin_node =
+            dag.get_node(&origin).ok_or_else(|| SdkError::ObjectNotFound(hex::encode(hash)))?;
+
+        let mut outcomes = Vec::new();
+        let downstream = dag.descendants(&origin, usize::MAX);
+        for node in std::iter::once(origin_node).chain(downstream) {
+            if node.kind != ReceiptKind::Outcome {
+                continue;
+            }
+            let receipt = self.ledger().get_by_hash(*node.id.as_bytes())?;
+            if let Some(Receipt::Outcome(outcome)) = receipt {
+                if outcome.accepted && !outcome.metadata.contains_key(OUTCOME_FAILURE_KEY) {
+                    outcomes.push(outcome);
+                }
+            }
+        }
+        outcomes.sort_by_key(|o| std::cmp::Reverse((o.timestamp, o.seq)));
+
+        let receipts = self.ledger().read_all(self.worldline())?;
+        let mut steps = Vec::new();
+        for outcome in outcomes {
+            if &outcome.worldline != self.worldline() {
+                steps.push(RollbackStep::Manual {
+                    undoes: outcome.receipt_hash,
+                    reason: format!(
+                        "recorded on worldline {}; plan its rollback there",
+                        outcome.worldline
+                    ),
+                    worldline: outcome.worldline,
+                    effects: outcome.effects,
+                });
+                continue;
+            }
+            let reversibility = outcome
+                .metadata
+                .get(REVERSIBILITY_KEY)
+                .and_then(|name| Reversibility::from_name(name))
+                .unwrap_or(Reversibility::Reversible);
+            let (compensate, manual, reason) = match reversibility {
+                Reversibility::Reversible => (true, Vec::new(), String::new()),
+                Reversibility::PartiallyReversible { constraints } => {
+                    let external = outcome
+                        .effects
+                        .iter()
+                        .filter(|effect| !effect.kind.is_tree_change())
+                        .cloned()
+                        .collect();
+                    (true, external, constraints)
+                }
+                Reversibility::Irreversible => {
+                    (false, outcome.effects.clone(), "the effects are irreversible".into())
+                }
+            };
+            if compensate {
+                steps.push(self.compensating_step(&outcome, &receipts)?);
+            }
+            if !compensate || !manual.is_empty() {
+                steps.push(RollbackStep::Manual {
+                    undoes: outcome.receipt_hash,
+                    worldline: outcome.worldline.clone(),
+                    effects: manual,
+                    reason,
+                });
+            }
+        }
+
+        Ok(RollbackPlan {
+            origin: hash,
+            impact: dag.impact_report(&origin),
+            steps,
+        })
+    }
+
+    /// Run one step of a [`RollbackPlan`], committing its compensation.
+    ///
+    /// Fails with [`SdkError::InvalidOperation`] for a manual step, and
+    /// like [`commit`](Self::commit) if the gate rejects the compensation.
+    pub fn execute_rollback_step(&self, step: &RollbackStep) -> SdkResult<CommitResult> {
+        match step {
+            RollbackStep::Compensate { proposal, state, .. } => {
+                self.commit_with_state(proposal.clone(), state.clone())
+            }
+            RollbackStep::Manual { undoes, reason, .. } => {
+                Err(SdkError::InvalidOperation(format!(
+                    "undoing {} needs manual intervention: {reason}",
+                    hex::encode(undoes)
+                )))
+            }
+        }
+    }
+
+    /// The compensating commit for `outcome`, given the receipts of its
+    /// worldline.
+    fn compensating_step(
+        &self,
+        outcome: &OutcomeReceipt,
+        receipts: &[Receipt],
+    ) -> SdkResult<RollbackStep> {
+        let mut before = BTreeMap::new();
+        let mut namespaces = NamespacedState::new();
+        for receipt in receipts.iter().take_while(|r| r.seq() < outcome.seq) {
+            match receipt {
+                Receipt::Outcome(o) if o.accepted => {
+                    for update in &o.state_updates {
+                        update.apply_to(&mut before, &mut namespaces);
+                    }
+                }
+                Receipt::Snapshot(s) => s.apply_to(&mut before, &mut namespaces),
+                _ => {}
+            }
+        }
+
+        let intent = match self.ledger().get_by_hash(outcome.commitment_receipt_hash)? {
+            Some(Receipt::Commitment(c)) => Some((c.intent, c.class)),
+            _ => None,
+        };
+        let subject = intent
+            .as_ref()
+            .and_then(|(intent, _)| intent.lines().next())
+            .unwrap_or_default();
+        let undone = CrossWorldlineRef::new(outcome.worldline.clone(), outcome.receipt_hash);
+        let mut proposal = CommitProposal::new(format!("Revert \"{subject}\""))
+            .with_evidence(undone.to_uri())
+            .with_metadata(ROLLBACK_OF_KEY, hex::encode(outcome.receipt_hash));
+        if let Some((_, class)) = intent {
+            proposal = proposal.with_class(class);
+        }
+
+        let mut state = Vec::new();
+        for update in &outcome.state_updates {
+            let key = update.key.as_str();
+            if update.namespace.is_none() && (key == "message" || key == TREE_STATE_KEY) {
+                continue;
+            }
+            let scope = match &update.namespace {
+                Some(name) => namespaces.get(name),
+                None => Some(&before),
+            };
+            let value = scope.and_then(|s| s.get(&update.key)).cloned().unwrap_or(Value::Null);
+            state.push(StateUpdate { value, ..update.clone() });
+        }
+        if crate::repository::outcome_tree(outcome)?.is_some() {
+            let tree = match before.get(TREE_STATE_KEY).and_then(Value::as_str) {
+                Some(hex) => ObjectId::from_hex(hex)
+                    .map_err(|e| SdkError::Internal(format!("invalid tree pointer: {e}")))?,
+                None => self.write_tree(Vec::new())?,
+            };
+            proposal = proposal.with_tree(tree);
+        }
+
+        Ok(RollbackStep::Compensate { undoes: outcome.receipt_hash, proposal, state })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wll_ledger::EffectKind;
+    use wll_store::{EntryMode, TreeEntry};
+
+    use super::*;
+
+    #[test]
+    fn compensating_commits_restore_tree_and_state() {
+        let wll = Wll::init().unwrap();
+        let commit = |name: &str, version: u64| {
+            let blob = wll.write_blob(name.as_bytes()).unwrap();
+            let tree = wll.write_tree(vec![TreeEntry::new(EntryMode::Regular, name, blob)]);
+            let tree = tree.unwrap();
+            let state = vec![StateUpdate::new("version", Value::from(version))];
+            let proposal = CommitProposal::new(name).with_tree(tree);
+            (wll.commit_with_state(proposal, state).unwrap(), tree)
+        };
+        let (_, first_tree) = commit("v1", 1);
+        let (second, _) = commit("v2", 2);
+
+        let plan = wll.plan_rollback(second.receipt_hash).unwrap();
+        assert!(plan.is_automatic());
+        assert_eq!(plan.steps.len(), 1);
+        assert_eq!(plan.steps[0].undoes(), second.receipt_hash);
+        wll.execute_rollback_step(&plan.steps[0]).unwrap();
+
+        let state = wll.latest_state().unwrap();
+        assert_eq!(state.get("version"), Some(&Value::from(1)));
+        assert_eq!(state.get("message"), Some(&Value::from("Revert \"v2\"")));
+        assert_eq!(wll.head_tree().unwrap(), Some(first_tree));
+    }
+
+    #[test]
+    fn external_effects_are_left_to_manual_steps_newest_first() {
+        let wll = Wll::init().unwrap();
+        let first = wll.commit(CommitProposal::new("prepare")).unwrap();
+        let deploy = CommitProposal::new("deploy api").with_reversibility(
+            Reversibility::PartiallyReversible { constraints: "drain traffic first".into() },
+        );
+        let pending = wll.propose(deploy).unwrap();
+        let mut outcome = pending.outcome.clone();
+        outcome.effects.push(EffectSummary::new(EffectKind::Deploy, "deploy://prod/api", "v2"));
+        let deployed = wll.record_outcome(&pending, outcome).unwrap();
+
+        let plan = wll.plan_rollback(first.commitment_receipt.receipt_hash).unwrap();
+        let undone: Vec<_> = plan.steps.iter().map(RollbackStep::undoes).collect();
+        let first_hash = first.outcome_receipt.receipt_hash;
+        let deployed_hash = deployed.receipt_hash;
+        assert_eq!(undone, [deployed_hash, deployed_hash, first_hash]);
+        assert!(matches!(plan.steps[0], RollbackStep::Compensate { .. }));
+        let [manual] = plan.manual_steps().collect::<Vec<_>>()[..] else { panic!() };
+        let RollbackStep::Manual { effects, reason, .. } = manual else { unreachable!() };
+        assert_eq!(effects[0].target, "deploy://prod/api");
+        assert_eq!(reason, "drain traffic first");
+        assert_eq!(plan.impact.downstream_receipts, 3);
+
+        let err = wll.execute_rollback_step(manual).unwrap_err();
+        assert!(matches!(err, SdkError::InvalidOperation(_)));
+    }
+}
@@ -0,0 +1,209 @@
+//! Confidential intents and state.
+//!
+//! A proposal with recipients ([`CommitProposal::with_recipient`]) goes
+//! through the hooks and the gate in the clear, and is then recorded with
+//! its intent and state values sealed to those recipients: each is replaced
+//! by an encoded [`Envelope`], so receipt hashes cover the ciphertext and
+//! every replica can still validate the chain. The class, evidence,
+//! effects, and tree pointer stay readable, as do state updates in
+//! namespaces with a registered schema, which the ledger checks on append.
+//! State recorded later with [`Wll::record_outcome`] is sealed to the
+//! recipients the commitment's intent was sealed to.
+//!
+//! A repository holding a recipient's key ([`Wll::with_envelope_key`])
+//! opens sealed fields on read with [`Wll::open_receipt`],
+//! [`Wll::open_text`], and [`Wll::open_value`], and re-checks sealed
+//! commitments in [`Wll::replay_strict`]. Without one, strict replay counts
+//! them among the commitments it could not re-check.
+//!
+//! [`CommitProposal::with_recipient`]: crate::CommitProposal::with_recipient
+
+use serde_json::Value;
+use wll_crypto::{Envelope, EnvelopeError, EnvelopePublicKey};
+use wll_ledger::{Receipt, StateUpdate};
+
+use crate::error::{SdkError, SdkResult};
+use crate::repository::{Wll, TREE_STATE_KEY};
+
+impl Wll {
+    /// `text`, opened if it is sealed.
+    ///
+    /// Fails with [`EnvelopeError::NotARecipient`] if it is sealed and no
+    /// key this repository holds opens it.
+    pub fn open_text(&self, text: &str) -> SdkResult<String> {
+        if !Envelope::is_sealed(text) {
+            return Ok(text.to_string());
+        }
+        String::from_utf8(self.unseal(text)?)
+            .map_err(|_| EnvelopeError::Malformed("sealed text is not UTF-8".into()).into())
+    }
+
+    /// A state value, opened if it is sealed. Fails as
+    /// [`open_text`](Self::open_text) does.
+    pub fn open_value(&self, value: &Value) -> SdkResult<Value> {
+        match value.as_str() {
+            Some(text) if Envelope::is_sealed(text) => {
+                Ok(serde_json::from_slice(&self.unseal(text)?)?)
+            }
+            _ => Ok(value.clone()),
+        }
+    }
+
+    /// `receipt` with every sealed intent and state value a held key opens
+    /// in the clear, and the rest left sealed.
+    ///
+    /// The copy is for reading: it no longer matches its receipt hash.
+    pub fn open_receipt(&self, receipt: &Receipt) -> SdkResult<Receipt> {
+        let mut receipt = receipt.clone();
+        match &mut receipt {
+            Receipt::Commitment(commitment) => {
+                if let Some(intent) = if_held(self.open_text(&commitment.intent))? {
+                    commitment.intent = intent;
+                }
+            }
+            Receipt::Outcome(outcome) => {
+                for update in &mut outcome.state_updates {
+                    if let Some(value) = if_held(self.open_value(&update.value))? {
+                        update.value = value;
+                    }
+                }
+            }
+            Receipt::Snapshot(_) => {}
+        }
+        Ok(receipt)
+    }
+
+    /// Seal the values of `updates` to `recipients`, except the tree
+    /// pointer, updates a registered schema checks, and values already
+    /// sealed.
+    pub(crate) fn seal_state(
+        &self,
+        updates: &mut [StateUpdate],
+        recipients: &[EnvelopePublicKey],
+    ) -> SdkResult<()> {
+        if recipients.is_empty() {
+            return Ok(());
+        }
+        let schemas = self.ledger().schemas()?;
+        for update in updates {
+            let tree = update.namespace.is_none() && update.key == TREE_STATE_KEY;
+            let sealed = update.value.as_str().is_some_and(Envelope::is_sealed);
+            if tree
+                || sealed
+                || schemas.current(self.worldline(), update.namespace.as_deref()).is_some()
+            {
+                continue;
+            }
+            let sealed = Envelope::seal(&serde_json::to_vec(&update.value)?, recipients)?;
+            update.value = Value::String(sealed.encode());
+        }
+        Ok(())
+    }
+
+    /// The plaintext of the sealed `text`, opened with the first held key
+    /// it was sealed to.
+    fn unseal(&self, text: &str) -> SdkResult<Vec<u8>> {
+        let envelope = Envelope::decode(text)?;
+        for key in self.envelope_keys() {
+            match envelope.open(key) {
+                Err(EnvelopeError::NotARecipient) => continue,
+                opened => return Ok(opened?),
+            }
+        }
+        Err(EnvelopeError::NotARecipient.into())
+    }
+}
+
+/// `text` sealed to `recipients`, or unchanged if there are none.
+pub(crate) fn seal_text(text: &str, recipients: &[EnvelopePublicKey]) -> SdkResult<String> {
+    if recipients.is_empty() {
+        return Ok(text.to_string());
+    }
+    Ok(Envelope::seal(text.as_bytes(), recipients)?.encode())
+}
+
+/// The recipients `intent` was sealed to, or none if it is in the clear.
+pub(crate) fn sealed_recipients(intent: &str) -> SdkResult<Vec<EnvelopePublicKey>> {
+    if !Envelope::is_sealed(intent) {
+        return Ok(Vec::new());
+    }
+    Ok(Envelope::decode(intent)?.recipients.into_iter().map(|key| key.recipient).collect())
+}
+
+/// `result`, or `None` if it failed only because no held key opens a
+/// sealed value.
+pub(crate) fn if_held<T>(result: SdkResult<T>) -> SdkResult<Option<T>> {
+    match result {
+        Err(SdkError::Envelope(EnvelopeError::NotARecipient)) => Ok(None),
+        result => result.map(Some),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use wll_crypto::EnvelopeSecretKey;
+    use wll_gate::{InMemoryPolicyStore, PolicyStore};
+    use wll_ledger::{InMemoryLedger, Ledger};
+    use wll_types::{IdentityMaterial, WorldlineId};
+
+    use super::*;
+    use crate::commit::CommitProposal;
+
+    #[test]
+    fn recipients_read_sealed_commits_and_others_cannot() {
+        let ledger: Arc<dyn Ledger> = Arc::new(InMemoryLedger::default());
+        let policies: Arc<dyn PolicyStore> = Arc::new(InMemoryPolicyStore::new());
+        let open = || {
+            Wll::builder()
+                .with_worldline(WorldlineId::derive(&IdentityMaterial::GenesisHash([7; 32])))
+                .with_ledger(ledger.clone())
+                .with_policy_store(policies.clone())
+                .build()
+                .unwrap()
+        };
+        let key = EnvelopeSecretKey::generate();
+        let wll = open();
+        let proposal = CommitProposal::new("fix: rotate the prod database password")
+            .with_recipient(key.public_key());
+        let result = wll.commit(proposal).unwrap();
+
+        let intent = &result.commitment_receipt.intent;
+        assert!(Envelope::is_sealed(intent));
+        let state = wll.latest_state().unwrap();
+        assert!(Envelope::is_sealed(state.state.get("message").unwrap().as_str().unwrap()));
+        assert!(wll.verify().unwrap().is_valid());
+        let err = wll.open_text(intent).unwrap_err();
+        assert!(matches!(err, SdkError::Envelope(EnvelopeError::NotARecipient)));
+        assert_eq!(wll.replay_strict().unwrap().decisions_checked, 0);
+
+        let reader = open().with_envelope_key(key);
+        let outcome = reader.open_receipt(&Receipt::Outcome(result.outcome_receipt)).unwrap();
+        let updates = &outcome.as_outcome().unwrap().state_updates;
+        let message = updates.iter().find(|u| u.key == "message").unwrap();
+        assert_eq!(message.value, "fix: rotate the prod database password");
+        assert_eq!(reader.open_text(intent).unwrap(), "fix: rotate the prod database password");
+        assert_eq!(reader.replay_strict().unwrap().decisions_checked, 1);
+    }
+
+    #[test]
+    fn outcomes_recorded_later_are_sealed_to_the_commitments_recipients() {
+        let key = EnvelopeSecretKey::generate();
+        let wll = Wll::init().unwrap().with_envelope_key(key.clone());
+        let proposal = CommitProposal::new("chore: rotate the prod database password")
+            .with_recipient(key.public_key());
+        let pending = wll.propose(proposal).unwrap();
+
+        let mut outcome = pending.outcome.clone();
+        outcome.state_updates.push(StateUpdate::new("password", Value::from("hunter2")));
+        let receipt = wll.record_outcome(&pending, outcome).unwrap();
+
+        let values: Vec<_> = receipt.state_updates.iter().map(|u| u.value.as_str().unwrap()).collect();
+        assert_eq!(values.len(), 2);
+        assert!(values.iter().all(|value| Envelope::is_sealed(value)));
+        let opened = wll.open_receipt(&Receipt::Outcome(receipt)).unwrap();
+        let updates = &opened.as_outcome().unwrap().state_updates;
+        assert_eq!(updates.iter().find(|u| u.key == "password").unwrap().value, "hunter2");
+    }
+}
@@ -0,0 +1,336 @@
+//! Full-text search over commit intents, metadata, targets and evidence.
+//!
+//! With the `search` feature, a `SearchIndex` (tantivy) holds one
+//! document per commit, keyed by its commitment receipt: the intent and
+//! evidence references and attachment names from the commitment, the
+//! metadata and effect targets from its outcome. Persistent repositories
+//! keep the index in `.wll/search/`; in-memory ones build it in memory.
+//!
+//! The index records how far into the ledger it has read, so
+//! `SearchIndex::update` only indexes receipts recorded since. When the
+//! repository has an event fabric, `SearchIndex::process_events` updates
+//! the index once outcomes have been published.
+
+use serde::Serialize;
+
+use crate::error::SdkResult;
+use crate::repository::Wll;
+
+/// A commit matching a search query.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SearchHit {
+    /// The commitment receipt of the commit.
+    pub receipt_hash: [u8; 32],
+    pub seq: u64,
+    pub intent: String,
+    pub score: f32,
+}
+
+impl Wll {
+    /// Commits matching `query`, best first, at most `limit` of them.
+    ///
+    /// Brings the index up to date first. Queries use tantivy's syntax:
+    /// words match any indexed field, `intent:refund` one field, and
+    /// `"payment refund"` a phrase.
+    #[cfg(feature = "search")]
+    pub fn search(&self, query: &str, limit: usize) -> SdkResult<Vec<SearchHit>> {
+        let mut index = self.search_index()?;
+        index.update(self)?;
+        index.search(query, limit)
+    }
+
+    #[cfg(not(feature = "search"))]
+    pub fn search(&self, _query: &str, _limit: usize) -> SdkResult<Vec<SearchHit>> {
+        Err(crate::SdkError::Unsupported(
+            "search needs wll-sdk built with the `search` feature".into(),
+        ))
+    }
+}
+
+#[cfg(feature = "search")]
+pub use index::SearchIndex;
+
+#[cfg(feature = "search")]
+mod index {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Serialize};
+    use tantivy::collector::TopDocs;
+    use tantivy::directory::MmapDirectory;
+    use tantivy::query::QueryParser;
+    use tantivy::schema::{Field, Schema, Value, INDEXED, STORED, STRING, TEXT};
+    use tantivy::{doc, Index, IndexReader, IndexWriter, ReloadPolicy, TantivyDocument, Term};
+    use wll_fabric::fabric::EventStream;
+    use wll_fabric::{EventFilter, EventKind};
+    use wll_ledger::{CommitmentReceipt, OutcomeReceipt, Receipt};
+    use wll_types::WorldlineId;
+
+    use super::SearchHit;
+    use crate::error::{SdkError, SdkResult};
+    use crate::repository::Wll;
+
+    /// Directory of the index inside `.wll`.
+    const SEARCH_DIR: &str = "search";
+
+    /// Memory the index writer may buffer before flushing, the least
+    /// tantivy accepts.
+    const WRITER_MEMORY: usize = 15_000_000;
+
+    /// How far the index has read the ledger, stored as the commit payload.
+    #[derive(Default, Serialize, Deserialize)]
+    struct Progress {
+        worldline: Option<WorldlineId>,
+        seq: u64,
+    }
+
+    struct Fields {
+        receipt: Field,
+        seq: Field,
+        intent: Field,
+        metadata: Field,
+        targets: Field,
+        evidence: Field,
+    }
+
+    impl Fields {
+        fn schema() -> (Schema, Self) {
+            let mut builder = Schema::builder();
+            let fields = Self {
+                receipt: builder.add_text_field("receipt", STRING | STORED),
+                seq: builder.add_u64_field("seq", INDEXED | STORED),
+                intent: builder.add_text_field("intent", TEXT | STORED),
+                metadata: builder.add_text_field("metadata", TEXT),
+                targets: builder.add_text_field("targets", TEXT),
+                evidence: builder.add_text_field("evidence", TEXT),
+            };
+            (builder.build(), fields)
+        }
+    }
+
+    /// Full-text index over one worldline's commits.
+    pub struct SearchIndex {
+        index: Index,
+        reader: IndexReader,
+        fields: Fields,
+        events: Option<EventStream>,
+    }
+
+    fn search_error(e: tantivy::TantivyError) -> SdkError {
+        SdkError::Search(e.to_string())
+    }
+
+    impl Wll {
+        /// Open the search index, creating it if needed. It follows the
+        /// repository's event fabric, if one is attached, from now on.
+        pub fn search_index(&self) -> SdkResult<SearchIndex> {
+            let (schema, fields) = Fields::schema();
+            let index = match self.repo_dir() {
+                Some(dir) => {
+                    let dir = dir.join(SEARCH_DIR);
+                    std::fs::create_dir_all(&dir)?;
+                    let directory = MmapDirectory::open(&dir)
+                        .map_err(|e| SdkError::Search(e.to_string()))?;
+                    Index::open_or_create(directory, schema).map_err(search_error)?
+                }
+                None => Index::create_in_ram(schema),
+            };
+            let reader = index
+                .reader_builder()
+                .reload_policy(ReloadPolicy::Manual)
+                .try_into()
+                .map_err(search_error)?;
+            let events = self.fabric().map(|fabric| {
+                fabric.subscribe(EventFilter {
+                    worldlines: Some(vec![self.worldline().clone()]),
+                    kinds: Some(vec![EventKind::OutcomeRecorded]),
+                    since: None,
+                })
+            });
+            Ok(SearchIndex { index, reader, fields, events })
+        }
+    }
+
+    impl SearchIndex {
+        /// Index the receipts of `wll`'s worldline recorded since the last
+        /// update, returning how many were read. Starts over if the index
+        /// was built for another worldline.
+        pub fn update(&mut self, wll: &Wll) -> SdkResult<usize> {
+            let mut progress = self.progress()?;
+            let mut writer: IndexWriter =
+                self.index.writer_with_num_threads(1, WRITER_MEMORY).map_err(search_error)?;
+            if progress.worldline.as_ref() != Some(wll.worldline()) {
+                writer.delete_all_documents().map_err(search_error)?;
+                progress = Progress { worldline: Some(wll.worldline().clone()), seq: 0 };
+            }
+            let Some(head) = wll.ledger().head(wll.worldline())? else {
+                return Ok(0);
+            };
+            if head.seq <= progress.seq {
+                return Ok(0);
+            }
+            let receipts = wll.ledger().read_range(wll.worldline(), progress.seq + 1, head.seq)?;
+            for receipt in &receipts {
+                match receipt {
+                    Receipt::Commitment(commitment) => {
+                        writer.add_document(self.document(commitment, None)).map_err(search_error)?;
+                    }
+                    Receipt::Outcome(outcome) => {
+                        let Some(Receipt::Commitment(commitment)) =
+                            wll.ledger().get_by_hash(outcome.commitment_receipt_hash)?
+                        else {
+                            continue;
+                        };
+                        let key = hex::encode(commitment.receipt_hash);
+                        writer.delete_term(Term::from_field_text(self.fields.receipt, &key));
+                        writer
+                            .add_document(self.document(&commitment, Some(outcome)))
+                            .map_err(search_error)?;
+                    }
+                    Receipt::Snapshot(_) => {}
+                }
+            }
+            progress.seq = head.seq;
+            let mut commit = writer.prepare_commit().map_err(search_error)?;
+            commit.set_payload(&serde_json::to_string(&progress)?);
+            commit.commit().map_err(search_error)?;
+            self.reader.reload().map_err(search_error)?;
+            Ok(receipts.len())
+        }
+
+        /// Update the index if outcomes were published on the fabric since
+        /// the last call. Returns how many receipts were indexed.
+        pub fn process_events(&mut self, wll: &Wll) -> SdkResult<usize> {
+            let Some(events) = &mut self.events else {
+                return Ok(0);
+            };
+            let mut published = false;
+            while let Ok(_) | Err(tokio::sync::broadcast::error::TryRecvError::Lagged(_)) =
+                events.try_recv()
+            {
+                published = true;
+            }
+            if published {
+                self.update(wll)
+            } else {
+                Ok(0)
+            }
+        }
+
+        /// Commits matching `query`, best first, at most `limit` of them.
+        pub fn search(&self, query: &str, limit: usize) -> SdkResult<Vec<SearchHit>> {
+            let fields = &self.fields;
+            let parser = QueryParser::for_index(
+                &self.index,
+                vec![fields.intent, fields.metadata, fields.targets, fields.evidence],
+            );
+            let query = parser
+                .parse_query(query)
+                .map_err(|e| SdkError::InvalidQuery(e.to_string()))?;
+            let searcher = self.reader.searcher();
+            let top = searcher
+                .search(&query, &TopDocs::with_limit(limit.max(1)))
+                .map_err(search_error)?;
+            let mut hits = Vec::with_capacity(top.len());
+            for (score, address) in top.into_iter().take(limit) {
+                let doc: TantivyDocument = searcher.doc(address).map_err(search_error)?;
+                let receipt = doc.get_first(fields.receipt).and_then(|v| v.as_str());
+                let Some(receipt_hash) = receipt.and_then(|hex| hex::decode(hex).ok()) else {
+                    continue;
+                };
+                hits.push(SearchHit {
+                    receipt_hash: receipt_hash.try_into().unwrap_or_default(),
+                    seq: doc.get_first(fields.seq).and_then(|v| v.as_u64()).unwrap_or(0),
+                    intent: doc
+                        .get_first(fields.intent)
+                        .and_then(|v| v.as_str())
+                        .unwrap_or_default()
+                        .to_string(),
+                    score,
+                });
+            }
+            Ok(hits)
+        }
+
+        fn progress(&self) -> SdkResult<Progress> {
+            let metas = self.index.load_metas().map_err(search_error)?;
+            Ok(match metas.payload {
+                Some(payload) => serde_json::from_str(&payload)?,
+                None => Progress::default(),
+            })
+        }
+
+        fn document(
+            &self,
+            commitment: &CommitmentReceipt,
+            outcome: Option<&OutcomeReceipt>,
+        ) -> TantivyDocument {
+            let fields = &self.fields;
+            let evidence = &commitment.evidence;
+            let attachments = evidence.attachments.iter().map(|a| a.name.clone());
+            let evidence: Vec<String> = evidence.references.iter().cloned().chain(attachments).collect();
+            let empty = BTreeMap::new();
+            let metadata = outcome.map_or(&empty, |o| &o.metadata);
+            let metadata: Vec<String> = metadata.iter().map(|(k, v)| format!("{k} {v}")).collect();
+            let targets: Vec<&str> = outcome
+                .map(|o| o.effects.iter().map(|e| e.target.as_str()).collect())
+                .unwrap_or_default();
+            doc!(
+                fields.receipt => hex::encode(commitment.receipt_hash),
+                fields.seq => commitment.seq,
+                fields.intent => commitment.intent.clone(),
+                fields.metadata => metadata.join("\n"),
+                fields.targets => targets.join("\n"),
+                fields.evidence => evidence.join("\n"),
+            )
+        }
+    }
+}
+
+#[cfg(all(test, feature = "search"))]
+mod tests {
+    use std::sync::Arc;
+
+    use wll_fabric::{EventFabric, FabricConfig};
+
+    use super::*;
+    use crate::{CommitProposal, WllBuilder};
+
+    #[test]
+    fn search_finds_commits_by_intent_metadata_and_evidence() {
+        let dir = tempfile::tempdir().unwrap();
+        let wll = Wll::init_at(dir.path()).unwrap();
+        let refund = wll
+            .commit(
+                CommitProposal::new("Add payment refund endpoint")
+                    .with_evidence("https://issues.example/PAY-12")
+                    .with_metadata("ticket", "billing-backlog"),
+            )
+            .unwrap();
+        wll.commit(CommitProposal::new("Bump logging level")).unwrap();
+
+        let hits = wll.search("\"payment refund\"", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].receipt_hash, refund.commitment_receipt.receipt_hash);
+        assert_eq!(wll.search("backlog", 10).unwrap()[0].intent, "Add payment refund endpoint");
+        assert_eq!(wll.search("evidence:PAY", 10).unwrap().len(), 1);
+        assert!(wll.search("intent:(", 10).is_err());
+
+        // Progress is kept with the index: reopening reads nothing twice.
+        let mut index = Wll::open(dir.path()).unwrap().search_index().unwrap();
+        assert_eq!(index.update(&wll).unwrap(), 0);
+    }
+
+    #[test]
+    fn fabric_events_drive_incremental_indexing() {
+        let dir = tempfile::tempdir().unwrap();
+        let fabric = EventFabric::new(&dir.path().join("fabric.wal"), FabricConfig::default());
+        let wll = WllBuilder::new().with_fabric(Arc::new(fabric.unwrap())).build().unwrap();
+        let mut index = wll.search_index().unwrap();
+        assert_eq!(index.process_events(&wll).unwrap(), 0);
+
+        wll.commit(CommitProposal::new("Rotate signing keys")).unwrap();
+        assert_eq!(index.process_events(&wll).unwrap(), 2);
+        assert_eq!(index.search("signing", 5).unwrap().len(), 1);
+        assert_eq!(index.process_events(&wll).unwrap(), 0);
+    }
+}
@@ -0,0 +1,251 @@
+//! Revision lookup and per-commit details for `wll show`.
+//!
+//! A commit is a commitment receipt plus the outcome that records it; either
+//! hash identifies the pair. Its tree diff is taken against the nearest
+//! earlier outcome that recorded a tree, and its state diff against the
+//! state folded from every earlier accepted outcome.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use serde_json::Value;
+use wll_diff::{diff_namespaces, diff_states, diff_trees, StateDiff, TreeDiff};
+use wll_ledger::{CommitmentReceipt, NamespacedState, OutcomeReceipt, Receipt};
+use wll_refs::Head;
+use wll_types::ObjectId;
+
+use crate::error::{SdkError, SdkResult};
+use crate::notes::StoredNote;
+use crate::repository::{outcome_tree, Wll};
+
+/// Shortest hash prefix accepted by [`Wll::resolve`].
+const MIN_PREFIX: usize = 4;
+
+/// A receipt together with the commit it belongs to and the changes it made.
+#[derive(Clone, Debug, Serialize)]
+pub struct ReceiptDetails {
+    /// The receipt the revision resolved to.
+    pub receipt: Receipt,
+    /// Commitment half of the commit (absent for snapshots).
+    pub commitment: Option<CommitmentReceipt>,
+    /// Outcome half of the commit, if one has been recorded.
+    pub outcome: Option<OutcomeReceipt>,
+    /// Tree recorded by the outcome.
+    pub tree: Option<ObjectId>,
+    /// Tree the diff was taken against.
+    pub parent_tree: Option<ObjectId>,
+    /// Tree changes, for outcomes that recorded a tree.
+    pub tree_diff: Option<TreeDiff>,
+    /// State keys the outcome added or changed, in the default namespace.
+    pub state_diff: StateDiff,
+    /// State keys the outcome added or changed in named namespaces.
+    pub namespace_diffs: BTreeMap<String, StateDiff>,
+    /// Notes on the resolved receipt, oldest first.
+    pub notes: Vec<StoredNote>,
+}
+
+impl Wll {
+    /// Resolve a revision to a receipt hash.
+    ///
+    /// Accepts `HEAD`, a full ref name, a branch, tag, or `remote/branch`
+    /// short name, or a receipt hash (or unique prefix of at least four hex
+    /// digits).
+    pub fn resolve(&self, rev: &str) -> SdkResult<[u8; 32]> {
+        if rev == "HEAD" {
+            let tip = match self.refs().head()? {
+                Some(Head::Symbolic(branch)) => self.ref_tip(&format!("refs/heads/{branch}"))?,
+                Some(Head::Detached(hash)) => Some(hash),
+                None => None,
+            };
+            return tip.ok_or_else(|| SdkError::ObjectNotFound("HEAD has no commits".into()));
+        }
+
+        let candidates = [
+            rev.to_string(),
+            format!("refs/heads/{rev}"),
+            format!("refs/tags/{rev}"),
+            format!("refs/remotes/{rev}"),
+        ];
+        for name in candidates.iter().filter(|n| n.starts_with("refs/")) {
+            if let Ok(Some(tip)) = self.ref_tip(name) {
+                return Ok(tip);
+            }
+        }
+
+        let prefix = rev.to_ascii_lowercase();
+        if prefix.len() < MIN_PREFIX || !prefix.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(SdkError::ObjectNotFound(rev.into()));
+        }
+        let mut matches = self
+            .ledger()
+            .read_all(self.worldline())?
+            .into_iter()
+            .map(|r| r.receipt_hash())
+            .filter(|hash| hex::encode(hash).starts_with(&prefix));
+        match (matches.next(), matches.next()) {
+            (Some(hash), None) => Ok(hash),
+            (Some(_), Some(_)) => Err(SdkError::InvalidOperation(format!(
+                "receipt prefix {rev} is ambiguous"
+            ))),
+            (None, _) => Err(SdkError::ObjectNotFound(rev.into())),
+        }
+    }
+
+    /// Commit details and diffs for the receipt with `receipt_hash`.
+    pub fn receipt_details(&self, receipt_hash: &[u8; 32]) -> SdkResult<ReceiptDetails> {
+        let receipt = self.show(receipt_hash)?;
+        let stream = self.ledger().read_all(self.worldline())?;
+
+        let (commitment, outcome) = match &receipt {
+            Receipt::Commitment(c) => {
+                let outcome = stream.iter().find_map(|r| match r {
+                    Receipt::Outcome(o) if o.commitment_receipt_hash == c.receipt_hash => {
+                        Some(o.clone())
+                    }
+                    _ => None,
+                });
+                (Some(c.clone()), outcome)
+            }
+            Receipt::Outcome(o) => {
+                let commitment = match self.ledger().get_by_hash(o.commitment_receipt_hash)? {
+                    Some(Receipt::Commitment(c)) => Some(c),
+                    _ => None,
+                };
+                (commitment, Some(o.clone()))
+            }
+            Receipt::Snapshot(_) => (None, None),
+        };
+
+        let mut details = ReceiptDetails {
+            receipt,
+            commitment,
+            outcome: outcome.clone(),
+            tree: None,
+            parent_tree: None,
+            tree_diff: None,
+            state_diff: StateDiff::new(),
+            namespace_diffs: BTreeMap::new(),
+            notes: self.notes(receipt_hash)?,
+        };
+        let Some(outcome) = outcome else {
+            return Ok(details);
+        };
+
+        let earlier: Vec<&OutcomeReceipt> = stream
+            .iter()
+            .filter_map(Receipt::as_outcome)
+            .filter(|o| o.accepted && o.seq < outcome.seq)
+            .collect();
+
+        details.tree = outcome_tree(&outcome)?;
+        if let Some(tree) = &details.tree {
+            for previous in earlier.iter().rev() {
+                if let Some(parent) = outcome_tree(previous)? {
+                    details.parent_tree = Some(parent);
+                    break;
+                }
+            }
+            details.tree_diff = Some(diff_trees(
+                self.store(),
+                details.parent_tree.as_ref(),
+                tree,
+            )?);
+        }
+
+        let mut before: BTreeMap<String, Value> = BTreeMap::new();
+        let mut before_namespaces = NamespacedState::new();
+        for previous in &earlier {
+            for update in &previous.state_updates {
+                update.apply_to(&mut before, &mut before_namespaces);
+            }
+        }
+        let (mut after, mut after_namespaces) = (before.clone(), before_namespaces.clone());
+        for update in &outcome.state_updates {
+            update.apply_to(&mut after, &mut after_namespaces);
+        }
+        details.state_diff = diff_states(&before, &after);
+        details.namespace_diffs = diff_namespaces(&before_namespaces, &after_namespaces);
+        Ok(details)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wll_diff::{StateChange, TreeChange};
+    use wll_store::{EntryMode, TreeEntry};
+
+    use super::*;
+    use crate::commit::CommitProposal;
+
+    fn tree(wll: &Wll, files: &[(&str, &[u8])]) -> ObjectId {
+        let entries = files
+            .iter()
+            .map(|(name, data)| {
+                TreeEntry::new(EntryMode::Regular, *name, wll.write_blob(data).unwrap())
+            })
+            .collect();
+        wll.write_tree(entries).unwrap()
+    }
+
+    #[test]
+    fn resolves_refs_head_and_prefixes() {
+        let wll = Wll::init().unwrap();
+        let first = wll.commit(CommitProposal::new("first")).unwrap();
+        let second = wll.commit(CommitProposal::new("second")).unwrap();
+
+        assert_eq!(wll.resolve("HEAD").unwrap(), second.receipt_hash);
+        assert_eq!(wll.resolve("main").unwrap(), second.receipt_hash);
+        assert_eq!(wll.resolve("refs/heads/main").unwrap(), second.receipt_hash);
+
+        let hex = hex::encode(first.receipt_hash);
+        assert_eq!(wll.resolve(&hex).unwrap(), first.receipt_hash);
+        assert_eq!(wll.resolve(&hex[..12]).unwrap(), first.receipt_hash);
+        assert!(matches!(
+            wll.resolve("zz-not-a-rev"),
+            Err(SdkError::ObjectNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn details_pair_commitment_and_outcome() {
+        let wll = Wll::init().unwrap();
+        let result = wll
+            .commit(CommitProposal::new("msg").with_evidence("issue://7"))
+            .unwrap();
+
+        let from_commitment = wll
+            .receipt_details(&result.commitment_receipt.receipt_hash)
+            .unwrap();
+        let from_outcome = wll.receipt_details(&result.receipt_hash).unwrap();
+        for details in [from_commitment, from_outcome] {
+            let commitment = details.commitment.unwrap();
+            assert_eq!(commitment.evidence.references, vec!["issue://7"]);
+            assert_eq!(details.outcome.unwrap().receipt_hash, result.receipt_hash);
+        }
+    }
+
+    #[test]
+    fn tree_and_state_diff_against_previous_commit() {
+        let wll = Wll::init().unwrap();
+        let v1 = tree(&wll, &[("a.txt", b"one\n")]);
+        wll.commit(CommitProposal::new("add a").with_tree(v1))
+            .unwrap();
+        wll.commit(CommitProposal::new("note")).unwrap();
+        let v2 = tree(&wll, &[("a.txt", b"two\n"), ("b.txt", b"new\n")]);
+        let result = wll
+            .commit(CommitProposal::new("edit").with_tree(v2))
+            .unwrap();
+
+        let details = wll.receipt_details(&result.receipt_hash).unwrap();
+        assert_eq!(details.parent_tree, Some(v1));
+        let changes = details.tree_diff.unwrap().changes;
+        assert_eq!(changes.len(), 2);
+        assert!(changes
+            .iter()
+            .any(|c| matches!(c, TreeChange::Modified { path, .. } if path == "a.txt")));
+        assert!(details.state_diff.changes.iter().any(|c| matches!(
+            c,
+            StateChange::Modified { key, new, .. } if key == "message" && new == "edit"
+        )));
+    }
+}
@@ -0,0 +1,200 @@
+//! Size and shape of a repository, for planning garbage collection and
+//! capacity.
+//!
+//! [`Wll::stats`] reads every object once, loose and packed, so it costs
+//! about as much as a full `fsck`.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::sync::Arc;
+
+use serde::Serialize;
+use wll_pack::PackManager;
+use wll_store::{ObjectKind, StoredObject, Tree};
+use wll_types::{ObjectId, WorldlineId};
+
+use crate::error::SdkResult;
+use crate::repository::Wll;
+
+/// Number of blobs [`RepoStats::largest_blobs`] lists.
+pub const LARGEST_BLOBS: usize = 10;
+
+/// Object, ledger and provenance figures for a repository.
+#[derive(Clone, Debug, Serialize)]
+pub struct RepoStats {
+    pub worldline: WorldlineId,
+    /// Distinct objects by kind (`blob`, `tree`, ...), loose and packed
+    /// together.
+    pub objects: BTreeMap<String, KindStats>,
+    /// Objects stored as loose files, and their content size.
+    pub loose_objects: u64,
+    pub loose_bytes: u64,
+    /// Distinct objects in packs.
+    pub packed_objects: u64,
+    pub packs: usize,
+    /// Size on disk of the packs and their indexes.
+    pub pack_bytes: u64,
+    /// Share of stored objects that are packed, from 0 to 1. An object both
+    /// loose and packed counts on each side.
+    pub packed_ratio: f64,
+    /// Receipts in the ledger for each worldline it records.
+    pub ledger: Vec<LedgerLength>,
+    pub dag_nodes: usize,
+    /// Causal parent links between provenance nodes.
+    pub dag_edges: usize,
+    /// The largest blobs, biggest first.
+    pub largest_blobs: Vec<ObjectSize>,
+    /// Objects no receipt, queued commit or note refers to, directly or
+    /// through a tree. Retention and `wll gc` leave these alone.
+    pub dangling: Vec<ObjectId>,
+}
+
+/// Count and content size of the objects of one kind.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct KindStats {
+    pub count: u64,
+    pub bytes: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ObjectSize {
+    pub id: ObjectId,
+    pub bytes: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct LedgerLength {
+    pub worldline: WorldlineId,
+    pub receipts: u64,
+}
+
+impl RepoStats {
+    /// Every distinct object.
+    pub fn total_objects(&self) -> u64 {
+        self.objects.values().map(|k| k.count).sum()
+    }
+
+    /// Content size of every distinct object.
+    pub fn total_bytes(&self) -> u64 {
+        self.objects.values().map(|k| k.bytes).sum()
+    }
+}
+
+impl Wll {
+    /// Gather [`RepoStats`] for this repository.
+    ///
+    /// Loose objects are only counted for stores that can list them
+    /// ([`ObjectStore::list_ids`](wll_store::ObjectStore::list_ids)); packs
+    /// only exist for repositories on disk.
+    pub fn stats(&self) -> SdkResult<RepoStats> {
+        let loose = self.store().list_ids()?.unwrap_or_default();
+        let packs = match self.repo_dir() {
+            Some(dir) => Some(PackManager::load(dir)?),
+            None => None,
+        };
+        let packed: BTreeSet<ObjectId> =
+            packs.iter().flat_map(|p| p.object_ids().copied()).collect();
+        let read = |id: &ObjectId| -> SdkResult<Option<Arc<StoredObject>>> {
+            if let Some(object) = self.store().read_shared(id)? {
+                return Ok(Some(object));
+            }
+            match &packs {
+                Some(packs) => Ok(packs.read_shared(id)?),
+                None => Ok(None),
+            }
+        };
+
+        let loose_set: HashSet<ObjectId> = loose.iter().copied().collect();
+        let all: BTreeSet<ObjectId> = loose.iter().chain(&packed).copied().collect();
+        let mut objects: BTreeMap<String, KindStats> = BTreeMap::new();
+        let mut loose_bytes = 0;
+        let mut blobs = Vec::new();
+        for id in &all {
+            let Some(object) = read(id)? else { continue };
+            let kind = objects.entry(object.kind.to_string()).or_default();
+            kind.count += 1;
+            kind.bytes += object.size;
+            if loose_set.contains(id) {
+                loose_bytes += object.size;
+            }
+            if object.kind == ObjectKind::Blob {
+                blobs.push(ObjectSize { id: *id, bytes: object.size });
+            }
+        }
+        blobs.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.id.cmp(&b.id)));
+        blobs.truncate(LARGEST_BLOBS);
+
+        let mut ledger = Vec::new();
+        let mut roots = Vec::new();
+        for worldline in self.ledger().worldlines()? {
+            roots.extend(self.receipt_objects(&self.ledger().read_all(&worldline)?)?);
+            let receipts = self.ledger().receipt_count(&worldline)?;
+            ledger.push(LedgerLength { worldline, receipts });
+        }
+        for queued in self.pending_commits()? {
+            roots.extend(queued.proposal.tree);
+            roots.extend(queued.proposal.attachments.iter().map(|a| a.object));
+        }
+        roots.extend(self.notes_root()?);
+        let mut reachable = HashSet::new();
+        while let Some(id) = roots.pop() {
+            if !reachable.insert(id) {
+                continue;
+            }
+            if let Some(object) = read(&id)? {
+                if object.kind == ObjectKind::Tree {
+                    let tree = Tree::from_stored_object(&object)?;
+                    roots.extend(tree.entries.iter().map(|entry| entry.object_id));
+                }
+            }
+        }
+        let dangling = all.iter().filter(|id| !reachable.contains(id)).copied().collect();
+
+        let dag = self.provenance()?;
+        let stored = loose.len() + packed.len();
+        Ok(RepoStats {
+            worldline: self.worldline().clone(),
+            objects,
+            loose_objects: loose.len() as u64,
+            loose_bytes,
+            packed_objects: packed.len() as u64,
+            packs: packs.as_ref().map_or(0, PackManager::pack_count),
+            pack_bytes: packs.as_ref().map_or(0, PackManager::pack_bytes),
+            packed_ratio: if stored == 0 { 0.0 } else { packed.len() as f64 / stored as f64 },
+            ledger,
+            dag_nodes: dag.len(),
+            dag_edges: dag.topological_order().iter().map(|n| n.parents.len()).sum(),
+            largest_blobs: blobs,
+            dangling,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commit::CommitProposal;
+    use wll_store::EntryMode;
+
+    #[test]
+    fn stats_count_objects_packs_and_dangling_blobs() {
+        let dir = tempfile::tempdir().unwrap();
+        let wll = Wll::init_at(dir.path()).unwrap();
+        let mut index = wll.new_index();
+        index.stage_file("big.bin", &[7; 4096], EntryMode::Regular).unwrap();
+        index.stage_file("small.txt", b"hi", EntryMode::Regular).unwrap();
+        let tree = wll.commit_tree(&mut index, CommitProposal::new("files")).unwrap().tree;
+        let stray = wll.write_blob(b"never committed").unwrap();
+        let mut packs = PackManager::load(wll.repo_dir().unwrap()).unwrap();
+        packs.repack(wll.store(), &[tree.unwrap()]).unwrap();
+
+        let stats = wll.stats().unwrap();
+        assert_eq!(stats.objects["blob"].count, 3);
+        assert_eq!(stats.objects["tree"].count, 1);
+        assert_eq!((stats.loose_objects, stats.packed_objects, stats.packs), (4, 1, 1));
+        assert!(stats.pack_bytes > 0 && stats.packed_ratio == 0.2);
+        assert_eq!(stats.ledger[0].receipts, 2);
+        assert_eq!((stats.dag_nodes, stats.dag_edges), (2, 1));
+        assert_eq!(stats.largest_blobs[0].bytes, 4096);
+        assert_eq!(stats.dangling, vec![stray]);
+    }
+}
@@ -0,0 +1,435 @@
+//! Templates that bootstrap new repositories.
+//!
+//! A [`RepoTemplate`] describes what a new worldline starts with: files for
+//! the initial tree, gate policies and capability grants, branches, and
+//! repository config. [`Wll::init_from_template`] records all of it as the
+//! genesis commitment, so the starting rules are part of the audited
+//! history rather than something configured beside it.
+//!
+//! Templates are built-in presets ([`PRESETS`]) or directories laid out as:
+//!
+//! ```text
+//! template.toml      name, description, branches, [[capability]], [config.*]
+//! policies/*.json    gate policies
+//! tree/              files of the initial tree
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use wll_gate::{
+    check_capability, Policy, PolicyRule, PolicyScope, CAPABILITY_KEY_PREFIX, POLICY_KEY_PREFIX,
+};
+use wll_ledger::StateUpdate;
+use wll_store::EntryMode;
+use wll_types::{
+    AttestationKind, Capability, CapabilityId, CapabilityScope, CommitmentClass, TemporalAnchor,
+};
+
+use crate::commit::{CommitProposal, CommitResult};
+use crate::config::{RepoConfig, WLL_DIR};
+use crate::error::{SdkError, SdkResult};
+use crate::repository::{random_worldline, Wll};
+
+/// Names of the built-in templates.
+pub const PRESETS: [&str; 2] = ["solo-dev", "strict-governance"];
+
+/// Manifest file of a template directory.
+pub const TEMPLATE_MANIFEST: &str = "template.toml";
+
+/// Everything a new repository is seeded with.
+#[derive(Clone, Debug, Default)]
+pub struct RepoTemplate {
+    pub name: String,
+    pub description: String,
+    /// Files of the initial tree, by path.
+    pub files: BTreeMap<String, Vec<u8>>,
+    pub policies: Vec<Policy>,
+    pub capabilities: Vec<CapabilityGrant>,
+    /// Branches pointing at the genesis commit. The first is checked out;
+    /// with none, that is `main`.
+    pub branches: Vec<String>,
+    /// Config tables (`[gate]`, `[hooks]`, ...) written to `.wll/config`.
+    pub config: toml::Table,
+}
+
+/// A capability the template grants the new worldline.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapabilityGrant {
+    pub id: String,
+    /// Limit the grant to paths under this prefix; global when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+impl CapabilityGrant {
+    /// The capability this grant records, granted at `at`.
+    fn capability(&self, at: TemporalAnchor) -> Capability {
+        Capability {
+            id: CapabilityId(self.id.clone()),
+            scope: self.path.clone().map_or(CapabilityScope::Global, CapabilityScope::Path),
+            granted_at: at,
+            expires_at: None,
+        }
+    }
+}
+
+/// TOML shape of `template.toml`.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct Manifest {
+    name: Option<String>,
+    description: String,
+    branches: Vec<String>,
+    capability: Vec<CapabilityGrant>,
+    config: toml::Table,
+}
+
+impl RepoTemplate {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into(), ..Self::default() }
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    pub fn with_file(mut self, path: impl Into<String>, content: impl Into<Vec<u8>>) -> Self {
+        self.files.insert(path.into(), content.into());
+        self
+    }
+
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.policies.push(policy);
+        self
+    }
+
+    pub fn with_capability(mut self, id: impl Into<String>, path: Option<String>) -> Self {
+        self.capabilities.push(CapabilityGrant { id: id.into(), path });
+        self
+    }
+
+    pub fn with_branch(mut self, name: impl Into<String>) -> Self {
+        self.branches.push(name.into());
+        self
+    }
+
+    /// Set `key` in config table `section`, e.g. `("gate", "require_evidence", true)`.
+    pub fn with_config(
+        mut self,
+        section: &str,
+        key: &str,
+        value: impl Into<toml::Value>,
+    ) -> Self {
+        let table = self
+            .config
+            .entry(section)
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        if let toml::Value::Table(table) = table {
+            table.insert(key.to_string(), value.into());
+        }
+        self
+    }
+
+    /// The built-in template called `name`.
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "solo-dev" => Some(
+                Self::new("solo-dev")
+                    .with_description("One developer: permissive gate, a single main branch.")
+                    .with_file("README.md", "# New worldline\n")
+                    .with_capability("deploy", None)
+                    .with_branch("main"),
+            ),
+            "strict-governance" => Some(
+                Self::new("strict-governance")
+                    .with_description(
+                        "Evidence on every commit, human attestation for policy changes, \
+                         and separate main and develop branches.",
+                    )
+                    .with_file("README.md", "# New worldline\n")
+                    .with_file(
+                        "GOVERNANCE.md",
+                        "# Governance\n\n\
+                         Every commitment carries evidence. Policy changes need a proposer \
+                         with a human identity attestation (`wll identity`). Work lands on \
+                         `develop` and is promoted to `main`.\n",
+                    )
+                    .with_policy(Policy {
+                        id: "strict-governance".into(),
+                        name: "Strict governance".into(),
+                        rules: vec![
+                            PolicyRule::RequireEvidence,
+                            PolicyRule::RequireAttestation {
+                                class: CommitmentClass::PolicyChange,
+                                kind: Some(AttestationKind::Human),
+                            },
+                        ],
+                        applies_to: PolicyScope::All,
+                    })
+                    .with_capability("release", Some("release/".into()))
+                    .with_branch("main")
+                    .with_branch("develop")
+                    .with_config("gate", "require_evidence", true),
+            ),
+            _ => None,
+        }
+    }
+
+    /// A preset by name, or else the template directory at `spec`.
+    pub fn resolve(spec: &str) -> SdkResult<Self> {
+        match Self::preset(spec) {
+            Some(template) => Ok(template),
+            None if Path::new(spec).is_dir() => Self::load(spec),
+            None => Err(SdkError::InvalidOperation(format!(
+                "no template {spec:?}: not a preset ({}) or a directory",
+                PRESETS.join(", ")
+            ))),
+        }
+    }
+
+    /// Read the template directory at `dir`.
+    pub fn load(dir: impl AsRef<Path>) -> SdkResult<Self> {
+        let dir = dir.as_ref();
+        let manifest = std::fs::read_to_string(dir.join(TEMPLATE_MANIFEST))?;
+        let manifest: Manifest = toml::from_str(&manifest)
+            .map_err(|e| SdkError::Config(format!("{TEMPLATE_MANIFEST}: {e}")))?;
+        let name = manifest.name.unwrap_or_else(|| {
+            dir.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default()
+        });
+        let mut template = Self {
+            name,
+            description: manifest.description,
+            capabilities: manifest.capability,
+            branches: manifest.branches,
+            config: manifest.config,
+            ..Self::default()
+        };
+
+        let policies = dir.join("policies");
+        if policies.is_dir() {
+            let mut paths: Vec<_> = std::fs::read_dir(&policies)?
+                .map(|entry| entry.map(|e| e.path()))
+                .collect::<Result<_, _>>()?;
+            paths.retain(|p| p.extension().is_some_and(|ext| ext == "json"));
+            paths.sort();
+            for path in paths {
+                let policy = serde_json::from_slice(&std::fs::read(&path)?).map_err(|e| {
+                    SdkError::Config(format!("{}: invalid policy: {e}", path.display()))
+                })?;
+                template.policies.push(policy);
+            }
+        }
+        let tree = dir.join("tree");
+        if tree.is_dir() {
+            read_files(&tree, "", &mut template.files)?;
+        }
+        Ok(template)
+    }
+
+    /// Check the policies and capability grants as the gate checks them
+    /// when loading, so one it would refuse fails before anything is
+    /// written.
+    pub fn check(&self) -> SdkResult<()> {
+        for policy in &self.policies {
+            policy.check()?;
+        }
+        for grant in &self.capabilities {
+            check_capability(&grant.capability(TemporalAnchor::new(0, 0, 0)))?;
+        }
+        Ok(())
+    }
+
+    /// What the genesis commitment attaches as evidence: the template
+    /// without its file contents.
+    fn summary(&self) -> serde_json::Value {
+        serde_json::json!({
+            "template": self.name,
+            "description": self.description,
+            "files": self.files.keys().collect::<Vec<_>>(),
+            "policies": self.policies,
+            "capabilities": self.capabilities,
+            "branches": self.branches,
+            "config": self.config,
+        })
+    }
+}
+
+fn read_files(dir: &Path, prefix: &str, files: &mut BTreeMap<String, Vec<u8>>) -> SdkResult<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = format!("{prefix}{}", entry.file_name().to_string_lossy());
+        if entry.file_type()?.is_dir() {
+            read_files(&entry.path(), &format!("{path}/"), files)?;
+        } else {
+            files.insert(path, std::fs::read(entry.path())?);
+        }
+    }
+    Ok(())
+}
+
+impl Wll {
+    /// Initialize a persistent repository in `<path>/.wll` seeded from
+    /// `template`.
+    ///
+    /// The template's config tables are written before the repository is
+    /// opened, so its gate settings judge the genesis commitment. That
+    /// commitment records the initial tree, the policies and capability
+    /// grants (under the gate's `policy/` and `capability/` state keys),
+    /// and the template itself as an evidence attachment. A policy or
+    /// grant with an invalid glob fails first ([`RepoTemplate::check`]). The template's
+    /// branches then point at it. Template files are also written to the
+    /// work tree at `path`, except where a file already exists.
+    pub fn init_from_template(
+        path: impl AsRef<Path>,
+        template: &RepoTemplate,
+    ) -> SdkResult<(Self, CommitResult)> {
+        let reserved = ["core", "remote"].into_iter().find(|k| template.config.contains_key(*k));
+        if let Some(key) = reserved {
+            return Err(SdkError::Config(format!("templates cannot set [{key}]")));
+        }
+        template.check()?;
+        let repo_dir = path.as_ref().join(WLL_DIR);
+        let config_path = repo_dir.join("config");
+        if config_path.exists() {
+            return Err(SdkError::InvalidOperation(format!(
+                "repository already exists at {}",
+                repo_dir.display()
+            )));
+        }
+        std::fs::create_dir_all(&repo_dir)?;
+        let settings =
+            toml::to_string(&template.config).map_err(|e| SdkError::Config(e.to_string()))?;
+        std::fs::write(&config_path, settings)?;
+        let config = RepoConfig::new(random_worldline());
+        config.save(&config_path)?;
+        let wll = Self::open_dir(repo_dir, config)?;
+
+        let default_branch = template.branches.first().map_or("main", String::as_str);
+        if default_branch != "main" {
+            wll.create_branch(default_branch)?;
+            wll.switch_branch(default_branch)?;
+            wll.refs().delete_ref("refs/heads/main")?;
+        }
+
+        let mut index = wll.new_index();
+        for (file, content) in &template.files {
+            index.stage_file(file, content, EntryMode::Regular)?;
+            let on_disk = path.as_ref().join(file);
+            if !on_disk.exists() {
+                if let Some(parent) = on_disk.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(on_disk, content)?;
+            }
+        }
+        let mut state = Vec::new();
+        for policy in &template.policies {
+            state.push(StateUpdate {
+                key: format!("{POLICY_KEY_PREFIX}{}", policy.id),
+                value: serde_json::to_value(policy)?,
+                namespace: None,
+            });
+        }
+        for grant in &template.capabilities {
+            let capability = grant.capability(wll.clock().anchor(0));
+            state.push(StateUpdate {
+                key: format!("{CAPABILITY_KEY_PREFIX}{}", grant.id),
+                value: serde_json::to_value(&capability)?,
+                namespace: None,
+            });
+        }
+        let evidence = serde_json::to_vec_pretty(&template.summary())?;
+        let message = format!("Initialize from template {}", template.name);
+        let mut proposal = CommitProposal::new(message)
+            .with_attachment(wll.attach_evidence("template.json", "application/json", &evidence)?)
+            .with_tree(index.write_tree()?);
+        if !state.is_empty() {
+            proposal = proposal.with_class(CommitmentClass::PolicyChange);
+        }
+        let genesis = wll.commit_with_state(proposal, state)?;
+
+        for branch in template.branches.iter().filter(|b| b.as_str() != default_branch) {
+            wll.create_branch(branch)?;
+        }
+        Ok((wll, genesis))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commit::CommitProposal;
+
+    #[test]
+    fn template_directory_seeds_the_genesis_commitment() {
+        let dir = tempfile::tempdir().unwrap();
+        let template_dir = dir.path().join("service");
+        std::fs::create_dir_all(template_dir.join("tree/src")).unwrap();
+        std::fs::create_dir_all(template_dir.join("policies")).unwrap();
+        std::fs::write(template_dir.join("tree/src/main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(
+            template_dir.join("policies/evidence.json"),
+            r#"{"id": "evidence", "name": "Evidence", "rules": ["RequireEvidence"],
+                "applies_to": "All"}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            template_dir.join(TEMPLATE_MANIFEST),
+            "branches = [\"trunk\", \"staging\"]\n\n\
+             [[capability]]\nid = \"deploy\"\n\n\
+             [config.gate]\nmax_targets_per_commitment = 20\n",
+        )
+        .unwrap();
+
+        let template = RepoTemplate::resolve(template_dir.to_str().unwrap()).unwrap();
+        assert_eq!(template.name, "service");
+        let (wll, genesis) = Wll::init_from_template(dir.path().join("repo"), &template).unwrap();
+        assert_eq!(wll.current_branch().unwrap(), "trunk");
+        assert_eq!(wll.list_branches().unwrap(), ["refs/heads/staging", "refs/heads/trunk"]);
+        assert_eq!(wll.receipt_count().unwrap(), 2);
+        assert_eq!(wll.read_tree(&genesis.tree.unwrap()).unwrap().entries[0].name, "src/main.rs");
+        assert_eq!(wll.config().unwrap().gate().unwrap().max_targets_per_commitment, Some(20));
+        assert!(dir.path().join("repo/src/main.rs").is_file());
+
+        // The recorded policy and grant are in force from the next commit.
+        let err = wll.commit(CommitProposal::new("no evidence")).unwrap_err();
+        assert!(matches!(err, SdkError::CommitmentRejected { .. }));
+        let deploy = CommitProposal::new("deploy").with_capability("deploy");
+        wll.commit(deploy.with_evidence("ci://1")).unwrap();
+
+        assert!(RepoTemplate::resolve("no-such-template").is_err());
+        let strict = RepoTemplate::preset("strict-governance").unwrap();
+        let (strict, _) = Wll::init_from_template(dir.path().join("strict"), &strict).unwrap();
+        assert!(strict.commit(CommitProposal::new("no evidence")).is_err());
+    }
+
+    #[test]
+    fn invalid_globs_fail_before_anything_is_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let template = RepoTemplate::new("broken").with_capability("deploy", Some("src/[".into()));
+        let err = Wll::init_from_template(dir.path(), &template).err().unwrap();
+        assert!(matches!(err, SdkError::Gate(wll_gate::GateError::Config(_))), "{err}");
+        assert!(!dir.path().join(WLL_DIR).exists());
+
+        let wll = Wll::init().unwrap();
+        let policy = Policy {
+            id: "paths".into(),
+            name: "Protected paths".into(),
+            rules: vec![PolicyRule::DenyTargets {
+                patterns: vec!["a/[".into()],
+                case_insensitive: false,
+            }],
+            applies_to: PolicyScope::All,
+        };
+        let state = vec![StateUpdate::new(
+            format!("{POLICY_KEY_PREFIX}paths"),
+            serde_json::to_value(&policy).unwrap(),
+        )];
+        let err = wll.commit_with_state(CommitProposal::new("chore: add policy"), state);
+        assert!(matches!(err, Err(SdkError::Gate(wll_gate::GateError::Config(_)))));
+        assert_eq!(wll.receipt_count().unwrap(), 0);
+    }
+}
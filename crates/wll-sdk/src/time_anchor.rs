@@ -0,0 +1,388 @@
+//! Time anchors: Roughtime timestamps over the ledger head.
+//!
+//! An anchor is a read-only commitment whose outcome records the anchored
+//! receipt, the signed time, and the server's response as an `obj://` proof,
+//! so strict verification can check it with a [`RoughtimeVerifier`].
+//! Anchors are not branch commits and leave branch refs where they are.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use base64::Engine;
+use wll_config::{TimestampServerSettings, TimestampSettings};
+use wll_crypto::VerifyingKey;
+use wll_ledger::timestamp::{
+    ANCHOR_MIDPOINT_KEY, ANCHOR_RADIUS_KEY, ANCHOR_RECEIPT_KEY, ANCHOR_SERVER_KEY,
+};
+use wll_ledger::{
+    anchor_nonce, roughtime_request, verify_roughtime_response, CommitmentProposal, Decision,
+    EffectKind, EffectSummary, EvidenceBundle, OutcomeReceipt, OutcomeRecord, ProofRef, Receipt,
+    RoughtimeVerifier,
+};
+use wll_types::{CommitmentClass, CommitmentId};
+
+use crate::error::{SdkError, SdkResult};
+use crate::repository::Wll;
+
+/// A Roughtime server and the long-term key its responses must verify under.
+#[derive(Clone, Debug)]
+pub struct RoughtimeServer {
+    pub name: String,
+    /// `host:port` of the server's UDP endpoint.
+    pub address: String,
+    pub public_key: VerifyingKey,
+}
+
+impl RoughtimeServer {
+    pub fn new(
+        name: impl Into<String>,
+        address: impl Into<String>,
+        public_key: VerifyingKey,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            address: address.into(),
+            public_key,
+        }
+    }
+
+    /// Server `name` from its `[timestamp.server.<name>]` settings.
+    pub fn from_settings(name: &str, settings: &TimestampServerSettings) -> SdkResult<Self> {
+        let public_key = parse_public_key(
+            &format!("timestamp.server.{name}.public_key"),
+            &settings.public_key,
+        )?;
+        Ok(Self::new(name, settings.address.clone(), public_key))
+    }
+
+    /// Every server in `settings`.
+    pub fn all_from_settings(settings: &TimestampSettings) -> SdkResult<Vec<Self>> {
+        settings
+            .servers
+            .iter()
+            .map(|(name, server)| Self::from_settings(name, server))
+            .collect()
+    }
+
+    /// Ask the server for a signed time over `nonce`, waiting at most
+    /// `timeout` for the reply.
+    pub async fn query(&self, nonce: &[u8; 64], timeout: Duration) -> SdkResult<Vec<u8>> {
+        let socket = tokio::net::UdpSocket::bind(("0.0.0.0", 0)).await?;
+        socket.connect(&self.address).await?;
+        socket.send(&roughtime_request(nonce)).await?;
+        let mut buf = vec![0; 4096];
+        let len = tokio::time::timeout(timeout, socket.recv(&mut buf))
+            .await
+            .map_err(|_| {
+                SdkError::Io(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    format!("no response from {} within {timeout:?}", self.address),
+                ))
+            })??;
+        buf.truncate(len);
+        Ok(buf)
+    }
+}
+
+/// A recorded time anchor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimeAnchor {
+    /// Receipt whose existence the anchor bounds.
+    pub anchored: [u8; 32],
+    /// Unix time in microseconds the server vouched for.
+    pub midpoint_us: u64,
+    /// Uncertainty of `midpoint_us`, in microseconds.
+    pub radius_us: u32,
+    pub server: String,
+    /// The anchor's outcome receipt.
+    pub receipt_hash: [u8; 32],
+}
+
+impl TimeAnchor {
+    /// The anchor recorded by `outcome`, if it is one.
+    pub fn from_outcome(outcome: &OutcomeReceipt) -> Option<Self> {
+        let get = |key| outcome.metadata.get(key);
+        Some(Self {
+            anchored: hex::decode(get(ANCHOR_RECEIPT_KEY)?)
+                .ok()?
+                .try_into()
+                .ok()?,
+            midpoint_us: get(ANCHOR_MIDPOINT_KEY)?.parse().ok()?,
+            radius_us: get(ANCHOR_RADIUS_KEY)?.parse().ok()?,
+            server: get(ANCHOR_SERVER_KEY)?.clone(),
+            receipt_hash: outcome.receipt_hash,
+        })
+    }
+}
+
+impl Wll {
+    /// Servers configured under `[timestamp]`.
+    pub fn timestamp_servers(&self) -> SdkResult<Vec<RoughtimeServer>> {
+        RoughtimeServer::all_from_settings(&self.config()?.timestamp()?)
+    }
+
+    /// Every time anchor on this worldline, oldest first.
+    pub fn time_anchors(&self) -> SdkResult<Vec<TimeAnchor>> {
+        Ok(self
+            .ledger()
+            .read_all(self.worldline())?
+            .iter()
+            .filter_map(|receipt| match receipt {
+                Receipt::Outcome(outcome) => TimeAnchor::from_outcome(outcome),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// The receipt a time anchor taken now would cover: the latest receipt
+    /// that isn't itself part of an anchor, unless the ledger is empty or
+    /// that receipt already has a time anchor.
+    pub fn time_anchor_target(&self) -> SdkResult<Option<[u8; 32]>> {
+        self.anchor_target(|outcome| TimeAnchor::from_outcome(outcome).is_some())
+    }
+
+    /// The latest receipt that isn't part of an anchor, unless an anchor
+    /// that `is_kind` matches already covers it.
+    pub(crate) fn anchor_target(
+        &self,
+        is_kind: impl Fn(&OutcomeReceipt) -> bool,
+    ) -> SdkResult<Option<[u8; 32]>> {
+        let receipts = self.ledger().read_all(self.worldline())?;
+        let anchors: Vec<&OutcomeReceipt> = receipts
+            .iter()
+            .filter_map(|receipt| match receipt {
+                Receipt::Outcome(outcome) if outcome.metadata.contains_key(ANCHOR_RECEIPT_KEY) => {
+                    Some(outcome)
+                }
+                _ => None,
+            })
+            .collect();
+        let Some(target) = receipts
+            .iter()
+            .rev()
+            .map(Receipt::receipt_hash)
+            .find(|hash| {
+                !anchors
+                    .iter()
+                    .any(|a| a.receipt_hash == *hash || a.commitment_receipt_hash == *hash)
+            })
+        else {
+            return Ok(None);
+        };
+        let covered = anchors.iter().any(|anchor| {
+            is_kind(anchor) && anchor.metadata.get(ANCHOR_RECEIPT_KEY) == Some(&hex::encode(target))
+        });
+        Ok((!covered).then_some(target))
+    }
+
+    /// Record an anchor of `anchored`: a read-only commitment, not run
+    /// through the gate, and an outcome whose proof is `artifact`.
+    pub(crate) fn record_anchor(
+        &self,
+        intent: String,
+        anchored: [u8; 32],
+        artifact: &[u8],
+        effect: EffectSummary,
+        mut metadata: BTreeMap<String, String>,
+    ) -> SdkResult<OutcomeReceipt> {
+        let blob = self.write_blob(artifact)?;
+        let proposal = CommitmentProposal {
+            worldline: self.worldline().clone(),
+            commitment_id: CommitmentId::new(),
+            class: CommitmentClass::ReadOnly,
+            intent,
+            requested_caps: vec![],
+            targets: vec![self.worldline().clone()],
+            evidence: EvidenceBundle::empty(),
+            nonce: self.time_nonce(),
+        };
+        let commitment =
+            self.ledger()
+                .append_commitment(&proposal, &Decision::Accepted, [0; 32])?;
+        metadata.insert(ANCHOR_RECEIPT_KEY.to_string(), hex::encode(anchored));
+        let outcome = self.ledger().append_outcome(
+            commitment.receipt_hash,
+            &OutcomeRecord {
+                effects: vec![effect],
+                proofs: vec![ProofRef {
+                    uri: format!("obj://{}", blob.to_hex()),
+                    digest: *blake3::hash(artifact).as_bytes(),
+                }],
+                state_updates: vec![],
+                metadata,
+            },
+        )?;
+        self.record_provenance(&[
+            Receipt::Commitment(commitment),
+            Receipt::Outcome(outcome.clone()),
+        ])?;
+        Ok(outcome)
+    }
+
+    /// Verify `response` as `server`'s signed time over `anchored` and
+    /// record it as an anchor.
+    pub fn record_time_anchor(
+        &self,
+        server: &RoughtimeServer,
+        anchored: [u8; 32],
+        response: &[u8],
+    ) -> SdkResult<TimeAnchor> {
+        let time =
+            verify_roughtime_response(response, &anchor_nonce(&anchored), &server.public_key)
+                .map_err(|e| {
+                    SdkError::InvalidOperation(format!(
+                        "roughtime response from {}: {e}",
+                        server.name
+                    ))
+                })?;
+
+        let artifact = serde_json::to_vec(&serde_json::json!({
+            "type": "roughtime",
+            "server": server.name,
+            "anchored": hex::encode(anchored),
+            "response": hex::encode(response),
+        }))?;
+        let outcome = self.record_anchor(
+            format!("time anchor via {}", server.name),
+            anchored,
+            &artifact,
+            EffectSummary::new(
+                EffectKind::TimeAnchor,
+                hex::encode(anchored),
+                format!("{} ± {}µs", time.midpoint_us, time.radius_us),
+            )
+            .with_payload(serde_json::json!({
+                "midpoint_us": time.midpoint_us,
+                "radius_us": time.radius_us,
+            })),
+            BTreeMap::from([
+                (
+                    ANCHOR_MIDPOINT_KEY.to_string(),
+                    time.midpoint_us.to_string(),
+                ),
+                (ANCHOR_RADIUS_KEY.to_string(), time.radius_us.to_string()),
+                (ANCHOR_SERVER_KEY.to_string(), server.name.clone()),
+            ]),
+        )?;
+
+        Ok(TimeAnchor {
+            anchored,
+            midpoint_us: time.midpoint_us,
+            radius_us: time.radius_us,
+            server: server.name.clone(),
+            receipt_hash: outcome.receipt_hash,
+        })
+    }
+}
+
+/// An Ed25519 public key given in config as hex or base64.
+pub(crate) fn parse_public_key(key: &str, encoded: &str) -> SdkResult<VerifyingKey> {
+    let encoded = encoded.trim();
+    let bytes = hex::decode(encoded)
+        .or_else(|_| base64::engine::general_purpose::STANDARD.decode(encoded))
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or_else(|| SdkError::Config(format!("{key}: expected a 32-byte hex or base64 key")))?;
+    VerifyingKey::from_bytes(bytes).map_err(|e| SdkError::Config(format!("{key}: {e}")))
+}
+
+/// Proof verifier trusting every server in `servers`.
+pub(crate) fn roughtime_verifier(servers: &[RoughtimeServer]) -> RoughtimeVerifier {
+    servers
+        .iter()
+        .fold(RoughtimeVerifier::new(), |verifier, server| {
+            verifier.with_server(server.name.clone(), server.public_key.clone())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use wll_crypto::SigningKey;
+    use wll_ledger::{ProofVerifiers, RoughtimeResponder, RoughtimeTime};
+
+    use super::*;
+    use crate::commit::CommitProposal;
+    use crate::AsyncWll;
+
+    const TIME: RoughtimeTime = RoughtimeTime {
+        midpoint_us: 1_700_000_000_000_000,
+        radius_us: 1_000_000,
+    };
+
+    /// A responder on a local UDP port answering every request with `TIME`.
+    async fn serve(long_term: &SigningKey) -> String {
+        let responder =
+            RoughtimeResponder::new(long_term, SigningKey::from_bytes([2; 32]), 0, u64::MAX);
+        let socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = socket.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let mut buf = vec![0; 2048];
+            while let Ok((len, peer)) = socket.recv_from(&mut buf).await {
+                let response = responder.respond(&buf[..len], TIME).unwrap();
+                socket.send_to(&response, peer).await.unwrap();
+            }
+        });
+        address
+    }
+
+    #[test]
+    fn server_keys_parse_from_hex_or_base64() {
+        let key = SigningKey::from_bytes([1; 32]).verifying_key();
+        for encoded in [
+            hex::encode(key.as_bytes()),
+            base64::engine::general_purpose::STANDARD.encode(key.as_bytes()),
+        ] {
+            let settings = TimestampServerSettings {
+                address: "time.example.com:2002".into(),
+                public_key: encoded,
+            };
+            let server = RoughtimeServer::from_settings("example", &settings).unwrap();
+            assert_eq!(server.public_key, key);
+        }
+        let settings = TimestampServerSettings {
+            address: "time.example.com:2002".into(),
+            public_key: "not a key".into(),
+        };
+        assert!(matches!(
+            RoughtimeServer::from_settings("example", &settings),
+            Err(SdkError::Config(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn anchors_head_and_verifies_strictly() {
+        let long_term = SigningKey::from_bytes([1; 32]);
+        let server =
+            RoughtimeServer::new("local", serve(&long_term).await, long_term.verifying_key());
+        let wll = Wll::init().unwrap();
+        let head = wll
+            .commit(CommitProposal::new("first"))
+            .unwrap()
+            .receipt_hash;
+        let wll = AsyncWll::new(
+            wll.with_proof_verifiers(
+                ProofVerifiers::new()
+                    .with_verifier(Arc::new(roughtime_verifier(std::slice::from_ref(&server)))),
+            ),
+        );
+
+        let anchor = wll.anchor_time(&server).await.unwrap().unwrap();
+        assert_eq!(anchor.anchored, head);
+        assert_eq!(anchor.midpoint_us, TIME.midpoint_us);
+        assert!(wll.anchor_time(&server).await.unwrap().is_none());
+
+        let repo = wll.blocking();
+        assert_eq!(repo.time_anchors().unwrap(), vec![anchor]);
+        assert_eq!(repo.current_branch().unwrap(), "main");
+        assert!(repo.verify_strict().unwrap().is_valid());
+
+        let stranger = SigningKey::from_bytes([3; 32]);
+        let response =
+            RoughtimeResponder::new(&stranger, SigningKey::from_bytes([2; 32]), 0, u64::MAX)
+                .respond(&roughtime_request(&anchor_nonce(&head)), TIME)
+                .unwrap();
+        assert!(repo.record_time_anchor(&server, head, &response).is_err());
+    }
+}
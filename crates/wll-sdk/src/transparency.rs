@@ -0,0 +1,363 @@
+//! Publishing the ledger head to transparency logs.
+//!
+//! A publication appends the hash of the latest receipt to an append-only
+//! log and records the log's inclusion proof the same way a time anchor is
+//! recorded: a read-only commitment whose outcome names the published
+//! receipt and carries the proof as a `transparency-log` artifact. Strict
+//! verification checks it with a [`TransparencyLogVerifier`], so a third
+//! party holding the log's checkpoints can confirm the ledger was not
+//! rewritten after the fact.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde::Serialize;
+use wll_config::{TransparencyLogSettings, TransparencySettings};
+use wll_crypto::VerifyingKey;
+use wll_ledger::timestamp::ANCHOR_RECEIPT_KEY;
+use wll_ledger::{
+    EffectKind, EffectSummary, LogInclusion, OutcomeReceipt, Receipt, TransparencyLogVerifier,
+};
+
+use crate::error::{SdkError, SdkResult};
+use crate::repository::Wll;
+use crate::time_anchor::parse_public_key;
+
+/// Outcome metadata key holding the name of the log a receipt was
+/// published to.
+pub const ANCHOR_LOG_KEY: &str = "anchor.log";
+/// Outcome metadata key holding the published entry's index in the log.
+pub const ANCHOR_LOG_INDEX_KEY: &str = "anchor.log_index";
+
+/// An append-only log of receipt hashes.
+pub trait TransparencyLog: Send + Sync {
+    /// Name the log's checkpoints are signed under.
+    fn name(&self) -> &str;
+
+    /// The key the log signs checkpoints with.
+    fn public_key(&self) -> &VerifyingKey;
+
+    /// Append `entry` and return the proof of its inclusion.
+    fn append(&self, entry: &[u8; 32]) -> SdkResult<LogInclusion>;
+}
+
+/// A log reached over HTTP.
+///
+/// `POST <url>/entries` with `{"entry": "<hex>"}` appends an entry; the log
+/// answers with the [`LogInclusion`] for it as JSON.
+pub struct HttpTransparencyLog {
+    name: String,
+    url: String,
+    public_key: VerifyingKey,
+    agent: ureq::Agent,
+}
+
+#[derive(Serialize)]
+struct AppendRequest {
+    entry: String,
+}
+
+impl HttpTransparencyLog {
+    /// Log `name` at `url`, with a 30 second timeout.
+    pub fn new(name: impl Into<String>, url: impl Into<String>, public_key: VerifyingKey) -> Self {
+        Self {
+            name: name.into(),
+            url: url.into().trim_end_matches('/').to_string(),
+            public_key,
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .build(),
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.agent = ureq::AgentBuilder::new().timeout(timeout).build();
+        self
+    }
+
+    /// Log `name` from its `[transparency.log.<name>]` settings.
+    pub fn from_settings(name: &str, settings: &TransparencyLogSettings) -> SdkResult<Self> {
+        let public_key = parse_public_key(
+            &format!("transparency.log.{name}.public_key"),
+            &settings.public_key,
+        )?;
+        Ok(Self::new(name, settings.url.clone(), public_key))
+    }
+
+    /// Every log in `settings`.
+    pub fn all_from_settings(settings: &TransparencySettings) -> SdkResult<Vec<Self>> {
+        settings
+            .logs
+            .iter()
+            .map(|(name, log)| Self::from_settings(name, log))
+            .collect()
+    }
+}
+
+impl TransparencyLog for HttpTransparencyLog {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn public_key(&self) -> &VerifyingKey {
+        &self.public_key
+    }
+
+    fn append(&self, entry: &[u8; 32]) -> SdkResult<LogInclusion> {
+        let url = format!("{}/entries", self.url);
+        let body = serde_json::to_string(&AppendRequest {
+            entry: hex::encode(entry),
+        })?;
+        let response = self
+            .agent
+            .post(&url)
+            .set("Content-Type", "application/json")
+            .send_string(&body)
+            .map_err(|e| SdkError::TransparencyLog(format!("{url}: {e}")))?;
+        let text = response
+            .into_string()
+            .map_err(|e| SdkError::TransparencyLog(format!("{url}: {e}")))?;
+        serde_json::from_str(&text)
+            .map_err(|e| SdkError::TransparencyLog(format!("{url}: malformed response: {e}")))
+    }
+}
+
+/// A receipt recorded as published to a log.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogPublication {
+    /// The published receipt.
+    pub anchored: [u8; 32],
+    pub log: String,
+    /// The entry's index in the log.
+    pub index: u64,
+    /// The publication's outcome receipt.
+    pub receipt_hash: [u8; 32],
+}
+
+impl LogPublication {
+    /// The publication recorded by `outcome`, if it is one.
+    pub fn from_outcome(outcome: &OutcomeReceipt) -> Option<Self> {
+        let get = |key| outcome.metadata.get(key);
+        Some(Self {
+            anchored: hex::decode(get(ANCHOR_RECEIPT_KEY)?)
+                .ok()?
+                .try_into()
+                .ok()?,
+            log: get(ANCHOR_LOG_KEY)?.clone(),
+            index: get(ANCHOR_LOG_INDEX_KEY)?.parse().ok()?,
+            receipt_hash: outcome.receipt_hash,
+        })
+    }
+}
+
+impl Wll {
+    /// Logs configured under `[transparency]`.
+    pub fn transparency_logs(&self) -> SdkResult<Vec<HttpTransparencyLog>> {
+        HttpTransparencyLog::all_from_settings(&self.config()?.transparency()?)
+    }
+
+    /// Every log publication on this worldline, oldest first.
+    pub fn log_publications(&self) -> SdkResult<Vec<LogPublication>> {
+        Ok(self
+            .ledger()
+            .read_all(self.worldline())?
+            .iter()
+            .filter_map(|receipt| match receipt {
+                Receipt::Outcome(outcome) => LogPublication::from_outcome(outcome),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// The receipt a publication to `log` made now would cover: the latest
+    /// receipt that isn't part of an anchor, unless it is already in `log`.
+    pub fn log_publication_target(&self, log: &str) -> SdkResult<Option<[u8; 32]>> {
+        self.anchor_target(|outcome| {
+            outcome.metadata.get(ANCHOR_LOG_KEY).map(String::as_str) == Some(log)
+        })
+    }
+
+    /// Append the latest receipt to `log` and record the publication.
+    ///
+    /// Returns `None` without contacting the log when there is nothing new
+    /// to publish. This blocks on the log; from async code use
+    /// [`AsyncWll::publish_head`](crate::AsyncWll::publish_head).
+    pub fn publish_head(&self, log: &dyn TransparencyLog) -> SdkResult<Option<LogPublication>> {
+        let Some(anchored) = self.log_publication_target(log.name())? else {
+            return Ok(None);
+        };
+        let inclusion = log.append(&anchored)?;
+        if inclusion.log != log.name() {
+            return Err(SdkError::TransparencyLog(format!(
+                "{} answered for log {:?}",
+                log.name(),
+                inclusion.log
+            )));
+        }
+        self.record_log_publication(log.public_key(), anchored, inclusion)
+            .map(Some)
+    }
+
+    /// Check that `inclusion` proves `anchored` is in the log `key` signs
+    /// for, and record the publication.
+    pub fn record_log_publication(
+        &self,
+        key: &VerifyingKey,
+        anchored: [u8; 32],
+        inclusion: LogInclusion,
+    ) -> SdkResult<LogPublication> {
+        inclusion
+            .verify(key, &anchored)
+            .map_err(|e| SdkError::TransparencyLog(format!("{}: {e}", inclusion.log)))?;
+
+        let mut artifact = serde_json::to_value(&inclusion)?;
+        artifact["type"] = "transparency-log".into();
+        let artifact = serde_json::to_vec(&artifact)?;
+
+        let outcome = self.record_anchor(
+            format!("publish to {}", inclusion.log),
+            anchored,
+            &artifact,
+            EffectSummary::new(
+                EffectKind::LogPublication,
+                hex::encode(anchored),
+                format!(
+                    "entry {} of {} in {}",
+                    inclusion.index, inclusion.tree_size, inclusion.log
+                ),
+            )
+            .with_payload(serde_json::json!({
+                "log": inclusion.log,
+                "index": inclusion.index,
+                "tree_size": inclusion.tree_size,
+            })),
+            BTreeMap::from([
+                (ANCHOR_LOG_KEY.to_string(), inclusion.log.clone()),
+                (
+                    ANCHOR_LOG_INDEX_KEY.to_string(),
+                    inclusion.index.to_string(),
+                ),
+            ]),
+        )?;
+        Ok(LogPublication {
+            anchored,
+            log: inclusion.log,
+            index: inclusion.index,
+            receipt_hash: outcome.receipt_hash,
+        })
+    }
+}
+
+/// Proof verifier trusting every log in `logs`.
+pub(crate) fn log_verifier<L: TransparencyLog>(logs: &[L]) -> TransparencyLogVerifier {
+    logs.iter()
+        .fold(TransparencyLogVerifier::new(), |verifier, log| {
+            verifier.with_log(log.name(), log.public_key().clone())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use wll_crypto::SigningKey;
+    use wll_ledger::proof::{checkpoint_message, log_tree_proof};
+    use wll_ledger::ProofVerifiers;
+
+    use super::*;
+    use crate::commit::CommitProposal;
+    use crate::AsyncWll;
+
+    /// A log kept in memory, signing a checkpoint per append.
+    struct MemoryLog {
+        key: SigningKey,
+        public_key: VerifyingKey,
+        entries: Mutex<Vec<[u8; 32]>>,
+    }
+
+    impl MemoryLog {
+        fn new() -> Self {
+            let key = SigningKey::from_bytes([5; 32]);
+            Self {
+                public_key: key.verifying_key(),
+                key,
+                entries: Mutex::new(vec![[1; 32], [2; 32]]),
+            }
+        }
+    }
+
+    impl TransparencyLog for MemoryLog {
+        fn name(&self) -> &str {
+            "memory"
+        }
+
+        fn public_key(&self) -> &VerifyingKey {
+            &self.public_key
+        }
+
+        fn append(&self, entry: &[u8; 32]) -> SdkResult<LogInclusion> {
+            let mut entries = self.entries.lock().unwrap();
+            entries.push(*entry);
+            let index = entries.len() - 1;
+            let (root, path) = log_tree_proof(&entries, index);
+            let tree_size = entries.len() as u64;
+            Ok(LogInclusion {
+                log: "memory".into(),
+                index: index as u64,
+                tree_size,
+                inclusion: path.iter().map(hex::encode).collect(),
+                root: hex::encode(root),
+                signature: hex::encode(
+                    self.key
+                        .sign(&checkpoint_message("memory", tree_size, &root))
+                        .to_bytes(),
+                ),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn publishes_head_and_verifies_strictly() {
+        let log = Arc::new(MemoryLog::new());
+        let wll = Wll::init().unwrap();
+        let head = wll
+            .commit(CommitProposal::new("first"))
+            .unwrap()
+            .receipt_hash;
+        let wll = AsyncWll::new(wll.with_proof_verifiers(
+            ProofVerifiers::new().with_verifier(Arc::new(log_verifier(&[MemoryLog::new()]))),
+        ));
+
+        let publication = wll.publish_head(log.clone()).await.unwrap().unwrap();
+        assert_eq!(publication.anchored, head);
+        assert_eq!(publication.index, 2);
+        assert!(wll.publish_head(log.clone()).await.unwrap().is_none());
+
+        let repo = wll.blocking();
+        assert_eq!(repo.log_publications().unwrap(), vec![publication]);
+        assert!(repo.verify_strict().unwrap().is_valid());
+
+        let next = repo
+            .commit(CommitProposal::new("second"))
+            .unwrap()
+            .receipt_hash;
+        assert_eq!(repo.log_publication_target("memory").unwrap(), Some(next));
+        assert_eq!(repo.log_publication_target("other").unwrap(), Some(next));
+    }
+
+    #[test]
+    fn rejects_proofs_for_other_entries() {
+        let log = MemoryLog::new();
+        let wll = Wll::init().unwrap();
+        let head = wll
+            .commit(CommitProposal::new("first"))
+            .unwrap()
+            .receipt_hash;
+        let inclusion = log.append(&[9; 32]).unwrap();
+        assert!(matches!(
+            wll.record_log_publication(log.public_key(), head, inclusion),
+            Err(SdkError::TransparencyLog(_))
+        ));
+        assert!(wll.log_publications().unwrap().is_empty());
+    }
+}
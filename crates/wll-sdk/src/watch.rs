@@ -0,0 +1,240 @@
+//! Watching projected state for changes.
+//!
+//! A [`StateWatch`] subscribes to the outcomes a repository publishes on its
+//! event fabric and reports when the watched keys of one namespace take a
+//! new value, so a service can treat the projection
+//! ([`Wll::latest_state`]) as a small reactive config store.
+//!
+//! The watch keeps its own copy of the namespace's values and reports only
+//! real changes: setting a key to the value it already has is silent. If
+//! the subscriber falls behind the fabric's channel, the watch reprojects
+//! from the ledger and reports the difference instead of the missed steps.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use serde_json::Value;
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+use wll_fabric::{EventFilter, EventKind, EventPayload, FabricError, FabricEvent};
+use wll_ledger::{Ledger, ProjectionBuilder, Receipt};
+use wll_types::WorldlineId;
+
+use crate::error::{SdkError, SdkResult};
+use crate::repository::Wll;
+
+/// A watched key that took a new value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StateChange {
+    pub namespace: Option<String>,
+    pub key: String,
+    /// `None` if the key had never been set.
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+    /// The outcome that made the change, or the head the watch reprojected
+    /// at after falling behind.
+    pub receipt_hash: [u8; 32],
+}
+
+/// A subscription to changes of some keys of one namespace.
+///
+/// With no [`key`](Self::key) or [`prefix`](Self::prefix) it watches every
+/// key of the namespace.
+pub struct StateWatch {
+    worldline: WorldlineId,
+    namespace: Option<String>,
+    keys: Vec<String>,
+    prefixes: Vec<String>,
+    ledger: Arc<dyn Ledger>,
+    events: wll_fabric::fabric::EventStream,
+    values: BTreeMap<String, Value>,
+}
+
+impl Wll {
+    /// Watch `namespace` (the default namespace when `None`) of this
+    /// worldline. Needs an event fabric ([`WllBuilder::with_fabric`]).
+    ///
+    /// [`WllBuilder::with_fabric`]: crate::WllBuilder::with_fabric
+    pub fn watch_state(&self, namespace: Option<&str>) -> SdkResult<StateWatch> {
+        let fabric = self.fabric().ok_or_else(|| {
+            SdkError::Unsupported("watching state needs an event fabric".into())
+        })?;
+        // Subscribe before projecting, so no outcome falls between the two.
+        let events = fabric.subscribe(EventFilter {
+            worldlines: Some(vec![self.worldline().clone()]),
+            kinds: Some(vec![EventKind::OutcomeRecorded]),
+            since: None,
+        });
+        let mut watch = StateWatch {
+            worldline: self.worldline().clone(),
+            namespace: namespace.map(str::to_string),
+            keys: Vec::new(),
+            prefixes: Vec::new(),
+            ledger: self.shared_ledger(),
+            events,
+            values: BTreeMap::new(),
+        };
+        watch.values = watch.project()?.1;
+        Ok(watch)
+    }
+}
+
+impl StateWatch {
+    /// Also watch `key`.
+    pub fn key(mut self, key: impl Into<String>) -> Self {
+        self.keys.push(key.into());
+        self
+    }
+
+    /// Also watch every key starting with `prefix`.
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefixes.push(prefix.into());
+        self
+    }
+
+    /// Current value of `key`, as of the last change this watch saw.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.values.get(key)
+    }
+
+    /// Changes published since the last call, without waiting.
+    pub fn try_changes(&mut self) -> SdkResult<Vec<StateChange>> {
+        let mut changes = Vec::new();
+        loop {
+            match self.events.try_recv() {
+                Ok(event) => changes.extend(self.on_event(&event)?),
+                Err(TryRecvError::Empty) => return Ok(changes),
+                Err(TryRecvError::Lagged(_)) => changes.extend(self.resync()?),
+                Err(TryRecvError::Closed) => {
+                    return Err(FabricError::SubscriberClosed.into());
+                }
+            }
+        }
+    }
+
+    /// Wait for the next outcome that changes a watched key.
+    pub async fn changed(&mut self) -> SdkResult<Vec<StateChange>> {
+        loop {
+            let changes = match self.events.recv().await {
+                Ok(event) => self.on_event(&event)?,
+                Err(RecvError::Lagged(_)) => self.resync()?,
+                Err(RecvError::Closed) => return Err(FabricError::SubscriberClosed.into()),
+            };
+            if !changes.is_empty() {
+                return Ok(changes);
+            }
+        }
+    }
+
+    fn watches(&self, key: &str) -> bool {
+        (self.keys.is_empty() && self.prefixes.is_empty())
+            || self.keys.iter().any(|k| k == key)
+            || self.prefixes.iter().any(|p| key.starts_with(p.as_str()))
+    }
+
+    fn on_event(&mut self, event: &FabricEvent) -> SdkResult<Vec<StateChange>> {
+        let EventPayload::ObjectRef { object_id, .. } = &event.payload else {
+            return Ok(Vec::new());
+        };
+        let Some(Receipt::Outcome(outcome)) = self.ledger.get_by_hash(*object_id.as_bytes())?
+        else {
+            return Ok(Vec::new());
+        };
+        let mut changes = Vec::new();
+        for update in &outcome.state_updates {
+            if update.namespace != self.namespace {
+                continue;
+            }
+            let old = self.values.insert(update.key.clone(), update.value.clone());
+            if self.watches(&update.key) && old.as_ref() != Some(&update.value) {
+                changes.push(self.change(&update.key, old, outcome.receipt_hash));
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Reproject from the ledger and report how the watched keys differ
+    /// from the values this watch last saw.
+    fn resync(&mut self) -> SdkResult<Vec<StateChange>> {
+        let (head, values) = self.project()?;
+        let old = std::mem::replace(&mut self.values, values);
+        let mut keys: Vec<&String> = old.keys().chain(self.values.keys()).collect();
+        keys.sort();
+        keys.dedup();
+        let changes = keys
+            .into_iter()
+            .filter(|key| self.watches(key) && old.get(*key) != self.values.get(*key))
+            .map(|key| self.change(key, old.get(key).cloned(), head))
+            .collect();
+        Ok(changes)
+    }
+
+    fn project(&self) -> SdkResult<([u8; 32], BTreeMap<String, Value>)> {
+        let mut projection =
+            ProjectionBuilder::latest_state(self.ledger.as_ref(), &self.worldline)?;
+        let head = projection.head.map_or([0; 32], |head| head.receipt_hash);
+        let values = match &self.namespace {
+            Some(name) => projection.namespaces.remove(name).unwrap_or_default(),
+            None => projection.state,
+        };
+        Ok((head, values))
+    }
+
+    fn change(&self, key: &str, old: Option<Value>, receipt_hash: [u8; 32]) -> StateChange {
+        StateChange {
+            namespace: self.namespace.clone(),
+            key: key.to_string(),
+            old,
+            new: self.values.get(key).cloned(),
+            receipt_hash,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wll_fabric::{EventFabric, FabricConfig};
+    use wll_ledger::StateUpdate;
+    use wll_types::IdentityMaterial;
+
+    use crate::commit::CommitProposal;
+    use crate::WllBuilder;
+
+    #[test]
+    fn watch_reports_changed_keys_and_catches_up_after_lagging() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = FabricConfig { channel_capacity: 1, ..FabricConfig::default() };
+        let fabric = Arc::new(EventFabric::new(&dir.path().join("fabric.wal"), config).unwrap());
+        let wll = WllBuilder::new()
+            .with_worldline(WorldlineId::derive(&IdentityMaterial::GenesisHash([6; 32])))
+            .with_fabric(fabric)
+            .build()
+            .unwrap();
+        let set = |pairs: &[(&str, i64)]| {
+            let state = pairs
+                .iter()
+                .map(|(k, v)| StateUpdate::new(*k, Value::from(*v)).in_namespace("config"))
+                .collect();
+            wll.commit_with_state(CommitProposal::new("set"), state).unwrap()
+        };
+        set(&[("mode", 1)]);
+        let mut watch = wll.watch_state(Some("config")).unwrap().key("mode").prefix("flags/");
+        assert_eq!(watch.get("mode"), Some(&Value::from(1)));
+
+        let result = set(&[("mode", 1), ("flags/dark", 1), ("other", 1)]);
+        let changes = watch.try_changes().unwrap();
+        assert_eq!(changes.len(), 1, "unchanged and unwatched keys are silent");
+        assert_eq!(changes[0].key, "flags/dark");
+        assert_eq!((&changes[0].old, &changes[0].new), (&None, &Some(Value::from(1))));
+        assert_eq!(changes[0].receipt_hash, result.outcome_receipt.receipt_hash);
+
+        // Two outcomes overflow the one-slot channel; the watch reprojects.
+        set(&[("mode", 2)]);
+        set(&[("mode", 3)]);
+        let changes = watch.try_changes().unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].old, Some(Value::from(1)));
+        assert_eq!(changes[0].new, Some(Value::from(3)));
+        assert!(watch.try_changes().unwrap().is_empty());
+    }
+}
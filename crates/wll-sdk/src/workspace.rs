@@ -0,0 +1,496 @@
+//! Workspaces: a repository that pins other worldlines.
+//!
+//! A platform built from several worldlines can track them from one parent
+//! repository. The parent's tree holds a manifest, [`WORKSPACE_MANIFEST`],
+//! listing each member: where its repository is checked out under the
+//! parent's work tree, where it is cloned from, its worldline, and the
+//! receipt it is pinned to, the way a git submodule pins a commit.
+//!
+//! The manifest only changes through commits in the parent, so every pin
+//! change is a receipt in the parent's ledger. [`Wll::update_workspace_pins`]
+//! moves any number of pins in one commitment, which records each new pin in
+//! its outcome metadata under [`WORKSPACE_PIN_KEY_PREFIX`].
+//!
+//! Members are cloned from a local repository or a bundle file; URLs of
+//! network remotes are not cloned here.
+
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use wll_store::EntryMode;
+use wll_types::{CommitmentClass, WorldlineId};
+
+use crate::bundle::{Bundle, BundleOptions};
+use crate::commit::{CommitProposal, CommitResult};
+use crate::config::{RepoConfig, WLL_DIR};
+use crate::error::{SdkError, SdkResult};
+use crate::repository::Wll;
+
+/// Path of the workspace manifest in the parent repository's tree.
+pub const WORKSPACE_MANIFEST: &str = "wll-workspace.toml";
+/// Commitment class of workspace manifest changes.
+pub const WORKSPACE_CLASS: &str = "workspace";
+/// Outcome metadata key prefix; `workspace.pin.<member>` holds the member's
+/// new hex pin.
+pub const WORKSPACE_PIN_KEY_PREFIX: &str = "workspace.pin.";
+
+/// A worldline repository pinned by a workspace.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WorkspaceMember {
+    pub name: String,
+    /// Where the member is checked out, relative to the parent's work tree.
+    pub path: String,
+    /// Local repository or bundle file the member is cloned from; relative
+    /// paths are resolved against the parent's work tree.
+    pub url: String,
+    pub worldline: WorldlineId,
+    /// Branch of the member whose tip [`update_workspace_pins`] pins.
+    ///
+    /// [`update_workspace_pins`]: Wll::update_workspace_pins
+    pub branch: String,
+    /// Hash of the receipt the member is pinned to.
+    pub pin: [u8; 32],
+}
+
+/// The members of a workspace, in name order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WorkspaceManifest {
+    pub members: Vec<WorkspaceMember>,
+}
+
+/// TOML shape of the manifest; ids and hashes are kept as hex strings.
+#[derive(Serialize, Deserialize)]
+struct ManifestFile {
+    #[serde(default)]
+    member: std::collections::BTreeMap<String, MemberSection>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct MemberSection {
+    path: String,
+    url: String,
+    worldline: String,
+    branch: String,
+    pin: String,
+}
+
+impl WorkspaceManifest {
+    /// The member called `name`, if any.
+    pub fn member(&self, name: &str) -> Option<&WorkspaceMember> {
+        self.members.iter().find(|m| m.name == name)
+    }
+
+    /// Render the manifest as TOML.
+    pub fn to_toml(&self) -> SdkResult<String> {
+        let file = ManifestFile {
+            member: self
+                .members
+                .iter()
+                .map(|m| {
+                    let section = MemberSection {
+                        path: m.path.clone(),
+                        url: m.url.clone(),
+                        worldline: m.worldline.to_hex(),
+                        branch: m.branch.clone(),
+                        pin: hex::encode(m.pin),
+                    };
+                    (m.name.clone(), section)
+                })
+                .collect(),
+        };
+        toml::to_string(&file).map_err(|e| SdkError::Config(e.to_string()))
+    }
+
+    /// Parse a manifest from TOML text.
+    pub fn from_toml(text: &str) -> SdkResult<Self> {
+        let file: ManifestFile =
+            toml::from_str(text).map_err(|e| SdkError::Config(e.to_string()))?;
+        let members = file
+            .member
+            .into_iter()
+            .map(|(name, section)| {
+                let invalid = |field: &str, e: &dyn std::fmt::Display| {
+                    SdkError::Config(format!("member.{name}.{field}: {e}"))
+                };
+                let worldline = WorldlineId::from_hex(&section.worldline)
+                    .map_err(|e| invalid("worldline", &e))?;
+                let pin = parse_hash(&section.pin).map_err(|e| invalid("pin", &e))?;
+                check_member_path(&section.path).map_err(|e| invalid("path", &e))?;
+                Ok(WorkspaceMember {
+                    name,
+                    path: section.path,
+                    url: section.url,
+                    worldline,
+                    branch: section.branch,
+                    pin,
+                })
+            })
+            .collect::<SdkResult<_>>()?;
+        Ok(Self { members })
+    }
+}
+
+/// How a member's checkout relates to its pin.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum PinState {
+    /// No repository at the member's path.
+    NotCloned,
+    /// The repository at the member's path records another worldline.
+    WrongWorldline { found: WorldlineId },
+    /// The checkout does not hold the pinned receipt.
+    PinMissing,
+    /// The branch tip is the pinned receipt.
+    AtPin,
+    /// The branch tip is this many receipts past the pin.
+    Ahead { receipts: u64 },
+    /// The branch tip is this many receipts before the pin.
+    Behind { receipts: u64 },
+}
+
+/// A member and the state of its checkout.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemberStatus {
+    pub member: WorkspaceMember,
+    /// Tip of the member's branch in its checkout.
+    pub tip: Option<[u8; 32]>,
+    pub state: PinState,
+}
+
+/// A member cloned by [`Wll::clone_workspace`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemberClone {
+    pub name: String,
+    pub path: PathBuf,
+    pub receipts_imported: usize,
+    pub objects: usize,
+}
+
+impl Wll {
+    /// The workspace manifest in the current branch's tree; empty if the
+    /// tree has none.
+    pub fn workspace(&self) -> SdkResult<WorkspaceManifest> {
+        let Some(tree) = self.head_tree()? else {
+            return Ok(WorkspaceManifest::default());
+        };
+        match self.read_tree(&tree)?.get(WORKSPACE_MANIFEST) {
+            Some(entry) => {
+                let text = String::from_utf8(self.read_blob(&entry.object_id)?)
+                    .map_err(|e| SdkError::Config(format!("{WORKSPACE_MANIFEST}: {e}")))?;
+                WorkspaceManifest::from_toml(&text)
+            }
+            None => Ok(WorkspaceManifest::default()),
+        }
+    }
+
+    /// Add the repository or bundle at `url` as member `name`, pinned to
+    /// the tip of its `branch`, and commit the manifest.
+    ///
+    /// `path` defaults to `name`. The member is not cloned; see
+    /// [`clone_workspace`](Self::clone_workspace).
+    pub fn add_workspace_member(
+        &self,
+        name: &str,
+        url: &str,
+        path: Option<&str>,
+        branch: &str,
+    ) -> SdkResult<CommitResult> {
+        let mut manifest = self.workspace()?;
+        if manifest.member(name).is_some() {
+            return Err(SdkError::InvalidOperation(format!(
+                "workspace member {name} already exists"
+            )));
+        }
+        let path = path.unwrap_or(name);
+        check_member_path(path).map_err(SdkError::InvalidOperation)?;
+
+        let source = self.member_source(url)?;
+        let ref_name = format!("refs/heads/{branch}");
+        let pin = source
+            .refs
+            .iter()
+            .find(|r| r.name == ref_name)
+            .map(|r| r.receipt_hash)
+            .ok_or_else(|| SdkError::BranchNotFound(format!("{branch} in {url}")))?;
+        manifest.members.push(WorkspaceMember {
+            name: name.to_string(),
+            path: path.to_string(),
+            url: url.to_string(),
+            worldline: source.worldline,
+            branch: branch.to_string(),
+            pin,
+        });
+        manifest.members.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let proposal = CommitProposal::new(format!("add workspace member {name}"))
+            .with_metadata(format!("{WORKSPACE_PIN_KEY_PREFIX}{name}"), hex::encode(pin));
+        self.commit_manifest(&manifest, proposal)
+    }
+
+    /// Clone the members named in `names`, or every member if it is empty,
+    /// into their paths under the work tree. Members that already have a
+    /// repository there are skipped.
+    pub fn clone_workspace(&self, names: &[String]) -> SdkResult<Vec<MemberClone>> {
+        let root = self.workspace_root()?;
+        let mut cloned = Vec::new();
+        for member in self.selected_members(names)? {
+            let path = root.join(&member.path);
+            if path.join(WLL_DIR).exists() {
+                continue;
+            }
+            let bundle = self.member_source(&member.url)?;
+            if bundle.worldline != member.worldline {
+                return Err(SdkError::InvalidOperation(format!(
+                    "{} holds worldline {}, not {}",
+                    member.url,
+                    bundle.worldline.to_hex(),
+                    member.worldline.to_hex()
+                )));
+            }
+            let clone = Wll::init_at_with_config(&path, RepoConfig::new(member.worldline))?;
+            let report = clone.unbundle(bundle)?;
+            if member.branch != clone.current_branch()? {
+                clone.switch_branch(&member.branch)?;
+            }
+            cloned.push(MemberClone {
+                name: member.name,
+                path,
+                receipts_imported: report.receipts_imported,
+                objects: report.objects,
+            });
+        }
+        Ok(cloned)
+    }
+
+    /// Where each member's checkout stands relative to its pin.
+    pub fn workspace_status(&self) -> SdkResult<Vec<MemberStatus>> {
+        let root = self.workspace_root()?;
+        self.workspace()?
+            .members
+            .into_iter()
+            .map(|member| {
+                let path = root.join(&member.path);
+                if !path.join(WLL_DIR).exists() {
+                    return Ok(MemberStatus { member, tip: None, state: PinState::NotCloned });
+                }
+                let checkout = Wll::open(&path)?;
+                if checkout.worldline() != &member.worldline {
+                    let found = checkout.worldline().clone();
+                    let state = PinState::WrongWorldline { found };
+                    return Ok(MemberStatus { member, tip: None, state });
+                }
+                let tip = checkout.ref_tip(&format!("refs/heads/{}", member.branch))?;
+                let pinned = checkout.local_seq(member.pin)?;
+                let at = match tip {
+                    Some(tip) => checkout.local_seq(tip)?,
+                    None => None,
+                };
+                let state = match (pinned, at) {
+                    (Some(pinned), Some(at)) if at > pinned => {
+                        PinState::Ahead { receipts: at - pinned }
+                    }
+                    (Some(pinned), Some(at)) if at < pinned => {
+                        PinState::Behind { receipts: pinned - at }
+                    }
+                    (Some(_), Some(_)) => PinState::AtPin,
+                    (Some(pinned), None) => PinState::Behind { receipts: pinned },
+                    (None, _) => PinState::PinMissing,
+                };
+                Ok(MemberStatus { member, tip, state })
+            })
+            .collect()
+    }
+
+    /// Pin the members named in `names`, or every member if it is empty, to
+    /// the tip of their branch in their checkout, in one commitment.
+    ///
+    /// Every named member must be cloned; if any is not, nothing changes.
+    /// Returns `None` when every pin is already current.
+    pub fn update_workspace_pins(
+        &self,
+        names: &[String],
+        message: Option<&str>,
+    ) -> SdkResult<Option<CommitResult>> {
+        let selected = self.selected_members(names)?;
+        let mut manifest = self.workspace()?;
+        let mut moved = Vec::new();
+        for status in self.workspace_status()? {
+            if !selected.iter().any(|m| m.name == status.member.name) {
+                continue;
+            }
+            let name = status.member.name;
+            let tip = match status.state {
+                PinState::NotCloned | PinState::WrongWorldline { .. } => None,
+                _ => status.tip,
+            }
+            .ok_or_else(|| {
+                SdkError::InvalidOperation(format!(
+                    "workspace member {name} has no checkout of {} to pin",
+                    status.member.branch
+                ))
+            })?;
+            if tip != status.member.pin {
+                moved.push((name, tip));
+            }
+        }
+        if moved.is_empty() {
+            return Ok(None);
+        }
+
+        for member in &mut manifest.members {
+            if let Some((_, tip)) = moved.iter().find(|(name, _)| *name == member.name) {
+                member.pin = *tip;
+            }
+        }
+        let names: Vec<&str> = moved.iter().map(|(name, _)| name.as_str()).collect();
+        let message = message
+            .map_or_else(|| format!("update workspace pins: {}", names.join(", ")), String::from);
+        let mut proposal = CommitProposal::new(message);
+        for (name, tip) in &moved {
+            let key = format!("{WORKSPACE_PIN_KEY_PREFIX}{name}");
+            proposal = proposal.with_metadata(key, hex::encode(tip));
+        }
+        self.commit_manifest(&manifest, proposal).map(Some)
+    }
+
+    /// Commit `manifest` over the current tree.
+    fn commit_manifest(
+        &self,
+        manifest: &WorkspaceManifest,
+        proposal: CommitProposal,
+    ) -> SdkResult<CommitResult> {
+        let mut index = self.new_index();
+        if let Some(tree) = self.head_tree()? {
+            index.read_tree(&tree)?;
+        }
+        let toml = manifest.to_toml()?;
+        index.stage_file(WORKSPACE_MANIFEST, toml.as_bytes(), EntryMode::Regular)?;
+        let proposal = proposal
+            .with_class(CommitmentClass::Custom(WORKSPACE_CLASS.into()))
+            .with_target(WORKSPACE_MANIFEST);
+        self.commit_tree(&mut index, proposal)
+    }
+
+    /// The members named in `names`, or all of them if it is empty.
+    fn selected_members(&self, names: &[String]) -> SdkResult<Vec<WorkspaceMember>> {
+        let manifest = self.workspace()?;
+        if names.is_empty() {
+            return Ok(manifest.members);
+        }
+        names
+            .iter()
+            .map(|name| {
+                manifest.member(name).cloned().ok_or_else(|| {
+                    SdkError::InvalidOperation(format!("no workspace member {name}"))
+                })
+            })
+            .collect()
+    }
+
+    /// The work tree members are checked out under.
+    fn workspace_root(&self) -> SdkResult<PathBuf> {
+        self.repo_dir()
+            .and_then(Path::parent)
+            .map(Path::to_path_buf)
+            .ok_or_else(|| {
+                SdkError::InvalidOperation("workspaces need an on-disk repository".into())
+            })
+    }
+
+    /// Everything the repository or bundle at `url` holds, as a bundle.
+    fn member_source(&self, url: &str) -> SdkResult<Bundle> {
+        if url.contains("://") {
+            return Err(SdkError::Unsupported(format!(
+                "workspace member URL {url}: only local repositories and bundle files can be cloned"
+            )));
+        }
+        let path = self.workspace_root()?.join(url);
+        if path.is_file() {
+            Bundle::from_bytes(&std::fs::read(&path)?)
+        } else {
+            Wll::open(&path)?.create_bundle(&BundleOptions::new())
+        }
+    }
+}
+
+/// A member path must stay inside the work tree.
+fn check_member_path(path: &str) -> Result<(), String> {
+    let inside = Path::new(path)
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    if path.is_empty() || !inside {
+        return Err(format!("{path:?} is not a relative path inside the work tree"));
+    }
+    Ok(())
+}
+
+fn parse_hash(text: &str) -> Result<[u8; 32], String> {
+    hex::decode(text)
+        .map_err(|e| e.to_string())?
+        .try_into()
+        .map_err(|_| "expected 32 bytes".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(path: &Path, file: &str) -> Wll {
+        let wll = Wll::init_at(path).unwrap();
+        let mut index = wll.new_index();
+        index.stage_file(file, b"content", EntryMode::Regular).unwrap();
+        wll.commit_tree(&mut index, CommitProposal::new(file)).unwrap();
+        wll
+    }
+
+    #[test]
+    fn workspace_clones_members_and_moves_pins_in_one_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let billing = source(&dir.path().join("billing"), "invoice.rs");
+        let auth = source(&dir.path().join("auth"), "login.rs");
+        let bundle = auth.create_bundle(&BundleOptions::new()).unwrap();
+        std::fs::write(dir.path().join("auth.wllb"), bundle.to_bytes().unwrap()).unwrap();
+
+        let parent = Wll::init_at(dir.path().join("platform")).unwrap();
+        assert_eq!(parent.workspace().unwrap(), WorkspaceManifest::default());
+        parent.add_workspace_member("billing", "../billing", None, "main").unwrap();
+        parent
+            .add_workspace_member("auth", "../auth.wllb", Some("services/auth"), "main")
+            .unwrap();
+        assert!(parent.add_workspace_member("auth", "../auth", None, "main").is_err());
+        assert!(parent.add_workspace_member("x", "../billing", Some("../x"), "main").is_err());
+
+        let manifest = parent.workspace().unwrap();
+        assert_eq!(WorkspaceManifest::from_toml(&manifest.to_toml().unwrap()).unwrap(), manifest);
+        let pinned = manifest.member("billing").unwrap();
+        assert_eq!(&pinned.worldline, billing.worldline());
+        assert_eq!(Some(pinned.pin), billing.ref_tip("refs/heads/main").unwrap());
+        let states = |wll: &Wll| -> Vec<PinState> {
+            wll.workspace_status().unwrap().into_iter().map(|s| s.state).collect()
+        };
+        assert_eq!(states(&parent), vec![PinState::NotCloned, PinState::NotCloned]);
+
+        let cloned = parent.clone_workspace(&[]).unwrap();
+        assert_eq!(cloned.len(), 2);
+        assert!(dir.path().join("platform/services/auth/.wll").is_dir());
+        assert_eq!(states(&parent), vec![PinState::AtPin, PinState::AtPin]);
+        assert!(parent.clone_workspace(&[]).unwrap().is_empty());
+        assert!(parent.update_workspace_pins(&[], None).unwrap().is_none());
+
+        let checkout = Wll::open(dir.path().join("platform/billing")).unwrap();
+        let tip = checkout.commit(CommitProposal::new("next")).unwrap().receipt_hash;
+        assert_eq!(
+            states(&parent),
+            vec![PinState::AtPin, PinState::Ahead { receipts: 2 }]
+        );
+        assert!(parent.update_workspace_pins(&["nope".into()], None).is_err());
+
+        let before = parent.receipt_count().unwrap();
+        let result = parent.update_workspace_pins(&[], None).unwrap().unwrap();
+        assert_eq!(parent.receipt_count().unwrap(), before + 2);
+        let key = format!("{WORKSPACE_PIN_KEY_PREFIX}billing");
+        assert_eq!(result.outcome_receipt.metadata.get(&key), Some(&hex::encode(tip)));
+        assert_eq!(parent.workspace().unwrap().member("billing").unwrap().pin, tip);
+        assert_eq!(states(&parent), vec![PinState::AtPin, PinState::AtPin]);
+    }
+}
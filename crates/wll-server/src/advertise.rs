@@ -0,0 +1,139 @@
+//! Which refs a client is shown.
+//!
+//! A [`RefFilter`] applies the `[refs]` config to every ref listing the
+//! server answers. Hidden refs are left out for everyone, admins included,
+//! and an identity with a `[[refs.access]]` entry sees only the refs that
+//! entry allows. Listings are filtered before they leave the server, so a
+//! client cannot see or ask for a ref by guessing its name.
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use wll_protocol::ref_in_prefix;
+
+use crate::auth::Identity;
+use crate::config::RefsConfig;
+use crate::error::{ServerError, ServerResult};
+
+/// Ref visibility built from [`RefsConfig`].
+#[derive(Clone, Debug)]
+pub struct RefFilter {
+    hidden: GlobSet,
+    access: Vec<(String, GlobSet)>,
+}
+
+impl Default for RefFilter {
+    /// Every ref visible to every reader.
+    fn default() -> Self {
+        Self { hidden: GlobSet::empty(), access: Vec::new() }
+    }
+}
+
+impl RefFilter {
+    pub fn new(config: &RefsConfig) -> ServerResult<Self> {
+        let access = config
+            .access
+            .iter()
+            .map(|rule| Ok((rule.identity.clone(), ref_globs(&rule.allow)?)))
+            .collect::<ServerResult<_>>()?;
+        Ok(Self { hidden: ref_globs(&config.hidden)?, access })
+    }
+
+    /// A filter showing no ref to anyone.
+    pub fn hide_all() -> Self {
+        let hidden = ref_globs(&["**".to_string()]).expect("`**` is a valid glob");
+        Self { hidden, access: Vec::new() }
+    }
+
+    /// Whether `identity` may see and fetch ref `name`.
+    pub fn permits(&self, identity: &Identity, name: &str) -> bool {
+        if self.hidden.is_match(name) {
+            return false;
+        }
+        if identity.is_admin {
+            return true;
+        }
+        self.access
+            .iter()
+            .find(|(who, _)| *who == identity.name)
+            .map_or(true, |(_, allowed)| allowed.is_match(name))
+    }
+
+    /// The entries of `refs` shown to `identity`, narrowed to `prefix` as
+    /// a `ListRefsRequest`'s.
+    pub fn advertise<T>(
+        &self,
+        identity: &Identity,
+        refs: Vec<(String, T)>,
+        prefix: Option<&str>,
+    ) -> Vec<(String, T)> {
+        refs.into_iter()
+            .filter(|(name, _)| prefix.map_or(true, |prefix| ref_in_prefix(name, prefix)))
+            .filter(|(name, _)| self.permits(identity, name))
+            .collect()
+    }
+}
+
+fn ref_globs(patterns: &[String]) -> ServerResult<GlobSet> {
+    let mut set = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| ServerError::Config(format!("invalid ref pattern {pattern:?}: {e}")))?;
+        set.add(glob);
+    }
+    set.build().map_err(|e| ServerError::Config(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{RefAccess, ServerConfig};
+
+    fn refs() -> Vec<(String, u8)> {
+        ["refs/heads/main", "refs/heads/release/1.0", "refs/internal/ci/cache", "refs/tags/v1"]
+            .into_iter()
+            .map(|name| (name.to_string(), 0))
+            .collect()
+    }
+
+    fn names(refs: Vec<(String, u8)>) -> Vec<String> {
+        refs.into_iter().map(|(name, _)| name).collect()
+    }
+
+    #[test]
+    fn hidden_and_restricted_refs_are_left_out() {
+        let filter = RefFilter::new(&RefsConfig {
+            hidden: vec!["refs/internal/**".into()],
+            access: vec![RefAccess {
+                identity: "ci".into(),
+                allow: vec!["refs/heads/release/*".into(), "refs/tags/*".into()],
+            }],
+        })
+        .unwrap();
+
+        let everyone = names(filter.advertise(&Identity::user("alice"), refs(), None));
+        assert_eq!(everyone, ["refs/heads/main", "refs/heads/release/1.0", "refs/tags/v1"]);
+        let ci = names(filter.advertise(&Identity::user("ci"), refs(), None));
+        assert_eq!(ci, ["refs/heads/release/1.0", "refs/tags/v1"]);
+        let admin = Identity::admin("root");
+        assert!(!filter.permits(&admin, "refs/internal/ci/cache"));
+        assert!(filter.permits(&admin, "refs/heads/main"));
+
+        let one = filter.advertise(&Identity::user("ci"), refs(), Some("refs/heads/main"));
+        assert!(one.is_empty());
+        let one = names(filter.advertise(&Identity::anonymous(), refs(), Some("refs/heads/main")));
+        assert_eq!(one, ["refs/heads/main"]);
+        assert!(RefFilter::hide_all().advertise(&admin, refs(), None).is_empty());
+    }
+
+    #[test]
+    fn invalid_patterns_fail_config_loading() {
+        let err = ServerConfig::from_toml("[refs]\nhidden = [\"refs/{internal\"]\n").unwrap_err();
+        assert!(matches!(err, ServerError::Config(_)));
+        let config = ServerConfig::from_toml(
+            "[refs]\nhidden = [\"refs/internal/**\"]\n\n[[refs.access]]\nidentity = \"ci\"\nallow = [\"refs/tags/*\"]\n",
+        )
+        .unwrap();
+        assert_eq!(config.refs.access[0].identity, "ci");
+    }
+}
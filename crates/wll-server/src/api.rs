@@ -0,0 +1,469 @@
+//! Read-only JSON queries over a hosted repository.
+//!
+//! Dashboards and scripts that only read do not need the sync protocol:
+//! [`QueryApi`] answers plain `GET`s with the serde representations of
+//! ledger and ref types.
+//!
+//! - `GET /v1/worldlines` — every worldline with its head and receipt count
+//! - `GET /v1/worldlines/{id}/receipts?from=&to=&limit=` — one page of a
+//!   worldline's receipts by seq, with the `from` of the next page in `next`
+//! - `GET /v1/receipts/{hash}` — one receipt by hex hash
+//! - `GET /v1/refs?prefix=` — refs by name, narrowed to one ref or
+//!   namespace by `prefix`
+//! - `POST /v1/info/refs` — a framed `ListRefsRequest`, answered with a
+//!   framed `ListRefsResponse` carrying the server's ledger position
+//!
+//! - `POST /v1/graphql` — nested queries over the same data and the
+//!   provenance DAG, with the `graphql` feature (see the `graphql` module)
+//!
+//! Each request authenticates its `Authorization: Bearer` token, or goes on
+//! anonymously without one, and must be authorized for
+//! [`Action::Read`] on the repository. Ref listings only hold the refs the
+//! [`RefFilter`] shows the caller.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use axum::body::Bytes;
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap};
+use axum::response::{IntoResponse, Json};
+use axum::routing::{get, post};
+use axum::Router;
+use serde::{Deserialize, Serialize};
+use wll_ledger::{Ledger, LedgerError, Receipt, ReceiptRef};
+use wll_protocol::{
+    endpoints, ConsistencyToken, WllCodec, WllMessage, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE,
+};
+use wll_refs::{Ref, RefStore};
+use wll_types::{DecodeLimits, ErrorCode, WorldlineId};
+
+use crate::advertise::RefFilter;
+use crate::auth::{authorize_request, Action, AuthProvider, Identity};
+#[cfg(feature = "graphql")]
+use crate::config::GraphqlConfig;
+use crate::error::{ServerError, ServerResult};
+#[cfg(feature = "graphql")]
+use crate::graphql::{DagSource, Graphql};
+
+/// A worldline in the `/v1/worldlines` listing.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorldlineSummary {
+    pub worldline: WorldlineId,
+    pub head: Option<ReceiptRef>,
+    /// Live receipts; archived ones are not counted.
+    pub receipt_count: u64,
+}
+
+/// Query string of `/v1/worldlines/{id}/receipts`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ReceiptRange {
+    /// First seq, inclusive; defaults to the first live receipt.
+    pub from: Option<u64>,
+    /// Last seq, inclusive; defaults to the head.
+    pub to: Option<u64>,
+    /// Page size: `DEFAULT_PAGE_SIZE` when absent or 0, at most
+    /// `MAX_PAGE_SIZE`.
+    pub limit: Option<u32>,
+}
+
+/// One page of a worldline's receipts, oldest first.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReceiptsPage {
+    pub worldline: WorldlineId,
+    pub receipts: Vec<Receipt>,
+    /// `from` of the next page, if the range holds more receipts.
+    pub next: Option<u64>,
+}
+
+/// Read-only query endpoints for one repository.
+#[derive(Clone)]
+pub struct QueryApi {
+    repo: String,
+    ledger: Arc<dyn Ledger>,
+    refs: Arc<dyn RefStore>,
+    auth: Arc<dyn AuthProvider>,
+    filter: RefFilter,
+    limits: DecodeLimits,
+    #[cfg(feature = "graphql")]
+    pub(crate) graphql: Option<Graphql>,
+}
+
+impl QueryApi {
+    /// Queries over `ledger` and `refs`, authorized as reads of `repo`.
+    pub fn new(
+        repo: impl Into<String>,
+        ledger: Arc<dyn Ledger>,
+        refs: Arc<dyn RefStore>,
+        auth: Arc<dyn AuthProvider>,
+    ) -> Self {
+        Self {
+            repo: repo.into(),
+            ledger,
+            refs,
+            auth,
+            filter: RefFilter::default(),
+            limits: DecodeLimits::DEFAULT,
+            #[cfg(feature = "graphql")]
+            graphql: None,
+        }
+    }
+
+    pub fn repo(&self) -> &str {
+        &self.repo
+    }
+
+    /// The refs under `prefix` that `identity` may see, by name.
+    fn visible_refs(
+        &self,
+        identity: &Identity,
+        prefix: Option<&str>,
+    ) -> ServerResult<Vec<(String, Ref)>> {
+        Ok(self.filter.advertise(identity, self.refs.list_refs("")?, prefix))
+    }
+
+    /// The ledger's position: every worldline's head.
+    fn position(&self) -> ServerResult<ConsistencyToken> {
+        let mut token = ConsistencyToken::new();
+        for worldline in self.ledger.worldlines()? {
+            if let Some(head) = self.ledger.head(&worldline)? {
+                token = token.with_position(worldline, head.seq, head.receipt_hash);
+            }
+        }
+        Ok(token)
+    }
+
+    /// Replace the auth provider.
+    pub fn with_auth(mut self, auth: Arc<dyn AuthProvider>) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// Show each caller only the refs `filter` permits it.
+    pub fn with_ref_filter(mut self, filter: RefFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Refuse protocol requests beyond `limits`.
+    pub fn with_decode_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Also answer `POST /v1/graphql`, reading the provenance DAG from
+    /// `dag` once per query and refusing queries beyond `limits`.
+    #[cfg(feature = "graphql")]
+    pub fn with_graphql(mut self, dag: DagSource, limits: GraphqlConfig) -> Self {
+        self.graphql = Some(Graphql::new(self.ledger.clone(), dag, limits));
+        self
+    }
+
+    /// The query routes, to merge into the server's router.
+    pub fn router(self) -> Router {
+        let router = Router::new()
+            .route("/v1/worldlines", get(worldlines))
+            .route("/v1/worldlines/:id/receipts", get(receipts))
+            .route("/v1/receipts/:hash", get(receipt))
+            .route("/v1/refs", get(refs))
+            .route(endpoints::INFO_REFS, post(info_refs));
+        #[cfg(feature = "graphql")]
+        let router = match self.graphql {
+            Some(_) => router.route("/v1/graphql", post(crate::graphql::execute)),
+            None => router,
+        };
+        router.with_state(Arc::new(self))
+    }
+
+    /// The caller of a request with `headers`, if it may read the
+    /// repository.
+    pub(crate) async fn reader(&self, headers: &HeaderMap) -> ServerResult<Identity> {
+        let action = Action::Read { repo: self.repo.clone() };
+        authorize_request(self.auth.as_ref(), headers, action).await
+    }
+}
+
+/// The page of `worldline`'s receipts `range` asks for.
+pub(crate) fn receipt_page(
+    ledger: &dyn Ledger,
+    worldline: WorldlineId,
+    range: &ReceiptRange,
+) -> ServerResult<ReceiptsPage> {
+    let head = ledger.head(&worldline)?.ok_or(LedgerError::WorldlineNotFound)?;
+    let first = ledger.archived_through(&worldline)?.map_or(1, |base| base.seq + 1);
+    let from = range.from.unwrap_or(first).max(first);
+    let to = range.to.unwrap_or(head.seq).min(head.seq);
+    if let (Some(from), Some(to)) = (range.from, range.to) {
+        if from > to {
+            return Err(LedgerError::InvalidRange { from, to }.into());
+        }
+    }
+    let limit = match range.limit {
+        None | Some(0) => DEFAULT_PAGE_SIZE,
+        Some(limit) => limit.min(MAX_PAGE_SIZE),
+    };
+    if from > to {
+        return Ok(ReceiptsPage { worldline, receipts: Vec::new(), next: None });
+    }
+    let last = to.min(from.saturating_add(u64::from(limit) - 1));
+    let receipts = ledger.read_range(&worldline, from, last)?;
+    let next = (last < to).then_some(last + 1);
+    Ok(ReceiptsPage { worldline, receipts, next })
+}
+
+type Api = State<Arc<QueryApi>>;
+
+async fn worldlines(
+    State(api): Api,
+    headers: HeaderMap,
+) -> ServerResult<Json<Vec<WorldlineSummary>>> {
+    api.reader(&headers).await?;
+    let summaries = api
+        .ledger
+        .worldlines()?
+        .into_iter()
+        .map(|worldline| {
+            Ok(WorldlineSummary {
+                head: api.ledger.head(&worldline)?,
+                receipt_count: api.ledger.receipt_count(&worldline)?,
+                worldline,
+            })
+        })
+        .collect::<Result<_, LedgerError>>()?;
+    Ok(Json(summaries))
+}
+
+async fn receipts(
+    State(api): Api,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Query(range): Query<ReceiptRange>,
+) -> ServerResult<Json<ReceiptsPage>> {
+    api.reader(&headers).await?;
+    let worldline = WorldlineId::from_hex(&id)
+        .map_err(|e| ServerError::InvalidRequest(format!("worldline {id}: {e}")))?;
+    Ok(Json(receipt_page(api.ledger.as_ref(), worldline, &range)?))
+}
+
+async fn receipt(
+    State(api): Api,
+    headers: HeaderMap,
+    Path(hash): Path<String>,
+) -> ServerResult<Json<Receipt>> {
+    api.reader(&headers).await?;
+    let bytes = hex::decode(&hash)
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or_else(|| ServerError::InvalidRequest(format!("not a receipt hash: {hash}")))?;
+    let receipt = api.ledger.get_by_hash(bytes)?;
+    receipt.map(Json).ok_or(ServerError::ReceiptNotFound(hash))
+}
+
+/// Query string of `/v1/refs`.
+#[derive(Debug, Default, Deserialize)]
+struct RefsQuery {
+    prefix: Option<String>,
+}
+
+async fn refs(
+    State(api): Api,
+    headers: HeaderMap,
+    Query(query): Query<RefsQuery>,
+) -> ServerResult<Json<BTreeMap<String, Ref>>> {
+    let identity = api.reader(&headers).await?;
+    let refs = api.visible_refs(&identity, query.prefix.as_deref())?;
+    Ok(Json(refs.into_iter().collect()))
+}
+
+async fn info_refs(
+    State(api): Api,
+    headers: HeaderMap,
+    body: Bytes,
+) -> ServerResult<impl IntoResponse> {
+    let identity = api.reader(&headers).await?;
+    let (request, _) = WllCodec::decode_with_limits(&body, &api.limits)?;
+    let WllMessage::ListRefsRequest { prefix, min_position } = request else {
+        return Err(ServerError::InvalidRequest(format!(
+            "expected ListRefsRequest, got {}",
+            request.type_name()
+        )));
+    };
+    let position = api.position()?;
+    let answer = match min_position.filter(|required| !position.covers(required)) {
+        Some(required) => {
+            let lagging: Vec<String> =
+                position.lagging(&required).iter().map(ToString::to_string).collect();
+            WllMessage::Error {
+                code: ErrorCode::ReplicaBehind.number().into(),
+                message: format!("behind on {}", lagging.join(", ")),
+            }
+        }
+        None => {
+            let refs = api
+                .visible_refs(&identity, prefix.as_deref())?
+                .into_iter()
+                .map(|(name, target)| (name, *target.target_hash()))
+                .collect();
+            WllMessage::ListRefsResponse { refs, consistency: Some(position) }
+        }
+    };
+    let frame = WllCodec::encode(&answer)?;
+    Ok(([(header::CONTENT_TYPE, "application/octet-stream")], frame))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use serde::de::DeserializeOwned;
+    use tower::util::ServiceExt;
+    use wll_ledger::{
+        CommitmentProposal, Decision, EvidenceBundle, InMemoryLedger, LedgerWriter,
+    };
+    use wll_refs::InMemoryRefStore;
+    use wll_types::{CommitmentClass, CommitmentId, IdentityMaterial};
+
+    use super::*;
+    use crate::auth::{TokenAuth, TokenGrant};
+    use crate::config::RefsConfig;
+    use crate::error::Problem;
+
+    fn worldline() -> WorldlineId {
+        WorldlineId::derive(&IdentityMaterial::GenesisHash([5; 32]))
+    }
+
+    fn api(receipts: u64) -> QueryApi {
+        let ledger = InMemoryLedger::default();
+        let mut head = [0; 32];
+        for nonce in 0..receipts {
+            let proposal = CommitmentProposal {
+                worldline: worldline(),
+                commitment_id: CommitmentId::new(),
+                class: CommitmentClass::ReadOnly,
+                intent: format!("read {nonce}"),
+                requested_caps: vec![],
+                targets: vec![worldline()],
+                evidence: EvidenceBundle::empty(),
+                nonce,
+            };
+            let receipt = ledger.append_commitment(&proposal, &Decision::Accepted, [0; 32]);
+            head = receipt.unwrap().receipt_hash;
+        }
+        let refs = InMemoryRefStore::new();
+        let main = Ref::Branch { name: "main".into(), worldline: worldline(), receipt_hash: head };
+        refs.write_ref("refs/heads/main", &main).unwrap();
+        let grant = TokenGrant { token: "dash".into(), name: "dashboard".into(), admin: false };
+        let auth = Arc::new(TokenAuth::new(vec![grant], false));
+        QueryApi::new("acme/site", Arc::new(ledger), Arc::new(refs), auth)
+    }
+
+    async fn get<T: DeserializeOwned>(api: QueryApi, uri: &str) -> Result<T, Problem> {
+        let request = Request::builder()
+            .uri(uri)
+            .header(header::AUTHORIZATION, "Bearer dash")
+            .body(Body::empty())
+            .unwrap();
+        let response = api.router().oneshot(request).await.unwrap();
+        let ok = response.status() == StatusCode::OK;
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        if ok {
+            Ok(serde_json::from_slice(&body).unwrap())
+        } else {
+            Err(serde_json::from_slice(&body).unwrap())
+        }
+    }
+
+    #[tokio::test]
+    async fn receipts_are_paged_by_seq_and_found_by_hash() {
+        let id = worldline().to_hex();
+        let api = api(5);
+        let listed: Vec<WorldlineSummary> = get(api.clone(), "/v1/worldlines").await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].receipt_count, 5);
+
+        let uri = format!("/v1/worldlines/{id}/receipts?from=2&limit=2");
+        let page: ReceiptsPage = get(api.clone(), &uri).await.unwrap();
+        let seqs: Vec<u64> = page.receipts.iter().map(Receipt::seq).collect();
+        assert_eq!(seqs, [2, 3]);
+        assert_eq!(page.next, Some(4));
+        let uri = format!("/v1/worldlines/{id}/receipts?from=4&to=9");
+        let page: ReceiptsPage = get(api.clone(), &uri).await.unwrap();
+        assert_eq!(page.receipts.len(), 2);
+        assert_eq!(page.next, None);
+
+        let refs: BTreeMap<String, Ref> = get(api.clone(), "/v1/refs").await.unwrap();
+        let Ref::Branch { receipt_hash, .. } = &refs["refs/heads/main"] else { panic!() };
+        let uri = format!("/v1/receipts/{}", hex::encode(receipt_hash));
+        let head: Receipt = get(api.clone(), &uri).await.unwrap();
+        assert_eq!(head.seq(), 5);
+
+        let missing = format!("/v1/receipts/{}", hex::encode([9; 32]));
+        let problem = get::<Receipt>(api.clone(), &missing).await.unwrap_err();
+        assert_eq!(problem.status, 404);
+        let bad = "/v1/worldlines/zz/receipts";
+        let problem = get::<ReceiptsPage>(api.clone(), bad).await.unwrap_err();
+        assert_eq!(problem.status, 400);
+    }
+
+    #[tokio::test]
+    async fn ref_listings_leave_out_what_the_caller_may_not_see() {
+        let config = RefsConfig { hidden: vec!["refs/internal/**".into()], access: vec![] };
+        let api = api(2).with_ref_filter(RefFilter::new(&config).unwrap());
+        let main = api.refs.read_ref("refs/heads/main").unwrap().unwrap();
+        for name in ["refs/heads/dev", "refs/internal/ci/cache"] {
+            api.refs.write_ref(name, &main).unwrap();
+        }
+
+        let refs: BTreeMap<String, Ref> = get(api.clone(), "/v1/refs").await.unwrap();
+        assert_eq!(refs.keys().collect::<Vec<_>>(), ["refs/heads/dev", "refs/heads/main"]);
+        let uri = "/v1/refs?prefix=refs/heads/main";
+        let refs: BTreeMap<String, Ref> = get(api.clone(), uri).await.unwrap();
+        assert_eq!(refs.keys().collect::<Vec<_>>(), ["refs/heads/main"]);
+
+        let list = |prefix: Option<&str>, min_position| {
+            let message = WllMessage::ListRefsRequest {
+                prefix: prefix.map(str::to_string),
+                min_position,
+            };
+            Request::post(endpoints::INFO_REFS)
+                .header(header::AUTHORIZATION, "Bearer dash")
+                .body(Body::from(WllCodec::encode(&message).unwrap()))
+                .unwrap()
+        };
+        let answer = |request: Request<Body>| async {
+            let response = api.clone().router().oneshot(request).await.unwrap();
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            WllCodec::decode(&body).unwrap().0
+        };
+        let WllMessage::ListRefsResponse { refs, consistency } =
+            answer(list(Some("refs/internal"), None)).await
+        else {
+            panic!("expected ListRefsResponse");
+        };
+        assert!(refs.is_empty());
+        assert_eq!(consistency.unwrap().position(&worldline()).unwrap().seq, 2);
+
+        let ahead = ConsistencyToken::new().with_position(worldline(), 3, [3; 32]);
+        let WllMessage::Error { code, .. } = answer(list(None, Some(ahead))).await else {
+            panic!("expected Error");
+        };
+        assert_eq!(code, u32::from(ErrorCode::ReplicaBehind.number()));
+    }
+
+    #[tokio::test]
+    async fn reads_need_an_authorized_caller() {
+        let request = |token: Option<&str>| {
+            let mut request = Request::builder().uri("/v1/worldlines");
+            if let Some(token) = token {
+                request = request.header(header::AUTHORIZATION, format!("Bearer {token}"));
+            }
+            request.body(Body::empty()).unwrap()
+        };
+        let status = |token| async move {
+            api(1).router().oneshot(request(token)).await.unwrap().status()
+        };
+        assert_eq!(status(None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(Some("wrong")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(Some("dash")).await, StatusCode::OK);
+    }
+}
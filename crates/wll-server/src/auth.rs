@@ -1,21 +1,49 @@
 use async_trait::async_trait;
-use crate::error::ServerResult;
+use axum::http::{header, HeaderMap};
+use serde::{Deserialize, Serialize};
+use wll_crypto::Secret;
+use wll_types::{IdentityAttestation, WorldlineId};
+use crate::error::{ServerError, ServerResult};
+use crate::oidc::VerifiedToken;
 
 #[derive(Clone, Debug)]
 pub struct Identity {
     pub name: String,
     pub is_admin: bool,
+    /// Whether the caller went unauthenticated. Only [`Identity::anonymous`]
+    /// sets it, so no authenticated name is ever mistaken for it.
+    pub anonymous: bool,
+    /// Verified OIDC claims, when authenticated by [`crate::OidcAuth`].
+    pub oidc: Option<VerifiedToken>,
 }
 
 impl Identity {
-    pub fn anonymous() -> Self { Self { name: "anonymous".into(), is_admin: false } }
-    pub fn user(name: impl Into<String>) -> Self { Self { name: name.into(), is_admin: false } }
-    pub fn admin(name: impl Into<String>) -> Self { Self { name: name.into(), is_admin: true } }
+    pub fn anonymous() -> Self { Self { name: "anonymous".into(), is_admin: false, anonymous: true, oidc: None } }
+    pub fn user(name: impl Into<String>) -> Self { Self { name: name.into(), is_admin: false, anonymous: false, oidc: None } }
+    pub fn admin(name: impl Into<String>) -> Self { Self { name: name.into(), is_admin: true, anonymous: false, oidc: None } }
+
+    /// Bind this identity's verified OIDC claims to `worldline`.
+    pub fn attestation(&self, worldline: WorldlineId) -> Option<IdentityAttestation> {
+        self.oidc.as_ref().map(|token| token.attest(worldline))
+    }
+
+    /// Default permissions: admins may do anything, authenticated users may
+    /// read and write, and anonymous clients may read if allowed.
+    pub(crate) fn may(&self, action: &Action, allow_anonymous_read: bool) -> bool {
+        if self.is_admin {
+            return true;
+        }
+        match action {
+            Action::Read { .. } => !self.anonymous || allow_anonymous_read,
+            Action::Write { .. } => !self.anonymous,
+            Action::Admin { .. } | Action::CreateRepo => false,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
 pub enum Credentials {
-    Bearer(String),
+    Bearer(Secret),
     Anonymous,
 }
 
@@ -44,19 +72,99 @@ pub trait AuthProvider: Send + Sync {
     async fn authorize(&self, identity: &Identity, action: &Action) -> ServerResult<bool>;
 }
 
+/// The caller of a request with `headers`, if `auth` lets it take
+/// `action`. The `Authorization: Bearer` token authenticates it, or it goes
+/// on anonymously without one; anonymous callers refused are asked to
+/// authenticate rather than denied.
+pub(crate) async fn authorize_request(
+    auth: &dyn AuthProvider,
+    headers: &HeaderMap,
+    action: Action,
+) -> ServerResult<Identity> {
+    let credentials = match headers.get(header::AUTHORIZATION) {
+        None => Credentials::Anonymous,
+        Some(value) => value
+            .to_str()
+            .ok()
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(|token| Credentials::Bearer(token.trim().into()))
+            .ok_or_else(|| ServerError::AuthFailed("expected a bearer token".into()))?,
+    };
+    let identity = auth.authenticate(&credentials).await?;
+    if auth.authorize(&identity, &action).await? {
+        Ok(identity)
+    } else if matches!(credentials, Credentials::Anonymous) {
+        Err(ServerError::AuthFailed("authentication required".into()))
+    } else {
+        let repo = match &action {
+            Action::Read { repo } | Action::Write { repo } | Action::Admin { repo } => repo.clone(),
+            Action::CreateRepo => String::new(),
+        };
+        Err(ServerError::AuthorizationDenied { repo, action: action.to_string() })
+    }
+}
+
+/// Lets every caller do anything, as the fixed anonymous identity. Tokens
+/// are not checked, so none of their bytes end up in identity names or logs.
 pub struct AllowAllAuth;
 
 #[async_trait]
 impl AuthProvider for AllowAllAuth {
+    async fn authenticate(&self, _credentials: &Credentials) -> ServerResult<Identity> {
+        Ok(Identity::anonymous())
+    }
+
+    async fn authorize(&self, _identity: &Identity, _action: &Action) -> ServerResult<bool> {
+        Ok(true)
+    }
+}
+
+/// A bearer token and the identity it authenticates as.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TokenGrant {
+    pub token: Secret,
+    pub name: String,
+    #[serde(default)]
+    pub admin: bool,
+}
+
+/// Authenticates bearer tokens against a fixed list.
+///
+/// A token is compared with every grant in constant time, so how long the
+/// check takes says nothing about how much of a token matched.
+/// Authenticated users may read and write; creating repositories and admin
+/// actions need an admin token. Anonymous clients may only read, and only
+/// when `allow_anonymous_read` is set.
+pub struct TokenAuth {
+    grants: Vec<TokenGrant>,
+    allow_anonymous_read: bool,
+}
+
+impl TokenAuth {
+    pub fn new(grants: Vec<TokenGrant>, allow_anonymous_read: bool) -> Self {
+        Self { grants, allow_anonymous_read }
+    }
+}
+
+#[async_trait]
+impl AuthProvider for TokenAuth {
     async fn authenticate(&self, credentials: &Credentials) -> ServerResult<Identity> {
         match credentials {
-            Credentials::Bearer(token) => Ok(Identity::user(format!("bearer:{}", &token[..8.min(token.len())]))),
+            Credentials::Bearer(token) => self
+                .grants
+                .iter()
+                .fold(None, |found, grant| match grant.token == *token {
+                    true => found.or(Some(grant)),
+                    false => found,
+                })
+                .map(|grant| if grant.admin { Identity::admin(&grant.name) } else { Identity::user(&grant.name) })
+                .ok_or_else(|| ServerError::AuthFailed("unknown bearer token".into())),
             Credentials::Anonymous => Ok(Identity::anonymous()),
         }
     }
 
-    async fn authorize(&self, _identity: &Identity, _action: &Action) -> ServerResult<bool> {
-        Ok(true)
+    async fn authorize(&self, identity: &Identity, action: &Action) -> ServerResult<bool> {
+        Ok(identity.may(action, self.allow_anonymous_read))
     }
 }
 
@@ -68,7 +176,7 @@ mod tests {
     fn identity_variants() {
         let a = Identity::anonymous();
         assert_eq!(a.name, "anonymous");
-        assert!(!a.is_admin);
+        assert!(a.anonymous && !a.is_admin);
 
         let u = Identity::user("alice");
         assert_eq!(u.name, "alice");
@@ -89,6 +197,7 @@ mod tests {
         let auth = AllowAllAuth;
         let id = auth.authenticate(&Credentials::Anonymous).await.unwrap();
         assert_eq!(id.name, "anonymous");
+        assert!(id.anonymous);
         assert!(auth.authorize(&id, &Action::CreateRepo).await.unwrap());
     }
 
@@ -96,6 +205,43 @@ mod tests {
     async fn allow_all_bearer() {
         let auth = AllowAllAuth;
         let id = auth.authenticate(&Credentials::Bearer("mytoken123".into())).await.unwrap();
-        assert!(id.name.starts_with("bearer:"));
+        assert_eq!(id.name, "anonymous");
+        assert!(id.anonymous);
+    }
+
+    #[tokio::test]
+    async fn token_auth_checks_grants() {
+        let auth = TokenAuth::new(
+            vec![
+                TokenGrant { token: "user-token".into(), name: "alice".into(), admin: false },
+                TokenGrant { token: "admin-token".into(), name: "root".into(), admin: true },
+                TokenGrant { token: "named-token".into(), name: "anonymous".into(), admin: false },
+            ],
+            false,
+        );
+        let read = Action::Read { repo: "r".into() };
+        let write = Action::Write { repo: "r".into() };
+
+        let alice = auth.authenticate(&Credentials::Bearer("user-token".into())).await.unwrap();
+        assert_eq!(alice.name, "alice");
+        assert!(auth.authorize(&alice, &write).await.unwrap());
+        assert!(!auth.authorize(&alice, &Action::CreateRepo).await.unwrap());
+
+        let root = auth.authenticate(&Credentials::Bearer("admin-token".into())).await.unwrap();
+        assert!(auth.authorize(&root, &Action::CreateRepo).await.unwrap());
+
+        let anon = auth.authenticate(&Credentials::Anonymous).await.unwrap();
+        assert!(!auth.authorize(&anon, &read).await.unwrap());
+        // A user who happens to be named "anonymous" is still authenticated.
+        let named = auth.authenticate(&Credentials::Bearer("named-token".into())).await.unwrap();
+        assert!(!named.anonymous);
+        assert!(auth.authorize(&named, &write).await.unwrap());
+        for wrong in ["nope", "user-tok", "user-token2"] {
+            assert!(matches!(
+                auth.authenticate(&Credentials::Bearer(wrong.into())).await,
+                Err(ServerError::AuthFailed(_))
+            ));
+        }
+        assert!(!format!("{:?}", auth.grants).contains("user-token"));
     }
 }
@@ -0,0 +1,912 @@
+//! Several servers over shared storage, one primary per worldline.
+//!
+//! The nodes of a cluster share object storage and each answers reads.
+//! Appends to a worldline go through its primary: the node holding the
+//! worldline's [`Lease`] in the cluster's [`Coordinator`]. A node takes a
+//! lease that is free or expired, renews the ones it holds every third of
+//! `lease_ttl_secs`, and forwards a write for a worldline another node
+//! holds to that node. When a primary fails, its leases expire and the
+//! next node to renew or route a write for those worldlines takes them.
+//!
+//! Every change of holder raises the lease's epoch. A write handler gets
+//! the epoch it was routed under as a [`Primary`] request extension and
+//! calls [`Cluster::fence`] before committing, and the ledger writes go
+//! through a [`FencedLedger`] that checks the lease again on every append,
+//! so a node that stalled past its lease cannot extend a receipt chain the
+//! new primary is extending too. Expiry compares the nodes' [`Clock`]s,
+//! which must agree to well within the lease TTL.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use axum::body::{to_bytes, Body};
+use axum::extract::{Request, State};
+use axum::http::{HeaderName, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+use wll_crypto::SigningKey;
+use wll_ledger::{
+    CommitmentProposal, CommitmentReceipt, Compaction, CompactionReceipt, Decision, Ledger,
+    LedgerError, LedgerReader, LedgerWriter, OutcomeReceipt, OutcomeRecord, Receipt, ReceiptRef,
+    SchemaRegistry, SnapshotInput, SnapshotReceipt, StateSchema,
+};
+use wll_protocol::endpoints;
+use wll_types::{Clock, SystemClock, WorldlineId};
+
+use crate::config::ClusterConfig;
+use crate::error::{ServerError, ServerResult};
+
+/// Header naming the worldline a write appends to, as hex. Writes without
+/// it go to the cluster's default worldline.
+pub const WORLDLINE_HEADER: &str = "wll-worldline";
+/// Header a node sets on the writes it forwards, naming itself. A node
+/// that is not the primary refuses such a write rather than forwarding it
+/// again.
+pub const FORWARDED_BY_HEADER: &str = "wll-forwarded-by";
+
+/// Routes whose requests append to a worldline.
+const WRITE_PATHS: &[&str] = &[endpoints::PUSH];
+/// How long [`FileCoordinator`] waits for another node's lock.
+const LOCK_WAIT: Duration = Duration::from_secs(2);
+/// Age after which a lock file is taken to belong to a crashed node.
+const LOCK_STALE: Duration = Duration::from_secs(10);
+
+/// The right of one node to append to one worldline until `expires_ms`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lease {
+    pub worldline: WorldlineId,
+    pub holder: String,
+    /// Base URL the holder takes forwarded writes at.
+    pub address: String,
+    /// Raised each time the lease changes hands.
+    pub epoch: u64,
+    pub expires_ms: u64,
+}
+
+impl Lease {
+    pub fn is_live(&self, now_ms: u64) -> bool {
+        self.expires_ms > now_ms
+    }
+
+    /// The lease after `holder` asks for it at `now_ms`: renewed if it is
+    /// theirs, taken under the next epoch if it is free or expired, and
+    /// unchanged if another holder's is live.
+    fn claimed(
+        current: Option<Lease>,
+        worldline: &WorldlineId,
+        holder: &str,
+        address: &str,
+        ttl: Duration,
+        now_ms: u64,
+    ) -> Lease {
+        let expires_ms = now_ms + ttl.as_millis() as u64;
+        match current {
+            Some(lease) if lease.holder == holder && lease.is_live(now_ms) => {
+                Lease { expires_ms, address: address.to_string(), ..lease }
+            }
+            Some(lease) if lease.is_live(now_ms) => lease,
+            current => Lease {
+                worldline: worldline.clone(),
+                holder: holder.to_string(),
+                address: address.to_string(),
+                epoch: current.map_or(1, |lease| lease.epoch + 1),
+                expires_ms,
+            },
+        }
+    }
+}
+
+/// Where worldline leases are kept. Each call must be atomic across every
+/// node of the cluster.
+pub trait Coordinator: Send + Sync {
+    /// The last lease taken on `worldline`, live or not.
+    fn lease(&self, worldline: &WorldlineId) -> ServerResult<Option<Lease>>;
+
+    /// Take or renew `worldline`'s lease for `holder` for `ttl` from
+    /// `now_ms`, unless another holder's is live. Returns the lease in
+    /// force afterwards.
+    fn acquire(
+        &self,
+        worldline: &WorldlineId,
+        holder: &str,
+        address: &str,
+        ttl: Duration,
+        now_ms: u64,
+    ) -> ServerResult<Lease>;
+
+    /// End `holder`'s lease on `worldline` now, so another node can take
+    /// it without waiting for it to expire.
+    fn release(&self, worldline: &WorldlineId, holder: &str) -> ServerResult<()>;
+}
+
+/// Leases held in memory, for nodes sharing one process and for tests.
+#[derive(Debug, Default)]
+pub struct MemoryCoordinator {
+    leases: Mutex<HashMap<WorldlineId, Lease>>,
+}
+
+impl MemoryCoordinator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Coordinator for MemoryCoordinator {
+    fn lease(&self, worldline: &WorldlineId) -> ServerResult<Option<Lease>> {
+        Ok(self.leases.lock().unwrap_or_else(|e| e.into_inner()).get(worldline).cloned())
+    }
+
+    fn acquire(
+        &self,
+        worldline: &WorldlineId,
+        holder: &str,
+        address: &str,
+        ttl: Duration,
+        now_ms: u64,
+    ) -> ServerResult<Lease> {
+        let mut leases = self.leases.lock().unwrap_or_else(|e| e.into_inner());
+        let lease =
+            Lease::claimed(leases.remove(worldline), worldline, holder, address, ttl, now_ms);
+        leases.insert(worldline.clone(), lease.clone());
+        Ok(lease)
+    }
+
+    fn release(&self, worldline: &WorldlineId, holder: &str) -> ServerResult<()> {
+        let mut leases = self.leases.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(lease) = leases.get_mut(worldline).filter(|lease| lease.holder == holder) {
+            lease.expires_ms = 0;
+        }
+        Ok(())
+    }
+}
+
+/// Leases kept as JSON files in a directory every node can reach, such as
+/// one on the shared object storage. Each change is made under a lock file
+/// created exclusively, and written through a rename.
+#[derive(Clone, Debug)]
+pub struct FileCoordinator {
+    dir: PathBuf,
+}
+
+impl FileCoordinator {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn lease_path(&self, worldline: &WorldlineId) -> PathBuf {
+        self.dir.join(format!("{}.json", worldline.to_hex()))
+    }
+
+    /// Replace `worldline`'s lease with `change` applied to it, holding
+    /// its lock throughout.
+    fn update(
+        &self,
+        worldline: &WorldlineId,
+        change: impl FnOnce(Option<Lease>) -> Option<Lease>,
+    ) -> ServerResult<Option<Lease>> {
+        fs::create_dir_all(&self.dir)?;
+        let _lock = LockFile::acquire(self.dir.join(format!("{}.lock", worldline.to_hex())))?;
+        let path = self.lease_path(worldline);
+        let Some(lease) = change(read_lease(&path)?) else {
+            return Ok(None);
+        };
+        let json = serde_json::to_vec(&lease).map_err(|e| ServerError::Internal(e.to_string()))?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, json)?;
+        fs::File::open(&tmp)?.sync_all()?;
+        fs::rename(&tmp, &path)?;
+        Ok(Some(lease))
+    }
+}
+
+impl Coordinator for FileCoordinator {
+    fn lease(&self, worldline: &WorldlineId) -> ServerResult<Option<Lease>> {
+        read_lease(&self.lease_path(worldline))
+    }
+
+    fn acquire(
+        &self,
+        worldline: &WorldlineId,
+        holder: &str,
+        address: &str,
+        ttl: Duration,
+        now_ms: u64,
+    ) -> ServerResult<Lease> {
+        let lease = self.update(worldline, |current| {
+            Some(Lease::claimed(current, worldline, holder, address, ttl, now_ms))
+        })?;
+        lease.ok_or_else(|| ServerError::Internal("lease update wrote nothing".into()))
+    }
+
+    fn release(&self, worldline: &WorldlineId, holder: &str) -> ServerResult<()> {
+        self.update(worldline, |current| {
+            current
+                .filter(|lease| lease.holder == holder)
+                .map(|lease| Lease { expires_ms: 0, ..lease })
+        })?;
+        Ok(())
+    }
+}
+
+fn read_lease(path: &Path) -> ServerResult<Option<Lease>> {
+    let mut json = Vec::new();
+    match fs::File::open(path) {
+        Ok(mut file) => file.read_to_end(&mut json)?,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    serde_json::from_slice(&json)
+        .map(Some)
+        .map_err(|e| ServerError::Internal(format!("lease {}: {e}", path.display())))
+}
+
+/// An exclusively created file, removed when dropped.
+struct LockFile(PathBuf);
+
+impl LockFile {
+    fn acquire(path: PathBuf) -> ServerResult<Self> {
+        let started = SystemTime::now();
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(Self(path)),
+                Err(e) if e.kind() != ErrorKind::AlreadyExists => return Err(e.into()),
+                Err(_) => {}
+            }
+            let age = fs::metadata(&path)
+                .and_then(|meta| meta.modified())
+                .map(|modified| modified.elapsed().unwrap_or_default());
+            if age.is_ok_and(|age| age > LOCK_STALE) {
+                tracing::warn!("breaking stale lease lock {}", path.display());
+                let _ = fs::remove_file(&path);
+                continue;
+            }
+            if started.elapsed().unwrap_or_default() > LOCK_WAIT {
+                return Err(std::io::Error::new(
+                    ErrorKind::TimedOut,
+                    format!("lease lock {} is held", path.display()),
+                )
+                .into());
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+impl Drop for LockFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// A request forwarded to a worldline's primary.
+#[derive(Clone, Debug)]
+pub struct ForwardedRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+/// The primary's answer to a [`ForwardedRequest`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ForwardedResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+/// Sends forwarded writes to the primary. Calls run on a blocking thread.
+pub trait Forwarder: Send + Sync {
+    fn forward(
+        &self,
+        request: ForwardedRequest,
+        timeout: Duration,
+    ) -> Result<ForwardedResponse, String>;
+}
+
+/// [`Forwarder`] over `ureq`.
+pub struct HttpForwarder;
+
+impl Forwarder for HttpForwarder {
+    fn forward(
+        &self,
+        request: ForwardedRequest,
+        timeout: Duration,
+    ) -> Result<ForwardedResponse, String> {
+        let agent = ureq::AgentBuilder::new().timeout(timeout).build();
+        let mut call = agent.request(&request.method, &request.url);
+        for (name, value) in &request.headers {
+            call = call.set(name, value);
+        }
+        let response = match call.send_bytes(&request.body) {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+            Err(e) => return Err(e.to_string()),
+        };
+        let status = response.status();
+        let headers = response
+            .headers_names()
+            .into_iter()
+            .filter_map(|name| Some((name.clone(), response.header(&name)?.to_string())))
+            .collect();
+        let mut body = Vec::new();
+        response.into_reader().read_to_end(&mut body).map_err(|e| e.to_string())?;
+        Ok(ForwardedResponse { status, headers, body })
+    }
+}
+
+/// Where a write to a worldline goes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Route {
+    /// This node is the primary and commits the write itself.
+    Local(Primary),
+    /// Another node holds the lease; the write goes there.
+    Forward(Lease),
+}
+
+/// Added to a write request this node is primary for. The handler passes
+/// it to [`Cluster::fence`] before committing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Primary {
+    pub worldline: WorldlineId,
+    pub epoch: u64,
+}
+
+/// This node's view of the cluster.
+#[derive(Clone)]
+pub struct Cluster {
+    config: Arc<ClusterConfig>,
+    coordinator: Arc<dyn Coordinator>,
+    forwarder: Arc<dyn Forwarder>,
+    clock: Arc<dyn Clock>,
+    /// Worldline written when a request names none.
+    worldline: Option<WorldlineId>,
+    /// Worldlines this node holds the lease on, with the lease's epoch.
+    held: Arc<Mutex<BTreeMap<WorldlineId, u64>>>,
+    body_limit: usize,
+}
+
+impl Cluster {
+    /// Node described by `config`, with leases kept in its `lease_dir`.
+    pub fn new(config: ClusterConfig) -> Self {
+        let coordinator = Arc::new(FileCoordinator::new(config.lease_dir.clone()));
+        Self {
+            config: Arc::new(config),
+            coordinator,
+            forwarder: Arc::new(HttpForwarder),
+            clock: Arc::new(SystemClock),
+            worldline: None,
+            held: Arc::default(),
+            body_limit: usize::MAX,
+        }
+    }
+
+    /// Keep leases in `coordinator` instead.
+    pub fn with_coordinator(mut self, coordinator: Arc<dyn Coordinator>) -> Self {
+        self.coordinator = coordinator;
+        self
+    }
+
+    /// Replace the HTTP forwarder.
+    pub fn with_forwarder(mut self, forwarder: Arc<dyn Forwarder>) -> Self {
+        self.forwarder = forwarder;
+        self
+    }
+
+    /// Time leases with `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Send writes that name no worldline to `worldline`'s primary, and
+    /// try for its lease on every renewal, so this node takes over when
+    /// the primary fails.
+    pub fn with_worldline(mut self, worldline: WorldlineId) -> Self {
+        self.worldline = Some(worldline);
+        self
+    }
+
+    /// Refuse to forward request bodies over `limit` bytes.
+    pub fn with_body_limit(mut self, limit: usize) -> Self {
+        self.body_limit = limit;
+        self
+    }
+
+    pub fn config(&self) -> &ClusterConfig {
+        &self.config
+    }
+
+    /// Worldlines this node is primary for, as of the last renewal.
+    pub fn held(&self) -> Vec<WorldlineId> {
+        self.held.lock().unwrap_or_else(|e| e.into_inner()).keys().cloned().collect()
+    }
+
+    /// Where a write to `worldline` goes, taking its lease if it is free.
+    pub fn route(&self, worldline: &WorldlineId) -> ServerResult<Route> {
+        let lease = self.coordinator.acquire(
+            worldline,
+            &self.config.node_id,
+            &self.config.advertise_url,
+            self.config.lease_ttl(),
+            self.clock.now_ms(),
+        )?;
+        let mut held = self.held.lock().unwrap_or_else(|e| e.into_inner());
+        if lease.holder == self.config.node_id {
+            held.insert(worldline.clone(), lease.epoch);
+            Ok(Route::Local(Primary { worldline: worldline.clone(), epoch: lease.epoch }))
+        } else {
+            if held.remove(worldline).is_some() {
+                tracing::warn!("lost the lease on {worldline} to {}", lease.holder);
+            }
+            Ok(Route::Forward(lease))
+        }
+    }
+
+    /// Check that this node still holds `primary`'s lease, under the same
+    /// epoch, before committing a write routed to it.
+    pub fn fence(&self, primary: &Primary) -> ServerResult<()> {
+        let lease = self.coordinator.lease(&primary.worldline)?;
+        match lease {
+            Some(lease)
+                if lease.holder == self.config.node_id
+                    && lease.epoch == primary.epoch
+                    && lease.is_live(self.clock.now_ms()) =>
+            {
+                Ok(())
+            }
+            lease => Err(ServerError::NotPrimary {
+                worldline: primary.worldline.to_string(),
+                holder: lease.map_or_else(|| "nobody".into(), |lease| lease.holder),
+            }),
+        }
+    }
+
+    /// Check that this node is still `worldline`'s primary, under the epoch
+    /// it last routed a write to it with.
+    pub fn fence_worldline(&self, worldline: &WorldlineId) -> ServerResult<()> {
+        let epoch = self.held.lock().unwrap_or_else(|e| e.into_inner()).get(worldline).copied();
+        match epoch {
+            Some(epoch) => self.fence(&Primary { worldline: worldline.clone(), epoch }),
+            None => Err(ServerError::NotPrimary {
+                worldline: worldline.to_string(),
+                holder: self
+                    .coordinator
+                    .lease(worldline)?
+                    .map_or_else(|| "nobody".into(), |lease| lease.holder),
+            }),
+        }
+    }
+
+    /// `ledger`, with every write fenced against this node's leases.
+    pub fn fenced(&self, ledger: Arc<dyn Ledger>) -> FencedLedger {
+        FencedLedger { inner: ledger, cluster: self.clone() }
+    }
+
+    /// Renew the held leases, and try for the default worldline's.
+    pub fn renew(&self) {
+        let mut worldlines: BTreeSet<_> = self.held().into_iter().collect();
+        worldlines.extend(self.worldline.clone());
+        for worldline in worldlines {
+            if let Err(e) = self.route(&worldline) {
+                tracing::warn!("cannot renew the lease on {worldline}: {e}");
+            }
+        }
+    }
+
+    /// Renew leases in the background every third of the lease TTL until
+    /// the returned task is aborted. Must be called inside a Tokio runtime.
+    pub fn start(&self) -> JoinHandle<()> {
+        let cluster = self.clone();
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(cluster.config.lease_ttl() / 3);
+            loop {
+                ticks.tick().await;
+                let renewing = cluster.clone();
+                if let Err(e) = tokio::task::spawn_blocking(move || renewing.renew()).await {
+                    tracing::warn!("lease renewal panicked: {e}");
+                }
+            }
+        })
+    }
+
+    /// Give up every held lease, so other nodes take over at once.
+    pub fn release_all(&self) {
+        let held = std::mem::take(&mut *self.held.lock().unwrap_or_else(|e| e.into_inner()));
+        for worldline in held.into_keys() {
+            if let Err(e) = self.coordinator.release(&worldline, &self.config.node_id) {
+                tracing::warn!("cannot release the lease on {worldline}: {e}");
+            }
+        }
+    }
+
+    /// Middleware routing writes: those this node is primary for carry a
+    /// [`Primary`] extension to their handler, and the rest are forwarded.
+    pub async fn route_writes(
+        State(cluster): State<Cluster>,
+        mut request: Request,
+        next: Next,
+    ) -> Response {
+        if request.method() != Method::POST || !WRITE_PATHS.contains(&request.uri().path()) {
+            return next.run(request).await;
+        }
+        let worldline = match request.headers().get(WORLDLINE_HEADER) {
+            Some(value) => match value.to_str().ok().map(WorldlineId::from_hex) {
+                Some(Ok(worldline)) => worldline,
+                _ => {
+                    let error = format!("{WORLDLINE_HEADER} is not a worldline id");
+                    return ServerError::InvalidRequest(error).into_response();
+                }
+            },
+            None => match &cluster.worldline {
+                Some(worldline) => worldline.clone(),
+                None => return next.run(request).await,
+            },
+        };
+        let routing = cluster.clone();
+        let route = tokio::task::spawn_blocking(move || routing.route(&worldline))
+            .await
+            .unwrap_or_else(|e| Err(ServerError::Internal(format!("routing panicked: {e}"))));
+        match route {
+            Ok(Route::Local(primary)) => {
+                request.extensions_mut().insert(primary);
+                next.run(request).await
+            }
+            Ok(Route::Forward(lease)) if request.headers().contains_key(FORWARDED_BY_HEADER) => {
+                let worldline = lease.worldline.to_string();
+                ServerError::NotPrimary { worldline, holder: lease.holder }.into_response()
+            }
+            Ok(Route::Forward(lease)) => cluster.forward(&lease, request).await,
+            Err(e) => e.into_response(),
+        }
+    }
+
+    async fn forward(&self, lease: &Lease, request: Request) -> Response {
+        let (parts, body) = request.into_parts();
+        let body = match to_bytes(body, self.body_limit).await {
+            Ok(body) => body.to_vec(),
+            Err(e) => return ServerError::InvalidRequest(e.to_string()).into_response(),
+        };
+        let path = parts.uri.path_and_query().map_or("/", |path| path.as_str());
+        let mut headers: Vec<(String, String)> = parts
+            .headers
+            .iter()
+            .filter(|(name, _)| !is_hop_header(name))
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        headers.push((FORWARDED_BY_HEADER.to_string(), self.config.node_id.clone()));
+        let forwarded = ForwardedRequest {
+            method: parts.method.to_string(),
+            url: format!("{}{path}", lease.address.trim_end_matches('/')),
+            headers,
+            body,
+        };
+        tracing::debug!("forwarding {} to {} ({})", forwarded.url, lease.holder, lease.worldline);
+        let forwarder = self.forwarder.clone();
+        let timeout = self.config.forward_timeout();
+        let answer = tokio::task::spawn_blocking(move || forwarder.forward(forwarded, timeout))
+            .await
+            .unwrap_or_else(|e| Err(format!("forwarding panicked: {e}")));
+        match answer {
+            Ok(answer) => {
+                let status = StatusCode::from_u16(answer.status).unwrap_or(StatusCode::BAD_GATEWAY);
+                let mut response = Response::new(Body::from(answer.body));
+                *response.status_mut() = status;
+                for (name, value) in answer.headers {
+                    let (Ok(name), Ok(value)) =
+                        (HeaderName::try_from(name), HeaderValue::try_from(value))
+                    else {
+                        continue;
+                    };
+                    if !is_hop_header(&name) {
+                        response.headers_mut().append(name, value);
+                    }
+                }
+                response
+            }
+            Err(e) => {
+                tracing::warn!("cannot forward a write to {}: {e}", lease.holder);
+                ServerError::NotPrimary {
+                    worldline: lease.worldline.to_string(),
+                    holder: lease.holder.clone(),
+                }
+                .into_response()
+            }
+        }
+    }
+}
+
+/// Headers describing one connection rather than the request, which a
+/// forwarding node sets afresh.
+fn is_hop_header(name: &HeaderName) -> bool {
+    matches!(
+        name.as_str(),
+        "host" | "content-length" | "connection" | "transfer-encoding" | "keep-alive"
+    )
+}
+
+/// A ledger only this node's leases let it write to: each write to a
+/// worldline is refused with [`LedgerError::Fenced`] unless
+/// [`Cluster::fence_worldline`] passes. Reads go straight through.
+#[derive(Clone)]
+pub struct FencedLedger {
+    inner: Arc<dyn Ledger>,
+    cluster: Cluster,
+}
+
+impl FencedLedger {
+    fn fence(&self, worldline: &WorldlineId) -> Result<(), LedgerError> {
+        self.cluster.fence_worldline(worldline).map_err(|e| match e {
+            ServerError::NotPrimary { worldline, holder } => {
+                LedgerError::Fenced { worldline, holder }
+            }
+            ServerError::Ledger(e) => e,
+            e => LedgerError::Io(e.to_string()),
+        })
+    }
+
+    /// Fence the worldline of the commitment `hash` names. An unknown
+    /// commitment is left for the inner ledger to refuse.
+    fn fence_commitment(&self, hash: [u8; 32]) -> Result<(), LedgerError> {
+        match self.inner.get_by_hash(hash)? {
+            Some(receipt) => self.fence(receipt.worldline()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl LedgerWriter for FencedLedger {
+    fn append_commitment(
+        &self,
+        proposal: &CommitmentProposal,
+        decision: &Decision,
+        policy_hash: [u8; 32],
+    ) -> Result<CommitmentReceipt, LedgerError> {
+        self.fence(&proposal.worldline)?;
+        self.inner.append_commitment(proposal, decision, policy_hash)
+    }
+
+    fn append_outcome(
+        &self,
+        commitment_receipt_hash: [u8; 32],
+        outcome: &OutcomeRecord,
+    ) -> Result<OutcomeReceipt, LedgerError> {
+        self.fence_commitment(commitment_receipt_hash)?;
+        self.inner.append_outcome(commitment_receipt_hash, outcome)
+    }
+
+    fn append_rejection_outcome(
+        &self,
+        commitment_receipt_hash: [u8; 32],
+        reason: &str,
+    ) -> Result<OutcomeReceipt, LedgerError> {
+        self.fence_commitment(commitment_receipt_hash)?;
+        self.inner.append_rejection_outcome(commitment_receipt_hash, reason)
+    }
+
+    fn append_snapshot(&self, snapshot: &SnapshotInput) -> Result<SnapshotReceipt, LedgerError> {
+        self.fence(&snapshot.worldline)?;
+        self.inner.append_snapshot(snapshot)
+    }
+
+    fn import_receipt(&self, receipt: Receipt) -> Result<(), LedgerError> {
+        self.fence(receipt.worldline())?;
+        self.inner.import_receipt(receipt)
+    }
+
+    fn archive(
+        &self,
+        worldline: &WorldlineId,
+        through_seq: u64,
+    ) -> Result<Vec<Receipt>, LedgerError> {
+        self.fence(worldline)?;
+        self.inner.archive(worldline, through_seq)
+    }
+
+    fn compact(
+        &self,
+        worldline: &WorldlineId,
+        through_seq: u64,
+        key: &SigningKey,
+    ) -> Result<Compaction, LedgerError> {
+        self.fence(worldline)?;
+        self.inner.compact(worldline, through_seq, key)
+    }
+
+    fn register_schema(
+        &self,
+        worldline: &WorldlineId,
+        namespace: Option<&str>,
+        schema: Arc<dyn StateSchema>,
+    ) -> Result<(), LedgerError> {
+        self.inner.register_schema(worldline, namespace, schema)
+    }
+}
+
+impl LedgerReader for FencedLedger {
+    fn head(&self, worldline: &WorldlineId) -> Result<Option<ReceiptRef>, LedgerError> {
+        self.inner.head(worldline)
+    }
+
+    fn read_range(
+        &self,
+        worldline: &WorldlineId,
+        from_seq: u64,
+        to_seq: u64,
+    ) -> Result<Vec<Receipt>, LedgerError> {
+        self.inner.read_range(worldline, from_seq, to_seq)
+    }
+
+    fn read_all(&self, worldline: &WorldlineId) -> Result<Vec<Receipt>, LedgerError> {
+        self.inner.read_all(worldline)
+    }
+
+    fn get_by_hash(&self, hash: [u8; 32]) -> Result<Option<Receipt>, LedgerError> {
+        self.inner.get_by_hash(hash)
+    }
+
+    fn worldlines(&self) -> Result<Vec<WorldlineId>, LedgerError> {
+        self.inner.worldlines()
+    }
+
+    fn receipt_count(&self, worldline: &WorldlineId) -> Result<u64, LedgerError> {
+        self.inner.receipt_count(worldline)
+    }
+
+    fn archived_through(
+        &self,
+        worldline: &WorldlineId,
+    ) -> Result<Option<ReceiptRef>, LedgerError> {
+        self.inner.archived_through(worldline)
+    }
+
+    fn compactions(
+        &self,
+        worldline: &WorldlineId,
+    ) -> Result<Vec<CompactionReceipt>, LedgerError> {
+        self.inner.compactions(worldline)
+    }
+
+    fn schemas(&self) -> Result<SchemaRegistry, LedgerError> {
+        self.inner.schemas()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::header;
+    use tower::util::ServiceExt;
+    use wll_ledger::InMemoryLedger;
+    use wll_types::{CommitmentClass, CommitmentId, EvidenceBundle, ManualClock};
+
+    use super::*;
+    use crate::config::ServerConfig;
+    use crate::server::WllServer;
+
+    fn node(name: &str, coordinator: Arc<dyn Coordinator>) -> Cluster {
+        Cluster::new(ClusterConfig {
+            node_id: name.into(),
+            advertise_url: format!("http://{name}:9418"),
+            lease_dir: PathBuf::new(),
+            lease_ttl_secs: 30,
+            forward_timeout_secs: 5,
+        })
+        .with_coordinator(coordinator)
+    }
+
+    #[test]
+    fn leases_change_hands_under_a_new_epoch() {
+        let dir = tempfile::tempdir().unwrap();
+        let leases: Arc<dyn Coordinator> = Arc::new(FileCoordinator::new(dir.path()));
+        let (a, b) = (node("a", leases.clone()), node("b", leases.clone()));
+        let worldline = WorldlineId::ephemeral();
+
+        let Route::Local(first) = a.route(&worldline).unwrap() else { panic!("a is primary") };
+        assert_eq!(first.epoch, 1);
+        let route = b.route(&worldline).unwrap();
+        assert!(matches!(route, Route::Forward(lease) if lease.holder == "a"));
+        assert_eq!(a.route(&worldline).unwrap(), Route::Local(first.clone()));
+        a.fence(&first).unwrap();
+
+        // Once a lets go, b takes over and a's writes are fenced off.
+        a.release_all();
+        assert!(a.held().is_empty());
+        let Route::Local(second) = b.route(&worldline).unwrap() else { panic!("b is primary") };
+        assert_eq!(second.epoch, 2);
+        let fenced = a.fence(&first);
+        assert!(matches!(fenced, Err(ServerError::NotPrimary { holder, .. }) if holder == "b"));
+        assert_eq!(leases.lease(&worldline).unwrap().unwrap().address, "http://b:9418");
+    }
+
+    fn proposal(worldline: &WorldlineId) -> CommitmentProposal {
+        CommitmentProposal {
+            worldline: worldline.clone(),
+            commitment_id: CommitmentId::new(),
+            class: CommitmentClass::ReadOnly,
+            intent: "write".into(),
+            requested_caps: vec![],
+            targets: vec![],
+            evidence: EvidenceBundle::empty(),
+            nonce: 0,
+        }
+    }
+
+    #[test]
+    fn a_primary_whose_lease_moved_on_cannot_append() {
+        let clock = Arc::new(ManualClock::new(1_000_000));
+        let leases: Arc<dyn Coordinator> = Arc::new(MemoryCoordinator::new());
+        let a = node("a", leases.clone()).with_clock(clock.clone());
+        let b = node("b", leases).with_clock(clock.clone());
+        let worldline = WorldlineId::ephemeral();
+        let shared: Arc<dyn Ledger> = Arc::new(InMemoryLedger::new(0));
+        let (on_a, on_b) = (a.fenced(shared.clone()), b.fenced(shared.clone()));
+        let append = |ledger: &FencedLedger| {
+            ledger.append_commitment(&proposal(&worldline), &Decision::Accepted, [0; 32])
+        };
+
+        // Only a node routed a write as primary appends.
+        assert!(matches!(append(&on_a), Err(LedgerError::Fenced { .. })));
+        a.route(&worldline).unwrap();
+        append(&on_a).unwrap();
+
+        // a stalls past its lease, and b takes over under the next epoch.
+        clock.advance_ms(31_000);
+        assert!(matches!(append(&on_a), Err(LedgerError::Fenced { .. })));
+        let Route::Local(primary) = b.route(&worldline).unwrap() else { panic!("b is primary") };
+        assert_eq!(primary.epoch, 2);
+        append(&on_b).unwrap();
+        let stale = append(&on_a);
+        assert!(matches!(stale, Err(LedgerError::Fenced { holder, .. }) if holder == "b"));
+        assert_eq!(shared.receipt_count(&worldline).unwrap(), 2);
+    }
+
+    struct Recorder(Mutex<Vec<ForwardedRequest>>);
+
+    impl Forwarder for Recorder {
+        fn forward(
+            &self,
+            request: ForwardedRequest,
+            _timeout: Duration,
+        ) -> Result<ForwardedResponse, String> {
+            self.0.lock().unwrap().push(request);
+            let headers = vec![("content-type".into(), "application/octet-stream".into())];
+            Ok(ForwardedResponse { status: 202, headers, body: b"ok".to_vec() })
+        }
+    }
+
+    fn push(worldline: &WorldlineId) -> axum::http::request::Builder {
+        Request::post(endpoints::PUSH)
+            .header(WORLDLINE_HEADER, worldline.to_hex())
+            .header(header::CONTENT_TYPE, "application/octet-stream")
+    }
+
+    #[tokio::test]
+    async fn writes_for_another_primary_are_forwarded_once() {
+        let leases: Arc<dyn Coordinator> = Arc::new(MemoryCoordinator::new());
+        let worldline = WorldlineId::ephemeral();
+        node("a", leases.clone()).route(&worldline).unwrap();
+        let recorder = Arc::new(Recorder(Mutex::default()));
+        let b = node("b", leases).with_forwarder(recorder.clone());
+        let router = WllServer::new(ServerConfig::default()).with_cluster(b).router();
+
+        let request = push(&worldline).body(Body::from("pack")).unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        assert_eq!(to_bytes(response.into_body(), usize::MAX).await.unwrap(), "ok");
+        let forwarded = recorder.0.lock().unwrap().pop().unwrap();
+        assert_eq!(forwarded.url, "http://a:9418/v1/push");
+        assert_eq!(forwarded.body, b"pack");
+        assert!(forwarded.headers.contains(&(FORWARDED_BY_HEADER.into(), "b".into())));
+
+        // A write another node already forwarded is not passed on again.
+        let request = push(&worldline).header(FORWARDED_BY_HEADER, "c").body(Body::empty());
+        let response = router.oneshot(request.unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert!(recorder.0.lock().unwrap().is_empty());
+    }
+}
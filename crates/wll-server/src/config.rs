@@ -1,8 +1,24 @@
 use std::net::SocketAddr;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 use serde::{Deserialize, Serialize};
+use wll_types::DecodeLimits;
 
+use crate::advertise::RefFilter;
+use crate::auth::{AllowAllAuth, AuthProvider, TokenAuth, TokenGrant};
+use crate::error::{ServerError, ServerResult};
+use crate::hooks::{CommandHook, ServerHook};
+use crate::maintenance::{MaintenanceTask, Schedule};
+use crate::oidc::{OidcAuth, OidcIssuer, OidcVerifier};
+use crate::webhooks::{DeliveryLog, WebhookConfig, WebhookDispatcher};
+
+/// Server configuration, usually loaded from a TOML file.
+///
+/// Every field has a default, so a config file only needs the keys it
+/// changes.
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ServerConfig {
     pub bind_addr: SocketAddr,
     pub repos_root: PathBuf,
@@ -10,6 +26,20 @@ pub struct ServerConfig {
     pub max_pack_size: u64,
     pub max_connections: usize,
     pub allow_anonymous_read: bool,
+    /// Seconds to let in-flight requests finish after a shutdown signal.
+    pub shutdown_timeout_secs: u64,
+    pub auth: AuthConfig,
+    pub hooks: Vec<HookConfig>,
+    pub webhooks: Vec<WebhookConfig>,
+    /// JSON Lines file every webhook delivery attempt is appended to.
+    pub webhook_log: Option<PathBuf>,
+    pub graphql: GraphqlConfig,
+    /// `[limits]` table: bounds on protocol messages decoded from clients.
+    pub limits: DecodeLimits,
+    pub refs: RefsConfig,
+    pub maintenance: MaintenanceConfig,
+    /// Set when this server is one node of a cluster.
+    pub cluster: Option<ClusterConfig>,
 }
 
 impl Default for ServerConfig {
@@ -21,7 +51,92 @@ impl Default for ServerConfig {
             max_pack_size: 100 * 1024 * 1024,
             max_connections: 256,
             allow_anonymous_read: true,
+            shutdown_timeout_secs: 30,
+            auth: AuthConfig::default(),
+            hooks: Vec::new(),
+            webhooks: Vec::new(),
+            webhook_log: None,
+            graphql: GraphqlConfig::default(),
+            limits: DecodeLimits::DEFAULT,
+            refs: RefsConfig::default(),
+            maintenance: MaintenanceConfig::default(),
+            cluster: None,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Parse a config from TOML text.
+    ///
+    /// OIDC key sets named by `jwks_path` are read here, so a bad path is
+    /// reported at startup rather than on the first request.
+    pub fn from_toml(text: &str) -> ServerResult<Self> {
+        let mut config: Self = toml::from_str(text).map_err(|e| ServerError::Config(e.to_string()))?;
+        if let AuthConfig::Oidc { issuers } = &mut config.auth {
+            for issuer in issuers {
+                issuer.load_jwks()?;
+            }
+        }
+        for webhook in &config.webhooks {
+            webhook.validate()?;
+        }
+        config.ref_filter()?;
+        if let Some(cluster) = &config.cluster {
+            cluster.validate()?;
+        }
+        Ok(config)
+    }
+
+    /// Read the config file at `path`.
+    pub fn load(path: &Path) -> ServerResult<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| ServerError::Config(format!("{}: {e}", path.display())))?;
+        Self::from_toml(&text)
+    }
+
+    pub fn shutdown_timeout(&self) -> Duration {
+        Duration::from_secs(self.shutdown_timeout_secs)
+    }
+
+    /// Build the auth provider selected by `[auth]`.
+    pub fn auth_provider(&self) -> Arc<dyn AuthProvider> {
+        match &self.auth {
+            AuthConfig::AllowAll => Arc::new(AllowAllAuth),
+            AuthConfig::Token { tokens } => {
+                Arc::new(TokenAuth::new(tokens.clone(), self.allow_anonymous_read))
+            }
+            AuthConfig::Oidc { issuers } => Arc::new(OidcAuth::new(
+                OidcVerifier::new(issuers.clone()),
+                self.allow_anonymous_read,
+            )),
+        }
+    }
+
+    /// Build the hooks listed under `[[hooks]]`, in order.
+    pub fn server_hooks(&self) -> Vec<Arc<dyn ServerHook>> {
+        self.hooks
+            .iter()
+            .map(|hook| match hook {
+                HookConfig::Command { pre_receive, post_receive } => Arc::new(CommandHook::new(
+                    pre_receive.clone(),
+                    post_receive.clone(),
+                )) as Arc<dyn ServerHook>,
+            })
+            .collect()
+    }
+
+    /// Filter for the refs `[refs]` hides or restricts.
+    pub fn ref_filter(&self) -> ServerResult<RefFilter> {
+        RefFilter::new(&self.refs)
+    }
+
+    /// Dispatcher for the `[[webhooks]]` endpoints, if there are any.
+    pub fn webhook_dispatcher(&self) -> Option<WebhookDispatcher> {
+        if self.webhooks.is_empty() {
+            return None;
         }
+        let log = DeliveryLog::new(self.webhook_log.clone());
+        Some(WebhookDispatcher::new(self.webhooks.clone(), log))
     }
 }
 
@@ -31,9 +146,159 @@ pub struct TlsConfig {
     pub key_path: PathBuf,
 }
 
+/// `[auth]` table: which [`AuthProvider`] guards the server.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(tag = "provider", rename_all = "kebab-case")]
+pub enum AuthConfig {
+    /// Accept every request.
+    #[default]
+    AllowAll,
+    /// Bearer tokens listed in the config.
+    Token { tokens: Vec<TokenGrant> },
+    /// OIDC ID tokens signed by one of the listed issuers.
+    Oidc { issuers: Vec<OidcIssuer> },
+}
+
+/// `[graphql]` table: limits on `/v1/graphql` queries (`graphql` feature).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphqlConfig {
+    /// Deepest selection nesting a query may have.
+    pub max_depth: usize,
+    /// Most fields a query may select, counting each nested field.
+    pub max_complexity: usize,
+}
+
+impl Default for GraphqlConfig {
+    fn default() -> Self {
+        Self { max_depth: 10, max_complexity: 500 }
+    }
+}
+
+/// `[refs]` table: which refs are advertised, and to whom.
+///
+/// Patterns are globs in which `*` stays within one path segment and `**`
+/// crosses them, so `refs/internal/**` covers a whole namespace.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RefsConfig {
+    /// Refs never advertised or served, to anyone.
+    pub hidden: Vec<String>,
+    /// Identities limited to some refs; the rest see every ref not hidden.
+    pub access: Vec<RefAccess>,
+}
+
+/// One `[[refs.access]]` entry.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RefAccess {
+    /// Identity name, as the auth provider authenticates it.
+    pub identity: String,
+    /// The only refs it sees.
+    pub allow: Vec<String>,
+}
+
+/// `[maintenance]` table: housekeeping run on a schedule.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaintenanceConfig {
+    /// Most tasks running at once, across every repository.
+    pub max_concurrent: usize,
+    /// Longest random delay, in seconds, added to each scheduled start.
+    pub jitter_secs: u64,
+    /// JSON Lines file every run is appended to.
+    pub log: Option<PathBuf>,
+    /// Event fabric WAL `wll serve` publishes finished runs to, for
+    /// repositories without a fabric of their own.
+    pub fabric_wal: Option<PathBuf>,
+    pub tasks: Vec<MaintenanceTaskConfig>,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self { max_concurrent: 1, jitter_secs: 0, log: None, fabric_wal: None, tasks: Vec::new() }
+    }
+}
+
+/// One `[[maintenance.tasks]]` entry.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MaintenanceTaskConfig {
+    pub task: MaintenanceTask,
+    pub schedule: Schedule,
+    /// The repository to maintain; every repository when unset.
+    pub repo: Option<String>,
+}
+
+/// `[cluster]` table: this node's place among servers sharing object
+/// storage, each worldline written through one primary node.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ClusterConfig {
+    /// Name of this node, unique in the cluster.
+    pub node_id: String,
+    /// Base URL other nodes forward writes to, such as
+    /// `http://10.0.0.5:9418`.
+    pub advertise_url: String,
+    /// Directory on the shared storage holding the worldline leases.
+    pub lease_dir: PathBuf,
+    /// Seconds a lease lasts unless renewed. Nodes renew theirs every
+    /// third of this, and take over a failed node's worldlines once its
+    /// leases expire.
+    #[serde(default = "default_lease_ttl_secs")]
+    pub lease_ttl_secs: u64,
+    /// Seconds to wait for the primary to answer a forwarded write.
+    #[serde(default = "default_forward_timeout_secs")]
+    pub forward_timeout_secs: u64,
+}
+
+fn default_lease_ttl_secs() -> u64 {
+    10
+}
+
+fn default_forward_timeout_secs() -> u64 {
+    30
+}
+
+impl ClusterConfig {
+    pub fn lease_ttl(&self) -> Duration {
+        Duration::from_secs(self.lease_ttl_secs)
+    }
+
+    pub fn forward_timeout(&self) -> Duration {
+        Duration::from_secs(self.forward_timeout_secs)
+    }
+
+    fn validate(&self) -> ServerResult<()> {
+        if self.node_id.is_empty() {
+            return Err(ServerError::Config("cluster node_id must not be empty".into()));
+        }
+        if !self.advertise_url.starts_with("http://") && !self.advertise_url.starts_with("https://")
+        {
+            return Err(ServerError::Config(format!(
+                "cluster advertise_url must be http(s): {:?}",
+                self.advertise_url
+            )));
+        }
+        if self.lease_ttl_secs < 3 {
+            return Err(ServerError::Config("cluster lease_ttl_secs must be at least 3".into()));
+        }
+        Ok(())
+    }
+}
+
+/// One `[[hooks]]` entry.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum HookConfig {
+    /// External programs run around each push.
+    Command {
+        pre_receive: Option<PathBuf>,
+        post_receive: Option<PathBuf>,
+    },
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::webhooks::WebhookEventKind;
 
     #[test]
     fn default_config() {
@@ -43,6 +308,7 @@ mod tests {
         assert_eq!(c.max_connections, 256);
         assert!(c.allow_anonymous_read);
         assert!(c.tls.is_none());
+        assert!(matches!(c.auth, AuthConfig::AllowAll));
     }
 
     #[test]
@@ -50,4 +316,85 @@ mod tests {
         let tls = TlsConfig { cert_path: "cert.pem".into(), key_path: "key.pem".into() };
         assert_eq!(tls.cert_path, PathBuf::from("cert.pem"));
     }
+
+    #[test]
+    fn from_toml_fills_defaults() {
+        let c = ServerConfig::from_toml(
+            r#"
+            bind_addr = "0.0.0.0:9000"
+            allow_anonymous_read = false
+
+            [tls]
+            cert_path = "cert.pem"
+            key_path = "key.pem"
+
+            [auth]
+            provider = "token"
+            tokens = [{ token = "s3cret", name = "ci", admin = true }]
+
+            [[hooks]]
+            kind = "command"
+            pre_receive = "/etc/wll/pre-receive"
+
+            [limits]
+            max_depth = 16
+            "#,
+        )
+        .unwrap();
+        assert_eq!(c.bind_addr.port(), 9000);
+        assert_eq!(c.max_connections, 256);
+        assert_eq!(c.tls.as_ref().unwrap().key_path, PathBuf::from("key.pem"));
+        assert!(matches!(&c.auth, AuthConfig::Token { tokens } if tokens[0].admin));
+        assert_eq!(c.server_hooks().len(), 1);
+        assert!(c.webhook_dispatcher().is_none());
+        assert_eq!(c.limits, DecodeLimits::DEFAULT.with_max_depth(16));
+    }
+
+    #[test]
+    fn from_toml_reads_and_checks_webhooks() {
+        let c = ServerConfig::from_toml(
+            r#"
+            webhook_log = "/var/log/wll/webhooks.jsonl"
+
+            [[webhooks]]
+            url = "https://chat.example/hooks/wll"
+            secret = "s3cret"
+            events = ["push-received", "commitment-rejected"]
+            "#,
+        )
+        .unwrap();
+        let hook = &c.webhooks[0];
+        assert_eq!(hook.events, vec![WebhookEventKind::PushReceived, WebhookEventKind::CommitmentRejected]);
+        assert_eq!((hook.max_attempts, hook.backoff_ms, hook.timeout_secs), (5, 1000, 10));
+        assert!(c.webhook_dispatcher().is_some());
+
+        let err = ServerConfig::from_toml("[[webhooks]]\nurl = \"ftp://x\"\n").unwrap_err();
+        assert!(matches!(err, ServerError::Config(_)));
+        let err = ServerConfig::from_toml("[[webhooks]]\nurl = \"https://x\"\nevents = [\"deploy\"]\n").unwrap_err();
+        assert!(matches!(err, ServerError::Config(_)));
+    }
+
+    #[test]
+    fn from_toml_loads_oidc_key_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let jwks = dir.path().join("jwks.json");
+        std::fs::write(&jwks, r#"{"keys":[{"kty":"OKP","crv":"Ed25519","kid":"k1","x":"11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo"}]}"#).unwrap();
+        let text = format!(
+            "[auth]\nprovider = \"oidc\"\n\n[[auth.issuers]]\nissuer = \"https://ci.example\"\naudiences = [\"wll\"]\nkind = \"workload\"\njwks_path = {:?}\n",
+            jwks.display().to_string()
+        );
+        let c = ServerConfig::from_toml(&text).unwrap();
+        let AuthConfig::Oidc { issuers } = &c.auth else { panic!("expected oidc auth") };
+        assert_eq!(issuers[0].kind, wll_types::AttestationKind::Workload);
+        assert_eq!(issuers[0].jwks.as_ref().unwrap().keys.len(), 1);
+
+        let missing = text.replace("jwks.json", "missing.json");
+        assert!(matches!(ServerConfig::from_toml(&missing), Err(ServerError::Config(_))));
+    }
+
+    #[test]
+    fn from_toml_rejects_unknown_provider() {
+        let err = ServerConfig::from_toml("[auth]\nprovider = \"kerberos\"\n").unwrap_err();
+        assert!(matches!(err, ServerError::Config(_)));
+    }
 }
@@ -1,4 +1,10 @@
+use std::path::PathBuf;
+
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use wll_types::{Coded, ErrorCode};
 
 #[derive(Debug, Error)]
 pub enum ServerError {
@@ -23,14 +29,152 @@ pub enum ServerError {
     #[error("ledger error: {0}")]
     Ledger(#[from] wll_ledger::LedgerError),
 
+    #[error("ref error: {0}")]
+    Refs(#[from] wll_refs::RefError),
+
+    #[error("receipt not found: {0}")]
+    ReceiptNotFound(String),
+
+    #[error("invalid request: {0}")]
+    InvalidRequest(String),
+
     #[error("configuration error: {0}")]
     Config(String),
 
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
 
+    /// A hook program could not be started.
+    #[error("hook {}: {source}", program.display())]
+    Hook {
+        program: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// A write reached a node that is not, or is no longer, the
+    /// worldline's primary.
+    #[error("not the primary for {worldline}: the lease is held by {holder}")]
+    NotPrimary { worldline: String, holder: String },
+
     #[error("internal error: {0}")]
     Internal(String),
 }
 
 pub type ServerResult<T> = Result<T, ServerError>;
+
+impl Coded for ServerError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::RepoNotFound(_) => ErrorCode::RepoNotFound,
+            Self::RepoAlreadyExists(_) => ErrorCode::RepoExists,
+            Self::AuthFailed(_) => ErrorCode::Unauthenticated,
+            Self::AuthorizationDenied { .. } => ErrorCode::PermissionDenied,
+            Self::Protocol(e) => e.code(),
+            Self::Store(e) => e.code(),
+            Self::Ledger(e) => e.code(),
+            Self::Refs(e) => e.code(),
+            Self::ReceiptNotFound(_) => ErrorCode::ReceiptNotFound,
+            Self::InvalidRequest(_) => ErrorCode::InvalidInput,
+            Self::Config(_) => ErrorCode::ConfigInvalid,
+            Self::Io(_) | Self::Hook { .. } => ErrorCode::Io,
+            Self::NotPrimary { .. } => ErrorCode::NotPrimary,
+            Self::Internal(_) => ErrorCode::Internal,
+        }
+    }
+}
+
+impl IntoResponse for ServerError {
+    fn into_response(self) -> Response {
+        Problem::from_error(&self).into_response()
+    }
+}
+
+/// An `application/problem+json` body (RFC 9457) describing a failed
+/// request.
+///
+/// `type` is `urn:wll:error:<code>`, so clients can branch on the stable
+/// [`ErrorCode`] without parsing `detail`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub type_uri: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    pub code: ErrorCode,
+    pub number: u16,
+}
+
+impl Problem {
+    pub const CONTENT_TYPE: &'static str = "application/problem+json";
+
+    /// A problem with `code`, described by `detail`.
+    pub fn new(code: ErrorCode, detail: impl Into<String>) -> Self {
+        let category = code.category();
+        Self {
+            type_uri: format!("urn:wll:error:{code}"),
+            title: category.title().to_string(),
+            status: category.http_status(),
+            detail: detail.into(),
+            code,
+            number: code.number(),
+        }
+    }
+
+    /// The problem for `error`. Internal failures are logged and answered
+    /// with the bare title, so paths and other server details stay out of
+    /// the response.
+    pub fn from_error<E: Coded + std::fmt::Display>(error: &E) -> Self {
+        let code = error.code();
+        if code.category().http_status() >= 500 {
+            tracing::error!(code = %code, "{error}");
+            Self::new(code, code.category().title())
+        } else {
+            Self::new(code, error.to_string())
+        }
+    }
+}
+
+impl IntoResponse for Problem {
+    fn into_response(self) -> Response {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let body = serde_json::to_vec(&self).unwrap_or_default();
+        (status, [(header::CONTENT_TYPE, Self::CONTENT_TYPE)], body).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::to_bytes;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn errors_become_problem_json() {
+        let response = ServerError::RepoNotFound("acme/site".into()).into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], Problem::CONTENT_TYPE);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let problem: Problem = serde_json::from_slice(&body).unwrap();
+        assert_eq!(problem.type_uri, "urn:wll:error:repo.not_found");
+        assert_eq!(problem.code, ErrorCode::RepoNotFound);
+        assert_eq!(problem.number, 2204);
+        assert_eq!(problem.detail, "repository not found: acme/site");
+    }
+
+    #[test]
+    fn wrapped_errors_keep_their_code_and_hide_internal_detail() {
+        let error = ServerError::Store(wll_store::StoreError::ReadOnly);
+        assert_eq!(Problem::from_error(&error).status, 403);
+
+        let error = ServerError::Hook {
+            program: "/srv/hooks/pre-receive".into(),
+            source: std::io::ErrorKind::NotFound.into(),
+        };
+        assert!(std::error::Error::source(&error).is_some());
+        let problem = Problem::from_error(&error);
+        assert_eq!(problem.status, 500);
+        assert!(!problem.detail.contains("/srv/hooks"));
+    }
+}
@@ -0,0 +1,435 @@
+//! GraphQL queries over the ledger and provenance DAG (`graphql` feature).
+//!
+//! `POST /v1/graphql` answers the nested questions an audit UI would
+//! otherwise need many REST calls for, such as a receipt, the receipts it
+//! follows from, the worldlines downstream of it, and the policies in
+//! force there:
+//!
+//! ```graphql
+//! {
+//!   receipt(hash: "9f2c…") {
+//!     intent
+//!     parents { relation receipt { seq worldline { id } } }
+//!     affectedWorldlines { id policies { id name } }
+//!   }
+//! }
+//! ```
+//!
+//! Worldlines are named by hex id and receipts by hex hash. A query nested
+//! deeper than [`GraphqlConfig::max_depth`], or selecting more fields than
+//! [`GraphqlConfig::max_complexity`], is refused before it runs. Failed
+//! fields carry their [`ErrorCode`](wll_types::ErrorCode) as the `code`
+//! extension.
+
+use std::fmt::Display;
+use std::sync::Arc;
+
+use async_graphql::{
+    Context, EmptyMutation, EmptySubscription, ErrorExtensions, Object, Request, Response, Schema,
+};
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::response::Json;
+use wll_dag::{ParentRef, ProvenanceDag};
+use wll_gate::{ContextProvider, LedgerContextProvider, Policy};
+use wll_ledger::{Decision, Ledger, Receipt};
+use wll_types::{Coded, ObjectId, TemporalAnchor, WorldlineId};
+
+use crate::api::{receipt_page, QueryApi, ReceiptRange};
+use crate::config::GraphqlConfig;
+use crate::error::{ServerError, ServerResult};
+
+/// Snapshot of the provenance DAG, taken once per query.
+pub type DagSource = Arc<dyn Fn() -> ProvenanceDag + Send + Sync>;
+
+type GraphqlSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// The schema of a [`QueryApi`] and where its DAG comes from.
+#[derive(Clone)]
+pub(crate) struct Graphql {
+    schema: GraphqlSchema,
+    dag: DagSource,
+}
+
+impl Graphql {
+    pub(crate) fn new(ledger: Arc<dyn Ledger>, dag: DagSource, limits: GraphqlConfig) -> Self {
+        let policies = LedgerContextProvider::new(ledger.clone());
+        let schema = Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+            .data(Sources { ledger, policies })
+            .limit_depth(limits.max_depth)
+            .limit_complexity(limits.max_complexity)
+            .finish();
+        Self { schema, dag }
+    }
+}
+
+/// `POST /v1/graphql`: run one query for an authorized reader.
+pub(crate) async fn execute(
+    State(api): State<Arc<QueryApi>>,
+    headers: HeaderMap,
+    Json(request): Json<Request>,
+) -> ServerResult<Json<Response>> {
+    api.reader(&headers).await?;
+    let graphql = api
+        .graphql
+        .as_ref()
+        .ok_or_else(|| ServerError::Internal("graphql is not configured".into()))?;
+    let dag = Arc::new((graphql.dag)());
+    Ok(Json(graphql.schema.execute(request.data(dag)).await))
+}
+
+/// What resolvers read besides the per-query DAG.
+struct Sources {
+    ledger: Arc<dyn Ledger>,
+    policies: LedgerContextProvider<dyn Ledger>,
+}
+
+fn sources<'a>(ctx: &Context<'a>) -> &'a Sources {
+    ctx.data_unchecked::<Sources>()
+}
+
+fn dag<'a>(ctx: &Context<'a>) -> &'a ProvenanceDag {
+    ctx.data_unchecked::<Arc<ProvenanceDag>>()
+}
+
+/// A field error carrying `error`'s code.
+fn coded<E: Coded + Display>(error: E) -> async_graphql::Error {
+    let code = error.code();
+    async_graphql::Error::new(error.to_string())
+        .extend_with(|_, extensions| extensions.set("code", code.to_string()))
+}
+
+fn receipt_by_hash(
+    ctx: &Context<'_>,
+    hash: [u8; 32],
+) -> async_graphql::Result<Option<ReceiptNode>> {
+    Ok(sources(ctx).ledger.get_by_hash(hash).map_err(coded)?.map(ReceiptNode))
+}
+
+pub(crate) struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// A receipt by hex hash.
+    async fn receipt(
+        &self,
+        ctx: &Context<'_>,
+        hash: String,
+    ) -> async_graphql::Result<Option<ReceiptNode>> {
+        let bytes = hex::decode(&hash)
+            .ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .ok_or_else(|| ServerError::InvalidRequest(format!("not a receipt hash: {hash}")))
+            .map_err(coded)?;
+        receipt_by_hash(ctx, bytes)
+    }
+
+    /// A worldline by hex id, if the ledger holds its stream.
+    async fn worldline(
+        &self,
+        ctx: &Context<'_>,
+        id: String,
+    ) -> async_graphql::Result<Option<WorldlineNode>> {
+        let worldline = WorldlineId::from_hex(&id).map_err(coded)?;
+        let known = sources(ctx).ledger.head(&worldline).map_err(coded)?.is_some();
+        Ok(known.then_some(WorldlineNode(worldline)))
+    }
+
+    /// Every worldline in the ledger.
+    async fn worldlines(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<WorldlineNode>> {
+        let worldlines = sources(ctx).ledger.worldlines().map_err(coded)?;
+        Ok(worldlines.into_iter().map(WorldlineNode).collect())
+    }
+}
+
+struct WorldlineNode(WorldlineId);
+
+#[Object(name = "Worldline")]
+impl WorldlineNode {
+    async fn id(&self) -> String {
+        self.0.to_hex()
+    }
+
+    async fn head(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<ReceiptNode>> {
+        match sources(ctx).ledger.head(&self.0).map_err(coded)? {
+            Some(head) => receipt_by_hash(ctx, head.receipt_hash),
+            None => Ok(None),
+        }
+    }
+
+    /// Live receipts; archived ones are not counted.
+    async fn receipt_count(&self, ctx: &Context<'_>) -> async_graphql::Result<u64> {
+        sources(ctx).ledger.receipt_count(&self.0).map_err(coded)
+    }
+
+    /// Receipts with seq in `from..=to`, at most `limit`, paged as
+    /// `/v1/worldlines/{id}/receipts` pages them.
+    async fn receipts(
+        &self,
+        ctx: &Context<'_>,
+        from: Option<u64>,
+        to: Option<u64>,
+        limit: Option<u32>,
+    ) -> async_graphql::Result<Vec<ReceiptNode>> {
+        let range = ReceiptRange { from, to, limit };
+        let page = receipt_page(sources(ctx).ledger.as_ref(), self.0.clone(), &range);
+        Ok(page.map_err(coded)?.receipts.into_iter().map(ReceiptNode).collect())
+    }
+
+    /// Policies the worldline's accepted outcomes have put in force.
+    async fn policies(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<PolicyNode>> {
+        let now = TemporalAnchor::now(0);
+        let policies = sources(ctx).policies.policies(&self.0, &now).map_err(coded)?;
+        Ok(policies.into_iter().map(PolicyNode).collect())
+    }
+}
+
+struct ReceiptNode(Receipt);
+
+impl ReceiptNode {
+    fn id(&self) -> ObjectId {
+        ObjectId::from_hash(self.0.receipt_hash())
+    }
+}
+
+#[Object(name = "Receipt")]
+impl ReceiptNode {
+    async fn hash(&self) -> String {
+        hex::encode(self.0.receipt_hash())
+    }
+
+    async fn worldline(&self) -> WorldlineNode {
+        WorldlineNode(self.0.worldline().clone())
+    }
+
+    async fn seq(&self) -> u64 {
+        self.0.seq()
+    }
+
+    /// `Commitment`, `Outcome` or `Snapshot`.
+    async fn kind(&self) -> String {
+        self.0.kind().to_string()
+    }
+
+    async fn timestamp_ms(&self) -> u64 {
+        self.0.timestamp().physical_ms
+    }
+
+    /// The commitment's intent, for commitment receipts.
+    async fn intent(&self) -> Option<&str> {
+        self.0.as_commitment().map(|commitment| commitment.intent.as_str())
+    }
+
+    /// `accepted`, `rejected` or `deferred`, for commitment receipts.
+    async fn decision(&self) -> Option<&str> {
+        self.0.as_commitment().map(|commitment| match commitment.decision {
+            Decision::Accepted => "accepted",
+            Decision::Rejected { .. } => "rejected",
+            Decision::Deferred { .. } => "deferred",
+        })
+    }
+
+    /// The receipts this one causally follows, from the provenance DAG.
+    async fn parents(&self, ctx: &Context<'_>) -> Vec<ParentNode> {
+        dag(ctx)
+            .get_node(&self.id())
+            .map(|node| node.parents.iter().cloned().map(ParentNode).collect())
+            .unwrap_or_default()
+    }
+
+    /// Worldlines holding a receipt downstream of this one in the
+    /// provenance DAG.
+    async fn affected_worldlines(&self, ctx: &Context<'_>) -> Vec<WorldlineNode> {
+        let report = dag(ctx).impact_report(&self.id());
+        report.affected_worldlines.into_iter().map(WorldlineNode).collect()
+    }
+
+    /// The whole receipt, as `/v1/receipts/{hash}` returns it.
+    async fn json(&self) -> async_graphql::Json<Receipt> {
+        async_graphql::Json(self.0.clone())
+    }
+}
+
+struct ParentNode(ParentRef);
+
+#[Object(name = "Parent")]
+impl ParentNode {
+    /// How the child follows the parent, such as `Sequential` or
+    /// `CrossWorldline`.
+    async fn relation(&self) -> String {
+        self.0.relation.to_string()
+    }
+
+    /// The parent, unless the ledger no longer holds it.
+    async fn receipt(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<ReceiptNode>> {
+        receipt_by_hash(ctx, *self.0.target.as_bytes())
+    }
+}
+
+struct PolicyNode(Policy);
+
+#[Object(name = "Policy")]
+impl PolicyNode {
+    async fn id(&self) -> &str {
+        &self.0.id
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    /// The whole policy, rules and scope included.
+    async fn json(&self) -> async_graphql::Json<Policy> {
+        async_graphql::Json(self.0.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::{to_bytes, Body};
+    use axum::http::{header, Request as HttpRequest};
+    use serde_json::{json, Value};
+    use tower::util::ServiceExt;
+    use wll_dag::{CausalRelation, DagNode, DagNodeMetadata};
+    use wll_gate::POLICY_KEY_PREFIX;
+    use wll_ledger::{
+        CommitmentProposal, EvidenceBundle, InMemoryLedger, LedgerWriter, OutcomeRecord,
+        StateUpdate,
+    };
+    use wll_refs::InMemoryRefStore;
+    use wll_types::{CommitmentClass, CommitmentId, IdentityMaterial};
+
+    use super::*;
+    use crate::auth::AllowAllAuth;
+
+    fn worldline(byte: u8) -> WorldlineId {
+        WorldlineId::derive(&IdentityMaterial::GenesisHash([byte; 32]))
+    }
+
+    fn proposal(worldline: &WorldlineId, intent: &str) -> CommitmentProposal {
+        CommitmentProposal {
+            worldline: worldline.clone(),
+            commitment_id: CommitmentId::new(),
+            class: CommitmentClass::ReadOnly,
+            intent: intent.into(),
+            requested_caps: vec![],
+            targets: vec![worldline.clone()],
+            evidence: EvidenceBundle::empty(),
+            nonce: 0,
+        }
+    }
+
+    fn node(receipt: &Receipt, parents: Vec<ParentRef>) -> DagNode {
+        DagNode {
+            id: ObjectId::from_hash(receipt.receipt_hash()),
+            worldline: receipt.worldline().clone(),
+            seq: receipt.seq(),
+            kind: receipt.kind(),
+            timestamp: receipt.timestamp(),
+            parents,
+            metadata: DagNodeMetadata::empty(),
+        }
+    }
+
+    /// A transfer out of `a`, whose outcome puts a policy in force, cited
+    /// by a commitment on `b`. Returns the API and the receipts in order.
+    fn transfer(limits: GraphqlConfig) -> (QueryApi, Vec<Receipt>) {
+        let (a, b) = (worldline(1), worldline(2));
+        let ledger = InMemoryLedger::default();
+        // Only a policy change may put a policy in force; this one is
+        // `a`'s genesis, so it needs no admin capability.
+        let send = CommitmentProposal { class: CommitmentClass::PolicyChange, ..proposal(&a, "send") };
+        let sent = ledger.append_commitment(&send, &Decision::Accepted, [0; 32]).unwrap();
+        let policy = serde_json::to_value(Policy::permissive()).unwrap();
+        let record = OutcomeRecord {
+            effects: vec![],
+            proofs: vec![],
+            state_updates: vec![StateUpdate::new(format!("{POLICY_KEY_PREFIX}open"), policy)],
+            metadata: Default::default(),
+        };
+        let done = ledger.append_outcome(sent.receipt_hash, &record).unwrap();
+        let received = proposal(&b, "receive");
+        let received = ledger.append_commitment(&received, &Decision::Accepted, [0; 32]);
+        let receipts = vec![
+            Receipt::Commitment(sent),
+            Receipt::Outcome(done),
+            Receipt::Commitment(received.unwrap()),
+        ];
+
+        let mut dag = ProvenanceDag::new();
+        let id = |receipt: &Receipt| ObjectId::from_hash(receipt.receipt_hash());
+        dag.add_node(node(&receipts[0], vec![])).unwrap();
+        let follows = ParentRef::new(id(&receipts[0]), CausalRelation::CommitmentToOutcome);
+        dag.add_node(node(&receipts[1], vec![follows])).unwrap();
+        let cites = ParentRef::new(id(&receipts[1]), CausalRelation::CrossWorldline);
+        dag.add_node(node(&receipts[2], vec![cites])).unwrap();
+
+        let refs = Arc::new(InMemoryRefStore::new());
+        let api = QueryApi::new("audit", Arc::new(ledger), refs, Arc::new(AllowAllAuth))
+            .with_graphql(Arc::new(move || dag.clone()), limits);
+        (api, receipts)
+    }
+
+    async fn query(api: QueryApi, query: &str) -> Value {
+        let request = HttpRequest::post("/v1/graphql")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(json!({ "query": query }).to_string()))
+            .unwrap();
+        let response = api.router().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), 200);
+        serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn nested_query_follows_parents_and_affected_worldlines() {
+        let (api, receipts) = transfer(GraphqlConfig::default());
+        let hash = |receipt: &Receipt| hex::encode(receipt.receipt_hash());
+
+        let text = format!(
+            r#"{{ receipt(hash: "{}") {{
+                intent
+                parents {{ relation receipt {{ kind intent worldline {{ policies {{ id }} }} }} }}
+            }} }}"#,
+            hash(&receipts[2])
+        );
+        let body = query(api.clone(), &text).await;
+        let receipt = &body["data"]["receipt"];
+        assert_eq!(receipt["intent"], "receive");
+        let parent = &receipt["parents"][0];
+        assert_eq!(parent["relation"], "CrossWorldline");
+        assert_eq!(parent["receipt"]["kind"], "Outcome");
+        assert_eq!(parent["receipt"]["worldline"]["policies"], json!([{ "id": "permissive" }]));
+
+        let text = format!(
+            r#"{{ receipt(hash: "{}") {{ affectedWorldlines {{ id }} }} }}"#,
+            hash(&receipts[0])
+        );
+        let body = query(api.clone(), &text).await;
+        let mut affected: Vec<&str> = body["data"]["receipt"]["affectedWorldlines"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|worldline| worldline["id"].as_str().unwrap())
+            .collect();
+        affected.sort_unstable();
+        let mut expected = [worldline(1).to_hex(), worldline(2).to_hex()];
+        expected.sort_unstable();
+        assert_eq!(affected, expected);
+
+        let body = query(api, r#"{ receipt(hash: "beef") { seq } }"#).await;
+        assert_eq!(body["errors"][0]["extensions"]["code"], "invalid_input");
+    }
+
+    #[tokio::test]
+    async fn queries_beyond_the_limits_are_refused() {
+        let limits = GraphqlConfig { max_depth: 3, max_complexity: 500 };
+        let (api, _) = transfer(limits);
+        let shallow = query(api.clone(), "{ worldlines { head { seq } } }").await;
+        assert_eq!(shallow["data"]["worldlines"].as_array().unwrap().len(), 2);
+
+        let deep = "{ worldlines { head { parents { receipt { seq } } } } }";
+        let body = query(api, deep).await;
+        assert!(body["data"].is_null());
+        assert!(body["errors"][0]["message"].as_str().unwrap().contains("nested too deep"));
+    }
+}
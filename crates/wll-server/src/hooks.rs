@@ -1,5 +1,10 @@
+use std::path::PathBuf;
+use std::process::Stdio;
+
 use async_trait::async_trait;
-use crate::error::ServerResult;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use crate::error::{ServerError, ServerResult};
 
 #[derive(Clone, Debug)]
 pub struct HookRefUpdate {
@@ -33,6 +38,90 @@ impl ServerHook for NoOpHook {
     }
 }
 
+/// Runs external programs around a push, in the manner of git's
+/// `pre-receive` and `post-receive` hooks.
+///
+/// Each program gets one `<old-hex> <new-hex> <ref>` line per update on
+/// stdin, with all zeros for a ref that did not exist. A non-zero exit from
+/// the pre-receive program rejects every update, with its stderr as the
+/// reason. Post-receive failures are logged and otherwise ignored.
+pub struct CommandHook {
+    pre_receive: Option<PathBuf>,
+    post_receive: Option<PathBuf>,
+}
+
+impl CommandHook {
+    pub fn new(pre_receive: Option<PathBuf>, post_receive: Option<PathBuf>) -> Self {
+        Self { pre_receive, post_receive }
+    }
+
+    async fn run(program: &PathBuf, updates: &[HookRefUpdate]) -> ServerResult<std::process::Output> {
+        let mut child = Command::new(program)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|source| ServerError::Hook {
+                program: program.clone(),
+                source,
+            })?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // A hook that exits without reading its input is not an error.
+            match stdin.write_all(update_lines(updates).as_bytes()).await {
+                Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => return Err(e.into()),
+                _ => {}
+            }
+        }
+        Ok(child.wait_with_output().await?)
+    }
+}
+
+fn update_lines(updates: &[HookRefUpdate]) -> String {
+    updates
+        .iter()
+        .map(|u| {
+            let old = u.old_hash.unwrap_or([0; 32]);
+            format!("{} {} {}\n", hex(&old), hex(&u.new_hash), u.name)
+        })
+        .collect()
+}
+
+pub(crate) fn hex(bytes: &[u8; 32]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[async_trait]
+impl ServerHook for CommandHook {
+    async fn pre_receive(&self, updates: &[HookRefUpdate]) -> ServerResult<Vec<HookResult>> {
+        let Some(program) = &self.pre_receive else {
+            return Ok(updates.iter().map(|_| HookResult::Allow).collect());
+        };
+        let output = Self::run(program, updates).await?;
+        if output.status.success() {
+            return Ok(updates.iter().map(|_| HookResult::Allow).collect());
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        let reason = if stderr.is_empty() {
+            format!("pre-receive hook exited with {}", output.status)
+        } else {
+            stderr
+        };
+        Ok(updates.iter().map(|_| HookResult::Reject { reason: reason.clone() }).collect())
+    }
+
+    async fn post_receive(&self, updates: &[HookRefUpdate]) -> ServerResult<()> {
+        let Some(program) = &self.post_receive else {
+            return Ok(());
+        };
+        match Self::run(program, updates).await {
+            Ok(output) if output.status.success() => {}
+            Ok(output) => tracing::warn!("post-receive hook exited with {}", output.status),
+            Err(e) => tracing::warn!("post-receive hook failed: {e}"),
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -51,4 +140,25 @@ mod tests {
         let hook = NoOpHook;
         hook.post_receive(&[]).await.unwrap();
     }
+
+    #[test]
+    fn update_lines_use_zero_hash_for_new_refs() {
+        let updates = vec![HookRefUpdate { name: "refs/heads/main".into(), old_hash: None, new_hash: [0xab; 32] }];
+        let line = update_lines(&updates);
+        assert_eq!(line, format!("{} {} refs/heads/main\n", "0".repeat(64), "ab".repeat(32)));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn command_hook_rejects_on_failure() {
+        let updates = vec![HookRefUpdate { name: "main".into(), old_hash: None, new_hash: [1; 32] }];
+
+        let allow = CommandHook::new(Some("true".into()), Some("false".into()));
+        assert!(matches!(allow.pre_receive(&updates).await.unwrap()[0], HookResult::Allow));
+        allow.post_receive(&updates).await.unwrap();
+
+        let deny = CommandHook::new(Some("false".into()), None);
+        let results = deny.pre_receive(&updates).await.unwrap();
+        assert!(matches!(&results[0], HookResult::Reject { reason } if reason.contains("exited")));
+    }
 }
@@ -3,19 +3,54 @@
 //! Hosts remote WLL repositories over HTTP/2 with authentication,
 //! server-side hooks, and policy enforcement.
 
+pub mod advertise;
+pub mod api;
 pub mod auth;
+pub mod cluster;
 pub mod config;
 pub mod error;
+#[cfg(feature = "graphql")]
+pub mod graphql;
 pub mod handler;
 pub mod hooks;
+pub mod maintenance;
+pub mod oidc;
+#[cfg(feature = "raft")]
+pub mod raft;
 pub mod router;
 pub mod server;
+pub mod shutdown;
+pub mod trace;
+pub mod webhooks;
 
-pub use auth::{Action, AllowAllAuth, AuthProvider, Credentials, Identity};
-pub use config::{ServerConfig, TlsConfig};
-pub use error::{ServerError, ServerResult};
-pub use hooks::{HookRefUpdate, HookResult, NoOpHook, ServerHook};
-pub use server::WllServer;
+pub use advertise::RefFilter;
+pub use api::{QueryApi, ReceiptRange, ReceiptsPage, WorldlineSummary};
+pub use auth::{Action, AllowAllAuth, AuthProvider, Credentials, Identity, TokenAuth, TokenGrant};
+pub use cluster::{
+    Cluster, Coordinator, FencedLedger, FileCoordinator, ForwardedRequest, ForwardedResponse,
+    Forwarder, HttpForwarder, Lease, MemoryCoordinator, Primary, Route,
+};
+pub use config::{
+    AuthConfig, ClusterConfig, GraphqlConfig, HookConfig, MaintenanceConfig,
+    MaintenanceTaskConfig, RefAccess, RefsConfig, ServerConfig, TlsConfig,
+};
+pub use error::{Problem, ServerError, ServerResult};
+#[cfg(feature = "graphql")]
+pub use graphql::DagSource;
+pub use hooks::{CommandHook, HookRefUpdate, HookResult, NoOpHook, ServerHook};
+pub use maintenance::{
+    MaintenanceLog, MaintenanceRun, MaintenanceScheduler, MaintenanceTarget, MaintenanceTask,
+    Schedule,
+};
+pub use oidc::{OidcAuth, OidcIssuer, OidcVerifier, VerifiedToken};
+#[cfg(feature = "raft")]
+pub use raft::{HttpRaftTransport, RaftEndpoint};
+pub use server::{shutdown_signal, WllServer};
+pub use shutdown::{InFlight, InFlightGuard, ShutdownTarget};
+pub use webhooks::{
+    DeliveryAttempt, DeliveryLog, WebhookConfig, WebhookDispatcher, WebhookEvent, WebhookEventKind,
+    WebhookTransport,
+};
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,579 @@
+//! Scheduled repository maintenance.
+//!
+//! Each `[[maintenance.tasks]]` entry runs one [`MaintenanceTask`] on a
+//! cron [`Schedule`], for one repository or all of them. The
+//! [`MaintenanceScheduler`] starts tasks as they fall due, each delayed by
+//! up to `jitter_secs` so hosts sharing a schedule do not all start at
+//! once, and runs at most `max_concurrent` of them together. A task still
+//! running when it falls due again is skipped rather than queued.
+//!
+//! Every run is recorded in the [`MaintenanceLog`], which admins read at
+//! `GET /v1/admin/maintenance`, and published as a `MaintenanceCompleted`
+//! event on the repository's event fabric when it has one.
+
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use axum::extract::{Query, State};
+use axum::http::HeaderMap;
+use axum::response::Json;
+use axum::routing::get;
+use axum::Router;
+use chrono::{DateTime, Datelike, Days, TimeZone, Timelike, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
+use wll_fabric::{EventFabric, EventKind, EventPayload};
+use wll_types::WorldlineId;
+
+use crate::auth::{authorize_request, Action, AuthProvider};
+use crate::config::MaintenanceConfig;
+use crate::error::{ServerError, ServerResult};
+use crate::shutdown::InFlight;
+
+/// Runs kept in memory by a [`MaintenanceLog`].
+const LOG_CAPACITY: usize = 256;
+
+/// Steps [`Schedule::next_after`] takes before deciding a schedule never
+/// fires; enough for a leap day several years out.
+const MAX_SCHEDULE_STEPS: usize = 100_000;
+
+/// Housekeeping a repository can be given.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MaintenanceTask {
+    /// Prune history under the repository's retention settings.
+    Gc,
+    /// Pack loose objects.
+    Repack,
+    /// Re-read objects, receipt streams and packs, reporting damage.
+    Scrub,
+    /// Drop provenance older than every live receipt.
+    DagCheckpoint,
+}
+
+impl MaintenanceTask {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Gc => "gc",
+            Self::Repack => "repack",
+            Self::Scrub => "scrub",
+            Self::DagCheckpoint => "dag-checkpoint",
+        }
+    }
+}
+
+impl std::fmt::Display for MaintenanceTask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A five-field cron expression, `minute hour day-of-month month
+/// day-of-week`, read in UTC.
+///
+/// Fields take `*`, numbers, ranges (`1-5`), lists (`1,15`) and steps
+/// (`*/15`, `0-30/10`); day-of-week counts from Sunday as 0 (7 is Sunday
+/// too). As in cron, when both day fields are restricted a day matching
+/// either one fires. `@hourly`, `@daily`, `@weekly` and `@monthly` stand
+/// for the usual expressions.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Schedule {
+    expr: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Whether each day field was given as something other than `*...`.
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl Schedule {
+    /// The next time after `after`, to the minute, the schedule fires.
+    /// `None` if it never does, as for February 30th.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut t = after.with_second(0)?.with_nanosecond(0)? + chrono::Duration::minutes(1);
+        for _ in 0..MAX_SCHEDULE_STEPS {
+            if !has(self.months, t.month()) {
+                let (year, month) =
+                    if t.month() == 12 { (t.year() + 1, 1) } else { (t.year(), t.month() + 1) };
+                t = Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).single()?;
+            } else if !self.fires_on(&t) {
+                t = (t.date_naive() + Days::new(1)).and_hms_opt(0, 0, 0)?.and_utc();
+            } else if !has(self.hours, t.hour()) {
+                t = t.with_minute(0)? + chrono::Duration::hours(1);
+            } else if !has(self.minutes, t.minute()) {
+                t += chrono::Duration::minutes(1);
+            } else {
+                return Some(t);
+            }
+        }
+        None
+    }
+
+    fn fires_on(&self, t: &DateTime<Utc>) -> bool {
+        let day = has(self.days, t.day());
+        let weekday = has(self.weekdays, t.weekday().num_days_from_sunday());
+        if self.days_restricted && self.weekdays_restricted {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+}
+
+fn has(set: u64, value: u32) -> bool {
+    set & (1 << value) != 0
+}
+
+impl FromStr for Schedule {
+    type Err = ServerError;
+
+    fn from_str(expr: &str) -> ServerResult<Self> {
+        let invalid = |reason: String| ServerError::Config(format!("schedule {expr:?}: {reason}"));
+        let spec = match expr.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+        let fields: Vec<&str> = spec.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(invalid(format!("expected 5 fields, found {}", fields.len())));
+        };
+        let mut weekdays = field(weekday, 0, 7).map_err(&invalid)?;
+        if has(weekdays, 7) {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        let schedule = Self {
+            expr: expr.to_string(),
+            minutes: field(minute, 0, 59).map_err(&invalid)?,
+            hours: field(hour, 0, 23).map_err(&invalid)?,
+            days: field(day, 1, 31).map_err(&invalid)?,
+            months: field(month, 1, 12).map_err(&invalid)?,
+            weekdays,
+            days_restricted: !day.starts_with('*'),
+            weekdays_restricted: !weekday.starts_with('*'),
+        };
+        if schedule.next_after(Utc::now()).is_none() {
+            return Err(invalid("never fires".into()));
+        }
+        Ok(schedule)
+    }
+}
+
+/// The values one cron field selects, as a bit set.
+fn field(spec: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut set = 0;
+    for part in spec.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => match step.parse::<u32>() {
+                Ok(step) if step > 0 => (range, Some(step)),
+                _ => return Err(format!("bad step in {part:?}")),
+            },
+            None => (part, None),
+        };
+        let number = |s: &str| s.parse::<u32>().map_err(|_| format!("bad value in {part:?}"));
+        let (first, last) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((first, last)) => (number(first)?, number(last)?),
+            None if step.is_some() => (number(range)?, max),
+            None => (number(range)?, number(range)?),
+        };
+        if first < min || last > max || first > last {
+            return Err(format!("{part:?} is outside {min}-{max}"));
+        }
+        for value in (first..=last).step_by(step.unwrap_or(1) as usize) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
+
+impl TryFrom<String> for Schedule {
+    type Error = ServerError;
+
+    fn try_from(expr: String) -> ServerResult<Self> {
+        expr.parse()
+    }
+}
+
+impl From<Schedule> for String {
+    fn from(schedule: Schedule) -> Self {
+        schedule.expr
+    }
+}
+
+impl std::fmt::Display for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.expr)
+    }
+}
+
+/// A repository the scheduler maintains.
+///
+/// Tasks run on a blocking thread, one at a time per task kind.
+pub trait MaintenanceTarget: Send + Sync {
+    /// Run `task` to completion, returning a one-line summary of what it
+    /// did, or why it failed.
+    fn run(&self, task: MaintenanceTask) -> Result<String, String>;
+
+    /// The event fabric finished runs are published on, and the worldline
+    /// their events are stamped with.
+    fn fabric(&self) -> Option<(Arc<EventFabric>, WorldlineId)> {
+        None
+    }
+}
+
+/// One finished maintenance run.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceRun {
+    pub repo: String,
+    pub task: MaintenanceTask,
+    pub started_ms: u64,
+    pub finished_ms: u64,
+    pub succeeded: bool,
+    /// The task's summary, or its error.
+    pub detail: String,
+}
+
+/// Recent maintenance runs, newest last, optionally appended to a JSON
+/// Lines file as well.
+pub struct MaintenanceLog {
+    recent: Mutex<VecDeque<MaintenanceRun>>,
+    path: Option<PathBuf>,
+}
+
+impl MaintenanceLog {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self { recent: Mutex::new(VecDeque::with_capacity(LOG_CAPACITY)), path }
+    }
+
+    pub fn recent(&self) -> Vec<MaintenanceRun> {
+        self.recent.lock().unwrap_or_else(|e| e.into_inner()).iter().cloned().collect()
+    }
+
+    fn record(&self, run: MaintenanceRun) {
+        if let Some(path) = &self.path {
+            if let Err(e) = Self::append(path, &run) {
+                tracing::warn!("maintenance log {}: {e}", path.display());
+            }
+        }
+        let mut recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+        if recent.len() == LOG_CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(run);
+    }
+
+    fn append(path: &Path, run: &MaintenanceRun) -> std::io::Result<()> {
+        let mut line = serde_json::to_vec(run)?;
+        line.push(b'\n');
+        std::fs::OpenOptions::new().create(true).append(true).open(path)?.write_all(&line)
+    }
+}
+
+/// Runs the `[maintenance]` tasks of the repositories given to it.
+#[derive(Clone)]
+pub struct MaintenanceScheduler {
+    config: Arc<MaintenanceConfig>,
+    targets: BTreeMap<String, Arc<dyn MaintenanceTarget>>,
+    log: Arc<MaintenanceLog>,
+    permits: Arc<Semaphore>,
+    running: Arc<Mutex<HashSet<(String, MaintenanceTask)>>>,
+    /// Runs whose blocking work has not returned, even if the job that
+    /// started them was aborted.
+    active: InFlight,
+}
+
+impl MaintenanceScheduler {
+    pub fn new(config: MaintenanceConfig) -> Self {
+        let log = MaintenanceLog::new(config.log.clone());
+        let permits = Semaphore::new(config.max_concurrent.max(1));
+        Self {
+            config: Arc::new(config),
+            targets: BTreeMap::new(),
+            log: Arc::new(log),
+            permits: Arc::new(permits),
+            running: Arc::default(),
+            active: InFlight::new(),
+        }
+    }
+
+    /// Maintain `target` under the name `repo`.
+    pub fn with_target(
+        mut self,
+        repo: impl Into<String>,
+        target: Arc<dyn MaintenanceTarget>,
+    ) -> Self {
+        self.targets.insert(repo.into(), target);
+        self
+    }
+
+    pub fn log(&self) -> &MaintenanceLog {
+        &self.log
+    }
+
+    /// Each repository and task with its schedule.
+    pub fn jobs(&self) -> Vec<(String, MaintenanceTask, Schedule)> {
+        let mut jobs = Vec::new();
+        for entry in &self.config.tasks {
+            for repo in self.targets.keys() {
+                if entry.repo.as_ref().map_or(true, |only| only == repo) {
+                    jobs.push((repo.clone(), entry.task, entry.schedule.clone()));
+                }
+            }
+        }
+        jobs
+    }
+
+    /// Run every job on its schedule in the background until the returned
+    /// tasks are aborted or the runtime shuts down. Must be called inside
+    /// a Tokio runtime.
+    pub fn start(&self) -> Vec<JoinHandle<()>> {
+        self.jobs()
+            .into_iter()
+            .map(|(repo, task, schedule)| {
+                let scheduler = self.clone();
+                tokio::spawn(async move {
+                    while let Some(next) = schedule.next_after(Utc::now()) {
+                        let wait = (next - Utc::now()).to_std().unwrap_or_default();
+                        tokio::time::sleep(wait + scheduler.jitter()).await;
+                        scheduler.run(&repo, task).await;
+                    }
+                    tracing::warn!("maintenance schedule {schedule} for {repo} never fires again");
+                })
+            })
+            .collect()
+    }
+
+    fn jitter(&self) -> Duration {
+        match self.config.jitter_secs {
+            0 => Duration::ZERO,
+            secs => Duration::from_millis(rand::thread_rng().gen_range(0..secs * 1000)),
+        }
+    }
+
+    /// Completes once no task is running.
+    pub async fn idle(&self) {
+        self.active.idle().await;
+    }
+
+    /// Run `task` on `repo` now, once a concurrency slot is free. `None`
+    /// if the repository is unknown or the task is already running on it.
+    pub async fn run(&self, repo: &str, task: MaintenanceTask) -> Option<MaintenanceRun> {
+        let target = self.targets.get(repo)?.clone();
+        let key = (repo.to_string(), task);
+        if !self.running.lock().unwrap_or_else(|e| e.into_inner()).insert(key.clone()) {
+            tracing::info!("skipping {task} on {repo}: the previous run has not finished");
+            return None;
+        }
+        let run = {
+            let _permit = self.permits.acquire().await.ok()?;
+            let started_ms = now_ms();
+            let worker = target.clone();
+            let active = self.active.enter();
+            let result = tokio::task::spawn_blocking(move || {
+                let _active = active;
+                worker.run(task)
+            })
+                .await
+                .unwrap_or_else(|e| Err(format!("task panicked: {e}")));
+            MaintenanceRun {
+                repo: repo.to_string(),
+                task,
+                started_ms,
+                finished_ms: now_ms(),
+                succeeded: result.is_ok(),
+                detail: result.unwrap_or_else(|e| e),
+            }
+        };
+        self.running.lock().unwrap_or_else(|e| e.into_inner()).remove(&key);
+
+        if run.succeeded {
+            tracing::info!("{task} on {repo}: {}", run.detail);
+        } else {
+            tracing::warn!("{task} on {repo} failed: {}", run.detail);
+        }
+        if let Some((fabric, worldline)) = target.fabric() {
+            let payload = EventPayload::Maintenance {
+                task: task.as_str().to_string(),
+                succeeded: run.succeeded,
+                detail: run.detail.clone(),
+            };
+            if let Err(e) = fabric.emit(worldline, EventKind::MaintenanceCompleted, payload) {
+                tracing::warn!("cannot publish {task} run on {repo}: {e}");
+            }
+        }
+        self.log.record(run.clone());
+        Some(run)
+    }
+
+    /// `GET /v1/admin/maintenance[?repo=]`: the recorded runs, newest
+    /// last, for callers authorized for [`Action::Admin`] on every
+    /// repository listed.
+    pub fn router(self, auth: Arc<dyn AuthProvider>) -> Router {
+        Router::new()
+            .route("/v1/admin/maintenance", get(history))
+            .with_state(Arc::new(Admin { scheduler: self, auth }))
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64)
+}
+
+struct Admin {
+    scheduler: MaintenanceScheduler,
+    auth: Arc<dyn AuthProvider>,
+}
+
+/// Query string of `/v1/admin/maintenance`.
+#[derive(Debug, Default, Deserialize)]
+struct HistoryQuery {
+    repo: Option<String>,
+}
+
+async fn history(
+    State(admin): State<Arc<Admin>>,
+    headers: HeaderMap,
+    Query(query): Query<HistoryQuery>,
+) -> ServerResult<Json<Vec<MaintenanceRun>>> {
+    let repos: Vec<&String> = match &query.repo {
+        Some(repo) => vec![repo],
+        None => admin.scheduler.targets.keys().collect(),
+    };
+    for repo in &repos {
+        let action = Action::Admin { repo: repo.to_string() };
+        authorize_request(admin.auth.as_ref(), &headers, action).await?;
+    }
+    let runs = admin.scheduler.log.recent().into_iter().filter(|run| repos.contains(&&run.repo));
+    Ok(Json(runs.collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::{to_bytes, Body};
+    use axum::http::{header, Request, StatusCode};
+    use tower::util::ServiceExt;
+    use wll_fabric::FabricConfig;
+    use wll_types::IdentityMaterial;
+
+    use super::*;
+    use crate::auth::{TokenAuth, TokenGrant};
+    use crate::config::{MaintenanceTaskConfig, ServerConfig};
+
+    fn at(expr: &str, after: &str) -> String {
+        let schedule: Schedule = expr.parse().unwrap();
+        let after = DateTime::parse_from_rfc3339(after).unwrap().with_timezone(&Utc);
+        schedule.next_after(after).unwrap().to_rfc3339()
+    }
+
+    #[test]
+    fn schedules_fire_at_the_next_matching_minute() {
+        assert_eq!(at("*/15 * * * *", "2026-03-01T10:07:30Z"), "2026-03-01T10:15:00+00:00");
+        assert_eq!(at("@daily", "2026-12-31T23:59:00Z"), "2027-01-01T00:00:00+00:00");
+        assert_eq!(at("30 2 * * 1-5", "2026-10-16T03:00:00Z"), "2026-10-19T02:30:00+00:00");
+        // Either restricted day field fires: the 1st, or any Sunday.
+        assert_eq!(at("0 4 1 * 7", "2026-10-02T00:00:00Z"), "2026-10-04T04:00:00+00:00");
+        assert_eq!(at("0 0 29 2 *", "2026-03-01T00:00:00Z"), "2028-02-29T00:00:00+00:00");
+
+        for bad in ["* * * *", "60 * * * *", "*/0 * * * *", "5-1 * * * *", "0 0 30 2 *"] {
+            assert!(matches!(bad.parse::<Schedule>(), Err(ServerError::Config(_))), "{bad}");
+        }
+        let config = ServerConfig::from_toml(
+            r#"
+            [maintenance]
+            max_concurrent = 2
+
+            [[maintenance.tasks]]
+            task = "dag-checkpoint"
+            schedule = "@weekly"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.maintenance.tasks[0].task, MaintenanceTask::DagCheckpoint);
+        let typo = "[[maintenance.tasks]]\ntask = \"gc\"\nschedule = \"daily\"\n";
+        assert!(ServerConfig::from_toml(typo).is_err());
+    }
+
+    /// Succeeds at everything but scrubbing, announcing runs on `fabric`.
+    struct Repo {
+        fabric: Arc<EventFabric>,
+    }
+
+    impl MaintenanceTarget for Repo {
+        fn run(&self, task: MaintenanceTask) -> Result<String, String> {
+            match task {
+                MaintenanceTask::Scrub => Err("pack-1.pack: checksum mismatch".into()),
+                _ => Ok(format!("{task} done")),
+            }
+        }
+
+        fn fabric(&self) -> Option<(Arc<EventFabric>, WorldlineId)> {
+            let worldline = WorldlineId::derive(&IdentityMaterial::GenesisHash([8; 32]));
+            Some((self.fabric.clone(), worldline))
+        }
+    }
+
+    #[tokio::test]
+    async fn runs_are_recorded_published_and_shown_to_admins() {
+        let dir = tempfile::tempdir().unwrap();
+        let fabric = EventFabric::new(&dir.path().join("fabric.wal"), FabricConfig::default());
+        let fabric = Arc::new(fabric.unwrap());
+        let config = MaintenanceConfig {
+            tasks: vec![MaintenanceTaskConfig {
+                task: MaintenanceTask::Gc,
+                schedule: "@daily".parse().unwrap(),
+                repo: Some("acme/site".into()),
+            }],
+            ..MaintenanceConfig::default()
+        };
+        let repo = Arc::new(Repo { fabric: fabric.clone() });
+        let scheduler = MaintenanceScheduler::new(config)
+            .with_target("acme/site", repo.clone())
+            .with_target("acme/docs", repo);
+        assert_eq!(scheduler.jobs().len(), 1);
+
+        let gc = scheduler.run("acme/site", MaintenanceTask::Gc).await.unwrap();
+        assert!(gc.succeeded && gc.detail == "gc done");
+        let scrub = scheduler.run("acme/docs", MaintenanceTask::Scrub).await.unwrap();
+        assert!(!scrub.succeeded);
+        assert!(scheduler.run("acme/blog", MaintenanceTask::Gc).await.is_none());
+        let events = fabric.recover().unwrap();
+        assert!(events.iter().all(|e| e.kind == EventKind::MaintenanceCompleted));
+        assert!(matches!(
+            &events[1].payload,
+            EventPayload::Maintenance { task, succeeded: false, .. } if task == "scrub"
+        ));
+
+        let grants = vec![
+            TokenGrant { token: "root".into(), name: "ops".into(), admin: true },
+            TokenGrant { token: "dev".into(), name: "dev".into(), admin: false },
+        ];
+        let auth = Arc::new(TokenAuth::new(grants, true));
+        let get = |token: &str, uri: &str| {
+            let request = Request::builder()
+                .uri(uri)
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .body(Body::empty())
+                .unwrap();
+            scheduler.clone().router(auth.clone()).oneshot(request)
+        };
+        let response = get("dev", "/v1/admin/maintenance").await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = get("root", "/v1/admin/maintenance?repo=acme/site").await.unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let runs: Vec<MaintenanceRun> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(runs, [gc]);
+    }
+}
@@ -0,0 +1,519 @@
+//! OpenID Connect bearer tokens, verified against each issuer's key set.
+//!
+//! A token is accepted only if its `iss` names a configured issuer, its
+//! header picks one of that issuer's published keys (by `kid`, or the only
+//! key), the signature verifies with that key under an asymmetric
+//! algorithm the key allows, and its `aud` and `exp` check out. Shared
+//! secret (`HS*`) tokens are never accepted, so a public key can't be
+//! replayed as an HMAC secret.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use jsonwebtoken::jwk::{AlgorithmParameters, Jwk, JwkSet};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use wll_types::{AttestationKind, IdentityAttestation, WorldlineId};
+
+use crate::auth::{Action, AuthProvider, Credentials, Identity};
+use crate::error::{ServerError, ServerResult};
+
+/// An identity provider whose ID tokens the server accepts.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OidcIssuer {
+    /// Expected `iss` claim, e.g. `https://token.actions.githubusercontent.com`.
+    pub issuer: String,
+    /// Accepted `aud` values; a token must name one of them.
+    pub audiences: Vec<String>,
+    /// What the issuer's subjects are: people or workloads.
+    #[serde(default = "default_kind")]
+    pub kind: AttestationKind,
+    /// Signing keys, as published at the issuer's `jwks_uri`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jwks: Option<JwkSet>,
+    /// JSON file holding the key set, read when the config is loaded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jwks_path: Option<PathBuf>,
+    /// Claim used as the identity name; defaults to `sub`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_claim: Option<String>,
+    /// Subjects granted admin rights.
+    #[serde(default)]
+    pub admins: Vec<String>,
+}
+
+fn default_kind() -> AttestationKind {
+    AttestationKind::Human
+}
+
+impl OidcIssuer {
+    /// Issuer `issuer` accepting tokens for `audience`, signed by `jwks`.
+    pub fn new(issuer: impl Into<String>, audience: impl Into<String>, jwks: JwkSet) -> Self {
+        Self {
+            issuer: issuer.into(),
+            audiences: vec![audience.into()],
+            kind: default_kind(),
+            jwks: Some(jwks),
+            jwks_path: None,
+            name_claim: None,
+            admins: Vec::new(),
+        }
+    }
+
+    pub fn with_kind(mut self, kind: AttestationKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn with_name_claim(mut self, claim: impl Into<String>) -> Self {
+        self.name_claim = Some(claim.into());
+        self
+    }
+
+    pub fn with_admin(mut self, subject: impl Into<String>) -> Self {
+        self.admins.push(subject.into());
+        self
+    }
+
+    /// Read `jwks_path` into `jwks` unless keys are given inline.
+    pub fn load_jwks(&mut self) -> ServerResult<()> {
+        if self.jwks.is_some() {
+            return Ok(());
+        }
+        let Some(path) = &self.jwks_path else {
+            return Err(ServerError::Config(format!(
+                "OIDC issuer {} needs jwks or jwks_path",
+                self.issuer
+            )));
+        };
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| ServerError::Config(format!("{}: {e}", path.display())))?;
+        let jwks = serde_json::from_str(&text)
+            .map_err(|e| ServerError::Config(format!("{}: {e}", path.display())))?;
+        self.jwks = Some(jwks);
+        Ok(())
+    }
+
+    /// The key a token with header key id `kid` was signed with.
+    fn key(&self, kid: Option<&str>) -> ServerResult<&Jwk> {
+        let keys = self
+            .jwks
+            .as_ref()
+            .map(|set| set.keys.as_slice())
+            .unwrap_or_default();
+        let key = match kid {
+            Some(kid) => keys
+                .iter()
+                .find(|jwk| jwk.common.key_id.as_deref() == Some(kid)),
+            None if keys.len() == 1 => keys.first(),
+            None => None,
+        };
+        key.ok_or_else(|| {
+            ServerError::AuthFailed(format!(
+                "no signing key {} for issuer {}",
+                kid.unwrap_or("(unnamed)"),
+                self.issuer
+            ))
+        })
+    }
+}
+
+/// The verified claims of an OIDC token.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct VerifiedToken {
+    pub issuer: String,
+    pub subject: String,
+    pub kind: AttestationKind,
+    /// Identity name from the issuer's `name_claim`, else the subject.
+    pub name: String,
+    /// Remaining string-valued claims.
+    pub claims: BTreeMap<String, String>,
+    pub issued_at: u64,
+    pub expires_at: u64,
+    /// BLAKE3 digest of the token.
+    pub token_digest: [u8; 32],
+}
+
+impl VerifiedToken {
+    /// An attestation binding this identity to `worldline`.
+    pub fn attest(&self, worldline: WorldlineId) -> IdentityAttestation {
+        IdentityAttestation {
+            worldline,
+            issuer: self.issuer.clone(),
+            subject: self.subject.clone(),
+            kind: self.kind,
+            claims: self.claims.clone(),
+            issued_at: self.issued_at,
+            expires_at: Some(self.expires_at),
+            token_digest: self.token_digest,
+        }
+    }
+}
+
+/// Checks OIDC tokens against a fixed set of trusted issuers.
+#[derive(Clone, Debug)]
+pub struct OidcVerifier {
+    issuers: Vec<OidcIssuer>,
+    leeway_secs: u64,
+}
+
+impl OidcVerifier {
+    pub fn new(issuers: Vec<OidcIssuer>) -> Self {
+        Self {
+            issuers,
+            leeway_secs: 60,
+        }
+    }
+
+    /// Clock skew tolerated on `exp` and `nbf`; 60 seconds by default.
+    pub fn with_leeway(mut self, secs: u64) -> Self {
+        self.leeway_secs = secs;
+        self
+    }
+
+    pub fn issuers(&self) -> &[OidcIssuer] {
+        &self.issuers
+    }
+
+    /// Verify `token` and return its claims.
+    pub fn verify(&self, token: &str) -> ServerResult<VerifiedToken> {
+        let header = jsonwebtoken::decode_header(token).map_err(invalid)?;
+        if matches!(
+            header.alg,
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+        ) {
+            return Err(ServerError::AuthFailed(format!(
+                "token algorithm {:?} is not accepted",
+                header.alg
+            )));
+        }
+
+        // The issuer picks the key set, so read it before the signature is
+        // checked; nothing else from the unverified payload is used.
+        let unverified = {
+            let mut validation = Validation::new(header.alg);
+            validation.insecure_disable_signature_validation();
+            validation.validate_exp = false;
+            validation.validate_aud = false;
+            validation.required_spec_claims.clear();
+            jsonwebtoken::decode::<UnverifiedIssuer>(
+                token,
+                &DecodingKey::from_secret(&[]),
+                &validation,
+            )
+            .map_err(invalid)?
+            .claims
+        };
+        let issuer = self
+            .issuers
+            .iter()
+            .find(|i| i.issuer == unverified.iss)
+            .ok_or_else(|| {
+                ServerError::AuthFailed(format!("untrusted issuer {}", unverified.iss))
+            })?;
+
+        let jwk = issuer.key(header.kid.as_deref())?;
+        if matches!(jwk.algorithm, AlgorithmParameters::OctetKey(_)) {
+            return Err(ServerError::AuthFailed(format!(
+                "issuer {} publishes a symmetric key",
+                issuer.issuer
+            )));
+        }
+        if let Some(alg) = jwk.common.key_algorithm {
+            if alg.to_string() != format!("{:?}", header.alg) {
+                return Err(ServerError::AuthFailed(format!(
+                    "key is for {alg}, token is signed with {:?}",
+                    header.alg
+                )));
+            }
+        }
+        let key = DecodingKey::from_jwk(jwk).map_err(invalid)?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.leeway = self.leeway_secs;
+        validation.set_issuer(&[&issuer.issuer]);
+        validation.set_audience(&issuer.audiences);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+        let mut claims = jsonwebtoken::decode::<BTreeMap<String, Value>>(token, &key, &validation)
+            .map_err(invalid)?
+            .claims;
+
+        let number = |claims: &BTreeMap<String, Value>, name: &str| {
+            claims.get(name).and_then(Value::as_u64).unwrap_or_default()
+        };
+        let issued_at = number(&claims, "iat");
+        let expires_at = number(&claims, "exp");
+        let subject = match claims.remove("sub") {
+            Some(Value::String(sub)) => sub,
+            _ => {
+                return Err(ServerError::AuthFailed(
+                    "token subject is not a string".into(),
+                ))
+            }
+        };
+        claims.remove("iss");
+        let claims: BTreeMap<String, String> = claims
+            .into_iter()
+            .filter_map(|(name, value)| match value {
+                Value::String(s) => Some((name, s)),
+                _ => None,
+            })
+            .collect();
+        let name = issuer
+            .name_claim
+            .as_ref()
+            .and_then(|claim| claims.get(claim))
+            .cloned()
+            .unwrap_or_else(|| subject.clone());
+
+        Ok(VerifiedToken {
+            issuer: issuer.issuer.clone(),
+            subject,
+            kind: issuer.kind,
+            name,
+            claims,
+            issued_at,
+            expires_at,
+            token_digest: IdentityAttestation::digest_token(token),
+        })
+    }
+
+    fn is_admin(&self, token: &VerifiedToken) -> bool {
+        self.issuers
+            .iter()
+            .any(|i| i.issuer == token.issuer && i.admins.contains(&token.subject))
+    }
+}
+
+#[derive(Deserialize)]
+struct UnverifiedIssuer {
+    iss: String,
+}
+
+fn invalid(e: jsonwebtoken::errors::Error) -> ServerError {
+    ServerError::AuthFailed(format!("invalid token: {e}"))
+}
+
+/// Authenticates OIDC bearer tokens with an [`OidcVerifier`].
+///
+/// Permissions match [`crate::TokenAuth`]: verified identities may read
+/// and write, subjects listed in their issuer's `admins` may do anything,
+/// and anonymous clients may read when `allow_anonymous_read` is set.
+pub struct OidcAuth {
+    verifier: OidcVerifier,
+    allow_anonymous_read: bool,
+}
+
+impl OidcAuth {
+    pub fn new(verifier: OidcVerifier, allow_anonymous_read: bool) -> Self {
+        Self {
+            verifier,
+            allow_anonymous_read,
+        }
+    }
+
+    pub fn verifier(&self) -> &OidcVerifier {
+        &self.verifier
+    }
+}
+
+#[async_trait]
+impl AuthProvider for OidcAuth {
+    async fn authenticate(&self, credentials: &Credentials) -> ServerResult<Identity> {
+        match credentials {
+            Credentials::Bearer(token) => {
+                let verified = self.verifier.verify(token.expose())?;
+                let mut identity = if self.verifier.is_admin(&verified) {
+                    Identity::admin(&verified.name)
+                } else {
+                    Identity::user(&verified.name)
+                };
+                identity.oidc = Some(verified);
+                Ok(identity)
+            }
+            Credentials::Anonymous => Ok(Identity::anonymous()),
+        }
+    }
+
+    async fn authorize(&self, identity: &Identity, action: &Action) -> ServerResult<bool> {
+        Ok(identity.may(action, self.allow_anonymous_read))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+    use jsonwebtoken::{EncodingKey, Header};
+    use serde_json::json;
+
+    const ISSUER: &str = "https://ci.example";
+    const SEED: [u8; 32] = [7; 32];
+
+    /// PKCS#8 v1 wrapping of an Ed25519 seed.
+    fn signing_key() -> EncodingKey {
+        let mut der = vec![
+            0x30, 0x2e, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x04, 0x22,
+            0x04, 0x20,
+        ];
+        der.extend_from_slice(&SEED);
+        EncodingKey::from_ed_der(&der)
+    }
+
+    fn jwks() -> JwkSet {
+        let public = ed25519_dalek::SigningKey::from_bytes(&SEED).verifying_key();
+        serde_json::from_value(json!({
+            "keys": [{
+                "kty": "OKP",
+                "crv": "Ed25519",
+                "kid": "k1",
+                "alg": "EdDSA",
+                "x": URL_SAFE_NO_PAD.encode(public.as_bytes()),
+            }]
+        }))
+        .unwrap()
+    }
+
+    fn now() -> u64 {
+        jsonwebtoken::get_current_timestamp()
+    }
+
+    fn token(claims: Value) -> String {
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = Some("k1".into());
+        jsonwebtoken::encode(&header, &claims, &signing_key()).unwrap()
+    }
+
+    fn claims() -> Value {
+        json!({
+            "iss": ISSUER,
+            "sub": "repo:team/app:ref:refs/heads/main",
+            "aud": "wll",
+            "iat": now(),
+            "exp": now() + 600,
+            "job_workflow_ref": "team/app/.github/workflows/release.yml@refs/heads/main",
+        })
+    }
+
+    fn verifier() -> OidcVerifier {
+        OidcVerifier::new(vec![OidcIssuer::new(ISSUER, "wll", jwks())
+            .with_kind(AttestationKind::Workload)
+            .with_name_claim("job_workflow_ref")])
+    }
+
+    #[test]
+    fn verifies_signature_and_claims() {
+        let token = token(claims());
+        let verified = verifier().verify(&token).unwrap();
+        assert_eq!(verified.subject, "repo:team/app:ref:refs/heads/main");
+        assert_eq!(verified.kind, AttestationKind::Workload);
+        assert!(verified.name.starts_with("team/app/.github"));
+        assert_eq!(verified.claims.get("aud").map(String::as_str), Some("wll"));
+        assert!(!verified.claims.contains_key("sub"));
+
+        let worldline = WorldlineId::derive(&wll_types::IdentityMaterial::GenesisHash([1; 32]));
+        let attestation = verified.attest(worldline.clone());
+        assert!(attestation.satisfies(&worldline, Some(AttestationKind::Workload), now()));
+        assert_eq!(
+            attestation.token_digest,
+            IdentityAttestation::digest_token(&token)
+        );
+    }
+
+    #[test]
+    fn rejects_bad_tokens() {
+        let verifier = verifier();
+        let rejects = |claims: Value| {
+            matches!(
+                verifier.verify(&token(claims)),
+                Err(ServerError::AuthFailed(_))
+            )
+        };
+
+        let mut c = claims();
+        c["aud"] = json!("other");
+        assert!(rejects(c));
+        let mut c = claims();
+        c["exp"] = json!(now() - 3600);
+        assert!(rejects(c));
+        let mut c = claims();
+        c["iss"] = json!("https://evil.example");
+        assert!(rejects(c));
+
+        // Tampered payload.
+        let good = token(claims());
+        let mut parts: Vec<&str> = good.split('.').collect();
+        let forged =
+            token(json!({ "iss": ISSUER, "sub": "admin", "aud": "wll", "exp": now() + 600 }));
+        parts[1] = forged.split('.').nth(1).unwrap();
+        assert!(verifier.verify(&parts.join(".")).is_err());
+
+        // A shared-secret token is refused before any key is consulted.
+        let hmac = jsonwebtoken::encode(
+            &Header::new(Algorithm::HS256),
+            &claims(),
+            &EncodingKey::from_secret(b"secret"),
+        )
+        .unwrap();
+        assert!(verifier.verify(&hmac).is_err());
+
+        // Unknown key id.
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = Some("k2".into());
+        let other_kid = jsonwebtoken::encode(&header, &claims(), &signing_key()).unwrap();
+        assert!(verifier.verify(&other_kid).is_err());
+    }
+
+    #[tokio::test]
+    async fn oidc_auth_maps_admins_and_permissions() {
+        let issuer = OidcIssuer::new(ISSUER, "wll", jwks()).with_admin("root");
+        let auth = OidcAuth::new(OidcVerifier::new(vec![issuer]), false);
+
+        let mut c = claims();
+        c["sub"] = json!("alice");
+        let alice = auth
+            .authenticate(&Credentials::Bearer(token(c).into()))
+            .await
+            .unwrap();
+        assert_eq!(alice.name, "alice");
+        assert_eq!(alice.oidc.as_ref().unwrap().kind, AttestationKind::Human);
+        assert!(auth
+            .authorize(&alice, &Action::Write { repo: "r".into() })
+            .await
+            .unwrap());
+        assert!(!auth.authorize(&alice, &Action::CreateRepo).await.unwrap());
+
+        let mut c = claims();
+        c["sub"] = json!("root");
+        let root = auth
+            .authenticate(&Credentials::Bearer(token(c).into()))
+            .await
+            .unwrap();
+        assert!(root.is_admin);
+
+        let anon = auth.authenticate(&Credentials::Anonymous).await.unwrap();
+        assert!(!auth
+            .authorize(&anon, &Action::Read { repo: "r".into() })
+            .await
+            .unwrap());
+    }
+
+    #[test]
+    fn load_jwks_reads_key_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("jwks.json");
+        std::fs::write(&path, serde_json::to_string(&jwks()).unwrap()).unwrap();
+
+        let mut issuer = OidcIssuer::new(ISSUER, "wll", jwks());
+        issuer.jwks = None;
+        assert!(matches!(issuer.load_jwks(), Err(ServerError::Config(_))));
+        issuer.jwks_path = Some(path);
+        issuer.load_jwks().unwrap();
+        assert!(OidcVerifier::new(vec![issuer])
+            .verify(&token(claims()))
+            .is_ok());
+    }
+}
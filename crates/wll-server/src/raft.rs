@@ -0,0 +1,262 @@
+//! Raft messages between ledger nodes over HTTP (`raft` feature).
+//!
+//! A [`wll_ledger::RaftLedger`] node sends its requests to the others
+//! through an [`HttpRaftTransport`], and answers theirs on the routes
+//! [`RaftEndpoint::router`] adds:
+//!
+//! - `POST /v1/raft/append-entries` — entries from the leader, or a heartbeat
+//! - `POST /v1/raft/request-vote` — a candidate asking for this node's vote
+//! - `GET /v1/raft/status` — the node's role, term, leader and log indexes
+//!
+//! Each is authorized as [`Action::Admin`] on the replicated repository,
+//! so nodes call each other with a token granting that.
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use wll_ledger::{
+    AppendEntries, AppendEntriesReply, LedgerError, NodeId, RaftService, RaftStatus,
+    RaftTransport, VoteReply, VoteRequest,
+};
+use wll_crypto::Secret;
+
+use crate::auth::{authorize_request, Action, AuthProvider};
+use crate::error::{ServerError, ServerResult};
+
+pub const APPEND_ENTRIES: &str = "/v1/raft/append-entries";
+pub const REQUEST_VOTE: &str = "/v1/raft/request-vote";
+pub const STATUS: &str = "/v1/raft/status";
+
+/// The raft routes of one node, answered by its ledger.
+#[derive(Clone)]
+pub struct RaftEndpoint {
+    repo: String,
+    service: Arc<dyn RaftService>,
+    auth: Arc<dyn AuthProvider>,
+}
+
+impl RaftEndpoint {
+    pub fn new(
+        repo: impl Into<String>,
+        service: Arc<dyn RaftService>,
+        auth: Arc<dyn AuthProvider>,
+    ) -> Self {
+        Self { repo: repo.into(), service, auth }
+    }
+
+    pub fn router(self) -> Router {
+        Router::new()
+            .route(APPEND_ENTRIES, post(append_entries))
+            .route(REQUEST_VOTE, post(request_vote))
+            .route(STATUS, get(status))
+            .with_state(Arc::new(self))
+    }
+
+    async fn authorize(&self, headers: &HeaderMap) -> ServerResult<()> {
+        let action = Action::Admin { repo: self.repo.clone() };
+        authorize_request(self.auth.as_ref(), headers, action).await.map(drop)
+    }
+
+    /// Run `call` on a blocking thread: answering may write and sync the
+    /// raft log and the ledger.
+    async fn call<T: Send + 'static>(
+        &self,
+        call: impl FnOnce(&dyn RaftService) -> Result<T, LedgerError> + Send + 'static,
+    ) -> ServerResult<T> {
+        let service = self.service.clone();
+        tokio::task::spawn_blocking(move || call(service.as_ref()))
+            .await
+            .map_err(|e| ServerError::Internal(format!("raft request panicked: {e}")))?
+            .map_err(ServerError::from)
+    }
+}
+
+async fn append_entries(
+    State(endpoint): State<Arc<RaftEndpoint>>,
+    headers: HeaderMap,
+    Json(request): Json<AppendEntries>,
+) -> ServerResult<Json<AppendEntriesReply>> {
+    endpoint.authorize(&headers).await?;
+    endpoint.call(move |service| service.append_entries(&request)).await.map(Json)
+}
+
+async fn request_vote(
+    State(endpoint): State<Arc<RaftEndpoint>>,
+    headers: HeaderMap,
+    Json(request): Json<VoteRequest>,
+) -> ServerResult<Json<VoteReply>> {
+    endpoint.authorize(&headers).await?;
+    endpoint.call(move |service| service.request_vote(&request)).await.map(Json)
+}
+
+async fn status(
+    State(endpoint): State<Arc<RaftEndpoint>>,
+    headers: HeaderMap,
+) -> ServerResult<Json<RaftStatus>> {
+    endpoint.authorize(&headers).await?;
+    Ok(Json(endpoint.service.status()))
+}
+
+/// [`RaftTransport`] over `ureq`, posting to each peer's raft routes.
+pub struct HttpRaftTransport {
+    peers: HashMap<NodeId, String>,
+    token: Option<Secret>,
+    timeout: Duration,
+}
+
+impl HttpRaftTransport {
+    /// Transport to `peers`, each a node id and the base URL it serves on.
+    pub fn new(peers: impl IntoIterator<Item = (NodeId, String)>) -> Self {
+        let peers = peers
+            .into_iter()
+            .map(|(id, url)| (id, url.trim_end_matches('/').to_string()))
+            .collect();
+        Self { peers, token: None, timeout: Duration::from_secs(1) }
+    }
+
+    /// Send `token` as a bearer token with every request.
+    pub fn with_token(mut self, token: impl Into<Secret>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Give up on a peer that has not answered within `timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn post<Q: Serialize, A: DeserializeOwned>(
+        &self,
+        peer: &str,
+        path: &str,
+        request: &Q,
+    ) -> Result<A, LedgerError> {
+        let failed =
+            |reason: String| LedgerError::Replication(format!("raft peer {peer}: {reason}"));
+        let base = self.peers.get(peer).ok_or_else(|| failed("no address".into()))?;
+        let body = serde_json::to_vec(request)
+            .map_err(|e| LedgerError::Serialization(e.to_string()))?;
+        let agent = ureq::AgentBuilder::new().timeout(self.timeout).build();
+        let mut call =
+            agent.post(&format!("{base}{path}")).set("content-type", "application/json");
+        if let Some(token) = &self.token {
+            call = call.set("authorization", &format!("Bearer {}", token.expose()));
+        }
+        let response = match call.send_bytes(&body) {
+            Ok(response) => response,
+            Err(ureq::Error::Status(status, _)) => return Err(failed(format!("status {status}"))),
+            Err(e) => return Err(failed(e.to_string())),
+        };
+        let mut reply = Vec::new();
+        response.into_reader().read_to_end(&mut reply).map_err(|e| failed(e.to_string()))?;
+        serde_json::from_slice(&reply).map_err(|e| failed(format!("unreadable reply: {e}")))
+    }
+}
+
+impl RaftTransport for HttpRaftTransport {
+    fn append_entries(
+        &self,
+        peer: &str,
+        request: &AppendEntries,
+    ) -> Result<AppendEntriesReply, LedgerError> {
+        self.post(peer, APPEND_ENTRIES, request)
+    }
+
+    fn request_vote(&self, peer: &str, request: &VoteRequest) -> Result<VoteReply, LedgerError> {
+        self.post(peer, REQUEST_VOTE, request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use wll_ledger::{
+        CommitmentProposal, Decision, InMemoryLedger, LedgerReader, LedgerWriter, RaftConfig,
+        RaftLedger, Role,
+    };
+    use wll_types::{
+        CommitmentClass, CommitmentId, EvidenceBundle, IdentityMaterial, WorldlineId,
+    };
+
+    use super::*;
+    use crate::auth::{TokenAuth, TokenGrant};
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn nodes_replicate_over_http_with_an_admin_token() {
+        let grants = vec![
+            TokenGrant { token: "node".into(), name: "raft".into(), admin: true },
+            TokenGrant { token: "dev".into(), name: "dev".into(), admin: false },
+        ];
+        let auth = Arc::new(TokenAuth::new(grants, true));
+        let mut listeners = Vec::new();
+        let mut peers = Vec::new();
+        for id in ["a", "b", "c"] {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            peers.push((id.to_string(), format!("http://{}", listener.local_addr().unwrap())));
+            listeners.push(listener);
+        }
+        let ids: Vec<NodeId> = peers.iter().map(|(id, _)| id.clone()).collect();
+        let config =
+            RaftConfig { election_timeout: Duration::from_millis(1), ..Default::default() };
+        let mut nodes = Vec::new();
+        for ((id, _), listener) in peers.iter().zip(listeners) {
+            let transport = Arc::new(HttpRaftTransport::new(peers.clone()).with_token("node"));
+            let node = Arc::new(
+                RaftLedger::in_memory(id, ids.clone(), InMemoryLedger::default(), transport)
+                    .with_config(config.clone()),
+            );
+            let router = RaftEndpoint::new("acme/site", node.clone(), auth.clone()).router();
+            tokio::spawn(async move { axum::serve(listener, router).await });
+            nodes.push(node);
+        }
+
+        let wid = WorldlineId::derive(&IdentityMaterial::GenesisHash([3; 32]));
+        let proposal = CommitmentProposal {
+            worldline: wid.clone(),
+            commitment_id: CommitmentId::new(),
+            class: CommitmentClass::ContentUpdate,
+            intent: "replicate".into(),
+            requested_caps: vec![],
+            targets: vec![],
+            evidence: EvidenceBundle::from_references(vec![]),
+            nonce: 1,
+        };
+        let replicated = nodes.clone();
+        tokio::task::spawn_blocking(move || {
+            let (a, c) = (&replicated[0], &replicated[2]);
+            std::thread::sleep(Duration::from_millis(5));
+            a.tick();
+            assert_eq!(a.status().role, Role::Leader);
+            a.append_commitment(&proposal, &Decision::Accepted, [0; 32]).unwrap();
+            a.tick();
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while c.read_all(&wid).unwrap().is_empty() && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            assert_eq!(c.read_all(&wid).unwrap(), a.read_all(&wid).unwrap());
+        })
+        .await
+        .unwrap();
+
+        let url = format!("{}{STATUS}", peers[1].1);
+        let status = tokio::task::spawn_blocking(move || {
+            let forbidden = ureq::get(&url).set("authorization", "Bearer dev").call();
+            assert!(matches!(forbidden, Err(ureq::Error::Status(403, _))));
+            let response = ureq::get(&url).set("authorization", "Bearer node").call().unwrap();
+            serde_json::from_reader::<_, RaftStatus>(response.into_reader()).unwrap()
+        })
+        .await
+        .unwrap();
+        assert_eq!((status.role, status.leader.as_deref()), (Role::Follower, Some("a")));
+    }
+}
@@ -1,9 +1,17 @@
-use axum::{Router, routing::get};
-use crate::handler;
+use axum::{Router, middleware, routing::get};
+use crate::{handler, trace};
 
 /// Build the axum router with all WLL endpoints.
 pub fn build_router() -> Router {
+    build_router_with(Router::new())
+}
+
+/// [`build_router`], plus `routes`, such as a repository's read-only
+/// queries and admin endpoints.
+pub fn build_router_with(routes: Router) -> Router {
     Router::new()
         .route("/v1/health", get(handler::health_handler))
         .route("/v1/info", get(handler::info_handler))
+        .merge(routes)
+        .layer(middleware::from_fn(trace::propagate))
 }
@@ -1,41 +1,385 @@
+use std::future::{Future, IntoFuture};
+use std::sync::Arc;
+
 use tokio::net::TcpListener;
+use tokio::time::Instant;
+use wll_ledger::Ledger;
+use wll_refs::RefStore;
+use crate::advertise::RefFilter;
+use crate::api::QueryApi;
+use crate::auth::AuthProvider;
+use crate::cluster::Cluster;
 use crate::config::ServerConfig;
-use crate::error::ServerResult;
-use crate::router::build_router;
+use crate::error::{ServerError, ServerResult};
+use crate::hooks::ServerHook;
+use crate::maintenance::{MaintenanceScheduler, MaintenanceTarget};
+use crate::router::build_router_with;
+use crate::shutdown::{InFlight, ShutdownTarget};
+use crate::webhooks::WebhookDispatcher;
 
 /// WLL repository server.
 pub struct WllServer {
     config: ServerConfig,
+    auth: Arc<dyn AuthProvider>,
+    hooks: Vec<Arc<dyn ServerHook>>,
+    webhooks: Option<WebhookDispatcher>,
+    queries: Option<QueryApi>,
+    maintenance: Option<MaintenanceScheduler>,
+    shutdown: Vec<(String, Arc<dyn ShutdownTarget>)>,
+    in_flight: InFlight,
+    cluster: Option<Cluster>,
+    #[cfg(feature = "raft")]
+    raft: Option<(String, Arc<dyn wll_ledger::RaftService>)>,
 }
 
 impl WllServer {
+    /// Server using the auth provider, hooks, and webhooks described by
+    /// `config`. Webhooks run after the other hooks.
     pub fn new(config: ServerConfig) -> Self {
-        Self { config }
+        let auth = config.auth_provider();
+        let mut hooks = config.server_hooks();
+        let webhooks = config.webhook_dispatcher();
+        if let Some(webhooks) = &webhooks {
+            hooks.push(Arc::new(webhooks.clone()));
+        }
+        Self {
+            config,
+            auth,
+            hooks,
+            webhooks,
+            queries: None,
+            maintenance: None,
+            shutdown: Vec::new(),
+            in_flight: InFlight::new(),
+            cluster: None,
+            #[cfg(feature = "raft")]
+            raft: None,
+        }
+    }
+
+    /// Replace the configured auth provider.
+    pub fn with_auth(mut self, auth: Arc<dyn AuthProvider>) -> Self {
+        self.queries = self.queries.map(|api| api.with_auth(auth.clone()));
+        self.auth = auth;
+        self
+    }
+
+    /// Answer read-only queries (`/v1/worldlines`, `/v1/receipts`,
+    /// `/v1/refs`, `/v1/info/refs`) from `ledger` and `refs`, authorized as
+    /// reads of `repo`. Ref listings follow the `[refs]` config; if it
+    /// does not parse, no ref is listed. Requests are decoded within the
+    /// `[limits]` config.
+    pub fn with_repository(
+        mut self,
+        repo: impl Into<String>,
+        ledger: Arc<dyn Ledger>,
+        refs: Arc<dyn RefStore>,
+    ) -> Self {
+        let filter = self.config.ref_filter().unwrap_or_else(|e| {
+            tracing::error!("hiding every ref: {e}");
+            RefFilter::hide_all()
+        });
+        let api = QueryApi::new(repo, ledger, refs, self.auth.clone())
+            .with_ref_filter(filter)
+            .with_decode_limits(self.config.limits);
+        self.queries = Some(api);
+        self
+    }
+
+    /// Also answer `POST /v1/graphql` for the repository given to
+    /// [`with_repository`](Self::with_repository), reading its provenance
+    /// DAG from `dag`, within the `[graphql]` limits.
+    #[cfg(feature = "graphql")]
+    pub fn with_provenance(mut self, dag: crate::graphql::DagSource) -> Self {
+        let limits = self.config.graphql;
+        self.queries = self.queries.map(|api| api.with_graphql(dag, limits));
+        self
+    }
+
+    /// Run the `[maintenance]` tasks on `target` under the name `repo`,
+    /// and answer `GET /v1/admin/maintenance` with their history.
+    pub fn with_maintenance(
+        mut self,
+        repo: impl Into<String>,
+        target: Arc<dyn MaintenanceTarget>,
+    ) -> Self {
+        let scheduler = self
+            .maintenance
+            .take()
+            .unwrap_or_else(|| MaintenanceScheduler::new(self.config.maintenance.clone()));
+        self.maintenance = Some(scheduler.with_target(repo, target));
+        self
+    }
+
+    /// Have `target`, named `repo` in logs, persist its state once requests
+    /// have drained at shutdown. Targets run in the order given.
+    pub fn with_shutdown(
+        mut self,
+        repo: impl Into<String>,
+        target: Arc<dyn ShutdownTarget>,
+    ) -> Self {
+        self.shutdown.push((repo.into(), target));
+        self
+    }
+
+    /// Serve as a node of `cluster`: writes for worldlines another node is
+    /// primary for are forwarded to it, and this node's leases are renewed
+    /// while it serves and released once it has shut down.
+    pub fn with_cluster(mut self, cluster: Cluster) -> Self {
+        let limit = usize::try_from(self.config.max_pack_size).unwrap_or(usize::MAX);
+        self.cluster = Some(cluster.with_body_limit(limit));
+        self
+    }
+
+    /// Answer the `/v1/raft/*` routes for `service`, the replicated ledger
+    /// of `repo`, authorized as admin of `repo`.
+    #[cfg(feature = "raft")]
+    pub fn with_raft(
+        mut self,
+        repo: impl Into<String>,
+        service: Arc<dyn wll_ledger::RaftService>,
+    ) -> Self {
+        self.raft = Some((repo.into(), service));
+        self
+    }
+
+    /// Run `hook` after the configured hooks.
+    pub fn with_hook(mut self, hook: Arc<dyn ServerHook>) -> Self {
+        self.hooks.push(hook);
+        self
     }
 
     pub fn config(&self) -> &ServerConfig {
         &self.config
     }
 
+    pub fn auth(&self) -> &Arc<dyn AuthProvider> {
+        &self.auth
+    }
+
+    pub fn hooks(&self) -> &[Arc<dyn ServerHook>] {
+        &self.hooks
+    }
+
+    /// Dispatcher for the configured webhooks, for events hooks do not
+    /// cover, such as gate rejections.
+    pub fn webhooks(&self) -> Option<&WebhookDispatcher> {
+        self.webhooks.as_ref()
+    }
+
+    /// The maintenance scheduler, once a repository is given to
+    /// [`with_maintenance`](Self::with_maintenance).
+    pub fn maintenance(&self) -> Option<&MaintenanceScheduler> {
+        self.maintenance.as_ref()
+    }
+
+    /// This node's cluster, once given to [`with_cluster`](Self::with_cluster).
+    pub fn cluster(&self) -> Option<&Cluster> {
+        self.cluster.as_ref()
+    }
+
+    /// Requests being handled right now.
+    pub fn in_flight(&self) -> &InFlight {
+        &self.in_flight
+    }
+
     /// Build the router (useful for testing).
     pub fn router(&self) -> axum::Router {
-        build_router()
+        let mut routes = axum::Router::new();
+        if let Some(api) = &self.queries {
+            routes = routes.merge(api.clone().router());
+        }
+        if let Some(maintenance) = &self.maintenance {
+            routes = routes.merge(maintenance.clone().router(self.auth.clone()));
+        }
+        #[cfg(feature = "raft")]
+        if let Some((repo, service)) = &self.raft {
+            let endpoint = crate::raft::RaftEndpoint::new(repo, service.clone(), self.auth.clone());
+            routes = routes.merge(endpoint.router());
+        }
+        let mut router = build_router_with(routes);
+        if let Some(cluster) = &self.cluster {
+            router = router.layer(axum::middleware::from_fn_with_state(
+                cluster.clone(),
+                Cluster::route_writes,
+            ));
+        }
+        router.layer(axum::middleware::from_fn_with_state(self.in_flight.clone(), InFlight::track))
     }
 
     /// Start serving requests.
     pub async fn serve(self) -> ServerResult<()> {
-        let app = build_router();
+        self.serve_with_shutdown(std::future::pending()).await
+    }
+
+    /// Serve until `signal` completes, then shut down: stop accepting
+    /// connections and starting maintenance, wait up to
+    /// `shutdown_timeout_secs` from the signal for in-flight requests and
+    /// maintenance runs, and run the [`with_shutdown`](Self::with_shutdown)
+    /// targets. The targets run even if serving failed or the wait timed
+    /// out. A cluster node then releases its leases.
+    pub async fn serve_with_shutdown(
+        self,
+        signal: impl Future<Output = ()> + Send + 'static,
+    ) -> ServerResult<()> {
+        let mut jobs =
+            self.maintenance.as_ref().map(MaintenanceScheduler::start).unwrap_or_default();
+        jobs.extend(self.cluster.as_ref().map(Cluster::start));
+        let (fired, on_fired) = tokio::sync::oneshot::channel();
+        let signal = async move {
+            signal.await;
+            let _ = fired.send(Instant::now());
+        };
+        let result = match self.config.tls.clone() {
+            Some(tls) => self.serve_tls(tls, signal).await,
+            None => self.serve_plain(signal).await,
+        };
+        jobs.iter().for_each(tokio::task::JoinHandle::abort);
+        let deadline = on_fired.await.unwrap_or_else(|_| Instant::now());
+        self.drain(deadline + self.config.shutdown_timeout()).await;
+        result
+    }
+
+    /// Wait until `deadline` for requests and maintenance runs to finish,
+    /// then run the shutdown targets.
+    async fn drain(&self, deadline: Instant) {
+        let idle = async {
+            self.in_flight.idle().await;
+            if let Some(maintenance) = &self.maintenance {
+                maintenance.idle().await;
+            }
+        };
+        if tokio::time::timeout_at(deadline, idle).await.is_err() {
+            tracing::warn!(
+                "{} request(s) still running at the shutdown deadline; dropping them",
+                self.in_flight.count()
+            );
+        }
+        for (repo, target) in &self.shutdown {
+            let target = target.clone();
+            let result = tokio::task::spawn_blocking(move || target.shutdown())
+                .await
+                .unwrap_or_else(|e| Err(format!("shutdown panicked: {e}")));
+            match result {
+                Ok(summary) => tracing::info!("shut down {repo}: {summary}"),
+                Err(e) => tracing::error!("shutting down {repo} failed: {e}"),
+            }
+        }
+        if let Some(cluster) = self.cluster.clone() {
+            let _ = tokio::task::spawn_blocking(move || cluster.release_all()).await;
+        }
+    }
+
+    async fn serve_plain(
+        &self,
+        signal: impl Future<Output = ()> + Send + 'static,
+    ) -> ServerResult<()> {
         let listener = TcpListener::bind(&self.config.bind_addr).await?;
-        tracing::info!("WLL server listening on {}", self.config.bind_addr);
-        axum::serve(listener, app)
+        tracing::info!("WLL server listening on {}", listener.local_addr()?);
+
+        let (fired, on_fired) = tokio::sync::oneshot::channel();
+        let signal = async move {
+            signal.await;
+            let _ = fired.send(());
+        };
+        let server = axum::serve(listener, self.router())
+            .with_graceful_shutdown(signal)
+            .into_future();
+        let timeout = self.config.shutdown_timeout();
+        tokio::select! {
+            result = server => result.map_err(ServerError::from),
+            _ = async {
+                if on_fired.await.is_ok() {
+                    tokio::time::sleep(timeout).await;
+                } else {
+                    std::future::pending::<()>().await;
+                }
+            } => {
+                tracing::warn!("shutdown timed out after {timeout:?}; dropping open connections");
+                Ok(())
+            }
+        }
+    }
+
+    #[cfg(feature = "tls")]
+    async fn serve_tls(
+        &self,
+        tls: crate::config::TlsConfig,
+        signal: impl Future<Output = ()> + Send + 'static,
+    ) -> ServerResult<()> {
+        use axum_server::tls_rustls::RustlsConfig;
+
+        // Several providers may be compiled in; pin ring unless the
+        // embedding application installed one already.
+        let _ = rustls::crypto::ring::default_provider().install_default();
+        let rustls = RustlsConfig::from_pem_file(&tls.cert_path, &tls.key_path)
             .await
-            .map_err(|e| crate::error::ServerError::Internal(e.to_string()))
+            .map_err(|e| ServerError::Config(format!("tls: {e}")))?;
+
+        let handle = axum_server::Handle::new();
+        let timeout = self.config.shutdown_timeout();
+        let shutdown = handle.clone();
+        tokio::spawn(async move {
+            signal.await;
+            shutdown.graceful_shutdown(Some(timeout));
+        });
+
+        tracing::info!("WLL server listening on {} (TLS)", self.config.bind_addr);
+        axum_server::bind_rustls(self.config.bind_addr, rustls)
+            .handle(handle)
+            .serve(self.router().into_make_service())
+            .await?;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "tls"))]
+    async fn serve_tls(
+        &self,
+        _tls: crate::config::TlsConfig,
+        _signal: impl Future<Output = ()> + Send + 'static,
+    ) -> ServerResult<()> {
+        Err(ServerError::Config(
+            "[tls] is configured but wll-server was built without the `tls` feature".into(),
+        ))
+    }
+}
+
+/// Completes on SIGINT (Ctrl-C) or, on Unix, SIGTERM.
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::warn!("cannot listen for Ctrl-C: {e}");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                tracing::warn!("cannot listen for SIGTERM: {e}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("received SIGINT, shutting down"),
+        _ = terminate => tracing::info!("received SIGTERM, shutting down"),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::auth::AllowAllAuth;
+    use crate::hooks::NoOpHook;
 
     #[test]
     fn server_construction() {
@@ -48,4 +392,57 @@ mod tests {
         let server = WllServer::new(ServerConfig::default());
         let _router = server.router();
     }
+
+    #[test]
+    fn builders_replace_auth_and_append_hooks() {
+        let server = WllServer::new(ServerConfig::default())
+            .with_auth(Arc::new(AllowAllAuth))
+            .with_hook(Arc::new(NoOpHook));
+        assert_eq!(server.hooks().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn serve_returns_after_shutdown_signal() {
+        let config = ServerConfig {
+            bind_addr: "127.0.0.1:0".parse().unwrap(),
+            ..ServerConfig::default()
+        };
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(WllServer::new(config).serve_with_shutdown(async {
+            let _ = stopped.await;
+        }));
+        stop.send(()).unwrap();
+        tokio::time::timeout(std::time::Duration::from_secs(5), server)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+    }
+
+    struct Flag(std::sync::atomic::AtomicBool);
+
+    impl ShutdownTarget for Flag {
+        fn shutdown(&self) -> Result<String, String> {
+            self.0.store(true, std::sync::atomic::Ordering::SeqCst);
+            Ok("flushed".into())
+        }
+    }
+
+    #[tokio::test]
+    async fn shutdown_targets_run_once_requests_drain() {
+        let config = ServerConfig {
+            bind_addr: "127.0.0.1:0".parse().unwrap(),
+            shutdown_timeout_secs: 1,
+            ..ServerConfig::default()
+        };
+        let flag = Arc::new(Flag(Default::default()));
+        let server = WllServer::new(config).with_shutdown("repo", flag.clone());
+        // A request that never finishes holds shutdown until the deadline.
+        let stuck = server.in_flight().enter();
+        let started = std::time::Instant::now();
+        server.serve_with_shutdown(async {}).await.unwrap();
+        assert!(started.elapsed() >= std::time::Duration::from_secs(1));
+        assert!(flag.0.load(std::sync::atomic::Ordering::SeqCst));
+        drop(stuck);
+    }
 }
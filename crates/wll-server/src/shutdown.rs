@@ -0,0 +1,122 @@
+//! Coordinated shutdown.
+//!
+//! When the shutdown signal fires the server stops accepting connections,
+//! stops starting scheduled maintenance, and waits for the requests and
+//! maintenance runs in flight, each counted by an [`InFlight`], to finish.
+//! Every [`ShutdownTarget`] is then asked to persist what it still holds
+//! in memory; `wll serve` flushes and checkpoints its event fabric.
+//!
+//! The wait ends `shutdown_timeout_secs` after the signal. Requests still
+//! running then are dropped with the runtime. A push dropped before its
+//! objects leave quarantine leaves nothing behind: the quarantine directory
+//! is removed with it, or swept when the server next starts. Ref updates
+//! and receipts are written through to disk as they are accepted, so none
+//! are pending once the server stops.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+use tokio::sync::Notify;
+
+/// Work a repository finishes before the server exits.
+pub trait ShutdownTarget: Send + Sync {
+    /// Persist buffered state, returning a one-line summary or the
+    /// failure. Runs on a blocking thread after requests have drained.
+    fn shutdown(&self) -> Result<String, String>;
+}
+
+/// Count of operations in progress, which can be waited on to reach zero.
+#[derive(Clone, Debug, Default)]
+pub struct InFlight {
+    inner: Arc<Counter>,
+}
+
+#[derive(Debug, Default)]
+struct Counter {
+    count: AtomicUsize,
+    idle: Notify,
+}
+
+/// Keeps one operation counted until dropped.
+#[derive(Debug)]
+pub struct InFlightGuard {
+    inner: Arc<Counter>,
+}
+
+impl InFlight {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count an operation until the returned guard is dropped.
+    pub fn enter(&self) -> InFlightGuard {
+        self.inner.count.fetch_add(1, Ordering::AcqRel);
+        InFlightGuard { inner: self.inner.clone() }
+    }
+
+    pub fn count(&self) -> usize {
+        self.inner.count.load(Ordering::Acquire)
+    }
+
+    /// Completes once no operation is counted.
+    pub async fn idle(&self) {
+        loop {
+            let notified = self.inner.idle.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            if self.count() == 0 {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Middleware counting each request while its handler runs.
+    pub async fn track(
+        State(in_flight): State<InFlight>,
+        request: Request,
+        next: Next,
+    ) -> Response {
+        let _guard = in_flight.enter();
+        next.run(request).await
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if self.inner.count.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.inner.idle.notify_waiters();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn idle_waits_for_every_guard() {
+        let in_flight = InFlight::new();
+        in_flight.idle().await;
+
+        let first = in_flight.enter();
+        let second = in_flight.enter();
+        assert_eq!(in_flight.count(), 2);
+        let waiter = tokio::spawn({
+            let in_flight = in_flight.clone();
+            async move { in_flight.idle().await }
+        });
+        drop(first);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiter.is_finished());
+
+        drop(second);
+        tokio::time::timeout(Duration::from_secs(5), waiter).await.unwrap().unwrap();
+        assert_eq!(in_flight.count(), 0);
+    }
+}
@@ -0,0 +1,76 @@
+//! Per-request spans and W3C trace context propagation.
+
+use axum::extract::Request;
+use axum::http::HeaderValue;
+use axum::middleware::Next;
+use axum::response::Response;
+use tracing::Instrument;
+use wll_telemetry::{TraceContext, TRACEPARENT, TRACERESPONSE};
+
+/// Middleware that runs each request in an `http.request` span.
+///
+/// The span continues the trace in the request's `traceparent` header, if it
+/// parses, and the server's own context is returned in `traceresponse` so
+/// the client can find the server side of the trace.
+pub async fn propagate(request: Request, next: Next) -> Response {
+    let parent = request
+        .headers()
+        .get(TRACEPARENT)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<TraceContext>().ok());
+    let span = tracing::info_span!(
+        "http.request",
+        method = %request.method(),
+        path = request.uri().path(),
+        trace_id = tracing::field::Empty,
+        status = tracing::field::Empty,
+    );
+    let trace = wll_telemetry::attach(&span, parent);
+    let mut response =
+        wll_telemetry::scope(trace, next.run(request).instrument(span.clone())).await;
+    span.record("status", response.status().as_u16());
+    if let Ok(value) = HeaderValue::from_str(&trace.to_string()) {
+        response.headers_mut().insert(TRACERESPONSE, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use tower::util::ServiceExt;
+
+    async fn traceresponse(request: Request) -> TraceContext {
+        let response = crate::router::build_router()
+            .oneshot(request)
+            .await
+            .unwrap();
+        response.headers()[TRACERESPONSE]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn responses_continue_the_callers_trace() {
+        let caller = TraceContext::new_root();
+        let request = Request::builder()
+            .uri("/v1/health")
+            .header(TRACEPARENT, caller.to_string())
+            .body(Body::empty())
+            .unwrap();
+        let server = traceresponse(request).await;
+        assert_eq!(server.trace_id, caller.trace_id);
+        assert_ne!(server.parent_id, caller.parent_id);
+
+        // A missing or malformed header starts a fresh trace.
+        let request = Request::builder()
+            .uri("/v1/health")
+            .header(TRACEPARENT, "not-a-traceparent")
+            .body(Body::empty())
+            .unwrap();
+        assert_ne!(traceresponse(request).await.trace_id, caller.trace_id);
+    }
+}
@@ -0,0 +1,604 @@
+//! Outgoing webhooks for push, ref, and gate events.
+//!
+//! Each `[[webhooks]]` endpoint receives a JSON payload for the events it
+//! subscribes to:
+//!
+//! ```json
+//! {"id": "0191…", "timestamp_ms": 1760000000000,
+//!  "event": "ref-updated", "data": {"name": "refs/heads/main", "old": null, "new": "ab12…"}}
+//! ```
+//!
+//! Requests carry `X-Wll-Event` and `X-Wll-Delivery` headers and, when the
+//! endpoint has a secret, `X-Wll-Signature-256: sha256=<hex>`: an HMAC-SHA256
+//! of the raw body keyed by the secret. A delivery is retried with
+//! exponential backoff until the endpoint answers 2xx or `max_attempts` is
+//! used up; every attempt is recorded in the [`DeliveryLog`].
+
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use wll_crypto::Secret;
+
+use crate::error::{ServerError, ServerResult};
+use crate::hooks::{hex, HookRefUpdate, HookResult, ServerHook};
+
+/// Attempts kept in memory by a [`DeliveryLog`].
+const LOG_CAPACITY: usize = 256;
+
+/// Longest wait between two attempts.
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// Event names endpoints subscribe to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WebhookEventKind {
+    PushReceived,
+    CommitmentRejected,
+    RefUpdated,
+}
+
+impl WebhookEventKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::PushReceived => "push-received",
+            Self::CommitmentRejected => "commitment-rejected",
+            Self::RefUpdated => "ref-updated",
+        }
+    }
+}
+
+/// A ref move, with hashes in hex and `old` null for a new ref.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookRefUpdate {
+    pub name: String,
+    pub old: Option<String>,
+    pub new: String,
+}
+
+impl From<&HookRefUpdate> for WebhookRefUpdate {
+    fn from(update: &HookRefUpdate) -> Self {
+        Self {
+            name: update.name.clone(),
+            old: update.old_hash.as_ref().map(hex),
+            new: hex(&update.new_hash),
+        }
+    }
+}
+
+/// Something webhooks are told about.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", content = "data", rename_all = "kebab-case")]
+pub enum WebhookEvent {
+    /// A push was accepted; sent once per push with all its updates.
+    PushReceived { updates: Vec<WebhookRefUpdate> },
+    /// The commitment gate refused a proposal.
+    CommitmentRejected {
+        worldline: String,
+        commitment_id: String,
+        class: String,
+        intent: String,
+        /// Gate stage that rejected it, when known.
+        stage: Option<String>,
+        reason: String,
+    },
+    /// One ref moved; sent for each update of a push.
+    RefUpdated(WebhookRefUpdate),
+}
+
+impl WebhookEvent {
+    pub fn kind(&self) -> WebhookEventKind {
+        match self {
+            Self::PushReceived { .. } => WebhookEventKind::PushReceived,
+            Self::CommitmentRejected { .. } => WebhookEventKind::CommitmentRejected,
+            Self::RefUpdated(_) => WebhookEventKind::RefUpdated,
+        }
+    }
+}
+
+/// The body POSTed to an endpoint.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookPayload {
+    /// Delivery id, the same on every retry.
+    pub id: String,
+    pub timestamp_ms: u64,
+    #[serde(flatten)]
+    pub event: WebhookEvent,
+}
+
+/// One `[[webhooks]]` entry.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// HMAC-SHA256 key for `X-Wll-Signature-256`. Unsigned when unset.
+    pub secret: Option<Secret>,
+    /// Events to send. Empty means all of them.
+    pub events: Vec<WebhookEventKind>,
+    pub max_attempts: u32,
+    /// Wait before the first retry; doubled after each failed attempt.
+    pub backoff_ms: u64,
+    pub timeout_secs: u64,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            secret: None,
+            events: Vec::new(),
+            max_attempts: 5,
+            backoff_ms: 1000,
+            timeout_secs: 10,
+        }
+    }
+}
+
+impl WebhookConfig {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self { url: url.into(), ..Self::default() }
+    }
+
+    pub fn with_secret(mut self, secret: impl Into<Secret>) -> Self {
+        self.secret = Some(secret.into());
+        self
+    }
+
+    pub fn with_events(mut self, events: Vec<WebhookEventKind>) -> Self {
+        self.events = events;
+        self
+    }
+
+    pub fn with_retry(mut self, max_attempts: u32, backoff_ms: u64) -> Self {
+        self.max_attempts = max_attempts;
+        self.backoff_ms = backoff_ms;
+        self
+    }
+
+    pub fn wants(&self, kind: WebhookEventKind) -> bool {
+        self.events.is_empty() || self.events.contains(&kind)
+    }
+
+    pub(crate) fn validate(&self) -> ServerResult<()> {
+        if !self.url.starts_with("http://") && !self.url.starts_with("https://") {
+            return Err(ServerError::Config(format!(
+                "webhook url must be http(s): {:?}",
+                self.url
+            )));
+        }
+        if self.max_attempts == 0 {
+            return Err(ServerError::Config(format!(
+                "webhook {}: max_attempts must be at least 1",
+                self.url
+            )));
+        }
+        Ok(())
+    }
+
+    /// Wait after failed attempt number `attempt` (1-based).
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u64 << attempt.saturating_sub(1).min(20);
+        Duration::from_millis(self.backoff_ms.saturating_mul(factor)).min(MAX_BACKOFF)
+    }
+}
+
+/// `sha256=<hex>` HMAC of `body` keyed by `secret`, as sent in
+/// `X-Wll-Signature-256`.
+pub fn sign_payload(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(body);
+    let digest: [u8; 32] = mac.finalize().into_bytes().into();
+    format!("sha256={}", hex(&digest))
+}
+
+/// How payloads reach an endpoint. Blocking; the dispatcher calls it off
+/// the async runtime.
+pub trait WebhookTransport: Send + Sync {
+    /// POST `body` to `url` and return the response status.
+    fn post(
+        &self,
+        url: &str,
+        headers: &[(&str, String)],
+        body: &[u8],
+        timeout: Duration,
+    ) -> Result<u16, String>;
+}
+
+/// [`WebhookTransport`] over `ureq`.
+pub struct HttpTransport;
+
+impl WebhookTransport for HttpTransport {
+    fn post(
+        &self,
+        url: &str,
+        headers: &[(&str, String)],
+        body: &[u8],
+        timeout: Duration,
+    ) -> Result<u16, String> {
+        let agent = ureq::AgentBuilder::new().timeout(timeout).build();
+        let mut request = agent.post(url);
+        for (name, value) in headers {
+            request = request.set(name, value);
+        }
+        match request.send_bytes(body) {
+            Ok(response) => Ok(response.status()),
+            Err(ureq::Error::Status(status, _)) => Ok(status),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+/// One attempt to deliver a payload to one endpoint.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeliveryAttempt {
+    pub delivery: String,
+    pub url: String,
+    pub event: WebhookEventKind,
+    pub attempt: u32,
+    pub timestamp_ms: u64,
+    /// Response status, if the endpoint answered.
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    pub delivered: bool,
+}
+
+/// Recent delivery attempts, newest last, optionally appended to a JSON
+/// Lines file as well.
+pub struct DeliveryLog {
+    recent: Mutex<VecDeque<DeliveryAttempt>>,
+    path: Option<PathBuf>,
+}
+
+impl DeliveryLog {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self { recent: Mutex::new(VecDeque::with_capacity(LOG_CAPACITY)), path }
+    }
+
+    pub fn recent(&self) -> Vec<DeliveryAttempt> {
+        self.recent.lock().unwrap_or_else(|e| e.into_inner()).iter().cloned().collect()
+    }
+
+    fn record(&self, attempt: DeliveryAttempt) {
+        if let Some(path) = &self.path {
+            if let Err(e) = Self::append(path, &attempt) {
+                tracing::warn!("webhook delivery log {}: {e}", path.display());
+            }
+        }
+        let mut recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+        if recent.len() == LOG_CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(attempt);
+    }
+
+    fn append(path: &Path, attempt: &DeliveryAttempt) -> std::io::Result<()> {
+        let mut line = serde_json::to_vec(attempt)?;
+        line.push(b'\n');
+        std::fs::OpenOptions::new().create(true).append(true).open(path)?.write_all(&line)
+    }
+}
+
+/// Sends events to the configured endpoints.
+///
+/// As a [`ServerHook`] it reports accepted pushes: one `push-received`
+/// event and a `ref-updated` event per ref. Other events are sent with
+/// [`dispatch`](Self::dispatch).
+#[derive(Clone)]
+pub struct WebhookDispatcher {
+    endpoints: Arc<Vec<WebhookConfig>>,
+    transport: Arc<dyn WebhookTransport>,
+    log: Arc<DeliveryLog>,
+}
+
+impl WebhookDispatcher {
+    pub fn new(endpoints: Vec<WebhookConfig>, log: DeliveryLog) -> Self {
+        Self {
+            endpoints: Arc::new(endpoints),
+            transport: Arc::new(HttpTransport),
+            log: Arc::new(log),
+        }
+    }
+
+    /// Replace the HTTP transport.
+    pub fn with_transport(mut self, transport: Arc<dyn WebhookTransport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    pub fn log(&self) -> &DeliveryLog {
+        &self.log
+    }
+
+    /// Deliver `event` in the background. Must be called inside a Tokio
+    /// runtime.
+    pub fn dispatch(&self, event: WebhookEvent) {
+        if !self.endpoints.iter().any(|e| e.wants(event.kind())) {
+            return;
+        }
+        let dispatcher = self.clone();
+        tokio::spawn(async move {
+            dispatcher.deliver(event).await;
+        });
+    }
+
+    /// Deliver `event` to every endpoint subscribed to it, retrying each
+    /// until it succeeds or runs out of attempts. Returns the final
+    /// attempt per endpoint.
+    pub async fn deliver(&self, event: WebhookEvent) -> Vec<DeliveryAttempt> {
+        let payload = WebhookPayload {
+            id: uuid::Uuid::now_v7().to_string(),
+            timestamp_ms: now_ms(),
+            event,
+        };
+        let body: Arc<[u8]> = match serde_json::to_vec(&payload) {
+            Ok(body) => body.into(),
+            Err(e) => {
+                tracing::error!("cannot encode webhook payload: {e}");
+                return Vec::new();
+            }
+        };
+        let kind = payload.event.kind();
+        // Endpoints retry independently, so a slow one does not hold up
+        // the others.
+        let tasks: Vec<_> = self
+            .endpoints
+            .iter()
+            .filter(|endpoint| endpoint.wants(kind))
+            .map(|endpoint| {
+                let (dispatcher, endpoint) = (self.clone(), endpoint.clone());
+                let (delivery, body) = (payload.id.clone(), body.clone());
+                tokio::spawn(async move { dispatcher.deliver_to(endpoint, delivery, kind, body).await })
+            })
+            .collect();
+        let mut attempts = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            match task.await {
+                Ok(attempt) => attempts.push(attempt),
+                Err(e) => tracing::error!("webhook delivery task failed: {e}"),
+            }
+        }
+        attempts
+    }
+
+    async fn deliver_to(
+        &self,
+        endpoint: WebhookConfig,
+        delivery: String,
+        kind: WebhookEventKind,
+        body: Arc<[u8]>,
+    ) -> DeliveryAttempt {
+        let mut headers = vec![
+            ("Content-Type", "application/json".to_string()),
+            ("X-Wll-Event", kind.as_str().to_string()),
+            ("X-Wll-Delivery", delivery.clone()),
+        ];
+        if let Some(secret) = &endpoint.secret {
+            headers.push(("X-Wll-Signature-256", sign_payload(secret.expose(), &body)));
+        }
+        let timeout = Duration::from_secs(endpoint.timeout_secs);
+
+        let mut attempt = 1;
+        loop {
+            let transport = self.transport.clone();
+            let (url, headers, body) = (endpoint.url.clone(), headers.clone(), body.clone());
+            let result = tokio::task::spawn_blocking(move || {
+                transport.post(&url, &headers, &body, timeout)
+            })
+            .await
+            .unwrap_or_else(|e| Err(format!("transport panicked: {e}")));
+
+            let record = DeliveryAttempt {
+                delivery: delivery.clone(),
+                url: endpoint.url.clone(),
+                event: kind,
+                attempt,
+                timestamp_ms: now_ms(),
+                status: result.as_ref().ok().copied(),
+                error: result.as_ref().err().cloned(),
+                delivered: matches!(result, Ok(status) if (200..300).contains(&status)),
+            };
+            self.log.record(record.clone());
+            if record.delivered || attempt >= endpoint.max_attempts {
+                if !record.delivered {
+                    tracing::warn!(
+                        "webhook {} gave up after {attempt} attempts: {}",
+                        endpoint.url,
+                        record.error.clone().unwrap_or_else(|| format!("status {:?}", record.status))
+                    );
+                }
+                return record;
+            }
+            tokio::time::sleep(endpoint.backoff(attempt)).await;
+            attempt += 1;
+        }
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64)
+}
+
+#[async_trait]
+impl ServerHook for WebhookDispatcher {
+    async fn pre_receive(&self, updates: &[HookRefUpdate]) -> ServerResult<Vec<HookResult>> {
+        Ok(updates.iter().map(|_| HookResult::Allow).collect())
+    }
+
+    async fn post_receive(&self, updates: &[HookRefUpdate]) -> ServerResult<()> {
+        let updates: Vec<WebhookRefUpdate> = updates.iter().map(WebhookRefUpdate::from).collect();
+        self.dispatch(WebhookEvent::PushReceived { updates: updates.clone() });
+        for update in updates {
+            self.dispatch(WebhookEvent::RefUpdated(update));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// URL, headers, and body of a request.
+    type Request = (String, Vec<(String, String)>, Vec<u8>);
+
+    /// Answers with `statuses` in turn, then 200, and records requests.
+    #[derive(Default)]
+    struct Scripted {
+        statuses: Mutex<VecDeque<Result<u16, String>>>,
+        requests: Mutex<Vec<Request>>,
+    }
+
+    impl Scripted {
+        fn new(statuses: Vec<Result<u16, String>>) -> Arc<Self> {
+            Arc::new(Self { statuses: Mutex::new(statuses.into()), ..Self::default() })
+        }
+    }
+
+    impl WebhookTransport for Scripted {
+        fn post(
+            &self,
+            url: &str,
+            headers: &[(&str, String)],
+            body: &[u8],
+            _timeout: Duration,
+        ) -> Result<u16, String> {
+            let headers = headers.iter().map(|(k, v)| (k.to_string(), v.clone())).collect();
+            self.requests.lock().unwrap().push((url.to_string(), headers, body.to_vec()));
+            self.statuses.lock().unwrap().pop_front().unwrap_or(Ok(200))
+        }
+    }
+
+    fn header<'a>(headers: &'a [(String, String)], name: &str) -> &'a str {
+        &headers.iter().find(|(k, _)| k == name).unwrap().1
+    }
+
+    fn rejected() -> WebhookEvent {
+        WebhookEvent::CommitmentRejected {
+            worldline: "wl".into(),
+            commitment_id: "c1".into(),
+            class: "PolicyChange".into(),
+            intent: "loosen policy".into(),
+            stage: Some("policy".into()),
+            reason: "needs two approvals".into(),
+        }
+    }
+
+    #[test]
+    fn signature_is_hmac_sha256() {
+        // RFC 4231, test case 2.
+        assert_eq!(
+            sign_payload("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let config = WebhookConfig::new("https://x").with_retry(30, 1000);
+        assert_eq!(config.backoff(1), Duration::from_secs(1));
+        assert_eq!(config.backoff(3), Duration::from_secs(4));
+        assert_eq!(config.backoff(25), MAX_BACKOFF);
+    }
+
+    #[tokio::test]
+    async fn retries_until_delivered_and_logs_each_attempt() {
+        let transport = Scripted::new(vec![Err("connection refused".into()), Ok(503)]);
+        let endpoint = WebhookConfig::new("https://ci.example/hook")
+            .with_secret("k")
+            .with_retry(5, 1);
+        let dispatcher = WebhookDispatcher::new(vec![endpoint], DeliveryLog::new(None))
+            .with_transport(transport.clone());
+
+        let results = dispatcher.deliver(rejected()).await;
+        assert_eq!(results.len(), 1);
+        assert!(results[0].delivered);
+        assert_eq!((results[0].attempt, results[0].status), (3, Some(200)));
+
+        let log = dispatcher.log().recent();
+        assert_eq!(log.len(), 3);
+        assert_eq!(log[0].error.as_deref(), Some("connection refused"));
+        assert_eq!(log[1].status, Some(503));
+        assert!(log.iter().all(|a| a.delivery == results[0].delivery));
+
+        let requests = transport.requests.lock().unwrap();
+        let (url, headers, body) = &requests[2];
+        assert_eq!(url, "https://ci.example/hook");
+        assert_eq!(header(headers, "X-Wll-Event"), "commitment-rejected");
+        assert_eq!(header(headers, "X-Wll-Signature-256"), sign_payload("k", body));
+        let payload: WebhookPayload = serde_json::from_slice(body).unwrap();
+        assert_eq!(payload.id, results[0].delivery);
+        assert_eq!(payload.event, rejected());
+        let json: serde_json::Value = serde_json::from_slice(body).unwrap();
+        assert_eq!(json["event"], "commitment-rejected");
+        assert_eq!(json["data"]["reason"], "needs two approvals");
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("deliveries.jsonl");
+        let transport = Scripted::new(vec![Ok(500), Ok(500), Ok(500)]);
+        let endpoint = WebhookConfig::new("https://ci.example/hook").with_retry(2, 1);
+        let dispatcher = WebhookDispatcher::new(vec![endpoint], DeliveryLog::new(Some(path.clone())))
+            .with_transport(transport.clone());
+
+        let results = dispatcher.deliver(rejected()).await;
+        assert!(!results[0].delivered);
+        assert_eq!(results[0].attempt, 2);
+        assert_eq!(transport.requests.lock().unwrap().len(), 2);
+        assert!(!transport.requests.lock().unwrap()[0]
+            .1
+            .iter()
+            .any(|(k, _)| k == "X-Wll-Signature-256"));
+
+        let lines = std::fs::read_to_string(path).unwrap();
+        let attempts: Vec<DeliveryAttempt> =
+            lines.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(attempts.len(), 2);
+        assert_eq!(attempts[1].status, Some(500));
+    }
+
+    #[tokio::test]
+    async fn post_receive_sends_push_and_ref_events_to_subscribers() {
+        let transport = Scripted::new(Vec::new());
+        let endpoints = vec![
+            WebhookConfig::new("https://chat.example/push")
+                .with_events(vec![WebhookEventKind::PushReceived]),
+            WebhookConfig::new("https://ci.example/refs")
+                .with_events(vec![WebhookEventKind::RefUpdated]),
+        ];
+        let dispatcher = WebhookDispatcher::new(endpoints, DeliveryLog::new(None))
+            .with_transport(transport.clone());
+
+        let updates = vec![
+            HookRefUpdate { name: "refs/heads/main".into(), old_hash: Some([1; 32]), new_hash: [2; 32] },
+            HookRefUpdate { name: "refs/heads/dev".into(), old_hash: None, new_hash: [3; 32] },
+        ];
+        let results = dispatcher.pre_receive(&updates).await.unwrap();
+        assert!(results.iter().all(|r| matches!(r, HookResult::Allow)));
+        dispatcher.post_receive(&updates).await.unwrap();
+
+        // Deliveries run in the background.
+        for _ in 0..100 {
+            if dispatcher.log().recent().len() == 3 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let requests = transport.requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        let pushes: Vec<_> = requests.iter().filter(|r| r.0.ends_with("/push")).collect();
+        assert_eq!(pushes.len(), 1);
+        let payload: WebhookPayload = serde_json::from_slice(&pushes[0].2).unwrap();
+        let WebhookEvent::PushReceived { updates } = payload.event else { panic!("expected a push") };
+        assert_eq!(updates[1], WebhookRefUpdate { name: "refs/heads/dev".into(), old: None, new: "03".repeat(32) });
+        assert_eq!(requests.iter().filter(|r| r.0.ends_with("/refs")).count(), 2);
+    }
+}
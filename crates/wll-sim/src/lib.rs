@@ -0,0 +1,19 @@
+//! Deterministic simulation of WorldLine Ledger nodes syncing with a hub.
+//!
+//! [`Simulation`] starts a hub repository and a number of client nodes on
+//! one worldline, all in memory, and drives them through a sequence of
+//! actions drawn from a seeded RNG: offline commits editing a few shared
+//! paths, reconciles with the hub, pulls from peers, network partitions,
+//! and clock skew. After every step it checks that each node's chain
+//! validates, that its provenance DAG is acyclic, and that its receipts
+//! are a prefix of the hub's; once the network heals and every queue has
+//! been replayed, every node must reach the hub's `main`.
+//!
+//! A failure names the seed and step it happened at, so
+//! `SimConfig::new(seed)` replays the same schedule.
+
+mod link;
+mod sim;
+
+pub use link::SimLink;
+pub use sim::{Action, Event, SimConfig, SimFailure, SimReport, Simulation};
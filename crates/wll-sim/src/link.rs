@@ -0,0 +1,103 @@
+//! A link to a node that the simulation can cut.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use wll_ledger::Receipt;
+use wll_protocol::ConsistencyToken;
+use wll_sdk::LocalRemote;
+use wll_sync::{RefRejection, RefUpdate, RemoteTransport, SyncError, SyncResult};
+use wll_types::{ObjectId, WorldlineId};
+
+/// [`LocalRemote`] that fails every call with a transport error while the
+/// link is cut. Clones share the link's state.
+#[derive(Clone)]
+pub struct SimLink {
+    remote: LocalRemote,
+    up: Arc<AtomicBool>,
+}
+
+impl SimLink {
+    /// A link to `remote`, up until [`cut`](Self::cut).
+    pub fn new(remote: LocalRemote) -> Self {
+        Self { remote, up: Arc::new(AtomicBool::new(true)) }
+    }
+
+    pub fn cut(&self) {
+        self.up.store(false, Ordering::SeqCst);
+    }
+
+    pub fn heal(&self) {
+        self.up.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_up(&self) -> bool {
+        self.up.load(Ordering::SeqCst)
+    }
+
+    fn check(&self) -> SyncResult<()> {
+        if self.is_up() {
+            Ok(())
+        } else {
+            Err(SyncError::TransportError("network partitioned".into()))
+        }
+    }
+}
+
+#[async_trait]
+impl RemoteTransport for SimLink {
+    async fn list_refs(&self) -> SyncResult<Vec<(String, [u8; 32])>> {
+        self.check()?;
+        self.remote.list_refs().await
+    }
+
+    async fn fetch_objects(&self, wants: &[ObjectId], haves: &[ObjectId]) -> SyncResult<Vec<u8>> {
+        self.check()?;
+        self.remote.fetch_objects(wants, haves).await
+    }
+
+    async fn fetch_receipts(
+        &self,
+        worldlines: &[WorldlineId],
+        since: Option<u64>,
+    ) -> SyncResult<Vec<Receipt>> {
+        self.check()?;
+        self.remote.fetch_receipts(worldlines, since).await
+    }
+
+    async fn push_pack(&self, pack_bytes: &[u8]) -> SyncResult<()> {
+        self.check()?;
+        self.remote.push_pack(pack_bytes).await
+    }
+
+    async fn push_receipts(&self, receipts: &[Receipt]) -> SyncResult<()> {
+        self.check()?;
+        self.remote.push_receipts(receipts).await
+    }
+
+    async fn update_refs(&self, updates: &[RefUpdate]) -> SyncResult<Vec<RefRejection>> {
+        self.check()?;
+        self.remote.update_refs(updates).await
+    }
+
+    async fn push_atomic(
+        &self,
+        pack_bytes: &[u8],
+        receipts: &[Receipt],
+        updates: &[RefUpdate],
+    ) -> SyncResult<Vec<RefRejection>> {
+        self.check()?;
+        self.remote.push_atomic(pack_bytes, receipts, updates).await
+    }
+
+    async fn ack_haves(&self, haves: &[ObjectId]) -> SyncResult<Vec<ObjectId>> {
+        self.check()?;
+        self.remote.ack_haves(haves).await
+    }
+
+    async fn consistency(&self) -> SyncResult<Option<ConsistencyToken>> {
+        self.check()?;
+        self.remote.consistency().await
+    }
+}
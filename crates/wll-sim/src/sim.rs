@@ -0,0 +1,485 @@
+//! The simulated network and the schedule driving it.
+
+use std::fmt;
+use std::sync::Arc;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use wll_ledger::Receipt;
+use wll_sdk::{
+    AsyncWll, CommitProposal, EntryMode, LocalRemote, RefSpec, ReplayStatus, SdkError, Wll,
+    WllBuilder,
+};
+use wll_types::{Coded, ErrorCode, IdentityMaterial, ManualClock, WorldlineId};
+
+use crate::link::SimLink;
+
+const START_MS: u64 = 1_700_000_000_000;
+const MAIN: &str = "refs/heads/main";
+
+/// Shape of a simulation run.
+#[derive(Clone, Debug)]
+pub struct SimConfig {
+    /// Seeds the RNG every action is drawn from.
+    pub seed: u64,
+    /// Client nodes besides the hub.
+    pub nodes: usize,
+    /// Actions to run before the network heals.
+    pub steps: usize,
+    /// Paths commits edit. Fewer paths mean more merge conflicts.
+    pub paths: usize,
+    /// Largest clock skew, either way, a node can be given.
+    pub max_skew_ms: u64,
+}
+
+impl SimConfig {
+    /// Three nodes, 200 steps over four paths, and up to a minute of skew.
+    pub fn new(seed: u64) -> Self {
+        Self { seed, nodes: 3, steps: 200, paths: 4, max_skew_ms: 60_000 }
+    }
+
+    pub fn with_nodes(mut self, nodes: usize) -> Self {
+        self.nodes = nodes;
+        self
+    }
+
+    pub fn with_steps(mut self, steps: usize) -> Self {
+        self.steps = steps;
+        self
+    }
+
+    pub fn with_paths(mut self, paths: usize) -> Self {
+        self.paths = paths;
+        self
+    }
+}
+
+/// One step of the schedule.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Queue a commit on `node` writing `path`.
+    Edit { node: usize, path: String },
+    /// Reconcile `node`'s queue with the hub, dropping a commit that needs
+    /// a merge.
+    Reconcile { node: usize },
+    /// Pull `main` from the hub.
+    Pull { node: usize },
+    /// Pull `main` from another node.
+    PullPeer { node: usize, peer: usize },
+    /// Cut `node` off from the hub and its peers.
+    Partition { node: usize },
+    /// Reconnect `node`.
+    Heal { node: usize },
+    /// Set `node`'s clock `skew_ms` off simulated time.
+    Skew { node: usize, skew_ms: i64 },
+    /// Move simulated time forwards.
+    Advance { ms: u64 },
+}
+
+/// An action and what came of it.
+#[derive(Clone, Debug)]
+pub struct Event {
+    pub step: usize,
+    pub action: Action,
+    pub outcome: String,
+}
+
+/// A run that kept every invariant.
+#[derive(Clone, Debug)]
+pub struct SimReport {
+    pub seed: u64,
+    pub trace: Vec<Event>,
+    /// Queued commits that reached the hub.
+    pub accepted: usize,
+    /// Of those, the ones merged with changes made after they were queued.
+    pub merged: usize,
+    /// Queued commits dropped because they conflicted with the hub.
+    pub discarded: usize,
+    /// Operations that failed because a link was cut.
+    pub unreachable: usize,
+    /// Receipts on the hub at the end.
+    pub receipts: usize,
+}
+
+/// The invariant a run broke, and where.
+#[derive(Clone, Debug)]
+pub struct SimFailure {
+    pub seed: u64,
+    /// Step the invariant broke at. Steps from `SimConfig::steps` on are
+    /// the settling phase after the network heals.
+    pub step: usize,
+    pub invariant: String,
+    pub trace: Vec<Event>,
+}
+
+impl fmt::Display for SimFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "seed {}, step {}: {}", self.seed, self.step, self.invariant)?;
+        for event in self.trace.iter().rev().take(8).rev() {
+            write!(f, "\n  {}: {:?} -> {}", event.step, event.action, event.outcome)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for SimFailure {}
+
+/// Why an action failed: a cut link, which the schedule expects, or
+/// anything else, which fails the run.
+struct Failed {
+    unreachable: bool,
+    message: String,
+}
+
+impl<E: Coded + fmt::Display> From<E> for Failed {
+    fn from(e: E) -> Self {
+        Self { unreachable: e.code() == ErrorCode::Transport, message: e.to_string() }
+    }
+}
+
+struct Node {
+    wll: Arc<Wll>,
+    clock: Arc<ManualClock>,
+    skew_ms: i64,
+    /// The node's link to the hub; cut while the node is partitioned.
+    link: SimLink,
+}
+
+impl Node {
+    fn sync(&self) -> AsyncWll {
+        AsyncWll::shared(self.wll.clone())
+    }
+}
+
+/// A hub and its client nodes, driven by a seeded schedule.
+pub struct Simulation {
+    config: SimConfig,
+    rng: StdRng,
+    now_ms: u64,
+    hub: Arc<Wll>,
+    nodes: Vec<Node>,
+    report: SimReport,
+}
+
+impl Simulation {
+    /// Run the schedule `config` seeds, then heal the network and settle.
+    pub fn run(config: SimConfig) -> Result<SimReport, SimFailure> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("simulation runtime");
+        let mut sim = Self::new(config)?;
+        runtime.block_on(sim.drive())?;
+        Ok(sim.report)
+    }
+
+    fn new(config: SimConfig) -> Result<Self, SimFailure> {
+        let worldline = WorldlineId::derive(&IdentityMaterial::GenesisHash([7; 32]));
+        let repo = |clock: Arc<ManualClock>| {
+            WllBuilder::new().with_worldline(worldline.clone()).with_clock(clock).build()
+        };
+        let report = SimReport {
+            seed: config.seed,
+            trace: Vec::new(),
+            accepted: 0,
+            merged: 0,
+            discarded: 0,
+            unreachable: 0,
+            receipts: 0,
+        };
+        let setup = |e: SdkError| SimFailure {
+            seed: config.seed,
+            step: 0,
+            invariant: format!("setup failed: {e}"),
+            trace: Vec::new(),
+        };
+        let hub = Arc::new(repo(Arc::new(ManualClock::new(START_MS))).map_err(setup)?);
+        let mut nodes = Vec::new();
+        for _ in 0..config.nodes {
+            let clock = Arc::new(ManualClock::new(START_MS));
+            let wll = Arc::new(repo(clock.clone()).map_err(setup)?);
+            let link = SimLink::new(LocalRemote::new(hub.clone()));
+            nodes.push(Node { wll, clock, skew_ms: 0, link });
+        }
+        Ok(Self {
+            rng: StdRng::seed_from_u64(config.seed),
+            config,
+            now_ms: START_MS,
+            hub,
+            nodes,
+            report,
+        })
+    }
+
+    async fn drive(&mut self) -> Result<(), SimFailure> {
+        for step in 0..self.config.steps {
+            let action = self.next_action();
+            self.step(step, action).await?;
+        }
+
+        // Heal the network, then replay every queue and pull until the
+        // nodes have nothing left to exchange.
+        let mut step = self.config.steps;
+        for node in 0..self.nodes.len() {
+            self.step(step, Action::Heal { node }).await?;
+            self.step(step + 1, Action::Skew { node, skew_ms: 0 }).await?;
+            step += 2;
+        }
+        let limit = step + 10_000;
+        loop {
+            let queued: Vec<usize> = (0..self.nodes.len())
+                .filter(|&node| self.pending(node).is_ok_and(|n| n > 0))
+                .collect();
+            if queued.is_empty() {
+                break;
+            }
+            if step > limit {
+                return Err(self.failure(step, "queues did not drain".into()));
+            }
+            for node in queued {
+                self.step(step, Action::Reconcile { node }).await?;
+                step += 1;
+            }
+        }
+        for node in 0..self.nodes.len() {
+            self.step(step, Action::Pull { node }).await?;
+            step += 1;
+        }
+        self.converged().map_err(|invariant| self.failure(step, invariant))?;
+        self.report.receipts = self.receipts(&self.hub).map_err(|e| self.failure(step, e))?.len();
+        Ok(())
+    }
+
+    fn next_action(&mut self) -> Action {
+        let count = self.nodes.len();
+        let node = self.rng.gen_range(0..count);
+        match self.rng.gen_range(0..100) {
+            0..=34 => {
+                let path = format!("f{}.txt", self.rng.gen_range(0..self.config.paths));
+                Action::Edit { node, path }
+            }
+            35..=54 => Action::Reconcile { node },
+            55..=59 => Action::Pull { node },
+            60..=69 if count > 1 => {
+                let peer = (node + self.rng.gen_range(1..count)) % count;
+                Action::PullPeer { node, peer }
+            }
+            70..=79 if self.nodes[node].link.is_up() => Action::Partition { node },
+            70..=79 => Action::Heal { node },
+            80..=87 => {
+                let max = self.config.max_skew_ms as i64;
+                Action::Skew { node, skew_ms: self.rng.gen_range(-max..=max) }
+            }
+            _ => Action::Advance { ms: self.rng.gen_range(1..=5_000) },
+        }
+    }
+
+    /// Apply `action`, record it, and check every invariant.
+    async fn step(&mut self, step: usize, action: Action) -> Result<(), SimFailure> {
+        let outcome = match self.apply(step, &action).await {
+            Ok(outcome) => outcome,
+            Err(failed) if failed.unreachable => {
+                self.report.unreachable += 1;
+                format!("unreachable: {}", failed.message)
+            }
+            Err(failed) => {
+                let invariant = format!("{action:?} failed: {}", failed.message);
+                self.report.trace.push(Event { step, action, outcome: "failed".into() });
+                return Err(self.failure(step, invariant));
+            }
+        };
+        self.report.trace.push(Event { step, action, outcome });
+        self.check().map_err(|invariant| self.failure(step, invariant))
+    }
+
+    async fn apply(&mut self, step: usize, action: &Action) -> Result<String, Failed> {
+        let main = RefSpec::new("main", "main");
+        match *action {
+            Action::Edit { node, ref path } => {
+                let wll = &self.nodes[node].wll;
+                let mut index = wll.new_index();
+                if let Some(tree) = wll.provisional_tree()? {
+                    index.read_tree(&tree)?;
+                }
+                let content = format!("node {node} at step {step}");
+                index.stage_file(path, content.as_bytes(), EntryMode::Regular)?;
+                let proposal = CommitProposal::new(format!("edit {path}"));
+                let queued = wll.queue_commit_tree(&mut index, proposal)?;
+                Ok(format!("queued #{}", queued.id))
+            }
+            Action::Reconcile { node } => {
+                let node = &self.nodes[node];
+                let report = node.sync().reconcile(&node.link, &main).await?;
+                let mut outcome = format!("accepted {}", report.accepted());
+                for commit in &report.commits {
+                    match &commit.status {
+                        ReplayStatus::Accepted { merged, .. } => {
+                            self.report.accepted += 1;
+                            self.report.merged += usize::from(*merged);
+                        }
+                        ReplayStatus::NeedsMerge { paths } => {
+                            node.wll.discard_queued(commit.id)?;
+                            self.report.discarded += 1;
+                            outcome += &format!(", dropped #{} on {}", commit.id, paths.join(","));
+                        }
+                        ReplayStatus::Rejected { reason, .. } => {
+                            let message = format!("#{} was rejected: {reason}", commit.id);
+                            return Err(Failed { unreachable: false, message });
+                        }
+                    }
+                }
+                Ok(outcome)
+            }
+            Action::Pull { node } => {
+                let node = &self.nodes[node];
+                pulled(node.sync().pull(&node.link, &main).await)
+            }
+            Action::PullPeer { node, peer } => {
+                let (to, from) = (&self.nodes[node], &self.nodes[peer]);
+                let link = SimLink::new(LocalRemote::new(from.wll.clone()));
+                if !to.link.is_up() || !from.link.is_up() {
+                    link.cut();
+                }
+                pulled(to.sync().pull(&link, &main).await)
+            }
+            Action::Partition { node } => {
+                self.nodes[node].link.cut();
+                Ok("cut".into())
+            }
+            Action::Heal { node } => {
+                self.nodes[node].link.heal();
+                Ok("healed".into())
+            }
+            Action::Skew { node, skew_ms } => {
+                self.nodes[node].skew_ms = skew_ms;
+                self.set_clocks();
+                Ok(format!("clock at {}", self.nodes[node].clock_ms(self.now_ms)))
+            }
+            Action::Advance { ms } => {
+                self.now_ms += ms;
+                self.set_clocks();
+                Ok(format!("time {}", self.now_ms))
+            }
+        }
+    }
+
+    fn set_clocks(&self) {
+        for node in &self.nodes {
+            node.clock.set_ms(node.clock_ms(self.now_ms));
+        }
+    }
+
+    /// Every repository validates and has an acyclic provenance DAG, and
+    /// each node's receipts are a prefix of the hub's.
+    fn check(&self) -> Result<(), String> {
+        check_repo("the hub", &self.hub)?;
+        let hub = self.receipts(&self.hub)?;
+        for (i, node) in self.nodes.iter().enumerate() {
+            let name = format!("node {i}");
+            check_repo(&name, &node.wll)?;
+            if !hub.starts_with(&self.receipts(&node.wll)?) {
+                return Err(format!("{name} has receipts the hub does not"));
+            }
+        }
+        Ok(())
+    }
+
+    /// After settling, every node has the hub's `main` and receipts and
+    /// nothing queued.
+    fn converged(&self) -> Result<(), String> {
+        let tip = |wll: &Wll| -> Result<Option<[u8; 32]>, String> {
+            let main = wll.refs().read_ref(MAIN).map_err(|e| e.to_string())?;
+            Ok(main.map(|r| *r.target_hash()))
+        };
+        let hub = (tip(&self.hub)?, self.receipts(&self.hub)?);
+        for (i, node) in self.nodes.iter().enumerate() {
+            if (tip(&node.wll)?, self.receipts(&node.wll)?) != hub {
+                return Err(format!("node {i} did not converge on the hub's main"));
+            }
+            if self.pending(i)? > 0 {
+                return Err(format!("node {i} still has queued commits"));
+            }
+        }
+        Ok(())
+    }
+
+    fn receipts(&self, wll: &Wll) -> Result<Vec<Receipt>, String> {
+        wll.ledger().read_all(wll.worldline()).map_err(|e| e.to_string())
+    }
+
+    fn pending(&self, node: usize) -> Result<usize, String> {
+        let pending = self.nodes[node].wll.pending_commits().map_err(|e| e.to_string())?;
+        Ok(pending.len())
+    }
+
+    fn failure(&self, step: usize, invariant: String) -> SimFailure {
+        SimFailure {
+            seed: self.config.seed,
+            step,
+            invariant,
+            trace: self.report.trace.clone(),
+        }
+    }
+}
+
+impl Node {
+    fn clock_ms(&self, now_ms: u64) -> u64 {
+        now_ms.saturating_add_signed(self.skew_ms)
+    }
+}
+
+fn check_repo(name: &str, wll: &Wll) -> Result<(), String> {
+    let report = wll.verify().map_err(|e| format!("{name}: {e}"))?;
+    if let Some(violation) = report.violations.first() {
+        return Err(format!("{name}'s chain is invalid: {}", violation.description));
+    }
+    let dag = wll.provenance().map_err(|e| format!("{name}: {e}"))?;
+    if dag.topological_order().len() != dag.len() {
+        return Err(format!("{name}'s provenance DAG has a cycle"));
+    }
+    Ok(())
+}
+
+/// A pull's outcome; a peer without `main` yet has nothing to pull.
+fn pulled(result: Result<wll_sync::PullResult, SdkError>) -> Result<String, Failed> {
+    match result {
+        Ok(pull) => Ok(format!("{:?}", pull.merge_status)),
+        Err(SdkError::BranchNotFound(_)) => Ok("no main yet".into()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Seeds to run, or just `WLL_SIM_SEED` to replay a failure.
+    fn seeds() -> Vec<u64> {
+        match std::env::var("WLL_SIM_SEED") {
+            Ok(seed) => vec![seed.parse().expect("WLL_SIM_SEED is a number")],
+            Err(_) => (0..8).collect(),
+        }
+    }
+
+    #[test]
+    fn nodes_converge_through_partitions_and_skew() {
+        for seed in seeds() {
+            let report = Simulation::run(SimConfig::new(seed)).unwrap_or_else(|e| panic!("{e}"));
+            assert!(report.accepted > 0, "seed {seed} committed nothing");
+            assert_eq!(report.receipts, report.accepted * 2, "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn a_seed_replays_the_same_schedule() {
+        let config = SimConfig::new(42).with_nodes(2).with_steps(60).with_paths(1);
+        let actions = |report: SimReport| -> Vec<Action> {
+            report.trace.into_iter().map(|e| e.action).collect()
+        };
+        let first = Simulation::run(config.clone()).unwrap();
+        let (merged, discarded) = (first.merged, first.discarded);
+        let second = Simulation::run(config).unwrap();
+        assert_eq!((second.merged, second.discarded), (merged, discarded));
+        assert_eq!(actions(first), actions(second));
+    }
+}
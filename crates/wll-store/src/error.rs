@@ -1,4 +1,4 @@
-use wll_types::ObjectId;
+use wll_types::{Coded, ErrorCode, LimitExceeded, ObjectId};
 
 /// Errors from object store operations.
 #[derive(Debug, thiserror::Error)]
@@ -34,6 +34,33 @@ pub enum StoreError {
     /// Storage backend is read-only or otherwise unavailable.
     #[error("store is read-only")]
     ReadOnly,
+
+    /// The object's content was redacted and replaced by a tombstone.
+    #[error("object {id} was redacted: {reason}")]
+    Redacted { id: ObjectId, reason: String },
+
+    /// A received object is larger or deeper than the decoding limits.
+    #[error("object {id} exceeds decoding limits: {source}")]
+    LimitExceeded {
+        id: ObjectId,
+        #[source]
+        source: LimitExceeded,
+    },
+}
+
+impl Coded for StoreError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::NotFound(_) => ErrorCode::ObjectNotFound,
+            Self::HashMismatch { .. } | Self::CorruptObject { .. } => ErrorCode::ObjectCorrupt,
+            Self::Serialization(_) => ErrorCode::Serialization,
+            Self::Io(_) => ErrorCode::Io,
+            Self::NullObjectId => ErrorCode::InvalidInput,
+            Self::ReadOnly => ErrorCode::StoreReadOnly,
+            Self::Redacted { .. } => ErrorCode::ObjectRedacted,
+            Self::LimitExceeded { source, .. } => source.code(),
+        }
+    }
 }
 
 /// Result alias for store operations.
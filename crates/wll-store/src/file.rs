@@ -0,0 +1,371 @@
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use wll_types::{HashAlgo, ObjectId};
+
+use crate::error::{StoreError, StoreResult};
+use crate::object::{ObjectKind, StoredObject, Tombstone};
+use crate::options::WriteOptions;
+use crate::shared::SharedReads;
+use crate::traits::ObjectStore;
+
+/// Loose-object store backed by a directory tree.
+///
+/// Objects live at `<root>/<xx>/<remaining 62 hex chars>`, mirroring git's
+/// loose object layout. Objects addressed by a non-default hash algorithm are
+/// kept under `<root>/<algo>/` so ids from different algorithms never share
+/// a path. Each file holds a one-byte [`ObjectKind`] tag followed by the raw
+/// object data.
+///
+/// Writes go to a temporary file in the fan-out directory and are renamed
+/// into place, so readers never observe a partially written object. Reads
+/// recompute the content hash and reject objects whose bytes no longer match
+/// their id; a tombstone matches the id it records. How durable a write is
+/// follows the store's [`WriteOptions`], which by default flush each object
+/// file before the rename.
+///
+/// [`read_shared`](ObjectStore::read_shared) decodes an object once for all
+/// the readers holding it at the time; clones of the store share them too.
+#[derive(Debug, Clone)]
+pub struct FileObjectStore {
+    root: PathBuf,
+    write_options: WriteOptions,
+    shared: Arc<SharedReads>,
+}
+
+impl FileObjectStore {
+    /// Open a store rooted at `root`, creating the directory if needed.
+    pub fn open(root: impl Into<PathBuf>) -> StoreResult<Self> {
+        let root = root.into();
+        fs::create_dir_all(&root)?;
+        Ok(Self { root, write_options: WriteOptions::default(), shared: Arc::default() })
+    }
+
+    /// Write with `options` wherever no options are given, as by
+    /// [`ObjectStore::write`].
+    pub fn with_write_options(mut self, options: WriteOptions) -> Self {
+        self.write_options = options;
+        self
+    }
+
+    pub fn write_options(&self) -> &WriteOptions {
+        &self.write_options
+    }
+
+    /// The directory this store writes objects into.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Path of the loose file that holds `id`.
+    pub fn object_path(&self, id: &ObjectId) -> PathBuf {
+        let hex = hex_bytes(id.as_bytes());
+        let base = match id.algo() {
+            HashAlgo::Blake3 => self.root.clone(),
+            other => self.root.join(other.name()),
+        };
+        base.join(&hex[..2]).join(&hex[2..])
+    }
+
+    /// Return every object id present in the store, sorted.
+    pub fn all_ids(&self) -> StoreResult<Vec<ObjectId>> {
+        let mut ids = Vec::new();
+        collect_ids(&self.root, HashAlgo::Blake3, &mut ids)?;
+        for algo in HashAlgo::ALL {
+            if algo != HashAlgo::Blake3 {
+                collect_ids(&self.root.join(algo.name()), algo, &mut ids)?;
+            }
+        }
+        ids.sort();
+        Ok(ids)
+    }
+
+    /// Write `object` to `path` through a temporary file renamed into place.
+    fn write_file(path: &Path, object: &StoredObject, options: &WriteOptions) -> StoreResult<()> {
+        let dir = path.parent().expect("object path always has a fan-out directory");
+        fs::create_dir_all(dir)?;
+        let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+        tmp.write_all(&[object.kind.tag()])?;
+        tmp.write_all(&object.data)?;
+        if options.fsync {
+            tmp.as_file().sync_all()?;
+        }
+        tmp.persist(path).map_err(|e| StoreError::Io(e.error))?;
+        options.sync_dir(dir)?;
+        Ok(())
+    }
+
+    fn decode(id: &ObjectId, bytes: Vec<u8>) -> StoreResult<StoredObject> {
+        let (&tag, data) = bytes.split_first().ok_or_else(|| StoreError::CorruptObject {
+            id: *id,
+            reason: "empty object file".into(),
+        })?;
+        let kind = ObjectKind::from_tag(tag).ok_or_else(|| StoreError::CorruptObject {
+            id: *id,
+            reason: format!("unknown object kind tag {tag}"),
+        })?;
+        let object = StoredObject::new(kind, data.to_vec());
+
+        let computed = object.compute_id();
+        if computed.as_bytes() != id.as_bytes() {
+            return Err(StoreError::HashMismatch {
+                id: *id,
+                expected: id.to_hex(),
+                computed: computed.to_hex(),
+            });
+        }
+        Ok(object)
+    }
+}
+
+impl ObjectStore for FileObjectStore {
+    fn read(&self, id: &ObjectId) -> StoreResult<Option<StoredObject>> {
+        match fs::read(self.object_path(id)) {
+            Ok(bytes) => Self::decode(id, bytes).map(Some),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn read_shared(&self, id: &ObjectId) -> StoreResult<Option<Arc<StoredObject>>> {
+        self.shared.get_or_load(id, || self.read(id))
+    }
+
+    fn write(&self, object: &StoredObject) -> StoreResult<ObjectId> {
+        self.write_with_options(object, &self.write_options)
+    }
+
+    /// With [`verify`](WriteOptions::verify) set, an object already on disk
+    /// is read back too, so a damaged copy is reported rather than trusted.
+    fn write_with_options(
+        &self,
+        object: &StoredObject,
+        options: &WriteOptions,
+    ) -> StoreResult<ObjectId> {
+        let id = object.compute_id();
+        if id.is_null() {
+            return Err(StoreError::NullObjectId);
+        }
+
+        let path = self.object_path(&id);
+        if !path.exists() {
+            Self::write_file(&path, object, options)?;
+        }
+        if options.verify {
+            Self::decode(&id, fs::read(&path)?)?;
+        }
+        Ok(id)
+    }
+
+    fn exists(&self, id: &ObjectId) -> StoreResult<bool> {
+        Ok(self.object_path(id).is_file())
+    }
+
+    fn delete(&self, id: &ObjectId) -> StoreResult<bool> {
+        self.shared.forget(id);
+        match fs::remove_file(self.object_path(id)) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn redact(&self, tombstone: &Tombstone) -> StoreResult<()> {
+        // The rename replaces the content in one step.
+        Self::write_file(
+            &self.object_path(&tombstone.original),
+            &tombstone.to_stored_object()?,
+            &self.write_options,
+        )?;
+        self.shared.forget(&tombstone.original);
+        Ok(())
+    }
+
+    fn list_ids(&self) -> StoreResult<Option<Vec<ObjectId>>> {
+        self.all_ids().map(Some)
+    }
+
+    fn quarantine_dir(&self) -> Option<PathBuf> {
+        Some(self.root.join("quarantine"))
+    }
+}
+
+fn collect_ids(base: &Path, algo: HashAlgo, out: &mut Vec<ObjectId>) -> StoreResult<()> {
+    let fan_outs = match fs::read_dir(base) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    for fan_out in fan_outs {
+        let fan_out = fan_out?;
+        let prefix = fan_out.file_name().to_string_lossy().into_owned();
+        if prefix.len() != 2 || !fan_out.file_type()?.is_dir() {
+            continue;
+        }
+        for entry in fs::read_dir(fan_out.path())? {
+            let name = entry?.file_name().to_string_lossy().into_owned();
+            if let Some(hash) = parse_hash(&format!("{prefix}{name}")) {
+                out.push(ObjectId::from_algo_hash(algo, hash));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn hex_bytes(bytes: &[u8; 32]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn parse_hash(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 {
+        return None;
+    }
+    let mut out = [0u8; 32];
+    for (i, byte) in out.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::{Blob, EntryMode, Tombstone, Tree, TreeEntry};
+
+    fn store() -> (tempfile::TempDir, FileObjectStore) {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileObjectStore::open(dir.path().join("objects")).unwrap();
+        (dir, store)
+    }
+
+    #[test]
+    fn verified_writes_report_a_damaged_copy_on_disk() {
+        let (_dir, store) = store();
+        let store = store.with_write_options(WriteOptions::fast());
+        let blob = Blob::new(b"checked".to_vec()).to_stored_object();
+        let id = store.write(&blob).unwrap();
+        assert_eq!(store.write_with_options(&blob, &WriteOptions::durable()).unwrap(), id);
+
+        fs::write(store.object_path(&id), [&[blob.kind.tag()][..], b"damaged"].concat()).unwrap();
+        assert_eq!(store.write(&blob).unwrap(), id, "unverified writes trust the file");
+        let err = store.write_with_options(&blob, &WriteOptions::durable()).unwrap_err();
+        assert!(matches!(err, StoreError::HashMismatch { .. }));
+    }
+
+    #[test]
+    fn write_then_read_roundtrip() {
+        let (_dir, store) = store();
+        let blob = Blob::new(b"persisted".to_vec()).to_stored_object();
+        let id = store.write(&blob).unwrap();
+
+        assert!(store.exists(&id).unwrap());
+        assert_eq!(store.read(&id).unwrap(), Some(blob));
+    }
+
+    #[test]
+    fn objects_survive_reopen() {
+        let (dir, store) = store();
+        let tree = Tree::new(vec![TreeEntry::new(
+            EntryMode::Regular,
+            "a.txt",
+            ObjectId::from_bytes(b"a"),
+        )])
+        .to_stored_object()
+        .unwrap();
+        let id = store.write(&tree).unwrap();
+        drop(store);
+
+        let reopened = FileObjectStore::open(dir.path().join("objects")).unwrap();
+        assert_eq!(reopened.read(&id).unwrap().unwrap().kind, ObjectKind::Tree);
+        assert_eq!(reopened.all_ids().unwrap(), vec![id]);
+    }
+
+    #[test]
+    fn missing_object_reads_none() {
+        let (_dir, store) = store();
+        let id = ObjectId::from_bytes(b"absent");
+        assert!(store.read(&id).unwrap().is_none());
+        assert!(!store.exists(&id).unwrap());
+        assert!(!store.delete(&id).unwrap());
+    }
+
+    #[test]
+    fn write_is_idempotent() {
+        let (_dir, store) = store();
+        let blob = Blob::new(b"same".to_vec()).to_stored_object();
+        assert_eq!(store.write(&blob).unwrap(), store.write(&blob).unwrap());
+        assert_eq!(store.all_ids().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn corrupted_file_is_detected() {
+        let (_dir, store) = store();
+        let id = store
+            .write(&Blob::new(b"original".to_vec()).to_stored_object())
+            .unwrap();
+
+        let mut bytes = fs::read(store.object_path(&id)).unwrap();
+        *bytes.last_mut().unwrap() ^= 0xff;
+        fs::write(store.object_path(&id), bytes).unwrap();
+
+        assert!(matches!(
+            store.read(&id),
+            Err(StoreError::HashMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn shared_reads_decode_once_for_every_holder() {
+        let (_dir, store) = store();
+        let id = store.write(&Blob::new(b"shared".to_vec()).to_stored_object()).unwrap();
+
+        let first = store.read_shared(&id).unwrap().unwrap();
+        let second = store.clone().read_shared(&id).unwrap().unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(*first, store.read(&id).unwrap().unwrap());
+        assert!(store.read_shared(&ObjectId::null()).unwrap().is_none());
+    }
+
+    #[test]
+    fn redacted_object_reads_back_as_its_tombstone() {
+        let (dir, store) = store();
+        let blob = Blob::new(b"redact me".to_vec()).to_stored_object();
+        let id = store.write(&blob).unwrap();
+        let held = store.read_shared(&id).unwrap().unwrap();
+        let key = wll_crypto::SigningKey::from_bytes([5; 32]);
+        let message = Tombstone::signed_message(&id, ObjectKind::Blob, blob.size, "takedown");
+        let tombstone = Tombstone {
+            original: id,
+            original_kind: ObjectKind::Blob,
+            original_size: blob.size,
+            reason: "takedown".into(),
+            signer: key.verifying_key().as_bytes(),
+            signature: key.sign(&message),
+            receipt_hash: [1; 32],
+        };
+        store.redact(&tombstone).unwrap();
+        assert!(!held.is_tombstone());
+        assert!(store.read_shared(&id).unwrap().unwrap().is_tombstone());
+
+        let reopened = FileObjectStore::open(dir.path().join("objects")).unwrap();
+        let stored = reopened.read(&id).unwrap().unwrap();
+        assert_eq!(Tombstone::from_stored_object(&stored).unwrap(), tombstone);
+        assert_eq!(reopened.all_ids().unwrap(), vec![id]);
+        // Writing the original again does not bring the content back.
+        reopened.write(&blob).unwrap();
+        assert!(reopened.read(&id).unwrap().unwrap().is_tombstone());
+    }
+
+    #[test]
+    fn delete_removes_object() {
+        let (_dir, store) = store();
+        let id = store
+            .write(&Blob::new(b"gone".to_vec()).to_stored_object())
+            .unwrap();
+        assert!(store.delete(&id).unwrap());
+        assert!(store.read(&id).unwrap().is_none());
+    }
+}
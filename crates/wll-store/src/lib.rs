@@ -11,16 +11,31 @@
 //! - [`Tree`] -- directory listing mapping names to object references
 //! - [`ReceiptObject`] -- serialized receipt for chain integrity
 //! - [`SnapshotObject`] -- point-in-time worldline state
+//! - [`Tombstone`] -- signed placeholder for redacted content, stored under
+//!   the redacted object's id
 //!
 //! # Storage Backends
 //!
 //! All backends implement the [`ObjectStore`] trait:
 //!
 //! - [`InMemoryObjectStore`] -- `HashMap`-based store for tests and embedding
+//! - [`FileObjectStore`] -- loose-object directory store for on-disk repositories
+//!
+//! [`FileObjectStore`] decodes objects on read and shares each decoded
+//! object between the readers holding it through [`SharedReads`].
+//!
+//! Objects received from a peer are written to a [`Quarantine`] first and
+//! only reach the store once verified.
+//!
+//! [`WriteOptions`] choose between durability and throughput per write
+//! ([`ObjectStore::write_with_options`]) or for a whole
+//! [`FileObjectStore`]: read-back verification, and flushing the object
+//! file and its directory.
 //!
 //! # Design Rules
 //!
-//! 1. Objects are immutable once written (content-addressing guarantees this).
+//! 1. Objects are immutable once written (content-addressing guarantees this),
+//!    except that [`ObjectStore::redact`] may replace one with its tombstone.
 //! 2. Write-then-link: write object, verify hash, then update references.
 //! 3. Concurrent reads are always safe (objects are immutable).
 //! 4. Writes are serialized per-stream but parallel across streams.
@@ -28,14 +43,23 @@
 //! 6. All I/O errors are propagated, never silently ignored.
 
 pub mod error;
+pub mod file;
 pub mod memory;
 pub mod object;
+pub mod options;
+pub mod quarantine;
+pub mod shared;
 pub mod traits;
 
 // Re-export primary types at crate root for ergonomic imports.
 pub use error::{StoreError, StoreResult};
+pub use file::FileObjectStore;
 pub use memory::InMemoryObjectStore;
 pub use object::{
-    Blob, EntryMode, ObjectKind, ReceiptObject, SnapshotObject, StoredObject, Tree, TreeEntry,
+    Blob, EntryMode, ObjectKind, ReceiptObject, SnapshotObject, StoredObject, Tombstone, Tree,
+    TreeEntry,
 };
+pub use options::WriteOptions;
+pub use quarantine::Quarantine;
+pub use shared::SharedReads;
 pub use traits::ObjectStore;
@@ -1,18 +1,20 @@
 use std::collections::HashMap;
-use std::sync::RwLock;
+use std::sync::{Arc, RwLock};
 
 use wll_types::ObjectId;
 
 use crate::error::{StoreError, StoreResult};
-use crate::object::StoredObject;
+use crate::object::{StoredObject, Tombstone};
 use crate::traits::ObjectStore;
 
 /// In-memory, HashMap-based object store.
 ///
 /// Intended for tests and embedding. All objects are held in memory behind a
-/// `RwLock` for safe concurrent access. Objects are cloned on read/write.
+/// `RwLock` for safe concurrent access. Objects are copied in on write and
+/// out on [`read`](ObjectStore::read); [`read_shared`](ObjectStore::read_shared)
+/// hands out the stored object without copying.
 pub struct InMemoryObjectStore {
-    objects: RwLock<HashMap<ObjectId, StoredObject>>,
+    objects: RwLock<HashMap<ObjectId, Arc<StoredObject>>>,
 }
 
 impl InMemoryObjectStore {
@@ -64,7 +66,16 @@ impl Default for InMemoryObjectStore {
 }
 
 impl ObjectStore for InMemoryObjectStore {
+    fn list_ids(&self) -> StoreResult<Option<Vec<ObjectId>>> {
+        Ok(Some(self.all_ids()))
+    }
+
     fn read(&self, id: &ObjectId) -> StoreResult<Option<StoredObject>> {
+        let map = self.objects.read().expect("lock poisoned");
+        Ok(map.get(id).map(|object| StoredObject::clone(object)))
+    }
+
+    fn read_shared(&self, id: &ObjectId) -> StoreResult<Option<Arc<StoredObject>>> {
         let map = self.objects.read().expect("lock poisoned");
         Ok(map.get(id).cloned())
     }
@@ -77,7 +88,7 @@ impl ObjectStore for InMemoryObjectStore {
         let mut map = self.objects.write().expect("lock poisoned");
         // Idempotent: if already present, skip (content-addressing guarantees
         // the same ID always maps to the same content).
-        map.entry(id).or_insert_with(|| object.clone());
+        map.entry(id).or_insert_with(|| Arc::new(object.clone()));
         Ok(id)
     }
 
@@ -90,6 +101,13 @@ impl ObjectStore for InMemoryObjectStore {
         let mut map = self.objects.write().expect("lock poisoned");
         Ok(map.remove(id).is_some())
     }
+
+    fn redact(&self, tombstone: &Tombstone) -> StoreResult<()> {
+        let object = tombstone.to_stored_object()?;
+        let mut map = self.objects.write().expect("lock poisoned");
+        map.insert(tombstone.original, Arc::new(object));
+        Ok(())
+    }
 }
 
 impl std::fmt::Debug for InMemoryObjectStore {
@@ -166,6 +184,19 @@ mod tests {
         assert_eq!(read_back, obj);
     }
 
+    #[test]
+    fn read_shared_hands_out_the_stored_object() {
+        let store = InMemoryObjectStore::new();
+        let obj = make_blob(b"shared");
+        let id = store.write(&obj).unwrap();
+
+        let first = store.read_shared(&id).unwrap().expect("should exist");
+        let second = store.read_shared(&id).unwrap().expect("should exist");
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(*first, obj);
+        assert!(store.read_shared(&ObjectId::null()).unwrap().is_none());
+    }
+
     #[test]
     fn write_and_read_tree() {
         let store = InMemoryObjectStore::new();
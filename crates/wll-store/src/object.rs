@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use wll_crypto::ContentHasher;
+use wll_crypto::{ContentHasher, Signature, VerifyingKey};
 use wll_types::{ObjectId, ReceiptKind, TemporalAnchor, WorldlineId};
 
 use crate::error::{StoreError, StoreResult};
@@ -17,6 +17,35 @@ pub enum ObjectKind {
     Snapshot,
     /// Packed object bundle (for pack storage).
     Pack,
+    /// Placeholder left where a redacted object's content was.
+    Tombstone,
+}
+
+impl ObjectKind {
+    /// One-byte tag used by on-disk object encodings.
+    pub fn tag(self) -> u8 {
+        match self {
+            Self::Blob => 1,
+            Self::Tree => 2,
+            Self::Receipt => 3,
+            Self::Snapshot => 4,
+            Self::Pack => 5,
+            Self::Tombstone => 6,
+        }
+    }
+
+    /// Inverse of [`ObjectKind::tag`].
+    pub fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            1 => Some(Self::Blob),
+            2 => Some(Self::Tree),
+            3 => Some(Self::Receipt),
+            4 => Some(Self::Snapshot),
+            5 => Some(Self::Pack),
+            6 => Some(Self::Tombstone),
+            _ => None,
+        }
+    }
 }
 
 impl std::fmt::Display for ObjectKind {
@@ -27,6 +56,7 @@ impl std::fmt::Display for ObjectKind {
             Self::Receipt => write!(f, "receipt"),
             Self::Snapshot => write!(f, "snapshot"),
             Self::Pack => write!(f, "pack"),
+            Self::Tombstone => write!(f, "tombstone"),
         }
     }
 }
@@ -54,16 +84,28 @@ impl StoredObject {
 
     /// Compute the content-addressed ID for this object.
     ///
-    /// Uses the appropriate domain-separated hasher for each object kind.
+    /// Uses the appropriate domain-separated hasher for each object kind. A
+    /// tombstone stands in for the object it redacted, so its ID is the one
+    /// it records; one that does not decode gets the hash of its bytes,
+    /// which matches no ID it could be stored under.
     pub fn compute_id(&self) -> ObjectId {
         let hasher = match self.kind {
             ObjectKind::Blob => &ContentHasher::BLOB,
             ObjectKind::Tree => &ContentHasher::TREE,
             ObjectKind::Receipt => &ContentHasher::RECEIPT,
             ObjectKind::Snapshot | ObjectKind::Pack => &ContentHasher::COMMIT,
+            ObjectKind::Tombstone => match serde_json::from_slice::<Tombstone>(&self.data) {
+                Ok(tombstone) => return tombstone.original,
+                Err(_) => &ContentHasher::COMMIT,
+            },
         };
         hasher.hash(&self.data)
     }
+
+    /// Returns `true` if this is a tombstone left by a redaction.
+    pub fn is_tombstone(&self) -> bool {
+        self.kind == ObjectKind::Tombstone
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -88,7 +130,16 @@ impl Blob {
     }
 
     /// Decode from a `StoredObject`.
+    ///
+    /// A tombstone decodes to [`StoreError::Redacted`].
     pub fn from_stored_object(obj: &StoredObject) -> StoreResult<Self> {
+        if obj.kind == ObjectKind::Tombstone {
+            let tombstone = Tombstone::from_stored_object(obj)?;
+            return Err(StoreError::Redacted {
+                id: tombstone.original,
+                reason: tombstone.reason,
+            });
+        }
         if obj.kind != ObjectKind::Blob {
             return Err(StoreError::CorruptObject {
                 id: obj.compute_id(),
@@ -319,6 +370,93 @@ impl SnapshotObject {
     }
 }
 
+// ---------------------------------------------------------------------------
+// Tombstone
+// ---------------------------------------------------------------------------
+
+/// Placeholder for an object whose content was redacted.
+///
+/// A tombstone is stored under the ID of the object it replaces, so trees
+/// and receipts that reference that ID stay intact. It records what the
+/// object was, why it was removed, and the receipt that recorded the
+/// redaction, and is signed by whoever redacted it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tombstone {
+    /// ID of the redacted object.
+    pub original: ObjectId,
+    /// Kind of the redacted object.
+    pub original_kind: ObjectKind,
+    /// Size of the redacted object's data in bytes.
+    pub original_size: u64,
+    /// Why the content was removed.
+    pub reason: String,
+    /// Ed25519 public key of the redactor.
+    pub signer: [u8; 32],
+    /// The redactor's signature over [`Tombstone::signed_message`].
+    pub signature: Signature,
+    /// Hash of the receipt that recorded the redaction.
+    pub receipt_hash: [u8; 32],
+}
+
+impl Tombstone {
+    /// The bytes a redactor signs: the redacted object's ID, kind, and
+    /// size, and the reason.
+    pub fn signed_message(
+        original: &ObjectId,
+        kind: ObjectKind,
+        size: u64,
+        reason: &str,
+    ) -> Vec<u8> {
+        let mut message = b"wll-redaction-v1".to_vec();
+        message.push(original.algo().tag());
+        message.extend_from_slice(original.as_bytes());
+        message.push(kind.tag());
+        message.extend_from_slice(&size.to_be_bytes());
+        message.extend_from_slice(reason.as_bytes());
+        message
+    }
+
+    /// Check the signature against the recorded signer.
+    ///
+    /// This shows the tombstone is what its signer made; whether the signer
+    /// may redact is for the caller to decide.
+    pub fn verify_signature(&self) -> StoreResult<()> {
+        let corrupt = |reason: String| StoreError::CorruptObject {
+            id: self.original,
+            reason,
+        };
+        let key = VerifyingKey::from_bytes(self.signer)
+            .map_err(|e| corrupt(format!("tombstone signer: {e}")))?;
+        let message = Self::signed_message(
+            &self.original,
+            self.original_kind,
+            self.original_size,
+            &self.reason,
+        );
+        key.verify(&message, &self.signature)
+            .map_err(|e| corrupt(format!("tombstone signature: {e}")))
+    }
+
+    /// Convert into a `StoredObject` for storage.
+    pub fn to_stored_object(&self) -> StoreResult<StoredObject> {
+        let data = serde_json::to_vec(self)
+            .map_err(|e| StoreError::Serialization(e.to_string()))?;
+        Ok(StoredObject::new(ObjectKind::Tombstone, data))
+    }
+
+    /// Decode from a `StoredObject`.
+    pub fn from_stored_object(obj: &StoredObject) -> StoreResult<Self> {
+        if obj.kind != ObjectKind::Tombstone {
+            return Err(StoreError::CorruptObject {
+                id: obj.compute_id(),
+                reason: format!("expected tombstone, got {}", obj.kind),
+            });
+        }
+        serde_json::from_slice(&obj.data)
+            .map_err(|e| StoreError::Serialization(e.to_string()))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -364,6 +502,19 @@ mod tests {
         assert_eq!(tree, decoded);
     }
 
+    #[test]
+    fn tree_ids_match_those_written_by_0_1_0() {
+        let tree = Tree::new(vec![
+            TreeEntry::new(EntryMode::Regular, "README.md", ObjectId::from_bytes(b"# readme\n")),
+            TreeEntry::new(EntryMode::Directory, "src", ObjectId::from_bytes(b"src tree")),
+        ]);
+        let id = tree.to_stored_object().unwrap().compute_id();
+        assert_eq!(
+            id.to_hex(),
+            "7eb1454883c0aa179d511a74cfd0fe5292788e2f411f39673679643549aca7a8"
+        );
+    }
+
     #[test]
     fn tree_get_entry() {
         let tree = Tree::new(vec![
@@ -449,6 +600,39 @@ mod tests {
         assert_ne!(blob.compute_id(), receipt.compute_id());
     }
 
+    #[test]
+    fn tombstone_stands_in_for_the_redacted_object() {
+        let blob = Blob::new(b"leaked secret".to_vec()).to_stored_object();
+        let id = blob.compute_id();
+        let key = wll_crypto::SigningKey::from_bytes([5; 32]);
+        let reason = "legal request";
+        let message = Tombstone::signed_message(&id, ObjectKind::Blob, blob.size, reason);
+        let tombstone = Tombstone {
+            original: id,
+            original_kind: ObjectKind::Blob,
+            original_size: blob.size,
+            reason: reason.into(),
+            signer: key.verifying_key().as_bytes(),
+            signature: key.sign(&message),
+            receipt_hash: [9; 32],
+        };
+        tombstone.verify_signature().unwrap();
+
+        let stored = tombstone.to_stored_object().unwrap();
+        assert!(stored.is_tombstone());
+        assert_eq!(stored.compute_id(), id);
+        assert!(matches!(
+            Blob::from_stored_object(&stored),
+            Err(StoreError::Redacted { id: redacted, .. }) if redacted == id
+        ));
+
+        let mut forged = tombstone;
+        forged.reason = "no reason".into();
+        assert!(forged.verify_signature().is_err());
+        let garbage = StoredObject::new(ObjectKind::Tombstone, b"not json".to_vec());
+        assert_ne!(garbage.compute_id(), id);
+    }
+
     #[test]
     fn object_kind_display() {
         assert_eq!(format!("{}", ObjectKind::Blob), "blob");
@@ -456,5 +640,6 @@ mod tests {
         assert_eq!(format!("{}", ObjectKind::Receipt), "receipt");
         assert_eq!(format!("{}", ObjectKind::Snapshot), "snapshot");
         assert_eq!(format!("{}", ObjectKind::Pack), "pack");
+        assert_eq!(format!("{}", ObjectKind::Tombstone), "tombstone");
     }
 }
@@ -0,0 +1,65 @@
+use std::path::Path;
+
+/// How durably an object is written, traded against write throughput.
+///
+/// The default flushes each object file before renaming it into place, so
+/// a crash leaves either the whole object or none of it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Read the object back after writing it and check that it hashes to
+    /// its id.
+    pub verify: bool,
+    /// Flush the object file to disk before renaming it into place.
+    pub fsync: bool,
+    /// Flush the directory holding the object after the rename, so the new
+    /// name survives a crash too.
+    pub fsync_dir: bool,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self { verify: false, fsync: true, fsync_dir: false }
+    }
+}
+
+impl WriteOptions {
+    /// No flushing and no read-back: fastest, for stores that can be
+    /// rebuilt, such as scratch repositories and bulk imports.
+    pub fn fast() -> Self {
+        Self { verify: false, fsync: false, fsync_dir: false }
+    }
+
+    /// Every check and flush this type offers.
+    pub fn durable() -> Self {
+        Self { verify: true, fsync: true, fsync_dir: true }
+    }
+
+    pub fn with_verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    pub fn with_fsync(mut self, fsync: bool) -> Self {
+        self.fsync = fsync;
+        self
+    }
+
+    pub fn with_fsync_dir(mut self, fsync_dir: bool) -> Self {
+        self.fsync_dir = fsync_dir;
+        self
+    }
+
+    /// Flush `dir` if these options ask for it.
+    pub fn sync_dir(&self, dir: &Path) -> std::io::Result<()> {
+        if !self.fsync_dir {
+            return Ok(());
+        }
+        // Only Unix lets a directory be opened and flushed like a file;
+        // elsewhere the rename is as durable as the filesystem makes it.
+        #[cfg(unix)]
+        std::fs::File::open(dir)?.sync_all()?;
+        #[cfg(not(unix))]
+        let _ = dir;
+        Ok(())
+    }
+}
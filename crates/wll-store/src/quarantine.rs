@@ -0,0 +1,271 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime};
+
+use wll_types::{DecodeLimits, ObjectId};
+
+use crate::error::{StoreError, StoreResult};
+use crate::file::FileObjectStore;
+use crate::memory::InMemoryObjectStore;
+use crate::object::{ObjectKind, StoredObject, Tombstone, Tree};
+use crate::traits::ObjectStore;
+
+/// Quarantine areas older than this are left over from a process that died
+/// mid-transfer and are removed when the next one opens.
+const STALE_AFTER: Duration = Duration::from_secs(60 * 60);
+
+/// Holding area for objects received from a peer, kept apart from the
+/// store they are meant for until they have been checked.
+///
+/// Writes land in the quarantine; reads and existence checks see the
+/// quarantine and then the primary store, so incoming objects can be
+/// verified against everything already held. [`migrate`](Self::migrate)
+/// copies them into the primary store. A quarantine dropped without
+/// migrating is discarded along with everything written to it, so a
+/// rejected transfer leaves nothing behind.
+///
+/// For a store with a [`quarantine_dir`](ObjectStore::quarantine_dir) the
+/// objects are written to a fresh `incoming-*` directory beneath it;
+/// otherwise they are held in memory.
+pub struct Quarantine<'a> {
+    primary: &'a dyn ObjectStore,
+    area: Area,
+    ids: RwLock<BTreeSet<ObjectId>>,
+    limits: DecodeLimits,
+}
+
+enum Area {
+    Memory(InMemoryObjectStore),
+    Disk {
+        store: FileObjectStore,
+        // Removes the directory on drop.
+        _dir: tempfile::TempDir,
+    },
+}
+
+impl Area {
+    fn store(&self) -> &dyn ObjectStore {
+        match self {
+            Self::Memory(store) => store,
+            Self::Disk { store, .. } => store,
+        }
+    }
+}
+
+impl<'a> Quarantine<'a> {
+    /// Open an empty quarantine in front of `primary`.
+    pub fn new(primary: &'a dyn ObjectStore) -> StoreResult<Self> {
+        let area = match primary.quarantine_dir() {
+            Some(root) => {
+                fs::create_dir_all(&root)?;
+                sweep_stale(&root)?;
+                let dir = tempfile::Builder::new().prefix("incoming-").tempdir_in(&root)?;
+                Area::Disk {
+                    store: FileObjectStore::open(dir.path())?,
+                    _dir: dir,
+                }
+            }
+            None => Area::Memory(InMemoryObjectStore::new()),
+        };
+        Ok(Self {
+            primary,
+            area,
+            ids: RwLock::new(BTreeSet::new()),
+            limits: DecodeLimits::DEFAULT,
+        })
+    }
+
+    /// Bound the trees, receipts, snapshots and tombstones [`verify`]
+    /// decodes by `limits` rather than the defaults.
+    ///
+    /// [`verify`]: Self::verify
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Number of objects in quarantine.
+    pub fn len(&self) -> usize {
+        self.ids.read().expect("quarantine lock poisoned").len()
+    }
+
+    /// Whether nothing has been quarantined.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every quarantined object id, sorted.
+    pub fn object_ids(&self) -> Vec<ObjectId> {
+        self.ids.read().expect("quarantine lock poisoned").iter().copied().collect()
+    }
+
+    /// Check every quarantined object.
+    ///
+    /// Each must read back under the id it was written as, and any but a
+    /// blob must be within the decoding limits before it is decoded. A
+    /// tombstone must carry a valid signature, and every entry of a tree
+    /// must be in the quarantine or the primary store.
+    pub fn verify(&self) -> StoreResult<()> {
+        for id in self.object_ids() {
+            let object = self.area.store().read(&id)?.ok_or(StoreError::NotFound(id))?;
+            let computed = object.compute_id();
+            if computed != id {
+                return Err(StoreError::HashMismatch {
+                    id,
+                    expected: id.to_hex(),
+                    computed: computed.to_hex(),
+                });
+            }
+            if !matches!(object.kind, ObjectKind::Blob | ObjectKind::Pack) {
+                self.limits
+                    .check_json(&object.data)
+                    .map_err(|source| StoreError::LimitExceeded { id, source })?;
+            }
+            match object.kind {
+                ObjectKind::Tombstone => Tombstone::from_stored_object(&object)?.verify_signature()?,
+                ObjectKind::Tree => {
+                    for entry in Tree::from_stored_object(&object)?.entries {
+                        if !self.exists(&entry.object_id)? {
+                            return Err(StoreError::CorruptObject {
+                                id,
+                                reason: format!(
+                                    "entry {} refers to missing object {}",
+                                    entry.name, entry.object_id
+                                ),
+                            });
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Copy every quarantined object into the primary store and discard
+    /// the quarantine. Returns the number of objects moved.
+    pub fn migrate(self) -> StoreResult<usize> {
+        let ids = self.object_ids();
+        for id in &ids {
+            let object = self.area.store().read(id)?.ok_or(StoreError::NotFound(*id))?;
+            self.primary.write(&object)?;
+        }
+        Ok(ids.len())
+    }
+}
+
+impl ObjectStore for Quarantine<'_> {
+    fn read(&self, id: &ObjectId) -> StoreResult<Option<StoredObject>> {
+        match self.area.store().read(id)? {
+            Some(object) => Ok(Some(object)),
+            None => self.primary.read(id),
+        }
+    }
+
+    fn write(&self, object: &StoredObject) -> StoreResult<ObjectId> {
+        let id = self.area.store().write(object)?;
+        self.ids.write().expect("quarantine lock poisoned").insert(id);
+        Ok(id)
+    }
+
+    fn exists(&self, id: &ObjectId) -> StoreResult<bool> {
+        if self.ids.read().expect("quarantine lock poisoned").contains(id) {
+            return Ok(true);
+        }
+        self.primary.exists(id)
+    }
+
+    /// Only quarantined objects can be deleted.
+    fn delete(&self, id: &ObjectId) -> StoreResult<bool> {
+        self.ids.write().expect("quarantine lock poisoned").remove(id);
+        self.area.store().delete(id)
+    }
+}
+
+/// Remove quarantine areas under `root` untouched for [`STALE_AFTER`].
+fn sweep_stale(root: &Path) -> StoreResult<()> {
+    let now = SystemTime::now();
+    for entry in fs::read_dir(root)? {
+        let entry = entry?;
+        if !entry.file_name().to_string_lossy().starts_with("incoming-") {
+            continue;
+        }
+        let modified = entry.metadata()?.modified()?;
+        if now.duration_since(modified).unwrap_or_default() < STALE_AFTER {
+            continue;
+        }
+        match fs::remove_dir_all(entry.path()) {
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::{Blob, EntryMode, TreeEntry};
+
+    #[test]
+    fn objects_reach_the_store_only_when_migrated() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileObjectStore::open(dir.path().join("objects")).unwrap();
+        let kept = Blob::new(b"kept".to_vec()).to_stored_object();
+        let kept_id = store.write(&kept).unwrap();
+
+        let blob = Blob::new(b"incoming".to_vec()).to_stored_object();
+        let tree = Tree::new(vec![
+            TreeEntry::new(EntryMode::Regular, "a", blob.compute_id()),
+            TreeEntry::new(EntryMode::Regular, "b", kept_id),
+        ])
+        .to_stored_object()
+        .unwrap();
+
+        // A tree whose entries are missing fails verification, and dropping
+        // its quarantine removes the area.
+        let rejected = Quarantine::new(&store).unwrap();
+        let tree_id = rejected.write(&tree).unwrap();
+        assert!(rejected.read(&kept_id).unwrap().is_some());
+        assert!(matches!(rejected.verify(), Err(StoreError::CorruptObject { .. })));
+        drop(rejected);
+        assert!(!store.exists(&tree_id).unwrap());
+        let areas = fs::read_dir(store.quarantine_dir().unwrap()).unwrap().count();
+        assert_eq!(areas, 0);
+
+        let accepted = Quarantine::new(&store).unwrap();
+        accepted.write(&tree).unwrap();
+        accepted.write(&blob).unwrap();
+        accepted.verify().unwrap();
+        assert!(!store.exists(&tree_id).unwrap());
+        assert_eq!(accepted.migrate().unwrap(), 2);
+        assert!(store.exists(&tree_id).unwrap() && store.exists(&blob.compute_id()).unwrap());
+        assert_eq!(store.all_ids().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn oversized_trees_are_refused_before_decoding() {
+        let store = InMemoryObjectStore::new();
+        let blob = Blob::new(b"leaf".to_vec()).to_stored_object();
+        let entries = (0..3)
+            .map(|n| TreeEntry::new(EntryMode::Regular, format!("f{n}"), blob.compute_id()))
+            .collect();
+        let tree = Tree::new(entries).to_stored_object().unwrap();
+
+        let quarantine = Quarantine::new(&store)
+            .unwrap()
+            .with_limits(DecodeLimits::new().with_max_array_len(2));
+        quarantine.write(&blob).unwrap();
+        let tree_id = quarantine.write(&tree).unwrap();
+        let err = quarantine.verify().unwrap_err();
+        assert!(matches!(err, StoreError::LimitExceeded { id, .. } if id == tree_id));
+
+        let quarantine = Quarantine::new(&store).unwrap();
+        quarantine.write(&blob).unwrap();
+        quarantine.write(&tree).unwrap();
+        quarantine.verify().unwrap();
+    }
+}
@@ -0,0 +1,90 @@
+//! Sharing decoded objects between concurrent readers.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Weak};
+
+use wll_types::ObjectId;
+
+use crate::object::StoredObject;
+
+/// Objects handed out by a store's [`read_shared`], held weakly.
+///
+/// Backends that decode objects on read (from files or packs) keep one so
+/// that readers of the same object at the same time share one buffer,
+/// without the store itself keeping objects alive once every reader has
+/// dropped them.
+///
+/// [`read_shared`]: crate::ObjectStore::read_shared
+#[derive(Debug, Default)]
+pub struct SharedReads {
+    live: Mutex<HashMap<ObjectId, Weak<StoredObject>>>,
+}
+
+impl SharedReads {
+    /// Create an empty map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The copy of `id` some reader still holds, or else the object `load`
+    /// returns, which later reads share for as long as anyone holds it.
+    pub fn get_or_load<E>(
+        &self,
+        id: &ObjectId,
+        load: impl FnOnce() -> Result<Option<StoredObject>, E>,
+    ) -> Result<Option<Arc<StoredObject>>, E> {
+        if let Some(object) = self.live(id) {
+            return Ok(Some(object));
+        }
+        let Some(object) = load()? else {
+            return Ok(None);
+        };
+        let mut live = self.live.lock().expect("lock poisoned");
+        // Another reader may have loaded it meanwhile.
+        if let Some(object) = live.get(id).and_then(Weak::upgrade) {
+            return Ok(Some(object));
+        }
+        if live.len() >= 64 && live.len().is_power_of_two() {
+            live.retain(|_, object| object.strong_count() > 0);
+        }
+        let object = Arc::new(object);
+        live.insert(*id, Arc::downgrade(&object));
+        Ok(Some(object))
+    }
+
+    /// Stop sharing the copy of `id` readers hold, so the next read loads
+    /// it again. Stores call this when an object's content changes.
+    pub fn forget(&self, id: &ObjectId) {
+        self.live.lock().expect("lock poisoned").remove(id);
+    }
+
+    fn live(&self, id: &ObjectId) -> Option<Arc<StoredObject>> {
+        self.live.lock().expect("lock poisoned").get(id).and_then(Weak::upgrade)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::ObjectKind;
+
+    #[test]
+    fn readers_share_a_copy_until_the_last_drops_it() {
+        let shared = SharedReads::new();
+        let object = StoredObject::new(ObjectKind::Blob, b"once".to_vec());
+        let id = object.compute_id();
+        let load = || Ok::<_, ()>(Some(object.clone()));
+
+        let first = shared.get_or_load(&id, load).unwrap().unwrap();
+        let second = shared.get_or_load(&id, || -> Result<_, ()> { panic!("loaded again") });
+        let second = second.unwrap().unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+
+        drop((first, second));
+        let third = shared.get_or_load(&id, load).unwrap().unwrap();
+        shared.forget(&id);
+        let fourth = shared.get_or_load(&id, load).unwrap().unwrap();
+        assert!(!Arc::ptr_eq(&third, &fourth));
+        assert!(shared.get_or_load(&ObjectId::null(), || Ok::<_, ()>(None)).unwrap().is_none());
+    }
+}
@@ -1,13 +1,18 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
 use wll_types::ObjectId;
 
-use crate::error::StoreResult;
-use crate::object::StoredObject;
+use crate::error::{StoreError, StoreResult};
+use crate::object::{StoredObject, Tombstone};
+use crate::options::WriteOptions;
 
 /// Content-addressed object store.
 ///
 /// All implementations must satisfy these invariants:
 /// - Objects are immutable once written. Content-addressing guarantees this:
-///   the same data always produces the same ID.
+///   the same data always produces the same ID. Redaction is the one
+///   exception: [`redact`](Self::redact) swaps an object for its tombstone.
 /// - Write-then-link: write the object, verify the hash, then return the ID.
 /// - Concurrent reads are always safe (objects are immutable).
 /// - The store never interprets object contents — it is a pure key-value store.
@@ -19,12 +24,48 @@ pub trait ObjectStore: Send + Sync {
     /// Returns `Err` on I/O failure or data corruption.
     fn read(&self, id: &ObjectId) -> StoreResult<Option<StoredObject>>;
 
+    /// Read an object without copying its data where the backend can.
+    ///
+    /// Backends that hold objects in memory return the stored object
+    /// itself, so concurrent readers share one buffer. The default wraps
+    /// [`read`](Self::read). Prefer this over `read` when the object is only
+    /// inspected, not consumed.
+    fn read_shared(&self, id: &ObjectId) -> StoreResult<Option<Arc<StoredObject>>> {
+        self.read(id).map(|object| object.map(Arc::new))
+    }
+
     /// Write an object and return its content-addressed ID.
     ///
     /// If the object already exists, this is a no-op (idempotent).
     /// The returned ID is computed from the object's kind and data.
     fn write(&self, object: &StoredObject) -> StoreResult<ObjectId>;
 
+    /// Write an object as durably as `options` ask.
+    ///
+    /// The default writes with [`write`](Self::write), whose durability is
+    /// the backend's own, and then reads the object back if
+    /// [`verify`](WriteOptions::verify) is set. Backends that write files
+    /// override it to honour the flush options.
+    fn write_with_options(
+        &self,
+        object: &StoredObject,
+        options: &WriteOptions,
+    ) -> StoreResult<ObjectId> {
+        let id = self.write(object)?;
+        if options.verify {
+            let stored = self.read(&id)?.ok_or(StoreError::NotFound(id))?;
+            let computed = stored.compute_id();
+            if computed != id {
+                return Err(StoreError::HashMismatch {
+                    id,
+                    expected: id.to_hex(),
+                    computed: computed.to_hex(),
+                });
+            }
+        }
+        Ok(id)
+    }
+
     /// Check whether an object exists in the store.
     fn exists(&self, id: &ObjectId) -> StoreResult<bool>;
 
@@ -34,6 +75,32 @@ pub trait ObjectStore: Send + Sync {
     /// referenced objects can corrupt the store.
     fn delete(&self, id: &ObjectId) -> StoreResult<bool>;
 
+    /// Replace an object's content with `tombstone`, which is then served
+    /// under the object's ID.
+    ///
+    /// Unlike [`write`](Self::write) this overwrites what is stored, and is
+    /// the only operation that does. The default deletes the object and
+    /// writes the tombstone; backends override it to make the swap atomic.
+    fn redact(&self, tombstone: &Tombstone) -> StoreResult<()> {
+        let object = tombstone.to_stored_object()?;
+        self.delete(&tombstone.original)?;
+        self.write(&object)?;
+        Ok(())
+    }
+
+    /// Every object id in the store, sorted, for backends that can list
+    /// their contents. The default is `None`.
+    fn list_ids(&self) -> StoreResult<Option<Vec<ObjectId>>> {
+        Ok(None)
+    }
+
+    /// Directory under which a [`Quarantine`](crate::Quarantine) in front
+    /// of this store keeps received objects, so they can be moved in cheaply.
+    /// The default is `None`: such stores quarantine in memory.
+    fn quarantine_dir(&self) -> Option<PathBuf> {
+        None
+    }
+
     /// Read multiple objects in a batch.
     ///
     /// Default implementation calls `read()` for each ID. Backends may
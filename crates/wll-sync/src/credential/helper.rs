@@ -0,0 +1,191 @@
+//! External credential helpers speaking the git-credential protocol.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use super::{Credential, CredentialRequest, CredentialSource};
+use crate::error::{SyncError, SyncResult};
+
+/// An external program asked for credentials.
+///
+/// The helper is run with one argument, `get`, `store`, or `erase`, and
+/// receives `key=value` lines on stdin terminated by a blank line:
+///
+/// ```text
+/// protocol=https
+/// host=wll.example.com
+/// path=team/repo
+/// ```
+///
+/// For `get` it prints the same format back; `password` (or `token`) holds
+/// the token and `username` is optional. `store` additionally receives
+/// `username` and `password`. Any git credential helper works unchanged.
+#[derive(Clone, Debug)]
+pub struct HelperCommand {
+    helper: String,
+}
+
+impl HelperCommand {
+    /// `helper` is a `credential.helper` value: `!shell command`, a path,
+    /// or a name resolved as `wll-credential-<name>`.
+    pub fn new(helper: impl Into<String>) -> Self {
+        Self {
+            helper: helper.into(),
+        }
+    }
+
+    fn command(&self, action: &str) -> Command {
+        if let Some(script) = self.helper.strip_prefix('!') {
+            let mut command = Command::new("sh");
+            command
+                .arg("-c")
+                .arg(format!("{script} \"$@\""))
+                .arg(script)
+                .arg(action);
+            command
+        } else {
+            let mut words = self.helper.split_whitespace();
+            let program = words.next().unwrap_or_default();
+            let program = if program.contains('/') {
+                program.to_string()
+            } else {
+                format!("wll-credential-{program}")
+            };
+            let mut command = Command::new(program);
+            command.args(words).arg(action);
+            command
+        }
+    }
+
+    /// Run the helper for `action`, returning its stdout.
+    fn run(&self, action: &str, input: &str) -> SyncResult<String> {
+        let mut child = self
+            .command(action)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| {
+                SyncError::Credential(format!("credential helper {:?}: {e}", self.helper))
+            })?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // Helpers may exit without reading their input.
+            match stdin.write_all(input.as_bytes()) {
+                Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => return Err(e.into()),
+                _ => {}
+            }
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(SyncError::Credential(format!(
+                "credential helper {:?} {action} {}",
+                self.helper, output.status
+            )));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+/// Render `request` (and `credential`, for `store`) as protocol input.
+fn encode(request: &CredentialRequest, credential: Option<&Credential>) -> String {
+    let mut text = format!("protocol={}\nhost={}\n", request.protocol, request.host);
+    if let Some(path) = &request.path {
+        text.push_str(&format!("path={path}\n"));
+    }
+    let username = credential
+        .and_then(|c| c.username.as_ref())
+        .or(request.username.as_ref());
+    if let Some(username) = username {
+        text.push_str(&format!("username={username}\n"));
+    }
+    if let Some(credential) = credential {
+        text.push_str(&format!("password={}\n", credential.token.expose()));
+    }
+    text.push('\n');
+    text
+}
+
+/// Parse a helper's `get` answer; no token means no credential.
+fn decode(request: &CredentialRequest, output: &str) -> Option<Credential> {
+    let mut username = request.username.clone();
+    let mut token = None;
+    for line in output.lines() {
+        if line.is_empty() {
+            break;
+        }
+        match line.split_once('=') {
+            Some(("username", value)) => username = Some(value.to_string()),
+            Some(("password" | "token", value)) => token = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    token.filter(|t| !t.is_empty()).map(|token| Credential {
+        host: request.host.clone(),
+        username,
+        token: token.into(),
+    })
+}
+
+impl CredentialSource for HelperCommand {
+    fn name(&self) -> &str {
+        &self.helper
+    }
+
+    fn get(&self, request: &CredentialRequest) -> SyncResult<Option<Credential>> {
+        Ok(decode(request, &self.run("get", &encode(request, None))?))
+    }
+
+    fn store(&self, request: &CredentialRequest, credential: &Credential) -> SyncResult<()> {
+        self.run("store", &encode(request, Some(credential)))
+            .map(drop)
+    }
+
+    fn erase(&self, request: &CredentialRequest) -> SyncResult<()> {
+        self.run("erase", &encode(request, None)).map(drop)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn protocol_roundtrip() {
+        let request = CredentialRequest::from_url("https://h/team/repo").unwrap();
+        let credential = Credential::new("h", "tok").with_username("bob");
+        let text = encode(&request, Some(&credential));
+        assert_eq!(
+            text,
+            "protocol=https\nhost=h\npath=team/repo\nusername=bob\npassword=tok\n\n"
+        );
+        assert_eq!(decode(&request, &text), Some(credential));
+        assert_eq!(decode(&request, "username=x\n\n"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn shell_helper_answers_get() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("log");
+        let helper = HelperCommand::new(format!(
+            "!f() {{ cat >> {log}; echo \"$1\" >> {log}; echo password=from-helper; }}; f",
+            log = log.display()
+        ));
+        let request = CredentialRequest::from_url("wll://h/r").unwrap();
+
+        assert_eq!(helper.get(&request).unwrap().unwrap().token.expose(), "from-helper");
+        helper.erase(&request).unwrap();
+        let seen = std::fs::read_to_string(&log).unwrap();
+        assert!(seen.contains("host=h\npath=r\n\nget\n"));
+        assert!(seen.ends_with("erase\n"));
+
+        let failing = HelperCommand::new("!exit 3");
+        assert!(matches!(
+            failing.get(&request),
+            Err(SyncError::Credential(_))
+        ));
+        assert!(HelperCommand::new("definitely-missing")
+            .get(&request)
+            .is_err());
+    }
+}
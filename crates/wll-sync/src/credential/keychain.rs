@@ -0,0 +1,151 @@
+//! OS keychain access through the platform's command-line tools.
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+use super::{Credential, CredentialRequest, CredentialSource};
+use crate::error::{SyncError, SyncResult};
+
+/// Service name tokens are filed under.
+const SERVICE: &str = "wll";
+
+/// Tokens in the OS keychain, one entry per host.
+///
+/// Uses `security` (the login keychain) on macOS and `secret-tool`
+/// (libsecret, e.g. GNOME Keyring or KWallet) elsewhere on Unix. Only the
+/// token is kept; user names are not.
+#[derive(Clone, Debug, Default)]
+pub struct Keychain;
+
+impl Keychain {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+fn run(program: &str, args: &[&str], input: Option<&str>) -> SyncResult<Output> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| SyncError::Credential(format!("keychain: cannot run {program}: {e}")))?;
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin.write_all(input.as_bytes())?;
+    }
+    Ok(child.wait_with_output()?)
+}
+
+fn failed(program: &str, output: &Output) -> SyncError {
+    SyncError::Credential(format!(
+        "keychain: {program} {}: {}",
+        output.status,
+        String::from_utf8_lossy(&output.stderr).trim()
+    ))
+}
+
+impl CredentialSource for Keychain {
+    fn name(&self) -> &str {
+        "keychain"
+    }
+
+    fn get(&self, request: &CredentialRequest) -> SyncResult<Option<Credential>> {
+        let output = if cfg!(target_os = "macos") {
+            run(
+                "security",
+                &[
+                    "find-generic-password",
+                    "-s",
+                    SERVICE,
+                    "-a",
+                    &request.host,
+                    "-w",
+                ],
+                None,
+            )?
+        } else {
+            run(
+                "secret-tool",
+                &["lookup", "service", SERVICE, "host", &request.host],
+                None,
+            )?
+        };
+        // Both tools exit non-zero when there is no matching entry.
+        if !output.status.success() {
+            return Ok(None);
+        }
+        let token = String::from_utf8_lossy(&output.stdout)
+            .trim_end_matches('\n')
+            .to_string();
+        Ok((!token.is_empty()).then(|| Credential {
+            host: request.host.clone(),
+            username: request.username.clone(),
+            token: token.into(),
+        }))
+    }
+
+    fn store(&self, request: &CredentialRequest, credential: &Credential) -> SyncResult<()> {
+        let (program, output) = if cfg!(target_os = "macos") {
+            let args = [
+                "add-generic-password",
+                "-U",
+                "-s",
+                SERVICE,
+                "-a",
+                &request.host,
+                "-w",
+                credential.token.expose(),
+            ];
+            ("security", run("security", &args, None)?)
+        } else {
+            let label = format!("wll credential for {}", request.host);
+            let args = [
+                "store",
+                "--label",
+                &label,
+                "service",
+                SERVICE,
+                "host",
+                &request.host,
+            ];
+            (
+                "secret-tool",
+                run("secret-tool", &args, Some(credential.token.expose()))?,
+            )
+        };
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(failed(program, &output))
+        }
+    }
+
+    fn erase(&self, request: &CredentialRequest) -> SyncResult<()> {
+        // A missing entry is not an error, so the exit status is ignored.
+        if cfg!(target_os = "macos") {
+            run(
+                "security",
+                &[
+                    "delete-generic-password",
+                    "-s",
+                    SERVICE,
+                    "-a",
+                    &request.host,
+                ],
+                None,
+            )?;
+        } else {
+            run(
+                "secret-tool",
+                &["clear", "service", SERVICE, "host", &request.host],
+                None,
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,463 @@
+//! Credentials for authenticating to remote repositories.
+//!
+//! Credentials are scoped by host: a [`CredentialRequest`] is derived from a
+//! remote URL, and every [`CredentialSource`] looks credentials up by the
+//! request's host. A [`CredentialManager`] asks its sources in order and
+//! caches the first answer for the rest of the process.
+//!
+//! Sources:
+//!
+//! - [`EnvCredentials`] — `WLL_TOKEN_<HOST>` and `WLL_TOKEN`
+//! - [`CredentialStore`] — a host-keyed TOML file (helper name `store`)
+//! - [`Keychain`] — the OS keychain (helper name `keychain`)
+//! - [`HelperCommand`] — an external program speaking the git-credential
+//!   protocol
+
+mod helper;
+mod keychain;
+mod store;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use wll_config::CredentialSettings;
+use wll_crypto::Secret;
+use wll_protocol::AuthMethod;
+
+use crate::error::{SyncError, SyncResult};
+
+pub use helper::HelperCommand;
+pub use keychain::Keychain;
+pub use store::CredentialStore;
+
+/// What is being authenticated to, parsed from a remote URL.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CredentialRequest {
+    /// URL scheme, e.g. `https` or `wll`.
+    pub protocol: String,
+    /// Host and optional port; the scope credentials are stored under.
+    pub host: String,
+    /// Repository path on the host, without the leading `/`.
+    pub path: Option<String>,
+    /// User name embedded in the URL, if any.
+    pub username: Option<String>,
+}
+
+impl CredentialRequest {
+    /// Parse `scheme://[user@]host[:port][/path]`.
+    pub fn from_url(url: &str) -> SyncResult<Self> {
+        let invalid =
+            || SyncError::Credential(format!("cannot derive a host from remote URL {url:?}"));
+        let (protocol, rest) = url.split_once("://").ok_or_else(invalid)?;
+        let (authority, path) = match rest.split_once('/') {
+            Some((authority, path)) => (authority, Some(path).filter(|p| !p.is_empty())),
+            None => (rest, None),
+        };
+        let (username, host) = match authority.rsplit_once('@') {
+            Some((user, host)) => (Some(user.to_string()), host),
+            None => (None, authority),
+        };
+        if protocol.is_empty() || host.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            protocol: protocol.to_ascii_lowercase(),
+            host: host.to_ascii_lowercase(),
+            path: path.map(str::to_string),
+            username,
+        })
+    }
+}
+
+/// A token for one host.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Credential {
+    pub host: String,
+    pub username: Option<String>,
+    /// Bearer token sent to the server.
+    pub token: Secret,
+}
+
+impl Credential {
+    pub fn new(host: impl Into<String>, token: impl Into<String>) -> Self {
+        Self {
+            host: host.into(),
+            username: None,
+            token: Secret::new(token),
+        }
+    }
+
+    pub fn with_username(mut self, username: impl Into<String>) -> Self {
+        self.username = Some(username.into());
+        self
+    }
+
+    /// How a transport should present this credential.
+    pub fn auth_method(&self) -> AuthMethod {
+        AuthMethod::Bearer(self.token.clone())
+    }
+}
+
+/// Somewhere credentials can be looked up, and possibly saved.
+pub trait CredentialSource: Send + Sync {
+    /// Name shown in diagnostics.
+    fn name(&self) -> &str;
+
+    /// Credential for `request`, or `None` if this source has none.
+    fn get(&self, request: &CredentialRequest) -> SyncResult<Option<Credential>>;
+
+    /// Remember `credential`. Read-only sources ignore it.
+    fn store(&self, _request: &CredentialRequest, _credential: &Credential) -> SyncResult<()> {
+        Ok(())
+    }
+
+    /// Forget the credential for `request`. Read-only sources ignore it.
+    fn erase(&self, _request: &CredentialRequest) -> SyncResult<()> {
+        Ok(())
+    }
+}
+
+/// Tokens from the environment: `<PREFIX>_<HOST>` first, then `<PREFIX>`.
+///
+/// The host part is upper-cased with every non-alphanumeric character
+/// replaced by `_`, so `wll.example.com:8443` reads
+/// `WLL_TOKEN_WLL_EXAMPLE_COM_8443`.
+#[derive(Clone, Debug)]
+pub struct EnvCredentials {
+    prefix: String,
+}
+
+impl EnvCredentials {
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+        }
+    }
+
+    /// Variable holding the token for `host`.
+    pub fn host_var(&self, host: &str) -> String {
+        let host: String = host
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_uppercase()
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        format!("{}_{host}", self.prefix)
+    }
+}
+
+impl Default for EnvCredentials {
+    fn default() -> Self {
+        Self::new("WLL_TOKEN")
+    }
+}
+
+impl CredentialSource for EnvCredentials {
+    fn name(&self) -> &str {
+        "env"
+    }
+
+    fn get(&self, request: &CredentialRequest) -> SyncResult<Option<Credential>> {
+        let token = [self.host_var(&request.host), self.prefix.clone()]
+            .iter()
+            .find_map(|var| std::env::var(var).ok().filter(|v| !v.is_empty()));
+        Ok(token.map(|token| Credential {
+            host: request.host.clone(),
+            username: request.username.clone(),
+            token: token.into(),
+        }))
+    }
+}
+
+/// Source for a `credential.helper` value.
+///
+/// `store` and `keychain` are built in (`store` keeps tokens in
+/// `store_path`). A value starting with `!` runs through the shell, a path
+/// runs as is, and any other name runs `wll-credential-<name>` from `PATH`.
+pub fn helper_source(
+    helper: &str,
+    store_path: Option<PathBuf>,
+) -> SyncResult<Box<dyn CredentialSource>> {
+    Ok(match helper.trim() {
+        "store" => {
+            let path = store_path.ok_or_else(|| {
+                SyncError::Credential("the store helper needs a credentials file location".into())
+            })?;
+            Box::new(CredentialStore::new(path))
+        }
+        "keychain" => Box::new(Keychain::new()),
+        "" => return Err(SyncError::Credential("empty credential helper".into())),
+        other => Box::new(HelperCommand::new(other)),
+    })
+}
+
+/// A source that only answers for URLs starting with `prefix`.
+struct Scoped {
+    prefix: Option<String>,
+    source: Box<dyn CredentialSource>,
+}
+
+impl Scoped {
+    fn matches(&self, url: &str) -> bool {
+        self.prefix
+            .as_deref()
+            .map_or(true, |prefix| url.starts_with(prefix))
+    }
+}
+
+/// Looks credentials up across sources, caching them per host.
+///
+/// Sources are asked in the order they were added; the first credential
+/// found wins and is cached until [`Self::reject`] is called for its host.
+pub struct CredentialManager {
+    sources: Vec<Scoped>,
+    cache: Mutex<HashMap<String, Credential>>,
+}
+
+impl CredentialManager {
+    /// A manager with no sources.
+    pub fn new() -> Self {
+        Self {
+            sources: Vec::new(),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Build from the `[credential]` config section.
+    ///
+    /// The environment is always asked first, then per-URL helpers (longest
+    /// prefix first), then the default helper.
+    pub fn from_settings(
+        settings: &CredentialSettings,
+        store_path: Option<PathBuf>,
+    ) -> SyncResult<Self> {
+        let mut manager = Self::new().with_source(EnvCredentials::default());
+        let mut per_url: Vec<_> = settings
+            .per_url
+            .iter()
+            .filter_map(|(prefix, s)| s.helper.as_deref().map(|helper| (prefix, helper)))
+            .collect();
+        per_url.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        for (prefix, helper) in per_url {
+            manager.sources.push(Scoped {
+                prefix: Some(prefix.clone()),
+                source: helper_source(helper, store_path.clone())?,
+            });
+        }
+        if let Some(helper) = &settings.helper {
+            manager.sources.push(Scoped {
+                prefix: None,
+                source: helper_source(helper, store_path)?,
+            });
+        }
+        Ok(manager)
+    }
+
+    /// Ask `source` for every URL, after the sources added so far.
+    pub fn with_source(mut self, source: impl CredentialSource + 'static) -> Self {
+        self.sources.push(Scoped {
+            prefix: None,
+            source: Box::new(source),
+        });
+        self
+    }
+
+    /// Names of the sources that apply to `url`, in lookup order.
+    pub fn sources_for(&self, url: &str) -> Vec<&str> {
+        self.sources
+            .iter()
+            .filter(|s| s.matches(url))
+            .map(|s| s.source.name())
+            .collect()
+    }
+
+    /// Credential for `url`, from the cache or the first source that has one.
+    pub fn get(&self, url: &str) -> SyncResult<Option<Credential>> {
+        let request = CredentialRequest::from_url(url)?;
+        if let Some(cached) = self.lock()?.get(&request.host) {
+            return Ok(Some(cached.clone()));
+        }
+        for scoped in self.sources.iter().filter(|s| s.matches(url)) {
+            if let Some(credential) = scoped.source.get(&request)? {
+                tracing::debug!(host = %request.host, source = scoped.source.name(), "credential found");
+                self.lock()?
+                    .insert(request.host.clone(), credential.clone());
+                return Ok(Some(credential));
+            }
+        }
+        Ok(None)
+    }
+
+    /// How to authenticate to `url`; anonymous when no source has a token.
+    pub fn auth_for(&self, url: &str) -> SyncResult<AuthMethod> {
+        Ok(self
+            .get(url)?
+            .map_or(AuthMethod::Anonymous, |credential| credential.auth_method()))
+    }
+
+    /// Save a credential that worked to every writable source for `url`.
+    ///
+    /// Fails if no source applies to `url`.
+    pub fn approve(&self, url: &str, credential: &Credential) -> SyncResult<()> {
+        let request = CredentialRequest::from_url(url)?;
+        let mut stored = false;
+        for scoped in self.sources.iter().filter(|s| s.matches(url)) {
+            scoped.source.store(&request, credential)?;
+            stored |= scoped.source.name() != "env";
+        }
+        if !stored {
+            return Err(SyncError::Credential(format!(
+                "no credential helper configured for {}",
+                request.host
+            )));
+        }
+        self.lock()?.insert(request.host, credential.clone());
+        Ok(())
+    }
+
+    /// Drop a credential the server refused, from the cache and every
+    /// source for `url`.
+    pub fn reject(&self, url: &str) -> SyncResult<()> {
+        let request = CredentialRequest::from_url(url)?;
+        self.lock()?.remove(&request.host);
+        for scoped in self.sources.iter().filter(|s| s.matches(url)) {
+            scoped.source.erase(&request)?;
+        }
+        Ok(())
+    }
+
+    fn lock(&self) -> SyncResult<std::sync::MutexGuard<'_, HashMap<String, Credential>>> {
+        self.cache
+            .lock()
+            .map_err(|_| SyncError::Credential("credential cache lock poisoned".into()))
+    }
+}
+
+impl Default for CredentialManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use wll_config::CredentialUrlSettings;
+
+    use super::*;
+
+    /// Counts lookups and answers for one host.
+    struct Fixed {
+        host: &'static str,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl CredentialSource for Fixed {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+
+        fn get(&self, request: &CredentialRequest) -> SyncResult<Option<Credential>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok((request.host == self.host).then(|| Credential::new(self.host, "t0ken")))
+        }
+    }
+
+    #[test]
+    fn request_from_url() {
+        let request =
+            CredentialRequest::from_url("https://alice@WLL.example.com:8443/team/repo").unwrap();
+        assert_eq!(request.protocol, "https");
+        assert_eq!(request.host, "wll.example.com:8443");
+        assert_eq!(request.path.as_deref(), Some("team/repo"));
+        assert_eq!(request.username.as_deref(), Some("alice"));
+
+        assert_eq!(CredentialRequest::from_url("wll://h").unwrap().path, None);
+        assert!(CredentialRequest::from_url("/local/path").is_err());
+        assert!(CredentialRequest::from_url("wll:///x").is_err());
+    }
+
+    #[test]
+    fn env_prefers_host_variable() {
+        let env = EnvCredentials::new("WLL_TEST_ENV_TOKEN");
+        assert_eq!(env.host_var("a.b:1"), "WLL_TEST_ENV_TOKEN_A_B_1");
+        let request = CredentialRequest::from_url("wll://a.b:1/r").unwrap();
+        assert!(env.get(&request).unwrap().is_none());
+
+        std::env::set_var("WLL_TEST_ENV_TOKEN", "general");
+        assert_eq!(env.get(&request).unwrap().unwrap().token.expose(), "general");
+        std::env::set_var("WLL_TEST_ENV_TOKEN_A_B_1", "scoped");
+        assert_eq!(env.get(&request).unwrap().unwrap().token.expose(), "scoped");
+    }
+
+    #[test]
+    fn manager_caches_per_host() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let manager = CredentialManager::new().with_source(Fixed {
+            host: "a",
+            calls: Arc::clone(&calls),
+        });
+
+        assert_eq!(manager.get("wll://a/x").unwrap().unwrap().token.expose(), "t0ken");
+        assert_eq!(manager.get("wll://a/y").unwrap().unwrap().token.expose(), "t0ken");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        assert!(matches!(
+            manager.auth_for("wll://b/x").unwrap(),
+            AuthMethod::Anonymous
+        ));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        manager.reject("wll://a/x").unwrap();
+        manager.get("wll://a/x").unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn settings_order_helpers_by_prefix() {
+        let mut settings = CredentialSettings {
+            helper: Some("keychain".into()),
+            ..Default::default()
+        };
+        settings.per_url.insert(
+            "https://corp/".into(),
+            CredentialUrlSettings {
+                helper: Some("store".into()),
+            },
+        );
+        let manager =
+            CredentialManager::from_settings(&settings, Some("/tmp/creds.toml".into())).unwrap();
+        assert_eq!(
+            manager.sources_for("https://corp/r"),
+            ["env", "store", "keychain"]
+        );
+        assert_eq!(manager.sources_for("https://other/r"), ["env", "keychain"]);
+
+        let no_store = CredentialSettings {
+            helper: Some("store".into()),
+            ..Default::default()
+        };
+        assert!(CredentialManager::from_settings(&no_store, None).is_err());
+    }
+
+    #[test]
+    fn approve_needs_a_writable_source() {
+        let manager = CredentialManager::new().with_source(EnvCredentials::new("WLL_TEST_APPROVE"));
+        let credential = Credential::new("h", "t");
+        assert!(manager.approve("wll://h/r", &credential).is_err());
+    }
+
+    #[test]
+    fn debug_redacts_token() {
+        let credential = Credential::new("h", "super-secret");
+        assert!(!format!("{credential:?}").contains("super-secret"));
+    }
+}
@@ -0,0 +1,143 @@
+//! Host-keyed credentials file.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use wll_crypto::Secret;
+
+use super::{Credential, CredentialRequest, CredentialSource};
+use crate::error::{SyncError, SyncResult};
+
+/// Tokens kept in a TOML file, one `[host."<host>"]` table per host.
+///
+/// The file is created readable only by its owner. Like git's `store`
+/// helper it holds tokens in plain text; prefer [`super::Keychain`] where
+/// one is available.
+#[derive(Clone, Debug)]
+pub struct CredentialStore {
+    path: PathBuf,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct StoreFile {
+    #[serde(default)]
+    host: BTreeMap<String, StoredCredential>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredCredential {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    username: Option<String>,
+    token: Secret,
+}
+
+impl CredentialStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn read(&self) -> SyncResult<StoreFile> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(text) => toml::from_str(&text)
+                .map_err(|e| SyncError::Credential(format!("{}: {e}", self.path.display()))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(StoreFile::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn write(&self, file: &StoreFile) -> SyncResult<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let text = toml::to_string(file).map_err(|e| SyncError::Credential(e.to_string()))?;
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        std::io::Write::write_all(&mut options.open(&self.path)?, text.as_bytes())?;
+        Ok(())
+    }
+}
+
+impl CredentialSource for CredentialStore {
+    fn name(&self) -> &str {
+        "store"
+    }
+
+    fn get(&self, request: &CredentialRequest) -> SyncResult<Option<Credential>> {
+        Ok(self
+            .read()?
+            .host
+            .remove(&request.host)
+            .map(|stored| Credential {
+                host: request.host.clone(),
+                username: stored.username,
+                token: stored.token,
+            }))
+    }
+
+    fn store(&self, request: &CredentialRequest, credential: &Credential) -> SyncResult<()> {
+        let mut file = self.read()?;
+        file.host.insert(
+            request.host.clone(),
+            StoredCredential {
+                username: credential.username.clone(),
+                token: credential.token.clone(),
+            },
+        );
+        self.write(&file)
+    }
+
+    fn erase(&self, request: &CredentialRequest) -> SyncResult<()> {
+        let mut file = self.read()?;
+        if file.host.remove(&request.host).is_some() {
+            self.write(&file)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn store_is_scoped_by_host() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = CredentialStore::new(dir.path().join("wll").join("credentials.toml"));
+        let a = CredentialRequest::from_url("https://a.example/x").unwrap();
+        let b = CredentialRequest::from_url("https://b.example/x").unwrap();
+
+        store
+            .store(
+                &a,
+                &Credential::new("a.example", "ta").with_username("alice"),
+            )
+            .unwrap();
+        store
+            .store(&b, &Credential::new("b.example", "tb"))
+            .unwrap();
+        let found = store.get(&a).unwrap().unwrap();
+        assert_eq!(found.token.expose(), "ta");
+        assert_eq!(found.username.as_deref(), Some("alice"));
+
+        store.erase(&a).unwrap();
+        assert!(store.get(&a).unwrap().is_none());
+        assert_eq!(store.get(&b).unwrap().unwrap().token.expose(), "tb");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(store.path())
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use thiserror::Error;
+use wll_types::{Coded, ErrorCode, LimitExceeded};
 
 #[derive(Debug, Error)]
 pub enum SyncError {
@@ -17,9 +18,24 @@ pub enum SyncError {
     #[error("transport error: {0}")]
     TransportError(String),
 
+    #[error("invalid remote config: {0}")]
+    InvalidRemote(String),
+
+    #[error("credential error: {0}")]
+    Credential(String),
+
     #[error("not a fast-forward update for ref {0}")]
     NotFastForward(String),
 
+    #[error("replica behind the required position: {0}")]
+    ReplicaBehind(String),
+
+    #[error("invalid receipt cursor: {0}")]
+    InvalidCursor(String),
+
+    #[error("received data exceeds decoding limits: {0}")]
+    LimitExceeded(#[from] LimitExceeded),
+
     #[error("pack error: {0}")]
     Pack(#[from] wll_pack::PackError),
 
@@ -30,4 +46,24 @@ pub enum SyncError {
     Io(#[from] std::io::Error),
 }
 
+impl Coded for SyncError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::RemoteError(_) => ErrorCode::RemoteError,
+            Self::RefRejected { .. } => ErrorCode::RefRejected,
+            Self::VerificationFailed(_) => ErrorCode::SyncVerification,
+            Self::NegotiationFailed(_) | Self::TransportError(_) => ErrorCode::Transport,
+            Self::InvalidRemote(_) => ErrorCode::InvalidRemote,
+            Self::Credential(_) => ErrorCode::Credential,
+            Self::NotFastForward(_) => ErrorCode::NotFastForward,
+            Self::ReplicaBehind(_) => ErrorCode::ReplicaBehind,
+            Self::InvalidCursor(_) => ErrorCode::InvalidCursor,
+            Self::LimitExceeded(e) => e.code(),
+            Self::Pack(e) => e.code(),
+            Self::Ledger(e) => e.code(),
+            Self::Io(_) => ErrorCode::Io,
+        }
+    }
+}
+
 pub type SyncResult<T> = Result<T, SyncError>;
@@ -3,17 +3,27 @@
 //! Provides push, pull, and fetch operations between WLL repositories.
 //! Unlike git, WLL sync also verifies receipt chain integrity on receive.
 
+pub mod credential;
 pub mod error;
 pub mod negotiation;
+pub mod paging;
+pub mod replica;
 pub mod transport;
 pub mod types;
 pub mod verifier;
 
+pub use credential::{
+    Credential, CredentialManager, CredentialRequest, CredentialSource, CredentialStore,
+    EnvCredentials, HelperCommand, Keychain,
+};
 pub use error::{SyncError, SyncResult};
-pub use negotiation::NegotiationEngine;
+pub use negotiation::{HaveNegotiator, NegotiationEngine, DEFAULT_MAX_ROUNDS};
+pub use paging::{ReceiptPage, ReceiptPager, ReceiptPages};
+pub use replica::ReplicaTransport;
 pub use transport::RemoteTransport;
 pub use types::{
     CloneOptions, FetchResult, MergeStatus, Negotiation, PullResult, PushResult,
-    RefRejection, RefSpec, RefUpdate, VerificationReport,
+    RefRejection, RefSpec, RefUpdate, RemoteConfig, TrustAnchor, UnresolvedReference,
+    VerificationReport,
 };
-pub use verifier::SyncVerifier;
+pub use verifier::{SyncVerifier, TrustRoots};
@@ -1,7 +1,25 @@
-use std::collections::HashSet;
+//! Deciding what a transfer must carry.
+//!
+//! The fetching side advertises history it has ("haves") and the remote
+//! acknowledges the ones it shares. Histories are receipt streams, so a
+//! remote holding an entry holds everything before it: the newest shared
+//! entry of each tip is found by search rather than by advertising the whole
+//! history. [`HaveNegotiator`] runs that search over a capped number of
+//! rounds, and [`NegotiationEngine::objects_to_send`] is the remote's half,
+//! leaving out objects the shared history already holds.
+
+use std::collections::{HashMap, HashSet};
+
 use wll_types::ObjectId;
+
 use crate::types::Negotiation;
 
+/// Rounds [`HaveNegotiator`] runs unless told otherwise.
+pub const DEFAULT_MAX_ROUNDS: usize = 6;
+
+/// Haves offered per history in each narrowing round.
+const PROBES_PER_ROUND: usize = 3;
+
 /// Object negotiation engine: computes wants/haves to minimize transfer.
 pub struct NegotiationEngine;
 
@@ -29,6 +47,12 @@ impl NegotiationEngine {
         local_refs: &[(String, [u8; 32])],
         remote_refs: &[(String, [u8; 32])],
     ) -> Negotiation {
+        let _span = tracing::info_span!(
+            "sync.negotiate",
+            local_refs = local_refs.len(),
+            remote_refs = remote_refs.len(),
+        )
+        .entered();
         let wants = Self::compute_wants(local_refs, remote_refs);
         let haves = Self::compute_haves(local_refs);
         let local: HashSet<[u8; 32]> = local_refs.iter().map(|(_, h)| *h).collect();
@@ -37,7 +61,178 @@ impl NegotiationEngine {
             .filter(|(_, h)| local.contains(h))
             .map(|(_, h)| ObjectId::from_hash(*h))
             .collect();
-        Negotiation { wants, haves, common }
+        Negotiation {
+            history_len: haves.len(),
+            wants,
+            haves,
+            common,
+            ..Negotiation::default()
+        }
+    }
+
+    /// Objects reachable from `wants` but not from `common`, which the
+    /// receiving side already holds in full.
+    ///
+    /// `children` lists the objects an object refers to (a tree's entries);
+    /// nothing below an object in `common`'s closure is visited.
+    pub fn objects_to_send(
+        wants: &[ObjectId],
+        common: &[ObjectId],
+        children: impl Fn(&ObjectId) -> Vec<ObjectId>,
+    ) -> Vec<ObjectId> {
+        let closure = |roots: &[ObjectId], stop: &HashSet<ObjectId>| {
+            let mut seen = HashSet::new();
+            let mut order = Vec::new();
+            let mut pending = roots.to_vec();
+            while let Some(id) = pending.pop() {
+                if stop.contains(&id) || !seen.insert(id) {
+                    continue;
+                }
+                pending.extend(children(&id));
+                order.push(id);
+            }
+            (seen, order)
+        };
+        let (held, _) = closure(common, &HashSet::new());
+        closure(wants, &held).1
+    }
+}
+
+/// The fetching side of a multi-round have/want exchange.
+///
+/// Each history is a tip followed by its ancestors, newest first. The first
+/// round offers every tip and ancestors 1, 3, 7, 15, … back; later rounds
+/// offer a few evenly spaced entries between the newest acknowledged and the
+/// oldest unacknowledged have, until every history's newest shared entry is
+/// pinned down or the round cap is hit. Entries shared between histories are
+/// offered once.
+pub struct HaveNegotiator {
+    histories: Vec<History>,
+    known: HashMap<ObjectId, bool>,
+    haves: Vec<ObjectId>,
+    rounds: usize,
+    max_rounds: usize,
+}
+
+/// One history and the span of it still unresolved: entries before
+/// `missing_below` are not on the remote, entries from `common_from` on are.
+struct History {
+    ids: Vec<ObjectId>,
+    missing_below: usize,
+    common_from: usize,
+}
+
+impl History {
+    fn unresolved(&self) -> std::ops::Range<usize> {
+        self.missing_below..self.common_from
+    }
+
+    /// Entries to offer next.
+    fn probes(&self) -> Vec<usize> {
+        let span = self.unresolved();
+        if span.is_empty() {
+            return Vec::new();
+        }
+        if span.end == self.ids.len() && span.start == 0 {
+            return (0..usize::BITS)
+                .map(|k| (1usize << k) - 1)
+                .take_while(|&i| i < self.ids.len())
+                .collect();
+        }
+        let step = span.len().div_ceil(PROBES_PER_ROUND + 1).max(1);
+        span.clone().skip(step - 1).step_by(step).take(PROBES_PER_ROUND).collect()
+    }
+}
+
+impl HaveNegotiator {
+    /// Negotiate over `histories`, each a tip then its ancestors, newest
+    /// first.
+    pub fn new(histories: Vec<Vec<ObjectId>>) -> Self {
+        Self {
+            histories: histories
+                .into_iter()
+                .map(|ids| History {
+                    missing_below: 0,
+                    common_from: ids.len(),
+                    ids,
+                })
+                .collect(),
+            known: HashMap::new(),
+            haves: Vec::new(),
+            rounds: 0,
+            max_rounds: DEFAULT_MAX_ROUNDS,
+        }
+    }
+
+    /// Stop after `rounds` rounds; at least one is always run.
+    pub fn with_max_rounds(mut self, rounds: usize) -> Self {
+        self.max_rounds = rounds.max(1);
+        self
+    }
+
+    /// Haves to offer in the next round, or `None` when negotiation is over.
+    pub fn next_round(&self) -> Option<Vec<ObjectId>> {
+        if self.rounds >= self.max_rounds {
+            return None;
+        }
+        let mut offered = HashSet::new();
+        let round: Vec<ObjectId> = self
+            .histories
+            .iter()
+            .flat_map(|h| h.probes().into_iter().map(|i| h.ids[i]))
+            .filter(|id| !self.known.contains_key(id) && offered.insert(*id))
+            .collect();
+        (!round.is_empty()).then_some(round)
+    }
+
+    /// Record the remote's answer to `offered`: the entries it also has.
+    pub fn acknowledge(&mut self, offered: &[ObjectId], common: &[ObjectId]) {
+        let common: HashSet<&ObjectId> = common.iter().collect();
+        for id in offered {
+            self.known.insert(*id, common.contains(id));
+        }
+        self.haves.extend_from_slice(offered);
+        self.rounds += 1;
+        for history in &mut self.histories {
+            for i in history.unresolved() {
+                match self.known.get(&history.ids[i]) {
+                    Some(true) => {
+                        history.common_from = i;
+                        break;
+                    }
+                    Some(false) => history.missing_below = i + 1,
+                    None => {}
+                }
+            }
+            history.missing_below = history.missing_below.min(history.common_from);
+        }
+    }
+
+    /// The outcome: the newest shared entry of each history, what was
+    /// offered, and the cost of getting there.
+    pub fn finish(self, wants: Vec<ObjectId>) -> Negotiation {
+        let mut common = Vec::new();
+        for history in &self.histories {
+            if let Some(id) = history.ids.get(history.common_from) {
+                if !common.contains(id) {
+                    common.push(*id);
+                }
+            }
+        }
+        let history_len = self
+            .histories
+            .iter()
+            .flat_map(|h| &h.ids)
+            .collect::<HashSet<_>>()
+            .len();
+        Negotiation {
+            wants,
+            haves: self.haves,
+            common,
+            rounds: self.rounds,
+            history_len,
+            objects_skipped: 0,
+        }
     }
 }
 
@@ -78,6 +273,49 @@ mod tests {
         assert_eq!(neg.haves.len(), 2);
     }
 
+    fn chain(len: u8) -> Vec<ObjectId> {
+        (0..len).rev().map(|i| ObjectId::from_hash([i; 32])).collect()
+    }
+
+    #[test]
+    fn negotiator_narrows_to_the_newest_shared_entry() {
+        // 100 local entries, newest first; the remote has the oldest 38.
+        let local = chain(100);
+        let remote: HashSet<ObjectId> = local[62..].iter().copied().collect();
+        let mut negotiator = HaveNegotiator::new(vec![local.clone()]).with_max_rounds(20);
+        while let Some(haves) = negotiator.next_round() {
+            let acked: Vec<ObjectId> =
+                haves.iter().filter(|id| remote.contains(id)).copied().collect();
+            negotiator.acknowledge(&haves, &acked);
+        }
+        let negotiation = negotiator.finish(Vec::new());
+        assert_eq!(negotiation.common, vec![local[62]]);
+        assert!(negotiation.haves.len() < 25, "offered {}", negotiation.haves.len());
+        assert_eq!(negotiation.history_len, 100);
+
+        let mut capped = HaveNegotiator::new(vec![local]).with_max_rounds(1);
+        let haves = capped.next_round().unwrap();
+        capped.acknowledge(&haves, &[]);
+        assert!(capped.next_round().is_none());
+        assert_eq!(capped.finish(Vec::new()).rounds, 1);
+    }
+
+    #[test]
+    fn objects_to_send_skips_what_the_common_history_holds() {
+        let id = |n: u8| ObjectId::from_hash([n; 32]);
+        // Trees 1 (old) and 2 (new) share subtree 3 and its blob 4.
+        let children = |o: &ObjectId| match o.as_bytes()[0] {
+            1 => vec![id(3), id(5)],
+            2 => vec![id(3), id(6)],
+            3 => vec![id(4)],
+            _ => vec![],
+        };
+        let mut sent = NegotiationEngine::objects_to_send(&[id(2)], &[id(1)], children);
+        sent.sort_by_key(|o| o.as_bytes()[0]);
+        assert_eq!(sent, vec![id(2), id(6)]);
+        assert_eq!(NegotiationEngine::objects_to_send(&[id(2)], &[], children).len(), 4);
+    }
+
     #[test]
     fn negotiate_empty_local() {
         let local: Vec<(String, [u8; 32])> = vec![];
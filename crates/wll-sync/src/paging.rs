@@ -0,0 +1,419 @@
+//! Browsing a remote receipt stream a page at a time.
+//!
+//! Thin clients list history with `ReceiptPageRequest`s instead of cloning
+//! it. [`ReceiptPager`] is the serving side's half, answering requests
+//! from a [`LedgerReader`]. [`ReceiptPages`] is the client's: it walks the
+//! pages of a [`RemoteTransport`], following each response's cursor and
+//! checking that every page continues the hash chain of the one before.
+
+use wll_ledger::{LedgerReader, Receipt};
+use wll_protocol::{PageDirection, ReceiptCursor, WllMessage, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+use wll_types::{DecodeLimits, WorldlineId};
+
+use crate::error::{SyncError, SyncResult};
+use crate::transport::RemoteTransport;
+
+/// One page of a worldline's receipts, in the order it was asked for.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReceiptPage {
+    pub receipts: Vec<Receipt>,
+    /// Where the following page starts; `None` on the last page.
+    pub next: Option<ReceiptCursor>,
+}
+
+impl ReceiptPage {
+    /// The `ReceiptPageResponse` carrying this page of `worldline`.
+    pub fn to_message(&self, worldline: &WorldlineId) -> SyncResult<WllMessage> {
+        let receipts_data = serde_json::to_vec(&self.receipts)
+            .map_err(|e| SyncError::TransportError(format!("encoding receipts: {e}")))?;
+        Ok(WllMessage::ReceiptPageResponse {
+            worldline: worldline.clone(),
+            receipts_data,
+            count: self.receipts.len() as u32,
+            next: self.next,
+        })
+    }
+
+    /// Decode the answer to a page request for `worldline`, under the
+    /// default [`DecodeLimits`].
+    pub fn from_message(message: WllMessage, worldline: &WorldlineId) -> SyncResult<Self> {
+        Self::from_message_with_limits(message, worldline, &DecodeLimits::DEFAULT)
+    }
+
+    /// Decode the answer to a page request for `worldline`, refusing
+    /// receipts that exceed `limits` before they are decoded.
+    pub fn from_message_with_limits(
+        message: WllMessage,
+        worldline: &WorldlineId,
+        limits: &DecodeLimits,
+    ) -> SyncResult<Self> {
+        match message {
+            WllMessage::ReceiptPageResponse { worldline: answered, receipts_data, count, next } => {
+                if &answered != worldline {
+                    return Err(SyncError::VerificationFailed(format!(
+                        "asked for receipts of {worldline}, got {answered}"
+                    )));
+                }
+                limits.check_json(&receipts_data)?;
+                let receipts: Vec<Receipt> = serde_json::from_slice(&receipts_data)
+                    .map_err(|e| SyncError::TransportError(format!("decoding receipts: {e}")))?;
+                if receipts.len() != count as usize {
+                    return Err(SyncError::TransportError(format!(
+                        "page announced {count} receipts but carried {}",
+                        receipts.len()
+                    )));
+                }
+                Ok(Self { receipts, next })
+            }
+            WllMessage::Error { message, .. } => Err(SyncError::RemoteError(message)),
+            other => Err(SyncError::TransportError(format!(
+                "expected ReceiptPageResponse, got {}",
+                other.type_name()
+            ))),
+        }
+    }
+}
+
+/// Answers receipt page requests from a ledger.
+pub struct ReceiptPager;
+
+impl ReceiptPager {
+    /// Up to `limit` live receipts of `worldline` after (or, backward,
+    /// before) `cursor`. A `limit` of 0 asks for [`DEFAULT_PAGE_SIZE`];
+    /// none larger than [`MAX_PAGE_SIZE`] is honoured.
+    ///
+    /// Fails with [`SyncError::InvalidCursor`] when the stream does not hold
+    /// the cursor's receipt at its seq.
+    pub fn page<R: LedgerReader + ?Sized>(
+        reader: &R,
+        worldline: &WorldlineId,
+        cursor: Option<&ReceiptCursor>,
+        limit: u32,
+        direction: PageDirection,
+    ) -> SyncResult<ReceiptPage> {
+        let limit = if limit == 0 { DEFAULT_PAGE_SIZE } else { limit.min(MAX_PAGE_SIZE) };
+        let limit = u64::from(limit);
+        let base = reader.archived_through(worldline)?;
+        let first_live = base.as_ref().map_or(1, |b| b.seq + 1);
+        let head = reader.head(worldline)?.map_or(first_live - 1, |h| h.seq);
+        if let Some(cursor) = cursor {
+            let held = match &base {
+                Some(base) if base.seq == cursor.seq => Some(base.receipt_hash),
+                _ if cursor.seq >= first_live && cursor.seq <= head => reader
+                    .read_range(worldline, cursor.seq, cursor.seq)?
+                    .first()
+                    .map(Receipt::receipt_hash),
+                _ => None,
+            };
+            if held != Some(cursor.receipt_hash) {
+                return Err(SyncError::InvalidCursor(format!(
+                    "{worldline} holds no receipt {} at seq {}",
+                    hex::encode(&cursor.receipt_hash[..8]),
+                    cursor.seq
+                )));
+            }
+        }
+
+        let (from, to) = match direction {
+            PageDirection::Forward => {
+                let from = cursor.map_or(first_live, |c| c.seq + 1);
+                (from, head.min(from.saturating_add(limit - 1)))
+            }
+            PageDirection::Backward => {
+                let to = cursor.map_or(head, |c| c.seq.saturating_sub(1));
+                (first_live.max(to.saturating_sub(limit - 1)), to)
+            }
+        };
+        if from > to {
+            return Ok(ReceiptPage::default());
+        }
+        let mut receipts = reader.read_range(worldline, from, to)?;
+        let more = match direction {
+            PageDirection::Forward => to < head,
+            PageDirection::Backward => {
+                receipts.reverse();
+                from > first_live
+            }
+        };
+        let next = receipts.last().filter(|_| more).map(|last| ReceiptCursor {
+            seq: last.seq(),
+            receipt_hash: last.receipt_hash(),
+        });
+        Ok(ReceiptPage { receipts, next })
+    }
+
+    /// Answer `request`, a `ReceiptPageRequest`, with a
+    /// `ReceiptPageResponse`, or with an `Error` message if it fails.
+    pub fn handle<R: LedgerReader + ?Sized>(reader: &R, request: &WllMessage) -> WllMessage {
+        let WllMessage::ReceiptPageRequest { worldline, cursor, limit, direction } = request else {
+            return WllMessage::error(&SyncError::TransportError(format!(
+                "expected ReceiptPageRequest, got {}",
+                request.type_name()
+            )));
+        };
+        Self::page(reader, worldline, cursor.as_ref(), *limit, *direction)
+            .and_then(|page| page.to_message(worldline))
+            .unwrap_or_else(|e| WllMessage::error(&e))
+    }
+}
+
+/// Walks a remote worldline's receipts page by page.
+pub struct ReceiptPages<'a> {
+    transport: &'a dyn RemoteTransport,
+    worldline: WorldlineId,
+    cursor: Option<ReceiptCursor>,
+    limit: u32,
+    direction: PageDirection,
+    /// Hash the next page must link to, once a page has been read.
+    expected: Option<[u8; 32]>,
+    done: bool,
+}
+
+impl<'a> ReceiptPages<'a> {
+    /// Pages of `worldline` from its oldest live receipt on, of
+    /// [`DEFAULT_PAGE_SIZE`] receipts.
+    pub fn new(transport: &'a dyn RemoteTransport, worldline: WorldlineId) -> Self {
+        Self {
+            transport,
+            worldline,
+            cursor: None,
+            limit: DEFAULT_PAGE_SIZE,
+            direction: PageDirection::Forward,
+            expected: None,
+            done: false,
+        }
+    }
+
+    pub fn with_limit(mut self, limit: u32) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Walk in `direction`; [`PageDirection::Backward`] starts at the head.
+    pub fn with_direction(mut self, direction: PageDirection) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// Continue from `cursor`, as returned by [`cursor`](Self::cursor) on
+    /// an earlier walk.
+    pub fn starting_at(mut self, cursor: ReceiptCursor) -> Self {
+        self.cursor = Some(cursor);
+        self
+    }
+
+    /// Where the next page starts.
+    pub fn cursor(&self) -> Option<&ReceiptCursor> {
+        self.cursor.as_ref()
+    }
+
+    /// The next page, or `None` once the end of the stream was reached.
+    ///
+    /// Fails with [`SyncError::VerificationFailed`] if the page does not
+    /// continue the hash chain from the cursor.
+    pub async fn next_page(&mut self) -> SyncResult<Option<Vec<Receipt>>> {
+        if self.done {
+            return Ok(None);
+        }
+        let page = self
+            .transport
+            .fetch_receipt_page(&self.worldline, self.cursor.as_ref(), self.limit, self.direction)
+            .await?;
+        self.check_links(&page.receipts)?;
+        self.done = page.next.is_none() || page.receipts.is_empty();
+        if let Some(last) = page.receipts.last() {
+            self.cursor =
+                Some(ReceiptCursor { seq: last.seq(), receipt_hash: last.receipt_hash() });
+        }
+        Ok((!page.receipts.is_empty()).then_some(page.receipts))
+    }
+
+    /// Check that `receipts` are of this worldline and chain on from the
+    /// cursor, updating the hash the next page must link to.
+    fn check_links(&mut self, receipts: &[Receipt]) -> SyncResult<()> {
+        let mut expected = match self.direction {
+            PageDirection::Forward => self.cursor.map(|c| c.receipt_hash),
+            PageDirection::Backward => self.expected,
+        };
+        let mut seq = self.cursor.map(|c| c.seq);
+        for receipt in receipts {
+            let (linked, next_seq) = match self.direction {
+                PageDirection::Forward => (
+                    expected.map_or(true, |hash| receipt.prev_hash() == Some(hash)),
+                    seq.map(|s| s + 1),
+                ),
+                PageDirection::Backward => (
+                    expected.map_or(true, |hash| receipt.receipt_hash() == hash),
+                    seq.map(|s| s.saturating_sub(1)),
+                ),
+            };
+            if receipt.worldline() != &self.worldline
+                || !linked
+                || next_seq.is_some_and(|s| s != receipt.seq())
+            {
+                return Err(SyncError::VerificationFailed(format!(
+                    "receipt {} of the page does not continue {} from the cursor",
+                    receipt.seq(),
+                    self.worldline
+                )));
+            }
+            expected = match self.direction {
+                PageDirection::Forward => Some(receipt.receipt_hash()),
+                PageDirection::Backward => receipt.prev_hash(),
+            };
+            seq = Some(receipt.seq());
+        }
+        self.expected = expected;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use wll_ledger::{
+        CommitmentProposal, Decision, EvidenceBundle, InMemoryLedger, LedgerWriter,
+    };
+    use wll_protocol::WllCodec;
+    use wll_types::{CommitmentClass, CommitmentId, IdentityMaterial, ObjectId};
+
+    use super::*;
+    use crate::types::{RefRejection, RefUpdate};
+
+    fn worldline() -> WorldlineId {
+        WorldlineId::derive(&IdentityMaterial::GenesisHash([3; 32]))
+    }
+
+    fn ledger(receipts: u64) -> InMemoryLedger {
+        let ledger = InMemoryLedger::default();
+        for nonce in 0..receipts {
+            let proposal = CommitmentProposal {
+                worldline: worldline(),
+                commitment_id: CommitmentId::new(),
+                class: CommitmentClass::ReadOnly,
+                intent: format!("read {nonce}"),
+                requested_caps: vec![],
+                targets: vec![worldline()],
+                evidence: EvidenceBundle::empty(),
+                nonce,
+            };
+            ledger.append_commitment(&proposal, &Decision::Accepted, [0; 32]).unwrap();
+        }
+        ledger
+    }
+
+    fn seqs(receipts: &[Receipt]) -> Vec<u64> {
+        receipts.iter().map(Receipt::seq).collect()
+    }
+
+    /// Serves pages by sending each request through the codec to a
+    /// [`ReceiptPager`], as a server would.
+    struct Served(InMemoryLedger);
+
+    fn unserved<T>() -> SyncResult<T> {
+        Err(SyncError::TransportError("only receipt pages are served".into()))
+    }
+
+    #[async_trait]
+    impl RemoteTransport for Served {
+        async fn list_refs(&self) -> SyncResult<Vec<(String, [u8; 32])>> {
+            unserved()
+        }
+        async fn fetch_objects(&self, _: &[ObjectId], _: &[ObjectId]) -> SyncResult<Vec<u8>> {
+            unserved()
+        }
+        async fn fetch_receipts(
+            &self,
+            _: &[WorldlineId],
+            _: Option<u64>,
+        ) -> SyncResult<Vec<Receipt>> {
+            unserved()
+        }
+        async fn push_pack(&self, _: &[u8]) -> SyncResult<()> {
+            unserved()
+        }
+        async fn push_receipts(&self, _: &[Receipt]) -> SyncResult<()> {
+            unserved()
+        }
+        async fn update_refs(&self, _: &[RefUpdate]) -> SyncResult<Vec<RefRejection>> {
+            unserved()
+        }
+
+        async fn fetch_receipt_page(
+            &self,
+            worldline: &WorldlineId,
+            cursor: Option<&ReceiptCursor>,
+            limit: u32,
+            direction: PageDirection,
+        ) -> SyncResult<ReceiptPage> {
+            let wire = |message: &WllMessage| {
+                let bytes = WllCodec::encode(message).unwrap();
+                WllCodec::decode(&bytes).unwrap().0
+            };
+            let request = wire(&WllMessage::ReceiptPageRequest {
+                worldline: worldline.clone(),
+                cursor: cursor.copied(),
+                limit,
+                direction,
+            });
+            let response = wire(&ReceiptPager::handle(&self.0, &request));
+            ReceiptPage::from_message(response, worldline)
+        }
+    }
+
+    #[test]
+    fn pages_run_either_way_from_a_cursor_pinned_to_its_receipt() {
+        let ledger = ledger(5);
+        let forward = PageDirection::Forward;
+        let first = ReceiptPager::page(&ledger, &worldline(), None, 2, forward).unwrap();
+        assert_eq!(seqs(&first.receipts), [1, 2]);
+        let cursor = first.next.unwrap();
+        assert_eq!(cursor.receipt_hash, first.receipts[1].receipt_hash());
+
+        let back = ReceiptPager::page(&ledger, &worldline(), None, 3, PageDirection::Backward);
+        let back = back.unwrap();
+        assert_eq!(seqs(&back.receipts), [5, 4, 3]);
+        let rest = ReceiptPager::page(
+            &ledger,
+            &worldline(),
+            back.next.as_ref(),
+            3,
+            PageDirection::Backward,
+        )
+        .unwrap();
+        assert_eq!(seqs(&rest.receipts), [2, 1]);
+        assert_eq!(rest.next, None);
+
+        let moved = ReceiptCursor { seq: 3, ..cursor };
+        let err = ReceiptPager::page(&ledger, &worldline(), Some(&moved), 2, forward).unwrap_err();
+        assert!(matches!(err, SyncError::InvalidCursor(_)));
+    }
+
+    #[test]
+    fn pages_beyond_the_receipt_limits_are_refused() {
+        let page = ReceiptPager::page(&ledger(3), &worldline(), None, 3, PageDirection::Forward);
+        let message = page.unwrap().to_message(&worldline()).unwrap();
+        let limits = DecodeLimits::new().with_max_array_len(2);
+        let err = ReceiptPage::from_message_with_limits(message.clone(), &worldline(), &limits);
+        assert!(matches!(err, Err(SyncError::LimitExceeded(_))));
+        assert_eq!(ReceiptPage::from_message(message, &worldline()).unwrap().receipts.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn client_walks_every_page_over_the_wire() {
+        let served = Served(ledger(7));
+        let mut pages = ReceiptPages::new(&served, worldline()).with_limit(3);
+        let mut walked = Vec::new();
+        while let Some(page) = pages.next_page().await.unwrap() {
+            walked.push(seqs(&page));
+        }
+        assert_eq!(walked, [vec![1, 2, 3], vec![4, 5, 6], vec![7]]);
+
+        let mut pages = ReceiptPages::new(&served, worldline())
+            .with_limit(4)
+            .with_direction(PageDirection::Backward);
+        assert_eq!(seqs(&pages.next_page().await.unwrap().unwrap()), [7, 6, 5, 4]);
+        assert_eq!(seqs(&pages.next_page().await.unwrap().unwrap()), [3, 2, 1]);
+        assert_eq!(pages.next_page().await.unwrap(), None);
+    }
+}
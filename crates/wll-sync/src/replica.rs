@@ -0,0 +1,199 @@
+//! Reads from a replica that may lag its primary.
+//!
+//! A [`ReplicaTransport`] sends reads to a replica only once the replica's
+//! [`ConsistencyToken`] covers the position the client requires, and every
+//! write to the primary. The primary's position after each write is added
+//! to the requirement, so a client reads its own writes. While the replica
+//! lags, reads are retried after a delay and then go to the primary, or
+//! fail with [`SyncError::ReplicaBehind`] when there is none.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use wll_ledger::Receipt;
+use wll_protocol::{AuthMethod, ConsistencyToken, PageDirection, ReceiptCursor};
+use wll_types::{ObjectId, WorldlineId};
+
+use crate::error::{SyncError, SyncResult};
+use crate::paging::ReceiptPage;
+use crate::transport::RemoteTransport;
+use crate::types::{RefRejection, RefUpdate};
+
+/// Times a lagging replica is asked again before reads give up on it.
+pub const DEFAULT_REPLICA_RETRIES: u32 = 3;
+/// Wait between those attempts.
+pub const DEFAULT_REPLICA_RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// Transport reading from a replica at or past a required position.
+pub struct ReplicaTransport {
+    replica: Box<dyn RemoteTransport>,
+    primary: Option<Box<dyn RemoteTransport>>,
+    required: RwLock<ConsistencyToken>,
+    retries: u32,
+    retry_delay: Duration,
+    /// Set once a read went to the primary; later reads stay there so one
+    /// fetch never mixes the two.
+    on_primary: AtomicBool,
+}
+
+impl ReplicaTransport {
+    pub fn new(replica: Box<dyn RemoteTransport>) -> Self {
+        Self {
+            replica,
+            primary: None,
+            required: RwLock::new(ConsistencyToken::new()),
+            retries: DEFAULT_REPLICA_RETRIES,
+            retry_delay: DEFAULT_REPLICA_RETRY_DELAY,
+            on_primary: AtomicBool::new(false),
+        }
+    }
+
+    /// Send writes, and reads the replica cannot serve, to `primary`.
+    pub fn with_primary(mut self, primary: Box<dyn RemoteTransport>) -> Self {
+        self.primary = Some(primary);
+        self
+    }
+
+    /// Only read from the replica once it has reached `position`.
+    pub fn require(self, position: &ConsistencyToken) -> Self {
+        self.required.write().expect("replica lock poisoned").merge(position);
+        self
+    }
+
+    /// Ask a lagging replica `retries` more times, `delay` apart.
+    pub fn with_retries(mut self, retries: u32, delay: Duration) -> Self {
+        self.retries = retries;
+        self.retry_delay = delay;
+        self
+    }
+
+    /// The position reads must reach.
+    pub fn required(&self) -> ConsistencyToken {
+        self.required.read().expect("replica lock poisoned").clone()
+    }
+
+    fn writer(&self) -> &dyn RemoteTransport {
+        self.primary.as_deref().unwrap_or(self.replica.as_ref())
+    }
+
+    /// The transport to read from.
+    async fn reader(&self) -> SyncResult<&dyn RemoteTransport> {
+        if let Some(primary) = &self.primary {
+            if self.on_primary.load(Ordering::Acquire) {
+                return Ok(primary.as_ref());
+            }
+        }
+        let required = self.required();
+        if required.is_empty() {
+            return Ok(self.replica.as_ref());
+        }
+        let mut lagging = Vec::new();
+        for attempt in 0..=self.retries {
+            if attempt > 0 {
+                tokio::time::sleep(self.retry_delay).await;
+            }
+            let position = self.replica.consistency().await?.unwrap_or_default();
+            lagging = position.lagging(&required).into_iter().cloned().collect();
+            if lagging.is_empty() {
+                return Ok(self.replica.as_ref());
+            }
+        }
+        let lagging: Vec<String> = lagging.iter().map(ToString::to_string).collect();
+        match &self.primary {
+            Some(primary) => {
+                let lagging = lagging.join(", ");
+                tracing::debug!("replica behind on {lagging}; reading from the primary");
+                self.on_primary.store(true, Ordering::Release);
+                Ok(primary.as_ref())
+            }
+            None => Err(SyncError::ReplicaBehind(format!(
+                "{} after {} attempt(s)",
+                lagging.join(", "),
+                self.retries + 1
+            ))),
+        }
+    }
+
+    /// Require later reads to see what the primary now holds.
+    async fn observe_write(&self) -> SyncResult<()> {
+        if let Some(position) = self.writer().consistency().await? {
+            self.required.write().expect("replica lock poisoned").merge(&position);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RemoteTransport for ReplicaTransport {
+    async fn list_refs(&self) -> SyncResult<Vec<(String, [u8; 32])>> {
+        self.reader().await?.list_refs().await
+    }
+
+    async fn list_refs_matching(&self, prefix: &str) -> SyncResult<Vec<(String, [u8; 32])>> {
+        self.reader().await?.list_refs_matching(prefix).await
+    }
+
+    async fn fetch_objects(&self, wants: &[ObjectId], haves: &[ObjectId]) -> SyncResult<Vec<u8>> {
+        self.reader().await?.fetch_objects(wants, haves).await
+    }
+
+    async fn fetch_receipts(
+        &self,
+        worldlines: &[WorldlineId],
+        since: Option<u64>,
+    ) -> SyncResult<Vec<Receipt>> {
+        self.reader().await?.fetch_receipts(worldlines, since).await
+    }
+
+    async fn fetch_receipt_page(
+        &self,
+        worldline: &WorldlineId,
+        cursor: Option<&ReceiptCursor>,
+        limit: u32,
+        direction: PageDirection,
+    ) -> SyncResult<ReceiptPage> {
+        self.reader().await?.fetch_receipt_page(worldline, cursor, limit, direction).await
+    }
+
+    async fn push_pack(&self, pack_bytes: &[u8]) -> SyncResult<()> {
+        self.writer().push_pack(pack_bytes).await
+    }
+
+    async fn push_receipts(&self, receipts: &[Receipt]) -> SyncResult<()> {
+        self.writer().push_receipts(receipts).await
+    }
+
+    async fn update_refs(&self, updates: &[RefUpdate]) -> SyncResult<Vec<RefRejection>> {
+        let rejected = self.writer().update_refs(updates).await?;
+        self.observe_write().await?;
+        Ok(rejected)
+    }
+
+    async fn push_atomic(
+        &self,
+        pack_bytes: &[u8],
+        receipts: &[Receipt],
+        updates: &[RefUpdate],
+    ) -> SyncResult<Vec<RefRejection>> {
+        let rejected = self.writer().push_atomic(pack_bytes, receipts, updates).await?;
+        self.observe_write().await?;
+        Ok(rejected)
+    }
+
+    async fn ack_haves(&self, haves: &[ObjectId]) -> SyncResult<Vec<ObjectId>> {
+        self.reader().await?.ack_haves(haves).await
+    }
+
+    async fn consistency(&self) -> SyncResult<Option<ConsistencyToken>> {
+        self.writer().consistency().await
+    }
+
+    fn set_auth(&mut self, auth: AuthMethod) {
+        if let Some(primary) = &mut self.primary {
+            primary.set_auth(auth.clone());
+        }
+        self.replica.set_auth(auth);
+    }
+}
@@ -1,8 +1,10 @@
 use async_trait::async_trait;
 use wll_ledger::Receipt;
+use wll_protocol::{ref_in_prefix, AuthMethod, ConsistencyToken, PageDirection, ReceiptCursor};
 use wll_types::{ObjectId, WorldlineId};
 
-use crate::error::SyncResult;
+use crate::error::{SyncError, SyncResult};
+use crate::paging::ReceiptPage;
 use crate::types::{RefRejection, RefUpdate};
 
 /// Transport interface for remote WLL repositories.
@@ -14,4 +16,62 @@ pub trait RemoteTransport: Send + Sync {
     async fn push_pack(&self, pack_bytes: &[u8]) -> SyncResult<()>;
     async fn push_receipts(&self, receipts: &[Receipt]) -> SyncResult<()>;
     async fn update_refs(&self, updates: &[RefUpdate]) -> SyncResult<Vec<RefRejection>>;
+
+    /// The remote refs named by `prefix` (see [`ref_in_prefix`]), as a
+    /// single-branch fetch asks for them. Transports that cannot narrow the
+    /// listing on the remote filter the full one.
+    async fn list_refs_matching(&self, prefix: &str) -> SyncResult<Vec<(String, [u8; 32])>> {
+        let mut refs = self.list_refs().await?;
+        refs.retain(|(name, _)| ref_in_prefix(name, prefix));
+        Ok(refs)
+    }
+
+    /// Apply a whole push as one transaction: the remote stages the pack and
+    /// receipts, checks every ref update, and applies all of them or none.
+    /// Returns the rejected updates; if there are any, nothing was applied.
+    ///
+    /// Transports whose remote lacks the `atomic-push` capability fail.
+    async fn push_atomic(
+        &self,
+        _pack_bytes: &[u8],
+        _receipts: &[Receipt],
+        _updates: &[RefUpdate],
+    ) -> SyncResult<Vec<RefRejection>> {
+        Err(SyncError::TransportError("remote does not support atomic push".into()))
+    }
+
+    /// The entries of `haves` the remote also has, answering one round of a
+    /// [`HaveNegotiator`](crate::HaveNegotiator) exchange (`WantRequest` /
+    /// `AckResponse` on the wire). Transports that cannot negotiate
+    /// acknowledge nothing, and fetches then carry everything reachable
+    /// from the wants.
+    async fn ack_haves(&self, _haves: &[ObjectId]) -> SyncResult<Vec<ObjectId>> {
+        Ok(Vec::new())
+    }
+
+    /// One page of `worldline`'s receipts from `cursor` on
+    /// (`ReceiptPageRequest` / `ReceiptPageResponse` on the wire), as
+    /// [`ReceiptPager::page`](crate::ReceiptPager::page) answers it.
+    ///
+    /// Transports whose remote lacks the `receipt-pages` capability fail.
+    async fn fetch_receipt_page(
+        &self,
+        _worldline: &WorldlineId,
+        _cursor: Option<&ReceiptCursor>,
+        _limit: u32,
+        _direction: PageDirection,
+    ) -> SyncResult<ReceiptPage> {
+        Err(SyncError::TransportError("remote does not support receipt pages".into()))
+    }
+
+    /// The remote's current ledger position (`consistency` on the wire).
+    /// Transports whose remote does not report one return `None`.
+    async fn consistency(&self) -> SyncResult<Option<ConsistencyToken>> {
+        Ok(None)
+    }
+
+    /// Present `auth` on subsequent requests, typically from
+    /// [`CredentialManager::auth_for`](crate::CredentialManager::auth_for).
+    /// Transports that need no authentication ignore it.
+    fn set_auth(&mut self, _auth: AuthMethod) {}
 }
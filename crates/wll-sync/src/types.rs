@@ -1,6 +1,12 @@
+use std::fmt;
+
 use serde::{Deserialize, Serialize};
+use wll_config::RemoteSettings;
+use wll_ledger::CrossWorldlineRef;
 use wll_types::{ObjectId, WorldlineId};
 
+use crate::error::{SyncError, SyncResult};
+
 /// A refspec mapping local to remote refs.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct RefSpec {
@@ -32,6 +38,96 @@ impl RefSpec {
             Some(Self { src: rest.into(), dst: rest.into(), force })
         }
     }
+
+    /// Concrete refspec for `name` if it matches `src`.
+    ///
+    /// A trailing `*` on both sides maps the matched suffix, so
+    /// `refs/heads/*:refs/remotes/origin/*` expands `refs/heads/main` to
+    /// `refs/heads/main:refs/remotes/origin/main`.
+    pub fn expand(&self, name: &str) -> Option<Self> {
+        let dst = match (self.src.strip_suffix('*'), self.dst.strip_suffix('*')) {
+            (Some(src_prefix), Some(dst_prefix)) => {
+                let suffix = name.strip_prefix(src_prefix)?;
+                format!("{dst_prefix}{suffix}")
+            }
+            (None, None) if name == self.src => self.dst.clone(),
+            _ => return None,
+        };
+        Some(Self { src: name.into(), dst, force: self.force })
+    }
+}
+
+impl fmt::Display for RefSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.force {
+            f.write_str("+")?;
+        }
+        write!(f, "{}:{}", self.src, self.dst)
+    }
+}
+
+/// A named remote repository and the refs fetched from it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteConfig {
+    pub name: String,
+    pub url: String,
+    pub fetch: Vec<RefSpec>,
+}
+
+impl RemoteConfig {
+    /// Remote fetching every branch into `refs/remotes/<name>/`.
+    pub fn new(name: impl Into<String>, url: impl Into<String>) -> Self {
+        let name = name.into();
+        let fetch = vec![RefSpec::forced("refs/heads/*", format!("refs/remotes/{name}/*"))];
+        Self { name, url: url.into(), fetch }
+    }
+
+    /// Remote from a `[remote.<name>]` config section. An empty fetch list
+    /// keeps the default refspec.
+    pub fn from_settings(name: &str, settings: &RemoteSettings) -> SyncResult<Self> {
+        wll_refs::validate_remote_name(name)
+            .map_err(|e| SyncError::InvalidRemote(format!("remote.{name}: {e}")))?;
+        let remote = Self::new(name, settings.url.clone());
+        if settings.fetch.is_empty() {
+            return Ok(remote);
+        }
+        let fetch = settings
+            .fetch
+            .iter()
+            .map(|text| {
+                RefSpec::parse(text)
+                    .filter(|spec| !spec.src.is_empty() && !spec.dst.is_empty())
+                    .ok_or_else(|| {
+                        SyncError::InvalidRemote(format!("remote.{name}.fetch: invalid refspec {text:?}"))
+                    })
+            })
+            .collect::<SyncResult<_>>()?;
+        Ok(remote.with_fetch(fetch))
+    }
+
+    /// The `[remote.<name>]` config section for this remote.
+    pub fn to_settings(&self) -> RemoteSettings {
+        RemoteSettings {
+            url: self.url.clone(),
+            fetch: self.fetch.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    pub fn with_fetch(mut self, fetch: Vec<RefSpec>) -> Self {
+        self.fetch = fetch;
+        self
+    }
+
+    /// Refspec for fetching `remote_ref`, from the first fetch refspec that
+    /// matches it. Short names are taken to be branches.
+    pub fn fetch_refspec(&self, remote_ref: &str) -> Option<RefSpec> {
+        let name = if remote_ref.starts_with("refs/") {
+            remote_ref.to_string()
+        } else {
+            format!("refs/heads/{remote_ref}")
+        };
+        self.fetch.iter().find_map(|spec| spec.expand(&name))
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -53,6 +149,16 @@ pub struct FetchResult {
     pub receipts_received: usize,
     pub refs_updated: Vec<RefUpdate>,
     pub bytes_transferred: u64,
+    /// Receipts of other worldlines fetched because received receipts
+    /// cite them.
+    pub cross_worldline_receipts: usize,
+    /// Cross-worldline references imported unresolved under
+    /// `sync.cross_worldline = "warn"`.
+    pub unresolved_references: Vec<UnresolvedReference>,
+    /// How the transfer was narrowed down.
+    pub negotiation: Negotiation,
+    /// Chain verification of the fetched receipts, with any warnings.
+    pub verification: Option<VerificationReport>,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -62,6 +168,9 @@ pub struct PushResult {
     pub refs_updated: Vec<RefUpdate>,
     pub rejected: Vec<RefRejection>,
     pub bytes_transferred: u64,
+    /// Whether the push was applied as one transaction. If so, a non-empty
+    /// `rejected` means the remote applied nothing.
+    pub atomic: bool,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -84,27 +193,71 @@ pub struct Negotiation {
     pub wants: Vec<ObjectId>,
     pub haves: Vec<ObjectId>,
     pub common: Vec<ObjectId>,
+    /// Have/want rounds exchanged.
+    pub rounds: usize,
+    /// Distinct local history entries that could have been offered as
+    /// haves; `haves` holds the ones sampling actually offered.
+    pub history_len: usize,
+    /// Objects left out of the transfer because the common history already
+    /// holds them.
+    pub objects_skipped: usize,
 }
 
-#[derive(Clone, Debug)]
+impl Negotiation {
+    /// History entries not advertised thanks to sampling.
+    pub fn haves_saved(&self) -> usize {
+        self.history_len.saturating_sub(self.haves.len())
+    }
+}
+
+#[derive(Clone, Debug, Default)]
 pub struct CloneOptions {
     pub bare: bool,
     pub branch: Option<String>,
     pub depth: Option<u32>,
 }
 
-impl Default for CloneOptions {
-    fn default() -> Self {
-        Self { bare: false, branch: None, depth: None }
-    }
-}
-
 #[derive(Clone, Debug)]
 pub struct VerificationReport {
     pub worldline: WorldlineId,
     pub receipts_verified: u64,
+    /// Whether there are no `violations`.
     pub chain_valid: bool,
+    /// Hard failures: the receipts must not be imported.
     pub violations: Vec<String>,
+    /// Findings that do not block the import, such as a stream checked
+    /// without trust roots.
+    pub warnings: Vec<String>,
+    /// What the receipts were found to chain back to, if anything trusted.
+    pub anchor: Option<TrustAnchor>,
+}
+
+/// A trusted point a verified stream chains back to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TrustAnchor {
+    /// A trusted genesis receipt, at seq 1.
+    Genesis { receipt_hash: [u8; 32] },
+    /// A trusted snapshot receipt.
+    Snapshot { seq: u64, receipt_hash: [u8; 32] },
+    /// The local head the receipts extend.
+    Local { seq: u64, receipt_hash: [u8; 32] },
+}
+
+/// A received receipt citing a receipt of another worldline that is
+/// neither known locally nor part of the transfer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnresolvedReference {
+    /// Worldline of the citing receipt.
+    pub worldline: WorldlineId,
+    /// Sequence number of the citing receipt.
+    pub seq: u64,
+    pub reference: CrossWorldlineRef,
+}
+
+impl fmt::Display for UnresolvedReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "seq {} of {} cites {}", self.seq, self.worldline, self.reference)
+    }
 }
 
 #[cfg(test)]
@@ -141,6 +294,50 @@ mod tests {
         assert!(rs.force);
     }
 
+    #[test]
+    fn refspec_display_roundtrips() {
+        let text = "+refs/heads/*:refs/remotes/origin/*";
+        assert_eq!(RefSpec::parse(text).unwrap().to_string(), text);
+        assert_eq!(RefSpec::new("a", "b").to_string(), "a:b");
+    }
+
+    #[test]
+    fn refspec_expand_maps_wildcard_suffix() {
+        let rs = RefSpec::parse("+refs/heads/*:refs/remotes/origin/*").unwrap();
+        let expanded = rs.expand("refs/heads/feature/x").unwrap();
+        assert_eq!(expanded, RefSpec::forced("refs/heads/feature/x", "refs/remotes/origin/feature/x"));
+        assert!(rs.expand("refs/tags/v1").is_none());
+
+        let exact = RefSpec::new("refs/heads/main", "refs/heads/upstream");
+        assert_eq!(exact.expand("refs/heads/main").unwrap().dst, "refs/heads/upstream");
+        assert!(exact.expand("refs/heads/dev").is_none());
+    }
+
+    #[test]
+    fn remote_config_default_fetch() {
+        let remote = RemoteConfig::new("origin", "wll://example.com/repo");
+        assert_eq!(remote.fetch[0].to_string(), "+refs/heads/*:refs/remotes/origin/*");
+        let spec = remote.fetch_refspec("main").unwrap();
+        assert_eq!(spec.src, "refs/heads/main");
+        assert_eq!(spec.dst, "refs/remotes/origin/main");
+        assert!(remote.fetch_refspec("refs/tags/v1").is_none());
+    }
+
+    #[test]
+    fn remote_config_from_settings() {
+        let settings = RemoteSettings {
+            url: "wll://u".into(),
+            fetch: vec!["refs/heads/main:refs/remotes/upstream/main".into()],
+        };
+        let remote = RemoteConfig::from_settings("upstream", &settings).unwrap();
+        assert_eq!(remote.fetch, vec![RefSpec::new("refs/heads/main", "refs/remotes/upstream/main")]);
+        assert_eq!(remote.to_settings(), settings);
+
+        let bad = RemoteSettings { fetch: vec![":x".into()], ..settings.clone() };
+        assert!(matches!(RemoteConfig::from_settings("upstream", &bad), Err(SyncError::InvalidRemote(_))));
+        assert!(RemoteConfig::from_settings("a/b", &settings).is_err());
+    }
+
     #[test]
     fn fetch_result_defaults() {
         let f = FetchResult::default();
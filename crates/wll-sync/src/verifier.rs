@@ -1,8 +1,69 @@
-use wll_ledger::Receipt;
+use wll_config::CrossWorldlinePolicy;
+use wll_crypto::VerifyingKey;
+use wll_ledger::{CrossWorldlineRef, Receipt};
 use wll_types::WorldlineId;
 
-use crate::error::SyncResult;
-use crate::types::VerificationReport;
+use crate::error::{SyncError, SyncResult};
+use crate::types::{TrustAnchor, UnresolvedReference, VerificationReport};
+
+/// Receipts and keys a fetched stream must be anchored to, from
+/// `sync.trusted_genesis`, `sync.trusted_snapshots` and `sync.trusted_keys`.
+#[derive(Clone, Debug, Default)]
+pub struct TrustRoots {
+    /// Hashes of genesis receipts a stream may start from.
+    pub genesis: Vec<[u8; 32]>,
+    /// Hashes of snapshot receipts a stream may be anchored at, for
+    /// remotes that archived their early history.
+    pub snapshots: Vec<[u8; 32]>,
+    /// Keys receipt signatures must verify against.
+    pub keys: Vec<VerifyingKey>,
+}
+
+impl TrustRoots {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_genesis(mut self, receipt_hash: [u8; 32]) -> Self {
+        self.genesis.push(receipt_hash);
+        self
+    }
+
+    pub fn with_snapshot(mut self, receipt_hash: [u8; 32]) -> Self {
+        self.snapshots.push(receipt_hash);
+        self
+    }
+
+    pub fn with_key(mut self, key: VerifyingKey) -> Self {
+        self.keys.push(key);
+        self
+    }
+
+    /// Whether no receipt is trusted, so streams cannot be anchored.
+    pub fn is_empty(&self) -> bool {
+        self.genesis.is_empty() && self.snapshots.is_empty()
+    }
+
+    /// The anchor `receipt` provides, if it is trusted.
+    fn anchor(&self, receipt: &Receipt) -> Option<TrustAnchor> {
+        let receipt_hash = receipt.receipt_hash();
+        match receipt {
+            Receipt::Snapshot(_) if self.snapshots.contains(&receipt_hash) => {
+                Some(TrustAnchor::Snapshot {
+                    seq: receipt.seq(),
+                    receipt_hash,
+                })
+            }
+            _ if receipt.seq() == 1
+                && receipt.prev_hash().is_none()
+                && self.genesis.contains(&receipt_hash) =>
+            {
+                Some(TrustAnchor::Genesis { receipt_hash })
+            }
+            _ => None,
+        }
+    }
+}
 
 /// Verifies receipt chain integrity after receiving from a remote.
 pub struct SyncVerifier;
@@ -13,10 +74,83 @@ impl SyncVerifier {
         receipts: &[Receipt],
         worldline: &WorldlineId,
     ) -> SyncResult<VerificationReport> {
-        let mut violations = Vec::new();
-        let mut prev_hash: Option<[u8; 32]> = None;
-        let mut prev_seq: Option<u64> = None;
+        let violations = Self::chain_violations(receipts, worldline, None, None);
+        Ok(VerificationReport {
+            worldline: worldline.clone(),
+            receipts_verified: receipts.len() as u64,
+            chain_valid: violations.is_empty(),
+            violations,
+            warnings: Vec::new(),
+            anchor: None,
+        })
+    }
+
+    /// Verify fetched receipts and that they chain back to something
+    /// trusted.
+    ///
+    /// `base` is the local head `(seq, receipt hash)` the receipts extend;
+    /// local history is trusted, so they anchor there. A stream fetched
+    /// from scratch must start at a genesis in `roots` or contain one of
+    /// its snapshots, a snapshot vouching for the receipts it is linked
+    /// from. Without roots that is only a warning. Receipt hashes are taken
+    /// as given; the ledger checks them against content on import.
+    pub fn verify_with_roots(
+        receipts: &[Receipt],
+        worldline: &WorldlineId,
+        base: Option<(u64, [u8; 32])>,
+        roots: &TrustRoots,
+    ) -> VerificationReport {
+        // A stream that does not start at genesis links to history the
+        // transfer left out.
+        let start = base.or_else(|| {
+            let first = receipts.first()?;
+            Some((first.seq().saturating_sub(1), first.prev_hash()?))
+        });
+        let mut violations = Self::chain_violations(
+            receipts,
+            worldline,
+            start.map(|(_, hash)| hash),
+            start.map(|(seq, _)| seq),
+        );
+        let mut warnings = Vec::new();
+
+        let anchor = match base {
+            Some((seq, receipt_hash)) => Some(TrustAnchor::Local { seq, receipt_hash }),
+            None => receipts.iter().find_map(|r| roots.anchor(r)),
+        };
+        if anchor.is_none() && !receipts.is_empty() {
+            if roots.is_empty() {
+                warnings.push("no trust roots configured; the stream is not anchored".into());
+            } else {
+                violations
+                    .push("the stream does not chain back to a trusted genesis or snapshot".into());
+            }
+        }
+        if !roots.keys.is_empty() && !receipts.is_empty() {
+            warnings.push(format!(
+                "receipts carry no signatures; {} trusted key(s) not checked",
+                roots.keys.len()
+            ));
+        }
 
+        VerificationReport {
+            worldline: worldline.clone(),
+            receipts_verified: receipts.len() as u64,
+            chain_valid: violations.is_empty(),
+            violations,
+            warnings,
+            anchor,
+        }
+    }
+
+    /// Chain breaks in `receipts`, which follow `prev_hash` at `prev_seq`.
+    fn chain_violations(
+        receipts: &[Receipt],
+        worldline: &WorldlineId,
+        mut prev_hash: Option<[u8; 32]>,
+        mut prev_seq: Option<u64>,
+    ) -> Vec<String> {
+        let mut violations = Vec::new();
         for receipt in receipts {
             if receipt.worldline() != worldline {
                 violations.push(format!("seq {}: wrong worldline", receipt.seq()));
@@ -35,13 +169,67 @@ impl SyncVerifier {
             prev_hash = Some(receipt.receipt_hash());
             prev_seq = Some(receipt.seq());
         }
+        violations
+    }
 
-        Ok(VerificationReport {
-            worldline: worldline.clone(),
-            receipts_verified: receipts.len() as u64,
-            chain_valid: violations.is_empty(),
-            violations,
-        })
+    /// Cross-worldline references made by `received` and `dependencies`
+    /// that resolve to neither of them nor to a receipt `is_local` knows.
+    ///
+    /// `dependencies` are receipts of other worldlines fetched alongside
+    /// `received` to satisfy its references.
+    pub fn unresolved_references(
+        received: &[Receipt],
+        dependencies: &[Receipt],
+        is_local: impl Fn(&CrossWorldlineRef) -> bool,
+    ) -> Vec<UnresolvedReference> {
+        let transferred = |reference: &CrossWorldlineRef| {
+            received.iter().chain(dependencies).any(|r| {
+                r.receipt_hash() == reference.receipt_hash && *r.worldline() == reference.worldline
+            })
+        };
+        received
+            .iter()
+            .chain(dependencies)
+            .flat_map(|receipt| {
+                receipt.cross_worldline_refs().into_iter().map(|reference| {
+                    UnresolvedReference {
+                        worldline: receipt.worldline().clone(),
+                        seq: receipt.seq(),
+                        reference,
+                    }
+                })
+            })
+            .filter(|u| !transferred(&u.reference) && !is_local(&u.reference))
+            .collect()
+    }
+
+    /// Apply `policy` to the references still unresolved.
+    ///
+    /// Under [`CrossWorldlinePolicy::Fetch`] the caller has already
+    /// fetched what the remote could supply, so anything left fails as
+    /// under `Reject`; `Warn` logs each reference and lets the import go
+    /// ahead.
+    pub fn enforce_reference_policy(
+        unresolved: &[UnresolvedReference],
+        policy: CrossWorldlinePolicy,
+    ) -> SyncResult<()> {
+        let Some(first) = unresolved.first() else {
+            return Ok(());
+        };
+        match policy {
+            CrossWorldlinePolicy::Warn => {
+                for u in unresolved {
+                    tracing::warn!("unresolved cross-worldline reference: {u}");
+                }
+                Ok(())
+            }
+            CrossWorldlinePolicy::Fetch | CrossWorldlinePolicy::Reject => {
+                Err(SyncError::VerificationFailed(format!(
+                    "{} unresolved cross-worldline reference(s); {first}",
+                    unresolved.len()
+                )))
+            }
+        }
     }
 }
 
@@ -49,7 +237,10 @@ impl SyncVerifier {
 mod tests {
     use super::*;
     
-    use wll_ledger::CommitmentReceipt;
+    use std::collections::BTreeMap;
+
+    use wll_crypto::SigningKey;
+    use wll_ledger::{CommitmentReceipt, SnapshotReceipt};
     use wll_types::{CommitmentClass, CommitmentId, TemporalAnchor};
     use wll_types::commitment::Decision;
     use wll_types::evidence::EvidenceBundle;
@@ -77,6 +268,14 @@ mod tests {
         })
     }
 
+    fn citing(receipt: Receipt, cited: &CrossWorldlineRef) -> Receipt {
+        let Receipt::Commitment(mut commitment) = receipt else {
+            unreachable!()
+        };
+        commitment.evidence = EvidenceBundle::from_references(vec![cited.to_uri()]);
+        Receipt::Commitment(commitment)
+    }
+
     #[test]
     fn valid_chain_passes() {
         let w = wl(1);
@@ -115,6 +314,76 @@ mod tests {
         assert_eq!(report.receipts_verified, 0);
     }
 
+    #[test]
+    fn cross_worldline_references_resolve_locally_or_in_the_transfer() {
+        let (w, other) = (wl(7), wl(8));
+        let cited = CrossWorldlineRef::new(other.clone(), [40; 32]);
+        let received = [citing(make_commitment(&w, 1, None, [1; 32]), &cited)];
+
+        let unresolved = SyncVerifier::unresolved_references(&received, &[], |_| false);
+        assert_eq!(unresolved.len(), 1);
+        assert_eq!((unresolved[0].seq, &unresolved[0].reference), (1, &cited));
+        assert!(SyncVerifier::unresolved_references(&received, &[], |r| *r == cited).is_empty());
+        let dependency = make_commitment(&other, 1, None, [40; 32]);
+        assert!(SyncVerifier::unresolved_references(&received, &[dependency], |_| false)
+            .is_empty());
+
+        assert!(SyncVerifier::enforce_reference_policy(&unresolved, CrossWorldlinePolicy::Warn)
+            .is_ok());
+        for policy in [CrossWorldlinePolicy::Fetch, CrossWorldlinePolicy::Reject] {
+            let err = SyncVerifier::enforce_reference_policy(&unresolved, policy).unwrap_err();
+            assert!(matches!(err, SyncError::VerificationFailed(_)));
+        }
+        assert!(SyncVerifier::enforce_reference_policy(&[], CrossWorldlinePolicy::Reject).is_ok());
+    }
+
+    #[test]
+    fn fetched_streams_must_chain_back_to_a_trust_root() {
+        let w = wl(9);
+        let genesis = make_commitment(&w, 1, None, [1; 32]);
+        let second = make_commitment(&w, 2, Some([1; 32]), [2; 32]);
+        let stream = [genesis, second.clone()];
+
+        let unrooted = SyncVerifier::verify_with_roots(&stream, &w, None, &TrustRoots::new());
+        assert!(unrooted.chain_valid && unrooted.anchor.is_none());
+        assert_eq!(unrooted.warnings.len(), 1);
+
+        let roots = TrustRoots::new().with_genesis([1; 32]);
+        let rooted = SyncVerifier::verify_with_roots(&stream, &w, None, &roots);
+        assert_eq!(rooted.anchor, Some(TrustAnchor::Genesis { receipt_hash: [1; 32] }));
+        assert!(rooted.chain_valid && rooted.warnings.is_empty());
+
+        let elsewhere = TrustRoots::new().with_genesis([7; 32]);
+        let other = SyncVerifier::verify_with_roots(&stream, &w, None, &elsewhere);
+        assert!(!other.chain_valid);
+        assert!(other.violations[0].contains("trusted genesis"));
+
+        // A remote that archived its early history is anchored by a
+        // trusted snapshot; extending local history needs no root.
+        let snapshot = Receipt::Snapshot(SnapshotReceipt {
+            worldline: w.clone(),
+            seq: 3,
+            receipt_hash: [3; 32],
+            prev_hash: Some([2; 32]),
+            timestamp: TemporalAnchor::new(3000, 0, 0),
+            anchored_receipt_hash: [2; 32],
+            state_hash: [0; 32],
+            state: BTreeMap::new(),
+            namespace: None,
+            namespaces: BTreeMap::new(),
+        });
+        let archived = [second, snapshot];
+        let roots = roots.with_snapshot([3; 32]).with_key(SigningKey::generate().verifying_key());
+        let report = SyncVerifier::verify_with_roots(&archived, &w, None, &roots);
+        assert_eq!(report.anchor, Some(TrustAnchor::Snapshot { seq: 3, receipt_hash: [3; 32] }));
+        assert!(report.chain_valid);
+        assert!(report.warnings[0].contains("no signatures"));
+        let local = SyncVerifier::verify_with_roots(&archived[1..], &w, Some((2, [2; 32])), &roots);
+        assert!(local.chain_valid && matches!(local.anchor, Some(TrustAnchor::Local { seq: 2, .. })));
+        let diverged = SyncVerifier::verify_with_roots(&archived[1..], &w, Some((2, [8; 32])), &roots);
+        assert!(diverged.violations.iter().any(|v| v.contains("prev_hash")));
+    }
+
     #[test]
     fn wrong_worldline_detected() {
         let w1 = wl(5);
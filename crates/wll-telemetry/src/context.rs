@@ -0,0 +1,247 @@
+//! W3C trace context: the ids that tie one distributed operation together.
+//!
+//! A [`TraceContext`] travels between client and server in the
+//! `traceparent` header. Inside a process it is carried in a task-local, so
+//! a transport can read the context of the operation it is serving with
+//! [`current`] instead of having it threaded through every call.
+
+use std::fmt;
+use std::future::Future;
+use std::str::FromStr;
+
+use rand::RngCore;
+use tracing::Span;
+
+/// Header carrying the caller's trace context on requests.
+pub const TRACEPARENT: &str = "traceparent";
+/// Header carrying the server's trace context on responses.
+pub const TRACERESPONSE: &str = "traceresponse";
+
+/// A W3C `traceparent`: which trace an operation belongs to and which span
+/// is its parent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TraceContext {
+    pub trace_id: [u8; 16],
+    pub parent_id: [u8; 8],
+    pub sampled: bool,
+}
+
+/// A `traceparent` value that does not parse.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("invalid traceparent: {0:?}")]
+pub struct InvalidTraceparent(pub String);
+
+impl TraceContext {
+    /// The first span of a new, sampled trace.
+    pub fn new_root() -> Self {
+        let mut rng = rand::thread_rng();
+        let mut trace_id = [0; 16];
+        let mut parent_id = [0; 8];
+        while trace_id == [0; 16] {
+            rng.fill_bytes(&mut trace_id);
+        }
+        while parent_id == [0; 8] {
+            rng.fill_bytes(&mut parent_id);
+        }
+        Self {
+            trace_id,
+            parent_id,
+            sampled: true,
+        }
+    }
+
+    /// A new span in the same trace.
+    pub fn child(&self) -> Self {
+        Self {
+            parent_id: Self::new_root().parent_id,
+            ..*self
+        }
+    }
+
+    /// The trace id as 32 lowercase hex digits.
+    pub fn trace_id_hex(&self) -> String {
+        hex::encode(self.trace_id)
+    }
+}
+
+impl fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "00-{}-{}-{:02x}",
+            hex::encode(self.trace_id),
+            hex::encode(self.parent_id),
+            u8::from(self.sampled)
+        )
+    }
+}
+
+impl FromStr for TraceContext {
+    type Err = InvalidTraceparent;
+
+    /// Parses version `00` and, per the spec, the leading fields of any
+    /// later version. All-zero ids are rejected.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidTraceparent(s.to_string());
+        let mut fields = s.trim().split('-');
+        let (Some(version), Some(trace_id), Some(parent_id), Some(flags)) =
+            (fields.next(), fields.next(), fields.next(), fields.next())
+        else {
+            return Err(invalid());
+        };
+        let version = u8::from_str_radix(version, 16).map_err(|_| invalid())?;
+        if version == 0xff || (version == 0 && fields.next().is_some()) {
+            return Err(invalid());
+        }
+        let mut context = Self {
+            trace_id: [0; 16],
+            parent_id: [0; 8],
+            sampled: false,
+        };
+        hex::decode_to_slice(trace_id, &mut context.trace_id).map_err(|_| invalid())?;
+        hex::decode_to_slice(parent_id, &mut context.parent_id).map_err(|_| invalid())?;
+        if flags.len() != 2 || context.trace_id == [0; 16] || context.parent_id == [0; 8] {
+            return Err(invalid());
+        }
+        context.sampled = u8::from_str_radix(flags, 16).map_err(|_| invalid())? & 1 == 1;
+        Ok(context)
+    }
+}
+
+tokio::task_local! {
+    static CURRENT: TraceContext;
+}
+
+/// The trace context of the operation the current task is running, if any.
+pub fn current() -> Option<TraceContext> {
+    CURRENT.try_with(|context| *context).ok()
+}
+
+/// Run `future` with `context` as the [`current`] trace context.
+pub async fn scope<F: Future>(context: TraceContext, future: F) -> F::Output {
+    CURRENT.scope(context, future).await
+}
+
+/// Tie `span` to a trace and return the context to propagate from it.
+///
+/// The span continues `parent` (an incoming `traceparent`, or [`current`])
+/// or starts a new trace. When OTLP export is active the span is parented
+/// in OpenTelemetry too, so exported spans carry the returned ids; otherwise
+/// the ids are generated here. The trace id is recorded in the span's
+/// `trace_id` field when it declares one.
+pub fn attach(span: &Span, parent: Option<TraceContext>) -> TraceContext {
+    let context = otel_context(span, parent)
+        .unwrap_or_else(|| parent.map_or_else(TraceContext::new_root, |p| p.child()));
+    span.record("trace_id", context.trace_id_hex());
+    context
+}
+
+#[cfg(feature = "otlp")]
+fn otel_context(span: &Span, parent: Option<TraceContext>) -> Option<TraceContext> {
+    use opentelemetry::trace::{
+        SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState,
+    };
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+    if let Some(parent) = parent {
+        let flags = if parent.sampled {
+            TraceFlags::SAMPLED
+        } else {
+            TraceFlags::default()
+        };
+        let remote = SpanContext::new(
+            TraceId::from_bytes(parent.trace_id),
+            SpanId::from_bytes(parent.parent_id),
+            flags,
+            true,
+            TraceState::default(),
+        );
+        // Fails only when no OpenTelemetry layer is installed.
+        span.set_parent(opentelemetry::Context::new().with_remote_span_context(remote))
+            .ok()?;
+    }
+    let context = span.context();
+    let span_context = context.span().span_context().clone();
+    span_context.is_valid().then(|| TraceContext {
+        trace_id: span_context.trace_id().to_bytes(),
+        parent_id: span_context.span_id().to_bytes(),
+        sampled: span_context.is_sampled(),
+    })
+}
+
+#[cfg(not(feature = "otlp"))]
+fn otel_context(_span: &Span, _parent: Option<TraceContext>) -> Option<TraceContext> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn traceparent_roundtrips() {
+        let text = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let context: TraceContext = text.parse().unwrap();
+        assert!(context.sampled);
+        assert_eq!(context.trace_id_hex(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(context.to_string(), text);
+
+        let child = context.child();
+        assert_eq!(child.trace_id, context.trace_id);
+        assert_ne!(child.parent_id, context.parent_id);
+    }
+
+    #[test]
+    fn malformed_traceparents_are_rejected() {
+        for text in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e473-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+        ] {
+            assert!(text.parse::<TraceContext>().is_err(), "{text}");
+        }
+        // Later versions may append fields.
+        assert!("01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00-x"
+            .parse::<TraceContext>()
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn scope_sets_current_and_attach_continues_it() {
+        assert_eq!(current(), None);
+        let root = TraceContext::new_root();
+        let seen = scope(root, async {
+            let span = tracing::info_span!("op", trace_id = tracing::field::Empty);
+            attach(&span, current())
+        })
+        .await;
+        assert_eq!(seen.trace_id, root.trace_id);
+        assert_eq!(current(), None);
+    }
+
+    #[cfg(feature = "otlp")]
+    #[test]
+    fn attach_uses_opentelemetry_ids_when_exporting() {
+        use opentelemetry::trace::{TraceContextExt, TracerProvider};
+        use tracing_opentelemetry::OpenTelemetrySpanExt;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let provider = opentelemetry_sdk::trace::SdkTracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        tracing::subscriber::with_default(subscriber, || {
+            let parent: TraceContext = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+                .parse()
+                .unwrap();
+            let span = tracing::info_span!("op", trace_id = tracing::field::Empty);
+            let context = attach(&span, Some(parent));
+            assert_eq!(context.trace_id, parent.trace_id);
+            let otel = span.context().span().span_context().clone();
+            assert_eq!(context.parent_id, otel.span_id().to_bytes());
+        });
+    }
+}
@@ -0,0 +1,159 @@
+//! Installing the global tracing subscriber.
+
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+/// Errors from [`init`].
+#[derive(Debug, thiserror::Error)]
+pub enum TelemetryError {
+    /// A global subscriber was already installed.
+    #[error("tracing is already initialized: {0}")]
+    AlreadyInitialized(#[from] tracing_subscriber::util::TryInitError),
+
+    /// OTLP export was requested from a build without the `otlp` feature.
+    #[error("OTLP export requested but wll-telemetry was built without the `otlp` feature")]
+    OtlpUnavailable,
+
+    /// The OTLP exporter could not be built.
+    #[cfg(feature = "otlp")]
+    #[error("OTLP exporter: {0}")]
+    Exporter(#[from] opentelemetry_otlp::ExporterBuildError),
+}
+
+/// Where to send spans over OTLP/HTTP.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OtlpExport {
+    /// Full traces URL, e.g. `http://collector:4318/v1/traces`. `None`
+    /// uses the standard `OTEL_EXPORTER_OTLP_*` environment variables.
+    pub endpoint: Option<String>,
+}
+
+/// What [`init`] installs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TelemetryConfig {
+    /// `service.name` on exported spans.
+    pub service_name: String,
+    /// Most verbose level logged to stderr and exported.
+    pub level: LevelFilter,
+    /// Export spans over OTLP as well as logging them.
+    pub otlp: Option<OtlpExport>,
+}
+
+impl TelemetryConfig {
+    /// Log at `INFO` to stderr, without export.
+    pub fn new(service_name: impl Into<String>) -> Self {
+        Self {
+            service_name: service_name.into(),
+            level: LevelFilter::INFO,
+            otlp: None,
+        }
+    }
+
+    /// [`new`](Self::new), exporting over OTLP when
+    /// `OTEL_EXPORTER_OTLP_ENDPOINT` or `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`
+    /// is set.
+    pub fn from_env(service_name: impl Into<String>) -> Self {
+        let configured = [
+            "OTEL_EXPORTER_OTLP_ENDPOINT",
+            "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT",
+        ]
+        .iter()
+        .any(|var| std::env::var_os(var).is_some_and(|value| !value.is_empty()));
+        Self {
+            otlp: configured.then(OtlpExport::default),
+            ..Self::new(service_name)
+        }
+    }
+
+    pub fn with_level(mut self, level: LevelFilter) -> Self {
+        self.level = level;
+        self
+    }
+
+    pub fn with_otlp(mut self, otlp: OtlpExport) -> Self {
+        self.otlp = Some(otlp);
+        self
+    }
+}
+
+/// Keeps the exporter alive; dropping it flushes buffered spans.
+#[derive(Debug, Default)]
+pub struct Telemetry {
+    #[cfg(feature = "otlp")]
+    provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+impl Telemetry {
+    /// Whether spans are being exported.
+    pub fn exporting(&self) -> bool {
+        #[cfg(feature = "otlp")]
+        return self.provider.is_some();
+        #[cfg(not(feature = "otlp"))]
+        false
+    }
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        #[cfg(feature = "otlp")]
+        if let Some(provider) = self.provider.take() {
+            let _ = provider.shutdown();
+        }
+    }
+}
+
+/// Install the global subscriber: a stderr log at `config.level`, plus an
+/// OTLP exporter when `config.otlp` is set. Keep the returned guard alive
+/// for as long as spans should be exported.
+pub fn init(config: &TelemetryConfig) -> Result<Telemetry, TelemetryError> {
+    let registry = tracing_subscriber::registry()
+        .with(config.level)
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr));
+
+    #[cfg(feature = "otlp")]
+    {
+        use opentelemetry::trace::TracerProvider;
+
+        let provider = config
+            .otlp
+            .as_ref()
+            .map(|otlp| otlp_provider(&config.service_name, otlp))
+            .transpose()?;
+        let layer = provider
+            .as_ref()
+            .map(|provider| tracing_opentelemetry::layer().with_tracer(provider.tracer("wll")));
+        registry.with(layer).try_init()?;
+        Ok(Telemetry { provider })
+    }
+
+    #[cfg(not(feature = "otlp"))]
+    {
+        if config.otlp.is_some() {
+            return Err(TelemetryError::OtlpUnavailable);
+        }
+        registry.try_init()?;
+        Ok(Telemetry::default())
+    }
+}
+
+#[cfg(feature = "otlp")]
+fn otlp_provider(
+    service_name: &str,
+    otlp: &OtlpExport,
+) -> Result<opentelemetry_sdk::trace::SdkTracerProvider, TelemetryError> {
+    use opentelemetry_otlp::WithExportConfig;
+
+    let mut exporter = opentelemetry_otlp::SpanExporter::builder().with_http();
+    if let Some(endpoint) = &otlp.endpoint {
+        exporter = exporter.with_endpoint(endpoint);
+    }
+    Ok(opentelemetry_sdk::trace::SdkTracerProvider::builder()
+        .with_batch_exporter(exporter.build()?)
+        .with_resource(
+            opentelemetry_sdk::Resource::builder()
+                .with_service_name(service_name.to_string())
+                .build(),
+        )
+        .build())
+}
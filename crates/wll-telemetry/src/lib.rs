@@ -0,0 +1,27 @@
+//! Tracing setup for the WorldLine Ledger.
+//!
+//! The WLL crates emit [`tracing`] spans for the operations worth following
+//! across processes: `gate.evaluate` and `gate.stage`, `sync.push`,
+//! `sync.pull` and `sync.negotiate`, `pack.write`, `wal.append_batch`, and
+//! `http.request` on the server. This crate ties those spans into
+//! distributed traces and installs the subscriber that records them.
+//!
+//! - [`TraceContext`] — W3C `traceparent` ids, sent as the [`TRACEPARENT`]
+//!   header by clients and echoed as [`TRACERESPONSE`] by the server
+//! - [`attach`], [`scope`], [`current`] — start or continue a trace for a
+//!   span and make it visible to transports running inside it
+//! - [`init()`] — stderr logging plus, with the `otlp` feature, export to an
+//!   OpenTelemetry collector
+//!
+//! # Features
+//!
+//! - `otlp` — OTLP/HTTP span export through `opentelemetry`. Without it,
+//!   trace ids are still generated and propagated, just not exported.
+
+pub mod context;
+pub mod init;
+
+pub use context::{
+    attach, current, scope, InvalidTraceparent, TraceContext, TRACEPARENT, TRACERESPONSE,
+};
+pub use init::{init, OtlpExport, Telemetry, TelemetryConfig, TelemetryError};
@@ -0,0 +1,120 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use core::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::identity::WorldlineId;
+
+/// What kind of principal an external identity belongs to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttestationKind {
+    /// A person, e.g. a corporate SSO account.
+    Human,
+    /// A machine identity, e.g. a CI job or service account.
+    Workload,
+}
+
+impl fmt::Display for AttestationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Human => write!(f, "human"),
+            Self::Workload => write!(f, "workload"),
+        }
+    }
+}
+
+/// A verified binding between a worldline and an external identity.
+///
+/// Produced after an identity token (typically an OIDC JWT) has been
+/// verified against its issuer's keys. The token itself is not kept; only
+/// its BLAKE3 digest, so the attestation can be matched to the token it
+/// came from without storing a bearer credential.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdentityAttestation {
+    /// The worldline the identity is bound to.
+    pub worldline: WorldlineId,
+    /// Token issuer (the OIDC `iss` claim).
+    pub issuer: String,
+    /// Subject at the issuer (the OIDC `sub` claim).
+    pub subject: String,
+    /// Kind of principal the issuer vouches for.
+    pub kind: AttestationKind,
+    /// Other string-valued claims from the token (e.g. `email`,
+    /// `repository`, `job_workflow_ref`).
+    #[serde(default)]
+    pub claims: BTreeMap<String, String>,
+    /// When the token was issued, in Unix seconds.
+    pub issued_at: u64,
+    /// When the token expires, in Unix seconds.
+    pub expires_at: Option<u64>,
+    /// BLAKE3 digest of the verified token.
+    pub token_digest: [u8; 32],
+}
+
+impl IdentityAttestation {
+    /// Digest recorded in [`Self::token_digest`] for `token`.
+    pub fn digest_token(token: &str) -> [u8; 32] {
+        *blake3::hash(token.as_bytes()).as_bytes()
+    }
+
+    /// Whether the attestation has not expired at `now` (Unix seconds).
+    pub fn is_valid_at(&self, now: u64) -> bool {
+        self.expires_at.map_or(true, |expires| now < expires)
+    }
+
+    /// Whether this attestation vouches for `worldline` as a principal of
+    /// `kind` (any kind when `None`) at `now`.
+    pub fn satisfies(
+        &self,
+        worldline: &WorldlineId,
+        kind: Option<AttestationKind>,
+        now: u64,
+    ) -> bool {
+        self.worldline == *worldline
+            && kind.map_or(true, |kind| self.kind == kind)
+            && self.is_valid_at(now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::IdentityMaterial;
+
+    fn attestation(kind: AttestationKind, expires_at: Option<u64>) -> IdentityAttestation {
+        IdentityAttestation {
+            worldline: WorldlineId::derive(&IdentityMaterial::GenesisHash([1; 32])),
+            issuer: "https://idp.example".into(),
+            subject: "alice".into(),
+            kind,
+            claims: BTreeMap::new(),
+            issued_at: 100,
+            expires_at,
+            token_digest: IdentityAttestation::digest_token("a.b.c"),
+        }
+    }
+
+    #[test]
+    fn satisfies_checks_worldline_kind_and_expiry() {
+        let a = attestation(AttestationKind::Human, Some(200));
+        let other = WorldlineId::derive(&IdentityMaterial::GenesisHash([2; 32]));
+
+        assert!(a.satisfies(&a.worldline, None, 150));
+        assert!(a.satisfies(&a.worldline, Some(AttestationKind::Human), 150));
+        assert!(!a.satisfies(&a.worldline, Some(AttestationKind::Workload), 150));
+        assert!(!a.satisfies(&a.worldline, None, 200));
+        assert!(!a.satisfies(&other, None, 150));
+        assert!(attestation(AttestationKind::Workload, None).is_valid_at(u64::MAX));
+    }
+
+    #[test]
+    fn serde_roundtrip() {
+        let a = attestation(AttestationKind::Workload, Some(200));
+        let json = serde_json::to_string(&a).unwrap();
+        assert!(json.contains("\"workload\""));
+        let back: IdentityAttestation = serde_json::from_str(&json).unwrap();
+        assert_eq!(a, back);
+    }
+}
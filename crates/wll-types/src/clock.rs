@@ -0,0 +1,123 @@
+//! Injectable wall-clock time.
+//!
+//! Components that stamp receipts, events, or index entries read time
+//! through a [`Clock`] instead of calling the system clock directly, so
+//! tests, replays, and simulations can run against a [`ManualClock`] and get
+//! the same timestamps every run.
+
+use core::fmt;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::TemporalAnchor;
+
+/// Source of wall-clock time.
+pub trait Clock: Send + Sync + fmt::Debug {
+    /// Milliseconds since the UNIX epoch.
+    fn now_ms(&self) -> u64;
+
+    /// Nanoseconds since the UNIX epoch. Defaults to [`now_ms`](Self::now_ms)
+    /// scaled up.
+    fn now_nanos(&self) -> u64 {
+        self.now_ms().saturating_mul(1_000_000)
+    }
+
+    /// Seconds since the UNIX epoch.
+    fn now_secs(&self) -> u64 {
+        self.now_ms() / 1_000
+    }
+
+    /// A [`TemporalAnchor`] for the current time with a zero logical counter.
+    fn anchor(&self, node_id: u16) -> TemporalAnchor {
+        TemporalAnchor::new(self.now_ms(), 0, node_id)
+    }
+
+    /// The current time as a [`SystemTime`](std::time::SystemTime).
+    #[cfg(feature = "std")]
+    fn system_time(&self) -> std::time::SystemTime {
+        std::time::UNIX_EPOCH + std::time::Duration::from_nanos(self.now_nanos())
+    }
+}
+
+/// The operating system's clock.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+    fn now_ms(&self) -> u64 {
+        self.now_nanos() / 1_000_000
+    }
+
+    fn now_nanos(&self) -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as u64
+    }
+
+    fn system_time(&self) -> std::time::SystemTime {
+        std::time::SystemTime::now()
+    }
+}
+
+/// A clock that only moves when told to.
+#[derive(Debug, Default)]
+pub struct ManualClock {
+    now_ms: AtomicU64,
+}
+
+impl ManualClock {
+    /// A clock stopped at `now_ms`.
+    pub fn new(now_ms: u64) -> Self {
+        Self {
+            now_ms: AtomicU64::new(now_ms),
+        }
+    }
+
+    /// Move the clock to `now_ms`, forwards or backwards.
+    pub fn set_ms(&self, now_ms: u64) {
+        self.now_ms.store(now_ms, Ordering::SeqCst);
+    }
+
+    /// Move the clock forwards by `delta_ms`.
+    pub fn advance_ms(&self, delta_ms: u64) {
+        self.now_ms.fetch_add(delta_ms, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now_ms(&self) -> u64 {
+        self.now_ms.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manual_clock_moves_only_when_told() {
+        let clock = ManualClock::new(1_000);
+        assert_eq!(clock.now_ms(), 1_000);
+        assert_eq!(clock.now_ms(), 1_000);
+        clock.advance_ms(250);
+        assert_eq!(clock.anchor(3), TemporalAnchor::new(1_250, 0, 3));
+        assert_eq!(clock.now_nanos(), 1_250_000_000);
+        clock.set_ms(5_000);
+        assert_eq!(clock.now_secs(), 5);
+        #[cfg(feature = "std")]
+        assert_eq!(
+            clock.system_time(),
+            std::time::UNIX_EPOCH + std::time::Duration::from_secs(5)
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn system_clock_agrees_with_temporal_anchor() {
+        let before = TemporalAnchor::now(0).physical_ms;
+        let now = SystemClock.now_ms();
+        assert!(now >= before && now - before < 60_000);
+    }
+}
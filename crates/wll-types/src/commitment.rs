@@ -1,4 +1,5 @@
-use std::fmt;
+use alloc::string::{String, ToString};
+use core::fmt;
 
 use serde::{Deserialize, Serialize};
 
@@ -11,6 +12,10 @@ pub struct CommitmentId(uuid::Uuid);
 
 impl CommitmentId {
     /// Generate a new time-ordered commitment ID (UUID v7).
+    ///
+    /// Requires the `std` feature for the system clock; `no_std` callers
+    /// build ids with [`CommitmentId::from_uuid`].
+    #[cfg(feature = "std")]
     pub fn new() -> Self {
         Self(uuid::Uuid::now_v7())
     }
@@ -31,6 +36,7 @@ impl CommitmentId {
     }
 }
 
+#[cfg(feature = "std")]
 impl Default for CommitmentId {
     fn default() -> Self {
         Self::new()
@@ -81,6 +87,27 @@ impl CommitmentClass {
             Self::Custom(_) => 2, // default to medium
         }
     }
+
+    /// Parse a class from its [`Display`](fmt::Display) name.
+    ///
+    /// Names that are not a built-in class (with or without the
+    /// `Custom(..)` wrapper) become [`CommitmentClass::Custom`].
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "ReadOnly" => Self::ReadOnly,
+            "ContentUpdate" => Self::ContentUpdate,
+            "StructuralChange" => Self::StructuralChange,
+            "PolicyChange" => Self::PolicyChange,
+            "IdentityOperation" => Self::IdentityOperation,
+            other => Self::Custom(
+                other
+                    .strip_prefix("Custom(")
+                    .and_then(|rest| rest.strip_suffix(')'))
+                    .unwrap_or(other)
+                    .to_string(),
+            ),
+        }
+    }
 }
 
 impl fmt::Display for CommitmentClass {
@@ -148,6 +175,45 @@ pub enum Reversibility {
     Irreversible,
 }
 
+impl Reversibility {
+    /// How hard the effects are to undo: 0 for reversible, 1 for partially
+    /// reversible, 2 for irreversible.
+    pub fn severity(&self) -> u8 {
+        match self {
+            Self::Reversible => 0,
+            Self::PartiallyReversible { .. } => 1,
+            Self::Irreversible => 2,
+        }
+    }
+
+    /// Parse a value from its [`Display`](fmt::Display) name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Reversible" => Some(Self::Reversible),
+            "Irreversible" => Some(Self::Irreversible),
+            other => {
+                let constraints = other
+                    .strip_prefix("PartiallyReversible(")?
+                    .strip_suffix(')')?
+                    .to_string();
+                Some(Self::PartiallyReversible { constraints })
+            }
+        }
+    }
+}
+
+impl fmt::Display for Reversibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Reversible => write!(f, "Reversible"),
+            Self::PartiallyReversible { constraints } => {
+                write!(f, "PartiallyReversible({constraints})")
+            }
+            Self::Irreversible => write!(f, "Irreversible"),
+        }
+    }
+}
+
 /// Unique identifier for a capability.
 #[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct CapabilityId(pub String);
@@ -194,6 +260,7 @@ impl Capability {
 mod tests {
     use super::*;
 
+    #[cfg(feature = "std")]
     #[test]
     fn commitment_id_is_unique() {
         let id1 = CommitmentId::new();
@@ -203,9 +270,8 @@ mod tests {
 
     #[test]
     fn commitment_id_short_format() {
-        let id = CommitmentId::new();
-        let short = id.short_id();
-        assert_eq!(short.len(), 8);
+        let id = CommitmentId::from_uuid(uuid::Uuid::from_u128(0x0192_3f4e << 96));
+        assert_eq!(id.short_id(), "01923f4e");
     }
 
     #[test]
@@ -218,6 +284,21 @@ mod tests {
         assert_eq!(CommitmentClass::Custom("x".into()).risk_level(), 2);
     }
 
+    #[test]
+    fn commitment_class_from_display_name() {
+        for class in [
+            CommitmentClass::ReadOnly,
+            CommitmentClass::PolicyChange,
+            CommitmentClass::Custom("deploy".into()),
+        ] {
+            assert_eq!(CommitmentClass::from_name(&class.to_string()), class);
+        }
+        assert_eq!(
+            CommitmentClass::from_name("deploy"),
+            CommitmentClass::Custom("deploy".into())
+        );
+    }
+
     #[test]
     fn decision_helpers() {
         assert!(Decision::Accepted.is_accepted());
@@ -247,6 +328,10 @@ mod tests {
         let _ = serde_json::to_string(&rev).unwrap();
         let _ = serde_json::to_string(&partial).unwrap();
         let _ = serde_json::to_string(&irrev).unwrap();
+        for value in [rev, partial, irrev] {
+            assert_eq!(Reversibility::from_name(&value.to_string()), Some(value));
+        }
+        assert_eq!(Reversibility::from_name("undo"), None);
     }
 
     #[test]
@@ -1,5 +1,9 @@
+use alloc::string::String;
+
 use thiserror::Error;
 
+use crate::error_code::{Coded, ErrorCode};
+
 /// Errors produced by type operations.
 #[derive(Debug, Error, PartialEq, Eq)]
 pub enum TypeError {
@@ -9,6 +13,19 @@ pub enum TypeError {
     #[error("invalid byte length: expected {expected}, got {actual}")]
     InvalidLength { expected: usize, actual: usize },
 
+    #[error("unknown hash algorithm: {0}")]
+    UnknownHashAlgo(String),
+
     #[error("serialization error: {0}")]
     Serialization(String),
 }
+
+impl Coded for TypeError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Self::InvalidHex(_) | Self::InvalidLength { .. } => ErrorCode::InvalidInput,
+            Self::UnknownHashAlgo(_) => ErrorCode::Unsupported,
+            Self::Serialization(_) => ErrorCode::Serialization,
+        }
+    }
+}
@@ -0,0 +1,373 @@
+//! Stable error codes shared by every WLL crate.
+//!
+//! Each crate keeps its own error enum, but every variant maps to an
+//! [`ErrorCode`] through [`Coded`]. A code has a number and a dotted name
+//! that never change once released, and an [`ErrorCategory`] that decides
+//! how the server answers over HTTP and which hint the CLI prints. Wrapper
+//! variants (`SdkError::Ledger(..)` and the like) report the code of the
+//! error they wrap, so the code survives however many layers an error
+//! passes through.
+
+use core::fmt;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Broad class of failure, independent of the crate that raised it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    /// The request or input was malformed.
+    InvalidInput,
+    /// The caller did not prove who they are.
+    Unauthenticated,
+    /// The caller is known but not allowed to do this.
+    PermissionDenied,
+    /// Something the request names does not exist.
+    NotFound,
+    /// The request contradicts the current state (already exists, not a
+    /// fast-forward, wrong lifecycle state).
+    Conflict,
+    /// A policy, gate, or remote refused the request.
+    Rejected,
+    /// Stored or received data failed an integrity check.
+    Integrity,
+    /// A dependency could not be reached; retrying may succeed.
+    Unavailable,
+    /// A bug or an environment failure on this side.
+    Internal,
+}
+
+impl ErrorCategory {
+    /// HTTP status the server answers with.
+    pub const fn http_status(self) -> u16 {
+        match self {
+            Self::InvalidInput => 400,
+            Self::Unauthenticated => 401,
+            Self::PermissionDenied => 403,
+            Self::NotFound => 404,
+            Self::Conflict => 409,
+            Self::Rejected | Self::Integrity => 422,
+            Self::Unavailable => 503,
+            Self::Internal => 500,
+        }
+    }
+
+    /// Short human-readable summary, used as the problem+json `title`.
+    pub const fn title(self) -> &'static str {
+        match self {
+            Self::InvalidInput => "Invalid input",
+            Self::Unauthenticated => "Unauthenticated",
+            Self::PermissionDenied => "Permission denied",
+            Self::NotFound => "Not found",
+            Self::Conflict => "Conflict",
+            Self::Rejected => "Rejected",
+            Self::Integrity => "Integrity check failed",
+            Self::Unavailable => "Unavailable",
+            Self::Internal => "Internal error",
+        }
+    }
+}
+
+macro_rules! error_codes {
+    ($($(#[$doc:meta])* $variant:ident = $number:literal, $name:literal, $category:ident;)*) => {
+        /// Stable identifier for a kind of failure.
+        ///
+        /// Numbers are grouped by the crate that raises them (1000 general,
+        /// 1100 config, 1200 store, 1300 ledger and proofs, 1400 gate, 1500
+        /// refs, 1600 index, 1700 DAG, 1800 pack, 1900 fabric, 2000 sync,
+        /// 2100 protocol, 2200 repository, 2300 server). Released codes are
+        /// never renumbered or renamed.
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        #[non_exhaustive]
+        pub enum ErrorCode {
+            $($(#[$doc])* $variant,)*
+        }
+
+        impl ErrorCode {
+            /// Every code, in numeric order.
+            pub const ALL: &'static [ErrorCode] = &[$(Self::$variant,)*];
+
+            /// The code's stable number.
+            pub const fn number(self) -> u16 {
+                match self {
+                    $(Self::$variant => $number,)*
+                }
+            }
+
+            /// The code's stable dotted name, e.g. `ref.not_found`.
+            pub const fn name(self) -> &'static str {
+                match self {
+                    $(Self::$variant => $name,)*
+                }
+            }
+
+            /// The code's category.
+            pub const fn category(self) -> ErrorCategory {
+                match self {
+                    $(Self::$variant => ErrorCategory::$category,)*
+                }
+            }
+        }
+    };
+}
+
+error_codes! {
+    /// A bug or unexpected state.
+    Internal = 1000, "internal", Internal;
+    /// An operating-system I/O call failed.
+    Io = 1001, "io", Internal;
+    /// Encoding or decoding an internal structure failed.
+    Serialization = 1002, "serialization", Internal;
+    /// A value could not be parsed (bad hex, wrong length).
+    InvalidInput = 1003, "invalid_input", InvalidInput;
+    /// The input names an algorithm, version, or type this build does not
+    /// support.
+    Unsupported = 1004, "unsupported", InvalidInput;
+    /// Input from a peer nests too deeply or holds a string, array or map
+    /// larger than the decoding limits allow.
+    LimitExceeded = 1005, "limit_exceeded", InvalidInput;
+
+    /// A config file or value is malformed.
+    ConfigInvalid = 1100, "config.invalid", InvalidInput;
+    /// The config level has no file to write to.
+    ConfigNoFile = 1101, "config.no_file", NotFound;
+
+    /// An object is missing from the store.
+    ObjectNotFound = 1200, "store.object_not_found", NotFound;
+    /// An object's bytes do not match its id or cannot be decoded.
+    ObjectCorrupt = 1201, "store.object_corrupt", Integrity;
+    /// The store does not accept writes.
+    StoreReadOnly = 1202, "store.read_only", PermissionDenied;
+    /// An object had a different kind than the operation expected.
+    UnexpectedObjectKind = 1203, "store.unexpected_kind", InvalidInput;
+    /// The object's content was redacted; only its tombstone remains.
+    ObjectRedacted = 1204, "store.object_redacted", NotFound;
+
+    /// The receipt chain is broken or was tampered with.
+    LedgerIntegrity = 1300, "ledger.integrity_violation", Integrity;
+    /// A commitment or anchor receipt is missing from the stream.
+    ReceiptNotFound = 1301, "ledger.receipt_not_found", NotFound;
+    /// The commitment is in the wrong state for the requested outcome.
+    CommitmentState = 1302, "ledger.commitment_state", Conflict;
+    /// A sequence range is empty or reversed.
+    InvalidRange = 1303, "ledger.invalid_range", InvalidInput;
+    /// No stream exists for the worldline.
+    WorldlineNotFound = 1304, "ledger.worldline_not_found", NotFound;
+    /// Archiving there would separate receipts that must stay together.
+    ArchiveBoundary = 1305, "ledger.archive_boundary", Conflict;
+    /// A state update does not satisfy the schema of its namespace.
+    SchemaViolation = 1306, "ledger.schema_violation", InvalidInput;
+    /// A receipt names a state schema version that is not registered.
+    UnknownSchema = 1307, "ledger.unknown_schema", NotFound;
+    /// A replicated ledger write reached a node that is not the leader.
+    NotLeader = 1308, "ledger.not_leader", Unavailable;
+    /// A replicated ledger write could not be committed by a majority.
+    ReplicationFailed = 1309, "ledger.replication_failed", Unavailable;
+    /// A proof artifact could not be fetched.
+    ProofUnavailable = 1310, "proof.unavailable", Unavailable;
+    /// A proof artifact is malformed or does not prove what it claims.
+    ProofInvalid = 1311, "proof.invalid", Integrity;
+    /// A proof was signed by a party that is not trusted.
+    ProofUntrusted = 1312, "proof.untrusted", Rejected;
+    /// No verifier is registered for the proof type.
+    ProofUnknownType = 1313, "proof.unknown_type", InvalidInput;
+    /// An outcome effect breaks the rules for its kind.
+    InvalidEffect = 1314, "ledger.invalid_effect", InvalidInput;
+    /// A signature does not verify.
+    InvalidSignature = 1320, "crypto.invalid_signature", Integrity;
+    /// A key is malformed.
+    InvalidKey = 1321, "crypto.invalid_key", InvalidInput;
+    /// A sealed value is malformed or was altered.
+    EnvelopeInvalid = 1322, "crypto.envelope_invalid", Integrity;
+    /// None of the keys held can open a sealed value.
+    NotARecipient = 1323, "crypto.not_a_recipient", PermissionDenied;
+
+    /// The policy gate rejected the commitment.
+    GateRejected = 1400, "gate.rejected", Rejected;
+    /// The proposer lacks a required capability.
+    CapabilityDenied = 1401, "gate.capability_denied", PermissionDenied;
+    /// The proposal is missing required fields.
+    GateValidation = 1402, "gate.validation", InvalidInput;
+    /// Gate evaluation ran out of time.
+    GateTimeout = 1403, "gate.timeout", Unavailable;
+    /// A gate stage failed unexpectedly.
+    GateStageFailed = 1404, "gate.stage_failed", Internal;
+    /// The gate is misconfigured.
+    GateConfig = 1405, "gate.config", InvalidInput;
+    /// Referenced evidence does not exist.
+    EvidenceNotFound = 1410, "evidence.not_found", NotFound;
+    /// Referenced evidence is malformed or unacceptable.
+    EvidenceInvalid = 1411, "evidence.invalid", InvalidInput;
+    /// Evidence could not be fetched.
+    EvidenceUnavailable = 1412, "evidence.unavailable", Unavailable;
+
+    /// A branch, tag, or other ref does not exist.
+    RefNotFound = 1500, "ref.not_found", NotFound;
+    /// A ref with that name already exists.
+    RefExists = 1501, "ref.already_exists", Conflict;
+    /// A ref name is not allowed.
+    RefInvalidName = 1502, "ref.invalid_name", InvalidInput;
+    /// Tags cannot be moved.
+    TagImmutable = 1503, "ref.tag_immutable", Conflict;
+    /// HEAD does not point to a branch.
+    DetachedHead = 1504, "ref.detached_head", Conflict;
+    /// The checked-out branch cannot be deleted.
+    DeleteCurrentBranch = 1505, "ref.delete_current_branch", Conflict;
+    /// A ref moved since an update read it.
+    RefStale = 1506, "ref.stale", Conflict;
+
+    /// The path is not in the index.
+    IndexPathNotFound = 1600, "index.path_not_found", NotFound;
+    /// The path is already staged or has an unresolved conflict.
+    IndexConflict = 1601, "index.conflict", Conflict;
+    /// The path is not a valid repository path.
+    InvalidPath = 1602, "index.invalid_path", InvalidInput;
+
+    /// A node is missing from the DAG.
+    DagNodeNotFound = 1700, "dag.node_not_found", NotFound;
+    /// Adding a node would break a DAG invariant.
+    DagInvalid = 1701, "dag.invalid", Integrity;
+    /// A sealed audit trail's signature does not verify.
+    AuditSealInvalid = 1702, "dag.audit_seal_invalid", Integrity;
+
+    /// A pack or pack index is malformed or fails its checksum.
+    PackCorrupt = 1800, "pack.corrupt", Integrity;
+    /// An object is missing from the pack.
+    PackObjectNotFound = 1801, "pack.object_not_found", NotFound;
+
+    /// A WAL segment failed its integrity checks.
+    WalCorrupt = 1900, "fabric.wal_corrupt", Integrity;
+    /// A timestamp is too far ahead of the local clock.
+    ClockDrift = 1901, "fabric.clock_drift", Rejected;
+    /// The fabric or a subscriber has shut down.
+    FabricClosed = 1902, "fabric.closed", Unavailable;
+    /// A checkpoint lies beyond the end of the WAL.
+    InvalidCheckpoint = 1903, "fabric.invalid_checkpoint", InvalidInput;
+
+    /// The remote reported an error.
+    RemoteError = 2000, "sync.remote_error", Unavailable;
+    /// The remote refused a ref update.
+    RefRejected = 2001, "sync.ref_rejected", Rejected;
+    /// A ref update would discard commits.
+    NotFastForward = 2002, "sync.not_fast_forward", Conflict;
+    /// Fetched receipts failed verification.
+    SyncVerification = 2003, "sync.verification_failed", Integrity;
+    /// The remote could not be reached or negotiation failed.
+    Transport = 2004, "sync.transport", Unavailable;
+    /// A remote is misconfigured.
+    InvalidRemote = 2005, "sync.invalid_remote", InvalidInput;
+    /// Credentials for a remote are missing or were refused.
+    Credential = 2006, "sync.credential", Unauthenticated;
+    /// A replica has not reached the ledger position a read requires.
+    ReplicaBehind = 2007, "sync.replica_behind", Unavailable;
+    /// A receipt page cursor names a receipt the stream does not hold.
+    InvalidCursor = 2008, "sync.invalid_cursor", InvalidInput;
+
+    /// A frame or message is malformed.
+    ProtocolMalformed = 2100, "protocol.malformed", InvalidInput;
+    /// A frame exceeds the size limit.
+    MessageTooLarge = 2101, "protocol.message_too_large", InvalidInput;
+    /// The peers speak incompatible protocol versions.
+    VersionMismatch = 2102, "protocol.version_mismatch", InvalidInput;
+
+    /// No repository exists at the path.
+    RepoNotInitialized = 2200, "repo.not_initialized", NotFound;
+    /// A repository already exists there.
+    RepoExists = 2201, "repo.already_exists", Conflict;
+    /// The operation is not valid in the repository's current state.
+    InvalidOperation = 2202, "repo.invalid_operation", InvalidInput;
+    /// A transparency log refused or could not record the head.
+    TransparencyLog = 2203, "repo.transparency_log", Unavailable;
+    /// The server hosts no repository by that name.
+    RepoNotFound = 2204, "repo.not_found", NotFound;
+    /// A bundle file is malformed, fails its checksum, or is not signed by
+    /// a trusted key.
+    BundleInvalid = 2205, "repo.bundle_invalid", Integrity;
+    /// A search query could not be parsed.
+    SearchQuery = 2206, "repo.search_query", InvalidInput;
+    /// The search index could not be opened, updated or read.
+    SearchIndex = 2207, "repo.search_index", Internal;
+    /// Another node holds the worldline's primary lease, or this node's
+    /// lease has lapsed.
+    NotPrimary = 2208, "repo.not_primary", Unavailable;
+
+    /// Authentication failed.
+    Unauthenticated = 2300, "auth.unauthenticated", Unauthenticated;
+    /// The authenticated caller may not perform the action.
+    PermissionDenied = 2301, "auth.permission_denied", PermissionDenied;
+}
+
+impl ErrorCode {
+    /// The code with `number`, if any.
+    pub fn from_number(number: u16) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|code| code.number() == number)
+    }
+
+    /// The code named `name`, if any.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|code| code.name() == name)
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Serialize for ErrorCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for ErrorCode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = alloc::string::String::deserialize(deserializer)?;
+        Self::from_name(&name)
+            .ok_or_else(|| serde::de::Error::custom(alloc::format!("unknown error code {name:?}")))
+    }
+}
+
+/// An error that carries a stable [`ErrorCode`].
+pub trait Coded {
+    /// The code for this error.
+    fn code(&self) -> ErrorCode;
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::collections::BTreeSet;
+
+    use super::*;
+
+    #[test]
+    fn codes_are_unique_and_ordered() {
+        let numbers: BTreeSet<_> = ErrorCode::ALL.iter().map(|c| c.number()).collect();
+        let names: BTreeSet<_> = ErrorCode::ALL.iter().map(|c| c.name()).collect();
+        assert_eq!(numbers.len(), ErrorCode::ALL.len());
+        assert_eq!(names.len(), ErrorCode::ALL.len());
+        assert!(ErrorCode::ALL
+            .windows(2)
+            .all(|w| w[0].number() < w[1].number()));
+        for code in ErrorCode::ALL {
+            assert_eq!(ErrorCode::from_number(code.number()), Some(*code));
+            assert_eq!(ErrorCode::from_name(code.name()), Some(*code));
+        }
+    }
+
+    #[test]
+    fn codes_serialize_by_name() {
+        let json = serde_json::to_string(&ErrorCode::RefNotFound).unwrap();
+        assert_eq!(json, "\"ref.not_found\"");
+        assert_eq!(
+            serde_json::from_str::<ErrorCode>(&json).unwrap(),
+            ErrorCode::RefNotFound
+        );
+        assert!(serde_json::from_str::<ErrorCode>("\"nope\"").is_err());
+        assert_eq!(ErrorCode::RefNotFound.category().http_status(), 404);
+    }
+}
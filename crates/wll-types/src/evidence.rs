@@ -1,55 +1,97 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
 use serde::{Deserialize, Serialize};
 
-/// External evidence references that anchor a commitment.
+use crate::object::ObjectId;
+
+/// Evidence that anchors a commitment.
 ///
 /// Evidence bundles provide proof that a commitment has justification.
 /// References are URIs pointing to external evidence stores (e.g.,
-/// `issue://PROJ-42`, `obj://hash`, `doc://spec-v2`).
+/// `issue://PROJ-42`, `obj://hash`, `doc://spec-v2`). Attachments are
+/// artifacts stored as blobs in the repository itself, so they stay
+/// available when the external systems change.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct EvidenceBundle {
     /// URIs to external evidence artifacts.
     pub references: Vec<String>,
-    /// BLAKE3 digest of the serialized references (for integrity).
+    /// Artifacts stored in the object store.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<EvidenceAttachment>,
+    /// BLAKE3 digest of the serialized references and attachments (for
+    /// integrity).
     pub digest: [u8; 32],
 }
 
+/// An evidence artifact (test log, review approval, SBOM) stored as a blob.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EvidenceAttachment {
+    /// Blob holding the artifact.
+    pub object: ObjectId,
+    /// Media type of the artifact (e.g. `text/plain`,
+    /// `application/spdx+json`).
+    pub media_type: String,
+    /// File name to extract the artifact as.
+    pub name: String,
+    /// Artifact size in bytes.
+    pub size: u64,
+}
+
 impl EvidenceBundle {
     /// Create a bundle from a list of reference URIs.
     ///
     /// The digest is computed automatically from the references.
     pub fn from_references(references: Vec<String>) -> Self {
-        let digest = compute_digest(&references);
-        Self { references, digest }
+        Self::new(references, Vec::new())
+    }
+
+    /// Create a bundle from reference URIs and stored attachments.
+    pub fn new(references: Vec<String>, attachments: Vec<EvidenceAttachment>) -> Self {
+        let digest = compute_digest(&references, &attachments);
+        Self {
+            references,
+            attachments,
+            digest,
+        }
     }
 
     /// Create an empty evidence bundle (no evidence).
     pub fn empty() -> Self {
-        Self::from_references(vec![])
+        Self::from_references(Vec::new())
     }
 
-    /// Returns `true` if the bundle has no references.
+    /// Returns `true` if the bundle has no references or attachments.
     pub fn is_empty(&self) -> bool {
-        self.references.is_empty()
+        self.references.is_empty() && self.attachments.is_empty()
     }
 
-    /// Number of evidence references.
+    /// Number of evidence references and attachments.
     pub fn len(&self) -> usize {
-        self.references.len()
+        self.references.len() + self.attachments.len()
     }
 
-    /// Verify that the digest matches the references.
+    /// Verify that the digest matches the references and attachments.
     pub fn verify_digest(&self) -> bool {
-        compute_digest(&self.references) == self.digest
+        compute_digest(&self.references, &self.attachments) == self.digest
     }
 }
 
-fn compute_digest(references: &[String]) -> [u8; 32] {
-    let serialized = serde_json::to_vec(references).unwrap_or_default();
-    *blake3::hash(&serialized).as_bytes()
+/// Attachments only contribute when present, so bundles without them keep
+/// the digest they had before attachments existed.
+fn compute_digest(references: &[String], attachments: &[EvidenceAttachment]) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&serde_json::to_vec(references).unwrap_or_default());
+    if !attachments.is_empty() {
+        hasher.update(&serde_json::to_vec(attachments).unwrap_or_default());
+    }
+    *hasher.finalize().as_bytes()
 }
 
 #[cfg(test)]
 mod tests {
+    use alloc::vec;
+
     use super::*;
 
     #[test]
@@ -85,6 +127,33 @@ mod tests {
     #[test]
     fn serde_roundtrip() {
         let bundle = EvidenceBundle::from_references(vec!["obj://abc".into()]);
+        let json = serde_json::to_string(&bundle).unwrap();
+        assert!(!json.contains("attachments"));
+        let parsed: EvidenceBundle = serde_json::from_str(&json).unwrap();
+        assert_eq!(bundle, parsed);
+    }
+
+    #[test]
+    fn attachments_are_covered_by_digest() {
+        let attachment = EvidenceAttachment {
+            object: ObjectId::from_bytes(b"test log"),
+            media_type: "text/plain".into(),
+            name: "test.log".into(),
+            size: 8,
+        };
+        let references = vec![String::from("issue://PROJ-42")];
+        let bundle = EvidenceBundle::new(references.clone(), vec![attachment]);
+        assert_eq!(bundle.len(), 2);
+        assert!(bundle.verify_digest());
+        assert_ne!(
+            bundle.digest,
+            EvidenceBundle::from_references(references).digest
+        );
+
+        let mut tampered = bundle.clone();
+        tampered.attachments[0].media_type = "text/html".into();
+        assert!(!tampered.verify_digest());
+
         let json = serde_json::to_string(&bundle).unwrap();
         let parsed: EvidenceBundle = serde_json::from_str(&json).unwrap();
         assert_eq!(bundle, parsed);
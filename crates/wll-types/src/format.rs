@@ -0,0 +1,54 @@
+//! Versions of on-disk formats.
+//!
+//! Each crate that writes a versioned file declares it as a [`FormatSpec`]:
+//! the version it writes, and the oldest it still reads. A reader accepts
+//! any version in between and refuses the rest, so a file written by a
+//! newer build fails with an unsupported-version error rather than being
+//! misread, and one in an older version can be upgraded in place (see
+//! `Wll::migrate` in `wll-sdk`).
+
+use core::fmt;
+
+/// An on-disk format and the versions of it this build handles.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FormatSpec {
+    /// Name of the format, e.g. `pack-index`.
+    pub name: &'static str,
+    /// Version new files are written in.
+    pub current: u32,
+    /// Oldest version still read.
+    pub oldest: u32,
+}
+
+impl FormatSpec {
+    /// Whether files in `version` can be read.
+    pub fn reads(&self, version: u32) -> bool {
+        (self.oldest..=self.current).contains(&version)
+    }
+
+    /// Whether a file in `version` is readable but should be upgraded.
+    pub fn is_outdated(&self, version: u32) -> bool {
+        self.reads(version) && version < self.current
+    }
+}
+
+impl fmt::Display for FormatSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} v{}", self.name, self.current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+
+    #[test]
+    fn reads_from_oldest_through_current() {
+        let spec = FormatSpec { name: "wal", current: 2, oldest: 1 };
+        assert!(!spec.reads(0) && spec.reads(1) && spec.reads(2) && !spec.reads(3));
+        assert!(spec.is_outdated(1) && !spec.is_outdated(2) && !spec.is_outdated(3));
+        assert_eq!(spec.to_string(), "wal v2");
+    }
+}
@@ -1,5 +1,7 @@
-use std::fmt;
-use std::hash::Hash;
+use alloc::format;
+use alloc::string::{String, ToString};
+use core::fmt;
+use core::hash::Hash;
 
 use serde::{Deserialize, Serialize};
 
@@ -54,6 +56,7 @@ impl WorldlineId {
     }
 
     /// Create an ephemeral (random) WorldlineId for tests and demos.
+    #[cfg(feature = "std")]
     pub fn ephemeral() -> Self {
         let mut bytes = [0u8; 32];
         rand::Rng::fill(&mut rand::thread_rng(), &mut bytes);
@@ -149,6 +152,7 @@ mod tests {
         assert_ne!(id1, id2);
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn ephemeral_ids_are_unique() {
         let id1 = WorldlineId::ephemeral();
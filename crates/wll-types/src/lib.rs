@@ -6,27 +6,55 @@
 //! # Key Types
 //!
 //! - [`WorldlineId`] — Persistent cryptographic identity derived from genesis material
-//! - [`ObjectId`] — Content-addressed identifier (BLAKE3 hash)
+//! - [`ObjectId`] — Content-addressed identifier tagged with its [`HashAlgo`]
 //! - [`TemporalAnchor`] — Hybrid Logical Clock timestamp for causal ordering
+//! - [`Clock`] — Injectable wall-clock time: [`SystemClock`], [`ManualClock`]
 //! - [`CommitmentId`] — UUID v7 commitment identifier
 //! - [`CommitmentClass`] — Risk classification for policy gating
-//! - [`Decision`] — Policy evaluation result
-//! - [`EvidenceBundle`] — External evidence references
+//! - [`Decision`](commitment::Decision) — Policy evaluation result
+//! - [`EvidenceBundle`] — Evidence references and stored attachments
+//! - [`IdentityAttestation`] — Verified binding to an external identity
+//! - [`ErrorCode`] — Stable error codes every crate's errors map to via [`Coded`]
+//! - [`FormatSpec`] — The versions of an on-disk format a build writes and reads
+//! - [`DecodeLimits`] — Caps on nesting and sizes when decoding input from peers
+//!
+//! # Features
+//!
+//! - `std` (default) — wall-clock constructors ([`TemporalAnchor::now`],
+//!   [`CommitmentId::new`], [`SystemClock`]) and random [`WorldlineId::ephemeral`] ids.
+//!   Without it the crate is `no_std` + `alloc`, for signers on constrained
+//!   devices that supply their own clock and ids.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
 
+pub mod attestation;
+pub mod clock;
 pub mod commitment;
 pub mod error;
+pub mod error_code;
 pub mod evidence;
+pub mod format;
 pub mod identity;
+pub mod limits;
 pub mod object;
 pub mod receipt;
 pub mod temporal;
 
+pub use attestation::{AttestationKind, IdentityAttestation};
+#[cfg(feature = "std")]
+pub use clock::SystemClock;
+pub use clock::{Clock, ManualClock};
 pub use commitment::{
     Capability, CapabilityId, CapabilityScope, CommitmentClass, CommitmentId, Reversibility,
 };
 pub use error::TypeError;
-pub use evidence::EvidenceBundle;
+pub use error_code::{Coded, ErrorCategory, ErrorCode};
+pub use evidence::{EvidenceAttachment, EvidenceBundle};
+pub use format::FormatSpec;
 pub use identity::{IdentityMaterial, WorldlineId};
-pub use object::ObjectId;
+pub use limits::{DecodeLimits, LimitExceeded};
+pub use object::{HashAlgo, ObjectId};
 pub use receipt::{ReceiptId, ReceiptKind};
 pub use temporal::TemporalAnchor;
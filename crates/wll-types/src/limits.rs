@@ -0,0 +1,202 @@
+//! Bounds on structures decoded from untrusted input.
+//!
+//! Receipts, tree objects and protocol messages arrive from peers. A frame
+//! is at most `MAX_MESSAGE_SIZE` bytes, but within that a peer can still
+//! nest arrays thousands deep or send one string, array or map large enough
+//! to exhaust memory once decoded. [`DecodeLimits`] caps each of these.
+//! [`DecodeLimits::check_json`] scans encoded JSON against them before it is
+//! handed to serde, without building anything, so oversized input is
+//! refused before it costs more than the bytes already received.
+
+use alloc::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::error_code::{Coded, ErrorCode};
+
+/// Caps on the shape of decoded input. Each subsystem that decodes input
+/// from peers takes its own, so a server can, say, allow larger trees than
+/// protocol messages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DecodeLimits {
+    /// Deepest nesting of arrays and maps.
+    pub max_depth: usize,
+    /// Longest string, in encoded bytes.
+    pub max_string_len: usize,
+    /// Most elements in one array.
+    pub max_array_len: usize,
+    /// Most entries in one map, such as a receipt's state or metadata.
+    pub max_map_entries: usize,
+}
+
+impl DecodeLimits {
+    /// Limits generous enough for any repository this build writes.
+    pub const DEFAULT: Self = Self {
+        max_depth: 64,
+        max_string_len: 4 * 1024 * 1024,
+        max_array_len: 1_000_000,
+        max_map_entries: 100_000,
+    };
+
+    pub const fn new() -> Self {
+        Self::DEFAULT
+    }
+
+    pub const fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    pub const fn with_max_string_len(mut self, len: usize) -> Self {
+        self.max_string_len = len;
+        self
+    }
+
+    pub const fn with_max_array_len(mut self, len: usize) -> Self {
+        self.max_array_len = len;
+        self
+    }
+
+    pub const fn with_max_map_entries(mut self, entries: usize) -> Self {
+        self.max_map_entries = entries;
+        self
+    }
+
+    /// Check a string of `len` bytes.
+    pub fn check_string(&self, len: usize) -> Result<(), LimitExceeded> {
+        if len > self.max_string_len {
+            return Err(LimitExceeded::String { max: self.max_string_len });
+        }
+        Ok(())
+    }
+
+    /// Check an array of `len` elements.
+    pub fn check_array(&self, len: usize) -> Result<(), LimitExceeded> {
+        if len > self.max_array_len {
+            return Err(LimitExceeded::Array { max: self.max_array_len });
+        }
+        Ok(())
+    }
+
+    /// Check a map of `len` entries.
+    pub fn check_map(&self, len: usize) -> Result<(), LimitExceeded> {
+        if len > self.max_map_entries {
+            return Err(LimitExceeded::Map { max: self.max_map_entries });
+        }
+        Ok(())
+    }
+
+    /// Check encoded JSON against the limits.
+    ///
+    /// Only the structure is scanned: strings are measured as encoded, and
+    /// malformed JSON passes, to be rejected by the decoder that follows.
+    pub fn check_json(&self, data: &[u8]) -> Result<(), LimitExceeded> {
+        // One entry per open array (`false`) or object (`true`), with the
+        // separators seen in it so far.
+        let mut open: Vec<(bool, usize)> = Vec::new();
+        let mut pos = 0;
+        while let Some(&byte) = data.get(pos) {
+            match byte {
+                b'"' => {
+                    let start = pos + 1;
+                    pos = start;
+                    while let Some(&byte) = data.get(pos) {
+                        match byte {
+                            b'"' => break,
+                            b'\\' => pos += 2,
+                            _ => pos += 1,
+                        }
+                    }
+                    self.check_string(pos.min(data.len()) - start)?;
+                }
+                b'[' | b'{' => {
+                    if open.len() == self.max_depth {
+                        return Err(LimitExceeded::Depth { max: self.max_depth });
+                    }
+                    open.push((byte == b'{', 0));
+                }
+                b']' | b'}' => {
+                    open.pop();
+                }
+                b',' => {
+                    if let Some((is_map, separators)) = open.last_mut() {
+                        *separators += 1;
+                        match is_map {
+                            true => self.check_map(*separators + 1)?,
+                            false => self.check_array(*separators + 1)?,
+                        }
+                    }
+                }
+                _ => {}
+            }
+            pos += 1;
+        }
+        Ok(())
+    }
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Input that [`DecodeLimits`] refused.
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum LimitExceeded {
+    #[error("nested more than {max} levels deep")]
+    Depth { max: usize },
+
+    #[error("string longer than {max} bytes")]
+    String { max: usize },
+
+    #[error("array of more than {max} elements")]
+    Array { max: usize },
+
+    #[error("map of more than {max} entries")]
+    Map { max: usize },
+}
+
+impl Coded for LimitExceeded {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::LimitExceeded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_within_limits_passes() {
+        let limits = DecodeLimits::new().with_max_depth(3).with_max_array_len(3);
+        let json = br#"{"entries":[{"name":"a\"]]]]"},[],{}],"n":[1,2,3]}"#;
+        assert_eq!(limits.check_json(json), Ok(()));
+        assert_eq!(limits.check_json(b"not json at all"), Ok(()));
+    }
+
+    #[test]
+    fn each_limit_is_enforced() {
+        let limits = DecodeLimits::new()
+            .with_max_depth(2)
+            .with_max_string_len(4)
+            .with_max_array_len(2)
+            .with_max_map_entries(1);
+        assert_eq!(limits.check_json(b"[[[]]]"), Err(LimitExceeded::Depth { max: 2 }));
+        assert_eq!(limits.check_json(br#"["abcde"]"#), Err(LimitExceeded::String { max: 4 }));
+        assert_eq!(limits.check_json(br#"["ab\"c"]"#), Err(LimitExceeded::String { max: 4 }));
+        assert_eq!(limits.check_json(b"[1,2,3]"), Err(LimitExceeded::Array { max: 2 }));
+        let map = br#"{"a":1,"b":2}"#;
+        assert_eq!(limits.check_json(map), Err(LimitExceeded::Map { max: 1 }));
+        // An unterminated string is measured to the end of the input.
+        assert_eq!(limits.check_json(br#"["abcdefgh"#), Err(LimitExceeded::String { max: 4 }));
+    }
+
+    #[test]
+    fn unset_fields_take_the_defaults() {
+        let limits: DecodeLimits = serde_json::from_str(r#"{"max_depth":8}"#).unwrap();
+        assert_eq!(limits, DecodeLimits::DEFAULT.with_max_depth(8));
+    }
+}
@@ -1,56 +1,161 @@
-use std::fmt;
+use alloc::format;
+use alloc::string::{String, ToString};
+use core::fmt;
+use core::str::FromStr;
 
-use serde::{Deserialize, Serialize};
+use serde::de::{self, SeqAccess, Visitor};
+use serde::ser::SerializeTuple;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::error::TypeError;
 
+/// Hash algorithm used to derive an [`ObjectId`] or receipt hash.
+///
+/// WLL hashes with BLAKE3 by default. The algorithm tag travels with every
+/// identifier so that repositories can migrate to, or interoperate with,
+/// other 256-bit digests (e.g. SHA-256) without ambiguity. The digest
+/// implementations live in `wll-crypto`.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub enum HashAlgo {
+    /// BLAKE3 (default).
+    #[default]
+    Blake3,
+    /// SHA-256.
+    Sha256,
+}
+
+impl HashAlgo {
+    /// All supported algorithms, in tag order.
+    pub const ALL: [HashAlgo; 2] = [HashAlgo::Blake3, HashAlgo::Sha256];
+
+    /// Stable one-byte tag used in binary encodings.
+    pub fn tag(&self) -> u8 {
+        match self {
+            Self::Blake3 => 1,
+            Self::Sha256 => 2,
+        }
+    }
+
+    /// Resolve an algorithm from its one-byte tag.
+    pub fn from_tag(tag: u8) -> Result<Self, TypeError> {
+        match tag {
+            1 => Ok(Self::Blake3),
+            2 => Ok(Self::Sha256),
+            other => Err(TypeError::UnknownHashAlgo(format!("tag {other}"))),
+        }
+    }
+
+    /// Lowercase algorithm name (`"blake3"`, `"sha256"`).
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Blake3 => "blake3",
+            Self::Sha256 => "sha256",
+        }
+    }
+
+    /// Digest length in bytes.
+    pub fn digest_len(&self) -> usize {
+        32
+    }
+}
+
+impl fmt::Display for HashAlgo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for HashAlgo {
+    type Err = TypeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "blake3" => Ok(Self::Blake3),
+            "sha256" | "sha-256" => Ok(Self::Sha256),
+            other => Err(TypeError::UnknownHashAlgo(other.to_string())),
+        }
+    }
+}
+
 /// Content-addressed identifier for any stored object.
 ///
-/// An `ObjectId` is the BLAKE3 hash of an object's content. Identical content
-/// always produces the same `ObjectId`, making objects deduplicatable and
-/// verifiable.
-#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
-pub struct ObjectId([u8; 32]);
+/// An `ObjectId` is the hash of an object's content, tagged with the
+/// [`HashAlgo`] that produced it. Identical content always produces the same
+/// `ObjectId` under a given algorithm, making objects deduplicatable and
+/// verifiable. Identifiers from different algorithms never compare equal.
+///
+/// BLAKE3 identifiers serialize as the bare `[u8; 32]` they were before
+/// identifiers carried an algorithm, so WAL segments, DAG files, trees and
+/// protocol frames written then still read. Other algorithms are tagged:
+/// as `"<algo>:<hex>"` in human-readable formats, and in binary ones as
+/// [`TAGGED_ID_MARKER`], the algorithm's tag, then the digest.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ObjectId {
+    algo: HashAlgo,
+    hash: [u8; 32],
+}
 
 impl ObjectId {
-    /// Compute an `ObjectId` from raw bytes.
+    /// Compute a BLAKE3 `ObjectId` from raw bytes.
     pub fn from_bytes(data: &[u8]) -> Self {
-        Self(*blake3::hash(data).as_bytes())
+        Self::from_hash(*blake3::hash(data).as_bytes())
     }
 
-    /// Create an `ObjectId` from a pre-computed hash.
+    /// Create a BLAKE3 `ObjectId` from a pre-computed hash.
     pub fn from_hash(hash: [u8; 32]) -> Self {
-        Self(hash)
+        Self::from_algo_hash(HashAlgo::Blake3, hash)
+    }
+
+    /// Create an `ObjectId` from a pre-computed hash of the given algorithm.
+    pub const fn from_algo_hash(algo: HashAlgo, hash: [u8; 32]) -> Self {
+        Self { algo, hash }
     }
 
     /// The null object ID (all zeros). Represents "no object".
     pub const fn null() -> Self {
-        Self([0u8; 32])
+        Self::from_algo_hash(HashAlgo::Blake3, [0u8; 32])
     }
 
     /// Returns `true` if this is the null object ID.
     pub fn is_null(&self) -> bool {
-        self.0 == [0u8; 32]
+        self.hash == [0u8; 32]
+    }
+
+    /// The algorithm that produced this identifier.
+    pub fn algo(&self) -> HashAlgo {
+        self.algo
     }
 
     /// The raw 32-byte hash.
     pub fn as_bytes(&self) -> &[u8; 32] {
-        &self.0
+        &self.hash
     }
 
     /// Hex-encoded string representation.
+    ///
+    /// BLAKE3 identifiers are bare hex; other algorithms are prefixed with
+    /// their name (`sha256:...`).
     pub fn to_hex(&self) -> String {
-        hex::encode(self.0)
+        match self.algo {
+            HashAlgo::Blake3 => hex::encode(self.hash),
+            other => format!("{other}:{}", hex::encode(self.hash)),
+        }
     }
 
     /// Short hex representation (first 8 characters).
     pub fn short_hex(&self) -> String {
-        hex::encode(&self.0[..4])
+        hex::encode(&self.hash[..4])
     }
 
-    /// Parse from a hex string.
+    /// Parse from a hex string, optionally prefixed with `<algo>:`.
     pub fn from_hex(s: &str) -> Result<Self, TypeError> {
-        let bytes = hex::decode(s).map_err(|e| TypeError::InvalidHex(e.to_string()))?;
+        let (algo, digits) = match s.split_once(':') {
+            Some((name, digits)) => (name.parse()?, digits),
+            None => (HashAlgo::Blake3, s),
+        };
+        let bytes = hex::decode(digits).map_err(|e| TypeError::InvalidHex(e.to_string()))?;
         if bytes.len() != 32 {
             return Err(TypeError::InvalidLength {
                 expected: 32,
@@ -59,13 +164,16 @@ impl ObjectId {
         }
         let mut arr = [0u8; 32];
         arr.copy_from_slice(&bytes);
-        Ok(Self(arr))
+        Ok(Self::from_algo_hash(algo, arr))
     }
 }
 
 impl fmt::Debug for ObjectId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "ObjectId({})", self.short_hex())
+        match self.algo {
+            HashAlgo::Blake3 => write!(f, "ObjectId({})", self.short_hex()),
+            other => write!(f, "ObjectId({other}:{})", self.short_hex()),
+        }
     }
 }
 
@@ -75,15 +183,105 @@ impl fmt::Display for ObjectId {
     }
 }
 
+/// Opens the binary encoding of an identifier from any algorithm but
+/// BLAKE3. A BLAKE3 identifier equal to it would take a preimage of these
+/// bytes, so the two encodings cannot be confused.
+pub const TAGGED_ID_MARKER: [u8; 32] = *b"wll:object-id:tagged-algorithm\0\0";
+
+/// Elements in a tagged binary identifier: marker, tag and digest.
+const TAGGED_ID_LEN: usize = 32 + 1 + 32;
+
+impl Serialize for ObjectId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.algo {
+            HashAlgo::Blake3 => serializer.serialize_newtype_struct("ObjectId", &self.hash),
+            _ if serializer.is_human_readable() => {
+                serializer.serialize_newtype_struct("ObjectId", &self.to_hex())
+            }
+            _ => serializer.serialize_newtype_struct("ObjectId", &Tagged(self)),
+        }
+    }
+}
+
+/// A non-BLAKE3 identifier in its binary encoding.
+struct Tagged<'a>(&'a ObjectId);
+
+impl Serialize for Tagged<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(TAGGED_ID_LEN)?;
+        for byte in TAGGED_ID_MARKER {
+            tuple.serialize_element(&byte)?;
+        }
+        tuple.serialize_element(&self.0.algo.tag())?;
+        for byte in self.0.hash {
+            tuple.serialize_element(&byte)?;
+        }
+        tuple.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for ObjectId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let human_readable = deserializer.is_human_readable();
+        deserializer.deserialize_newtype_struct("ObjectId", IdVisitor { human_readable })
+    }
+}
+
+struct IdVisitor {
+    human_readable: bool,
+}
+
+impl<'de> Visitor<'de> for IdVisitor {
+    type Value = ObjectId;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a 32-byte BLAKE3 digest or an algorithm-tagged object id")
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, d: D) -> Result<ObjectId, D::Error> {
+        if self.human_readable {
+            d.deserialize_any(self)
+        } else {
+            // Reads stop after the digest unless it is the marker.
+            d.deserialize_tuple(TAGGED_ID_LEN, self)
+        }
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<ObjectId, E> {
+        ObjectId::from_hex(s).map_err(E::custom)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<ObjectId, A::Error> {
+        let mut read = 0;
+        let mut next = |seq: &mut A| -> Result<u8, A::Error> {
+            let byte = seq.next_element()?.ok_or_else(|| de::Error::invalid_length(read, &self))?;
+            read += 1;
+            Ok(byte)
+        };
+        let mut hash = [0; 32];
+        for byte in &mut hash {
+            *byte = next(&mut seq)?;
+        }
+        if hash != TAGGED_ID_MARKER {
+            return Ok(ObjectId::from_hash(hash));
+        }
+        let algo = HashAlgo::from_tag(next(&mut seq)?).map_err(de::Error::custom)?;
+        for byte in &mut hash {
+            *byte = next(&mut seq)?;
+        }
+        Ok(ObjectId::from_algo_hash(algo, hash))
+    }
+}
+
 impl From<[u8; 32]> for ObjectId {
     fn from(bytes: [u8; 32]) -> Self {
-        Self(bytes)
+        Self::from_hash(bytes)
     }
 }
 
 impl From<ObjectId> for [u8; 32] {
     fn from(id: ObjectId) -> Self {
-        id.0
+        id.hash
     }
 }
 
@@ -143,10 +341,86 @@ mod tests {
         assert_eq!(id, parsed);
     }
 
+    /// `ObjectId::from_bytes(b"wll")` as encoded by 0.1.0, when ids were a
+    /// bare `[u8; 32]`.
+    const WLL_ID: &str = "ab7b9a249cb4aa0642b08e32ec1600009045ca0686fe62bc4bdec61388b7dc34";
+
+    #[test]
+    fn blake3_ids_keep_the_untagged_encoding() {
+        let id = ObjectId::from_bytes(b"wll");
+        let bytes = bincode::serialize(&id).unwrap();
+        assert_eq!(hex::encode(&bytes), WLL_ID);
+        assert_eq!(bincode::deserialize::<ObjectId>(&bytes).unwrap(), id);
+
+        let json = "[171,123,154,36,156,180,170,6,66,176,142,50,236,22,0,0,\
+                    144,69,202,6,134,254,98,188,75,222,198,19,136,183,220,52]";
+        assert_eq!(serde_json::to_string(&id).unwrap(), json);
+        assert_eq!(serde_json::from_str::<ObjectId>(json).unwrap(), id);
+    }
+
+    #[test]
+    fn other_algorithms_are_tagged() {
+        let id = ObjectId::from_algo_hash(HashAlgo::Sha256, [0xcd; 32]);
+        let bytes = bincode::serialize(&id).unwrap();
+        assert_eq!(bytes.len(), 65);
+        assert_eq!(bytes[..32], TAGGED_ID_MARKER);
+        assert_eq!(bincode::deserialize::<ObjectId>(&bytes).unwrap(), id);
+
+        let json = serde_json::to_string(&id).unwrap();
+        assert_eq!(json, format!("\"{}\"", id.to_hex()));
+        assert_eq!(serde_json::from_str::<ObjectId>(&json).unwrap(), id);
+    }
+
+    #[test]
+    fn ids_in_containers_read_back_in_either_encoding() {
+        let ids = [
+            ObjectId::from_algo_hash(HashAlgo::Sha256, [1; 32]),
+            ObjectId::from_bytes(b"wll"),
+            ObjectId::from_algo_hash(HashAlgo::Sha256, [2; 32]),
+        ];
+        let bytes = bincode::serialize(&(ids, 7u8)).unwrap();
+        assert_eq!(bincode::deserialize::<([ObjectId; 3], u8)>(&bytes).unwrap(), (ids, 7));
+    }
+
     #[test]
     fn ordering_is_consistent() {
         let id1 = ObjectId::from_hash([0; 32]);
         let id2 = ObjectId::from_hash([1; 32]);
         assert!(id1 < id2);
     }
+
+    #[test]
+    fn algorithms_do_not_compare_equal() {
+        let blake = ObjectId::from_algo_hash(HashAlgo::Blake3, [5; 32]);
+        let sha = ObjectId::from_algo_hash(HashAlgo::Sha256, [5; 32]);
+        assert_ne!(blake, sha);
+        assert_eq!(blake.as_bytes(), sha.as_bytes());
+    }
+
+    #[test]
+    fn prefixed_hex_roundtrip() {
+        let id = ObjectId::from_algo_hash(HashAlgo::Sha256, [0xcd; 32]);
+        let hex = id.to_hex();
+        assert!(hex.starts_with("sha256:"));
+        assert_eq!(ObjectId::from_hex(&hex).unwrap(), id);
+        assert_eq!(
+            ObjectId::from_hex(&format!("blake3:{}", "00".repeat(32))).unwrap(),
+            ObjectId::null()
+        );
+    }
+
+    #[test]
+    fn unknown_algorithm_prefix_is_rejected() {
+        let err = ObjectId::from_hex(&format!("md5:{}", "00".repeat(32))).unwrap_err();
+        assert_eq!(err, TypeError::UnknownHashAlgo("md5".into()));
+    }
+
+    #[test]
+    fn hash_algo_tags_roundtrip() {
+        for algo in HashAlgo::ALL {
+            assert_eq!(HashAlgo::from_tag(algo.tag()).unwrap(), algo);
+            assert_eq!(algo.name().parse::<HashAlgo>().unwrap(), algo);
+        }
+        assert!(HashAlgo::from_tag(0).is_err());
+    }
 }
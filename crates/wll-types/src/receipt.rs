@@ -1,8 +1,10 @@
-use std::fmt;
+use alloc::string::String;
+use core::fmt;
 
 use serde::{Deserialize, Serialize};
 
 use crate::identity::WorldlineId;
+use crate::object::HashAlgo;
 
 /// Unique identifier for a receipt within a worldline stream.
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -11,20 +13,30 @@ pub struct ReceiptId {
     pub worldline: WorldlineId,
     /// Sequence number within the stream (1-based, monotonic).
     pub seq: u64,
-    /// BLAKE3 hash of the receipt content.
+    /// Hash of the receipt content.
     pub hash: [u8; 32],
+    /// Algorithm that produced `hash`.
+    #[serde(default)]
+    pub algo: HashAlgo,
 }
 
 impl ReceiptId {
-    /// Create a new receipt ID.
+    /// Create a new receipt ID for a BLAKE3 receipt hash.
     pub fn new(worldline: WorldlineId, seq: u64, hash: [u8; 32]) -> Self {
         Self {
             worldline,
             seq,
             hash,
+            algo: HashAlgo::Blake3,
         }
     }
 
+    /// Tag the receipt hash with a non-default algorithm.
+    pub fn with_algo(mut self, algo: HashAlgo) -> Self {
+        self.algo = algo;
+        self
+    }
+
     /// Short hex representation of the hash.
     pub fn short_hash(&self) -> String {
         hex::encode(&self.hash[..4])
@@ -60,6 +72,8 @@ impl fmt::Display for ReceiptKind {
 
 #[cfg(test)]
 mod tests {
+    use alloc::format;
+
     use super::*;
     use crate::identity::IdentityMaterial;
 
@@ -72,6 +86,15 @@ mod tests {
         assert!(display.contains("abababab"));
     }
 
+    #[test]
+    fn receipt_id_defaults_to_blake3() {
+        let wid = WorldlineId::derive(&IdentityMaterial::GenesisHash([1; 32]));
+        let rid = ReceiptId::new(wid.clone(), 1, [0; 32]);
+        assert_eq!(rid.algo, HashAlgo::Blake3);
+        let sha = ReceiptId::new(wid, 1, [0; 32]).with_algo(HashAlgo::Sha256);
+        assert_ne!(rid, sha);
+    }
+
     #[test]
     fn receipt_kind_display() {
         assert_eq!(format!("{}", ReceiptKind::Commitment), "Commitment");
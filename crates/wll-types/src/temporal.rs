@@ -1,4 +1,5 @@
-use std::fmt;
+use core::fmt;
+#[cfg(feature = "std")]
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use serde::{Deserialize, Serialize};
@@ -31,6 +32,7 @@ impl TemporalAnchor {
     }
 
     /// Create an anchor for the current wall-clock time.
+    #[cfg(feature = "std")]
     pub fn now(node_id: u16) -> Self {
         let physical_ms = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -62,14 +64,49 @@ impl TemporalAnchor {
         self < other
     }
 
+    /// Milliseconds this anchor's physical time is ahead of `reference_ms`;
+    /// negative if it is behind.
+    pub fn skew_ms(&self, reference_ms: u64) -> i64 {
+        (self.physical_ms as i128 - reference_ms as i128)
+            .clamp(i64::MIN as i128, i64::MAX as i128) as i64
+    }
+
+    /// Milliseconds this anchor's physical time is ahead of `other`'s;
+    /// negative if it is behind. Logical counters are ignored.
+    pub fn drift_ms(&self, other: &Self) -> i64 {
+        self.skew_ms(other.physical_ms)
+    }
+
+    /// Returns `true` if this anchor is more than `max_skew_ms` ahead of
+    /// `reference_ms`. Anchors behind the reference never exceed it.
+    pub fn exceeds_skew(&self, reference_ms: u64, max_skew_ms: u64) -> bool {
+        self.physical_ms.saturating_sub(reference_ms) > max_skew_ms
+    }
+
+    /// [`skew_ms`](Self::skew_ms) against the current wall clock.
+    #[cfg(feature = "std")]
+    pub fn skew_from_now(&self) -> i64 {
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        self.skew_ms(now_ms)
+    }
+
     /// Advance this anchor, ensuring it is strictly after the given anchor.
     /// Used in HLC update on message receive.
+    #[cfg(feature = "std")]
     pub fn advance(&self, received: &Self, node_id: u16) -> Self {
         let now_ms = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap_or_default()
             .as_millis() as u64;
+        self.advance_at(received, node_id, now_ms)
+    }
 
+    /// [`advance`](Self::advance) against a caller-supplied wall clock
+    /// reading, for targets without `std::time`.
+    pub fn advance_at(&self, received: &Self, node_id: u16, now_ms: u64) -> Self {
         let max_physical = now_ms.max(self.physical_ms).max(received.physical_ms);
 
         let logical = if max_physical == self.physical_ms
@@ -93,13 +130,13 @@ impl TemporalAnchor {
 }
 
 impl PartialOrd for TemporalAnchor {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
         Some(self.cmp(other))
     }
 }
 
 impl Ord for TemporalAnchor {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
         self.physical_ms
             .cmp(&other.physical_ms)
             .then(self.logical.cmp(&other.logical))
@@ -129,6 +166,8 @@ impl fmt::Display for TemporalAnchor {
 
 #[cfg(test)]
 mod tests {
+    use alloc::format;
+
     use super::*;
 
     #[test]
@@ -161,6 +200,7 @@ mod tests {
         assert!(!a.is_before(&b));
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn now_produces_reasonable_timestamp() {
         let anchor = TemporalAnchor::now(0);
@@ -177,6 +217,7 @@ mod tests {
         assert!(zero < any);
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn advance_increases_monotonically() {
         let local = TemporalAnchor::new(100, 3, 1);
@@ -186,6 +227,32 @@ mod tests {
         assert!(advanced > received);
     }
 
+    #[test]
+    fn advance_at_uses_supplied_clock() {
+        let local = TemporalAnchor::new(100, 3, 1);
+        let received = TemporalAnchor::new(100, 5, 2);
+        assert_eq!(
+            local.advance_at(&received, 1, 50),
+            TemporalAnchor::new(100, 6, 1)
+        );
+        assert_eq!(
+            local.advance_at(&received, 1, 200),
+            TemporalAnchor::new(200, 0, 1)
+        );
+    }
+
+    #[test]
+    fn skew_and_drift_are_signed() {
+        let anchor = TemporalAnchor::new(1_500, 7, 1);
+        assert_eq!(anchor.skew_ms(1_000), 500);
+        assert_eq!(anchor.skew_ms(2_000), -500);
+        assert_eq!(anchor.drift_ms(&TemporalAnchor::new(1_600, 0, 2)), -100);
+        assert_eq!(TemporalAnchor::new(u64::MAX, 0, 0).skew_ms(0), i64::MAX);
+        assert!(anchor.exceeds_skew(1_000, 499));
+        assert!(!anchor.exceeds_skew(1_000, 500));
+        assert!(!anchor.exceeds_skew(9_000, 0));
+    }
+
     #[test]
     fn serde_roundtrip() {
         let anchor = TemporalAnchor::new(1234567890, 42, 7);
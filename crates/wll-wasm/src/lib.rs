@@ -0,0 +1,258 @@
+//! Browser-side verification for the WorldLine Ledger.
+//!
+//! A small subset of WLL that compiles to `wasm32-unknown-unknown`, so an
+//! auditor's browser can check receipt chains and Merkle inclusion proofs
+//! itself instead of trusting the server that served them. Every entry point
+//! takes and returns JSON in the same shapes the CLI and server emit.
+//!
+//! On `wasm32` the functions are exported through `wasm-bindgen` under
+//! camelCase names:
+//!
+//! ```text
+//! cargo build -p wll-wasm --target wasm32-unknown-unknown --release
+//! wasm-bindgen --target web --out-dir pkg \
+//!     target/wasm32-unknown-unknown/release/wll_wasm.wasm
+//! ```
+//!
+//! On other targets they are ordinary Rust functions, which is how they are
+//! tested.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::wasm_bindgen;
+use wll_crypto::MerkleProof;
+use wll_ledger::{detect_hash_format, Receipt, StreamValidator, ValidationReport};
+use wll_types::{ObjectId, ReceiptKind, WorldlineId};
+
+/// Validation result for one worldline stream.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ChainReport {
+    pub worldline: String,
+    pub receipt_count: u64,
+    pub valid: bool,
+    pub hash_chain_valid: bool,
+    pub sequence_monotonic: bool,
+    pub outcomes_attributed: bool,
+    pub snapshots_anchored: bool,
+    pub proofs_verified: bool,
+    pub links_resolved: bool,
+    pub violations: Vec<ChainViolation>,
+}
+
+/// One integrity violation found in a stream.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ChainViolation {
+    pub seq: u64,
+    pub kind: String,
+    pub description: String,
+}
+
+impl From<ValidationReport> for ChainReport {
+    fn from(report: ValidationReport) -> Self {
+        Self {
+            worldline: report.worldline.to_hex(),
+            receipt_count: report.receipt_count,
+            valid: report.is_valid(),
+            hash_chain_valid: report.hash_chain_valid,
+            sequence_monotonic: report.sequence_monotonic,
+            outcomes_attributed: report.outcomes_attributed,
+            snapshots_anchored: report.snapshots_anchored,
+            proofs_verified: report.proofs_verified,
+            links_resolved: report.links_resolved,
+            violations: report
+                .violations
+                .into_iter()
+                .map(|v| ChainViolation {
+                    seq: v.seq,
+                    kind: format!("{:?}", v.kind),
+                    description: v.description,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// What a single receipt says about itself, and whether its hash checks out.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ReceiptInfo {
+    pub kind: ReceiptKind,
+    pub worldline: String,
+    pub seq: u64,
+    pub receipt_hash: String,
+    pub prev_hash: Option<String>,
+    /// Encoding and algorithm that reproduce the stored hash, e.g.
+    /// `"Canonical/blake3"`; `None` means the hash does not verify.
+    pub hash_format: Option<String>,
+}
+
+/// Validate every stream in `receipts`.
+///
+/// Receipts are grouped by worldline, keeping their relative order, and each
+/// group must be a complete stream starting at seq 1.
+pub fn check_receipts(receipts: &[Receipt]) -> Vec<ChainReport> {
+    let mut streams: BTreeMap<WorldlineId, Vec<Receipt>> = BTreeMap::new();
+    for receipt in receipts {
+        streams
+            .entry(receipt.worldline().clone())
+            .or_default()
+            .push(receipt.clone());
+    }
+    streams
+        .iter()
+        .map(|(worldline, stream)| StreamValidator::validate_receipts(worldline, stream).into())
+        .collect()
+}
+
+/// Describe `receipt` and check its stored hash.
+pub fn inspect_receipt(receipt: &Receipt) -> ReceiptInfo {
+    ReceiptInfo {
+        kind: receipt.kind(),
+        worldline: receipt.worldline().to_hex(),
+        seq: receipt.seq(),
+        receipt_hash: hex::encode(receipt.receipt_hash()),
+        prev_hash: receipt.prev_hash().map(hex::encode),
+        hash_format: detect_hash_format(receipt)
+            .ok()
+            .flatten()
+            .map(|f| format!("{:?}/{}", f.encoding, f.algo.name())),
+    }
+}
+
+/// Verify a JSON array of receipts; returns a JSON array of [`ChainReport`]s.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = verifyReceiptChain))]
+pub fn verify_receipt_chain(receipts_json: &str) -> Result<String, String> {
+    let receipts: Vec<Receipt> =
+        serde_json::from_str(receipts_json).map_err(|e| format!("invalid receipts: {e}"))?;
+    to_json(&check_receipts(&receipts))
+}
+
+/// Parse one JSON receipt; returns its [`ReceiptInfo`] as JSON.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = parseReceipt))]
+pub fn parse_receipt(receipt_json: &str) -> Result<String, String> {
+    let receipt: Receipt =
+        serde_json::from_str(receipt_json).map_err(|e| format!("invalid receipt: {e}"))?;
+    to_json(&inspect_receipt(&receipt))
+}
+
+/// Check a JSON [`MerkleProof`] that `leaf` is included under
+/// `expected_root`, both hex ids the caller already trusts.
+///
+/// The proof's own leaf and root must match them and its path must lead
+/// from one to the other: a proof that only agrees with itself proves
+/// nothing about a root the server made up.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(js_name = verifyMerkleProof))]
+pub fn verify_merkle_proof(
+    proof_json: &str,
+    expected_root: &str,
+    leaf: &str,
+) -> Result<bool, String> {
+    let proof: MerkleProof =
+        serde_json::from_str(proof_json).map_err(|e| format!("invalid proof: {e}"))?;
+    let expected_root =
+        ObjectId::from_hex(expected_root).map_err(|e| format!("invalid root: {e}"))?;
+    let leaf = ObjectId::from_hex(leaf).map_err(|e| format!("invalid leaf: {e}"))?;
+    Ok(proof.root == expected_root && proof.leaf == leaf && proof.verify())
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, String> {
+    serde_json::to_string(value).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wll_crypto::MerkleTree;
+    use wll_ledger::{
+        CommitmentProposal, Decision, EvidenceBundle, InMemoryLedger, LedgerReader, LedgerWriter,
+    };
+    use wll_types::{CommitmentClass, CommitmentId, IdentityMaterial};
+
+    fn stream(seed: u8, commits: u64) -> Vec<Receipt> {
+        let worldline = WorldlineId::derive(&IdentityMaterial::GenesisHash([seed; 32]));
+        let ledger = InMemoryLedger::default();
+        for nonce in 0..commits {
+            let proposal = CommitmentProposal {
+                worldline: worldline.clone(),
+                commitment_id: CommitmentId::new(),
+                class: CommitmentClass::ContentUpdate,
+                intent: "audit".into(),
+                requested_caps: vec![],
+                targets: vec![],
+                evidence: EvidenceBundle::empty(),
+                nonce,
+            };
+            ledger
+                .append_commitment(&proposal, &Decision::Accepted, [0; 32])
+                .unwrap();
+        }
+        ledger.read_all(&worldline).unwrap()
+    }
+
+    #[test]
+    fn intact_streams_verify() {
+        let mut receipts = stream(1, 3);
+        receipts.extend(stream(2, 2));
+        let json = serde_json::to_string(&receipts).unwrap();
+
+        let reports: Vec<serde_json::Value> =
+            serde_json::from_str(&verify_receipt_chain(&json).unwrap()).unwrap();
+        assert_eq!(reports.len(), 2);
+        assert!(reports.iter().all(|r| r["valid"] == true));
+    }
+
+    #[test]
+    fn tampered_receipt_is_reported() {
+        let json =
+            serde_json::to_string(&stream(3, 2))
+                .unwrap()
+                .replacen("\"audit\"", "\"forged\"", 1);
+
+        let reports = check_receipts(&serde_json::from_str::<Vec<Receipt>>(&json).unwrap());
+        assert!(!reports[0].valid);
+        assert_eq!(reports[0].violations[0].kind, "HashMismatch");
+    }
+
+    #[test]
+    fn parse_receipt_reports_hash_format() {
+        let receipt = &stream(4, 1)[0];
+        let info = inspect_receipt(receipt);
+        assert_eq!(info.seq, 1);
+        assert_eq!(info.hash_format.as_deref(), Some("Canonical/blake3"));
+        assert!(parse_receipt("{").is_err());
+    }
+
+    #[test]
+    fn merkle_proofs_verify() {
+        let leaves: Vec<ObjectId> = (0u8..5).map(|i| ObjectId::from_bytes(&[i])).collect();
+        let tree = MerkleTree::from_leaves(leaves);
+        let root = tree.root().to_hex();
+        let proof = tree.proof(3).unwrap();
+        let leaf = proof.leaf.to_hex();
+        let json = serde_json::to_string(&proof).unwrap();
+        assert!(verify_merkle_proof(&json, &root, &leaf).unwrap());
+        assert!(!verify_merkle_proof(&json, &root, &tree.proof(2).unwrap().leaf.to_hex()).unwrap());
+        assert!(verify_merkle_proof(&json, "zz", &leaf).is_err());
+
+        let mut other = proof.clone();
+        other.leaf = ObjectId::from_bytes(b"other");
+        let json = serde_json::to_string(&other).unwrap();
+        assert!(!verify_merkle_proof(&json, &root, &other.leaf.to_hex()).unwrap());
+    }
+
+    #[test]
+    fn merkle_proofs_must_lead_to_the_expected_root() {
+        let leaves: Vec<ObjectId> = (0u8..5).map(|i| ObjectId::from_bytes(&[i])).collect();
+        let trusted = MerkleTree::from_leaves(leaves.clone()).root().to_hex();
+        // A proof from a tree the server made up checks out on its own.
+        let forged = MerkleTree::from_leaves(vec![leaves[3], ObjectId::from_bytes(b"forged")]);
+        let proof = forged.proof(0).unwrap();
+        assert!(proof.verify());
+
+        let json = serde_json::to_string(&proof).unwrap();
+        assert!(!verify_merkle_proof(&json, &trusted, &leaves[3].to_hex()).unwrap());
+        let claimed = proof.root.to_hex();
+        assert!(verify_merkle_proof(&json, &claimed, &leaves[3].to_hex()).unwrap());
+    }
+}
@@ -0,0 +1,17 @@
+//! Parse a pack index and look up every id it lists plus one it may not.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wll_pack::PackIndex;
+use wll_types::ObjectId;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(index) = PackIndex::from_bytes(data) {
+        for id in &index.object_ids {
+            assert!(index.contains(id));
+        }
+        let _ = index.lookup(&ObjectId::from_bytes(data));
+        let _ = index.to_bytes();
+    }
+});
@@ -0,0 +1,23 @@
+//! Scan a pack received without its index, then read back every object.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wll_pack::PackReader;
+
+fuzz_target!(|data: &[u8]| {
+    // Reseal the trailing checksum so inputs reach the entry parser rather
+    // than all stopping at the checksum comparison.
+    let mut pack = data.to_vec();
+    if pack.len() >= 32 {
+        let body_len = pack.len() - 32;
+        let checksum = *blake3::hash(&pack[..body_len]).as_bytes();
+        pack[body_len..].copy_from_slice(&checksum);
+    }
+
+    if let Ok(reader) = PackReader::from_pack_bytes(pack) {
+        for id in reader.object_ids() {
+            let _ = reader.read_object(id);
+        }
+    }
+});
@@ -0,0 +1,15 @@
+//! Decode a stream of framed protocol messages.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wll_protocol::WllCodec;
+
+fuzz_target!(|data: &[u8]| {
+    let mut rest = data;
+    while let Ok((msg, consumed)) = WllCodec::decode(rest) {
+        let _ = WllCodec::encode(&msg);
+        rest = &rest[consumed..];
+    }
+    let _ = WllCodec::decode_payload(data);
+});
@@ -0,0 +1,12 @@
+//! Recover events from a write-ahead log segment.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wll_fabric::WalEntry;
+
+fuzz_target!(|data: &[u8]| {
+    for entry in WalEntry::decode_segment(data).into_iter().flatten() {
+        let _ = entry.encode();
+    }
+});